@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::{fs, io};
 
 use rusqlite::{
@@ -112,6 +112,10 @@ impl FromRow<BlockstackOperationType> for BlockstackOperationType {
 
 pub const BURNCHAIN_DB_VERSION: &'static str = "1";
 
+/// By default, orphaned burnchain blocks are pruned once they are this far below the canonical
+/// burnchain tip.  A burnchain reorg this deep is not expected to ever happen.
+pub const BURNCHAIN_DB_DEFAULT_PRUNE_DEPTH: u64 = 144;
+
 const BURNCHAIN_DB_INITIAL_SCHEMA: &'static str = "
 CREATE TABLE burnchain_db_block_headers (
     block_height INTEGER NOT NULL,
@@ -132,6 +136,10 @@ CREATE TABLE burnchain_db_block_ops (
 
 CREATE TABLE db_config(version TEXT NOT NULL);";
 
+/// Index of burnchain ops by the block that contains them, so a pruned block's ops can be found.
+/// Created on open if it is missing, since it was added after the initial schema.
+const BURNCHAIN_DB_BLOCK_OPS_INDEX: &'static str = "CREATE INDEX IF NOT EXISTS index_burnchain_db_block_ops_block_hash ON burnchain_db_block_ops(block_hash);";
+
 impl<'a> BurnchainDBTransaction<'a> {
     fn store_burnchain_db_entry(
         &self,
@@ -237,6 +245,9 @@ impl BurnchainDB {
             db_tx.store_burnchain_db_entry(&first_block_header)?;
             db_tx.commit()?;
         }
+        if readwrite {
+            db.conn.execute_batch(BURNCHAIN_DB_BLOCK_OPS_INDEX)?;
+        }
 
         Ok(db)
     }
@@ -249,6 +260,9 @@ impl BurnchainDB {
         };
         let conn = Connection::open_with_flags(path, open_flags)?;
         conn.busy_handler(Some(tx_busy_handler))?;
+        if readwrite {
+            conn.execute_batch(BURNCHAIN_DB_BLOCK_OPS_INDEX)?;
+        }
 
         Ok(BurnchainDB { conn })
     }
@@ -358,6 +372,145 @@ impl BurnchainDB {
         Ok(blockstack_ops)
     }
 
+    /// Delete the headers and ops of burnchain blocks that are not on the canonical burnchain fork,
+    /// and are at least `keep_depth` blocks below its tip.  Blocks on the canonical fork are left
+    /// alone, since the chains coordinator re-reads their ops whenever a newly-discovered PoX
+    /// anchor block makes it reprocess sortitions (see `prune_stable_blocks()` for when it won't).
+    /// Returns the number of blocks pruned.
+    pub fn prune_orphaned_blocks(&mut self, keep_depth: u64) -> Result<u64, BurnchainError> {
+        let tip = self.get_canonical_chain_tip()?;
+        if tip.block_height < keep_depth {
+            return Ok(0);
+        }
+        let cutoff_height = tip.block_height - keep_depth;
+
+        // lowest height at which there is more than one block
+        let qry = "SELECT block_height FROM burnchain_db_block_headers WHERE block_height <= ?1
+                   GROUP BY block_height HAVING COUNT(*) > 1 ORDER BY block_height ASC LIMIT 1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(cutoff_height)?];
+        let lowest_fork_height: u64 = match query_row(&self.conn, qry, args)? {
+            Some(height) => height,
+            None => {
+                return Ok(0);
+            }
+        };
+
+        // find the canonical blocks at and below the cutoff
+        let header_qry = "SELECT * FROM burnchain_db_block_headers WHERE block_hash = ?1";
+        let mut canonical = HashSet::new();
+        let mut cursor = tip;
+        while cursor.block_height >= lowest_fork_height {
+            if cursor.block_height <= cutoff_height {
+                canonical.insert(cursor.block_hash.clone());
+            }
+            if cursor.parent_block_hash == BurnchainHeaderHash::sentinel() {
+                break;
+            }
+            cursor = match query_row(&self.conn, header_qry, &[&cursor.parent_block_hash])? {
+                Some(parent) => parent,
+                None => {
+                    break;
+                }
+            };
+        }
+
+        let candidates_qry = "SELECT * FROM burnchain_db_block_headers
+                              WHERE block_height >= ?1 AND block_height <= ?2";
+        let args: &[&dyn ToSql] = &[
+            &u64_to_sql(lowest_fork_height)?,
+            &u64_to_sql(cutoff_height)?,
+        ];
+        let candidates: Vec<BurnchainBlockHeader> = query_rows(&self.conn, candidates_qry, args)?;
+        let orphans: Vec<BurnchainHeaderHash> = candidates
+            .into_iter()
+            .map(|header| header.block_hash)
+            .filter(|block_hash| !canonical.contains(block_hash))
+            .collect();
+
+        if orphans.len() == 0 {
+            return Ok(0);
+        }
+
+        let db_tx = self.tx_begin()?;
+        for block_hash in orphans.iter() {
+            db_tx.sql_tx.execute(
+                "DELETE FROM burnchain_db_block_ops WHERE block_hash = ?1",
+                &[block_hash],
+            )?;
+            db_tx.sql_tx.execute(
+                "DELETE FROM burnchain_db_block_headers WHERE block_hash = ?1",
+                &[block_hash],
+            )?;
+        }
+        db_tx.commit()?;
+
+        debug!(
+            "Pruned {} orphaned burnchain blocks between heights {} and {}",
+            orphans.len(),
+            lowest_fork_height,
+            cutoff_height
+        );
+        Ok(orphans.len() as u64)
+    }
+
+    /// Delete the ops of canonical burnchain blocks at and below `stable_height`, whose
+    /// sortitions the chains coordinator will never evaluate again (see
+    /// `SortitionDB::get_pox_reprocessing_floor()`).  These ops are the only transaction payloads
+    /// the burnchain DB keeps, and they are not read again once their sortitions are final --
+    /// except for `PreStx` ops, which a later `StackStx` or `TransferStx` can still spend, so
+    /// those are kept.  Block headers are kept too, so the canonical fork can still be walked.
+    /// Only blocks that are alone at their height are considered canonical, so prune orphaned
+    /// blocks below `stable_height` first.
+    /// Returns the number of ops pruned.
+    pub fn prune_stable_blocks(&mut self, stable_height: u64) -> Result<u64, BurnchainError> {
+        let qry = "SELECT rowid, op FROM burnchain_db_block_ops WHERE block_hash IN
+                   (SELECT block_hash FROM burnchain_db_block_headers WHERE block_height <= ?1
+                    GROUP BY block_height HAVING COUNT(*) = 1)";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(stable_height)?];
+
+        let mut prunable = vec![];
+        {
+            let mut stmt = self.conn.prepare(qry)?;
+            let mut rows = stmt.query(args)?;
+            while let Some(row) = rows.next()? {
+                let rowid: i64 = row.get_unwrap(0);
+                match BlockstackOperationType::from_row(row)? {
+                    BlockstackOperationType::PreStx(_) => {}
+                    _ => {
+                        prunable.push(rowid);
+                    }
+                }
+            }
+        }
+
+        if prunable.len() == 0 {
+            return Ok(0);
+        }
+
+        let db_tx = self.tx_begin()?;
+        for rowid in prunable.iter() {
+            db_tx.sql_tx.execute(
+                "DELETE FROM burnchain_db_block_ops WHERE rowid = ?1",
+                &[rowid],
+            )?;
+        }
+        db_tx.commit()?;
+
+        debug!(
+            "Pruned {} burnchain ops at or below stable height {}",
+            prunable.len(),
+            stable_height
+        );
+        Ok(prunable.len() as u64)
+    }
+
+    /// Reclaim the disk space freed by pruning.  This rewrites the whole database, so only do it
+    /// on demand (i.e. not while the node is running).
+    pub fn compact(&mut self) -> Result<(), BurnchainError> {
+        self.conn.execute_batch("VACUUM;")?;
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn raw_store_burnchain_block(
         &mut self,
@@ -386,6 +539,7 @@ mod tests {
     use burnchains::bitcoin::*;
     use burnchains::PoxConstants;
     use burnchains::BLOCKSTACK_MAGIC_MAINNET;
+    use chainstate::burn::operations::{PreStxOp, TransferStxOp};
     use chainstate::burn::*;
     use chainstate::stacks::*;
     use deps::bitcoin::blockdata::transaction::Transaction as BtcTx;
@@ -513,6 +667,157 @@ mod tests {
         assert_eq!(&header, &looked_up_canon);
     }
 
+    #[test]
+    fn test_prune_orphaned_blocks() {
+        let first_bhh = BurnchainHeaderHash([0; 32]);
+        let mut burnchain_db = BurnchainDB::connect(":memory:", 1, &first_bhh, 321, true).unwrap();
+
+        let make_header =
+            |height: u64, hash: u8, parent: BurnchainHeaderHash| BurnchainBlockHeader {
+                block_height: height,
+                block_hash: BurnchainHeaderHash([hash; 32]),
+                parent_block_hash: parent,
+                num_txs: 0,
+                timestamp: 321 + height,
+            };
+
+        // canonical fork from heights 2 through 10
+        let mut parent = first_bhh.clone();
+        for height in 2..11 {
+            let header = make_header(height, 0xa0 + height as u8, parent.clone());
+            parent = header.block_hash.clone();
+            burnchain_db
+                .raw_store_burnchain_block(header, vec![])
+                .unwrap();
+        }
+
+        // an old orphaned fork at heights 2 and 3, and a recent one at height 9
+        let old_orphan_1 = make_header(2, 0xb2, first_bhh.clone());
+        let old_orphan_2 = make_header(3, 0xb3, old_orphan_1.block_hash.clone());
+        let new_orphan = make_header(9, 0xb9, BurnchainHeaderHash([0xa8; 32]));
+        for header in [&old_orphan_1, &old_orphan_2, &new_orphan].iter() {
+            burnchain_db
+                .raw_store_burnchain_block((*header).clone(), vec![])
+                .unwrap();
+        }
+
+        assert_eq!(burnchain_db.prune_orphaned_blocks(20).unwrap(), 0);
+        assert_eq!(burnchain_db.prune_orphaned_blocks(5).unwrap(), 2);
+
+        assert!(burnchain_db
+            .get_burnchain_block(&old_orphan_1.block_hash)
+            .is_err());
+        assert!(burnchain_db
+            .get_burnchain_block(&old_orphan_2.block_hash)
+            .is_err());
+        assert!(burnchain_db
+            .get_burnchain_block(&new_orphan.block_hash)
+            .is_ok());
+        for height in 2..11 {
+            assert!(burnchain_db
+                .get_burnchain_block(&BurnchainHeaderHash([0xa0 + height as u8; 32]))
+                .is_ok());
+        }
+        assert_eq!(
+            burnchain_db.get_canonical_chain_tip().unwrap().block_hash,
+            BurnchainHeaderHash([0xaa; 32])
+        );
+
+        // nothing left to prune
+        assert_eq!(burnchain_db.prune_orphaned_blocks(5).unwrap(), 0);
+        burnchain_db.compact().unwrap();
+    }
+
+    #[test]
+    fn test_prune_stable_blocks() {
+        let first_bhh = BurnchainHeaderHash([0; 32]);
+        let mut burnchain_db = BurnchainDB::connect(":memory:", 1, &first_bhh, 321, true).unwrap();
+
+        let addr = StacksAddress {
+            version: 26,
+            bytes: Hash160([0x01; 20]),
+        };
+        let make_ops = |height: u64, block_hash: &BurnchainHeaderHash| {
+            vec![
+                BlockstackOperationType::PreStx(PreStxOp {
+                    output: addr.clone(),
+                    txid: Txid([height as u8; 32]),
+                    vtxindex: 1,
+                    block_height: height,
+                    burn_header_hash: block_hash.clone(),
+                }),
+                BlockstackOperationType::TransferStx(TransferStxOp {
+                    sender: addr.clone(),
+                    recipient: addr.clone(),
+                    transfered_ustx: 123,
+                    memo: vec![],
+                    txid: Txid([0x80 + height as u8; 32]),
+                    vtxindex: 2,
+                    block_height: height,
+                    burn_header_hash: block_hash.clone(),
+                }),
+            ]
+        };
+
+        // canonical fork from heights 2 through 10, each with a PreStx and a TransferStx op
+        let mut parent = first_bhh.clone();
+        for height in 2..11 {
+            let header = BurnchainBlockHeader {
+                block_height: height,
+                block_hash: BurnchainHeaderHash([0xa0 + height as u8; 32]),
+                parent_block_hash: parent.clone(),
+                num_txs: 2,
+                timestamp: 321 + height,
+            };
+            parent = header.block_hash.clone();
+            let ops = make_ops(height, &header.block_hash);
+            burnchain_db.raw_store_burnchain_block(header, ops).unwrap();
+        }
+
+        // an orphan at height 4 that hasn't been pruned yet
+        let orphan = BurnchainBlockHeader {
+            block_height: 4,
+            block_hash: BurnchainHeaderHash([0xb4; 32]),
+            parent_block_hash: BurnchainHeaderHash([0xa3; 32]),
+            num_txs: 0,
+            timestamp: 325,
+        };
+        burnchain_db
+            .raw_store_burnchain_block(orphan.clone(), vec![])
+            .unwrap();
+
+        // heights 2, 3, 5 and 6 are alone at their height, so only their TransferStx ops go
+        assert_eq!(burnchain_db.prune_stable_blocks(6).unwrap(), 4);
+
+        for height in 2..11 {
+            let block_hash = BurnchainHeaderHash([0xa0 + height as u8; 32]);
+            let BurnchainBlockData { header, ops } =
+                burnchain_db.get_burnchain_block(&block_hash).unwrap();
+            assert_eq!(header.block_height, height);
+
+            assert!(burnchain_db
+                .get_burnchain_op(&Txid([height as u8; 32]))
+                .is_some());
+            if height <= 6 && height != 4 {
+                assert_eq!(ops.len(), 1);
+                assert!(burnchain_db
+                    .get_burnchain_op(&Txid([0x80 + height as u8; 32]))
+                    .is_none());
+            } else {
+                assert_eq!(ops.len(), 2);
+            }
+        }
+
+        // nothing left to prune
+        assert_eq!(burnchain_db.prune_stable_blocks(6).unwrap(), 0);
+
+        // once the orphan is gone, height 4 is pruned too
+        assert_eq!(burnchain_db.prune_orphaned_blocks(5).unwrap(), 1);
+        assert_eq!(burnchain_db.prune_stable_blocks(6).unwrap(), 1);
+        assert!(burnchain_db.get_burnchain_op(&Txid([0x84; 32])).is_none());
+        assert!(burnchain_db.get_burnchain_op(&Txid([4; 32])).is_some());
+    }
+
     #[test]
     fn test_classify_stack_stx() {
         let first_bhh = BurnchainHeaderHash([0; 32]);
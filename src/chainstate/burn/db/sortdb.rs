@@ -76,7 +76,8 @@ use vm::types::Value;
 
 use crate::types::chainstate::StacksAddress;
 use crate::types::chainstate::{
-    BlockHeaderHash, BurnchainHeaderHash, MARFValue, PoxId, SortitionId, VRFSeed,
+    BlockHeaderHash, BurnchainHeaderHash, MARFValue, PoxId, SortitionId, StacksBlockHeader,
+    StacksBlockId, VRFSeed,
 };
 use crate::types::proof::{ClarityMarfTrieId, TrieHash};
 
@@ -2398,6 +2399,24 @@ impl SortitionDB {
         handle.get_pox_id().map_err(BurnchainError::from)
     }
 
+    /// Get the lowest burnchain block height whose sortition the chains coordinator could still
+    /// evaluate again on the canonical sortition fork.  It re-evaluates sortitions when it
+    /// processes the anchor block of a reward cycle that it didn't have while processing the
+    /// cycle's sortitions, starting at that reward cycle.  So every sortition below the first
+    /// reward cycle with a missing anchor block (or below the current reward cycle, if none are
+    /// missing) is final.
+    pub fn get_pox_reprocessing_floor(&self, burnchain: &Burnchain) -> Result<u64, BurnchainError> {
+        let tip = SortitionDB::get_canonical_burn_chain_tip(self.conn())?;
+        let pox_id = self.index_handle(&tip.sortition_id).get_pox_id()?;
+
+        // bit 0 is the initial reward set, and bit i > 0 is added when reward cycle i - 1 or a
+        // later one starts, so this errs low
+        let first_unknown = (1..pox_id.len())
+            .find(|i| !pox_id.has_ith_anchor_block(*i))
+            .unwrap_or(pox_id.len());
+        Ok(burnchain.reward_cycle_to_block_height(first_unknown.saturating_sub(1) as u64))
+    }
+
     pub fn get_sortition_result(
         &self,
         id: &SortitionId,
@@ -2824,6 +2843,29 @@ impl SortitionDB {
         })
     }
 
+    /// Find the snapshot whose sortition chose the Stacks block with the given index block hash,
+    /// in any burnchain fork.
+    /// SLOW: this scans every snapshot with a sortition, since the index block hash is not stored.
+    pub fn find_block_snapshot_for_stacks_block(
+        conn: &Connection,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<BlockSnapshot>, db_error> {
+        let qry =
+            "SELECT consensus_hash, winning_stacks_block_hash FROM snapshots WHERE sortition = 1";
+        let mut stmt = conn.prepare(qry)?;
+        let mut rows = stmt.query(NO_PARAMS)?;
+        while let Some(row) = rows.next()? {
+            let consensus_hash = ConsensusHash::from_column(&row, "consensus_hash")?;
+            let block_hash = BlockHeaderHash::from_column(&row, "winning_stacks_block_hash")?;
+            if StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash)
+                == *index_block_hash
+            {
+                return SortitionDB::get_block_snapshot_consensus(conn, &consensus_hash);
+            }
+        }
+        Ok(None)
+    }
+
     /// Get a snapshot for an processed sortition.
     /// The snapshot may not be valid
     pub fn get_block_snapshot(
@@ -6156,6 +6198,46 @@ pub mod tests {
         }
     }
 
+    #[test]
+    fn test_find_block_snapshot_for_stacks_block() {
+        let first_burn_hash = BurnchainHeaderHash::from_hex(
+            "10000000000000000000000000000000000000000000000000000000000000ff",
+        )
+        .unwrap();
+        let mut db = SortitionDB::connect_test(0, &first_burn_hash).unwrap();
+
+        let first_snapshot = SortitionDB::get_first_block_snapshot(db.conn()).unwrap();
+        make_fork_run(&mut db, &first_snapshot, 5, 0);
+
+        // a burn fork off of the 3rd block
+        let fork_snapshot = SortitionDB::get_block_snapshot(db.conn(), &SortitionId([0x02; 32]))
+            .unwrap()
+            .unwrap();
+        make_fork_run(&mut db, &fork_snapshot, 3, 0x80);
+
+        for sortition_id in [SortitionId([0x04; 32]), SortitionId([0x84; 32])].iter() {
+            let sn = SortitionDB::get_block_snapshot(db.conn(), sortition_id)
+                .unwrap()
+                .unwrap();
+            let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                &sn.consensus_hash,
+                &sn.winning_stacks_block_hash,
+            );
+            let found =
+                SortitionDB::find_block_snapshot_for_stacks_block(db.conn(), &index_block_hash)
+                    .unwrap()
+                    .unwrap();
+            assert_eq!(found, sn);
+        }
+
+        assert!(SortitionDB::find_block_snapshot_for_stacks_block(
+            db.conn(),
+            &StacksBlockId([0x11; 32])
+        )
+        .unwrap()
+        .is_none());
+    }
+
     #[test]
     fn test_set_stacks_block_accepted() {
         let first_burn_hash = BurnchainHeaderHash::from_hex(
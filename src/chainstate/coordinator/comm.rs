@@ -15,12 +15,14 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::sync::{
-    atomic::{AtomicU64, Ordering},
+    atomic::{AtomicBool, AtomicU64, Ordering},
     Arc, Condvar, LockResult, Mutex, MutexGuard, RwLock, TryLockResult,
 };
 use std::time::{Duration, Instant};
 use std::{process, thread};
 
+use net::BlockProcessingControl;
+
 /// Trait for use by the ChainsCoordinator
 ///
 pub trait CoordinatorNotices {
@@ -60,6 +62,10 @@ pub struct CoordinatorChannels {
     stacks_blocks_processed: Arc<AtomicU64>,
     /// how many sortitions have been processed by this Coordinator thread since startup?
     sortitions_processed: Arc<AtomicU64>,
+    /// has the Coordinator been asked to stop processing blocks until it's resumed?
+    pause_requested: Arc<AtomicBool>,
+    /// has the Coordinator finished the blocks it was processing and stopped?
+    paused: Arc<AtomicBool>,
 }
 
 /// Notification struct for communicating to
@@ -83,6 +89,8 @@ pub struct CoordinatorReceivers {
     signal_wakeup: Arc<Condvar>,
     pub stacks_blocks_processed: Arc<AtomicU64>,
     pub sortitions_processed: Arc<AtomicU64>,
+    pub pause_requested: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
 }
 
 /// Static struct used to hold all the static methods
@@ -118,6 +126,16 @@ impl SignalBools {
 impl CoordinatorReceivers {
     pub fn wait_on(&self) -> CoordinatorEvents {
         let mut signal_bools = self.signal_bools.lock().unwrap();
+        if self.pause_requested.load(Ordering::SeqCst) && !signal_bools.stop {
+            // leave any new block notices for when processing is resumed
+            self.paused.store(true, Ordering::SeqCst);
+            signal_bools = self.signal_wakeup.wait(signal_bools).unwrap();
+            if signal_bools.stop {
+                return CoordinatorEvents::STOP;
+            }
+            return CoordinatorEvents::TIMEOUT;
+        }
+        self.paused.store(false, Ordering::SeqCst);
         if !signal_bools.activated_signal() {
             signal_bools = self.signal_wakeup.wait(signal_bools).unwrap();
         }
@@ -152,6 +170,34 @@ impl CoordinatorChannels {
         bools.stop.clone()
     }
 
+    /// Stop processing Stacks blocks and sortitions once the ones being processed now are done.
+    /// New blocks can still be stored in the meantime; they'll be processed once
+    /// `resume_block_processing` is called.
+    pub fn pause_block_processing(&self) {
+        let _bools = self.signal_bools.lock().unwrap();
+        self.pause_requested.store(true, Ordering::SeqCst);
+        self.signal_wakeup.notify_all();
+    }
+
+    pub fn resume_block_processing(&self) {
+        let mut bools = self.signal_bools.lock().unwrap();
+        self.pause_requested.store(false, Ordering::SeqCst);
+        self.paused.store(false, Ordering::SeqCst);
+        // pick up wherever processing was interrupted, even if no new blocks arrived
+        bools.new_stacks_block = true;
+        bools.new_burn_block = true;
+        self.signal_wakeup.notify_all();
+    }
+
+    pub fn is_block_processing_pause_requested(&self) -> bool {
+        self.pause_requested.load(Ordering::SeqCst)
+    }
+
+    /// Has the Coordinator stopped processing blocks since a pause was requested?
+    pub fn is_block_processing_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
     pub fn get_stacks_blocks_processed(&self) -> u64 {
         self.stacks_blocks_processed.load(Ordering::SeqCst)
     }
@@ -185,6 +231,23 @@ impl CoordinatorChannels {
     }
 }
 
+impl BlockProcessingControl for CoordinatorChannels {
+    fn pause_block_processing(&self) {
+        CoordinatorChannels::pause_block_processing(self)
+    }
+
+    fn resume_block_processing(&self) {
+        CoordinatorChannels::resume_block_processing(self)
+    }
+
+    fn get_block_processing_state(&self) -> (bool, bool) {
+        (
+            self.is_block_processing_pause_requested(),
+            self.is_block_processing_paused(),
+        )
+    }
+}
+
 impl CoordinatorCommunication {
     pub fn instantiate() -> (CoordinatorReceivers, CoordinatorChannels) {
         let signal_bools = Arc::new(Mutex::new(SignalBools {
@@ -197,6 +260,8 @@ impl CoordinatorCommunication {
 
         let stacks_blocks_processed = Arc::new(AtomicU64::new(0));
         let sortitions_processed = Arc::new(AtomicU64::new(0));
+        let pause_requested = Arc::new(AtomicBool::new(false));
+        let paused = Arc::new(AtomicBool::new(false));
 
         let senders = CoordinatorChannels {
             signal_bools: signal_bools.clone(),
@@ -204,6 +269,8 @@ impl CoordinatorCommunication {
             stacks_blocks_processed: stacks_blocks_processed.clone(),
 
             sortitions_processed: sortitions_processed.clone(),
+            pause_requested: pause_requested.clone(),
+            paused: paused.clone(),
         };
 
         let rcvrs = CoordinatorReceivers {
@@ -211,6 +278,8 @@ impl CoordinatorCommunication {
             signal_wakeup: signal_wakeup,
             stacks_blocks_processed,
             sortitions_processed,
+            pause_requested,
+            paused,
         };
 
         (rcvrs, senders)
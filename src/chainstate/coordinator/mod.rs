@@ -16,7 +16,9 @@
 
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
-use std::sync::mpsc::SyncSender;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::Arc;
 use std::time::Duration;
 
 use burnchains::{
@@ -25,7 +27,8 @@ use burnchains::{
 };
 use chainstate::burn::{
     db::sortdb::SortitionDB, operations::leader_block_commit::RewardSetInfo,
-    operations::BlockstackOperationType, BlockSnapshot, ConsensusHash,
+    operations::BlockstackOperationType, operations::LeaderBlockCommitOp, BlockSnapshot,
+    ConsensusHash,
 };
 use chainstate::coordinator::comm::{
     ArcCounterCoordinatorNotices, CoordinatorEvents, CoordinatorNotices, CoordinatorReceivers,
@@ -41,7 +44,7 @@ use chainstate::stacks::{
 };
 use monitoring::{
     increment_contract_calls_processed, increment_stx_blocks_processed_counter,
-    update_stacks_tip_height,
+    update_reward_cycle, update_stacks_tip_height,
 };
 use net::atlas::{AtlasConfig, AttachmentInstance};
 use util::db::Error as DBError;
@@ -65,14 +68,14 @@ pub mod tests;
 
 /// The 3 different states for the current
 ///  reward cycle's relationship to its PoX anchor
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum PoxAnchorBlockStatus {
     SelectedAndKnown(BlockHeaderHash, Vec<StacksAddress>),
     SelectedAndUnknown(BlockHeaderHash),
     NotSelected,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct RewardCycleInfo {
     pub anchor_status: PoxAnchorBlockStatus,
 }
@@ -100,6 +103,13 @@ impl RewardCycleInfo {
             NotSelected => None,
         }
     }
+    /// Will this reward cycle pay out to PoX recipients?  If not, block commits in this cycle
+    /// fall back to burning.
+    pub fn is_pox_active(&self) -> bool {
+        self.known_selected_anchor_block()
+            .map(|reward_set| !reward_set.is_empty())
+            .unwrap_or(false)
+    }
     pub fn known_selected_anchor_block_owned(self) -> Option<Vec<StacksAddress>> {
         use self::PoxAnchorBlockStatus::*;
         match self.anchor_status {
@@ -135,6 +145,28 @@ pub trait BlockEventDispatcher {
         reward_recipients: Vec<StacksAddress>,
     );
 
+    /// called whenever a burn block that begins a reward
+    ///  cycle has been processed for sortition, with the
+    ///  PoX anchor block status (and reward set, if known)
+    ///  for that cycle. as with `announce_burn_block`, in
+    ///  the event of PoX forks, this may be called
+    ///  _multiple_ times for the same reward cycle.
+    fn announce_reward_cycle(
+        &self,
+        burn_block: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        reward_cycle: u64,
+        reward_cycle_info: &RewardCycleInfo,
+    );
+
+    /// called whenever a burn block has been processed for
+    ///  sortition, with the resulting snapshot and the block
+    ///  commits that were accepted in it. as with
+    ///  `announce_burn_block`, in the event of PoX forks, this
+    ///  may be called _multiple_ times for the same burnchain
+    ///  header hash.
+    fn announce_sortition(&self, snapshot: &BlockSnapshot, block_commits: &[LeaderBlockCommitOp]);
+
     fn dispatch_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>);
 }
 
@@ -156,6 +188,9 @@ pub struct ChainsCoordinator<
     reward_set_provider: R,
     notifier: N,
     atlas_config: AtlasConfig,
+    /// set over the admin RPC to stop processing blocks between one block (or sortition) and
+    /// the next
+    pause_requested: Arc<AtomicBool>,
 }
 
 #[derive(Debug)]
@@ -288,6 +323,7 @@ impl<'a, T: BlockEventDispatcher>
             notifier: arc_notices,
             reward_set_provider: OnChainRewardSetProvider(),
             atlas_config,
+            pause_requested: comms.pause_requested.clone(),
         };
 
         loop {
@@ -355,6 +391,7 @@ impl<'a, T: BlockEventDispatcher, U: RewardSetProvider> ChainsCoordinator<'a, T,
             notifier: (),
             attachments_tx,
             atlas_config: AtlasConfig::default(false),
+            pause_requested: Arc::new(AtomicBool::new(false)),
         }
     }
 }
@@ -497,9 +534,55 @@ fn dispatcher_announce_burn_ops<T: BlockEventDispatcher>(
     );
 }
 
+/// Log, record metrics for, and (if there is a dispatcher) announce the start of a reward cycle
+fn announce_reward_cycle_start<T: BlockEventDispatcher>(
+    dispatcher: Option<&T>,
+    burnchain: &Burnchain,
+    burn_header: &BurnchainBlockHeader,
+    reward_cycle_info: &RewardCycleInfo,
+) {
+    let reward_cycle = burnchain
+        .block_height_to_reward_cycle(burn_header.block_height)
+        .expect("FATAL: reward cycle start is before the first burnchain block");
+    let reward_set_size = reward_cycle_info
+        .known_selected_anchor_block()
+        .map(|reward_set| reward_set.len())
+        .unwrap_or(0);
+    let anchor_block_missing = !reward_cycle_info.is_reward_info_known();
+
+    info!("Reward cycle boundary";
+          "reward_cycle" => reward_cycle,
+          "burn_height" => burn_header.block_height,
+          "burn_header_hash" => %burn_header.block_hash,
+          "anchor_block" => ?reward_cycle_info.selected_anchor_block(),
+          "anchor_block_missing" => anchor_block_missing,
+          "reward_set_size" => reward_set_size,
+          "pox_active" => reward_cycle_info.is_pox_active());
+
+    update_reward_cycle(
+        reward_cycle as i64,
+        reward_set_size as i64,
+        reward_cycle_info.is_pox_active(),
+        anchor_block_missing,
+    );
+
+    if let Some(dispatcher) = dispatcher {
+        dispatcher.announce_reward_cycle(
+            &burn_header.block_hash,
+            burn_header.block_height,
+            reward_cycle,
+            reward_cycle_info,
+        );
+    }
+}
+
 impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
     ChainsCoordinator<'a, T, N, U>
 {
+    fn is_pause_requested(&self) -> bool {
+        self.pause_requested.load(Ordering::SeqCst)
+    }
+
     pub fn handle_new_stacks_block(&mut self) -> Result<(), Error> {
         if let Some(pox_anchor) = self.process_ready_blocks()? {
             self.process_new_pox_anchor(pox_anchor)
@@ -552,6 +635,11 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
         );
 
         for unprocessed_block in sortitions_to_process.into_iter() {
+            if self.is_pause_requested() {
+                // the rest will be processed once block processing is resumed
+                info!("Block processing paused; not processing any more sortitions");
+                break;
+            }
             let BurnchainBlockData { header, ops } = unprocessed_block;
 
             // calculate paid rewards during this burnchain block if we announce
@@ -568,7 +656,8 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
             // at this point, we need to figure out if the sortition we are
             //  about to process is the first block in reward cycle.
             let reward_cycle_info = self.get_reward_cycle_info(&header)?;
-            let (next_snapshot, _, reward_set_info) = self
+            let reward_cycle_start = reward_cycle_info.clone();
+            let (next_snapshot, state_transition, reward_set_info) = self
                 .sortition_db
                 .evaluate_sortition(
                     &header,
@@ -582,8 +671,27 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
                     Error::FailedToProcessSortition(e)
                 })?;
 
+            if let Some(reward_cycle_info) = reward_cycle_start.as_ref() {
+                announce_reward_cycle_start(
+                    self.dispatcher,
+                    &self.burnchain,
+                    &header,
+                    reward_cycle_info,
+                );
+            }
+
             if let Some(dispatcher) = self.dispatcher {
                 dispatcher_announce_burn_ops(dispatcher, &header, paid_rewards, reward_set_info);
+
+                let block_commits: Vec<_> = state_transition
+                    .accepted_ops
+                    .into_iter()
+                    .filter_map(|op| match op {
+                        BlockstackOperationType::LeaderBlockCommit(commit) => Some(commit),
+                        _ => None,
+                    })
+                    .collect();
+                dispatcher.announce_sortition(&next_snapshot, &block_commits);
             }
 
             let sortition_id = next_snapshot.sortition_id;
@@ -644,7 +752,29 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
     /// Returns Some(StacksBlockId) if such an anchor block is discovered,
     ///   otherwise returns None
     ///
+    /// Hand one block's attachment instances to Atlas. Only waits on the peer network if it has
+    /// fallen a full channel's worth of blocks behind.
+    fn dispatch_attachments(&self, attachments_instances: HashSet<AttachmentInstance>) {
+        let attachments_instances = match self.attachments_tx.try_send(attachments_instances) {
+            Ok(_) => return,
+            Err(TrySendError::Full(attachments_instances)) => {
+                warn!("Atlas: attachment batches channel is full, waiting on the peer network");
+                attachments_instances
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                error!("Atlas: error dispatching attachments: channel disconnected");
+                return;
+            }
+        };
+        if let Err(e) = self.attachments_tx.send(attachments_instances) {
+            error!("Atlas: error dispatching attachments {}", e);
+        }
+    }
+
     fn process_ready_blocks(&mut self) -> Result<Option<BlockHeaderHash>, Error> {
+        if self.is_pause_requested() {
+            return Ok(None);
+        }
         let canonical_sortition_tip = self.canonical_sortition_tip.as_ref().expect(
             "FAIL: processing a new Stacks block, but don't have a canonical sortition tip",
         );
@@ -682,47 +812,24 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
 
                     let block_hash = block_receipt.header.anchored_header.block_hash();
 
-                    let mut attachments_instances = HashSet::new();
                     for receipt in block_receipt.tx_receipts.iter() {
                         if let TransactionOrigin::Stacks(ref transaction) = receipt.transaction {
-                            if let TransactionPayload::ContractCall(ref contract_call) =
-                                transaction.payload
-                            {
-                                let contract_id = contract_call.to_clarity_contract_id();
+                            if let TransactionPayload::ContractCall(_) = transaction.payload {
                                 increment_contract_calls_processed();
-                                if self.atlas_config.contracts.contains(&contract_id) {
-                                    for event in receipt.events.iter() {
-                                        if let StacksTransactionEvent::SmartContractEvent(
-                                            ref event_data,
-                                        ) = event
-                                        {
-                                            let res = AttachmentInstance::try_new_from_value(
-                                                &event_data.value,
-                                                &contract_id,
-                                                block_receipt.header.index_block_hash(),
-                                                block_receipt.header.block_height,
-                                                receipt.transaction.txid(),
-                                            );
-                                            if let Some(attachment_instance) = res {
-                                                attachments_instances.insert(attachment_instance);
-                                            }
-                                        }
-                                    }
-                                }
                             }
                         }
                     }
+
+                    let attachments_instances = AttachmentInstance::try_new_from_block_receipt(
+                        &self.atlas_config,
+                        &block_receipt,
+                    );
                     if !attachments_instances.is_empty() {
                         info!(
                             "Atlas: {} attachment instances emitted from events",
                             attachments_instances.len()
                         );
-                        match self.attachments_tx.send(attachments_instances) {
-                            Ok(_) => {}
-                            Err(e) => {
-                                error!("Atlas: error dispatching attachments {}", e);
-                            }
-                        };
+                        self.dispatch_attachments(attachments_instances);
                     }
 
                     if let Some(dispatcher) = self.dispatcher {
@@ -777,6 +884,11 @@ impl<'a, T: BlockEventDispatcher, N: CoordinatorNotices, U: RewardSetProvider>
             }
             // TODO: do something with a poison result
 
+            if self.is_pause_requested() {
+                info!("Block processing paused; not processing any more Stacks blocks");
+                break;
+            }
+
             let sortdb_handle = self.sortition_db.tx_handle_begin(canonical_sortition_tip)?;
             processed_blocks = self.chain_state_db.process_blocks(sortdb_handle, 1)?;
         }
@@ -35,8 +35,9 @@ use chainstate::burn::operations::*;
 use chainstate::burn::*;
 use chainstate::coordinator::{Error as CoordError, *};
 use chainstate::stacks::db::{
-    accounts::MinerReward, ClarityTx, StacksChainState, StacksHeaderInfo,
+    accounts::MinerReward, ClarityTx, StacksChainState, StacksHeaderInfo, MINER_REWARD_MATURITY,
 };
+use chainstate::stacks::events::{STXEventType, StacksTransactionEvent, TransactionOrigin};
 use chainstate::stacks::*;
 use clarity_vm::clarity::ClarityConnection;
 use core;
@@ -321,6 +322,22 @@ impl BlockEventDispatcher for NullEventDispatcher {
     ) {
     }
 
+    fn announce_reward_cycle(
+        &self,
+        _burn_block: &BurnchainHeaderHash,
+        _burn_block_height: u64,
+        _reward_cycle: u64,
+        _reward_cycle_info: &RewardCycleInfo,
+    ) {
+    }
+
+    fn announce_sortition(
+        &self,
+        _snapshot: &BlockSnapshot,
+        _block_commits: &[LeaderBlockCommitOp],
+    ) {
+    }
+
     fn dispatch_boot_receipts(&mut self, _receipts: Vec<StacksTransactionReceipt>) {}
 }
 
@@ -614,6 +631,43 @@ fn make_stacks_block_with_input(
     sunset_burn: u64,
     post_sunset_burn: bool,
     input: (Txid, u32),
+) -> (BlockstackOperationType, StacksBlock) {
+    make_stacks_block_with_txs(
+        sort_db,
+        state,
+        burnchain,
+        parent_block,
+        parent_height,
+        miner,
+        my_burn,
+        vrf_key,
+        key_index,
+        recipients,
+        sunset_burn,
+        post_sunset_burn,
+        input,
+        vec![],
+    )
+}
+
+/// build a stacks block with the coinbase and then `txs` off of
+///  parent_block, in the canonical sortition fork of SortitionDB.
+/// parent_block _must_ be included in the StacksChainState
+fn make_stacks_block_with_txs(
+    sort_db: &SortitionDB,
+    state: &mut StacksChainState,
+    burnchain: &Burnchain,
+    parent_block: &BlockHeaderHash,
+    parent_height: u64,
+    miner: &StacksPrivateKey,
+    my_burn: u64,
+    vrf_key: &VRFPrivateKey,
+    key_index: u32,
+    recipients: Option<&RewardSetInfo>,
+    sunset_burn: u64,
+    post_sunset_burn: bool,
+    input: (Txid, u32),
+    txs: Vec<StacksTransaction>,
 ) -> (BlockstackOperationType, StacksBlock) {
     let tx_auth = TransactionAuth::from_p2pkh(miner).unwrap();
 
@@ -669,6 +723,9 @@ fn make_stacks_block_with_input(
     .unwrap();
     let mut epoch_tx = builder.epoch_begin(state, &iconn).unwrap();
     builder.try_mine_tx(&mut epoch_tx, &coinbase_op).unwrap();
+    for tx in txs.iter() {
+        builder.try_mine_tx(&mut epoch_tx, tx).unwrap();
+    }
 
     let block = builder.mine_anchored_block(&mut epoch_tx);
     builder.epoch_finish(epoch_tx);
@@ -2259,6 +2316,223 @@ fn test_stx_transfer_btc_ops() {
     }
 }
 
+#[test]
+fn test_replay_processed_blocks() {
+    let path = "/tmp/stacks-blockchain-replay-processed-blocks";
+    let _r = std::fs::remove_dir_all(path);
+
+    let pox_consts = Some(PoxConstants::new(5, 3, 3, 25, 5, 7010, 8000));
+    let burnchain_conf = get_burnchain(path, pox_consts.clone());
+
+    let vrf_keys: Vec<_> = (0..8).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..8).map(|_| StacksPrivateKey::new()).collect();
+
+    let stacker = p2pkh_from(&StacksPrivateKey::new());
+    let recipient = p2pkh_from(&StacksPrivateKey::new());
+    let balance = 6_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
+    let transfer_amt = 1_000_000_000 * (core::MICROSTACKS_PER_STACKS as u128);
+    let initial_balances = vec![(stacker.clone().into(), balance)];
+
+    setup_states(
+        &[path],
+        &vrf_keys,
+        &committers,
+        pox_consts.clone(),
+        Some(initial_balances),
+    );
+
+    let mut coord = make_coordinator(path, Some(burnchain_conf.clone()));
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, pox_consts.clone());
+    let mut stacks_blocks: Vec<StacksBlock> = vec![];
+    let transfer_txid = next_txid();
+
+    // the last block is mined after the others are replayed
+    for ix in 0..vrf_keys.len() {
+        if ix == vrf_keys.len() - 1 {
+            let mut chainstate = get_chainstate(path);
+            let stacks_tip =
+                SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap();
+            let tip = StacksBlockId::new(&stacks_tip.0, &stacks_tip.1);
+
+            // the genesis block and unknown blocks can't be replayed
+            let genesis_id = chainstate
+                .index_conn()
+                .unwrap()
+                .get_ancestor_block_hash(0, &tip)
+                .unwrap()
+                .unwrap();
+            assert!(chainstate
+                .replay_processed_block(&sort_db.index_conn(), &genesis_id)
+                .unwrap()
+                .is_none());
+            assert!(chainstate
+                .replay_processed_block(&sort_db.index_conn(), &StacksBlockId([0x11; 32]))
+                .unwrap()
+                .is_none());
+
+            let mut transfer_replayed = false;
+            for height in 1..(ix as u64 + 1) {
+                let block_id = chainstate
+                    .index_conn()
+                    .unwrap()
+                    .get_ancestor_block_hash(height, &tip)
+                    .unwrap()
+                    .unwrap();
+                let receipt = chainstate
+                    .replay_processed_block(&sort_db.index_conn(), &block_id)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(receipt.header.block_height, height);
+                assert_eq!(receipt.header.index_block_hash(), block_id);
+                assert_eq!(
+                    receipt.header.anchored_header.block_hash(),
+                    stacks_blocks[height as usize - 1].block_hash()
+                );
+                assert_eq!(
+                    receipt.matured_rewards.is_empty(),
+                    height <= MINER_REWARD_MATURITY + 1
+                );
+
+                // one receipt per transaction, and one for each processed burnchain operation
+                let stacks_txs = receipt
+                    .tx_receipts
+                    .iter()
+                    .filter(|r| match r.transaction {
+                        TransactionOrigin::Stacks(_) => true,
+                        TransactionOrigin::Burn(_) => false,
+                    })
+                    .count();
+                assert_eq!(stacks_txs, stacks_blocks[height as usize - 1].txs.len());
+
+                for tx_receipt in receipt.tx_receipts.iter() {
+                    if tx_receipt.transaction == TransactionOrigin::Burn(transfer_txid.clone()) {
+                        assert!(!transfer_replayed);
+                        transfer_replayed = true;
+                        match tx_receipt.events[0] {
+                            StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+                                ref event,
+                            )) => {
+                                assert_eq!(event.amount, transfer_amt);
+                            }
+                            _ => panic!("Expected an STX transfer event"),
+                        }
+                    }
+                }
+
+                // replays are repeatable, and leave no trace
+                let again = chainstate
+                    .replay_processed_block(&sort_db.index_conn(), &block_id)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(again.tx_receipts.len(), receipt.tx_receipts.len());
+                assert_eq!(again.anchored_block_cost, receipt.anchored_block_cost);
+            }
+            assert!(transfer_replayed);
+            assert_eq!(
+                SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap(),
+                stacks_tip
+            );
+        }
+
+        let vrf_key = &vrf_keys[ix];
+        let miner = &committers[ix];
+
+        let mut burnchain = get_burnchain_db(path, pox_consts.clone());
+        let mut chainstate = get_chainstate(path);
+
+        let parent = if ix == 0 {
+            BlockHeaderHash([0; 32])
+        } else {
+            stacks_blocks[ix - 1].header.block_hash()
+        };
+
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        let next_mock_header = BurnchainBlockHeader {
+            block_height: burnchain_tip.block_height + 1,
+            block_hash: BurnchainHeaderHash([0; 32]),
+            parent_block_hash: burnchain_tip.block_hash,
+            num_txs: 0,
+            timestamp: 1,
+        };
+
+        let reward_cycle_info = coord.get_reward_cycle_info(&next_mock_header).unwrap();
+        let next_block_recipients = get_rw_sortdb(path, pox_consts.clone())
+            .test_get_next_block_recipients(&burnchain_conf, reward_cycle_info.as_ref())
+            .unwrap();
+
+        let b = get_burnchain(path, pox_consts.clone());
+        let (good_op, block) = if ix == 0 {
+            make_genesis_block_with_recipients(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+                next_block_recipients.as_ref(),
+            )
+        } else {
+            make_stacks_block_with_recipients(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                1000,
+                vrf_key,
+                ix as u32,
+                next_block_recipients.as_ref(),
+            )
+        };
+
+        let mut ops = vec![good_op];
+        if ix == 0 {
+            ops.push(BlockstackOperationType::PreStx(PreStxOp {
+                output: stacker.clone(),
+                txid: next_txid(),
+                vtxindex: 5,
+                block_height: 0,
+                burn_header_hash: BurnchainHeaderHash([0; 32]),
+            }));
+        } else if ix == 1 {
+            ops.push(BlockstackOperationType::TransferStx(TransferStxOp {
+                sender: stacker.clone(),
+                recipient: recipient.clone(),
+                transfered_ustx: transfer_amt,
+                memo: vec![],
+                txid: transfer_txid.clone(),
+                vtxindex: 5,
+                block_height: 0,
+                burn_header_hash: BurnchainHeaderHash([0; 32]),
+            }));
+        }
+
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            ops,
+            vec![].iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        assert_eq!(&tip.winning_stacks_block_hash, &block.header.block_hash());
+        stacks_blocks.push(block.clone());
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+        coord.handle_new_stacks_block().unwrap();
+    }
+
+    // the chain still grows after the replays
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+    assert_eq!(tip.canonical_stacks_tip_height, vrf_keys.len() as u64);
+}
+
 #[test]
 fn test_initial_coinbase_reward_distributions() {
     let path = "/tmp/initial_coinbase_reward_distributions";
@@ -3386,6 +3660,20 @@ fn test_pox_fork_out_of_order() {
         assert_eq!(&pox_id.to_string(), "11000");
     }
 
+    // the blinded coordinator could still reprocess from the first reward cycle with a missing
+    //  anchor block, but the other one only from the current reward cycle
+    let burnchain = get_burnchain(path, None);
+    assert_eq!(
+        sort_db.get_pox_reprocessing_floor(&burnchain).unwrap(),
+        burnchain.reward_cycle_to_block_height(4)
+    );
+    assert_eq!(
+        sort_db_blind
+            .get_pox_reprocessing_floor(&burnchain)
+            .unwrap(),
+        burnchain.reward_cycle_to_block_height(1)
+    );
+
     // now, we reveal to the blinded coordinator, but out of order.
     //  reveal block 0 first,
     //   then the 6-7-8-9-10 fork.
@@ -3408,6 +3696,12 @@ fn test_pox_fork_out_of_order() {
         let pox_id = ic.get_pox_id().unwrap();
         assert_eq!(&pox_id.to_string(), "11110");
     }
+    assert_eq!(
+        sort_db_blind
+            .get_pox_reprocessing_floor(&burnchain)
+            .unwrap(),
+        burnchain.reward_cycle_to_block_height(3)
+    );
 
     let block_height = eval_at_chain_tip(path_blinded, &sort_db_blind, "block-height");
     assert_eq!(block_height, Value::UInt(1));
@@ -3544,6 +3838,306 @@ fn test_pox_fork_out_of_order() {
     );
 }
 
+#[test]
+fn test_pause_block_processing() {
+    let path = "/tmp/stacks-blockchain-pause-block-processing";
+    let _r = std::fs::remove_dir_all(path);
+
+    let vrf_keys: Vec<_> = (0..6).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..6).map(|_| StacksPrivateKey::new()).collect();
+
+    setup_states(&[path], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    coord.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+    let b = get_burnchain(path, None);
+    let mut parent = BlockHeaderHash([0; 32]);
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        };
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            vec![].iter_mut(),
+        );
+        coord.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let block_hash = block.header.block_hash();
+        preprocess_block(&mut chainstate, &sort_db, &tip, block);
+
+        // the last block is stored while processing is paused
+        if ix == vrf_keys.len() - 1 {
+            coord.pause_requested.store(true, Ordering::SeqCst);
+        }
+        coord.handle_new_stacks_block().unwrap();
+        parent = block_hash;
+    }
+
+    // neither the last Stacks block nor a new burnchain block get processed while paused
+    let paused_tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+    assert_eq!(
+        paused_tip.canonical_stacks_tip_height,
+        vrf_keys.len() as u64 - 1
+    );
+    {
+        let mut burnchain = get_burnchain_db(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![],
+            vec![].iter_mut(),
+        );
+    }
+    coord.handle_new_burnchain_block().unwrap();
+    coord.handle_new_stacks_block().unwrap();
+    assert_eq!(
+        SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap(),
+        paused_tip
+    );
+
+    // ...and both are processed once resumed
+    coord.pause_requested.store(false, Ordering::SeqCst);
+    coord.handle_new_burnchain_block().unwrap();
+    coord.handle_new_stacks_block().unwrap();
+    let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+    assert_eq!(tip.block_height, paused_tip.block_height + 1);
+    assert_eq!(tip.canonical_stacks_tip_height, vrf_keys.len() as u64);
+    assert_eq!(tip.canonical_stacks_tip_hash, parent);
+}
+
+#[test]
+fn test_pause_block_processing_signals() {
+    let (receivers, channels) = CoordinatorCommunication::instantiate();
+
+    channels.pause_block_processing();
+    channels.announce_new_stacks_block();
+    let waiter = std::thread::spawn(move || {
+        let mut events = vec![];
+        for _ in 0..2 {
+            events.push(match receivers.wait_on() {
+                CoordinatorEvents::NEW_STACKS_BLOCK => "stacks",
+                CoordinatorEvents::NEW_BURN_BLOCK => "burn",
+                CoordinatorEvents::STOP => "stop",
+                CoordinatorEvents::TIMEOUT => "timeout",
+            });
+        }
+        events
+    });
+
+    // the coordinator holds off on the new block until it's resumed
+    let start = std::time::Instant::now();
+    while !channels.is_block_processing_paused() {
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert!(channels.is_block_processing_pause_requested());
+
+    channels.resume_block_processing();
+    assert!(!channels.is_block_processing_paused());
+    assert_eq!(waiter.join().unwrap(), vec!["timeout", "burn"]);
+}
+
+#[test]
+fn test_parallel_analysis_matches_serial() {
+    let path = "/tmp/stacks-blockchain-parallel-analysis";
+    // a second set of states that processes the same blocks without parallel analysis
+    let path_serial = "/tmp/stacks-blockchain-parallel-analysis.serial";
+    let _r = std::fs::remove_dir_all(path);
+    let _r = std::fs::remove_dir_all(path_serial);
+
+    let vrf_keys: Vec<_> = (0..2).map(|_| VRFPrivateKey::new()).collect();
+    let committers: Vec<_> = (0..2).map(|_| StacksPrivateKey::new()).collect();
+    let deployer = StacksPrivateKey::new();
+    let deployer_addr = p2pkh_from(&deployer);
+
+    setup_states(&[path, path_serial], &vrf_keys, &committers, None, None);
+
+    let mut coord = make_coordinator(path, None);
+    let mut coord_serial = make_coordinator(path_serial, None);
+    coord.chain_state_db.parallel_analysis_workers = 4;
+
+    coord.handle_new_burnchain_block().unwrap();
+    coord_serial.handle_new_burnchain_block().unwrap();
+
+    let sort_db = get_sortition_db(path, None);
+    let sort_db_serial = get_sortition_db(path_serial, None);
+
+    // counter-b calls counter-a, which is deployed earlier in the same block, so it can't
+    //  use its pre-analysis; the other contracts don't depend on each other.
+    let contracts = vec![
+        ("counter-a", "(define-read-only (get-count) (ok u1))".to_string()),
+        (
+            "counter-b",
+            format!(
+                "(define-read-only (get-count) (ok (+ u1 (unwrap-panic (contract-call? '{}.counter-a get-count)))))",
+                &deployer_addr
+            ),
+        ),
+        ("greeter", "(define-read-only (greet) (ok \"hello\"))".to_string()),
+        (
+            "adder",
+            "(define-read-only (add (a int) (b int)) (ok (+ a b)))".to_string(),
+        ),
+    ];
+    let deploys: Vec<_> = contracts
+        .iter()
+        .enumerate()
+        .map(|(nonce, (name, code))| {
+            let mut tx = StacksTransaction::new(
+                TransactionVersion::Testnet,
+                TransactionAuth::from_p2pkh(&deployer).unwrap(),
+                TransactionPayload::new_smart_contract(name, code).unwrap(),
+            );
+            tx.chain_id = 0x80000000;
+            tx.auth.set_origin_nonce(nonce as u64);
+            tx.set_tx_fee(0);
+            let mut tx_signer = StacksTransactionSigner::new(&tx);
+            tx_signer.sign_origin(&deployer).unwrap();
+            tx_signer.get_tx().unwrap()
+        })
+        .collect();
+    let mut deploys = Some(deploys);
+
+    let mut parent = BlockHeaderHash([0; 32]);
+    for (ix, (vrf_key, miner)) in vrf_keys.iter().zip(committers.iter()).enumerate() {
+        let mut burnchain = get_burnchain_db(path, None);
+        let mut chainstate = get_chainstate(path);
+        let mut chainstate_serial = get_chainstate(path_serial);
+        let b = get_burnchain(path, None);
+        let burnchain_tip = burnchain.get_canonical_chain_tip().unwrap();
+        let burnchain_serial = get_burnchain_db(path_serial, None);
+
+        let (op, block) = if ix == 0 {
+            make_genesis_block(
+                &sort_db,
+                &mut chainstate,
+                &parent,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+            )
+        } else {
+            make_stacks_block_with_txs(
+                &sort_db,
+                &mut chainstate,
+                &b,
+                &parent,
+                burnchain_tip.block_height,
+                miner,
+                10000,
+                vrf_key,
+                ix as u32,
+                None,
+                0,
+                false,
+                (Txid([0; 32]), 0),
+                deploys.take().unwrap(),
+            )
+        };
+
+        produce_burn_block(
+            &mut burnchain,
+            &burnchain_tip.block_hash,
+            vec![op],
+            [burnchain_serial].iter_mut(),
+        );
+        // handle the sortition
+        coord.handle_new_burnchain_block().unwrap();
+        coord_serial.handle_new_burnchain_block().unwrap();
+
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sort_db.conn()).unwrap();
+        let tip_serial = SortitionDB::get_canonical_burn_chain_tip(sort_db_serial.conn()).unwrap();
+
+        // load the block into staging
+        let block_hash = block.header.block_hash();
+        assert_eq!(&tip.winning_stacks_block_hash, &block_hash);
+        assert_eq!(&tip_serial.winning_stacks_block_hash, &block_hash);
+
+        preprocess_block(&mut chainstate, &sort_db, &tip, block.clone());
+        preprocess_block(&mut chainstate_serial, &sort_db_serial, &tip_serial, block);
+
+        // handle the stacks block
+        coord.handle_new_stacks_block().unwrap();
+        coord_serial.handle_new_stacks_block().unwrap();
+
+        parent = block_hash;
+    }
+
+    let stacks_tip = SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap();
+    let stacks_tip_serial =
+        SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db_serial.conn()).unwrap();
+    assert_eq!(stacks_tip.1, parent);
+    assert_eq!(stacks_tip_serial.1, parent);
+
+    // both paths charged the block the same cost...
+    let block_id = StacksBlockId::new(&stacks_tip.0, &stacks_tip.1);
+    let cost =
+        StacksChainState::get_stacks_block_anchored_cost(get_chainstate(path).db(), &block_id)
+            .unwrap()
+            .unwrap();
+    let cost_serial = StacksChainState::get_stacks_block_anchored_cost(
+        get_chainstate(path_serial).db(),
+        &block_id,
+    )
+    .unwrap()
+    .unwrap();
+    assert_eq!(cost, cost_serial);
+    assert!(cost.exceeds(&ExecutionCost::zero()));
+
+    // ...and left the same contracts behind
+    for eval in [
+        format!("(contract-call? '{}.counter-b get-count)", &deployer_addr),
+        format!("(contract-call? '{}.greeter greet)", &deployer_addr),
+        format!("(contract-call? '{}.adder add 2 3)", &deployer_addr),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            eval_at_chain_tip(path, &sort_db, eval),
+            eval_at_chain_tip(path_serial, &sort_db_serial, eval)
+        );
+    }
+    assert_eq!(
+        eval_at_chain_tip(
+            path,
+            &sort_db,
+            &format!("(contract-call? '{}.counter-b get-count)", &deployer_addr)
+        ),
+        Value::okay(Value::UInt(2)).unwrap()
+    );
+}
+
 fn eval_at_chain_tip(chainstate_path: &str, sort_db: &SortitionDB, eval: &str) -> Value {
     let stacks_tip = SortitionDB::get_canonical_stacks_chain_tip_hash(sort_db.conn()).unwrap();
     let mut chainstate = get_chainstate(chainstate_path);
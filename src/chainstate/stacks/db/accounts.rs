@@ -425,6 +425,59 @@ impl StacksChainState {
         Ok(principal_seq_opt.map(|(principal, seq)| (principal.into(), seq)))
     }
 
+    /// Record the genesis STX vesting schedule, indexed by recipient.
+    /// Each entry is (recipient, Stacks block height at which it unlocks, amount in uSTX).
+    pub fn insert_genesis_lockups<'a>(
+        tx: &mut StacksDBTx<'a>,
+        lockups: &[(PrincipalData, u64, u64)],
+    ) -> Result<(), Error> {
+        for (recipient, block_height, amount) in lockups.iter() {
+            let args: &[&dyn ToSql] = &[
+                &recipient.to_string(),
+                &u64_to_sql(*block_height)?,
+                &format!("{}", amount),
+            ];
+            tx.execute(
+                "INSERT INTO genesis_lockups (recipient, block_height, amount) VALUES (?1, ?2, ?3)",
+                args,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Get the genesis STX vesting schedule for a principal, as a list of (Stacks block height,
+    /// amount in uSTX) in ascending block height order.  Entries at the same height are merged.
+    pub fn get_genesis_lockups(
+        conn: &DBConn,
+        principal: &PrincipalData,
+    ) -> Result<Vec<(u64, u128)>, Error> {
+        let qry = "SELECT block_height, amount FROM genesis_lockups WHERE recipient = ?1 ORDER BY block_height ASC";
+        let args: &[&dyn ToSql] = &[&principal.to_string()];
+        let mut stmt = conn.prepare(qry)?;
+        let mut rows = stmt.query(args)?;
+
+        let mut schedule: Vec<(u64, u128)> = vec![];
+        while let Some(row) = rows.next()? {
+            let block_height = u64::from_column(row, "block_height")?;
+            let amount_text: String = row.get_unwrap("amount");
+            let amount = amount_text
+                .parse::<u128>()
+                .map_err(|_e| Error::DBError(db_error::ParseError))?;
+
+            match schedule.last_mut() {
+                Some((last_height, last_amount)) if *last_height == block_height => {
+                    *last_amount = last_amount
+                        .checked_add(amount)
+                        .ok_or(Error::DBError(db_error::Overflow))?;
+                }
+                _ => {
+                    schedule.push((block_height, amount));
+                }
+            }
+        }
+        Ok(schedule)
+    }
+
     /// Get the scheduled miner rewards at a particular index hash
     pub fn get_scheduled_block_rewards_at_block<'a>(
         tx: &mut StacksDBTx<'a>,
@@ -480,6 +533,31 @@ impl StacksChainState {
         StacksChainState::get_scheduled_block_rewards_in_fork_at_height(tx, tip, block_height)
     }
 
+    /// Get all the scheduled rewards earned by an address -- as a miner or as a user burn
+    /// supporter -- in the fork ending at `tip`, in ascending block height order.
+    pub fn get_scheduled_block_rewards_for_address(
+        conn: &StacksDBConn,
+        tip: &StacksBlockId,
+        address: &StacksAddress,
+    ) -> Result<Vec<MinerPaymentSchedule>, Error> {
+        let qry = "SELECT * FROM payments WHERE address = ?1 ORDER BY stacks_block_height ASC, vtxindex ASC";
+        let args: &[&dyn ToSql] = &[&address.to_string()];
+        let rows =
+            query_rows::<MinerPaymentSchedule, _>(conn, qry, args).map_err(Error::DBError)?;
+
+        // the payments table holds rewards from every fork
+        let mut in_fork = vec![];
+        for row in rows.into_iter() {
+            let index_block_hash =
+                StacksBlockHeader::make_index_block_hash(&row.consensus_hash, &row.block_hash);
+            if conn.get_ancestor_block_hash(row.stacks_block_height, tip)? == Some(index_block_hash)
+            {
+                in_fork.push(row);
+            }
+        }
+        Ok(in_fork)
+    }
+
     /// Get the miner info at a particular burn/stacks block
     pub fn get_miner_info(
         conn: &DBConn,
@@ -1011,6 +1089,52 @@ mod test {
         };
     }
 
+    #[test]
+    fn get_scheduled_block_rewards_for_address() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "get_scheduled_block_rewards_for_address");
+        let miner_1 =
+            StacksAddress::from_string(&"SP1A2K3ENNA6QQ7G8DVJXM24T6QMBDVS7D0TRTAR5".to_string())
+                .unwrap();
+        let miner_2 =
+            StacksAddress::from_string(&"SP2837ZMC89J40K4YTS64B00M7065C6X46JX6ARG0".to_string())
+                .unwrap();
+
+        // miner 1 mines blocks 1 and 3, and miner 2 mines block 2
+        let mut tips = vec![StacksHeaderInfo::regtest_genesis()];
+        let mut rewards = vec![];
+        for (i, miner) in [&miner_1, &miner_2, &miner_1].iter().enumerate() {
+            let mut reward =
+                make_dummy_miner_payment_schedule(miner, 500 + (i as u128), 0, 0, 1000, 1000);
+            reward.stacks_block_height = (i as u64) + 1;
+            let tip = advance_tip(&mut chainstate, &tips[i], &mut reward, &mut vec![]);
+            tips.push(tip);
+            rewards.push(reward);
+        }
+
+        let conn = chainstate.index_conn().unwrap();
+        let tip_2 = tips[2].index_block_hash();
+        let tip_3 = tips[3].index_block_hash();
+
+        assert_eq!(
+            StacksChainState::get_scheduled_block_rewards_for_address(&conn, &tip_3, &miner_1)
+                .unwrap(),
+            vec![rewards[0].clone(), rewards[2].clone()]
+        );
+        assert_eq!(
+            StacksChainState::get_scheduled_block_rewards_for_address(&conn, &tip_3, &miner_2)
+                .unwrap(),
+            vec![rewards[1].clone()]
+        );
+
+        // rewards from blocks that are not ancestors of the tip are excluded
+        assert_eq!(
+            StacksChainState::get_scheduled_block_rewards_for_address(&conn, &tip_2, &miner_1)
+                .unwrap(),
+            vec![rewards[0].clone()]
+        );
+    }
+
     #[test]
     fn miner_reward_one_miner_no_tx_fees_no_users() {
         let miner_1 =
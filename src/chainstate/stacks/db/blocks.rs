@@ -45,7 +45,9 @@ use chainstate::stacks::{
     C32_ADDRESS_VERSION_MAINNET_MULTISIG, C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
     C32_ADDRESS_VERSION_TESTNET_MULTISIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
 };
-use clarity_vm::clarity::{ClarityBlockConnection, ClarityConnection, ClarityInstance};
+use clarity_vm::clarity::{
+    ClarityBlockConnection, ClarityConnection, ClarityInstance, PreAnalyzedContract,
+};
 use core::mempool::MAXIMUM_MEMPOOL_TX_CHAINING;
 use core::*;
 use net::BlocksInvData;
@@ -64,6 +66,8 @@ use util::strings::StacksString;
 pub use vm::analysis::errors::{CheckError, CheckErrors};
 use vm::analysis::run_analysis;
 use vm::ast::build_ast;
+use vm::ast::errors::ParseError;
+use vm::ast::parser;
 use vm::contexts::AssetMap;
 use vm::contracts::Contract;
 use vm::costs::LimitedCostTracker;
@@ -90,6 +94,27 @@ pub struct StagingMicroblock {
     pub block_data: Vec<u8>,
 }
 
+/// One of the microblock streams stored for an anchored block: the path from the stream's
+/// first microblock to one of the tips of the microblock tree.  Streams that fork off of one
+/// another share the microblocks before the fork.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MicroblockStreamFork {
+    pub tip_hash: BlockHeaderHash,
+    pub min_seq: u16,
+    pub max_seq: u16,
+    pub num_microblocks: u64,
+    /// sequence number of this stream's first microblock that is not shared with another stream
+    pub fork_seq: Option<u16>,
+    /// hash of the public key that signed the tip
+    pub signer: Option<Hash160>,
+    /// when the stream's first and last microblocks were stored
+    pub first_seen: Option<u64>,
+    pub tip_first_seen: Option<u64>,
+    /// number of this stream's microblocks that a child anchored block confirmed
+    pub num_confirmed: u64,
+    pub orphaned: bool,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct StagingBlock {
     pub consensus_hash: ConsensusHash,
@@ -145,6 +170,11 @@ pub enum MemPoolRejection {
     BadTransactionVersion,
     TransferRecipientIsSender(PrincipalData),
     TransferAmountMustBePositive,
+    TransactionExpired(u64, u64),
+    ContractSourceTooLarge(u64, u64),
+    ContractTooDeep(u64, u64),
+    ContractTooManyExpressions(u64, u64),
+    ContractParseFailure(ParseError),
     DBError(db_error),
     Other(String),
 }
@@ -190,6 +220,12 @@ impl MemPoolRejection {
                 Some(json!({"recipient": recipient.to_string()})),
             ),
             TransferAmountMustBePositive => ("TransferAmountMustBePositive", None),
+            TransactionExpired(expire_height, block_height) => (
+                "TransactionExpired",
+                Some(json!({
+                    "expire_height": expire_height,
+                    "block_height": block_height})),
+            ),
             BadNonces(TransactionNonceMismatch {
                 expected,
                 actual,
@@ -211,6 +247,28 @@ impl MemPoolRejection {
                     "actual": format!("0x{}", to_hex(&actual.to_be_bytes()))
                 })),
             ),
+            ContractSourceTooLarge(actual, max) => (
+                "ContractSourceTooLarge",
+                Some(json!({
+                    "expected": max,
+                    "actual": actual})),
+            ),
+            ContractTooDeep(actual, max) => (
+                "ContractTooDeep",
+                Some(json!({
+                    "expected": max,
+                    "actual": actual})),
+            ),
+            ContractTooManyExpressions(actual, max) => (
+                "ContractTooManyExpressions",
+                Some(json!({
+                    "expected": max,
+                    "actual": actual})),
+            ),
+            ContractParseFailure(e) => (
+                "ContractParseFailure",
+                Some(json!({"message": e.to_string()})),
+            ),
             NoSuchContract => ("NoSuchContract", None),
             NoSuchPublicFunction => ("NoSuchPublicFunction", None),
             BadFunctionArgument(e) => (
@@ -263,6 +321,24 @@ impl From<db_error> for MemPoolRejection {
 pub const MINIMUM_TX_FEE: u64 = 1;
 pub const MINIMUM_TX_FEE_RATE_PER_BYTE: u64 = 1;
 
+// Structural limits on contract-deploy transactions admitted to the mempool.
+//  These are checked with the lexer alone, so a pathological contract is
+//  rejected before a miner spends any execution budget on it.
+pub const MAXIMUM_MEMPOOL_CONTRACT_SOURCE_LEN: u64 = 256 * 1024;
+pub const MAXIMUM_MEMPOOL_CONTRACT_AST_DEPTH: u64 = 64;
+pub const MAXIMUM_MEMPOOL_CONTRACT_EXPRESSIONS: u64 = 100_000;
+
+/// Approximate number of burnchain blocks per year, per the token whitepaper.
+const COINBASE_BLOCKS_PER_YEAR: u64 = 52596;
+
+/// Coinbase reward eras, as (burn block height offset from the first burnchain block, STX).
+const COINBASE_REWARD_SCHEDULE: &'static [(u64, u128)] = &[
+    (0, 1000),
+    (COINBASE_BLOCKS_PER_YEAR * 4, 500),
+    (COINBASE_BLOCKS_PER_YEAR * 8, 250),
+    (COINBASE_BLOCKS_PER_YEAR * 12, 125),
+];
+
 impl StagingBlock {
     pub fn is_first_mined(&self) -> bool {
         self.parent_anchored_block_hash == FIRST_STACKS_BLOCK_HASH
@@ -894,6 +970,25 @@ impl StacksChainState {
         Ok(Some(block))
     }
 
+    /// Load up a block from the chunk store, given its index block hash.
+    /// Returns Ok(Some(block)) if found.
+    /// Returns Ok(None) if this block was found, but is known to be invalid
+    /// Returns Err(...) on not found or I/O error
+    pub fn load_block_indexed(
+        blocks_dir: &String,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<StacksBlock>, Error> {
+        let block_path = StacksChainState::get_index_block_path(blocks_dir, index_block_hash)?;
+        let sz = StacksChainState::get_file_size(&block_path)?;
+        if sz == 0 {
+            debug!("Zero-sized block {}", index_block_hash);
+            return Ok(None);
+        }
+
+        let block: StacksBlock = StacksChainState::consensus_load(&block_path)?;
+        Ok(Some(block))
+    }
+
     /// Load up an anchored block header from the chunk store.
     /// Returns Ok(Some(blockheader)) if found.
     /// Returns Ok(None) if this block was found, but is known to be invalid
@@ -1717,6 +1812,14 @@ impl StacksChainState {
         tx.execute(&block_sql, block_args)
             .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
 
+        // remember when we first saw it
+        let arrival_sql = "INSERT OR IGNORE INTO staging_microblocks_arrivals (index_microblock_hash, arrival_time) VALUES (?1, ?2)";
+        let arrival_args: &[&dyn ToSql] =
+            &[&index_microblock_hash, &u64_to_sql(get_epoch_time_secs())?];
+
+        tx.execute(&arrival_sql, arrival_args)
+            .map_err(|e| Error::DBError(db_error::SqliteError(e)))?;
+
         Ok(())
     }
 
@@ -1836,6 +1939,81 @@ impl StacksChainState {
             })
     }
 
+    /// Get every microblock stream stored for the given anchored block -- there is more than one
+    /// if its miner forked its stream -- ordered by when each stream's tip was first seen.
+    pub fn get_microblock_stream_forks(
+        blocks_conn: &DBConn,
+        parent_index_block_hash: &StacksBlockId,
+    ) -> Result<Vec<MicroblockStreamFork>, Error> {
+        let sql = "SELECT * FROM staging_microblocks WHERE index_block_hash = ?1";
+        let args: &[&dyn ToSql] = &[&parent_index_block_hash];
+        let rows = query_rows::<StagingMicroblock, _>(blocks_conn, sql, args)?;
+
+        let mut microblocks: HashMap<BlockHeaderHash, StagingMicroblock> = HashMap::new();
+        let mut children: HashMap<BlockHeaderHash, u64> = HashMap::new();
+        for row in rows.into_iter() {
+            *children.entry(row.parent_hash.clone()).or_insert(0) += 1;
+            microblocks.insert(row.microblock_hash.clone(), row);
+        }
+
+        let arrival_time = |mblock: &StagingMicroblock| -> Result<Option<u64>, Error> {
+            let index_microblock_hash = StacksBlockHeader::make_index_block_hash(
+                &mblock.consensus_hash,
+                &mblock.microblock_hash,
+            );
+            let times = StacksChainState::read_i64s(
+                blocks_conn,
+                "SELECT arrival_time FROM staging_microblocks_arrivals WHERE index_microblock_hash = ?1",
+                &[&index_microblock_hash],
+            )?;
+            Ok(times.first().map(|t| *t as u64))
+        };
+
+        let mut forks = vec![];
+        for tip in microblocks.values() {
+            if children.contains_key(&tip.microblock_hash) {
+                continue;
+            }
+
+            // walk back to the start of the stream
+            let mut path = vec![tip];
+            while let Some(parent) = microblocks.get(&path[path.len() - 1].parent_hash) {
+                if path.len() > microblocks.len() {
+                    // the stored microblocks form a cycle, which can't happen with valid hashes
+                    break;
+                }
+                path.push(parent);
+            }
+            path.reverse();
+
+            let fork_seq = path
+                .iter()
+                .find(|mblock| children.get(&mblock.parent_hash).cloned().unwrap_or(0) > 1)
+                .map(|mblock| mblock.sequence);
+
+            let signer =
+                StacksChainState::load_staging_microblock_bytes(blocks_conn, &tip.microblock_hash)?
+                    .and_then(|bytes| StacksMicroblock::consensus_deserialize(&mut &bytes[..]).ok())
+                    .and_then(|mblock| mblock.header.check_recover_pubkey().ok());
+
+            forks.push(MicroblockStreamFork {
+                tip_hash: tip.microblock_hash.clone(),
+                min_seq: path[0].sequence,
+                max_seq: tip.sequence,
+                num_microblocks: path.len() as u64,
+                fork_seq,
+                signer,
+                first_seen: arrival_time(path[0])?,
+                tip_first_seen: arrival_time(tip)?,
+                num_confirmed: path.iter().filter(|mblock| mblock.processed).count() as u64,
+                orphaned: path.iter().any(|mblock| mblock.orphaned),
+            });
+        }
+
+        forks.sort_by_key(|fork| (fork.tip_first_seen, fork.max_seq));
+        Ok(forks)
+    }
+
     /// Given an anchor block's index hash, does it confirm any microblocks?
     /// Due to the way we process microblocks -- i.e. all microblocks between a parent/child anchor
     /// block are processed atomically -- it is sufficient to check that there exists a microblock
@@ -2461,6 +2639,73 @@ impl StacksChainState {
         Ok(res)
     }
 
+    /// Get the headers of every processed block that includes the given transaction, either in the
+    /// block itself or in the microblock stream it confirms.  There will be more than one if the
+    /// transaction was mined in more than one fork.  Returned in order of block height.
+    pub fn get_transaction_blocks(
+        conn: &DBConn,
+        txid: &Txid,
+    ) -> Result<Vec<StacksHeaderInfo>, Error> {
+        let sql = "SELECT block_headers.* FROM block_headers JOIN transaction_blocks \
+                   ON block_headers.index_block_hash = transaction_blocks.index_block_hash \
+                   WHERE transaction_blocks.txid = ?1 \
+                   ORDER BY block_headers.block_height ASC, block_headers.index_block_hash ASC";
+        query_rows(conn, sql, &[txid as &dyn ToSql]).map_err(Error::DBError)
+    }
+
+    /// Load a transaction from a processed anchored block, or from the microblock stream that
+    /// the block confirms.
+    /// Returns Ok(None) if the transaction is in neither.
+    pub fn load_processed_transaction(
+        &self,
+        index_block_hash: &StacksBlockId,
+        txid: &Txid,
+    ) -> Result<Option<StacksTransaction>, Error> {
+        if let Some(block) =
+            StacksChainState::load_block_indexed(&self.blocks_path, index_block_hash)?
+        {
+            if let Some(tx) = block.txs.into_iter().find(|tx| tx.txid() == *txid) {
+                return Ok(Some(tx));
+            }
+        }
+
+        let microblocks = self
+            .load_confirmed_microblock_stream(index_block_hash)?
+            .unwrap_or(vec![]);
+
+        Ok(microblocks
+            .into_iter()
+            .flat_map(|mblock| mblock.txs.into_iter())
+            .find(|tx| tx.txid() == *txid))
+    }
+
+    /// Load the processed microblock stream that a processed anchored block confirms, in sequence
+    /// order.
+    /// Returns Ok(None) if the block confirms no microblocks.
+    pub fn load_confirmed_microblock_stream(
+        &self,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<Vec<StacksMicroblock>>, Error> {
+        let tail_index_microblock_hash =
+            match self.get_confirmed_microblock_index_hash(index_block_hash)? {
+                Some(hash) => hash,
+                None => return Ok(None),
+            };
+        let mblock_info = match StacksChainState::load_staging_microblock_info_indexed(
+            &self.db(),
+            &tail_index_microblock_hash,
+        )? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+        StacksChainState::load_processed_microblock_stream_fork(
+            &self.db(),
+            &mblock_info.consensus_hash,
+            &mblock_info.anchored_block_hash,
+            &mblock_info.microblock_hash,
+        )
+    }
+
     /// Given an index anchor block hash, get the index microblock hash for a confirmed microblock stream.
     pub fn get_confirmed_microblock_index_hash(
         &self,
@@ -3487,20 +3732,37 @@ impl StacksChainState {
         //   where we are computing the coinbase reward for blocks that occur *before*
         //   the `first_burn_block_height`
         let effective_ht = burn_block_height.saturating_sub(first_burn_block_height);
-        let blocks_per_year = 52596;
-        let stx_reward = if effective_ht < blocks_per_year * 4 {
-            1000
-        } else if effective_ht < blocks_per_year * 8 {
-            500
-        } else if effective_ht < blocks_per_year * 12 {
-            250
-        } else {
-            125
-        };
+        let stx_reward = COINBASE_REWARD_SCHEDULE
+            .iter()
+            .rev()
+            .find(|(start_ht, _)| effective_ht >= *start_ht)
+            .map(|(_, stx_reward)| *stx_reward)
+            .expect("BUG: coinbase reward schedule does not start at 0");
 
         stx_reward * (MICROSTACKS_PER_STACKS as u128)
     }
 
+    /// Get the coinbase reward schedule, as a list of (first burn block height, last burn block
+    /// height, coinbase in microSTX) in ascending order.  The last era has no end.
+    pub fn get_coinbase_reward_schedule(
+        first_burn_block_height: u64,
+    ) -> Vec<(u64, Option<u64>, u128)> {
+        COINBASE_REWARD_SCHEDULE
+            .iter()
+            .enumerate()
+            .map(|(i, (start_ht, stx_reward))| {
+                let end_ht = COINBASE_REWARD_SCHEDULE
+                    .get(i + 1)
+                    .map(|(next_start_ht, _)| first_burn_block_height + next_start_ht - 1);
+                (
+                    first_burn_block_height + start_ht,
+                    end_ht,
+                    stx_reward * (MICROSTACKS_PER_STACKS as u128),
+                )
+            })
+            .collect()
+    }
+
     /// Create the block reward.
     /// `coinbase_reward_ustx` is the total coinbase reward for this block, including any
     ///    accumulated rewards from missed sortitions or initial mining rewards.
@@ -4036,6 +4298,72 @@ impl StacksChainState {
         Ok((fees, burns, receipts))
     }
 
+    /// The contract a transaction deploys (and its code), if any
+    fn contract_deployed(tx: &StacksTransaction) -> Option<(QualifiedContractIdentifier, String)> {
+        match tx.payload {
+            TransactionPayload::SmartContract(ref smart_contract) => Some((
+                QualifiedContractIdentifier::new(
+                    tx.origin_address().into(),
+                    smart_contract.name.clone(),
+                ),
+                smart_contract.code_body.to_string(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Analyze the contracts deployed by an anchored block's transactions on up to
+    /// `num_workers` threads, before processing the block.  They are analyzed
+    /// against the parent block's state, so only the analyses of contracts that refer to no
+    /// contract deployed before them -- earlier in the block, or in the parent microblock stream
+    /// it confirms -- are kept.  Returns the tracker they were charged to, and the analyses.
+    fn pre_analyze_block_contracts(
+        clarity_instance: &ClarityInstance,
+        parent_index_block: &StacksBlockId,
+        microblocks: &Vec<StacksMicroblock>,
+        block: &StacksBlock,
+        num_workers: usize,
+    ) -> Option<(
+        LimitedCostTracker,
+        HashMap<QualifiedContractIdentifier, PreAnalyzedContract>,
+    )> {
+        let deploys: Vec<_> = block
+            .txs
+            .iter()
+            .filter_map(StacksChainState::contract_deployed)
+            .collect();
+        if num_workers < 2 || deploys.len() < 2 {
+            return None;
+        }
+        let contract_ids: Vec<_> = deploys.iter().map(|(id, _)| id.clone()).collect();
+        let num_deploys = contract_ids.len();
+        let (cost_track, analyses) =
+            clarity_instance.pre_analyze_contracts(parent_index_block, deploys, num_workers)?;
+
+        let mut deployed: HashSet<_> = microblocks
+            .iter()
+            .flat_map(|mblock| mblock.txs.iter())
+            .filter_map(StacksChainState::contract_deployed)
+            .map(|(contract_id, _)| contract_id)
+            .collect();
+        let mut pre_analyzed = HashMap::new();
+        for (contract_id, analysis) in contract_ids.into_iter().zip(analyses.into_iter()) {
+            if let Some((contract, referenced)) = analysis {
+                if referenced.is_disjoint(&deployed) && !pre_analyzed.contains_key(&contract_id) {
+                    pre_analyzed.insert(contract_id.clone(), contract);
+                }
+            }
+            deployed.insert(contract_id);
+        }
+        debug!(
+            "Pre-analyzed {} of {} contracts deployed in block {}",
+            pre_analyzed.len(),
+            num_deploys,
+            block.block_hash()
+        );
+        Some((cost_track, pre_analyzed))
+    }
+
     /// Process a single matured miner reward.
     /// Grant it STX tokens.
     fn process_matured_miner_reward<'a>(
@@ -4202,6 +4530,8 @@ impl StacksChainState {
         burnchain_commit_burn: u64,
         burnchain_sortition_burn: u64,
         user_burns: &Vec<StagingUserBurnSupport>,
+        parallel_analysis_workers: usize,
+        contract_cost_stats: bool,
     ) -> Result<StacksEpochReceipt, Error> {
         debug!(
             "Process block {:?} with {} transactions",
@@ -4299,6 +4629,19 @@ impl StacksChainState {
                 parent_consensus_hash, parent_block_hash
             ));
 
+            // analyze the block's contracts before the Clarity MARF is opened for writing, since
+            // the workers read it on their own connections
+            let pre_analyzed = StacksChainState::pre_analyze_block_contracts(
+                clarity_instance,
+                &StacksChainState::get_parent_index_block(
+                    &parent_consensus_hash,
+                    &parent_block_hash,
+                ),
+                microblocks,
+                block,
+                parallel_analysis_workers,
+            );
+
             let mut clarity_tx = StacksChainState::chainstate_block_begin(
                 chainstate_tx,
                 clarity_instance,
@@ -4400,6 +4743,14 @@ impl StacksChainState {
             // epoch defined by this miner.
             clarity_tx.reset_cost(ExecutionCost::zero());
 
+            if let Some((cost_track, contracts)) = pre_analyzed {
+                if !clarity_tx.set_pre_analyzed_contracts(&cost_track, contracts) {
+                    debug!(
+                        "Cost functions changed in this block; not using pre-analyzed contracts"
+                    );
+                }
+            }
+
             debug!("Append block";
                    "block" => %format!("{}/{}", chain_tip_consensus_hash, block.block_hash()),
                    "parent_block" => %format!("{}/{}", parent_consensus_hash, parent_block_hash),
@@ -4606,6 +4957,12 @@ impl StacksChainState {
         .expect("FATAL: failed to advance chain tip");
 
         chainstate_tx.log_transactions_processed(&new_tip.index_block_hash(), &tx_receipts);
+        if contract_cost_stats {
+            if let Err(e) = chainstate_tx.log_contract_costs(new_tip.block_height, &tx_receipts) {
+                warn!("Failed to record contract execution costs: {:?}", &e;
+                      "index_block_hash" => %new_tip.index_block_hash());
+            }
+        }
 
         let epoch_receipt = StacksEpochReceipt {
             header: new_tip,
@@ -4619,6 +4976,246 @@ impl StacksChainState {
         Ok(epoch_receipt)
     }
 
+    /// Re-execute a processed block against its parent's state to regenerate the receipt that
+    /// `append_block` produced for it, so that its events can be replayed to an event observer.
+    /// The burnchain STX operations, the confirmed microblock stream, the anchored block's
+    /// transactions, the matured miner rewards and the STX unlocks are applied in the same order
+    /// as when the block was appended, and the resulting state root must match the block's.
+    /// Nothing is written: the Clarity block is always rolled back, and the headers DB is only
+    /// read.
+    /// Returns Ok(None) if the block has not been processed, or is the genesis block.
+    pub fn replay_processed_block(
+        &mut self,
+        burn_dbconn: &SortitionDBConn,
+        index_block_hash: &StacksBlockId,
+    ) -> Result<Option<StacksEpochReceipt>, Error> {
+        let header = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            index_block_hash,
+        )? {
+            Some(header) if header.block_height > 0 => header,
+            _ => return Ok(None),
+        };
+        let block = match StacksChainState::load_block(
+            &self.blocks_path,
+            &header.consensus_hash,
+            &header.anchored_header.block_hash(),
+        )? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+
+        let parent_index_block_hash = self.get_parent(index_block_hash)?;
+        let parent_header = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            &parent_index_block_hash,
+        )?
+        .ok_or(Error::DBError(db_error::NotFoundError))?;
+        let parent_block_cost =
+            StacksChainState::get_stacks_block_anchored_cost(self.db(), &parent_index_block_hash)?
+                .ok_or(Error::DBError(db_error::NotFoundError))?;
+
+        let (parent_consensus_hash, parent_block_hash) = if block.is_first_mined() {
+            (
+                FIRST_BURNCHAIN_CONSENSUS_HASH.clone(),
+                FIRST_STACKS_BLOCK_HASH.clone(),
+            )
+        } else {
+            (
+                parent_header.consensus_hash.clone(),
+                parent_header.anchored_header.block_hash(),
+            )
+        };
+
+        let microblocks = self
+            .load_confirmed_microblock_stream(index_block_hash)?
+            .unwrap_or(vec![]);
+
+        let parent_burn_hash =
+            SortitionDB::get_block_snapshot_consensus(burn_dbconn.conn(), &header.consensus_hash)?
+                .ok_or(Error::DBError(db_error::NotFoundError))?
+                .parent_burn_header_hash;
+        let stacking_burn_ops =
+            SortitionDB::get_stack_stx_ops(burn_dbconn.conn(), &parent_burn_hash)?;
+        let transfer_burn_ops =
+            SortitionDB::get_transfer_stx_ops(burn_dbconn.conn(), &parent_burn_hash)?;
+
+        let mainnet = self.mainnet;
+        let (latest_matured_miners, matured_miner_parent) = {
+            let mut tx = self.index_tx_begin()?;
+            let latest_miners =
+                StacksChainState::get_scheduled_block_rewards(&mut tx, &parent_header)?;
+            let parent_miner =
+                StacksChainState::get_parent_matured_miner(&mut tx, mainnet, &latest_miners)?;
+            (latest_miners, parent_miner)
+        };
+
+        debug!("Replay block";
+               "block" => %format!("{}/{}", &header.consensus_hash, block.block_hash()),
+               "parent_block" => %format!("{}/{}", parent_consensus_hash, parent_block_hash),
+               "stacks_height" => %header.block_height,
+               "microblock_parent_count" => %microblocks.len());
+
+        // this is a throwaway block, like the ones the miner builds
+        let mut clarity_tx = self.block_begin(
+            burn_dbconn,
+            &parent_consensus_hash,
+            &parent_block_hash,
+            &MINER_BLOCK_CONSENSUS_HASH,
+            &MINER_BLOCK_HEADER_HASH,
+        );
+        let result = StacksChainState::replay_block_receipts(
+            &mut clarity_tx,
+            &parent_header,
+            &block,
+            &microblocks,
+            parent_block_cost,
+            stacking_burn_ops,
+            transfer_burn_ops,
+            latest_matured_miners,
+            matured_miner_parent,
+        );
+        clarity_tx.rollback_block();
+
+        let (
+            tx_receipts,
+            matured_rewards,
+            matured_rewards_info,
+            parent_microblocks_cost,
+            anchored_block_cost,
+        ) = result?;
+
+        Ok(Some(StacksEpochReceipt {
+            header,
+            tx_receipts,
+            matured_rewards,
+            matured_rewards_info,
+            parent_microblocks_cost,
+            anchored_block_cost,
+        }))
+    }
+
+    /// Apply a processed block to an open (and to-be-discarded) Clarity block the way
+    /// `append_block` does, returning the receipts, matured rewards and execution costs it
+    /// produced.  Fails if the state root does not match the one the block committed to.
+    fn replay_block_receipts(
+        clarity_tx: &mut ClarityTx,
+        parent_chain_tip: &StacksHeaderInfo,
+        block: &StacksBlock,
+        microblocks: &Vec<StacksMicroblock>,
+        parent_block_cost: ExecutionCost,
+        stacking_burn_ops: Vec<StackStxOp>,
+        transfer_burn_ops: Vec<TransferStxOp>,
+        latest_matured_miners: Vec<MinerPaymentSchedule>,
+        matured_miner_parent: MinerPaymentSchedule,
+    ) -> Result<
+        (
+            Vec<StacksTransactionReceipt>,
+            Vec<MinerReward>,
+            Option<MinerRewardInfo>,
+            ExecutionCost,
+            ExecutionCost,
+        ),
+        Error,
+    > {
+        clarity_tx.reset_cost(parent_block_cost.clone());
+
+        let matured_miner_rewards_opt = StacksChainState::find_mature_miner_rewards(
+            clarity_tx,
+            parent_chain_tip,
+            latest_matured_miners,
+            matured_miner_parent,
+        )?;
+
+        let (_, _, microblock_txs_receipts) =
+            StacksChainState::process_microblocks_transactions(clarity_tx, microblocks)
+                .map_err(|(e, _)| e)?;
+
+        let mut microblock_cost = clarity_tx.cost_so_far();
+        microblock_cost
+            .sub(&parent_block_cost)
+            .expect("BUG: block_cost + microblock_cost < block_cost");
+
+        clarity_tx.reset_cost(ExecutionCost::zero());
+
+        let mut receipts = StacksChainState::process_stacking_ops(clarity_tx, stacking_burn_ops);
+        receipts.extend(StacksChainState::process_transfer_ops(
+            clarity_tx,
+            transfer_burn_ops,
+        ));
+
+        let (_, _, txs_receipts) = StacksChainState::process_block_transactions(clarity_tx, block)?;
+        receipts.extend(txs_receipts.into_iter());
+
+        let block_cost = clarity_tx.cost_so_far();
+
+        let new_liquid_miner_ustx =
+            if let Some((ref miner_reward, ref user_rewards, ref parent_miner_reward, _)) =
+                matured_miner_rewards_opt.as_ref()
+            {
+                StacksChainState::process_matured_miner_rewards(
+                    clarity_tx,
+                    miner_reward,
+                    user_rewards,
+                    parent_miner_reward,
+                )?
+            } else {
+                0
+            };
+        clarity_tx.increment_ustx_liquid_supply(new_liquid_miner_ustx);
+
+        let (matured_rewards, matured_rewards_info) =
+            if let Some((miner_reward, mut user_rewards, parent_reward, reward_ptr)) =
+                matured_miner_rewards_opt
+            {
+                let mut ret = vec![];
+                ret.push(miner_reward);
+                ret.append(&mut user_rewards);
+                ret.push(parent_reward);
+                (ret, Some(reward_ptr))
+            } else {
+                (vec![], None)
+            };
+
+        let (new_unlocked_ustx, mut lockup_events) =
+            StacksChainState::process_stx_unlocks(clarity_tx)?;
+        if lockup_events.len() > 0 {
+            if let Some(receipt) = receipts.get_mut(0) {
+                if receipt.is_coinbase_tx() {
+                    receipt.events.append(&mut lockup_events);
+                }
+            }
+        }
+        clarity_tx.increment_ustx_liquid_supply(new_unlocked_ustx);
+
+        StacksChainState::insert_microblock_pubkey_hash(
+            clarity_tx,
+            block.header.total_work.work as u32,
+            &block.header.microblock_pubkey_hash,
+        )?;
+
+        let root_hash = clarity_tx.get_root_hash();
+        if root_hash != block.header.state_index_root {
+            let msg = format!(
+                "Replayed block {} state root mismatch: expected {}, got {}",
+                block.block_hash(),
+                block.header.state_index_root,
+                root_hash
+            );
+            warn!("{}", &msg);
+            return Err(Error::InvalidStacksBlock(msg));
+        }
+
+        receipts.extend(microblock_txs_receipts.into_iter());
+        Ok((
+            receipts,
+            matured_rewards,
+            matured_rewards_info,
+            microblock_cost,
+            block_cost,
+        ))
+    }
+
     /// Verify that a Stacks anchored block attaches to its parent anchored block.
     /// * checks .header.total_work.work
     /// * checks .header.parent_block
@@ -4762,6 +5359,8 @@ impl StacksChainState {
         sort_tx: &mut SortitionHandleTx,
     ) -> Result<(Option<StacksEpochReceipt>, Option<TransactionPayload>), Error> {
         let blocks_path = self.blocks_path.clone();
+        let parallel_analysis_workers = self.parallel_analysis_workers;
+        let contract_cost_stats = self.contract_cost_stats;
         let (mut chainstate_tx, clarity_instance) = self.chainstate_tx_begin()?;
 
         // this is a transaction against both the headers and staging blocks databases!
@@ -4925,6 +5524,8 @@ impl StacksChainState {
             next_staging_block.commit_burn,
             next_staging_block.sortition_burn,
             &user_supports,
+            parallel_analysis_workers,
+            contract_cost_stats,
         ) {
             Ok(next_chain_tip_info) => next_chain_tip_info,
             Err(e) => {
@@ -5148,6 +5749,92 @@ impl StacksChainState {
         query_row(&self.db(), sql, args).map_err(Error::DBError)
     }
 
+    /// Get all processed, non-orphaned blocks at the given height whose sortitions are on the
+    /// canonical burn chain fork.  These are the tips a miner could choose to build on when
+    /// there is a Stacks fork at that height.  Blocks are returned in lexigraphical order of
+    /// their index block hashes.  No block data will be filled in.
+    pub fn get_stacks_chain_tips_at_height(
+        &self,
+        sortdb: &SortitionDB,
+        height: u64,
+    ) -> Result<Vec<StagingBlock>, Error> {
+        let sql = "SELECT * FROM staging_blocks WHERE processed = 1 AND orphaned = 0 AND height = ?1 ORDER BY index_block_hash ASC";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(height)?];
+        let candidates: Vec<StagingBlock> =
+            query_rows(&self.db(), sql, args).map_err(Error::DBError)?;
+
+        let sort_handle = sortdb.index_handle_at_tip();
+        let mut tips = vec![];
+        for candidate in candidates.into_iter() {
+            let sn = match SortitionDB::get_block_snapshot_consensus(
+                sortdb.conn(),
+                &candidate.consensus_hash,
+            )? {
+                Some(sn) => sn,
+                None => continue,
+            };
+            if !sn.pox_valid {
+                continue;
+            }
+            if sort_handle.get_consensus_at(sn.block_height)? == Some(sn.consensus_hash) {
+                tips.push(candidate);
+            }
+        }
+        Ok(tips)
+    }
+
+    /// How far below `canonical_tip` the fork that `tip` is on diverged from it: the height of
+    /// `canonical_tip` less the height of the last block the two have in common.  This is 0 if
+    /// `tip` is `canonical_tip` or one of its ancestors.  The last common block is found with a
+    /// binary search over the two blocks' ancestries in the MARF, so this takes a logarithmic
+    /// number of index lookups however long ago the fork happened.
+    /// Returns Ok(None) if either block has not been processed.
+    pub fn get_fork_depth(
+        &self,
+        tip: &StacksBlockId,
+        canonical_tip: &StacksBlockId,
+    ) -> Result<Option<u64>, Error> {
+        let tip_height = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            self.db(),
+            tip,
+        )? {
+            Some(header) => header.block_height,
+            None => return Ok(None),
+        };
+        let canonical_height =
+            match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                self.db(),
+                canonical_tip,
+            )? {
+                Some(header) => header.block_height,
+                None => return Ok(None),
+            };
+
+        let index_conn = self.index_conn()?;
+        if index_conn
+            .get_ancestor_block_height(tip, canonical_tip)?
+            .is_some()
+        {
+            return Ok(Some(0));
+        }
+
+        // the highest height at which both blocks have the same ancestor.  Every fork shares the
+        // boot block at height 0.
+        let mut common = 0;
+        let mut diverged = cmp::min(tip_height, canonical_height) + 1;
+        while common + 1 < diverged {
+            let height = common + (diverged - common) / 2;
+            let tip_ancestor = index_conn.get_ancestor_block_hash(height, tip)?;
+            let canonical_ancestor = index_conn.get_ancestor_block_hash(height, canonical_tip)?;
+            if tip_ancestor.is_some() && tip_ancestor == canonical_ancestor {
+                common = height;
+            } else {
+                diverged = height;
+            }
+        }
+        Ok(Some(canonical_height - common))
+    }
+
     /// Get the height of a staging block
     pub fn get_stacks_block_height(
         &self,
@@ -5183,10 +5870,48 @@ impl StacksChainState {
                 }
                 Ok(())
             }
+            TransactionPayload::SmartContract(ref smart_contract) => {
+                StacksChainState::can_admit_mempool_contract(&smart_contract.code_body.to_string())
+            }
             _ => Ok(()),
         }
     }
 
+    /// Cheap structural checks on a contract's source code: its size, the nesting depth of its
+    ///   expressions, and the number of expressions.  Only the lexer and parser are run -- no
+    ///   analysis or cost tracking -- so this is safe to do for every contract-deploy transaction
+    ///   the mempool receives.
+    fn can_admit_mempool_contract(code_body: &str) -> Result<(), MemPoolRejection> {
+        let source_len = code_body.len() as u64;
+        if source_len > MAXIMUM_MEMPOOL_CONTRACT_SOURCE_LEN {
+            return Err(MemPoolRejection::ContractSourceTooLarge(
+                source_len,
+                MAXIMUM_MEMPOOL_CONTRACT_SOURCE_LEN,
+            ));
+        }
+
+        let lexed = parser::lex(code_body).map_err(MemPoolRejection::ContractParseFailure)?;
+
+        // measure before building the expression tree, so a deeply-nested contract never gets
+        // materialized
+        let (depth, num_expressions) = parser::measure_lexed(&lexed);
+        if depth > MAXIMUM_MEMPOOL_CONTRACT_AST_DEPTH {
+            return Err(MemPoolRejection::ContractTooDeep(
+                depth,
+                MAXIMUM_MEMPOOL_CONTRACT_AST_DEPTH,
+            ));
+        }
+        if num_expressions > MAXIMUM_MEMPOOL_CONTRACT_EXPRESSIONS {
+            return Err(MemPoolRejection::ContractTooManyExpressions(
+                num_expressions,
+                MAXIMUM_MEMPOOL_CONTRACT_EXPRESSIONS,
+            ));
+        }
+
+        parser::parse_lexed(lexed).map_err(MemPoolRejection::ContractParseFailure)?;
+        Ok(())
+    }
+
     /// Check to see if a transaction can be (potentially) appended on top of a given chain tip.
     /// Note that this only checks the transaction against the _anchored chain tip_, not the
     /// unconfirmed microblock stream trailing off of it.
@@ -6151,6 +6876,36 @@ pub mod test {
         tx.commit().unwrap();
     }
 
+    #[test]
+    fn coinbase_reward_schedule() {
+        let first_burn_block_height = 666050;
+        let schedule = StacksChainState::get_coinbase_reward_schedule(first_burn_block_height);
+        assert_eq!(schedule.len(), 4);
+        assert_eq!(schedule[0].0, first_burn_block_height);
+        assert_eq!(schedule[3].1, None);
+
+        for (i, (start_ht, end_ht_opt, coinbase)) in schedule.iter().enumerate() {
+            assert_eq!(
+                StacksChainState::get_coinbase_reward(*start_ht, first_burn_block_height),
+                *coinbase
+            );
+            if let Some(end_ht) = end_ht_opt {
+                assert_eq!(
+                    StacksChainState::get_coinbase_reward(*end_ht, first_burn_block_height),
+                    *coinbase
+                );
+                assert_eq!(schedule[i + 1].0, end_ht + 1);
+            }
+        }
+
+        // blocks before the first burnchain block get the first era's coinbase
+        assert_eq!(
+            StacksChainState::get_coinbase_reward(0, first_burn_block_height),
+            1000 * (MICROSTACKS_PER_STACKS as u128)
+        );
+        assert_eq!(schedule[3].2, 125 * (MICROSTACKS_PER_STACKS as u128));
+    }
+
     #[test]
     fn stacks_db_block_load_store_empty() {
         let chainstate =
@@ -9204,6 +9959,270 @@ pub mod test {
         }
     }
 
+    /// Store the header of a child of `parent`.  Children of the same parent with different
+    /// `fork` values are on different forks.
+    fn make_fork_tip(
+        chainstate: &mut StacksChainState,
+        parent: &StacksHeaderInfo,
+        fork: u8,
+    ) -> StacksHeaderInfo {
+        let mut new_tip = parent.clone();
+        new_tip.anchored_header.parent_block = parent.anchored_header.block_hash();
+        new_tip.anchored_header.tx_merkle_root = Sha512Trunc256Sum([fork; 32]);
+        new_tip.anchored_header.total_work.work = parent.anchored_header.total_work.work + 1;
+        new_tip.consensus_hash =
+            ConsensusHash(Hash160::from_data(&[&parent.consensus_hash.0[..], &[fork]].concat()).0);
+        new_tip.burn_header_hash =
+            BurnchainHeaderHash(Sha512Trunc256Sum::from_data(&new_tip.consensus_hash.0).0);
+        new_tip.burn_header_height = parent.burn_header_height + 1;
+
+        let mut block_reward = MinerPaymentSchedule::genesis(false);
+        block_reward.parent_consensus_hash = parent.consensus_hash.clone();
+        block_reward.parent_block_hash = parent.anchored_header.block_hash();
+        block_reward.consensus_hash = new_tip.consensus_hash.clone();
+        block_reward.block_hash = new_tip.anchored_header.block_hash();
+
+        let mut tx = chainstate.index_tx_begin().unwrap();
+        let tip = StacksChainState::advance_tip(
+            &mut tx,
+            &parent.anchored_header,
+            &parent.consensus_hash,
+            &new_tip.anchored_header,
+            &new_tip.consensus_hash,
+            &new_tip.burn_header_hash,
+            new_tip.burn_header_height,
+            new_tip.burn_header_timestamp,
+            None,
+            &block_reward,
+            &vec![],
+            &ExecutionCost::zero(),
+            123,
+        )
+        .unwrap();
+        tx.commit().unwrap();
+        tip
+    }
+
+    #[test]
+    fn test_can_admit_mempool_contract() {
+        let privk = StacksPrivateKey::from_hex(
+            "59e4d5e18351d6027a37920efe53c2f1cbadc50dca7d77169b7291dff936ed6d01",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let make_deploy = |code: &str| {
+            StacksTransaction::new(
+                TransactionVersion::Testnet,
+                auth.clone(),
+                TransactionPayload::new_smart_contract("hello-world", code).unwrap(),
+            )
+        };
+
+        // well-formed contract passes
+        let tx =
+            make_deploy("(define-data-var bar int 0) (define-public (get-bar) (ok (var-get bar)))");
+        StacksChainState::can_admit_mempool_semantic(&tx, false).unwrap();
+
+        // too big
+        let big = format!("(begin {})", "(print u1)".repeat(30_000));
+        let tx = make_deploy(&big);
+        match StacksChainState::can_admit_mempool_semantic(&tx, false) {
+            Err(MemPoolRejection::ContractSourceTooLarge(actual, max)) => {
+                assert_eq!(actual, big.len() as u64);
+                assert_eq!(max, MAXIMUM_MEMPOOL_CONTRACT_SOURCE_LEN);
+            }
+            x => panic!("unexpected result {:?}", &x),
+        }
+
+        // too deep (but still within the size limit)
+        let deep = format!("{}1{}", "(+ 1 ".repeat(65), ")".repeat(65));
+        let tx = make_deploy(&deep);
+        match StacksChainState::can_admit_mempool_semantic(&tx, false) {
+            Err(MemPoolRejection::ContractTooDeep(65, max)) => {
+                assert_eq!(max, MAXIMUM_MEMPOOL_CONTRACT_AST_DEPTH);
+            }
+            x => panic!("unexpected result {:?}", &x),
+        }
+
+        // tuples count towards the depth
+        let deep = format!("{}1{}", "{a: ".repeat(65), "}".repeat(65));
+        let tx = make_deploy(&deep);
+        match StacksChainState::can_admit_mempool_semantic(&tx, false) {
+            Err(MemPoolRejection::ContractTooDeep(65, _)) => {}
+            x => panic!("unexpected result {:?}", &x),
+        }
+
+        // too many expressions (but still within the size limit)
+        let wide = format!("(list {})", "1 ".repeat(100_000));
+        let tx = make_deploy(&wide);
+        match StacksChainState::can_admit_mempool_semantic(&tx, false) {
+            Err(MemPoolRejection::ContractTooManyExpressions(100_002, max)) => {
+                assert_eq!(max, MAXIMUM_MEMPOOL_CONTRACT_EXPRESSIONS);
+            }
+            x => panic!("unexpected result {:?}", &x),
+        }
+
+        // unparseable
+        let tx = make_deploy("(define-data-var bar int 0");
+        match StacksChainState::can_admit_mempool_semantic(&tx, false) {
+            Err(MemPoolRejection::ContractParseFailure(_)) => {}
+            x => panic!("unexpected result {:?}", &x),
+        }
+    }
+
+    #[test]
+    fn test_get_fork_depth() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "test_get_fork_depth");
+
+        // canonical fork: 10 blocks.  Fork A leaves it after block 8 and has 2 blocks; fork B
+        // leaves it after block 3 and has 9 blocks.
+        let mut canonical = vec![StacksHeaderInfo::regtest_genesis()];
+        for _ in 0..10 {
+            let tip = make_fork_tip(&mut chainstate, canonical.last().unwrap(), 0);
+            canonical.push(tip);
+        }
+        let mut fork_a = vec![canonical[8].clone()];
+        for _ in 0..2 {
+            let tip = make_fork_tip(&mut chainstate, fork_a.last().unwrap(), 1);
+            fork_a.push(tip);
+        }
+        let mut fork_b = vec![canonical[3].clone()];
+        for _ in 0..9 {
+            let tip = make_fork_tip(&mut chainstate, fork_b.last().unwrap(), 2);
+            fork_b.push(tip);
+        }
+
+        let canonical_tip = canonical[10].index_block_hash();
+        let depth = |tip: &StacksHeaderInfo| {
+            chainstate
+                .get_fork_depth(&tip.index_block_hash(), &canonical_tip)
+                .unwrap()
+        };
+
+        // the canonical tip and its ancestors are not on a fork
+        assert_eq!(depth(&canonical[10]), Some(0));
+        assert_eq!(depth(&canonical[4]), Some(0));
+
+        // fork A diverged 2 blocks below the tip, however far along it is
+        assert_eq!(depth(&fork_a[1]), Some(2));
+        assert_eq!(depth(&fork_a[2]), Some(2));
+
+        // fork B diverged 7 blocks below the tip, even though it is now as high as the tip
+        assert_eq!(depth(&fork_b[1]), Some(7));
+        assert_eq!(depth(&fork_b[7]), Some(7));
+        assert_eq!(depth(&fork_b[9]), Some(7));
+
+        // measured from a tip on fork B, the canonical tip is on a fork too
+        assert_eq!(
+            chainstate
+                .get_fork_depth(&canonical_tip, &fork_b[9].index_block_hash())
+                .unwrap(),
+            Some(9)
+        );
+
+        // unknown blocks have no depth
+        assert_eq!(
+            chainstate
+                .get_fork_depth(&StacksBlockId([0x11; 32]), &canonical_tip)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_transaction_blocks() {
+        let peer_config = TestPeerConfig::new("test_get_transaction_blocks", 21315, 21316);
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 5;
+        let mut coinbase_txids = vec![];
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    let anchored_block = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                        None,
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            let (_, _, consensus_hash) = peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let coinbase_txid = stacks_block.txs[0].txid();
+            let index_block_hash = StacksBlockHeader::make_index_block_hash(
+                &consensus_hash,
+                &stacks_block.block_hash(),
+            );
+            coinbase_txids.push((coinbase_txid, index_block_hash));
+        }
+
+        for (txid, index_block_hash) in coinbase_txids.iter() {
+            let headers =
+                StacksChainState::get_transaction_blocks(peer.chainstate().db(), txid).unwrap();
+            assert_eq!(headers.len(), 1);
+            assert_eq!(headers[0].index_block_hash(), *index_block_hash);
+
+            let tx = peer
+                .chainstate()
+                .load_processed_transaction(index_block_hash, txid)
+                .unwrap()
+                .unwrap();
+            assert_eq!(tx.txid(), *txid);
+        }
+
+        let headers =
+            StacksChainState::get_transaction_blocks(peer.chainstate().db(), &Txid([0x11; 32]))
+                .unwrap();
+        assert!(headers.is_empty());
+    }
+
     #[test]
     fn stacks_db_staging_microblocks_fork() {
         // multiple anchored blocks build off of a forked microblock stream
@@ -9362,6 +10381,44 @@ pub mod test {
             .unwrap(),
             vec![]
         );
+
+        // both streams are reported, forking at seq 0
+        let forks = StacksChainState::get_microblock_stream_forks(
+            &chainstate.db(),
+            &StacksBlockHeader::make_index_block_hash(&consensus_hashes[0], &block_1.block_hash()),
+        )
+        .unwrap();
+        assert_eq!(forks.len(), 2);
+
+        let signer = Hash160::from_node_public_key(&StacksPublicKey::from_private(&privk));
+        for mblocks in [&mblocks_1, &mblocks_2].iter() {
+            let fork = forks
+                .iter()
+                .find(|fork| fork.tip_hash == mblocks[2].block_hash())
+                .unwrap();
+            assert_eq!(fork.min_seq, 0);
+            assert_eq!(fork.max_seq, 2);
+            assert_eq!(fork.num_microblocks, 3);
+            assert_eq!(fork.fork_seq, Some(0));
+            assert_eq!(fork.signer, Some(signer.clone()));
+            assert!(fork.first_seen.is_some());
+            assert!(fork.tip_first_seen.is_some());
+            assert_eq!(fork.num_confirmed, 3);
+            assert!(!fork.orphaned);
+        }
+
+        // nothing was seen for a block with no microblocks
+        assert_eq!(
+            StacksChainState::get_microblock_stream_forks(
+                &chainstate.db(),
+                &StacksBlockHeader::make_index_block_hash(
+                    &consensus_hashes[1],
+                    &block_2.block_hash()
+                ),
+            )
+            .unwrap(),
+            vec![]
+        );
     }
 
     #[test]
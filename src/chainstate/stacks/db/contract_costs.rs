@@ -0,0 +1,260 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Historic execution cost statistics per contract (opt-in, see
+//! `StacksChainState::contract_cost_stats`).
+//!
+//! The cost of every contract-call and contract deployment in a processed block is added to a
+//! running total for its contract, kept per range of `CONTRACT_COST_STATS_BUCKET_SIZE` blocks.
+//! Only the most recent `CONTRACT_COST_STATS_RETENTION_BUCKETS` ranges are kept.  Blocks from all
+//! forks are counted, so totals can run ahead of the canonical chain's while forks are live.
+
+use std::collections::HashMap;
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+
+use chainstate::stacks::db::ChainstateTx;
+use chainstate::stacks::db::StacksChainState;
+use chainstate::stacks::events::{StacksTransactionReceipt, TransactionOrigin};
+use chainstate::stacks::TransactionPayload;
+use util::db::u64_to_sql;
+use util::db::Error as db_error;
+use util::db::{query_rows, DBConn, FromColumn, FromRow};
+use vm::costs::ExecutionCost;
+use vm::types::QualifiedContractIdentifier;
+
+// number of blocks whose costs are added up together
+pub const CONTRACT_COST_STATS_BUCKET_SIZE: u64 = 100;
+// number of most-recent buckets kept
+pub const CONTRACT_COST_STATS_RETENTION_BUCKETS: u64 = 1000;
+
+/// The execution cost spent on one contract over a range of blocks
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractCostStats {
+    pub contract_id: String,
+    pub num_txs: u64,
+    pub cost: ExecutionCost,
+}
+
+impl FromRow<ContractCostStats> for ContractCostStats {
+    fn from_row<'a>(row: &'a Row) -> Result<ContractCostStats, db_error> {
+        Ok(ContractCostStats {
+            contract_id: row.get_unwrap("contract_id"),
+            num_txs: u64::from_column(row, "num_txs")?,
+            cost: ExecutionCost::from_row(row)?,
+        })
+    }
+}
+
+/// The first block height of the bucket containing `block_height`
+pub fn contract_cost_stats_bucket(block_height: u64) -> u64 {
+    block_height - block_height % CONTRACT_COST_STATS_BUCKET_SIZE
+}
+
+/// The contract a transaction's execution cost is charged to, if any
+fn contract_charged(receipt: &StacksTransactionReceipt) -> Option<QualifiedContractIdentifier> {
+    match receipt.transaction {
+        TransactionOrigin::Stacks(ref tx) => match tx.payload {
+            TransactionPayload::ContractCall(ref cc) => Some(cc.to_clarity_contract_id()),
+            TransactionPayload::SmartContract(ref sc) => Some(QualifiedContractIdentifier::new(
+                tx.origin_address().into(),
+                sc.name.clone(),
+            )),
+            _ => None,
+        },
+        TransactionOrigin::Burn(_) => None,
+    }
+}
+
+impl<'a> ChainstateTx<'a> {
+    /// Add the execution costs of a processed block's transactions to their contracts' totals,
+    /// and forget buckets that have fallen out of the retention window.
+    pub fn log_contract_costs(
+        &self,
+        block_height: u64,
+        receipts: &[StacksTransactionReceipt],
+    ) -> Result<(), db_error> {
+        let mut totals: HashMap<QualifiedContractIdentifier, (u64, ExecutionCost)> = HashMap::new();
+        for receipt in receipts.iter() {
+            if let Some(contract_id) = contract_charged(receipt) {
+                let (num_txs, cost) = totals
+                    .entry(contract_id.clone())
+                    .or_insert_with(|| (0, ExecutionCost::zero()));
+                *num_txs += 1;
+                if let Err(e) = cost.add(&receipt.execution_cost) {
+                    warn!("Failed to add up execution costs: {:?}", &e; "contract_id" => %contract_id);
+                }
+            }
+        }
+
+        let bucket = u64_to_sql(contract_cost_stats_bucket(block_height))?;
+        for (contract_id, (num_txs, cost)) in totals.iter() {
+            let contract_id = contract_id.to_string();
+            self.tx()
+                .execute(
+                    "INSERT OR IGNORE INTO contract_costs (contract_id, bucket_start, num_txs, runtime, write_length, write_count, read_length, read_count) \
+                     VALUES (?1, ?2, 0, 0, 0, 0, 0, 0)",
+                    &[&contract_id as &dyn ToSql, &bucket],
+                )
+                .map_err(db_error::SqliteError)?;
+
+            let sql = "UPDATE contract_costs SET num_txs = num_txs + ?3, runtime = runtime + ?4, \
+                       write_length = write_length + ?5, write_count = write_count + ?6, \
+                       read_length = read_length + ?7, read_count = read_count + ?8 \
+                       WHERE contract_id = ?1 AND bucket_start = ?2";
+            let args: &[&dyn ToSql] = &[
+                &contract_id,
+                &bucket,
+                &u64_to_sql(*num_txs)?,
+                &u64_to_sql(cost.runtime)?,
+                &u64_to_sql(cost.write_length)?,
+                &u64_to_sql(cost.write_count)?,
+                &u64_to_sql(cost.read_length)?,
+                &u64_to_sql(cost.read_count)?,
+            ];
+            self.tx()
+                .execute(sql, args)
+                .map_err(db_error::SqliteError)?;
+        }
+
+        let current_bucket = contract_cost_stats_bucket(block_height);
+        let retention = CONTRACT_COST_STATS_BUCKET_SIZE * CONTRACT_COST_STATS_RETENTION_BUCKETS;
+        if current_bucket >= retention {
+            let expired = u64_to_sql(current_bucket - retention)?;
+            self.tx()
+                .execute(
+                    "DELETE FROM contract_costs WHERE bucket_start <= ?1",
+                    &[&expired as &dyn ToSql],
+                )
+                .map_err(db_error::SqliteError)?;
+        }
+        Ok(())
+    }
+}
+
+impl StacksChainState {
+    /// Get the execution cost spent on each contract in the buckets overlapping the blocks
+    /// `from_height` through `to_height`, in no particular order.
+    pub fn get_contract_cost_stats(
+        conn: &DBConn,
+        from_height: u64,
+        to_height: u64,
+    ) -> Result<Vec<ContractCostStats>, db_error> {
+        let sql = "SELECT contract_id, SUM(num_txs) AS num_txs, SUM(runtime) AS runtime, \
+                   SUM(write_length) AS write_length, SUM(write_count) AS write_count, \
+                   SUM(read_length) AS read_length, SUM(read_count) AS read_count \
+                   FROM contract_costs WHERE bucket_start >= ?1 AND bucket_start <= ?2 \
+                   GROUP BY contract_id";
+        let args: &[&dyn ToSql] = &[
+            &u64_to_sql(contract_cost_stats_bucket(from_height))?,
+            &u64_to_sql(to_height)?,
+        ];
+        query_rows(conn, sql, args)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chainstate::stacks::db::test::*;
+    use chainstate::stacks::*;
+    use util::hash::Hash160;
+    use vm::types::PrincipalData;
+    use vm::Value;
+
+    use crate::types::chainstate::StacksAddress;
+
+    use super::*;
+
+    fn make_receipt(payload: TransactionPayload, runtime: u64) -> StacksTransactionReceipt {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let tx = StacksTransaction::new(TransactionVersion::Testnet, auth, payload);
+        let mut cost = ExecutionCost::zero();
+        cost.runtime = runtime;
+        cost.read_count = 1;
+        StacksTransactionReceipt::from_stx_transfer(tx, vec![], Value::okay_true(), cost)
+    }
+
+    #[test]
+    fn test_contract_cost_stats() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "contract-cost-stats");
+        let contract_addr = StacksAddress {
+            version: 26,
+            bytes: Hash160([0x11; 20]),
+        };
+        let call = |runtime| {
+            make_receipt(
+                TransactionPayload::new_contract_call(
+                    contract_addr.clone(),
+                    "hello-world",
+                    "add-unit",
+                    vec![],
+                )
+                .unwrap(),
+                runtime,
+            )
+        };
+        let transfer = make_receipt(
+            TransactionPayload::TokenTransfer(
+                PrincipalData::from(contract_addr.clone()),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+            1000,
+        );
+
+        {
+            let (chainstate_tx, _) = chainstate.chainstate_tx_begin().unwrap();
+            chainstate_tx
+                .log_contract_costs(105, &[call(10), transfer.clone(), call(20)])
+                .unwrap();
+            chainstate_tx.log_contract_costs(150, &[call(30)]).unwrap();
+            chainstate_tx.log_contract_costs(250, &[call(40)]).unwrap();
+            chainstate_tx.commit().unwrap();
+        }
+
+        let contract_id = format!("{}.hello-world", &contract_addr);
+        let stats = StacksChainState::get_contract_cost_stats(chainstate.db(), 120, 199).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].contract_id, contract_id);
+        assert_eq!(stats[0].num_txs, 3);
+        assert_eq!(stats[0].cost.runtime, 60);
+        assert_eq!(stats[0].cost.read_count, 3);
+
+        let stats = StacksChainState::get_contract_cost_stats(chainstate.db(), 0, 299).unwrap();
+        assert_eq!(stats[0].num_txs, 4);
+        assert_eq!(stats[0].cost.runtime, 100);
+
+        // buckets past the retention window are forgotten
+        {
+            let (chainstate_tx, _) = chainstate.chainstate_tx_begin().unwrap();
+            let expiry_height =
+                CONTRACT_COST_STATS_BUCKET_SIZE * (CONTRACT_COST_STATS_RETENTION_BUCKETS + 1);
+            chainstate_tx
+                .log_contract_costs(expiry_height, &[call(50)])
+                .unwrap();
+            chainstate_tx.commit().unwrap();
+        }
+        let stats =
+            StacksChainState::get_contract_cost_stats(chainstate.db(), 0, u32::MAX as u64).unwrap();
+        assert_eq!(stats[0].num_txs, 2);
+        assert_eq!(stats[0].cost.runtime, 90);
+    }
+}
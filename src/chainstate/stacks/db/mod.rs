@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::collections::{btree_map::Entry, BTreeMap};
+use std::collections::{btree_map::Entry, BTreeMap, HashMap};
 use std::fmt;
 use std::fs;
 use std::io;
@@ -53,10 +53,10 @@ use chainstate::stacks::{
 };
 use clarity_vm::clarity::{
     ClarityBlockConnection, ClarityConnection, ClarityInstance, ClarityReadOnlyConnection,
-    Error as clarity_error,
+    Error as clarity_error, PreAnalyzedContract,
 };
 use core::*;
-use net::atlas::BNS_CHARS_REGEX;
+use net::atlas::bns::{parse_bns_fully_qualified_name, validate_bns_namespace, PunycodePolicy};
 use net::Error as net_error;
 use util::db::Error as db_error;
 use util::db::{
@@ -74,7 +74,7 @@ use vm::database::{
 };
 use vm::representations::ClarityName;
 use vm::representations::ContractName;
-use vm::types::TupleData;
+use vm::types::{QualifiedContractIdentifier, TupleData};
 use {monitoring, util};
 
 use crate::clarity_vm::database::marf::MarfedKV;
@@ -87,6 +87,7 @@ use vm::Value;
 
 pub mod accounts;
 pub mod blocks;
+pub mod contract_costs;
 pub mod contracts;
 pub mod headers;
 pub mod transactions;
@@ -108,6 +109,12 @@ pub struct StacksChainState {
     pub root_path: String,
     pub block_limit: ExecutionCost,
     pub unconfirmed_state: Option<UnconfirmedState>,
+    /// Number of threads that analyze the contracts deployed in a block before it is processed.
+    /// Fewer than 2 (the default) disables it.
+    pub parallel_analysis_workers: usize,
+    /// Whether to keep per-contract execution cost statistics for the blocks this instance
+    /// processes, and serve them over RPC (see `contract_costs`).  Off by default.
+    pub contract_cost_stats: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -303,6 +310,16 @@ impl<'a> ClarityTx<'a> {
         self.block.set_cost_tracker(new_tracker)
     }
 
+    /// Use the given analyses of contracts deployed in this block, if they were charged to a
+    /// tracker with the same costs as this block's.  Returns whether or not they will be used.
+    pub fn set_pre_analyzed_contracts(
+        &mut self,
+        cost_track: &LimitedCostTracker,
+        contracts: HashMap<QualifiedContractIdentifier, PreAnalyzedContract>,
+    ) -> bool {
+        self.block.set_pre_analyzed_contracts(cost_track, contracts)
+    }
+
     /// Run `todo` in this ClarityTx with `new_tracker`.
     /// Returns the result of `todo` and the `new_tracker`
     pub fn with_temporary_cost_tracker<F, R>(
@@ -421,6 +438,15 @@ impl<'a> ChainstateTx<'a> {
                 }
             }
         }
+        let insert =
+            "INSERT OR IGNORE INTO transaction_blocks (txid, index_block_hash) VALUES (?1, ?2)";
+        for tx_event in events.iter() {
+            let txid = tx_event.transaction.txid();
+            let params: &[&dyn ToSql] = &[&txid, block_id];
+            if let Err(e) = self.tx.tx().execute(insert, params) {
+                warn!("Failed to index TX: {}", e; "txid" => %txid);
+            }
+        }
         for tx_event in events.iter() {
             let txid = tx_event.transaction.txid();
             if let Err(e) = monitoring::log_transaction_processed(&txid, &self.root_path) {
@@ -613,6 +639,61 @@ const CHAINSTATE_INITIAL_SCHEMA: &'static [&'static str] = &[
     "CREATE INDEX index_block_hash_tx_index ON transactions(index_block_hash);",
 ];
 
+/// The boot `lockup` contract's `lockups` map is keyed only by Stacks block height, so genesis
+/// vesting entries are also indexed here by recipient.  This table is created on open if it is
+/// missing, since it was added after the initial schema.
+const CHAINSTATE_GENESIS_LOCKUPS_SCHEMA: &'static [&'static str] = &[
+    r#"
+    -- genesis STX vesting schedule
+    -- no designated primary key since there can be duplicate entries
+    CREATE TABLE IF NOT EXISTS genesis_lockups(
+        recipient TEXT NOT NULL,
+        block_height INTEGER NOT NULL,      -- Stacks block height at which the STX unlock
+        amount TEXT NOT NULL                -- encodes u128
+    );"#,
+    "CREATE INDEX IF NOT EXISTS genesis_lockups_recipient ON genesis_lockups(recipient);",
+];
+
+/// When each microblock was first stored, so competing microblock streams can be told apart
+/// by age.  Created on open if it is missing, since it was added after the initial schema.
+const CHAINSTATE_MICROBLOCK_ARRIVALS_SCHEMA: &'static str = r#"
+    CREATE TABLE IF NOT EXISTS staging_microblocks_arrivals(
+        index_microblock_hash TEXT NOT NULL,
+        arrival_time INT NOT NULL,
+        PRIMARY KEY(index_microblock_hash)
+    );"#;
+
+/// Which processed blocks include each transaction (including the transactions of the microblocks
+/// they confirm), across all forks.  Unlike the `transactions` log, this is always maintained.
+/// Created on open if it is missing, since it was added after the initial schema.
+const CHAINSTATE_TRANSACTION_BLOCKS_SCHEMA: &'static str = r#"
+    CREATE TABLE IF NOT EXISTS transaction_blocks(
+        txid TEXT NOT NULL,
+        index_block_hash TEXT NOT NULL,
+        PRIMARY KEY(txid, index_block_hash)
+    );"#;
+
+/// Execution cost spent per contract per range of blocks, maintained if `contract_cost_stats` is
+/// set (see `contract_costs`).  Created on open if it is missing, since it was added after the
+/// initial schema.
+const CHAINSTATE_CONTRACT_COSTS_SCHEMA: &'static str = r#"
+    CREATE TABLE IF NOT EXISTS contract_costs(
+        contract_id TEXT NOT NULL,
+        bucket_start INTEGER NOT NULL,
+        num_txs INTEGER NOT NULL,
+        runtime INTEGER NOT NULL,
+        write_length INTEGER NOT NULL,
+        write_count INTEGER NOT NULL,
+        read_length INTEGER NOT NULL,
+        read_count INTEGER NOT NULL,
+        PRIMARY KEY(contract_id, bucket_start)
+    );"#;
+
+/// Index of the miner payments table by recipient, so an address's rewards can be looked up.
+/// Created on open if it is missing, since it was added after the initial schema.
+const CHAINSTATE_PAYMENTS_ADDRESS_INDEX: &'static str =
+    "CREATE INDEX IF NOT EXISTS payments_address ON payments(address);";
+
 #[cfg(test)]
 pub const MINER_REWARD_MATURITY: u64 = 2; // small for testing purposes
 
@@ -721,6 +802,13 @@ impl StacksChainState {
             for cmd in CHAINSTATE_INITIAL_SCHEMA {
                 tx.execute_batch(cmd)?;
             }
+            for cmd in CHAINSTATE_GENESIS_LOCKUPS_SCHEMA {
+                tx.execute_batch(cmd)?;
+            }
+            tx.execute_batch(CHAINSTATE_PAYMENTS_ADDRESS_INDEX)?;
+            tx.execute_batch(CHAINSTATE_MICROBLOCK_ARRIVALS_SCHEMA)?;
+            tx.execute_batch(CHAINSTATE_TRANSACTION_BLOCKS_SCHEMA)?;
+            tx.execute_batch(CHAINSTATE_CONTRACT_COSTS_SCHEMA)?;
 
             tx.execute(
                 "INSERT INTO db_config (version,mainnet,chain_id) VALUES (?1,?2,?3)",
@@ -781,6 +869,18 @@ impl StacksChainState {
                 return Err(Error::InvalidChainstateDB);
             }
 
+            for cmd in CHAINSTATE_GENESIS_LOCKUPS_SCHEMA {
+                marf.sqlite_conn().execute_batch(cmd)?;
+            }
+            marf.sqlite_conn()
+                .execute_batch(CHAINSTATE_PAYMENTS_ADDRESS_INDEX)?;
+            marf.sqlite_conn()
+                .execute_batch(CHAINSTATE_MICROBLOCK_ARRIVALS_SCHEMA)?;
+            marf.sqlite_conn()
+                .execute_batch(CHAINSTATE_TRANSACTION_BLOCKS_SCHEMA)?;
+            marf.sqlite_conn()
+                .execute_batch(CHAINSTATE_CONTRACT_COSTS_SCHEMA)?;
+
             Ok(marf)
         }
     }
@@ -875,6 +975,7 @@ impl StacksChainState {
 
         let mut initial_liquid_ustx = 0u128;
         let mut receipts = vec![];
+        let mut genesis_lockups = vec![];
 
         {
             let mut clarity_tx = chainstate.genesis_block_begin(
@@ -981,6 +1082,11 @@ impl StacksChainState {
                     for schedule in initial_lockups {
                         let stx_address =
                             StacksChainState::parse_genesis_address(&schedule.address, mainnet);
+                        genesis_lockups.push((
+                            stx_address.clone(),
+                            schedule.block_height,
+                            schedule.amount,
+                        ));
                         let value = Value::Tuple(
                             TupleData::from_data(vec![
                                 ("recipient".into(), Value::Principal(stx_address)),
@@ -1026,8 +1132,11 @@ impl StacksChainState {
                             let initial_namespaces = get_namespaces();
                             for entry in initial_namespaces {
                                 let namespace = {
-                                    if !BNS_CHARS_REGEX.is_match(&entry.namespace_id) {
-                                        panic!("Invalid namespace characters");
+                                    if let Err(e) = validate_bns_namespace(
+                                        &entry.namespace_id,
+                                        PunycodePolicy::Opaque,
+                                    ) {
+                                        panic!("Invalid namespace '{}': {}", entry.namespace_id, e);
                                     }
                                     let buffer = entry.namespace_id.as_bytes();
                                     Value::buff_from(buffer.to_vec()).expect("Invalid namespace")
@@ -1099,24 +1208,20 @@ impl StacksChainState {
                         .with_clarity_db(|db| {
                             let initial_names = get_names();
                             for entry in initial_names {
-                                let components: Vec<_> =
-                                    entry.fully_qualified_name.split(".").collect();
-                                assert_eq!(components.len(), 2);
+                                let (name_str, namespace_str) = parse_bns_fully_qualified_name(
+                                    &entry.fully_qualified_name,
+                                    PunycodePolicy::Opaque,
+                                )
+                                .unwrap_or_else(|e| {
+                                    panic!("Invalid name '{}': {}", entry.fully_qualified_name, e)
+                                });
 
                                 let namespace = {
-                                    let namespace_str = components[1];
-                                    if !BNS_CHARS_REGEX.is_match(&namespace_str) {
-                                        panic!("Invalid namespace characters");
-                                    }
                                     let buffer = namespace_str.as_bytes();
                                     Value::buff_from(buffer.to_vec()).expect("Invalid namespace")
                                 };
 
                                 let name = {
-                                    let name_str = components[0].to_string();
-                                    if !BNS_CHARS_REGEX.is_match(&name_str) {
-                                        panic!("Invalid name characters");
-                                    }
                                     let buffer = name_str.as_bytes();
                                     Value::buff_from(buffer.to_vec()).expect("Invalid name")
                                 };
@@ -1302,6 +1407,7 @@ impl StacksChainState {
                 &first_tip_info,
                 &ExecutionCost::zero(),
             )?;
+            StacksChainState::insert_genesis_lockups(&mut tx, &genesis_lockups)?;
             tx.commit()?;
         }
 
@@ -1309,6 +1415,48 @@ impl StacksChainState {
         Ok(receipts)
     }
 
+    /// Populate the genesis lockups index of a chainstate that was instantiated before the index
+    /// existed, using the same genesis data that was used to boot it.
+    fn backfill_genesis_lockups(
+        chainstate: &mut StacksChainState,
+        boot_data: &mut ChainStateBootData,
+    ) -> Result<(), Error> {
+        let num_indexed: i64 = query_count(
+            chainstate.db(),
+            "SELECT COUNT(*) FROM genesis_lockups",
+            NO_PARAMS,
+        )?;
+        if num_indexed > 0 {
+            return Ok(());
+        }
+
+        let get_schedules = match boot_data.get_bulk_initial_lockups.take() {
+            Some(get_schedules) => get_schedules,
+            None => {
+                return Ok(());
+            }
+        };
+
+        let mainnet = chainstate.mainnet;
+        let genesis_lockups: Vec<_> = get_schedules()
+            .map(|schedule| {
+                (
+                    StacksChainState::parse_genesis_address(&schedule.address, mainnet),
+                    schedule.block_height,
+                    schedule.amount,
+                )
+            })
+            .collect();
+
+        if genesis_lockups.len() > 0 {
+            info!("Indexing {} genesis lockups", genesis_lockups.len());
+            let mut tx = chainstate.index_tx_begin()?;
+            StacksChainState::insert_genesis_lockups(&mut tx, &genesis_lockups)?;
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
     pub fn open(
         mainnet: bool,
         chain_id: u32,
@@ -1432,6 +1580,8 @@ impl StacksChainState {
             root_path: path_str.to_string(),
             block_limit: block_limit,
             unconfirmed_state: None,
+            parallel_analysis_workers: 0,
+            contract_cost_stats: false,
         };
 
         let mut receipts = vec![];
@@ -1446,7 +1596,10 @@ impl StacksChainState {
                     "StacksChainState initialization is required, but boot_data was not passed."
                 );
             }
-            (false, _) => {}
+            (false, Some(boot_data)) => {
+                StacksChainState::backfill_genesis_lockups(&mut chainstate, boot_data)?;
+            }
+            (false, None) => {}
         }
 
         Ok((chainstate, receipts))
@@ -2072,6 +2225,110 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_genesis_lockups_index() {
+        let alice =
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let bob = StacksAddress::from_string("ST1HB1T8WRNBYB0Y3T7WXZS38NKKPTBR3EG9EPJKR").unwrap();
+        let make_lockups = || {
+            vec![
+                ChainstateAccountLockup::new(alice.clone(), 300, 20),
+                ChainstateAccountLockup::new(bob.clone(), 1000, 10),
+                ChainstateAccountLockup::new(alice.clone(), 100, 10),
+                ChainstateAccountLockup::new(alice.clone(), 200, 20),
+            ]
+        };
+        let make_boot_data = move |lockups: Vec<ChainstateAccountLockup>| ChainStateBootData {
+            initial_balances: vec![],
+            post_flight_callback: None,
+            first_burnchain_block_hash: BurnchainHeaderHash::zero(),
+            first_burnchain_block_height: 0,
+            first_burnchain_block_timestamp: 0,
+            pox_constants: PoxConstants::testnet_default(),
+            get_bulk_initial_lockups: Some(Box::new(move || Box::new(lockups.into_iter()))),
+            get_bulk_initial_balances: None,
+            get_bulk_initial_names: None,
+            get_bulk_initial_namespaces: None,
+        };
+
+        let path = chainstate_path("genesis-lockups-index");
+        match fs::metadata(&path) {
+            Ok(_) => {
+                fs::remove_dir_all(&path).unwrap();
+            }
+            Err(_) => {}
+        };
+
+        let expected_alice = vec![(10, 100), (20, 500)];
+        let expected_bob = vec![(10, 1000)];
+
+        {
+            let chainstate = StacksChainState::open_and_exec(
+                false,
+                0x80000000,
+                &path,
+                Some(&mut make_boot_data(make_lockups())),
+                ExecutionCost::max_value(),
+            )
+            .unwrap()
+            .0;
+
+            assert_eq!(
+                StacksChainState::get_genesis_lockups(chainstate.db(), &alice.clone().into())
+                    .unwrap(),
+                expected_alice
+            );
+            assert_eq!(
+                StacksChainState::get_genesis_lockups(chainstate.db(), &bob.clone().into())
+                    .unwrap(),
+                expected_bob
+            );
+
+            // simulate a chainstate that predates the index
+            chainstate
+                .db()
+                .execute_batch("DROP TABLE genesis_lockups")
+                .unwrap();
+        }
+
+        // reopening without boot data creates an empty index
+        let chainstate = open_chainstate(false, 0x80000000, "genesis-lockups-index");
+        assert_eq!(
+            StacksChainState::get_genesis_lockups(chainstate.db(), &alice.clone().into()).unwrap(),
+            vec![]
+        );
+
+        // reopening with boot data backfills it
+        let chainstate = StacksChainState::open_and_exec(
+            false,
+            0x80000000,
+            &path,
+            Some(&mut make_boot_data(make_lockups())),
+            ExecutionCost::max_value(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(
+            StacksChainState::get_genesis_lockups(chainstate.db(), &alice.clone().into()).unwrap(),
+            expected_alice
+        );
+
+        // ...but only once
+        let chainstate = StacksChainState::open_and_exec(
+            false,
+            0x80000000,
+            &path,
+            Some(&mut make_boot_data(make_lockups())),
+            ExecutionCost::max_value(),
+        )
+        .unwrap()
+        .0;
+        assert_eq!(
+            StacksChainState::get_genesis_lockups(chainstate.db(), &bob.clone().into()).unwrap(),
+            expected_bob
+        );
+    }
+
     #[test]
     fn test_chainstate_sampled_genesis_consistency() {
         // Test root hash for the test chainstate data set
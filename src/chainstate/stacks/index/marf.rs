@@ -14,6 +14,8 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
 use std::error;
 use std::fmt;
 use std::fs;
@@ -28,7 +30,8 @@ use sha2::Digest;
 use chainstate::stacks::index::bits::{get_leaf_hash, get_node_hash, read_root_hash};
 use chainstate::stacks::index::node::{
     clear_backptr, is_backptr, set_backptr, CursorError, TrieCursor, TrieNode, TrieNode16,
-    TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr, TRIEPTR_SIZE,
+    TrieNode256, TrieNode4, TrieNode48, TrieNodeID, TrieNodeType, TriePath, TriePtr,
+    TRIEPATH_MAX_LEN, TRIEPTR_SIZE,
 };
 use chainstate::stacks::index::storage::{
     TrieFileStorage, TrieStorageConnection, TrieStorageTransaction,
@@ -1203,6 +1206,252 @@ impl<T: MarfTrieId> MARF<T> {
 
         result
     }
+
+    /// Read the children of an intermediate node that was read from `block`, following any
+    /// back-pointers.  Returns each child's path character, the child, its hash, and the block
+    /// it was read from, in path character order.
+    fn read_children(
+        storage: &mut TrieStorageConnection<T>,
+        node: &TrieNodeType,
+        block: &(T, Option<u32>),
+    ) -> Result<Vec<(u8, TrieNodeType, TrieHash, (T, Option<u32>))>, Error> {
+        let mut children = vec![];
+        for ptr in node.ptrs().iter() {
+            if ptr.id() == TrieNodeID::Empty as u8 {
+                continue;
+            }
+            storage.open_block_maybe_id(&block.0, block.1)?;
+            if is_backptr(ptr.id()) {
+                let back_block_hash = storage.get_block_from_local_id(ptr.back_block())?.clone();
+                storage.open_block_known_id(&back_block_hash, ptr.back_block())?;
+                let (child, child_hash) = storage.read_nodetype(&ptr.from_backptr())?;
+                children.push((
+                    ptr.chr(),
+                    child,
+                    child_hash,
+                    (back_block_hash, Some(ptr.back_block())),
+                ));
+            } else {
+                let (child, child_hash) = storage.read_nodetype(ptr)?;
+                children.push((ptr.chr(), child, child_hash, block.clone()));
+            }
+        }
+        children.sort_by(|c1, c2| c1.0.cmp(&c2.0));
+        Ok(children)
+    }
+
+    /// Append each leaf under `node` (read from `block`) whose path starts with `path_prefix` to
+    /// `leaves`, in path order.  `path` holds the path bytes walked to reach `node`.
+    fn collect_leaves(
+        storage: &mut TrieStorageConnection<T>,
+        node: &TrieNodeType,
+        block: &(T, Option<u32>),
+        path: &mut Vec<u8>,
+        path_prefix: &[u8],
+        leaves: &mut Vec<(TriePath, MARFValue)>,
+    ) -> Result<(), Error> {
+        let path_len = path.len();
+        path.extend_from_slice(node.path_bytes());
+
+        // skip subtries that can't contain the prefix
+        let overlap = cmp::min(path.len(), path_prefix.len());
+        if path[0..overlap] == path_prefix[0..overlap] {
+            match node {
+                TrieNodeType::Leaf(ref leaf) => {
+                    let leaf_path = TriePath::from_bytes(&path[..]).ok_or_else(|| {
+                        Error::CorruptionError(format!(
+                            "Leaf path has {} bytes, not {}",
+                            path.len(),
+                            TRIEPATH_MAX_LEN
+                        ))
+                    })?;
+                    leaves.push((leaf_path, leaf.data.clone()));
+                }
+                _ => {
+                    for (chr, child, _, child_block) in
+                        MARF::read_children(storage, node, block)?.into_iter()
+                    {
+                        path.push(chr);
+                        MARF::collect_leaves(
+                            storage,
+                            &child,
+                            &child_block,
+                            path,
+                            path_prefix,
+                            leaves,
+                        )?;
+                        path.pop();
+                    }
+                }
+            }
+        }
+
+        path.truncate(path_len);
+        Ok(())
+    }
+
+    /// Append the leaves that differ between the subtries under `node_1` and `node_2` to
+    /// `diffs`, as (path, value under node_1, value under node_2).  Both nodes are reached by
+    /// `path`.  Subtries with the same hash are skipped, unless they're the roots (whose hashes
+    /// also cover their ancestor tries).
+    fn diff_nodes(
+        storage: &mut TrieStorageConnection<T>,
+        node_1: &(TrieNodeType, TrieHash, (T, Option<u32>)),
+        node_2: &(TrieNodeType, TrieHash, (T, Option<u32>)),
+        path: &mut Vec<u8>,
+        is_root: bool,
+        diffs: &mut Vec<(TriePath, Option<MARFValue>, Option<MARFValue>)>,
+    ) -> Result<(), Error> {
+        if !is_root && node_1.1 == node_2.1 {
+            return Ok(());
+        }
+
+        if node_1.0.is_leaf()
+            || node_2.0.is_leaf()
+            || node_1.0.path_bytes() != node_2.0.path_bytes()
+        {
+            // the subtries are shaped differently, so just compare their leaves
+            let mut leaves_1 = vec![];
+            let mut leaves_2 = vec![];
+            MARF::collect_leaves(storage, &node_1.0, &node_1.2, path, &[], &mut leaves_1)?;
+            MARF::collect_leaves(storage, &node_2.0, &node_2.2, path, &[], &mut leaves_2)?;
+
+            let mut values: BTreeMap<Vec<u8>, (TriePath, Option<MARFValue>, Option<MARFValue>)> =
+                BTreeMap::new();
+            for (leaf_path, value) in leaves_1.into_iter() {
+                values.insert(
+                    leaf_path.as_bytes().to_vec(),
+                    (leaf_path, Some(value), None),
+                );
+            }
+            for (leaf_path, value) in leaves_2.into_iter() {
+                values
+                    .entry(leaf_path.as_bytes().to_vec())
+                    .or_insert((leaf_path, None, None))
+                    .2 = Some(value);
+            }
+            diffs.extend(
+                values
+                    .into_iter()
+                    .map(|(_, diff)| diff)
+                    .filter(|diff| diff.1 != diff.2),
+            );
+            return Ok(());
+        }
+
+        let path_len = path.len();
+        path.extend_from_slice(node_1.0.path_bytes());
+
+        let mut children: BTreeMap<u8, (Option<_>, Option<_>)> = BTreeMap::new();
+        for (chr, child, child_hash, child_block) in
+            MARF::read_children(storage, &node_1.0, &node_1.2)?.into_iter()
+        {
+            children.entry(chr).or_insert((None, None)).0 = Some((child, child_hash, child_block));
+        }
+        for (chr, child, child_hash, child_block) in
+            MARF::read_children(storage, &node_2.0, &node_2.2)?.into_iter()
+        {
+            children.entry(chr).or_insert((None, None)).1 = Some((child, child_hash, child_block));
+        }
+
+        for (chr, (child_1, child_2)) in children.into_iter() {
+            path.push(chr);
+            match (child_1, child_2) {
+                (Some(child_1), Some(child_2)) => {
+                    MARF::diff_nodes(storage, &child_1, &child_2, path, false, diffs)?;
+                }
+                (Some(child_1), None) => {
+                    let mut leaves = vec![];
+                    MARF::collect_leaves(storage, &child_1.0, &child_1.2, path, &[], &mut leaves)?;
+                    diffs.extend(
+                        leaves
+                            .into_iter()
+                            .map(|(leaf_path, value)| (leaf_path, Some(value), None)),
+                    );
+                }
+                (None, Some(child_2)) => {
+                    let mut leaves = vec![];
+                    MARF::collect_leaves(storage, &child_2.0, &child_2.2, path, &[], &mut leaves)?;
+                    diffs.extend(
+                        leaves
+                            .into_iter()
+                            .map(|(leaf_path, value)| (leaf_path, None, Some(value))),
+                    );
+                }
+                (None, None) => {}
+            }
+            path.pop();
+        }
+
+        path.truncate(path_len);
+        Ok(())
+    }
+
+    /// Get each leaf in the MARF as of `block_hash` whose path starts with `path_prefix`, in
+    /// path order.
+    pub fn get_leaves_by_prefix(
+        storage: &mut TrieStorageConnection<T>,
+        block_hash: &T,
+        path_prefix: &[u8],
+    ) -> Result<Vec<(TriePath, MARFValue)>, Error> {
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let result = storage.open_block(block_hash).and_then(|_| {
+            let (root, _) = Trie::read_root(storage)?;
+            let root_block = storage.get_cur_block_and_id();
+            let mut leaves = vec![];
+            MARF::collect_leaves(
+                storage,
+                &root,
+                &root_block,
+                &mut vec![],
+                path_prefix,
+                &mut leaves,
+            )?;
+            Ok(leaves)
+        });
+
+        // restore
+        storage.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
+
+        result
+    }
+
+    /// Compare the MARF as of `block_hash_1` to the MARF as of `block_hash_2`.  Returns (path,
+    /// value as of block_hash_1, value as of block_hash_2) for each path whose value differs, in
+    /// path order.
+    pub fn diff_blocks(
+        storage: &mut TrieStorageConnection<T>,
+        block_hash_1: &T,
+        block_hash_2: &T,
+    ) -> Result<Vec<(TriePath, Option<MARFValue>, Option<MARFValue>)>, Error> {
+        let (cur_block_hash, cur_block_id) = storage.get_cur_block_and_id();
+
+        let result = storage.open_block(block_hash_1).and_then(|_| {
+            let (root_1, root_hash_1) = Trie::read_root(storage)?;
+            let root_block_1 = storage.get_cur_block_and_id();
+
+            storage.open_block(block_hash_2)?;
+            let (root_2, root_hash_2) = Trie::read_root(storage)?;
+            let root_block_2 = storage.get_cur_block_and_id();
+
+            let mut diffs = vec![];
+            MARF::diff_nodes(
+                storage,
+                &(root_1, root_hash_1, root_block_1),
+                &(root_2, root_hash_2, root_block_2),
+                &mut vec![],
+                true,
+                &mut diffs,
+            )?;
+            Ok(diffs)
+        });
+
+        // restore
+        storage.open_block_maybe_id(&cur_block_hash, cur_block_id)?;
+
+        result
+    }
 }
 
 // instance methods
@@ -1234,6 +1483,31 @@ impl<T: MarfTrieId> MARF<T> {
         Ok(Some((marf_value, proof)))
     }
 
+    /// Get each leaf as of `block_hash` whose path starts with `path_prefix`, in path order
+    pub fn get_leaves(
+        &mut self,
+        block_hash: &T,
+        path_prefix: &[u8],
+    ) -> Result<Vec<(TriePath, MARFValue)>, Error> {
+        MARF::get_leaves_by_prefix(&mut self.storage.connection(), block_hash, path_prefix)
+    }
+
+    /// Get each path whose value differs between `block_hash_1` and `block_hash_2`, along with
+    /// its value as of each block
+    pub fn diff(
+        &mut self,
+        block_hash_1: &T,
+        block_hash_2: &T,
+    ) -> Result<Vec<(TriePath, Option<MARFValue>, Option<MARFValue>)>, Error> {
+        MARF::diff_blocks(&mut self.storage.connection(), block_hash_1, block_hash_2)
+    }
+
+    /// Generate a mapping between trie root hashes and the blocks that contain them, for
+    /// verifying proofs
+    pub fn get_root_to_block_table(&mut self) -> Result<HashMap<TrieHash, T>, Error> {
+        self.storage.connection().read_root_to_block_table()
+    }
+
     pub fn get_bhh_at_height(&mut self, block_hash: &T, height: u32) -> Result<Option<T>, Error> {
         MARF::get_block_at_height(&mut self.storage.connection(), height, block_hash)
     }
@@ -3612,4 +3886,125 @@ mod test {
             assert!(false);
         }
     }
+
+    #[test]
+    fn marf_get_leaves_and_diff() {
+        let mut m = MARF::from_path(":memory:").unwrap();
+        let block_0 = BlockHeaderHash([0x00; 32]);
+        let block_1 = BlockHeaderHash([0x01; 32]);
+        let block_2 = BlockHeaderHash([0x02; 32]);
+        let block_3 = BlockHeaderHash([0x03; 32]);
+
+        m.begin(&BlockHeaderHash::sentinel(), &block_0).unwrap();
+        m.commit().unwrap();
+
+        let keys: Vec<String> = (0..50).map(|i| format!("a-{}", i)).collect();
+        let values: Vec<MARFValue> = (0..50)
+            .map(|i| MARFValue::from_value(&format!("{}", i)))
+            .collect();
+        m.begin(&block_0, &block_1).unwrap();
+        m.insert_batch(&keys, values.clone()).unwrap();
+        m.commit().unwrap();
+
+        // block 2 changes a-0 and adds b-0
+        m.begin(&block_1, &block_2).unwrap();
+        m.insert_batch(
+            &vec!["a-0".to_string(), "b-0".to_string()],
+            vec![MARFValue::from_value("new"), MARFValue::from_value("b")],
+        )
+        .unwrap();
+        m.commit().unwrap();
+
+        // block 3 forks off of block 1 and adds c-0
+        m.begin(&block_1, &block_3).unwrap();
+        m.insert_batch(&vec!["c-0".to_string()], vec![MARFValue::from_value("c")])
+            .unwrap();
+        m.commit().unwrap();
+
+        // every leaf is found, in path order
+        let leaves = m.get_leaves(&block_1, &[]).unwrap();
+        for (key, value) in keys.iter().zip(values.iter()) {
+            assert!(leaves.contains(&(TriePath::from_key(key), value.clone())));
+        }
+        for i in 1..leaves.len() {
+            assert!(leaves[i - 1].0.as_bytes() < leaves[i].0.as_bytes());
+        }
+        for (path, value) in leaves.iter() {
+            let leaf = MARF::get_path(&mut m.borrow_storage_backend(), &block_1, path)
+                .unwrap()
+                .unwrap();
+            assert_eq!(leaf.data, *value);
+        }
+        assert!(!leaves
+            .iter()
+            .any(|(path, _)| *path == TriePath::from_key("b-0")));
+
+        // filter by path prefix
+        let path = TriePath::from_key("a-7");
+        let prefixed = m.get_leaves(&block_1, &path.as_bytes()[0..1]).unwrap();
+        assert!(prefixed.contains(&(path.clone(), values[7].clone())));
+        assert!(prefixed.len() < leaves.len());
+        for (leaf_path, _) in prefixed.iter() {
+            assert_eq!(leaf_path.as_bytes()[0], path.as_bytes()[0]);
+        }
+        assert_eq!(
+            m.get_leaves(&block_1, path.as_bytes()).unwrap(),
+            vec![(path, values[7].clone())]
+        );
+
+        // diffs
+        assert_eq!(m.diff(&block_1, &block_1).unwrap(), vec![]);
+
+        let diffs = m.diff(&block_1, &block_2).unwrap();
+        assert!(diffs.contains(&(
+            TriePath::from_key("a-0"),
+            Some(values[0].clone()),
+            Some(MARFValue::from_value("new"))
+        )));
+        assert!(diffs.contains(&(
+            TriePath::from_key("b-0"),
+            None,
+            Some(MARFValue::from_value("b"))
+        )));
+        for key in keys[1..].iter() {
+            let path = TriePath::from_key(key);
+            assert!(!diffs.iter().any(|diff| diff.0 == path));
+        }
+        for diff in diffs.iter() {
+            assert!(diff.1 != diff.2);
+        }
+
+        let diffs = m.diff(&block_2, &block_3).unwrap();
+        assert!(diffs.contains(&(
+            TriePath::from_key("a-0"),
+            Some(MARFValue::from_value("new")),
+            Some(values[0].clone())
+        )));
+        assert!(diffs.contains(&(
+            TriePath::from_key("b-0"),
+            Some(MARFValue::from_value("b")),
+            None
+        )));
+        assert!(diffs.contains(&(
+            TriePath::from_key("c-0"),
+            None,
+            Some(MARFValue::from_value("c"))
+        )));
+        for i in 1..diffs.len() {
+            assert!(diffs[i - 1].0.as_bytes() < diffs[i].0.as_bytes());
+        }
+
+        // a diff is the same as comparing every leaf
+        let leaves_2 = m.get_leaves(&block_2, &[]).unwrap();
+        let leaves_3 = m.get_leaves(&block_3, &[]).unwrap();
+        let changed = leaves_2
+            .iter()
+            .filter(|leaf| !leaves_3.contains(leaf))
+            .count()
+            + leaves_3
+                .iter()
+                .filter(|leaf| !leaves_2.iter().any(|leaf_2| leaf_2.0 == leaf.0))
+                .count();
+        assert_eq!(diffs.len(), changed);
+    }
 }
@@ -1304,7 +1304,6 @@ impl<'a, T: MarfTrieId> TrieStorageConnection<'a, T> {
     }
 
     /// Generate a mapping between Trie root hashes and the blocks that contain them
-    #[cfg(test)]
     pub fn read_root_to_block_table(&mut self) -> Result<HashMap<TrieHash, T>, Error> {
         let mut ret =
             HashMap::from_iter(trie_sql::read_all_block_hashes_and_roots(&self.db)?.into_iter());
@@ -242,7 +242,6 @@ pub fn open_trie_blob<'a>(conn: &'a Connection, block_id: u32) -> Result<Blob<'a
     Ok(blob)
 }
 
-#[cfg(test)]
 pub fn read_all_block_hashes_and_roots<T: MarfTrieId>(
     conn: &Connection,
 ) -> Result<Vec<(TrieHash, T)>, Error> {
@@ -16,6 +16,7 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::convert::From;
 use std::fs;
 use std::mem;
@@ -37,7 +38,9 @@ use clarity_vm::clarity::ClarityConnection;
 use core::mempool::*;
 use core::*;
 use net::Error as net_error;
+use util::db::DBConn;
 use util::get_epoch_time_ms;
+use util::get_epoch_time_secs;
 use util::hash::MerkleTree;
 use util::hash::Sha512Trunc256Sum;
 use util::secp256k1::{MessageSignature, Secp256k1PrivateKey};
@@ -50,6 +53,11 @@ use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksWorkScore};
 use crate::types::chainstate::{StacksBlockHeader, StacksBlockId, StacksMicroblockHeader};
 use crate::types::proof::TrieHash;
 
+// number of transactions mined between block assembly checkpoints
+pub const BLOCK_ASSEMBLY_CHECKPOINT_INTERVAL: usize = 25;
+// block assembly checkpoints older than this many seconds are not resumed from
+pub const BLOCK_ASSEMBLY_CHECKPOINT_MAX_AGE: u64 = 600;
+
 #[derive(Clone)]
 struct MicroblockMinerRuntime {
     bytes_so_far: u64,
@@ -70,6 +78,47 @@ enum BlockLimitFunction {
     LIMIT_REACHED,
 }
 
+/// Operator-designated principals whose transactions are mined ahead of the fee-ordered
+/// mempool walk, until they have used up `reserved_budget_pct` percent of the block budget.
+/// Whatever they don't use is left for everyone else.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PriorityLanes {
+    pub principals: Vec<StacksAddress>,
+    pub reserved_budget_pct: u64,
+}
+
+impl PriorityLanes {
+    pub fn new(principals: Vec<StacksAddress>, reserved_budget_pct: u64) -> PriorityLanes {
+        PriorityLanes {
+            principals,
+            reserved_budget_pct,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.principals.len() > 0 && self.reserved_budget_pct > 0
+    }
+
+    /// Is this transaction sent or sponsored by one of the priority principals?
+    pub fn is_priority_tx(&self, tx: &StacksTransaction) -> bool {
+        self.principals.contains(&tx.origin_address())
+            || tx
+                .sponsor_address()
+                .map(|addr| self.principals.contains(&addr))
+                .unwrap_or(false)
+    }
+}
+
+/// What a dry run of anchored block assembly would have mined, without producing a block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockSimulation {
+    /// Transactions that would be mined, in block order, not counting the coinbase
+    pub txs: Vec<StacksTransaction>,
+    pub total_fees: u64,
+    pub size: u64,
+    pub cost: ExecutionCost,
+}
+
 impl From<&UnconfirmedState> for MicroblockMinerRuntime {
     fn from(unconfirmed: &UnconfirmedState) -> MicroblockMinerRuntime {
         let considered = unconfirmed
@@ -1364,6 +1413,175 @@ impl StacksBlockBuilder {
         execution_budget: ExecutionCost,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
     ) -> Result<(StacksBlock, ExecutionCost, u64), Error> {
+        StacksBlockBuilder::build_anchored_block_preemptible(
+            chainstate_handle,
+            burn_dbconn,
+            mempool,
+            parent_stacks_header,
+            total_burn,
+            proof,
+            pubkey_hash,
+            coinbase_tx,
+            execution_budget,
+            event_observer,
+            &PriorityLanes::default(),
+            false,
+            &mut || false,
+        )
+    }
+
+    /// Like `build_anchored_block`, but consult `should_preempt` before each candidate
+    /// transaction is considered.  If it returns true, the partially-assembled block is
+    /// discarded and `Error::BlockAssemblyPreempted` is returned, so the caller can restart
+    /// assembly on a better parent.
+    /// If `checkpoint_assembly` is set, the transactions mined so far are saved to the mempool
+    /// every `BLOCK_ASSEMBLY_CHECKPOINT_INTERVAL` transactions and once assembly is done, and a
+    /// recent checkpoint for the same parent is mined first, ahead of everything else.
+    pub fn build_anchored_block_preemptible(
+        chainstate_handle: &StacksChainState, // not directly used; used as a handle to open other chainstates
+        burn_dbconn: &SortitionDBConn,
+        mempool: &mut MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo, // Stacks header we're building off of
+        total_burn: u64, // the burn so far on the burnchain (i.e. from the last burnchain block)
+        proof: VRFProof, // proof over the burnchain's last seed
+        pubkey_hash: Hash160,
+        coinbase_tx: &StacksTransaction,
+        execution_budget: ExecutionCost,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+        priority_lanes: &PriorityLanes,
+        checkpoint_assembly: bool,
+        should_preempt: &mut dyn FnMut() -> bool,
+    ) -> Result<(StacksBlock, ExecutionCost, u64), Error> {
+        let ts_start = get_epoch_time_ms();
+        StacksBlockBuilder::assemble_anchored_block(
+            chainstate_handle,
+            burn_dbconn,
+            mempool,
+            parent_stacks_header,
+            total_burn,
+            proof,
+            pubkey_hash,
+            coinbase_tx,
+            execution_budget,
+            event_observer,
+            priority_lanes,
+            checkpoint_assembly,
+            should_preempt,
+            false,
+            |mut builder, mut epoch_tx| {
+                // save the block so we can build microblocks off of it
+                let block = builder.mine_anchored_block(&mut epoch_tx);
+                let size = builder.bytes_so_far;
+                let consumed = builder.epoch_finish(epoch_tx);
+
+                let ts_end = get_epoch_time_ms();
+
+                debug!(
+                    "Miner: mined anchored block {} height {} with {} txs, parent block {}, parent microblock {} ({}), size {}, consumed {:?}, in {}ms",
+                    block.block_hash(),
+                    block.header.total_work.work,
+                    block.txs.len(),
+                    &block.header.parent_block,
+                    &block.header.parent_microblock,
+                    block.header.parent_microblock_sequence,
+                    size,
+                    &consumed,
+                    ts_end.saturating_sub(ts_start);
+                );
+
+                (block, consumed, size)
+            },
+        )
+    }
+
+    /// Run block assembly off of the given parent as a dry run: select transactions from the
+    /// mempool exactly as `build_anchored_block` would, then throw the block away.  The mempool
+    /// is left untouched.  The coinbase is paid to a throwaway key.
+    pub fn simulate_anchored_block(
+        chainstate_handle: &StacksChainState,
+        burn_dbconn: &SortitionDBConn,
+        mempool: &mut MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo,
+        total_burn: u64,
+        execution_budget: ExecutionCost,
+        should_preempt: &mut dyn FnMut() -> bool,
+    ) -> Result<BlockSimulation, Error> {
+        let privk = StacksPrivateKey::new();
+        let auth = TransactionAuth::from_p2pkh(&privk).ok_or(Error::InvalidStacksTransaction(
+            "Failed to make coinbase authorization".to_string(),
+            false,
+        ))?;
+        let version = if chainstate_handle.mainnet {
+            TransactionVersion::Mainnet
+        } else {
+            TransactionVersion::Testnet
+        };
+        let mut coinbase_tx = StacksTransaction::new(
+            version,
+            auth,
+            TransactionPayload::Coinbase(CoinbasePayload([0u8; 32])),
+        );
+        coinbase_tx.chain_id = chainstate_handle.chain_id;
+        coinbase_tx.anchor_mode = TransactionAnchorMode::OnChainOnly;
+        let mut signer = StacksTransactionSigner::new(&coinbase_tx);
+        signer.sign_origin(&privk)?;
+        let coinbase_tx = signer.get_tx().ok_or(Error::InvalidStacksTransaction(
+            "Failed to sign coinbase".to_string(),
+            false,
+        ))?;
+
+        StacksBlockBuilder::assemble_anchored_block(
+            chainstate_handle,
+            burn_dbconn,
+            mempool,
+            parent_stacks_header,
+            total_burn,
+            VRFProof::empty(),
+            Hash160([0u8; 20]),
+            &coinbase_tx,
+            execution_budget,
+            None,
+            &PriorityLanes::default(),
+            false,
+            should_preempt,
+            true,
+            |builder, epoch_tx| {
+                let cost = epoch_tx.cost_so_far();
+                epoch_tx.rollback_block();
+                BlockSimulation {
+                    txs: builder.txs.into_iter().skip(1).collect(),
+                    total_fees: builder.total_anchored_fees,
+                    size: builder.bytes_so_far,
+                    cost,
+                }
+            },
+        )
+    }
+
+    /// Select transactions from the mempool into a new block off of `parent_stacks_header`, and
+    /// hand the builder and its open Clarity transaction to `finish`.  Unless this is a
+    /// `dry_run`, the mempool is told which transactions were too expensive to ever mine and
+    /// what the mined contract-calls cost.
+    fn assemble_anchored_block<T, F>(
+        chainstate_handle: &StacksChainState,
+        burn_dbconn: &SortitionDBConn,
+        mempool: &mut MemPoolDB,
+        parent_stacks_header: &StacksHeaderInfo,
+        total_burn: u64,
+        proof: VRFProof,
+        pubkey_hash: Hash160,
+        coinbase_tx: &StacksTransaction,
+        execution_budget: ExecutionCost,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+        priority_lanes: &PriorityLanes,
+        checkpoint_assembly: bool,
+        should_preempt: &mut dyn FnMut() -> bool,
+        dry_run: bool,
+        finish: F,
+    ) -> Result<T, Error>
+    where
+        F: FnOnce(StacksBlockBuilder, ClarityTx) -> T,
+    {
         if let TransactionPayload::Coinbase(..) = coinbase_tx.payload {
         } else {
             return Err(Error::MemPoolError(
@@ -1382,7 +1600,7 @@ impl StacksBlockBuilder {
             &tip_consensus_hash, &tip_block_hash, tip_height, execution_budget
         );
 
-        let (mut chainstate, _) = chainstate_handle.reopen_limited(execution_budget)?; // used for processing a block up to the given limit
+        let (mut chainstate, _) = chainstate_handle.reopen_limited(execution_budget.clone())?; // used for processing a block up to the given limit
 
         let mut builder = StacksBlockBuilder::make_block_builder(
             chainstate.mainnet,
@@ -1392,8 +1610,6 @@ impl StacksBlockBuilder {
             pubkey_hash,
         )?;
 
-        let ts_start = get_epoch_time_ms();
-
         let mut epoch_tx = builder.epoch_begin(&mut chainstate, burn_dbconn)?;
         builder.try_mine_tx(&mut epoch_tx, coinbase_tx)?;
 
@@ -1405,12 +1621,144 @@ impl StacksBlockBuilder {
 
         let mut block_limit_hit = BlockLimitFunction::NO_LIMIT_HIT;
 
+        // contract-calls (and everything after them from the same origin) that target a
+        // contract which isn't deployed yet, keyed by that contract.  They get retried as soon
+        // as a transaction in this block deploys it.
+        let mut deferred_txs: HashMap<QualifiedContractIdentifier, Vec<MemPoolTxInfo>> =
+            HashMap::new();
+        let mut deferred_origins: HashMap<StacksAddress, QualifiedContractIdentifier> =
+            HashMap::new();
+
+        // costs actually consumed by the contract-calls we ran, to train the cost estimator
+        let mut cost_observations = vec![];
+
+        // pick up where a previous attempt off of this parent left off, if the miner restarted
+        // mid-tenure
+        let parent_block_id = parent_stacks_header.index_block_hash();
+        let mut checkpointed_num_txs = 0;
+        if checkpoint_assembly {
+            let checkpoint = MemPoolDB::get_assembly_checkpoint(mempool.conn(), &parent_block_id)?
+                .filter(|checkpoint| {
+                    checkpoint.checkpoint_time + BLOCK_ASSEMBLY_CHECKPOINT_MAX_AGE
+                        >= get_epoch_time_secs()
+                });
+            if let Some(checkpoint) = checkpoint {
+                info!(
+                    "Resume block assembly off of {} from a checkpoint of {} tx(s)",
+                    &parent_block_id,
+                    checkpoint.txids.len()
+                );
+                for txid in checkpoint.txids.iter() {
+                    if should_preempt() {
+                        epoch_tx.rollback_block();
+                        return Err(Error::BlockAssemblyPreempted);
+                    }
+                    let txinfo = match MemPoolDB::get_tx(mempool.conn(), txid)? {
+                        Some(txinfo) => txinfo,
+                        None => {
+                            debug!("Checkpointed tx {} is no longer in the mempool", txid);
+                            continue;
+                        }
+                    };
+                    if MemPoolDB::is_tx_expired(mempool.conn(), txid, tip_height + 1)? {
+                        continue;
+                    }
+
+                    match builder.try_mine_tx_with_len(
+                        &mut epoch_tx,
+                        &txinfo.tx,
+                        txinfo.metadata.len,
+                        &BlockLimitFunction::NO_LIMIT_HIT,
+                    ) {
+                        Ok(_) => {
+                            considered.insert(txinfo.tx.txid());
+                            mined_origin_nonces
+                                .insert(txinfo.tx.origin_address(), txinfo.tx.get_origin_nonce());
+                            if let (Some(sponsor_addr), Some(sponsor_nonce)) =
+                                (txinfo.tx.sponsor_address(), txinfo.tx.get_sponsor_nonce())
+                            {
+                                mined_sponsor_nonces.insert(sponsor_addr, sponsor_nonce);
+                            }
+                        }
+                        Err(e) => {
+                            debug!("Failed to mine checkpointed tx {}: {:?}", txid, &e);
+                        }
+                    }
+                }
+                if epoch_tx.cost_so_far() != checkpoint.cost {
+                    debug!(
+                        "Resumed block assembly consumed {}, but the checkpoint recorded {}",
+                        &epoch_tx.cost_so_far(),
+                        &checkpoint.cost
+                    );
+                }
+                checkpointed_num_txs = builder.txs.len().saturating_sub(1);
+            }
+        }
+
+        // give the priority principals first claim on their reserved share of the budget,
+        // regardless of what fees they pay
+        if priority_lanes.is_enabled() {
+            let mut priority_txs: Vec<MemPoolTxInfo> =
+                MemPoolDB::get_candidate_txs(mempool.conn(), tip_height)?
+                    .into_iter()
+                    .filter(|txinfo| priority_lanes.is_priority_tx(&txinfo.tx))
+                    .collect();
+            priority_txs.sort_by_key(|txinfo| txinfo.metadata.origin_nonce);
+
+            for txinfo in priority_txs.into_iter() {
+                if should_preempt() {
+                    epoch_tx.rollback_block();
+                    return Err(Error::BlockAssemblyPreempted);
+                }
+                if execution_budget.proportion_largest_dimension(&epoch_tx.cost_so_far())
+                    >= priority_lanes.reserved_budget_pct
+                {
+                    debug!("Priority principals used up their reserved block budget");
+                    break;
+                }
+                if let Some(nonce) = mined_origin_nonces.get(&txinfo.tx.origin_address()) {
+                    if *nonce >= txinfo.tx.get_origin_nonce() {
+                        continue;
+                    }
+                }
+
+                match builder.try_mine_tx_with_len(
+                    &mut epoch_tx,
+                    &txinfo.tx,
+                    txinfo.metadata.len,
+                    &BlockLimitFunction::NO_LIMIT_HIT,
+                ) {
+                    Ok(_) => {
+                        debug!("Mined priority tx {}", &txinfo.tx.txid());
+                        considered.insert(txinfo.tx.txid());
+                        mined_origin_nonces
+                            .insert(txinfo.tx.origin_address(), txinfo.tx.get_origin_nonce());
+                        if let (Some(sponsor_addr), Some(sponsor_nonce)) =
+                            (txinfo.tx.sponsor_address(), txinfo.tx.get_sponsor_nonce())
+                        {
+                            mined_sponsor_nonces.insert(sponsor_addr, sponsor_nonce);
+                        }
+                    }
+                    Err(e) => {
+                        // leave it for the fee-ordered pass to retry
+                        debug!("Failed to mine priority tx {}: {:?}", &txinfo.tx.txid(), &e);
+                    }
+                }
+            }
+        }
+
         let result = mempool.iterate_candidates(tip_height, |available_txs| {
             if block_limit_hit == BlockLimitFunction::LIMIT_REACHED {
                 return Ok(());
             }
 
-            for txinfo in available_txs.into_iter() {
+            let mut pending_txs: VecDeque<MemPoolTxInfo> = available_txs.into();
+            while let Some(txinfo) = pending_txs.pop_front() {
+                if should_preempt() {
+                    return Err(Error::BlockAssemblyPreempted);
+                }
+
                 // skip transactions early if we can
                 if considered.contains(&txinfo.tx.txid()) {
                     continue;
@@ -1430,8 +1778,47 @@ impl StacksBlockBuilder {
                     }
                 }
 
+                // wait for a missing contract to be deployed earlier in this block
+                if let Some(contract_id) = deferred_origins.get(&txinfo.tx.origin_address()) {
+                    deferred_txs
+                        .entry(contract_id.clone())
+                        .or_insert_with(Vec::new)
+                        .push(txinfo);
+                    continue;
+                }
+                if let TransactionPayload::ContractCall(ref cc) = txinfo.tx.payload {
+                    let contract_id = cc.to_clarity_contract_id();
+                    if StacksChainState::get_contract(&mut epoch_tx, &contract_id)?.is_none() {
+                        debug!(
+                            "Defer tx {} until {} is deployed",
+                            &txinfo.tx.txid(),
+                            &contract_id
+                        );
+                        deferred_origins.insert(txinfo.tx.origin_address(), contract_id.clone());
+                        deferred_txs
+                            .entry(contract_id)
+                            .or_insert_with(Vec::new)
+                            .push(txinfo);
+                        continue;
+                    }
+                }
+
                 considered.insert(txinfo.tx.txid());
 
+                // don't bother running a contract-call that history says won't fit
+                if let Some(estimate) = mempool.estimate_tx_cost(&txinfo.tx)? {
+                    let mut projected = epoch_tx.cost_so_far();
+                    if projected.add(&estimate).is_err() || projected.exceeds(&execution_budget) {
+                        debug!(
+                            "Skip tx {}: estimated cost {} would exceed the remaining block budget",
+                            &txinfo.tx.txid(),
+                            &estimate
+                        );
+                        continue;
+                    }
+                }
+
+                let cost_before = epoch_tx.cost_so_far();
                 match builder.try_mine_tx_with_len(
                     &mut epoch_tx,
                     &txinfo.tx,
@@ -1469,6 +1856,17 @@ impl StacksBlockBuilder {
                     }
                 }
 
+                if let TransactionPayload::ContractCall(ref cc) = txinfo.tx.payload {
+                    let mut cost = epoch_tx.cost_so_far();
+                    if cost.sub(&cost_before).is_ok() && cost != ExecutionCost::zero() {
+                        cost_observations.push((
+                            cc.to_clarity_contract_id(),
+                            cc.function_name.clone(),
+                            cost,
+                        ));
+                    }
+                }
+
                 mined_origin_nonces
                     .insert(txinfo.tx.origin_address(), txinfo.tx.get_origin_nonce());
                 if let (Some(sponsor_addr), Some(sponsor_nonce)) =
@@ -1476,17 +1874,62 @@ impl StacksBlockBuilder {
                 {
                     mined_sponsor_nonces.insert(sponsor_addr, sponsor_nonce);
                 }
+
+                let num_txs = builder.txs.len().saturating_sub(1);
+                if checkpoint_assembly
+                    && num_txs >= checkpointed_num_txs + BLOCK_ASSEMBLY_CHECKPOINT_INTERVAL
+                {
+                    builder.save_assembly_checkpoint(
+                        mempool.conn(),
+                        &parent_block_id,
+                        epoch_tx.cost_so_far(),
+                    );
+                    checkpointed_num_txs = num_txs;
+                }
+
+                // if this deployed a contract that other transactions were waiting on, retry
+                // them next, in the order they were deferred.
+                if let TransactionPayload::SmartContract(ref sc) = txinfo.tx.payload {
+                    let contract_id = QualifiedContractIdentifier::new(
+                        txinfo.tx.origin_address().into(),
+                        sc.name.clone(),
+                    );
+                    if let Some(waiting_txs) = deferred_txs.remove(&contract_id) {
+                        debug!(
+                            "Retry {} tx(s) deferred until {} was deployed",
+                            waiting_txs.len(),
+                            &contract_id
+                        );
+                        deferred_origins.retain(|_, waiting_on| *waiting_on != contract_id);
+                        for waiting_tx in waiting_txs.into_iter().rev() {
+                            pending_txs.push_front(waiting_tx);
+                        }
+                    }
+                }
             }
             Ok(())
         });
 
-        mempool.drop_txs(&invalidated_txs)?;
-        if let Some(observer) = event_observer {
-            observer.mempool_txs_dropped(invalidated_txs, MemPoolDropReason::TOO_EXPENSIVE);
+        if !dry_run {
+            mempool.drop_txs(&invalidated_txs, &MemPoolDropReason::TOO_EXPENSIVE)?;
+            mempool.record_execution_costs(&cost_observations)?;
+            if let Some(observer) = event_observer {
+                observer.mempool_txs_dropped(invalidated_txs, MemPoolDropReason::TOO_EXPENSIVE);
+            }
         }
 
         match result {
             Ok(_) => {}
+            Err(Error::BlockAssemblyPreempted) => {
+                info!(
+                    "Block assembly off of {}/{} preempted after considering {} transactions",
+                    &tip_consensus_hash,
+                    &tip_block_hash,
+                    considered.len()
+                );
+                epoch_tx.rollback_block();
+                return Err(Error::BlockAssemblyPreempted);
+            }
             Err(e) => {
                 warn!("Failure building block: {}", e);
                 epoch_tx.rollback_block();
@@ -1497,27 +1940,37 @@ impl StacksBlockBuilder {
         // the prior do_rebuild logic wasn't necessary
         // a transaction that caused a budget exception is rolled back in process_transaction
 
-        // save the block so we can build microblocks off of it
-        let block = builder.mine_anchored_block(&mut epoch_tx);
-        let size = builder.bytes_so_far;
-        let consumed = builder.epoch_finish(epoch_tx);
-
-        let ts_end = get_epoch_time_ms();
+        if checkpoint_assembly {
+            builder.save_assembly_checkpoint(
+                mempool.conn(),
+                &parent_block_id,
+                epoch_tx.cost_so_far(),
+            );
+        }
 
-        debug!(
-            "Miner: mined anchored block {} height {} with {} txs, parent block {}, parent microblock {} ({}), size {}, consumed {:?}, in {}ms",
-            block.block_hash(),
-            block.header.total_work.work,
-            block.txs.len(),
-            &block.header.parent_block,
-            &block.header.parent_microblock,
-            block.header.parent_microblock_sequence,
-            size,
-            &consumed,
-            ts_end.saturating_sub(ts_start);
-        );
+        Ok(finish(builder, epoch_tx))
+    }
 
-        Ok((block, consumed, size))
+    /// Save the transactions mined so far to the mempool, so that block assembly off of this
+    /// parent can resume from here if the miner restarts.
+    fn save_assembly_checkpoint(
+        &self,
+        mempool_conn: &DBConn,
+        parent_block_id: &StacksBlockId,
+        cost: ExecutionCost,
+    ) {
+        let checkpoint = BlockAssemblyCheckpoint {
+            parent_block_id: parent_block_id.clone(),
+            txids: self.txs.iter().skip(1).map(|tx| tx.txid()).collect(),
+            cost,
+            checkpoint_time: get_epoch_time_secs(),
+        };
+        if let Err(e) = MemPoolDB::save_assembly_checkpoint(mempool_conn, &checkpoint) {
+            warn!(
+                "Failed to save block assembly checkpoint off of {}: {:?}",
+                parent_block_id, &e
+            );
+        }
     }
 }
 
@@ -6181,6 +6634,25 @@ pub mod test {
         sign_standard_singlesig_tx(payload.into(), sender, nonce, tx_fee)
     }
 
+    pub fn make_user_contract_call(
+        sender: &StacksPrivateKey,
+        nonce: u64,
+        tx_fee: u64,
+        contract_addr: &StacksAddress,
+        contract_name: &str,
+        function_name: &str,
+        function_args: Vec<Value>,
+    ) -> StacksTransaction {
+        let payload = TransactionContractCall {
+            address: contract_addr.clone(),
+            contract_name: ContractName::from(contract_name),
+            function_name: ClarityName::from(function_name),
+            function_args,
+        };
+
+        sign_standard_singlesig_tx(payload.into(), sender, nonce, tx_fee)
+    }
+
     pub fn make_user_stacks_transfer(
         sender: &StacksPrivateKey,
         nonce: u64,
@@ -6464,6 +6936,593 @@ pub mod test {
         }
     }
 
+    #[test]
+    fn test_build_anchored_blocks_preempted() {
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+
+        let mut peer_config =
+            TestPeerConfig::new("test_build_anchored_blocks_preempted", 4010, 4011);
+        peer_config.initial_balances = vec![(addr.to_account_principal(), 1000000000)];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 3;
+        let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+        let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+        let mut sender_nonce = 0;
+
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let parent_header_hash = parent_tip.anchored_header.block_hash();
+                    let parent_consensus_hash = parent_tip.consensus_hash.clone();
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    if tenure_id > 0 {
+                        let stx_transfer = make_user_stacks_transfer(
+                            &privk,
+                            sender_nonce,
+                            200,
+                            &recipient.to_account_principal(),
+                            1,
+                        );
+                        sender_nonce += 1;
+
+                        mempool
+                            .submit(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                &stx_transfer,
+                                None,
+                            )
+                            .unwrap();
+
+                        // a better parent "arrives" before the transfer is considered
+                        let mut checks = 0;
+                        match StacksBlockBuilder::build_anchored_block_preemptible(
+                            chainstate,
+                            &sortdb.index_conn(),
+                            &mut mempool,
+                            &parent_tip,
+                            tip.total_burn,
+                            vrf_proof.clone(),
+                            Hash160([tenure_id as u8; 20]),
+                            &coinbase_tx,
+                            ExecutionCost::max_value(),
+                            None,
+                            &PriorityLanes::default(),
+                            false,
+                            &mut || {
+                                checks += 1;
+                                true
+                            },
+                        ) {
+                            Err(super::Error::BlockAssemblyPreempted) => {}
+                            Err(e) => panic!("Expected preemption, got {:?}", &e),
+                            Ok(_) => panic!("Expected preemption, got a block"),
+                        }
+                        assert_eq!(checks, 1);
+                    }
+
+                    // nothing better showed up, so assembly runs to completion
+                    let mut checks = 0;
+                    let anchored_block = StacksBlockBuilder::build_anchored_block_preemptible(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                        None,
+                        &PriorityLanes::default(),
+                        false,
+                        &mut || {
+                            checks += 1;
+                            false
+                        },
+                    )
+                    .unwrap();
+                    assert_eq!(checks > 0, tenure_id > 0);
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            if tenure_id > 0 {
+                // the preempted attempt did not consume the transfer
+                assert_eq!(stacks_block.txs.len(), 2);
+            } else {
+                assert_eq!(stacks_block.txs.len(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_simulate_anchored_block() {
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+
+        let mut peer_config = TestPeerConfig::new("test_simulate_anchored_block", 4016, 4017);
+        peer_config.initial_balances = vec![(addr.to_account_principal(), 1000000000)];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 3;
+        let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+        let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+        let mut sender_nonce = 0;
+
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let mut simulated_txids = vec![];
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let parent_header_hash = parent_tip.anchored_header.block_hash();
+                    let parent_consensus_hash = parent_tip.consensus_hash.clone();
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    if tenure_id > 0 {
+                        let stx_transfer = make_user_stacks_transfer(
+                            &privk,
+                            sender_nonce,
+                            200,
+                            &recipient.to_account_principal(),
+                            1,
+                        );
+                        sender_nonce += 1;
+
+                        mempool
+                            .submit(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                &stx_transfer,
+                                None,
+                            )
+                            .unwrap();
+                    }
+
+                    let simulation = StacksBlockBuilder::simulate_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        ExecutionCost::max_value(),
+                        &mut || false,
+                    )
+                    .unwrap();
+                    if tenure_id > 0 {
+                        assert_eq!(simulation.txs.len(), 1);
+                        assert_eq!(simulation.total_fees, 200);
+                        assert!(simulation.size > 0);
+                    } else {
+                        assert_eq!(simulation.txs.len(), 0);
+                        assert_eq!(simulation.total_fees, 0);
+                    }
+                    simulated_txids = simulation.txs.iter().map(|tx| tx.txid()).collect();
+
+                    // the dry run left the mempool and chainstate as they were
+                    let anchored_block = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                        None,
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            let mined_txids: Vec<Txid> = stacks_block.txs[1..].iter().map(|tx| tx.txid()).collect();
+            assert_eq!(mined_txids, simulated_txids);
+        }
+    }
+
+    #[test]
+    fn test_build_anchored_blocks_priority_lanes() {
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+
+        let priority_privk = StacksPrivateKey::from_hex(
+            "94c319327cc5cd04da7147d32d836eb2e4c44f4db39aa5ede7314a761183d0c701",
+        )
+        .unwrap();
+        let priority_addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&priority_privk)],
+        )
+        .unwrap();
+
+        let mut peer_config =
+            TestPeerConfig::new("test_build_anchored_blocks_priority_lanes", 4014, 4015);
+        peer_config.initial_balances = vec![
+            (addr.to_account_principal(), 1000000000),
+            (priority_addr.to_account_principal(), 1000000000),
+        ];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 3;
+        let recipient_addr_str = "ST1RFD5Q2QPK3E0F08HG9XDX7SSC7CNRS0QR0SGEV";
+        let recipient = StacksAddress::from_string(recipient_addr_str).unwrap();
+        let mut sender_nonce = 0;
+
+        let priority_lanes = PriorityLanes::new(vec![priority_addr.clone()], 10);
+
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let parent_header_hash = parent_tip.anchored_header.block_hash();
+                    let parent_consensus_hash = parent_tip.consensus_hash.clone();
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    if tenure_id > 0 {
+                        // the priority principal pays a much lower fee
+                        let stx_transfer = make_user_stacks_transfer(
+                            &privk,
+                            sender_nonce,
+                            2000,
+                            &recipient.to_account_principal(),
+                            1,
+                        );
+                        let priority_transfer = make_user_stacks_transfer(
+                            &priority_privk,
+                            sender_nonce,
+                            200,
+                            &recipient.to_account_principal(),
+                            2,
+                        );
+                        sender_nonce += 1;
+
+                        for tx in [stx_transfer, priority_transfer].iter() {
+                            mempool
+                                .submit(
+                                    chainstate,
+                                    &parent_consensus_hash,
+                                    &parent_header_hash,
+                                    tx,
+                                    None,
+                                )
+                                .unwrap();
+                        }
+                    }
+
+                    let anchored_block = StacksBlockBuilder::build_anchored_block_preemptible(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                        None,
+                        &priority_lanes,
+                        false,
+                        &mut || false,
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            if tenure_id > 0 {
+                // both were mined, but the priority principal's went first
+                assert_eq!(stacks_block.txs.len(), 3);
+                assert_eq!(stacks_block.txs[1].origin_address(), priority_addr);
+                assert_eq!(stacks_block.txs[2].origin_address(), addr);
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_anchored_blocks_deploy_then_call() {
+        let privk = StacksPrivateKey::from_hex(
+            "42faca653724860da7a41bfcef7e6ba78db55146f6900de8cb2a9f760ffac70c01",
+        )
+        .unwrap();
+        let addr = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk)],
+        )
+        .unwrap();
+        let privk_caller = StacksPrivateKey::new();
+        let addr_caller = StacksAddress::from_public_keys(
+            C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            &AddressHashMode::SerializeP2PKH,
+            1,
+            &vec![StacksPublicKey::from_private(&privk_caller)],
+        )
+        .unwrap();
+
+        let mut peer_config =
+            TestPeerConfig::new("test_build_anchored_blocks_deploy_then_call", 4012, 4013);
+        peer_config.initial_balances = vec![
+            (addr.to_account_principal(), 1000000000),
+            (addr_caller.to_account_principal(), 1000000000),
+        ];
+
+        let mut peer = TestPeer::new(peer_config);
+
+        let chainstate_path = peer.chainstate_path.clone();
+
+        let num_blocks = 2;
+        let contract = "(define-public (hello) (ok u1))";
+
+        for tenure_id in 0..num_blocks {
+            let tip =
+                SortitionDB::get_canonical_burn_chain_tip(&peer.sortdb.as_ref().unwrap().conn())
+                    .unwrap();
+
+            let (burn_ops, stacks_block, microblocks) = peer.make_tenure(
+                |ref mut miner,
+                 ref mut sortdb,
+                 ref mut chainstate,
+                 vrf_proof,
+                 ref parent_opt,
+                 ref parent_microblock_header_opt| {
+                    let parent_tip = match parent_opt {
+                        None => StacksChainState::get_genesis_header_info(chainstate.db()).unwrap(),
+                        Some(block) => {
+                            let ic = sortdb.index_conn();
+                            let snapshot =
+                                SortitionDB::get_block_snapshot_for_winning_stacks_block(
+                                    &ic,
+                                    &tip.sortition_id,
+                                    &block.block_hash(),
+                                )
+                                .unwrap()
+                                .unwrap(); // succeeds because we don't fork
+                            StacksChainState::get_anchored_block_header_info(
+                                chainstate.db(),
+                                &snapshot.consensus_hash,
+                                &snapshot.winning_stacks_block_hash,
+                            )
+                            .unwrap()
+                            .unwrap()
+                        }
+                    };
+
+                    let parent_header_hash = parent_tip.anchored_header.block_hash();
+                    let parent_consensus_hash = parent_tip.consensus_hash.clone();
+
+                    let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+                    let coinbase_tx = make_coinbase(miner, tenure_id);
+
+                    if tenure_id > 0 {
+                        // the call is considered before the deploy it depends on.  It can't pass
+                        // admission checks yet, so submit it directly.
+                        let call_tx = make_user_contract_call(
+                            &privk_caller,
+                            0,
+                            300,
+                            &addr,
+                            "hello-world",
+                            "hello",
+                            vec![],
+                        );
+                        mempool
+                            .submit_raw(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                call_tx.serialize_to_vec(),
+                            )
+                            .unwrap();
+
+                        let deploy_tx =
+                            make_user_contract_publish(&privk, 0, 200, "hello-world", contract);
+                        mempool
+                            .submit(
+                                chainstate,
+                                &parent_consensus_hash,
+                                &parent_header_hash,
+                                &deploy_tx,
+                                None,
+                            )
+                            .unwrap();
+                    }
+
+                    let anchored_block = StacksBlockBuilder::build_anchored_block(
+                        chainstate,
+                        &sortdb.index_conn(),
+                        &mut mempool,
+                        &parent_tip,
+                        tip.total_burn,
+                        vrf_proof,
+                        Hash160([tenure_id as u8; 20]),
+                        &coinbase_tx,
+                        ExecutionCost::max_value(),
+                        None,
+                    )
+                    .unwrap();
+                    (anchored_block.0, vec![])
+                },
+            );
+
+            peer.next_burnchain_block(burn_ops.clone());
+            peer.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+
+            if tenure_id > 0 {
+                // both were mined, with the call retried after the deploy
+                assert_eq!(stacks_block.txs.len(), 3);
+                match stacks_block.txs[1].payload {
+                    TransactionPayload::SmartContract(ref sc) => {
+                        assert_eq!(sc.name, ContractName::from("hello-world"));
+                    }
+                    _ => panic!("Expected contract deploy"),
+                }
+                match stacks_block.txs[2].payload {
+                    TransactionPayload::ContractCall(ref cc) => {
+                        assert_eq!(cc.address, addr);
+                        assert_eq!(cc.function_name, ClarityName::from("hello"));
+                    }
+                    _ => panic!("Expected contract call"),
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_build_anchored_blocks_stx_transfers_multi() {
         let mut privks = vec![];
@@ -166,6 +166,7 @@ pub enum Error {
     PoxAlreadyLocked,
     PoxInsufficientBalance,
     PoxNoRewardCycle,
+    BlockAssemblyPreempted,
 }
 
 impl From<marf_error> for Error {
@@ -229,6 +230,7 @@ impl fmt::Display for Error {
             Error::PoxAlreadyLocked => write!(f, "Account has already locked STX for PoX"),
             Error::PoxInsufficientBalance => write!(f, "Not enough STX to lock"),
             Error::PoxNoRewardCycle => write!(f, "No such reward cycle"),
+            Error::BlockAssemblyPreempted => write!(f, "Block assembly was preempted"),
         }
     }
 }
@@ -261,6 +263,7 @@ impl error::Error for Error {
             Error::PoxAlreadyLocked => None,
             Error::PoxInsufficientBalance => None,
             Error::PoxNoRewardCycle => None,
+            Error::BlockAssemblyPreempted => None,
         }
     }
 }
@@ -293,6 +296,7 @@ impl Error {
             Error::PoxAlreadyLocked => "PoxAlreadyLocked",
             Error::PoxInsufficientBalance => "PoxInsufficientBalance",
             Error::PoxNoRewardCycle => "PoxNoRewardCycle",
+            Error::BlockAssemblyPreempted => "BlockAssemblyPreempted",
         }
     }
 
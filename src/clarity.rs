@@ -186,6 +186,7 @@ fn run_analysis_free<C: ClarityStorage>(
     expressions: &mut [SymbolicExpression],
     marf_kv: &mut C,
     save_contract: bool,
+    mainnet: bool,
 ) -> Result<ContractAnalysis, (CheckError, LimitedCostTracker)> {
     analysis::run_analysis(
         contract_identifier,
@@ -193,6 +194,7 @@ fn run_analysis_free<C: ClarityStorage>(
         &mut marf_kv.get_analysis_db(),
         save_contract,
         LimitedCostTracker::new_free(),
+        mainnet,
     )
 }
 
@@ -220,6 +222,7 @@ fn run_analysis<C: ClarityStorage>(
         &mut marf_kv.get_analysis_db(),
         save_contract,
         cost_track,
+        mainnet,
     )
 }
 
@@ -711,7 +714,8 @@ fn install_boot_code<C: ClarityStorage>(header_db: &CLIHeadersDB, marf: &mut C)
             "Failed to parse program.",
         );
 
-        let analysis_result = run_analysis_free(&contract_identifier, &mut ast, marf, true);
+        let analysis_result =
+            run_analysis_free(&contract_identifier, &mut ast, marf, true, mainnet);
         match analysis_result {
             Ok(_) => {
                 let db = marf.get_clarity_db(header_db, &NULL_BURN_STATE_DB);
@@ -1060,7 +1064,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
                     }
                 };
 
-                match run_analysis_free(&contract_id, &mut ast, &mut analysis_marf, true) {
+                match run_analysis_free(&contract_id, &mut ast, &mut analysis_marf, true, mainnet) {
                     Ok(_) => (),
                     Err((error, _)) => {
                         println!("Type check error:\n{}", error);
@@ -1097,7 +1101,7 @@ pub fn invoke_command(invoked_by: &str, args: &[String]) -> (i32, Option<serde_j
 
             let mut ast =
                 friendly_expect(parse(&contract_id, &content), "Failed to parse program.");
-            match run_analysis_free(&contract_id, &mut ast, &mut analysis_marf, true) {
+            match run_analysis_free(&contract_id, &mut ast, &mut analysis_marf, true, true) {
                 Ok(_) => {
                     let result = vm_env.get_exec_environment(None).eval_raw(&content);
                     match result {
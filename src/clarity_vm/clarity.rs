@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
 use std::error;
 use std::fmt;
 
@@ -43,11 +44,13 @@ use vm::types::{
 
 use crate::clarity_vm::database::marf::ReadOnlyMarfStore;
 use crate::clarity_vm::database::marf::{MarfedKV, WritableMarfStore};
+use crate::clarity_vm::parallel_analysis::{self, PreAnalysis};
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::StacksBlockId;
 use crate::types::chainstate::StacksMicroblockHeader;
 use crate::types::proof::TrieHash;
 use crate::util::boot::boot_code_id;
+use crate::util::hash::Sha512Trunc256Sum;
 
 ///
 /// A high-level interface for interacting with the Clarity VM.
@@ -77,6 +80,7 @@ pub struct ClarityBlockConnection<'a> {
     burn_state_db: &'a dyn BurnStateDB,
     cost_track: Option<LimitedCostTracker>,
     mainnet: bool,
+    pre_analyzed: HashMap<QualifiedContractIdentifier, PreAnalyzedContract>,
 }
 
 ///
@@ -91,6 +95,7 @@ pub struct ClarityTransactionConnection<'a, 'b> {
     burn_state_db: &'a dyn BurnStateDB,
     cost_track: &'a mut Option<LimitedCostTracker>,
     mainnet: bool,
+    pre_analyzed: &'a mut HashMap<QualifiedContractIdentifier, PreAnalyzedContract>,
 }
 
 pub struct ClarityReadOnlyConnection<'a> {
@@ -99,6 +104,56 @@ pub struct ClarityReadOnlyConnection<'a> {
     burn_state_db: &'a dyn BurnStateDB,
 }
 
+/// The outcome of analyzing a smart contract ahead of its deploy transaction, against a copy of
+/// the state it will be deployed on (see `parallel_analysis`).
+#[derive(Debug, Clone)]
+pub struct PreAnalyzedContract {
+    code_hash: Sha512Trunc256Sum,
+    contract_ast: ContractAST,
+    contract_analysis: ContractAnalysis,
+    /// cost charged for the analysis, from zero
+    cost: ExecutionCost,
+    /// memory still held by the cost tracker when the analysis finished, from zero
+    memory: u64,
+}
+
+impl PreAnalyzedContract {
+    /// Parse and analyze a contract like `analyze_smart_contract()` does, charging the analysis to
+    /// `cost_track` (which should have nothing spent yet).  Returns None if the analysis fails:
+    /// failures are left to the deploy transaction to reproduce.
+    pub fn analyze(
+        identifier: &QualifiedContractIdentifier,
+        contract_content: &str,
+        analysis_db: &mut AnalysisDatabase,
+        mut cost_track: LimitedCostTracker,
+        mainnet: bool,
+    ) -> Option<PreAnalyzedContract> {
+        let mut contract_ast =
+            ast::build_ast(identifier, contract_content, &mut cost_track).ok()?;
+        let mut contract_analysis = analysis::run_analysis(
+            identifier,
+            &mut contract_ast.expressions,
+            analysis_db,
+            false,
+            cost_track,
+            mainnet,
+        )
+        .ok()?;
+        let cost_track = contract_analysis.take_contract_cost_tracker();
+        Some(PreAnalyzedContract {
+            code_hash: Sha512Trunc256Sum::from_data(contract_content.as_bytes()),
+            contract_ast,
+            contract_analysis,
+            cost: cost_track.get_total(),
+            memory: cost_track.get_memory(),
+        })
+    }
+
+    pub fn contract_ast(&self) -> &ContractAST {
+        &self.contract_ast
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     Analysis(CheckError),
@@ -233,6 +288,23 @@ impl ClarityBlockConnection<'_> {
             None => ExecutionCost::zero(),
         }
     }
+
+    /// Use the given analyses in place of analyzing the same contracts when they are deployed in
+    /// this block.  They are only used if `cost_track` (the tracker they were charged to) charges
+    /// the same costs as this block's tracker.  Returns whether or not they will be used.
+    pub fn set_pre_analyzed_contracts(
+        &mut self,
+        cost_track: &LimitedCostTracker,
+        contracts: HashMap<QualifiedContractIdentifier, PreAnalyzedContract>,
+    ) -> bool {
+        match self.cost_track {
+            Some(ref track) if track.has_same_costs_as(cost_track) => {
+                self.pre_analyzed = contracts;
+                true
+            }
+            _ => false,
+        }
+    }
 }
 
 impl ClarityInstance {
@@ -255,6 +327,24 @@ impl ClarityInstance {
         self.mainnet
     }
 
+    /// Analyze `contracts` against the state of block `at_block`, on up to `num_workers` threads.
+    /// See `parallel_analysis::pre_analyze_contracts`.
+    pub fn pre_analyze_contracts(
+        &self,
+        at_block: &StacksBlockId,
+        contracts: Vec<(QualifiedContractIdentifier, String)>,
+        num_workers: usize,
+    ) -> Option<(LimitedCostTracker, Vec<Option<PreAnalysis>>)> {
+        parallel_analysis::pre_analyze_contracts(
+            &self.datastore,
+            self.mainnet,
+            &self.block_limit,
+            at_block,
+            contracts,
+            num_workers,
+        )
+    }
+
     pub fn begin_block<'a>(
         &'a mut self,
         current: &StacksBlockId,
@@ -278,6 +368,7 @@ impl ClarityInstance {
             burn_state_db,
             cost_track,
             mainnet: self.mainnet,
+            pre_analyzed: HashMap::new(),
         }
     }
 
@@ -298,6 +389,7 @@ impl ClarityInstance {
             burn_state_db,
             cost_track,
             mainnet: self.mainnet,
+            pre_analyzed: HashMap::new(),
         }
     }
 
@@ -320,6 +412,7 @@ impl ClarityInstance {
             burn_state_db,
             cost_track,
             mainnet: false,
+            pre_analyzed: HashMap::new(),
         };
 
         conn.as_transaction(|clarity_db| {
@@ -396,6 +489,7 @@ impl ClarityInstance {
             burn_state_db,
             cost_track,
             mainnet: self.mainnet,
+            pre_analyzed: HashMap::new(),
         }
     }
 
@@ -613,6 +707,7 @@ impl<'a> ClarityBlockConnection<'a> {
         let header_db = &self.header_db;
         let burn_state_db = &self.burn_state_db;
         let mainnet = self.mainnet;
+        let pre_analyzed = &mut self.pre_analyzed;
         let mut log = RollbackWrapperPersistedLog::new();
         log.nest();
         ClarityTransactionConnection {
@@ -622,6 +717,7 @@ impl<'a> ClarityBlockConnection<'a> {
             burn_state_db,
             log: Some(log),
             mainnet,
+            pre_analyzed,
         }
     }
 
@@ -739,6 +835,13 @@ impl<'a, 'b> ClarityTransactionConnection<'a, 'b> {
         identifier: &QualifiedContractIdentifier,
         contract_content: &str,
     ) -> Result<(ContractAST, ContractAnalysis), Error> {
+        if let Some(pre_analyzed) = self.pre_analyzed.remove(identifier) {
+            if let Some(result) = self.use_pre_analyzed(pre_analyzed, contract_content) {
+                return Ok(result);
+            }
+        }
+
+        let mainnet = self.mainnet;
         using!(self.cost_track, "cost tracker", |mut cost_track| {
             self.inner_with_analysis_db(|db| {
                 let ast_result = ast::build_ast(identifier, contract_content, &mut cost_track);
@@ -754,6 +857,7 @@ impl<'a, 'b> ClarityTransactionConnection<'a, 'b> {
                     db,
                     false,
                     cost_track,
+                    mainnet,
                 );
 
                 match result {
@@ -767,6 +871,37 @@ impl<'a, 'b> ClarityTransactionConnection<'a, 'b> {
         })
     }
 
+    /// Charge a contract's analysis from ahead of time, and return it, if it analyzed this exact
+    /// code and would have run to completion here.  Since costs only ever go up, the analysis
+    /// exceeds the block budget here if and only if its total cost would.  Otherwise (None), the
+    /// contract has to be analyzed again, to fail in the same way.
+    fn use_pre_analyzed(
+        &mut self,
+        pre_analyzed: PreAnalyzedContract,
+        contract_content: &str,
+    ) -> Option<(ContractAST, ContractAnalysis)> {
+        if pre_analyzed.code_hash != Sha512Trunc256Sum::from_data(contract_content.as_bytes()) {
+            return None;
+        }
+        let cost_track = self.cost_track.as_mut()?;
+        if cost_track.get_memory() != 0 {
+            return None;
+        }
+        let mut total = cost_track.get_total();
+        total.add(&pre_analyzed.cost).ok()?;
+        if total.exceeds(&cost_track.get_limit()) {
+            return None;
+        }
+
+        cost_track
+            .add_cost(pre_analyzed.cost)
+            .expect("BUG: pre-analyzed contract cost exceeded the checked budget");
+        cost_track
+            .add_memory(pre_analyzed.memory)
+            .expect("BUG: pre-analyzed contract memory exceeded the memory limit");
+        Some((pre_analyzed.contract_ast, pre_analyzed.contract_analysis))
+    }
+
     fn with_abort_callback<F, A, R>(
         &mut self,
         to_do: F,
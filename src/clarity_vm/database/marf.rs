@@ -89,6 +89,19 @@ impl MarfedKV {
         Ok(MarfedKV { marf, chain_tip })
     }
 
+    /// Open another, read-only handle on this MARF (e.g. to read from another thread).  Fails if
+    /// a block is being written.
+    pub fn reopen_readonly(&self) -> InterpreterResult<MarfedKV> {
+        let marf = self
+            .marf
+            .reopen_readonly()
+            .map_err(|err| InterpreterError::MarfFailure(IncomparableError { err }))?;
+        Ok(MarfedKV {
+            marf,
+            chain_tip: self.chain_tip.clone(),
+        })
+    }
+
     // used by benchmarks
     pub fn temporary() -> MarfedKV {
         use rand::Rng;
@@ -4,4 +4,7 @@ pub mod clarity;
 /// Stacks blockchain specific Clarity database implementations and wrappers
 pub mod database;
 
+/// Analyzing the contracts deployed in a block on a pool of threads
+pub mod parallel_analysis;
+
 mod tests;
@@ -0,0 +1,292 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Analyzing a batch of smart contracts on a pool of threads.
+//!
+//! Parsing and type-checking a contract only reads the analyses of the contracts it refers to, so
+//! the contracts deployed in a block can be analyzed ahead of time, in parallel, against the
+//! state the block builds on.  Each worker reads that state through its own read-only handle on
+//! the Clarity MARF, and charges each analysis to its own copy of the block's cost tracker.
+//! Whether an analysis can stand in for the one its deploy transaction would have run is up to
+//! the caller: it must not refer to a contract deployed before it in the block, and it must be
+//! charged to a tracker with the same costs as the block's (see
+//! `ClarityBlockConnection::set_pre_analyzed_contracts`).
+
+use std::collections::HashSet;
+use std::thread;
+
+use vm::costs::{ExecutionCost, LimitedCostTracker};
+use vm::database::{NULL_BURN_STATE_DB, NULL_HEADER_DB};
+use vm::representations::{SymbolicExpression, SymbolicExpressionType, TraitDefinition};
+use vm::types::{PrincipalData, QualifiedContractIdentifier, Value};
+
+use crate::clarity_vm::clarity::PreAnalyzedContract;
+use crate::clarity_vm::database::marf::MarfedKV;
+use crate::types::chainstate::StacksBlockId;
+
+/// A successful pre-analysis, with the contracts it refers to
+pub type PreAnalysis = (PreAnalyzedContract, HashSet<QualifiedContractIdentifier>);
+
+/// Every contract that `expressions` refer to by name, whether to call it, use its traits, or
+/// pass it as an argument.  These are the only contracts their analysis can read.
+pub fn referenced_contracts(
+    expressions: &[SymbolicExpression],
+) -> HashSet<QualifiedContractIdentifier> {
+    let mut referenced = HashSet::new();
+    let mut queue: Vec<&SymbolicExpression> = expressions.iter().collect();
+    while let Some(expr) = queue.pop() {
+        match expr.expr {
+            SymbolicExpressionType::AtomValue(Value::Principal(PrincipalData::Contract(
+                ref contract_id,
+            )))
+            | SymbolicExpressionType::LiteralValue(Value::Principal(PrincipalData::Contract(
+                ref contract_id,
+            ))) => {
+                referenced.insert(contract_id.clone());
+            }
+            SymbolicExpressionType::Field(ref trait_id)
+            | SymbolicExpressionType::TraitReference(_, TraitDefinition::Defined(ref trait_id))
+            | SymbolicExpressionType::TraitReference(_, TraitDefinition::Imported(ref trait_id)) => {
+                referenced.insert(trait_id.contract_identifier.clone());
+            }
+            SymbolicExpressionType::List(ref children) => {
+                queue.extend(children.iter());
+            }
+            _ => {}
+        }
+    }
+    referenced
+}
+
+/// Analyze `contracts` against the state of the block `at_block` in `datastore`, on up to
+/// `num_workers` threads, charging each one from zero to a copy of a tracker with `block_limit`
+/// and the cost functions in effect at `at_block`.  Returns that tracker and, in the order given,
+/// each contract's analysis (None if it failed).  Returns None if the state could not be read.
+pub fn pre_analyze_contracts(
+    datastore: &MarfedKV,
+    mainnet: bool,
+    block_limit: &ExecutionCost,
+    at_block: &StacksBlockId,
+    contracts: Vec<(QualifiedContractIdentifier, String)>,
+    num_workers: usize,
+) -> Option<(LimitedCostTracker, Vec<Option<PreAnalysis>>)> {
+    let cost_track = {
+        let mut marf = datastore.reopen_readonly().ok()?;
+        let mut store = marf.begin_read_only_checked(Some(at_block)).ok()?;
+        let mut clarity_db = store.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB);
+        match LimitedCostTracker::new_mid_block(mainnet, block_limit.clone(), &mut clarity_db) {
+            Ok(cost_track) => cost_track,
+            Err(e) => {
+                debug!("Failed to load cost functions at {}: {:?}", at_block, &e);
+                return None;
+            }
+        }
+    };
+
+    let num_contracts = contracts.len();
+    let num_workers = num_workers.max(1).min(num_contracts);
+    let mut batches: Vec<Vec<(usize, QualifiedContractIdentifier, String)>> =
+        (0..num_workers).map(|_| vec![]).collect();
+    for (i, (contract_id, code)) in contracts.into_iter().enumerate() {
+        batches[i % num_workers].push((i, contract_id, code));
+    }
+
+    let mut workers = vec![];
+    for batch in batches.into_iter() {
+        let mut marf = datastore.reopen_readonly().ok()?;
+        let at_block = at_block.clone();
+        let cost_track = cost_track.clone();
+        let worker = thread::Builder::new()
+            .name("contract-analysis".to_string())
+            .spawn(move || {
+                let mut results = vec![];
+                for (i, contract_id, code) in batch.into_iter() {
+                    let mut store = match marf.begin_read_only_checked(Some(&at_block)) {
+                        Ok(store) => store,
+                        Err(_) => {
+                            results.push((i, None));
+                            continue;
+                        }
+                    };
+                    let mut analysis_db = store.as_analysis_db();
+                    analysis_db.begin();
+                    let result = PreAnalyzedContract::analyze(
+                        &contract_id,
+                        &code,
+                        &mut analysis_db,
+                        cost_track.clone(),
+                        mainnet,
+                    )
+                    .map(|pre_analyzed| {
+                        let referenced =
+                            referenced_contracts(&pre_analyzed.contract_ast().expressions);
+                        (pre_analyzed, referenced)
+                    });
+                    analysis_db.roll_back();
+                    results.push((i, result));
+                }
+                results
+            })
+            .ok()?;
+        workers.push(worker);
+    }
+
+    let mut analyses: Vec<Option<PreAnalysis>> = (0..num_contracts).map(|_| None).collect();
+    for worker in workers.into_iter() {
+        match worker.join() {
+            Ok(results) => {
+                for (i, result) in results.into_iter() {
+                    analyses[i] = result;
+                }
+            }
+            Err(_) => {
+                warn!("Contract analysis worker panicked");
+            }
+        }
+    }
+    Some((cost_track, analyses))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use clarity_vm::clarity::ClarityInstance;
+    use vm::ast;
+
+    use crate::types::proof::ClarityMarfTrieId;
+
+    use super::*;
+
+    const BASE: &str = "
+        (define-trait trait-a ((get-a () (response uint uint))))
+        (define-public (get-a) (ok u1))";
+    const USES_BASE: &str = "
+        (use-trait trait-a .base.trait-a)
+        (define-public (call-a (target <trait-a>)) (contract-call? target get-a))
+        (define-public (call-base) (contract-call? .base get-a))";
+    const USES_NEW: &str = "(define-public (call-new) (contract-call? .uses-base call-base))";
+    const BAD: &str = "(define-public (bad) (ok (+ u1 1)))";
+
+    #[test]
+    fn test_referenced_contracts() {
+        let contract_id = QualifiedContractIdentifier::local("uses-base").unwrap();
+        let contract_ast = ast::build_ast(&contract_id, USES_BASE, &mut ()).unwrap();
+        let referenced = referenced_contracts(&contract_ast.expressions);
+        assert_eq!(referenced.len(), 1);
+        assert!(referenced.contains(&QualifiedContractIdentifier::local("base").unwrap()));
+
+        let contract_ast = ast::build_ast(&contract_id, BAD, &mut ()).unwrap();
+        assert!(referenced_contracts(&contract_ast.expressions).is_empty());
+    }
+
+    #[test]
+    fn test_pre_analyze_contracts() {
+        let marf = MarfedKV::temporary();
+        let mut clarity_instance = ClarityInstance::new(false, marf, ExecutionCost::max_value());
+        let base_id = QualifiedContractIdentifier::local("base").unwrap();
+        let uses_base_id = QualifiedContractIdentifier::local("uses-base").unwrap();
+        let uses_new_id = QualifiedContractIdentifier::local("uses-new").unwrap();
+        let bad_id = QualifiedContractIdentifier::local("bad").unwrap();
+
+        clarity_instance
+            .begin_test_genesis_block(
+                &StacksBlockId::sentinel(),
+                &StacksBlockId([0 as u8; 32]),
+                &NULL_HEADER_DB,
+                &NULL_BURN_STATE_DB,
+            )
+            .commit_block();
+        {
+            let mut conn = clarity_instance.begin_block(
+                &StacksBlockId([0 as u8; 32]),
+                &StacksBlockId([1 as u8; 32]),
+                &NULL_HEADER_DB,
+                &NULL_BURN_STATE_DB,
+            );
+            conn.as_transaction(|tx| {
+                let (ct_ast, ct_analysis) = tx.analyze_smart_contract(&base_id, BASE).unwrap();
+                tx.initialize_smart_contract(&base_id, &ct_ast, BASE, |_, _| false)
+                    .unwrap();
+                tx.save_analysis(&base_id, &ct_analysis).unwrap();
+            });
+            conn.commit_block();
+        }
+
+        let (cost_track, mut analyses) = clarity_instance
+            .pre_analyze_contracts(
+                &StacksBlockId([1 as u8; 32]),
+                vec![
+                    (uses_base_id.clone(), USES_BASE.to_string()),
+                    (uses_new_id.clone(), USES_NEW.to_string()),
+                    (bad_id.clone(), BAD.to_string()),
+                ],
+                2,
+            )
+            .unwrap();
+        assert_eq!(analyses.len(), 3);
+        // uses-new refers to a contract that the parent block does not have
+        assert!(analyses[1].is_none());
+        assert!(analyses[2].is_none());
+        let (pre_analyzed, referenced) = analyses[0].take().unwrap();
+        assert_eq!(referenced.len(), 1);
+        assert!(referenced.contains(&base_id));
+
+        // analyze it the usual way, for comparison
+        let (expected_analysis, expected_cost) = {
+            let mut conn = clarity_instance.begin_block(
+                &StacksBlockId([1 as u8; 32]),
+                &StacksBlockId([2 as u8; 32]),
+                &NULL_HEADER_DB,
+                &NULL_BURN_STATE_DB,
+            );
+            let (_, analysis) = conn
+                .as_transaction(|tx| tx.analyze_smart_contract(&uses_base_id, USES_BASE))
+                .unwrap();
+            let cost = conn.cost_so_far();
+            conn.rollback_block();
+            (analysis, cost)
+        };
+        assert!(expected_cost.runtime > 0);
+
+        let mut conn = clarity_instance.begin_block(
+            &StacksBlockId([1 as u8; 32]),
+            &StacksBlockId([2 as u8; 32]),
+            &NULL_HEADER_DB,
+            &NULL_BURN_STATE_DB,
+        );
+        let mut contracts = HashMap::new();
+        contracts.insert(uses_base_id.clone(), pre_analyzed.clone());
+        assert!(conn.set_pre_analyzed_contracts(&cost_track, contracts));
+        let (_, analysis) = conn
+            .as_transaction(|tx| tx.analyze_smart_contract(&uses_base_id, USES_BASE))
+            .unwrap();
+        assert_eq!(analysis, expected_analysis);
+        assert_eq!(conn.cost_so_far(), expected_cost);
+
+        // a pre-analysis of different code is ignored
+        let mut contracts = HashMap::new();
+        contracts.insert(uses_base_id.clone(), pre_analyzed);
+        assert!(conn.set_pre_analyzed_contracts(&cost_track, contracts));
+        assert!(conn
+            .as_transaction(|tx| tx.analyze_smart_contract(&uses_base_id, BAD))
+            .is_err());
+
+        // as is one charged to a tracker with different costs
+        assert!(!conn.set_pre_analyzed_contracts(&LimitedCostTracker::new_free(), HashMap::new()));
+        conn.rollback_block();
+    }
+}
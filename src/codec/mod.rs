@@ -39,6 +39,20 @@ impl fmt::Display for Error {
     }
 }
 
+impl Error {
+    /// Did decoding fail because the input itself was malformed (as opposed to an I/O failure
+    /// or a failure to encode our own data)?
+    pub fn is_malformed_input(&self) -> bool {
+        match *self {
+            Error::DeserializeError(_)
+            | Error::UnderflowError(_)
+            | Error::OverflowError(_)
+            | Error::ArrayTooLong => true,
+            Error::SerializeError(_) | Error::ReadError(_) | Error::WriteError(_) => false,
+        }
+    }
+}
+
 impl error::Error for Error {
     fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
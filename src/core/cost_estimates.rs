@@ -0,0 +1,262 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Execution cost estimates learned from history.
+//!
+//! Every time the miner executes a contract-call, the cost it actually consumed is recorded
+//! against the (contract, function) it called.  The estimate for a function is a pessimistic
+//! percentile of its most recent observations, taken independently in each cost dimension.
+//! This is usually far below the static worst case, so common calls are not starved out of
+//! blocks, while still being conservative about calls whose cost varies with their inputs.
+
+use std::cmp;
+
+use rusqlite::types::ToSql;
+use rusqlite::Row;
+
+use chainstate::stacks::{StacksTransaction, TransactionPayload};
+use util::db::query_rows;
+use util::db::tx_begin_immediate;
+use util::db::u64_to_sql;
+use util::db::Error as db_error;
+use util::db::{DBConn, DBTx, FromColumn, FromRow};
+use util::get_epoch_time_secs;
+use vm::costs::ExecutionCost;
+use vm::types::QualifiedContractIdentifier;
+use vm::ClarityName;
+
+// number of most-recent observations kept (and used) per contract function
+pub const COST_ESTIMATE_WINDOW: u64 = 20;
+// percentile of the observed costs reported as the estimate
+pub const COST_ESTIMATE_PERCENTILE: u64 = 90;
+
+// Applied on every open, so that existing databases pick it up.
+const COST_ESTIMATES_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS cost_observations(
+        contract_id TEXT NOT NULL,
+        function_name TEXT NOT NULL,
+        runtime INTEGER NOT NULL,
+        write_length INTEGER NOT NULL,
+        write_count INTEGER NOT NULL,
+        read_length INTEGER NOT NULL,
+        read_count INTEGER NOT NULL,
+        observed_at INTEGER NOT NULL
+    );
+    "#,
+    "CREATE INDEX IF NOT EXISTS cost_observations_by_function ON cost_observations(contract_id, function_name);",
+];
+
+impl FromRow<ExecutionCost> for ExecutionCost {
+    fn from_row<'a>(row: &'a Row) -> Result<ExecutionCost, db_error> {
+        Ok(ExecutionCost {
+            runtime: u64::from_column(row, "runtime")?,
+            write_length: u64::from_column(row, "write_length")?,
+            write_count: u64::from_column(row, "write_count")?,
+            read_length: u64::from_column(row, "read_length")?,
+            read_count: u64::from_column(row, "read_count")?,
+        })
+    }
+}
+
+/// Produce the `percentile`-th percentile of each cost dimension across `observations`.
+/// Returns None if there are no observations.
+pub fn pessimistic_estimate(
+    observations: &[ExecutionCost],
+    percentile: u64,
+) -> Option<ExecutionCost> {
+    if observations.len() == 0 {
+        return None;
+    }
+
+    let pick = |mut values: Vec<u64>| -> u64 {
+        values.sort();
+        // nearest-rank: ceil(percentile/100 * n), 1-indexed
+        let rank = (cmp::min(percentile, 100) * (values.len() as u64) + 99) / 100;
+        values[cmp::max(rank, 1) as usize - 1]
+    };
+
+    Some(ExecutionCost {
+        runtime: pick(observations.iter().map(|c| c.runtime).collect()),
+        write_length: pick(observations.iter().map(|c| c.write_length).collect()),
+        write_count: pick(observations.iter().map(|c| c.write_count).collect()),
+        read_length: pick(observations.iter().map(|c| c.read_length).collect()),
+        read_count: pick(observations.iter().map(|c| c.read_count).collect()),
+    })
+}
+
+/// Cost estimator backed by the observed execution costs of past contract-calls.
+pub struct PessimisticEstimator {}
+
+impl PessimisticEstimator {
+    pub fn instantiate(conn: &mut DBConn) -> Result<(), db_error> {
+        let tx = tx_begin_immediate(conn)?;
+
+        for cmd in COST_ESTIMATES_SCHEMA {
+            tx.execute_batch(cmd).map_err(db_error::SqliteError)?;
+        }
+
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Record the cost a call to `contract_id.function_name` actually consumed, and forget
+    /// all but the most recent `COST_ESTIMATE_WINDOW` observations for that function.
+    pub fn record(
+        tx: &DBTx,
+        contract_id: &QualifiedContractIdentifier,
+        function_name: &ClarityName,
+        cost: &ExecutionCost,
+    ) -> Result<(), db_error> {
+        let contract_id = contract_id.to_string();
+        let function_name = function_name.to_string();
+
+        let sql = "INSERT INTO cost_observations (contract_id, function_name, runtime, write_length, write_count, read_length, read_count, observed_at) \
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+        let args: &[&dyn ToSql] = &[
+            &contract_id,
+            &function_name,
+            &u64_to_sql(cost.runtime)?,
+            &u64_to_sql(cost.write_length)?,
+            &u64_to_sql(cost.write_count)?,
+            &u64_to_sql(cost.read_length)?,
+            &u64_to_sql(cost.read_count)?,
+            &u64_to_sql(get_epoch_time_secs())?,
+        ];
+        tx.execute(sql, args).map_err(db_error::SqliteError)?;
+
+        let sql = "DELETE FROM cost_observations WHERE contract_id = ?1 AND function_name = ?2 AND rowid NOT IN \
+                   (SELECT rowid FROM cost_observations WHERE contract_id = ?1 AND function_name = ?2 ORDER BY rowid DESC LIMIT ?3)";
+        let args: &[&dyn ToSql] = &[
+            &contract_id,
+            &function_name,
+            &u64_to_sql(COST_ESTIMATE_WINDOW)?,
+        ];
+        tx.execute(sql, args).map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Get the recorded costs of calls to `contract_id.function_name`, newest first.
+    pub fn get_observations(
+        conn: &DBConn,
+        contract_id: &QualifiedContractIdentifier,
+        function_name: &ClarityName,
+    ) -> Result<Vec<ExecutionCost>, db_error> {
+        let sql = "SELECT * FROM cost_observations WHERE contract_id = ?1 AND function_name = ?2 ORDER BY rowid DESC LIMIT ?3";
+        let args: &[&dyn ToSql] = &[
+            &contract_id.to_string(),
+            &function_name.to_string(),
+            &u64_to_sql(COST_ESTIMATE_WINDOW)?,
+        ];
+        query_rows(conn, sql, args)
+    }
+
+    /// Estimate the cost of calling `contract_id.function_name`, if it has been seen before.
+    pub fn estimate(
+        conn: &DBConn,
+        contract_id: &QualifiedContractIdentifier,
+        function_name: &ClarityName,
+    ) -> Result<Option<ExecutionCost>, db_error> {
+        let observations =
+            PessimisticEstimator::get_observations(conn, contract_id, function_name)?;
+        Ok(pessimistic_estimate(
+            &observations,
+            COST_ESTIMATE_PERCENTILE,
+        ))
+    }
+
+    /// Estimate the execution cost of a transaction.  Only contract-calls are estimated.
+    pub fn estimate_tx(
+        conn: &DBConn,
+        tx: &StacksTransaction,
+    ) -> Result<Option<ExecutionCost>, db_error> {
+        match tx.payload {
+            TransactionPayload::ContractCall(ref cc) => PessimisticEstimator::estimate(
+                conn,
+                &cc.to_clarity_contract_id(),
+                &cc.function_name,
+            ),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cost(runtime: u64, read_count: u64) -> ExecutionCost {
+        ExecutionCost {
+            runtime,
+            write_length: 0,
+            write_count: 0,
+            read_length: 0,
+            read_count,
+        }
+    }
+
+    #[test]
+    fn pessimistic_estimate_percentiles() {
+        assert_eq!(pessimistic_estimate(&[], 90), None);
+        assert_eq!(pessimistic_estimate(&[cost(7, 3)], 90), Some(cost(7, 3)));
+
+        // 1..=10 in runtime, 10..=1 in read_count
+        let observations: Vec<_> = (1..11).map(|i| cost(i, 11 - i)).collect();
+        assert_eq!(pessimistic_estimate(&observations, 90), Some(cost(9, 9)));
+        assert_eq!(pessimistic_estimate(&observations, 50), Some(cost(5, 5)));
+        assert_eq!(pessimistic_estimate(&observations, 100), Some(cost(10, 10)));
+        assert_eq!(pessimistic_estimate(&observations, 0), Some(cost(1, 1)));
+    }
+
+    #[test]
+    fn record_and_estimate() {
+        let mut conn = DBConn::open_in_memory().unwrap();
+        PessimisticEstimator::instantiate(&mut conn).unwrap();
+
+        let contract_id = QualifiedContractIdentifier::local("counter").unwrap();
+        let function_name = ClarityName::from("increment");
+        let other_function = ClarityName::from("decrement");
+
+        assert_eq!(
+            PessimisticEstimator::estimate(&conn, &contract_id, &function_name).unwrap(),
+            None
+        );
+
+        let tx = tx_begin_immediate(&mut conn).unwrap();
+        for i in 0..(COST_ESTIMATE_WINDOW + 10) {
+            PessimisticEstimator::record(&tx, &contract_id, &function_name, &cost(i, 1)).unwrap();
+        }
+        PessimisticEstimator::record(&tx, &contract_id, &other_function, &cost(1000, 1)).unwrap();
+        tx.commit().unwrap();
+
+        // only the most recent observations are kept
+        let observations =
+            PessimisticEstimator::get_observations(&conn, &contract_id, &function_name).unwrap();
+        assert_eq!(observations.len() as u64, COST_ESTIMATE_WINDOW);
+        assert_eq!(observations[0], cost(COST_ESTIMATE_WINDOW + 9, 1));
+        assert_eq!(observations[observations.len() - 1], cost(10, 1));
+
+        // runtimes 10..=29; the 90th percentile of 20 is the 18th
+        assert_eq!(
+            PessimisticEstimator::estimate(&conn, &contract_id, &function_name).unwrap(),
+            Some(cost(27, 1))
+        );
+        assert_eq!(
+            PessimisticEstimator::estimate(&conn, &contract_id, &other_function).unwrap(),
+            Some(cost(1000, 1))
+        );
+    }
+}
@@ -15,8 +15,9 @@
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::ops::Deref;
 use std::ops::DerefMut;
 use std::path::{Path, PathBuf};
@@ -37,9 +38,12 @@ use chainstate::stacks::{
     db::blocks::MemPoolRejection, db::StacksChainState, index::Error as MarfError,
     Error as ChainstateError, StacksTransaction,
 };
+use core::cost_estimates::PessimisticEstimator;
 use core::FIRST_BURNCHAIN_CONSENSUS_HASH;
 use core::FIRST_STACKS_BLOCK_HASH;
 use monitoring::increment_stx_mempool_gc;
+use util::db::query_count;
+use util::db::query_row_columns;
 use util::db::query_rows;
 use util::db::tx_begin_immediate;
 use util::db::tx_busy_handler;
@@ -49,15 +53,21 @@ use util::db::FromColumn;
 use util::db::{query_row, Error};
 use util::db::{sql_pragma, DBConn, DBTx, FromRow};
 use util::get_epoch_time_secs;
-use vm::types::PrincipalData;
+use util::hash::{hex_bytes, to_hex};
+use vm::costs::ExecutionCost;
+use vm::types::{PrincipalData, QualifiedContractIdentifier};
+use vm::ClarityName;
 
+use crate::codec::Error as codec_error;
 use crate::codec::StacksMessageCodec;
 use crate::monitoring;
-use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockHeader};
+use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockHeader, StacksBlockId};
 
 // maximum number of confirmations a transaction can have before it's garbage-collected
 pub const MEMPOOL_MAX_TRANSACTION_AGE: u64 = 256;
 pub const MAXIMUM_MEMPOOL_TX_CHAINING: u64 = 25;
+// maximum number of entries kept in the dropped-transaction audit log
+pub const MEMPOOL_MAX_DROP_LOG_ENTRIES: u64 = 100_000;
 
 pub struct MemPoolAdmitter {
     cur_block: BlockHeaderHash,
@@ -97,6 +107,11 @@ pub enum MemPoolDropReason {
     REPLACE_BY_FEE,
     STALE_COLLECT,
     TOO_EXPENSIVE,
+    EXPIRED,
+    STALE_NONCE,
+    /// never admitted: a transaction with the same origin or sponsor nonce, and at least the same
+    /// fee, was already pending in the same fork
+    CONFLICTING_NONCE,
 }
 
 impl std::fmt::Display for MemPoolDropReason {
@@ -104,12 +119,76 @@ impl std::fmt::Display for MemPoolDropReason {
         match self {
             MemPoolDropReason::STALE_COLLECT => write!(f, "StaleGarbageCollect"),
             MemPoolDropReason::TOO_EXPENSIVE => write!(f, "TooExpensive"),
+            MemPoolDropReason::EXPIRED => write!(f, "Expired"),
+            MemPoolDropReason::STALE_NONCE => write!(f, "StaleNonce"),
             MemPoolDropReason::REPLACE_ACROSS_FORK => write!(f, "ReplaceAcrossFork"),
             MemPoolDropReason::REPLACE_BY_FEE => write!(f, "ReplaceByFee"),
+            MemPoolDropReason::CONFLICTING_NONCE => write!(f, "ConflictingNonce"),
         }
     }
 }
 
+/// The transactions a miner had selected so far while assembling a block off of
+/// `parent_block_id`, and the execution cost they consumed.  Saved as assembly progresses, so a
+/// miner that restarts mid-tenure can pick up where it left off.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockAssemblyCheckpoint {
+    pub parent_block_id: StacksBlockId,
+    /// in block order, not counting the coinbase
+    pub txids: Vec<Txid>,
+    pub cost: ExecutionCost,
+    /// when the checkpoint was saved, in seconds since the epoch
+    pub checkpoint_time: u64,
+}
+
+impl FromRow<BlockAssemblyCheckpoint> for BlockAssemblyCheckpoint {
+    fn from_row<'a>(row: &'a Row) -> Result<BlockAssemblyCheckpoint, db_error> {
+        let txids_str: String = row.get_unwrap("txids");
+        let mut txids = vec![];
+        for txid_hex in txids_str.split(',').filter(|txid_hex| txid_hex.len() > 0) {
+            txids.push(Txid::from_hex(txid_hex).map_err(|_e| db_error::ParseError)?);
+        }
+        Ok(BlockAssemblyCheckpoint {
+            parent_block_id: StacksBlockId::from_column(row, "parent_block_id")?,
+            txids,
+            cost: ExecutionCost::from_row(row)?,
+            checkpoint_time: u64::from_column(row, "checkpoint_time")?,
+        })
+    }
+}
+
+/// Outcome of importing a mempool snapshot with `MemPoolDB::import_txs`
+#[derive(Debug, Default)]
+pub struct MemPoolImportReport {
+    /// transactions that passed admission and were added to the mempool
+    pub accepted: u64,
+    /// transactions that were already in the mempool
+    pub already_present: u64,
+    /// transactions that failed admission, and why
+    pub rejected: Vec<(Txid, MemPoolRejection)>,
+}
+
+/// Encode transactions for a mempool snapshot, which is a JSON list of hex-encoded transactions
+pub fn encode_mempool_snapshot(txs: &[StacksTransaction]) -> Vec<String> {
+    txs.iter()
+        .map(|tx| to_hex(&tx.serialize_to_vec()))
+        .collect()
+}
+
+/// Decode the transactions in a mempool snapshot
+pub fn decode_mempool_snapshot(hex_txs: &[String]) -> Result<Vec<StacksTransaction>, codec_error> {
+    let mut txs = Vec::with_capacity(hex_txs.len());
+    for hex_tx in hex_txs.iter() {
+        let tx_bytes = hex_bytes(hex_tx).map_err(|_e| {
+            codec_error::DeserializeError("Snapshot transaction is not hex-encoded".to_string())
+        })?;
+        txs.push(StacksTransaction::consensus_deserialize(
+            &mut &tx_bytes[..],
+        )?);
+    }
+    Ok(txs)
+}
+
 pub trait MemPoolEventDispatcher {
     fn mempool_txs_dropped(&self, txids: Vec<Txid>, reason: MemPoolDropReason);
 }
@@ -171,6 +250,53 @@ impl FromRow<MemPoolTxMetadata> for MemPoolTxMetadata {
     }
 }
 
+/// An entry in the mempool's audit log of dropped transactions
+#[derive(Debug, PartialEq, Clone)]
+pub struct MemPoolDropRecord {
+    pub txid: Txid,
+    /// why the transaction was dropped (see `MemPoolDropReason`)
+    pub reason: String,
+    /// when the transaction was dropped, in seconds since the epoch
+    pub drop_time: u64,
+    pub tx_fee: u64,
+    pub block_height: u64,
+    pub origin_address: StacksAddress,
+    pub origin_nonce: u64,
+    pub sponsor_address: StacksAddress,
+    pub sponsor_nonce: u64,
+    /// the transaction that replaced this one, or that was pending when this one was rejected for
+    /// conflicting with it
+    pub replaced_by: Option<Txid>,
+}
+
+impl FromRow<MemPoolDropRecord> for MemPoolDropRecord {
+    fn from_row<'a>(row: &'a Row) -> Result<MemPoolDropRecord, db_error> {
+        let txid = Txid::from_column(row, "txid")?;
+        let reason: String = row.get_unwrap("reason");
+        let drop_time = u64::from_column(row, "drop_time")?;
+        let tx_fee = u64::from_column(row, "tx_fee")?;
+        let height = u64::from_column(row, "height")?;
+        let origin_address = StacksAddress::from_column(row, "origin_address")?;
+        let origin_nonce = u64::from_column(row, "origin_nonce")?;
+        let sponsor_address = StacksAddress::from_column(row, "sponsor_address")?;
+        let sponsor_nonce = u64::from_column(row, "sponsor_nonce")?;
+        let replaced_by: Option<Txid> = row.get_unwrap("replaced_by");
+
+        Ok(MemPoolDropRecord {
+            txid: txid,
+            reason: reason,
+            drop_time: drop_time,
+            tx_fee: tx_fee,
+            block_height: height,
+            origin_address: origin_address,
+            origin_nonce: origin_nonce,
+            sponsor_address: sponsor_address,
+            sponsor_nonce: sponsor_nonce,
+            replaced_by: replaced_by,
+        })
+    }
+}
+
 impl FromRow<MemPoolTxInfo> for MemPoolTxInfo {
     fn from_row<'a>(row: &'a Row) -> Result<MemPoolTxInfo, db_error> {
         let md = MemPoolTxMetadata::from_row(row)?;
@@ -227,6 +353,62 @@ const MEMPOOL_INITIAL_SCHEMA: &'static [&'static str] = &[
     "CREATE INDEX by_chaintip ON mempool(consensus_hash,block_header_hash);",
 ];
 
+// audit log of dropped transactions.  Applied on every open, so that existing mempools pick it up.
+const MEMPOOL_DROP_LOG_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS mempool_drops(
+        txid TEXT NOT NULL,
+        reason TEXT NOT NULL,
+        drop_time INTEGER NOT NULL,
+        tx_fee INTEGER NOT NULL,
+        height INTEGER NOT NULL,
+        origin_address TEXT NOT NULL,
+        origin_nonce INTEGER NOT NULL,
+        sponsor_address TEXT NOT NULL,
+        sponsor_nonce INTEGER NOT NULL,
+        replaced_by TEXT
+    );
+    "#,
+    "CREATE INDEX IF NOT EXISTS by_dropped_txid ON mempool_drops(txid);",
+];
+
+// client-requested expiration heights of mempool transactions.  A transaction may only be mined
+// into a block whose height is at most its expire_height.  This is mempool policy, not consensus.
+// Applied on every open, so that existing mempools pick it up.
+const MEMPOOL_EXPIRATION_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS mempool_expirations(
+        txid TEXT NOT NULL,
+        expire_height INTEGER NOT NULL,
+        PRIMARY KEY (txid)
+    );
+    "#,
+    "CREATE INDEX IF NOT EXISTS by_expire_height ON mempool_expirations(expire_height);",
+];
+
+// the miner's latest block assembly checkpoint.  Applied on every open, so that existing
+// mempools pick it up.
+const MEMPOOL_ASSEMBLY_CHECKPOINT_SCHEMA: &'static [&'static str] = &[r#"
+    CREATE TABLE IF NOT EXISTS block_assembly_checkpoints(
+        parent_block_id TEXT NOT NULL,
+        txids TEXT NOT NULL,    -- comma-separated, in block order
+        runtime INTEGER NOT NULL,
+        write_length INTEGER NOT NULL,
+        write_count INTEGER NOT NULL,
+        read_length INTEGER NOT NULL,
+        read_count INTEGER NOT NULL,
+        checkpoint_time INTEGER NOT NULL,
+        PRIMARY KEY (parent_block_id)
+    );
+    "#];
+
+// columns of a drop log entry copied out of the mempool, and the expressions that fill them from a
+// mempool row (?2 is the drop reason and ?3 the drop time)
+const MEMPOOL_DROP_LOG_COLUMNS: &'static str =
+    "txid, reason, drop_time, tx_fee, height, origin_address, origin_nonce, sponsor_address, sponsor_nonce";
+const MEMPOOL_DROP_LOG_SOURCE_COLUMNS: &'static str =
+    "txid, ?2, ?3, tx_fee, height, origin_address, origin_nonce, sponsor_address, sponsor_nonce";
+
 pub struct MemPoolDB {
     db: DBConn,
     path: String,
@@ -316,6 +498,25 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Create the tables kept alongside the mempool (the drop log, expirations and block assembly
+    /// checkpoints) if they don't exist yet.
+    fn instantiate_side_tables(conn: &mut DBConn) -> Result<(), db_error> {
+        let tx = tx_begin_immediate(conn)?;
+
+        for cmd in MEMPOOL_DROP_LOG_SCHEMA {
+            tx.execute_batch(cmd).map_err(db_error::SqliteError)?;
+        }
+        for cmd in MEMPOOL_EXPIRATION_SCHEMA {
+            tx.execute_batch(cmd).map_err(db_error::SqliteError)?;
+        }
+        for cmd in MEMPOOL_ASSEMBLY_CHECKPOINT_SCHEMA {
+            tx.execute_batch(cmd).map_err(db_error::SqliteError)?;
+        }
+
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
     pub fn db_path(chainstate_root_path: &str) -> Result<String, db_error> {
         let mut path = PathBuf::from(chainstate_root_path);
 
@@ -369,6 +570,8 @@ impl MemPoolDB {
             // instantiate!
             MemPoolDB::instantiate_mempool_db(&mut conn)?;
         }
+        MemPoolDB::instantiate_side_tables(&mut conn)?;
+        PessimisticEstimator::instantiate(&mut conn)?;
 
         Ok(MemPoolDB {
             db: conn,
@@ -386,6 +589,7 @@ impl MemPoolDB {
     ///
     /// Consider transactions across all forks where the transactions have
     /// height >= max(0, tip_height - MEMPOOL_MAX_TRANSACTION_AGE) and height <= tip_height.
+    /// Transactions that expire before the block at tip_height + 1 are skipped.
     pub fn iterate_candidates<F, E>(&self, tip_height: u64, mut todo: F) -> Result<(), E>
     where
         F: FnMut(Vec<MemPoolTxInfo>) -> Result<(), E>,
@@ -434,10 +638,87 @@ impl MemPoolDB {
         }
     }
 
+    /// Get all transactions that would be considered for a block built on a tip at
+    /// `tip_height`, i.e. the same set `iterate_candidates` walks over.
+    pub fn get_candidate_txs(
+        conn: &DBConn,
+        tip_height: u64,
+    ) -> Result<Vec<MemPoolTxInfo>, db_error> {
+        let min_height = match tip_height.checked_sub(MEMPOOL_MAX_TRANSACTION_AGE + 1) {
+            None => -1,
+            Some(h) => u64_to_sql(h)?,
+        };
+        // skip transactions that expire before the block after the tip
+        let sql = "SELECT mempool.* FROM mempool LEFT JOIN mempool_expirations \
+                   ON mempool.txid = mempool_expirations.txid \
+                   WHERE height > ?1 AND height <= ?2 \
+                   AND (expire_height IS NULL OR expire_height > ?2)";
+        let args: &[&dyn ToSql] = &[&min_height, &u64_to_sql(tip_height)?];
+        query_rows(conn, sql, args)
+    }
+
     pub fn conn(&self) -> &DBConn {
         &self.db
     }
 
+    /// Get the expiration height a client gave for a transaction, if any
+    pub fn get_tx_expire_height(conn: &DBConn, txid: &Txid) -> Result<Option<u64>, db_error> {
+        query_row(
+            conn,
+            "SELECT expire_height FROM mempool_expirations WHERE txid = ?1",
+            &[txid as &dyn ToSql],
+        )
+    }
+
+    /// Would a transaction be expired if it were mined into a block at the given height?
+    pub fn is_tx_expired(conn: &DBConn, txid: &Txid, block_height: u64) -> Result<bool, db_error> {
+        Ok(MemPoolDB::get_tx_expire_height(conn, txid)?
+            .map(|expire_height| expire_height < block_height)
+            .unwrap_or(false))
+    }
+
+    /// Save the miner's progress assembling a block, replacing any earlier checkpoint.  Only the
+    /// latest checkpoint is kept, since a miner only assembles one block at a time.
+    pub fn save_assembly_checkpoint(
+        conn: &DBConn,
+        checkpoint: &BlockAssemblyCheckpoint,
+    ) -> Result<(), db_error> {
+        let txids: Vec<String> = checkpoint.txids.iter().map(|txid| txid.to_hex()).collect();
+        conn.execute(
+            "DELETE FROM block_assembly_checkpoints WHERE parent_block_id != ?1",
+            &[&checkpoint.parent_block_id as &dyn ToSql],
+        )
+        .map_err(db_error::SqliteError)?;
+
+        let sql = "INSERT OR REPLACE INTO block_assembly_checkpoints \
+                   (parent_block_id, txids, runtime, write_length, write_count, read_length, read_count, checkpoint_time) \
+                   VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+        let args: &[&dyn ToSql] = &[
+            &checkpoint.parent_block_id,
+            &txids.join(","),
+            &u64_to_sql(checkpoint.cost.runtime)?,
+            &u64_to_sql(checkpoint.cost.write_length)?,
+            &u64_to_sql(checkpoint.cost.write_count)?,
+            &u64_to_sql(checkpoint.cost.read_length)?,
+            &u64_to_sql(checkpoint.cost.read_count)?,
+            &u64_to_sql(checkpoint.checkpoint_time)?,
+        ];
+        conn.execute(sql, args).map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Get the miner's checkpoint for assembling a block off of the given parent, if any
+    pub fn get_assembly_checkpoint(
+        conn: &DBConn,
+        parent_block_id: &StacksBlockId,
+    ) -> Result<Option<BlockAssemblyCheckpoint>, db_error> {
+        query_row(
+            conn,
+            "SELECT * FROM block_assembly_checkpoints WHERE parent_block_id = ?1",
+            &[parent_block_id as &dyn ToSql],
+        )
+    }
+
     pub fn tx_begin<'a>(&'a mut self) -> Result<MemPoolTx<'a>, db_error> {
         let tx = tx_begin_immediate(&mut self.db)?;
         Ok(MemPoolTx::new(tx, &mut self.admitter))
@@ -468,6 +749,13 @@ impl MemPoolDB {
         Ok(rows)
     }
 
+    /// How many transactions are in the mempool?
+    pub fn get_num_txs(conn: &DBConn) -> Result<u64, db_error> {
+        let sql = "SELECT COUNT(*) FROM mempool";
+        let count = query_count(conn, sql, NO_PARAMS)?;
+        Ok(count as u64)
+    }
+
     /// Get all transactions at a specific block
     #[cfg(test)]
     pub fn get_num_tx_at_block(
@@ -519,8 +807,12 @@ impl MemPoolDB {
         let limit = 200;
         let offset = limit * curr_page;
 
-        let sql = "SELECT * FROM mempool WHERE origin_nonce = ?1 AND \
-        height > ?2 AND height <= ?3 ORDER BY sponsor_nonce ASC LIMIT ?4 OFFSET ?5";
+        // skip transactions that expire before the block after max_height
+        let sql = "SELECT mempool.* FROM mempool LEFT JOIN mempool_expirations \
+                   ON mempool.txid = mempool_expirations.txid \
+                   WHERE origin_nonce = ?1 AND height > ?2 AND height <= ?3 \
+                   AND (expire_height IS NULL OR expire_height > ?3) \
+                   ORDER BY sponsor_nonce ASC LIMIT ?4 OFFSET ?5";
         let args: &[&dyn ToSql] = &[
             &u64_to_sql(nonce)?,
             &min_height_sql_arg,
@@ -730,9 +1022,29 @@ impl MemPoolDB {
         };
 
         if !add_tx {
+            if let Some(ref prior_tx) = prior_tx {
+                MemPoolDB::log_rejected_conflict(
+                    tx,
+                    &txid,
+                    tx_fee,
+                    height,
+                    origin_address,
+                    origin_nonce,
+                    sponsor_address,
+                    sponsor_nonce,
+                    &prior_tx.txid,
+                )
+                .map_err(MemPoolRejection::DBError)?;
+            }
             return Err(MemPoolRejection::ConflictingNonceInMempool);
         }
 
+        if let Some(ref prior_tx) = prior_tx {
+            MemPoolDB::log_dropped_txs(tx, &[prior_tx.txid.clone()], &replace_reason, Some(&txid))
+                .map_err(MemPoolRejection::DBError)?;
+            MemPoolDB::forget_expiration(tx, &prior_tx.txid).map_err(MemPoolRejection::DBError)?;
+        }
+
         let sql = "INSERT OR REPLACE INTO mempool (
             txid,
             origin_address,
@@ -789,6 +1101,22 @@ impl MemPoolDB {
             event_observer.mempool_txs_dropped(txids, MemPoolDropReason::STALE_COLLECT);
         }
 
+        let sql = format!(
+            "INSERT INTO mempool_drops ({0}) SELECT {1} FROM mempool WHERE height < ?1",
+            MEMPOOL_DROP_LOG_COLUMNS, MEMPOOL_DROP_LOG_SOURCE_COLUMNS
+        );
+        let log_args: &[&dyn ToSql] = &[
+            &u64_to_sql(min_height)?,
+            &MemPoolDropReason::STALE_COLLECT.to_string(),
+            &u64_to_sql(get_epoch_time_secs())?,
+        ];
+        tx.execute(&sql, log_args)?;
+        MemPoolDB::trim_drop_log(tx)?;
+
+        let sql = "DELETE FROM mempool_expirations \
+                   WHERE txid IN (SELECT txid FROM mempool WHERE height < ?1)";
+        tx.execute(sql, args)?;
+
         let sql = "DELETE FROM mempool WHERE height < ?1";
 
         tx.execute(sql, args)?;
@@ -796,6 +1124,94 @@ impl MemPoolDB {
         Ok(())
     }
 
+    /// Remove transactions that can no longer be mined into the block after `chain_height`,
+    /// because they expire before it.
+    pub fn garbage_collect_expired(
+        tx: &mut MemPoolTx,
+        chain_height: u64,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<(), db_error> {
+        let sql = "SELECT txid FROM mempool_expirations WHERE expire_height <= ?1 \
+                   AND txid IN (SELECT txid FROM mempool)";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(chain_height)?];
+        let txids: Vec<Txid> = query_rows(tx, sql, args)?;
+        if txids.len() > 0 {
+            debug!(
+                "Remove {} transactions that expire at or before block height {}",
+                txids.len(),
+                chain_height
+            );
+            MemPoolDB::log_dropped_txs(tx, &txids, &MemPoolDropReason::EXPIRED, None)?;
+            for txid in txids.iter() {
+                tx.execute("DELETE FROM mempool WHERE txid = ?1", &[txid as &dyn ToSql])?;
+                MemPoolDB::forget_expiration(tx, txid)?;
+            }
+            if let Some(event_observer) = event_observer {
+                event_observer.mempool_txs_dropped(txids, MemPoolDropReason::EXPIRED);
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the origin and sponsor addresses of all transactions in the mempool
+    pub fn get_tx_addresses(conn: &DBConn) -> Result<Vec<StacksAddress>, db_error> {
+        let sql = "SELECT origin_address AS address FROM mempool \
+                   UNION SELECT sponsor_address AS address FROM mempool";
+        query_row_columns(conn, sql, NO_PARAMS, "address")
+    }
+
+    /// Remove transactions that can no longer be mined, because their origin or sponsor account
+    /// has already used their nonce.  `account_nonces` holds the confirmed nonces of (some of) the
+    /// accounts in the mempool.  Returns the number of transactions removed.
+    pub fn garbage_collect_stale_nonces(
+        tx: &mut MemPoolTx,
+        account_nonces: &HashMap<StacksAddress, u64>,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<u64, db_error> {
+        let sql = "SELECT txid FROM mempool WHERE (origin_address = ?1 AND origin_nonce < ?2) \
+                   OR (sponsor_address = ?1 AND sponsor_nonce < ?2)";
+        let mut txids: Vec<Txid> = vec![];
+        let mut seen = HashSet::new();
+        for (address, nonce) in account_nonces.iter() {
+            if *nonce == 0 {
+                continue;
+            }
+            let args: &[&dyn ToSql] = &[&address.to_string(), &u64_to_sql(*nonce)?];
+            for txid in query_rows::<Txid, _>(tx, sql, args)?.into_iter() {
+                if seen.insert(txid.clone()) {
+                    txids.push(txid);
+                }
+            }
+        }
+
+        if txids.len() > 0 {
+            debug!(
+                "Remove {} transactions whose nonces have already been used",
+                txids.len()
+            );
+            MemPoolDB::log_dropped_txs(tx, &txids, &MemPoolDropReason::STALE_NONCE, None)?;
+            for txid in txids.iter() {
+                tx.execute("DELETE FROM mempool WHERE txid = ?1", &[txid as &dyn ToSql])?;
+                MemPoolDB::forget_expiration(tx, txid)?;
+            }
+        }
+
+        let num_dropped = txids.len() as u64;
+        if let (Some(event_observer), true) = (event_observer, num_dropped > 0) {
+            event_observer.mempool_txs_dropped(txids, MemPoolDropReason::STALE_NONCE);
+        }
+        Ok(num_dropped)
+    }
+
+    /// Forget the expiration height of a transaction that is leaving the mempool
+    fn forget_expiration(tx: &mut MemPoolTx, txid: &Txid) -> Result<(), db_error> {
+        tx.execute(
+            "DELETE FROM mempool_expirations WHERE txid = ?1",
+            &[txid as &dyn ToSql],
+        )?;
+        Ok(())
+    }
+
     #[cfg(test)]
     pub fn clear_before_height(&mut self, min_height: u64) -> Result<(), db_error> {
         let mut tx = self.tx_begin()?;
@@ -840,6 +1256,7 @@ impl MemPoolDB {
         block_hash: &BlockHeaderHash,
         tx: &StacksTransaction,
         do_admission_checks: bool,
+        expire_height: Option<u64>,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
     ) -> Result<(), MemPoolRejection> {
         test_debug!(
@@ -869,6 +1286,16 @@ impl MemPoolDB {
             }
         };
 
+        if let Some(expire_height) = expire_height {
+            // the earliest block this transaction could be mined into is the next one
+            if expire_height <= height {
+                return Err(MemPoolRejection::TransactionExpired(
+                    expire_height,
+                    height + 1,
+                ));
+            }
+        }
+
         let txid = tx.txid();
         let mut tx_data = vec![];
         tx.consensus_serialize(&mut tx_data)
@@ -908,6 +1335,19 @@ impl MemPoolDB {
             event_observer,
         )?;
 
+        match expire_height {
+            Some(expire_height) => {
+                let sql = "INSERT OR REPLACE INTO mempool_expirations (txid, expire_height) VALUES (?1, ?2)";
+                let args: &[&dyn ToSql] = &[&txid, &u64_to_sql(expire_height)?];
+                mempool_tx.execute(sql, args)
+            }
+            None => mempool_tx.execute(
+                "DELETE FROM mempool_expirations WHERE txid = ?1",
+                &[&txid as &dyn ToSql],
+            ),
+        }
+        .map_err(|e| MemPoolRejection::DBError(db_error::SqliteError(e)))?;
+
         if let Err(e) = monitoring::mempool_accepted(&txid, &chainstate.root_path) {
             warn!("Failed to monitor TX receive: {:?}", e; "txid" => %txid);
         }
@@ -923,19 +1363,47 @@ impl MemPoolDB {
         block_hash: &BlockHeaderHash,
         tx: &StacksTransaction,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<(), MemPoolRejection> {
+        self.submit_with_expiration(
+            chainstate,
+            consensus_hash,
+            block_hash,
+            tx,
+            None,
+            event_observer,
+        )
+    }
+
+    /// One-shot submit of a transaction that must not be mined into a block above
+    /// `expire_height`, if given.  The transaction is rejected if it has already expired.
+    pub fn submit_with_expiration(
+        &mut self,
+        chainstate: &mut StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        tx: &StacksTransaction,
+        expire_height: Option<u64>,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
     ) -> Result<(), MemPoolRejection> {
         let mut mempool_tx = self.tx_begin().map_err(MemPoolRejection::DBError)?;
-        MemPoolDB::tx_submit(
+        let result = MemPoolDB::tx_submit(
             &mut mempool_tx,
             chainstate,
             consensus_hash,
             block_hash,
             tx,
             true,
+            expire_height,
             event_observer,
-        )?;
-        mempool_tx.commit().map_err(MemPoolRejection::DBError)?;
-        Ok(())
+        );
+        match result {
+            // a conflicting transaction only writes its drop log entry, which we keep
+            Ok(_) | Err(MemPoolRejection::ConflictingNonceInMempool) => {
+                mempool_tx.commit().map_err(MemPoolRejection::DBError)?;
+            }
+            Err(_) => {}
+        }
+        result
     }
 
     /// Directly submit to the mempool, and don't do any admissions checks.
@@ -958,22 +1426,265 @@ impl MemPoolDB {
             &tx,
             false,
             None,
+            None,
         )?;
         mempool_tx.commit().map_err(MemPoolRejection::DBError)?;
         Ok(())
     }
 
-    /// Drop transactions from the mempool
-    pub fn drop_txs(&mut self, txids: &[Txid]) -> Result<(), db_error> {
+    /// Estimate a transaction's execution cost from the costs of past calls to the same
+    /// contract function.  Returns None if there is no history to go on.
+    pub fn estimate_tx_cost(
+        &self,
+        tx: &StacksTransaction,
+    ) -> Result<Option<ExecutionCost>, db_error> {
+        PessimisticEstimator::estimate_tx(&self.db, tx)
+    }
+
+    /// Record the execution costs actually consumed by contract-calls, so they can inform
+    /// future estimates.
+    pub fn record_execution_costs(
+        &mut self,
+        observations: &[(QualifiedContractIdentifier, ClarityName, ExecutionCost)],
+    ) -> Result<(), db_error> {
+        if observations.len() == 0 {
+            return Ok(());
+        }
         let mempool_tx = self.tx_begin()?;
+        for (contract_id, function_name, cost) in observations.iter() {
+            PessimisticEstimator::record(&mempool_tx, contract_id, function_name, cost)?;
+        }
+        mempool_tx.commit()?;
+        Ok(())
+    }
+
+    /// Drop transactions from the mempool, recording why in the drop log
+    pub fn drop_txs(&mut self, txids: &[Txid], reason: &MemPoolDropReason) -> Result<(), db_error> {
+        let mut mempool_tx = self.tx_begin()?;
+        MemPoolDB::log_dropped_txs(&mut mempool_tx, txids, reason, None)?;
         let sql = "DELETE FROM mempool WHERE txid = ?";
         for txid in txids.iter() {
             mempool_tx.execute(sql, &[txid])?;
+            MemPoolDB::forget_expiration(&mut mempool_tx, txid)?;
         }
         mempool_tx.commit()?;
         Ok(())
     }
 
+    /// Record that the given transactions are being dropped from the mempool, and why.
+    /// Must be called before they are deleted, since the log entries are copied from their
+    /// mempool rows.  Transactions not in the mempool are ignored.
+    fn log_dropped_txs(
+        tx: &mut MemPoolTx,
+        txids: &[Txid],
+        reason: &MemPoolDropReason,
+        replaced_by: Option<&Txid>,
+    ) -> Result<(), db_error> {
+        let sql = format!(
+            "INSERT INTO mempool_drops ({0}, replaced_by) SELECT {1}, ?4 FROM mempool WHERE txid = ?1",
+            MEMPOOL_DROP_LOG_COLUMNS, MEMPOOL_DROP_LOG_SOURCE_COLUMNS
+        );
+        let reason = reason.to_string();
+        let now = u64_to_sql(get_epoch_time_secs())?;
+        for txid in txids.iter() {
+            let args: &[&dyn ToSql] = &[txid, &reason, &now, &replaced_by];
+            tx.execute(&sql, args)?;
+        }
+        MemPoolDB::trim_drop_log(tx)
+    }
+
+    /// Record that a transaction was rejected because `pending_txid`, which has the same origin
+    /// or sponsor nonce and at least the same fee, was already pending in the same fork.  A
+    /// transaction that keeps being resubmitted is only recorded once per pending transaction.
+    fn log_rejected_conflict(
+        tx: &mut MemPoolTx,
+        txid: &Txid,
+        tx_fee: u64,
+        height: u64,
+        origin_address: &StacksAddress,
+        origin_nonce: u64,
+        sponsor_address: &StacksAddress,
+        sponsor_nonce: u64,
+        pending_txid: &Txid,
+    ) -> Result<(), db_error> {
+        let sql = format!(
+            "INSERT INTO mempool_drops ({0}, replaced_by) SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10 \
+             WHERE NOT EXISTS (SELECT 1 FROM mempool_drops WHERE txid = ?1 AND reason = ?2 AND replaced_by = ?10)",
+            MEMPOOL_DROP_LOG_COLUMNS
+        );
+        let args: &[&dyn ToSql] = &[
+            txid,
+            &MemPoolDropReason::CONFLICTING_NONCE.to_string(),
+            &u64_to_sql(get_epoch_time_secs())?,
+            &u64_to_sql(tx_fee)?,
+            &u64_to_sql(height)?,
+            &origin_address.to_string(),
+            &u64_to_sql(origin_nonce)?,
+            &sponsor_address.to_string(),
+            &u64_to_sql(sponsor_nonce)?,
+            pending_txid,
+        ];
+        tx.execute(&sql, args)?;
+        MemPoolDB::trim_drop_log(tx)
+    }
+
+    /// Discard the oldest drop log entries beyond MEMPOOL_MAX_DROP_LOG_ENTRIES
+    fn trim_drop_log(tx: &mut MemPoolTx) -> Result<(), db_error> {
+        let sql =
+            "DELETE FROM mempool_drops WHERE rowid <= (SELECT MAX(rowid) FROM mempool_drops) - ?1";
+        let args: &[&dyn ToSql] = &[&u64_to_sql(MEMPOOL_MAX_DROP_LOG_ENTRIES)?];
+        tx.execute(sql, args)?;
+        Ok(())
+    }
+
+    /// Get the drop log entries for a transaction, oldest first.  A transaction can be dropped
+    /// more than once if it was resubmitted.
+    pub fn get_drop_records(
+        conn: &DBConn,
+        txid: &Txid,
+    ) -> Result<Vec<MemPoolDropRecord>, db_error> {
+        let sql = "SELECT * FROM mempool_drops WHERE txid = ?1 ORDER BY rowid ASC";
+        query_rows(conn, sql, &[txid as &dyn ToSql])
+    }
+
+    /// Get the drop log entries of the transactions that lost to a pending transaction: those with
+    /// its origin nonce or sponsor nonce that were replaced by a higher-fee transaction, or that
+    /// were rejected because a transaction with at least their fee was pending.  Oldest first.
+    pub fn get_conflict_records(
+        conn: &DBConn,
+        pending: &MemPoolTxMetadata,
+    ) -> Result<Vec<MemPoolDropRecord>, db_error> {
+        let sql = "SELECT * FROM mempool_drops WHERE replaced_by IS NOT NULL AND txid != ?1 \
+                   AND ((origin_address = ?2 AND origin_nonce = ?3) OR (sponsor_address = ?4 AND sponsor_nonce = ?5)) \
+                   ORDER BY rowid ASC";
+        let args: &[&dyn ToSql] = &[
+            &pending.txid,
+            &pending.origin_address.to_string(),
+            &u64_to_sql(pending.origin_nonce)?,
+            &pending.sponsor_address.to_string(),
+            &u64_to_sql(pending.sponsor_nonce)?,
+        ];
+        query_rows(conn, sql, args)
+    }
+
+    /// Get every transaction in the mempool, across all forks, in origin nonce order so that
+    /// chained transactions can be re-admitted one after another.
+    pub fn get_snapshot_txs(conn: &DBConn) -> Result<Vec<StacksTransaction>, db_error> {
+        let sql = "SELECT * FROM mempool ORDER BY origin_address ASC, origin_nonce ASC";
+        let rows = query_rows::<MemPoolTxInfo, _>(conn, sql, NO_PARAMS)?;
+        Ok(rows.into_iter().map(|txinfo| txinfo.tx).collect())
+    }
+
+    /// Get up to `limit` transactions of a mempool snapshot, in the same order as
+    /// `get_snapshot_txs()`.  If `after` is given, only transactions that come after that origin
+    /// address and nonce are loaded.
+    pub fn get_snapshot_txs_page(
+        conn: &DBConn,
+        after: Option<&(StacksAddress, u64)>,
+        limit: u32,
+    ) -> Result<Vec<MemPoolTxInfo>, db_error> {
+        match after {
+            Some((origin_address, origin_nonce)) => {
+                let sql = "SELECT * FROM mempool WHERE origin_address > ?1 OR (origin_address = ?1 AND origin_nonce > ?2) ORDER BY origin_address ASC, origin_nonce ASC LIMIT ?3";
+                let args: &[&dyn ToSql] = &[
+                    &origin_address.to_string(),
+                    &u64_to_sql(*origin_nonce)?,
+                    &limit,
+                ];
+                query_rows(conn, sql, args)
+            }
+            None => {
+                let sql =
+                    "SELECT * FROM mempool ORDER BY origin_address ASC, origin_nonce ASC LIMIT ?1";
+                query_rows(conn, sql, &[&limit as &dyn ToSql])
+            }
+        }
+    }
+
+    /// Get up to `limit` of the pending transactions that the given address originates or
+    /// sponsors, across all forks, ordered by the address's nonce in each transaction and then by
+    /// txid.  If `after` is given, only transactions that come after that nonce and txid are
+    /// loaded.
+    pub fn get_address_txs_page(
+        conn: &DBConn,
+        addr: &StacksAddress,
+        after: Option<&(u64, Txid)>,
+        limit: u32,
+    ) -> Result<Vec<MemPoolTxMetadata>, db_error> {
+        let select = "SELECT txid, origin_address, origin_nonce, sponsor_address, sponsor_nonce, \
+                      tx_fee, length, consensus_hash, block_header_hash, height, accept_time \
+                      FROM mempool WHERE (origin_address = ?1 OR sponsor_address = ?1)";
+        let nonce_expr = "CASE WHEN origin_address = ?1 THEN origin_nonce ELSE sponsor_nonce END";
+        match after {
+            Some((nonce, txid)) => {
+                let sql = format!(
+                    "{0} AND ({1} > ?2 OR ({1} = ?2 AND txid > ?3)) ORDER BY {1} ASC, txid ASC LIMIT ?4",
+                    select, nonce_expr
+                );
+                let args: &[&dyn ToSql] = &[&addr.to_string(), &u64_to_sql(*nonce)?, txid, &limit];
+                query_rows(conn, &sql, args)
+            }
+            None => {
+                let sql = format!(
+                    "{0} ORDER BY {1} ASC, txid ASC LIMIT ?2",
+                    select, nonce_expr
+                );
+                let args: &[&dyn ToSql] = &[&addr.to_string(), &limit];
+                query_rows(conn, &sql, args)
+            }
+        }
+    }
+
+    /// Write a snapshot of the mempool to `fd`, to be loaded into another node's mempool with
+    /// `read_snapshot()` and `import_txs()`.  Returns the number of transactions written.
+    pub fn export_snapshot<W: Write>(conn: &DBConn, fd: &mut W) -> Result<u64, db_error> {
+        let txs = MemPoolDB::get_snapshot_txs(conn)?;
+        serde_json::to_writer(fd, &encode_mempool_snapshot(&txs))
+            .map_err(|e| db_error::Other(format!("Failed to write mempool snapshot: {:?}", &e)))?;
+        Ok(txs.len() as u64)
+    }
+
+    /// Read a mempool snapshot written by `export_snapshot()`
+    pub fn read_snapshot<R: Read>(fd: &mut R) -> Result<Vec<StacksTransaction>, db_error> {
+        let hex_txs: Vec<String> = serde_json::from_reader(fd)
+            .map_err(|e| db_error::Other(format!("Failed to read mempool snapshot: {:?}", &e)))?;
+        decode_mempool_snapshot(&hex_txs)
+            .map_err(|e| db_error::Other(format!("Invalid mempool snapshot: {:?}", &e)))
+    }
+
+    /// Submit each of the given transactions at the given chain tip, running the usual admission
+    /// checks.  Transactions that fail admission are reported, not treated as errors.
+    pub fn import_txs(
+        &mut self,
+        chainstate: &mut StacksChainState,
+        consensus_hash: &ConsensusHash,
+        block_hash: &BlockHeaderHash,
+        txs: Vec<StacksTransaction>,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<MemPoolImportReport, db_error> {
+        let mut report = MemPoolImportReport::default();
+        for tx in txs.into_iter() {
+            let txid = tx.txid();
+            if self.has_tx(&txid) {
+                report.already_present += 1;
+                continue;
+            }
+            match self.submit(chainstate, consensus_hash, block_hash, &tx, event_observer) {
+                Ok(_) => {
+                    report.accepted += 1;
+                }
+                Err(MemPoolRejection::DBError(e)) => {
+                    return Err(e);
+                }
+                Err(e) => {
+                    debug!("Mempool snapshot transaction rejected: {:?}", &e; "txid" => %txid);
+                    report.rejected.push((txid, e));
+                }
+            }
+        }
+        Ok(report)
+    }
+
     #[cfg(test)]
     pub fn dump_txs(&self) {
         let sql = "SELECT * FROM mempool";
@@ -1001,12 +1712,16 @@ impl MemPoolDB {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use address::AddressHashMode;
     use burnchains::Address;
+    use burnchains::Txid;
     use chainstate::burn::ConsensusHash;
     use chainstate::stacks::db::test::chainstate_path;
     use chainstate::stacks::db::test::instantiate_chainstate;
     use chainstate::stacks::db::test::instantiate_chainstate_with_balances;
+    use chainstate::stacks::miner::test::make_user_stacks_transfer;
     use chainstate::stacks::test::codec_all_transactions;
     use chainstate::stacks::{
         db::blocks::MemPoolRejection, db::StacksChainState, index::MarfTrieId, CoinbasePayload,
@@ -1022,7 +1737,7 @@ mod tests {
     use core::FIRST_BURNCHAIN_CONSENSUS_HASH;
     use core::FIRST_STACKS_BLOCK_HASH;
     use net::Error as NetError;
-    use util::db::{DBConn, FromRow};
+    use util::db::{query_row, DBConn, FromRow};
     use util::hash::Hash160;
     use util::secp256k1::MessageSignature;
     use util::{hash::hex_bytes, hash::to_hex, hash::*, log, secp256k1::*, strings::StacksString};
@@ -1046,7 +1761,9 @@ mod tests {
         chainstate::stacks::db::StacksHeaderInfo, util::vrf::VRFProof, vm::costs::ExecutionCost,
     };
 
+    use super::BlockAssemblyCheckpoint;
     use super::MemPoolDB;
+    use super::MemPoolDropReason;
 
     const FOO_CONTRACT: &'static str = "(define-public (foo) (ok 1))
                                         (define-public (bar (x uint)) (ok x))";
@@ -1706,6 +2423,23 @@ mod tests {
             // was NOT replaced
             assert!(MemPoolDB::db_has_tx(&mempool_tx, &old_txid).unwrap());
             assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txid).unwrap());
+
+            // but the rejected conflict was logged against the pending transaction
+            let drop_records = MemPoolDB::get_drop_records(&mempool_tx, &txid).unwrap();
+            assert_eq!(drop_records.len(), 1);
+            assert_eq!(drop_records[0].reason, "ConflictingNonce");
+            assert_eq!(drop_records[0].tx_fee, 122);
+            assert_eq!(drop_records[0].replaced_by, Some(old_txid.clone()));
+
+            // the pending transaction lost to nothing, and beat both the transaction it replaced
+            // and the one that was rejected
+            let pending = MemPoolDB::get_tx(&mempool_tx, &old_txid).unwrap().unwrap();
+            let conflicts =
+                MemPoolDB::get_conflict_records(&mempool_tx, &pending.metadata).unwrap();
+            assert_eq!(conflicts.len(), 2);
+            assert_eq!(conflicts[0].reason, "ReplaceByFee");
+            assert_eq!(conflicts[0].replaced_by, Some(old_txid.clone()));
+            assert_eq!(conflicts[1].txid, txid);
         }
         mempool_tx.commit().unwrap();
 
@@ -1758,6 +2492,473 @@ mod tests {
         assert_eq!(txs.len(), 0);
     }
 
+    #[test]
+    fn mempool_snapshot_export_import() {
+        let mut chainstate =
+            instantiate_chainstate(false, 0x80000000, "mempool_snapshot_export_import");
+        let chainstate_path = chainstate_path("mempool_snapshot_export_import");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (i, tx) in txs.iter().enumerate() {
+            // make sure each address is unique per tx (not the case in codec_all_transactions)
+            let origin_address = StacksAddress {
+                version: 22,
+                bytes: Hash160::from_data(&i.to_be_bytes()),
+            };
+            let sponsor_address = StacksAddress {
+                version: 22,
+                bytes: Hash160::from_data(&(i + 1).to_be_bytes()),
+            };
+            let origin_nonce = tx.get_origin_nonce();
+            let sponsor_nonce = tx.get_sponsor_nonce().unwrap_or(origin_nonce);
+
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                tx.txid(),
+                tx.serialize_to_vec(),
+                tx.get_tx_fee(),
+                100,
+                &origin_address,
+                origin_nonce,
+                &sponsor_address,
+                sponsor_nonce,
+                None,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut snapshot = vec![];
+        let num_exported = MemPoolDB::export_snapshot(mempool.conn(), &mut snapshot).unwrap();
+        assert_eq!(num_exported, txs.len() as u64);
+
+        let mut imported_txs = MemPoolDB::read_snapshot(&mut &snapshot[..]).unwrap();
+        let mut expected_txs = txs.clone();
+        imported_txs.sort_by_key(|tx| tx.txid().to_hex());
+        expected_txs.sort_by_key(|tx| tx.txid().to_hex());
+        assert_eq!(imported_txs, expected_txs);
+
+        // paging through the snapshot gives back the same transactions, in the same order
+        let all_txs = MemPoolDB::get_snapshot_txs(mempool.conn()).unwrap();
+        let mut paged_txs = vec![];
+        let mut after = None;
+        loop {
+            let page = MemPoolDB::get_snapshot_txs_page(mempool.conn(), after.as_ref(), 7).unwrap();
+            assert!(page.len() <= 7);
+            if page.is_empty() {
+                break;
+            }
+            let last = page.last().unwrap();
+            after = Some((
+                last.metadata.origin_address.clone(),
+                last.metadata.origin_nonce,
+            ));
+            paged_txs.extend(page.into_iter().map(|txinfo| txinfo.tx));
+        }
+        assert_eq!(paged_txs, all_txs);
+
+        // everything in the snapshot is already in this mempool
+        let report = mempool
+            .import_txs(
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                imported_txs,
+                None,
+            )
+            .unwrap();
+        assert_eq!(report.accepted, 0);
+        assert_eq!(report.already_present, txs.len() as u64);
+        assert!(report.rejected.is_empty());
+
+        // new transactions still have to pass admission
+        let mut new_tx = txs[0].clone();
+        new_tx.set_tx_fee(txs[0].get_tx_fee() + 1);
+        let report = mempool
+            .import_txs(
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                vec![new_tx.clone()],
+                None,
+            )
+            .unwrap();
+        assert_eq!(report.accepted, 0);
+        assert_eq!(report.already_present, 0);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, new_tx.txid());
+        match report.rejected[0].1 {
+            MemPoolRejection::NoSuchChainTip(..) => {}
+            ref e => panic!("Unexpected rejection: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn mempool_stale_nonce_gc() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_stale_nonce_gc");
+        let chainstate_path = chainstate_path("mempool_stale_nonce_gc");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let txs: Vec<_> = txs.into_iter().take(3).collect();
+
+        let origin_address = |i: usize| StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&i.to_be_bytes()),
+        };
+        let sponsor_address = |i: usize| StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&(i + 1000).to_be_bytes()),
+        };
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (i, tx) in txs.iter().enumerate() {
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                tx.txid(),
+                tx.serialize_to_vec(),
+                tx.get_tx_fee(),
+                100,
+                &origin_address(i),
+                10,
+                &sponsor_address(i),
+                20,
+                None,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let mut addresses = MemPoolDB::get_tx_addresses(mempool.conn()).unwrap();
+        addresses.sort_by_key(|addr| addr.to_string());
+        let mut expected_addresses: Vec<_> = (0..3)
+            .map(origin_address)
+            .chain((0..3).map(sponsor_address))
+            .collect();
+        expected_addresses.sort_by_key(|addr| addr.to_string());
+        assert_eq!(addresses, expected_addresses);
+
+        let mut account_nonces = HashMap::new();
+        // tx 0's origin nonce has been used
+        account_nonces.insert(origin_address(0), 11);
+        // tx 1's origin nonce has not
+        account_nonces.insert(origin_address(1), 10);
+        // tx 2's sponsor nonce has been used
+        account_nonces.insert(sponsor_address(2), 21);
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        let num_dropped =
+            MemPoolDB::garbage_collect_stale_nonces(&mut mempool_tx, &account_nonces, None)
+                .unwrap();
+        assert_eq!(num_dropped, 2);
+
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txs[0].txid()).unwrap());
+        assert!(MemPoolDB::db_has_tx(&mempool_tx, &txs[1].txid()).unwrap());
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txs[2].txid()).unwrap());
+
+        for dropped_tx in [&txs[0], &txs[2]].iter() {
+            let drops = MemPoolDB::get_drop_records(&mempool_tx, &dropped_tx.txid()).unwrap();
+            assert_eq!(drops.len(), 1);
+            assert_eq!(drops[0].reason, "StaleNonce");
+        }
+
+        // nothing left to drop
+        let num_dropped =
+            MemPoolDB::garbage_collect_stale_nonces(&mut mempool_tx, &account_nonces, None)
+                .unwrap();
+        assert_eq!(num_dropped, 0);
+        mempool_tx.commit().unwrap();
+    }
+
+    #[test]
+    fn mempool_address_txs_page() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_address_txs_page");
+        let chainstate_path = chainstate_path("mempool_address_txs_page");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let txs = codec_all_transactions(
+            &TransactionVersion::Testnet,
+            0x80000000,
+            &TransactionAnchorMode::Any,
+            &TransactionPostConditionMode::Allow,
+        );
+        let txs: Vec<_> = txs.into_iter().take(4).collect();
+
+        let make_address = |i: usize| StacksAddress {
+            version: 22,
+            bytes: Hash160::from_data(&i.to_be_bytes()),
+        };
+        let wallet = make_address(0);
+
+        // (origin, origin nonce, sponsor, sponsor nonce) of each transaction.  The wallet
+        // originates tx 0 and tx 2, sponsors tx 1, and has nothing to do with tx 3.
+        let participants = vec![
+            (wallet.clone(), 5, wallet.clone(), 5),
+            (make_address(1), 0, wallet.clone(), 2),
+            (wallet.clone(), 7, make_address(2), 0),
+            (make_address(3), 0, make_address(3), 0),
+        ];
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (tx, (origin, origin_nonce, sponsor, sponsor_nonce)) in
+            txs.iter().zip(participants.iter())
+        {
+            MemPoolDB::try_add_tx(
+                &mut mempool_tx,
+                &mut chainstate,
+                &ConsensusHash([0x1; 20]),
+                &BlockHeaderHash([0x2; 32]),
+                tx.txid(),
+                tx.serialize_to_vec(),
+                tx.get_tx_fee(),
+                100,
+                origin,
+                *origin_nonce,
+                sponsor,
+                *sponsor_nonce,
+                None,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        // ordered by the wallet's nonce in each transaction
+        let all = MemPoolDB::get_address_txs_page(mempool.conn(), &wallet, None, 10).unwrap();
+        let txids: Vec<_> = all.iter().map(|md| md.txid.clone()).collect();
+        assert_eq!(txids, vec![txs[1].txid(), txs[0].txid(), txs[2].txid()]);
+
+        let first = MemPoolDB::get_address_txs_page(mempool.conn(), &wallet, None, 2).unwrap();
+        assert_eq!(first.len(), 2);
+        let after = (5, first[1].txid.clone());
+        let rest =
+            MemPoolDB::get_address_txs_page(mempool.conn(), &wallet, Some(&after), 2).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].txid, txs[2].txid());
+
+        let none =
+            MemPoolDB::get_address_txs_page(mempool.conn(), &make_address(4), None, 10).unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn mempool_tx_expiration() {
+        let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_tx_expiration");
+        let chainstate_path = chainstate_path("mempool_tx_expiration");
+        let mut mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let recipient = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0xff; 20]),
+        });
+        let keys: Vec<_> = (0..3).map(|_| StacksPrivateKey::new()).collect();
+        let txs: Vec<_> = keys
+            .iter()
+            .map(|key| make_user_stacks_transfer(key, 0, 200, &recipient, 1))
+            .collect();
+        let expirations = vec![Some(5), None, Some(1)];
+
+        // the first block a transaction submitted now could be mined into is at height 1
+        match mempool.submit_with_expiration(
+            &mut chainstate,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &txs[0],
+            Some(0),
+            None,
+        ) {
+            Err(MemPoolRejection::TransactionExpired(0, 1)) => {}
+            res => panic!("Unexpected result: {:?}", res),
+        }
+        assert!(!mempool.has_tx(&txs[0].txid()));
+
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        for (tx, expire_height) in txs.iter().zip(expirations.iter()) {
+            MemPoolDB::tx_submit(
+                &mut mempool_tx,
+                &mut chainstate,
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+                tx,
+                false,
+                *expire_height,
+                None,
+            )
+            .unwrap();
+        }
+        mempool_tx.commit().unwrap();
+
+        let conn = mempool.conn();
+        assert_eq!(
+            MemPoolDB::get_tx_expire_height(conn, &txs[0].txid()).unwrap(),
+            Some(5)
+        );
+        assert_eq!(
+            MemPoolDB::get_tx_expire_height(conn, &txs[1].txid()).unwrap(),
+            None
+        );
+        assert!(!MemPoolDB::is_tx_expired(conn, &txs[0].txid(), 5).unwrap());
+        assert!(MemPoolDB::is_tx_expired(conn, &txs[0].txid(), 6).unwrap());
+        assert!(!MemPoolDB::is_tx_expired(conn, &txs[1].txid(), 1000).unwrap());
+
+        // miners skip transactions that would be expired in the next block
+        let mut candidates = vec![];
+        mempool
+            .iterate_candidates::<_, ChainstateError>(1, |available_txs| {
+                for txinfo in available_txs.into_iter() {
+                    candidates.push(txinfo.metadata.txid);
+                }
+                Ok(())
+            })
+            .unwrap();
+        candidates.sort_by_key(|txid| txid.to_hex());
+        let mut expected = vec![txs[0].txid(), txs[1].txid()];
+        expected.sort_by_key(|txid| txid.to_hex());
+        assert_eq!(candidates, expected);
+
+        let mut candidates: Vec<_> = MemPoolDB::get_candidate_txs(conn, 1)
+            .unwrap()
+            .into_iter()
+            .map(|txinfo| txinfo.metadata.txid)
+            .collect();
+        candidates.sort_by_key(|txid| txid.to_hex());
+        assert_eq!(candidates, expected);
+
+        // replacing a transaction by fee forgets its expiration height
+        let replacement = make_user_stacks_transfer(&keys[2], 0, 300, &recipient, 1);
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        MemPoolDB::tx_submit(
+            &mut mempool_tx,
+            &mut chainstate,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &replacement,
+            false,
+            None,
+            None,
+        )
+        .unwrap();
+        mempool_tx.commit().unwrap();
+        let conn = mempool.conn();
+        assert!(!mempool.has_tx(&txs[2].txid()));
+        assert_eq!(
+            MemPoolDB::get_tx_expire_height(conn, &txs[2].txid()).unwrap(),
+            None
+        );
+
+        // garbage-collecting at height 5 drops everything that can't make it into block 6
+        let mut mempool_tx = mempool.tx_begin().unwrap();
+        MemPoolDB::garbage_collect_expired(&mut mempool_tx, 4, None).unwrap();
+        assert!(MemPoolDB::db_has_tx(&mempool_tx, &txs[0].txid()).unwrap());
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txs[2].txid()).unwrap());
+
+        MemPoolDB::garbage_collect_expired(&mut mempool_tx, 5, None).unwrap();
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txs[0].txid()).unwrap());
+        assert!(MemPoolDB::db_has_tx(&mempool_tx, &txs[1].txid()).unwrap());
+        assert_eq!(
+            MemPoolDB::get_tx_expire_height(&mempool_tx, &txs[0].txid()).unwrap(),
+            None
+        );
+
+        let drops = MemPoolDB::get_drop_records(&mempool_tx, &txs[0].txid()).unwrap();
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].reason, MemPoolDropReason::EXPIRED.to_string());
+
+        // garbage-collecting stale transactions forgets their expiration heights too
+        MemPoolDB::tx_submit(
+            &mut mempool_tx,
+            &mut chainstate,
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+            &make_user_stacks_transfer(&keys[0], 1, 200, &recipient, 1),
+            false,
+            Some(10),
+            None,
+        )
+        .unwrap();
+        MemPoolDB::garbage_collect(&mut mempool_tx, 1, None).unwrap();
+        let num_expirations: i64 = query_row(
+            &mempool_tx,
+            "SELECT COUNT(*) FROM mempool_expirations",
+            rusqlite::NO_PARAMS,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(num_expirations, 0);
+        mempool_tx.commit().unwrap();
+    }
+
+    #[test]
+    fn mempool_assembly_checkpoint() {
+        let _chainstate = instantiate_chainstate(false, 0x80000000, "mempool_assembly_checkpoint");
+        let chainstate_path = chainstate_path("mempool_assembly_checkpoint");
+        let mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let parent_1 = StacksBlockId([0x11; 32]);
+        let parent_2 = StacksBlockId([0x22; 32]);
+        let mut checkpoint = BlockAssemblyCheckpoint {
+            parent_block_id: parent_1.clone(),
+            txids: vec![],
+            cost: ExecutionCost::zero(),
+            checkpoint_time: 1000,
+        };
+        assert_eq!(
+            MemPoolDB::get_assembly_checkpoint(mempool.conn(), &parent_1).unwrap(),
+            None
+        );
+
+        MemPoolDB::save_assembly_checkpoint(mempool.conn(), &checkpoint).unwrap();
+        assert_eq!(
+            MemPoolDB::get_assembly_checkpoint(mempool.conn(), &parent_1).unwrap(),
+            Some(checkpoint.clone())
+        );
+
+        // later checkpoints off of the same parent replace earlier ones
+        checkpoint.txids = vec![Txid([0x01; 32]), Txid([0x02; 32])];
+        checkpoint.cost.runtime = 1234;
+        checkpoint.checkpoint_time = 1010;
+        MemPoolDB::save_assembly_checkpoint(mempool.conn(), &checkpoint).unwrap();
+        assert_eq!(
+            MemPoolDB::get_assembly_checkpoint(mempool.conn(), &parent_1).unwrap(),
+            Some(checkpoint.clone())
+        );
+
+        // ...and so do checkpoints off of another parent
+        let other_checkpoint = BlockAssemblyCheckpoint {
+            parent_block_id: parent_2.clone(),
+            txids: vec![Txid([0x03; 32])],
+            cost: ExecutionCost::zero(),
+            checkpoint_time: 1020,
+        };
+        MemPoolDB::save_assembly_checkpoint(mempool.conn(), &other_checkpoint).unwrap();
+        assert_eq!(
+            MemPoolDB::get_assembly_checkpoint(mempool.conn(), &parent_1).unwrap(),
+            None
+        );
+        assert_eq!(
+            MemPoolDB::get_assembly_checkpoint(mempool.conn(), &parent_2).unwrap(),
+            Some(other_checkpoint)
+        );
+    }
+
     #[test]
     fn mempool_db_test_rbf() {
         let mut chainstate = instantiate_chainstate(false, 0x80000000, "mempool_db_test_rbf");
@@ -1907,5 +3108,29 @@ mod tests {
         assert_eq!(tx_info.metadata, tx_info_after);
         assert_eq!(tx_info.metadata.len, second_len);
         assert_eq!(tx_info.metadata.tx_fee, 124);
+
+        // the replaced transaction is in the drop log
+        let drops = MemPoolDB::get_drop_records(&mempool_tx, &old_txid).unwrap();
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].txid, old_txid);
+        assert_eq!(drops[0].reason, "ReplaceByFee");
+        assert_eq!(drops[0].tx_fee, old_tx_fee);
+        assert_eq!(drops[0].origin_address, origin_address);
+        assert_eq!(drops[0].origin_nonce, origin_nonce);
+        assert_eq!(drops[0].replaced_by, Some(txid.clone()));
+        assert!(MemPoolDB::get_drop_records(&mempool_tx, &txid)
+            .unwrap()
+            .is_empty());
+
+        // so is a garbage-collected transaction
+        MemPoolDB::garbage_collect(&mut mempool_tx, height + 1, None).unwrap();
+        assert!(!MemPoolDB::db_has_tx(&mempool_tx, &txid).unwrap());
+
+        let drops = MemPoolDB::get_drop_records(&mempool_tx, &txid).unwrap();
+        assert_eq!(drops.len(), 1);
+        assert_eq!(drops[0].reason, "StaleGarbageCollect");
+        assert_eq!(drops[0].tx_fee, 124);
+        assert_eq!(drops[0].block_height, height);
+        assert_eq!(drops[0].replaced_by, None);
     }
 }
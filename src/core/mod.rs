@@ -26,6 +26,7 @@ use crate::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash};
 
 pub use self::mempool::MemPoolDB;
 
+pub mod cost_estimates;
 pub mod mempool;
 
 // fork set identifier -- to be mixed with the consensus hash (encodes the version)
@@ -32,14 +32,17 @@ extern crate tini;
 #[macro_use]
 extern crate lazy_static;
 extern crate integer_sqrt;
+extern crate lz4_flex;
 extern crate mio;
 extern crate percent_encoding;
 extern crate regex;
 extern crate ripemd160;
+extern crate rustls;
 extern crate sha2;
 extern crate sha3;
 extern crate time;
 extern crate url;
+extern crate webpki;
 
 #[macro_use(o, slog_log, slog_trace, slog_debug, slog_info, slog_warn, slog_error)]
 extern crate slog;
@@ -56,6 +59,9 @@ extern crate serde_derive;
 #[macro_use]
 extern crate serde_json;
 
+#[cfg(feature = "async_net")]
+extern crate tokio;
+
 #[cfg(test)]
 #[macro_use]
 extern crate assert_json_diff;
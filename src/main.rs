@@ -23,6 +23,8 @@
 #[macro_use]
 extern crate blockstack_lib;
 extern crate rusqlite;
+#[macro_use]
+extern crate serde_json;
 
 #[macro_use(o, slog_log, slog_trace, slog_debug, slog_info, slog_warn, slog_error)]
 extern crate slog;
@@ -43,11 +45,13 @@ use blockstack_lib::chainstate::burn::ConsensusHash;
 use blockstack_lib::chainstate::stacks::db::ChainStateBootData;
 use blockstack_lib::chainstate::stacks::index::marf::MarfConnection;
 use blockstack_lib::chainstate::stacks::index::marf::MARF;
+use blockstack_lib::chainstate::stacks::index::node::TriePath;
 use blockstack_lib::chainstate::stacks::*;
 use blockstack_lib::codec::StacksMessageCodec;
+use blockstack_lib::types::chainstate::MARFValue;
 use blockstack_lib::types::chainstate::{BlockHeaderHash, BurnchainHeaderHash, PoxId};
 use blockstack_lib::types::chainstate::{StacksBlockHeader, StacksBlockId};
-use blockstack_lib::types::proof::ClarityMarfTrieId;
+use blockstack_lib::types::proof::{ClarityMarfTrieId, TrieHash, TrieMerkleProof};
 use blockstack_lib::util::get_epoch_time_ms;
 use blockstack_lib::util::hash::{hex_bytes, to_hex};
 use blockstack_lib::util::log;
@@ -182,6 +186,74 @@ fn main() {
         process::exit(0);
     }
 
+    if argv[1] == "tx-decode" || argv[1] == "tx-verify" || argv[1] == "tx-id" {
+        if argv.len() > 2 && argv[2] == "-h" {
+            eprintln!("Usage: {} {} [TRANSACTION|-]", argv[0], argv[1]);
+            eprintln!("       TRANSACTION is a hex string.  If it's '-' or omitted, transactions are read from stdin, one per line.");
+            match argv[1].as_str() {
+                "tx-decode" => eprintln!("       Prints each transaction's txid, length, origin and sponsor, whether its signatures verify, and the transaction itself, as one line of JSON."),
+                "tx-verify" => eprintln!("       Prints each transaction's txid and whether its signatures verify.  Exits with an error if any don't."),
+                _ => eprintln!("       Prints each transaction's txid."),
+            }
+            process::exit(1);
+        }
+
+        let cmd = argv[1].clone();
+        let all_ok = for_each_input(&argv, 2, |input| {
+            let (tx, tx_len) = decode_tx_hex(input)?;
+            let txid = tx.txid().to_hex();
+            match cmd.as_str() {
+                "tx-decode" => {
+                    let verify_error = tx.verify().err().map(|e| format!("{:?}", &e));
+                    let output = json!({
+                        "txid": txid,
+                        "tx_len": tx_len,
+                        "origin": tx.origin_address().to_string(),
+                        "sponsor": tx.sponsor_address().map(|addr| addr.to_string()),
+                        "verified": verify_error.is_none(),
+                        "verify_error": verify_error,
+                        "tx": tx,
+                    });
+                    Ok(output.to_string())
+                }
+                "tx-verify" => match tx.verify() {
+                    Ok(_) => Ok(format!("{} verified", &txid)),
+                    Err(e) => Err(format!("{} not verified: {:?}", &txid, &e)),
+                },
+                _ => Ok(txid),
+            }
+        });
+        process::exit(if all_ok { 0 } else { 1 });
+    }
+
+    if argv[1] == "tx-encode" {
+        if argv.len() > 2 && argv[2] == "-h" {
+            eprintln!("Usage: {} tx-encode [TRANSACTION_JSON|-]", argv[0]);
+            eprintln!("       TRANSACTION_JSON is a transaction as printed by tx-decode -- either its whole output, or just its 'tx' field.");
+            eprintln!(
+                "       If it's '-' or omitted, transactions are read from stdin, one per line."
+            );
+            eprintln!("       Prints each transaction's txid and its hex encoding.");
+            process::exit(1);
+        }
+
+        let all_ok = for_each_input(&argv, 2, |input| {
+            let mut json: serde_json::Value = serde_json::from_str(input)
+                .map_err(|e| format!("Failed to parse transaction JSON: {:?}", &e))?;
+            if let Some(tx_json) = json.get_mut("tx") {
+                json = tx_json.take();
+            }
+            let tx: StacksTransaction = serde_json::from_value(json)
+                .map_err(|e| format!("Failed to decode transaction from JSON: {:?}", &e))?;
+
+            let mut tx_bytes = vec![];
+            tx.consensus_serialize(&mut tx_bytes)
+                .map_err(|e| format!("Failed to encode transaction: {:?}", &e))?;
+            Ok(format!("{} {}", tx.txid().to_hex(), to_hex(&tx_bytes)))
+        });
+        process::exit(if all_ok { 0 } else { 1 });
+    }
+
     if argv[1] == "decode-block" {
         if argv.len() < 3 {
             eprintln!("Usage: {} decode-block BLOCK_PATH", argv[0]);
@@ -495,6 +567,88 @@ simulating a miner.
         process::exit(0);
     }
 
+    if argv[1] == "export-mempool" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} export-mempool <working-dir> <snapshot-file>
+
+Write every transaction in the mainnet mempool in <working-dir> to <snapshot-file>, so it can be
+loaded into another node's mempool with import-mempool.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let chain_state_path = format!("{}/mainnet/chainstate/", &argv[2]);
+        let mempool_db = MemPoolDB::open(true, core::CHAIN_ID_MAINNET, &chain_state_path)
+            .expect("Failed to open mempool db");
+
+        let mut snapshot_file = fs::File::create(&argv[3])
+            .expect(&format!("Failed to create snapshot file {}", &argv[3]));
+        let num_txs = MemPoolDB::export_snapshot(mempool_db.conn(), &mut snapshot_file)
+            .expect("Failed to export mempool");
+
+        println!("Exported {} transactions to {}", num_txs, &argv[3]);
+        process::exit(0);
+    }
+
+    if argv[1] == "import-mempool" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} import-mempool <working-dir> <snapshot-file>
+
+Load the transactions in <snapshot-file> (written by export-mempool) into the mainnet mempool in
+<working-dir>. Each transaction must pass the mempool admission checks at the canonical chain tip.
+The node should not be running.
+",
+                argv[0]
+            );
+            process::exit(1);
+        }
+
+        let sort_db_path = format!("{}/mainnet/burnchain/sortition", &argv[2]);
+        let chain_state_path = format!("{}/mainnet/chainstate/", &argv[2]);
+
+        let sort_db = SortitionDB::open(&sort_db_path, false)
+            .expect(&format!("Failed to open {}", &sort_db_path));
+        let chain_id = core::CHAIN_ID_MAINNET;
+        let (mut chain_state, _) = StacksChainState::open(true, chain_id, &chain_state_path)
+            .expect("Failed to open stacks chain state");
+        let mut mempool_db =
+            MemPoolDB::open(true, chain_id, &chain_state_path).expect("Failed to open mempool db");
+
+        let stacks_tip = chain_state
+            .get_stacks_chain_tip(&sort_db)
+            .expect("Failed to load Stacks chain tip")
+            .expect("No Stacks chain tip");
+
+        let mut snapshot_file =
+            fs::File::open(&argv[3]).expect(&format!("Failed to open snapshot file {}", &argv[3]));
+        let txs = MemPoolDB::read_snapshot(&mut snapshot_file).expect("Failed to read snapshot");
+
+        let report = mempool_db
+            .import_txs(
+                &mut chain_state,
+                &stacks_tip.consensus_hash,
+                &stacks_tip.anchored_block_hash,
+                txs,
+                None,
+            )
+            .expect("Failed to import mempool snapshot");
+
+        for (txid, rejection) in report.rejected.iter() {
+            println!("Rejected {}: {:?}", txid, rejection);
+        }
+        println!(
+            "Imported {} transactions; {} already present, {} rejected",
+            report.accepted,
+            report.already_present,
+            report.rejected.len()
+        );
+        process::exit(0);
+    }
+
     if argv[1] == "decode-microblocks" {
         if argv.len() < 3 {
             eprintln!(
@@ -617,6 +771,185 @@ simulating a miner.
         return;
     }
 
+    if argv[1] == "marf-dump" {
+        if argv.len() < 4 {
+            eprintln!(
+                "Usage: {} marf-dump MARF_PATH BLOCK_ID_HASH [PATH_PREFIX]",
+                argv[0]
+            );
+            eprintln!("       Prints the MARF's root hash as of BLOCK_ID_HASH, and then each path (the hash of a key) and its value hash.");
+            eprintln!("       PATH_PREFIX is an optional hex prefix of the paths to print.");
+            process::exit(1);
+        }
+        let marf_path = &argv[2];
+        let marf_bhh = StacksBlockId::from_hex(&argv[3]).expect("Bad MARF block hash");
+        let path_prefix_hex = if argv.len() > 4 {
+            argv[4].to_lowercase()
+        } else {
+            "".to_string()
+        };
+        // the MARF is walked by whole bytes; a trailing half-byte is matched below
+        let path_prefix = hex_bytes(&path_prefix_hex[0..(path_prefix_hex.len() / 2 * 2)])
+            .expect("Bad path prefix");
+
+        if fs::metadata(marf_path).is_err() {
+            eprintln!("No such file or directory: {}", marf_path);
+            process::exit(1);
+        }
+
+        let mut marf = MARF::from_path(marf_path).expect("Failed to open MARF");
+        let root_hash = marf
+            .get_root_hash_at(&marf_bhh)
+            .expect("Failed to read MARF root hash");
+        let leaves = marf
+            .get_leaves(&marf_bhh, &path_prefix)
+            .expect("Failed to read MARF");
+
+        println!("root {}", root_hash.to_hex());
+        for (path, value) in leaves.iter() {
+            let path_hex = to_hex(path.as_bytes());
+            if path_hex.starts_with(&path_prefix_hex) {
+                println!("{} {}", path_hex, value.to_hex());
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "marf-proof" {
+        if argv.len() < 5 {
+            eprintln!("Usage: {} marf-proof MARF_PATH BLOCK_ID_HASH KEY", argv[0]);
+            eprintln!("       Prints KEY's path, value hash, and Merkle proof as of BLOCK_ID_HASH, along with the MARF's root hash.");
+            process::exit(1);
+        }
+        let marf_path = &argv[2];
+        let marf_bhh = StacksBlockId::from_hex(&argv[3]).expect("Bad MARF block hash");
+        let marf_key = &argv[4];
+
+        if fs::metadata(marf_path).is_err() {
+            eprintln!("No such file or directory: {}", marf_path);
+            process::exit(1);
+        }
+
+        let mut marf = MARF::from_path(marf_path).expect("Failed to open MARF");
+        let root_hash = marf
+            .get_root_hash_at(&marf_bhh)
+            .expect("Failed to read MARF root hash");
+
+        println!("root {}", root_hash.to_hex());
+        println!("path {}", to_hex(TriePath::from_key(marf_key).as_bytes()));
+        match marf
+            .get_with_proof(&marf_bhh, marf_key)
+            .expect("Failed to read MARF")
+        {
+            Some((value, proof)) => {
+                println!("value {}", value.to_hex());
+                println!("proof {}", proof.to_hex());
+            }
+            None => {
+                println!("(undefined)");
+            }
+        }
+        process::exit(0);
+    }
+
+    if argv[1] == "marf-verify-proof" {
+        if argv.len() < 7 {
+            eprintln!(
+                "Usage: {} marf-verify-proof MARF_PATH ROOT_HASH KEY VALUE_HASH PROOF",
+                argv[0]
+            );
+            eprintln!("       Checks that PROOF (hex) proves that KEY maps to VALUE_HASH in the MARF with root hash ROOT_HASH.");
+            eprintln!(
+                "       MARF_PATH is only used to map the proof's trie root hashes to blocks."
+            );
+            process::exit(1);
+        }
+        let marf_path = &argv[2];
+        let root_hash = TrieHash::from_hex(&argv[3]).expect("Bad root hash");
+        let marf_key = &argv[4];
+        let value = MARFValue::from_hex(&argv[5]).expect("Bad value hash");
+        let proof_bytes = hex_bytes(&argv[6]).expect("Bad proof");
+        let proof = TrieMerkleProof::<StacksBlockId>(
+            Vec::consensus_deserialize(&mut &proof_bytes[..]).expect("Failed to decode proof"),
+        );
+
+        if fs::metadata(marf_path).is_err() {
+            eprintln!("No such file or directory: {}", marf_path);
+            process::exit(1);
+        }
+
+        let mut marf: MARF<StacksBlockId> =
+            MARF::from_path(marf_path).expect("Failed to open MARF");
+        let root_to_block = marf
+            .get_root_to_block_table()
+            .expect("Failed to read MARF root hashes");
+
+        if proof.verify(
+            &TriePath::from_key(marf_key),
+            &value,
+            &root_hash,
+            &root_to_block,
+        ) {
+            println!("valid");
+            process::exit(0);
+        } else {
+            println!("invalid");
+            process::exit(1);
+        }
+    }
+
+    if argv[1] == "marf-diff" {
+        if argv.len() < 5 {
+            eprintln!(
+                "Usage: {} marf-diff MARF_PATH BLOCK_ID_HASH_1 BLOCK_ID_HASH_2",
+                argv[0]
+            );
+            eprintln!("       Prints the MARF's root hash as of each block, and then each path whose value hash differs between them:");
+            eprintln!("       '- PATH VALUE' if it's only set as of BLOCK_ID_HASH_1, '+ PATH VALUE' if it's only set as of BLOCK_ID_HASH_2,");
+            eprintln!("       and '~ PATH VALUE_1 VALUE_2' if it's set as of both.");
+            process::exit(1);
+        }
+        let marf_path = &argv[2];
+        let marf_bhh_1 = StacksBlockId::from_hex(&argv[3]).expect("Bad MARF block hash");
+        let marf_bhh_2 = StacksBlockId::from_hex(&argv[4]).expect("Bad MARF block hash");
+
+        if fs::metadata(marf_path).is_err() {
+            eprintln!("No such file or directory: {}", marf_path);
+            process::exit(1);
+        }
+
+        let mut marf = MARF::from_path(marf_path).expect("Failed to open MARF");
+        let root_hash_1 = marf
+            .get_root_hash_at(&marf_bhh_1)
+            .expect("Failed to read MARF root hash");
+        let root_hash_2 = marf
+            .get_root_hash_at(&marf_bhh_2)
+            .expect("Failed to read MARF root hash");
+        let diffs = marf
+            .diff(&marf_bhh_1, &marf_bhh_2)
+            .expect("Failed to read MARF");
+
+        println!("root {} {}", root_hash_1.to_hex(), root_hash_2.to_hex());
+        for (path, value_1, value_2) in diffs.iter() {
+            match (value_1, value_2) {
+                (Some(value_1), Some(value_2)) => println!(
+                    "~ {} {} {}",
+                    to_hex(path.as_bytes()),
+                    value_1.to_hex(),
+                    value_2.to_hex()
+                ),
+                (Some(value_1), None) => {
+                    println!("- {} {}", to_hex(path.as_bytes()), value_1.to_hex())
+                }
+                (None, Some(value_2)) => {
+                    println!("+ {} {}", to_hex(path.as_bytes()), value_2.to_hex())
+                }
+                (None, None) => {}
+            }
+        }
+        process::exit(0);
+    }
+
     if argv[1] == "get-ancestors" {
         let path = &argv[2];
         let tip = BlockHeaderHash::from_hex(&argv[3]).unwrap();
@@ -647,6 +980,70 @@ simulating a miner.
         return;
     }
 
+    if argv[1] == "compact-burnchain-db" {
+        use burnchains::db::{BurnchainDB, BURNCHAIN_DB_DEFAULT_PRUNE_DEPTH};
+
+        if argv.len() < 3 {
+            eprintln!(
+                "Usage: {} compact-burnchain-db BURNCHAIN_DB_PATH [PRUNE_DEPTH]",
+                &argv[0]
+            );
+            process::exit(1);
+        }
+        let path = &argv[2];
+        let prune_depth = if argv.len() > 3 {
+            argv[3].parse::<u64>().expect("Invalid prune depth")
+        } else {
+            BURNCHAIN_DB_DEFAULT_PRUNE_DEPTH
+        };
+
+        let size_before = fs::metadata(path).expect("Failed to stat DB").len();
+        let mut burnchain_db = BurnchainDB::open(path, true).expect("Failed to open DB");
+        let num_pruned = burnchain_db
+            .prune_orphaned_blocks(prune_depth)
+            .expect("Failed to prune DB");
+        burnchain_db.compact().expect("Failed to compact DB");
+        let size_after = fs::metadata(path).expect("Failed to stat DB").len();
+
+        println!(
+            "Pruned {} orphaned burnchain blocks; {} bytes before, {} bytes after",
+            num_pruned, size_before, size_after
+        );
+        return;
+    }
+
+    if argv[1] == "gen-test-vectors" {
+        if argv.len() < 3 {
+            eprintln!("Usage: {} gen-test-vectors OUTPUT_DIR", &argv[0]);
+            process::exit(1);
+        }
+        let num_written = net::conformance::write_test_vectors(std::path::Path::new(&argv[2]))
+            .expect("Failed to write test vectors");
+        println!("Wrote {} test vectors to {}", num_written, &argv[2]);
+        return;
+    }
+
+    if argv[1] == "verify-test-vectors" {
+        if argv.len() < 3 {
+            eprintln!("Usage: {} verify-test-vectors VECTORS_DIR", &argv[0]);
+            process::exit(1);
+        }
+        let report = net::conformance::verify_test_vectors(std::path::Path::new(&argv[2]))
+            .expect("Failed to read test vectors");
+        for (name, reason) in report.failures.iter() {
+            println!("FAIL {}: {}", name, reason);
+        }
+        println!(
+            "Checked {} test vectors; {} failed",
+            report.checked,
+            report.failures.len()
+        );
+        if report.failures.len() > 0 {
+            process::exit(1);
+        }
+        return;
+    }
+
     if argv[1] == "docgen" {
         println!("{}", vm::docs::make_json_api_reference());
         return;
@@ -1000,3 +1397,61 @@ simulating a miner.
         process::exit(1);
     }
 }
+
+/// Run `handler` on the command's input: either argv[arg_index], or -- if that's "-" or missing --
+/// each non-empty line of stdin.  Each output is printed to stdout, and each error to stderr.
+/// Returns true if there were no errors.
+fn for_each_input<F>(argv: &[String], arg_index: usize, mut handler: F) -> bool
+where
+    F: FnMut(&str) -> Result<String, String>,
+{
+    let mut all_ok = true;
+    let mut handle = |input: &str| match handler(input.trim()) {
+        Ok(output) => {
+            println!("{}", output);
+        }
+        Err(msg) => {
+            eprintln!("{}", msg);
+            all_ok = false;
+        }
+    };
+
+    if argv.len() > arg_index && argv[arg_index] != "-" {
+        handle(&argv[arg_index]);
+    } else {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = line.expect("Failed to read from stdin");
+            if line.trim().len() > 0 {
+                handle(&line);
+            }
+        }
+    }
+    all_ok
+}
+
+/// Decode a hex-encoded transaction, along with its length in bytes
+fn decode_tx_hex(tx_str: &str) -> Result<(StacksTransaction, usize), String> {
+    let tx_bytes = hex_bytes(tx_str)
+        .map_err(|_e| "Failed to decode transaction: must be a hex string".to_string())?;
+
+    let mut cursor = io::Cursor::new(&tx_bytes);
+    let tx = {
+        let mut debug_cursor = LogReader::from_reader(&mut cursor);
+        StacksTransaction::consensus_deserialize(&mut debug_cursor).map_err(|e| {
+            let mut msg = format!("Failed to decode transaction: {:?}\nBytes consumed:", &e);
+            for buf in debug_cursor.log().iter() {
+                msg.push_str(&format!("\n  {}", to_hex(buf)));
+            }
+            msg
+        })?
+    };
+
+    if (cursor.position() as usize) < tx_bytes.len() {
+        return Err(format!(
+            "Failed to decode transaction: {} trailing bytes",
+            tx_bytes.len() - (cursor.position() as usize)
+        ));
+    }
+    Ok((tx, tx_bytes.len()))
+}
@@ -14,22 +14,23 @@
 // You should have received a copy of the GNU General Public License
 // along with this program.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::{fmt, fs, path::PathBuf};
 
-use rusqlite::{OpenFlags, OptionalExtension};
+use rusqlite::{types::ToSql, OpenFlags, OptionalExtension};
 
 use crate::{
     burnchains::Txid,
     core::MemPoolDB,
     net::{Error as net_error, HttpRequestType},
     util::{
-        db::{tx_busy_handler, DBConn},
+        db::{tx_begin_immediate, tx_busy_handler, DBConn},
         get_epoch_time_secs,
     },
 };
 use burnchains::BurnchainSigner;
 use std::error::Error;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Mutex;
 use util::db::Error as DatabaseError;
 use util::uint::{Uint256, Uint512};
@@ -42,6 +43,10 @@ lazy_static! {
     static ref GLOBAL_BURNCHAIN_SIGNER: Mutex<Option<BurnchainSigner>> = Mutex::new(None);
 }
 
+/// Whether the persisted counters have picked up their totals from the node's last run yet
+#[cfg(feature = "monitoring_prom")]
+static PERSISTED_COUNTERS_RESTORED: AtomicBool = AtomicBool::new(false);
+
 pub fn increment_rpc_calls_counter() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::RPC_CALL_COUNTER.inc();
@@ -241,6 +246,49 @@ pub fn update_burnchain_height(value: i64) {
     prometheus::BURNCHAIN_HEIGHT_GAUGE.set(value);
 }
 
+/// Record the outcome of a reward cycle boundary: the cycle number, the size of its reward set,
+/// whether it pays PoX recipients, and whether its selected anchor block was missing.
+#[allow(unused_variables)]
+pub fn update_reward_cycle(
+    reward_cycle: i64,
+    reward_set_size: i64,
+    pox_active: bool,
+    anchor_block_missing: bool,
+) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::REWARD_CYCLE_GAUGE.set(reward_cycle);
+        prometheus::REWARD_SET_SIZE_GAUGE.set(reward_set_size);
+        prometheus::POX_ACTIVE_GAUGE.set(if pox_active { 1 } else { 0 });
+        if anchor_block_missing {
+            prometheus::REWARD_CYCLE_ANCHOR_BLOCK_MISSING_COUNTER.inc();
+        }
+    }
+}
+
+#[allow(unused_variables)]
+pub fn update_burnchain_fee_rate_estimate(value: i64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BURNCHAIN_FEE_RATE_ESTIMATE_GAUGE.set(value);
+}
+
+#[allow(unused_variables)]
+pub fn update_block_commit_fee_rate(value: i64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BLOCK_COMMIT_FEE_RATE_GAUGE.set(value);
+}
+
+pub fn increment_block_commit_fee_capped_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::BLOCK_COMMIT_FEE_CAPPED_COUNTER.inc();
+}
+
+#[allow(unused_variables)]
+pub fn update_disk_free_space(value: i64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::DISK_FREE_SPACE_GAUGE.set(value);
+}
+
 #[allow(unused_variables)]
 pub fn update_inbound_neighbors(value: i64) {
     #[cfg(feature = "monitoring_prom")]
@@ -253,6 +301,94 @@ pub fn update_outbound_neighbors(value: i64) {
     prometheus::OUTBOUND_NEIGHBORS_GAUGE.set(value);
 }
 
+/// Record a completed pass of the neighbor walk: how long it took, the resulting frontier size,
+/// how many frontier peers it added or replaced, the moving average of the fraction of peers it
+/// could not reach, and the resulting frontier quality score.
+#[allow(unused_variables)]
+pub fn update_neighbor_walk(
+    duration_ms: u64,
+    frontier_size: i64,
+    added: u64,
+    replaced: u64,
+    unreachable_ratio: f64,
+    frontier_quality_score: i64,
+) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::NEIGHBOR_WALK_DURATION_HISTOGRAM.observe((duration_ms as f64) / 1000.0);
+        prometheus::FRONTIER_SIZE_GAUGE.set(frontier_size);
+        prometheus::NEIGHBOR_WALK_CHURN_COUNTER_VEC
+            .with_label_values(&["added"])
+            .inc_by(added as i64);
+        prometheus::NEIGHBOR_WALK_CHURN_COUNTER_VEC
+            .with_label_values(&["replaced"])
+            .inc_by(replaced as i64);
+        prometheus::NEIGHBOR_WALK_UNREACHABLE_RATIO.set(unreachable_ratio);
+        prometheus::FRONTIER_QUALITY_SCORE_GAUGE.set(frontier_quality_score);
+    }
+}
+
+/// Record the last comparison of our block inventory to our neighbors': how many neighbors were
+/// compared, and what percentage of them claim blocks we lack or lack blocks we have.
+#[allow(unused_variables)]
+pub fn update_inv_agreement(num_peers: i64, pct_ahead: i64, pct_behind: i64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::INV_AGREEMENT_PEERS_GAUGE.set(num_peers);
+        prometheus::INV_AGREEMENT_PEERS_AHEAD_GAUGE.set(pct_ahead);
+        prometheus::INV_AGREEMENT_PEERS_BEHIND_GAUGE.set(pct_behind);
+    }
+}
+
+/// Count an alert that too many neighbors have disagreed with our block inventory for too long.
+/// `kind` is "ahead" if they claim blocks we lack, or "behind" if they lack blocks we have.
+#[allow(unused_variables)]
+pub fn increment_inv_disagreement_alerts(kind: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::INV_DISAGREEMENT_ALERTS_COUNTER_VEC
+        .with_label_values(&[kind])
+        .inc();
+}
+
+/// Count a lookup in the RPC response cache
+#[allow(unused_variables)]
+pub fn increment_rpc_cache_lookups(hit: bool) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RPC_CACHE_LOOKUPS_COUNTER_VEC
+        .with_label_values(&[if hit { "hit" } else { "miss" }])
+        .inc();
+}
+
+/// Record the last census of peer versions: how many connected peers and how many observed
+/// (connected or crawled) peers advertise each version, and what percentage of observed peers
+/// advertise a newer version than ours.
+#[allow(unused_variables)]
+pub fn update_peer_versions(
+    connected: &BTreeMap<u32, u64>,
+    observed: &BTreeMap<u32, u64>,
+    pct_newer: i64,
+) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::PEER_VERSIONS_GAUGE_VEC.reset();
+        for (scope, counts) in [("connected", connected), ("observed", observed)].iter() {
+            for (peer_version, count) in counts.iter() {
+                let version = format!("{:08x}", peer_version);
+                prometheus::PEER_VERSIONS_GAUGE_VEC
+                    .with_label_values(&[*scope, version.as_str()])
+                    .set(*count as i64);
+            }
+        }
+        prometheus::PEERS_NEWER_VERSION_GAUGE.set(pct_newer);
+    }
+}
+
+/// Count an alert that too many peers advertise a newer peer version than ours.
+pub fn increment_peer_version_alerts() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::PEER_VERSION_ALERTS_COUNTER.inc();
+}
+
 #[allow(unused_variables)]
 pub fn update_inbound_bandwidth(value: i64) {
     #[cfg(feature = "monitoring_prom")]
@@ -285,6 +421,27 @@ pub fn increment_msg_counter(name: String) {
         .inc();
 }
 
+/// Record a miner tip selection decision.  `outcome` is either `canonical` (the canonical Stacks
+/// chain tip was kept) or `alternate` (the policy chose a competing tip at the same height).
+#[allow(unused_variables)]
+pub fn increment_miner_tip_selection(policy: &str, outcome: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::MINER_TIP_SELECTION_COUNTER_VEC
+        .with_label_values(&[policy, outcome])
+        .inc();
+}
+
+/// Record that the miner abandoned an in-progress block assembly.  `reason` is either
+/// `new_parent` (a newer Stacks chain tip arrived) or `microblocks` (the parent's microblock
+/// stream grew by enough fees to be worth confirming).
+#[allow(unused_variables)]
+pub fn increment_miner_block_assembly_preempted(reason: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::MINER_BLOCK_ASSEMBLY_PREEMPTED_COUNTER_VEC
+        .with_label_values(&[reason])
+        .inc();
+}
+
 pub fn increment_stx_mempool_gc() {
     #[cfg(feature = "monitoring_prom")]
     prometheus::STX_MEMPOOL_GC.inc();
@@ -295,6 +452,186 @@ pub fn increment_contract_calls_processed() {
     prometheus::CONTRACT_CALLS_PROCESSED_COUNT.inc();
 }
 
+#[allow(unused_variables)]
+pub fn increment_relayed_bytes_counter(bytes: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::RELAYED_BYTES_COUNTER.inc_by(bytes as i64);
+}
+
+pub fn increment_attachments_served_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATTACHMENTS_SERVED_COUNTER.inc();
+}
+
+/// Record one sweep of the Atlas retention policy: how many attachments it deleted, how many
+/// bytes of content that reclaimed, and how many bytes of content are left.
+#[allow(unused_variables)]
+pub fn record_attachments_pruned(num_pruned: u64, pruned_bytes: u64, stored_bytes: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::ATTACHMENTS_PRUNED_COUNTER.inc_by(num_pruned as i64);
+        prometheus::ATTACHMENTS_PRUNED_BYTES_COUNTER.inc_by(pruned_bytes as i64);
+        prometheus::ATTACHMENTS_STORED_BYTES_GAUGE.set(stored_bytes as i64);
+    }
+}
+
+pub fn increment_attachments_mirrored_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATTACHMENTS_MIRRORED_COUNTER.inc();
+}
+
+pub fn increment_attachments_mirror_failures_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATTACHMENTS_MIRROR_FAILURES_COUNTER.inc();
+}
+
+pub fn increment_attachments_mirror_reads_counter() {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::ATTACHMENTS_MIRROR_READS_COUNTER.inc();
+}
+
+/// Count the transactions of a compact block we pushed: how many were sent in full, and how
+/// many only by txid.
+#[allow(unused_variables)]
+pub fn increment_compact_block_sent_txs(num_full: u64, num_txids: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::COMPACT_BLOCK_SENT_TXS_COUNTER_VEC
+            .with_label_values(&["full"])
+            .inc_by(num_full as i64);
+        prometheus::COMPACT_BLOCK_SENT_TXS_COUNTER_VEC
+            .with_label_values(&["txid"])
+            .inc_by(num_txids as i64);
+    }
+}
+
+/// Record an attempt to rebuild a compact block pushed to us.  `result` is `rebuilt`,
+/// `incomplete` (some transactions were neither sent nor in our mempool), or `invalid`.  The
+/// transaction counts say how many were sent in full, found in our mempool, or missing; the
+/// mempool hit rate tells how well the pushers' prefill guesses are working.
+#[allow(unused_variables)]
+pub fn record_compact_block_reconstruction(
+    result: &str,
+    num_sent: u64,
+    num_from_mempool: u64,
+    num_missing: u64,
+) {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        prometheus::COMPACT_BLOCK_RECONSTRUCTIONS_COUNTER_VEC
+            .with_label_values(&[result])
+            .inc();
+        for (source, count) in [
+            ("sent", num_sent),
+            ("mempool", num_from_mempool),
+            ("missing", num_missing),
+        ]
+        .iter()
+        {
+            prometheus::COMPACT_BLOCK_RECEIVED_TXS_COUNTER_VEC
+                .with_label_values(&[*source])
+                .inc_by(*count as i64);
+        }
+    }
+}
+
+/// Count a transaction we did not relay because its origin hit its relay rate limit.  `limit`
+/// is `txs` or `bytes`.
+#[allow(unused_variables)]
+pub fn increment_tx_relay_throttled_counter(limit: &str) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::TX_RELAY_THROTTLED_COUNTER_VEC
+        .with_label_values(&[limit])
+        .inc();
+}
+
+#[allow(unused_variables)]
+pub fn update_tx_relay_origins_tracked(num_origins: u64) {
+    #[cfg(feature = "monitoring_prom")]
+    prometheus::TX_RELAY_ORIGINS_TRACKED_GAUGE.set(num_origins as i64);
+}
+
+#[cfg(feature = "monitoring_prom")]
+fn persisted_counters_db(chainstate_root_path: &str) -> Result<DBConn, DatabaseError> {
+    let mut path = PathBuf::from(chainstate_root_path);
+
+    path.push("metrics.sqlite");
+    let db_path = path.to_str().ok_or_else(|| DatabaseError::ParseError)?;
+
+    let conn = DBConn::open_with_flags(
+        &db_path,
+        OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
+    )?;
+    conn.busy_handler(Some(tx_busy_handler))?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS persisted_counters (name TEXT NOT NULL PRIMARY KEY, value INTEGER NOT NULL)",
+        rusqlite::NO_PARAMS,
+    )?;
+
+    Ok(conn)
+}
+
+/// Add the totals that the persisted counters had reached when the node last saved them, so
+/// they keep counting up across restarts instead of going back to zero.  Call once at startup;
+/// `persist_counters()` does nothing until this has succeeded, so it can't overwrite the saved
+/// totals with this run's alone.
+#[allow(unused_variables)]
+pub fn restore_persisted_counters(chainstate_root_path: &str) -> Result<(), DatabaseError> {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if PERSISTED_COUNTERS_RESTORED.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let conn = persisted_counters_db(chainstate_root_path)?;
+        let mut saved_totals = vec![];
+        for (name, counter) in prometheus::persisted_counters().into_iter() {
+            let saved_total: Option<i64> = conn
+                .query_row(
+                    "SELECT value FROM persisted_counters WHERE name = ?1",
+                    &[name],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            if let Some(saved_total) = saved_total {
+                saved_totals.push((counter, saved_total));
+            }
+        }
+
+        for (counter, saved_total) in saved_totals.into_iter() {
+            if saved_total > 0 {
+                counter.inc_by(saved_total);
+            }
+        }
+        PERSISTED_COUNTERS_RESTORED.store(true, Ordering::SeqCst);
+    }
+    Ok(())
+}
+
+/// Save the persisted counters' current totals, to be picked up by
+/// `restore_persisted_counters()` the next time the node starts.
+#[allow(unused_variables)]
+pub fn persist_counters(chainstate_root_path: &str) -> Result<(), DatabaseError> {
+    #[cfg(feature = "monitoring_prom")]
+    {
+        if !PERSISTED_COUNTERS_RESTORED.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let mut conn = persisted_counters_db(chainstate_root_path)?;
+        let tx = tx_begin_immediate(&mut conn)?;
+        for (name, counter) in prometheus::persisted_counters().into_iter() {
+            let args: &[&dyn ToSql] = &[&name, &counter.get()];
+            tx.execute(
+                "INSERT OR REPLACE INTO persisted_counters (name, value) VALUES (?1, ?2)",
+                args,
+            )?;
+        }
+        tx.commit()?;
+    }
+    Ok(())
+}
+
 /// Given a value (type uint256), return value/uint256::max() as an f64 value.
 /// The precision of the percentage is determined by the input `precision_points`, which is capped
 /// at a max of 15.
@@ -16,6 +16,7 @@
 
 use prometheus::{
     Gauge, Histogram, HistogramTimer, HistogramVec, IntCounter, IntCounterVec, IntGauge,
+    IntGaugeVec,
 };
 
 lazy_static! {
@@ -106,6 +107,11 @@ lazy_static! {
         "Burnchain tip height"
     )).unwrap();
 
+    pub static ref DISK_FREE_SPACE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_disk_free_bytes",
+        "Lowest free space, in bytes, on the filesystems holding the node's databases"
+    )).unwrap();
+
     pub static ref INBOUND_NEIGHBORS_GAUGE: IntGauge = register_int_gauge!(opts!(
         "stacks_node_neighbors_inbound",
         "Total count of current known inbound neighbors"
@@ -142,6 +148,18 @@ lazy_static! {
         &["name"]
     ).unwrap();
 
+    pub static ref MINER_TIP_SELECTION_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_miner_tip_selection_total",
+        "Number of times the miner chose a chain tip, by tip selection policy and whether the canonical tip was kept",
+        &["policy", "outcome"]
+    ).unwrap();
+
+    pub static ref MINER_BLOCK_ASSEMBLY_PREEMPTED_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_miner_block_assembly_preempted_total",
+        "Number of times the miner abandoned an in-progress block assembly, by the reason it was preempted",
+        &["reason"]
+    ).unwrap();
+
 
     pub static ref STX_MEMPOOL_GC: IntCounter = register_int_counter!(opts!(
         "stacks_node_mempool_gc_count",
@@ -190,6 +208,207 @@ lazy_static! {
         "stacks_node_miner_current_median_commitment_low",
         "Low 64 bits of a miner's median commitment over the mining commitment window."
     )).unwrap();
+
+    pub static ref REWARD_CYCLE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_reward_cycle",
+        "Reward cycle of the last reward cycle boundary processed"
+    )).unwrap();
+
+    pub static ref REWARD_SET_SIZE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_reward_set_size",
+        "Number of reward slots in the reward set selected for the current reward cycle"
+    )).unwrap();
+
+    pub static ref POX_ACTIVE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_pox_active",
+        "1 if the current reward cycle pays PoX recipients, 0 if it falls back to burning"
+    )).unwrap();
+
+    pub static ref REWARD_CYCLE_ANCHOR_BLOCK_MISSING_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_reward_cycle_anchor_block_missing_total",
+        "Total number of reward cycles whose selected anchor block was not known when the cycle began"
+    )).unwrap();
+
+    pub static ref BURNCHAIN_FEE_RATE_ESTIMATE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_burnchain_fee_rate_estimate",
+        "Last fee rate (sat/vB) estimated by the burnchain node for block commits"
+    )).unwrap();
+
+    pub static ref BLOCK_COMMIT_FEE_RATE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_block_commit_fee_rate",
+        "Fee rate (sat/vB) of the last block commit submitted to the burnchain"
+    )).unwrap();
+
+    pub static ref BLOCK_COMMIT_FEE_CAPPED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_block_commit_fee_capped_total",
+        "Total number of block commits (or RBF attempts) not submitted because they exceeded a configured fee cap"
+    )).unwrap();
+
+    pub static ref NEIGHBOR_WALK_DURATION_HISTOGRAM: Histogram = register_histogram!(histogram_opts!(
+        "stacks_node_neighbor_walk_duration_seconds",
+        "Time (seconds) taken by each pass of the neighbor walk",
+        vec![1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1200.0]
+    )).unwrap();
+
+    pub static ref FRONTIER_SIZE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_frontier_size",
+        "Number of peers in the node's frontier (its peer database)"
+    )).unwrap();
+
+    pub static ref NEIGHBOR_WALK_CHURN_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_neighbor_walk_churn_total",
+        "Number of frontier peers added or replaced by the neighbor walk",
+        &["kind"]
+    ).unwrap();
+
+    pub static ref NEIGHBOR_WALK_UNREACHABLE_RATIO: Gauge = register_gauge!(opts!(
+        "stacks_node_neighbor_walk_unreachable_ratio",
+        "Moving average of the fraction of peers the neighbor walk could not connect to"
+    )).unwrap();
+
+    pub static ref FRONTIER_QUALITY_SCORE_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_frontier_quality_score",
+        "Score (0-100) of how fresh, reachable and diverse the node's frontier is"
+    )).unwrap();
+
+    pub static ref INV_AGREEMENT_PEERS_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_inv_agreement_peers",
+        "Number of neighbors whose block inventories were last compared to ours"
+    )).unwrap();
+
+    pub static ref INV_AGREEMENT_PEERS_AHEAD_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_inv_agreement_peers_ahead_pct",
+        "Percentage of compared neighbors that claim Stacks blocks we don't have"
+    )).unwrap();
+
+    pub static ref INV_AGREEMENT_PEERS_BEHIND_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_inv_agreement_peers_behind_pct",
+        "Percentage of compared neighbors that lack Stacks blocks we have"
+    )).unwrap();
+
+    pub static ref INV_DISAGREEMENT_ALERTS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_inv_disagreement_alerts_total",
+        "Number of times too many neighbors disagreed with our block inventory for too long",
+        &["kind"]
+    ).unwrap();
+
+    pub static ref RPC_CACHE_LOOKUPS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_rpc_cache_lookups_total",
+        "Number of lookups in the RPC response cache, by result (hit or miss)",
+        &["result"]
+    ).unwrap();
+
+    pub static ref PEER_VERSIONS_GAUGE_VEC: IntGaugeVec = register_int_gauge_vec!(
+        "stacks_node_peer_versions",
+        "Number of peers advertising each peer version, among connected peers or all observed (connected and crawled) peers",
+        &["scope", "version"]
+    ).unwrap();
+
+    pub static ref PEERS_NEWER_VERSION_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_peers_newer_version_pct",
+        "Percentage of observed peers advertising a newer peer version than ours"
+    )).unwrap();
+
+    pub static ref PEER_VERSION_ALERTS_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_peer_version_alerts_total",
+        "Number of times too many peers advertised a newer peer version than ours"
+    )).unwrap();
+
+    pub static ref RELAYED_BYTES_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_relayed_bytes_total",
+        "Total number of message payload bytes relayed to peers"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_SERVED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_attachments_served_total",
+        "Total number of Atlas attachments served"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_PRUNED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_attachments_pruned_total",
+        "Total number of Atlas attachments deleted by the retention policy"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_PRUNED_BYTES_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_attachments_pruned_bytes_total",
+        "Total number of bytes of Atlas attachment content deleted by the retention policy"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_STORED_BYTES_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_attachments_stored_bytes",
+        "Number of bytes of Atlas attachment content stored, as of the last retention sweep"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_MIRRORED_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_attachments_mirrored_total",
+        "Total number of Atlas attachments uploaded to the mirror"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_MIRROR_FAILURES_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_attachments_mirror_failures_total",
+        "Total number of failed requests to the Atlas attachment mirror"
+    )).unwrap();
+
+    pub static ref ATTACHMENTS_MIRROR_READS_COUNTER: IntCounter = register_int_counter!(opts!(
+        "stacks_node_attachments_mirror_reads_total",
+        "Total number of Atlas attachments served from the mirror because they were not stored locally"
+    )).unwrap();
+
+    pub static ref COMPACT_BLOCK_SENT_TXS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_compact_block_sent_txs_total",
+        "Number of transactions in the compact blocks we pushed, by how they were sent (full or txid)",
+        &["kind"]
+    ).unwrap();
+
+    pub static ref COMPACT_BLOCK_RECEIVED_TXS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_compact_block_received_txs_total",
+        "Number of transactions in the compact blocks pushed to us, by where we got them (sent, mempool, or missing)",
+        &["source"]
+    ).unwrap();
+
+    pub static ref COMPACT_BLOCK_RECONSTRUCTIONS_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_compact_block_reconstructions_total",
+        "Number of compact blocks pushed to us, by whether we could rebuild them (rebuilt, incomplete, or invalid)",
+        &["result"]
+    ).unwrap();
+
+    pub static ref TX_RELAY_THROTTLED_COUNTER_VEC: IntCounterVec = register_int_counter_vec!(
+        "stacks_node_tx_relay_throttled_total",
+        "Number of transactions we did not relay because their origin hit a relay rate limit, by which limit (txs or bytes)",
+        &["limit"]
+    ).unwrap();
+
+    pub static ref TX_RELAY_ORIGINS_TRACKED_GAUGE: IntGauge = register_int_gauge!(opts!(
+        "stacks_node_tx_relay_origins_tracked",
+        "Number of transaction origins the relayer is rate-limiting"
+    )).unwrap();
+}
+
+/// The counters whose totals are carried over from one run of the node to the next, keyed by
+/// the name they are persisted under
+pub fn persisted_counters() -> Vec<(&'static str, &'static IntCounter)> {
+    vec![
+        ("stx_blocks_processed", &*STX_BLOCKS_PROCESSED_COUNTER),
+        ("stx_blocks_received", &*STX_BLOCKS_RECEIVED_COUNTER),
+        (
+            "stx_micro_blocks_received",
+            &*STX_MICRO_BLOCKS_RECEIVED_COUNTER,
+        ),
+        ("stx_blocks_served", &*STX_BLOCKS_SERVED_COUNTER),
+        ("stx_micro_blocks_served", &*STX_MICRO_BLOCKS_SERVED_COUNTER),
+        ("stx_blocks_mined", &*STX_BLOCKS_MINED_COUNTER),
+        ("transactions_received", &*TXS_RECEIVED_COUNTER),
+        ("btc_blocks_received", &*BTC_BLOCKS_RECEIVED_COUNTER),
+        ("btc_ops_sent", &*BTC_OPS_SENT_COUNTER),
+        ("relayed_bytes", &*RELAYED_BYTES_COUNTER),
+        ("attachments_served", &*ATTACHMENTS_SERVED_COUNTER),
+        ("attachments_pruned", &*ATTACHMENTS_PRUNED_COUNTER),
+        (
+            "attachments_pruned_bytes",
+            &*ATTACHMENTS_PRUNED_BYTES_COUNTER,
+        ),
+        ("attachments_mirrored", &*ATTACHMENTS_MIRRORED_COUNTER),
+    ]
 }
 
 pub fn new_rpc_call_timer(path: &str) -> HistogramTimer {
@@ -0,0 +1,599 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Async socket I/O for the p2p and HTTP state machines (feature `async_net`).
+//!
+//! The conversation state machines (`ConversationP2P`, `ConversationHttp`) only need something
+//! that implements `Read` and `Write`.  If the node's `NetworkState` has an `AsyncFrontEnd`
+//! (i.e. `ConnectionOptions::async_io_threads` is non-zero), each connection that `PeerNetwork`
+//! or `HttpPeer` registers is handed over to a multi-threaded tokio runtime, which does the actual
+//! socket I/O.  The state machines instead read and write an `AsyncSocket` -- an in-memory
+//! `Read + Write` endpoint that never blocks -- and `NetworkState::poll()` reports its event ID
+//! whenever the runtime has received more bytes or sent queued ones, just as it would for a mio
+//! socket.  This way, a slow peer's socket can no longer hold up everyone else's.
+//!
+//! Each socket buffers at most `max_inbound_bytes` of received data.  Once that much is waiting
+//! to be read, the runtime stops reading from the socket until the state machine catches up, so
+//! a peer can't make the node buffer more than that on its behalf.
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::io::{Read, Write};
+use std::net;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use mio;
+use mio::{PollOpt, Ready, Registration, SetReadiness, Token};
+
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::runtime::{Builder, Runtime};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::sync::oneshot;
+
+use net::Error as net_error;
+
+/// How many bytes to read from a socket at once
+const ASYNC_SOCKET_READ_BUF_LEN: usize = 65536;
+
+/// Bytes received from a socket that the state machine has not yet read
+#[derive(Debug)]
+struct InboundBuffer {
+    chunks: VecDeque<Vec<u8>>,
+    /// how much of the first chunk has been read already
+    offset: usize,
+    /// how many unread bytes there are in total
+    num_bytes: usize,
+    /// has the remote peer hung up (or the socket failed)?
+    hung_up: bool,
+    /// wakes up the runtime's I/O task once there is room to read more
+    waker: Option<Waker>,
+}
+
+impl InboundBuffer {
+    fn new() -> InboundBuffer {
+        InboundBuffer {
+            chunks: VecDeque::new(),
+            offset: 0,
+            num_bytes: 0,
+            hung_up: false,
+            waker: None,
+        }
+    }
+}
+
+/// One connection, as seen by the thread that drives the state machines.
+/// Reads return `WouldBlock` until the runtime has received more bytes, and `Ok(0)` once the
+/// remote peer hangs up.  Writes are queued for the runtime to send, and return `WouldBlock`
+/// once more than `max_outbound_bytes` are still waiting to go out.
+/// Dropping the socket closes the connection.
+pub struct AsyncSocket {
+    event_id: usize,
+    peer_addr: SocketAddr,
+    inbound: Arc<Mutex<InboundBuffer>>,
+    max_inbound_bytes: usize,
+    outbound: UnboundedSender<Vec<u8>>,
+    outbound_bytes: Arc<AtomicUsize>,
+    max_outbound_bytes: usize,
+    registration: Registration,
+    _hangup: oneshot::Sender<()>,
+}
+
+impl fmt::Debug for AsyncSocket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "AsyncSocket {{ event_id: {}, peer_addr: {:?} }}",
+            self.event_id, &self.peer_addr
+        )
+    }
+}
+
+impl AsyncSocket {
+    /// Event ID this socket was started with
+    pub fn event_id(&self) -> usize {
+        self.event_id
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.peer_addr.clone())
+    }
+
+    /// How many received bytes have not yet been read?
+    pub fn num_inbound_bytes(&self) -> usize {
+        self.inbound.lock().unwrap().num_bytes
+    }
+
+    /// How many written bytes have not yet been sent to the remote peer?
+    pub fn num_outbound_bytes(&self) -> usize {
+        self.outbound_bytes.load(Ordering::SeqCst)
+    }
+}
+
+impl Read for AsyncSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut inbound = self.inbound.lock().unwrap();
+        if inbound.num_bytes == 0 {
+            if inbound.hung_up {
+                // remote peer hung up, and we have consumed everything it sent
+                return Ok(0);
+            }
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+
+        let mut num_read = 0;
+        while num_read < buf.len() && inbound.chunks.len() > 0 {
+            let offset = inbound.offset;
+            let (num_bytes, consumed) = {
+                let chunk = inbound.chunks.front().unwrap();
+                let num_bytes = cmp::min(buf.len() - num_read, chunk.len() - offset);
+                buf[num_read..num_read + num_bytes]
+                    .copy_from_slice(&chunk[offset..offset + num_bytes]);
+                (num_bytes, offset + num_bytes >= chunk.len())
+            };
+            if consumed {
+                inbound.chunks.pop_front();
+                inbound.offset = 0;
+            } else {
+                inbound.offset += num_bytes;
+            }
+            num_read += num_bytes;
+        }
+        inbound.num_bytes -= num_read;
+
+        if inbound.num_bytes < self.max_inbound_bytes {
+            // the runtime may have stopped reading because the buffer was full
+            if let Some(waker) = inbound.waker.take() {
+                waker.wake();
+            }
+        }
+        Ok(num_read)
+    }
+}
+
+impl Write for AsyncSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+        if self.outbound_bytes.load(Ordering::SeqCst) >= self.max_outbound_bytes {
+            return Err(io::Error::from(io::ErrorKind::WouldBlock));
+        }
+        self.outbound_bytes.fetch_add(buf.len(), Ordering::SeqCst);
+        self.outbound
+            .send(buf.to_vec())
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl mio::Evented for AsyncSocket {
+    fn register(
+        &self,
+        poll: &mio::Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        poll.register(&self.registration, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &mio::Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        poll.reregister(&self.registration, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &mio::Poll) -> io::Result<()> {
+        poll.deregister(&self.registration)
+    }
+}
+
+/// Task that shuttles bytes between a socket and its `AsyncSocket`, until either side hangs up
+struct SocketIO {
+    event_id: usize,
+    stream: TcpStream,
+    buf: Vec<u8>,
+    inbound: Arc<Mutex<InboundBuffer>>,
+    max_inbound_bytes: usize,
+    read_closed: bool,
+    outbound: UnboundedReceiver<Vec<u8>>,
+    sending: Option<(Vec<u8>, usize)>,
+    outbound_bytes: Arc<AtomicUsize>,
+    max_outbound_bytes: usize,
+    readiness: SetReadiness,
+    hangup: oneshot::Receiver<()>,
+}
+
+impl SocketIO {
+    /// Read from the socket until it would block, or until the inbound buffer is full.
+    /// Returns true if nothing more can be read from the socket.
+    fn poll_inbound(&mut self, cx: &mut Context<'_>) -> bool {
+        loop {
+            let room = {
+                let mut inbound = self.inbound.lock().unwrap();
+                if inbound.num_bytes >= self.max_inbound_bytes {
+                    // wait for the state machine to read some of what's buffered
+                    inbound.waker = Some(cx.waker().clone());
+                    return false;
+                }
+                self.max_inbound_bytes - inbound.num_bytes
+            };
+
+            let len = cmp::min(room, self.buf.len());
+            let num_read = match Pin::new(&mut self.stream).poll_read(cx, &mut self.buf[0..len]) {
+                Poll::Pending => {
+                    return false;
+                }
+                Poll::Ready(Ok(num_read)) => num_read,
+                Poll::Ready(Err(e)) => {
+                    debug!(
+                        "Failed to read from async socket {}: {:?}",
+                        self.event_id, &e
+                    );
+                    0
+                }
+            };
+
+            let mut inbound = self.inbound.lock().unwrap();
+            if num_read == 0 {
+                inbound.hung_up = true;
+            } else {
+                inbound.chunks.push_back(self.buf[0..num_read].to_vec());
+                inbound.num_bytes += num_read;
+            }
+            drop(inbound);
+
+            // wake up the state machine so it reads the data (or notices the hang-up)
+            let _ = self.readiness.set_readiness(Ready::readable());
+            if num_read == 0 {
+                return true;
+            }
+        }
+    }
+
+    /// Write queued bytes to the socket until it would block, or until nothing is left.
+    /// Returns false if the socket can't be written to anymore.
+    fn poll_outbound(&mut self, cx: &mut Context<'_>) -> bool {
+        loop {
+            if self.sending.is_none() {
+                match self.outbound.poll_recv(cx) {
+                    Poll::Pending => {
+                        return true;
+                    }
+                    Poll::Ready(Some(bytes)) => {
+                        self.sending = Some((bytes, 0));
+                    }
+                    Poll::Ready(None) => {
+                        // AsyncSocket was dropped
+                        return false;
+                    }
+                }
+            }
+
+            let (len, offset) = match self.sending {
+                Some((ref bytes, ref mut offset)) => {
+                    match Pin::new(&mut self.stream).poll_write(cx, &bytes[*offset..]) {
+                        Poll::Pending => {
+                            return true;
+                        }
+                        Poll::Ready(Ok(0)) => {
+                            debug!("Async socket {} closed for writing", self.event_id);
+                            return false;
+                        }
+                        Poll::Ready(Ok(num_written)) => {
+                            *offset += num_written;
+                            (bytes.len(), *offset)
+                        }
+                        Poll::Ready(Err(e)) => {
+                            debug!(
+                                "Failed to write to async socket {}: {:?}",
+                                self.event_id, &e
+                            );
+                            return false;
+                        }
+                    }
+                }
+                None => {
+                    continue;
+                }
+            };
+
+            if offset >= len {
+                self.sending = None;
+                let prior = self.outbound_bytes.fetch_sub(len, Ordering::SeqCst);
+                if prior >= self.max_outbound_bytes && prior - len < self.max_outbound_bytes {
+                    // the state machine may have been blocked on writing
+                    let _ = self.readiness.set_readiness(Ready::writable());
+                }
+            }
+        }
+    }
+}
+
+impl Future for SocketIO {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if let Poll::Ready(_) = Pin::new(&mut this.hangup).poll(cx) {
+            // AsyncSocket was dropped, so close the connection
+            return Poll::Ready(());
+        }
+        if !this.read_closed {
+            this.read_closed = this.poll_inbound(cx);
+        }
+        if !this.poll_outbound(cx) {
+            // the socket is dead, so make sure the state machine finds out
+            this.inbound.lock().unwrap().hung_up = true;
+            let _ = this.readiness.set_readiness(Ready::readable());
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
+
+/// Runs socket I/O for any number of connections on a tokio runtime.
+#[derive(Debug)]
+pub struct AsyncFrontEnd {
+    runtime: Runtime,
+    max_inbound_bytes: usize,
+    max_outbound_bytes: usize,
+}
+
+impl AsyncFrontEnd {
+    /// Start a runtime with `num_threads` I/O threads.  Each socket may buffer up to
+    /// `max_inbound_bytes` of received data before the runtime stops reading from it, and up to
+    /// `max_outbound_bytes` of unsent data before writes start returning `WouldBlock`.
+    pub fn new(
+        num_threads: usize,
+        max_inbound_bytes: usize,
+        max_outbound_bytes: usize,
+    ) -> Result<AsyncFrontEnd, net_error> {
+        let runtime = Builder::new()
+            .threaded_scheduler()
+            .core_threads(cmp::max(num_threads, 1))
+            .enable_io()
+            .thread_name("net-async-io")
+            .build()
+            .map_err(|e| {
+                warn!("Failed to start async network runtime: {:?}", &e);
+                net_error::SocketError
+            })?;
+
+        Ok(AsyncFrontEnd {
+            runtime,
+            max_inbound_bytes: cmp::max(max_inbound_bytes, 1),
+            max_outbound_bytes: cmp::max(max_outbound_bytes, 1),
+        })
+    }
+
+    /// Hand a connected, non-blocking socket over to the runtime.  The returned `AsyncSocket`
+    /// becomes readable (or writable) in a `mio::Poll` it is registered with whenever the runtime
+    /// receives data for it (or sends its queued data).
+    pub fn start(&self, event_id: usize, stream: net::TcpStream) -> Result<AsyncSocket, net_error> {
+        let peer_addr = stream.peer_addr().map_err(|e| {
+            debug!("Failed to get peer address of {:?}: {:?}", &stream, &e);
+            net_error::SocketError
+        })?;
+        let stream = self
+            .runtime
+            .enter(|| TcpStream::from_std(stream))
+            .map_err(|e| {
+                warn!("Failed to start async I/O on socket {}: {:?}", event_id, &e);
+                net_error::SocketError
+            })?;
+        let _ = stream.set_nodelay(true);
+
+        let inbound = Arc::new(Mutex::new(InboundBuffer::new()));
+        let (outbound_tx, outbound_rx) = unbounded_channel();
+        let outbound_bytes = Arc::new(AtomicUsize::new(0));
+        let (registration, readiness) = Registration::new2();
+        let (hangup_tx, hangup_rx) = oneshot::channel();
+
+        self.runtime.spawn(SocketIO {
+            event_id,
+            stream,
+            buf: vec![0u8; ASYNC_SOCKET_READ_BUF_LEN],
+            inbound: inbound.clone(),
+            max_inbound_bytes: self.max_inbound_bytes,
+            read_closed: false,
+            outbound: outbound_rx,
+            sending: None,
+            outbound_bytes: outbound_bytes.clone(),
+            max_outbound_bytes: self.max_outbound_bytes,
+            readiness,
+            hangup: hangup_rx,
+        });
+
+        Ok(AsyncSocket {
+            event_id,
+            peer_addr,
+            inbound,
+            max_inbound_bytes: self.max_inbound_bytes,
+            outbound: outbound_tx,
+            outbound_bytes,
+            max_outbound_bytes: self.max_outbound_bytes,
+            registration,
+            _hangup: hangup_tx,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::{TcpListener, TcpStream as StdTcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Make a connected pair of sockets, and start async I/O on the first one
+    fn make_async_socket(front_end: &AsyncFrontEnd) -> (AsyncSocket, StdTcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = StdTcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        server.set_nonblocking(true).unwrap();
+        (front_end.start(1, server).unwrap(), client)
+    }
+
+    fn poll_until<F: FnMut() -> bool>(poll: &mio::Poll, mut done: F) {
+        let mut events = mio::Events::with_capacity(16);
+        for _ in 0..100 {
+            if done() {
+                return;
+            }
+            poll.poll(&mut events, Some(Duration::from_millis(100)))
+                .unwrap();
+        }
+        panic!("Timed out");
+    }
+
+    #[test]
+    fn test_async_socket_read_write() {
+        let front_end = AsyncFrontEnd::new(2, 1024, 1024).unwrap();
+        let (mut socket, mut client) = make_async_socket(&front_end);
+
+        let poll = mio::Poll::new().unwrap();
+        poll.register(&socket, Token(1), Ready::all(), PollOpt::edge())
+            .unwrap();
+
+        // nothing to read yet is not a hang-up
+        let mut buf = [0u8; 64];
+        match socket.read(&mut buf) {
+            Err(e) => assert_eq!(e.kind(), io::ErrorKind::WouldBlock),
+            Ok(_) => panic!("Expected read to block"),
+        }
+
+        client.write_all(b"hello world").unwrap();
+        let mut received = vec![];
+        poll_until(&poll, || match socket.read(&mut buf) {
+            Ok(n) => {
+                received.extend_from_slice(&buf[0..n]);
+                received.len() == 11
+            }
+            Err(e) => {
+                assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                false
+            }
+        });
+        assert_eq!(&received[..], b"hello world");
+
+        socket.write_all(b"hello back").unwrap();
+        let mut reply = [0u8; 10];
+        client.read_exact(&mut reply).unwrap();
+        assert_eq!(&reply, b"hello back");
+
+        // hang up
+        drop(client);
+        poll_until(&poll, || match socket.read(&mut buf) {
+            Ok(0) => true,
+            Ok(_) => panic!("Unexpected data"),
+            Err(_) => false,
+        });
+
+        // dropping the async socket closes the connection
+        let (socket, mut client) = make_async_socket(&front_end);
+        drop(socket);
+        client
+            .set_read_timeout(Some(Duration::from_secs(10)))
+            .unwrap();
+        assert_eq!(client.read(&mut buf).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_async_socket_inbound_limit() {
+        let front_end = AsyncFrontEnd::new(1, 1000, 1024).unwrap();
+        let (mut socket, mut client) = make_async_socket(&front_end);
+
+        let poll = mio::Poll::new().unwrap();
+        poll.register(&socket, Token(1), Ready::all(), PollOpt::edge())
+            .unwrap();
+
+        let sent = (0..10000).map(|i| i as u8).collect::<Vec<u8>>();
+        let to_send = sent.clone();
+        let sender = thread::spawn(move || {
+            client.write_all(&to_send).unwrap();
+            client
+        });
+
+        // the runtime stops reading once the buffer is full
+        poll_until(&poll, || socket.num_inbound_bytes() == 1000);
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(socket.num_inbound_bytes(), 1000);
+
+        // ...and resumes as the state machine catches up
+        let mut buf = [0u8; 64];
+        let mut received = vec![];
+        poll_until(&poll, || {
+            loop {
+                match socket.read(&mut buf) {
+                    Ok(n) => {
+                        assert!(n > 0);
+                        received.extend_from_slice(&buf[0..n]);
+                    }
+                    Err(e) => {
+                        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                        break;
+                    }
+                }
+            }
+            assert!(socket.num_inbound_bytes() <= 1000);
+            received.len() == sent.len()
+        });
+        assert_eq!(received, sent);
+        let _client = sender.join().unwrap();
+    }
+
+    #[test]
+    fn test_async_socket_outbound_limit() {
+        let front_end = AsyncFrontEnd::new(1, 1024, 16).unwrap();
+        let (mut socket, client) = make_async_socket(&front_end);
+
+        // the runtime sends what it can, but nothing reads it, so writes eventually block
+        let mut num_written = 0;
+        loop {
+            match socket.write(&[0u8; 1024]) {
+                Ok(n) => {
+                    num_written += n;
+                }
+                Err(e) => {
+                    assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+                    break;
+                }
+            }
+            if num_written > 1024 * 1024 * 1024 {
+                panic!("Writes never blocked");
+            }
+        }
+        assert!(socket.num_outbound_bytes() >= 16);
+        drop(client);
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of BNS names and namespaces, mirroring the checks made by the `bns` boot contract.
+//!
+//! On chain, names and namespaces are ASCII byte buffers drawn from `[a-z0-9-_]`. Unicode names
+//! can only be registered in their punycode form (`xn--...`); how such labels are treated is
+//! chosen explicitly through a `PunycodePolicy`.
+
+use std::error;
+use std::fmt;
+
+/// Longest name the `bns` contract accepts, in bytes (`(buff 48)`)
+pub const BNS_NAME_MAX_LEN: usize = 48;
+/// Longest namespace the `bns` contract accepts, in bytes (`(buff 20)`)
+pub const BNS_NAMESPACE_MAX_LEN: usize = 20;
+/// Prefix marking a punycode-encoded label
+pub const BNS_PUNYCODE_PREFIX: &str = "xn--";
+
+/// How labels carrying the punycode prefix are handled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PunycodePolicy {
+    /// Treat `xn--` labels as plain ASCII, exactly as the `bns` contract does.
+    Opaque,
+    /// Accept `xn--` labels only if they decode to a valid Unicode label.
+    Strict,
+    /// Refuse `xn--` labels altogether.
+    Reject,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BnsNameError {
+    /// The label is empty
+    Empty,
+    /// The label is longer than the given number of bytes
+    TooLong(usize),
+    /// The label contains a character outside of `[a-z0-9-_]`
+    InvalidChar(char),
+    /// A fully-qualified name is not of the form `name.namespace`
+    NotFullyQualified,
+    /// The label is punycode-encoded, which the policy refuses
+    PunycodeRejected,
+    /// The label is punycode-encoded, but does not decode to a valid Unicode label
+    InvalidPunycode,
+}
+
+impl fmt::Display for BnsNameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BnsNameError::Empty => write!(f, "empty label"),
+            BnsNameError::TooLong(max) => write!(f, "label is longer than {} bytes", max),
+            BnsNameError::InvalidChar(c) => write!(f, "invalid character {:?}", c),
+            BnsNameError::NotFullyQualified => write!(f, "expected 'name.namespace'"),
+            BnsNameError::PunycodeRejected => write!(f, "punycode labels are not accepted"),
+            BnsNameError::InvalidPunycode => write!(f, "invalid punycode label"),
+        }
+    }
+}
+
+impl error::Error for BnsNameError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+/// Is this byte allowed in a name or namespace (`is-char-valid` in the `bns` contract)?
+pub fn is_valid_bns_char(c: u8) -> bool {
+    match c {
+        b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => true,
+        _ => false,
+    }
+}
+
+/// Bring user input to the form used on chain: surrounding whitespace is dropped and ASCII
+/// letters are lowercased. Non-ASCII characters are kept, and will fail validation; Unicode
+/// names must be punycode-encoded before being looked up.
+pub fn normalize_bns_label(label: &str) -> String {
+    label.trim().to_ascii_lowercase()
+}
+
+/// Check a name (the part before the `.`) against the `bns` contract's rules.
+pub fn validate_bns_name(name: &str, policy: PunycodePolicy) -> Result<(), BnsNameError> {
+    validate_bns_label(name, BNS_NAME_MAX_LEN, policy)
+}
+
+/// Check a namespace (the part after the `.`) against the `bns` contract's rules.
+pub fn validate_bns_namespace(namespace: &str, policy: PunycodePolicy) -> Result<(), BnsNameError> {
+    validate_bns_label(namespace, BNS_NAMESPACE_MAX_LEN, policy)
+}
+
+/// Split a fully-qualified `name.namespace` into its validated name and namespace.
+/// The input is not normalized; see `normalize_bns_label`.
+pub fn parse_bns_fully_qualified_name(
+    fqn: &str,
+    policy: PunycodePolicy,
+) -> Result<(String, String), BnsNameError> {
+    let components: Vec<&str> = fqn.split('.').collect();
+    if components.len() != 2 {
+        return Err(BnsNameError::NotFullyQualified);
+    }
+    validate_bns_name(components[0], policy)?;
+    validate_bns_namespace(components[1], policy)?;
+    Ok((components[0].to_string(), components[1].to_string()))
+}
+
+/// Render a validated label for display, decoding it if it is punycode-encoded.
+pub fn bns_label_to_unicode(label: &str) -> Result<String, BnsNameError> {
+    if label.starts_with(BNS_PUNYCODE_PREFIX) {
+        decode_punycode_label(&label[BNS_PUNYCODE_PREFIX.len()..])
+    } else {
+        Ok(label.to_string())
+    }
+}
+
+fn validate_bns_label(
+    label: &str,
+    max_len: usize,
+    policy: PunycodePolicy,
+) -> Result<(), BnsNameError> {
+    if label.is_empty() {
+        return Err(BnsNameError::Empty);
+    }
+    if let Some(c) = label
+        .chars()
+        .find(|c| !c.is_ascii() || !is_valid_bns_char(*c as u8))
+    {
+        return Err(BnsNameError::InvalidChar(c));
+    }
+    if label.len() > max_len {
+        return Err(BnsNameError::TooLong(max_len));
+    }
+    if label.starts_with(BNS_PUNYCODE_PREFIX) {
+        match policy {
+            PunycodePolicy::Opaque => {}
+            PunycodePolicy::Reject => return Err(BnsNameError::PunycodeRejected),
+            PunycodePolicy::Strict => {
+                bns_label_to_unicode(label)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode the part of a punycode label following `xn--` (RFC 3492). The result must contain
+/// at least one non-ASCII character, no uppercase or control characters, and only ASCII
+/// characters that are valid on their own.
+fn decode_punycode_label(encoded: &str) -> Result<String, BnsNameError> {
+    const BASE: u32 = 36;
+    const TMIN: u32 = 1;
+    const TMAX: u32 = 26;
+    const INITIAL_BIAS: u32 = 72;
+    const INITIAL_N: u32 = 128;
+
+    let (basic, extended) = match encoded.rfind('-') {
+        Some(i) => (&encoded[..i], &encoded[i + 1..]),
+        None => ("", encoded),
+    };
+    if extended.is_empty() {
+        return Err(BnsNameError::InvalidPunycode);
+    }
+
+    let mut output: Vec<char> = basic.chars().collect();
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut weight: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next() {
+                Some(c @ b'a'..=b'z') => (c - b'a') as u32,
+                Some(c @ b'0'..=b'9') => (c - b'0') as u32 + 26,
+                _ => return Err(BnsNameError::InvalidPunycode),
+            };
+            i = digit
+                .checked_mul(weight)
+                .and_then(|x| i.checked_add(x))
+                .ok_or(BnsNameError::InvalidPunycode)?;
+            let t = if k <= bias {
+                TMIN
+            } else if k >= bias + TMAX {
+                TMAX
+            } else {
+                k - bias
+            };
+            if digit < t {
+                break;
+            }
+            weight = weight
+                .checked_mul(BASE - t)
+                .ok_or(BnsNameError::InvalidPunycode)?;
+            k += BASE;
+        }
+        let len = output.len() as u32 + 1;
+        bias = punycode_adapt(i - old_i, len, old_i == 0);
+        n = n
+            .checked_add(i / len)
+            .ok_or(BnsNameError::InvalidPunycode)?;
+        i %= len;
+        let c = ::std::char::from_u32(n).ok_or(BnsNameError::InvalidPunycode)?;
+        output.insert(i as usize, c);
+        i += 1;
+    }
+
+    if !output.iter().any(|c| !c.is_ascii()) {
+        return Err(BnsNameError::InvalidPunycode);
+    }
+    for c in output.iter() {
+        let valid = if c.is_ascii() {
+            is_valid_bns_char(*c as u8)
+        } else {
+            !c.is_uppercase() && !c.is_control() && !c.is_whitespace()
+        };
+        if !valid {
+            return Err(BnsNameError::InvalidPunycode);
+        }
+    }
+    Ok(output.into_iter().collect())
+}
+
+fn punycode_adapt(delta: u32, num_points: u32, first_time: bool) -> u32 {
+    const BASE: u32 = 36;
+    const TMIN: u32 = 1;
+    const TMAX: u32 = 26;
+    const SKEW: u32 = 38;
+    const DAMP: u32 = 700;
+
+    let mut delta = if first_time { delta / DAMP } else { delta / 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - TMIN) * TMAX) / 2 {
+        delta /= BASE - TMIN;
+        k += BASE;
+    }
+    k + ((BASE - TMIN + 1) * delta) / (delta + SKEW)
+}
@@ -24,10 +24,14 @@ use std::convert::From;
 use std::convert::TryFrom;
 use std::fs;
 
+use monitoring;
 use util::db::tx_begin_immediate;
 use util::db::DBConn;
 use util::db::Error as db_error;
-use util::db::{query_count, query_int, query_row, query_rows, u64_to_sql, FromColumn, FromRow};
+use util::db::{
+    query_count, query_int, query_row, query_row_columns, query_rows, u64_to_sql, FromColumn,
+    FromRow,
+};
 
 use util;
 use util::hash::{bin_bytes, hex_bytes, to_bin, to_hex, Hash160};
@@ -42,7 +46,7 @@ use crate::codec::StacksMessageCodec;
 use crate::types::chainstate::StacksBlockId;
 use burnchains::Txid;
 
-use super::{AtlasConfig, Attachment, AttachmentInstance};
+use super::{AtlasConfig, Attachment, AttachmentInstance, AttachmentMirror};
 
 pub const ATLASDB_VERSION: &'static str = "1";
 
@@ -71,6 +75,31 @@ const ATLASDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     "CREATE TABLE db_config(version TEXT NOT NULL);",
 ];
 
+/// Attachments that the retention policy must never delete, and an index to find an
+/// attachment's instances when it is deleted.  Created on open if they are missing, since they
+/// were added after the initial schema.
+const ATLASDB_PRUNING_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS pinned_attachments(
+        hash TEXT PRIMARY KEY NOT NULL
+    );"#,
+    "CREATE INDEX IF NOT EXISTS index_attachment_instances_content_hash ON attachment_instances(content_hash);",
+];
+
+/// Attachments whose content has been uploaded to the mirror.  Created on open if it is
+/// missing, since it was added after the initial schema.
+const ATLASDB_MIRROR_SCHEMA: &'static [&'static str] = &[r#"
+    CREATE TABLE IF NOT EXISTS mirrored_attachments(
+        hash TEXT PRIMARY KEY NOT NULL
+    );"#];
+
+/// What one sweep of the attachment retention policy deleted
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AttachmentsPruneResult {
+    pub attachments: u64,
+    pub bytes: u64,
+}
+
 impl FromRow<Attachment> for Attachment {
     fn from_row<'a>(row: &'a Row) -> Result<Attachment, db_error> {
         let content: Vec<u8> = row.get_unwrap("content");
@@ -127,6 +156,12 @@ impl AtlasDB {
         for row_text in ATLASDB_INITIAL_SCHEMA {
             tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
         }
+        for row_text in ATLASDB_PRUNING_SCHEMA {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
+        for row_text in ATLASDB_MIRROR_SCHEMA {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
 
         tx.execute(
             "INSERT INTO db_config (version) VALUES (?1)",
@@ -159,15 +194,24 @@ impl AtlasDB {
         contract_id: &QualifiedContractIdentifier,
         attachment: &Attachment,
     ) -> bool {
-        if !self.atlas_config.contracts.contains(contract_id) {
+        if !self.atlas_config.is_tracked(contract_id) {
             info!(
                 "Atlas: will discard posted attachment - {} not in supported contracts",
                 contract_id
             );
             return false;
         }
-        if attachment.content.len() as u32 > self.atlas_config.attachments_max_size {
-            info!("Atlas: will discard posted attachment - attachment too large");
+        let max_size = self
+            .atlas_config
+            .get_domain(contract_id)
+            .attachments_max_size;
+        if attachment.content.len() as u32 > max_size {
+            info!(
+                "Atlas: will discard posted attachment - attachment too large for {} ({} > {})",
+                contract_id,
+                attachment.content.len(),
+                max_size
+            );
             return false;
         }
         true
@@ -207,6 +251,15 @@ impl AtlasDB {
         };
         if create_flag {
             db.instantiate()?;
+        } else if readwrite {
+            let tx = db.tx_begin()?;
+            for row_text in ATLASDB_PRUNING_SCHEMA {
+                tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+            }
+            for row_text in ATLASDB_MIRROR_SCHEMA {
+                tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+            }
+            tx.commit().map_err(db_error::SqliteError)?;
         }
         Ok(db)
     }
@@ -403,12 +456,43 @@ impl AtlasDB {
             - self
                 .atlas_config
                 .unresolved_attachment_instances_expire_after as i64;
+        let domains: Vec<_> = self
+            .atlas_config
+            .domains
+            .iter()
+            .map(|(contract_id, domain)| (contract_id.clone(), domain.clone()))
+            .chain(self.atlas_config.watch_list.entries().into_iter())
+            .map(|(contract_id, domain)| {
+                (
+                    contract_id.to_string(),
+                    now - domain.unresolved_attachment_instances_expire_after as i64,
+                )
+            })
+            .collect();
+
         let tx = self.tx_begin()?;
-        let res = tx.execute(
-            "DELETE FROM attachment_instances WHERE is_available = 0 AND created_at < ?",
-            &[&cut_off as &dyn ToSql],
-        );
-        res.map_err(db_error::SqliteError)?;
+        // contracts with their own retention policy
+        for (contract_id, domain_cut_off) in domains.iter() {
+            tx.execute(
+                "DELETE FROM attachment_instances WHERE is_available = 0 AND contract_id = ? AND created_at < ?",
+                &[contract_id as &dyn ToSql, domain_cut_off as &dyn ToSql],
+            )
+            .map_err(db_error::SqliteError)?;
+        }
+
+        // everything else
+        let mut qry = "DELETE FROM attachment_instances WHERE is_available = 0 AND created_at < ?"
+            .to_string();
+        let mut args: Vec<&dyn ToSql> = vec![&cut_off];
+        if !domains.is_empty() {
+            let placeholders = vec!["?"; domains.len()].join(",");
+            qry.push_str(&format!(" AND contract_id NOT IN ({})", placeholders));
+            for (contract_id, _) in domains.iter() {
+                args.push(contract_id);
+            }
+        }
+        tx.execute(&qry, args.as_slice())
+            .map_err(db_error::SqliteError)?;
         tx.commit().map_err(db_error::SqliteError)?;
         Ok(())
     }
@@ -444,6 +528,36 @@ impl AtlasDB {
         Ok(row)
     }
 
+    /// Find an attachment we store, or else read it from the mirror if one is configured to
+    /// serve reads.  Failing to reach the mirror is logged and treated as a miss.
+    pub fn find_attachment_or_mirrored(
+        &mut self,
+        content_hash: &Hash160,
+    ) -> Result<Option<Attachment>, db_error> {
+        if let Some(attachment) = self.find_attachment(content_hash)? {
+            return Ok(Some(attachment));
+        }
+        let mirror = match self.atlas_config.mirror {
+            Some(ref config) if config.read_fallback => AttachmentMirror::new(config.clone()),
+            _ => return Ok(None),
+        };
+        match mirror.get_attachment(content_hash) {
+            Ok(Some(attachment)) => {
+                monitoring::increment_attachments_mirror_reads_counter();
+                Ok(Some(attachment))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                warn!(
+                    "Atlas: failed to read attachment {} from mirror: {:?}",
+                    content_hash, &e
+                );
+                monitoring::increment_attachments_mirror_failures_counter();
+                Ok(None)
+            }
+        }
+    }
+
     pub fn insert_uninstantiated_attachment_instance(
         &mut self,
         attachment: &AttachmentInstance,
@@ -471,4 +585,169 @@ impl AtlasDB {
         tx.commit().map_err(db_error::SqliteError)?;
         Ok(())
     }
+
+    /// Keep this attachment whatever the retention policy says
+    pub fn pin_attachment(&mut self, content_hash: &Hash160) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO pinned_attachments (hash) VALUES (?1)",
+            &[content_hash as &dyn ToSql],
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Let the retention policy delete this attachment again
+    pub fn unpin_attachment(&mut self, content_hash: &Hash160) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        tx.execute(
+            "DELETE FROM pinned_attachments WHERE hash = ?1",
+            &[content_hash as &dyn ToSql],
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    pub fn get_pinned_attachments(&self) -> Result<Vec<Hash160>, db_error> {
+        let qry = "SELECT hash FROM pinned_attachments ORDER BY hash ASC";
+        query_row_columns::<Hash160, _>(&self.conn, qry, NO_PARAMS, "hash")
+    }
+
+    /// Total size of the content of the attachments we store and serve
+    pub fn get_attachments_total_size(&self) -> Result<u64, db_error> {
+        let qry =
+            "SELECT IFNULL(SUM(LENGTH(content)), 0) FROM attachments WHERE was_instantiated = 1";
+        let size = query_int(&self.conn, qry, NO_PARAMS)?;
+        Ok(size as u64)
+    }
+
+    /// Stored attachments that have not been uploaded to the mirror yet, oldest first
+    pub fn get_attachments_to_mirror(&self, limit: u64) -> Result<Vec<Attachment>, db_error> {
+        let qry = "SELECT content, hash FROM attachments WHERE was_instantiated = 1 \
+                   AND hash NOT IN (SELECT hash FROM mirrored_attachments) \
+                   ORDER BY created_at ASC LIMIT ?1";
+        let args = [&u64_to_sql(limit)? as &dyn ToSql];
+        query_rows::<Attachment, _>(&self.conn, qry, &args)
+    }
+
+    pub fn mark_attachment_mirrored(&mut self, content_hash: &Hash160) -> Result<(), db_error> {
+        let tx = self.tx_begin()?;
+        tx.execute(
+            "INSERT OR IGNORE INTO mirrored_attachments (hash) VALUES (?1)",
+            &[content_hash as &dyn ToSql],
+        )
+        .map_err(db_error::SqliteError)?;
+        tx.commit().map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// How many attachments have been uploaded to the mirror, including ones since pruned
+    /// locally
+    pub fn count_mirrored_attachments(&self) -> Result<u64, db_error> {
+        let count = query_count(
+            &self.conn,
+            "SELECT COUNT(*) FROM mirrored_attachments",
+            NO_PARAMS,
+        )?;
+        Ok(count as u64)
+    }
+
+    /// Apply the retention policy: delete the attachments that were last announced too long
+    /// before the given Stacks chain tip height, and then the ones that were last announced
+    /// longest ago until what is left fits in the size limit.  A deleted attachment's instances
+    /// are deleted with it, so it is no longer advertised in our attachment inventories.
+    ///
+    /// If a mirror is configured, only attachments that were already uploaded to it are
+    /// deleted.  If the mirror also serves reads, their instances are kept: the attachments are
+    /// still advertised, and served from the mirror.
+    pub fn prune_attachments(
+        &mut self,
+        stacks_tip_height: u64,
+    ) -> Result<AttachmentsPruneResult, db_error> {
+        let policy = self.atlas_config.retention.clone();
+        let mut result = AttachmentsPruneResult::default();
+        if !policy.is_enabled() {
+            return Ok(result);
+        }
+
+        let (only_mirrored, keep_instances) = match self.atlas_config.mirror {
+            Some(ref config) => (true, config.read_fallback),
+            None => (false, false),
+        };
+
+        // oldest first
+        let qry = format!(
+            "SELECT a.hash, LENGTH(a.content), MAX(i.block_height) AS last_height FROM attachments a \
+             JOIN attachment_instances i ON i.content_hash = a.hash \
+             WHERE a.was_instantiated = 1 AND a.hash NOT IN (SELECT hash FROM pinned_attachments) {} \
+             GROUP BY a.hash ORDER BY last_height ASC, a.created_at ASC",
+            if only_mirrored {
+                "AND a.hash IN (SELECT hash FROM mirrored_attachments)"
+            } else {
+                ""
+            }
+        );
+        let candidates = {
+            let mut stmt = self.conn.prepare(&qry).map_err(db_error::SqliteError)?;
+            let mut rows = stmt.query(NO_PARAMS).map_err(db_error::SqliteError)?;
+            let mut candidates = vec![];
+            while let Some(row) = rows.next().map_err(db_error::SqliteError)? {
+                let content_hash = Hash160::from_column(row, "hash")?;
+                let size: i64 = row.get_unwrap(1);
+                let last_height: i64 = row.get_unwrap(2);
+                candidates.push((content_hash, size as u64, last_height as u64));
+            }
+            candidates
+        };
+
+        let mut total_size = self.get_attachments_total_size()?;
+        let mut to_delete = vec![];
+        for (content_hash, size, last_height) in candidates.into_iter() {
+            let too_old = match policy.max_age_blocks {
+                Some(max_age) => last_height + max_age < stacks_tip_height,
+                None => false,
+            };
+            let too_big = match policy.max_total_size {
+                Some(max_size) => total_size > max_size,
+                None => false,
+            };
+            if !too_old && !too_big {
+                // candidates are sorted oldest first, so nothing after this is too old either
+                break;
+            }
+            total_size = total_size.saturating_sub(size);
+            result.attachments += 1;
+            result.bytes += size;
+            to_delete.push(content_hash);
+        }
+
+        if to_delete.is_empty() {
+            return Ok(result);
+        }
+
+        let tx = self.tx_begin()?;
+        for content_hash in to_delete.iter() {
+            tx.execute(
+                "DELETE FROM attachments WHERE hash = ?1",
+                &[content_hash as &dyn ToSql],
+            )
+            .map_err(db_error::SqliteError)?;
+            if !keep_instances {
+                tx.execute(
+                    "DELETE FROM attachment_instances WHERE content_hash = ?1",
+                    &[content_hash as &dyn ToSql],
+                )
+                .map_err(db_error::SqliteError)?;
+            }
+        }
+        tx.commit().map_err(db_error::SqliteError)?;
+
+        debug!(
+            "Atlas: pruned {} attachments ({} bytes)",
+            result.attachments, result.bytes
+        );
+        Ok(result)
+    }
 }
@@ -27,10 +27,14 @@ use chainstate::stacks::db::StacksChainState;
 use net::atlas::MAX_RETRY_DELAY;
 use net::connection::ConnectionOptions;
 use net::dns::*;
+use net::download::DataUrlHostStats;
 use net::p2p::PeerNetwork;
 use net::server::HttpPeer;
+use net::AttachmentContentRange;
 use net::Error as net_error;
+use net::HttpByteRange;
 use net::NeighborKey;
+use net::ServiceFlags;
 use net::{GetAttachmentResponse, GetAttachmentsInvResponse};
 use net::{HttpRequestMetadata, HttpRequestType, HttpResponseType, PeerHost, Requestable};
 use util::hash::{Hash160, MerkleHashFunc};
@@ -41,7 +45,10 @@ use vm::types::QualifiedContractIdentifier;
 
 use crate::types::chainstate::{BlockHeaderHash, StacksBlockHeader};
 
-use super::{AtlasDB, Attachment, AttachmentInstance, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST};
+use super::{
+    AtlasDB, Attachment, AttachmentInstance, ATTACHMENT_RANGE_LEN,
+    MAX_ATTACHMENTS_PER_BATCH_REQUEST, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
+};
 
 use rand::thread_rng;
 use rand::Rng;
@@ -49,11 +56,10 @@ use std::cmp;
 
 #[derive(Debug)]
 pub struct AttachmentsDownloader {
-    priority_queue: BinaryHeap<AttachmentsBatch>,
+    pub priority_queue: BinaryHeap<AttachmentsBatch>,
     initial_batch: Vec<AttachmentInstance>,
     ongoing_batch: Option<AttachmentsBatchStateMachine>,
     processed_batches: Vec<AttachmentsBatch>,
-    reliability_reports: HashMap<UrlString, ReliabilityReport>,
 }
 
 impl AttachmentsDownloader {
@@ -62,7 +68,6 @@ impl AttachmentsDownloader {
             priority_queue: BinaryHeap::new(),
             ongoing_batch: None,
             processed_batches: vec![],
-            reliability_reports: HashMap::new(),
             initial_batch,
         }
     }
@@ -97,6 +102,31 @@ impl AttachmentsDownloader {
         }
     }
 
+    /// Make the queued batches that wait on any of the given attachments ready for a retry right
+    /// away, since a peer just announced that they are available.  Returns how many batches were
+    /// woken up.
+    pub fn wake_batches(&mut self, available: &[(StacksBlockId, Hash160)]) -> usize {
+        let now = get_epoch_time_secs();
+        let mut batches = self.priority_queue.drain().collect::<Vec<_>>();
+        let mut woken = 0;
+        for batch in batches.iter_mut() {
+            if batch.retry_deadline < now {
+                // already ready
+                continue;
+            }
+            let wanted = available.iter().any(|(index_block_hash, content_hash)| {
+                batch.index_block_hash == *index_block_hash && batch.waits_for(content_hash)
+            });
+            if wanted {
+                batch.retry_deadline = 0;
+                woken += 1;
+            }
+        }
+        // re-sort, since the retry deadlines changed
+        self.priority_queue = BinaryHeap::from(batches);
+        woken
+    }
+
     pub fn run(
         &mut self,
         dns_client: &mut DNSClient,
@@ -125,13 +155,49 @@ impl AttachmentsDownloader {
                     return Ok((vec![], vec![]));
                 }
 
+                // only ask peers that advertise that they serve attachments, unless none of them
+                // do (i.e. they all predate the ATLAS service bit)
+                let sync_peers = network.get_outbound_sync_peers();
+                let atlas_peers: Vec<NeighborKey> = sync_peers
+                    .iter()
+                    .filter(|peer| {
+                        network
+                            .get_convo(peer)
+                            .map(|convo| convo.supports_service(ServiceFlags::ATLAS))
+                            .unwrap_or(false)
+                    })
+                    .cloned()
+                    .collect();
+                let candidate_peers = if atlas_peers.len() > 0 {
+                    atlas_peers
+                } else {
+                    debug!("Atlas: no sync peers advertise the Atlas service; asking all of them");
+                    sync_peers.into_iter().collect()
+                };
+
                 let mut peers = HashMap::new();
-                for peer in network.get_outbound_sync_peers() {
+                let mut batch_peers = HashSet::new();
+                let mut range_peers = HashSet::new();
+                for peer in candidate_peers {
                     if let Some(peer_url) = network.get_data_url(&peer) {
-                        let report = match self.reliability_reports.get(&peer_url) {
-                            Some(report) => report.clone(),
+                        let report = match network.data_url_stats.get_host_stats(&peer_url) {
+                            Some(stats) => ReliabilityReport::from_host_stats(stats),
                             None => ReliabilityReport::empty(),
                         };
+                        let serves_batches = network
+                            .get_convo(&peer)
+                            .map(|convo| convo.supports_service(ServiceFlags::ATTACHMENTS_BATCH))
+                            .unwrap_or(false);
+                        if serves_batches {
+                            batch_peers.insert(peer_url.clone());
+                        }
+                        let serves_ranges = network
+                            .get_convo(&peer)
+                            .map(|convo| convo.supports_service(ServiceFlags::ATTACHMENT_RANGES))
+                            .unwrap_or(false);
+                        if serves_ranges {
+                            range_peers.insert(peer_url.clone());
+                        }
                         peers.insert(peer_url, report);
                     }
                 }
@@ -149,11 +215,13 @@ impl AttachmentsDownloader {
                     }
                 };
 
-                let ctx = AttachmentsBatchStateContext::new(
+                let mut ctx = AttachmentsBatchStateContext::new(
                     attachments_batch,
                     peers,
                     &network.connection_opts,
                 );
+                ctx.batch_peers = batch_peers;
+                ctx.range_peers = range_peers;
                 AttachmentsBatchStateMachine::new(ctx)
             }
         };
@@ -164,14 +232,29 @@ impl AttachmentsDownloader {
         match progress {
             AttachmentsBatchStateMachine::Done(ref mut context) => {
                 for attachment in context.attachments.drain() {
-                    let attachments_instances = network
+                    let attachments_instances: Vec<_> = network
                         .atlasdb
                         .find_all_attachment_instances(&attachment.hash())
-                        .map_err(|e| net_error::DBError(e))?;
-                    network
-                        .atlasdb
-                        .insert_instantiated_attachment(&attachment)
-                        .map_err(|e| net_error::DBError(e))?;
+                        .map_err(|e| net_error::DBError(e))?
+                        .into_iter()
+                        .filter(|attachment_instance| {
+                            network.atlasdb.atlas_config.is_attachment_in_domain(
+                                &attachment_instance.contract_id,
+                                &attachment,
+                            )
+                        })
+                        .collect();
+                    if attachments_instances.is_empty() {
+                        warn!(
+                            "Atlas: discarding downloaded attachment {} - exceeds the limits of every contract that signaled it",
+                            attachment.hash()
+                        );
+                    } else {
+                        network
+                            .atlasdb
+                            .insert_instantiated_attachment(&attachment)
+                            .map_err(|e| net_error::DBError(e))?;
+                    }
                     for attachment_instance in attachments_instances.into_iter() {
                         resolved_attachments.push((attachment_instance, attachment.clone()));
                     }
@@ -191,11 +274,6 @@ impl AttachmentsDownloader {
                     .atlasdb
                     .evict_expired_unresolved_attachment_instances()?;
 
-                // Update reliability reports
-                for (peer_url, report) in context.peers.drain() {
-                    self.reliability_reports.insert(peer_url, report);
-                }
-
                 // Re-insert AttachmentsBatch back to the queue if not fully processed
                 if !context.attachments_batch.has_fully_succeed() {
                     context.attachments_batch.bump_retry_count();
@@ -250,6 +328,16 @@ impl AttachmentsDownloader {
 
             // Do we already have a matching validated attachment
             if let Ok(Some(entry)) = atlasdb.find_attachment(&attachment_instance.content_hash) {
+                if !atlasdb
+                    .atlas_config
+                    .is_attachment_in_domain(&attachment_instance.contract_id, &entry)
+                {
+                    warn!(
+                        "Atlas: dropping attachment instance {:?} - attachment exceeds the limits of {}",
+                        attachment_instance, attachment_instance.contract_id
+                    );
+                    continue;
+                }
                 atlasdb
                     .insert_uninstantiated_attachment_instance(&attachment_instance, true)
                     .map_err(|e| net_error::DBError(e))?;
@@ -264,6 +352,16 @@ impl AttachmentsDownloader {
             if let Ok(Some(attachment)) =
                 atlasdb.find_uninstantiated_attachment(&attachment_instance.content_hash)
             {
+                if !atlasdb
+                    .atlas_config
+                    .is_attachment_in_domain(&attachment_instance.contract_id, &attachment)
+                {
+                    warn!(
+                        "Atlas: dropping attachment instance {:?} - attachment exceeds the limits of {}",
+                        attachment_instance, attachment_instance.contract_id
+                    );
+                    continue;
+                }
                 atlasdb
                     .insert_instantiated_attachment(&attachment)
                     .map_err(|e| net_error::DBError(e))?;
@@ -307,6 +405,10 @@ impl AttachmentsDownloader {
 pub struct AttachmentsBatchStateContext {
     pub attachments_batch: AttachmentsBatch,
     pub peers: HashMap<UrlString, ReliabilityReport>,
+    /// peers that advertise ServiceFlags::ATTACHMENTS_BATCH
+    pub batch_peers: HashSet<UrlString>,
+    /// peers that advertise ServiceFlags::ATTACHMENT_RANGES
+    pub range_peers: HashSet<UrlString>,
     pub connection_options: ConnectionOptions,
     pub dns_lookups: HashMap<UrlString, Option<Vec<SocketAddr>>>,
     pub inventories: HashMap<
@@ -314,6 +416,8 @@ pub struct AttachmentsBatchStateContext {
         HashMap<UrlString, GetAttachmentsInvResponse>,
     >,
     pub attachments: HashSet<Attachment>,
+    /// whether the last round of requests got us more of an attachment that's still incomplete
+    pub partial_progress: bool,
     pub events_to_deregister: Vec<usize>,
 }
 
@@ -326,10 +430,13 @@ impl AttachmentsBatchStateContext {
         AttachmentsBatchStateContext {
             attachments_batch,
             peers,
+            batch_peers: HashSet::new(),
+            range_peers: HashSet::new(),
             connection_options: connection_options.clone(),
             dns_lookups: HashMap::new(),
             inventories: HashMap::new(),
             attachments: HashSet::new(),
+            partial_progress: false,
             events_to_deregister: vec![],
         }
     }
@@ -365,7 +472,12 @@ impl AttachmentsBatchStateContext {
 
     pub fn get_prioritized_attachments_requests(&self) -> BinaryHeap<AttachmentRequest> {
         let mut queue = BinaryHeap::new();
-        let mut enqueued = HashSet::new();
+        // attachments we already got from a batch request don't need to be asked for again
+        let mut enqueued: HashSet<Hash160> = self
+            .attachments
+            .iter()
+            .map(|attachment| attachment.hash())
+            .collect();
         for ((contract_id, pages, _), peers_responses) in self.inventories.iter() {
             let missing_attachments = match self
                 .attachments_batch
@@ -435,17 +547,74 @@ impl AttachmentsBatchStateContext {
                 }
 
                 // Success, we found at least one inventory including the attachment we're looking for.
-                let request = AttachmentRequest {
+                let mut request = AttachmentRequest {
                     sources,
                     content_hash: content_hash.clone(),
+                    range_start: None,
                 };
-                enqueued.insert(content_hash);
+                // Peers that serve byte ranges get asked for the attachment a range at a time,
+                // picking up after whatever we already have of it.
+                if self
+                    .range_peers
+                    .contains(request.get_most_reliable_source().0)
+                {
+                    request.range_start =
+                        Some(self.attachments_batch.partial_content_len(content_hash));
+                }
+                enqueued.insert(content_hash.clone());
                 queue.push(request);
             }
         }
         queue
     }
 
+    /// Group the attachments we're missing by the peer we'd ask for each of them, so that each
+    /// peer gets asked for up to `max_attachments_per_request` attachments at once.  Attachments
+    /// whose best source doesn't serve batches are left for the one-by-one requests.
+    pub fn get_prioritized_attachments_batch_requests(
+        &self,
+    ) -> BinaryHeap<AttachmentsBatchRequest> {
+        let batch_size = cmp::min(
+            cmp::max(self.connection_options.max_attachments_per_request, 1) as usize,
+            MAX_ATTACHMENTS_PER_BATCH_REQUEST,
+        );
+
+        let mut requests_per_peer: HashMap<UrlString, Vec<Hash160>> = HashMap::new();
+        for request in self
+            .get_prioritized_attachments_requests()
+            .into_sorted_vec()
+        {
+            let (peer_url, _) = request.get_most_reliable_source();
+            if !self.batch_peers.contains(peer_url) {
+                continue;
+            }
+            match requests_per_peer.entry(peer_url.clone()) {
+                Entry::Occupied(content_hashes) => {
+                    content_hashes.into_mut().push(request.content_hash);
+                }
+                Entry::Vacant(v) => {
+                    v.insert(vec![request.content_hash]);
+                }
+            };
+        }
+
+        let mut queue = BinaryHeap::new();
+        for (peer_url, content_hashes) in requests_per_peer.into_iter() {
+            let reliability_report = self
+                .peers
+                .get(&peer_url)
+                .expect("Atlas: unable to retrieve reliability report for peer");
+            for chunk in content_hashes.chunks(batch_size) {
+                queue.push(AttachmentsBatchRequest {
+                    url: peer_url.clone(),
+                    reliability_report: reliability_report.clone(),
+                    content_hashes: chunk.to_vec(),
+                });
+            }
+        }
+        queue
+    }
+
     pub fn extend_with_dns_lookups(
         mut self,
         results: &mut BatchedDNSLookupsResults,
@@ -496,13 +665,105 @@ impl AttachmentsBatchStateContext {
         mut self,
         results: &mut BatchedRequestsResult<AttachmentRequest>,
     ) -> AttachmentsBatchStateContext {
+        self.partial_progress = false;
         for (request, response) in results.succeeded.drain() {
             let report = self
                 .peers
                 .get_mut(request.get_url())
                 .expect("Atlas: unable to retrieve reliability report for peer");
-            if let Some(HttpResponseType::GetAttachment(_, response)) = response {
-                self.attachments.insert(response.attachment);
+            match response {
+                Some(HttpResponseType::GetAttachment(_, response)) => {
+                    self.attachments_batch
+                        .partial_contents
+                        .remove(&request.content_hash);
+                    self.attachments.insert(response.attachment);
+                    report.bump_successful_requests();
+                }
+                Some(HttpResponseType::GetAttachmentContent(_, range)) => {
+                    let had_len = self
+                        .attachments_batch
+                        .partial_content_len(&request.content_hash);
+                    match self.attachments_batch.add_content_range(
+                        &request.content_hash,
+                        range,
+                        self.connection_options.maximum_attachment_size,
+                    ) {
+                        Ok(Some(attachment)) => {
+                            self.attachments.insert(attachment);
+                            report.bump_successful_requests();
+                        }
+                        Ok(None) => {
+                            // a peer that keeps sending the same range doesn't count as progress
+                            if self
+                                .attachments_batch
+                                .partial_content_len(&request.content_hash)
+                                > had_len
+                            {
+                                self.partial_progress = true;
+                            }
+                            report.bump_successful_requests();
+                        }
+                        Err(e) => {
+                            warn!(
+                                "Atlas: discarding content of attachment {} from {} - {}",
+                                request.content_hash,
+                                request.get_url(),
+                                e
+                            );
+                            report.bump_failed_requests();
+                        }
+                    }
+                }
+                _ => {
+                    report.bump_failed_requests();
+                }
+            }
+        }
+        let mut events_ids = results
+            .faulty_peers
+            .iter()
+            .map(|(k, _)| *k)
+            .collect::<Vec<usize>>();
+        self.events_to_deregister.append(&mut events_ids);
+
+        self
+    }
+
+    pub fn extend_with_attachments_batches(
+        mut self,
+        results: &mut BatchedRequestsResult<AttachmentsBatchRequest>,
+    ) -> AttachmentsBatchStateContext {
+        for (request, response) in results.succeeded.drain() {
+            let report = self
+                .peers
+                .get_mut(request.get_url())
+                .expect("Atlas: unable to retrieve reliability report for peer");
+            if let Some(HttpResponseType::GetAttachmentsBatch(_, response)) = response {
+                for GetAttachmentResponse { attachment } in response.attachments.into_iter() {
+                    // a batch response isn't checked while it streams in, so check each
+                    // attachment here
+                    let content_hash = attachment.hash();
+                    if !request.content_hashes.contains(&content_hash) {
+                        warn!(
+                            "Atlas: peer {} sent attachment {}, which we did not ask for",
+                            request.get_url(),
+                            content_hash
+                        );
+                        continue;
+                    }
+                    if attachment.content.len() as u64
+                        > self.connection_options.maximum_attachment_size as u64
+                    {
+                        warn!(
+                            "Atlas: discarding attachment {} from {} - too big ({} bytes)",
+                            content_hash,
+                            request.get_url(),
+                            attachment.content.len()
+                        );
+                        continue;
+                    }
+                    self.attachments.insert(attachment);
+                }
                 report.bump_successful_requests();
             } else {
                 report.bump_failed_requests();
@@ -529,6 +790,12 @@ enum AttachmentsBatchStateMachine {
             AttachmentsBatchStateContext,
         ),
     ),
+    DownloadingAttachmentsBatch(
+        (
+            BatchedRequestsState<AttachmentsBatchRequest>,
+            AttachmentsBatchStateContext,
+        ),
+    ),
     DownloadingAttachment(
         (
             BatchedRequestsState<AttachmentRequest>,
@@ -587,17 +854,67 @@ impl AttachmentsBatchStateMachine {
                 ) {
                     BatchedRequestsState::Done(ref mut results) => {
                         let context = context.extend_with_inventories(results);
-                        let sub_state = {
-                            let requests_queue = context.get_prioritized_attachments_requests();
-                            BatchedRequestsState::BeginRequests(Some(requests_queue), None)
-                        };
-                        AttachmentsBatchStateMachine::DownloadingAttachment((sub_state, context))
+                        let batch_requests_queue =
+                            if context.connection_options.max_attachments_per_request > 1 {
+                                context.get_prioritized_attachments_batch_requests()
+                            } else {
+                                BinaryHeap::new()
+                            };
+                        if batch_requests_queue.len() > 0 {
+                            let sub_state = BatchedRequestsState::BeginRequests(
+                                Some(batch_requests_queue),
+                                None,
+                            );
+                            AttachmentsBatchStateMachine::DownloadingAttachmentsBatch((
+                                sub_state, context,
+                            ))
+                        } else {
+                            let sub_state = {
+                                let requests_queue = context.get_prioritized_attachments_requests();
+                                BatchedRequestsState::BeginRequests(Some(requests_queue), None)
+                            };
+                            AttachmentsBatchStateMachine::DownloadingAttachment((
+                                sub_state, context,
+                            ))
+                        }
                     }
                     state => {
                         AttachmentsBatchStateMachine::DownloadingAttachmentsInv((state, context))
                     }
                 }
             }
+            AttachmentsBatchStateMachine::DownloadingAttachmentsBatch((
+                attachments_batch_requests,
+                context,
+            )) => {
+                match BatchedRequestsState::try_proceed(
+                    attachments_batch_requests,
+                    &context.dns_lookups,
+                    network,
+                    chainstate,
+                    &context.connection_options,
+                ) {
+                    BatchedRequestsState::Done(ref mut results) => {
+                        let context = context.extend_with_attachments_batches(results);
+                        // Fall back to one request per attachment for whatever the batches
+                        // didn't get us (e.g. peers that don't serve batches, or responses that
+                        // were full).
+                        let requests_queue = context.get_prioritized_attachments_requests();
+                        if requests_queue.is_empty() {
+                            AttachmentsBatchStateMachine::Done(context)
+                        } else {
+                            let sub_state =
+                                BatchedRequestsState::BeginRequests(Some(requests_queue), None);
+                            AttachmentsBatchStateMachine::DownloadingAttachment((
+                                sub_state, context,
+                            ))
+                        }
+                    }
+                    state => {
+                        AttachmentsBatchStateMachine::DownloadingAttachmentsBatch((state, context))
+                    }
+                }
+            }
             AttachmentsBatchStateMachine::DownloadingAttachment((
                 attachments_requests,
                 context,
@@ -611,7 +928,26 @@ impl AttachmentsBatchStateMachine {
                 ) {
                     BatchedRequestsState::Done(ref mut results) => {
                         let context = context.extend_with_attachments(results);
-                        AttachmentsBatchStateMachine::Done(context)
+                        // Keep going for as long as ranges of the attachments still being
+                        // fetched are coming in.
+                        let requests_queue: BinaryHeap<_> = if context.partial_progress {
+                            context
+                                .get_prioritized_attachments_requests()
+                                .into_iter()
+                                .filter(|request| request.range_start.unwrap_or(0) > 0)
+                                .collect()
+                        } else {
+                            BinaryHeap::new()
+                        };
+                        if requests_queue.is_empty() {
+                            AttachmentsBatchStateMachine::Done(context)
+                        } else {
+                            let sub_state =
+                                BatchedRequestsState::BeginRequests(Some(requests_queue), None);
+                            AttachmentsBatchStateMachine::DownloadingAttachment((
+                                sub_state, context,
+                            ))
+                        }
                     }
                     state => AttachmentsBatchStateMachine::DownloadingAttachment((state, context)),
                 }
@@ -784,6 +1120,8 @@ impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState
                     None => BatchedRequestsResult::new(HashMap::new()),
                 };
 
+                results.begin_ms = get_epoch_time_ms() as u64;
+
                 // We want to limit the number of requests in flight,
                 // so we will be batching our requests.
                 for _ in 0..connection_options.max_inflight_attachments {
@@ -832,6 +1170,7 @@ impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState
                                     event_id
                                 );
                                 let peer_url = request.get_url().clone();
+                                network.data_url_stats.record_failure(&peer_url);
                                 state.faulty_peers.insert(event_id, peer_url);
                             }
                         }
@@ -851,9 +1190,14 @@ impl<T: Ord + Requestable + fmt::Display + std::hash::Hash> BatchedRequestsState
                                     let peer_url = request.get_url().clone();
 
                                     if let HttpResponseType::NotFound(_, _) = response {
+                                        network.data_url_stats.record_failure(&peer_url);
                                         state.faulty_peers.insert(event_id, peer_url);
                                         continue;
                                     }
+                                    network.data_url_stats.record_success(
+                                        &peer_url,
+                                        (get_epoch_time_ms() as u64).saturating_sub(state.begin_ms),
+                                    );
                                     debug!(
                                         "Atlas: Request {} (event_id: {}) received response {:?}",
                                         request, event_id, response
@@ -909,6 +1253,8 @@ struct BatchedRequestsInitializedState<T: Ord + Requestable> {
 #[derive(Debug, Default)]
 pub struct BatchedRequestsResult<T: Requestable> {
     pub remaining: HashMap<usize, T>,
+    /// when (in milliseconds) the in-flight requests were sent
+    pub begin_ms: u64,
     pub succeeded: HashMap<T, Option<HttpResponseType>>,
     pub errors: HashMap<T, net_error>,
     pub faulty_peers: HashMap<usize, UrlString>,
@@ -918,6 +1264,7 @@ impl<T: Requestable> BatchedRequestsResult<T> {
     pub fn new(remaining: HashMap<usize, T>) -> BatchedRequestsResult<T> {
         BatchedRequestsResult {
             remaining,
+            begin_ms: get_epoch_time_ms() as u64,
             succeeded: HashMap::new(),
             errors: HashMap::new(),
             faulty_peers: HashMap::new(),
@@ -927,6 +1274,7 @@ impl<T: Requestable> BatchedRequestsResult<T> {
     pub fn empty() -> BatchedRequestsResult<T> {
         BatchedRequestsResult {
             remaining: HashMap::new(),
+            begin_ms: get_epoch_time_ms() as u64,
             succeeded: HashMap::new(),
             errors: HashMap::new(),
             faulty_peers: HashMap::new(),
@@ -1004,6 +1352,9 @@ impl std::fmt::Display for AttachmentsInventoryRequest {
 pub struct AttachmentRequest {
     pub content_hash: Hash160,
     pub sources: HashMap<UrlString, ReliabilityReport>,
+    /// If set, ask for the next ATTACHMENT_RANGE_LEN bytes of the content from this offset,
+    /// instead of for the whole attachment.
+    pub range_start: Option<u64>,
 }
 
 impl AttachmentRequest {
@@ -1044,7 +1395,17 @@ impl Requestable for AttachmentRequest {
     }
 
     fn make_request_type(&self, peer_host: PeerHost) -> HttpRequestType {
-        HttpRequestType::GetAttachment(HttpRequestMetadata::from_host(peer_host), self.content_hash)
+        match self.range_start {
+            Some(start) => HttpRequestType::GetAttachmentContent(
+                HttpRequestMetadata::from_host(peer_host),
+                self.content_hash,
+                Some(HttpByteRange::Span(start, start + ATTACHMENT_RANGE_LEN - 1)),
+            ),
+            None => HttpRequestType::GetAttachment(
+                HttpRequestMetadata::from_host(peer_host),
+                self.content_hash,
+            ),
+        }
     }
 }
 
@@ -1055,13 +1416,89 @@ impl std::fmt::Display for AttachmentRequest {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AttachmentsBatchRequest {
+    pub url: UrlString,
+    pub content_hashes: Vec<Hash160>,
+    pub reliability_report: ReliabilityReport,
+}
+
+impl Hash for AttachmentsBatchRequest {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.url.hash(state);
+        self.content_hashes.hash(state);
+    }
+}
+
+impl Ord for AttachmentsBatchRequest {
+    fn cmp(&self, other: &AttachmentsBatchRequest) -> Ordering {
+        self.reliability_report
+            .cmp(&other.reliability_report)
+            .then_with(|| self.content_hashes.len().cmp(&other.content_hashes.len()))
+    }
+}
+
+impl PartialOrd for AttachmentsBatchRequest {
+    fn partial_cmp(&self, other: &AttachmentsBatchRequest) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Requestable for AttachmentsBatchRequest {
+    fn get_url(&self) -> &UrlString {
+        &self.url
+    }
+
+    fn make_request_type(&self, peer_host: PeerHost) -> HttpRequestType {
+        HttpRequestType::GetAttachmentsBatch(
+            HttpRequestMetadata::from_host(peer_host),
+            self.content_hashes.clone(),
+        )
+    }
+}
+
+impl std::fmt::Display for AttachmentsBatchRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let url = &**self.get_url();
+        write!(
+            f,
+            "<Request<AttachmentsBatch>: url={}, count={}>",
+            url,
+            self.content_hashes.len()
+        )
+    }
+}
+
+#[derive(Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct AttachmentsBatch {
     pub block_height: u64,
     pub index_block_hash: StacksBlockId,
     pub attachments_instances: HashMap<QualifiedContractIdentifier, HashMap<u32, Hash160>>,
     pub retry_count: u64,
     pub retry_deadline: u64,
+    /// The content received so far of attachments that are being fetched a range at a time.
+    /// Kept across retries, so that an interrupted download resumes where it left off.
+    #[serde(skip)]
+    pub partial_contents: HashMap<Hash160, Vec<u8>>,
+}
+
+impl fmt::Debug for AttachmentsBatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // don't dump the partial contents themselves, just how far along they are
+        let partial_lens: HashMap<_, _> = self
+            .partial_contents
+            .iter()
+            .map(|(content_hash, content)| (content_hash, content.len()))
+            .collect();
+        f.debug_struct("AttachmentsBatch")
+            .field("block_height", &self.block_height)
+            .field("index_block_hash", &self.index_block_hash)
+            .field("attachments_instances", &self.attachments_instances)
+            .field("retry_count", &self.retry_count)
+            .field("retry_deadline", &self.retry_deadline)
+            .field("partial_contents", &partial_lens)
+            .finish()
+    }
 }
 
 impl AttachmentsBatch {
@@ -1072,6 +1509,7 @@ impl AttachmentsBatch {
             attachments_instances: HashMap::new(),
             retry_count: 0,
             retry_deadline: 0,
+            partial_contents: HashMap::new(),
         }
     }
 
@@ -1119,6 +1557,13 @@ impl AttachmentsBatch {
         self.retry_deadline = get_epoch_time_secs() + delay;
     }
 
+    /// Is this batch still missing the attachment with this content hash?
+    pub fn waits_for(&self, content_hash: &Hash160) -> bool {
+        self.attachments_instances
+            .values()
+            .any(|missing_attachments| missing_attachments.values().any(|h| h == content_hash))
+    }
+
     pub fn has_fully_succeed(&self) -> bool {
         self.attachments_instances_count() == 0
     }
@@ -1150,7 +1595,62 @@ impl AttachmentsBatch {
         paginated
     }
 
+    /// How many bytes of this attachment's content we have so far, if it's being fetched a range
+    /// at a time
+    pub fn partial_content_len(&self, content_hash: &Hash160) -> u64 {
+        self.partial_contents
+            .get(content_hash)
+            .map(|content| content.len() as u64)
+            .unwrap_or(0)
+    }
+
+    /// Add a byte range of an attachment's content that a peer sent us.  Returns the attachment
+    /// once all of its content is here and matches its hash.  On error, whatever we had of the
+    /// attachment is thrown away, so the next attempt starts over.
+    pub fn add_content_range(
+        &mut self,
+        content_hash: &Hash160,
+        range: AttachmentContentRange,
+        max_size: u32,
+    ) -> Result<Option<Attachment>, String> {
+        if range.total_len > max_size as u64 {
+            self.partial_contents.remove(content_hash);
+            return Err(format!("too big ({} bytes)", range.total_len));
+        }
+        let content = self
+            .partial_contents
+            .entry(content_hash.clone())
+            .or_insert_with(Vec::new);
+        if range.start > content.len() as u64 {
+            return Err(format!(
+                "range starts at {}, but we only have {} bytes",
+                range.start,
+                content.len()
+            ));
+        }
+        content.truncate(range.start as usize);
+        content.extend_from_slice(&range.content);
+        if (content.len() as u64) > range.total_len {
+            self.partial_contents.remove(content_hash);
+            return Err(format!("more than the {} bytes announced", range.total_len));
+        }
+        if (content.len() as u64) < range.total_len {
+            return Ok(None);
+        }
+
+        let content = self
+            .partial_contents
+            .remove(content_hash)
+            .expect("Atlas: partial content disappeared");
+        let attachment = Attachment::new(content);
+        if attachment.hash() != *content_hash {
+            return Err(format!("content hashes to {}", attachment.hash()));
+        }
+        Ok(Some(attachment))
+    }
+
     pub fn resolve_attachment(&mut self, content_hash: &Hash160) {
+        self.partial_contents.remove(content_hash);
         for missing_attachments in self.attachments_instances.values_mut() {
             let mut keys = vec![];
             for (k, hash) in missing_attachments.iter() {
@@ -1215,6 +1715,15 @@ impl ReliabilityReport {
         }
     }
 
+    /// Seed a report with what the PeerNetwork has learned about a data URL host, from both
+    /// the block downloader and earlier attachment batches.
+    pub fn from_host_stats(stats: &DataUrlHostStats) -> ReliabilityReport {
+        ReliabilityReport {
+            total_requests_sent: cmp::min(stats.requests_sent, u32::MAX as u64) as u32,
+            total_requests_success: cmp::min(stats.requests_succeeded, u32::MAX as u64) as u32,
+        }
+    }
+
     pub fn empty() -> ReliabilityReport {
         ReliabilityReport {
             total_requests_sent: 0,
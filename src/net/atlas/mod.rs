@@ -16,9 +16,12 @@
 
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
+use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::sync::{Arc, RwLock};
 
 use regex::Regex;
+use sha2::{Digest, Sha256};
 
 use crate::codec::StacksMessageCodec;
 use crate::types::chainstate::StacksBlockId;
@@ -26,32 +29,166 @@ use crate::util::boot::boot_code_id;
 use burnchains::Txid;
 use chainstate::burn::db::sortdb::SortitionDB;
 use chainstate::burn::ConsensusHash;
+use chainstate::stacks::db::StacksEpochReceipt;
+use chainstate::stacks::events::{StacksTransactionEvent, TransactionOrigin};
+use chainstate::stacks::TransactionPayload;
+use net::Error as net_error;
 use util::hash::{to_hex, Hash160, MerkleHashFunc};
 use vm::types::{QualifiedContractIdentifier, SequenceData, TupleData, Value};
 
 use crate::types::chainstate::{BlockHeaderHash, StacksBlockHeader};
 
-pub use self::db::AtlasDB;
+pub use self::db::{AtlasDB, AttachmentsPruneResult};
 pub use self::download::AttachmentsDownloader;
+pub use self::mirror::{AttachmentMirror, AttachmentMirrorConfig};
 
+pub mod bns;
 pub mod db;
 pub mod download;
+pub mod mirror;
+#[cfg(feature = "zonefile")]
+pub mod zonefile;
 
 pub const MAX_ATTACHMENT_INV_PAGES_PER_REQUEST: usize = 8;
+/// How many attachments a single `GET /v2/attachments?hashes=...` request may ask for.
+pub const MAX_ATTACHMENTS_PER_BATCH_REQUEST: usize = 32;
+/// How many bytes of attachment content a node will put in a single batch response.  The content
+/// is hex-encoded, so the response body is about twice this size.
+pub const MAX_ATTACHMENTS_BATCH_RESPONSE_LEN: u64 = 4 * 1024 * 1024;
+/// How many bytes of an attachment the downloader asks for at a time from peers that serve
+/// `GET /v2/attachments/:hash/content`.  An interrupted download resumes after the last range
+/// received.
+pub const ATTACHMENT_RANGE_LEN: u64 = 256 * 1024;
 pub const MAX_RETRY_DELAY: u64 = 600; // seconds
+/// How many per-block batches of attachment instances the chains coordinator can queue up for
+/// the peer network before block processing has to wait on Atlas.
+pub const ATTACHMENT_BATCHES_CHANNEL_CAPACITY: usize = 256;
 
 lazy_static! {
+    /// Character set of BNS names and namespaces. Prefer the length- and punycode-aware
+    /// validation functions in `bns`.
     pub static ref BNS_CHARS_REGEX: Regex = Regex::new("^([a-z0-9]|[-_])*$").unwrap();
 }
 
+/// Limits and retention policy for the attachments of one tracked contract.
+/// Inventory pages are not per-contract: `GET /v2/attachments/inv` is answered per block, so
+/// every peer must agree on `AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentDomainConfig {
+    pub attachments_max_size: u32,
+    pub unresolved_attachment_instances_expire_after: u32,
+}
+
+/// Contracts registered for tracking while the node runs.  Clones share the same list, so a
+/// contract registered through one copy of an `AtlasConfig` (e.g. the peer network's, via the
+/// admin RPC) is also tracked by every other copy (e.g. the chains coordinator's).
+#[derive(Debug, Clone, Default)]
+pub struct AtlasWatchList {
+    contracts: Arc<RwLock<HashMap<QualifiedContractIdentifier, AttachmentDomainConfig>>>,
+}
+
+impl AtlasWatchList {
+    pub fn new() -> AtlasWatchList {
+        AtlasWatchList::default()
+    }
+
+    pub fn contains(&self, contract_id: &QualifiedContractIdentifier) -> bool {
+        self.contracts
+            .read()
+            .expect("FATAL: Atlas watch list lock poisoned")
+            .contains_key(contract_id)
+    }
+
+    pub fn get(&self, contract_id: &QualifiedContractIdentifier) -> Option<AttachmentDomainConfig> {
+        self.contracts
+            .read()
+            .expect("FATAL: Atlas watch list lock poisoned")
+            .get(contract_id)
+            .cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contracts
+            .read()
+            .expect("FATAL: Atlas watch list lock poisoned")
+            .is_empty()
+    }
+
+    /// Returns false if the contract was already on the list, in which case its limits are
+    /// left unchanged.
+    fn insert(
+        &self,
+        contract_id: QualifiedContractIdentifier,
+        domain: AttachmentDomainConfig,
+    ) -> bool {
+        let mut contracts = self
+            .contracts
+            .write()
+            .expect("FATAL: Atlas watch list lock poisoned");
+        if contracts.contains_key(&contract_id) {
+            return false;
+        }
+        contracts.insert(contract_id, domain);
+        true
+    }
+
+    pub fn entries(&self) -> Vec<(QualifiedContractIdentifier, AttachmentDomainConfig)> {
+        self.contracts
+            .read()
+            .expect("FATAL: Atlas watch list lock poisoned")
+            .iter()
+            .map(|(contract_id, domain)| (contract_id.clone(), domain.clone()))
+            .collect()
+    }
+}
+
+/// When attachments that were downloaded or posted for announced instances may be deleted to
+/// reclaim space.  Pinned attachments, and attachments that no block announced (such as those
+/// loaded at genesis), are never deleted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentsRetentionPolicy {
+    /// Keep at most this many bytes of attachment content, deleting the attachments that were
+    /// last announced longest ago first
+    pub max_total_size: Option<u64>,
+    /// Delete attachments last announced more than this many Stacks blocks below the chain tip
+    pub max_age_blocks: Option<u64>,
+    /// Seconds between two sweeps
+    pub sweep_interval: u64,
+}
+
+impl Default for AttachmentsRetentionPolicy {
+    fn default() -> AttachmentsRetentionPolicy {
+        AttachmentsRetentionPolicy {
+            max_total_size: None,
+            max_age_blocks: None,
+            sweep_interval: 600,
+        }
+    }
+}
+
+impl AttachmentsRetentionPolicy {
+    /// Does this policy ever delete anything?
+    pub fn is_enabled(&self) -> bool {
+        self.max_total_size.is_some() || self.max_age_blocks.is_some()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AtlasConfig {
     pub contracts: HashSet<QualifiedContractIdentifier>,
+    /// Limits for tracked contracts that do not declare their own domain
     pub attachments_max_size: u32,
     pub max_uninstantiated_attachments: u32,
     pub uninstantiated_attachments_expire_after: u32,
     pub unresolved_attachment_instances_expire_after: u32,
     pub genesis_attachments: Option<Vec<Attachment>>,
+    /// Per-contract limits, overriding the defaults above
+    pub domains: HashMap<QualifiedContractIdentifier, AttachmentDomainConfig>,
+    /// Contracts registered with `add_contract()` after startup
+    pub watch_list: AtlasWatchList,
+    pub retention: AttachmentsRetentionPolicy,
+    /// Object storage that attachment content is copied to, and optionally read back from
+    pub mirror: Option<AttachmentMirrorConfig>,
 }
 
 impl AtlasConfig {
@@ -65,8 +202,100 @@ impl AtlasConfig {
             uninstantiated_attachments_expire_after: 3_600,
             unresolved_attachment_instances_expire_after: 172_800,
             genesis_attachments: None,
+            domains: HashMap::new(),
+            watch_list: AtlasWatchList::new(),
+            retention: AttachmentsRetentionPolicy::default(),
+            mirror: None,
+        }
+    }
+
+    /// Track a contract's attachments under its own limits.
+    pub fn add_domain(
+        &mut self,
+        contract_id: QualifiedContractIdentifier,
+        domain: AttachmentDomainConfig,
+    ) {
+        self.contracts.insert(contract_id.clone());
+        self.domains.insert(contract_id, domain);
+    }
+
+    /// Start tracking a contract's attachments while the node is running.  Only attachment
+    /// instances in blocks processed from now on are picked up.  The peer network's limits are
+    /// fixed at startup, so the contract's attachments cannot be larger than
+    /// `max_attachment_size()`.
+    /// Returns false if the contract was already tracked, in which case its limits are left
+    /// unchanged.
+    pub fn add_contract(
+        &self,
+        contract_id: QualifiedContractIdentifier,
+        domain: AttachmentDomainConfig,
+    ) -> Result<bool, String> {
+        if domain.attachments_max_size == 0 {
+            return Err("attachments_max_size must be positive".to_string());
+        }
+        if domain.attachments_max_size > self.max_attachment_size() {
+            return Err(format!(
+                "attachments_max_size cannot exceed {} while the node is running",
+                self.max_attachment_size()
+            ));
+        }
+        if self.contracts.contains(&contract_id) {
+            return Ok(false);
+        }
+        Ok(self.watch_list.insert(contract_id, domain))
+    }
+
+    /// Are attachment instances signaled by this contract tracked?
+    pub fn is_tracked(&self, contract_id: &QualifiedContractIdentifier) -> bool {
+        self.contracts.contains(contract_id) || self.watch_list.contains(contract_id)
+    }
+
+    /// Get every tracked contract and the limits that apply to its attachments, ordered by
+    /// contract identifier.
+    pub fn tracked_contracts(&self) -> Vec<(QualifiedContractIdentifier, AttachmentDomainConfig)> {
+        let mut tracked: Vec<_> = self
+            .contracts
+            .iter()
+            .map(|contract_id| (contract_id.clone(), self.get_domain(contract_id)))
+            .chain(self.watch_list.entries().into_iter())
+            .collect();
+        tracked.sort_by_key(|(contract_id, _)| contract_id.to_string());
+        tracked
+    }
+
+    /// Get the limits that apply to a tracked contract's attachments.
+    pub fn get_domain(&self, contract_id: &QualifiedContractIdentifier) -> AttachmentDomainConfig {
+        if let Some(domain) = self.domains.get(contract_id) {
+            return domain.clone();
+        }
+        if let Some(domain) = self.watch_list.get(contract_id) {
+            return domain;
+        }
+        AttachmentDomainConfig {
+            attachments_max_size: self.attachments_max_size,
+            unresolved_attachment_instances_expire_after: self
+                .unresolved_attachment_instances_expire_after,
         }
     }
+
+    /// Get the size of the largest attachment that any contract tracked from startup accepts.
+    pub fn max_attachment_size(&self) -> u32 {
+        let mut max_size = self.attachments_max_size;
+        for domain in self.domains.values() {
+            max_size = max_size.max(domain.attachments_max_size);
+        }
+        max_size
+    }
+
+    /// Can this attachment be paired with an instance signaled by the given contract?
+    pub fn is_attachment_in_domain(
+        &self,
+        contract_id: &QualifiedContractIdentifier,
+        attachment: &Attachment,
+    ) -> bool {
+        self.is_tracked(contract_id)
+            && attachment.content.len() as u32 <= self.get_domain(contract_id).attachments_max_size
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
@@ -88,6 +317,145 @@ impl Attachment {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AttachmentStreamState {
+    /// Before the opening quote
+    Start,
+    /// Between the quotes
+    Content,
+    /// After the closing quote
+    End,
+}
+
+/// Checks the body of a `GET /v2/attachments/:hash` response as it arrives -- a JSON string of
+/// the hex-encoded content -- so that a peer cannot make us buffer more than `max_size` bytes of
+/// content, and so that the content is hashed as it is decoded.
+#[derive(Clone)]
+pub struct AttachmentStreamVerifier {
+    expected_hash: Hash160,
+    max_size: u32,
+    state: AttachmentStreamState,
+    high_nibble: Option<u8>,
+    content: Vec<u8>,
+    hasher: Sha256,
+}
+
+impl fmt::Debug for AttachmentStreamVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AttachmentStreamVerifier")
+            .field("expected_hash", &self.expected_hash)
+            .field("max_size", &self.max_size)
+            .field("state", &self.state)
+            .field("content_len", &self.content.len())
+            .finish()
+    }
+}
+
+impl PartialEq for AttachmentStreamVerifier {
+    fn eq(&self, other: &AttachmentStreamVerifier) -> bool {
+        // the hasher has seen exactly `content`
+        self.expected_hash == other.expected_hash
+            && self.max_size == other.max_size
+            && self.state == other.state
+            && self.high_nibble == other.high_nibble
+            && self.content == other.content
+    }
+}
+
+impl AttachmentStreamVerifier {
+    pub fn new(expected_hash: Hash160, max_size: u32) -> AttachmentStreamVerifier {
+        AttachmentStreamVerifier {
+            expected_hash,
+            max_size,
+            state: AttachmentStreamState::Start,
+            high_nibble: None,
+            content: vec![],
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Largest body that can hold an attachment of `max_size` bytes
+    pub fn max_body_len(max_size: u32) -> u64 {
+        2 * (max_size as u64) + 2
+    }
+
+    /// Feed in the next bytes of the body.  Fails as soon as they cannot be part of a
+    /// hex-encoded attachment of at most `max_size` bytes.
+    pub fn write(&mut self, buf: &[u8]) -> Result<(), net_error> {
+        let mut decoded = Vec::with_capacity(buf.len() / 2 + 1);
+        for byte in buf.iter() {
+            match self.state {
+                AttachmentStreamState::Start => match *byte {
+                    b'"' => self.state = AttachmentStreamState::Content,
+                    b' ' | b'\t' | b'\r' | b'\n' => {}
+                    _ => {
+                        return Err(net_error::DeserializeError(
+                            "Invalid attachment: expected a JSON string".to_string(),
+                        ));
+                    }
+                },
+                AttachmentStreamState::Content => {
+                    if *byte == b'"' {
+                        if self.high_nibble.is_some() {
+                            return Err(net_error::DeserializeError(
+                                "Invalid attachment: odd number of hex digits".to_string(),
+                            ));
+                        }
+                        self.state = AttachmentStreamState::End;
+                        continue;
+                    }
+                    let nibble = (*byte as char).to_digit(16).ok_or_else(|| {
+                        net_error::DeserializeError(
+                            "Invalid attachment: expected hex digits".to_string(),
+                        )
+                    })? as u8;
+                    match self.high_nibble.take() {
+                        Some(high) => decoded.push((high << 4) | nibble),
+                        None => self.high_nibble = Some(nibble),
+                    }
+                }
+                AttachmentStreamState::End => match *byte {
+                    b' ' | b'\t' | b'\r' | b'\n' => {}
+                    _ => {
+                        return Err(net_error::DeserializeError(
+                            "Invalid attachment: trailing data".to_string(),
+                        ));
+                    }
+                },
+            }
+        }
+
+        if (self.content.len() + decoded.len()) as u64 > self.max_size as u64 {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid attachment: longer than {} bytes",
+                self.max_size
+            )));
+        }
+        self.hasher.input(&decoded);
+        self.content.extend_from_slice(&decoded);
+        Ok(())
+    }
+
+    /// The whole body has arrived.  Returns the attachment if it has the expected hash.
+    pub fn finish(self) -> Result<Attachment, net_error> {
+        if self.state != AttachmentStreamState::End {
+            return Err(net_error::DeserializeError(
+                "Invalid attachment: truncated".to_string(),
+            ));
+        }
+        let mut sha256 = [0u8; 32];
+        sha256.copy_from_slice(self.hasher.result().as_slice());
+        let hash = Hash160::from_sha256(&sha256);
+        if hash != self.expected_hash {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid attachment: expected hash {}, got {}",
+                &self.expected_hash, &hash
+            )));
+        }
+        Ok(Attachment::new(self.content))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Hash)]
 pub struct AttachmentInstance {
     pub content_hash: Hash160,
@@ -154,6 +522,47 @@ impl AttachmentInstance {
         }
         None
     }
+
+    /// Collect the attachment instances announced by calls to tracked contracts in one
+    /// processed block, so they can be handed to Atlas as a single batch.
+    pub fn try_new_from_block_receipt(
+        atlas_config: &AtlasConfig,
+        block_receipt: &StacksEpochReceipt,
+    ) -> HashSet<AttachmentInstance> {
+        let mut attachments_instances = HashSet::new();
+        if atlas_config.contracts.is_empty() && atlas_config.watch_list.is_empty() {
+            return attachments_instances;
+        }
+        let index_block_hash = block_receipt.header.index_block_hash();
+        for receipt in block_receipt.tx_receipts.iter() {
+            let contract_call = match receipt.transaction {
+                TransactionOrigin::Stacks(ref transaction) => match transaction.payload {
+                    TransactionPayload::ContractCall(ref contract_call) => contract_call,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            let contract_id = contract_call.to_clarity_contract_id();
+            if !atlas_config.is_tracked(&contract_id) {
+                continue;
+            }
+            for event in receipt.events.iter() {
+                if let StacksTransactionEvent::SmartContractEvent(ref event_data) = event {
+                    let res = AttachmentInstance::try_new_from_value(
+                        &event_data.value,
+                        &contract_id,
+                        index_block_hash.clone(),
+                        block_receipt.header.block_height,
+                        receipt.transaction.txid(),
+                    );
+                    if let Some(attachment_instance) = res {
+                        attachments_instances.insert(attachment_instance);
+                    }
+                }
+            }
+        }
+        attachments_instances
+    }
 }
 
 #[cfg(test)]
@@ -26,20 +26,25 @@ use chainstate::burn::ConsensusHash;
 use chainstate::stacks::db::StacksChainState;
 use net::connection::ConnectionOptions;
 use net::{
-    AttachmentPage, GetAttachmentsInvResponse, HttpResponseMetadata, HttpResponseType, HttpVersion,
-    PeerHost, Requestable,
+    AttachmentContentRange, AttachmentPage, GetAttachmentResponse, GetAttachmentsBatchResponse,
+    GetAttachmentsInvResponse, HttpByteRange, HttpRequestType, HttpResponseMetadata,
+    HttpResponseType, HttpVersion, PeerHost, Requestable,
 };
-use util::hash::Hash160;
+use util::hash::{to_hex, Hash160};
 use vm::representations::UrlString;
 use vm::types::QualifiedContractIdentifier;
 
 use crate::types::chainstate::{BlockHeaderHash, StacksBlockHeader};
 
 use super::download::{
-    AttachmentRequest, AttachmentsBatch, AttachmentsBatchStateContext, AttachmentsInventoryRequest,
-    BatchedRequestsResult, ReliabilityReport,
+    AttachmentRequest, AttachmentsBatch, AttachmentsBatchStateContext, AttachmentsDownloader,
+    AttachmentsInventoryRequest, BatchedRequestsResult, ReliabilityReport,
+};
+use super::{
+    AtlasConfig, AtlasDB, AtlasWatchList, Attachment, AttachmentDomainConfig, AttachmentInstance,
+    AttachmentMirrorConfig, AttachmentStreamVerifier, AttachmentsPruneResult,
+    AttachmentsRetentionPolicy, ATTACHMENT_RANGE_LEN,
 };
-use super::{AtlasConfig, AtlasDB, Attachment, AttachmentInstance};
 
 fn new_attachment_from(content: &str) -> Attachment {
     Attachment {
@@ -101,6 +106,7 @@ fn new_attachment_request(
     AttachmentRequest {
         sources,
         content_hash: content_hash.clone(),
+        range_start: None,
     }
 }
 
@@ -280,6 +286,176 @@ fn test_attachment_instance_parsing() {
     }
 }
 
+#[test]
+fn test_bns_name_validation() {
+    use super::bns::*;
+
+    assert_eq!(validate_bns_name("muneeb", PunycodePolicy::Opaque), Ok(()));
+    assert_eq!(validate_bns_name("a-b_c9", PunycodePolicy::Opaque), Ok(()));
+    assert_eq!(
+        validate_bns_name("", PunycodePolicy::Opaque),
+        Err(BnsNameError::Empty)
+    );
+    assert_eq!(
+        validate_bns_name("Muneeb", PunycodePolicy::Opaque),
+        Err(BnsNameError::InvalidChar('M'))
+    );
+    assert_eq!(
+        validate_bns_name("mün", PunycodePolicy::Opaque),
+        Err(BnsNameError::InvalidChar('ü'))
+    );
+    assert_eq!(
+        validate_bns_name(&"a".repeat(BNS_NAME_MAX_LEN), PunycodePolicy::Opaque),
+        Ok(())
+    );
+    assert_eq!(
+        validate_bns_name(&"a".repeat(BNS_NAME_MAX_LEN + 1), PunycodePolicy::Opaque),
+        Err(BnsNameError::TooLong(BNS_NAME_MAX_LEN))
+    );
+    assert_eq!(
+        validate_bns_namespace(
+            &"a".repeat(BNS_NAMESPACE_MAX_LEN + 1),
+            PunycodePolicy::Opaque
+        ),
+        Err(BnsNameError::TooLong(BNS_NAMESPACE_MAX_LEN))
+    );
+
+    assert_eq!(normalize_bns_label("  Muneeb "), "muneeb");
+
+    assert_eq!(
+        parse_bns_fully_qualified_name("muneeb.id", PunycodePolicy::Opaque),
+        Ok(("muneeb".to_string(), "id".to_string()))
+    );
+    assert_eq!(
+        parse_bns_fully_qualified_name("muneeb", PunycodePolicy::Opaque),
+        Err(BnsNameError::NotFullyQualified)
+    );
+    assert_eq!(
+        parse_bns_fully_qualified_name("a.muneeb.id", PunycodePolicy::Opaque),
+        Err(BnsNameError::NotFullyQualified)
+    );
+}
+
+#[test]
+fn test_bns_punycode_policy() {
+    use super::bns::*;
+
+    assert_eq!(bns_label_to_unicode("xn--mnchen-3ya").unwrap(), "münchen");
+    assert_eq!(bns_label_to_unicode("xn--bcher-kva").unwrap(), "bücher");
+    assert_eq!(bns_label_to_unicode("muneeb").unwrap(), "muneeb");
+
+    for policy in [PunycodePolicy::Opaque, PunycodePolicy::Strict].iter() {
+        assert_eq!(validate_bns_name("xn--mnchen-3ya", *policy), Ok(()));
+    }
+    assert_eq!(
+        validate_bns_name("xn--mnchen-3ya", PunycodePolicy::Reject),
+        Err(BnsNameError::PunycodeRejected)
+    );
+
+    // well-formed for the contract, but not valid punycode
+    assert_eq!(validate_bns_name("xn--abc", PunycodePolicy::Opaque), Ok(()));
+    assert_eq!(
+        validate_bns_name("xn--abc", PunycodePolicy::Strict),
+        Err(BnsNameError::InvalidPunycode)
+    );
+    assert_eq!(
+        validate_bns_name("xn--", PunycodePolicy::Strict),
+        Err(BnsNameError::InvalidPunycode)
+    );
+    // decodes to plain ASCII, so it is not a genuine punycode label
+    assert_eq!(
+        validate_bns_name("xn--muneeb-", PunycodePolicy::Strict),
+        Err(BnsNameError::InvalidPunycode)
+    );
+}
+
+#[test]
+fn test_attachment_instances_from_block_receipt() {
+    use chainstate::stacks::db::{StacksEpochReceipt, StacksHeaderInfo};
+    use chainstate::stacks::events::{
+        SmartContractEventData, StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin,
+    };
+    use chainstate::stacks::{
+        StacksPrivateKey, StacksTransaction, TransactionAuth, TransactionPayload,
+        TransactionVersion,
+    };
+    use types::chainstate::StacksAddress;
+    use vm;
+    use vm::costs::ExecutionCost;
+
+    let atlas_config = AtlasConfig::default(false);
+    let tracked_contract = boot_code_id("bns", false);
+    let untracked_contract = QualifiedContractIdentifier::transient();
+
+    let event_value = vm::execute(
+        r#"
+    {
+        attachment: {
+            attachment-index: u1,
+            hash: 0xd37581093088f5237a8dc885f38c231e42389cb2,
+            metadata: {
+                name: "stacks"
+            }
+        }
+    }
+    "#,
+    )
+    .unwrap()
+    .unwrap();
+
+    let make_receipt = |contract_id: &QualifiedContractIdentifier| {
+        let payload = TransactionPayload::new_contract_call(
+            StacksAddress::from(contract_id.issuer.clone()),
+            &contract_id.name,
+            "name-register",
+            vec![],
+        )
+        .unwrap();
+        let tx = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            TransactionAuth::from_p2pkh(&StacksPrivateKey::new()).unwrap(),
+            payload,
+        );
+        StacksTransactionReceipt {
+            transaction: TransactionOrigin::Stacks(tx),
+            events: vec![StacksTransactionEvent::SmartContractEvent(
+                SmartContractEventData {
+                    key: (contract_id.clone(), "print".to_string()),
+                    value: event_value.clone(),
+                },
+            )],
+            post_condition_aborted: false,
+            result: vm::Value::okay_true(),
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: ExecutionCost::zero(),
+        }
+    };
+
+    let block_receipt = StacksEpochReceipt {
+        header: StacksHeaderInfo::regtest_genesis(),
+        tx_receipts: vec![
+            make_receipt(&tracked_contract),
+            make_receipt(&untracked_contract),
+        ],
+        matured_rewards: vec![],
+        matured_rewards_info: None,
+        parent_microblocks_cost: ExecutionCost::zero(),
+        anchored_block_cost: ExecutionCost::zero(),
+    };
+
+    let attachment_instances =
+        AttachmentInstance::try_new_from_block_receipt(&atlas_config, &block_receipt);
+    assert_eq!(attachment_instances.len(), 1);
+    let attachment_instance = attachment_instances.into_iter().next().unwrap();
+    assert_eq!(attachment_instance.contract_id, tracked_contract);
+    assert_eq!(
+        attachment_instance.index_block_hash,
+        block_receipt.header.index_block_hash()
+    );
+    assert_eq!(attachment_instance.attachment_index, 1);
+}
+
 #[test]
 fn test_attachments_batch_ordering() {
     // Ensuring that when batches are being queued, we are correctly dequeueing, based on the following priorities:
@@ -717,6 +893,194 @@ fn test_downloader_context_attachment_requests() {
     assert_eq!(request.get_url(), &peer_url_1);
 }
 
+#[test]
+fn test_downloader_context_attachments_batch_requests() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+    let attachment_3 = new_attachment_from("facade03");
+    let attachment_4 = new_attachment_from("facade04");
+
+    let localhost = PeerHost::from_host_port("127.0.0.1".to_string(), 1024);
+    let page_size = AttachmentInstance::ATTACHMENTS_INV_PAGE_SIZE;
+
+    let attachments_batch = new_attachments_batch_from(
+        vec![
+            new_attachment_instance_from(&attachment_1, page_size * 0, 1),
+            new_attachment_instance_from(&attachment_2, page_size * 0 + 1, 1),
+            new_attachment_instance_from(&attachment_3, page_size * 0 + 2, 1),
+            new_attachment_instance_from(&attachment_4, page_size * 1, 1),
+        ],
+        0,
+    );
+    let peers = new_peers(vec![
+        ("http://localhost:20443", 4, 4),
+        ("http://localhost:30443", 1, 1),
+    ]);
+    let mut connection_options = ConnectionOptions::default();
+    connection_options.max_attachments_per_request = 2;
+    let mut context =
+        AttachmentsBatchStateContext::new(attachments_batch, peers, &connection_options);
+
+    let mut inventories_requests = context.get_prioritized_attachments_inventory_requests();
+    let mut inventories_results = BatchedRequestsResult::empty();
+
+    // Peer 1 has attachments 1, 2, and 3, and serves batches.
+    // Peer 2 has attachments 1 and 4, and doesn't.
+    let request_1 = inventories_requests.pop().unwrap();
+    let peer_url_1 = request_1.get_url().clone();
+    let request_2 = inventories_requests.pop().unwrap();
+    let peer_url_2 = request_2.get_url().clone();
+    inventories_results.succeeded.insert(
+        request_1,
+        Some(new_attachments_inventory_response(vec![
+            (0, vec![1, 1, 1]),
+            (1, vec![0]),
+        ])),
+    );
+    inventories_results.succeeded.insert(
+        request_2,
+        Some(new_attachments_inventory_response(vec![
+            (0, vec![1, 0, 0]),
+            (1, vec![1]),
+        ])),
+    );
+    context.batch_peers.insert(peer_url_1.clone());
+
+    let context = context.extend_with_inventories(&mut inventories_results);
+
+    // Attachments 1, 2, and 3 are asked from Peer 1, two at a time
+    let mut batch_requests = context.get_prioritized_attachments_batch_requests();
+    assert_eq!(batch_requests.len(), 2);
+    let mut requested = vec![];
+    let mut first_request = None;
+    while let Some(request) = batch_requests.pop() {
+        assert_eq!(request.get_url(), &peer_url_1);
+        assert!(request.content_hashes.len() <= 2);
+        let hashes = request
+            .content_hashes
+            .iter()
+            .map(|hash| format!("{}", hash))
+            .collect::<Vec<String>>();
+        assert_eq!(
+            request.make_request_type(localhost.clone()).request_path(),
+            format!("/v2/attachments?hashes={}", hashes.join(","))
+        );
+        requested.extend_from_slice(&request.content_hashes);
+        if first_request.is_none() {
+            first_request = Some(request);
+        }
+    }
+    requested.sort();
+    let mut expected = vec![
+        attachment_1.hash(),
+        attachment_2.hash(),
+        attachment_3.hash(),
+    ];
+    expected.sort();
+    assert_eq!(requested, expected);
+
+    // Peer 1 answers one of its batches, and throws in an attachment it wasn't asked for
+    let first_request = first_request.unwrap();
+    let mut served: Vec<Attachment> = vec![
+        attachment_1.clone(),
+        attachment_2.clone(),
+        attachment_3.clone(),
+    ]
+    .into_iter()
+    .filter(|attachment| first_request.content_hashes.contains(&attachment.hash()))
+    .collect();
+    served.push(attachment_4.clone());
+    let response = GetAttachmentsBatchResponse {
+        attachments: served
+            .iter()
+            .map(|attachment| GetAttachmentResponse {
+                attachment: attachment.clone(),
+            })
+            .collect(),
+    };
+    let mut batch_results = BatchedRequestsResult::empty();
+    batch_results.succeeded.insert(
+        first_request.clone(),
+        Some(HttpResponseType::GetAttachmentsBatch(
+            HttpResponseMetadata::new(HttpVersion::Http11, 1, None, true),
+            response,
+        )),
+    );
+    let context = context.extend_with_attachments_batches(&mut batch_results);
+    assert_eq!(
+        context.attachments.len(),
+        first_request.content_hashes.len()
+    );
+    assert!(!context.attachments.contains(&attachment_4));
+
+    // Everything else is then asked for one by one
+    let mut attachments_requests = context.get_prioritized_attachments_requests();
+    let mut remaining = vec![];
+    while let Some(request) = attachments_requests.pop() {
+        if request.content_hash == attachment_4.hash() {
+            assert_eq!(request.get_url(), &peer_url_2);
+        }
+        remaining.push(request.content_hash);
+    }
+    remaining.sort();
+    let mut expected: Vec<Hash160> = vec![
+        attachment_1.hash(),
+        attachment_2.hash(),
+        attachment_3.hash(),
+        attachment_4.hash(),
+    ]
+    .into_iter()
+    .filter(|hash| !first_request.content_hashes.contains(hash))
+    .collect();
+    expected.sort();
+    assert_eq!(remaining, expected);
+}
+
+#[test]
+fn test_downloader_wake_batches() {
+    let attachment_1 = new_attachment_from("facade01");
+    let attachment_2 = new_attachment_from("facade02");
+    let mut downloader = AttachmentsDownloader::new(vec![]);
+
+    // one batch waiting out its backoff, and one ready to go
+    let waiting_batch =
+        new_attachments_batch_from(vec![new_attachment_instance_from(&attachment_1, 1, 1)], 3);
+    let ready_batch =
+        new_attachments_batch_from(vec![new_attachment_instance_from(&attachment_2, 1, 2)], 0);
+    downloader.priority_queue.push(waiting_batch.clone());
+    downloader.priority_queue.push(ready_batch.clone());
+    assert_eq!(downloader.pop_next_ready_batch(), Some(ready_batch.clone()));
+    assert_eq!(downloader.pop_next_ready_batch(), None);
+    downloader.priority_queue.push(ready_batch.clone());
+
+    // announced in another block, or not missing from the batch
+    assert_eq!(
+        downloader.wake_batches(&[(StacksBlockId([2u8; 32]), attachment_1.hash())]),
+        0
+    );
+    assert_eq!(
+        downloader.wake_batches(&[(StacksBlockId([1u8; 32]), attachment_2.hash())]),
+        0
+    );
+
+    // the waiting batch is ready again
+    assert_eq!(
+        downloader.wake_batches(&[
+            (StacksBlockId([1u8; 32]), attachment_1.hash()),
+            (StacksBlockId([2u8; 32]), attachment_2.hash())
+        ]),
+        1
+    );
+    let mut woken = vec![
+        downloader.pop_next_ready_batch().unwrap(),
+        downloader.pop_next_ready_batch().unwrap(),
+    ];
+    woken.sort_by_key(|batch| batch.block_height);
+    assert_eq!(woken[0].index_block_hash, waiting_batch.index_block_hash);
+    assert_eq!(woken[0].retry_deadline, 0);
+    assert_eq!(woken[1], ready_batch);
+}
+
 #[test]
 fn test_keep_uninstantiated_attachments() {
     let bns_contract_id = boot_code_id("bns", false);
@@ -732,6 +1096,10 @@ fn test_keep_uninstantiated_attachments() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
     };
 
     let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -755,6 +1123,257 @@ fn test_keep_uninstantiated_attachments() {
     );
 }
 
+#[test]
+fn test_attachment_domains() {
+    let bns_contract_id = boot_code_id("bns", false);
+    let attestations_contract_id =
+        QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.attestations").unwrap();
+
+    let mut contracts = HashSet::new();
+    contracts.insert(bns_contract_id.clone());
+
+    let mut atlas_config = AtlasConfig {
+        contracts,
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 10,
+        uninstantiated_attachments_expire_after: 10,
+        unresolved_attachment_instances_expire_after: 0,
+        genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
+    };
+    atlas_config.add_domain(
+        attestations_contract_id.clone(),
+        AttachmentDomainConfig {
+            attachments_max_size: 16,
+            unresolved_attachment_instances_expire_after: 3600,
+        },
+    );
+    assert!(atlas_config.contracts.contains(&attestations_contract_id));
+    assert_eq!(atlas_config.max_attachment_size(), 1024);
+
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // each contract is held to its own size limit
+    let attachment = new_attachment_from("facadefacadefacade02");
+    assert!(atlas_db.should_keep_attachment(&bns_contract_id, &attachment));
+    assert!(!atlas_db.should_keep_attachment(&attestations_contract_id, &attachment));
+    assert!(atlas_db
+        .atlas_config
+        .is_attachment_in_domain(&bns_contract_id, &attachment));
+    assert!(!atlas_db
+        .atlas_config
+        .is_attachment_in_domain(&attestations_contract_id, &attachment));
+    assert!(atlas_db
+        .should_keep_attachment(&attestations_contract_id, &new_attachment_from("facade02")));
+
+    // ...and to its own retention policy
+    let mut bns_instance = new_attachment_instance_from(&new_attachment_from("facade11"), 0, 10);
+    bns_instance.contract_id = bns_contract_id.clone();
+    bns_instance.tx_id = Txid([0xaa; 32]);
+    let mut attestation_instance =
+        new_attachment_instance_from(&new_attachment_from("facade12"), 0, 10);
+    attestation_instance.contract_id = attestations_contract_id.clone();
+    attestation_instance.tx_id = Txid([0xbb; 32]);
+    for attachment_instance in [bns_instance, attestation_instance].iter() {
+        atlas_db
+            .insert_uninstantiated_attachment_instance(attachment_instance, false)
+            .unwrap();
+    }
+
+    thread::sleep(time::Duration::from_secs(2));
+
+    atlas_db
+        .evict_expired_unresolved_attachment_instances()
+        .unwrap();
+    let remaining = atlas_db.find_unresolved_attachment_instances().unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].contract_id, attestations_contract_id);
+}
+
+#[test]
+fn test_atlas_watch_list() {
+    let bns_contract_id = boot_code_id("bns", false);
+    let attestations_contract_id =
+        QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.attestations").unwrap();
+
+    let atlas_config = AtlasConfig::default(false);
+    let atlas_db = AtlasDB::connect_memory(atlas_config.clone()).unwrap();
+    let attachment = new_attachment_from("facade02");
+    assert!(!atlas_db.should_keep_attachment(&attestations_contract_id, &attachment));
+
+    // limits cannot be raised past what the peer network was started with
+    assert!(atlas_config
+        .add_contract(
+            attestations_contract_id.clone(),
+            AttachmentDomainConfig {
+                attachments_max_size: atlas_config.max_attachment_size() + 1,
+                unresolved_attachment_instances_expire_after: 3600,
+            },
+        )
+        .is_err());
+    assert!(!atlas_config.is_tracked(&attestations_contract_id));
+
+    assert_eq!(
+        atlas_config.add_contract(
+            attestations_contract_id.clone(),
+            AttachmentDomainConfig {
+                attachments_max_size: 6,
+                unresolved_attachment_instances_expire_after: 3600,
+            },
+        ),
+        Ok(true)
+    );
+    assert_eq!(
+        atlas_config.add_contract(
+            attestations_contract_id.clone(),
+            atlas_config.get_domain(&bns_contract_id),
+        ),
+        Ok(false)
+    );
+    assert_eq!(
+        atlas_config.add_contract(
+            bns_contract_id.clone(),
+            atlas_config.get_domain(&bns_contract_id)
+        ),
+        Ok(false)
+    );
+
+    // every copy of the config sees the registration, under the registered limits
+    assert!(atlas_db.atlas_config.is_tracked(&attestations_contract_id));
+    assert!(
+        atlas_db.should_keep_attachment(&attestations_contract_id, &new_attachment_from("facade"))
+    );
+    assert!(!atlas_db.should_keep_attachment(&attestations_contract_id, &attachment));
+    assert_eq!(
+        atlas_db
+            .atlas_config
+            .get_domain(&attestations_contract_id)
+            .attachments_max_size,
+        6
+    );
+
+    let tracked: Vec<_> = atlas_db
+        .atlas_config
+        .tracked_contracts()
+        .into_iter()
+        .map(|(contract_id, _)| contract_id)
+        .collect();
+    assert_eq!(tracked, vec![attestations_contract_id, bns_contract_id]);
+}
+
+#[test]
+fn test_attachment_stream_verifier() {
+    let attachment = new_attachment_from("facadefacadefacade02");
+    let body = format!("\"{}\"\n", to_hex(&attachment.content));
+
+    // the body can arrive in pieces of any size
+    for piece_len in 1..body.len() {
+        let mut verifier = AttachmentStreamVerifier::new(attachment.hash(), 20);
+        for piece in body.as_bytes().chunks(piece_len) {
+            verifier.write(piece).unwrap();
+        }
+        assert_eq!(verifier.finish().unwrap(), attachment);
+    }
+
+    // wrong content
+    let mut verifier = AttachmentStreamVerifier::new(Hash160([0x11; 20]), 20);
+    verifier.write(body.as_bytes()).unwrap();
+    assert!(verifier.finish().is_err());
+
+    // too much content is refused as soon as it arrives
+    let mut verifier = AttachmentStreamVerifier::new(attachment.hash(), 19);
+    verifier.write(&body.as_bytes()[0..39]).unwrap();
+    assert!(verifier.write(&body.as_bytes()[39..41]).is_err());
+
+    // not a hex-encoded JSON string
+    for bad_body in [
+        "facade",
+        "\"facad\"",
+        "\"facadg\"",
+        "\"facade\"x",
+        "\"facade",
+    ]
+    .iter()
+    {
+        let mut verifier = AttachmentStreamVerifier::new(attachment.hash(), 20);
+        assert!(verifier
+            .write(bad_body.as_bytes())
+            .and_then(|_| verifier.finish())
+            .is_err());
+    }
+}
+
+#[test]
+fn test_attachments_batch_content_ranges() {
+    let attachment = new_attachment_from("facadefacadefacade02");
+    let content_hash = attachment.hash();
+    let range = |start: usize, end: usize| AttachmentContentRange {
+        start: start as u64,
+        total_len: 20,
+        content: attachment.content[start..end].to_vec(),
+    };
+
+    // ranges are put together as they arrive, and a re-sent range doesn't duplicate anything
+    let mut batch = AttachmentsBatch::new();
+    assert_eq!(
+        batch.add_content_range(&content_hash, range(0, 8), 20),
+        Ok(None)
+    );
+    assert_eq!(
+        batch.add_content_range(&content_hash, range(4, 12), 20),
+        Ok(None)
+    );
+    assert_eq!(batch.partial_content_len(&content_hash), 12);
+
+    // the download resumes after an interruption
+    batch.bump_retry_count();
+    assert_eq!(
+        batch.add_content_range(&content_hash, range(12, 20), 20),
+        Ok(Some(attachment.clone()))
+    );
+    assert_eq!(batch.partial_content_len(&content_hash), 0);
+
+    // a range that leaves a gap is refused, but keeps what we have
+    let mut batch = AttachmentsBatch::new();
+    batch
+        .add_content_range(&content_hash, range(0, 8), 20)
+        .unwrap();
+    assert!(batch
+        .add_content_range(&content_hash, range(12, 20), 20)
+        .is_err());
+    assert_eq!(batch.partial_content_len(&content_hash), 8);
+
+    // too big, or the wrong content, and we start over
+    assert!(batch
+        .add_content_range(&content_hash, range(8, 12), 19)
+        .is_err());
+    assert_eq!(batch.partial_content_len(&content_hash), 0);
+    assert!(batch
+        .add_content_range(&Hash160([0x11; 20]), range(0, 20), 20)
+        .is_err());
+    assert_eq!(batch.partial_content_len(&Hash160([0x11; 20])), 0);
+
+    // a resumed request asks for the range after what we have
+    let request = AttachmentRequest {
+        sources: HashMap::new(),
+        content_hash: content_hash.clone(),
+        range_start: Some(12),
+    };
+    match request.make_request_type(PeerHost::from_host_port("localhost".to_string(), 20443)) {
+        HttpRequestType::GetAttachmentContent(_, hash, range) => {
+            assert_eq!(hash, content_hash);
+            assert_eq!(
+                range,
+                Some(HttpByteRange::Span(12, 12 + ATTACHMENT_RANGE_LEN - 1))
+            );
+        }
+        req => panic!("Unexpected request {:?}", &req),
+    }
+}
+
 #[test]
 fn test_evict_k_oldest_uninstantiated_attachments() {
     let atlas_config = AtlasConfig {
@@ -764,6 +1383,10 @@ fn test_evict_k_oldest_uninstantiated_attachments() {
         uninstantiated_attachments_expire_after: 0,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
     };
 
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -912,6 +1535,10 @@ fn test_evict_expired_uninstantiated_attachments() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
     };
 
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -981,6 +1608,10 @@ fn test_evict_expired_unresolved_attachment_instances() {
         uninstantiated_attachments_expire_after: 200,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
     };
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
 
@@ -1040,6 +1671,201 @@ fn test_evict_expired_unresolved_attachment_instances() {
     assert_eq!(atlas_db.count_unresolved_attachment_instances().unwrap(), 3);
 }
 
+#[test]
+fn test_prune_attachments() {
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 200,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy {
+            max_total_size: Some(24),
+            max_age_blocks: Some(10),
+            sweep_interval: 0,
+        },
+        mirror: None,
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    // Every attachment is 8 bytes long
+    let old_attachment = new_attachment_from("facade41");
+    let pinned_attachment = new_attachment_from("facade42");
+    let announced_attachments = [
+        (new_attachment_from("facade43"), 12),
+        (new_attachment_from("facade44"), 14),
+        (new_attachment_from("facade45"), 16),
+    ];
+    let genesis_attachment = new_attachment_from("facade46");
+
+    let mut attachment_index = 0;
+    for (attachment, block_height) in [(old_attachment.clone(), 1), (pinned_attachment.clone(), 2)]
+        .iter()
+        .chain(announced_attachments.iter())
+    {
+        let attachment_instance =
+            new_attachment_instance_from(attachment, attachment_index, *block_height);
+        atlas_db
+            .insert_uninstantiated_attachment_instance(&attachment_instance, true)
+            .unwrap();
+        atlas_db.insert_instantiated_attachment(attachment).unwrap();
+        attachment_index += 1;
+    }
+    atlas_db
+        .insert_instantiated_attachment(&genesis_attachment)
+        .unwrap();
+    atlas_db.pin_attachment(&pinned_attachment.hash()).unwrap();
+    assert_eq!(
+        atlas_db.get_pinned_attachments().unwrap(),
+        vec![pinned_attachment.hash()]
+    );
+    assert_eq!(atlas_db.get_attachments_total_size().unwrap(), 48);
+
+    // The attachment announced at height 1 is too old, then the oldest ones go until the rest
+    // fits.  The pinned and genesis attachments count towards the cap, but are kept.
+    let result = atlas_db.prune_attachments(20).unwrap();
+    assert_eq!(
+        result,
+        AttachmentsPruneResult {
+            attachments: 3,
+            bytes: 24,
+        }
+    );
+    assert_eq!(atlas_db.get_attachments_total_size().unwrap(), 24);
+    assert!(atlas_db
+        .find_attachment(&old_attachment.hash())
+        .unwrap()
+        .is_none());
+    assert!(atlas_db
+        .find_attachment(&announced_attachments[1].0.hash())
+        .unwrap()
+        .is_none());
+    assert!(atlas_db
+        .find_attachment(&announced_attachments[2].0.hash())
+        .unwrap()
+        .is_some());
+    assert!(atlas_db
+        .find_attachment(&pinned_attachment.hash())
+        .unwrap()
+        .is_some());
+    assert!(atlas_db
+        .find_attachment(&genesis_attachment.hash())
+        .unwrap()
+        .is_some());
+
+    // Nothing else to do
+    assert_eq!(
+        atlas_db.prune_attachments(20).unwrap(),
+        AttachmentsPruneResult::default()
+    );
+
+    // Once unpinned, the attachment announced at height 2 is too old
+    atlas_db
+        .unpin_attachment(&pinned_attachment.hash())
+        .unwrap();
+    assert!(atlas_db.get_pinned_attachments().unwrap().is_empty());
+    assert_eq!(
+        atlas_db.prune_attachments(20).unwrap(),
+        AttachmentsPruneResult {
+            attachments: 1,
+            bytes: 8,
+        }
+    );
+    assert!(atlas_db
+        .find_attachment(&pinned_attachment.hash())
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_prune_mirrored_attachments() {
+    // nothing listens on this port, so reads from the mirror miss
+    let mirror = AttachmentMirrorConfig::new("http://127.0.0.1:1", "atlas", "key", "secret");
+    let atlas_config = AtlasConfig {
+        contracts: HashSet::new(),
+        attachments_max_size: 1024,
+        max_uninstantiated_attachments: 100,
+        uninstantiated_attachments_expire_after: 200,
+        unresolved_attachment_instances_expire_after: 10,
+        genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy {
+            max_total_size: None,
+            max_age_blocks: Some(10),
+            sweep_interval: 0,
+        },
+        mirror: Some(mirror),
+    };
+    let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
+
+    let attachments = [
+        new_attachment_from("facade41"),
+        new_attachment_from("facade42"),
+    ];
+    for (attachment_index, attachment) in attachments.iter().enumerate() {
+        let attachment_instance =
+            new_attachment_instance_from(attachment, attachment_index as u32, 1);
+        atlas_db
+            .insert_uninstantiated_attachment_instance(&attachment_instance, true)
+            .unwrap();
+        atlas_db.insert_instantiated_attachment(attachment).unwrap();
+    }
+
+    // Both attachments wait to be uploaded, oldest first
+    assert_eq!(atlas_db.get_attachments_to_mirror(1).unwrap().len(), 1);
+    assert_eq!(atlas_db.get_attachments_to_mirror(10).unwrap().len(), 2);
+    atlas_db
+        .mark_attachment_mirrored(&attachments[0].hash())
+        .unwrap();
+    assert_eq!(
+        atlas_db.get_attachments_to_mirror(10).unwrap(),
+        vec![attachments[1].clone()]
+    );
+    assert_eq!(atlas_db.count_mirrored_attachments().unwrap(), 1);
+
+    // Both are too old, but only the uploaded one may be deleted.  Its instance is kept, since
+    // it can still be served from the mirror.
+    assert_eq!(
+        atlas_db.prune_attachments(20).unwrap(),
+        AttachmentsPruneResult {
+            attachments: 1,
+            bytes: 8,
+        }
+    );
+    assert!(atlas_db
+        .find_attachment(&attachments[0].hash())
+        .unwrap()
+        .is_none());
+    let num_instances: i64 = atlas_db
+        .conn()
+        .query_row(
+            "SELECT COUNT(*) FROM attachment_instances WHERE content_hash = ?1",
+            &[&to_hex(&attachments[0].hash().0[..])],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(num_instances, 1);
+    assert!(atlas_db
+        .find_attachment_or_mirrored(&attachments[0].hash())
+        .unwrap()
+        .is_none());
+    assert!(atlas_db
+        .find_attachment_or_mirrored(&attachments[1].hash())
+        .unwrap()
+        .is_some());
+
+    // Once everything is uploaded there is nothing left to mirror, pruned or not
+    atlas_db
+        .mark_attachment_mirrored(&attachments[1].hash())
+        .unwrap();
+    assert!(atlas_db.get_attachments_to_mirror(10).unwrap().is_empty());
+    assert_eq!(atlas_db.count_mirrored_attachments().unwrap(), 2);
+}
+
 #[test]
 fn test_get_minmax_heights_atlasdb() {
     let atlas_config = AtlasConfig {
@@ -1049,6 +1875,10 @@ fn test_get_minmax_heights_atlasdb() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
     };
 
     let atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -1069,6 +1899,10 @@ fn test_bit_vectors() {
         uninstantiated_attachments_expire_after: 10,
         unresolved_attachment_instances_expire_after: 10,
         genesis_attachments: None,
+        domains: HashMap::new(),
+        watch_list: AtlasWatchList::new(),
+        retention: AttachmentsRetentionPolicy::default(),
+        mirror: None,
     };
 
     let mut atlas_db = AtlasDB::connect_memory(atlas_config).unwrap();
@@ -0,0 +1,340 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Parsing of zonefile-format attachments (feature `zonefile`).
+//!
+//! BNS names point at a zonefile, stored by Atlas as an attachment. Resolvers only care about
+//! a handful of records -- `URI` records locating profiles and resolvers, and `TXT` records --
+//! so this parser extracts those and skips every other record type. It understands `$ORIGIN`
+//! and `$TTL` directives, `;` comments, quoted strings, and records that inherit the previous
+//! record's owner name by starting with whitespace. Multi-line `( ... )` records are not
+//! supported.
+
+use std::error;
+use std::fmt;
+
+use net::atlas::Attachment;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UriRecord {
+    pub name: String,
+    pub ttl: Option<u32>,
+    pub priority: u16,
+    pub weight: u16,
+    pub target: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxtRecord {
+    pub name: String,
+    pub ttl: Option<u32>,
+    /// The record's character-strings, in order
+    pub text: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Zonefile {
+    pub origin: Option<String>,
+    pub ttl: Option<u32>,
+    pub uri: Vec<UriRecord>,
+    pub txt: Vec<TxtRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZonefileError {
+    /// The attachment is not UTF-8 text
+    NotText,
+    /// The given (1-based) line could not be parsed
+    Malformed(usize, String),
+}
+
+impl fmt::Display for ZonefileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ZonefileError::NotText => write!(f, "zonefile is not UTF-8 text"),
+            ZonefileError::Malformed(line, ref msg) => {
+                write!(f, "malformed zonefile at line {}: {}", line, msg)
+            }
+        }
+    }
+}
+
+impl error::Error for ZonefileError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+const DNS_CLASSES: [&str; 4] = ["IN", "CH", "HS", "CS"];
+
+impl Zonefile {
+    pub fn from_attachment(attachment: &Attachment) -> Result<Zonefile, ZonefileError> {
+        let content =
+            ::std::str::from_utf8(&attachment.content).map_err(|_| ZonefileError::NotText)?;
+        Zonefile::parse(content)
+    }
+
+    pub fn parse(content: &str) -> Result<Zonefile, ZonefileError> {
+        let mut zonefile = Zonefile::default();
+        let mut last_name: Option<String> = None;
+
+        for (i, line) in content.lines().enumerate() {
+            let line_num = i + 1;
+            let malformed = |msg: &str| ZonefileError::Malformed(line_num, msg.to_string());
+
+            let mut tokens = tokenize(line).map_err(|e| malformed(e))?.into_iter();
+            let inherits_name = line.starts_with(|c: char| c == ' ' || c == '\t');
+
+            let first = match tokens.next() {
+                Some(token) => token,
+                None => continue,
+            };
+
+            if !first.quoted && first.text.starts_with('$') {
+                let value = tokens
+                    .next()
+                    .ok_or_else(|| malformed("directive without a value"))?;
+                match first.text.to_uppercase().as_str() {
+                    "$ORIGIN" => zonefile.origin = Some(value.text),
+                    "$TTL" => {
+                        zonefile.ttl =
+                            Some(value.text.parse().map_err(|_| malformed("invalid $TTL"))?)
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+
+            let mut tokens: Vec<Token> = if inherits_name {
+                let mut all = vec![first];
+                all.extend(tokens);
+                all
+            } else {
+                last_name = Some(first.text);
+                tokens.collect()
+            };
+            let name = last_name
+                .clone()
+                .ok_or_else(|| malformed("record without an owner name"))?;
+
+            // optional TTL and class, in either order
+            let mut ttl = None;
+            let mut record_type = None;
+            let mut rdata_start = tokens.len();
+            for (idx, token) in tokens.iter().enumerate() {
+                if token.quoted {
+                    break;
+                }
+                if ttl.is_none() && token.text.chars().all(|c| c.is_ascii_digit()) {
+                    ttl = Some(token.text.parse().map_err(|_| malformed("invalid TTL"))?);
+                } else if DNS_CLASSES.contains(&token.text.to_uppercase().as_str()) {
+                    continue;
+                } else {
+                    record_type = Some(token.text.to_uppercase());
+                    rdata_start = idx + 1;
+                    break;
+                }
+            }
+            let record_type = record_type.ok_or_else(|| malformed("record without a type"))?;
+            let rdata: Vec<Token> = tokens.drain(rdata_start..).collect();
+
+            match record_type.as_str() {
+                "URI" => {
+                    if rdata.len() != 3 {
+                        return Err(malformed("URI record needs priority, weight and target"));
+                    }
+                    let priority = rdata[0]
+                        .text
+                        .parse()
+                        .map_err(|_| malformed("invalid URI priority"))?;
+                    let weight = rdata[1]
+                        .text
+                        .parse()
+                        .map_err(|_| malformed("invalid URI weight"))?;
+                    zonefile.uri.push(UriRecord {
+                        name,
+                        ttl,
+                        priority,
+                        weight,
+                        target: rdata[2].text.clone(),
+                    });
+                }
+                "TXT" => {
+                    if rdata.is_empty() {
+                        return Err(malformed("TXT record without text"));
+                    }
+                    zonefile.txt.push(TxtRecord {
+                        name,
+                        ttl,
+                        text: rdata.into_iter().map(|token| token.text).collect(),
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(zonefile)
+    }
+
+    /// The URI records for the given owner name, lowest priority (most preferred) first.
+    pub fn uris_for(&self, name: &str) -> Vec<&UriRecord> {
+        let mut records: Vec<&UriRecord> = self.uri.iter().filter(|r| r.name == name).collect();
+        records.sort_by_key(|r| (r.priority, ::std::cmp::Reverse(r.weight)));
+        records
+    }
+
+    /// The TXT records for the given owner name, each joined into a single string.
+    pub fn txt_for(&self, name: &str) -> Vec<String> {
+        self.txt
+            .iter()
+            .filter(|r| r.name == name)
+            .map(|r| r.text.concat())
+            .collect()
+    }
+}
+
+/// Split a line into whitespace-separated tokens, keeping quoted strings whole and dropping
+/// everything after an unquoted `;`.
+fn tokenize(line: &str) -> Result<Vec<Token>, &'static str> {
+    let mut tokens = vec![];
+    let mut chars = line.chars();
+    let mut current = String::new();
+    loop {
+        let c = match chars.next() {
+            Some(c) => c,
+            None => break,
+        };
+        match c {
+            ';' => break,
+            '"' => {
+                if !current.is_empty() {
+                    return Err("unexpected quote");
+                }
+                let mut text = String::new();
+                let mut closed = false;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            Some(escaped) => text.push(escaped),
+                            None => return Err("unterminated escape"),
+                        },
+                        '"' => {
+                            closed = true;
+                            break;
+                        }
+                        _ => text.push(c),
+                    }
+                }
+                if !closed {
+                    return Err("unterminated quoted string");
+                }
+                tokens.push(Token { text, quoted: true });
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(Token {
+                        text: current.split_off(0),
+                        quoted: false,
+                    });
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(Token {
+            text: current,
+            quoted: false,
+        });
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_zonefile() {
+        let content = "$ORIGIN muneeb.id\n\
+                       $TTL 3600\n\
+                       ; profile location\n\
+                       _http._tcp\tIN\tURI\t10\t1\t\"https://gaia.blockstack.org/hub/1J3PUxY5uDShUnHRrMyU6yKtoHEUPhKULs/0/profile.json\"\n\
+                       _resolver 300 IN URI 20 1 \"https://resolver.example.com/\" ; comment\n\
+                       \tIN URI 10 1 \"https://resolver2.example.com/\"\n\
+                       @ IN TXT \"owner=muneeb\" \"v=1\"\n\
+                       @ IN A 127.0.0.1\n";
+        let zonefile = Zonefile::parse(content).unwrap();
+        assert_eq!(zonefile.origin, Some("muneeb.id".to_string()));
+        assert_eq!(zonefile.ttl, Some(3600));
+        assert_eq!(zonefile.uri.len(), 3);
+        assert_eq!(
+            zonefile.uri[0],
+            UriRecord {
+                name: "_http._tcp".to_string(),
+                ttl: None,
+                priority: 10,
+                weight: 1,
+                target: "https://gaia.blockstack.org/hub/1J3PUxY5uDShUnHRrMyU6yKtoHEUPhKULs/0/profile.json".to_string(),
+            }
+        );
+        assert_eq!(zonefile.uri[1].ttl, Some(300));
+
+        let resolvers = zonefile.uris_for("_resolver");
+        assert_eq!(resolvers.len(), 2);
+        assert_eq!(resolvers[0].target, "https://resolver2.example.com/");
+        assert_eq!(resolvers[1].target, "https://resolver.example.com/");
+
+        assert_eq!(zonefile.txt_for("@"), vec!["owner=muneebv=1".to_string()]);
+        assert_eq!(zonefile.txt[0].text, vec!["owner=muneeb", "v=1"]);
+    }
+
+    #[test]
+    fn test_parse_zonefile_errors() {
+        assert_eq!(
+            Zonefile::parse("_http._tcp IN URI 10 \"https://example.com\""),
+            Err(ZonefileError::Malformed(
+                1,
+                "URI record needs priority, weight and target".to_string()
+            ))
+        );
+        assert_eq!(
+            Zonefile::parse("$TTL 3600\n@ IN TXT \"unterminated"),
+            Err(ZonefileError::Malformed(
+                2,
+                "unterminated quoted string".to_string()
+            ))
+        );
+        assert_eq!(
+            Zonefile::parse("\tIN TXT \"orphan\""),
+            Err(ZonefileError::Malformed(
+                1,
+                "record without an owner name".to_string()
+            ))
+        );
+        assert_eq!(
+            Zonefile::from_attachment(&Attachment::new(vec![0xff, 0xfe])),
+            Err(ZonefileError::NotText)
+        );
+        assert_eq!(Zonefile::parse("").unwrap(), Zonefile::default());
+    }
+}
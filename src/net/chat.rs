@@ -45,8 +45,10 @@ use net::db::PeerDB;
 use net::db::*;
 use net::neighbors::MAX_NEIGHBOR_BLOCK_DELAY;
 use net::relay::*;
+use net::tls::TlsConfig;
 use net::Error as net_error;
 use net::GetBlocksInv;
+use net::GetBlocksInvPage;
 use net::GetPoxInv;
 use net::Neighbor;
 use net::NeighborKey;
@@ -63,6 +65,7 @@ use util::log;
 use util::secp256k1::Secp256k1PrivateKey;
 use util::secp256k1::Secp256k1PublicKey;
 
+use crate::codec::MAX_PAYLOAD_LEN;
 use crate::types::chainstate::PoxId;
 use crate::types::chainstate::StacksBlockHeader;
 use crate::types::StacksPublicKeyBuffer;
@@ -305,9 +308,15 @@ pub struct ConversationP2P {
     pub handshake_port: u16,              // from handshake
     pub peer_heartbeat: u32,              // how often do we need to ping the remote peer?
     pub peer_expire_block_height: u64,    // when does the peer's key expire?
+    pub peer_max_payload_len: u32,        // largest message payload the peer will accept from us
 
     pub data_url: UrlString, // where does this peer's data live?  Set to a 0-length string if not known.
 
+    pub handshake_burn_block_height: u64, // our burnchain height when this peer last gave us its handshake data
+    pub rehandshake_burn_block_height: u64, // our burnchain height when we last asked this peer to re-handshake
+
+    pub push_backpressure: bool, // refuse pushed blocks and microblocks, since we can't keep up with the ones we have
+
     // highest block height and consensus hash this peer has seen
     pub burnchain_tip_height: u64,
     pub burnchain_tip_burn_header_hash: BurnchainHeaderHash,
@@ -318,6 +327,9 @@ pub struct ConversationP2P {
 
     // outbound replies
     pub reply_handles: VecDeque<ReplyHandleP2P>,
+
+    // did we already decide whether or not to switch this conversation to TLS?
+    pub tls_negotiated: bool,
 }
 
 impl fmt::Display for ConversationP2P {
@@ -499,9 +511,15 @@ impl ConversationP2P {
             peer_heartbeat: 0,
             peer_services: 0,
             peer_expire_block_height: 0,
+            peer_max_payload_len: MAX_PAYLOAD_LEN,
 
             data_url: UrlString::try_from("".to_string()).unwrap(),
 
+            handshake_burn_block_height: 0,
+            rehandshake_burn_block_height: 0,
+
+            push_backpressure: false,
+
             burnchain_tip_height: 0,
             burnchain_tip_burn_header_hash: BurnchainHeaderHash::zero(),
             burnchain_stable_tip_height: 0,
@@ -509,6 +527,8 @@ impl ConversationP2P {
 
             stats: NeighborStats::new(outbound),
             reply_handles: VecDeque::new(),
+
+            tls_negotiated: false,
         }
     }
 
@@ -585,6 +605,78 @@ impl ConversationP2P {
         self.connection.has_public_key()
     }
 
+    /// Has the key this peer gave us in its last handshake expired as of the given burnchain
+    /// height?  Always false if we have not handshaked with this peer yet.
+    pub fn is_key_expired(&self, burn_block_height: u64) -> bool {
+        self.is_authenticated() && self.peer_expire_block_height <= burn_block_height
+    }
+
+    /// Has it been at least `interval` burn blocks since this peer last gave us its handshake data,
+    /// and since we last asked it to re-handshake?  Always false if we have not handshaked with
+    /// this peer yet, or if `interval` is 0.
+    pub fn needs_rehandshake(&self, burn_block_height: u64, interval: u64) -> bool {
+        let last_refresh = cmp::max(
+            self.handshake_burn_block_height,
+            self.rehandshake_burn_block_height,
+        );
+        interval > 0 && self.is_authenticated() && last_refresh + interval <= burn_block_height
+    }
+
+    /// Did the remote peer advertise this service in its last handshake?
+    pub fn supports_service(&self, flag: ServiceFlags) -> bool {
+        flag.is_set(self.peer_services)
+    }
+
+    /// Is this conversation carried over TLS?
+    pub fn is_tls(&self) -> bool {
+        self.connection.is_tls()
+    }
+
+    /// Switch this conversation over to TLS once its first handshake completes, if both we and
+    /// the remote peer advertised ServiceFlags::TLS.  The inbound side acts as the TLS server and
+    /// the outbound side as the TLS client.  Only the first handshake counts -- a later
+    /// re-handshake never upgrades the connection mid-stream.  The TLS session is bound to our
+    /// p2p key and the public key the remote peer authenticated with.
+    /// Returns true if the conversation was switched over.
+    pub fn negotiate_tls(
+        &mut self,
+        local_peer: &LocalPeer,
+        tls_config: Option<&TlsConfig>,
+    ) -> bool {
+        if self.tls_negotiated || !self.is_authenticated() {
+            return false;
+        }
+        self.tls_negotiated = true;
+
+        if !ServiceFlags::TLS.is_set(local_peer.services)
+            || !self.supports_service(ServiceFlags::TLS)
+        {
+            return false;
+        }
+        let tls_config = match tls_config {
+            Some(config) => config,
+            None => {
+                return false;
+            }
+        };
+
+        let peer_key = match self.connection.get_public_key() {
+            Some(pubk) => pubk,
+            None => {
+                return false;
+            }
+        };
+
+        let session = if self.is_outbound() {
+            tls_config.client_session(&local_peer.private_key, &peer_key)
+        } else {
+            tls_config.server_session(&local_peer.private_key, &peer_key)
+        };
+        debug!("{:?}: switching to TLS", &self);
+        self.connection.start_tls(session);
+        true
+    }
+
     pub fn get_public_key(&self) -> Option<StacksPublicKey> {
         self.connection.get_public_key()
     }
@@ -716,6 +808,24 @@ impl ConversationP2P {
         rng.gen::<u32>()
     }
 
+    /// Make an unsigned message for this conversation.  Its relayers and payload will be
+    /// compressed when it is signed if the payload is worth compressing and the remote peer
+    /// advertises that it can decompress them.
+    fn make_message(
+        &self,
+        chain_view: &BurnchainView,
+        payload: StacksMessageType,
+    ) -> StacksMessage {
+        let compress =
+            payload.is_compressible() && self.supports_service(ServiceFlags::COMPRESSION);
+        let mut msg =
+            StacksMessage::from_chain_view(self.version, self.network_id, chain_view, payload);
+        if compress {
+            msg.request_compression();
+        }
+        msg
+    }
+
     /// Generate a signed message for this conversation
     pub fn sign_message(
         &mut self,
@@ -723,8 +833,7 @@ impl ConversationP2P {
         private_key: &Secp256k1PrivateKey,
         payload: StacksMessageType,
     ) -> Result<StacksMessage, net_error> {
-        let mut msg =
-            StacksMessage::from_chain_view(self.version, self.network_id, chain_view, payload);
+        let mut msg = self.make_message(chain_view, payload);
         msg.sign(self.next_seq(), private_key)?;
         Ok(msg)
     }
@@ -738,8 +847,7 @@ impl ConversationP2P {
         mut relay_hints: Vec<RelayData>,
         payload: StacksMessageType,
     ) -> Result<StacksMessage, net_error> {
-        let mut msg =
-            StacksMessage::from_chain_view(self.version, self.network_id, chain_view, payload);
+        let mut msg = self.make_message(chain_view, payload);
         msg.relayers.append(&mut relay_hints);
         msg.sign_relay(
             &local_peer.private_key,
@@ -757,8 +865,7 @@ impl ConversationP2P {
         payload: StacksMessageType,
         seq: u32,
     ) -> Result<StacksMessage, net_error> {
-        let mut msg =
-            StacksMessage::from_chain_view(self.version, self.network_id, chain_view, payload);
+        let mut msg = self.make_message(chain_view, payload);
         msg.sign(seq, private_key)?;
         Ok(msg)
     }
@@ -798,12 +905,14 @@ impl ConversationP2P {
         let _msgtype = forward_message.get_message_name().to_owned();
         let fwd =
             self.sign_relay_message(local_peer, burnchain_view, relay_hints, forward_message)?;
+        let relayed_bytes = fwd.preamble.payload_len;
         let fwd_handle = self.relay_signed_message(fwd).map_err(|e| {
             debug!("Unable to forward a {}: {:?}", _msgtype, &e);
             e
         })?;
 
         self.stats.msgs_tx += 1;
+        monitoring::increment_relayed_bytes_counter(relayed_bytes as u64);
         Ok(fwd_handle)
     }
 
@@ -961,6 +1070,13 @@ impl ConversationP2P {
 
         self.peer_version = preamble.peer_version;
         self.peer_network_id = preamble.network_id;
+        if self.peer_services != handshake_data.services {
+            debug!(
+                "{:?}: peer advertises services {:?}",
+                &self,
+                ServiceFlags::names(handshake_data.services)
+            );
+        }
         self.peer_services = handshake_data.services;
         self.peer_expire_block_height = handshake_data.expire_block_height;
         self.handshake_addrbytes = handshake_data.addrbytes.clone();
@@ -1007,6 +1123,42 @@ impl ConversationP2P {
         msg
     }
 
+    /// Handle an inbound MessageLimit -- remember the largest payload the remote peer is willing
+    /// to accept from us, so the relayer can split up pushed blocks and microblocks to fit.
+    fn handle_message_limit(&mut self, data: &MessageLimitData) -> () {
+        let max_payload_len = cmp::min(
+            MAX_PAYLOAD_LEN,
+            cmp::max(MIN_PEER_PAYLOAD_LEN, data.max_payload_len),
+        );
+        debug!(
+            "{:?}: peer accepts payloads of at most {} bytes (requested {})",
+            &self, max_payload_len, data.max_payload_len
+        );
+        self.peer_max_payload_len = max_payload_len;
+    }
+
+    /// Tell the remote peer the largest message payload we are willing to accept from it, now
+    /// that the handshake is complete.  Only done if our limit is below the protocol maximum and
+    /// the peer advertised that it can honor a MessageLimit.
+    fn send_message_limit(
+        &mut self,
+        local_peer: &LocalPeer,
+        burnchain_view: &BurnchainView,
+    ) -> Result<(), net_error> {
+        let max_payload_len = self.connection.options.max_payload_len;
+        if max_payload_len >= MAX_PAYLOAD_LEN || !self.supports_service(ServiceFlags::MESSAGE_LIMIT)
+        {
+            return Ok(());
+        }
+
+        debug!("{:?}: Send MessageLimit({})", &self, max_payload_len);
+        let payload = StacksMessageType::MessageLimit(MessageLimitData { max_payload_len });
+        let msg = self.sign_message(burnchain_view, &local_peer.private_key, payload)?;
+        let handle = self.relay_signed_message(msg)?;
+        self.reply_handles.push_back(handle);
+        Ok(())
+    }
+
     /// Handle an inbound handshake request, and generate either a HandshakeAccept or a HandshakeReject
     /// payload to send back.
     /// A handshake will only be accepted if we do not yet know the public key of this remote peer,
@@ -1049,8 +1201,25 @@ impl ConversationP2P {
             _ => panic!("Message is not a handshake"),
         };
 
+        let pubkey_hash =
+            Hash160::from_node_public_key(&handshake_data.node_public_key.to_public_key()?);
+        if PeerDB::is_pubkey_hash_denied(peerdb.conn(), &pubkey_hash)? {
+            let reject = StacksMessage::from_chain_view(
+                self.version,
+                self.network_id,
+                chain_view,
+                StacksMessageType::HandshakeReject,
+            );
+            debug!(
+                "{:?}: public key hash {} is denied; rejecting handshake",
+                &self, &pubkey_hash
+            );
+            return Ok((Some(reject), true));
+        }
+
         let old_pubkey_opt = self.connection.get_public_key();
         let updated = self.update_from_handshake_data(&message.preamble, &handshake_data)?;
+        self.handshake_burn_block_height = chain_view.burn_block_height;
         let _authentic_msg = if !updated {
             "same"
         } else if old_pubkey_opt.is_none() {
@@ -1117,10 +1286,12 @@ impl ConversationP2P {
     /// Called from the p2p network thread.
     fn handle_handshake_accept(
         &mut self,
+        burnchain_view: &BurnchainView,
         preamble: &Preamble,
         handshake_accept: &HandshakeAcceptData,
     ) -> Result<(), net_error> {
         self.update_from_handshake_data(preamble, &handshake_accept.handshake)?;
+        self.handshake_burn_block_height = burnchain_view.burn_block_height;
         self.peer_heartbeat =
             if handshake_accept.heartbeat_interval > (MAX_PEER_HEARTBEAT_INTERVAL as u32) {
                 debug!(
@@ -1251,22 +1422,96 @@ impl ConversationP2P {
             )));
         }
 
-        // request must correspond to valid PoX fork and must be aligned to reward cycle
-        let base_snapshot = match SortitionDB::get_block_snapshot_consensus(
-            sortdb.conn(),
+        ConversationP2P::make_blocks_inv_range(
+            _local_peer,
+            burnchain,
+            sortdb,
+            chainstate,
+            header_cache,
             &get_blocks_inv.consensus_hash,
-        )? {
-            Some(sn) => sn,
-            None => {
-                debug!(
-                    "{:?}: No such block snapshot for {}",
-                    &_local_peer, &get_blocks_inv.consensus_hash
-                );
-                return Ok(StacksMessageType::Nack(NackData::new(
-                    NackErrorCodes::NoSuchBurnchainBlock,
-                )));
+            0,
+            get_blocks_inv.num_blocks as u64,
+        )
+    }
+
+    /// Create a response to an inbound GetBlocksInvPage request, but unsigned.
+    /// Replies with up to BLOCKS_INV_PAGE_MAX_BITLEN bits starting at the request's cursor, and
+    /// the cursor at which the next page starts (or 0 if this page reaches the end of the range).
+    pub fn make_getblocksinv_page_response(
+        local_peer: &LocalPeer,
+        burnchain: &Burnchain,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        header_cache: &mut BlockHeaderCache,
+        get_blocks_inv_page: &GetBlocksInvPage,
+    ) -> Result<StacksMessageType, net_error> {
+        if get_blocks_inv_page.num_blocks == 0
+            || get_blocks_inv_page.cursor >= get_blocks_inv_page.num_blocks
+        {
+            return Ok(StacksMessageType::Nack(NackData::new(
+                NackErrorCodes::InvalidMessage,
+            )));
+        }
+
+        let num_blocks = cmp::min(
+            BLOCKS_INV_PAGE_MAX_BITLEN,
+            (get_blocks_inv_page.num_blocks - get_blocks_inv_page.cursor) as u64,
+        );
+        let response = ConversationP2P::make_blocks_inv_range(
+            local_peer,
+            burnchain,
+            sortdb,
+            chainstate,
+            header_cache,
+            &get_blocks_inv_page.consensus_hash,
+            get_blocks_inv_page.cursor as u64,
+            num_blocks,
+        )?;
+
+        match response {
+            StacksMessageType::BlocksInv(blocks_inv_data) => {
+                let next_cursor = get_blocks_inv_page.cursor + (num_blocks as u32);
+                Ok(StacksMessageType::BlocksInvPage(BlocksInvPageData {
+                    cursor: get_blocks_inv_page.cursor,
+                    next_cursor: if next_cursor < get_blocks_inv_page.num_blocks {
+                        next_cursor
+                    } else {
+                        0
+                    },
+                    inv: blocks_inv_data,
+                }))
             }
-        };
+            nack => Ok(nack),
+        }
+    }
+
+    /// Load the block inventory for num_blocks sortitions, starting offset sortitions past the
+    /// start of the reward cycle identified by consensus_hash.
+    /// Returns a BlocksInv, or a Nack if the range isn't on our canonical PoX fork.
+    fn make_blocks_inv_range(
+        _local_peer: &LocalPeer,
+        burnchain: &Burnchain,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        header_cache: &mut BlockHeaderCache,
+        consensus_hash: &ConsensusHash,
+        offset: u64,
+        num_blocks: u64,
+    ) -> Result<StacksMessageType, net_error> {
+        // request must correspond to valid PoX fork and must be aligned to reward cycle
+        let base_snapshot =
+            match SortitionDB::get_block_snapshot_consensus(sortdb.conn(), consensus_hash)? {
+                Some(sn) => sn,
+                None => {
+                    debug!(
+                        "{:?}: No such block snapshot for {}",
+                        &_local_peer, consensus_hash
+                    );
+                    return Ok(StacksMessageType::Nack(NackData::new(
+                        NackErrorCodes::NoSuchBurnchainBlock,
+                    )));
+                }
+            };
 
         // must be on the main PoX fork
         if !base_snapshot.pox_valid {
@@ -1301,17 +1546,18 @@ impl ConversationP2P {
             // tip_snapshot.consensus_hash at the end.
             match SortitionDB::get_ancestor_snapshot(
                 &ic,
-                base_snapshot.block_height + (get_blocks_inv.num_blocks as u64) - 1,
+                base_snapshot.block_height + offset + num_blocks - 1,
                 &tip_sort_id,
             )? {
                 Some(sn) => sn,
                 None => {
                     debug!(
-                        "{:?}: No block known for base {} + num_blocks {} = {} block height",
+                        "{:?}: No block known for base {} + offset {} + num_blocks {} = {} block height",
                         _local_peer,
                         base_snapshot.block_height,
-                        get_blocks_inv.num_blocks,
-                        base_snapshot.block_height + (get_blocks_inv.num_blocks as u64)
+                        offset,
+                        num_blocks,
+                        base_snapshot.block_height + offset + num_blocks
                     );
                     return Ok(StacksMessageType::Nack(NackData::new(
                         NackErrorCodes::NoSuchBurnchainBlock,
@@ -1321,17 +1567,9 @@ impl ConversationP2P {
         };
 
         let block_hashes = {
-            let num_headers = cmp::min(
-                burnchain.pox_constants.reward_cycle_length as u64,
-                get_blocks_inv.num_blocks as u64,
-            );
-
             let ic = sortdb.index_conn();
-            let res = ic.get_stacks_header_hashes(
-                num_headers,
-                &tip_snapshot.consensus_hash,
-                header_cache,
-            );
+            let res =
+                ic.get_stacks_header_hashes(num_blocks, &tip_snapshot.consensus_hash, header_cache);
             match res {
                 Ok(hashes) => Ok(hashes),
                 Err(db_error::NotFoundError) => {
@@ -1407,6 +1645,53 @@ impl ConversationP2P {
         self.sign_and_reply(local_peer, burnchain_view, preamble, response)
     }
 
+    /// Handle an inbound GetBlocksInvPage request.
+    /// Returns a reply handle to the generated message (possibly a nack)
+    fn handle_getblocksinv_page(
+        &mut self,
+        local_peer: &LocalPeer,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        header_cache: &mut BlockHeaderCache,
+        burnchain_view: &BurnchainView,
+        preamble: &Preamble,
+        get_blocks_inv_page: &GetBlocksInvPage,
+    ) -> Result<ReplyHandleP2P, net_error> {
+        monitoring::increment_msg_counter("p2p_get_blocks_inv_page".to_string());
+
+        let mut response = ConversationP2P::make_getblocksinv_page_response(
+            local_peer,
+            &self.burnchain,
+            sortdb,
+            chainstate,
+            header_cache,
+            get_blocks_inv_page,
+        )?;
+
+        if let StacksMessageType::BlocksInvPage(ref mut blocks_inv_page) = &mut response {
+            debug!(
+                "{:?}: Handled GetBlocksInvPage. Reply {:?} to request {:?}",
+                &local_peer, &blocks_inv_page, get_blocks_inv_page
+            );
+
+            if self.connection.options.disable_inv_chat {
+                // never reply that we have blocks
+                test_debug!(
+                    "{:?}: Disable inv chat -- pretend like we have nothing",
+                    local_peer
+                );
+                for i in 0..blocks_inv_page.inv.block_bitvec.len() {
+                    blocks_inv_page.inv.block_bitvec[i] = 0;
+                }
+                for i in 0..blocks_inv_page.inv.microblocks_bitvec.len() {
+                    blocks_inv_page.inv.microblocks_bitvec[i] = 0;
+                }
+            }
+        }
+
+        self.sign_and_reply(local_peer, burnchain_view, preamble, response)
+    }
+
     /// Create a response an inbound GetPoxInv request, but unsigned.
     /// Returns a reply handle to the generated message (possibly a nack)
     pub fn make_getpoxinv_response(
@@ -1598,6 +1883,17 @@ impl ConversationP2P {
 
         self.stats.add_block_push((preamble.payload_len as u64) - 5);
 
+        if self.push_backpressure {
+            debug!(
+                "{:?}: Refuse pushed blocks from {:?} -- too many staging blocks to process",
+                local_peer,
+                &self.to_neighbor_key()
+            );
+            return self
+                .reply_nack(local_peer, chain_view, preamble, NackErrorCodes::Throttled)
+                .and_then(|handle| Ok(Some(handle)));
+        }
+
         if self.connection.options.max_block_push_bandwidth > 0
             && self.stats.get_block_push_bandwidth()
                 > (self.connection.options.max_block_push_bandwidth as f64)
@@ -1639,6 +1935,17 @@ impl ConversationP2P {
         self.stats
             .add_microblocks_push((preamble.payload_len as u64) - 5);
 
+        if self.push_backpressure {
+            debug!(
+                "{:?}: Refuse pushed microblocks from {:?} -- too many staging blocks to process",
+                local_peer,
+                &self.to_neighbor_key()
+            );
+            return self
+                .reply_nack(local_peer, chain_view, preamble, NackErrorCodes::Throttled)
+                .and_then(|handle| Ok(Some(handle)));
+        }
+
         if self.connection.options.max_microblocks_push_bandwidth > 0
             && self.stats.get_microblocks_push_bandwidth()
                 > (self.connection.options.max_microblocks_push_bandwidth as f64)
@@ -1720,7 +2027,17 @@ impl ConversationP2P {
                 &msg.preamble,
                 get_blocks_inv,
             ),
-            StacksMessageType::Blocks(_) => {
+            StacksMessageType::GetBlocksInvPage(ref get_blocks_inv_page) => self
+                .handle_getblocksinv_page(
+                    local_peer,
+                    sortdb,
+                    chainstate,
+                    header_cache,
+                    chain_view,
+                    &msg.preamble,
+                    get_blocks_inv_page,
+                ),
+            StacksMessageType::Blocks(_) | StacksMessageType::CompactBlock(_) => {
                 monitoring::increment_stx_blocks_received_counter();
 
                 // not handled here, but do some accounting -- we can't receive blocks too often,
@@ -1786,7 +2103,11 @@ impl ConversationP2P {
                 Ok(None)
             }
             Err(e) => {
-                debug!("Failed to handle messsage: {:?}", &e);
+                let message_type = msg.payload.get_message_name();
+                debug!(
+                    "Failed to handle messsage: {}",
+                    e.with_context(Some(self.to_neighbor_key()), Some(message_type))
+                );
                 Ok(Some(msg))
             }
         }
@@ -1923,7 +2244,14 @@ impl ConversationP2P {
                     }
                     _ => {
                         // skip this message
-                        info!("{:?}: Failed to process message: {:?}", &self, &e);
+                        info!(
+                            "{:?}: Failed to process message: {}",
+                            &self,
+                            e.with_context(
+                                Some(self.to_neighbor_key()),
+                                Some(msg.payload.get_message_name())
+                            )
+                        );
                         self.stats.msgs_err += 1;
                         self.stats.add_healthpoint(false);
                         return Ok(false);
@@ -1959,7 +2287,7 @@ impl ConversationP2P {
             }
             StacksMessageType::HandshakeAccept(ref data) => {
                 test_debug!("{:?}: Got HandshakeAccept", &self);
-                self.handle_handshake_accept(&msg.preamble, data)
+                self.handle_handshake_accept(burnchain_view, &msg.preamble, data)
                     .and_then(|_| Ok(None))
             }
             StacksMessageType::Ping(_) => {
@@ -1990,6 +2318,13 @@ impl ConversationP2P {
                 test_debug!("{:?}: Got NatPunchReply({})", &self, _m.nonce);
                 Ok(None)
             }
+            StacksMessageType::MessageLimit(ref data) => {
+                test_debug!("{:?}: Got MessageLimit({})", &self, data.max_payload_len);
+
+                consume = true;
+                self.handle_message_limit(data);
+                Ok(None)
+            }
             _ => {
                 test_debug!(
                     "{:?}: Got a data-plane message (type {})",
@@ -2031,7 +2366,7 @@ impl ConversationP2P {
             StacksMessageType::HandshakeAccept(ref data) => {
                 if solicited {
                     test_debug!("{:?}: Got unauthenticated HandshakeAccept", &self);
-                    self.handle_handshake_accept(&msg.preamble, data)
+                    self.handle_handshake_accept(burnchain_view, &msg.preamble, data)
                         .and_then(|_| Ok(None))
                 } else {
                     test_debug!("{:?}: Unsolicited unauthenticated HandshakeAccept", &self);
@@ -2163,6 +2498,18 @@ impl ConversationP2P {
                 )?
             };
 
+            // did we just finish a handshake with this peer, in either direction?
+            let handshake_finished = match msg.payload {
+                StacksMessageType::HandshakeAccept(_) => self.connection.has_public_key(),
+                _ => match reply_opt {
+                    Some(ref reply) => match reply.payload {
+                        StacksMessageType::HandshakeAccept(_) => true,
+                        _ => false,
+                    },
+                    None => false,
+                },
+            };
+
             match reply_opt {
                 None => {}
                 Some(mut reply) => {
@@ -2178,6 +2525,10 @@ impl ConversationP2P {
                 }
             }
 
+            if handshake_finished {
+                self.send_message_limit(local_peer, burnchain_view)?;
+            }
+
             let now = get_epoch_time_secs();
             let _msgtype = msg.payload.get_message_description().to_owned();
             let _relayers = format!("{:?}", &msg.relayers);
@@ -2317,6 +2668,7 @@ mod test {
     use chainstate::burn::db::sortdb::*;
     use chainstate::burn::*;
     use chainstate::stacks::db::ChainStateBootData;
+    use chainstate::stacks::test::make_codec_test_block;
     use chainstate::*;
     use core::{NETWORK_P2P_PORT, PEER_VERSION_TESTNET};
     use net::connection::*;
@@ -2661,7 +3013,14 @@ mod test {
                 StacksMessageType::HandshakeAccept(ref data) => {
                     assert_eq!(data.handshake.addrbytes, local_peer_2.addrbytes);
                     assert_eq!(data.handshake.port, local_peer_2.port);
-                    assert_eq!(data.handshake.services, local_peer_2.services);
+                    assert_eq!(
+                        data.handshake.services,
+                        local_peer_2.services
+                            | ServiceFlags::to_services(&[
+                                ServiceFlags::MESSAGE_LIMIT,
+                                ServiceFlags::URL_HEALTH
+                            ])
+                    );
                     assert_eq!(
                         data.handshake.node_public_key,
                         StacksPublicKeyBuffer::from_public_key(&Secp256k1PublicKey::from_private(
@@ -2695,6 +3054,41 @@ mod test {
                 Secp256k1PublicKey::from_private(&local_peer_2.private_key)
             );
             assert_eq!(convo_1.data_url, "http://peer2.com".into());
+
+            // convo_1 knows when convo_2's key expires
+            assert!(!convo_1.is_key_expired(chain_view.burn_block_height));
+            assert!(!convo_1.is_key_expired(local_peer_2.private_key_expire - 1));
+            assert!(convo_1.is_key_expired(local_peer_2.private_key_expire));
+
+            // an unauthenticated peer's key never counts as expired
+            let convo_3 =
+                ConversationP2P::new(123, 456, &burnchain, &socketaddr_2, &conn_opts, true, 0);
+            assert!(!convo_3.is_key_expired(local_peer_2.private_key_expire));
+
+            // both sides know when they last got each other's handshake data, and want to
+            // re-handshake once it is rehandshake_interval burn blocks old
+            let interval = conn_opts.rehandshake_interval;
+            assert_eq!(
+                convo_1.handshake_burn_block_height,
+                chain_view.burn_block_height
+            );
+            assert_eq!(
+                convo_2.handshake_burn_block_height,
+                chain_view.burn_block_height
+            );
+            assert!(
+                !convo_1.needs_rehandshake(chain_view.burn_block_height + interval - 1, interval)
+            );
+            assert!(convo_1.needs_rehandshake(chain_view.burn_block_height + interval, interval));
+            assert!(!convo_1.needs_rehandshake(chain_view.burn_block_height + interval, 0));
+            assert!(!convo_3.needs_rehandshake(chain_view.burn_block_height + interval, interval));
+
+            // ...but not again until another interval has passed since the last request
+            convo_1.rehandshake_burn_block_height = chain_view.burn_block_height + interval;
+            assert!(!convo_1.needs_rehandshake(chain_view.burn_block_height + interval, interval));
+            assert!(
+                convo_1.needs_rehandshake(chain_view.burn_block_height + 2 * interval, interval)
+            );
         })
     }
 
@@ -3352,7 +3746,14 @@ mod test {
                 StacksMessageType::HandshakeAccept(ref data) => {
                     assert_eq!(data.handshake.addrbytes, local_peer_2.addrbytes);
                     assert_eq!(data.handshake.port, local_peer_2.port);
-                    assert_eq!(data.handshake.services, local_peer_2.services);
+                    assert_eq!(
+                        data.handshake.services,
+                        local_peer_2.services
+                            | ServiceFlags::to_services(&[
+                                ServiceFlags::MESSAGE_LIMIT,
+                                ServiceFlags::URL_HEALTH
+                            ])
+                    );
                     assert_eq!(
                         data.handshake.node_public_key,
                         StacksPublicKeyBuffer::from_public_key(&Secp256k1PublicKey::from_private(
@@ -3647,7 +4048,14 @@ mod test {
                 StacksMessageType::HandshakeAccept(ref data) => {
                     assert_eq!(data.handshake.addrbytes, local_peer_2.addrbytes);
                     assert_eq!(data.handshake.port, local_peer_2.port);
-                    assert_eq!(data.handshake.services, local_peer_2.services);
+                    assert_eq!(
+                        data.handshake.services,
+                        local_peer_2.services
+                            | ServiceFlags::to_services(&[
+                                ServiceFlags::MESSAGE_LIMIT,
+                                ServiceFlags::URL_HEALTH
+                            ])
+                    );
                     assert_eq!(
                         data.handshake.node_public_key,
                         StacksPublicKeyBuffer::from_public_key(&Secp256k1PublicKey::from_private(
@@ -3930,6 +4338,119 @@ mod test {
         }
     }
 
+    #[test]
+    fn convo_message_limit() {
+        let conn_opts_1 = ConnectionOptions::default();
+        let mut conn_opts_2 = ConnectionOptions::default();
+        conn_opts_2.max_payload_len = 200_000;
+
+        let socketaddr_1 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
+        let socketaddr_2 = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)), 8081);
+
+        let burnchain = testing_burnchain_config();
+
+        let mut chain_view = BurnchainView {
+            burn_block_height: 12348,
+            burn_block_hash: BurnchainHeaderHash([0x11; 32]),
+            burn_stable_block_height: 12341,
+            burn_stable_block_hash: BurnchainHeaderHash([0x22; 32]),
+            last_burn_block_hashes: HashMap::new(),
+        };
+        chain_view.make_test_data();
+
+        let (mut peerdb_1, mut sortdb_1, pox_id_1, mut chainstate_1) = make_test_chain_dbs(
+            "convo_message_limit_1",
+            &burnchain,
+            0x9abcdef0,
+            12354,
+            "http://peer1.com".into(),
+            &vec![],
+            &vec![],
+        );
+        let (mut peerdb_2, mut sortdb_2, pox_id_2, mut chainstate_2) = make_test_chain_dbs(
+            "convo_message_limit_2",
+            &burnchain,
+            0x9abcdef0,
+            12355,
+            "http://peer2.com".into(),
+            &vec![],
+            &vec![],
+        );
+
+        db_setup(&mut peerdb_1, &mut sortdb_1, &socketaddr_1, &chain_view);
+        db_setup(&mut peerdb_2, &mut sortdb_2, &socketaddr_2, &chain_view);
+
+        let local_peer_1 = PeerDB::get_local_peer(&peerdb_1.conn()).unwrap();
+        let local_peer_2 = PeerDB::get_local_peer(&peerdb_2.conn()).unwrap();
+
+        let mut convo_1 =
+            ConversationP2P::new(123, 456, &burnchain, &socketaddr_2, &conn_opts_1, true, 0);
+        let mut convo_2 =
+            ConversationP2P::new(123, 456, &burnchain, &socketaddr_1, &conn_opts_2, true, 0);
+
+        assert_eq!(convo_1.peer_max_payload_len, MAX_PAYLOAD_LEN);
+        assert_eq!(convo_2.peer_max_payload_len, MAX_PAYLOAD_LEN);
+
+        // convo_1 sends a handshake to convo_2
+        let handshake_data_1 = HandshakeData::from_local_peer(&local_peer_1);
+        let handshake_1 = convo_1
+            .sign_message(
+                &chain_view,
+                &local_peer_1.private_key,
+                StacksMessageType::Handshake(handshake_data_1.clone()),
+            )
+            .unwrap();
+        let mut rh_1 = convo_1.send_signed_request(handshake_1, 1000000).unwrap();
+
+        // convo_2 accepts it, and follows up with its payload limit
+        convo_send_recv(&mut convo_1, vec![&mut rh_1], &mut convo_2);
+        let unhandled_2 = convo_2
+            .chat(
+                &local_peer_2,
+                &mut peerdb_2,
+                &sortdb_2,
+                &pox_id_2,
+                &mut chainstate_2,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        // convo_1 gets the handshake-accept and the limit
+        convo_send_recv(&mut convo_2, vec![&mut rh_1], &mut convo_1);
+        let unhandled_1 = convo_1
+            .chat(
+                &local_peer_1,
+                &mut peerdb_1,
+                &sortdb_1,
+                &pox_id_1,
+                &mut chainstate_1,
+                &mut BlockHeaderCache::new(),
+                &chain_view,
+            )
+            .unwrap();
+
+        let reply_1 = rh_1.recv(0).unwrap();
+        match reply_1.payload {
+            StacksMessageType::HandshakeAccept(_) => {}
+            _ => {
+                assert!(false);
+            }
+        }
+
+        // the handshake is passed along to the caller, but the limit was consumed
+        assert_eq!(unhandled_1.len(), 0);
+        assert_eq!(unhandled_2.len(), 1);
+
+        // convo_1 will honor convo_2's limit, but convo_1 has no limit of its own
+        assert_eq!(convo_1.peer_max_payload_len, 200_000);
+        assert_eq!(convo_2.peer_max_payload_len, MAX_PAYLOAD_LEN);
+
+        // limits below the minimum are clamped
+        convo_1.handle_message_limit(&MessageLimitData { max_payload_len: 1 });
+        assert_eq!(convo_1.peer_max_payload_len, MIN_PEER_PAYLOAD_LEN);
+    }
+
     #[test]
     fn convo_is_preamble_valid() {
         let conn_opts = ConnectionOptions::default();
@@ -4182,6 +4703,68 @@ mod test {
             assert_eq!(stats.num_bytes, (msg.preamble.payload_len - 1) as u64);
         }
     }
+
+    #[test]
+    fn convo_compressed_messages() {
+        let conn_opts = ConnectionOptions::default();
+        let socketaddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8091);
+        let burnchain = testing_burnchain_config();
+
+        let mut chain_view = BurnchainView {
+            burn_block_height: 12348,
+            burn_block_hash: BurnchainHeaderHash([0x11; 32]),
+            burn_stable_block_height: 12341,
+            burn_stable_block_hash: BurnchainHeaderHash([0x22; 32]),
+            last_burn_block_hashes: HashMap::new(),
+        };
+        chain_view.make_test_data();
+
+        let local_peer = LocalPeer::new(
+            123,
+            burnchain.network_id,
+            PeerAddress::from_ipv4(127, 0, 0, 1),
+            NETWORK_P2P_PORT,
+            None,
+            get_epoch_time_secs() + 123456,
+            UrlString::try_from("http://foo.com").unwrap(),
+        );
+        let mut convo =
+            ConversationP2P::new(123, 456, &burnchain, &socketaddr, &conn_opts, true, 0);
+
+        let blocks = StacksMessageType::Blocks(BlocksData {
+            blocks: vec![(ConsensusHash([0x11; 20]), make_codec_test_block(100))],
+        });
+        let ping = StacksMessageType::Ping(PingData::new());
+
+        // peer can't decompress messages
+        convo.peer_services = ServiceFlags::RELAY.bit();
+        let msg = convo
+            .sign_message(&chain_view, &local_peer.private_key, blocks.clone())
+            .unwrap();
+        assert!(!msg.preamble.is_compressed());
+
+        // peer can decompress messages, but only big payloads are compressed
+        convo.peer_services = ServiceFlags::COMPRESSION.set(convo.peer_services);
+        let msg = convo
+            .sign_message(&chain_view, &local_peer.private_key, blocks.clone())
+            .unwrap();
+        assert!(msg.preamble.is_compressed());
+
+        let msg = convo
+            .sign_reply(&chain_view, &local_peer.private_key, blocks.clone(), 123)
+            .unwrap();
+        assert!(msg.preamble.is_compressed());
+
+        let msg = convo
+            .sign_relay_message(&local_peer, &chain_view, vec![], blocks.clone())
+            .unwrap();
+        assert!(msg.preamble.is_compressed());
+
+        let msg = convo
+            .sign_message(&chain_view, &local_peer.private_key, ping)
+            .unwrap();
+        assert!(!msg.preamble.is_compressed());
+    }
 }
 
 // TODO: test bandwidth limits
@@ -21,6 +21,7 @@ use std::io::prelude::*;
 use std::io::Read;
 use std::mem;
 
+use lz4_flex::block::{compress as lz4_compress, decompress_into as lz4_decompress_into};
 use rand;
 use rand::Rng;
 use sha2::Digest;
@@ -29,11 +30,13 @@ use sha2::Sha512Trunc256;
 use burnchains::BurnchainView;
 use burnchains::PrivateKey;
 use burnchains::PublicKey;
+use burnchains::Txid;
 use chainstate::burn::ConsensusHash;
 use chainstate::stacks::StacksBlock;
 use chainstate::stacks::StacksMicroblock;
 use chainstate::stacks::StacksPublicKey;
 use chainstate::stacks::StacksTransaction;
+use chainstate::stacks::TransactionPayload;
 use chainstate::stacks::MAX_BLOCK_LEN;
 use codec::{read_next_at_most, read_next_exact, MAX_MESSAGE_LEN};
 use core::PEER_VERSION_TESTNET;
@@ -57,6 +60,7 @@ use crate::codec::{
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::BurnchainHeaderHash;
 use crate::types::chainstate::StacksBlockHeader;
+use crate::types::chainstate::StacksBlockId;
 use crate::types::StacksPublicKeyBuffer;
 
 // macro for determining how big an inv bitvec can be, given its bitlen
@@ -178,6 +182,56 @@ impl Preamble {
             ))
         }
     }
+
+    /// Are the relayers and payload that follow this preamble compressed?
+    pub fn is_compressed(&self) -> bool {
+        (self.additional_data & PREAMBLE_FLAG_COMPRESSED) != 0
+    }
+
+    pub fn set_compressed(&mut self, compressed: bool) {
+        if compressed {
+            self.additional_data |= PREAMBLE_FLAG_COMPRESSED;
+        } else {
+            self.additional_data &= !PREAMBLE_FLAG_COMPRESSED;
+        }
+    }
+}
+
+/// LZ4-compress a serialized message body (relayers and payload).  The result is prefixed with
+/// the body's length, so the receiver can refuse a body that would be too big before
+/// decompressing it.
+fn compress_message_body(body: &[u8]) -> Result<Vec<u8>, codec_error> {
+    let mut compressed = vec![];
+    write_next(&mut compressed, &(body.len() as u32))?;
+    compressed.append(&mut lz4_compress(body));
+    Ok(compressed)
+}
+
+/// Decompress a message body compressed with compress_message_body().  Fails without
+/// decompressing anything if the body claims to be bigger than a message can be, and fails if it
+/// decompresses to anything other than the length it claims.
+fn decompress_message_body(bytes: &[u8]) -> Result<Vec<u8>, codec_error> {
+    let mut fd = bytes;
+    let body_len: u32 = read_next(&mut fd)?;
+    if body_len > MAX_MESSAGE_LEN - PREAMBLE_ENCODED_SIZE {
+        return Err(codec_error::DeserializeError(format!(
+            "Compressed message body would be too big: {} bytes",
+            body_len
+        )));
+    }
+
+    // decompress into a buffer of exactly the claimed length, so a body can't decompress to more
+    let mut body = vec![0u8; body_len as usize];
+    let body_written = lz4_decompress_into(fd, &mut body).map_err(|e| {
+        codec_error::DeserializeError(format!("Failed to decompress message body: {:?}", &e))
+    })?;
+    if body_written != body.len() {
+        return Err(codec_error::DeserializeError(format!(
+            "Compressed message body decompressed to {} bytes, but should be {} bytes",
+            body_written, body_len
+        )));
+    }
+    Ok(body)
 }
 
 impl StacksMessageCodec for Preamble {
@@ -301,6 +355,68 @@ impl StacksMessageCodec for BlocksInvData {
     }
 }
 
+impl StacksMessageCodec for GetBlocksInvPage {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.consensus_hash)?;
+        write_next(fd, &self.num_blocks)?;
+        write_next(fd, &self.cursor)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<GetBlocksInvPage, codec_error> {
+        let consensus_hash: ConsensusHash = read_next(fd)?;
+        let num_blocks: u32 = read_next(fd)?;
+        let cursor: u32 = read_next(fd)?;
+        if num_blocks == 0 {
+            return Err(codec_error::DeserializeError(
+                "GetBlocksInvPage must request at least one block".to_string(),
+            ));
+        }
+        if cursor >= num_blocks {
+            return Err(codec_error::DeserializeError(
+                "GetBlocksInvPage cursor is past the end of its range".to_string(),
+            ));
+        }
+
+        Ok(GetBlocksInvPage {
+            consensus_hash,
+            num_blocks,
+            cursor,
+        })
+    }
+}
+
+impl StacksMessageCodec for BlocksInvPageData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.cursor)?;
+        write_next(fd, &self.next_cursor)?;
+        write_next(fd, &self.inv)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<BlocksInvPageData, codec_error> {
+        let cursor: u32 = read_next(fd)?;
+        let next_cursor: u32 = read_next(fd)?;
+        let inv: BlocksInvData = read_next(fd)?;
+        if inv.bitlen as u64 > BLOCKS_INV_PAGE_MAX_BITLEN {
+            return Err(codec_error::DeserializeError(
+                "BlocksInvPage has too many bits".to_string(),
+            ));
+        }
+        if next_cursor != 0 && (next_cursor as u64) != (cursor as u64) + (inv.bitlen as u64) {
+            return Err(codec_error::DeserializeError(
+                "BlocksInvPage next cursor does not follow its bits".to_string(),
+            ));
+        }
+
+        Ok(BlocksInvPageData {
+            cursor,
+            next_cursor,
+            inv,
+        })
+    }
+}
+
 impl BlocksInvData {
     pub fn empty() -> BlocksInvData {
         BlocksInvData {
@@ -614,7 +730,12 @@ impl HandshakeData {
         HandshakeData {
             addrbytes: addrbytes,
             port: port,
-            services: local_peer.services,
+            // every node can receive and honor a MessageLimit and a DataUrlHealth, regardless of
+            // its other services
+            services: ServiceFlags::to_services(&[
+                ServiceFlags::MESSAGE_LIMIT,
+                ServiceFlags::URL_HEALTH,
+            ]) | local_peer.services,
             node_public_key: StacksPublicKeyBuffer::from_public_key(
                 &Secp256k1PublicKey::from_private(&local_peer.private_key),
             ),
@@ -761,6 +882,153 @@ impl StacksMessageCodec for NatPunchData {
     }
 }
 
+impl StacksMessageCodec for MessageLimitData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.max_payload_len)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<MessageLimitData, codec_error> {
+        let max_payload_len: u32 = read_next(fd)?;
+        Ok(MessageLimitData { max_payload_len })
+    }
+}
+
+impl StacksMessageCodec for DataUrlHealthData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.unreachable)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<DataUrlHealthData, codec_error> {
+        let unreachable: Vec<UrlString> =
+            read_next_at_most::<_, UrlString>(fd, MAX_DATA_URL_HEALTH_REPORTS)?;
+        Ok(DataUrlHealthData { unreachable })
+    }
+}
+
+impl StacksMessageCodec for MicroblocksSubscribeData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &(self.subscribe as u8))?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<MicroblocksSubscribeData, codec_error> {
+        let subscribe: u8 = read_next(fd)?;
+        if subscribe > 1 {
+            return Err(codec_error::DeserializeError(
+                "MicroblocksSubscribe flag must be 0 or 1".to_string(),
+            ));
+        }
+        Ok(MicroblocksSubscribeData {
+            subscribe: subscribe == 1,
+        })
+    }
+}
+
+impl CompactBlockData {
+    /// Make a compact block from a block we're about to push.  Only the coinbase gets sent in
+    /// full, since it never goes through the mempool.
+    pub fn from_block(consensus_hash: &ConsensusHash, block: &StacksBlock) -> CompactBlockData {
+        CompactBlockData::from_block_prefilled(consensus_hash, block, &HashSet::new())
+    }
+
+    /// Make a compact block from a block we're about to push, sending the coinbase and the
+    /// `prefill` transactions in full.
+    pub fn from_block_prefilled(
+        consensus_hash: &ConsensusHash,
+        block: &StacksBlock,
+        prefill: &HashSet<Txid>,
+    ) -> CompactBlockData {
+        CompactBlockData {
+            consensus_hash: consensus_hash.clone(),
+            header: block.header.clone(),
+            txids: block.txs.iter().map(|tx| tx.txid()).collect(),
+            txs: block
+                .txs
+                .iter()
+                .filter(|tx| match tx.payload {
+                    TransactionPayload::Coinbase(_) => true,
+                    _ => prefill.contains(&tx.txid()),
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+impl StacksMessageCodec for CompactBlockData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.consensus_hash)?;
+        write_next(fd, &self.header)?;
+        write_next(fd, &self.txids)?;
+        write_next(fd, &self.txs)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<CompactBlockData, codec_error> {
+        let consensus_hash: ConsensusHash = read_next(fd)?;
+        let header: StacksBlockHeader = read_next(fd)?;
+        let txids: Vec<Txid> = read_next_at_most::<_, Txid>(fd, MAX_COMPACT_BLOCK_TXS)?;
+        let txs: Vec<StacksTransaction> = {
+            // loose upper-bound
+            let mut bound_read = BoundReader::from_reader(fd, MAX_BLOCK_LEN as u64);
+            read_next_at_most::<_, StacksTransaction>(&mut bound_read, MAX_COMPACT_BLOCK_TXS)
+        }?;
+
+        // txids must be unique, and every transaction sent in full must be one of them
+        let mut present = HashSet::new();
+        for txid in txids.iter() {
+            if !present.insert(txid.clone()) {
+                return Err(codec_error::DeserializeError(
+                    "Invalid CompactBlockData: duplicate txid".to_string(),
+                ));
+            }
+        }
+        for tx in txs.iter() {
+            if !present.contains(&tx.txid()) {
+                return Err(codec_error::DeserializeError(
+                    "Invalid CompactBlockData: transaction not in the block".to_string(),
+                ));
+            }
+        }
+
+        Ok(CompactBlockData {
+            consensus_hash,
+            header,
+            txids,
+            txs,
+        })
+    }
+}
+
+impl StacksMessageCodec for (StacksBlockId, Hash160) {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.0)?;
+        write_next(fd, &self.1)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<(StacksBlockId, Hash160), codec_error> {
+        let index_block_hash: StacksBlockId = read_next(fd)?;
+        let content_hash: Hash160 = read_next(fd)?;
+        Ok((index_block_hash, content_hash))
+    }
+}
+
+impl StacksMessageCodec for AttachmentsAvailableData {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        write_next(fd, &self.available)?;
+        Ok(())
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<AttachmentsAvailableData, codec_error> {
+        let available: Vec<(StacksBlockId, Hash160)> =
+            read_next_at_most::<_, (StacksBlockId, Hash160)>(fd, ATTACHMENTS_AVAILABLE_MAX_LEN)?;
+        Ok(AttachmentsAvailableData { available })
+    }
+}
+
 impl StacksMessageCodec for RelayData {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         write_next(fd, &self.peer)?;
@@ -799,6 +1067,17 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => StacksMessageID::Pong,
             StacksMessageType::NatPunchRequest(ref _m) => StacksMessageID::NatPunchRequest,
             StacksMessageType::NatPunchReply(ref _m) => StacksMessageID::NatPunchReply,
+            StacksMessageType::MessageLimit(ref _m) => StacksMessageID::MessageLimit,
+            StacksMessageType::DataUrlHealth(ref _m) => StacksMessageID::DataUrlHealth,
+            StacksMessageType::CompactBlock(ref _m) => StacksMessageID::CompactBlock,
+            StacksMessageType::AttachmentsAvailable(ref _m) => {
+                StacksMessageID::AttachmentsAvailable
+            }
+            StacksMessageType::GetBlocksInvPage(ref _m) => StacksMessageID::GetBlocksInvPage,
+            StacksMessageType::BlocksInvPage(ref _m) => StacksMessageID::BlocksInvPage,
+            StacksMessageType::MicroblocksSubscribe(ref _m) => {
+                StacksMessageID::MicroblocksSubscribe
+            }
         }
     }
 
@@ -823,6 +1102,13 @@ impl StacksMessageType {
             StacksMessageType::Pong(ref _m) => "Pong",
             StacksMessageType::NatPunchRequest(ref _m) => "NatPunchRequest",
             StacksMessageType::NatPunchReply(ref _m) => "NatPunchReply",
+            StacksMessageType::MessageLimit(ref _m) => "MessageLimit",
+            StacksMessageType::DataUrlHealth(ref _m) => "DataUrlHealth",
+            StacksMessageType::CompactBlock(ref _m) => "CompactBlock",
+            StacksMessageType::AttachmentsAvailable(ref _m) => "AttachmentsAvailable",
+            StacksMessageType::GetBlocksInvPage(ref _m) => "GetBlocksInvPage",
+            StacksMessageType::BlocksInvPage(ref _m) => "BlocksInvPage",
+            StacksMessageType::MicroblocksSubscribe(ref _m) => "MicroblocksSubscribe",
         }
     }
 
@@ -881,6 +1167,45 @@ impl StacksMessageType {
             StacksMessageType::NatPunchReply(ref m) => {
                 format!("NatPunchReply({},{}:{})", m.nonce, &m.addrbytes, m.port)
             }
+            StacksMessageType::MessageLimit(ref m) => {
+                format!("MessageLimit({})", m.max_payload_len)
+            }
+            StacksMessageType::DataUrlHealth(ref m) => {
+                format!("DataUrlHealth({})", m.unreachable.len())
+            }
+            StacksMessageType::CompactBlock(ref m) => format!(
+                "CompactBlock({}/{},{}/{})",
+                &m.consensus_hash,
+                m.header.block_hash(),
+                m.txs.len(),
+                m.txids.len()
+            ),
+            StacksMessageType::AttachmentsAvailable(ref m) => {
+                format!("AttachmentsAvailable({})", m.available.len())
+            }
+            StacksMessageType::GetBlocksInvPage(ref m) => format!(
+                "GetBlocksInvPage({},{},{})",
+                &m.consensus_hash, m.num_blocks, m.cursor
+            ),
+            StacksMessageType::BlocksInvPage(ref m) => format!(
+                "BlocksInvPage({},{},{},{:?},{:?})",
+                m.cursor,
+                m.next_cursor,
+                m.inv.bitlen,
+                &m.inv.block_bitvec,
+                &m.inv.microblocks_bitvec
+            ),
+            StacksMessageType::MicroblocksSubscribe(ref m) => {
+                format!("MicroblocksSubscribe({})", m.subscribe)
+            }
+        }
+    }
+
+    /// Is this payload worth compressing for peers that advertise ServiceFlags::COMPRESSION?
+    pub fn is_compressible(&self) -> bool {
+        match *self {
+            StacksMessageType::Blocks(_) | StacksMessageType::Microblocks(_) => true,
+            _ => false,
         }
     }
 }
@@ -914,6 +1239,17 @@ impl StacksMessageCodec for StacksMessageID {
             x if x == StacksMessageID::Pong as u8 => StacksMessageID::Pong,
             x if x == StacksMessageID::NatPunchRequest as u8 => StacksMessageID::NatPunchRequest,
             x if x == StacksMessageID::NatPunchReply as u8 => StacksMessageID::NatPunchReply,
+            x if x == StacksMessageID::MessageLimit as u8 => StacksMessageID::MessageLimit,
+            x if x == StacksMessageID::DataUrlHealth as u8 => StacksMessageID::DataUrlHealth,
+            x if x == StacksMessageID::CompactBlock as u8 => StacksMessageID::CompactBlock,
+            x if x == StacksMessageID::AttachmentsAvailable as u8 => {
+                StacksMessageID::AttachmentsAvailable
+            }
+            x if x == StacksMessageID::GetBlocksInvPage as u8 => StacksMessageID::GetBlocksInvPage,
+            x if x == StacksMessageID::BlocksInvPage as u8 => StacksMessageID::BlocksInvPage,
+            x if x == StacksMessageID::MicroblocksSubscribe as u8 => {
+                StacksMessageID::MicroblocksSubscribe
+            }
             _ => {
                 return Err(codec_error::DeserializeError(
                     "Unknown message ID".to_string(),
@@ -947,6 +1283,13 @@ impl StacksMessageCodec for StacksMessageType {
             StacksMessageType::Pong(ref m) => write_next(fd, m)?,
             StacksMessageType::NatPunchRequest(ref nonce) => write_next(fd, nonce)?,
             StacksMessageType::NatPunchReply(ref m) => write_next(fd, m)?,
+            StacksMessageType::MessageLimit(ref m) => write_next(fd, m)?,
+            StacksMessageType::DataUrlHealth(ref m) => write_next(fd, m)?,
+            StacksMessageType::CompactBlock(ref m) => write_next(fd, m)?,
+            StacksMessageType::AttachmentsAvailable(ref m) => write_next(fd, m)?,
+            StacksMessageType::GetBlocksInvPage(ref m) => write_next(fd, m)?,
+            StacksMessageType::BlocksInvPage(ref m) => write_next(fd, m)?,
+            StacksMessageType::MicroblocksSubscribe(ref m) => write_next(fd, m)?,
         }
         Ok(())
     }
@@ -1024,6 +1367,34 @@ impl StacksMessageCodec for StacksMessageType {
                 let m: NatPunchData = read_next(fd)?;
                 StacksMessageType::NatPunchReply(m)
             }
+            StacksMessageID::MessageLimit => {
+                let m: MessageLimitData = read_next(fd)?;
+                StacksMessageType::MessageLimit(m)
+            }
+            StacksMessageID::DataUrlHealth => {
+                let m: DataUrlHealthData = read_next(fd)?;
+                StacksMessageType::DataUrlHealth(m)
+            }
+            StacksMessageID::CompactBlock => {
+                let m: CompactBlockData = read_next(fd)?;
+                StacksMessageType::CompactBlock(m)
+            }
+            StacksMessageID::AttachmentsAvailable => {
+                let m: AttachmentsAvailableData = read_next(fd)?;
+                StacksMessageType::AttachmentsAvailable(m)
+            }
+            StacksMessageID::GetBlocksInvPage => {
+                let m: GetBlocksInvPage = read_next(fd)?;
+                StacksMessageType::GetBlocksInvPage(m)
+            }
+            StacksMessageID::BlocksInvPage => {
+                let m: BlocksInvPageData = read_next(fd)?;
+                StacksMessageType::BlocksInvPage(m)
+            }
+            StacksMessageID::MicroblocksSubscribe => {
+                let m: MicroblocksSubscribeData = read_next(fd)?;
+                StacksMessageType::MicroblocksSubscribe(m)
+            }
             StacksMessageID::Reserved => {
                 return Err(codec_error::DeserializeError(
                     "Unsupported message ID 'reserved'".to_string(),
@@ -1037,8 +1408,8 @@ impl StacksMessageCodec for StacksMessageType {
 impl StacksMessageCodec for StacksMessage {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         write_next(fd, &self.preamble)?;
-        write_next(fd, &self.relayers)?;
-        write_next(fd, &self.payload)?;
+        let body = self.body_bytes()?;
+        fd.write_all(&body).map_err(codec_error::WriteError)?;
         Ok(())
     }
 
@@ -1050,8 +1421,21 @@ impl StacksMessageCodec for StacksMessage {
             ));
         }
 
-        let relayers: Vec<RelayData> = read_next_at_most::<_, RelayData>(fd, MAX_RELAYERS_LEN)?;
-        let payload: StacksMessageType = read_next(fd)?;
+        let (relayers, payload) = if preamble.is_compressed() {
+            let mut compressed = vec![0u8; preamble.payload_len as usize];
+            fd.read_exact(&mut compressed)
+                .map_err(codec_error::ReadError)?;
+            let body = decompress_message_body(&compressed)?;
+            let body_fd = &mut &body[..];
+            let relayers: Vec<RelayData> =
+                read_next_at_most::<_, RelayData>(body_fd, MAX_RELAYERS_LEN)?;
+            let payload: StacksMessageType = read_next(body_fd)?;
+            (relayers, payload)
+        } else {
+            let relayers: Vec<RelayData> = read_next_at_most::<_, RelayData>(fd, MAX_RELAYERS_LEN)?;
+            let payload: StacksMessageType = read_next(fd)?;
+            (relayers, payload)
+        };
 
         let message = StacksMessage {
             preamble,
@@ -1117,12 +1501,47 @@ impl StacksMessage {
         }
     }
 
+    /// Ask for this message's relayers and payload to be compressed when it is signed.  The
+    /// message is still sent uncompressed if it is too small for compression to pay off.
+    pub fn request_compression(&mut self) {
+        self.preamble.set_compressed(true);
+    }
+
+    /// The relayers and payload as they are sent after the preamble: compressed if the preamble
+    /// says so.
+    fn body_bytes(&self) -> Result<Vec<u8>, codec_error> {
+        let mut body = vec![];
+        self.relayers.consensus_serialize(&mut body)?;
+        self.payload.consensus_serialize(&mut body)?;
+        if self.preamble.is_compressed() {
+            compress_message_body(&body)
+        } else {
+            Ok(body)
+        }
+    }
+
     /// Sign the stacks message
     fn do_sign(&mut self, private_key: &Secp256k1PrivateKey) -> Result<(), net_error> {
         let mut message_bits = vec![];
         self.relayers.consensus_serialize(&mut message_bits)?;
         self.payload.consensus_serialize(&mut message_bits)?;
 
+        if self.preamble.is_compressed() {
+            let compressed_bits = if message_bits.len() >= MIN_COMPRESSED_BODY_LEN {
+                Some(compress_message_body(&message_bits)?)
+            } else {
+                None
+            };
+            match compressed_bits {
+                Some(bits) if bits.len() < message_bits.len() => {
+                    message_bits = bits;
+                }
+                _ => {
+                    self.preamble.set_compressed(false);
+                }
+            }
+        }
+
         self.preamble.payload_len = message_bits.len() as u32;
         self.preamble.sign(&message_bits[..], private_key)
     }
@@ -1189,10 +1608,7 @@ impl StacksMessage {
     /// * the buffer doesn't encode a secp256k1 public key
     pub fn verify_secp256k1(&self, public_key: &StacksPublicKeyBuffer) -> Result<(), net_error> {
         let secp256k1_pubkey = public_key.to_public_key()?;
-
-        let mut message_bits = vec![];
-        self.relayers.consensus_serialize(&mut message_bits)?;
-        self.payload.consensus_serialize(&mut message_bits)?;
+        let message_bits = self.body_bytes()?;
 
         let mut p = self.preamble.clone();
         p.verify(&message_bits, &secp256k1_pubkey)
@@ -1266,14 +1682,22 @@ impl ProtocolFamily for StacksP2P {
             ));
         }
 
-        let mut cursor = io::Cursor::new(&bytes[0..(preamble.payload_len as usize)]);
-        let (relayers, payload) = StacksMessage::deserialize_body(&mut cursor)?;
+        let body_bytes = &bytes[0..(preamble.payload_len as usize)];
+        let (relayers, payload, consumed) = if preamble.is_compressed() {
+            let body = decompress_message_body(body_bytes)?;
+            let (relayers, payload) = StacksMessage::deserialize_body(&mut &body[..])?;
+            (relayers, payload, body_bytes.len())
+        } else {
+            let mut cursor = io::Cursor::new(body_bytes);
+            let (relayers, payload) = StacksMessage::deserialize_body(&mut cursor)?;
+            (relayers, payload, cursor.position() as usize)
+        };
         let message = StacksMessage {
             preamble: preamble.clone(),
             relayers: relayers,
             payload: payload,
         };
-        Ok((message, cursor.position() as usize))
+        Ok((message, consumed))
     }
 
     fn verify_payload_bytes(
@@ -1299,6 +1723,7 @@ impl ProtocolFamily for StacksP2P {
 
 #[cfg(test)]
 pub mod test {
+    use chainstate::stacks::test::make_codec_test_block;
     use codec::NEIGHBOR_ADDRESS_ENCODED_SIZE;
     use util::hash::hex_bytes;
     use util::secp256k1::*;
@@ -1707,6 +2132,96 @@ pub mod test {
         );
     }
 
+    #[test]
+    fn codec_GetBlocksInvPage() {
+        let getblocksinvpage = GetBlocksInvPage {
+            consensus_hash: ConsensusHash([0x55; 20]),
+            num_blocks: 0x1000,
+            cursor: 0x20,
+        };
+
+        let getblocksinvpage_bytes: Vec<u8> = vec![
+            // consensus hash
+            0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55, 0x55,
+            0x55, 0x55, 0x55, 0x55, 0x55, 0x55, // num blocks
+            0x00, 0x00, 0x10, 0x00, // cursor
+            0x00, 0x00, 0x00, 0x20,
+        ];
+
+        check_codec_and_corruption::<GetBlocksInvPage>(&getblocksinvpage, &getblocksinvpage_bytes);
+
+        // must ask for something
+        let empty = GetBlocksInvPage {
+            consensus_hash: ConsensusHash([0x55; 20]),
+            num_blocks: 0,
+            cursor: 0,
+        };
+        assert!(check_deserialize_failure::<GetBlocksInvPage>(&empty));
+
+        // cursor must be inside the range
+        let past_end = GetBlocksInvPage {
+            consensus_hash: ConsensusHash([0x55; 20]),
+            num_blocks: 0x20,
+            cursor: 0x20,
+        };
+        assert!(check_deserialize_failure::<GetBlocksInvPage>(&past_end));
+    }
+
+    #[test]
+    fn codec_BlocksInvPageData() {
+        let page = BlocksInvPageData {
+            cursor: 0x10,
+            next_cursor: 0x18,
+            inv: BlocksInvData {
+                bitlen: 8,
+                block_bitvec: vec![0x0f],
+                microblocks_bitvec: vec![0xf0],
+            },
+        };
+
+        let page_bytes: Vec<u8> = vec![
+            // cursor
+            0x00, 0x00, 0x00, 0x10, // next cursor
+            0x00, 0x00, 0x00, 0x18, // bitlen
+            0x00, 0x08, // block bitvec
+            0x00, 0x00, 0x00, 0x01, 0x0f, // microblocks bitvec
+            0x00, 0x00, 0x00, 0x01, 0xf0,
+        ];
+
+        check_codec_and_corruption::<BlocksInvPageData>(&page, &page_bytes);
+
+        // last page
+        let mut last_page = page.clone();
+        last_page.next_cursor = 0;
+        let bytes = last_page.serialize_to_vec();
+        assert_eq!(
+            BlocksInvPageData::consensus_deserialize(&mut &bytes[..]).unwrap(),
+            last_page
+        );
+
+        // next cursor must pick up right after this page's bits
+        let mut bad_next_cursor = page.clone();
+        bad_next_cursor.next_cursor = 0x19;
+        assert!(check_deserialize_failure::<BlocksInvPageData>(
+            &bad_next_cursor
+        ));
+
+        // page can't be too big
+        let too_long = BlocksInvPageData {
+            cursor: 0,
+            next_cursor: 0,
+            inv: BlocksInvData {
+                bitlen: (BLOCKS_INV_PAGE_MAX_BITLEN + 1) as u16,
+                block_bitvec: vec![0xff; BITVEC_LEN!(BLOCKS_INV_PAGE_MAX_BITLEN + 1) as usize],
+                microblocks_bitvec: vec![
+                    0xff;
+                    BITVEC_LEN!(BLOCKS_INV_PAGE_MAX_BITLEN + 1) as usize
+                ],
+            },
+        };
+        assert!(check_deserialize_failure::<BlocksInvPageData>(&too_long));
+    }
+
     #[test]
     fn codec_NeighborAddress() {
         let data = NeighborAddress {
@@ -1814,6 +2329,36 @@ pub mod test {
         check_codec_and_corruption::<HandshakeData>(&data, &bytes);
     }
 
+    #[test]
+    fn service_flags_registry() {
+        let services = ServiceFlags::to_services(&[ServiceFlags::RELAY, ServiceFlags::ATLAS]);
+        assert_eq!(services, 0x0011);
+        assert!(ServiceFlags::RELAY.is_set(services));
+        assert!(ServiceFlags::ATLAS.is_set(services));
+        assert!(!ServiceFlags::ARCHIVAL.is_set(services));
+
+        let services = ServiceFlags::ARCHIVAL.set(services);
+        assert!(ServiceFlags::ARCHIVAL.is_set(services));
+        let services = ServiceFlags::RELAY.clear(services);
+        assert!(!ServiceFlags::RELAY.is_set(services));
+        assert_eq!(services, 0x0018);
+
+        for flag in SERVICE_FLAGS_REGISTRY.iter() {
+            assert_eq!(ServiceFlags::from_name(flag.name()), Some(*flag));
+        }
+        assert_eq!(ServiceFlags::from_name("nonexistent"), None);
+
+        // unregistered bits are still reported
+        assert_eq!(
+            ServiceFlags::names(0x8000 | services),
+            vec![
+                "archival".to_string(),
+                "atlas".to_string(),
+                "0x8000".to_string()
+            ]
+        );
+    }
+
     #[test]
     fn codec_HandshakeAcceptData() {
         let data = HandshakeAcceptData {
@@ -1943,6 +2488,108 @@ pub mod test {
         check_codec_and_corruption::<NatPunchData>(&data, &bytes);
     }
 
+    #[test]
+    fn codec_MessageLimit() {
+        let data = MessageLimitData {
+            max_payload_len: 0x01020304,
+        };
+        let bytes = vec![0x01, 0x02, 0x03, 0x04];
+
+        check_codec_and_corruption::<MessageLimitData>(&data, &bytes);
+    }
+
+    #[test]
+    fn codec_CompactBlockData() {
+        let block = make_codec_test_block(3);
+        let data = CompactBlockData::from_block(&ConsensusHash([0x11; 20]), &block);
+
+        let mut bytes = vec![0x11; 20];
+        bytes.append(&mut block.header.serialize_to_vec());
+        bytes.append(&mut vec![0x00, 0x00, 0x00, 0x03]);
+        for tx in block.txs.iter() {
+            bytes.extend_from_slice(tx.txid().as_bytes());
+        }
+        bytes.append(&mut vec![0x00, 0x00, 0x00, 0x01]);
+        bytes.append(&mut block.txs[0].serialize_to_vec());
+
+        check_codec_and_corruption::<CompactBlockData>(&data, &bytes);
+
+        // duplicate txid
+        let mut bad_data = data.clone();
+        bad_data.txids[2] = bad_data.txids[1].clone();
+        let bytes = bad_data.serialize_to_vec();
+        assert!(CompactBlockData::consensus_deserialize(&mut &bytes[..]).is_err());
+
+        // transaction sent in full that isn't in the block
+        let mut bad_data = data.clone();
+        bad_data.txids.remove(0);
+        let bytes = bad_data.serialize_to_vec();
+        assert!(CompactBlockData::consensus_deserialize(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn codec_AttachmentsAvailableData() {
+        let data = AttachmentsAvailableData {
+            available: vec![
+                (StacksBlockId([0x11; 32]), Hash160([0x22; 20])),
+                (StacksBlockId([0x33; 32]), Hash160([0x44; 20])),
+            ],
+        };
+        let mut bytes = vec![0x00, 0x00, 0x00, 0x02];
+        bytes.extend_from_slice(&[0x11; 32]);
+        bytes.extend_from_slice(&[0x22; 20]);
+        bytes.extend_from_slice(&[0x33; 32]);
+        bytes.extend_from_slice(&[0x44; 20]);
+
+        check_codec_and_corruption::<AttachmentsAvailableData>(&data, &bytes);
+
+        // too many attachments
+        let data = AttachmentsAvailableData {
+            available: (0..(ATTACHMENTS_AVAILABLE_MAX_LEN + 1))
+                .map(|i| (StacksBlockId([i as u8; 32]), Hash160([i as u8; 20])))
+                .collect(),
+        };
+        let bytes = data.serialize_to_vec();
+        assert!(AttachmentsAvailableData::consensus_deserialize(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn codec_DataUrlHealthData() {
+        let data = DataUrlHealthData {
+            unreachable: vec![
+                UrlString::try_from("http://a.com").unwrap(),
+                UrlString::try_from("http://1.2.3.4:20443").unwrap(),
+            ],
+        };
+        let mut bytes = vec![0x00, 0x00, 0x00, 0x02, 12];
+        bytes.extend_from_slice("http://a.com".as_bytes());
+        bytes.push(20);
+        bytes.extend_from_slice("http://1.2.3.4:20443".as_bytes());
+
+        check_codec_and_corruption::<DataUrlHealthData>(&data, &bytes);
+
+        // too many URLs
+        let data = DataUrlHealthData {
+            unreachable: (0..(MAX_DATA_URL_HEALTH_REPORTS + 1))
+                .map(|i| UrlString::try_from(format!("http://{}.com", i).as_str()).unwrap())
+                .collect(),
+        };
+        let bytes = data.serialize_to_vec();
+        assert!(DataUrlHealthData::consensus_deserialize(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn codec_MicroblocksSubscribeData() {
+        let data = MicroblocksSubscribeData { subscribe: true };
+        check_codec_and_corruption::<MicroblocksSubscribeData>(&data, &vec![0x01]);
+
+        let data = MicroblocksSubscribeData { subscribe: false };
+        check_codec_and_corruption::<MicroblocksSubscribeData>(&data, &vec![0x00]);
+
+        // not a flag
+        assert!(MicroblocksSubscribeData::consensus_deserialize(&mut &[0x02][..]).is_err());
+    }
+
     #[test]
     fn codec_StacksMessage() {
         let payloads: Vec<StacksMessageType> = vec![
@@ -2059,6 +2706,34 @@ pub mod test {
                 port: 12345,
                 nonce: 0x12345678,
             }),
+            StacksMessageType::MessageLimit(MessageLimitData {
+                max_payload_len: 0x01020304,
+            }),
+            StacksMessageType::DataUrlHealth(DataUrlHealthData {
+                unreachable: vec![UrlString::try_from("http://a.com").unwrap()],
+            }),
+            StacksMessageType::CompactBlock(CompactBlockData::from_block(
+                &ConsensusHash([0x11; 20]),
+                &make_codec_test_block(3),
+            )),
+            StacksMessageType::AttachmentsAvailable(AttachmentsAvailableData {
+                available: vec![(StacksBlockId([0x11; 32]), Hash160([0x22; 20]))],
+            }),
+            StacksMessageType::GetBlocksInvPage(GetBlocksInvPage {
+                consensus_hash: ConsensusHash([0x01; 20]),
+                num_blocks: 4096,
+                cursor: 8,
+            }),
+            StacksMessageType::BlocksInvPage(BlocksInvPageData {
+                cursor: 8,
+                next_cursor: 0,
+                inv: BlocksInvData {
+                    bitlen: 2,
+                    block_bitvec: vec![0x03],
+                    microblocks_bitvec: vec![0x03],
+                },
+            }),
+            StacksMessageType::MicroblocksSubscribe(MicroblocksSubscribeData { subscribe: true }),
         ];
 
         let mut maximal_relayers: Vec<RelayData> = vec![];
@@ -2180,6 +2855,146 @@ pub mod test {
         ping.verify_secp256k1(&pubkey_buf).unwrap();
     }
 
+    #[test]
+    fn codec_compressed_sign_and_verify() {
+        let privkey = Secp256k1PrivateKey::new();
+        let pubkey = Secp256k1PublicKey::from_private(&privkey);
+        let pubkey_buf = StacksPublicKeyBuffer::from_public_key(&pubkey);
+
+        let payload = StacksMessageType::Blocks(BlocksData {
+            blocks: vec![(ConsensusHash([0x11; 20]), make_codec_test_block(100))],
+        });
+        assert!(payload.is_compressible());
+
+        let mut uncompressed = StacksMessage::new(
+            PEER_VERSION_TESTNET,
+            0x9abcdef0,
+            12345,
+            &BurnchainHeaderHash([0x11; 32]),
+            12339,
+            &BurnchainHeaderHash([0x22; 32]),
+            payload,
+        );
+        let mut compressed = uncompressed.clone();
+        uncompressed.sign(444, &privkey).unwrap();
+        compressed.request_compression();
+        compressed.sign(444, &privkey).unwrap();
+
+        assert!(!uncompressed.preamble.is_compressed());
+        assert!(compressed.preamble.is_compressed());
+        assert!(compressed.preamble.payload_len < uncompressed.preamble.payload_len);
+        compressed.verify_secp256k1(&pubkey_buf).unwrap();
+
+        // round-trips through the codec
+        let bytes = compressed.serialize_to_vec();
+        assert_eq!(
+            bytes.len() as u32,
+            PREAMBLE_ENCODED_SIZE + compressed.preamble.payload_len
+        );
+        let decoded = StacksMessage::consensus_deserialize(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, compressed);
+
+        // round-trips through the protocol family, which checks the signature over the
+        // compressed bytes
+        let mut protocol = StacksP2P::new();
+        let (preamble, preamble_len) = protocol.read_preamble(&bytes).unwrap();
+        assert!(preamble.is_compressed());
+        protocol
+            .verify_payload_bytes(&pubkey, &preamble, &bytes[preamble_len..])
+            .unwrap();
+        let (decoded, consumed) = protocol
+            .read_payload(&preamble, &bytes[preamble_len..])
+            .unwrap();
+        assert_eq!(decoded, compressed);
+        assert_eq!(consumed, preamble.payload_len as usize);
+
+        // small messages are sent as-is, even if compression is asked for
+        let mut ping = StacksMessage::new(
+            PEER_VERSION_TESTNET,
+            0x9abcdef0,
+            12345,
+            &BurnchainHeaderHash([0x11; 32]),
+            12339,
+            &BurnchainHeaderHash([0x22; 32]),
+            StacksMessageType::Ping(PingData { nonce: 0x01020304 }),
+        );
+        ping.request_compression();
+        ping.sign(445, &privkey).unwrap();
+        assert!(!ping.preamble.is_compressed());
+        ping.verify_secp256k1(&pubkey_buf).unwrap();
+    }
+
+    #[test]
+    fn codec_compressed_body_limits() {
+        let body = vec![0u8; 4096];
+        let compressed = compress_message_body(&body).unwrap();
+        assert!(compressed.len() < body.len());
+        assert_eq!(decompress_message_body(&compressed).unwrap(), body);
+
+        let with_len = |len: u32| {
+            let mut bytes = compressed.clone();
+            bytes[0..4].copy_from_slice(&len.to_be_bytes());
+            bytes
+        };
+
+        // refused before decompressing if it claims to be too big
+        assert!(check_deserialize(decompress_message_body(&with_len(
+            MAX_MESSAGE_LEN - PREAMBLE_ENCODED_SIZE + 1
+        ))));
+
+        // must decompress to exactly the length it claims
+        assert!(check_deserialize(decompress_message_body(&with_len(4095))));
+        assert!(check_deserialize(decompress_message_body(&with_len(4097))));
+
+        // must actually be compressed
+        let mut garbage = with_len(4096);
+        garbage.truncate(4);
+        garbage.append(&mut vec![0xff; 64]);
+        assert!(decompress_message_body(&garbage).is_err());
+    }
+
+    #[test]
+    fn codec_old_preamble_flags_not_compressed() {
+        let privkey = Secp256k1PrivateKey::new();
+        let pubkey_buf =
+            StacksPublicKeyBuffer::from_public_key(&Secp256k1PublicKey::from_private(&privkey));
+
+        // preambles from before compression could set any of the low reserved bits of
+        // additional_data, including bit 0.  Their bodies are read as-is, not decompressed.
+        let mut msg = StacksMessage::new(
+            PEER_VERSION_TESTNET,
+            0x9abcdef0,
+            12345,
+            &BurnchainHeaderHash([0x11; 32]),
+            12339,
+            &BurnchainHeaderHash([0x22; 32]),
+            StacksMessageType::Ping(PingData { nonce: 0x01020304 }),
+        );
+        msg.preamble.additional_data = 0x00000001;
+        msg.sign(446, &privkey).unwrap();
+        assert!(!msg.preamble.is_compressed());
+        assert_eq!(msg.preamble.additional_data, 0x00000001);
+        msg.verify_secp256k1(&pubkey_buf).unwrap();
+
+        let mut body = vec![];
+        msg.relayers.consensus_serialize(&mut body).unwrap();
+        msg.payload.consensus_serialize(&mut body).unwrap();
+
+        let bytes = msg.serialize_to_vec();
+        assert_eq!(&bytes[PREAMBLE_ENCODED_SIZE as usize..], &body[..]);
+        let decoded = StacksMessage::consensus_deserialize(&mut &bytes[..]).unwrap();
+        assert_eq!(decoded, msg);
+
+        let mut protocol = StacksP2P::new();
+        let (preamble, preamble_len) = protocol.read_preamble(&bytes).unwrap();
+        assert!(!preamble.is_compressed());
+        let (decoded, consumed) = protocol
+            .read_payload(&preamble, &bytes[preamble_len..])
+            .unwrap();
+        assert_eq!(decoded, msg);
+        assert_eq!(consumed, body.len());
+    }
+
     #[test]
     fn codec_stacks_public_key_roundtrip() {
         for i in 0..100 {
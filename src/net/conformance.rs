@@ -0,0 +1,789 @@
+/*
+ copyright: (c) 2013-2020 by Blockstack PBC, a public benefit corporation.
+
+ This file is part of Blockstack.
+
+ Blockstack is free software. You may redistribute or modify
+ it under the terms of the GNU General Public License as published by
+ the Free Software Foundation, either version 3 of the License or
+ (at your option) any later version.
+
+ Blockstack is distributed in the hope that it will be useful,
+ but WITHOUT ANY WARRANTY, including without the implied warranty of
+ MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ GNU General Public License for more details.
+
+ You should have received a copy of the GNU General Public License
+ along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
+*/
+
+//! Canonical byte-level test vectors for the p2p wire format.
+//!
+//! `make_test_vectors()` deterministically builds one signed `StacksMessage` for every
+//! `StacksMessageType` variant, and one signed `StacksTransaction` for every payload and
+//! authorization variant, at each supported peer version.  `write_test_vectors()` stores them in
+//! a directory as one JSON file per vector, for alternative client implementations to check
+//! their codecs against.  `verify_test_vectors()` checks such a directory against this
+//! implementation.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+
+use serde_json;
+
+use address::AddressHashMode;
+use chainstate::burn::ConsensusHash;
+use chainstate::stacks::{
+    CoinbasePayload, StacksBlock, StacksMicroblock, StacksPrivateKey, StacksPublicKey,
+    StacksTransaction, StacksTransactionSigner, TokenTransferMemo, TransactionAuth,
+    TransactionPayload, TransactionSpendingCondition, TransactionVersion,
+    C32_ADDRESS_VERSION_MAINNET_SINGLESIG, C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+};
+use codec::StacksMessageCodec;
+use core::{
+    CHAIN_ID_MAINNET, CHAIN_ID_TESTNET, NETWORK_ID_MAINNET, NETWORK_ID_TESTNET,
+    PEER_VERSION_MAINNET, PEER_VERSION_TESTNET,
+};
+use net::Error as net_error;
+use net::*;
+use util::hash::{hex_bytes, to_hex, Hash160, MerkleTree, Sha512Trunc256Sum};
+use util::vrf::VRFProof;
+use vm::types::{PrincipalData, StandardPrincipalData};
+use vm::Value;
+
+use crate::types::chainstate::{
+    BlockHeaderHash, BurnchainHeaderHash, StacksAddress, StacksBlockHeader, StacksBlockId,
+    StacksMicroblockHeader, StacksWorkScore,
+};
+use crate::types::proof::TrieHash;
+use crate::types::StacksPublicKeyBuffer;
+
+/// Peer versions for which test vectors are generated
+pub const TEST_VECTOR_PEER_VERSIONS: &[u32] = &[PEER_VERSION_MAINNET, PEER_VERSION_TESTNET];
+
+/// Private keys that sign every test vector.  The first one signs p2p messages, microblocks, and
+/// transaction origins; the others are used as multisig co-signers and sponsors.
+const TEST_VECTOR_PRIVATE_KEYS: &[&str] = &[
+    "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+    "2a584d899fed1d24e26b524f202763c8ab30260167429f157f1c119f550fa6af01",
+    "d5200dee706ee53ae98a03fba6cf4fdcc5084c30cfa9e1b3462dcdeaa3e0f1d201",
+];
+
+/// A VRF proof that decodes, for use in sample block headers
+const TEST_VECTOR_VRF_PROOF: &str = "9275df67a68c8745c0ff97b48201ee6db447f7c93b23ae24cdc2400f52fdb08a1a6ac7ec71bf9c9c76e96ee4675ebff60625af28718501047bfd87b810c2d2139b73c23bd69de66360953a642c2a330a";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum TestVectorKind {
+    /// A complete `StacksMessage`: preamble, relayers, and payload
+    #[serde(rename = "p2p")]
+    Message,
+    /// A standalone `StacksTransaction`
+    #[serde(rename = "transaction")]
+    Transaction,
+}
+
+/// A single test vector, as stored on disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestVector {
+    /// Unique name; also the file name (without `.json`)
+    pub name: String,
+    pub kind: TestVectorKind,
+    pub peer_version: u32,
+    pub network_id: u32,
+    /// Compressed public key that signed the message (or the transaction origin), as hex
+    pub signer_public_key: String,
+    /// The canonical consensus serialization, as hex
+    pub bytes: String,
+}
+
+/// Outcome of checking a directory of test vectors
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestVectorReport {
+    /// Number of vectors checked
+    pub checked: usize,
+    /// (vector name, reason) for every vector that failed
+    pub failures: Vec<(String, String)>,
+}
+
+/// Everything about a network that shows up in a vector's bytes
+struct TestVectorNetwork {
+    peer_version: u32,
+    network_id: u32,
+    chain_id: u32,
+    tx_version: TransactionVersion,
+    address_version: u8,
+}
+
+impl TestVectorNetwork {
+    fn from_peer_version(peer_version: u32) -> TestVectorNetwork {
+        if peer_version == PEER_VERSION_MAINNET {
+            TestVectorNetwork {
+                peer_version,
+                network_id: NETWORK_ID_MAINNET,
+                chain_id: CHAIN_ID_MAINNET,
+                tx_version: TransactionVersion::Mainnet,
+                address_version: C32_ADDRESS_VERSION_MAINNET_SINGLESIG,
+            }
+        } else {
+            TestVectorNetwork {
+                peer_version,
+                network_id: NETWORK_ID_TESTNET,
+                chain_id: CHAIN_ID_TESTNET,
+                tx_version: TransactionVersion::Testnet,
+                address_version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            }
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        if self.peer_version == PEER_VERSION_MAINNET {
+            "mainnet"
+        } else {
+            "testnet"
+        }
+    }
+}
+
+fn test_vector_private_key(i: usize) -> StacksPrivateKey {
+    StacksPrivateKey::from_hex(TEST_VECTOR_PRIVATE_KEYS[i])
+        .expect("FATAL: invalid test vector private key")
+}
+
+fn test_vector_public_key(i: usize) -> StacksPublicKey {
+    StacksPublicKey::from_private(&test_vector_private_key(i))
+}
+
+fn signer_public_key_hex() -> String {
+    to_hex(&test_vector_public_key(0).to_bytes_compressed())
+}
+
+/// Sign a transaction with the test vector keys.  The origin's first `num_origin_signers` keys
+/// sign, and the public keys of the rest of its `num_origin_keys` keys are appended in order.
+fn sign_test_transaction(
+    tx: StacksTransaction,
+    num_origin_keys: usize,
+    num_origin_signers: usize,
+    sponsor: Option<TransactionSpendingCondition>,
+) -> StacksTransaction {
+    let mut signer = StacksTransactionSigner::new(&tx);
+    for i in 0..num_origin_keys {
+        if i < num_origin_signers {
+            signer
+                .sign_origin(&test_vector_private_key(i))
+                .expect("FATAL: failed to sign test vector transaction origin");
+        } else {
+            signer
+                .append_origin(&test_vector_public_key(i))
+                .expect("FATAL: failed to append test vector transaction origin key");
+        }
+    }
+    let tx = signer.get_tx_incomplete();
+
+    match sponsor {
+        Some(sponsor_condition) => {
+            let mut signer = StacksTransactionSigner::new_sponsor(&tx, sponsor_condition)
+                .expect("FATAL: failed to make test vector sponsor signer");
+            signer
+                .sign_sponsor(&test_vector_private_key(2))
+                .expect("FATAL: failed to sign test vector transaction sponsor");
+            signer
+                .get_tx()
+                .expect("FATAL: failed to sponsor-sign test vector transaction")
+        }
+        None => signer
+            .get_tx()
+            .expect("FATAL: failed to sign test vector transaction"),
+    }
+}
+
+/// Sample payloads, one per `TransactionPayload` variant
+fn make_test_payloads(network: &TestVectorNetwork) -> Vec<(&'static str, TransactionPayload)> {
+    let contract_addr = StacksAddress::from_public_keys(
+        network.address_version,
+        &AddressHashMode::SerializeP2PKH,
+        1,
+        &vec![test_vector_public_key(1)],
+    )
+    .expect("FATAL: failed to make test vector address");
+    let recipient = PrincipalData::Standard(StandardPrincipalData::from(contract_addr.clone()));
+
+    let mut mblock_header_1 = StacksMicroblockHeader::first_unsigned(
+        &BlockHeaderHash([0x01; 32]),
+        &Sha512Trunc256Sum([0x02; 32]),
+    );
+    let mut mblock_header_2 = StacksMicroblockHeader::first_unsigned(
+        &BlockHeaderHash([0x01; 32]),
+        &Sha512Trunc256Sum([0x03; 32]),
+    );
+    mblock_header_1
+        .sign(&test_vector_private_key(0))
+        .expect("FATAL: failed to sign test vector microblock header");
+    mblock_header_2
+        .sign(&test_vector_private_key(0))
+        .expect("FATAL: failed to sign test vector microblock header");
+
+    vec![
+        (
+            "token-transfer",
+            TransactionPayload::TokenTransfer(recipient, 12345, TokenTransferMemo([0x01; 34])),
+        ),
+        (
+            "smart-contract",
+            TransactionPayload::new_smart_contract(
+                "hello-world",
+                "(define-data-var bar int 0)\n(define-public (get-bar) (ok (var-get bar)))",
+            )
+            .expect("FATAL: failed to make test vector smart contract"),
+        ),
+        (
+            "contract-call",
+            TransactionPayload::new_contract_call(
+                contract_addr,
+                "hello-world",
+                "set-bar",
+                vec![Value::Int(-1), Value::UInt(1)],
+            )
+            .expect("FATAL: failed to make test vector contract call"),
+        ),
+        (
+            "poison-microblock",
+            TransactionPayload::PoisonMicroblock(mblock_header_1, mblock_header_2),
+        ),
+        (
+            "coinbase",
+            TransactionPayload::Coinbase(CoinbasePayload([0x04; 32])),
+        ),
+    ]
+}
+
+/// Sample signed transactions, one per payload and authorization variant
+fn make_test_transactions(network: &TestVectorNetwork) -> Vec<(String, StacksTransaction)> {
+    let singlesig = || {
+        TransactionSpendingCondition::new_singlesig_p2pkh(test_vector_public_key(0))
+            .expect("FATAL: failed to make test vector spending condition")
+    };
+    let multisig = || {
+        TransactionSpendingCondition::new_multisig_p2sh(
+            2,
+            vec![
+                test_vector_public_key(0),
+                test_vector_public_key(1),
+                test_vector_public_key(2),
+            ],
+        )
+        .expect("FATAL: failed to make test vector spending condition")
+    };
+    let sponsor = || {
+        TransactionSpendingCondition::new_singlesig_p2wpkh(test_vector_public_key(2))
+            .expect("FATAL: failed to make test vector spending condition")
+    };
+
+    let mut txs = vec![];
+    for (payload_name, payload) in make_test_payloads(network).into_iter() {
+        let is_coinbase = match payload {
+            TransactionPayload::Coinbase(_) => true,
+            _ => false,
+        };
+
+        let mut auths = vec![
+            ("p2pkh", TransactionAuth::Standard(singlesig()), 1, 1, None),
+            ("p2sh", TransactionAuth::Standard(multisig()), 3, 2, None),
+        ];
+        if !is_coinbase {
+            // coinbases can't be sponsored
+            auths.push((
+                "sponsored",
+                TransactionAuth::Sponsored(
+                    singlesig(),
+                    TransactionSpendingCondition::new_initial_sighash(),
+                ),
+                1,
+                1,
+                Some(sponsor()),
+            ));
+        }
+
+        for (auth_name, auth, num_keys, num_signers, sponsor_condition) in auths.into_iter() {
+            let mut tx = StacksTransaction::new(network.tx_version.clone(), auth, payload.clone());
+            tx.chain_id = network.chain_id;
+            tx.set_tx_fee(1000);
+            tx.set_origin_nonce(7);
+            if sponsor_condition.is_some() {
+                tx.set_sponsor_nonce(3)
+                    .expect("FATAL: failed to set test vector sponsor nonce");
+            }
+
+            let tx = sign_test_transaction(tx, num_keys, num_signers, sponsor_condition);
+            txs.push((format!("{}-{}", payload_name, auth_name), tx));
+        }
+    }
+    txs
+}
+
+/// A sample anchored block with a coinbase and a token transfer
+fn make_test_block(network: &TestVectorNetwork) -> StacksBlock {
+    let mut all_txs: HashMap<String, StacksTransaction> =
+        make_test_transactions(network).into_iter().collect();
+
+    // the coinbase must come first
+    let txs = vec![
+        all_txs
+            .remove("coinbase-p2pkh")
+            .expect("FATAL: no test vector coinbase"),
+        all_txs
+            .remove("token-transfer-p2pkh")
+            .expect("FATAL: no test vector token transfer"),
+    ];
+
+    let txids = txs.iter().map(|tx| tx.txid().as_bytes().to_vec()).collect();
+    let merkle_tree = MerkleTree::<Sha512Trunc256Sum>::new(&txids);
+    let proof = VRFProof::from_bytes(
+        &hex_bytes(TEST_VECTOR_VRF_PROOF).expect("FATAL: invalid test vector VRF proof"),
+    )
+    .expect("FATAL: invalid test vector VRF proof");
+
+    let header = StacksBlockHeader {
+        version: 0x01,
+        total_work: StacksWorkScore {
+            burn: 234,
+            work: 567,
+        },
+        proof,
+        parent_block: BlockHeaderHash([0x05; 32]),
+        parent_microblock: BlockHeaderHash([0x06; 32]),
+        parent_microblock_sequence: 4,
+        tx_merkle_root: merkle_tree.root(),
+        state_index_root: TrieHash([0x08; 32]),
+        microblock_pubkey_hash: Hash160([0x09; 20]),
+    };
+
+    StacksBlock { header, txs }
+}
+
+/// A sample stream of two signed microblocks
+fn make_test_microblocks(network: &TestVectorNetwork) -> Vec<StacksMicroblock> {
+    let mut all_txs: HashMap<String, StacksTransaction> =
+        make_test_transactions(network).into_iter().collect();
+    let first_tx = all_txs
+        .remove("token-transfer-p2pkh")
+        .expect("FATAL: no test vector token transfer");
+    let second_tx = all_txs
+        .remove("contract-call-sponsored")
+        .expect("FATAL: no test vector contract call");
+
+    let mut first = StacksMicroblock::first_unsigned(&BlockHeaderHash([0x0a; 32]), vec![first_tx]);
+    first
+        .sign(&test_vector_private_key(0))
+        .expect("FATAL: failed to sign test vector microblock");
+
+    let mut second = StacksMicroblock::from_parent_unsigned(&first.header, vec![second_tx])
+        .expect("FATAL: failed to make test vector microblock");
+    second
+        .sign(&test_vector_private_key(0))
+        .expect("FATAL: failed to sign test vector microblock");
+
+    vec![first, second]
+}
+
+/// Sample p2p payloads, one per `StacksMessageType` variant
+fn make_test_message_payloads(network: &TestVectorNetwork) -> Vec<StacksMessageType> {
+    let handshake = HandshakeData {
+        addrbytes: PeerAddress::from_ipv4(127, 0, 0, 1),
+        port: 20444,
+        services: (ServiceFlags::RELAY as u16) | (ServiceFlags::RPC as u16),
+        node_public_key: StacksPublicKeyBuffer::from_public_key(&test_vector_public_key(0)),
+        expire_block_height: 12345,
+        data_url: UrlString::try_from("http://127.0.0.1:20443")
+            .expect("FATAL: invalid test vector data URL"),
+    };
+    let neighbor = NeighborAddress {
+        addrbytes: PeerAddress::from_ipv4(10, 0, 0, 1),
+        port: 20444,
+        public_key_hash: Hash160::from_node_public_key(&test_vector_public_key(1)),
+    };
+    let available = vec![
+        (ConsensusHash([0x11; 20]), BurnchainHeaderHash([0x12; 32])),
+        (ConsensusHash([0x13; 20]), BurnchainHeaderHash([0x14; 32])),
+    ];
+    let transaction = make_test_transactions(network)
+        .into_iter()
+        .find(|(name, _)| name == "token-transfer-p2pkh")
+        .map(|(_, tx)| tx)
+        .expect("FATAL: no test vector token transfer");
+
+    vec![
+        StacksMessageType::Handshake(handshake.clone()),
+        StacksMessageType::HandshakeAccept(HandshakeAcceptData {
+            handshake,
+            heartbeat_interval: 600,
+        }),
+        StacksMessageType::HandshakeReject,
+        StacksMessageType::GetNeighbors,
+        StacksMessageType::Neighbors(NeighborsData {
+            neighbors: vec![neighbor],
+        }),
+        StacksMessageType::GetBlocksInv(GetBlocksInv {
+            consensus_hash: ConsensusHash([0x15; 20]),
+            num_blocks: 10,
+        }),
+        StacksMessageType::BlocksInv(BlocksInvData {
+            bitlen: 10,
+            block_bitvec: vec![0xff, 0x01],
+            microblocks_bitvec: vec![0x55, 0x02],
+        }),
+        // PoX bit vectors stay within the (smaller) test-build GETPOXINV_MAX_BITLEN, so these
+        // vectors decode in every build
+        StacksMessageType::GetPoxInv(GetPoxInv {
+            consensus_hash: ConsensusHash([0x16; 20]),
+            num_cycles: 7,
+        }),
+        StacksMessageType::PoxInv(PoxInvData {
+            bitlen: 7,
+            pox_bitvec: vec![0x5d],
+        }),
+        StacksMessageType::BlocksAvailable(BlocksAvailableData {
+            available: available.clone(),
+        }),
+        StacksMessageType::MicroblocksAvailable(BlocksAvailableData { available }),
+        StacksMessageType::Blocks(BlocksData {
+            blocks: vec![(ConsensusHash([0x17; 20]), make_test_block(network))],
+        }),
+        StacksMessageType::Microblocks(MicroblocksData {
+            index_anchor_block: StacksBlockId([0x18; 32]),
+            microblocks: make_test_microblocks(network),
+        }),
+        StacksMessageType::Transaction(transaction),
+        StacksMessageType::Nack(NackData::new(NackErrorCodes::HandshakeRequired)),
+        StacksMessageType::Ping(PingData { nonce: 0x01020304 }),
+        StacksMessageType::Pong(PongData { nonce: 0x01020304 }),
+        StacksMessageType::NatPunchRequest(0x05060708),
+        StacksMessageType::NatPunchReply(NatPunchData {
+            addrbytes: PeerAddress::from_ipv4(192, 168, 0, 1),
+            port: 20444,
+            nonce: 0x05060708,
+        }),
+        StacksMessageType::MessageLimit(MessageLimitData {
+            max_payload_len: 0x00100000,
+        }),
+        StacksMessageType::DataUrlHealth(DataUrlHealthData {
+            unreachable: vec![UrlString::try_from("http://10.0.0.2:20443")
+                .expect("FATAL: invalid test vector data URL")],
+        }),
+        StacksMessageType::CompactBlock(CompactBlockData::from_block(
+            &ConsensusHash([0x19; 20]),
+            &make_test_block(network),
+        )),
+        StacksMessageType::AttachmentsAvailable(AttachmentsAvailableData {
+            available: vec![(StacksBlockId([0x1a; 32]), Hash160([0x1b; 20]))],
+        }),
+        StacksMessageType::GetBlocksInvPage(GetBlocksInvPage {
+            consensus_hash: ConsensusHash([0x1c; 20]),
+            num_blocks: 4200,
+            cursor: 8,
+        }),
+        StacksMessageType::BlocksInvPage(BlocksInvPageData {
+            cursor: 8,
+            next_cursor: 16,
+            inv: BlocksInvData {
+                bitlen: 8,
+                block_bitvec: vec![0x5a],
+                microblocks_bitvec: vec![0x18],
+            },
+        }),
+        StacksMessageType::MicroblocksSubscribe(MicroblocksSubscribeData { subscribe: true }),
+    ]
+}
+
+/// Build a signed p2p message around the given payload
+fn make_test_message(network: &TestVectorNetwork, payload: StacksMessageType) -> StacksMessage {
+    let mut msg = StacksMessage::new(
+        network.peer_version,
+        network.network_id,
+        680000,
+        &BurnchainHeaderHash([0x21; 32]),
+        679993,
+        &BurnchainHeaderHash([0x22; 32]),
+        payload,
+    );
+    msg.sign(123, &test_vector_private_key(0))
+        .expect("FATAL: failed to sign test vector message");
+    msg
+}
+
+fn make_vector<T: StacksMessageCodec>(
+    name: String,
+    kind: TestVectorKind,
+    network: &TestVectorNetwork,
+    item: &T,
+) -> TestVector {
+    let mut bytes = vec![];
+    item.consensus_serialize(&mut bytes)
+        .expect("FATAL: failed to serialize test vector");
+    TestVector {
+        name,
+        kind,
+        peer_version: network.peer_version,
+        network_id: network.network_id,
+        signer_public_key: signer_public_key_hex(),
+        bytes: to_hex(&bytes),
+    }
+}
+
+/// Generate the canonical test vectors for every p2p message and transaction variant, at every
+/// supported peer version.  The output is deterministic.
+pub fn make_test_vectors() -> Vec<TestVector> {
+    let mut vectors = vec![];
+    for peer_version in TEST_VECTOR_PEER_VERSIONS.iter() {
+        let network = TestVectorNetwork::from_peer_version(*peer_version);
+        for payload in make_test_message_payloads(&network).into_iter() {
+            let name = format!(
+                "p2p-{}-{}",
+                payload.get_message_name().to_lowercase(),
+                network.name()
+            );
+            let msg = make_test_message(&network, payload);
+            vectors.push(make_vector(name, TestVectorKind::Message, &network, &msg));
+        }
+        for (tx_name, tx) in make_test_transactions(&network).into_iter() {
+            let name = format!("tx-{}-{}", tx_name, network.name());
+            vectors.push(make_vector(
+                name,
+                TestVectorKind::Transaction,
+                &network,
+                &tx,
+            ));
+        }
+    }
+    vectors
+}
+
+/// Write the canonical test vectors to `dir` (which will be created if need be), one JSON file
+/// per vector.  Returns the number of vectors written.
+pub fn write_test_vectors(dir: &Path) -> Result<usize, net_error> {
+    fs::create_dir_all(dir).map_err(net_error::WriteError)?;
+    let vectors = make_test_vectors();
+    for vector in vectors.iter() {
+        let json = serde_json::to_string_pretty(vector)
+            .map_err(|e| net_error::SerializeError(e.to_string()))?;
+        fs::write(dir.join(format!("{}.json", &vector.name)), json)
+            .map_err(net_error::WriteError)?;
+    }
+    Ok(vectors.len())
+}
+
+/// Decode `bytes`, requiring that all of them are consumed, and re-encode the result.
+/// Returns the decoded value if the re-encoding matches `bytes`.
+fn roundtrip<T: StacksMessageCodec>(bytes: &[u8]) -> Result<T, String> {
+    let mut cursor = Cursor::new(bytes);
+    let item =
+        T::consensus_deserialize(&mut cursor).map_err(|e| format!("does not decode: {}", e))?;
+    if (cursor.position() as usize) != bytes.len() {
+        return Err(format!(
+            "has {} trailing bytes",
+            bytes.len() - (cursor.position() as usize)
+        ));
+    }
+
+    let mut reencoded = vec![];
+    item.consensus_serialize(&mut reencoded)
+        .map_err(|e| format!("does not re-encode: {}", e))?;
+    if reencoded != bytes {
+        return Err(format!("re-encodes differently: {}", to_hex(&reencoded)));
+    }
+    Ok(item)
+}
+
+/// Check one test vector: it must decode and re-encode to the same bytes, its signatures must
+/// verify, and if it has the name of a canonical vector, it must match that vector exactly.
+fn verify_test_vector(
+    vector: &TestVector,
+    canonical: &HashMap<String, TestVector>,
+) -> Result<(), String> {
+    let bytes = hex_bytes(&vector.bytes).map_err(|_| "bytes are not hex".to_string())?;
+    match vector.kind {
+        TestVectorKind::Message => {
+            let msg: StacksMessage = roundtrip(&bytes)?;
+            if msg.preamble.peer_version != vector.peer_version {
+                return Err(format!(
+                    "has peer version {:08x}, expected {:08x}",
+                    msg.preamble.peer_version, vector.peer_version
+                ));
+            }
+            if msg.preamble.network_id != vector.network_id {
+                return Err(format!(
+                    "has network ID {:08x}, expected {:08x}",
+                    msg.preamble.network_id, vector.network_id
+                ));
+            }
+            let pubkey_bytes = hex_bytes(&vector.signer_public_key)
+                .map_err(|_| "signer public key is not hex".to_string())?;
+            let pubkey = StacksPublicKey::from_slice(&pubkey_bytes)
+                .map_err(|e| format!("signer public key is invalid: {}", e))?;
+            msg.verify_secp256k1(&StacksPublicKeyBuffer::from_public_key(&pubkey))
+                .map_err(|e| format!("signature does not verify: {}", e))?;
+        }
+        TestVectorKind::Transaction => {
+            let tx: StacksTransaction = roundtrip(&bytes)?;
+            tx.verify()
+                .map_err(|e| format!("signature does not verify: {}", e))?;
+        }
+    }
+
+    if let Some(expected) = canonical.get(&vector.name) {
+        if expected != vector {
+            return Err("differs from the canonical vector of the same name".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Check every `*.json` test vector in `dir` against this implementation.
+/// Vectors with the name of a canonical vector must match it exactly; any other vector only has
+/// to round-trip and carry valid signatures.
+pub fn verify_test_vectors(dir: &Path) -> Result<TestVectorReport, net_error> {
+    let canonical: HashMap<String, TestVector> = make_test_vectors()
+        .into_iter()
+        .map(|vector| (vector.name.clone(), vector))
+        .collect();
+
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir).map_err(net_error::ReadError)? {
+        let path = entry.map_err(net_error::ReadError)?.path();
+        if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut report = TestVectorReport {
+        checked: 0,
+        failures: vec![],
+    };
+    for path in paths.iter() {
+        report.checked += 1;
+        let file_name = path.display().to_string();
+        let json = fs::read_to_string(path).map_err(net_error::ReadError)?;
+        let vector: TestVector = match serde_json::from_str(&json) {
+            Ok(vector) => vector,
+            Err(e) => {
+                report
+                    .failures
+                    .push((file_name, format!("is not a test vector: {}", e)));
+                continue;
+            }
+        };
+        if let Err(reason) = verify_test_vector(&vector, &canonical) {
+            report.failures.push((vector.name.clone(), reason));
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_vectors_cover_all_variants() {
+        let vectors = make_test_vectors();
+        let canonical = HashMap::new();
+
+        let mut message_ids = HashSet::new();
+        let mut payload_ids = HashSet::new();
+        for vector in vectors.iter() {
+            verify_test_vector(vector, &canonical).unwrap();
+            let bytes = hex_bytes(&vector.bytes).unwrap();
+            match vector.kind {
+                TestVectorKind::Message => {
+                    let msg = StacksMessage::consensus_deserialize(&mut &bytes[..]).unwrap();
+                    message_ids.insert((vector.peer_version, msg.payload.get_message_id() as u8));
+                }
+                TestVectorKind::Transaction => {
+                    let tx = StacksTransaction::consensus_deserialize(&mut &bytes[..]).unwrap();
+                    payload_ids.insert((vector.peer_version, tx.payload.name()));
+                }
+            }
+        }
+
+        // every message ID except Reserved, and every payload type, at every peer version
+        assert_eq!(
+            message_ids.len(),
+            TEST_VECTOR_PEER_VERSIONS.len() * (StacksMessageID::MicroblocksSubscribe as usize + 1)
+        );
+        assert_eq!(payload_ids.len(), TEST_VECTOR_PEER_VERSIONS.len() * 5);
+
+        // generation is deterministic
+        assert_eq!(vectors, make_test_vectors());
+    }
+
+    #[test]
+    fn test_write_and_verify_vectors() {
+        let dir = "/tmp/test-write-and-verify-vectors";
+        if fs::metadata(dir).is_ok() {
+            fs::remove_dir_all(dir).unwrap();
+        }
+        let dir = Path::new(dir);
+
+        let num_written = write_test_vectors(dir).unwrap();
+        let report = verify_test_vectors(dir).unwrap();
+        assert_eq!(report.checked, num_written);
+        assert_eq!(report.failures, vec![]);
+
+        // corrupt a vector's signature, and change another's canonical bytes
+        let mut vector: TestVector =
+            serde_json::from_str(&fs::read_to_string(dir.join("p2p-ping-mainnet.json")).unwrap())
+                .unwrap();
+        let mut bytes = hex_bytes(&vector.bytes).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0x01;
+        vector.bytes = to_hex(&bytes);
+        fs::write(
+            dir.join("p2p-ping-mainnet.json"),
+            serde_json::to_string(&vector).unwrap(),
+        )
+        .unwrap();
+
+        let mut vector: TestVector = serde_json::from_str(
+            &fs::read_to_string(dir.join("tx-coinbase-p2pkh-testnet.json")).unwrap(),
+        )
+        .unwrap();
+        vector.peer_version = PEER_VERSION_MAINNET;
+        fs::write(
+            dir.join("tx-coinbase-p2pkh-testnet.json"),
+            serde_json::to_string(&vector).unwrap(),
+        )
+        .unwrap();
+
+        fs::write(dir.join("garbage.json"), "not a vector").unwrap();
+
+        let report = verify_test_vectors(dir).unwrap();
+        assert_eq!(report.checked, num_written + 1);
+        let failed: HashSet<String> = report
+            .failures
+            .iter()
+            .map(|(name, _)| {
+                Path::new(name)
+                    .file_stem()
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+        assert_eq!(
+            failed,
+            vec![
+                "garbage".to_string(),
+                "p2p-ping-mainnet".to_string(),
+                "tx-coinbase-p2pkh-testnet".to_string()
+            ]
+            .into_iter()
+            .collect()
+        );
+    }
+}
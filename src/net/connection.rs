@@ -36,6 +36,7 @@ use mio::net as mio_net;
 
 use crate::codec::StacksMessageCodec;
 use crate::codec::MAX_MESSAGE_LEN;
+use crate::codec::MAX_PAYLOAD_LEN;
 use net::codec::*;
 use net::Error as net_error;
 use net::HttpRequestPreamble;
@@ -45,19 +46,24 @@ use net::PeerAddress;
 use net::Preamble;
 use net::ProtocolFamily;
 use net::RelayData;
+use net::ServiceFlags;
 use net::StacksHttp;
 use net::StacksP2P;
 
+use net::tls::TlsSession;
+
 use net::download::BLOCK_DOWNLOAD_INTERVAL;
 use net::inv::{FULL_INV_SYNC_INTERVAL, INV_REWARD_CYCLES, INV_SYNC_INTERVAL};
 use net::neighbors::{
-    NEIGHBOR_REQUEST_TIMEOUT, NEIGHBOR_WALK_INTERVAL, NUM_INITIAL_WALKS, WALK_MAX_DURATION,
+    MAX_CONCURRENT_HANDSHAKES, MIN_CONCURRENT_HANDSHAKES, NEIGHBOR_REQUEST_TIMEOUT,
+    NEIGHBOR_WALK_INTERVAL, NUM_INITIAL_WALKS, SATURATED_NETWORK_PASS_MS, WALK_MAX_DURATION,
     WALK_MIN_DURATION, WALK_RESET_INTERVAL, WALK_RESET_PROB, WALK_RETRY_COUNT, WALK_STATE_TIMEOUT,
 };
 
 use vm::{costs::ExecutionCost, types::BOUND_VALUE_SERIALIZATION_HEX};
 
 use chainstate::burn::ConsensusHash;
+use chainstate::stacks::MAX_TRANSACTION_LEN;
 
 use util::get_epoch_time_secs;
 use util::hash::to_hex;
@@ -312,10 +318,89 @@ struct ConnectionOutbox<P: ProtocolFamily> {
     socket_out_buf: Vec<u8>,
     socket_out_ptr: usize,
 
+    // if set, stop sending once this many more messages have gone out
+    send_limit: Option<usize>,
+
     // in-flight messages
     inflight: VecDeque<ReceiverNotify<P>>,
 }
 
+/// Which kinds of p2p traffic the node takes part in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkMode {
+    /// Accept inbound connections, walk the network, relay pushed data, and serve chain data over
+    /// HTTP (the default).
+    Full,
+    /// Only accept inbound connections.  Never connect out to peers (so no neighbor walks), but
+    /// relay pushed data to and serve chain data to the peers that connect to us.
+    Listener,
+    /// Only connect out to peers.  Refuse inbound connections, and consume chain data without
+    /// relaying it or serving it to others.
+    Leecher,
+    /// Serve the read-only RPC endpoints from databases that another node keeps in sync.  Take
+    /// no part in p2p, and refuse RPC requests that would write chain data or the mempool.
+    Replica,
+}
+
+impl NetworkMode {
+    pub fn from_string(raw_mode: &str) -> Option<NetworkMode> {
+        match raw_mode {
+            "full" => Some(NetworkMode::Full),
+            "listener" => Some(NetworkMode::Listener),
+            "leecher" => Some(NetworkMode::Leecher),
+            "replica" => Some(NetworkMode::Replica),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NetworkMode::Full => "full",
+            NetworkMode::Listener => "listener",
+            NetworkMode::Leecher => "leecher",
+            NetworkMode::Replica => "replica",
+        }
+    }
+
+    /// Do we accept inbound p2p connections?
+    pub fn accepts_inbound(&self) -> bool {
+        *self != NetworkMode::Leecher && *self != NetworkMode::Replica
+    }
+
+    /// Do we open outbound p2p connections (including for neighbor walks)?
+    pub fn initiates_outbound(&self) -> bool {
+        *self != NetworkMode::Listener && *self != NetworkMode::Replica
+    }
+
+    /// Do we advertise and push blocks, microblocks, and transactions to our peers?
+    pub fn relays_pushes(&self) -> bool {
+        *self != NetworkMode::Leecher && *self != NetworkMode::Replica
+    }
+
+    /// Do we accept RPC requests that store blocks, microblocks, or transactions, or that fetch
+    /// and download chain data?
+    pub fn accepts_writes(&self) -> bool {
+        *self != NetworkMode::Replica
+    }
+
+    /// Do we serve blocks, microblocks, and attachments over HTTP?
+    pub fn serves_data(&self) -> bool {
+        *self != NetworkMode::Leecher
+    }
+
+    /// Clear the service bits for whatever this mode doesn't do.
+    pub fn filter_services(&self, services: u16) -> u16 {
+        let mut services = services;
+        if !self.relays_pushes() {
+            services &= !(ServiceFlags::RELAY as u16) & !(ServiceFlags::MICROBLOCK_PUSH as u16);
+        }
+        if !self.serves_data() {
+            services &= !(ServiceFlags::RPC as u16) & !(ServiceFlags::ATLAS as u16);
+        }
+        services
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ConnectionOptions {
     pub inbox_maxlen: usize,
@@ -336,7 +421,12 @@ pub struct ConnectionOptions {
     pub soft_max_neighbors_per_org: u64,
     pub soft_max_clients_per_host: u64,
     pub max_neighbors_of_neighbor: u64,
+    pub max_concurrent_handshakes: u64,
+    pub min_concurrent_handshakes: u64,
+    pub saturated_network_pass_ms: u64,
     pub max_http_clients: u64,
+    pub max_http_connections_per_host: u64,
+    pub http_client_idle_timeout: u64,
     pub neighbor_request_timeout: u64,
     pub num_initial_walks: u64,
     pub walk_retry_count: u64,
@@ -355,25 +445,71 @@ pub struct ConnectionOptions {
     pub dns_timeout: u128,
     pub max_inflight_blocks: u64,
     pub max_inflight_attachments: u64,
+    pub max_attachments_per_request: u64,
+    pub max_download_peers: u64,
+    pub max_download_bandwidth: u64,
     pub max_attachment_retry_count: u64,
     pub read_only_call_limit: ExecutionCost,
     pub maximum_call_argument_size: u32,
+    pub maximum_transaction_body_size: u32,
+    pub maximum_request_body_size: u32,
+    pub maximum_attachment_size: u32,
     pub max_block_push_bandwidth: u64,
     pub max_microblocks_push_bandwidth: u64,
     pub max_transaction_push_bandwidth: u64,
+    pub compact_block_prefill_window_ms: u64,
+    pub compact_block_max_prefill_txs: u64,
+    pub max_microblock_subscribers: u64,
+    pub microblock_push_max_pending: u64,
     pub max_sockets: usize,
     pub public_ip_address: Option<(PeerAddress, u16)>,
     pub public_ip_request_timeout: u64,
     pub public_ip_timeout: u64,
     pub public_ip_max_retries: u64,
+    pub public_ip_quorum: u64,
+    pub relay_min_fee_rate: u64,
+    pub relay_min_stx_transfer: u64,
+    pub relay_max_tx_len: u64,
+    pub relay_max_txs_per_origin: u64,
+    pub relay_max_tx_bytes_per_origin: u64,
     pub max_block_push: u64,
     pub max_microblock_push: u64,
     pub antientropy_retry: u64,
     pub antientropy_public: bool,
+    pub inv_agreement_check_interval: u64,
+    pub inv_agreement_alert_pct: u64,
+    pub inv_agreement_alert_duration: u64,
+    pub peer_version_check_interval: u64,
+    pub data_url_health_interval: u64,
+    pub peer_version_alert_pct: u64,
+    pub rehandshake_interval: u64,
+    pub staging_backlog_check_interval: u64,
+    pub max_staging_blocks_backlog: u64,
     pub max_buffered_blocks_available: u64,
     pub max_buffered_microblocks_available: u64,
     pub max_buffered_blocks: u64,
     pub max_buffered_microblocks: u64,
+    pub max_payload_len: u32,
+    pub download_state_save_interval: u64,
+    pub enable_admin_rpc: bool,
+    pub max_rpc_page_size: u32,
+    pub rpc_request_timeout: u64,
+    pub max_rpc_tip_fork_depth: u64,
+    pub rpc_cache_max_entries: u64,
+    pub rpc_idempotency_key_ttl: u64,
+    pub rpc_idempotency_max_keys: u64,
+    pub max_query_jobs: u64,
+    pub query_job_blocks_per_pass: u64,
+    pub max_query_job_results: u64,
+    pub query_job_ttl: u64,
+    pub network_mode: NetworkMode,
+    pub p2p_tls_cert_file: Option<String>,
+    pub p2p_tls_key_file: Option<String>,
+    pub p2p_tls_ca_file: Option<String>,
+    pub p2p_tls_require_client_cert: bool,
+    pub async_io_threads: usize,
+    pub async_io_max_inbound_bytes: usize,
+    pub async_io_max_outbound_bytes: usize,
 
     // fault injection
     pub disable_neighbor_walk: bool,
@@ -391,6 +527,18 @@ pub struct ConnectionOptions {
     pub force_disconnect_interval: Option<u64>,
 }
 
+impl ConnectionOptions {
+    /// Clear the service bits for whatever our network mode doesn't do, and for TLS if we have no
+    /// certificate to use.
+    pub fn filter_services(&self, services: u16) -> u16 {
+        let mut services = self.network_mode.filter_services(services);
+        if self.p2p_tls_cert_file.is_none() {
+            services &= !(ServiceFlags::TLS as u16);
+        }
+        services
+    }
+}
+
 impl std::default::Default for ConnectionOptions {
     fn default() -> ConnectionOptions {
         ConnectionOptions {
@@ -412,7 +560,12 @@ impl std::default::Default for ConnectionOptions {
             soft_max_neighbors_per_org: 10, // how many outbound connections we can have per AS-owning organization, before we start pruning them
             soft_max_clients_per_host: 10, // how many inbound connections we can have per IP address, before we start pruning them,
             max_neighbors_of_neighbor: 10,
+            max_concurrent_handshakes: MAX_CONCURRENT_HANDSHAKES, // most outbound handshakes a neighbor walk step has in flight at once
+            min_concurrent_handshakes: MIN_CONCURRENT_HANDSHAKES, // fewest we back off to when the event loop is saturated
+            saturated_network_pass_ms: SATURATED_NETWORK_PASS_MS, // back off when a network pass takes longer than this
             max_http_clients: 10,
+            max_http_connections_per_host: 4, // how many outbound HTTP connections we keep open to each data URL host
+            http_client_idle_timeout: 10, // how long an outbound HTTP connection can sit unused before it's closed (keep this below servers' idle timeouts)
             neighbor_request_timeout: NEIGHBOR_REQUEST_TIMEOUT, // how long to wait for a neighbor request
             num_initial_walks: NUM_INITIAL_WALKS,
             walk_retry_count: WALK_RETRY_COUNT,
@@ -428,9 +581,12 @@ impl std::default::Default for ConnectionOptions {
             inv_reward_cycles: INV_REWARD_CYCLES, // how many reward cycles of blocks to sync in a non-full inventory sync
             download_interval: BLOCK_DOWNLOAD_INTERVAL, // how often to scan for blocks to download
             pingback_timeout: 60,
-            dns_timeout: 15_000,            // DNS timeout, in millis
-            max_inflight_blocks: 6,         // number of parallel block downloads
-            max_inflight_attachments: 6,    // number of parallel attachments downloads
+            dns_timeout: 15_000,             // DNS timeout, in millis
+            max_inflight_blocks: 6,          // number of parallel block downloads
+            max_inflight_attachments: 6,     // number of parallel attachments downloads
+            max_attachments_per_request: 16, // how many attachments to ask a peer for in one request (1 to fetch them one by one)
+            max_download_peers: 0, // number of peers to download blocks from at once (0 for no limit)
+            max_download_bandwidth: 0, // bytes/sec of block and microblock downloads (0 for no limit)
             max_attachment_retry_count: 32, // how many attempt to get an attachment before giving up
             read_only_call_limit: ExecutionCost {
                 write_length: 0,
@@ -440,22 +596,65 @@ impl std::default::Default for ConnectionOptions {
                 runtime: 1_000_000_000,
             },
             maximum_call_argument_size: 20 * BOUND_VALUE_SERIALIZATION_HEX,
-            max_block_push_bandwidth: 0, // infinite upload bandwidth allowed
-            max_microblocks_push_bandwidth: 0, // infinite upload bandwidth allowed
-            max_transaction_push_bandwidth: 0, // infinite upload bandwidth allowed
-            max_sockets: 800,            // maximum number of client sockets we'll ever register
-            public_ip_address: None,     // resolve it at runtime by default
+            maximum_transaction_body_size: 4 * MAX_TRANSACTION_LEN, // largest POST /v2/transactions body (hex-encoded transaction and attachment); bigger bodies get a 413
+            maximum_request_body_size: MAX_PAYLOAD_LEN, // largest body of any other HTTP request; bigger bodies get a 413
+            maximum_attachment_size: 1_048_576, // largest attachment the Atlas downloader will accept; set from the Atlas config
+            max_block_push_bandwidth: 0,        // infinite upload bandwidth allowed
+            max_microblocks_push_bandwidth: 0,  // infinite upload bandwidth allowed
+            max_transaction_push_bandwidth: 0,  // infinite upload bandwidth allowed
+            compact_block_prefill_window_ms: 5_000, // transactions we relayed this recently get sent in full in the compact blocks we push
+            compact_block_max_prefill_txs: 32, // most transactions per compact block to send in full besides the coinbase (0 to disable)
+            max_microblock_subscribers: 32, // most peers we'll push our freshly-mined microblocks to
+            microblock_push_max_pending: 8, // skip pushing microblocks to a subscriber with this many messages still queued for it
+            max_sockets: 800,               // maximum number of client sockets we'll ever register
+            public_ip_address: None,        // resolve it at runtime by default
             public_ip_request_timeout: 60, // how often we can attempt to look up our public IP address
             public_ip_timeout: 3600,       // re-learn the public IP ever hour, if it's not given
             public_ip_max_retries: 3, // maximum number of retries before self-throttling for $public_ip_timeout
+            public_ip_quorum: 3, // number of distinct peers that must report the same public IP address before we use it
+            relay_min_fee_rate: 0, // minimum fee (microSTX per byte) of a transaction we'll relay (0 for no minimum)
+            relay_min_stx_transfer: 0, // minimum amount (microSTX) of an STX transfer we'll relay (0 for no minimum)
+            relay_max_tx_len: 0,       // largest transaction (bytes) we'll relay (0 for no limit)
+            relay_max_txs_per_origin: 0, // most transactions from one origin we'll relay per minute (0 for no limit)
+            relay_max_tx_bytes_per_origin: 0, // most bytes of transactions from one origin we'll relay per minute (0 for no limit)
             max_block_push: 10, // maximum number of blocksData messages to push out via our anti-entropy protocol
             max_microblock_push: 10, // maximum number of microblocks messages to push out via our anti-entropy protocol
             antientropy_retry: 3600, // retry pushing data only once every hour
             antientropy_public: true, // run antientropy even if we're NOT NAT'ed
+            inv_agreement_check_interval: 60, // how often to compare our block inventory to our neighbors', in seconds (0 to disable)
+            inv_agreement_alert_pct: 50, // alert when more than this percentage of neighbors disagree with our block inventory...
+            inv_agreement_alert_duration: 600, // ...for at least this many seconds
+            peer_version_check_interval: 60, // how often to count the peer versions our peers advertise, in seconds (0 to disable)
+            data_url_health_interval: 600, // how often to tell our peers which data URLs we couldn't reach, in seconds (0 to disable)
+            peer_version_alert_pct: 50, // warn when more than this percentage of observed peers advertise a newer peer version than ours
+            rehandshake_interval: 144, // re-handshake with a peer after this many burn blocks, to refresh its data URL, services, and key expiry (0 to disable)
+            staging_backlog_check_interval: 10, // how often to count our unprocessed staging blocks, in seconds
+            max_staging_blocks_backlog: 0, // refuse block pushes while we have more than this many attachable blocks left to process (0, the default, disables it)
             max_buffered_blocks_available: 1,
             max_buffered_microblocks_available: 1,
             max_buffered_blocks: 1,
             max_buffered_microblocks: 10,
+            max_payload_len: MAX_PAYLOAD_LEN, // largest message payload we'll accept from a peer that honors MessageLimit
+            download_state_save_interval: 60, // how often to save inv sync and block download progress, in seconds (0 to disable)
+            enable_admin_rpc: false, // serve the /v2/admin/ RPC endpoints (e.g. mempool export/import)
+            max_rpc_page_size: 1000, // most items a paginated RPC endpoint will return in one page
+            rpc_request_timeout: 30, // how long an RPC request may run before it is aborted, in seconds (0 for no limit); clients may lower it with X-Request-Timeout
+            max_rpc_tip_fork_depth: 144, // how far below the canonical tip a fork may have diverged for its blocks to be served as `tip=` (0 for no limit)
+            rpc_cache_max_entries: 1024, // most expensive read-only RPC responses to cache until the chain tip changes (0 to disable)
+            rpc_idempotency_key_ttl: 3600, // how long the response to a transaction POST is replayed to a retry with the same Idempotency-Key (0 to disable)
+            rpc_idempotency_max_keys: 10000, // most submissions with an Idempotency-Key to remember; the oldest are forgotten first
+            max_query_jobs: 4,               // most query jobs (POST /v2/jobs) that may run at once
+            query_job_blocks_per_pass: 50, // how many blocks each query job scans per network pass
+            max_query_job_results: 10000, // a query job stops once it has found this many transactions
+            query_job_ttl: 3600, // how long a finished query job's results are kept, in seconds
+            network_mode: NetworkMode::Full, // accept inbound peers, connect out, relay, and serve data
+            p2p_tls_cert_file: None, // PEM certificate chain for TLS p2p connections (no TLS if not set)
+            p2p_tls_key_file: None,  // PEM private key for p2p_tls_cert_file
+            p2p_tls_ca_file: None, // PEM CA bundle to check peers' TLS certificates against (not checked if not set)
+            p2p_tls_require_client_cert: false, // refuse TLS from peers that connect to us without a certificate signed by p2p_tls_ca_file
+            async_io_threads: 0, // threads that do socket I/O for p2p and HTTP connections (0 to do it in the network thread; needs the async_net feature)
+            async_io_max_inbound_bytes: 4 * 1024 * 1024, // most received bytes an async socket buffers before it stops reading
+            async_io_max_outbound_bytes: 4 * 1024 * 1024, // most unsent bytes an async socket buffers before writes block
 
             // no faults on by default
             disable_neighbor_walk: false,
@@ -481,6 +680,7 @@ pub struct NetworkConnection<P: ProtocolFamily> {
     pub protocol: P,
     inbox: ConnectionInbox<P>,
     outbox: ConnectionOutbox<P>,
+    tls: Option<TlsSession>,
 }
 
 impl<P: ProtocolFamily> ConnectionInbox<P> {
@@ -725,6 +925,11 @@ impl<P: ProtocolFamily> ConnectionInbox<P> {
                     &preamble,
                     bytes_consumed
                 );
+
+                // the protocol has taken what it needs from the streamed bytes, so don't hold
+                // onto them while the rest of the stream arrives
+                self.buf.drain(self.message_ptr..self.payload_ptr);
+                self.payload_ptr = self.message_ptr;
                 None
             }
         };
@@ -964,6 +1169,7 @@ impl<P: ProtocolFamily> ConnectionOutbox<P> {
             pending_message_fd: None,
             socket_out_buf: vec![],
             socket_out_ptr: 0,
+            send_limit: None,
             inflight: VecDeque::new(),
         }
     }
@@ -1034,6 +1240,10 @@ impl<P: ProtocolFamily> ConnectionOutbox<P> {
             let mut message_eof = false;
 
             if self.pending_message_fd.is_none() {
+                if self.send_limit == Some(0) {
+                    test_debug!("Connection reached its send limit");
+                    break;
+                }
                 self.pending_message_fd = self.begin_next_message();
             }
 
@@ -1138,6 +1348,9 @@ impl<P: ProtocolFamily> ConnectionOutbox<P> {
                 // no pending bytes, so we must have a message
                 test_debug!("End of message, {} bytes sent", self.socket_out_ptr);
                 self.finish_message();
+                if let Some(ref mut send_limit) = self.send_limit {
+                    *send_limit = send_limit.saturating_sub(1);
+                }
 
                 self.pending_message_fd = None;
                 self.socket_out_buf.clear();
@@ -1178,6 +1391,7 @@ impl<P: ProtocolFamily + Clone> NetworkConnection<P> {
 
             inbox: ConnectionInbox::new(options.inbox_maxlen, public_key_opt),
             outbox: ConnectionOutbox::new(options.outbox_maxlen),
+            tls: None,
         }
     }
 
@@ -1317,12 +1531,53 @@ impl<P: ProtocolFamily + Clone> NetworkConnection<P> {
 
     /// Send data
     pub fn send_data<W: Write>(&mut self, fd: &mut W) -> Result<usize, net_error> {
-        self.outbox.send_bytes(fd)
+        let tls = match self.tls {
+            Some(ref mut tls) => tls,
+            None => {
+                return self.outbox.send_bytes(fd);
+            }
+        };
+
+        if self.outbox.send_limit.is_some() {
+            // still sending the messages that were queued before we switched to TLS
+            let sent = self.outbox.send_bytes(fd)?;
+            if self.outbox.send_limit != Some(0) {
+                return Ok(sent);
+            }
+            self.outbox.send_limit = None;
+        }
+
+        // only encrypt more once the socket has taken what we already encrypted
+        let mut sent = tls.send_tls(fd)?;
+        if !tls.wants_write() {
+            self.outbox.send_bytes(tls)?;
+            sent += tls.send_tls(fd)?;
+        }
+        Ok(sent)
     }
 
     /// Receive data
     pub fn recv_data<R: Read>(&mut self, fd: &mut R) -> Result<usize, net_error> {
-        self.inbox.recv_bytes(&mut self.protocol, fd)
+        match self.tls {
+            Some(ref mut tls) => {
+                tls.recv_tls(fd)?;
+                self.inbox.recv_bytes(&mut self.protocol, tls)
+            }
+            None => self.inbox.recv_bytes(&mut self.protocol, fd),
+        }
+    }
+
+    /// Switch this connection over to TLS.  The messages already queued are still sent in the
+    /// clear, since the remote peer reads them before it switches too; everything queued after
+    /// this call is encrypted.  The remote peer must not send anything more in the clear.
+    pub fn start_tls(&mut self, session: TlsSession) -> () {
+        self.outbox.send_limit = Some(self.outbox.num_messages());
+        self.tls = Some(session);
+    }
+
+    /// Has this connection switched over to TLS?
+    pub fn is_tls(&self) -> bool {
+        self.tls.is_some()
     }
 
     /// how many inbox messages pending?
@@ -2244,4 +2499,57 @@ mod test {
 
         pinger.join().unwrap();
     }
+
+    #[test]
+    fn test_network_mode() {
+        for mode in [
+            NetworkMode::Full,
+            NetworkMode::Listener,
+            NetworkMode::Leecher,
+            NetworkMode::Replica,
+        ]
+        .iter()
+        {
+            assert_eq!(NetworkMode::from_string(mode.as_str()), Some(*mode));
+        }
+        assert_eq!(NetworkMode::from_string("seeder"), None);
+
+        let services = ServiceFlags::to_services(&[
+            ServiceFlags::RELAY,
+            ServiceFlags::RPC,
+            ServiceFlags::ARCHIVAL,
+            ServiceFlags::ATLAS,
+            ServiceFlags::MICROBLOCK_PUSH,
+        ]);
+
+        assert!(NetworkMode::Full.accepts_inbound());
+        assert!(NetworkMode::Full.initiates_outbound());
+        assert_eq!(NetworkMode::Full.filter_services(services), services);
+
+        assert!(NetworkMode::Listener.accepts_inbound());
+        assert!(!NetworkMode::Listener.initiates_outbound());
+        assert!(NetworkMode::Listener.relays_pushes());
+        assert!(NetworkMode::Listener.serves_data());
+        assert_eq!(NetworkMode::Listener.filter_services(services), services);
+
+        assert!(!NetworkMode::Leecher.accepts_inbound());
+        assert!(NetworkMode::Leecher.initiates_outbound());
+        assert!(!NetworkMode::Leecher.relays_pushes());
+        assert!(!NetworkMode::Leecher.serves_data());
+        assert_eq!(
+            NetworkMode::Leecher.filter_services(services),
+            ServiceFlags::ARCHIVAL as u16
+        );
+        assert!(NetworkMode::Leecher.accepts_writes());
+
+        assert!(!NetworkMode::Replica.accepts_inbound());
+        assert!(!NetworkMode::Replica.initiates_outbound());
+        assert!(!NetworkMode::Replica.relays_pushes());
+        assert!(NetworkMode::Replica.serves_data());
+        assert!(!NetworkMode::Replica.accepts_writes());
+        assert_eq!(
+            NetworkMode::Replica.filter_services(services),
+            services & !(ServiceFlags::RELAY as u16) & !(ServiceFlags::MICROBLOCK_PUSH as u16)
+        );
+    }
 }
@@ -234,6 +234,72 @@ impl FromRow<LocalPeer> for LocalPeer {
     }
 }
 
+/// A neighbor's block inventory and how far we got scanning it, saved by the inventory sync
+/// state machine so it can resume after a restart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedInvSyncState {
+    pub nk: NeighborKey,
+    pub block_inv: Vec<u8>,
+    pub microblocks_inv: Vec<u8>,
+    pub pox_inv: Vec<u8>,
+    pub num_sortitions: u64,
+    pub num_reward_cycles: u64,
+    pub first_block_height: u64,
+    /// reward cycle at which the block inventory scan left off
+    pub block_reward_cycle: u64,
+}
+
+impl FromRow<SavedInvSyncState> for SavedInvSyncState {
+    fn from_row<'a>(row: &'a Row) -> Result<SavedInvSyncState, db_error> {
+        let peer_version: u32 = row.get_unwrap("peer_version");
+        let network_id: u32 = row.get_unwrap("network_id");
+        let addrbytes: PeerAddress = PeerAddress::from_column(row, "addrbytes")?;
+        let port: u16 = row.get_unwrap("port");
+        let num_sortitions = u64::from_column(row, "num_sortitions")?;
+        let num_reward_cycles = u64::from_column(row, "num_reward_cycles")?;
+        let first_block_height = u64::from_column(row, "first_block_height")?;
+        let block_reward_cycle = u64::from_column(row, "block_reward_cycle")?;
+
+        let mut bitvecs = vec![];
+        for column_name in ["block_inv", "microblocks_inv", "pox_inv"].iter() {
+            let bitvec_hex: String = row.get_unwrap(*column_name);
+            let bitvec = hex_bytes(&bitvec_hex).map_err(|_e| {
+                error!("Unparseable {} {}", column_name, &bitvec_hex);
+                db_error::ParseError
+            })?;
+            bitvecs.push(bitvec);
+        }
+        let pox_inv = bitvecs.pop().unwrap();
+        let microblocks_inv = bitvecs.pop().unwrap();
+        let block_inv = bitvecs.pop().unwrap();
+
+        if block_inv.len() != microblocks_inv.len() {
+            error!(
+                "Saved block inventory has {} bytes, but microblock inventory has {}",
+                block_inv.len(),
+                microblocks_inv.len()
+            );
+            return Err(db_error::ParseError);
+        }
+
+        Ok(SavedInvSyncState {
+            nk: NeighborKey {
+                peer_version,
+                network_id,
+                addrbytes,
+                port,
+            },
+            block_inv,
+            microblocks_inv,
+            pox_inv,
+            num_sortitions,
+            num_reward_cycles,
+            first_block_height,
+            block_reward_cycle,
+        })
+    }
+}
+
 impl FromRow<ASEntry4> for ASEntry4 {
     fn from_row<'a>(row: &'a Row) -> Result<ASEntry4, db_error> {
         let prefix: u32 = row.get_unwrap("prefix");
@@ -357,6 +423,46 @@ const PEERDB_INITIAL_SCHEMA: &'static [&'static str] = &[
     );"#,
 ];
 
+/// Inventory sync and block download progress, saved so that a restarted node can resume where it
+/// left off instead of re-scanning every neighbor's inventory.  These tables are created on open if
+/// they are missing, since they were added after the initial schema.
+const PEERDB_DOWNLOAD_STATE_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS inv_sync_state(
+        network_id INTEGER NOT NULL,
+        peer_version INTEGER NOT NULL,
+        addrbytes TEXT NOT NULL,
+        port INTEGER NOT NULL,
+        block_inv TEXT NOT NULL,            -- hex-encoded bit vector
+        microblocks_inv TEXT NOT NULL,      -- hex-encoded bit vector
+        pox_inv TEXT NOT NULL,              -- hex-encoded bit vector
+        num_sortitions INTEGER NOT NULL,
+        num_reward_cycles INTEGER NOT NULL,
+        first_block_height INTEGER NOT NULL,
+        block_reward_cycle INTEGER NOT NULL,
+
+        PRIMARY KEY(network_id,addrbytes,port)
+    );"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS block_download_state(
+        block_sortition_height INTEGER NOT NULL,
+        microblock_sortition_height INTEGER NOT NULL
+    );"#,
+];
+
+/// Allow and deny lists of peer public key hashes.  Like the download state tables, these are
+/// created on open if they are missing.
+const PEERDB_PUBKEY_HASH_FILTER_SCHEMA: &'static [&'static str] = &[
+    r#"
+    CREATE TABLE IF NOT EXISTS allowed_pubkey_hashes(
+        pubkey_hash TEXT PRIMARY KEY NOT NULL
+    );"#,
+    r#"
+    CREATE TABLE IF NOT EXISTS denied_pubkey_hashes(
+        pubkey_hash TEXT PRIMARY KEY NOT NULL
+    );"#,
+];
+
 #[derive(Debug)]
 pub struct PeerDB {
     pub conn: Connection,
@@ -391,6 +497,12 @@ impl PeerDB {
         for row_text in PEERDB_INITIAL_SCHEMA {
             tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
         }
+        for row_text in PEERDB_DOWNLOAD_STATE_SCHEMA {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
+        for row_text in PEERDB_PUBKEY_HASH_FILTER_SCHEMA {
+            tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+        }
 
         tx.execute(
             "INSERT INTO db_config (version) VALUES (?1)",
@@ -480,6 +592,10 @@ impl PeerDB {
             debug!("Refresh deny {}/{}", &prefix, mask);
             PeerDB::apply_cidr_filter(tx, &prefix, mask, "denied", i64::max_value())?;
         }
+        for pubkey_hash in PeerDB::get_denied_pubkey_hashes(tx)?.into_iter() {
+            debug!("Refresh deny public key hash {}", &pubkey_hash);
+            PeerDB::apply_pubkey_hash_filter(tx, &pubkey_hash, "denied", i64::max_value())?;
+        }
         Ok(())
     }
 
@@ -490,6 +606,10 @@ impl PeerDB {
             debug!("Refresh allow {}/{}", &prefix, mask);
             PeerDB::apply_cidr_filter(tx, &prefix, mask, "allowed", i64::max_value())?;
         }
+        for pubkey_hash in PeerDB::get_allowed_pubkey_hashes(tx)?.into_iter() {
+            debug!("Refresh allow public key hash {}", &pubkey_hash);
+            PeerDB::apply_pubkey_hash_filter(tx, &pubkey_hash, "allowed", -1)?;
+        }
         Ok(())
     }
 
@@ -570,6 +690,13 @@ impl PeerDB {
 
             {
                 let mut tx = db.tx_begin()?;
+                for row_text in PEERDB_DOWNLOAD_STATE_SCHEMA {
+                    tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+                }
+                for row_text in PEERDB_PUBKEY_HASH_FILTER_SCHEMA {
+                    tx.execute_batch(row_text).map_err(db_error::SqliteError)?;
+                }
+
                 PeerDB::refresh_allows(&mut tx)?;
                 PeerDB::refresh_denies(&mut tx)?;
                 PeerDB::clear_initial_peers(&mut tx)?;
@@ -797,15 +924,7 @@ impl PeerDB {
         peer_port: u16,
     ) -> Result<bool, db_error> {
         match PeerDB::get_peer(conn, network_id, peer_addr, peer_port)? {
-            Some(neighbor) => {
-                if neighbor.is_denied() {
-                    return Ok(true);
-                }
-                if PeerDB::is_address_denied(conn, &neighbor.addr.addrbytes)? {
-                    return Ok(true);
-                }
-                return Ok(false);
-            }
+            Some(neighbor) => PeerDB::is_neighbor_denied(conn, &neighbor),
             None => {
                 if PeerDB::is_address_denied(conn, &peer_addr)? {
                     return Ok(true);
@@ -815,6 +934,26 @@ impl PeerDB {
         }
     }
 
+    /// Is a known neighbor denied, either on its own or by one of the CIDR or public key hash deny
+    /// lists?
+    pub fn is_neighbor_denied(conn: &DBConn, neighbor: &Neighbor) -> Result<bool, db_error> {
+        if neighbor.is_denied() {
+            return Ok(true);
+        }
+        PeerDB::is_neighbor_matched_by_deny_rules(conn, neighbor)
+    }
+
+    /// Does one of the CIDR or public key hash deny lists match a neighbor?
+    fn is_neighbor_matched_by_deny_rules(
+        conn: &DBConn,
+        neighbor: &Neighbor,
+    ) -> Result<bool, db_error> {
+        if PeerDB::is_address_denied(conn, &neighbor.addr.addrbytes)? {
+            return Ok(true);
+        }
+        PeerDB::is_pubkey_hash_denied(conn, &Hash160::from_node_public_key(&neighbor.public_key))
+    }
+
     /// Is a peer always allowed?
     pub fn is_peer_always_allowed(
         conn: &DBConn,
@@ -1232,6 +1371,210 @@ impl PeerDB {
         Ok(())
     }
 
+    /// Remove an allowed CIDR prefix.  Peers it matched are no longer allowed, unless another
+    /// allow rule matches them.
+    pub fn remove_allow_cidr<'a>(
+        tx: &mut Transaction<'a>,
+        prefix: &PeerAddress,
+        mask: u32,
+    ) -> Result<(), db_error> {
+        assert!(mask > 0 && mask <= 128);
+        PeerDB::remove_cidr_prefix(tx, "allowed_prefixes", prefix, mask)?;
+
+        debug!("Remove allow {}/{}", &prefix, mask);
+        PeerDB::apply_cidr_filter(tx, prefix, mask, "allowed", 0)?;
+        PeerDB::reapply_allows(tx)
+    }
+
+    /// Remove a denied CIDR prefix.  Peers it matched are no longer denied, unless another deny
+    /// rule matches them.
+    pub fn remove_deny_cidr<'a>(
+        tx: &mut Transaction<'a>,
+        prefix: &PeerAddress,
+        mask: u32,
+    ) -> Result<(), db_error> {
+        assert!(mask > 0 && mask <= 128);
+        PeerDB::remove_cidr_prefix(tx, "denied_prefixes", prefix, mask)?;
+
+        debug!("Remove deny {}/{}", &prefix, mask);
+        PeerDB::apply_cidr_filter(tx, prefix, mask, "denied", 0)?;
+        PeerDB::reapply_denies(tx)
+    }
+
+    /// Re-apply all allow rules, without resetting any peer's allow flag
+    fn reapply_allows<'a>(tx: &mut Transaction<'a>) -> Result<(), db_error> {
+        for (prefix, mask) in PeerDB::get_allowed_cidrs(tx)?.into_iter() {
+            PeerDB::apply_cidr_filter(tx, &prefix, mask, "allowed", -1)?;
+        }
+        for pubkey_hash in PeerDB::get_allowed_pubkey_hashes(tx)?.into_iter() {
+            PeerDB::apply_pubkey_hash_filter(tx, &pubkey_hash, "allowed", -1)?;
+        }
+        Ok(())
+    }
+
+    /// Re-apply all deny rules, without resetting any peer's deny flag
+    fn reapply_denies<'a>(tx: &mut Transaction<'a>) -> Result<(), db_error> {
+        for (prefix, mask) in PeerDB::get_denied_cidrs(tx)?.into_iter() {
+            PeerDB::apply_cidr_filter(tx, &prefix, mask, "denied", i64::max_value())?;
+        }
+        for pubkey_hash in PeerDB::get_denied_pubkey_hashes(tx)?.into_iter() {
+            PeerDB::apply_pubkey_hash_filter(tx, &pubkey_hash, "denied", i64::max_value())?;
+        }
+        Ok(())
+    }
+
+    /// Get all public key hashes from a given table
+    fn get_pubkey_hashes(conn: &DBConn, table: &str) -> Result<Vec<Hash160>, db_error> {
+        let sql_query = format!("SELECT pubkey_hash FROM {} ORDER BY pubkey_hash", table);
+        let mut stmt = conn.prepare(&sql_query)?;
+        let rows_res_iter = stmt
+            .query_and_then(NO_PARAMS, |row| {
+                let pubkey_hash_hex: String = row.get_unwrap("pubkey_hash");
+                Hash160::from_hex(&pubkey_hash_hex).map_err(|_e| db_error::ParseError)
+            })
+            .map_err(db_error::SqliteError)?;
+
+        let mut ret = vec![];
+        for row_res in rows_res_iter {
+            ret.push(row_res?);
+        }
+
+        Ok(ret)
+    }
+
+    /// Get all denied public key hashes
+    pub fn get_denied_pubkey_hashes(conn: &DBConn) -> Result<Vec<Hash160>, db_error> {
+        PeerDB::get_pubkey_hashes(conn, "denied_pubkey_hashes")
+    }
+
+    /// Get all allowed public key hashes
+    pub fn get_allowed_pubkey_hashes(conn: &DBConn) -> Result<Vec<Hash160>, db_error> {
+        PeerDB::get_pubkey_hashes(conn, "allowed_pubkey_hashes")
+    }
+
+    /// Is a public key hash on the deny list?
+    pub fn is_pubkey_hash_denied(conn: &DBConn, pubkey_hash: &Hash160) -> Result<bool, db_error> {
+        let args: &[&dyn ToSql] = &[&pubkey_hash.to_hex()];
+        let count = query_count(
+            conn,
+            "SELECT COUNT(*) FROM denied_pubkey_hashes WHERE pubkey_hash = ?1",
+            args,
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Update the given column to be equal to the given value for all peers whose public key
+    /// hashes to the given hash.  Public keys are stored in full, so this has to scan the
+    /// frontier.
+    fn apply_pubkey_hash_filter<'a>(
+        tx: &mut Transaction<'a>,
+        pubkey_hash: &Hash160,
+        column: &str,
+        value: i64,
+    ) -> Result<(), db_error> {
+        let peers = query_rows::<Neighbor, _>(tx, "SELECT * FROM frontier", NO_PARAMS)?;
+        for peer in peers.into_iter() {
+            if Hash160::from_node_public_key(&peer.public_key) != *pubkey_hash {
+                continue;
+            }
+            let args: &[&dyn ToSql] = &[
+                &value,
+                &peer.addr.network_id,
+                &peer.addr.addrbytes.to_bin(),
+                &peer.addr.port,
+            ];
+            tx.execute(
+                &format!(
+                    "UPDATE frontier SET {} = ?1 WHERE network_id = ?2 AND addrbytes = ?3 AND port = ?4",
+                    column
+                ),
+                args,
+            )
+            .map_err(db_error::SqliteError)?;
+        }
+        Ok(())
+    }
+
+    /// Allow the peer(s) with the given public key hash
+    pub fn add_allow_pubkey_hash<'a>(
+        tx: &mut Transaction<'a>,
+        pubkey_hash: &Hash160,
+    ) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[&pubkey_hash.to_hex()];
+        tx.execute(
+            "INSERT OR REPLACE INTO allowed_pubkey_hashes (pubkey_hash) VALUES (?1)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+
+        debug!("Apply allow public key hash {}", pubkey_hash);
+        PeerDB::apply_pubkey_hash_filter(tx, pubkey_hash, "allowed", -1)
+    }
+
+    /// Deny the peer(s) with the given public key hash
+    pub fn add_deny_pubkey_hash<'a>(
+        tx: &mut Transaction<'a>,
+        pubkey_hash: &Hash160,
+    ) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[&pubkey_hash.to_hex()];
+        tx.execute(
+            "INSERT OR REPLACE INTO denied_pubkey_hashes (pubkey_hash) VALUES (?1)",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+
+        debug!("Apply deny public key hash {}", pubkey_hash);
+        PeerDB::apply_pubkey_hash_filter(tx, pubkey_hash, "denied", i64::max_value())
+    }
+
+    /// Remove an allowed public key hash.  Peers with that key are no longer allowed, unless
+    /// another allow rule matches them.
+    pub fn remove_allow_pubkey_hash<'a>(
+        tx: &mut Transaction<'a>,
+        pubkey_hash: &Hash160,
+    ) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[&pubkey_hash.to_hex()];
+        tx.execute(
+            "DELETE FROM allowed_pubkey_hashes WHERE pubkey_hash = ?1",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+
+        debug!("Remove allow public key hash {}", pubkey_hash);
+        PeerDB::apply_pubkey_hash_filter(tx, pubkey_hash, "allowed", 0)?;
+        PeerDB::reapply_allows(tx)
+    }
+
+    /// Remove a denied public key hash.  Peers with that key are no longer denied, unless another
+    /// deny rule matches them.
+    pub fn remove_deny_pubkey_hash<'a>(
+        tx: &mut Transaction<'a>,
+        pubkey_hash: &Hash160,
+    ) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[&pubkey_hash.to_hex()];
+        tx.execute(
+            "DELETE FROM denied_pubkey_hashes WHERE pubkey_hash = ?1",
+            args,
+        )
+        .map_err(db_error::SqliteError)?;
+
+        debug!("Remove deny public key hash {}", pubkey_hash);
+        PeerDB::apply_pubkey_hash_filter(tx, pubkey_hash, "denied", 0)?;
+        PeerDB::reapply_denies(tx)
+    }
+
+    /// Drop the neighbors matched by a deny list from a list of candidates.  The deny lists are
+    /// applied to the frontier when they change, but peers added since then may match them too.
+    fn filter_denied(conn: &DBConn, neighbors: Vec<Neighbor>) -> Result<Vec<Neighbor>, db_error> {
+        let mut ret = Vec::with_capacity(neighbors.len());
+        for neighbor in neighbors.into_iter() {
+            if !PeerDB::is_neighbor_matched_by_deny_rules(conn, &neighbor)? {
+                ret.push(neighbor);
+            }
+        }
+        Ok(ret)
+    }
+
     /// Get random neighbors, optionally always including allowed neighbors
     pub fn get_random_neighbors(
         conn: &DBConn,
@@ -1250,7 +1593,10 @@ impl PeerDB {
             let allow_qry = "SELECT * FROM frontier WHERE network_id = ?1 AND denied < ?2 AND (allowed < 0 OR ?3 < allowed)".to_string();
             let allow_args: &[&dyn ToSql] =
                 &[&network_id, &u64_to_sql(now_secs)?, &u64_to_sql(now_secs)?];
-            let mut allow_rows = query_rows::<Neighbor, _>(conn, &allow_qry, allow_args)?;
+            let mut allow_rows = PeerDB::filter_denied(
+                conn,
+                query_rows::<Neighbor, _>(conn, &allow_qry, allow_args)?,
+            )?;
 
             if allow_rows.len() >= (count as usize) {
                 // return a random subset
@@ -1278,8 +1624,10 @@ impl PeerDB {
             &u64_to_sql(now_secs)?,
             &(count - (ret.len() as u32)),
         ];
-        let mut random_peers =
-            query_rows::<Neighbor, _>(conn, &random_peers_qry, random_peers_args)?;
+        let mut random_peers = PeerDB::filter_denied(
+            conn,
+            query_rows::<Neighbor, _>(conn, &random_peers_qry, random_peers_args)?,
+        )?;
 
         ret.append(&mut random_peers);
         Ok(ret)
@@ -1375,6 +1723,81 @@ impl PeerDB {
         let rows = query_rows::<Neighbor, _>(conn, &qry, NO_PARAMS)?;
         Ok(rows)
     }
+
+    /// Replace all saved neighbor inventory sync state with the given state
+    pub fn set_inv_sync_state<'a>(
+        tx: &mut Transaction<'a>,
+        saved: &[SavedInvSyncState],
+    ) -> Result<(), db_error> {
+        tx.execute("DELETE FROM inv_sync_state", NO_PARAMS)
+            .map_err(db_error::SqliteError)?;
+
+        for state in saved.iter() {
+            let args: &[&dyn ToSql] = &[
+                &state.nk.network_id,
+                &state.nk.peer_version,
+                &to_bin(state.nk.addrbytes.as_bytes()),
+                &state.nk.port,
+                &to_hex(&state.block_inv),
+                &to_hex(&state.microblocks_inv),
+                &to_hex(&state.pox_inv),
+                &u64_to_sql(state.num_sortitions)?,
+                &u64_to_sql(state.num_reward_cycles)?,
+                &u64_to_sql(state.first_block_height)?,
+                &u64_to_sql(state.block_reward_cycle)?,
+            ];
+            tx.execute("INSERT OR REPLACE INTO inv_sync_state (network_id, peer_version, addrbytes, port, block_inv, microblocks_inv, pox_inv, num_sortitions, num_reward_cycles, first_block_height, block_reward_cycle) VALUES (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11)", args)
+                .map_err(db_error::SqliteError)?;
+        }
+        Ok(())
+    }
+
+    /// Get all saved neighbor inventory sync state for a network
+    pub fn get_inv_sync_state(
+        conn: &DBConn,
+        network_id: u32,
+    ) -> Result<Vec<SavedInvSyncState>, db_error> {
+        let qry =
+            "SELECT * FROM inv_sync_state WHERE network_id = ?1 ORDER BY addrbytes ASC, port ASC"
+                .to_string();
+        let args = [&network_id as &dyn ToSql];
+        query_rows::<SavedInvSyncState, _>(conn, &qry, &args)
+    }
+
+    /// Save the block downloader's scan position
+    pub fn set_block_download_state<'a>(
+        tx: &mut Transaction<'a>,
+        block_sortition_height: u64,
+        microblock_sortition_height: u64,
+    ) -> Result<(), db_error> {
+        tx.execute("DELETE FROM block_download_state", NO_PARAMS)
+            .map_err(db_error::SqliteError)?;
+
+        let args: &[&dyn ToSql] = &[
+            &u64_to_sql(block_sortition_height)?,
+            &u64_to_sql(microblock_sortition_height)?,
+        ];
+        tx.execute("INSERT INTO block_download_state (block_sortition_height, microblock_sortition_height) VALUES (?1,?2)", args)
+            .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Get the block downloader's saved scan position, as (block sortition height, microblock
+    /// sortition height)
+    pub fn get_block_download_state(conn: &DBConn) -> Result<Option<(u64, u64)>, db_error> {
+        let qry = "SELECT block_sortition_height, microblock_sortition_height FROM block_download_state LIMIT 1";
+        let mut stmt = conn.prepare(qry).map_err(db_error::SqliteError)?;
+        let mut rows = stmt.query(NO_PARAMS).map_err(db_error::SqliteError)?;
+        match rows.next().map_err(db_error::SqliteError)? {
+            Some(row) => {
+                let block_sortition_height = u64::from_column(&row, "block_sortition_height")?;
+                let microblock_sortition_height =
+                    u64::from_column(&row, "microblock_sortition_height")?;
+                Ok(Some((block_sortition_height, microblock_sortition_height)))
+            }
+            None => Ok(None),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -2046,6 +2469,165 @@ mod test {
         assert_eq!(n2.denied, 67890);
     }
 
+    #[test]
+    fn test_peer_deny_allow_pubkey_hash() {
+        let neighbor_1 = Neighbor {
+            addr: NeighborKey {
+                peer_version: 0x12345678,
+                network_id: 0x9abcdef0,
+                addrbytes: PeerAddress([
+                    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c,
+                    0x0d, 0x0e, 0x0f,
+                ]),
+                port: 12345,
+            },
+            public_key: Secp256k1PublicKey::from_hex(
+                "02fa66b66f8971a8cd4d20ffded09674e030f0f33883f337f34b95ad4935bac0e3",
+            )
+            .unwrap(),
+            expire_block: 23456,
+            last_contact_time: 1552509642,
+            allowed: 0,
+            denied: 0,
+            asn: 34567,
+            org: 45678,
+            in_degree: 1,
+            out_degree: 1,
+        };
+
+        let neighbor_2 = Neighbor {
+            addr: NeighborKey {
+                peer_version: 0x12345678,
+                network_id: 0x9abcdef0,
+                addrbytes: PeerAddress([
+                    0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18, 0x19, 0x1a, 0x1b, 0x1c,
+                    0x1d, 0x1e, 0x1f,
+                ]),
+                port: 12345,
+            },
+            public_key: Secp256k1PublicKey::from_hex(
+                "02287c1f1b280b5dde764b146976f6bad3fb485a3df9b1ad2d8ddc5719e7e91ff2",
+            )
+            .unwrap(),
+            expire_block: 23456,
+            last_contact_time: 1552509642,
+            allowed: 0,
+            denied: 0,
+            asn: 34567,
+            org: 45678,
+            in_degree: 1,
+            out_degree: 1,
+        };
+
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![neighbor_1.clone(), neighbor_2.clone()],
+        )
+        .unwrap();
+
+        let get_peer = |db: &PeerDB, neighbor: &Neighbor| {
+            PeerDB::get_peer(
+                db.conn(),
+                neighbor.addr.network_id,
+                &neighbor.addr.addrbytes,
+                neighbor.addr.port,
+            )
+            .unwrap()
+            .unwrap()
+        };
+
+        let pubkey_hash_1 = Hash160::from_node_public_key(&neighbor_1.public_key);
+        let pubkey_hash_2 = Hash160::from_node_public_key(&neighbor_2.public_key);
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::add_deny_pubkey_hash(&mut tx, &pubkey_hash_1).unwrap();
+            PeerDB::add_allow_pubkey_hash(&mut tx, &pubkey_hash_2).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(
+            PeerDB::get_denied_pubkey_hashes(db.conn()).unwrap(),
+            vec![pubkey_hash_1.clone()]
+        );
+        assert_eq!(
+            PeerDB::get_allowed_pubkey_hashes(db.conn()).unwrap(),
+            vec![pubkey_hash_2.clone()]
+        );
+        assert!(PeerDB::is_pubkey_hash_denied(db.conn(), &pubkey_hash_1).unwrap());
+        assert!(!PeerDB::is_pubkey_hash_denied(db.conn(), &pubkey_hash_2).unwrap());
+
+        let n1 = get_peer(&db, &neighbor_1);
+        let n2 = get_peer(&db, &neighbor_2);
+        assert_eq!(n1.denied, i64::max_value());
+        assert_eq!(n1.allowed, 0);
+        assert_eq!(n2.denied, 0);
+        assert_eq!(n2.allowed, -1);
+
+        // a denied peer is denied even if its address is not
+        assert!(PeerDB::is_neighbor_denied(db.conn(), &neighbor_1).unwrap());
+        assert!(!PeerDB::is_neighbor_denied(db.conn(), &neighbor_2).unwrap());
+
+        // denied peers are never sampled
+        let sampled =
+            PeerDB::get_random_neighbors(db.conn(), 0x9abcdef0, 10, 23455, false).unwrap();
+        assert_eq!(sampled.len(), 1);
+        assert_eq!(sampled[0].addr, neighbor_2.addr);
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::remove_deny_pubkey_hash(&mut tx, &pubkey_hash_1).unwrap();
+            PeerDB::remove_allow_pubkey_hash(&mut tx, &pubkey_hash_2).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert!(PeerDB::get_denied_pubkey_hashes(db.conn())
+            .unwrap()
+            .is_empty());
+        assert!(PeerDB::get_allowed_pubkey_hashes(db.conn())
+            .unwrap()
+            .is_empty());
+
+        let n1 = get_peer(&db, &neighbor_1);
+        let n2 = get_peer(&db, &neighbor_2);
+        assert_eq!(n1.denied, 0);
+        assert_eq!(n2.allowed, 0);
+        assert!(!PeerDB::is_neighbor_denied(db.conn(), &neighbor_1).unwrap());
+
+        {
+            // removing a CIDR rule does not undo a public key hash rule
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::add_deny_pubkey_hash(&mut tx, &pubkey_hash_1).unwrap();
+            PeerDB::add_deny_cidr(
+                &mut tx,
+                &PeerAddress([
+                    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x00,
+                ]),
+                64,
+            )
+            .unwrap();
+            PeerDB::remove_deny_cidr(
+                &mut tx,
+                &PeerAddress([
+                    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x00, 0x00, 0x00, 0x00, 0x00,
+                    0x00, 0x00, 0x00,
+                ]),
+                64,
+            )
+            .unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert!(PeerDB::get_denied_cidrs(db.conn()).unwrap().is_empty());
+        let n1 = get_peer(&db, &neighbor_1);
+        assert_eq!(n1.denied, i64::max_value());
+    }
+
     #[test]
     fn test_peer_refresh_cidr() {
         let neighbor_1 = Neighbor {
@@ -2167,4 +2749,81 @@ mod test {
         assert_eq!(n1.allowed, -1);
         assert_eq!(n2.allowed, -1);
     }
+
+    #[test]
+    fn test_save_download_state() {
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+
+        assert_eq!(
+            PeerDB::get_inv_sync_state(db.conn(), 0x9abcdef0).unwrap(),
+            vec![]
+        );
+        assert_eq!(PeerDB::get_block_download_state(db.conn()).unwrap(), None);
+
+        let saved = SavedInvSyncState {
+            nk: NeighborKey {
+                peer_version: 0x12345678,
+                network_id: 0x9abcdef0,
+                addrbytes: PeerAddress([0x01; 16]),
+                port: 20444,
+            },
+            block_inv: vec![0x55, 0x77],
+            microblocks_inv: vec![0x11, 0x22],
+            pox_inv: vec![0x0f],
+            num_sortitions: 16,
+            num_reward_cycles: 4,
+            first_block_height: 12345,
+            block_reward_cycle: 3,
+        };
+        let mut saved_other_network = saved.clone();
+        saved_other_network.nk.network_id = 0x12345678;
+
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::set_inv_sync_state(&mut tx, &[saved.clone(), saved_other_network.clone()])
+                .unwrap();
+            PeerDB::set_block_download_state(&mut tx, 100, 90).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(
+            PeerDB::get_inv_sync_state(db.conn(), 0x9abcdef0).unwrap(),
+            vec![saved.clone()]
+        );
+        assert_eq!(
+            PeerDB::get_block_download_state(db.conn()).unwrap(),
+            Some((100, 90))
+        );
+
+        // saving again replaces what was there
+        let mut saved_again = saved.clone();
+        saved_again.block_reward_cycle = 4;
+        {
+            let mut tx = db.tx_begin().unwrap();
+            PeerDB::set_inv_sync_state(&mut tx, &[saved_again.clone()]).unwrap();
+            PeerDB::set_block_download_state(&mut tx, 110, 100).unwrap();
+            tx.commit().unwrap();
+        }
+
+        assert_eq!(
+            PeerDB::get_inv_sync_state(db.conn(), 0x9abcdef0).unwrap(),
+            vec![saved_again]
+        );
+        assert_eq!(
+            PeerDB::get_inv_sync_state(db.conn(), 0x12345678).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            PeerDB::get_block_download_state(db.conn()).unwrap(),
+            Some((110, 100))
+        );
+    }
 }
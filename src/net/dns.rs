@@ -51,6 +51,17 @@ use util::get_epoch_time_secs;
 use util::hash::to_hex;
 use util::log;
 
+/// How long the DNS client remembers a successful lookup, in millis.  getaddrinfo() doesn't tell
+/// us the records' actual TTLs, so this is the TTL we give every name.
+pub const DNS_CACHE_TTL: u128 = 300_000;
+
+/// How long the DNS client remembers that a name could not be resolved, in millis
+pub const DNS_NEGATIVE_CACHE_TTL: u128 = 30_000;
+
+/// Prefix of the error message the resolver gives when the name itself could not be resolved (as
+/// opposed to the lookup timing out or getting dropped).  Only these errors are negative-cached.
+const DNS_RESOLVE_ERROR: &str = "DNS resolve error";
+
 /// In Rust, there's no easy way to do non-blocking DNS lookups (I blame getaddrinfo), so do it in
 /// a separate thread, and implement a way for the block downloader to periodically poll for
 /// resolved names.
@@ -109,6 +120,21 @@ impl DNSResponse {
             result: Err(errstr),
         }
     }
+
+    /// Did the resolver fail to resolve the name?
+    pub fn is_resolve_error(&self) -> bool {
+        match self.result {
+            Ok(_) => false,
+            Err(ref msg) => msg.starts_with(DNS_RESOLVE_ERROR),
+        }
+    }
+}
+
+/// A remembered lookup result, and when it expires (in millis)
+#[derive(Debug, PartialEq, Clone)]
+struct DNSCacheEntry {
+    result: Result<Vec<SocketAddr>, String>,
+    expires_at: u128,
 }
 
 #[derive(Debug)]
@@ -127,6 +153,11 @@ pub struct DNSClient {
     requests: HashMap<DNSRequest, Option<DNSResponse>>,
     requests_tx: SyncSender<DNSRequest>,
     requests_rx: Receiver<DNSResponse>,
+
+    // recent lookup results, so repeated lookups of the same names don't go to the resolver
+    cache: HashMap<(String, u16), DNSCacheEntry>,
+    cache_ttl: u128,
+    negative_cache_ttl: u128,
 }
 
 impl DNSResolver {
@@ -166,12 +197,12 @@ impl DNSResolver {
                 list
             }
             Err(ioe) => {
-                return DNSResponse::error(req, format!("DNS resolve error: {:?}", &ioe));
+                return DNSResponse::error(req, format!("{}: {:?}", DNS_RESOLVE_ERROR, &ioe));
             }
         };
 
         if addrs.len() == 0 {
-            return DNSResponse::error(req, "DNS resolve error: got zero addresses".to_string());
+            return DNSResponse::error(req, format!("{}: got zero addresses", DNS_RESOLVE_ERROR));
         }
         DNSResponse::new(req, Ok(addrs))
     }
@@ -272,11 +303,76 @@ impl DNSClient {
             requests_tx: inbound,
             requests_rx: outbound,
             requests: HashMap::new(),
+            cache: HashMap::new(),
+            cache_ttl: DNS_CACHE_TTL,
+            negative_cache_ttl: DNS_NEGATIVE_CACHE_TTL,
+        }
+    }
+
+    /// Set how long (in millis) to remember successful and failed lookups.  0 disables caching.
+    pub fn set_cache_ttls(&mut self, cache_ttl: u128, negative_cache_ttl: u128) -> () {
+        self.cache_ttl = cache_ttl;
+        self.negative_cache_ttl = negative_cache_ttl;
+    }
+
+    /// Remember a resolver's answer.  Timeouts and other transient failures are not cached.
+    fn cache_response(&mut self, resp: &DNSResponse) -> () {
+        let ttl = match resp.result {
+            Ok(_) => self.cache_ttl,
+            Err(_) if resp.is_resolve_error() => self.negative_cache_ttl,
+            Err(_) => {
+                return;
+            }
+        };
+        if ttl == 0 {
+            return;
         }
+        self.cache.insert(
+            (resp.request.host.clone(), resp.request.port),
+            DNSCacheEntry {
+                result: resp.result.clone(),
+                expires_at: get_epoch_time_ms() + ttl,
+            },
+        );
     }
 
+    /// Get a remembered lookup result, if it hasn't expired
+    fn get_cached(&mut self, host: &str, port: u16) -> Option<Result<Vec<SocketAddr>, String>> {
+        let key = (host.to_string(), port);
+        let expired = match self.cache.get(&key) {
+            Some(entry) => entry.expires_at <= get_epoch_time_ms(),
+            None => {
+                return None;
+            }
+        };
+        if expired {
+            self.cache.remove(&key);
+            return None;
+        }
+        self.cache.get(&key).map(|entry| entry.result.clone())
+    }
+
+    /// Forget expired lookup results
+    pub fn clear_expired_cache(&mut self) -> () {
+        let now = get_epoch_time_ms();
+        self.cache.retain(|_, entry| entry.expires_at > now);
+    }
+
+    /// Queue up a lookup.  If we looked this name up recently, the answer is available
+    /// immediately via poll_lookup(); if the same lookup is already in-flight, it won't be sent to
+    /// the resolver twice.
     pub fn queue_lookup(&mut self, host: &str, port: u16, timeout: u128) -> Result<(), net_error> {
         let req = DNSRequest::new(host.to_string(), port, timeout);
+        if let Some(result) = self.get_cached(host, port) {
+            test_debug!("DNS cache hit for {}:{}", host, port);
+            self.requests
+                .insert(req.clone(), Some(DNSResponse::new(req, result)));
+            return Ok(());
+        }
+        if let Some(None) = self.requests.get(&req) {
+            test_debug!("DNS lookup for {}:{} is already in-flight", host, port);
+            return Ok(());
+        }
         self.requests_tx
             .send(req.clone())
             .map_err(|_se| net_error::LookupError("Failed to queue DNS query".to_string()))?;
@@ -302,6 +398,7 @@ impl DNSClient {
 
     pub fn try_recv(&mut self) -> Result<usize, net_error> {
         self.clear_timeouts();
+        self.clear_expired_cache();
 
         let mut num_recved = 0;
         loop {
@@ -309,6 +406,7 @@ impl DNSClient {
                 Ok(resp) => {
                     if self.requests.contains_key(&resp.request) {
                         if !resp.request.is_timed_out() {
+                            self.cache_response(&resp);
                             self.requests.insert(resp.request.clone(), Some(resp));
                             num_recved += 1;
                         } else {
@@ -372,9 +470,11 @@ impl DNSClient {
 
 #[cfg(test)]
 mod test {
+    use super::*;
     use net::test::*;
     use std::collections::HashMap;
     use std::error::Error;
+    use std::thread;
     use util::*;
 
     #[test]
@@ -515,6 +615,64 @@ mod test {
         dns_thread_shutdown(client, thread_handle);
     }
 
+    #[test]
+    fn dns_cache_lookups() {
+        let (mut resolver, mut client) = DNSResolver::new(100);
+        let addrs = vec![SocketAddr::from(([127, 0, 0, 1], 20443))];
+        resolver.add_hardcoded("node.example.local", 20443, addrs.clone());
+        let thread_handle = thread::spawn(move || resolver.thread_main());
+
+        client
+            .queue_lookup("node.example.local", 20443, get_epoch_time_ms() + 120_000)
+            .unwrap();
+        let resp = loop {
+            client.try_recv().unwrap();
+            if let Some(resp) = client.poll_lookup("node.example.local", 20443).unwrap() {
+                break resp;
+            }
+            sleep_ms(100);
+        };
+        assert_eq!(resp.result, Ok(addrs.clone()));
+
+        // answered from the cache, without waiting on the resolver
+        client
+            .queue_lookup("node.example.local", 20443, get_epoch_time_ms() + 120_000)
+            .unwrap();
+        let resp = client
+            .poll_lookup("node.example.local", 20443)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resp.result, Ok(addrs.clone()));
+
+        // names that don't resolve are remembered, but timeouts are not
+        client.cache_response(&DNSResponse::error(
+            DNSRequest::new("asdfjkl;".to_string(), 80, 0),
+            format!("{}: no such name", DNS_RESOLVE_ERROR),
+        ));
+        client.cache_response(&DNSResponse::error(
+            DNSRequest::new("slow.example.local".to_string(), 80, 0),
+            "DNS lookup timed out".to_string(),
+        ));
+        assert!(client.get_cached("asdfjkl;", 80).unwrap().is_err());
+        assert!(client.get_cached("slow.example.local", 80).is_none());
+
+        client
+            .queue_lookup("asdfjkl;", 80, get_epoch_time_ms() + 120_000)
+            .unwrap();
+        let resp = client.poll_lookup("asdfjkl;", 80).unwrap().unwrap();
+        assert!(resp.is_resolve_error());
+
+        // expired entries are forgotten
+        for entry in client.cache.values_mut() {
+            entry.expires_at = 0;
+        }
+        assert!(client.get_cached("node.example.local", 20443).is_none());
+        client.clear_expired_cache();
+        assert!(client.cache.is_empty());
+
+        dns_thread_shutdown(client, thread_handle);
+    }
+
     #[test]
     fn dns_resolve_timeout() {
         let (mut client, thread_handle) = dns_thread_start(100);
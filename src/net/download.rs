@@ -17,6 +17,7 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -75,6 +76,7 @@ use util::db::Error as db_error;
 use util::get_epoch_time_ms;
 use util::get_epoch_time_secs;
 use util::hash::to_hex;
+use util::hash::Hash160;
 use util::log;
 use util::secp256k1::Secp256k1PrivateKey;
 use util::secp256k1::Secp256k1PublicKey;
@@ -99,6 +101,33 @@ pub const BLOCK_REREQUEST_INTERVAL: u64 = 60;
 #[cfg(test)]
 pub const BLOCK_REREQUEST_INTERVAL: u64 = 30;
 
+/// Maximum number of miners for which we remember which neighbors deliver their blocks soonest
+pub const MAX_MINER_DELIVERY_STATS: usize = 256;
+
+/// Once a data URL host has served this many requests, its older history counts for half as much
+pub const DATA_URL_STATS_MAX_SAMPLES: u64 = 128;
+
+/// A data URL host that failed this many requests in a row is reported to our peers as unreachable
+pub const DATA_URL_UNREACHABLE_FAILURES: u64 = 3;
+
+/// Reports of unreachable data URL hosts, ours and our peers', are only good for this many seconds
+pub const DATA_URL_HEALTH_REPORT_TTL: u64 = 1800;
+
+/// A data URL host is only ranked down on our peers' word once peers in this many different
+/// networks have reported it unreachable
+pub const DATA_URL_HEALTH_MIN_REPORTERS: usize = 2;
+
+/// Rank of a data URL host that enough of our peers report as unreachable: worse than a host we
+/// know nothing about, but better than one that has failed all of our own requests
+pub const DATA_URL_REPORTED_UNREACHABLE_RANK: u64 = 900;
+
+/// Blocks and microblock streams downloaded within this many seconds count towards the measured
+/// download bandwidth
+pub const BLOCK_DOWNLOAD_BANDWIDTH_WINDOW: u64 = 10;
+
+/// Finished operator-requested block fetches are remembered for this many seconds
+pub const BLOCK_FETCH_STATUS_LIFETIME: u64 = 3600;
+
 /// This module is responsible for downloading blocks and microblocks from other peers, using block
 /// inventory state (see src/net/inv.rs)
 
@@ -120,6 +149,7 @@ pub struct BlockRequestKey {
     pub sortition_height: u64,
     pub download_start: u64,
     pub kind: BlockRequestKeyKind,
+    pub miner_pubkey_hash: Option<Hash160>, // only used if asking for an anchored block; used to track delivery latency
 }
 
 impl BlockRequestKey {
@@ -133,6 +163,7 @@ impl BlockRequestKey {
         parent_consensus_hash: Option<ConsensusHash>,
         sortition_height: u64,
         kind: BlockRequestKeyKind,
+        miner_pubkey_hash: Option<Hash160>,
     ) -> BlockRequestKey {
         BlockRequestKey {
             neighbor: neighbor,
@@ -145,6 +176,7 @@ impl BlockRequestKey {
             sortition_height: sortition_height,
             download_start: get_epoch_time_secs(),
             kind,
+            miner_pubkey_hash,
         }
     }
 }
@@ -180,6 +212,230 @@ impl std::fmt::Display for BlockRequestKey {
     }
 }
 
+/// How soon a neighbor gets a particular miner's blocks to us.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NeighborDeliveryStats {
+    /// Fewest relay hops between this neighbor and the origin of the miner's pushed blocks, as
+    /// learned from the messages' relayer chains (0 means the neighbor pushed the block first)
+    pub relay_hops: Option<usize>,
+    /// Moving average of how long, in milliseconds, this neighbor took to serve us the miner's
+    /// anchored blocks
+    pub latency_ms: Option<u64>,
+}
+
+/// Per-neighbor delivery statistics for one miner
+#[derive(Debug, Clone, PartialEq)]
+pub struct MinerDeliveryStats {
+    pub neighbors: HashMap<NeighborKey, NeighborDeliveryStats>,
+    pub last_update: u64,
+}
+
+impl MinerDeliveryStats {
+    fn new() -> MinerDeliveryStats {
+        MinerDeliveryStats {
+            neighbors: HashMap::new(),
+            last_update: get_epoch_time_secs(),
+        }
+    }
+}
+
+/// How well a peer's data URL host has served our HTTP requests for blocks, microblocks, and
+/// attachments.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DataUrlHostStats {
+    pub requests_sent: u64,
+    pub requests_succeeded: u64,
+    /// Total time, in milliseconds, this host took to answer the requests that succeeded
+    pub total_latency_ms: u64,
+    pub last_request_at: u64,
+    pub last_success_at: u64,
+    /// Requests that failed since the last one that succeeded
+    pub consecutive_failures: u64,
+    /// The URL of the most recent request that failed
+    pub last_failed_url: Option<UrlString>,
+    /// When peers reported this host as unreachable, keyed by the network prefix of the peer's
+    /// address.  Peers in the same network only count once.
+    pub unreachable_reports: HashMap<[u8; 4], u64>,
+}
+
+impl DataUrlHostStats {
+    fn age(&mut self) {
+        if self.requests_sent >= DATA_URL_STATS_MAX_SAMPLES {
+            self.requests_sent /= 2;
+            self.requests_succeeded /= 2;
+            self.total_latency_ms /= 2;
+        }
+    }
+
+    pub fn record_success(&mut self, latency_ms: u64) {
+        self.age();
+        self.requests_sent += 1;
+        self.requests_succeeded += 1;
+        self.total_latency_ms = self.total_latency_ms.saturating_add(latency_ms);
+        self.last_request_at = get_epoch_time_secs();
+        self.last_success_at = self.last_request_at;
+        self.consecutive_failures = 0;
+    }
+
+    pub fn record_failure(&mut self, url: &UrlString) {
+        self.age();
+        self.requests_sent += 1;
+        self.last_request_at = get_epoch_time_secs();
+        self.consecutive_failures += 1;
+        self.last_failed_url = Some(url.clone());
+    }
+
+    /// Did this host fail enough of our own requests in a row, recently enough, to tell our peers
+    /// about it?
+    pub fn is_unreachable(&self, now: u64) -> bool {
+        self.consecutive_failures >= DATA_URL_UNREACHABLE_FAILURES
+            && self.last_request_at + DATA_URL_HEALTH_REPORT_TTL >= now
+    }
+
+    /// Number of different networks whose peers reported this host as unreachable, recently and
+    /// since it last served one of our own requests
+    pub fn num_unreachable_reports(&self, now: u64) -> usize {
+        self.unreachable_reports
+            .values()
+            .filter(|reported_at| {
+                **reported_at + DATA_URL_HEALTH_REPORT_TTL >= now
+                    && **reported_at >= self.last_success_at
+            })
+            .count()
+    }
+
+    /// Fraction of requests that succeeded, in thousandths
+    pub fn success_permille(&self) -> u64 {
+        if self.requests_sent == 0 {
+            return 0;
+        }
+        self.requests_succeeded * 1000 / self.requests_sent
+    }
+
+    /// Average time taken to answer a successful request
+    pub fn avg_latency_ms(&self) -> Option<u64> {
+        if self.requests_succeeded == 0 {
+            return None;
+        }
+        Some(self.total_latency_ms / self.requests_succeeded)
+    }
+}
+
+/// Per-host data URL statistics.  These are kept by the PeerNetwork, so the block downloader and
+/// the Atlas attachments downloader learn from each other's requests, and don't forget what they
+/// learned when they get re-instantiated.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DataUrlStats {
+    hosts: HashMap<String, DataUrlHostStats>,
+}
+
+impl DataUrlStats {
+    pub fn new() -> DataUrlStats {
+        DataUrlStats {
+            hosts: HashMap::new(),
+        }
+    }
+
+    /// Data URLs are scored by host and port, since that's what determines whether or not a
+    /// request will be served well (e.g. two peers sharing a Gaia hub share a score).
+    fn host_key(url: &UrlString) -> String {
+        match url.parse_to_block_url() {
+            Ok(parsed) => match (parsed.host_str(), parsed.port_or_known_default()) {
+                (Some(host), Some(port)) => format!("{}:{}", host, port),
+                (Some(host), None) => host.to_string(),
+                _ => url.to_string(),
+            },
+            Err(_) => url.to_string(),
+        }
+    }
+
+    fn host_stats_mut(&mut self, url: &UrlString) -> &mut DataUrlHostStats {
+        self.hosts
+            .entry(DataUrlStats::host_key(url))
+            .or_insert_with(DataUrlHostStats::default)
+    }
+
+    pub fn record_success(&mut self, url: &UrlString, latency_ms: u64) {
+        self.host_stats_mut(url).record_success(latency_ms);
+    }
+
+    pub fn record_failure(&mut self, url: &UrlString) {
+        self.host_stats_mut(url).record_failure(url);
+    }
+
+    /// Data URLs on hosts that we recently failed to reach ourselves, most recent first, for
+    /// telling our peers about.
+    pub fn get_unreachable_urls(&self, now: u64, max_len: usize) -> Vec<UrlString> {
+        let mut unreachable: Vec<_> = self
+            .hosts
+            .values()
+            .filter(|stats| stats.is_unreachable(now))
+            .filter_map(|stats| {
+                stats
+                    .last_failed_url
+                    .as_ref()
+                    .map(|url| (stats.last_request_at, url.clone()))
+            })
+            .collect();
+        unreachable.sort_by(|a, b| b.0.cmp(&a.0));
+        unreachable.truncate(max_len);
+        unreachable.into_iter().map(|(_, url)| url).collect()
+    }
+
+    /// Record the data URLs that a peer in the network `reporter_prefix` reports it could not
+    /// reach.  These replace any earlier report from that network, so no network can count twice
+    /// for a host, or keep more than one report's worth of hosts in memory.  Reports that have
+    /// expired are forgotten.
+    pub fn record_unreachable_reports(
+        &mut self,
+        reporter_prefix: [u8; 4],
+        unreachable: &[UrlString],
+        now: u64,
+    ) {
+        for stats in self.hosts.values_mut() {
+            stats.unreachable_reports.remove(&reporter_prefix);
+            stats
+                .unreachable_reports
+                .retain(|_, reported_at| *reported_at + DATA_URL_HEALTH_REPORT_TTL >= now);
+        }
+        for url in unreachable.iter() {
+            self.host_stats_mut(url)
+                .unreachable_reports
+                .insert(reporter_prefix, now);
+        }
+        self.hosts
+            .retain(|_, stats| stats.requests_sent > 0 || stats.unreachable_reports.len() > 0);
+    }
+
+    pub fn get_host_stats(&self, url: &UrlString) -> Option<&DataUrlHostStats> {
+        self.hosts.get(&DataUrlStats::host_key(url))
+    }
+
+    /// Sort key for data URLs: most reliable host first, then the fastest.  A host we have not
+    /// sent anything to yet is ranked as if it served half its requests, at unknown speed.  A host
+    /// that peers in enough different networks report as unreachable is ranked no better than
+    /// DATA_URL_REPORTED_UNREACHABLE_RANK, until it serves one of our own requests.
+    pub fn rank_key(&self, url: &UrlString) -> (u64, u64) {
+        let stats = match self.get_host_stats(url) {
+            Some(stats) => stats,
+            None => return (500, u64::MAX),
+        };
+        let (rank, latency) = if stats.requests_sent > 0 {
+            (
+                1000 - stats.success_permille(),
+                stats.avg_latency_ms().unwrap_or(u64::MAX),
+            )
+        } else {
+            (500, u64::MAX)
+        };
+        if stats.num_unreachable_reports(get_epoch_time_secs()) >= DATA_URL_HEALTH_MIN_REPORTERS {
+            (cmp::max(rank, DATA_URL_REPORTED_UNREACHABLE_RANK), latency)
+        } else {
+            (rank, latency)
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum BlockDownloaderState {
     DNSLookupBegin,
@@ -218,6 +474,15 @@ pub struct BlockDownloader {
     /// Maximum number of concurrent requests
     max_inflight_requests: u64,
 
+    /// Maximum number of distinct data URLs to download from at once (0 for no limit)
+    max_download_peers: u64,
+
+    /// Maximum download bandwidth, in bytes per second (0 for no limit)
+    max_download_bandwidth: u64,
+
+    /// (time received, num bytes) of each recently-downloaded block and microblock stream
+    download_rx_counts: VecDeque<(u64, u64)>,
+
     /// Block requests to try, grouped by block, keyed by sortition height
     blocks_to_try: HashMap<u64, VecDeque<BlockRequestKey>>,
 
@@ -249,6 +514,16 @@ pub struct BlockDownloader {
     /// when did we last request a given block hash
     requested_blocks: HashMap<StacksBlockId, u64>,
     requested_microblocks: HashMap<StacksBlockId, u64>,
+
+    /// when (in milliseconds) the current batch of block requests began
+    getblocks_begin_ms: u64,
+
+    /// when (in milliseconds) the current batch of microblock requests began
+    getmicroblocks_begin_ms: u64,
+
+    /// which neighbors deliver each miner's blocks to us soonest, keyed by the hash of the
+    /// public key(s) that signed the miner's block-commits
+    miner_stats: HashMap<Hash160, MinerDeliveryStats>,
 }
 
 impl BlockDownloader {
@@ -256,6 +531,8 @@ impl BlockDownloader {
         dns_timeout: u128,
         download_interval: u64,
         max_inflight_requests: u64,
+        max_download_peers: u64,
+        max_download_bandwidth: u64,
     ) -> BlockDownloader {
         BlockDownloader {
             state: BlockDownloaderState::DNSLookupBegin,
@@ -274,6 +551,9 @@ impl BlockDownloader {
             last_inv_update_at: 0,
 
             max_inflight_requests: max_inflight_requests,
+            max_download_peers: max_download_peers,
+            max_download_bandwidth: max_download_bandwidth,
+            download_rx_counts: VecDeque::new(),
             blocks_to_try: HashMap::new(),
             microblocks_to_try: HashMap::new(),
 
@@ -294,6 +574,10 @@ impl BlockDownloader {
             download_interval: download_interval,
             requested_blocks: HashMap::new(),
             requested_microblocks: HashMap::new(),
+
+            getblocks_begin_ms: 0,
+            getmicroblocks_begin_ms: 0,
+            miner_stats: HashMap::new(),
         }
     }
 
@@ -317,6 +601,27 @@ impl BlockDownloader {
 
         // perserve sortition height
         // preserve download accounting
+        // preserve miner delivery stats
+    }
+
+    /// Pick up a scan that was underway before the node restarted
+    pub fn resume_scan(
+        &mut self,
+        block_sortition_height: u64,
+        microblock_sortition_height: u64,
+    ) -> () {
+        self.block_sortition_height = block_sortition_height;
+        self.microblock_sortition_height = microblock_sortition_height;
+        self.next_block_sortition_height = block_sortition_height;
+        self.next_microblock_sortition_height = microblock_sortition_height;
+    }
+
+    /// Get the current scan position, as (block sortition height, microblock sortition height)
+    pub fn get_scan_heights(&self) -> (u64, u64) {
+        (
+            self.block_sortition_height,
+            self.microblock_sortition_height,
+        )
     }
 
     pub fn restart_scan(&mut self, sortition_start: u64) -> () {
@@ -429,6 +734,7 @@ impl BlockDownloader {
 
         // don't touch blocks-to-try -- that's managed by the peer network directly.
         self.getblock_requests = requests;
+        self.getblocks_begin_ms = get_epoch_time_ms() as u64;
         self.state = BlockDownloaderState::GetBlocksFinish;
     }
 
@@ -441,6 +747,12 @@ impl BlockDownloader {
         // requests that are still pending
         let mut pending_block_requests = HashMap::new();
 
+        // how long it took each neighbor to serve each miner's block
+        let mut miner_latencies = vec![];
+
+        // sizes of the blocks we got
+        let mut downloaded_bytes = vec![];
+
         for (block_key, event_id) in self.getblock_requests.drain() {
             match network.http.get_conversation(event_id) {
                 None => {
@@ -455,6 +767,7 @@ impl BlockDownloader {
                         pending_block_requests.insert(block_key, event_id);
                     } else {
                         self.dead_peers.push(event_id);
+                        network.data_url_stats.record_failure(&block_key.data_url);
 
                         let is_always_allowed = match PeerDB::get_peer(
                             &network.peerdb.conn(),
@@ -500,6 +813,7 @@ impl BlockDownloader {
                                 ) != block_key.index_block_hash
                                 {
                                     info!("Invalid block from {:?} ({:?}): did not ask for block {}/{}", &block_key.neighbor, &block_key.data_url, block_key.consensus_hash, block.block_hash());
+                                    network.data_url_stats.record_failure(&block_key.data_url);
                                     self.broken_peers.push(event_id);
                                     self.broken_neighbors.push(block_key.neighbor.clone());
                                 } else {
@@ -510,6 +824,19 @@ impl BlockDownloader {
                                         &block_key.consensus_hash,
                                         block.block_hash()
                                     );
+                                    let latency_ms = (get_epoch_time_ms() as u64)
+                                        .saturating_sub(self.getblocks_begin_ms);
+                                    network
+                                        .data_url_stats
+                                        .record_success(&block_key.data_url, latency_ms);
+                                    if let Some(miner) = block_key.miner_pubkey_hash.clone() {
+                                        miner_latencies.push((
+                                            miner,
+                                            block_key.neighbor.clone(),
+                                            latency_ms,
+                                        ));
+                                    }
+                                    downloaded_bytes.push(block.serialize_to_vec().len() as u64);
                                     self.blocks.insert(block_key, block);
                                 }
                             }
@@ -520,6 +847,7 @@ impl BlockDownloader {
 
                                 // the fact that we asked this peer means that it's block inv indicated
                                 // it was present, so the absence is the mark of a broken peer
+                                network.data_url_stats.record_failure(&block_key.data_url);
                                 self.broken_peers.push(event_id);
                                 self.broken_neighbors.push(block_key.neighbor.clone());
                             }
@@ -529,6 +857,7 @@ impl BlockDownloader {
                                     "Got bad HTTP response from {:?}: {:?}",
                                     &block_key.data_url, &http_response
                                 );
+                                network.data_url_stats.record_failure(&block_key.data_url);
                                 self.broken_peers.push(event_id);
                                 self.broken_neighbors.push(block_key.neighbor.clone());
                             }
@@ -538,6 +867,13 @@ impl BlockDownloader {
             }
         }
 
+        for (miner, neighbor, latency_ms) in miner_latencies.drain(..) {
+            self.record_miner_block_latency(&miner, &neighbor, latency_ms);
+        }
+        for num_bytes in downloaded_bytes.drain(..) {
+            self.record_download_bytes(num_bytes);
+        }
+
         // are we done?
         if pending_block_requests.len() == 0 {
             self.state = BlockDownloaderState::GetMicroblocksBegin;
@@ -556,6 +892,7 @@ impl BlockDownloader {
         assert_eq!(self.state, BlockDownloaderState::GetMicroblocksBegin);
 
         self.getmicroblocks_requests = requests;
+        self.getmicroblocks_begin_ms = get_epoch_time_ms() as u64;
         self.state = BlockDownloaderState::GetMicroblocksFinish;
     }
 
@@ -568,6 +905,9 @@ impl BlockDownloader {
         // requests that are still pending
         let mut pending_microblock_requests = HashMap::new();
 
+        // sizes of the microblock streams we got
+        let mut downloaded_bytes = vec![];
+
         for (block_key, event_id) in self.getmicroblocks_requests.drain() {
             let rh_block_key = block_key.clone();
             match network.http.get_conversation(event_id) {
@@ -577,6 +917,7 @@ impl BlockDownloader {
                         pending_microblock_requests.insert(block_key, event_id);
                     } else {
                         self.dead_peers.push(event_id);
+                        network.data_url_stats.record_failure(&block_key.data_url);
 
                         let is_always_allowed = match PeerDB::get_peer(
                             &network.peerdb.conn(),
@@ -617,6 +958,7 @@ impl BlockDownloader {
                                 if microblocks.len() == 0 {
                                     // we wouldn't have asked for a 0-length stream
                                     info!("Got unexpected zero-length microblock stream from {:?} ({:?})", &block_key.neighbor, &block_key.data_url);
+                                    network.data_url_stats.record_failure(&block_key.data_url);
                                     self.broken_peers.push(event_id);
                                     self.broken_neighbors.push(block_key.neighbor.clone());
                                 } else {
@@ -628,6 +970,17 @@ impl BlockDownloader {
                                         &block_key.index_block_hash,
                                         microblocks[0].block_hash()
                                     );
+                                    let latency_ms = (get_epoch_time_ms() as u64)
+                                        .saturating_sub(self.getmicroblocks_begin_ms);
+                                    network
+                                        .data_url_stats
+                                        .record_success(&block_key.data_url, latency_ms);
+                                    downloaded_bytes.push(
+                                        microblocks
+                                            .iter()
+                                            .map(|mblock| mblock.serialize_to_vec().len() as u64)
+                                            .sum(),
+                                    );
                                     self.microblocks.insert(block_key, microblocks);
                                 }
                             }
@@ -643,10 +996,12 @@ impl BlockDownloader {
                                 // invalid microblock streams as present, even though they are
                                 // truly absent.  Don't punish these peers with a ban; just don't
                                 // talk to them for a while.
+                                network.data_url_stats.record_failure(&block_key.data_url);
                             }
                             _ => {
                                 // wrong message response
                                 info!("Got bad HTTP response from {:?}", &block_key.data_url);
+                                network.data_url_stats.record_failure(&block_key.data_url);
                                 self.broken_peers.push(event_id);
                                 self.broken_neighbors.push(block_key.neighbor.clone());
                             }
@@ -656,6 +1011,10 @@ impl BlockDownloader {
             }
         }
 
+        for num_bytes in downloaded_bytes.drain(..) {
+            self.record_download_bytes(num_bytes);
+        }
+
         // are we done?
         if pending_microblock_requests.len() == 0 {
             self.state = BlockDownloaderState::Done;
@@ -669,6 +1028,38 @@ impl BlockDownloader {
         return Ok(false);
     }
 
+    /// Count a downloaded block or microblock stream towards the measured download bandwidth
+    fn record_download_bytes(&mut self, num_bytes: u64) -> () {
+        let now = get_epoch_time_secs();
+        self.download_rx_counts.push_back((now, num_bytes));
+        while let Some((time, _)) = self.download_rx_counts.front() {
+            if *time + BLOCK_DOWNLOAD_BANDWIDTH_WINDOW > now {
+                break;
+            }
+            self.download_rx_counts.pop_front();
+        }
+    }
+
+    /// Get the average download bandwidth over the last BLOCK_DOWNLOAD_BANDWIDTH_WINDOW seconds,
+    /// in bytes per second
+    pub fn get_download_bandwidth(&self) -> u64 {
+        let now = get_epoch_time_secs();
+        let total_bytes: u64 = self
+            .download_rx_counts
+            .iter()
+            .filter(|(time, _)| *time + BLOCK_DOWNLOAD_BANDWIDTH_WINDOW > now)
+            .map(|(_, num_bytes)| *num_bytes)
+            .sum();
+        total_bytes / BLOCK_DOWNLOAD_BANDWIDTH_WINDOW
+    }
+
+    /// Are we downloading faster than the configured bandwidth ceiling?  If so, new requests
+    /// should wait.
+    pub fn is_bandwidth_throttled(&self) -> bool {
+        self.max_download_bandwidth > 0
+            && self.get_download_bandwidth() > self.max_download_bandwidth
+    }
+
     /// Get the availability of each block in the given sortition range, using the inv state.
     /// Return the local block headers, paired with the list of peers that can serve them.
     /// Possibly less than the given range request.
@@ -867,9 +1258,108 @@ impl BlockDownloader {
         disconnect.append(&mut self.dead_peers);
         disconnect_neighbors.append(&mut self.broken_neighbors);
 
+        // don't prefer broken neighbors for any miner's blocks
+        for (_, stats) in self.miner_stats.iter_mut() {
+            for nk in disconnect_neighbors.iter() {
+                stats.neighbors.remove(nk);
+            }
+        }
+
         (disconnect, disconnect_neighbors)
     }
 
+    /// Get (or create) the delivery stats for a miner, evicting the least-recently-updated
+    /// miner's stats if we're tracking too many.
+    fn get_miner_stats_mut(&mut self, miner: &Hash160) -> &mut MinerDeliveryStats {
+        if !self.miner_stats.contains_key(miner)
+            && self.miner_stats.len() >= MAX_MINER_DELIVERY_STATS
+        {
+            let oldest = self
+                .miner_stats
+                .iter()
+                .min_by_key(|(_, stats)| stats.last_update)
+                .map(|(pkh, _)| pkh.clone());
+            if let Some(oldest) = oldest {
+                self.miner_stats.remove(&oldest);
+            }
+        }
+
+        let stats = self
+            .miner_stats
+            .entry(miner.clone())
+            .or_insert_with(MinerDeliveryStats::new);
+        stats.last_update = get_epoch_time_secs();
+        stats
+    }
+
+    /// Record that a neighbor is the given number of relay hops away from the origin of a
+    /// miner's pushed block.  We remember the fewest hops seen.
+    pub fn record_miner_relay_hops(
+        &mut self,
+        miner: &Hash160,
+        neighbor: &NeighborKey,
+        hops: usize,
+    ) {
+        let stats = self
+            .get_miner_stats_mut(miner)
+            .neighbors
+            .entry(neighbor.clone())
+            .or_insert_with(NeighborDeliveryStats::default);
+        stats.relay_hops = Some(match stats.relay_hops {
+            Some(prev_hops) => cmp::min(prev_hops, hops),
+            None => hops,
+        });
+    }
+
+    /// Record how long a neighbor took to serve us one of a miner's anchored blocks.
+    pub fn record_miner_block_latency(
+        &mut self,
+        miner: &Hash160,
+        neighbor: &NeighborKey,
+        latency_ms: u64,
+    ) {
+        let stats = self
+            .get_miner_stats_mut(miner)
+            .neighbors
+            .entry(neighbor.clone())
+            .or_insert_with(NeighborDeliveryStats::default);
+        stats.latency_ms = Some(match stats.latency_ms {
+            Some(prev_latency_ms) => (3 * prev_latency_ms + latency_ms) / 4,
+            None => latency_ms,
+        });
+    }
+
+    /// Get the delivery stats we have for a miner and neighbor
+    pub fn get_miner_delivery_stats(
+        &self,
+        miner: &Hash160,
+        neighbor: &NeighborKey,
+    ) -> Option<&NeighborDeliveryStats> {
+        self.miner_stats
+            .get(miner)
+            .and_then(|stats| stats.neighbors.get(neighbor))
+    }
+
+    /// Order neighbors so that the ones that have gotten this miner's blocks to us soonest come
+    /// first: fewest relay hops from the miner first, then lowest download latency.  Neighbors
+    /// we know nothing about keep their relative order, after the ones we do.
+    pub fn rank_neighbors_for_miner(&self, miner: &Hash160, neighbors: &mut Vec<NeighborKey>) {
+        let stats = match self.miner_stats.get(miner) {
+            Some(stats) => stats,
+            None => {
+                return;
+            }
+        };
+
+        neighbors.sort_by_key(|nk| match stats.neighbors.get(nk) {
+            Some(nstats) => (
+                nstats.relay_hops.unwrap_or(usize::MAX),
+                nstats.latency_ms.unwrap_or(u64::MAX),
+            ),
+            None => (usize::MAX, u64::MAX),
+        });
+    }
+
     /// Set a hint that a block is now available from a remote peer, if we're idling or we're ahead
     /// of the given height.
     pub fn hint_block_sortition_height_available(&mut self, block_sortition_height: u64) -> () {
@@ -1208,6 +1698,7 @@ impl PeerNetwork {
 
             let mut parent_block_header_opt = None;
             let mut parent_consensus_hash_opt = None;
+            let mut miner_pubkey_hash_opt = None;
 
             let index_block_hash =
                 StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
@@ -1248,6 +1739,8 @@ impl PeerNetwork {
                     &index_block_hash
                 );
 
+                miner_pubkey_hash_opt =
+                    PeerNetwork::get_miner_pubkey_hash(sortdb, &consensus_hash)?;
                 (consensus_hash, block_hash)
             } else {
                 // asking for microblocks
@@ -1386,6 +1879,18 @@ impl PeerNetwork {
             let block_urls: HashSet<UrlString> = HashSet::new();
             (&mut neighbors[..]).shuffle(&mut thread_rng());
 
+            // prefer data URL hosts that have served us well before.  The sort is stable, so this
+            // only breaks ties in the miner-specific ranking below.
+            neighbors.sort_by_key(|nk| match self.get_data_url(nk) {
+                Some(data_url) => self.data_url_stats.rank_key(&data_url),
+                None => (u64::MAX, u64::MAX),
+            });
+
+            // ask the neighbors who have gotten this miner's blocks to us soonest first
+            if let Some(miner_pubkey_hash) = miner_pubkey_hash_opt.as_ref() {
+                downloader.rank_neighbors_for_miner(miner_pubkey_hash, &mut neighbors);
+            }
+
             let mut requests = VecDeque::new();
             for nk in neighbors.drain(..) {
                 let data_url = match self.get_data_url(&nk) {
@@ -1456,6 +1961,7 @@ impl PeerNetwork {
                     } else {
                         BlockRequestKeyKind::Block
                     },
+                    miner_pubkey_hash_opt.clone(),
                 );
                 requests.push_back(request);
             }
@@ -1466,6 +1972,36 @@ impl PeerNetwork {
         Ok(blocks_to_try)
     }
 
+    /// Identify the miner that won the sortition with the given consensus hash, by the hash of
+    /// the public key(s) that signed its block-commit.
+    pub fn get_miner_pubkey_hash(
+        sortdb: &SortitionDB,
+        consensus_hash: &ConsensusHash,
+    ) -> Result<Option<Hash160>, net_error> {
+        let sn = match SortitionDB::get_block_snapshot_consensus(sortdb.conn(), consensus_hash)? {
+            Some(sn) => sn,
+            None => {
+                return Ok(None);
+            }
+        };
+        if !sn.sortition {
+            return Ok(None);
+        }
+        let block_commit = match SortitionDB::get_block_commit(
+            sortdb.conn(),
+            &sn.winning_block_txid,
+            &sn.sortition_id,
+        )? {
+            Some(block_commit) => block_commit,
+            None => {
+                return Ok(None);
+            }
+        };
+        Ok(Hash160::from_bytes(
+            &block_commit.apparent_sender.to_address_bits(),
+        ))
+    }
+
     /// Make requests for missing anchored blocks
     fn make_block_requests(
         &mut self,
@@ -1504,6 +2040,163 @@ impl PeerNetwork {
         ordered.iter().map(|(ref h, _)| *h).collect()
     }
 
+    /// Are there operator-requested block fetches waiting for the downloader?
+    fn has_queued_block_fetches(&self) -> bool {
+        self.block_fetches
+            .values()
+            .any(|fetch| fetch.status == BlockFetchStatus::Queued)
+    }
+
+    /// Queue up request keys for each operator-requested block fetch, ahead of the downloader's
+    /// own scan.  A fetch is only ever sent to peers whose inventories say they have the block (or
+    /// stream), and is tried even if the block was requested recently.
+    /// Must be called before the downloader's DNS lookups begin, since that's when the data URLs
+    /// to use are resolved.
+    pub fn block_fetches_begin(
+        &mut self,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+    ) -> Result<(), net_error> {
+        let now = get_epoch_time_secs();
+        self.block_fetches.retain(|_, fetch| {
+            fetch.status == BlockFetchStatus::Queued
+                || fetch.status == BlockFetchStatus::InFlight
+                || fetch.updated_at + BLOCK_FETCH_STATUS_LIFETIME >= now
+        });
+
+        let queued: Vec<(StacksBlockId, bool)> = self
+            .block_fetches
+            .iter()
+            .filter(|(_, fetch)| fetch.status == BlockFetchStatus::Queued)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        if queued.len() == 0 {
+            return Ok(());
+        }
+
+        PeerNetwork::with_downloader_state(self, |ref mut network, ref mut downloader| {
+            for (index_block_hash, microblocks) in queued.into_iter() {
+                let sortition_height_opt = SortitionDB::find_block_snapshot_for_stacks_block(
+                    sortdb.conn(),
+                    &index_block_hash,
+                )?
+                .map(|sn| sn.block_height - sortdb.first_block_height);
+
+                let (status, data_urls) = match sortition_height_opt {
+                    Some(sortition_height) => {
+                        // don't let a recent request for this block stop us from asking again
+                        if microblocks {
+                            downloader.requested_microblocks.remove(&index_block_hash);
+                        } else {
+                            downloader.requested_blocks.remove(&index_block_hash);
+                        }
+
+                        let to_try = if microblocks {
+                            &downloader.microblocks_to_try
+                        } else {
+                            &downloader.blocks_to_try
+                        };
+                        let mut requests = match to_try.get(&sortition_height) {
+                            Some(requests)
+                                if requests
+                                    .front()
+                                    .map(|key| key.index_block_hash == index_block_hash)
+                                    .unwrap_or(false) =>
+                            {
+                                // already queued by the downloader's own scan
+                                requests.clone()
+                            }
+                            _ => network
+                                .make_requests(
+                                    sortdb,
+                                    chainstate,
+                                    downloader,
+                                    sortition_height,
+                                    microblocks,
+                                )?
+                                .remove(&sortition_height)
+                                .unwrap_or(VecDeque::new()),
+                        };
+                        requests.retain(|key| key.index_block_hash == index_block_hash);
+
+                        if requests.len() > 0 {
+                            let data_urls = requests
+                                .iter()
+                                .map(|key| key.data_url.to_string())
+                                .collect();
+
+                            debug!(
+                                "{:?}: fetch {} {} at sortition height {} from {} peer(s)",
+                                &network.local_peer,
+                                if microblocks {
+                                    "microblocks confirmed by"
+                                } else {
+                                    "anchored block"
+                                },
+                                &index_block_hash,
+                                sortition_height,
+                                requests.len()
+                            );
+                            if microblocks {
+                                downloader
+                                    .microblocks_to_try
+                                    .insert(sortition_height, requests);
+                            } else {
+                                downloader.blocks_to_try.insert(sortition_height, requests);
+                            }
+                            (BlockFetchStatus::InFlight, data_urls)
+                        } else {
+                            (BlockFetchStatus::Unavailable, vec![])
+                        }
+                    }
+                    None => (BlockFetchStatus::Unavailable, vec![]),
+                };
+
+                if let Some(fetch) = network
+                    .block_fetches
+                    .get_mut(&(index_block_hash.clone(), microblocks))
+                {
+                    if status == BlockFetchStatus::Unavailable {
+                        debug!(
+                            "{:?}: cannot fetch {} (microblocks: {}): no peer has it",
+                            &network.local_peer, &index_block_hash, microblocks
+                        );
+                    }
+                    fetch.status = status;
+                    fetch.sortition_height = sortition_height_opt;
+                    fetch.data_urls = data_urls;
+                    fetch.updated_at = now;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Record the outcome of each in-flight operator-requested block fetch, once the downloader
+    /// has finished (or given up on) the requests at its sortition height.
+    fn finish_block_fetches(&mut self, downloader: &BlockDownloader) {
+        let now = get_epoch_time_secs();
+        for ((_, microblocks), fetch) in self.block_fetches.iter_mut() {
+            if fetch.status != BlockFetchStatus::InFlight {
+                continue;
+            }
+            let to_try = if *microblocks {
+                &downloader.microblocks_to_try
+            } else {
+                &downloader.blocks_to_try
+            };
+            let pending = fetch
+                .sortition_height
+                .map(|height| to_try.contains_key(&height))
+                .unwrap_or(false);
+            if !pending {
+                fetch.status = BlockFetchStatus::Failed;
+                fetch.updated_at = now;
+            }
+        }
+    }
+
     /// Go start resolving block URLs to their IP addresses
     pub fn block_dns_lookups_begin(
         &mut self,
@@ -1948,6 +2641,35 @@ impl PeerNetwork {
         None
     }
 
+    /// Start a block or microblock request, like `begin_request()`, but without exceeding the
+    /// downloader's limit on how many distinct data URLs it downloads from at once.  Once
+    /// `used_urls` is at the limit, only keys for those URLs are tried; the others are left in
+    /// `keys`.
+    fn begin_peer_capped_request(
+        network: &mut PeerNetwork,
+        dns_lookups: &HashMap<UrlString, Option<Vec<SocketAddr>>>,
+        keys: &mut VecDeque<BlockRequestKey>,
+        used_urls: &mut HashSet<UrlString>,
+        max_download_peers: u64,
+        chainstate: &mut StacksChainState,
+    ) -> Option<(BlockRequestKey, usize)> {
+        if max_download_peers == 0 || (used_urls.len() as u64) < max_download_peers {
+            let res = PeerNetwork::begin_request(network, dns_lookups, keys, chainstate);
+            if let Some((ref key, _)) = res {
+                used_urls.insert(key.data_url.clone());
+            }
+            return res;
+        }
+
+        let (mut allowed, deferred): (VecDeque<_>, VecDeque<_>) = keys
+            .drain(..)
+            .partition(|key| used_urls.contains(&key.data_url));
+        let res = PeerNetwork::begin_request(network, dns_lookups, &mut allowed, chainstate);
+        keys.extend(allowed);
+        keys.extend(deferred);
+        res
+    }
+
     /// Start fetching blocks
     pub fn block_getblocks_begin(
         &mut self,
@@ -1955,15 +2677,28 @@ impl PeerNetwork {
     ) -> Result<(), net_error> {
         test_debug!("{:?}: block_getblocks_begin", &self.local_peer);
         PeerNetwork::with_downloader_state(self, |ref mut network, ref mut downloader| {
+            if downloader.is_bandwidth_throttled() {
+                debug!(
+                    "{:?}: Throttle block requests: downloading at {} bytes/sec (limit is {})",
+                    &network.local_peer,
+                    downloader.get_download_bandwidth(),
+                    downloader.max_download_bandwidth
+                );
+                return Ok(());
+            }
+
             let mut priority = PeerNetwork::prioritize_requests(&downloader.blocks_to_try);
             let mut requests = HashMap::new();
+            let mut used_urls = HashSet::new();
             for sortition_height in priority.drain(..) {
                 match downloader.blocks_to_try.get_mut(&sortition_height) {
                     Some(ref mut keys) => {
-                        match PeerNetwork::begin_request(
+                        match PeerNetwork::begin_peer_capped_request(
                             network,
                             &downloader.dns_lookups,
                             keys,
+                            &mut used_urls,
+                            downloader.max_download_peers,
                             chainstate,
                         ) {
                             Some((key, handle)) => {
@@ -2001,15 +2736,28 @@ impl PeerNetwork {
     ) -> Result<(), net_error> {
         test_debug!("{:?}: block_getmicroblocks_begin", &self.local_peer);
         PeerNetwork::with_downloader_state(self, |ref mut network, ref mut downloader| {
+            if downloader.is_bandwidth_throttled() {
+                debug!(
+                    "{:?}: Throttle microblock requests: downloading at {} bytes/sec (limit is {})",
+                    &network.local_peer,
+                    downloader.get_download_bandwidth(),
+                    downloader.max_download_bandwidth
+                );
+                return Ok(());
+            }
+
             let mut priority = PeerNetwork::prioritize_requests(&downloader.microblocks_to_try);
             let mut requests = HashMap::new();
+            let mut used_urls = HashSet::new();
             for sortition_height in priority.drain(..) {
                 match downloader.microblocks_to_try.get_mut(&sortition_height) {
                     Some(ref mut keys) => {
-                        match PeerNetwork::begin_request(
+                        match PeerNetwork::begin_peer_capped_request(
                             network,
                             &downloader.dns_lookups,
                             keys,
+                            &mut used_urls,
+                            downloader.max_download_peers,
                             chainstate,
                         ) {
                             Some((key, handle)) => {
@@ -2088,6 +2836,14 @@ impl PeerNetwork {
                 ));
                 downloader.num_blocks_downloaded += 1;
 
+                if let Some(fetch) = network
+                    .block_fetches
+                    .get_mut(&(request_key.index_block_hash.clone(), false))
+                {
+                    fetch.status = BlockFetchStatus::Fetched;
+                    fetch.updated_at = now;
+                }
+
                 // don't try this again
                 downloader
                     .blocks_to_try
@@ -2136,6 +2892,14 @@ impl PeerNetwork {
                         now.saturating_sub(request_key.download_start),
                     ));
                     downloader.num_microblocks_downloaded += 1;
+
+                    if let Some(fetch) = network
+                        .block_fetches
+                        .get_mut(&(request_key.index_block_hash.clone(), true))
+                    {
+                        fetch.status = BlockFetchStatus::Fetched;
+                        fetch.updated_at = now;
+                    }
                 } else {
                     // stream is not well-formed
                     debug!(
@@ -2175,6 +2939,8 @@ impl PeerNetwork {
                 downloader.microblocks_to_try.remove(&height);
             }
 
+            network.finish_block_fetches(downloader);
+
             debug!(
                 "Blocks to try: {}; Microblocks to try: {}",
                 downloader.blocks_to_try.len(),
@@ -2293,11 +3059,52 @@ impl PeerNetwork {
 
     /// Initialize the downloader
     pub fn init_block_downloader(&mut self) -> () {
-        self.block_downloader = Some(BlockDownloader::new(
+        let mut downloader = BlockDownloader::new(
             self.connection_opts.dns_timeout,
             self.connection_opts.download_interval,
             self.connection_opts.max_inflight_blocks,
-        ));
+            self.connection_opts.max_download_peers,
+            self.connection_opts.max_download_bandwidth,
+        );
+
+        // pick up where we left off before we last shut down
+        match PeerDB::get_block_download_state(self.peerdb.conn()) {
+            Ok(Some((block_sortition_height, microblock_sortition_height))) => {
+                debug!(
+                    "{:?}: Resume block download scan at sortition heights {} (blocks), {} (microblocks)",
+                    &self.local_peer, block_sortition_height, microblock_sortition_height
+                );
+                downloader.resume_scan(block_sortition_height, microblock_sortition_height);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!(
+                    "{:?}: Failed to load saved block download state: {:?}",
+                    &self.local_peer, &e
+                );
+            }
+        }
+        self.block_downloader = Some(downloader);
+    }
+
+    /// Save the downloader's scan position, so a restarted node can resume downloading blocks
+    /// where it left off.
+    pub fn save_block_download_state(&mut self) -> Result<(), net_error> {
+        let (block_sortition_height, microblock_sortition_height) = match self.block_downloader {
+            Some(ref downloader) => downloader.get_scan_heights(),
+            None => {
+                return Ok(());
+            }
+        };
+
+        let mut tx = self.peerdb.tx_begin()?;
+        PeerDB::set_block_download_state(
+            &mut tx,
+            block_sortition_height,
+            microblock_sortition_height,
+        )?;
+        tx.commit()?;
+        Ok(())
     }
 
     /// Initialize the attachment downloader
@@ -2348,10 +3155,13 @@ impl PeerNetwork {
             inv_start_sortition = inv_state.block_sortition_start;
         }
 
+        let have_block_fetches = self.has_queued_block_fetches();
+
         match self.block_downloader {
             Some(ref mut downloader) => {
                 if downloader.empty_block_download_passes > 0
                     && downloader.empty_microblock_download_passes > 0
+                    && !have_block_fetches
                 {
                     if downloader.last_inv_update_at == last_inv_update_at
                         && downloader.finished_scan_at + downloader.download_interval
@@ -2397,6 +3207,7 @@ impl PeerNetwork {
             debug!("{:?}: Download state is {:?}", &self.local_peer, &dlstate);
             match dlstate {
                 BlockDownloaderState::DNSLookupBegin => {
+                    self.block_fetches_begin(sortdb, chainstate)?;
                     self.block_dns_lookups_begin(sortdb, chainstate, dns_client)?;
                 }
                 BlockDownloaderState::DNSLookupFinish => {
@@ -2669,6 +3480,221 @@ pub mod test {
         })
     }
 
+    #[test]
+    fn test_rank_neighbors_for_miner() {
+        let mut downloader = BlockDownloader::new(0, 0, 4, 0, 0);
+
+        let miner = Hash160([0x11; 20]);
+        let other_miner = Hash160([0x22; 20]);
+        let neighbors: Vec<NeighborKey> = (0..4)
+            .map(|i| NeighborKey {
+                peer_version: 0x12345678,
+                network_id: 0x9abcdef0,
+                addrbytes: PeerAddress([i as u8; 16]),
+                port: 20444 + i,
+            })
+            .collect();
+
+        // neighbor 2 is one hop from the miner, neighbor 1 is two hops away
+        downloader.record_miner_relay_hops(&miner, &neighbors[1], 3);
+        downloader.record_miner_relay_hops(&miner, &neighbors[1], 2);
+        downloader.record_miner_relay_hops(&miner, &neighbors[2], 1);
+
+        // neighbor 3 is the fastest of the two whose distance we don't know
+        downloader.record_miner_block_latency(&miner, &neighbors[3], 100);
+        downloader.record_miner_block_latency(&miner, &neighbors[0], 1000);
+        downloader.record_miner_block_latency(&miner, &neighbors[0], 200);
+
+        assert_eq!(
+            downloader
+                .get_miner_delivery_stats(&miner, &neighbors[1])
+                .unwrap()
+                .relay_hops,
+            Some(2)
+        );
+        assert_eq!(
+            downloader
+                .get_miner_delivery_stats(&miner, &neighbors[0])
+                .unwrap()
+                .latency_ms,
+            Some(800)
+        );
+
+        let mut ranked = neighbors.clone();
+        downloader.rank_neighbors_for_miner(&miner, &mut ranked);
+        assert_eq!(
+            ranked,
+            vec![
+                neighbors[2].clone(),
+                neighbors[1].clone(),
+                neighbors[3].clone(),
+                neighbors[0].clone()
+            ]
+        );
+
+        // no stats for this miner, so order is unchanged
+        let mut unranked = neighbors.clone();
+        downloader.rank_neighbors_for_miner(&other_miner, &mut unranked);
+        assert_eq!(unranked, neighbors);
+
+        // broken neighbors are forgotten
+        downloader.broken_neighbors.push(neighbors[2].clone());
+        downloader.clear_broken_peers();
+        assert!(downloader
+            .get_miner_delivery_stats(&miner, &neighbors[2])
+            .is_none());
+    }
+
+    #[test]
+    fn test_data_url_stats() {
+        let mut stats = DataUrlStats::new();
+
+        let hub_url = UrlString::try_from("http://hub.example.com/blocks").unwrap();
+        let other_hub_url = UrlString::try_from("http://hub.example.com:80/data").unwrap();
+        let fast_url = UrlString::try_from("http://127.0.0.1:20443").unwrap();
+        let slow_url = UrlString::try_from("http://127.0.0.2:20443").unwrap();
+        let new_url = UrlString::try_from("http://127.0.0.3:20443").unwrap();
+
+        // URLs on the same host share a score
+        stats.record_success(&hub_url, 100);
+        stats.record_failure(&other_hub_url);
+        let hub_stats = stats.get_host_stats(&hub_url).unwrap().clone();
+        assert_eq!(hub_stats.requests_sent, 2);
+        assert_eq!(hub_stats.requests_succeeded, 1);
+        assert_eq!(hub_stats.success_permille(), 500);
+        assert_eq!(hub_stats.avg_latency_ms(), Some(100));
+        assert_eq!(stats.get_host_stats(&other_hub_url), Some(&hub_stats));
+
+        stats.record_success(&fast_url, 10);
+        stats.record_success(&fast_url, 30);
+        stats.record_success(&slow_url, 500);
+
+        assert_eq!(stats.rank_key(&fast_url), (0, 20));
+        assert_eq!(stats.rank_key(&slow_url), (0, 500));
+        assert_eq!(stats.rank_key(&new_url), (500, u64::MAX));
+
+        let mut urls = vec![
+            new_url.clone(),
+            hub_url.clone(),
+            slow_url.clone(),
+            fast_url.clone(),
+        ];
+        urls.sort_by_key(|url| stats.rank_key(url));
+        assert_eq!(urls, vec![fast_url.clone(), slow_url, hub_url, new_url]);
+
+        // old history counts for less once there's enough of it
+        for _ in 0..DATA_URL_STATS_MAX_SAMPLES {
+            stats.record_failure(&fast_url);
+        }
+        let fast_stats = stats.get_host_stats(&fast_url).unwrap();
+        assert!(fast_stats.requests_sent < DATA_URL_STATS_MAX_SAMPLES);
+        assert_eq!(fast_stats.requests_succeeded, 1);
+        assert!(fast_stats.success_permille() < 20);
+    }
+
+    #[test]
+    fn test_data_url_health_reports() {
+        let mut stats = DataUrlStats::new();
+        let now = get_epoch_time_secs();
+
+        let dead_url = UrlString::try_from("http://127.0.0.1:20443").unwrap();
+        let dead_hub_url = UrlString::try_from("http://hub.example.com/blocks").unwrap();
+        let live_url = UrlString::try_from("http://127.0.0.2:20443").unwrap();
+        let new_url = UrlString::try_from("http://127.0.0.3:20443").unwrap();
+
+        // only hosts that failed enough requests in a row are reported
+        for _ in 0..(DATA_URL_UNREACHABLE_FAILURES - 1) {
+            stats.record_failure(&dead_url);
+            stats.record_failure(&dead_hub_url);
+        }
+        stats.record_success(&live_url, 10);
+        assert_eq!(stats.get_unreachable_urls(now, 16).len(), 0);
+
+        stats.record_failure(&dead_url);
+        stats.record_failure(&dead_hub_url);
+        stats.record_failure(&live_url);
+        let unreachable = stats.get_unreachable_urls(now, 16);
+        assert_eq!(unreachable.len(), 2);
+        assert!(unreachable.contains(&dead_url));
+        assert!(unreachable.contains(&dead_hub_url));
+        assert_eq!(stats.get_unreachable_urls(now, 1).len(), 1);
+
+        // stale failures aren't reported
+        assert_eq!(
+            stats
+                .get_unreachable_urls(now + DATA_URL_HEALTH_REPORT_TTL + 60, 16)
+                .len(),
+            0
+        );
+
+        // a success resets the count
+        stats.record_success(&dead_hub_url, 10);
+        assert_eq!(stats.get_unreachable_urls(now, 16), vec![dead_url.clone()]);
+
+        // one network's reports don't move the ranking, no matter how often they're sent
+        assert_eq!(stats.rank_key(&new_url), (500, u64::MAX));
+        stats.record_unreachable_reports([10, 1, 0, 0], &[new_url.clone()], now);
+        stats.record_unreachable_reports([10, 1, 0, 0], &[new_url.clone()], now);
+        assert_eq!(stats.rank_key(&new_url), (500, u64::MAX));
+
+        // a second network's does
+        stats.record_unreachable_reports([10, 2, 0, 0], &[new_url.clone()], now);
+        assert_eq!(
+            stats.rank_key(&new_url),
+            (DATA_URL_REPORTED_UNREACHABLE_RANK, u64::MAX)
+        );
+
+        // a later report from a network replaces its earlier one
+        stats.record_unreachable_reports([10, 2, 0, 0], &[dead_url.clone()], now);
+        assert_eq!(stats.rank_key(&new_url), (500, u64::MAX));
+
+        // reports don't improve the ranking of a host that failed us
+        stats.record_unreachable_reports([10, 1, 0, 0], &[dead_url.clone()], now);
+        assert_eq!(stats.rank_key(&dead_url), (1000, u64::MAX));
+
+        // our own success outweighs our peers' reports
+        stats.record_unreachable_reports([10, 1, 0, 0], &[live_url.clone()], now - 1);
+        stats.record_unreachable_reports([10, 2, 0, 0], &[live_url.clone()], now - 1);
+        assert_eq!(
+            stats
+                .get_host_stats(&live_url)
+                .unwrap()
+                .num_unreachable_reports(now),
+            0
+        );
+        assert!(stats.rank_key(&live_url).0 < DATA_URL_REPORTED_UNREACHABLE_RANK);
+
+        // hosts only known from expired reports are forgotten
+        stats.record_unreachable_reports([10, 3, 0, 0], &[new_url.clone()], now);
+        assert!(stats.get_host_stats(&new_url).is_some());
+        stats.record_unreachable_reports([10, 4, 0, 0], &[], now + DATA_URL_HEALTH_REPORT_TTL + 1);
+        assert!(stats.get_host_stats(&new_url).is_none());
+        assert!(stats.get_host_stats(&dead_url).is_some());
+    }
+
+    #[test]
+    fn test_download_bandwidth_throttle() {
+        let mut unlimited = BlockDownloader::new(0, 0, 4, 0, 0);
+        unlimited.record_download_bytes(1_000_000);
+        assert_eq!(
+            unlimited.get_download_bandwidth(),
+            1_000_000 / BLOCK_DOWNLOAD_BANDWIDTH_WINDOW
+        );
+        assert!(!unlimited.is_bandwidth_throttled());
+
+        let mut downloader = BlockDownloader::new(0, 0, 4, 0, 1000);
+        assert_eq!(downloader.get_download_bandwidth(), 0);
+        assert!(!downloader.is_bandwidth_throttled());
+
+        downloader.record_download_bytes(5 * BLOCK_DOWNLOAD_BANDWIDTH_WINDOW * 100);
+        assert_eq!(downloader.get_download_bandwidth(), 500);
+        assert!(!downloader.is_bandwidth_throttled());
+
+        downloader.record_download_bytes(10 * BLOCK_DOWNLOAD_BANDWIDTH_WINDOW * 100);
+        assert_eq!(downloader.get_download_bandwidth(), 1500);
+        assert!(downloader.is_bandwidth_throttled());
+    }
+
     fn get_blocks_inventory(
         peer: &mut TestPeer,
         start_height: u64,
@@ -0,0 +1,276 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use net::connection::ConnectionOptions;
+use net::ServiceFlags;
+use net::{RPCNodeFeature, RPCNodeFeatures};
+
+/// Where a feature is switched on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeFeatureSource {
+    /// a cargo feature of the build
+    Build,
+    /// the node's config file
+    Config,
+}
+
+impl NodeFeatureSource {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            NodeFeatureSource::Build => "build",
+            NodeFeatureSource::Config => "config",
+        }
+    }
+}
+
+/// Registry of the optional capabilities of a node.  Code paths that depend on one of these
+/// should check `NodeFeatures::is_enabled()` rather than the service bits or config options
+/// behind it, so that what the node does and what it reports on `GET /v2/features` agree.
+///
+/// | Feature              | Source | Enabled when                                                |
+/// |----------------------|--------|-------------------------------------------------------------|
+/// | `atlas`              | config | `atlas` is in `node.services`                               |
+/// | `archival`           | config | `archival` is in `node.services`                            |
+/// | `compact-blocks`     | config | `compact-blocks` is in `node.services`                      |
+/// | `attachments-batch`  | config | `attachments-batch` is in `node.services`                   |
+/// | `compression`        | config | `compression` is in `node.services`                         |
+/// | `paged-blocks-inv`   | config | `paged-blocks-inv` is in `node.services`                    |
+/// | `microblock-push`    | config | `microblock-push` is in `node.services`                     |
+/// | `tls`                | config | `tls` is in `node.services` and `p2p_tls_cert_file` is set  |
+/// | `attachment-ranges`  | config | `attachment-ranges` is in `node.services`                   |
+/// | `mempool-sync`       | config | never: not implemented yet                                  |
+/// | `tip-subscribe`      | config | never: not implemented yet                                  |
+/// | `tx-index`           | config | never: not implemented yet                                  |
+/// | `admin-rpc`          | config | `connection_options.enable_admin_rpc` is set                |
+/// | `prometheus`         | build  | built with `monitoring_prom`                                |
+/// | `zonefile`           | build  | built with `zonefile`                                       |
+/// | `clarity-extensions` | build  | built with `clarity_extensions`                             |
+/// | `async-net`          | build  | built with `async_net`                                      |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeFeature {
+    Atlas,
+    Archival,
+    CompactBlocks,
+    AttachmentsBatch,
+    Compression,
+    PagedBlocksInv,
+    MicroblockPush,
+    Tls,
+    AttachmentRanges,
+    MempoolSync,
+    TipSubscribe,
+    TxIndex,
+    AdminRpc,
+    Prometheus,
+    Zonefile,
+    ClarityExtensions,
+    AsyncNet,
+}
+
+/// Every registered feature, in the order they are reported
+pub const NODE_FEATURES_REGISTRY: &[NodeFeature] = &[
+    NodeFeature::Atlas,
+    NodeFeature::Archival,
+    NodeFeature::CompactBlocks,
+    NodeFeature::AttachmentsBatch,
+    NodeFeature::Compression,
+    NodeFeature::PagedBlocksInv,
+    NodeFeature::MicroblockPush,
+    NodeFeature::Tls,
+    NodeFeature::AttachmentRanges,
+    NodeFeature::MempoolSync,
+    NodeFeature::TipSubscribe,
+    NodeFeature::TxIndex,
+    NodeFeature::AdminRpc,
+    NodeFeature::Prometheus,
+    NodeFeature::Zonefile,
+    NodeFeature::ClarityExtensions,
+    NodeFeature::AsyncNet,
+];
+
+impl NodeFeature {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            NodeFeature::Atlas => "atlas",
+            NodeFeature::Archival => "archival",
+            NodeFeature::CompactBlocks => "compact-blocks",
+            NodeFeature::AttachmentsBatch => "attachments-batch",
+            NodeFeature::Compression => "compression",
+            NodeFeature::PagedBlocksInv => "paged-blocks-inv",
+            NodeFeature::MicroblockPush => "microblock-push",
+            NodeFeature::Tls => "tls",
+            NodeFeature::AttachmentRanges => "attachment-ranges",
+            NodeFeature::MempoolSync => "mempool-sync",
+            NodeFeature::TipSubscribe => "tip-subscribe",
+            NodeFeature::TxIndex => "tx-index",
+            NodeFeature::AdminRpc => "admin-rpc",
+            NodeFeature::Prometheus => "prometheus",
+            NodeFeature::Zonefile => "zonefile",
+            NodeFeature::ClarityExtensions => "clarity-extensions",
+            NodeFeature::AsyncNet => "async-net",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<NodeFeature> {
+        NODE_FEATURES_REGISTRY
+            .iter()
+            .find(|feature| feature.name() == name)
+            .cloned()
+    }
+
+    pub fn source(&self) -> NodeFeatureSource {
+        match *self {
+            NodeFeature::Prometheus
+            | NodeFeature::Zonefile
+            | NodeFeature::ClarityExtensions
+            | NodeFeature::AsyncNet => NodeFeatureSource::Build,
+            _ => NodeFeatureSource::Config,
+        }
+    }
+
+    /// The service bit that advertises this feature to peers, if any
+    pub fn service_flag(&self) -> Option<ServiceFlags> {
+        match *self {
+            NodeFeature::Atlas => Some(ServiceFlags::ATLAS),
+            NodeFeature::Archival => Some(ServiceFlags::ARCHIVAL),
+            NodeFeature::CompactBlocks => Some(ServiceFlags::COMPACT_BLOCKS),
+            NodeFeature::AttachmentsBatch => Some(ServiceFlags::ATTACHMENTS_BATCH),
+            NodeFeature::Compression => Some(ServiceFlags::COMPRESSION),
+            NodeFeature::PagedBlocksInv => Some(ServiceFlags::PAGED_BLOCKS_INV),
+            NodeFeature::MicroblockPush => Some(ServiceFlags::MICROBLOCK_PUSH),
+            NodeFeature::Tls => Some(ServiceFlags::TLS),
+            NodeFeature::AttachmentRanges => Some(ServiceFlags::ATTACHMENT_RANGES),
+            NodeFeature::MempoolSync => Some(ServiceFlags::MEMPOOL_SYNC),
+            NodeFeature::TipSubscribe => Some(ServiceFlags::TIP_SUBSCRIBE),
+            _ => None,
+        }
+    }
+
+    /// Can this build of the node do this at all?
+    pub fn is_available(&self) -> bool {
+        match *self {
+            NodeFeature::MempoolSync | NodeFeature::TipSubscribe | NodeFeature::TxIndex => false,
+            NodeFeature::Prometheus => cfg!(feature = "monitoring_prom"),
+            NodeFeature::Zonefile => cfg!(feature = "zonefile"),
+            NodeFeature::ClarityExtensions => cfg!(feature = "clarity_extensions"),
+            NodeFeature::AsyncNet => cfg!(feature = "async_net"),
+            _ => true,
+        }
+    }
+}
+
+/// The features a running node has switched on
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeFeatures {
+    /// the service bits the node advertises, after its network mode is applied
+    services: u16,
+    admin_rpc: bool,
+}
+
+impl NodeFeatures {
+    pub fn new(services: u16, connection_opts: &ConnectionOptions) -> NodeFeatures {
+        NodeFeatures {
+            services: connection_opts.filter_services(services),
+            admin_rpc: connection_opts.enable_admin_rpc,
+        }
+    }
+
+    pub fn is_enabled(&self, feature: NodeFeature) -> bool {
+        if !feature.is_available() {
+            return false;
+        }
+        match feature {
+            NodeFeature::AdminRpc => self.admin_rpc,
+            _ => match feature.service_flag() {
+                Some(flag) => flag.is_set(self.services),
+                None => true,
+            },
+        }
+    }
+
+    /// Every registered feature and whether or not it is switched on, as served on
+    /// `GET /v2/features`
+    pub fn to_rpc(&self) -> RPCNodeFeatures {
+        let features = NODE_FEATURES_REGISTRY
+            .iter()
+            .map(|feature| RPCNodeFeature {
+                name: feature.name().to_string(),
+                source: feature.source().as_str().to_string(),
+                available: feature.is_available(),
+                enabled: self.is_enabled(*feature),
+                service_bit: feature.service_flag().map(|flag| flag.bit()),
+            })
+            .collect();
+        RPCNodeFeatures { features }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use net::connection::NetworkMode;
+
+    #[test]
+    fn test_node_features() {
+        for feature in NODE_FEATURES_REGISTRY.iter() {
+            assert_eq!(NodeFeature::from_name(feature.name()), Some(*feature));
+        }
+        assert_eq!(NodeFeature::from_name("bogus"), None);
+
+        let services = ServiceFlags::to_services(&[
+            ServiceFlags::RELAY,
+            ServiceFlags::RPC,
+            ServiceFlags::ATLAS,
+            ServiceFlags::COMPACT_BLOCKS,
+            ServiceFlags::MEMPOOL_SYNC,
+            ServiceFlags::TLS,
+        ]);
+        let mut connection_opts = ConnectionOptions::default();
+        let features = NodeFeatures::new(services, &connection_opts);
+        assert!(features.is_enabled(NodeFeature::Atlas));
+        assert!(features.is_enabled(NodeFeature::CompactBlocks));
+        assert!(!features.is_enabled(NodeFeature::Archival));
+        assert!(!features.is_enabled(NodeFeature::AdminRpc));
+        // advertised, but not implemented
+        assert!(!features.is_enabled(NodeFeature::MempoolSync));
+        assert_eq!(
+            features.is_enabled(NodeFeature::Zonefile),
+            cfg!(feature = "zonefile")
+        );
+        // advertised, but there's no certificate to use
+        assert!(!features.is_enabled(NodeFeature::Tls));
+
+        connection_opts.p2p_tls_cert_file = Some("/path/to/cert.pem".to_string());
+        let features = NodeFeatures::new(services, &connection_opts);
+        assert!(features.is_enabled(NodeFeature::Tls));
+
+        // a leecher serves no attachments, whatever its config says
+        connection_opts.network_mode = NetworkMode::Leecher;
+        connection_opts.enable_admin_rpc = true;
+        let features = NodeFeatures::new(services, &connection_opts);
+        assert!(!features.is_enabled(NodeFeature::Atlas));
+        assert!(features.is_enabled(NodeFeature::AdminRpc));
+
+        let rpc_features = features.to_rpc();
+        assert_eq!(rpc_features.features.len(), NODE_FEATURES_REGISTRY.len());
+        let atlas = &rpc_features.features[0];
+        assert_eq!(atlas.name, "atlas");
+        assert_eq!(atlas.source, "config");
+        assert!(atlas.available);
+        assert!(!atlas.enabled);
+        assert_eq!(atlas.service_bit, Some(ServiceFlags::ATLAS.bit()));
+    }
+}
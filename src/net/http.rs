@@ -17,6 +17,7 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt;
@@ -29,6 +30,7 @@ use std::str;
 use std::str::FromStr;
 use std::time::SystemTime;
 
+use chrono::{DateTime, TimeZone, Utc};
 use percent_encoding::percent_decode_str;
 use regex::{Captures, Regex};
 use serde::{Deserialize, Serialize};
@@ -38,13 +40,26 @@ use url::{form_urlencoded, Url};
 
 use burnchains::{Address, Txid};
 use chainstate::burn::ConsensusHash;
-use chainstate::stacks::{StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction};
+use chainstate::stacks::{
+    StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction, MAX_TRANSACTION_LEN,
+};
+use core::mempool::{decode_mempool_snapshot, encode_mempool_snapshot};
 use deps::httparse;
-use net::atlas::Attachment;
+use net::atlas::{Attachment, AttachmentStreamVerifier};
+use net::AtBlock;
+use net::AtlasContractRegistrationRequestBody;
+use net::AttachmentContentRange;
+use net::AttachmentPinRequestBody;
+use net::BlockProcessingRequestBody;
 use net::CallReadOnlyRequestBody;
 use net::ClientError;
 use net::Error as net_error;
 use net::Error::ClarityError;
+use net::ErrorFault;
+use net::EventReplayRequestBody;
+use net::HttpApiDeprecation;
+use net::HttpApiVersion;
+use net::HttpByteRange;
 use net::HttpContentType;
 use net::HttpRequestMetadata;
 use net::HttpRequestPreamble;
@@ -56,17 +71,29 @@ use net::HttpVersion;
 use net::MessageSequence;
 use net::NeighborAddress;
 use net::PeerAddress;
+use net::PeerFilterUpdateRequestBody;
 use net::PeerHost;
 use net::ProtocolFamily;
+use net::QueryJobRequestBody;
+use net::RPCPage;
+use net::RPCPageRequest;
 use net::StacksHttpMessage;
 use net::StacksHttpPreamble;
 use net::UnconfirmedTransactionResponse;
 use net::UnconfirmedTransactionStatus;
+use net::ValidateContractCallRequestBody;
+use net::VerifyContractSrcRequestBody;
 use net::HTTP_PREAMBLE_MAX_ENCODED_SIZE;
 use net::HTTP_PREAMBLE_MAX_NUM_HEADERS;
 use net::HTTP_REQUEST_ID_RESERVED;
+use net::MAX_IDEMPOTENCY_KEY_LEN;
 use net::MAX_MICROBLOCKS_UNCONFIRMED;
-use net::{GetAttachmentResponse, GetAttachmentsInvResponse, PostTransactionRequestBody};
+use net::MAX_NODE_IDENTITY_CHALLENGE_LEN;
+use net::{DecodeClarityValueRequestBody, EncodeClarityValueRequestBody};
+use net::{
+    GetAttachmentResponse, GetAttachmentsBatchResponse, GetAttachmentsInvResponse,
+    PostTransactionRequestBody,
+};
 use util::hash::hex_bytes;
 use util::hash::to_hex;
 use util::hash::Hash160;
@@ -90,17 +117,48 @@ use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId};
 
 lazy_static! {
     static ref PATH_GETINFO: Regex = Regex::new(r#"^/v2/info$"#).unwrap();
+    static ref PATH_GETFEATURES: Regex = Regex::new(r#"^/v2/features$"#).unwrap();
     static ref PATH_GETPOXINFO: Regex = Regex::new(r#"^/v2/pox$"#).unwrap();
+    static ref PATH_GETSTATEHASH: Regex =
+        Regex::new(r#"^/v2/state_hash/([0-9]{1,20})$"#).unwrap();
     static ref PATH_GETNEIGHBORS: Regex = Regex::new(r#"^/v2/neighbors$"#).unwrap();
+    static ref PATH_GET_NEIGHBOR_WALK: Regex = Regex::new(r#"^/v2/neighbors/walk$"#).unwrap();
+    static ref PATH_GET_PEER_VERSIONS: Regex =
+        Regex::new(r#"^/v2/neighbors/versions$"#).unwrap();
+    static ref PATH_GET_NODE_STATUS: Regex = Regex::new(r#"^/v2/status$"#).unwrap();
+    static ref PATH_GET_NODE_IDENTITY: Regex = Regex::new(r#"^/v2/node_identity$"#).unwrap();
     static ref PATH_GETBLOCK: Regex = Regex::new(r#"^/v2/blocks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_INDEXED: Regex =
         Regex::new(r#"^/v2/microblocks/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_CONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/confirmed/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETMICROBLOCKS_FORKS: Regex =
+        Regex::new(r#"^/v2/microblocks/forks(/([0-9a-f]{64}))?$"#).unwrap();
     static ref PATH_GETMICROBLOCKS_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/microblocks/unconfirmed/([0-9a-f]{64})/([0-9]{1,5})$"#).unwrap();
+    static ref PATH_GETMICROBLOCK_PROOF: Regex =
+        Regex::new(r#"^/v2/microblocks/proof/([0-9a-f]{64})$"#).unwrap();
     static ref PATH_GETTRANSACTION_UNCONFIRMED: Regex =
         Regex::new(r#"^/v2/transactions/unconfirmed/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GETMEMPOOL_DROPS: Regex =
+        Regex::new(r#"^/v2/mempool/dropped/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_GET_MEMPOOL_SNAPSHOT: Regex =
+        Regex::new(r#"^/v2/admin/mempool/export$"#).unwrap();
+    static ref PATH_POST_MEMPOOL_SNAPSHOT: Regex =
+        Regex::new(r#"^/v2/admin/mempool/import$"#).unwrap();
+    static ref PATH_GET_EVENT_OBSERVER_STATUS: Regex =
+        Regex::new(r#"^/v2/admin/events/status$"#).unwrap();
+    static ref PATH_EVENT_REPLAYS: Regex = Regex::new(r#"^/v2/admin/events/replay$"#).unwrap();
+    static ref PATH_PEER_FILTERS: Regex = Regex::new(r#"^/v2/admin/peers/filters$"#).unwrap();
+    static ref PATH_ATLAS_CONTRACTS: Regex =
+        Regex::new(r#"^/v2/admin/atlas/contracts$"#).unwrap();
+    static ref PATH_ATTACHMENT_PINS: Regex = Regex::new(r#"^/v2/admin/atlas/pins$"#).unwrap();
+    static ref PATH_BLOCK_PROCESSING: Regex =
+        Regex::new(r#"^/v2/admin/blocks/processing$"#).unwrap();
+    static ref PATH_BLOCK_FETCH: Regex =
+        Regex::new(r#"^/v2/blocks/fetch/([0-9a-f]{64})$"#).unwrap();
+    static ref PATH_POST_QUERY_JOB: Regex = Regex::new(r#"^/v2/jobs$"#).unwrap();
+    static ref PATH_GET_QUERY_JOB: Regex = Regex::new(r#"^/v2/jobs/([0-9]{1,20})$"#).unwrap();
     static ref PATH_POSTTRANSACTION: Regex = Regex::new(r#"^/v2/transactions$"#).unwrap();
     static ref PATH_POSTBLOCK: Regex = Regex::new(r#"^/v2/blocks/upload/([0-9a-f]{40})$"#).unwrap();
     static ref PATH_POSTMICROBLOCK: Regex = Regex::new(r#"^/v2/microblocks$"#).unwrap();
@@ -109,6 +167,21 @@ lazy_static! {
         *PRINCIPAL_DATA_REGEX
     ))
     .unwrap();
+    static ref PATH_GET_ACCOUNT_UNLOCKS: Regex = Regex::new(&format!(
+        "^/v2/accounts/(?P<principal>{})/unlocks$",
+        *PRINCIPAL_DATA_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_ADDRESS_MEMPOOL: Regex = Regex::new(&format!(
+        "^/v2/addresses/(?P<address>{})/mempool$",
+        *STANDARD_PRINCIPAL_REGEX
+    ))
+    .unwrap();
+    static ref PATH_GET_MINER_REWARDS: Regex = Regex::new(&format!(
+        "^/v2/miner_rewards/(?P<address>{})$",
+        *STANDARD_PRINCIPAL_REGEX
+    ))
+    .unwrap();
     static ref PATH_GET_MAP_ENTRY: Regex = Regex::new(&format!(
         "^/v2/map_entry/(?P<address>{})/(?P<contract>{})/(?P<map>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
@@ -129,15 +202,36 @@ lazy_static! {
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
     ))
     .unwrap();
+    static ref PATH_POST_VERIFY_CONTRACT_SRC: Regex = Regex::new(&format!(
+        "^/v2/contracts/verify/(?P<address>{})/(?P<contract>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
+    ))
+    .unwrap();
+    static ref PATH_POST_VALIDATE_CONTRACT_CALL: Regex = Regex::new(&format!(
+        "^/v2/contracts/validate-call/(?P<address>{})/(?P<contract>{})/(?P<function>{})$",
+        *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX, *CLARITY_NAME_REGEX
+    ))
+    .unwrap();
+    static ref PATH_POST_ENCODE_CLARITY_VALUE: Regex =
+        Regex::new("^/v2/clarity/value/encode$").unwrap();
+    static ref PATH_POST_DECODE_CLARITY_VALUE: Regex =
+        Regex::new("^/v2/clarity/value/decode$").unwrap();
     static ref PATH_GET_CONTRACT_ABI: Regex = Regex::new(&format!(
         "^/v2/contracts/interface/(?P<address>{})/(?P<contract>{})$",
         *STANDARD_PRINCIPAL_REGEX, *CONTRACT_NAME_REGEX
     ))
     .unwrap();
     static ref PATH_GET_TRANSFER_COST: Regex = Regex::new("^/v2/fees/transfer$").unwrap();
+    static ref PATH_GET_MEMPOOL_FEES: Regex = Regex::new("^/v2/fees/mempool$").unwrap();
+    static ref PATH_GET_SIMULATE_BLOCK: Regex =
+        Regex::new("^/v2/mempool/simulate_block$").unwrap();
+    static ref PATH_GET_CONTRACT_COSTS: Regex = Regex::new("^/v2/contracts/costs$").unwrap();
     static ref PATH_GET_ATTACHMENTS_INV: Regex = Regex::new("^/v2/attachments/inv$").unwrap();
+    static ref PATH_GET_ATTACHMENTS_BATCH: Regex = Regex::new("^/v2/attachments$").unwrap();
     static ref PATH_GET_ATTACHMENT: Regex =
         Regex::new(r#"^/v2/attachments/([0-9a-f]{40})$"#).unwrap();
+    static ref PATH_GET_ATTACHMENT_CONTENT: Regex =
+        Regex::new(r#"^/v2/attachments/([0-9a-f]{40})/content$"#).unwrap();
     static ref PATH_OPTIONS_WILDCARD: Regex = Regex::new("^/v2/.{0,4096}$").unwrap();
 }
 
@@ -308,7 +402,7 @@ impl HttpChunkedTransferReaderState {
 }
 
 /// read adapter for chunked transfer encoding
-struct HttpChunkedTransferReader<'a, R: Read> {
+pub struct HttpChunkedTransferReader<'a, R: Read> {
     fd: &'a mut R,
     state: HttpChunkedTransferReaderState,
 }
@@ -321,7 +415,7 @@ impl<'a, R: Read> HttpChunkedTransferReader<'a, R> {
         }
     }
 
-    pub fn from_state(
+    fn from_state(
         r: &'a mut R,
         state: HttpChunkedTransferReaderState,
     ) -> HttpChunkedTransferReader<'a, R> {
@@ -871,6 +965,190 @@ fn keep_alive_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata) -> Result
     Ok(())
 }
 
+/// Routes that have been superseded by a newer API version: the route (as given by
+/// `HttpRequestType::get_path()`, under the deprecated version's prefix), the version that
+/// replaces it, and the Unix time after which it may stop being served.
+const HTTP_API_DEPRECATIONS: &[(&str, HttpApiVersion, Option<u64>)] = &[];
+
+impl HttpApiDeprecation {
+    /// Decode the deprecation notice from a response's `Deprecation`, `Link` and `Sunset`
+    /// headers.  There is no notice unless the response says where the successor route is.
+    pub fn from_headers(headers: &HashMap<String, String>) -> Option<HttpApiDeprecation> {
+        if headers.get("deprecation").map(|value| value.as_str()) != Some("true") {
+            return None;
+        }
+        let successor = headers.get("link").and_then(|link| {
+            let mut parts = link.split(';').map(|part| part.trim());
+            let target = parts.next()?;
+            if !target.starts_with('<') || !target.ends_with('>') {
+                return None;
+            }
+            if !parts.any(|param| param == "rel=\"successor-version\"") {
+                return None;
+            }
+            Some(target[1..target.len() - 1].to_string())
+        })?;
+        let sunset = headers
+            .get("sunset")
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.timestamp() as u64);
+        Some(HttpApiDeprecation { successor, sunset })
+    }
+}
+
+impl HttpByteRange {
+    /// Decode a `Range` header.  Returns None for anything but a single range of bytes --
+    /// including multiple ranges and malformed ones -- in which case the header is ignored and
+    /// the whole content is served, as RFC 7233 allows.
+    pub fn from_header(value: &str) -> Option<HttpByteRange> {
+        let spec = value.trim();
+        if !spec.starts_with("bytes=") {
+            return None;
+        }
+        let spec = spec["bytes=".len()..].trim();
+        if spec.contains(',') {
+            return None;
+        }
+        let dash = spec.find('-')?;
+        let (first, last) = (spec[0..dash].trim(), spec[dash + 1..].trim());
+        match (first.len(), last.len()) {
+            (0, 0) => None,
+            (0, _) => last.parse::<u64>().ok().map(HttpByteRange::Suffix),
+            (_, 0) => first.parse::<u64>().ok().map(HttpByteRange::From),
+            (_, _) => {
+                let start = first.parse::<u64>().ok()?;
+                let end = last.parse::<u64>().ok()?;
+                if end < start {
+                    return None;
+                }
+                Some(HttpByteRange::Span(start, end))
+            }
+        }
+    }
+
+    pub fn to_header(&self) -> String {
+        match *self {
+            HttpByteRange::Span(start, end) => format!("bytes={}-{}", start, end),
+            HttpByteRange::From(start) => format!("bytes={}-", start),
+            HttpByteRange::Suffix(len) => format!("bytes=-{}", len),
+        }
+    }
+
+    /// Get the first and last (inclusive) offsets of this range in content of the given
+    /// length, or None if none of the content is in range.
+    pub fn resolve(&self, content_len: u64) -> Option<(u64, u64)> {
+        if content_len == 0 {
+            return None;
+        }
+        match *self {
+            HttpByteRange::Span(start, end) if start < content_len => {
+                Some((start, cmp::min(end, content_len - 1)))
+            }
+            HttpByteRange::From(start) if start < content_len => Some((start, content_len - 1)),
+            HttpByteRange::Suffix(len) if len > 0 => {
+                Some((content_len.saturating_sub(len), content_len - 1))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl AttachmentContentRange {
+    /// Is this less than the whole attachment?  If so, it is sent as a 206 Partial Content.
+    pub fn is_partial(&self) -> bool {
+        self.start > 0 || (self.content.len() as u64) < self.total_len
+    }
+
+    /// Decode the `Content-Range` header of a 206 Partial Content response, and check it
+    /// against the content that came with it.
+    fn from_header(value: &str, content: Vec<u8>) -> Result<AttachmentContentRange, net_error> {
+        let invalid =
+            || net_error::DeserializeError(format!("Invalid Content-Range header: {}", value));
+        let spec = value.trim();
+        if !spec.starts_with("bytes ") {
+            return Err(invalid());
+        }
+        let spec = &spec["bytes ".len()..];
+        let slash = spec.find('/').ok_or_else(invalid)?;
+        let dash = spec[0..slash].find('-').ok_or_else(invalid)?;
+        let start = spec[0..dash].parse::<u64>().map_err(|_| invalid())?;
+        let end = spec[dash + 1..slash]
+            .parse::<u64>()
+            .map_err(|_| invalid())?;
+        let total_len = spec[slash + 1..].parse::<u64>().map_err(|_| invalid())?;
+        if end < start || end >= total_len || end - start + 1 != content.len() as u64 {
+            return Err(invalid());
+        }
+        Ok(AttachmentContentRange {
+            start,
+            total_len,
+            content,
+        })
+    }
+
+    fn to_header(&self) -> String {
+        format!(
+            "bytes {}-{}/{}",
+            self.start,
+            (self.start + self.content.len() as u64).saturating_sub(1),
+            self.total_len
+        )
+    }
+}
+
+/// Tell the client that the route it used is deprecated, and what replaces it
+fn deprecation_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata) -> Result<(), codec_error> {
+    if let Some(ref deprecation) = md.deprecation {
+        fd.write_all("Deprecation: true\r\n".as_bytes())
+            .map_err(codec_error::WriteError)?;
+        fd.write_all(
+            format!(
+                "Link: <{}>; rel=\"successor-version\"\r\n",
+                &deprecation.successor
+            )
+            .as_bytes(),
+        )
+        .map_err(codec_error::WriteError)?;
+        if let Some(sunset) = deprecation.sunset {
+            fd.write_all(format!("Sunset: {}\r\n", rfc7231_date(sunset)).as_bytes())
+                .map_err(codec_error::WriteError)?;
+        }
+    }
+    Ok(())
+}
+
+/// Headers that every response carries, beyond the preamble's own
+fn response_headers<W: Write>(fd: &mut W, md: &HttpResponseMetadata) -> Result<(), codec_error> {
+    keep_alive_headers(fd, md)?;
+    deprecation_headers(fd, md)
+}
+
+fn range_headers<W: Write>(fd: &mut W, range: &Option<HttpByteRange>) -> Result<(), codec_error> {
+    if let Some(ref range) = range {
+        fd.write_all(format!("Range: {}\r\n", range.to_header()).as_bytes())
+            .map_err(codec_error::WriteError)?;
+    }
+    Ok(())
+}
+
+/// An idempotency key is 1 to MAX_IDEMPOTENCY_KEY_LEN visible ASCII characters
+fn is_valid_idempotency_key(key: &str) -> bool {
+    key.len() > 0
+        && key.len() <= MAX_IDEMPOTENCY_KEY_LEN
+        && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn idempotency_key_headers<W: Write>(
+    fd: &mut W,
+    md: &HttpRequestMetadata,
+) -> Result<(), codec_error> {
+    if let Some(ref key) = md.idempotency_key {
+        fd.write_all(format!("Idempotency-Key: {}\r\n", key).as_bytes())
+            .map_err(codec_error::WriteError)?;
+    }
+    Ok(())
+}
+
 fn write_headers<W: Write>(
     fd: &mut W,
     headers: &HashMap<String, String>,
@@ -1095,7 +1373,7 @@ impl HttpResponsePreamble {
             md.content_length.clone(),
             &HttpContentType::JSON,
             md.request_id,
-            |ref mut fd| keep_alive_headers(fd, md),
+            |ref mut fd| response_headers(fd, md),
         )
     }
 
@@ -1250,6 +1528,13 @@ fn rfc7231_now() -> String {
     now.format("%a, %b %-d %-Y %-H:%M:%S GMT")
 }
 
+/// Get the RFC 7231 IMF-fixdate for a Unix time
+fn rfc7231_date(timestamp: u64) -> String {
+    Utc.timestamp(timestamp as i64, 0)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
 impl StacksMessageCodec for HttpResponsePreamble {
     fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
         HttpResponsePreamble::new_serialized(
@@ -1448,12 +1733,42 @@ impl HttpRequestType {
             ) -> Result<HttpRequestType, net_error>,
         )] = &[
             ("GET", &PATH_GETINFO, &HttpRequestType::parse_getinfo),
+            (
+                "GET",
+                &PATH_GETFEATURES,
+                &HttpRequestType::parse_getfeatures,
+            ),
             ("GET", &PATH_GETPOXINFO, &HttpRequestType::parse_getpoxinfo),
+            (
+                "GET",
+                &PATH_GETSTATEHASH,
+                &HttpRequestType::parse_getstatehash,
+            ),
             (
                 "GET",
                 &PATH_GETNEIGHBORS,
                 &HttpRequestType::parse_getneighbors,
             ),
+            (
+                "GET",
+                &PATH_GET_NEIGHBOR_WALK,
+                &HttpRequestType::parse_get_neighbor_walk,
+            ),
+            (
+                "GET",
+                &PATH_GET_PEER_VERSIONS,
+                &HttpRequestType::parse_get_peer_versions,
+            ),
+            (
+                "GET",
+                &PATH_GET_NODE_STATUS,
+                &HttpRequestType::parse_get_node_status,
+            ),
+            (
+                "GET",
+                &PATH_GET_NODE_IDENTITY,
+                &HttpRequestType::parse_get_node_identity,
+            ),
             ("GET", &PATH_GETBLOCK, &HttpRequestType::parse_getblock),
             (
                 "GET",
@@ -1465,6 +1780,11 @@ impl HttpRequestType {
                 &PATH_GETMICROBLOCKS_CONFIRMED,
                 &HttpRequestType::parse_getmicroblocks_confirmed,
             ),
+            (
+                "GET",
+                &PATH_GETMICROBLOCKS_FORKS,
+                &HttpRequestType::parse_getmicroblocks_forks,
+            ),
             (
                 "GET",
                 &PATH_GETMICROBLOCKS_UNCONFIRMED,
@@ -1475,6 +1795,121 @@ impl HttpRequestType {
                 &PATH_GETTRANSACTION_UNCONFIRMED,
                 &HttpRequestType::parse_gettransaction_unconfirmed,
             ),
+            (
+                "GET",
+                &PATH_GETMEMPOOL_DROPS,
+                &HttpRequestType::parse_getmempool_drops,
+            ),
+            (
+                "GET",
+                &PATH_GETMICROBLOCK_PROOF,
+                &HttpRequestType::parse_getmicroblock_proof,
+            ),
+            (
+                "GET",
+                &PATH_GET_ADDRESS_MEMPOOL,
+                &HttpRequestType::parse_get_address_mempool,
+            ),
+            (
+                "GET",
+                &PATH_GET_MEMPOOL_FEES,
+                &HttpRequestType::parse_get_mempool_fees,
+            ),
+            (
+                "GET",
+                &PATH_GET_SIMULATE_BLOCK,
+                &HttpRequestType::parse_get_simulate_block,
+            ),
+            (
+                "GET",
+                &PATH_GET_CONTRACT_COSTS,
+                &HttpRequestType::parse_get_contract_costs,
+            ),
+            (
+                "GET",
+                &PATH_GET_MEMPOOL_SNAPSHOT,
+                &HttpRequestType::parse_get_mempool_snapshot,
+            ),
+            (
+                "POST",
+                &PATH_POST_MEMPOOL_SNAPSHOT,
+                &HttpRequestType::parse_post_mempool_snapshot,
+            ),
+            (
+                "GET",
+                &PATH_GET_EVENT_OBSERVER_STATUS,
+                &HttpRequestType::parse_get_event_observer_status,
+            ),
+            (
+                "GET",
+                &PATH_EVENT_REPLAYS,
+                &HttpRequestType::parse_get_event_replays,
+            ),
+            (
+                "POST",
+                &PATH_EVENT_REPLAYS,
+                &HttpRequestType::parse_post_event_replay,
+            ),
+            (
+                "GET",
+                &PATH_PEER_FILTERS,
+                &HttpRequestType::parse_get_peer_filters,
+            ),
+            (
+                "POST",
+                &PATH_PEER_FILTERS,
+                &HttpRequestType::parse_post_peer_filter,
+            ),
+            (
+                "GET",
+                &PATH_ATLAS_CONTRACTS,
+                &HttpRequestType::parse_get_atlas_contracts,
+            ),
+            (
+                "POST",
+                &PATH_ATLAS_CONTRACTS,
+                &HttpRequestType::parse_post_atlas_contract,
+            ),
+            (
+                "GET",
+                &PATH_ATTACHMENT_PINS,
+                &HttpRequestType::parse_get_attachment_pins,
+            ),
+            (
+                "POST",
+                &PATH_ATTACHMENT_PINS,
+                &HttpRequestType::parse_post_attachment_pin,
+            ),
+            (
+                "GET",
+                &PATH_BLOCK_PROCESSING,
+                &HttpRequestType::parse_get_block_processing,
+            ),
+            (
+                "POST",
+                &PATH_BLOCK_PROCESSING,
+                &HttpRequestType::parse_post_block_processing,
+            ),
+            (
+                "GET",
+                &PATH_BLOCK_FETCH,
+                &HttpRequestType::parse_get_block_fetch,
+            ),
+            (
+                "POST",
+                &PATH_BLOCK_FETCH,
+                &HttpRequestType::parse_post_block_fetch,
+            ),
+            (
+                "GET",
+                &PATH_GET_QUERY_JOB,
+                &HttpRequestType::parse_get_query_job,
+            ),
+            (
+                "POST",
+                &PATH_POST_QUERY_JOB,
+                &HttpRequestType::parse_post_query_job,
+            ),
             (
                 "POST",
                 &PATH_POSTTRANSACTION,
@@ -1491,6 +1926,16 @@ impl HttpRequestType {
                 &PATH_GET_ACCOUNT,
                 &HttpRequestType::parse_get_account,
             ),
+            (
+                "GET",
+                &PATH_GET_ACCOUNT_UNLOCKS,
+                &HttpRequestType::parse_get_account_unlocks,
+            ),
+            (
+                "GET",
+                &PATH_GET_MINER_REWARDS,
+                &HttpRequestType::parse_get_miner_rewards,
+            ),
             (
                 "POST",
                 &PATH_GET_MAP_ENTRY,
@@ -1521,6 +1966,26 @@ impl HttpRequestType {
                 &PATH_POST_CALL_READ_ONLY,
                 &HttpRequestType::parse_call_read_only,
             ),
+            (
+                "POST",
+                &PATH_POST_VERIFY_CONTRACT_SRC,
+                &HttpRequestType::parse_verify_contract_src,
+            ),
+            (
+                "POST",
+                &PATH_POST_VALIDATE_CONTRACT_CALL,
+                &HttpRequestType::parse_validate_contract_call,
+            ),
+            (
+                "POST",
+                &PATH_POST_ENCODE_CLARITY_VALUE,
+                &HttpRequestType::parse_encode_clarity_value,
+            ),
+            (
+                "POST",
+                &PATH_POST_DECODE_CLARITY_VALUE,
+                &HttpRequestType::parse_decode_clarity_value,
+            ),
             (
                 "OPTIONS",
                 &PATH_OPTIONS_WILDCARD,
@@ -1531,11 +1996,21 @@ impl HttpRequestType {
                 &PATH_GET_ATTACHMENT,
                 &HttpRequestType::parse_get_attachment,
             ),
+            (
+                "GET",
+                &PATH_GET_ATTACHMENT_CONTENT,
+                &HttpRequestType::parse_get_attachment_content,
+            ),
             (
                 "GET",
                 &PATH_GET_ATTACHMENTS_INV,
                 &HttpRequestType::parse_get_attachments_inv,
             ),
+            (
+                "GET",
+                &PATH_GET_ATTACHMENTS_BATCH,
+                &HttpRequestType::parse_get_attachments_batch,
+            ),
         ];
 
         // use url::Url to parse path and query string
@@ -1551,13 +2026,17 @@ impl HttpRequestType {
             )
         })?;
 
+        // every version serves the v2 routes through the same handlers; the parsed request's
+        // metadata records which version the client asked for.
+        let route_path = HttpApiVersion::V2.rewrite_path(&decoded_path);
+
         for (verb, regex, parser) in REQUEST_METHODS.iter() {
             match HttpRequestType::try_parse(
                 protocol,
                 verb,
                 regex,
                 preamble,
-                &decoded_path,
+                &route_path,
                 url.query(),
                 fd,
                 parser,
@@ -1601,6 +2080,23 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_getfeatures<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetFeatures".to_string(),
+            ));
+        }
+        Ok(HttpRequestType::GetFeatures(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
     fn parse_getpoxinfo<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1622,6 +2118,37 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_getstatehash<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetStateHash".to_string(),
+            ));
+        }
+
+        let height = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block height group".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_| net_error::DeserializeError("Failed to parse block height".to_string()))?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetStateHash(
+            HttpRequestMetadata::from_preamble(preamble),
+            height,
+            tip,
+        ))
+    }
+
     fn parse_getneighbors<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1640,7 +2167,7 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_get_transfer_cost<R: Read>(
+    fn parse_get_neighbor_walk<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         _regex: &Captures,
@@ -1649,34 +2176,185 @@ impl HttpRequestType {
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetTransferCost".to_string(),
+                "Invalid Http request: expected 0-length body for GetNeighborWalkInfo".to_string(),
             ));
         }
 
-        Ok(HttpRequestType::GetTransferCost(
+        Ok(HttpRequestType::GetNeighborWalkInfo(
             HttpRequestMetadata::from_preamble(preamble),
         ))
     }
 
-    /// check whether the given option query string
-    ///   sets proof=0 (setting proof to false).
-    /// Defaults to _true_
-    fn get_proof_query(query: Option<&str>) -> bool {
-        let no_proof = if let Some(query_string) = query {
-            form_urlencoded::parse(query_string.as_bytes())
-                .find(|(key, _v)| key == "proof")
-                .map(|(_k, value)| value == "0")
-                .unwrap_or(false)
-        } else {
-            false
-        };
+    fn parse_get_peer_versions<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetPeerVersions".to_string(),
+            ));
+        }
 
-        !no_proof
+        Ok(HttpRequestType::GetPeerVersions(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
     }
 
-    /// get the chain tip optional query argument (`tip`)
-    /// Take the first value we can parse.
-    fn get_chain_tip_query(query: Option<&str>) -> Option<StacksBlockId> {
+    fn parse_get_node_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetNodeStatus".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetNodeStatus(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_node_identity<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetNodeIdentity".to_string(),
+            ));
+        }
+
+        let challenge = HttpRequestType::get_challenge_query(query)?;
+        Ok(HttpRequestType::GetNodeIdentity(
+            HttpRequestMetadata::from_preamble(preamble),
+            challenge,
+        ))
+    }
+
+    fn parse_get_transfer_cost<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetTransferCost".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetTransferCost(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    /// check whether the given option query string
+    ///   sets proof=0 (setting proof to false).
+    /// Defaults to _true_
+    fn get_proof_query(query: Option<&str>) -> bool {
+        let no_proof = if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "proof")
+                .map(|(_k, value)| value == "0")
+                .unwrap_or(false)
+        } else {
+            false
+        };
+
+        !no_proof
+    }
+
+    /// check whether the given option query string
+    ///   sets decode=true or decode=1 (asking for a decoded JSON response).
+    /// Defaults to _false_
+    fn get_decode_query(query: Option<&str>) -> bool {
+        if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "decode")
+                .map(|(_k, value)| value == "true" || value == "1")
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// get the `microblocks` query argument of a block fetch: fetch the confirmed microblock
+    /// stream instead of the block
+    fn get_microblocks_query(query: Option<&str>) -> bool {
+        if let Some(query_string) = query {
+            form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "microblocks")
+                .map(|(_k, value)| value == "true" || value == "1")
+                .unwrap_or(false)
+        } else {
+            false
+        }
+    }
+
+    /// get the optional `challenge` query argument of a node identity request: a hex string of at
+    /// most MAX_NODE_IDENTITY_CHALLENGE_LEN bytes
+    fn get_challenge_query(query: Option<&str>) -> Result<Option<Vec<u8>>, net_error> {
+        let challenge_hex = match query {
+            Some(query_string) => form_urlencoded::parse(query_string.as_bytes())
+                .find(|(key, _v)| key == "challenge")
+                .map(|(_k, value)| value.to_string()),
+            None => None,
+        };
+        match challenge_hex {
+            Some(challenge_hex) => {
+                let challenge = hex_bytes(&challenge_hex).map_err(|_e| {
+                    net_error::DeserializeError("Invalid challenge: not a hex string".to_string())
+                })?;
+                if challenge.len() > MAX_NODE_IDENTITY_CHALLENGE_LEN {
+                    return Err(net_error::DeserializeError(format!(
+                        "Invalid challenge: longer than {} bytes",
+                        MAX_NODE_IDENTITY_CHALLENGE_LEN
+                    )));
+                }
+                Ok(Some(challenge))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// get the pagination query arguments (`cursor` and `limit`) of a list endpoint.
+    /// The limit must be positive.
+    fn get_page_query(query: Option<&str>) -> Result<RPCPageRequest, net_error> {
+        let mut page = RPCPageRequest::default();
+        if let Some(query_string) = query {
+            for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                if key == "cursor" && page.cursor.is_none() {
+                    page.cursor = Some(value.to_string());
+                } else if key == "limit" && page.limit.is_none() {
+                    let limit = value.parse::<u32>().map_err(|_e| {
+                        net_error::DeserializeError(format!("Invalid page limit '{}'", &value))
+                    })?;
+                    if limit == 0 {
+                        return Err(net_error::DeserializeError(
+                            "Invalid page limit: must be positive".to_string(),
+                        ));
+                    }
+                    page.limit = Some(limit);
+                }
+            }
+        }
+        Ok(page)
+    }
+
+    /// get the chain tip optional query argument (`tip`)
+    /// Take the first value we can parse.
+    fn get_chain_tip_query(query: Option<&str>) -> Option<StacksBlockId> {
         match query {
             Some(query_string) => {
                 for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
@@ -1696,6 +2374,31 @@ impl HttpRequestType {
         }
     }
 
+    /// get the historic block optional query argument (`at_block` or `at_height`).
+    /// `at_block` is an index block hash, and `at_height` is a Stacks block height.
+    /// Take the first value we can parse.
+    fn get_at_block_query(query: Option<&str>) -> Option<AtBlock> {
+        match query {
+            Some(query_string) => {
+                for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                    if key == "at_block" {
+                        if let Ok(block_id) = StacksBlockId::from_hex(&value) {
+                            return Some(AtBlock::BlockId(block_id));
+                        }
+                    } else if key == "at_height" {
+                        if let Ok(height) = value.parse::<u64>() {
+                            return Some(AtBlock::Height(height));
+                        }
+                    }
+                }
+                return None;
+            }
+            None => {
+                return None;
+            }
+        }
+    }
+
     fn parse_get_account<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1724,6 +2427,57 @@ impl HttpRequestType {
         ))
     }
 
+    fn parse_get_account_unlocks<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetAccountUnlocks".to_string(),
+            ));
+        }
+
+        let principal = PrincipalData::parse(&captures["principal"]).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse account principal".into())
+        })?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetAccountUnlocks(
+            HttpRequestMetadata::from_preamble(preamble),
+            principal,
+            tip,
+        ))
+    }
+
+    fn parse_get_miner_rewards<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMinerRewards".to_string(),
+            ));
+        }
+
+        let address = StacksAddress::from_string(&captures["address"])
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse miner address".into()))?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::GetMinerRewards(
+            HttpRequestMetadata::from_preamble(preamble),
+            address,
+            tip,
+        ))
+    }
+
     fn parse_get_map_entry<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
@@ -1818,6 +2572,7 @@ impl HttpRequestType {
             })?;
 
         let tip = HttpRequestType::get_chain_tip_query(query);
+        let at_block = HttpRequestType::get_at_block_query(query);
 
         Ok(HttpRequestType::CallReadOnlyFunction(
             HttpRequestMetadata::from_preamble(preamble),
@@ -1827,6 +2582,7 @@ impl HttpRequestType {
             func_name,
             arguments,
             tip,
+            at_block,
         ))
     }
 
@@ -1921,81 +2677,238 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_getblock<R: Read>(
+    fn parse_verify_contract_src<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
-        _query: Option<&str>,
-        _fd: &mut R,
+        query: Option<&str>,
+        fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() != 0 {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for VerifyContractSrc ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetBlock".to_string(),
+                "Invalid content-type: expected application/json".to_string(),
             ));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to block hash group".to_string(),
-            ))?
-            .as_str();
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
+        })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
 
-        let block_hash = StacksBlockId::from_hex(block_hash_str)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: VerifyContractSrcRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
 
-        Ok(HttpRequestType::GetBlock(
+        Ok(HttpRequestType::VerifyContractSrc(
             HttpRequestMetadata::from_preamble(preamble),
-            block_hash,
+            contract_addr,
+            contract_name,
+            body.source,
+            tip,
         ))
     }
 
-    fn parse_getmicroblocks_indexed<R: Read>(
-        _protocol: &mut StacksHttp,
+    fn parse_validate_contract_call<R: Read>(
+        protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         captures: &Captures,
-        _query: Option<&str>,
-        _fd: &mut R,
+        query: Option<&str>,
+        fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() != 0 {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for ValidateContractCall ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetMicroblocksIndexed"
-                    .to_string(),
+                "Invalid content-type: expected application/json".to_string(),
             ));
         }
 
-        let block_hash_str = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to microblock hash group".to_string(),
-            ))?
-            .as_str();
-
-        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
-            net_error::DeserializeError("Failed to parse microblock hash".to_string())
+        let contract_addr = StacksAddress::from_string(&captures["address"]).ok_or_else(|| {
+            net_error::DeserializeError("Failed to parse contract address".into())
         })?;
+        let contract_name = ContractName::try_from(captures["contract"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse contract name".into()))?;
+        let func_name = ClarityName::try_from(captures["function"].to_string())
+            .map_err(|_e| net_error::DeserializeError("Failed to parse function name".into()))?;
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: ValidateContractCallRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+
+        let tip = HttpRequestType::get_chain_tip_query(query);
 
-        Ok(HttpRequestType::GetMicroblocksIndexed(
+        // arguments are decoded by the handler, so that each one can be reported on
+        Ok(HttpRequestType::ValidateContractCall(
             HttpRequestMetadata::from_preamble(preamble),
-            block_hash,
+            contract_addr,
+            contract_name,
+            func_name,
+            body.arguments,
+            tip,
         ))
     }
 
-    fn parse_getmicroblocks_confirmed<R: Read>(
-        _protocol: &mut StacksHttp,
+    /// Read the JSON body of a POST to one of the Clarity value conversion endpoints
+    fn parse_clarity_value_body<R: Read, T: serde::de::DeserializeOwned>(
+        protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        captures: &Captures,
-        _query: Option<&str>,
-        _fd: &mut R,
-    ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() != 0 {
+        fd: &mut R,
+    ) -> Result<T, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < protocol.maximum_call_argument_size) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for Clarity value conversion ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body for GetMicrolocks".to_string(),
+                "Invalid content-type: expected application/json".to_string(),
             ));
         }
 
-        let block_hash_str = captures
-            .get(1)
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))
+    }
+
+    fn parse_encode_clarity_value<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let body: EncodeClarityValueRequestBody =
+            HttpRequestType::parse_clarity_value_body(protocol, preamble, fd)?;
+        Ok(HttpRequestType::EncodeClarityValue(
+            HttpRequestMetadata::from_preamble(preamble),
+            body.value,
+        ))
+    }
+
+    fn parse_decode_clarity_value<R: Read>(
+        protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let body: DecodeClarityValueRequestBody =
+            HttpRequestType::parse_clarity_value_body(protocol, preamble, fd)?;
+        Ok(HttpRequestType::DecodeClarityValue(
+            HttpRequestMetadata::from_preamble(preamble),
+            body.hex,
+        ))
+    }
+
+    fn parse_getblock<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlock".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
+
+        if HttpRequestType::get_decode_query(query) {
+            Ok(HttpRequestType::GetBlockDecoded(
+                HttpRequestMetadata::from_preamble(preamble),
+                block_hash,
+            ))
+        } else {
+            Ok(HttpRequestType::GetBlock(
+                HttpRequestMetadata::from_preamble(preamble),
+                block_hash,
+            ))
+        }
+    }
+
+    fn parse_getmicroblocks_indexed<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicroblocksIndexed"
+                    .to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to microblock hash group".to_string(),
+            ))?
+            .as_str();
+
+        let block_hash = StacksBlockId::from_hex(block_hash_str).map_err(|_e| {
+            net_error::DeserializeError("Failed to parse microblock hash".to_string())
+        })?;
+
+        if HttpRequestType::get_decode_query(query) {
+            Ok(HttpRequestType::GetMicroblocksIndexedDecoded(
+                HttpRequestMetadata::from_preamble(preamble),
+                block_hash,
+            ))
+        } else {
+            Ok(HttpRequestType::GetMicroblocksIndexed(
+                HttpRequestMetadata::from_preamble(preamble),
+                block_hash,
+            ))
+        }
+    }
+
+    fn parse_getmicroblocks_confirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicrolocks".to_string(),
+            ));
+        }
+
+        let block_hash_str = captures
+            .get(1)
             .ok_or(net_error::DeserializeError(
                 "Failed to match path to microblock hash group".to_string(),
             ))?
@@ -2005,7 +2918,42 @@ impl HttpRequestType {
             net_error::DeserializeError("Failed to parse microblock hash".to_string())
         })?;
 
-        Ok(HttpRequestType::GetMicroblocksConfirmed(
+        if HttpRequestType::get_decode_query(query) {
+            Ok(HttpRequestType::GetMicroblocksConfirmedDecoded(
+                HttpRequestMetadata::from_preamble(preamble),
+                block_hash,
+            ))
+        } else {
+            Ok(HttpRequestType::GetMicroblocksConfirmed(
+                HttpRequestMetadata::from_preamble(preamble),
+                block_hash,
+            ))
+        }
+    }
+
+    fn parse_getmicroblocks_forks<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicroblockForks".to_string(),
+            ));
+        }
+
+        let block_hash = match captures.get(2) {
+            Some(block_hash_str) => Some(
+                StacksBlockId::from_hex(block_hash_str.as_str()).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse block hash".to_string())
+                })?,
+            ),
+            None => None,
+        };
+
+        Ok(HttpRequestType::GetMicroblockForks(
             HttpRequestMetadata::from_preamble(preamble),
             block_hash,
         ))
@@ -2090,3296 +3038,6466 @@ impl HttpRequestType {
         ))
     }
 
-    fn parse_posttransaction<R: Read>(
+    fn parse_getmempool_drops<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        _regex: &Captures,
+        regex: &Captures,
         _query: Option<&str>,
-        fd: &mut R,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() == 0 {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected non-zero-length body for PostTransaction"
-                    .to_string(),
+                "Invalid Http request: expected 0-length body for GetMemPoolDrops".to_string(),
             ));
         }
 
-        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
-            return Err(net_error::DeserializeError(
-                "Invalid Http request: PostTransaction body is too big".to_string(),
-            ));
-        }
+        let txid_hex = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to txid group".to_string(),
+            ))?
+            .as_str();
 
-        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
+        let txid = Txid::from_hex(&txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
 
-        match preamble.content_type {
-            None => {
-                return Err(net_error::DeserializeError(
-                    "Missing Content-Type for transaction".to_string(),
-                ));
-            }
-            Some(HttpContentType::Bytes) => {
-                HttpRequestType::parse_posttransaction_octets(preamble, &mut bound_fd)
-            }
-            Some(HttpContentType::JSON) => {
-                HttpRequestType::parse_posttransaction_json(preamble, &mut bound_fd)
-            }
-            _ => {
-                return Err(net_error::DeserializeError(
-                    "Wrong Content-Type for transaction; expected application/json".to_string(),
-                ));
-            }
-        }
+        Ok(HttpRequestType::GetMemPoolDrops(
+            HttpRequestMetadata::from_preamble(preamble),
+            txid,
+        ))
     }
 
-    fn parse_posttransaction_octets<R: Read>(
+    fn parse_getmicroblock_proof<R: Read>(
+        _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        fd: &mut R,
+        regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        let tx = StacksTransaction::consensus_deserialize(fd).map_err(|e| {
-            if let codec_error::DeserializeError(msg) = e {
-                net_error::ClientError(ClientError::Message(format!(
-                    "Failed to deserialize posted transaction: {}",
-                    msg
-                )))
-            } else {
-                e.into()
-            }
-        })?;
-        Ok(HttpRequestType::PostTransaction(
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMicroblockProof".to_string(),
+            ));
+        }
+
+        let txid_hex = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to txid group".to_string(),
+            ))?
+            .as_str();
+
+        let txid = Txid::from_hex(&txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+
+        Ok(HttpRequestType::GetMicroblockProof(
             HttpRequestMetadata::from_preamble(preamble),
-            tx,
-            None,
+            txid,
         ))
     }
 
-    fn parse_posttransaction_json<R: Read>(
+    fn parse_get_address_mempool<R: Read>(
+        _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        fd: &mut R,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        let body: PostTransactionRequestBody = serde_json::from_reader(fd)
-            .map_err(|_e| net_error::DeserializeError("Failed to parse body".into()))?;
-
-        let tx = {
-            let tx_bytes = hex_bytes(&body.tx)
-                .map_err(|_e| net_error::DeserializeError("Failed to parse tx".into()))?;
-            StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|e| {
-                if let codec_error::DeserializeError(msg) = e {
-                    net_error::ClientError(ClientError::Message(format!(
-                        "Failed to deserialize posted transaction: {}",
-                        msg
-                    )))
-                } else {
-                    e.into()
-                }
-            })
-        }?;
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetAddressMempool".to_string(),
+            ));
+        }
 
-        let attachment = match body.attachment {
-            None => None,
-            Some(attachment_content) => {
-                let content = hex_bytes(&attachment_content).map_err(|_e| {
-                    net_error::DeserializeError("Failed to parse attachment".into())
-                })?;
-                Some(Attachment::new(content))
-            }
-        };
+        let address = StacksAddress::from_string(&captures["address"])
+            .ok_or_else(|| net_error::DeserializeError("Failed to parse address".into()))?;
 
-        Ok(HttpRequestType::PostTransaction(
+        Ok(HttpRequestType::GetAddressMempool(
             HttpRequestMetadata::from_preamble(preamble),
-            tx,
-            attachment,
+            address,
+            HttpRequestType::get_page_query(query)?,
         ))
     }
 
-    fn parse_postblock<R: Read>(
+    fn parse_get_mempool_fees<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        regex: &Captures,
+        _regex: &Captures,
         _query: Option<&str>,
-        fd: &mut R,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() == 0 {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected non-zero-length body for PostBlock".to_string(),
+                "Invalid Http request: expected 0-length body for GetMemPoolFees".to_string(),
             ));
         }
 
-        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+        Ok(HttpRequestType::GetMemPoolFees(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_simulate_block<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: PostBlock body is too big".to_string(),
+                "Invalid Http request: expected 0-length body for SimulateBlock".to_string(),
             ));
         }
 
-        // content-type must be given, and must be application/octet-stream
-        match preamble.content_type {
-            None => {
-                return Err(net_error::DeserializeError(
-                    "Missing Content-Type for Stacks block".to_string(),
-                ));
-            }
-            Some(ref c) => {
-                if *c != HttpContentType::Bytes {
-                    return Err(net_error::DeserializeError(
-                        "Wrong Content-Type for Stacks block; expected application/octet-stream"
-                            .to_string(),
-                    ));
+        Ok(HttpRequestType::SimulateBlock(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_contract_costs<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetContractCosts".to_string(),
+            ));
+        }
+
+        let mut from_height = None;
+        let mut to_height = None;
+        let mut limit = None;
+        if let Some(query_string) = query {
+            for (key, value) in form_urlencoded::parse(query_string.as_bytes()) {
+                if key == "from_height" && from_height.is_none() {
+                    from_height = Some(value.parse::<u64>().map_err(|_e| {
+                        net_error::DeserializeError(format!("Invalid from_height '{}'", &value))
+                    })?);
+                } else if key == "to_height" && to_height.is_none() {
+                    to_height = Some(value.parse::<u64>().map_err(|_e| {
+                        net_error::DeserializeError(format!("Invalid to_height '{}'", &value))
+                    })?);
+                } else if key == "limit" && limit.is_none() {
+                    let value = value.parse::<u32>().map_err(|_e| {
+                        net_error::DeserializeError(format!("Invalid limit '{}'", &value))
+                    })?;
+                    if value == 0 {
+                        return Err(net_error::DeserializeError(
+                            "Invalid limit: must be positive".to_string(),
+                        ));
+                    }
+                    limit = Some(value);
                 }
             }
-        };
-
-        let consensus_hash_str = regex
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match consensus hash in path group".to_string(),
-            ))?
-            .as_str();
+        }
 
-        let consensus_hash: ConsensusHash =
-            ConsensusHash::from_hex(consensus_hash_str).map_err(|_| {
-                net_error::DeserializeError("Failed to parse consensus hash".to_string())
-            })?;
+        Ok(HttpRequestType::GetContractCosts(
+            HttpRequestMetadata::from_preamble(preamble),
+            from_height,
+            to_height,
+            limit,
+        ))
+    }
 
-        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
-        let stacks_block = StacksBlock::consensus_deserialize(&mut bound_fd)?;
+    fn parse_get_mempool_snapshot<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetMemPoolSnapshot".to_string(),
+            ));
+        }
 
-        Ok(HttpRequestType::PostBlock(
+        Ok(HttpRequestType::GetMemPoolSnapshot(
             HttpRequestMetadata::from_preamble(preamble),
-            consensus_hash,
-            stacks_block,
+            HttpRequestType::get_page_query(query)?,
         ))
     }
 
-    fn parse_postmicroblock<R: Read>(
+    fn parse_get_event_observer_status<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         _regex: &Captures,
-        query: Option<&str>,
-        fd: &mut R,
+        _query: Option<&str>,
+        _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        if preamble.get_content_length() == 0 {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected non-zero-length body for PostMicroblock"
+                "Invalid Http request: expected 0-length body for GetEventObserverStatus"
                     .to_string(),
             ));
         }
 
-        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+        Ok(HttpRequestType::GetEventObserverStatus(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
+
+    fn parse_get_event_replays<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: PostMicroblock body is too big".to_string(),
+                "Invalid Http request: expected 0-length body for GetEventReplays".to_string(),
             ));
         }
 
-        // content-type must be given, and must be application/octet-stream
-        match preamble.content_type {
-            None => {
-                return Err(net_error::DeserializeError(
-                    "Missing Content-Type for microblock".to_string(),
-                ));
-            }
-            Some(ref c) => {
-                if *c != HttpContentType::Bytes {
-                    return Err(net_error::DeserializeError(
-                        "Wrong Content-Type for microblock; expected application/octet-stream"
-                            .to_string(),
-                    ));
-                }
-            }
-        };
+        Ok(HttpRequestType::GetEventReplays(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
 
-        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
+    fn parse_post_event_replay<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostEventReplay ({})",
+                content_len
+            )));
+        }
 
-        let mb = StacksMicroblock::consensus_deserialize(&mut bound_fd)?;
-        let tip = HttpRequestType::get_chain_tip_query(query);
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
 
-        Ok(HttpRequestType::PostMicroblock(
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: EventReplayRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        body.validate().map_err(net_error::DeserializeError)?;
+
+        Ok(HttpRequestType::PostEventReplay(
             HttpRequestMetadata::from_preamble(preamble),
-            mb,
-            tip,
+            body,
         ))
     }
 
-    fn parse_get_attachment<R: Read>(
+    fn parse_get_peer_filters<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        captures: &Captures,
+        _regex: &Captures,
         _query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body".to_string(),
+                "Invalid Http request: expected 0-length body for GetPeerFilters".to_string(),
             ));
         }
-        let hex_content_hash = captures
-            .get(1)
-            .ok_or(net_error::DeserializeError(
-                "Failed to match path to attachment hash group".to_string(),
-            ))?
-            .as_str();
 
-        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| {
-            net_error::DeserializeError("Failed to construct hash160 from inputs".to_string())
-        })?;
+        Ok(HttpRequestType::GetPeerFilters(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
 
-        Ok(HttpRequestType::GetAttachment(
+    fn parse_post_peer_filter<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostPeerFilter ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: PeerFilterUpdateRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let (action, list, rule) = body.to_update().map_err(net_error::DeserializeError)?;
+
+        Ok(HttpRequestType::PostPeerFilter(
             HttpRequestMetadata::from_preamble(preamble),
-            content_hash,
+            action,
+            list,
+            rule,
         ))
     }
 
-    fn parse_get_attachments_inv<R: Read>(
+    fn parse_get_atlas_contracts<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
-        _captures: &Captures,
-        query: Option<&str>,
+        _regex: &Captures,
+        _query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
         if preamble.get_content_length() != 0 {
             return Err(net_error::DeserializeError(
-                "Invalid Http request: expected 0-length body".to_string(),
+                "Invalid Http request: expected 0-length body for GetAtlasContracts".to_string(),
             ));
         }
 
-        let (index_block_hash, pages_indexes) = match query {
-            None => {
-                return Err(net_error::DeserializeError(
-                    "Invalid Http request: expecting index_block_hash and pages_indexes"
-                        .to_string(),
-                ));
-            }
-            Some(query) => {
-                let mut index_block_hash = None;
-                let mut pages_indexes = HashSet::new();
-
-                for (key, value) in form_urlencoded::parse(query.as_bytes()) {
-                    if key == "index_block_hash" {
-                        index_block_hash = match StacksBlockId::from_hex(&value) {
-                            Ok(index_block_hash) => Some(index_block_hash),
-                            _ => None,
-                        };
-                    } else if key == "pages_indexes" {
-                        if let Ok(pages_indexes_value) = value.parse::<String>() {
-                            for entry in pages_indexes_value.split(",") {
-                                if let Ok(page_index) = entry.parse::<u32>() {
-                                    pages_indexes.insert(page_index);
-                                }
-                            }
-                        }
-                    }
-                }
+        Ok(HttpRequestType::GetAtlasContracts(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
+    }
 
-                let index_block_hash = match index_block_hash {
-                    None => {
-                        return Err(net_error::DeserializeError(
-                            "Invalid Http request: expecting index_block_hash".to_string(),
-                        ));
-                    }
-                    Some(index_block_hash) => index_block_hash,
-                };
+    fn parse_post_atlas_contract<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostAtlasContract ({})",
+                content_len
+            )));
+        }
 
-                if pages_indexes.is_empty() {
-                    return Err(net_error::DeserializeError(
-                        "Invalid Http request: expecting pages_indexes".to_string(),
-                    ));
-                }
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
 
-                (index_block_hash, pages_indexes)
-            }
-        };
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: AtlasContractRegistrationRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let (contract_id, attachments_max_size, unresolved_attachment_instances_expire_after) =
+            body.to_registration()
+                .map_err(net_error::DeserializeError)?;
 
-        Ok(HttpRequestType::GetAttachmentsInv(
+        Ok(HttpRequestType::PostAtlasContract(
             HttpRequestMetadata::from_preamble(preamble),
-            index_block_hash,
-            pages_indexes,
+            contract_id,
+            attachments_max_size,
+            unresolved_attachment_instances_expire_after,
         ))
     }
 
-    fn parse_options_preflight<R: Read>(
+    fn parse_get_attachment_pins<R: Read>(
         _protocol: &mut StacksHttp,
         preamble: &HttpRequestPreamble,
         _regex: &Captures,
         _query: Option<&str>,
         _fd: &mut R,
     ) -> Result<HttpRequestType, net_error> {
-        Ok(HttpRequestType::OptionsPreflight(
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetAttachmentPins".to_string(),
+            ));
+        }
+
+        Ok(HttpRequestType::GetAttachmentPins(
             HttpRequestMetadata::from_preamble(preamble),
-            preamble.path.to_string(),
         ))
     }
 
-    pub fn metadata(&self) -> &HttpRequestMetadata {
-        match *self {
-            HttpRequestType::GetInfo(ref md) => md,
-            HttpRequestType::GetPoxInfo(ref md, _) => md,
-            HttpRequestType::GetNeighbors(ref md) => md,
-            HttpRequestType::GetBlock(ref md, _) => md,
-            HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
-            HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
-            HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
-            HttpRequestType::GetTransactionUnconfirmed(ref md, _) => md,
-            HttpRequestType::PostTransaction(ref md, _, _) => md,
-            HttpRequestType::PostBlock(ref md, ..) => md,
-            HttpRequestType::PostMicroblock(ref md, ..) => md,
-            HttpRequestType::GetAccount(ref md, ..) => md,
-            HttpRequestType::GetMapEntry(ref md, ..) => md,
-            HttpRequestType::GetTransferCost(ref md) => md,
-            HttpRequestType::GetContractABI(ref md, ..) => md,
-            HttpRequestType::GetContractSrc(ref md, ..) => md,
-            HttpRequestType::GetIsTraitImplemented(ref md, ..) => md,
-            HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
-            HttpRequestType::OptionsPreflight(ref md, ..) => md,
-            HttpRequestType::GetAttachmentsInv(ref md, ..) => md,
-            HttpRequestType::GetAttachment(ref md, ..) => md,
-            HttpRequestType::ClientError(ref md, ..) => md,
+    fn parse_post_attachment_pin<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostAttachmentPin ({})",
+                content_len
+            )));
         }
-    }
 
-    pub fn metadata_mut(&mut self) -> &mut HttpRequestMetadata {
-        match *self {
-            HttpRequestType::GetInfo(ref mut md) => md,
-            HttpRequestType::GetPoxInfo(ref mut md, _) => md,
-            HttpRequestType::GetNeighbors(ref mut md) => md,
-            HttpRequestType::GetBlock(ref mut md, _) => md,
-            HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
-            HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
-            HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
-            HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
-            HttpRequestType::PostTransaction(ref mut md, _, _) => md,
-            HttpRequestType::PostBlock(ref mut md, ..) => md,
-            HttpRequestType::PostMicroblock(ref mut md, ..) => md,
-            HttpRequestType::GetAccount(ref mut md, ..) => md,
-            HttpRequestType::GetMapEntry(ref mut md, ..) => md,
-            HttpRequestType::GetTransferCost(ref mut md) => md,
-            HttpRequestType::GetContractABI(ref mut md, ..) => md,
-            HttpRequestType::GetContractSrc(ref mut md, ..) => md,
-            HttpRequestType::GetIsTraitImplemented(ref mut md, ..) => md,
-            HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
-            HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
-            HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
-            HttpRequestType::GetAttachment(ref mut md, ..) => md,
-            HttpRequestType::ClientError(ref mut md, ..) => md,
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
         }
+
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: AttachmentPinRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let (action, content_hash) = body.to_update().map_err(net_error::DeserializeError)?;
+
+        Ok(HttpRequestType::PostAttachmentPin(
+            HttpRequestMetadata::from_preamble(preamble),
+            action,
+            content_hash,
+        ))
     }
 
-    fn make_query_string(tip_opt: Option<&StacksBlockId>, with_proof: bool) -> String {
-        if let Some(tip) = tip_opt {
-            format!("?tip={}{}", tip, if with_proof { "" } else { "&proof=0" })
-        } else if !with_proof {
-            format!("?proof=0")
-        } else {
-            "".to_string()
+    fn parse_get_block_processing<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetBlockProcessing".to_string(),
+            ));
         }
+
+        Ok(HttpRequestType::GetBlockProcessing(
+            HttpRequestMetadata::from_preamble(preamble),
+        ))
     }
 
-    pub fn request_path(&self) -> String {
-        match self {
-            HttpRequestType::GetInfo(_md) => "/v2/info".to_string(),
-            HttpRequestType::GetPoxInfo(_md, tip_opt) => format!(
-                "/v2/pox{}",
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
-            HttpRequestType::GetBlock(_md, block_hash) => {
-                format!("/v2/blocks/{}", block_hash.to_hex())
-            }
-            HttpRequestType::GetMicroblocksIndexed(_md, block_hash) => {
-                format!("/v2/microblocks/{}", block_hash.to_hex())
-            }
-            HttpRequestType::GetMicroblocksConfirmed(_md, block_hash) => {
-                format!("/v2/microblocks/confirmed/{}", block_hash.to_hex())
-            }
-            HttpRequestType::GetMicroblocksUnconfirmed(_md, block_hash, min_seq) => format!(
-                "/v2/microblocks/unconfirmed/{}/{}",
-                block_hash.to_hex(),
-                min_seq
-            ),
-            HttpRequestType::GetTransactionUnconfirmed(_md, txid) => {
-                format!("/v2/transactions/unconfirmed/{}", txid)
-            }
-            HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
-            HttpRequestType::PostBlock(_md, ch, ..) => format!("/v2/blocks/upload/{}", &ch),
-            HttpRequestType::PostMicroblock(_md, _, tip_opt) => format!(
-                "/v2/microblocks{}",
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::GetAccount(_md, principal, tip_opt, with_proof) => format!(
-                "/v2/accounts/{}{}",
-                &principal.to_string(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
-            ),
-            HttpRequestType::GetMapEntry(
-                _md,
-                contract_addr,
-                contract_name,
-                map_name,
-                _key,
-                tip_opt,
-                with_proof,
-            ) => format!(
-                "/v2/map_entry/{}/{}/{}{}",
-                &contract_addr.to_string(),
-                contract_name.as_str(),
-                map_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
-            ),
-            HttpRequestType::GetTransferCost(_md) => "/v2/fees/transfer".into(),
-            HttpRequestType::GetContractABI(_, contract_addr, contract_name, tip_opt) => format!(
-                "/v2/contracts/interface/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::GetContractSrc(
-                _,
-                contract_addr,
-                contract_name,
-                tip_opt,
-                with_proof,
-            ) => format!(
-                "/v2/contracts/source/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
-            ),
-            HttpRequestType::GetIsTraitImplemented(
-                _,
-                contract_addr,
-                contract_name,
-                trait_id,
-                tip_opt,
-            ) => format!(
-                "/v2/traits/{}/{}/{}/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                trait_id.name.to_string(),
-                StacksAddress::from(trait_id.clone().contract_identifier.issuer),
-                trait_id.contract_identifier.name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::CallReadOnlyFunction(
-                _,
-                contract_addr,
-                contract_name,
-                _,
-                func_name,
-                _,
-                tip_opt,
-            ) => format!(
-                "/v2/contracts/call-read/{}/{}/{}{}",
-                contract_addr,
-                contract_name.as_str(),
-                func_name.as_str(),
-                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
-            ),
-            HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
-            HttpRequestType::GetAttachmentsInv(_md, index_block_hash, pages_indexes) => {
-                let pages_query = match pages_indexes.len() {
-                    0 => format!(""),
-                    _n => {
-                        let mut indexes = pages_indexes
-                            .iter()
-                            .map(|i| format!("{}", i))
-                            .collect::<Vec<String>>();
-                        indexes.sort();
-                        format!("&pages_indexes={}", indexes.join(","))
-                    }
-                };
-                let index_block_hash = format!("index_block_hash={}", index_block_hash);
-                format!("/v2/attachments/inv?{}{}", index_block_hash, pages_query,)
-            }
-            HttpRequestType::GetAttachment(_, content_hash) => {
-                format!("/v2/attachments/{}", to_hex(&content_hash.0[..]))
-            }
-            HttpRequestType::ClientError(_md, e) => match e {
-                ClientError::NotFound(path) => path.to_string(),
-                _ => "error path unknown".into(),
-            },
+    fn parse_post_block_processing<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostBlockProcessing ({})",
+                content_len
+            )));
         }
-    }
 
-    pub fn get_path(&self) -> &str {
-        match self {
-            HttpRequestType::GetInfo(..) => "/v2/info",
-            HttpRequestType::GetPoxInfo(..) => "/v2/pox",
-            HttpRequestType::GetNeighbors(..) => "/v2/neighbors",
-            HttpRequestType::GetBlock(..) => "/v2/blocks/:hash",
-            HttpRequestType::GetMicroblocksIndexed(..) => "/v2/microblocks/:hash",
-            HttpRequestType::GetMicroblocksConfirmed(..) => "/v2/microblocks/confirmed/:hash",
-            HttpRequestType::GetMicroblocksUnconfirmed(..) => {
-                "/v2/microblocks/unconfirmed/:hash/:seq"
-            }
-            HttpRequestType::GetTransactionUnconfirmed(..) => "/v2/transactions/unconfirmed/:txid",
-            HttpRequestType::PostTransaction(..) => "/v2/transactions",
-            HttpRequestType::PostBlock(..) => "/v2/blocks/upload/:block",
-            HttpRequestType::PostMicroblock(..) => "/v2/microblocks",
-            HttpRequestType::GetAccount(..) => "/v2/accounts/:principal",
-            HttpRequestType::GetMapEntry(..) => "/v2/map_entry/:principal/:contract_name/:map_name",
-            HttpRequestType::GetTransferCost(..) => "/v2/fees/transfer",
-            HttpRequestType::GetContractABI(..) => {
-                "/v2/contracts/interface/:principal/:contract_name"
-            }
-            HttpRequestType::GetContractSrc(..) => "/v2/contracts/source/:principal/:contract_name",
-            HttpRequestType::CallReadOnlyFunction(..) => {
-                "/v2/contracts/call-read/:principal/:contract_name/:func_name"
-            }
-            HttpRequestType::GetAttachmentsInv(..) => "/v2/attachments/inv",
-            HttpRequestType::GetAttachment(..) => "/v2/attachments/:hash",
-            HttpRequestType::GetIsTraitImplemented(..) => "/v2/traits/:principal/:contract_name",
-            HttpRequestType::OptionsPreflight(..) | HttpRequestType::ClientError(..) => "/",
+        if preamble.content_type != Some(HttpContentType::JSON) {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
         }
-    }
 
-    pub fn send<W: Write>(&self, _protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
-        match self {
-            HttpRequestType::PostTransaction(md, tx, attachment) => {
-                let mut tx_bytes = vec![];
-                write_next(&mut tx_bytes, tx)?;
-                let tx_hex = to_hex(&tx_bytes[..]);
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: BlockProcessingRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let action = body.to_action().map_err(net_error::DeserializeError)?;
 
-                let (content_type, request_body_bytes) = match attachment {
-                    None => {
-                        // Transaction does not include an attachment: HttpContentType::Bytes (more compressed)
-                        (Some(&HttpContentType::Bytes), tx_bytes)
-                    }
-                    Some(attachment) => {
-                        // Transaction is including an attachment: HttpContentType::JSON
-                        let request_body = PostTransactionRequestBody {
-                            tx: tx_hex,
-                            attachment: Some(to_hex(&attachment.content[..])),
-                        };
+        Ok(HttpRequestType::PostBlockProcessing(
+            HttpRequestMetadata::from_preamble(preamble),
+            action,
+        ))
+    }
 
-                        let mut request_body_bytes = vec![];
-                        serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(
-                            |e| {
-                                net_error::SerializeError(format!(
-                                    "Failed to serialize read-only call to JSON: {:?}",
-                                    &e
-                                ))
-                            },
-                        )?;
-                        (Some(&HttpContentType::JSON), request_body_bytes)
-                    }
-                };
-
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(request_body_bytes.len() as u32),
-                    content_type,
-                    empty_headers,
-                )?;
-                fd.write_all(&request_body_bytes)
-                    .map_err(net_error::WriteError)?;
-            }
-            HttpRequestType::PostBlock(md, _ch, block) => {
-                let mut block_bytes = vec![];
-                write_next(&mut block_bytes, block)?;
-
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(block_bytes.len() as u32),
-                    Some(&HttpContentType::Bytes),
-                    empty_headers,
-                )?;
-                fd.write_all(&block_bytes).map_err(net_error::WriteError)?;
-            }
-            HttpRequestType::PostMicroblock(md, mb, ..) => {
-                let mut mb_bytes = vec![];
-                write_next(&mut mb_bytes, mb)?;
+    /// parse the index block hash and `microblocks` flag of GET and POST /v2/blocks/fetch/:hash
+    fn parse_block_fetch_args(
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+    ) -> Result<(StacksBlockId, bool), net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for block fetch".to_string(),
+            ));
+        }
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(mb_bytes.len() as u32),
-                    Some(&HttpContentType::Bytes),
-                    empty_headers,
-                )?;
-                fd.write_all(&mb_bytes).map_err(net_error::WriteError)?;
-            }
-            HttpRequestType::GetMapEntry(
-                md,
-                _contract_addr,
-                _contract_name,
-                _map_name,
-                key,
-                ..,
-            ) => {
-                let mut request_bytes = vec![];
-                key.serialize_write(&mut request_bytes)
-                    .map_err(net_error::WriteError)?;
-                let request_json = format!("\"{}\"", to_hex(&request_bytes));
+        let block_hash_str = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to block hash group".to_string(),
+            ))?
+            .as_str();
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(request_json.as_bytes().len() as u32),
-                    Some(&HttpContentType::JSON),
-                    empty_headers,
-                )?;
-                fd.write_all(&request_json.as_bytes())
-                    .map_err(net_error::WriteError)?;
-            }
-            HttpRequestType::CallReadOnlyFunction(
-                md,
-                _contract_addr,
-                _contract_name,
-                sender,
-                _func_name,
-                func_args,
-                ..,
-            ) => {
-                let mut args = vec![];
-                for arg in func_args.iter() {
-                    let mut arg_bytes = vec![];
-                    arg.serialize_write(&mut arg_bytes)
-                        .map_err(net_error::WriteError)?;
-                    args.push(to_hex(&arg_bytes));
-                }
+        let block_hash = StacksBlockId::from_hex(block_hash_str)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse block hash".to_string()))?;
 
-                let request_body = CallReadOnlyRequestBody {
-                    sender: sender.to_string(),
-                    arguments: args,
-                };
+        Ok((block_hash, HttpRequestType::get_microblocks_query(query)))
+    }
 
-                let mut request_body_bytes = vec![];
-                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
-                    net_error::SerializeError(format!(
-                        "Failed to serialize read-only call to JSON: {:?}",
-                        &e
-                    ))
-                })?;
+    fn parse_get_block_fetch<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let (block_hash, microblocks) =
+            HttpRequestType::parse_block_fetch_args(preamble, captures, query)?;
+        Ok(HttpRequestType::GetBlockFetch(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+            microblocks,
+        ))
+    }
 
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "POST",
-                    &self.request_path(),
-                    &md.peer,
-                    md.keep_alive,
-                    Some(request_body_bytes.len() as u32),
-                    Some(&HttpContentType::JSON),
-                    empty_headers,
-                )?;
-                fd.write_all(&request_body_bytes)
-                    .map_err(net_error::WriteError)?;
-            }
-            other_type => {
-                let md = other_type.metadata();
-                let request_path = other_type.request_path();
-                HttpRequestPreamble::new_serialized(
-                    fd,
-                    &md.version,
-                    "GET",
-                    &request_path,
-                    &md.peer,
-                    md.keep_alive,
-                    None,
-                    None,
-                    empty_headers,
-                )?;
-            }
-        }
-        Ok(())
+    fn parse_post_block_fetch<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let (block_hash, microblocks) =
+            HttpRequestType::parse_block_fetch_args(preamble, captures, query)?;
+        Ok(HttpRequestType::PostBlockFetch(
+            HttpRequestMetadata::from_preamble(preamble),
+            block_hash,
+            microblocks,
+        ))
     }
-}
 
-impl HttpResponseType {
-    fn try_parse<R: Read, F>(
-        protocol: &mut StacksHttp,
-        regex: &Regex,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        request_path: &String,
-        fd: &mut R,
-        len_hint: Option<usize>,
-        parser: F,
-    ) -> Result<Option<HttpResponseType>, net_error>
-    where
-        F: Fn(
-            &mut StacksHttp,
-            HttpVersion,
-            &HttpResponsePreamble,
-            &mut R,
-            Option<usize>,
-        ) -> Result<HttpResponseType, net_error>,
-    {
-        if regex.is_match(request_path) {
-            let payload = parser(protocol, request_version, preamble, fd, len_hint)?;
-            Ok(Some(payload))
-        } else {
-            Ok(None)
+    fn parse_get_query_job<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body for GetQueryJob".to_string(),
+            ));
         }
+
+        let job_id = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to job ID group".to_string(),
+            ))?
+            .as_str()
+            .parse::<u64>()
+            .map_err(|_e| net_error::DeserializeError("Failed to parse job ID".to_string()))?;
+
+        Ok(HttpRequestType::GetQueryJob(
+            HttpRequestMetadata::from_preamble(preamble),
+            job_id,
+            HttpRequestType::get_page_query(query)?,
+        ))
     }
 
-    fn parse_error<R: Read>(
+    fn parse_post_query_job<R: Read>(
         _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
         fd: &mut R,
-    ) -> Result<HttpResponseType, net_error> {
-        if preamble.status_code < 400 || preamble.status_code > 599 {
-            return Err(net_error::DeserializeError(
-                "Inavlid response: not an error".to_string(),
-            ));
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostQueryJob ({})",
+                content_len
+            )));
         }
 
-        if preamble.content_type != HttpContentType::Text {
+        if preamble.content_type != Some(HttpContentType::JSON) {
             return Err(net_error::DeserializeError(
-                "Invalid error response: expected text/plain".to_string(),
+                "Invalid content-type: expected application/json".to_string(),
             ));
         }
 
-        let mut error_text = String::new();
-        fd.read_to_string(&mut error_text)
-            .map_err(net_error::ReadError)?;
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let body: QueryJobRequestBody = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let kind = body.to_query().map_err(net_error::DeserializeError)?;
 
-        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
-        let resp = match preamble.status_code {
-            400 => HttpResponseType::BadRequest(md, error_text),
-            401 => HttpResponseType::Unauthorized(md, error_text),
-            402 => HttpResponseType::PaymentRequired(md, error_text),
-            403 => HttpResponseType::Forbidden(md, error_text),
-            404 => HttpResponseType::NotFound(md, error_text),
-            500 => HttpResponseType::ServerError(md, error_text),
-            503 => HttpResponseType::ServiceUnavailable(md, error_text),
-            _ => HttpResponseType::Error(md, preamble.status_code, error_text),
-        };
-        Ok(resp)
+        Ok(HttpRequestType::PostQueryJob(
+            HttpRequestMetadata::from_preamble(preamble),
+            kind,
+        ))
     }
 
-    fn parse_bytestream<R: Read, T: StacksMessageCodec>(
-        preamble: &HttpResponsePreamble,
+    fn parse_post_mempool_snapshot<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
         fd: &mut R,
-        len_hint: Option<usize>,
-        max_len: u64,
-    ) -> Result<T, net_error> {
-        // content-type has to be Bytes
-        if preamble.content_type != HttpContentType::Bytes {
+    ) -> Result<HttpRequestType, net_error> {
+        let content_len = preamble.get_content_length();
+        if !(content_len > 0 && content_len < MAX_PAYLOAD_LEN) {
+            return Err(net_error::DeserializeError(format!(
+                "Invalid Http request: invalid body length for PostMemPoolSnapshot ({})",
+                content_len
+            )));
+        }
+
+        if preamble.content_type != Some(HttpContentType::JSON) {
             return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/octet-stream".to_string(),
+                "Invalid content-type: expected application/json".to_string(),
             ));
         }
 
-        let item: T = if preamble.is_chunked() && len_hint.is_none() {
-            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
-            read_next(&mut chunked_fd)?
-        } else {
-            let content_length_opt = match (preamble.content_length, len_hint) {
-                (Some(l), _) => Some(l as u32),
-                (None, Some(l)) => Some(l as u32),
-                (None, None) => None,
-            };
-            if let Some(content_length) = content_length_opt {
-                if (content_length as u64) > max_len {
-                    return Err(net_error::DeserializeError(
-                        "Invalid Content-Length header: too long".to_string(),
-                    ));
-                }
-
-                let mut bound_fd = BoundReader::from_reader(fd, content_length as u64);
-                read_next(&mut bound_fd)?
-            } else {
-                // unsupported headers
-                trace!("preamble: {:?}", preamble);
-                return Err(net_error::DeserializeError(
-                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
-                ));
-            }
-        };
+        let bound_fd = BoundReader::from_reader(fd, content_len as u64);
+        let hex_txs: Vec<String> = serde_json::from_reader(bound_fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse JSON body".into()))?;
+        let txs = decode_mempool_snapshot(&hex_txs)?;
 
-        Ok(item)
+        Ok(HttpRequestType::PostMemPoolSnapshot(
+            HttpRequestMetadata::from_preamble(preamble),
+            txs,
+        ))
     }
 
-    fn parse_json<R: Read, T: serde::de::DeserializeOwned>(
-        preamble: &HttpResponsePreamble,
+    fn parse_posttransaction<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
         fd: &mut R,
-        len_hint: Option<usize>,
-        max_len: u64,
-    ) -> Result<T, net_error> {
-        // content-type has to be JSON
-        if preamble.content_type != HttpContentType::JSON {
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
             return Err(net_error::DeserializeError(
-                "Invalid content-type: expected application/json".to_string(),
+                "Invalid Http request: expected non-zero-length body for PostTransaction"
+                    .to_string(),
             ));
         }
 
-        let item_result: Result<T, serde_json::Error> = if preamble.is_chunked()
-            && len_hint.is_none()
-        {
-            let chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
-            serde_json::from_reader(chunked_fd)
-        } else {
-            let content_length_opt = match (preamble.content_length, len_hint) {
-                (Some(l), _) => Some(l as u32),
-                (None, Some(l)) => Some(l as u32),
-                (None, None) => None,
-            };
-            if let Some(content_length) = content_length_opt {
-                if (content_length as u64) > max_len {
-                    return Err(net_error::DeserializeError(
-                        "Invalid Content-Length header: too long".to_string(),
-                    ));
-                }
-                let bound_fd = BoundReader::from_reader(fd, content_length as u64);
-                serde_json::from_reader(bound_fd)
-            } else {
-                // unsupported headers
-                trace!("preamble: {:?}", preamble);
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: PostTransaction body is too big".to_string(),
+            ));
+        }
+
+        if let Some(key) = preamble.headers.get("idempotency-key") {
+            if !is_valid_idempotency_key(key) {
                 return Err(net_error::DeserializeError(
-                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                    "Invalid Http request: Idempotency-Key must be 1 to 255 visible ASCII characters"
+                        .to_string(),
                 ));
             }
-        };
+        }
 
-        item_result.map_err(|e| {
-            if e.is_eof() {
-                net_error::UnderflowError(format!("Not enough bytes to parse JSON"))
+        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
+
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for transaction".to_string(),
+                ));
+            }
+            Some(HttpContentType::Bytes) => {
+                HttpRequestType::parse_posttransaction_octets(preamble, &mut bound_fd)
+            }
+            Some(HttpContentType::JSON) => {
+                HttpRequestType::parse_posttransaction_json(preamble, &mut bound_fd)
+            }
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Wrong Content-Type for transaction; expected application/json".to_string(),
+                ));
+            }
+        }
+    }
+
+    fn parse_posttransaction_octets<R: Read>(
+        preamble: &HttpRequestPreamble,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        let tx = StacksTransaction::consensus_deserialize(fd).map_err(|e| {
+            if let codec_error::DeserializeError(msg) = e {
+                net_error::ClientError(ClientError::Message(format!(
+                    "Failed to deserialize posted transaction: {}",
+                    msg
+                )))
             } else {
-                net_error::DeserializeError(format!("Failed to parse JSON: {:?}", &e))
+                e.into()
             }
-        })
+        })?;
+        Ok(HttpRequestType::PostTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            tx,
+            None,
+            None,
+        ))
     }
 
-    fn parse_text<R: Read>(
-        preamble: &HttpResponsePreamble,
+    fn parse_posttransaction_json<R: Read>(
+        preamble: &HttpRequestPreamble,
         fd: &mut R,
-        len_hint: Option<usize>,
-        max_len: u64,
-    ) -> Result<Vec<u8>, net_error> {
-        // content-type has to be text/plain
-        if preamble.content_type != HttpContentType::Text {
+    ) -> Result<HttpRequestType, net_error> {
+        let body: PostTransactionRequestBody = serde_json::from_reader(fd)
+            .map_err(|_e| net_error::DeserializeError("Failed to parse body".into()))?;
+
+        let tx = {
+            let tx_bytes = hex_bytes(&body.tx)
+                .map_err(|_e| net_error::DeserializeError("Failed to parse tx".into()))?;
+            StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|e| {
+                if let codec_error::DeserializeError(msg) = e {
+                    net_error::ClientError(ClientError::Message(format!(
+                        "Failed to deserialize posted transaction: {}",
+                        msg
+                    )))
+                } else {
+                    e.into()
+                }
+            })
+        }?;
+
+        let attachment = match body.attachment {
+            None => None,
+            Some(attachment_content) => {
+                let content = hex_bytes(&attachment_content).map_err(|_e| {
+                    net_error::DeserializeError("Failed to parse attachment".into())
+                })?;
+                Some(Attachment::new(content))
+            }
+        };
+
+        Ok(HttpRequestType::PostTransaction(
+            HttpRequestMetadata::from_preamble(preamble),
+            tx,
+            attachment,
+            body.expire_height,
+        ))
+    }
+
+    fn parse_postblock<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        regex: &Captures,
+        _query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
             return Err(net_error::DeserializeError(
-                "Invalid content-type: expected text/plain".to_string(),
+                "Invalid Http request: expected non-zero-length body for PostBlock".to_string(),
             ));
         }
-        let buf = if preamble.is_chunked() && len_hint.is_none() {
-            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
-            let mut buf = vec![];
-            chunked_fd
-                .read_to_end(&mut buf)
-                .map_err(net_error::ReadError)?;
-            buf
-        } else {
-            let content_length_opt = match (preamble.content_length, len_hint) {
-                (Some(l), _) => Some(l as u32),
-                (None, Some(l)) => Some(l as u32),
-                (None, None) => None,
-            };
-            if let Some(len) = content_length_opt {
-                let mut buf = vec![0u8; len as usize];
-                fd.read_exact(&mut buf).map_err(net_error::ReadError)?;
-                buf
-            } else {
-                // unsupported headers
-                trace!("preamble: {:?}", preamble);
+
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: PostBlock body is too big".to_string(),
+            ));
+        }
+
+        // content-type must be given, and must be application/octet-stream
+        match preamble.content_type {
+            None => {
                 return Err(net_error::DeserializeError(
-                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                    "Missing Content-Type for Stacks block".to_string(),
                 ));
             }
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for Stacks block; expected application/octet-stream"
+                            .to_string(),
+                    ));
+                }
+            }
         };
 
-        Ok(buf)
-    }
+        let consensus_hash_str = regex
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match consensus hash in path group".to_string(),
+            ))?
+            .as_str();
 
-    // len_hint is given by the StacksHttp protocol implementation
-    pub fn parse<R: Read>(
-        protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        request_path: String,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        if preamble.status_code >= 400 {
-            return HttpResponseType::parse_error(protocol, request_version, preamble, fd);
-        }
+        let consensus_hash: ConsensusHash =
+            ConsensusHash::from_hex(consensus_hash_str).map_err(|_| {
+                net_error::DeserializeError("Failed to parse consensus hash".to_string())
+            })?;
 
-        // TODO: make this static somehow
-        let RESPONSE_METHODS: &[(
-            &Regex,
-            &dyn Fn(
-                &mut StacksHttp,
-                HttpVersion,
-                &HttpResponsePreamble,
-                &mut R,
-                Option<usize>,
-            ) -> Result<HttpResponseType, net_error>,
-        )] = &[
-            (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
-            (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
-            (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
-            (&PATH_GETBLOCK, &HttpResponseType::parse_block),
-            (&PATH_GET_MAP_ENTRY, &HttpResponseType::parse_get_map_entry),
-            (
-                &PATH_GETMICROBLOCKS_INDEXED,
-                &HttpResponseType::parse_microblocks,
-            ),
-            (
-                &PATH_GETMICROBLOCKS_CONFIRMED,
-                &HttpResponseType::parse_microblocks,
-            ),
-            (
-                &PATH_GETMICROBLOCKS_UNCONFIRMED,
-                &HttpResponseType::parse_microblocks_unconfirmed,
-            ),
-            (
-                &PATH_GETTRANSACTION_UNCONFIRMED,
-                &HttpResponseType::parse_transaction_unconfirmed,
-            ),
-            (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
-            (
-                &PATH_POSTBLOCK,
-                &HttpResponseType::parse_stacks_block_accepted,
-            ),
-            (
-                &PATH_POSTMICROBLOCK,
-                &HttpResponseType::parse_microblock_hash,
-            ),
-            (&PATH_GET_ACCOUNT, &HttpResponseType::parse_get_account),
-            (
-                &PATH_GET_CONTRACT_SRC,
-                &HttpResponseType::parse_get_contract_src,
-            ),
-            (
-                &PATH_GET_IS_TRAIT_IMPLEMENTED,
-                &HttpResponseType::parse_get_is_trait_implemented,
-            ),
-            (
-                &PATH_GET_CONTRACT_ABI,
-                &HttpResponseType::parse_get_contract_abi,
-            ),
-            (
-                &PATH_POST_CALL_READ_ONLY,
-                &HttpResponseType::parse_call_read_only,
-            ),
-            (
-                &PATH_GET_ATTACHMENT,
-                &HttpResponseType::parse_get_attachment,
-            ),
-            (
-                &PATH_GET_ATTACHMENTS_INV,
-                &HttpResponseType::parse_get_attachments_inv,
-            ),
-        ];
+        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
+        let stacks_block = StacksBlock::consensus_deserialize(&mut bound_fd)?;
 
-        // use url::Url to parse path and query string
-        //   Url will refuse to parse just a path, so create a dummy URL
-        let local_url = format!("http://local{}", &request_path);
-        let url = Url::parse(&local_url).map_err(|_e| {
-            net_error::DeserializeError("Http request path could not be parsed".to_string())
-        })?;
+        Ok(HttpRequestType::PostBlock(
+            HttpRequestMetadata::from_preamble(preamble),
+            consensus_hash,
+            stacks_block,
+        ))
+    }
 
-        let decoded_path = percent_decode_str(url.path()).decode_utf8().map_err(|_e| {
-            net_error::DeserializeError(
-                "Http response path could not be parsed as UTF-8".to_string(),
-            )
-        })?;
+    fn parse_postmicroblock<R: Read>(
+        _protocol: &mut StacksHttp,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        query: Option<&str>,
+        fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() == 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected non-zero-length body for PostMicroblock"
+                    .to_string(),
+            ));
+        }
 
-        for (regex, parser) in RESPONSE_METHODS.iter() {
-            match HttpResponseType::try_parse(
-                protocol,
-                regex,
-                request_version,
-                preamble,
-                &decoded_path.to_string(),
-                fd,
-                len_hint,
-                parser,
-            ) {
-                Ok(Some(request)) => {
-                    return Ok(request);
-                }
-                Ok(None) => {
-                    continue;
-                }
-                Err(e) => {
-                    test_debug!("Failed to parse {}: {:?}", &request_path, &e);
-                    return Err(e);
+        if preamble.get_content_length() > MAX_PAYLOAD_LEN {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: PostMicroblock body is too big".to_string(),
+            ));
+        }
+
+        // content-type must be given, and must be application/octet-stream
+        match preamble.content_type {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Missing Content-Type for microblock".to_string(),
+                ));
+            }
+            Some(ref c) => {
+                if *c != HttpContentType::Bytes {
+                    return Err(net_error::DeserializeError(
+                        "Wrong Content-Type for microblock; expected application/octet-stream"
+                            .to_string(),
+                    ));
                 }
             }
-        }
+        };
 
-        test_debug!(
-            "Failed to match request path '{}' to a handler",
-            &request_path
-        );
-        return Err(net_error::DeserializeError(
-            "Http response could not be parsed".to_string(),
-        ));
-    }
+        let mut bound_fd = BoundReader::from_reader(fd, preamble.get_content_length() as u64);
 
-    fn parse_peerinfo<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let peer_info =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::PeerInfo(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            peer_info,
+        let mb = StacksMicroblock::consensus_deserialize(&mut bound_fd)?;
+        let tip = HttpRequestType::get_chain_tip_query(query);
+
+        Ok(HttpRequestType::PostMicroblock(
+            HttpRequestMetadata::from_preamble(preamble),
+            mb,
+            tip,
         ))
     }
 
-    fn parse_poxinfo<R: Read>(
+    fn parse_get_attachment<R: Read>(
         _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let pox_info =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::PoxInfo(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            pox_info,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        let hex_content_hash = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to attachment hash group".to_string(),
+            ))?
+            .as_str();
+
+        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| {
+            net_error::DeserializeError("Failed to construct hash160 from inputs".to_string())
+        })?;
+
+        Ok(HttpRequestType::GetAttachment(
+            HttpRequestMetadata::from_preamble(preamble),
+            content_hash,
         ))
     }
 
-    fn parse_neighbors<R: Read>(
+    fn parse_get_attachment_content<R: Read>(
         _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let neighbors_data =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Neighbors(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            neighbors_data,
+        preamble: &HttpRequestPreamble,
+        captures: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
+        let hex_content_hash = captures
+            .get(1)
+            .ok_or(net_error::DeserializeError(
+                "Failed to match path to attachment hash group".to_string(),
+            ))?
+            .as_str();
+
+        let content_hash = Hash160::from_hex(&hex_content_hash).map_err(|_| {
+            net_error::DeserializeError("Failed to construct hash160 from inputs".to_string())
+        })?;
+
+        let range = preamble
+            .headers
+            .get("range")
+            .and_then(|value| HttpByteRange::from_header(value));
+
+        Ok(HttpRequestType::GetAttachmentContent(
+            HttpRequestMetadata::from_preamble(preamble),
+            content_hash,
+            range,
         ))
     }
 
-    fn parse_block<R: Read>(
+    fn parse_get_attachments_inv<R: Read>(
         _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let block: StacksBlock =
-            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Block(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            block,
-        ))
-    }
-
-    fn parse_microblocks<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let microblocks: Vec<StacksMicroblock> =
-            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::Microblocks(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            microblocks,
-        ))
-    }
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
 
-    fn parse_get_account<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let account_entry =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetAccount(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            account_entry,
-        ))
-    }
+        let (index_block_hash, pages_indexes) = match query {
+            None => {
+                return Err(net_error::DeserializeError(
+                    "Invalid Http request: expecting index_block_hash and pages_indexes"
+                        .to_string(),
+                ));
+            }
+            Some(query) => {
+                let mut index_block_hash = None;
+                let mut pages_indexes = HashSet::new();
 
-    fn parse_get_map_entry<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let map_entry =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetMapEntry(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            map_entry,
-        ))
-    }
+                for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                    if key == "index_block_hash" {
+                        index_block_hash = match StacksBlockId::from_hex(&value) {
+                            Ok(index_block_hash) => Some(index_block_hash),
+                            _ => None,
+                        };
+                    } else if key == "pages_indexes" {
+                        if let Ok(pages_indexes_value) = value.parse::<String>() {
+                            for entry in pages_indexes_value.split(",") {
+                                if let Ok(page_index) = entry.parse::<u32>() {
+                                    pages_indexes.insert(page_index);
+                                }
+                            }
+                        }
+                    }
+                }
 
-    fn parse_get_contract_src<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let src_data =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetContractSrc(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            src_data,
-        ))
-    }
+                let index_block_hash = match index_block_hash {
+                    None => {
+                        return Err(net_error::DeserializeError(
+                            "Invalid Http request: expecting index_block_hash".to_string(),
+                        ));
+                    }
+                    Some(index_block_hash) => index_block_hash,
+                };
 
-    fn parse_get_is_trait_implemented<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let src_data =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetIsTraitImplemented(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            src_data,
-        ))
-    }
+                if pages_indexes.is_empty() {
+                    return Err(net_error::DeserializeError(
+                        "Invalid Http request: expecting pages_indexes".to_string(),
+                    ));
+                }
 
-    fn parse_get_contract_abi<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let abi = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::GetContractABI(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            abi,
-        ))
-    }
+                (index_block_hash, pages_indexes)
+            }
+        };
 
-    fn parse_call_read_only<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let call_data =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-        Ok(HttpResponseType::CallReadOnlyFunction(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            call_data,
+        Ok(HttpRequestType::GetAttachmentsInv(
+            HttpRequestMetadata::from_preamble(preamble),
+            index_block_hash,
+            pages_indexes,
         ))
     }
 
-    fn parse_microblocks_unconfirmed<R: Read>(
+    fn parse_get_attachments_batch<R: Read>(
         _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        // NOTE: there will be no length prefix on this, but we won't ever get more than
-        // MAX_MICROBLOCKS_UNCONFIRMED microblocks
-        let mut microblocks = vec![];
-        let max_len = len_hint.unwrap_or(MAX_MESSAGE_LEN as usize) as u64;
-        let mut bound_reader = BoundReader::from_reader(fd, max_len);
-        loop {
-            let mblock: StacksMicroblock = match read_next(&mut bound_reader) {
-                Ok(mblock) => Ok(mblock),
-                Err(e) => match e {
-                    codec_error::ReadError(ref ioe) => match ioe.kind() {
-                        io::ErrorKind::UnexpectedEof => {
-                            // end of stream -- this is fine
-                            break;
-                        }
-                        _ => Err(e),
-                    },
-                    _ => Err(e),
-                },
-            }?;
+        preamble: &HttpRequestPreamble,
+        _captures: &Captures,
+        query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        if preamble.get_content_length() != 0 {
+            return Err(net_error::DeserializeError(
+                "Invalid Http request: expected 0-length body".to_string(),
+            ));
+        }
 
-            microblocks.push(mblock);
-            if microblocks.len() == MAX_MICROBLOCKS_UNCONFIRMED {
-                break;
+        let mut content_hashes: Vec<Hash160> = vec![];
+        if let Some(query) = query {
+            for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+                if key != "hashes" {
+                    continue;
+                }
+                for entry in value.split(",") {
+                    let content_hash = Hash160::from_hex(entry).map_err(|_| {
+                        net_error::DeserializeError(
+                            "Failed to construct hash160 from inputs".to_string(),
+                        )
+                    })?;
+                    if !content_hashes.contains(&content_hash) {
+                        content_hashes.push(content_hash);
+                    }
+                }
             }
         }
-        Ok(HttpResponseType::Microblocks(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            microblocks,
-        ))
-    }
-
-    fn parse_transaction_unconfirmed<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let unconfirmed_status: UnconfirmedTransactionResponse =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-
-        // tx payload must decode to a transaction
-        let tx_bytes = hex_bytes(&unconfirmed_status.tx).map_err(|_| {
-            net_error::DeserializeError("Unconfirmed transaction is not hex-encoded".to_string())
-        })?;
-        let _ = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|_| {
-            net_error::DeserializeError(
-                "Unconfirmed transaction is not a well-formed Stacks transaction".to_string(),
-            )
-        })?;
-
-        Ok(HttpResponseType::UnconfirmedTransaction(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            unconfirmed_status,
-        ))
-    }
 
-    fn parse_txid<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let txid_hex: String = HttpResponseType::parse_json(preamble, fd, len_hint, 66)?;
-        if txid_hex.len() != 64 {
+        if content_hashes.is_empty() {
             return Err(net_error::DeserializeError(
-                "Invalid txid: expected 64 bytes".to_string(),
+                "Invalid Http request: expecting hashes".to_string(),
             ));
         }
 
-        let txid = Txid::from_hex(&txid_hex)
-            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
-        Ok(HttpResponseType::TransactionID(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            txid,
+        Ok(HttpRequestType::GetAttachmentsBatch(
+            HttpRequestMetadata::from_preamble(preamble),
+            content_hashes,
         ))
     }
 
-    fn parse_get_attachment<R: Read>(
+    fn parse_options_preflight<R: Read>(
         _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let res: GetAttachmentResponse =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-
-        Ok(HttpResponseType::GetAttachment(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            res,
-        ))
-    }
-
-    fn parse_get_attachments_inv<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let res: GetAttachmentsInvResponse =
-            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
-
-        Ok(HttpResponseType::GetAttachmentsInv(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            res,
-        ))
-    }
-
-    fn parse_stacks_block_accepted<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let stacks_block_accepted: StacksBlockAcceptedData =
-            HttpResponseType::parse_json(preamble, fd, len_hint, 128)?;
-        Ok(HttpResponseType::StacksBlockAccepted(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            stacks_block_accepted.stacks_block_id,
-            stacks_block_accepted.accepted,
+        preamble: &HttpRequestPreamble,
+        _regex: &Captures,
+        _query: Option<&str>,
+        _fd: &mut R,
+    ) -> Result<HttpRequestType, net_error> {
+        Ok(HttpRequestType::OptionsPreflight(
+            HttpRequestMetadata::from_preamble(preamble),
+            preamble.path.to_string(),
         ))
     }
 
-    fn parse_microblock_hash<R: Read>(
-        _protocol: &mut StacksHttp,
-        request_version: HttpVersion,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-        len_hint: Option<usize>,
-    ) -> Result<HttpResponseType, net_error> {
-        let mblock_hex: String = HttpResponseType::parse_json(preamble, fd, len_hint, 66)?;
-        if mblock_hex.len() != 64 {
-            return Err(net_error::DeserializeError(
-                "Invalid microblock hash: expected 64 bytes".to_string(),
-            ));
+    pub fn metadata(&self) -> &HttpRequestMetadata {
+        match *self {
+            HttpRequestType::GetInfo(ref md) => md,
+            HttpRequestType::GetFeatures(ref md) => md,
+            HttpRequestType::GetPoxInfo(ref md, _) => md,
+            HttpRequestType::GetStateHash(ref md, ..) => md,
+            HttpRequestType::GetNeighbors(ref md) => md,
+            HttpRequestType::GetNeighborWalkInfo(ref md) => md,
+            HttpRequestType::GetPeerVersions(ref md) => md,
+            HttpRequestType::GetNodeStatus(ref md) => md,
+            HttpRequestType::GetNodeIdentity(ref md, _) => md,
+            HttpRequestType::GetBlock(ref md, _) => md,
+            HttpRequestType::GetBlockDecoded(ref md, _) => md,
+            HttpRequestType::GetMicroblocksIndexed(ref md, _) => md,
+            HttpRequestType::GetMicroblocksIndexedDecoded(ref md, _) => md,
+            HttpRequestType::GetMicroblocksConfirmed(ref md, _) => md,
+            HttpRequestType::GetMicroblocksConfirmedDecoded(ref md, _) => md,
+            HttpRequestType::GetMicroblockForks(ref md, _) => md,
+            HttpRequestType::GetMicroblocksUnconfirmed(ref md, _, _) => md,
+            HttpRequestType::GetTransactionUnconfirmed(ref md, _) => md,
+            HttpRequestType::GetMemPoolDrops(ref md, _) => md,
+            HttpRequestType::GetMicroblockProof(ref md, _) => md,
+            HttpRequestType::GetAddressMempool(ref md, ..) => md,
+            HttpRequestType::GetMemPoolFees(ref md) => md,
+            HttpRequestType::SimulateBlock(ref md) => md,
+            HttpRequestType::GetContractCosts(ref md, ..) => md,
+            HttpRequestType::GetMemPoolSnapshot(ref md, _) => md,
+            HttpRequestType::PostMemPoolSnapshot(ref md, _) => md,
+            HttpRequestType::GetEventObserverStatus(ref md) => md,
+            HttpRequestType::GetEventReplays(ref md) => md,
+            HttpRequestType::PostEventReplay(ref md, _) => md,
+            HttpRequestType::GetPeerFilters(ref md) => md,
+            HttpRequestType::PostPeerFilter(ref md, ..) => md,
+            HttpRequestType::GetAtlasContracts(ref md) => md,
+            HttpRequestType::PostAtlasContract(ref md, ..) => md,
+            HttpRequestType::GetAttachmentPins(ref md) => md,
+            HttpRequestType::PostAttachmentPin(ref md, ..) => md,
+            HttpRequestType::GetBlockProcessing(ref md) => md,
+            HttpRequestType::PostBlockProcessing(ref md, ..) => md,
+            HttpRequestType::GetBlockFetch(ref md, ..) => md,
+            HttpRequestType::PostBlockFetch(ref md, ..) => md,
+            HttpRequestType::GetQueryJob(ref md, ..) => md,
+            HttpRequestType::PostQueryJob(ref md, _) => md,
+            HttpRequestType::PostTransaction(ref md, ..) => md,
+            HttpRequestType::PostBlock(ref md, ..) => md,
+            HttpRequestType::PostMicroblock(ref md, ..) => md,
+            HttpRequestType::GetAccount(ref md, ..) => md,
+            HttpRequestType::GetAccountUnlocks(ref md, ..) => md,
+            HttpRequestType::GetMinerRewards(ref md, ..) => md,
+            HttpRequestType::GetMapEntry(ref md, ..) => md,
+            HttpRequestType::GetTransferCost(ref md) => md,
+            HttpRequestType::GetContractABI(ref md, ..) => md,
+            HttpRequestType::GetContractSrc(ref md, ..) => md,
+            HttpRequestType::GetIsTraitImplemented(ref md, ..) => md,
+            HttpRequestType::VerifyContractSrc(ref md, ..) => md,
+            HttpRequestType::ValidateContractCall(ref md, ..) => md,
+            HttpRequestType::EncodeClarityValue(ref md, ..) => md,
+            HttpRequestType::DecodeClarityValue(ref md, ..) => md,
+            HttpRequestType::CallReadOnlyFunction(ref md, ..) => md,
+            HttpRequestType::OptionsPreflight(ref md, ..) => md,
+            HttpRequestType::GetAttachmentsInv(ref md, ..) => md,
+            HttpRequestType::GetAttachment(ref md, ..) => md,
+            HttpRequestType::GetAttachmentContent(ref md, ..) => md,
+            HttpRequestType::GetAttachmentsBatch(ref md, ..) => md,
+            HttpRequestType::ClientError(ref md, ..) => md,
         }
-
-        let mblock_hash = BlockHeaderHash::from_hex(&mblock_hex).map_err(|_e| {
-            net_error::DeserializeError("Failed to decode microblock hash hex".to_string())
-        })?;
-        Ok(HttpResponseType::MicroblockHash(
-            HttpResponseMetadata::from_preamble(request_version, preamble),
-            mblock_hash,
-        ))
     }
 
-    fn error_reason(code: u16) -> &'static str {
-        match code {
-            400 => "Bad Request",
-            401 => "Unauthorized",
-            402 => "Payment Required",
-            403 => "Forbidden",
-            404 => "Not Found",
-            500 => "Internal Server Error",
-            503 => "Service Temporarily Unavailable",
-            _ => "Error",
+    pub fn metadata_mut(&mut self) -> &mut HttpRequestMetadata {
+        match *self {
+            HttpRequestType::GetInfo(ref mut md) => md,
+            HttpRequestType::GetFeatures(ref mut md) => md,
+            HttpRequestType::GetPoxInfo(ref mut md, _) => md,
+            HttpRequestType::GetStateHash(ref mut md, ..) => md,
+            HttpRequestType::GetNeighbors(ref mut md) => md,
+            HttpRequestType::GetNeighborWalkInfo(ref mut md) => md,
+            HttpRequestType::GetPeerVersions(ref mut md) => md,
+            HttpRequestType::GetNodeStatus(ref mut md) => md,
+            HttpRequestType::GetNodeIdentity(ref mut md, _) => md,
+            HttpRequestType::GetBlock(ref mut md, _) => md,
+            HttpRequestType::GetBlockDecoded(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksIndexed(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksIndexedDecoded(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksConfirmed(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksConfirmedDecoded(ref mut md, _) => md,
+            HttpRequestType::GetMicroblockForks(ref mut md, _) => md,
+            HttpRequestType::GetMicroblocksUnconfirmed(ref mut md, _, _) => md,
+            HttpRequestType::GetTransactionUnconfirmed(ref mut md, _) => md,
+            HttpRequestType::GetMemPoolDrops(ref mut md, _) => md,
+            HttpRequestType::GetMicroblockProof(ref mut md, _) => md,
+            HttpRequestType::GetAddressMempool(ref mut md, ..) => md,
+            HttpRequestType::GetMemPoolFees(ref mut md) => md,
+            HttpRequestType::SimulateBlock(ref mut md) => md,
+            HttpRequestType::GetContractCosts(ref mut md, ..) => md,
+            HttpRequestType::GetMemPoolSnapshot(ref mut md, _) => md,
+            HttpRequestType::PostMemPoolSnapshot(ref mut md, _) => md,
+            HttpRequestType::GetEventObserverStatus(ref mut md) => md,
+            HttpRequestType::GetEventReplays(ref mut md) => md,
+            HttpRequestType::PostEventReplay(ref mut md, _) => md,
+            HttpRequestType::GetPeerFilters(ref mut md) => md,
+            HttpRequestType::PostPeerFilter(ref mut md, ..) => md,
+            HttpRequestType::GetAtlasContracts(ref mut md) => md,
+            HttpRequestType::PostAtlasContract(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentPins(ref mut md) => md,
+            HttpRequestType::PostAttachmentPin(ref mut md, ..) => md,
+            HttpRequestType::GetBlockProcessing(ref mut md) => md,
+            HttpRequestType::PostBlockProcessing(ref mut md, ..) => md,
+            HttpRequestType::GetBlockFetch(ref mut md, ..) => md,
+            HttpRequestType::PostBlockFetch(ref mut md, ..) => md,
+            HttpRequestType::GetQueryJob(ref mut md, ..) => md,
+            HttpRequestType::PostQueryJob(ref mut md, _) => md,
+            HttpRequestType::PostTransaction(ref mut md, ..) => md,
+            HttpRequestType::PostBlock(ref mut md, ..) => md,
+            HttpRequestType::PostMicroblock(ref mut md, ..) => md,
+            HttpRequestType::GetAccount(ref mut md, ..) => md,
+            HttpRequestType::GetAccountUnlocks(ref mut md, ..) => md,
+            HttpRequestType::GetMinerRewards(ref mut md, ..) => md,
+            HttpRequestType::GetMapEntry(ref mut md, ..) => md,
+            HttpRequestType::GetTransferCost(ref mut md) => md,
+            HttpRequestType::GetContractABI(ref mut md, ..) => md,
+            HttpRequestType::GetContractSrc(ref mut md, ..) => md,
+            HttpRequestType::GetIsTraitImplemented(ref mut md, ..) => md,
+            HttpRequestType::VerifyContractSrc(ref mut md, ..) => md,
+            HttpRequestType::ValidateContractCall(ref mut md, ..) => md,
+            HttpRequestType::EncodeClarityValue(ref mut md, ..) => md,
+            HttpRequestType::DecodeClarityValue(ref mut md, ..) => md,
+            HttpRequestType::CallReadOnlyFunction(ref mut md, ..) => md,
+            HttpRequestType::OptionsPreflight(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentsInv(ref mut md, ..) => md,
+            HttpRequestType::GetAttachment(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentContent(ref mut md, ..) => md,
+            HttpRequestType::GetAttachmentsBatch(ref mut md, ..) => md,
+            HttpRequestType::ClientError(ref mut md, ..) => md,
         }
     }
 
-    fn error_response<W: Write>(
-        &self,
-        fd: &mut W,
-        code: u16,
-        message: &str,
-    ) -> Result<(), net_error> {
-        let md = self.metadata();
-        HttpResponsePreamble::new_serialized(
-            fd,
-            code,
-            HttpResponseType::error_reason(code),
-            Some(message.len() as u32),
-            &HttpContentType::Text,
-            md.request_id,
-            |ref mut fd| keep_alive_headers(fd, md),
-        )?;
-        fd.write_all(message.as_bytes())
-            .map_err(net_error::WriteError)?;
-        Ok(())
-    }
-
-    pub fn metadata(&self) -> &HttpResponseMetadata {
-        match *self {
-            HttpResponseType::PeerInfo(ref md, _) => md,
-            HttpResponseType::PoxInfo(ref md, _) => md,
-            HttpResponseType::Neighbors(ref md, _) => md,
-            HttpResponseType::Block(ref md, _) => md,
-            HttpResponseType::BlockStream(ref md) => md,
-            HttpResponseType::Microblocks(ref md, _) => md,
-            HttpResponseType::MicroblockStream(ref md) => md,
-            HttpResponseType::TransactionID(ref md, _) => md,
-            HttpResponseType::StacksBlockAccepted(ref md, ..) => md,
-            HttpResponseType::MicroblockHash(ref md, _) => md,
-            HttpResponseType::TokenTransferCost(ref md, _) => md,
-            HttpResponseType::GetMapEntry(ref md, _) => md,
-            HttpResponseType::GetAccount(ref md, _) => md,
-            HttpResponseType::GetContractABI(ref md, _) => md,
-            HttpResponseType::GetContractSrc(ref md, _) => md,
-            HttpResponseType::GetIsTraitImplemented(ref md, _) => md,
-            HttpResponseType::CallReadOnlyFunction(ref md, _) => md,
-            HttpResponseType::UnconfirmedTransaction(ref md, _) => md,
-            HttpResponseType::GetAttachment(ref md, _) => md,
-            HttpResponseType::GetAttachmentsInv(ref md, _) => md,
-            HttpResponseType::OptionsPreflight(ref md) => md,
-            // errors
-            HttpResponseType::BadRequestJSON(ref md, _) => md,
-            HttpResponseType::BadRequest(ref md, _) => md,
-            HttpResponseType::Unauthorized(ref md, _) => md,
-            HttpResponseType::PaymentRequired(ref md, _) => md,
-            HttpResponseType::Forbidden(ref md, _) => md,
-            HttpResponseType::NotFound(ref md, _) => md,
-            HttpResponseType::ServerError(ref md, _) => md,
-            HttpResponseType::ServiceUnavailable(ref md, _) => md,
-            HttpResponseType::Error(ref md, _, _) => md,
+    fn make_query_string(tip_opt: Option<&StacksBlockId>, with_proof: bool) -> String {
+        if let Some(tip) = tip_opt {
+            format!("?tip={}{}", tip, if with_proof { "" } else { "&proof=0" })
+        } else if !with_proof {
+            format!("?proof=0")
+        } else {
+            "".to_string()
         }
     }
 
-    fn send_bytestream<W: Write, T: StacksMessageCodec>(
-        protocol: &mut StacksHttp,
-        md: &HttpResponseMetadata,
-        fd: &mut W,
-        message: &T,
-    ) -> Result<(), codec_error> {
-        if md.content_length.is_some() {
-            // have explicit content-length, so we can send as-is
-            write_next(fd, message)
+    fn make_page_query_string(page: &RPCPageRequest) -> String {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        if let Some(ref cursor) = page.cursor {
+            serializer.append_pair("cursor", cursor);
+        }
+        if let Some(limit) = page.limit {
+            serializer.append_pair("limit", &limit.to_string());
+        }
+        let query = serializer.finish();
+        if query.is_empty() {
+            query
         } else {
-            // no content-length, so send as chunk-encoded
-            let mut write_state = HttpChunkedTransferWriterState::new(protocol.chunk_size as usize);
-            let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
-            write_next(&mut encoder, message)?;
-            encoder.flush().map_err(codec_error::WriteError)?;
-            Ok(())
+            format!("?{}", query)
         }
     }
 
-    fn send_text<W: Write>(
-        protocol: &mut StacksHttp,
-        md: &HttpResponseMetadata,
-        fd: &mut W,
-        text: &[u8],
-    ) -> Result<(), net_error> {
-        if md.content_length.is_some() {
-            // have explicit content-length, so we can send as-is
-            fd.write_all(text).map_err(net_error::WriteError)
-        } else {
-            // no content-length, so send as chunk-encoded
-            let mut write_state = HttpChunkedTransferWriterState::new(protocol.chunk_size as usize);
-            let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
-            encoder.write_all(text).map_err(net_error::WriteError)?;
-            encoder.flush().map_err(net_error::WriteError)?;
-            Ok(())
+    /// Is this a request that peers make to download chain data from us (blocks, microblocks,
+    /// and attachments)?
+    pub fn is_chain_data_request(&self) -> bool {
+        match self {
+            HttpRequestType::GetBlock(..)
+            | HttpRequestType::GetMicroblocksIndexed(..)
+            | HttpRequestType::GetMicroblocksConfirmed(..)
+            | HttpRequestType::GetMicroblocksUnconfirmed(..)
+            | HttpRequestType::GetAttachment(..)
+            | HttpRequestType::GetAttachmentContent(..)
+            | HttpRequestType::GetAttachmentsBatch(..)
+            | HttpRequestType::GetAttachmentsInv(..) => true,
+            _ => false,
         }
     }
 
-    fn send_json<W: Write, T: serde::ser::Serialize>(
-        protocol: &mut StacksHttp,
-        md: &HttpResponseMetadata,
-        fd: &mut W,
-        message: &T,
-    ) -> Result<(), net_error> {
-        if md.content_length.is_some() {
-            // have explicit content-length, so we can send as-is
-            serde_json::to_writer(fd, message)
-                .map_err(|e| net_error::SerializeError(format!("Failed to send as JSON: {:?}", &e)))
-        } else {
-            // no content-length, so send as chunk-encoded
-            let mut write_state = HttpChunkedTransferWriterState::new(protocol.chunk_size as usize);
-            let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
-            serde_json::to_writer(&mut encoder, message).map_err(|e| {
-                net_error::SerializeError(format!("Failed to send as chunk-encoded JSON: {:?}", &e))
-            })?;
-            encoder.flush().map_err(net_error::WriteError)?;
-            Ok(())
+    /// Is this a request that stores data in our chain state or mempool, or that makes us
+    /// download chain data?
+    pub fn is_chain_write_request(&self) -> bool {
+        match self {
+            HttpRequestType::PostTransaction(..)
+            | HttpRequestType::PostBlock(..)
+            | HttpRequestType::PostMicroblock(..)
+            | HttpRequestType::PostMemPoolSnapshot(..)
+            | HttpRequestType::PostBlockFetch(..) => true,
+            _ => false,
         }
     }
 
-    pub fn send<W: Write>(&self, protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
-        match *self {
-            HttpResponseType::GetAccount(ref md, ref account_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, account_data)?;
+    pub fn request_path(&self) -> String {
+        let path = match self {
+            HttpRequestType::GetInfo(_md) => "/v2/info".to_string(),
+            HttpRequestType::GetFeatures(_md) => "/v2/features".to_string(),
+            HttpRequestType::GetPoxInfo(_md, tip_opt) => format!(
+                "/v2/pox{}",
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetStateHash(_md, height, tip_opt) => format!(
+                "/v2/state_hash/{}{}",
+                height,
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetNeighbors(_md) => "/v2/neighbors".to_string(),
+            HttpRequestType::GetNeighborWalkInfo(_md) => "/v2/neighbors/walk".to_string(),
+            HttpRequestType::GetPeerVersions(_md) => "/v2/neighbors/versions".to_string(),
+            HttpRequestType::GetNodeStatus(_md) => "/v2/status".to_string(),
+            HttpRequestType::GetNodeIdentity(_md, challenge_opt) => match challenge_opt {
+                Some(challenge) => format!("/v2/node_identity?challenge={}", to_hex(challenge)),
+                None => "/v2/node_identity".to_string(),
+            },
+            HttpRequestType::GetBlock(_md, block_hash) => {
+                format!("/v2/blocks/{}", block_hash.to_hex())
             }
-            HttpResponseType::GetContractABI(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, data)?;
+            HttpRequestType::GetBlockDecoded(_md, block_hash) => {
+                format!("/v2/blocks/{}?decode=true", block_hash.to_hex())
             }
-            HttpResponseType::GetContractSrc(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, data)?;
+            HttpRequestType::GetMicroblocksIndexed(_md, block_hash) => {
+                format!("/v2/microblocks/{}", block_hash.to_hex())
             }
-            HttpResponseType::GetIsTraitImplemented(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, data)?;
+            HttpRequestType::GetMicroblocksIndexedDecoded(_md, block_hash) => {
+                format!("/v2/microblocks/{}?decode=true", block_hash.to_hex())
             }
-            HttpResponseType::TokenTransferCost(ref md, ref cost) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, cost)?;
+            HttpRequestType::GetMicroblocksConfirmed(_md, block_hash) => {
+                format!("/v2/microblocks/confirmed/{}", block_hash.to_hex())
             }
-            HttpResponseType::CallReadOnlyFunction(ref md, ref data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, data)?;
+            HttpRequestType::GetMicroblocksConfirmedDecoded(_md, block_hash) => {
+                format!(
+                    "/v2/microblocks/confirmed/{}?decode=true",
+                    block_hash.to_hex()
+                )
             }
-            HttpResponseType::GetMapEntry(ref md, ref map_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, map_data)?;
+            HttpRequestType::GetMicroblockForks(_md, block_hash_opt) => match block_hash_opt {
+                Some(block_hash) => format!("/v2/microblocks/forks/{}", block_hash.to_hex()),
+                None => "/v2/microblocks/forks".to_string(),
+            },
+            HttpRequestType::GetMicroblocksUnconfirmed(_md, block_hash, min_seq) => format!(
+                "/v2/microblocks/unconfirmed/{}/{}",
+                block_hash.to_hex(),
+                min_seq
+            ),
+            HttpRequestType::GetTransactionUnconfirmed(_md, txid) => {
+                format!("/v2/transactions/unconfirmed/{}", txid)
             }
-            HttpResponseType::PeerInfo(ref md, ref peer_info) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, peer_info)?;
+            HttpRequestType::GetMemPoolDrops(_md, txid) => {
+                format!("/v2/mempool/dropped/{}", txid)
             }
-            HttpResponseType::PoxInfo(ref md, ref pox_info) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, pox_info)?;
+            HttpRequestType::GetMicroblockProof(_md, txid) => {
+                format!("/v2/microblocks/proof/{}", txid)
             }
-            HttpResponseType::Neighbors(ref md, ref neighbor_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
+            HttpRequestType::GetAddressMempool(_md, address, page) => format!(
+                "/v2/addresses/{}/mempool{}",
+                address,
+                HttpRequestType::make_page_query_string(page)
+            ),
+            HttpRequestType::GetMemPoolSnapshot(_md, page) => format!(
+                "/v2/admin/mempool/export{}",
+                HttpRequestType::make_page_query_string(page)
+            ),
+            HttpRequestType::PostMemPoolSnapshot(..) => "/v2/admin/mempool/import".to_string(),
+            HttpRequestType::GetEventObserverStatus(_md) => "/v2/admin/events/status".to_string(),
+            HttpRequestType::GetEventReplays(_md) => "/v2/admin/events/replay".to_string(),
+            HttpRequestType::PostEventReplay(..) => "/v2/admin/events/replay".to_string(),
+            HttpRequestType::GetPeerFilters(_md) => "/v2/admin/peers/filters".to_string(),
+            HttpRequestType::PostPeerFilter(..) => "/v2/admin/peers/filters".to_string(),
+            HttpRequestType::GetAtlasContracts(_md) => "/v2/admin/atlas/contracts".to_string(),
+            HttpRequestType::PostAtlasContract(..) => "/v2/admin/atlas/contracts".to_string(),
+            HttpRequestType::GetAttachmentPins(_md) => "/v2/admin/atlas/pins".to_string(),
+            HttpRequestType::PostAttachmentPin(..) => "/v2/admin/atlas/pins".to_string(),
+            HttpRequestType::GetBlockProcessing(_md) => "/v2/admin/blocks/processing".to_string(),
+            HttpRequestType::PostBlockProcessing(..) => "/v2/admin/blocks/processing".to_string(),
+            HttpRequestType::GetBlockFetch(_md, block_hash, microblocks)
+            | HttpRequestType::PostBlockFetch(_md, block_hash, microblocks) => format!(
+                "/v2/blocks/fetch/{}{}",
+                block_hash,
+                if *microblocks { "?microblocks=1" } else { "" }
+            ),
+            HttpRequestType::GetQueryJob(_md, job_id, page) => format!(
+                "/v2/jobs/{}{}",
+                job_id,
+                HttpRequestType::make_page_query_string(page)
+            ),
+            HttpRequestType::PostQueryJob(..) => "/v2/jobs".to_string(),
+            HttpRequestType::PostTransaction(_md, ..) => "/v2/transactions".to_string(),
+            HttpRequestType::PostBlock(_md, ch, ..) => format!("/v2/blocks/upload/{}", &ch),
+            HttpRequestType::PostMicroblock(_md, _, tip_opt) => format!(
+                "/v2/microblocks{}",
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetAccount(_md, principal, tip_opt, with_proof) => format!(
+                "/v2/accounts/{}{}",
+                &principal.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
+            ),
+            HttpRequestType::GetAccountUnlocks(_md, principal, tip_opt) => format!(
+                "/v2/accounts/{}/unlocks{}",
+                &principal.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetMinerRewards(_md, address, tip_opt) => format!(
+                "/v2/miner_rewards/{}{}",
+                &address.to_string(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetMapEntry(
+                _md,
+                contract_addr,
+                contract_name,
+                map_name,
+                _key,
+                tip_opt,
+                with_proof,
+            ) => format!(
+                "/v2/map_entry/{}/{}/{}{}",
+                &contract_addr.to_string(),
+                contract_name.as_str(),
+                map_name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
+            ),
+            HttpRequestType::GetTransferCost(_md) => "/v2/fees/transfer".into(),
+            HttpRequestType::GetMemPoolFees(_md) => "/v2/fees/mempool".into(),
+            HttpRequestType::SimulateBlock(_md) => "/v2/mempool/simulate_block".into(),
+            HttpRequestType::GetContractCosts(_md, from_height, to_height, limit) => {
+                let query: Vec<String> = vec![
+                    from_height.map(|h| format!("from_height={}", h)),
+                    to_height.map(|h| format!("to_height={}", h)),
+                    limit.map(|l| format!("limit={}", l)),
+                ]
+                .into_iter()
+                .filter_map(|param| param)
+                .collect();
+                if query.is_empty() {
+                    "/v2/contracts/costs".to_string()
+                } else {
+                    format!("/v2/contracts/costs?{}", query.join("&"))
+                }
             }
-            HttpResponseType::GetAttachment(ref md, ref zonefile_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
+            HttpRequestType::GetContractABI(_, contract_addr, contract_name, tip_opt) => format!(
+                "/v2/contracts/interface/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::GetContractSrc(
+                _,
+                contract_addr,
+                contract_name,
+                tip_opt,
+                with_proof,
+            ) => format!(
+                "/v2/contracts/source/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), *with_proof)
+            ),
+            HttpRequestType::GetIsTraitImplemented(
+                _,
+                contract_addr,
+                contract_name,
+                trait_id,
+                tip_opt,
+            ) => format!(
+                "/v2/traits/{}/{}/{}/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                trait_id.name.to_string(),
+                StacksAddress::from(trait_id.clone().contract_identifier.issuer),
+                trait_id.contract_identifier.name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::CallReadOnlyFunction(
+                _,
+                contract_addr,
+                contract_name,
+                _,
+                func_name,
+                _,
+                tip_opt,
+                at_block_opt,
+            ) => {
+                let query = HttpRequestType::make_query_string(tip_opt.as_ref(), true);
+                let at_block_query = match at_block_opt {
+                    Some(AtBlock::Height(height)) => format!("at_height={}", height),
+                    Some(AtBlock::BlockId(block_id)) => format!("at_block={}", block_id),
+                    None => "".to_string(),
+                };
+                let query = match (query.len(), at_block_query.len()) {
+                    (_, 0) => query,
+                    (0, _) => format!("?{}", at_block_query),
+                    (_, _) => format!("{}&{}", query, at_block_query),
+                };
+                format!(
+                    "/v2/contracts/call-read/{}/{}/{}{}",
+                    contract_addr,
+                    contract_name.as_str(),
+                    func_name.as_str(),
+                    query
+                )
             }
-            HttpResponseType::GetAttachmentsInv(ref md, ref zonefile_data) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
+            HttpRequestType::VerifyContractSrc(_, contract_addr, contract_name, _, tip_opt) => {
+                format!(
+                    "/v2/contracts/verify/{}/{}{}",
+                    contract_addr,
+                    contract_name.as_str(),
+                    HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+                )
             }
-            HttpResponseType::Block(ref md, ref block) => {
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    md.content_length.clone(),
-                    &HttpContentType::Bytes,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_bytestream(protocol, md, fd, block)?;
+            HttpRequestType::ValidateContractCall(
+                _,
+                contract_addr,
+                contract_name,
+                func_name,
+                _,
+                tip_opt,
+            ) => format!(
+                "/v2/contracts/validate-call/{}/{}/{}{}",
+                contract_addr,
+                contract_name.as_str(),
+                func_name.as_str(),
+                HttpRequestType::make_query_string(tip_opt.as_ref(), true)
+            ),
+            HttpRequestType::EncodeClarityValue(..) => "/v2/clarity/value/encode".to_string(),
+            HttpRequestType::DecodeClarityValue(..) => "/v2/clarity/value/decode".to_string(),
+            HttpRequestType::OptionsPreflight(_md, path) => path.to_string(),
+            HttpRequestType::GetAttachmentsInv(_md, index_block_hash, pages_indexes) => {
+                let pages_query = match pages_indexes.len() {
+                    0 => format!(""),
+                    _n => {
+                        let mut indexes = pages_indexes
+                            .iter()
+                            .map(|i| format!("{}", i))
+                            .collect::<Vec<String>>();
+                        indexes.sort();
+                        format!("&pages_indexes={}", indexes.join(","))
+                    }
+                };
+                let index_block_hash = format!("index_block_hash={}", index_block_hash);
+                format!("/v2/attachments/inv?{}{}", index_block_hash, pages_query,)
             }
-            HttpResponseType::BlockStream(ref md) => {
-                // only send the preamble.  The caller will need to figure out how to send along
-                // the block data itself.
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    None,
-                    &HttpContentType::Bytes,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
+            HttpRequestType::GetAttachment(_, content_hash) => {
+                format!("/v2/attachments/{}", to_hex(&content_hash.0[..]))
             }
-            HttpResponseType::Microblocks(ref md, ref microblocks) => {
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    md.content_length.clone(),
-                    &HttpContentType::Bytes,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_bytestream(protocol, md, fd, microblocks)?;
+            HttpRequestType::GetAttachmentContent(_, content_hash, _) => {
+                format!("/v2/attachments/{}/content", to_hex(&content_hash.0[..]))
             }
-            HttpResponseType::MicroblockStream(ref md) => {
-                // only send the preamble.  The caller will need to figure out how to send along
-                // the microblock data itself.
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    None,
-                    &HttpContentType::Bytes,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
+            HttpRequestType::GetAttachmentsBatch(_, content_hashes) => {
+                let hashes = content_hashes
+                    .iter()
+                    .map(|content_hash| to_hex(&content_hash.0[..]))
+                    .collect::<Vec<String>>();
+                format!("/v2/attachments?hashes={}", hashes.join(","))
             }
-            HttpResponseType::TransactionID(ref md, ref txid) => {
-                let txid_bytes = txid.to_hex();
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    md.content_length.clone(),
-                    &HttpContentType::JSON,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_json(protocol, md, fd, &txid_bytes)?;
+            HttpRequestType::ClientError(_md, e) => match e {
+                ClientError::NotFound(path) => path.to_string(),
+                _ => "error path unknown".into(),
+            },
+        };
+        self.metadata().api_version.rewrite_path(&path)
+    }
+
+    pub fn get_path(&self) -> &str {
+        match self {
+            HttpRequestType::GetInfo(..) => "/v2/info",
+            HttpRequestType::GetFeatures(..) => "/v2/features",
+            HttpRequestType::GetPoxInfo(..) => "/v2/pox",
+            HttpRequestType::GetStateHash(..) => "/v2/state_hash/:height",
+            HttpRequestType::GetNeighbors(..) => "/v2/neighbors",
+            HttpRequestType::GetNeighborWalkInfo(..) => "/v2/neighbors/walk",
+            HttpRequestType::GetPeerVersions(..) => "/v2/neighbors/versions",
+            HttpRequestType::GetNodeStatus(..) => "/v2/status",
+            HttpRequestType::GetNodeIdentity(..) => "/v2/node_identity",
+            HttpRequestType::GetBlock(..) | HttpRequestType::GetBlockDecoded(..) => {
+                "/v2/blocks/:hash"
             }
-            HttpResponseType::StacksBlockAccepted(ref md, ref stacks_block_id, ref accepted) => {
-                let accepted_data = StacksBlockAcceptedData {
-                    stacks_block_id: stacks_block_id.clone(),
-                    accepted: *accepted,
-                };
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    md.content_length.clone(),
-                    &HttpContentType::JSON,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_json(protocol, md, fd, &accepted_data)?;
+            HttpRequestType::GetMicroblocksIndexed(..)
+            | HttpRequestType::GetMicroblocksIndexedDecoded(..) => "/v2/microblocks/:hash",
+            HttpRequestType::GetMicroblocksConfirmed(..)
+            | HttpRequestType::GetMicroblocksConfirmedDecoded(..) => {
+                "/v2/microblocks/confirmed/:hash"
             }
-            HttpResponseType::MicroblockHash(ref md, ref mblock_hash) => {
-                let mblock_bytes = mblock_hash.to_hex();
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    md.content_length.clone(),
-                    &HttpContentType::JSON,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_json(protocol, md, fd, &mblock_bytes)?;
+            HttpRequestType::GetMicroblockForks(..) => "/v2/microblocks/forks/:hash",
+            HttpRequestType::GetMicroblocksUnconfirmed(..) => {
+                "/v2/microblocks/unconfirmed/:hash/:seq"
             }
-            HttpResponseType::UnconfirmedTransaction(ref md, ref unconfirmed_status) => {
-                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
-                HttpResponseType::send_json(protocol, md, fd, unconfirmed_status)?;
+            HttpRequestType::GetTransactionUnconfirmed(..) => "/v2/transactions/unconfirmed/:txid",
+            HttpRequestType::GetMemPoolDrops(..) => "/v2/mempool/dropped/:txid",
+            HttpRequestType::GetMicroblockProof(..) => "/v2/microblocks/proof/:txid",
+            HttpRequestType::GetAddressMempool(..) => "/v2/addresses/:principal/mempool",
+            HttpRequestType::GetMemPoolSnapshot(..) => "/v2/admin/mempool/export",
+            HttpRequestType::PostMemPoolSnapshot(..) => "/v2/admin/mempool/import",
+            HttpRequestType::GetEventObserverStatus(..) => "/v2/admin/events/status",
+            HttpRequestType::GetEventReplays(..) => "/v2/admin/events/replay",
+            HttpRequestType::PostEventReplay(..) => "/v2/admin/events/replay",
+            HttpRequestType::GetPeerFilters(..) => "/v2/admin/peers/filters",
+            HttpRequestType::PostPeerFilter(..) => "/v2/admin/peers/filters",
+            HttpRequestType::GetAtlasContracts(..) => "/v2/admin/atlas/contracts",
+            HttpRequestType::PostAtlasContract(..) => "/v2/admin/atlas/contracts",
+            HttpRequestType::GetAttachmentPins(..) => "/v2/admin/atlas/pins",
+            HttpRequestType::PostAttachmentPin(..) => "/v2/admin/atlas/pins",
+            HttpRequestType::GetBlockProcessing(..) => "/v2/admin/blocks/processing",
+            HttpRequestType::PostBlockProcessing(..) => "/v2/admin/blocks/processing",
+            HttpRequestType::GetBlockFetch(..) | HttpRequestType::PostBlockFetch(..) => {
+                "/v2/blocks/fetch/:hash"
             }
-            HttpResponseType::OptionsPreflight(ref md) => {
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    200,
-                    "OK",
-                    None,
-                    &HttpContentType::Text,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_text(protocol, md, fd, "".as_bytes())?;
-            }
-            HttpResponseType::BadRequestJSON(ref md, ref data) => {
-                HttpResponsePreamble::new_serialized(
-                    fd,
-                    400,
-                    HttpResponseType::error_reason(400),
-                    md.content_length.clone(),
-                    &HttpContentType::JSON,
-                    md.request_id,
-                    |ref mut fd| keep_alive_headers(fd, md),
-                )?;
-                HttpResponseType::send_json(protocol, md, fd, data)?;
+            HttpRequestType::GetQueryJob(..) => "/v2/jobs/:id",
+            HttpRequestType::PostQueryJob(..) => "/v2/jobs",
+            HttpRequestType::PostTransaction(..) => "/v2/transactions",
+            HttpRequestType::PostBlock(..) => "/v2/blocks/upload/:block",
+            HttpRequestType::PostMicroblock(..) => "/v2/microblocks",
+            HttpRequestType::GetAccount(..) => "/v2/accounts/:principal",
+            HttpRequestType::GetAccountUnlocks(..) => "/v2/accounts/:principal/unlocks",
+            HttpRequestType::GetMinerRewards(..) => "/v2/miner_rewards/:principal",
+            HttpRequestType::GetMapEntry(..) => "/v2/map_entry/:principal/:contract_name/:map_name",
+            HttpRequestType::GetTransferCost(..) => "/v2/fees/transfer",
+            HttpRequestType::GetMemPoolFees(..) => "/v2/fees/mempool",
+            HttpRequestType::SimulateBlock(..) => "/v2/mempool/simulate_block",
+            HttpRequestType::GetContractCosts(..) => "/v2/contracts/costs",
+            HttpRequestType::GetContractABI(..) => {
+                "/v2/contracts/interface/:principal/:contract_name"
             }
-            HttpResponseType::BadRequest(_, ref msg) => self.error_response(fd, 400, msg)?,
-            HttpResponseType::Unauthorized(_, ref msg) => self.error_response(fd, 401, msg)?,
-            HttpResponseType::PaymentRequired(_, ref msg) => self.error_response(fd, 402, msg)?,
-            HttpResponseType::Forbidden(_, ref msg) => self.error_response(fd, 403, msg)?,
-            HttpResponseType::NotFound(_, ref msg) => self.error_response(fd, 404, msg)?,
-            HttpResponseType::ServerError(_, ref msg) => self.error_response(fd, 500, msg)?,
-            HttpResponseType::ServiceUnavailable(_, ref msg) => {
-                self.error_response(fd, 503, msg)?
+            HttpRequestType::GetContractSrc(..) => "/v2/contracts/source/:principal/:contract_name",
+            HttpRequestType::CallReadOnlyFunction(..) => {
+                "/v2/contracts/call-read/:principal/:contract_name/:func_name"
             }
-            HttpResponseType::Error(_, ref error_code, ref msg) => {
-                self.error_response(fd, *error_code, msg)?
+            HttpRequestType::GetAttachmentsInv(..) => "/v2/attachments/inv",
+            HttpRequestType::GetAttachment(..) => "/v2/attachments/:hash",
+            HttpRequestType::GetAttachmentContent(..) => "/v2/attachments/:hash/content",
+            HttpRequestType::GetAttachmentsBatch(..) => "/v2/attachments",
+            HttpRequestType::GetIsTraitImplemented(..) => "/v2/traits/:principal/:contract_name",
+            HttpRequestType::VerifyContractSrc(..) => {
+                "/v2/contracts/verify/:principal/:contract_name"
             }
-        };
-        Ok(())
-    }
-}
-
-impl StacksMessageCodec for StacksHttpPreamble {
-    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
-        match *self {
-            StacksHttpPreamble::Request(ref req) => req.consensus_serialize(fd),
-            StacksHttpPreamble::Response(ref res) => res.consensus_serialize(fd),
-        }
-    }
-
-    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StacksHttpPreamble, codec_error> {
-        let mut retry_fd = RetryReader::new(fd);
-
-        // the byte stream can decode to a http request or a http response, but not both.
-        match HttpRequestPreamble::consensus_deserialize(&mut retry_fd) {
-            Ok(request) => Ok(StacksHttpPreamble::Request(request)),
-            Err(e_request) => {
-                // maybe a http response?
-                retry_fd.set_position(0);
-                match HttpResponsePreamble::consensus_deserialize(&mut retry_fd) {
-                    Ok(response) => Ok(StacksHttpPreamble::Response(response)),
-                    Err(e) => {
-                        // underflow?
-                        match (e_request, e) {
-                            (codec_error::ReadError(ref ioe1), codec_error::ReadError(ref ioe2)) => {
-                                if ioe1.kind() == io::ErrorKind::UnexpectedEof && ioe2.kind() == io::ErrorKind::UnexpectedEof {
-                                    // out of bytes
-                                    Err(codec_error::UnderflowError("Not enough bytes to form a HTTP request or response".to_string()))
-                                }
-                                else {
-                                    Err(codec_error::DeserializeError(format!("Neither a HTTP request ({:?}) or HTTP response ({:?})", ioe1, ioe2)))
-                                }
-                            },
-                            (e_req, e_res) => Err(codec_error::DeserializeError(format!("Failed to decode HTTP request or HTTP response (request error: {:?}; response error: {:?})", &e_req, &e_res)))
-                        }
-                    }
-                }
+            HttpRequestType::ValidateContractCall(..) => {
+                "/v2/contracts/validate-call/:principal/:contract_name/:func_name"
             }
+            HttpRequestType::EncodeClarityValue(..) => "/v2/clarity/value/encode",
+            HttpRequestType::DecodeClarityValue(..) => "/v2/clarity/value/decode",
+            HttpRequestType::OptionsPreflight(..) | HttpRequestType::ClientError(..) => "/",
         }
     }
-}
-
-impl MessageSequence for StacksHttpMessage {
-    fn request_id(&self) -> u32 {
-        // there is at most one in-flight HTTP request, as far as a Connection<P> is concerned
-        HTTP_REQUEST_ID_RESERVED
-    }
 
-    fn get_message_name(&self) -> &'static str {
-        match *self {
-            StacksHttpMessage::Request(ref req) => match req {
-                HttpRequestType::GetInfo(_) => "HTTP(GetInfo)",
-                HttpRequestType::GetPoxInfo(_, _) => "HTTP(GetPoxInfo)",
-                HttpRequestType::GetNeighbors(_) => "HTTP(GetNeighbors)",
-                HttpRequestType::GetBlock(_, _) => "HTTP(GetBlock)",
-                HttpRequestType::GetMicroblocksIndexed(_, _) => "HTTP(GetMicroblocksIndexed)",
-                HttpRequestType::GetMicroblocksConfirmed(_, _) => "HTTP(GetMicroblocksConfirmed)",
-                HttpRequestType::GetMicroblocksUnconfirmed(_, _, _) => {
-                    "HTTP(GetMicroblocksUnconfirmed)"
-                }
-                HttpRequestType::GetTransactionUnconfirmed(_, _) => {
-                    "HTTP(GetTransactionUnconfirmed)"
-                }
-                HttpRequestType::PostTransaction(_, _, _) => "HTTP(PostTransaction)",
-                HttpRequestType::PostBlock(..) => "HTTP(PostBlock)",
-                HttpRequestType::PostMicroblock(..) => "HTTP(PostMicroblock)",
-                HttpRequestType::GetAccount(..) => "HTTP(GetAccount)",
-                HttpRequestType::GetMapEntry(..) => "HTTP(GetMapEntry)",
-                HttpRequestType::GetTransferCost(_) => "HTTP(GetTransferCost)",
-                HttpRequestType::GetContractABI(..) => "HTTP(GetContractABI)",
-                HttpRequestType::GetContractSrc(..) => "HTTP(GetContractSrc)",
-                HttpRequestType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
-                HttpRequestType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
-                HttpRequestType::GetAttachment(..) => "HTTP(GetAttachment)",
-                HttpRequestType::GetAttachmentsInv(..) => "HTTP(GetAttachmentsInv)",
-                HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
-                HttpRequestType::ClientError(..) => "HTTP(ClientError)",
-            },
-            StacksHttpMessage::Response(ref res) => match res {
-                HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
-                HttpResponseType::GetMapEntry(_, _) => "HTTP(GetMapEntry)",
-                HttpResponseType::GetAccount(_, _) => "HTTP(GetAccount)",
-                HttpResponseType::GetContractABI(..) => "HTTP(GetContractABI)",
-                HttpResponseType::GetContractSrc(..) => "HTTP(GetContractSrc)",
-                HttpResponseType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
-                HttpResponseType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
-                HttpResponseType::GetAttachment(_, _) => "HTTP(GetAttachment)",
-                HttpResponseType::GetAttachmentsInv(_, _) => "HTTP(GetAttachmentsInv)",
-                HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
-                HttpResponseType::PoxInfo(_, _) => "HTTP(PeerInfo)",
-                HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
-                HttpResponseType::Block(_, _) => "HTTP(Block)",
-                HttpResponseType::BlockStream(_) => "HTTP(BlockStream)",
-                HttpResponseType::Microblocks(_, _) => "HTTP(Microblocks)",
-                HttpResponseType::MicroblockStream(_) => "HTTP(MicroblockStream)",
-                HttpResponseType::TransactionID(_, _) => "HTTP(Transaction)",
-                HttpResponseType::StacksBlockAccepted(..) => "HTTP(StacksBlockAccepted)",
-                HttpResponseType::MicroblockHash(_, _) => "HTTP(MicroblockHash)",
-                HttpResponseType::UnconfirmedTransaction(_, _) => "HTTP(UnconfirmedTransaction)",
-                HttpResponseType::OptionsPreflight(_) => "HTTP(OptionsPreflight)",
-                HttpResponseType::BadRequestJSON(..) | HttpResponseType::BadRequest(..) => {
-                    "HTTP(400)"
-                }
-                HttpResponseType::Unauthorized(_, _) => "HTTP(401)",
-                HttpResponseType::PaymentRequired(_, _) => "HTTP(402)",
-                HttpResponseType::Forbidden(_, _) => "HTTP(403)",
-                HttpResponseType::NotFound(_, _) => "HTTP(404)",
-                HttpResponseType::ServerError(_, _) => "HTTP(500)",
-                HttpResponseType::ServiceUnavailable(_, _) => "HTTP(503)",
-                HttpResponseType::Error(_, _, _) => "HTTP(other)",
-            },
-        }
+    /// If this request was made against a deprecated route, get the notice to send back
+    pub fn api_deprecation(&self) -> Option<HttpApiDeprecation> {
+        let api_version = self.metadata().api_version;
+        let route = api_version.rewrite_path(self.get_path());
+        HTTP_API_DEPRECATIONS
+            .iter()
+            .find(|(path, _, _)| *path == route)
+            .map(|(_, successor, sunset)| HttpApiDeprecation {
+                successor: successor.rewrite_path(&self.request_path()),
+                sunset: sunset.clone(),
+            })
     }
-}
-
-/// A partially-decoded, streamed HTTP message (response) being received.
-/// Internally used by StacksHttp to keep track of chunk-decoding state.
-#[derive(Debug, Clone, PartialEq)]
-struct HttpRecvStream {
-    state: HttpChunkedTransferReaderState,
-    data: Vec<u8>,
-    total_consumed: usize, // number of *encoded* bytes consumed
-}
 
-impl HttpRecvStream {
-    pub fn new(max_size: u64) -> HttpRecvStream {
-        HttpRecvStream {
-            state: HttpChunkedTransferReaderState::new(max_size),
-            data: vec![],
-            total_consumed: 0,
-        }
-    }
+    pub fn send<W: Write>(&self, _protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
+        match self {
+            HttpRequestType::PostTransaction(md, tx, attachment, expire_height) => {
+                let mut tx_bytes = vec![];
+                write_next(&mut tx_bytes, tx)?;
+                let tx_hex = to_hex(&tx_bytes[..]);
 
-    /// Feed data into our chunked transfer reader state.  If we finish reading a stream, return
-    /// the decoded bytes (as Some(Vec<u8>) and the total number of encoded bytes consumed).
-    /// Always returns the number of bytes consumed.
-    pub fn consume_data<R: Read>(
-        &mut self,
-        fd: &mut R,
-    ) -> Result<(Option<(Vec<u8>, usize)>, usize), net_error> {
-        let mut consumed = 0;
-        let mut blocked = false;
-        while !blocked {
-            let mut decoded_buf = vec![0u8; 8192];
-            let (read_pass, consumed_pass) = match self.state.do_read(fd, &mut decoded_buf) {
-                Ok((0, num_consumed)) => {
-                    trace!(
-                        "consume_data blocked on 0 decoded bytes ({} consumed)",
-                        num_consumed
-                    );
-                    blocked = true;
-                    (0, num_consumed)
-                }
-                Ok((num_read, num_consumed)) => (num_read, num_consumed),
-                Err(e) => match e.kind() {
-                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
-                        trace!("consume_data blocked on read error");
-                        blocked = true;
-                        (0, 0)
+                let (content_type, request_body_bytes) = match (attachment, expire_height) {
+                    (None, None) => {
+                        // Transaction does not include an attachment or expiration: HttpContentType::Bytes (more compressed)
+                        (Some(&HttpContentType::Bytes), tx_bytes)
                     }
-                    _ => {
-                        return Err(net_error::ReadError(e));
+                    (attachment, expire_height) => {
+                        // Transaction is including an attachment or expiration: HttpContentType::JSON
+                        let request_body = PostTransactionRequestBody {
+                            tx: tx_hex,
+                            attachment: attachment
+                                .as_ref()
+                                .map(|attachment| to_hex(&attachment.content[..])),
+                            expire_height: *expire_height,
+                        };
+
+                        let mut request_body_bytes = vec![];
+                        serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(
+                            |e| {
+                                net_error::SerializeError(format!(
+                                    "Failed to serialize read-only call to JSON: {:?}",
+                                    &e
+                                ))
+                            },
+                        )?;
+                        (Some(&HttpContentType::JSON), request_body_bytes)
                     }
-                },
-            };
+                };
 
-            consumed += consumed_pass;
-            if read_pass > 0 {
-                self.data.extend_from_slice(&decoded_buf[0..read_pass]);
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    content_type,
+                    |ref mut fd| idempotency_key_headers(fd, md),
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
             }
-        }
+            HttpRequestType::PostBlock(md, _ch, block) => {
+                let mut block_bytes = vec![];
+                write_next(&mut block_bytes, block)?;
 
-        self.total_consumed += consumed;
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(block_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    empty_headers,
+                )?;
+                fd.write_all(&block_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostMicroblock(md, mb, ..) => {
+                let mut mb_bytes = vec![];
+                write_next(&mut mb_bytes, mb)?;
 
-        // did we get a message?
-        if self.state.is_eof() {
-            // reset
-            let message_data = mem::replace(&mut self.data, vec![]);
-            let total_consumed = self.total_consumed;
-
-            self.state = HttpChunkedTransferReaderState::new(self.state.max_size);
-            self.total_consumed = 0;
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(mb_bytes.len() as u32),
+                    Some(&HttpContentType::Bytes),
+                    empty_headers,
+                )?;
+                fd.write_all(&mb_bytes).map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::GetMapEntry(
+                md,
+                _contract_addr,
+                _contract_name,
+                _map_name,
+                key,
+                ..,
+            ) => {
+                let mut request_bytes = vec![];
+                key.serialize_write(&mut request_bytes)
+                    .map_err(net_error::WriteError)?;
+                let request_json = format!("\"{}\"", to_hex(&request_bytes));
 
-            Ok((Some((message_data, total_consumed)), consumed))
-        } else {
-            Ok((None, consumed))
-        }
-    }
-}
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_json.as_bytes().len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_json.as_bytes())
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::CallReadOnlyFunction(
+                md,
+                _contract_addr,
+                _contract_name,
+                sender,
+                _func_name,
+                func_args,
+                ..,
+            ) => {
+                let mut args = vec![];
+                for arg in func_args.iter() {
+                    let mut arg_bytes = vec![];
+                    arg.serialize_write(&mut arg_bytes)
+                        .map_err(net_error::WriteError)?;
+                    args.push(to_hex(&arg_bytes));
+                }
 
-/// Information about an in-flight request
-#[derive(Debug, Clone, PartialEq)]
-struct HttpReplyData {
-    request_id: u32,
-    stream: HttpRecvStream,
-}
+                let request_body = CallReadOnlyRequestBody {
+                    sender: sender.to_string(),
+                    arguments: args,
+                };
 
-/// Stacks HTTP implementation, for bufferring up data.
-/// One of these exists per Connection<P: Protocol>.
-/// There can be at most one HTTP request in-flight (i.e. we don't do pipelining)
-#[derive(Debug, Clone, PartialEq)]
-pub struct StacksHttp {
-    /// Address of peer
-    peer_addr: SocketAddr,
-    /// Version of client
-    request_version: Option<HttpVersion>,
-    /// Path we requested
-    request_path: Option<String>,
-    /// Incoming reply
-    reply: Option<HttpReplyData>,
-    /// Size of HTTP chunks to write
-    chunk_size: usize,
-    /// Maximum size of call arguments
-    pub maximum_call_argument_size: u32,
-}
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize read-only call to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-impl StacksHttp {
-    pub fn new(peer_addr: SocketAddr) -> StacksHttp {
-        StacksHttp {
-            peer_addr,
-            reply: None,
-            request_version: None,
-            request_path: None,
-            chunk_size: 8192,
-            maximum_call_argument_size: 20 * BOUND_VALUE_SERIALIZATION_HEX,
-        }
-    }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::VerifyContractSrc(md, _, _, source, _) => {
+                let request_body = VerifyContractSrcRequestBody {
+                    source: source.clone(),
+                };
 
-    pub fn set_chunk_size(&mut self, size: usize) -> () {
-        self.chunk_size = size;
-    }
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize contract source to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-    pub fn num_pending(&self) -> usize {
-        if self.reply.is_some() {
-            1
-        } else {
-            0
-        }
-    }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::ValidateContractCall(md, _, _, _, args, _) => {
+                let request_body = ValidateContractCallRequestBody {
+                    arguments: args.clone(),
+                };
 
-    pub fn has_pending_reply(&self) -> bool {
-        self.reply.is_some()
-    }
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize contract call arguments to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-    pub fn set_pending(&mut self, preamble: &HttpResponsePreamble) -> bool {
-        if self.reply.is_some() {
-            // already pending
-            return false;
-        }
-        self.reply = Some(HttpReplyData {
-            request_id: preamble.request_id,
-            stream: HttpRecvStream::new(MAX_MESSAGE_LEN as u64),
-        });
-        true
-    }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::EncodeClarityValue(md, value) => {
+                let request_body = EncodeClarityValueRequestBody {
+                    value: value.clone(),
+                };
 
-    pub fn begin_request(&mut self, client_version: HttpVersion, request_path: String) -> () {
-        self.request_version = Some(client_version);
-        self.request_path = Some(request_path);
-    }
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize Clarity value to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-    pub fn reset(&mut self) -> () {
-        self.request_version = None;
-        self.request_path = None;
-        self.reply = None;
-    }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::DecodeClarityValue(md, hex) => {
+                let request_body = DecodeClarityValueRequestBody { hex: hex.clone() };
 
-    /// Used for processing chunk-encoded streams.
-    /// Given the preamble and a Read, stream the bytes into a chunk-decoder.  Return the decoded
-    /// bytes if we decode an entire stream.  Always return the number of bytes consumed.
-    /// Returns Ok((Some(request path, decoded bytes we got, total number of encoded bytes), number of bytes gotten in this call))
-    pub fn consume_data<R: Read>(
-        &mut self,
-        preamble: &HttpResponsePreamble,
-        fd: &mut R,
-    ) -> Result<(Option<(HttpVersion, String, Vec<u8>, usize)>, usize), net_error> {
-        assert!(preamble.is_chunked());
-        assert!(self.reply.is_some());
-        assert!(self.request_path.is_some());
-        assert!(self.request_version.is_some());
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize Clarity value hex to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-        let mut finished = false;
-        let res = match self.reply {
-            Some(ref mut reply) => {
-                assert_eq!(reply.request_id, preamble.request_id);
-                match reply.stream.consume_data(fd) {
-                    Ok(res) => {
-                        match res {
-                            (None, sz) => Ok((None, sz)),
-                            (Some((byte_vec, bytes_total)), sz) => {
-                                // done receiving
-                                finished = true;
-                                Ok((
-                                    Some((
-                                        self.request_version.clone().unwrap(),
-                                        self.request_path.clone().unwrap(),
-                                        byte_vec,
-                                        bytes_total,
-                                    )),
-                                    sz,
-                                ))
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        // broken stream
-                        finished = true;
-                        Err(e)
-                    }
-                }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
             }
-            None => {
-                unreachable!();
+            HttpRequestType::PostMemPoolSnapshot(md, txs) => {
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &encode_mempool_snapshot(txs))
+                    .map_err(|e| {
+                        net_error::SerializeError(format!(
+                            "Failed to serialize mempool snapshot to JSON: {:?}",
+                            &e
+                        ))
+                    })?;
+
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
             }
-        };
+            HttpRequestType::PostPeerFilter(md, action, list, rule) => {
+                let request_body = PeerFilterUpdateRequestBody::new(*action, *list, rule);
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize peer filter update to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-        if finished {
-            // if we fetch the whole message, or encounter an error, then we're done -- we can free
-            // up this stream.
-            self.reset();
-        }
-        res
-    }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostAtlasContract(
+                md,
+                contract_id,
+                attachments_max_size,
+                unresolved_attachment_instances_expire_after,
+            ) => {
+                let request_body = AtlasContractRegistrationRequestBody {
+                    contract_id: contract_id.to_string(),
+                    attachments_max_size: *attachments_max_size,
+                    unresolved_attachment_instances_expire_after:
+                        *unresolved_attachment_instances_expire_after,
+                };
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize Atlas contract registration to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-    /// Given a HTTP request, serialize it out
-    #[cfg(test)]
-    pub fn serialize_request(req: &HttpRequestType) -> Result<Vec<u8>, net_error> {
-        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
-        let mut ret = vec![];
-        req.send(&mut http, &mut ret)?;
-        Ok(ret)
-    }
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostEventReplay(md, request_body) => {
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize event replay request to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-    /// Given a fully-formed single HTTP response, parse it (used by clients).
-    #[cfg(test)]
-    pub fn parse_response(
-        request_path: &str,
-        response_buf: &[u8],
-    ) -> Result<StacksHttpMessage, net_error> {
-        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
-        http.reset();
-        http.begin_request(HttpVersion::Http11, request_path.to_string());
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostBlockProcessing(md, action) => {
+                let request_body = BlockProcessingRequestBody::new(*action);
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize block processing update to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-        let (preamble, message_offset) = http.read_preamble(response_buf)?;
-        let is_chunked = match preamble {
-            StacksHttpPreamble::Response(ref resp) => resp.is_chunked(),
-            _ => {
-                return Err(net_error::DeserializeError(
-                    "Invalid HTTP message: did not get a Response preamble".to_string(),
-                ));
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
             }
-        };
+            HttpRequestType::PostAttachmentPin(md, action, content_hash) => {
+                let request_body = AttachmentPinRequestBody::new(*action, content_hash);
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize attachment pin update to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-        let mut message_bytes = &response_buf[message_offset..];
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::PostQueryJob(md, kind) => {
+                let request_body = QueryJobRequestBody::new(kind);
+                let mut request_body_bytes = vec![];
+                serde_json::to_writer(&mut request_body_bytes, &request_body).map_err(|e| {
+                    net_error::SerializeError(format!(
+                        "Failed to serialize query job to JSON: {:?}",
+                        &e
+                    ))
+                })?;
 
-        if is_chunked {
-            match http.stream_payload(&preamble, &mut message_bytes) {
-                Ok((Some((message, _)), _)) => Ok(message),
-                Ok((None, _)) => Err(net_error::UnderflowError(
-                    "Not enough bytes to form a streamed HTTP response".to_string(),
-                )),
-                Err(e) => Err(e),
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(request_body_bytes.len() as u32),
+                    Some(&HttpContentType::JSON),
+                    empty_headers,
+                )?;
+                fd.write_all(&request_body_bytes)
+                    .map_err(net_error::WriteError)?;
+            }
+            HttpRequestType::GetAttachmentContent(md, _, range) => {
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "GET",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    None,
+                    None,
+                    |ref mut fd| range_headers(fd, range),
+                )?;
+            }
+            HttpRequestType::PostBlockFetch(md, ..) => {
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "POST",
+                    &self.request_path(),
+                    &md.peer,
+                    md.keep_alive,
+                    Some(0),
+                    None,
+                    empty_headers,
+                )?;
+            }
+            other_type => {
+                let md = other_type.metadata();
+                let request_path = other_type.request_path();
+                HttpRequestPreamble::new_serialized(
+                    fd,
+                    &md.version,
+                    "GET",
+                    &request_path,
+                    &md.peer,
+                    md.keep_alive,
+                    None,
+                    None,
+                    empty_headers,
+                )?;
             }
-        } else {
-            let (message, _) = http.read_payload(&preamble, &mut message_bytes)?;
-            Ok(message)
         }
+        Ok(())
     }
 }
 
-impl ProtocolFamily for StacksHttp {
-    type Preamble = StacksHttpPreamble;
-    type Message = StacksHttpMessage;
-
-    /// how big can a preamble get?
-    fn preamble_size_hint(&mut self) -> usize {
-        HTTP_PREAMBLE_MAX_ENCODED_SIZE as usize
-    }
-
-    /// how big is this message?  Might not know if we're dealing with chunked encoding.
-    fn payload_len(&mut self, preamble: &StacksHttpPreamble) -> Option<usize> {
-        match *preamble {
-            StacksHttpPreamble::Request(ref http_request_preamble) => {
-                Some(http_request_preamble.get_content_length() as usize)
-            }
-            StacksHttpPreamble::Response(ref http_response_preamble) => {
-                match http_response_preamble.content_length {
-                    Some(len) => Some(len as usize),
-                    None => None,
-                }
-            }
+impl HttpResponseType {
+    fn try_parse<R: Read, F>(
+        protocol: &mut StacksHttp,
+        regex: &Regex,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        request_path: &String,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        parser: F,
+    ) -> Result<Option<HttpResponseType>, net_error>
+    where
+        F: Fn(
+            &mut StacksHttp,
+            HttpVersion,
+            &HttpResponsePreamble,
+            &mut R,
+            Option<usize>,
+        ) -> Result<HttpResponseType, net_error>,
+    {
+        if regex.is_match(request_path) {
+            let payload = parser(protocol, request_version, preamble, fd, len_hint)?;
+            Ok(Some(payload))
+        } else {
+            Ok(None)
         }
     }
 
-    /// StacksHttpMessage deals with HttpRequestPreambles and HttpResponsePreambles
-    fn read_preamble(&mut self, buf: &[u8]) -> Result<(StacksHttpPreamble, usize), net_error> {
-        let mut cursor = io::Cursor::new(buf);
+    fn parse_error<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+    ) -> Result<HttpResponseType, net_error> {
+        if preamble.status_code < 400 || preamble.status_code > 599 {
+            return Err(net_error::DeserializeError(
+                "Inavlid response: not an error".to_string(),
+            ));
+        }
 
-        let preamble = {
-            let mut rd = BoundReader::from_reader(&mut cursor, 4096);
-            let preamble: StacksHttpPreamble = read_next(&mut rd)?;
-            preamble
+        if preamble.content_type != HttpContentType::Text {
+            return Err(net_error::DeserializeError(
+                "Invalid error response: expected text/plain".to_string(),
+            ));
+        }
+
+        let mut error_text = String::new();
+        fd.read_to_string(&mut error_text)
+            .map_err(net_error::ReadError)?;
+
+        let md = HttpResponseMetadata::from_preamble(request_version, preamble);
+        let resp = match preamble.status_code {
+            400 => HttpResponseType::BadRequest(md, error_text),
+            401 => HttpResponseType::Unauthorized(md, error_text),
+            402 => HttpResponseType::PaymentRequired(md, error_text),
+            403 => HttpResponseType::Forbidden(md, error_text),
+            404 => HttpResponseType::NotFound(md, error_text),
+            500 => HttpResponseType::ServerError(md, error_text),
+            503 => HttpResponseType::ServiceUnavailable(md, error_text),
+            _ => HttpResponseType::Error(md, preamble.status_code, error_text),
         };
+        Ok(resp)
+    }
 
-        let preamble_len = cursor.position() as usize;
+    fn parse_bytestream<R: Read, T: StacksMessageCodec>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<T, net_error> {
+        // content-type has to be Bytes
+        if preamble.content_type != HttpContentType::Bytes {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/octet-stream".to_string(),
+            ));
+        }
 
-        // if we already have a pending message, then this preamble cannot be processed (indicates an un-compliant client)
-        match preamble {
-            StacksHttpPreamble::Response(ref http_response_preamble) => {
-                // request path must have been set
-                if self.request_path.is_none() {
+        let item: T = if preamble.is_chunked() && len_hint.is_none() {
+            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            read_next(&mut chunked_fd)?
+        } else {
+            let content_length_opt = match (preamble.content_length, len_hint) {
+                (Some(l), _) => Some(l as u32),
+                (None, Some(l)) => Some(l as u32),
+                (None, None) => None,
+            };
+            if let Some(content_length) = content_length_opt {
+                if (content_length as u64) > max_len {
                     return Err(net_error::DeserializeError(
-                        "Possible bug: did not set the request path".to_string(),
+                        "Invalid Content-Length header: too long".to_string(),
                     ));
                 }
 
-                if http_response_preamble.is_chunked() {
-                    // will stream this.  Make sure we're not doing so already (no collisions
-                    // allowed on in-flight request IDs!)
-                    if self.has_pending_reply() {
-                        test_debug!("Have pending reply already");
-                        return Err(net_error::InProgress);
-                    }
-
-                    // mark as pending -- we can stream this
-                    if !self.set_pending(http_response_preamble) {
-                        test_debug!("Have pending reply already");
-                        return Err(net_error::InProgress);
-                    }
-                }
+                let mut bound_fd = BoundReader::from_reader(fd, content_length as u64);
+                read_next(&mut bound_fd)?
+            } else {
+                // unsupported headers
+                trace!("preamble: {:?}", preamble);
+                return Err(net_error::DeserializeError(
+                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                ));
             }
-            _ => {}
-        }
+        };
 
-        Ok((preamble, preamble_len))
+        Ok(item)
     }
 
-    /// Stream a payload of unknown length.  Only gets called if payload_len() returns None.
-    /// Returns the message if we get enough data to form one.
-    /// Always returns the number of bytes consumed.
-    fn stream_payload<R: Read>(
-        &mut self,
-        preamble: &StacksHttpPreamble,
+    fn parse_json<R: Read, T: serde::de::DeserializeOwned>(
+        preamble: &HttpResponsePreamble,
         fd: &mut R,
-    ) -> Result<(Option<(StacksHttpMessage, usize)>, usize), net_error> {
-        assert!(self.payload_len(preamble).is_none());
-        match preamble {
-            StacksHttpPreamble::Request(_) => {
-                // HTTP requests can't be chunk-encoded, so this should never be reached
-                unreachable!()
-            }
-            StacksHttpPreamble::Response(ref http_response_preamble) => {
-                assert!(http_response_preamble.is_chunked());
-                assert!(self.request_path.is_some());
-
-                // message of unknown length.  Buffer up and maybe we can parse it.
-                let (message_bytes_opt, num_read) =
-                    self.consume_data(http_response_preamble, fd).map_err(|e| {
-                        self.reset();
-                        e
-                    })?;
-
-                match message_bytes_opt {
-                    Some((request_version, request_path, message_bytes, total_bytes_consumed)) => {
-                        // can parse!
-                        test_debug!(
-                            "read http response payload of {} bytes (just buffered {}) for {}",
-                            message_bytes.len(),
-                            num_read,
-                            &request_path
-                        );
-
-                        // we now know the content-length, so pass it into the parser.
-                        let len_hint = message_bytes.len();
-                        let parse_res = HttpResponseType::parse(
-                            self,
-                            request_version,
-                            http_response_preamble,
-                            request_path,
-                            &mut &message_bytes[..],
-                            Some(len_hint),
-                        );
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<T, net_error> {
+        // content-type has to be JSON
+        if preamble.content_type != HttpContentType::JSON {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/json".to_string(),
+            ));
+        }
 
-                        // done parsing
-                        self.reset();
-                        match parse_res {
-                            Ok(data_response) => Ok((
-                                Some((
-                                    StacksHttpMessage::Response(data_response),
-                                    total_bytes_consumed,
-                                )),
-                                num_read,
-                            )),
-                            Err(e) => {
-                                info!("Failed to parse HTTP response: {:?}", &e);
-                                Err(e)
-                            }
-                        }
-                    }
-                    None => {
-                        // need more data
-                        trace!(
-                            "did not read http response payload, but buffered {}",
-                            num_read
-                        );
-                        Ok((None, num_read))
-                    }
+        let item_result: Result<T, serde_json::Error> = if preamble.is_chunked()
+            && len_hint.is_none()
+        {
+            let chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            serde_json::from_reader(chunked_fd)
+        } else {
+            let content_length_opt = match (preamble.content_length, len_hint) {
+                (Some(l), _) => Some(l as u32),
+                (None, Some(l)) => Some(l as u32),
+                (None, None) => None,
+            };
+            if let Some(content_length) = content_length_opt {
+                if (content_length as u64) > max_len {
+                    return Err(net_error::DeserializeError(
+                        "Invalid Content-Length header: too long".to_string(),
+                    ));
                 }
+                let bound_fd = BoundReader::from_reader(fd, content_length as u64);
+                serde_json::from_reader(bound_fd)
+            } else {
+                // unsupported headers
+                trace!("preamble: {:?}", preamble);
+                return Err(net_error::DeserializeError(
+                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                ));
             }
-        }
+        };
+
+        item_result.map_err(|e| {
+            if e.is_eof() {
+                net_error::UnderflowError(format!("Not enough bytes to parse JSON"))
+            } else {
+                net_error::DeserializeError(format!("Failed to parse JSON: {:?}", &e))
+            }
+        })
     }
 
-    /// Parse a payload of known length.
-    /// Only gets called if payload_len() returns Some(...)
-    fn read_payload(
-        &mut self,
-        preamble: &StacksHttpPreamble,
-        buf: &[u8],
-    ) -> Result<(StacksHttpMessage, usize), net_error> {
-        match preamble {
-            StacksHttpPreamble::Request(ref http_request_preamble) => {
-                // all requests have a known length
-                let len = http_request_preamble.get_content_length() as usize;
-                assert!(len <= buf.len(), "{} > {}", len, buf.len());
-
-                trace!("read http request payload of {} bytes", len);
-
-                let mut cursor = io::Cursor::new(buf);
-                match HttpRequestType::parse(self, http_request_preamble, &mut cursor) {
-                    Ok(data_request) => Ok((
-                        StacksHttpMessage::Request(data_request),
-                        cursor.position() as usize,
-                    )),
-                    Err(e) => {
-                        info!("Failed to parse HTTP request: {:?}", &e);
-                        if let net_error::ClientError(client_err) = e {
-                            let req = HttpRequestType::ClientError(
-                                HttpRequestMetadata::from_preamble(http_request_preamble),
-                                client_err,
-                            );
-                            // consume any remaining HTTP request content by returning bytes read = len
-                            Ok((StacksHttpMessage::Request(req), len))
-                        } else {
-                            Err(e)
-                        }
-                    }
-                }
-            }
-            StacksHttpPreamble::Response(ref http_response_preamble) => {
-                assert!(!http_response_preamble.is_chunked());
-                assert!(self.request_path.is_some());
-                assert!(self.request_version.is_some());
-
-                let request_path = self.request_path.take().unwrap();
-                let request_version = self.request_version.take().unwrap();
-
-                // message of known length
-                test_debug!(
-                    "read http response payload of {} bytes for {}",
-                    buf.len(),
-                    &request_path
-                );
-
-                let mut cursor = io::Cursor::new(buf);
-                match HttpResponseType::parse(
-                    self,
-                    request_version,
-                    http_response_preamble,
-                    request_path,
-                    &mut cursor,
-                    None,
-                ) {
-                    Ok(data_response) => Ok((
-                        StacksHttpMessage::Response(data_response),
-                        cursor.position() as usize,
-                    )),
-                    Err(e) => Err(e),
-                }
-            }
+    fn parse_text<R: Read>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<Vec<u8>, net_error> {
+        // content-type has to be text/plain
+        if preamble.content_type != HttpContentType::Text {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected text/plain".to_string(),
+            ));
         }
-    }
-
-    fn verify_payload_bytes(
-        &mut self,
-        _key: &StacksPublicKey,
-        _preamble: &StacksHttpPreamble,
-        _bytes: &[u8],
-    ) -> Result<(), net_error> {
-        // not defined for HTTP messages, but maybe we could add a signature header at some point
-        // in the future if needed.
-        Ok(())
-    }
-
-    fn write_message<W: Write>(
-        &mut self,
-        fd: &mut W,
-        message: &StacksHttpMessage,
-    ) -> Result<(), net_error> {
-        match *message {
-            StacksHttpMessage::Request(ref req) => {
-                if self.request_path.is_some() {
-                    test_debug!("Have pending request already");
-                    return Err(net_error::InProgress);
-                }
-                req.send(self, fd)?;
-
-                self.reset();
-                self.begin_request(req.metadata().version, req.request_path());
-                Ok(())
+        let buf = if preamble.is_chunked() && len_hint.is_none() {
+            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            let mut buf = vec![];
+            chunked_fd
+                .read_to_end(&mut buf)
+                .map_err(net_error::ReadError)?;
+            buf
+        } else {
+            let content_length_opt = match (preamble.content_length, len_hint) {
+                (Some(l), _) => Some(l as u32),
+                (None, Some(l)) => Some(l as u32),
+                (None, None) => None,
+            };
+            if let Some(len) = content_length_opt {
+                let mut buf = vec![0u8; len as usize];
+                fd.read_exact(&mut buf).map_err(net_error::ReadError)?;
+                buf
+            } else {
+                // unsupported headers
+                trace!("preamble: {:?}", preamble);
+                return Err(net_error::DeserializeError(
+                    "Invalid headers: need either Transfer-Encoding or Content-Length".to_string(),
+                ));
             }
-            StacksHttpMessage::Response(ref resp) => resp.send(self, fd),
-        }
-    }
-}
-
-#[cfg(test)]
-mod test {
-    use std::error::Error;
-
-    use rand;
-    use rand::RngCore;
-
-    use burnchains::Txid;
-    use chainstate::stacks::db::blocks::test::make_sample_microblock_stream;
-    use chainstate::stacks::test::make_codec_test_block;
-    use chainstate::stacks::StacksBlock;
-    use chainstate::stacks::StacksMicroblock;
-    use chainstate::stacks::StacksPrivateKey;
-    use chainstate::stacks::StacksTransaction;
-    use chainstate::stacks::TokenTransferMemo;
-    use chainstate::stacks::TransactionAuth;
-    use chainstate::stacks::TransactionPayload;
-    use chainstate::stacks::TransactionPostConditionMode;
-    use chainstate::stacks::TransactionVersion;
-    use net::codec::test::check_codec_and_corruption;
-    use net::test::*;
-    use net::RPCNeighbor;
-    use net::RPCNeighborsInfo;
-    use util::hash::to_hex;
-    use util::hash::Hash160;
-    use util::hash::MerkleTree;
-    use util::hash::Sha512Trunc256Sum;
-
-    use crate::types::chainstate::StacksAddress;
-    use crate::types::chainstate::StacksBlockHeader;
-
-    use super::*;
+        };
 
-    /// Simulate reading variable-length segments
-    struct SegmentReader {
-        segments: Vec<Vec<u8>>,
-        i: usize, // which segment
-        j: usize, // which offset in segment
+        Ok(buf)
     }
 
-    impl SegmentReader {
-        pub fn new(segments: Vec<Vec<u8>>) -> SegmentReader {
-            SegmentReader {
-                segments: segments,
-                i: 0,
-                j: 0,
-            }
+    /// Read a raw application/octet-stream body of at most max_len bytes
+    fn parse_octets<R: Read>(
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+        max_len: u64,
+    ) -> Result<Vec<u8>, net_error> {
+        if preamble.content_type != HttpContentType::Bytes {
+            return Err(net_error::DeserializeError(
+                "Invalid content-type: expected application/octet-stream".to_string(),
+            ));
         }
-    }
-
-    impl Read for SegmentReader {
-        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-            if self.i >= self.segments.len() {
-                return Ok(0);
-            }
-            let mut written = 0;
-            while written < buf.len() {
-                let to_copy = if self.segments[self.i][self.j..].len() < buf[written..].len() {
-                    self.segments[self.i][self.j..].len()
-                } else {
-                    buf[written..].len()
-                };
-
-                buf[written..(written + to_copy)]
-                    .copy_from_slice(&self.segments[self.i][self.j..(self.j + to_copy)]);
-
-                self.j += to_copy;
-                written += to_copy;
-
-                if self.j >= self.segments[self.i].len() {
-                    self.i += 1;
-                    self.j = 0;
+        let buf = if preamble.is_chunked() && len_hint.is_none() {
+            let mut chunked_fd = HttpChunkedTransferReader::from_reader(fd, max_len);
+            let mut buf = vec![];
+            chunked_fd
+                .read_to_end(&mut buf)
+                .map_err(net_error::ReadError)?;
+            buf
+        } else {
+            let len = match (preamble.content_length, len_hint) {
+                (Some(l), _) => l as u64,
+                (None, Some(l)) => l as u64,
+                (None, None) => {
+                    return Err(net_error::DeserializeError(
+                        "Invalid headers: need either Transfer-Encoding or Content-Length"
+                            .to_string(),
+                    ));
                 }
+            };
+            if len > max_len {
+                return Err(net_error::DeserializeError(format!(
+                    "Invalid Content-Length header: body exceeds {} bytes",
+                    max_len
+                )));
             }
-            Ok(written)
-        }
+            let mut buf = vec![0u8; len as usize];
+            fd.read_exact(&mut buf).map_err(net_error::ReadError)?;
+            buf
+        };
+
+        Ok(buf)
     }
 
-    fn vec_u8(mut v: Vec<&str>) -> Vec<Vec<u8>> {
-        let mut ret = vec![];
-        for s_vec in v.drain(..) {
-            let v_u8 = s_vec.as_bytes().to_vec();
-            ret.push(v_u8);
+    // len_hint is given by the StacksHttp protocol implementation
+    pub fn parse<R: Read>(
+        protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        request_path: String,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        if preamble.status_code >= 400 {
+            return HttpResponseType::parse_error(protocol, request_version, preamble, fd);
         }
-        ret
-    }
 
-    #[test]
-    fn test_segment_reader() {
-        let mut tests = vec![
-            (vec_u8(vec!["a", "b"]), "ab"),
-            (vec_u8(vec!["aa", "bbb", "cccc"]), "aabbbcccc"),
-            (vec_u8(vec!["aaaa", "bbb", "cc", "d", ""]), "aaaabbbccd"),
-            (vec_u8(vec!["", "a", "", "b", ""]), "ab"),
-            (vec_u8(vec![""]), ""),
-        ];
-        for (input_vec, expected) in tests.drain(..) {
-            let num_segments = input_vec.len();
-            let mut segment_io = SegmentReader::new(input_vec);
-            let mut output = vec![0u8; expected.len()];
-            let mut offset = 0;
-            for i in 0..num_segments {
-                let nw = segment_io.read(&mut output[offset..]).unwrap();
-                offset += nw;
-            }
-            assert_eq!(output, expected.as_bytes().to_vec());
-        }
-    }
-
-    #[test]
-    fn test_http_chunked_encode() {
-        let tests = vec![
-            // (chunk size, byte string, expected encoding)
-            (10, "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd", "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n0\r\n\r\n"),
-            (10, "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddde", "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n1\r\ne\r\n0\r\n\r\n"),
-            (10, "aaaaaaaaaabbbbbbbbbbccccccccccddddddddddeeeee", "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n5\r\neeeee\r\n0\r\n\r\n"),
-            (1, "abcd", "1\r\na\r\n1\r\nb\r\n1\r\nc\r\n1\r\nd\r\n0\r\n\r\n"),
-            (3, "abcd", "3\r\nabc\r\n1\r\nd\r\n0\r\n\r\n"),
-            (10, "", "0\r\n\r\n")
-        ];
-        for (chunk_size, input_bytes, encoding) in tests.iter() {
-            let mut bytes = vec![];
-            {
-                let mut write_state = HttpChunkedTransferWriterState::new(*chunk_size as usize);
-                let mut encoder =
-                    HttpChunkedTransferWriter::from_writer_state(&mut bytes, &mut write_state);
-                encoder.write_all(input_bytes.as_bytes()).unwrap();
-                encoder.flush().unwrap();
-            }
-
-            assert_eq!(bytes, encoding.as_bytes().to_vec());
-        }
-    }
-
-    #[test]
-    fn test_http_chunked_encode_multi() {
-        let tests = vec![
-            // chunk size, sequence of writes, expected encoding
-            (10, vec!["aaaaaaaaaa", "bbbbb", "bbbbb", "ccc", "ccc", "ccc", "c", "dd", "ddddd", "ddd"], "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n0\r\n\r\n"),
-            (10, vec!["a", "a", "a", "a", "a", "a", "a", "a", "a", "a"], "a\r\naaaaaaaaaa\r\n0\r\n\r\n"),
-            (10, vec!["a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", ""], "a\r\naaaaaaaaaa\r\n0\r\n\r\n"),
-        ];
-
-        for (chunk_size, input_vec, encoding) in tests.iter() {
-            let mut bytes = vec![];
-            {
-                let mut write_state = HttpChunkedTransferWriterState::new(*chunk_size as usize);
-                let mut encoder =
-                    HttpChunkedTransferWriter::from_writer_state(&mut bytes, &mut write_state);
-                for input in input_vec.iter() {
-                    encoder.write_all(input.as_bytes()).unwrap();
-                }
-                encoder.flush().unwrap();
-            }
-
-            assert_eq!(bytes, encoding.as_bytes().to_vec());
-        }
-    }
-
-    #[test]
-    fn test_http_chunked_decode() {
-        let tests = vec![
-            ("a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n0\r\n\r\n", "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd"),
-            ("A\r\naaaaaaaaaa\r\nA\r\nbbbbbbbbbb\r\nA\r\ncccccccccc\r\nA\r\ndddddddddd\r\n0\r\n\r\n", "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd"),
-            ("1\r\na\r\n2\r\nbb\r\n3\r\nccc\r\n4\r\ndddd\r\n0\r\n\r\n", "abbcccdddd"),
-            ("1\r\na\r\n0\r\n\r\n", "a"),
-            ("1\r\na\r\n0\r\n\r\n1\r\nb\r\n0\r\n\r\n", "a"),     // stop reading after the first 0-length chunk encountered
-            ("1; a; b\r\na\r\n0; c\r\n\r\n", "a"),                          // ignore short extensions
-            ("1  ; a ; b \r\na\r\n0     ; extension003\r\n\r\n", "a"),      // ignore short extensions
-            ("1 \t; a\t;\tb ;\r\na\r\n0\t\t;c\r\n\r\n", "a"),               // ignore short extensions
-        ];
-        for (encoded, expected) in tests.iter() {
-            let mut cursor = io::Cursor::new(encoded.as_bytes());
-            let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 50);
-            let mut output = vec![0u8; expected.len()];
-            decoder.read_exact(&mut output).unwrap();
-
-            assert_eq!(output, expected.as_bytes().to_vec());
-        }
-    }
-
-    #[test]
-    fn test_http_chunked_decode_multi() {
-        let tests = vec![
-            (vec_u8(vec!["1\r\na", "\r\n", "0\r\n\r\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r", "\n0\r\n\r\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r\n", "0\r\n\r", "\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r\n0\r\n", "\r\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r\n0\r", "\n\r\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r\n0\r", "\n", "\r\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r\n0\r", "\n\r", "\n"]), "a"),
-            (vec_u8(vec!["1\r\na\r\n0\r", "\n", "\r", "\n"]), "a"),
+        // TODO: make this static somehow
+        let RESPONSE_METHODS: &[(
+            &Regex,
+            &dyn Fn(
+                &mut StacksHttp,
+                HttpVersion,
+                &HttpResponsePreamble,
+                &mut R,
+                Option<usize>,
+            ) -> Result<HttpResponseType, net_error>,
+        )] = &[
+            (&PATH_GETINFO, &HttpResponseType::parse_peerinfo),
+            (&PATH_GETFEATURES, &HttpResponseType::parse_node_features),
+            (&PATH_GETPOXINFO, &HttpResponseType::parse_poxinfo),
+            (&PATH_GETSTATEHASH, &HttpResponseType::parse_statehash),
+            (&PATH_GETNEIGHBORS, &HttpResponseType::parse_neighbors),
             (
-                vec_u8(vec![
-                    "1", "\r", "\n", "a", "\r", "\n", "0", "\r", "\n", "\r", "\n",
-                ]),
-                "a",
+                &PATH_GET_NEIGHBOR_WALK,
+                &HttpResponseType::parse_neighbor_walk_info,
             ),
             (
-                vec_u8(vec![
-                    "a\r",
-                    "\n",
-                    "aaaa",
-                    "aaaaa",
-                    "a",
-                    "\r\n",
-                    "a\r\n",
-                    "bbbbbbbbbb\r",
-                    "\na\r\nccc",
-                    "ccccccc",
-                    "\r",
-                    "\na\r",
-                    "\ndddddd",
-                    "dddd",
-                    "\r\n0\r",
-                    "\n",
-                    "\r",
-                    "\n",
-                ]),
-                "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd",
+                &PATH_GET_PEER_VERSIONS,
+                &HttpResponseType::parse_peer_versions,
             ),
+            (&PATH_GET_NODE_STATUS, &HttpResponseType::parse_node_status),
             (
-                vec_u8(vec![
-                    "a\r\naaaaaaaaaa",
-                    "\r",
-                    "\n",
-                    "a\r\nbbbbbbbbbb\r",
-                    "\n",
-                    "a\r\ncccccccccc\r",
-                    "\na\r\nddddd",
-                    "ddddd\r",
-                    "\n0\r",
-                    "\n\r",
-                    "\n",
-                ]),
-                "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd",
+                &PATH_GET_NODE_IDENTITY,
+                &HttpResponseType::parse_node_identity,
             ),
+            (&PATH_GETBLOCK, &HttpResponseType::parse_block),
+            (&PATH_GET_MAP_ENTRY, &HttpResponseType::parse_get_map_entry),
             (
-                vec_u8(vec![
-                    "1",
-                    "\r",
-                    "\n",
-                    "",
-                    "a",
-                    "\r",
-                    "\n",
-                    "2",
-                    "\r\n",
-                    "bb",
-                    "\r\n",
-                    "3\r\n",
-                    "ccc\r",
-                    "\n4\r\n",
-                    "dddd\r\n",
-                    "0\r\n\r\n",
-                ]),
-                "abbcccdddd",
+                &PATH_GETMICROBLOCKS_INDEXED,
+                &HttpResponseType::parse_microblocks,
             ),
-        ];
-        for (encoded_vec, expected) in tests.iter() {
-            test_debug!("expect {:?}", &expected);
-
-            let mut output = vec![];
-            let mut cursor = SegmentReader::new((*encoded_vec).clone());
-            let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 50);
-
-            for encoded in encoded_vec.iter() {
-                let mut tmp = vec![0u8; encoded.len()];
-                let nr = decoder.read(&mut tmp).unwrap();
-
-                output.extend_from_slice(&tmp[0..nr]);
-            }
-
-            assert_eq!(output, expected.as_bytes().to_vec());
-        }
-    }
-
-    #[test]
-    fn test_http_chunked_decode_err() {
-        let tests = vec![
             (
-                "1; reallyreallyreallyreallylongextension;\r\na\r\n0\r\n\r\n",
-                1,
-                "too long",
+                &PATH_GETMICROBLOCKS_CONFIRMED,
+                &HttpResponseType::parse_microblocks,
             ),
-            ("ffffffff\r\n", 1, "too big"),
-            ("nope\r\n", 1, "could not parse"),
-            ("1\na\r\n0\r\n\r\n", 1, "could not parse"),
-            ("a\r\naaaaaaaaaa", 11, "failed to fill whole buffer"),
-            ("1\r\nab\r\n0\r\n\r\n", 2, "Invalid chunk trailer"),
             (
-                "15\r\naaaaaaaaaabbbbbbbbbbb\r\n0\r\n\r\n",
-                21,
-                "HTTP body exceeds maximum expected length",
+                &PATH_GETMICROBLOCKS_FORKS,
+                &HttpResponseType::parse_microblock_forks,
             ),
             (
-                "7\r\naaaaaaa\r\n8\r\nbbbbbbbb\r\n6\r\ncccccc\r\n0\r\n\r\n",
-                21,
-                "HTTP body exceeds maximum expected length",
+                &PATH_GETMICROBLOCKS_UNCONFIRMED,
+                &HttpResponseType::parse_microblocks_unconfirmed,
             ),
-        ];
-        for (encoded, expected_len, expected) in tests.iter() {
-            test_debug!("expect '{}'", expected);
-            let mut cursor = io::Cursor::new(encoded.as_bytes());
-            let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 20);
-            let mut output = vec![0u8; *expected_len as usize];
-
-            let err = decoder.read_exact(&mut output).unwrap_err();
-            let errstr = format!("{:?}", &err);
-
-            assert!(
-                errstr.find(expected).is_some(),
-                "Expected '{}' in '{:?}'",
-                expected,
-                errstr
-            );
-        }
-    }
-
-    #[test]
-    fn test_http_chunked_encode_decode_roundtrip() {
-        let mut rng = rand::thread_rng();
-        for i in 0..100 {
-            let mut data = vec![0u8; 256];
-            rng.fill_bytes(&mut data);
-
-            let mut encoded_data = vec![];
-            {
-                let mut write_state = HttpChunkedTransferWriterState::new(i + 1);
-                let mut encoder = HttpChunkedTransferWriter::from_writer_state(
-                    &mut encoded_data,
-                    &mut write_state,
-                );
-                encoder.write_all(&data).unwrap();
-                encoder.flush().unwrap();
-            }
-
-            let mut decoded_data = vec![0u8; 256];
-            {
-                let mut cursor = io::Cursor::new(&encoded_data);
-                let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 256);
-                decoder.read_exact(&mut decoded_data).unwrap();
-            }
-
-            assert_eq!(data, decoded_data);
-        }
-    }
-
-    #[test]
-    fn test_parse_reserved_header() {
-        let tests = vec![
             (
-                "Content-Length",
-                "123",
-                Some(HttpReservedHeader::ContentLength(123)),
+                &PATH_GETTRANSACTION_UNCONFIRMED,
+                &HttpResponseType::parse_transaction_unconfirmed,
             ),
             (
-                "Content-Type",
-                "text/plain",
-                Some(HttpReservedHeader::ContentType(HttpContentType::Text)),
+                &PATH_GETMEMPOOL_DROPS,
+                &HttpResponseType::parse_mempool_drops,
             ),
             (
-                "Content-Type",
-                "application/octet-stream",
-                Some(HttpReservedHeader::ContentType(HttpContentType::Bytes)),
+                &PATH_GETMICROBLOCK_PROOF,
+                &HttpResponseType::parse_microblock_proof,
             ),
             (
-                "Content-Type",
-                "application/json",
-                Some(HttpReservedHeader::ContentType(HttpContentType::JSON)),
+                &PATH_GET_ADDRESS_MEMPOOL,
+                &HttpResponseType::parse_address_mempool,
             ),
             (
-                "X-Request-Id",
-                "123",
-                Some(HttpReservedHeader::XRequestID(123)),
+                &PATH_GET_MEMPOOL_FEES,
+                &HttpResponseType::parse_mempool_fees,
             ),
             (
-                "Host",
-                "foo:123",
-                Some(HttpReservedHeader::Host(PeerHost::DNS(
-                    "foo".to_string(),
-                    123,
-                ))),
+                &PATH_GET_SIMULATE_BLOCK,
+                &HttpResponseType::parse_block_simulation,
             ),
             (
-                "Host",
-                "1.2.3.4:123",
-                Some(HttpReservedHeader::Host(PeerHost::IP(
-                    PeerAddress([
-                        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff,
-                        0x01, 0x02, 0x03, 0x04,
-                    ]),
-                    123,
-                ))),
+                &PATH_GET_CONTRACT_COSTS,
+                &HttpResponseType::parse_contract_costs,
             ),
-            // errors
-            ("Content-Length", "-1", None),
-            ("Content-Length", "asdf", None),
-            ("Content-Length", "4294967296", None),
-            ("Content-Type", "blargh", None),
-            ("X-Request-Id", "-1", None),
-            ("X-Request-Id", "asdf", None),
-            ("X-Request-Id", "4294967296", None),
-            ("Unrecognized", "header", None),
-        ];
-
-        for (key, value, expected_result) in tests {
-            let result = HttpReservedHeader::try_from_str(key, value);
-            assert_eq!(result, expected_result);
-        }
-    }
-
-    #[test]
-    fn test_parse_http_request_preamble_ok() {
-        let tests = vec![
-            ("GET /foo HTTP/1.1\r\nHost: localhost:6270\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, true, vec![], vec![])),
-            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nFoo: Bar\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, true, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nFoo: Bar\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, true, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("GET /foo HTTP/1.1\r\nConnection: close\r\nHost: localhost:6270\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, false, vec![], vec![])),
-            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nConnection: close\r\nFoo: Bar\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, false, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nFoo: Bar\r\nConnection: close\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, false, vec!["foo".to_string()], vec!["Bar".to_string()])) 
-        ];
-
-        for (data, request) in tests.iter() {
-            let req = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(req.is_ok(), format!("{:?}", &req));
-            assert_eq!(req.unwrap(), *request);
-
-            let sreq = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(sreq.is_ok(), format!("{:?}", &sreq));
-            assert_eq!(
-                sreq.unwrap(),
-                StacksHttpPreamble::Request((*request).clone())
-            );
-        }
-    }
-
-    #[test]
-    fn test_parse_http_request_options() {
-        let data = "OPTIONS /foo HTTP/1.1\r\nHost: localhost:6270\r\n\r\n";
-        let req = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
-        let preamble = HttpRequestPreamble::from_headers(
-            HttpVersion::Http11,
-            "OPTIONS".to_string(),
-            "/foo".to_string(),
-            "localhost".to_string(),
-            6270,
-            true,
-            vec![],
-            vec![],
-        );
-        assert_eq!(req.unwrap(), preamble);
-    }
-
-    #[test]
-    fn test_parse_http_request_preamble_case_ok() {
-        let tests = vec![
-            ("GET /foo HTTP/1.1\r\nhOsT: localhost:6270\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, true, vec![], vec![])),
-            ("GET /foo HTTP/1.1\r\ncOnNeCtIoN: cLoSe\r\nhOsT: localhost:6270\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, false, vec![], vec![])),
-            ("POST asdf HTTP/1.1\r\nhOsT: core.blockstack.org\r\nCOnNeCtIoN: kEeP-aLiVE\r\nFoo: Bar\r\n\r\n",
-             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, true, vec!["foo".to_string()], vec!["Bar".to_string()])),
-        ];
-
-        for (data, request) in tests.iter() {
-            let req = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(req.is_ok(), format!("{:?}", &req));
-            assert_eq!(req.unwrap(), *request);
-
-            let sreq = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(sreq.is_ok(), format!("{:?}", &sreq));
-            assert_eq!(
-                sreq.unwrap(),
-                StacksHttpPreamble::Request((*request).clone())
-            );
-        }
-    }
-
-    #[test]
-    fn test_parse_http_request_preamble_err() {
-        let tests = vec![
-            ("GET /foo HTTP/1.1\r\n", "failed to fill whole buffer"),
-            ("GET /foo HTTP/1.1\r\n\r\n", "Missing Host header"),
             (
-                "GET /foo HTTP/1.1\r\nFoo: Bar\r\n\r\n",
-                "Missing Host header",
+                &PATH_GET_MEMPOOL_SNAPSHOT,
+                &HttpResponseType::parse_mempool_snapshot,
             ),
-            ("GET /foo HTTP/\r\n\r\n", "Failed to parse HTTP request"),
-            ("GET /foo HTTP/1.1\r\nHost:", "failed to fill whole buffer"),
             (
-                "GET /foo HTTP/1.1\r\nHost: foo:80\r\nHost: bar:80\r\n\r\n",
-                "duplicate header",
+                &PATH_POST_MEMPOOL_SNAPSHOT,
+                &HttpResponseType::parse_mempool_import,
             ),
             (
-                "GET /foo HTTP/1.1\r\nHost: localhost:6270\r\nfoo: \u{2764}\r\n\r\n",
-                "header value is not ASCII-US",
+                &PATH_GET_EVENT_OBSERVER_STATUS,
+                &HttpResponseType::parse_event_observer_status,
             ),
+            (&PATH_EVENT_REPLAYS, &HttpResponseType::parse_event_replays),
+            (&PATH_PEER_FILTERS, &HttpResponseType::parse_peer_filters),
             (
-                "Get /foo HTTP/1.1\r\nHost: localhost:666666\r\n\r\n",
-                "Missing Host header",
+                &PATH_ATLAS_CONTRACTS,
+                &HttpResponseType::parse_atlas_contracts,
             ),
             (
-                "GET /foo HTTP/1.1\r\nHost: localhost:8080\r\nConnection: foo\r\n\r\n",
-                "invalid Connection: header",
+                &PATH_ATTACHMENT_PINS,
+                &HttpResponseType::parse_attachment_pins,
             ),
-        ];
-
-        for (data, errstr) in tests.iter() {
-            let res = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
-            test_debug!("Expect '{}'", errstr);
-            let expected_errstr = format!("{:?}", &res);
-            assert!(res.is_err(), expected_errstr);
-            assert!(
-                res.unwrap_err().to_string().find(errstr).is_some(),
-                expected_errstr
-            );
-        }
-    }
-
-    #[test]
-    fn test_parse_stacks_http_preamble_request_err() {
-        let tests = vec![
             (
-                "GET /foo HTTP/1.1\r\n",
-                "Not enough bytes to form a HTTP request or response",
+                &PATH_BLOCK_PROCESSING,
+                &HttpResponseType::parse_block_processing,
             ),
+            (&PATH_BLOCK_FETCH, &HttpResponseType::parse_block_fetch),
+            (&PATH_GET_QUERY_JOB, &HttpResponseType::parse_query_job),
+            (&PATH_POST_QUERY_JOB, &HttpResponseType::parse_query_job),
+            (&PATH_POSTTRANSACTION, &HttpResponseType::parse_txid),
             (
-                "GET /foo HTTP/1.1\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_POSTBLOCK,
+                &HttpResponseType::parse_stacks_block_accepted,
             ),
             (
-                "GET /foo HTTP/1.1\r\nFoo: Bar\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_POSTMICROBLOCK,
+                &HttpResponseType::parse_microblock_hash,
             ),
+            (&PATH_GET_ACCOUNT, &HttpResponseType::parse_get_account),
             (
-                "GET /foo HTTP/\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_GET_ACCOUNT_UNLOCKS,
+                &HttpResponseType::parse_get_account_unlocks,
             ),
             (
-                "GET /foo HTTP/1.1\r\nHost:",
-                "Not enough bytes to form a HTTP request or response",
+                &PATH_GET_MINER_REWARDS,
+                &HttpResponseType::parse_get_miner_rewards,
             ),
             (
-                "GET /foo HTTP/1.1\r\nHost: foo:80\r\nHost: bar:80\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_GET_CONTRACT_SRC,
+                &HttpResponseType::parse_get_contract_src,
             ),
             (
-                "GET /foo HTTP/1.1\r\nHost: localhost:6270\r\nfoo: \u{2764}\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_GET_IS_TRAIT_IMPLEMENTED,
+                &HttpResponseType::parse_get_is_trait_implemented,
             ),
             (
-                "Get /foo HTTP/1.1\r\nHost: localhost:666666\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_GET_CONTRACT_ABI,
+                &HttpResponseType::parse_get_contract_abi,
             ),
             (
-                "GET /foo HTTP/1.1\r\nHost: localhost:8080\r\nConnection: foo\r\n\r\n",
-                "Failed to decode HTTP request or HTTP response",
+                &PATH_POST_CALL_READ_ONLY,
+                &HttpResponseType::parse_call_read_only,
+            ),
+            (
+                &PATH_POST_VERIFY_CONTRACT_SRC,
+                &HttpResponseType::parse_verify_contract_src,
+            ),
+            (
+                &PATH_POST_VALIDATE_CONTRACT_CALL,
+                &HttpResponseType::parse_validate_contract_call,
+            ),
+            (
+                &PATH_POST_ENCODE_CLARITY_VALUE,
+                &HttpResponseType::parse_clarity_value,
+            ),
+            (
+                &PATH_POST_DECODE_CLARITY_VALUE,
+                &HttpResponseType::parse_clarity_value,
+            ),
+            (
+                &PATH_GET_ATTACHMENT,
+                &HttpResponseType::parse_get_attachment,
+            ),
+            (
+                &PATH_GET_ATTACHMENT_CONTENT,
+                &HttpResponseType::parse_get_attachment_content,
+            ),
+            (
+                &PATH_GET_ATTACHMENTS_INV,
+                &HttpResponseType::parse_get_attachments_inv,
+            ),
+            (
+                &PATH_GET_ATTACHMENTS_BATCH,
+                &HttpResponseType::parse_get_attachments_batch,
             ),
         ];
 
-        for (data, errstr) in tests.iter() {
-            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
-            let expected_serrstr = format!("{:?}", &sres);
-            test_debug!("Expect '{}'", errstr);
-            assert!(sres.is_err(), expected_serrstr);
-            assert!(
-                sres.unwrap_err().to_string().find(errstr).is_some(),
-                expected_serrstr
-            );
-        }
-    }
+        // use url::Url to parse path and query string
+        //   Url will refuse to parse just a path, so create a dummy URL
+        let local_url = format!("http://local{}", &request_path);
+        let url = Url::parse(&local_url).map_err(|_e| {
+            net_error::DeserializeError("Http request path could not be parsed".to_string())
+        })?;
 
-    #[test]
-    fn test_http_request_preamble_headers() {
-        let mut req = HttpRequestPreamble::new(
-            HttpVersion::Http11,
-            "GET".to_string(),
-            "/foo".to_string(),
-            "localhost".to_string(),
-            6270,
-            true,
-        );
-        let req_11 = HttpRequestPreamble::new(
-            HttpVersion::Http11,
-            "GET".to_string(),
-            "/foo".to_string(),
-            "localhost".to_string(),
-            6270,
-            false,
-        );
-        let req_10 = HttpRequestPreamble::new(
-            HttpVersion::Http10,
-            "GET".to_string(),
-            "/foo".to_string(),
-            "localhost".to_string(),
-            6270,
-            false,
-        );
+        let decoded_path = percent_decode_str(url.path()).decode_utf8().map_err(|_e| {
+            net_error::DeserializeError(
+                "Http response path could not be parsed as UTF-8".to_string(),
+            )
+        })?;
 
-        req.add_header("foo".to_string(), "bar".to_string());
+        // every version shares the v2 response schemas
+        let route_path = HttpApiVersion::V2.rewrite_path(&decoded_path);
 
-        assert_eq!(req.content_type, None);
-        req.set_content_type(HttpContentType::JSON);
-        assert_eq!(req.content_type, Some(HttpContentType::JSON));
+        for (regex, parser) in RESPONSE_METHODS.iter() {
+            match HttpResponseType::try_parse(
+                protocol,
+                regex,
+                request_version,
+                preamble,
+                &route_path,
+                fd,
+                len_hint,
+                parser,
+            ) {
+                Ok(Some(request)) => {
+                    return Ok(request);
+                }
+                Ok(None) => {
+                    continue;
+                }
+                Err(e) => {
+                    test_debug!("Failed to parse {}: {:?}", &request_path, &e);
+                    return Err(e);
+                }
+            }
+        }
 
-        req.add_header(
-            "content-type".to_string(),
-            "application/octet-stream".to_string(),
+        test_debug!(
+            "Failed to match request path '{}' to a handler",
+            &request_path
         );
-        assert_eq!(req.content_type, Some(HttpContentType::Bytes));
+        return Err(net_error::DeserializeError(
+            "Http response could not be parsed".to_string(),
+        ));
+    }
 
-        let mut bytes = vec![];
-        req.consensus_serialize(&mut bytes).unwrap();
-        let txt = String::from_utf8(bytes).unwrap();
+    fn parse_peerinfo<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let peer_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeerInfo(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            peer_info,
+        ))
+    }
 
-        test_debug!("headers:\n{}", txt);
+    fn parse_node_features<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let features =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::NodeFeatures(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            features,
+        ))
+    }
 
-        assert!(txt.find("HTTP/1.1").is_some(), "HTTP version is missing");
-        assert!(
-            txt.find("User-Agent: stacks/2.0\r\n").is_some(),
-            "User-Agnet header is missing"
-        );
-        assert!(
-            txt.find("Host: localhost:6270\r\n").is_some(),
-            "Host header is missing"
-        );
-        assert!(txt.find("foo: bar\r\n").is_some(), "foo header is missing");
-        assert!(
-            txt.find("Content-Type: application/octet-stream\r\n")
-                .is_some(),
-            "content-type is missing"
-        );
-        assert!(txt.find("Connection: ").is_none()); // not sent if keep_alive is true (for HTTP/1.1)
+    fn parse_poxinfo<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let pox_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PoxInfo(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            pox_info,
+        ))
+    }
 
-        let mut bytes_10 = vec![];
-        req_10.consensus_serialize(&mut bytes_10).unwrap();
-        let txt_10 = String::from_utf8(bytes_10).unwrap();
+    fn parse_statehash<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let state_hash =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::StateHash(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            state_hash,
+        ))
+    }
 
-        assert!(txt_10.find("HTTP/1.0").is_some(), "HTTP version is missing");
+    fn parse_neighbors<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let neighbors_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Neighbors(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            neighbors_data,
+        ))
+    }
 
-        let mut bytes_11 = vec![];
-        req_11.consensus_serialize(&mut bytes_11).unwrap();
-        let txt_11 = String::from_utf8(bytes_11).unwrap();
+    fn parse_neighbor_walk_info<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let walk_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::NeighborWalkInfo(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            walk_info,
+        ))
+    }
 
-        assert!(txt_11.find("HTTP/1.1").is_some(), "HTTP version is wrong");
-        assert!(
-            txt_11.find("Connection: close").is_some(),
-            "Explicit Connection: close is missing"
-        );
+    fn parse_peer_versions<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let peer_versions =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeerVersions(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            peer_versions,
+        ))
     }
 
-    #[test]
-    fn test_parse_http_response_preamble_ok() {
-        let tests = vec![
-            ("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 123\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "OK".to_string(), true, Some(123), HttpContentType::Bytes, 0, vec![], vec![])),
-            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: 456\r\nFoo: Bar\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), true, Some(456), HttpContentType::JSON, 0, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: 456\r\nX-Request-Id: 123\r\nFoo: Bar\r\n\r\n",
-             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), true, Some(456), HttpContentType::JSON, 123, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("HTTP/1.1 200 Ok\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "Ok".to_string(), true, None, HttpContentType::Bytes, 0, vec![], vec![])),
-            ("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 123\r\nConnection: close\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "OK".to_string(), false, Some(123), HttpContentType::Bytes, 0, vec![], vec![])),
-            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: 456\r\nConnection: close\r\nFoo: Bar\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), false, Some(456), HttpContentType::JSON, 0, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: 456\r\nX-Request-Id: 123\r\nFoo: Bar\r\n\r\n",
-             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), false, Some(456), HttpContentType::JSON, 123, vec!["foo".to_string()], vec!["Bar".to_string()])),
-            ("HTTP/1.1 200 Ok\r\nConnection: close\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "Ok".to_string(), false, None, HttpContentType::Bytes, 0, vec![], vec![])),
-        ];
+    fn parse_node_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let status = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::NodeStatus(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            status,
+        ))
+    }
 
-        for (data, response) in tests.iter() {
-            test_debug!("Try parsing:\n{}\n", data);
-            let res = HttpResponsePreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(res.is_ok(), format!("{:?}", &res));
-            assert_eq!(res.unwrap(), *response);
+    fn parse_node_identity<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let identity =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::NodeIdentity(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            identity,
+        ))
+    }
 
-            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(sres.is_ok(), format!("{:?}", &sres));
-            assert_eq!(
-                sres.unwrap(),
-                StacksHttpPreamble::Response((*response).clone())
-            );
+    fn parse_block<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        if preamble.content_type == HttpContentType::JSON {
+            let decoded_block =
+                HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+            return Ok(HttpResponseType::DecodedBlock(
+                HttpResponseMetadata::from_preamble(request_version, preamble),
+                decoded_block,
+            ));
         }
+        let block: StacksBlock =
+            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Block(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            block,
+        ))
     }
 
-    #[test]
-    fn test_parse_http_response_case_ok() {
-        let tests = vec![
-            ("HTTP/1.1 200 OK\r\ncOnTeNt-TyPe: aPpLiCaTiOn/oCtEt-StReAm\r\ncOnTeNt-LeNgTh: 123\r\nx-ReQuEsT-iD: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "OK".to_string(), true, Some(123), HttpContentType::Bytes, 0, vec![], vec![])),
-            ("HTTP/1.1 200 Ok\r\ncOnTeNt-tYpE: aPpLiCaTiOn/OcTeT-sTrEaM\r\ntRaNsFeR-eNcOdInG: cHuNkEd\r\nX-rEqUeSt-Id: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "Ok".to_string(), true, None, HttpContentType::Bytes, 0, vec![], vec![])),
-            ("HTTP/1.1 200 Ok\r\ncOnNeCtIoN: cLoSe\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "Ok".to_string(), false, None, HttpContentType::Bytes, 0, vec![], vec![])),
-            ("HTTP/1.1 200 Ok\r\ncOnNeCtIoN: kEeP-AlIvE\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
-             HttpResponsePreamble::from_headers(200, "Ok".to_string(), true, None, HttpContentType::Bytes, 0, vec![], vec![])),
-        ];
-
-        for (data, response) in tests.iter() {
-            test_debug!("Try parsing:\n{}\n", data);
-            let res = HttpResponsePreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(res.is_ok(), format!("{:?}", &res));
-            assert_eq!(res.unwrap(), *response);
-
-            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
-            assert!(sres.is_ok(), format!("{:?}", &sres));
-            assert_eq!(
-                sres.unwrap(),
-                StacksHttpPreamble::Response((*response).clone())
-            );
+    fn parse_microblocks<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        if preamble.content_type == HttpContentType::JSON {
+            let decoded_microblocks =
+                HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+            return Ok(HttpResponseType::DecodedMicroblocks(
+                HttpResponseMetadata::from_preamble(request_version, preamble),
+                decoded_microblocks,
+            ));
         }
+        let microblocks: Vec<StacksMicroblock> =
+            HttpResponseType::parse_bytestream(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::Microblocks(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            microblocks,
+        ))
     }
 
-    #[test]
-    fn test_http_response_preamble_headers() {
-        let mut res = HttpResponsePreamble::new(
-            200,
-            "OK".to_string(),
-            Some(123),
-            HttpContentType::JSON,
-            true,
-            123,
-        );
-        assert_eq!(res.request_id, 123);
+    fn parse_microblock_forks<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let forks_info =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MicroblockForks(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            forks_info,
+        ))
+    }
 
-        res.set_request_id(456);
-        assert_eq!(res.request_id, 456);
+    fn parse_get_account<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let account_entry =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetAccount(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            account_entry,
+        ))
+    }
 
-        res.add_header("foo".to_string(), "bar".to_string());
-        res.add_CORS_headers();
+    fn parse_get_account_unlocks<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let unlocks = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetAccountUnlocks(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            unlocks,
+        ))
+    }
 
-        let mut bytes = vec![];
-        res.consensus_serialize(&mut bytes).unwrap();
-        let txt = String::from_utf8(bytes).unwrap();
-        assert!(
-            txt.find("Server: stacks/2.0\r\n").is_some(),
-            "Server header is missing"
-        );
-        assert!(
-            txt.find("Content-Length: 123\r\n").is_some(),
-            "Content-Length is missing"
-        );
-        assert!(
-            txt.find("Content-Type: application/json\r\n").is_some(),
-            "Content-Type is missing"
-        );
-        assert!(txt.find("Date: ").is_some(), "Date header is missing");
-        assert!(txt.find("foo: bar\r\n").is_some(), "foo header is missing");
-        assert!(
-            txt.find("X-Request-Id: 456\r\n").is_some(),
-            "X-Request-Id is missing"
-        );
-        assert!(
-            txt.find("Access-Control-Allow-Origin: *\r\n").is_some(),
-            "CORS header is missing"
-        );
-        assert!(
-            txt.find("Access-Control-Allow-Headers: origin, content-type\r\n")
-                .is_some(),
-            "CORS header is missing"
-        );
-        assert!(
-            txt.find("Access-Control-Allow-Methods: POST, GET, OPTIONS\r\n")
-                .is_some(),
-            "CORS header is missing"
-        );
-        assert!(txt.find("Connection: ").is_none()); // not sent if keep_alive is true
+    fn parse_get_miner_rewards<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let rewards = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetMinerRewards(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            rewards,
+        ))
     }
 
-    #[test]
-    fn test_parse_http_response_preamble_err() {
-        let tests = vec![
-            ("HTTP/1.1 200",
-            "failed to fill whole buffer"),
-            ("HTTP/1.1 200 OK\r\nfoo: \u{2764}\r\n\r\n",
-            "header value is not ASCII-US"),
-            ("HTTP/1.1 200 OK\r\nfoo: bar\r\nfoo: bar\r\n\r\n",
-             "duplicate header"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n",
-             "Unsupported HTTP content type"),
-            ("HTTP/1.1 200 OK\r\nContent-Length: foo\r\n\r\n",
-             "Invalid Content-Length"),
-            ("HTTP/1.1 200 OK\r\nContent-Length: 123\r\n\r\n",
-             "missing Content-Type, Content-Length"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n",
-             "missing Content-Type, Content-Length"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nTransfer-Encoding: chunked\r\n\r\n",
-             "incompatible transfer-encoding and content-length"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nConnection: foo\r\n\r\n",
-             "invalid Connection: header"),
-        ];
+    fn parse_get_map_entry<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let map_entry =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetMapEntry(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            map_entry,
+        ))
+    }
 
-        for (data, errstr) in tests.iter() {
-            let res = HttpResponsePreamble::consensus_deserialize(&mut data.as_bytes());
-            test_debug!("Expect '{}', got: {:?}", errstr, &res);
-            assert!(res.is_err(), format!("{:?}", &res));
-            assert!(res.unwrap_err().to_string().find(errstr).is_some());
-        }
+    fn parse_get_contract_src<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let src_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetContractSrc(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            src_data,
+        ))
     }
 
-    #[test]
-    fn test_parse_stacks_http_preamble_response_err() {
-        let tests = vec![
-            ("HTTP/1.1 200",
-            "Not enough bytes to form a HTTP request or response"),
-            ("HTTP/1.1 200 OK\r\nfoo: \u{2764}\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nfoo: bar\r\nfoo: bar\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nContent-Length: foo\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nContent-Length: 123\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nTransfer-Encoding: chunked\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nConnection: foo\r\n\r\n",
-             "Failed to decode HTTP request or HTTP response"),
-        ];
+    fn parse_get_is_trait_implemented<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let src_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetIsTraitImplemented(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            src_data,
+        ))
+    }
 
-        for (data, errstr) in tests.iter() {
-            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
-            let expected_serrstr = format!("{:?}", &sres);
-            test_debug!("Expect '{}', got: {:?}", errstr, &sres);
-            assert!(sres.is_err(), expected_serrstr);
-            assert!(
-                sres.unwrap_err().to_string().find(errstr).is_some(),
-                expected_serrstr
-            );
-        }
+    fn parse_get_contract_abi<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let abi = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::GetContractABI(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            abi,
+        ))
     }
 
-    fn make_test_transaction() -> StacksTransaction {
-        let privk = StacksPrivateKey::from_hex(
-            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
-        )
-        .unwrap();
-        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
-        let addr = auth.origin().address_testnet();
-        let recv_addr = StacksAddress {
-            version: 1,
-            bytes: Hash160([0xff; 20]),
-        };
+    fn parse_call_read_only<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let call_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::CallReadOnlyFunction(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            call_data,
+        ))
+    }
 
-        let mut tx_stx_transfer = StacksTransaction::new(
-            TransactionVersion::Testnet,
-            auth.clone(),
-            TransactionPayload::TokenTransfer(
-                recv_addr.clone().into(),
-                123,
-                TokenTransferMemo([0u8; 34]),
-            ),
-        );
-        tx_stx_transfer.chain_id = 0x80000000;
-        tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
-        tx_stx_transfer.set_tx_fee(0);
-        tx_stx_transfer
+    fn parse_verify_contract_src<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let verify_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::VerifyContractSrc(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            verify_data,
+        ))
     }
 
-    #[test]
-    fn test_http_parse_host_header_value() {
-        let hosts = vec![
-            "1.2.3.4",
-            "1.2.3.4:5678",
-            "[1:203:405:607:809:a0b:c0d:e0f]",
-            "[1:203:405:607:809:a0b:c0d:e0f]:12345",
-            "www.foo.com",
-            "www.foo.com:12345",
-            // invalid IP addresses will be parsed to DNS names
-            "1.2.3.4.5",
-            "[1:203:405:607:809:a0b:c0d:e0f:1011]",
-            // these won't parse at all, since the port is invalid
-            "1.2.3.4:1234567",
-            "1.2.3.4.5:1234567",
-            "[1:203:405:607:809:a0b:c0d:e0f]:1234567",
-            "[1:203:405:607:809:a0b:c0d:e0f:1011]:1234567",
-            "www.foo.com:1234567",
-            ":",
-            ":123",
-        ];
+    fn parse_validate_contract_call<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let validation_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ValidateContractCall(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            validation_data,
+        ))
+    }
 
-        let peerhosts = vec![
-            Some(PeerHost::IP(
-                PeerAddress([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 1, 2, 3, 4]),
-                80,
-            )),
-            Some(PeerHost::IP(
-                PeerAddress([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 1, 2, 3, 4]),
-                5678,
-            )),
-            Some(PeerHost::IP(
-                PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
-                80,
-            )),
-            Some(PeerHost::IP(
-                PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
-                12345,
-            )),
-            Some(PeerHost::DNS("www.foo.com".to_string(), 80)),
-            Some(PeerHost::DNS("www.foo.com".to_string(), 12345)),
-            Some(PeerHost::DNS("1.2.3.4.5".to_string(), 80)),
-            Some(PeerHost::DNS(
-                "[1:203:405:607:809:a0b:c0d:e0f:1011]".to_string(),
-                80,
-            )),
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-            None,
-        ];
+    fn parse_clarity_value<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let value_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ClarityValue(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            value_data,
+        ))
+    }
 
-        for (host, expected_host) in hosts.iter().zip(peerhosts.iter()) {
-            let peerhost = match host.parse::<PeerHost>() {
-                Ok(ph) => Some(ph),
-                Err(_) => None,
-            };
+    fn parse_microblocks_unconfirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        // NOTE: there will be no length prefix on this, but we won't ever get more than
+        // MAX_MICROBLOCKS_UNCONFIRMED microblocks
+        let mut microblocks = vec![];
+        let max_len = len_hint.unwrap_or(MAX_MESSAGE_LEN as usize) as u64;
+        let mut bound_reader = BoundReader::from_reader(fd, max_len);
+        loop {
+            let mblock: StacksMicroblock = match read_next(&mut bound_reader) {
+                Ok(mblock) => Ok(mblock),
+                Err(e) => match e {
+                    codec_error::ReadError(ref ioe) => match ioe.kind() {
+                        io::ErrorKind::UnexpectedEof => {
+                            // end of stream -- this is fine
+                            break;
+                        }
+                        _ => Err(e),
+                    },
+                    _ => Err(e),
+                },
+            }?;
 
-            match (peerhost, expected_host) {
-                (Some(ref ph), Some(ref expected_ph)) => assert_eq!(*ph, *expected_ph),
-                (None, None) => {}
-                (Some(ph), None) => {
-                    eprintln!(
-                        "Parsed {} successfully to {:?}, but expected error",
-                        host, ph
-                    );
-                    assert!(false);
-                }
-                (None, Some(expected_ph)) => {
-                    eprintln!("Failed to parse {} successfully", host);
-                    assert!(false);
-                }
+            microblocks.push(mblock);
+            if microblocks.len() == MAX_MICROBLOCKS_UNCONFIRMED {
+                break;
             }
         }
+        Ok(HttpResponseType::Microblocks(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            microblocks,
+        ))
     }
 
-    #[test]
-    fn test_http_request_type_codec() {
-        let http_request_metadata_ip = HttpRequestMetadata {
-            version: HttpVersion::Http11,
-            peer: PeerHost::IP(
-                PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
-                12345,
-            ),
-            keep_alive: true,
-        };
-        let http_request_metadata_dns = HttpRequestMetadata {
-            version: HttpVersion::Http11,
-            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
-            keep_alive: true,
+    fn parse_transaction_unconfirmed<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let unconfirmed_status: UnconfirmedTransactionResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+
+        // tx payload must decode to a transaction
+        let tx_bytes = hex_bytes(&unconfirmed_status.tx).map_err(|_| {
+            net_error::DeserializeError("Unconfirmed transaction is not hex-encoded".to_string())
+        })?;
+        let _ = StacksTransaction::consensus_deserialize(&mut &tx_bytes[..]).map_err(|_| {
+            net_error::DeserializeError(
+                "Unconfirmed transaction is not a well-formed Stacks transaction".to_string(),
+            )
+        })?;
+
+        Ok(HttpResponseType::UnconfirmedTransaction(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            unconfirmed_status,
+        ))
+    }
+
+    fn parse_mempool_fees<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let fee_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MemPoolFees(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            fee_data,
+        ))
+    }
+
+    fn parse_block_simulation<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let simulation_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockSimulation(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            simulation_data,
+        ))
+    }
+
+    fn parse_contract_costs<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let cost_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::ContractCosts(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            cost_data,
+        ))
+    }
+
+    fn parse_mempool_drops<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let drops = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MemPoolDrops(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            drops,
+        ))
+    }
+
+    fn parse_microblock_proof<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let proof = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MicroblockProof(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            proof,
+        ))
+    }
+
+    fn parse_address_mempool<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let page = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AddressMempool(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            page,
+        ))
+    }
+
+    fn parse_mempool_snapshot<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let hex_page: RPCPage<String> =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        let page = RPCPage {
+            items: decode_mempool_snapshot(&hex_page.items)?,
+            next_cursor: hex_page.next_cursor,
         };
+        Ok(HttpResponseType::MemPoolSnapshot(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            page,
+        ))
+    }
 
-        let tests = vec![
-            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
-            HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
-            HttpRequestType::GetMicroblocksIndexed(
-                http_request_metadata_ip.clone(),
-                StacksBlockId([3u8; 32]),
+    fn parse_event_observer_status<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let status = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::EventObserverStatus(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            status,
+        ))
+    }
+
+    fn parse_event_replays<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let replays = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::EventReplays(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            replays,
+        ))
+    }
+
+    fn parse_peer_filters<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let filters = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::PeerFilters(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            filters,
+        ))
+    }
+
+    fn parse_atlas_contracts<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let contracts =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AtlasContracts(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            contracts,
+        ))
+    }
+
+    fn parse_attachment_pins<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let pins = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::AttachmentPins(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            pins,
+        ))
+    }
+
+    fn parse_block_processing<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let status = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockProcessing(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            status,
+        ))
+    }
+
+    fn parse_block_fetch<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let fetch = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::BlockFetch(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            fetch,
+        ))
+    }
+
+    fn parse_query_job<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let job = HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::QueryJob(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            job,
+        ))
+    }
+
+    fn parse_mempool_import<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let import_data =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+        Ok(HttpResponseType::MemPoolImport(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            import_data,
+        ))
+    }
+
+    fn parse_txid<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let txid_hex: String = HttpResponseType::parse_json(preamble, fd, len_hint, 66)?;
+        if txid_hex.len() != 64 {
+            return Err(net_error::DeserializeError(
+                "Invalid txid: expected 64 bytes".to_string(),
+            ));
+        }
+
+        let txid = Txid::from_hex(&txid_hex)
+            .map_err(|_e| net_error::DeserializeError("Failed to decode txid hex".to_string()))?;
+        Ok(HttpResponseType::TransactionID(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            txid,
+        ))
+    }
+
+    fn parse_get_attachment<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let res: GetAttachmentResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+
+        Ok(HttpResponseType::GetAttachment(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            res,
+        ))
+    }
+
+    fn parse_get_attachment_content<R: Read>(
+        protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let content = HttpResponseType::parse_octets(
+            preamble,
+            fd,
+            len_hint,
+            protocol.maximum_attachment_size as u64,
+        )?;
+        let range = if preamble.status_code == 206 {
+            let content_range =
+                preamble
+                    .headers
+                    .get("content-range")
+                    .ok_or(net_error::DeserializeError(
+                        "Missing Content-Range header".to_string(),
+                    ))?;
+            AttachmentContentRange::from_header(content_range, content)?
+        } else {
+            AttachmentContentRange {
+                start: 0,
+                total_len: content.len() as u64,
+                content,
+            }
+        };
+
+        Ok(HttpResponseType::GetAttachmentContent(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            range,
+        ))
+    }
+
+    fn parse_get_attachments_batch<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let res: GetAttachmentsBatchResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+
+        Ok(HttpResponseType::GetAttachmentsBatch(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            res,
+        ))
+    }
+
+    fn parse_get_attachments_inv<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let res: GetAttachmentsInvResponse =
+            HttpResponseType::parse_json(preamble, fd, len_hint, MAX_MESSAGE_LEN as u64)?;
+
+        Ok(HttpResponseType::GetAttachmentsInv(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            res,
+        ))
+    }
+
+    fn parse_stacks_block_accepted<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let stacks_block_accepted: StacksBlockAcceptedData =
+            HttpResponseType::parse_json(preamble, fd, len_hint, 128)?;
+        Ok(HttpResponseType::StacksBlockAccepted(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            stacks_block_accepted.stacks_block_id,
+            stacks_block_accepted.accepted,
+        ))
+    }
+
+    fn parse_microblock_hash<R: Read>(
+        _protocol: &mut StacksHttp,
+        request_version: HttpVersion,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+        len_hint: Option<usize>,
+    ) -> Result<HttpResponseType, net_error> {
+        let mblock_hex: String = HttpResponseType::parse_json(preamble, fd, len_hint, 66)?;
+        if mblock_hex.len() != 64 {
+            return Err(net_error::DeserializeError(
+                "Invalid microblock hash: expected 64 bytes".to_string(),
+            ));
+        }
+
+        let mblock_hash = BlockHeaderHash::from_hex(&mblock_hex).map_err(|_e| {
+            net_error::DeserializeError("Failed to decode microblock hash hex".to_string())
+        })?;
+        Ok(HttpResponseType::MicroblockHash(
+            HttpResponseMetadata::from_preamble(request_version, preamble),
+            mblock_hash,
+        ))
+    }
+
+    /// Make an error response for a request that failed with a network error.  The status code
+    /// tells the client what to do about it: 404 if the data is missing, 400 if the client is at
+    /// fault, 503 if the request can be retried, and 500 otherwise.
+    pub fn from_net_error(md: HttpResponseMetadata, e: &net_error) -> HttpResponseType {
+        let msg = format!(
+            "{} (fault: {}, retryable: {})",
+            e,
+            e.fault().as_str(),
+            e.is_retryable()
+        );
+        match e {
+            net_error::NotFoundError | net_error::ClientError(ClientError::NotFound(_)) => {
+                HttpResponseType::NotFound(md, msg)
+            }
+            _ if e.fault() == ErrorFault::Peer => HttpResponseType::BadRequest(md, msg),
+            _ if e.is_retryable() => HttpResponseType::ServiceUnavailable(md, msg),
+            _ => HttpResponseType::ServerError(md, msg),
+        }
+    }
+
+    fn error_reason(code: u16) -> &'static str {
+        match code {
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            413 => "Payload Too Large",
+            416 => "Range Not Satisfiable",
+            500 => "Internal Server Error",
+            503 => "Service Temporarily Unavailable",
+            _ => "Error",
+        }
+    }
+
+    fn error_response<W: Write>(
+        &self,
+        fd: &mut W,
+        code: u16,
+        message: &str,
+    ) -> Result<(), net_error> {
+        let md = self.metadata();
+        HttpResponsePreamble::new_serialized(
+            fd,
+            code,
+            HttpResponseType::error_reason(code),
+            Some(message.len() as u32),
+            &HttpContentType::Text,
+            md.request_id,
+            |ref mut fd| response_headers(fd, md),
+        )?;
+        fd.write_all(message.as_bytes())
+            .map_err(net_error::WriteError)?;
+        Ok(())
+    }
+
+    pub fn metadata(&self) -> &HttpResponseMetadata {
+        match *self {
+            HttpResponseType::PeerInfo(ref md, _) => md,
+            HttpResponseType::NodeFeatures(ref md, _) => md,
+            HttpResponseType::PoxInfo(ref md, _) => md,
+            HttpResponseType::StateHash(ref md, _) => md,
+            HttpResponseType::Neighbors(ref md, _) => md,
+            HttpResponseType::NeighborWalkInfo(ref md, _) => md,
+            HttpResponseType::PeerVersions(ref md, _) => md,
+            HttpResponseType::NodeStatus(ref md, _) => md,
+            HttpResponseType::NodeIdentity(ref md, _) => md,
+            HttpResponseType::Block(ref md, _) => md,
+            HttpResponseType::DecodedBlock(ref md, _) => md,
+            HttpResponseType::BlockStream(ref md) => md,
+            HttpResponseType::Microblocks(ref md, _) => md,
+            HttpResponseType::DecodedMicroblocks(ref md, _) => md,
+            HttpResponseType::MicroblockForks(ref md, _) => md,
+            HttpResponseType::MicroblockStream(ref md) => md,
+            HttpResponseType::TransactionID(ref md, _) => md,
+            HttpResponseType::StacksBlockAccepted(ref md, ..) => md,
+            HttpResponseType::MicroblockHash(ref md, _) => md,
+            HttpResponseType::TokenTransferCost(ref md, _) => md,
+            HttpResponseType::GetMapEntry(ref md, _) => md,
+            HttpResponseType::GetAccount(ref md, _) => md,
+            HttpResponseType::GetAccountUnlocks(ref md, _) => md,
+            HttpResponseType::GetMinerRewards(ref md, _) => md,
+            HttpResponseType::GetContractABI(ref md, _) => md,
+            HttpResponseType::GetContractSrc(ref md, _) => md,
+            HttpResponseType::GetIsTraitImplemented(ref md, _) => md,
+            HttpResponseType::VerifyContractSrc(ref md, _) => md,
+            HttpResponseType::ValidateContractCall(ref md, _) => md,
+            HttpResponseType::ClarityValue(ref md, _) => md,
+            HttpResponseType::CallReadOnlyFunction(ref md, _) => md,
+            HttpResponseType::UnconfirmedTransaction(ref md, _) => md,
+            HttpResponseType::MemPoolDrops(ref md, _) => md,
+            HttpResponseType::MicroblockProof(ref md, _) => md,
+            HttpResponseType::AddressMempool(ref md, _) => md,
+            HttpResponseType::MemPoolFees(ref md, _) => md,
+            HttpResponseType::BlockSimulation(ref md, _) => md,
+            HttpResponseType::ContractCosts(ref md, _) => md,
+            HttpResponseType::MemPoolSnapshot(ref md, _) => md,
+            HttpResponseType::EventObserverStatus(ref md, _) => md,
+            HttpResponseType::EventReplays(ref md, _) => md,
+            HttpResponseType::PeerFilters(ref md, _) => md,
+            HttpResponseType::AtlasContracts(ref md, _) => md,
+            HttpResponseType::AttachmentPins(ref md, _) => md,
+            HttpResponseType::BlockProcessing(ref md, _) => md,
+            HttpResponseType::BlockFetch(ref md, _) => md,
+            HttpResponseType::QueryJob(ref md, _) => md,
+            HttpResponseType::MemPoolImport(ref md, _) => md,
+            HttpResponseType::GetAttachment(ref md, _) => md,
+            HttpResponseType::GetAttachmentContent(ref md, _) => md,
+            HttpResponseType::GetAttachmentsBatch(ref md, _) => md,
+            HttpResponseType::GetAttachmentsInv(ref md, _) => md,
+            HttpResponseType::OptionsPreflight(ref md) => md,
+            // errors
+            HttpResponseType::BadRequestJSON(ref md, _) => md,
+            HttpResponseType::BadRequest(ref md, _) => md,
+            HttpResponseType::Unauthorized(ref md, _) => md,
+            HttpResponseType::PaymentRequired(ref md, _) => md,
+            HttpResponseType::Forbidden(ref md, _) => md,
+            HttpResponseType::NotFound(ref md, _) => md,
+            HttpResponseType::ServerError(ref md, _) => md,
+            HttpResponseType::ServiceUnavailable(ref md, _) => md,
+            HttpResponseType::Error(ref md, _, _) => md,
+        }
+    }
+
+    fn send_bytestream<W: Write, T: StacksMessageCodec>(
+        protocol: &mut StacksHttp,
+        md: &HttpResponseMetadata,
+        fd: &mut W,
+        message: &T,
+    ) -> Result<(), codec_error> {
+        if md.content_length.is_some() {
+            // have explicit content-length, so we can send as-is
+            write_next(fd, message)
+        } else {
+            // no content-length, so send as chunk-encoded
+            let mut write_state = HttpChunkedTransferWriterState::new(protocol.chunk_size as usize);
+            let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
+            write_next(&mut encoder, message)?;
+            encoder.flush().map_err(codec_error::WriteError)?;
+            Ok(())
+        }
+    }
+
+    fn send_text<W: Write>(
+        protocol: &mut StacksHttp,
+        md: &HttpResponseMetadata,
+        fd: &mut W,
+        text: &[u8],
+    ) -> Result<(), net_error> {
+        if md.content_length.is_some() {
+            // have explicit content-length, so we can send as-is
+            fd.write_all(text).map_err(net_error::WriteError)
+        } else {
+            // no content-length, so send as chunk-encoded
+            let mut write_state = HttpChunkedTransferWriterState::new(protocol.chunk_size as usize);
+            let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
+            encoder.write_all(text).map_err(net_error::WriteError)?;
+            encoder.flush().map_err(net_error::WriteError)?;
+            Ok(())
+        }
+    }
+
+    fn send_json<W: Write, T: serde::ser::Serialize>(
+        protocol: &mut StacksHttp,
+        md: &HttpResponseMetadata,
+        fd: &mut W,
+        message: &T,
+    ) -> Result<(), net_error> {
+        if md.content_length.is_some() {
+            // have explicit content-length, so we can send as-is
+            serde_json::to_writer(fd, message)
+                .map_err(|e| net_error::SerializeError(format!("Failed to send as JSON: {:?}", &e)))
+        } else {
+            // no content-length, so send as chunk-encoded
+            let mut write_state = HttpChunkedTransferWriterState::new(protocol.chunk_size as usize);
+            let mut encoder = HttpChunkedTransferWriter::from_writer_state(fd, &mut write_state);
+            serde_json::to_writer(&mut encoder, message).map_err(|e| {
+                net_error::SerializeError(format!("Failed to send as chunk-encoded JSON: {:?}", &e))
+            })?;
+            encoder.flush().map_err(net_error::WriteError)?;
+            Ok(())
+        }
+    }
+
+    pub fn send<W: Write>(&self, protocol: &mut StacksHttp, fd: &mut W) -> Result<(), net_error> {
+        match *self {
+            HttpResponseType::GetAccount(ref md, ref account_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, account_data)?;
+            }
+            HttpResponseType::GetAccountUnlocks(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::GetMinerRewards(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::GetContractABI(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::GetContractSrc(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::GetIsTraitImplemented(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::VerifyContractSrc(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::ValidateContractCall(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::ClarityValue(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::TokenTransferCost(ref md, ref cost) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, cost)?;
+            }
+            HttpResponseType::CallReadOnlyFunction(ref md, ref data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::GetMapEntry(ref md, ref map_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, map_data)?;
+            }
+            HttpResponseType::PeerInfo(ref md, ref peer_info) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, peer_info)?;
+            }
+            HttpResponseType::NodeFeatures(ref md, ref features) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, features)?;
+            }
+            HttpResponseType::PoxInfo(ref md, ref pox_info) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, pox_info)?;
+            }
+            HttpResponseType::StateHash(ref md, ref state_hash) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, state_hash)?;
+            }
+            HttpResponseType::Neighbors(ref md, ref neighbor_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, neighbor_data)?;
+            }
+            HttpResponseType::NeighborWalkInfo(ref md, ref walk_info) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, walk_info)?;
+            }
+            HttpResponseType::PeerVersions(ref md, ref peer_versions) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, peer_versions)?;
+            }
+            HttpResponseType::NodeStatus(ref md, ref status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, status)?;
+            }
+            HttpResponseType::NodeIdentity(ref md, ref identity) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, identity)?;
+            }
+            HttpResponseType::MicroblockForks(ref md, ref forks_info) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, forks_info)?;
+            }
+            HttpResponseType::GetAttachment(ref md, ref zonefile_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
+            }
+            HttpResponseType::GetAttachmentContent(ref md, ref range) => {
+                if range.is_partial() {
+                    HttpResponsePreamble::new_serialized(
+                        fd,
+                        206,
+                        "Partial Content",
+                        Some(range.content.len() as u32),
+                        &HttpContentType::Bytes,
+                        md.request_id,
+                        |ref mut fd| {
+                            response_headers(fd, md)?;
+                            fd.write_all(
+                                format!("Content-Range: {}\r\n", range.to_header()).as_bytes(),
+                            )
+                            .map_err(codec_error::WriteError)
+                        },
+                    )?;
+                    fd.write_all(&range.content)
+                        .map_err(net_error::WriteError)?;
+                } else {
+                    HttpResponsePreamble::new_serialized(
+                        fd,
+                        200,
+                        "OK",
+                        md.content_length.clone(),
+                        &HttpContentType::Bytes,
+                        md.request_id,
+                        |ref mut fd| response_headers(fd, md),
+                    )?;
+                    HttpResponseType::send_text(protocol, md, fd, &range.content)?;
+                }
+            }
+            HttpResponseType::GetAttachmentsInv(ref md, ref zonefile_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, zonefile_data)?;
+            }
+            HttpResponseType::GetAttachmentsBatch(ref md, ref attachments) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, attachments)?;
+            }
+            HttpResponseType::Block(ref md, ref block) => {
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::Bytes,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_bytestream(protocol, md, fd, block)?;
+            }
+            HttpResponseType::DecodedBlock(ref md, ref decoded_block) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, decoded_block)?;
+            }
+            HttpResponseType::BlockStream(ref md) => {
+                // only send the preamble.  The caller will need to figure out how to send along
+                // the block data itself.
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    None,
+                    &HttpContentType::Bytes,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+            }
+            HttpResponseType::Microblocks(ref md, ref microblocks) => {
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::Bytes,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_bytestream(protocol, md, fd, microblocks)?;
+            }
+            HttpResponseType::DecodedMicroblocks(ref md, ref decoded_microblocks) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, decoded_microblocks)?;
+            }
+            HttpResponseType::MicroblockStream(ref md) => {
+                // only send the preamble.  The caller will need to figure out how to send along
+                // the microblock data itself.
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    None,
+                    &HttpContentType::Bytes,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+            }
+            HttpResponseType::TransactionID(ref md, ref txid) => {
+                let txid_bytes = txid.to_hex();
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, &txid_bytes)?;
+            }
+            HttpResponseType::StacksBlockAccepted(ref md, ref stacks_block_id, ref accepted) => {
+                let accepted_data = StacksBlockAcceptedData {
+                    stacks_block_id: stacks_block_id.clone(),
+                    accepted: *accepted,
+                };
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, &accepted_data)?;
+            }
+            HttpResponseType::MicroblockHash(ref md, ref mblock_hash) => {
+                let mblock_bytes = mblock_hash.to_hex();
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, &mblock_bytes)?;
+            }
+            HttpResponseType::UnconfirmedTransaction(ref md, ref unconfirmed_status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, unconfirmed_status)?;
+            }
+            HttpResponseType::MemPoolDrops(ref md, ref drops) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, drops)?;
+            }
+            HttpResponseType::MicroblockProof(ref md, ref proof) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, proof)?;
+            }
+            HttpResponseType::AddressMempool(ref md, ref page) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, page)?;
+            }
+            HttpResponseType::MemPoolFees(ref md, ref fee_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, fee_data)?;
+            }
+            HttpResponseType::BlockSimulation(ref md, ref simulation_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, simulation_data)?;
+            }
+            HttpResponseType::ContractCosts(ref md, ref cost_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, cost_data)?;
+            }
+            HttpResponseType::MemPoolSnapshot(ref md, ref page) => {
+                let hex_page = RPCPage {
+                    items: encode_mempool_snapshot(&page.items),
+                    next_cursor: page.next_cursor.clone(),
+                };
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, &hex_page)?;
+            }
+            HttpResponseType::MemPoolImport(ref md, ref import_data) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, import_data)?;
+            }
+            HttpResponseType::EventObserverStatus(ref md, ref status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, status)?;
+            }
+            HttpResponseType::EventReplays(ref md, ref replays) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, replays)?;
+            }
+            HttpResponseType::PeerFilters(ref md, ref filters) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, filters)?;
+            }
+            HttpResponseType::AtlasContracts(ref md, ref contracts) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, contracts)?;
+            }
+            HttpResponseType::AttachmentPins(ref md, ref pins) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, pins)?;
+            }
+            HttpResponseType::BlockProcessing(ref md, ref status) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, status)?;
+            }
+            HttpResponseType::BlockFetch(ref md, ref fetch) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, fetch)?;
+            }
+            HttpResponseType::QueryJob(ref md, ref job) => {
+                HttpResponsePreamble::ok_JSON_from_md(fd, md)?;
+                HttpResponseType::send_json(protocol, md, fd, job)?;
+            }
+            HttpResponseType::OptionsPreflight(ref md) => {
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    200,
+                    "OK",
+                    None,
+                    &HttpContentType::Text,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_text(protocol, md, fd, "".as_bytes())?;
+            }
+            HttpResponseType::BadRequestJSON(ref md, ref data) => {
+                HttpResponsePreamble::new_serialized(
+                    fd,
+                    400,
+                    HttpResponseType::error_reason(400),
+                    md.content_length.clone(),
+                    &HttpContentType::JSON,
+                    md.request_id,
+                    |ref mut fd| response_headers(fd, md),
+                )?;
+                HttpResponseType::send_json(protocol, md, fd, data)?;
+            }
+            HttpResponseType::BadRequest(_, ref msg) => self.error_response(fd, 400, msg)?,
+            HttpResponseType::Unauthorized(_, ref msg) => self.error_response(fd, 401, msg)?,
+            HttpResponseType::PaymentRequired(_, ref msg) => self.error_response(fd, 402, msg)?,
+            HttpResponseType::Forbidden(_, ref msg) => self.error_response(fd, 403, msg)?,
+            HttpResponseType::NotFound(_, ref msg) => self.error_response(fd, 404, msg)?,
+            HttpResponseType::ServerError(_, ref msg) => self.error_response(fd, 500, msg)?,
+            HttpResponseType::ServiceUnavailable(_, ref msg) => {
+                self.error_response(fd, 503, msg)?
+            }
+            HttpResponseType::Error(_, ref error_code, ref msg) => {
+                self.error_response(fd, *error_code, msg)?
+            }
+        };
+        Ok(())
+    }
+}
+
+impl StacksMessageCodec for StacksHttpPreamble {
+    fn consensus_serialize<W: Write>(&self, fd: &mut W) -> Result<(), codec_error> {
+        match *self {
+            StacksHttpPreamble::Request(ref req) => req.consensus_serialize(fd),
+            StacksHttpPreamble::Response(ref res) => res.consensus_serialize(fd),
+        }
+    }
+
+    fn consensus_deserialize<R: Read>(fd: &mut R) -> Result<StacksHttpPreamble, codec_error> {
+        let mut retry_fd = RetryReader::new(fd);
+
+        // the byte stream can decode to a http request or a http response, but not both.
+        match HttpRequestPreamble::consensus_deserialize(&mut retry_fd) {
+            Ok(request) => Ok(StacksHttpPreamble::Request(request)),
+            Err(e_request) => {
+                // maybe a http response?
+                retry_fd.set_position(0);
+                match HttpResponsePreamble::consensus_deserialize(&mut retry_fd) {
+                    Ok(response) => Ok(StacksHttpPreamble::Response(response)),
+                    Err(e) => {
+                        // underflow?
+                        match (e_request, e) {
+                            (codec_error::ReadError(ref ioe1), codec_error::ReadError(ref ioe2)) => {
+                                if ioe1.kind() == io::ErrorKind::UnexpectedEof && ioe2.kind() == io::ErrorKind::UnexpectedEof {
+                                    // out of bytes
+                                    Err(codec_error::UnderflowError("Not enough bytes to form a HTTP request or response".to_string()))
+                                }
+                                else {
+                                    Err(codec_error::DeserializeError(format!("Neither a HTTP request ({:?}) or HTTP response ({:?})", ioe1, ioe2)))
+                                }
+                            },
+                            (e_req, e_res) => Err(codec_error::DeserializeError(format!("Failed to decode HTTP request or HTTP response (request error: {:?}; response error: {:?})", &e_req, &e_res)))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl MessageSequence for StacksHttpMessage {
+    fn request_id(&self) -> u32 {
+        // there is at most one in-flight HTTP request, as far as a Connection<P> is concerned
+        HTTP_REQUEST_ID_RESERVED
+    }
+
+    fn get_message_name(&self) -> &'static str {
+        match *self {
+            StacksHttpMessage::Request(ref req) => match req {
+                HttpRequestType::GetInfo(_) => "HTTP(GetInfo)",
+                HttpRequestType::GetFeatures(_) => "HTTP(GetFeatures)",
+                HttpRequestType::GetPoxInfo(_, _) => "HTTP(GetPoxInfo)",
+                HttpRequestType::GetStateHash(..) => "HTTP(GetStateHash)",
+                HttpRequestType::GetNeighbors(_) => "HTTP(GetNeighbors)",
+                HttpRequestType::GetNeighborWalkInfo(_) => "HTTP(GetNeighborWalkInfo)",
+                HttpRequestType::GetPeerVersions(_) => "HTTP(GetPeerVersions)",
+                HttpRequestType::GetNodeStatus(_) => "HTTP(GetNodeStatus)",
+                HttpRequestType::GetNodeIdentity(..) => "HTTP(GetNodeIdentity)",
+                HttpRequestType::GetBlock(_, _) => "HTTP(GetBlock)",
+                HttpRequestType::GetBlockDecoded(_, _) => "HTTP(GetBlockDecoded)",
+                HttpRequestType::GetMicroblocksIndexed(_, _) => "HTTP(GetMicroblocksIndexed)",
+                HttpRequestType::GetMicroblocksIndexedDecoded(_, _) => {
+                    "HTTP(GetMicroblocksIndexedDecoded)"
+                }
+                HttpRequestType::GetMicroblocksConfirmed(_, _) => "HTTP(GetMicroblocksConfirmed)",
+                HttpRequestType::GetMicroblocksConfirmedDecoded(_, _) => {
+                    "HTTP(GetMicroblocksConfirmedDecoded)"
+                }
+                HttpRequestType::GetMicroblockForks(_, _) => "HTTP(GetMicroblockForks)",
+                HttpRequestType::GetMicroblocksUnconfirmed(_, _, _) => {
+                    "HTTP(GetMicroblocksUnconfirmed)"
+                }
+                HttpRequestType::GetTransactionUnconfirmed(_, _) => {
+                    "HTTP(GetTransactionUnconfirmed)"
+                }
+                HttpRequestType::GetMemPoolDrops(_, _) => "HTTP(GetMemPoolDrops)",
+                HttpRequestType::GetMicroblockProof(_, _) => "HTTP(GetMicroblockProof)",
+                HttpRequestType::GetAddressMempool(..) => "HTTP(GetAddressMempool)",
+                HttpRequestType::GetMemPoolFees(_) => "HTTP(GetMemPoolFees)",
+                HttpRequestType::SimulateBlock(_) => "HTTP(SimulateBlock)",
+                HttpRequestType::GetContractCosts(..) => "HTTP(GetContractCosts)",
+                HttpRequestType::GetMemPoolSnapshot(..) => "HTTP(GetMemPoolSnapshot)",
+                HttpRequestType::PostMemPoolSnapshot(_, _) => "HTTP(PostMemPoolSnapshot)",
+                HttpRequestType::GetEventObserverStatus(_) => "HTTP(GetEventObserverStatus)",
+                HttpRequestType::GetEventReplays(_) => "HTTP(GetEventReplays)",
+                HttpRequestType::PostEventReplay(..) => "HTTP(PostEventReplay)",
+                HttpRequestType::GetPeerFilters(_) => "HTTP(GetPeerFilters)",
+                HttpRequestType::PostPeerFilter(..) => "HTTP(PostPeerFilter)",
+                HttpRequestType::GetAtlasContracts(_) => "HTTP(GetAtlasContracts)",
+                HttpRequestType::PostAtlasContract(..) => "HTTP(PostAtlasContract)",
+                HttpRequestType::GetAttachmentPins(_) => "HTTP(GetAttachmentPins)",
+                HttpRequestType::PostAttachmentPin(..) => "HTTP(PostAttachmentPin)",
+                HttpRequestType::GetBlockProcessing(_) => "HTTP(GetBlockProcessing)",
+                HttpRequestType::PostBlockProcessing(..) => "HTTP(PostBlockProcessing)",
+                HttpRequestType::GetBlockFetch(..) => "HTTP(GetBlockFetch)",
+                HttpRequestType::PostBlockFetch(..) => "HTTP(PostBlockFetch)",
+                HttpRequestType::GetQueryJob(..) => "HTTP(GetQueryJob)",
+                HttpRequestType::PostQueryJob(..) => "HTTP(PostQueryJob)",
+                HttpRequestType::PostTransaction(..) => "HTTP(PostTransaction)",
+                HttpRequestType::PostBlock(..) => "HTTP(PostBlock)",
+                HttpRequestType::PostMicroblock(..) => "HTTP(PostMicroblock)",
+                HttpRequestType::GetAccount(..) => "HTTP(GetAccount)",
+                HttpRequestType::GetAccountUnlocks(..) => "HTTP(GetAccountUnlocks)",
+                HttpRequestType::GetMinerRewards(..) => "HTTP(GetMinerRewards)",
+                HttpRequestType::GetMapEntry(..) => "HTTP(GetMapEntry)",
+                HttpRequestType::GetTransferCost(_) => "HTTP(GetTransferCost)",
+                HttpRequestType::GetContractABI(..) => "HTTP(GetContractABI)",
+                HttpRequestType::GetContractSrc(..) => "HTTP(GetContractSrc)",
+                HttpRequestType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
+                HttpRequestType::VerifyContractSrc(..) => "HTTP(VerifyContractSrc)",
+                HttpRequestType::ValidateContractCall(..) => "HTTP(ValidateContractCall)",
+                HttpRequestType::EncodeClarityValue(..) => "HTTP(EncodeClarityValue)",
+                HttpRequestType::DecodeClarityValue(..) => "HTTP(DecodeClarityValue)",
+                HttpRequestType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpRequestType::GetAttachment(..) => "HTTP(GetAttachment)",
+                HttpRequestType::GetAttachmentContent(..) => "HTTP(GetAttachmentContent)",
+                HttpRequestType::GetAttachmentsBatch(..) => "HTTP(GetAttachmentsBatch)",
+                HttpRequestType::GetAttachmentsInv(..) => "HTTP(GetAttachmentsInv)",
+                HttpRequestType::OptionsPreflight(..) => "HTTP(OptionsPreflight)",
+                HttpRequestType::ClientError(..) => "HTTP(ClientError)",
+            },
+            StacksHttpMessage::Response(ref res) => match res {
+                HttpResponseType::TokenTransferCost(_, _) => "HTTP(TokenTransferCost)",
+                HttpResponseType::GetMapEntry(_, _) => "HTTP(GetMapEntry)",
+                HttpResponseType::GetAccount(_, _) => "HTTP(GetAccount)",
+                HttpResponseType::GetAccountUnlocks(_, _) => "HTTP(GetAccountUnlocks)",
+                HttpResponseType::GetMinerRewards(_, _) => "HTTP(GetMinerRewards)",
+                HttpResponseType::GetContractABI(..) => "HTTP(GetContractABI)",
+                HttpResponseType::GetContractSrc(..) => "HTTP(GetContractSrc)",
+                HttpResponseType::GetIsTraitImplemented(..) => "HTTP(GetIsTraitImplemented)",
+                HttpResponseType::VerifyContractSrc(..) => "HTTP(VerifyContractSrc)",
+                HttpResponseType::ValidateContractCall(..) => "HTTP(ValidateContractCall)",
+                HttpResponseType::ClarityValue(..) => "HTTP(ClarityValue)",
+                HttpResponseType::CallReadOnlyFunction(..) => "HTTP(CallReadOnlyFunction)",
+                HttpResponseType::GetAttachment(_, _) => "HTTP(GetAttachment)",
+                HttpResponseType::GetAttachmentContent(_, _) => "HTTP(GetAttachmentContent)",
+                HttpResponseType::GetAttachmentsBatch(_, _) => "HTTP(GetAttachmentsBatch)",
+                HttpResponseType::GetAttachmentsInv(_, _) => "HTTP(GetAttachmentsInv)",
+                HttpResponseType::PeerInfo(_, _) => "HTTP(PeerInfo)",
+                HttpResponseType::NodeFeatures(_, _) => "HTTP(NodeFeatures)",
+                HttpResponseType::PoxInfo(_, _) => "HTTP(PeerInfo)",
+                HttpResponseType::StateHash(_, _) => "HTTP(StateHash)",
+                HttpResponseType::Neighbors(_, _) => "HTTP(Neighbors)",
+                HttpResponseType::NeighborWalkInfo(_, _) => "HTTP(NeighborWalkInfo)",
+                HttpResponseType::PeerVersions(_, _) => "HTTP(PeerVersions)",
+                HttpResponseType::NodeStatus(_, _) => "HTTP(NodeStatus)",
+                HttpResponseType::NodeIdentity(_, _) => "HTTP(NodeIdentity)",
+                HttpResponseType::Block(_, _) => "HTTP(Block)",
+                HttpResponseType::DecodedBlock(_, _) => "HTTP(DecodedBlock)",
+                HttpResponseType::BlockStream(_) => "HTTP(BlockStream)",
+                HttpResponseType::Microblocks(_, _) => "HTTP(Microblocks)",
+                HttpResponseType::DecodedMicroblocks(_, _) => "HTTP(DecodedMicroblocks)",
+                HttpResponseType::MicroblockForks(_, _) => "HTTP(MicroblockForks)",
+                HttpResponseType::MicroblockStream(_) => "HTTP(MicroblockStream)",
+                HttpResponseType::TransactionID(_, _) => "HTTP(Transaction)",
+                HttpResponseType::StacksBlockAccepted(..) => "HTTP(StacksBlockAccepted)",
+                HttpResponseType::MicroblockHash(_, _) => "HTTP(MicroblockHash)",
+                HttpResponseType::UnconfirmedTransaction(_, _) => "HTTP(UnconfirmedTransaction)",
+                HttpResponseType::MemPoolDrops(_, _) => "HTTP(MemPoolDrops)",
+                HttpResponseType::MicroblockProof(_, _) => "HTTP(MicroblockProof)",
+                HttpResponseType::AddressMempool(_, _) => "HTTP(AddressMempool)",
+                HttpResponseType::MemPoolFees(_, _) => "HTTP(MemPoolFees)",
+                HttpResponseType::BlockSimulation(_, _) => "HTTP(BlockSimulation)",
+                HttpResponseType::ContractCosts(_, _) => "HTTP(ContractCosts)",
+                HttpResponseType::MemPoolSnapshot(_, _) => "HTTP(MemPoolSnapshot)",
+                HttpResponseType::EventObserverStatus(_, _) => "HTTP(EventObserverStatus)",
+                HttpResponseType::EventReplays(_, _) => "HTTP(EventReplays)",
+                HttpResponseType::PeerFilters(_, _) => "HTTP(PeerFilters)",
+                HttpResponseType::AtlasContracts(_, _) => "HTTP(AtlasContracts)",
+                HttpResponseType::AttachmentPins(_, _) => "HTTP(AttachmentPins)",
+                HttpResponseType::BlockProcessing(_, _) => "HTTP(BlockProcessing)",
+                HttpResponseType::BlockFetch(_, _) => "HTTP(BlockFetch)",
+                HttpResponseType::QueryJob(_, _) => "HTTP(QueryJob)",
+                HttpResponseType::MemPoolImport(_, _) => "HTTP(MemPoolImport)",
+                HttpResponseType::OptionsPreflight(_) => "HTTP(OptionsPreflight)",
+                HttpResponseType::BadRequestJSON(..) | HttpResponseType::BadRequest(..) => {
+                    "HTTP(400)"
+                }
+                HttpResponseType::Unauthorized(_, _) => "HTTP(401)",
+                HttpResponseType::PaymentRequired(_, _) => "HTTP(402)",
+                HttpResponseType::Forbidden(_, _) => "HTTP(403)",
+                HttpResponseType::NotFound(_, _) => "HTTP(404)",
+                HttpResponseType::ServerError(_, _) => "HTTP(500)",
+                HttpResponseType::ServiceUnavailable(_, _) => "HTTP(503)",
+                HttpResponseType::Error(_, _, _) => "HTTP(other)",
+            },
+        }
+    }
+}
+
+/// A partially-decoded, streamed HTTP message (response) being received.
+/// Internally used by StacksHttp to keep track of chunk-decoding state.
+#[derive(Debug, Clone, PartialEq)]
+struct HttpRecvStream {
+    state: HttpChunkedTransferReaderState,
+    data: Vec<u8>,
+    total_consumed: usize, // number of *encoded* bytes consumed
+    /// If this is an attachment, its content is checked as it arrives instead of being
+    /// buffered in `data`
+    attachment: Option<AttachmentStreamVerifier>,
+}
+
+impl HttpRecvStream {
+    pub fn new(max_size: u64) -> HttpRecvStream {
+        HttpRecvStream {
+            state: HttpChunkedTransferReaderState::new(max_size),
+            data: vec![],
+            total_consumed: 0,
+            attachment: None,
+        }
+    }
+
+    pub fn new_attachment(max_size: u64, verifier: AttachmentStreamVerifier) -> HttpRecvStream {
+        HttpRecvStream {
+            attachment: Some(verifier),
+            ..HttpRecvStream::new(max_size)
+        }
+    }
+
+    /// Feed data into our chunked transfer reader state.  If we finish reading a stream, return
+    /// the decoded bytes (as Some(Vec<u8>) and the total number of encoded bytes consumed).
+    /// If this is an attachment, the decoded bytes are its verified content.
+    /// Always returns the number of bytes consumed.
+    pub fn consume_data<R: Read>(
+        &mut self,
+        fd: &mut R,
+    ) -> Result<(Option<(Vec<u8>, usize)>, usize), net_error> {
+        let mut consumed = 0;
+        let mut blocked = false;
+        while !blocked {
+            let mut decoded_buf = vec![0u8; 8192];
+            let (read_pass, consumed_pass) = match self.state.do_read(fd, &mut decoded_buf) {
+                Ok((0, num_consumed)) => {
+                    trace!(
+                        "consume_data blocked on 0 decoded bytes ({} consumed)",
+                        num_consumed
+                    );
+                    blocked = true;
+                    (0, num_consumed)
+                }
+                Ok((num_read, num_consumed)) => (num_read, num_consumed),
+                Err(e) => match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => {
+                        trace!("consume_data blocked on read error");
+                        blocked = true;
+                        (0, 0)
+                    }
+                    _ => {
+                        return Err(net_error::ReadError(e));
+                    }
+                },
+            };
+
+            consumed += consumed_pass;
+            if read_pass > 0 {
+                match self.attachment {
+                    Some(ref mut verifier) => verifier.write(&decoded_buf[0..read_pass])?,
+                    None => self.data.extend_from_slice(&decoded_buf[0..read_pass]),
+                }
+            }
+        }
+
+        self.total_consumed += consumed;
+
+        // did we get a message?
+        if self.state.is_eof() {
+            // reset
+            let message_data = match self.attachment.take() {
+                Some(verifier) => verifier.finish()?.content,
+                None => mem::replace(&mut self.data, vec![]),
+            };
+            let total_consumed = self.total_consumed;
+
+            self.state = HttpChunkedTransferReaderState::new(self.state.max_size);
+            self.total_consumed = 0;
+
+            Ok((Some((message_data, total_consumed)), consumed))
+        } else {
+            Ok((None, consumed))
+        }
+    }
+}
+
+/// Information about an in-flight request
+#[derive(Debug, Clone, PartialEq)]
+struct HttpReplyData {
+    request_id: u32,
+    stream: HttpRecvStream,
+}
+
+/// Stacks HTTP implementation, for bufferring up data.
+/// One of these exists per Connection<P: Protocol>.
+/// There can be at most one HTTP request in-flight (i.e. we don't do pipelining)
+#[derive(Debug, Clone, PartialEq)]
+pub struct StacksHttp {
+    /// Address of peer
+    peer_addr: SocketAddr,
+    /// Version of client
+    request_version: Option<HttpVersion>,
+    /// Path we requested
+    request_path: Option<String>,
+    /// Incoming reply
+    reply: Option<HttpReplyData>,
+    /// Size of HTTP chunks to write
+    chunk_size: usize,
+    /// Maximum size of call arguments
+    pub maximum_call_argument_size: u32,
+    /// Maximum size of a transaction submission body (which may carry an attachment)
+    pub maximum_transaction_body_size: u32,
+    /// Maximum size of any other request body
+    pub maximum_request_body_size: u32,
+    /// Maximum size of an attachment we download
+    pub maximum_attachment_size: u32,
+    /// Bytes still to be discarded from a request body that was too big to accept
+    rejected_body_remaining: Option<u64>,
+}
+
+impl StacksHttp {
+    pub fn new(peer_addr: SocketAddr) -> StacksHttp {
+        StacksHttp {
+            peer_addr,
+            reply: None,
+            request_version: None,
+            request_path: None,
+            chunk_size: 8192,
+            maximum_call_argument_size: 20 * BOUND_VALUE_SERIALIZATION_HEX,
+            maximum_transaction_body_size: 4 * MAX_TRANSACTION_LEN,
+            maximum_request_body_size: MAX_PAYLOAD_LEN,
+            maximum_attachment_size: 1_048_576,
+            rejected_body_remaining: None,
+        }
+    }
+
+    pub fn set_chunk_size(&mut self, size: usize) -> () {
+        self.chunk_size = size;
+    }
+
+    pub fn num_pending(&self) -> usize {
+        if self.reply.is_some() {
+            1
+        } else {
+            0
+        }
+    }
+
+    pub fn has_pending_reply(&self) -> bool {
+        self.reply.is_some()
+    }
+
+    pub fn set_pending(&mut self, preamble: &HttpResponsePreamble) -> bool {
+        if self.reply.is_some() {
+            // already pending
+            return false;
+        }
+        let stream = match self.attachment_verifier(preamble) {
+            Some(verifier) => HttpRecvStream::new_attachment(MAX_MESSAGE_LEN as u64, verifier),
+            None => HttpRecvStream::new(MAX_MESSAGE_LEN as u64),
+        };
+        self.reply = Some(HttpReplyData {
+            request_id: preamble.request_id,
+            stream,
+        });
+        true
+    }
+
+    /// If this response carries the content of an attachment we asked for, get a verifier for
+    /// it.
+    fn attachment_verifier(
+        &self,
+        preamble: &HttpResponsePreamble,
+    ) -> Option<AttachmentStreamVerifier> {
+        if preamble.status_code != 200 {
+            return None;
+        }
+        let request_path = self.request_path.as_ref()?;
+        let captures = PATH_GET_ATTACHMENT.captures(request_path)?;
+        let content_hash = Hash160::from_hex(captures.get(1)?.as_str()).ok()?;
+        Some(AttachmentStreamVerifier::new(
+            content_hash,
+            self.maximum_attachment_size,
+        ))
+    }
+
+    pub fn begin_request(&mut self, client_version: HttpVersion, request_path: String) -> () {
+        self.request_version = Some(client_version);
+        self.request_path = Some(request_path);
+    }
+
+    pub fn reset(&mut self) -> () {
+        self.request_version = None;
+        self.request_path = None;
+        self.reply = None;
+    }
+
+    /// The largest body we'll accept for this request, depending on its route.
+    pub fn maximum_body_size(&self, preamble: &HttpRequestPreamble) -> u32 {
+        if preamble.verb == "POST" {
+            let path = preamble.path.split('?').next().unwrap_or("");
+            let decoded_path = percent_decode_str(path).decode_utf8_lossy();
+            if PATH_POSTTRANSACTION.is_match(&decoded_path) {
+                return self.maximum_transaction_body_size;
+            }
+            if PATH_POST_CALL_READ_ONLY.is_match(&decoded_path)
+                || PATH_POST_VALIDATE_CONTRACT_CALL.is_match(&decoded_path)
+            {
+                return self.maximum_call_argument_size;
+            }
+        }
+        self.maximum_request_body_size
+    }
+
+    /// Read and drop the body of a request that is too big to accept, without buffering it.
+    /// Once all of it has been read, return a request for the 413 to send back.
+    /// Always returns the number of bytes consumed.
+    fn discard_request_body<R: Read>(
+        &mut self,
+        preamble: &HttpRequestPreamble,
+        fd: &mut R,
+    ) -> Result<(Option<(StacksHttpMessage, usize)>, usize), net_error> {
+        let mut remaining = self
+            .rejected_body_remaining
+            .take()
+            .unwrap_or(preamble.get_content_length() as u64);
+        let mut consumed = 0;
+        let mut buf = [0u8; 8192];
+        while remaining > 0 {
+            let to_read = cmp::min(remaining, buf.len() as u64) as usize;
+            let nr = match fd.read(&mut buf[0..to_read]) {
+                Ok(0) => break,
+                Ok(nr) => nr,
+                Err(e) => match e.kind() {
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => break,
+                    _ => return Err(net_error::ReadError(e)),
+                },
+            };
+            remaining -= nr as u64;
+            consumed += nr;
+        }
+
+        if remaining > 0 {
+            self.rejected_body_remaining = Some(remaining);
+            return Ok((None, consumed));
+        }
+
+        let req = HttpRequestType::ClientError(
+            HttpRequestMetadata::from_preamble(preamble),
+            ClientError::PayloadTooLarge(self.maximum_body_size(preamble)),
+        );
+        Ok((
+            Some((
+                StacksHttpMessage::Request(req),
+                preamble.get_content_length() as usize,
+            )),
+            consumed,
+        ))
+    }
+
+    /// Used for processing chunk-encoded streams.
+    /// Given the preamble and a Read, stream the bytes into a chunk-decoder.  Return the decoded
+    /// bytes if we decode an entire stream.  Always return the number of bytes consumed.
+    /// Returns Ok((Some(request path, decoded bytes we got, total number of encoded bytes), number of bytes gotten in this call))
+    pub fn consume_data<R: Read>(
+        &mut self,
+        preamble: &HttpResponsePreamble,
+        fd: &mut R,
+    ) -> Result<(Option<(HttpVersion, String, Vec<u8>, usize)>, usize), net_error> {
+        assert!(preamble.is_chunked());
+        assert!(self.reply.is_some());
+        assert!(self.request_path.is_some());
+        assert!(self.request_version.is_some());
+
+        let mut finished = false;
+        let res = match self.reply {
+            Some(ref mut reply) => {
+                assert_eq!(reply.request_id, preamble.request_id);
+                match reply.stream.consume_data(fd) {
+                    Ok(res) => {
+                        match res {
+                            (None, sz) => Ok((None, sz)),
+                            (Some((byte_vec, bytes_total)), sz) => {
+                                // done receiving
+                                finished = true;
+                                Ok((
+                                    Some((
+                                        self.request_version.clone().unwrap(),
+                                        self.request_path.clone().unwrap(),
+                                        byte_vec,
+                                        bytes_total,
+                                    )),
+                                    sz,
+                                ))
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        // broken stream
+                        finished = true;
+                        Err(e)
+                    }
+                }
+            }
+            None => {
+                unreachable!();
+            }
+        };
+
+        if finished {
+            // if we fetch the whole message, or encounter an error, then we're done -- we can free
+            // up this stream.
+            self.reset();
+        }
+        res
+    }
+
+    /// Given a HTTP request, serialize it out
+    #[cfg(test)]
+    pub fn serialize_request(req: &HttpRequestType) -> Result<Vec<u8>, net_error> {
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut ret = vec![];
+        req.send(&mut http, &mut ret)?;
+        Ok(ret)
+    }
+
+    /// Given a fully-formed single HTTP response, parse it (used by clients).
+    #[cfg(test)]
+    pub fn parse_response(
+        request_path: &str,
+        response_buf: &[u8],
+    ) -> Result<StacksHttpMessage, net_error> {
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.reset();
+        http.begin_request(HttpVersion::Http11, request_path.to_string());
+
+        let (preamble, message_offset) = http.read_preamble(response_buf)?;
+        let is_chunked = match preamble {
+            StacksHttpPreamble::Response(ref resp) => resp.is_chunked(),
+            _ => {
+                return Err(net_error::DeserializeError(
+                    "Invalid HTTP message: did not get a Response preamble".to_string(),
+                ));
+            }
+        };
+
+        let mut message_bytes = &response_buf[message_offset..];
+
+        if is_chunked {
+            match http.stream_payload(&preamble, &mut message_bytes) {
+                Ok((Some((message, _)), _)) => Ok(message),
+                Ok((None, _)) => Err(net_error::UnderflowError(
+                    "Not enough bytes to form a streamed HTTP response".to_string(),
+                )),
+                Err(e) => Err(e),
+            }
+        } else {
+            let (message, _) = http.read_payload(&preamble, &mut message_bytes)?;
+            Ok(message)
+        }
+    }
+}
+
+impl ProtocolFamily for StacksHttp {
+    type Preamble = StacksHttpPreamble;
+    type Message = StacksHttpMessage;
+
+    /// how big can a preamble get?
+    fn preamble_size_hint(&mut self) -> usize {
+        HTTP_PREAMBLE_MAX_ENCODED_SIZE as usize
+    }
+
+    /// how big is this message?  Might not know if we're dealing with chunked encoding.
+    /// A request body that is too big for its route is treated as a stream, so it can be
+    /// discarded as it arrives instead of being buffered.
+    fn payload_len(&mut self, preamble: &StacksHttpPreamble) -> Option<usize> {
+        match *preamble {
+            StacksHttpPreamble::Request(ref http_request_preamble) => {
+                let len = http_request_preamble.get_content_length();
+                if len > self.maximum_body_size(http_request_preamble) {
+                    None
+                } else {
+                    Some(len as usize)
+                }
+            }
+            StacksHttpPreamble::Response(ref http_response_preamble) => {
+                match http_response_preamble.content_length {
+                    Some(len) => Some(len as usize),
+                    None => None,
+                }
+            }
+        }
+    }
+
+    /// StacksHttpMessage deals with HttpRequestPreambles and HttpResponsePreambles
+    fn read_preamble(&mut self, buf: &[u8]) -> Result<(StacksHttpPreamble, usize), net_error> {
+        let mut cursor = io::Cursor::new(buf);
+
+        let preamble = {
+            let mut rd = BoundReader::from_reader(&mut cursor, 4096);
+            let preamble: StacksHttpPreamble = read_next(&mut rd)?;
+            preamble
+        };
+
+        let preamble_len = cursor.position() as usize;
+
+        // if we already have a pending message, then this preamble cannot be processed (indicates an un-compliant client)
+        match preamble {
+            StacksHttpPreamble::Response(ref http_response_preamble) => {
+                // request path must have been set
+                if self.request_path.is_none() {
+                    return Err(net_error::DeserializeError(
+                        "Possible bug: did not set the request path".to_string(),
+                    ));
+                }
+
+                if let Some(content_length) = http_response_preamble.content_length {
+                    if self.attachment_verifier(http_response_preamble).is_some()
+                        && (content_length as u64)
+                            > AttachmentStreamVerifier::max_body_len(self.maximum_attachment_size)
+                    {
+                        return Err(net_error::DeserializeError(
+                            "Invalid Content-Length header: attachment too long".to_string(),
+                        ));
+                    }
+                }
+
+                if http_response_preamble.is_chunked() {
+                    // will stream this.  Make sure we're not doing so already (no collisions
+                    // allowed on in-flight request IDs!)
+                    if self.has_pending_reply() {
+                        test_debug!("Have pending reply already");
+                        return Err(net_error::InProgress);
+                    }
+
+                    // mark as pending -- we can stream this
+                    if !self.set_pending(http_response_preamble) {
+                        test_debug!("Have pending reply already");
+                        return Err(net_error::InProgress);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok((preamble, preamble_len))
+    }
+
+    /// Stream a payload of unknown length.  Only gets called if payload_len() returns None.
+    /// Returns the message if we get enough data to form one.
+    /// Always returns the number of bytes consumed.
+    fn stream_payload<R: Read>(
+        &mut self,
+        preamble: &StacksHttpPreamble,
+        fd: &mut R,
+    ) -> Result<(Option<(StacksHttpMessage, usize)>, usize), net_error> {
+        assert!(self.payload_len(preamble).is_none());
+        match preamble {
+            StacksHttpPreamble::Request(ref http_request_preamble) => {
+                // HTTP requests can't be chunk-encoded, so this is only reached for bodies that
+                // are too big to accept
+                self.discard_request_body(http_request_preamble, fd)
+            }
+            StacksHttpPreamble::Response(ref http_response_preamble) => {
+                assert!(http_response_preamble.is_chunked());
+                assert!(self.request_path.is_some());
+
+                let is_attachment = match self.reply {
+                    Some(ref reply) => reply.stream.attachment.is_some(),
+                    None => false,
+                };
+
+                // message of unknown length.  Buffer up and maybe we can parse it.
+                let (message_bytes_opt, num_read) =
+                    self.consume_data(http_response_preamble, fd).map_err(|e| {
+                        self.reset();
+                        e
+                    })?;
+
+                match message_bytes_opt {
+                    Some((request_version, _, content, total_bytes_consumed)) if is_attachment => {
+                        // already decoded and verified
+                        self.reset();
+                        let response = HttpResponseType::GetAttachment(
+                            HttpResponseMetadata::from_preamble(
+                                request_version,
+                                http_response_preamble,
+                            ),
+                            GetAttachmentResponse {
+                                attachment: Attachment::new(content),
+                            },
+                        );
+                        Ok((
+                            Some((StacksHttpMessage::Response(response), total_bytes_consumed)),
+                            num_read,
+                        ))
+                    }
+                    Some((request_version, request_path, message_bytes, total_bytes_consumed)) => {
+                        // can parse!
+                        test_debug!(
+                            "read http response payload of {} bytes (just buffered {}) for {}",
+                            message_bytes.len(),
+                            num_read,
+                            &request_path
+                        );
+
+                        // we now know the content-length, so pass it into the parser.
+                        let len_hint = message_bytes.len();
+                        let parse_res = HttpResponseType::parse(
+                            self,
+                            request_version,
+                            http_response_preamble,
+                            request_path,
+                            &mut &message_bytes[..],
+                            Some(len_hint),
+                        );
+
+                        // done parsing
+                        self.reset();
+                        match parse_res {
+                            Ok(data_response) => Ok((
+                                Some((
+                                    StacksHttpMessage::Response(data_response),
+                                    total_bytes_consumed,
+                                )),
+                                num_read,
+                            )),
+                            Err(e) => {
+                                info!("Failed to parse HTTP response: {:?}", &e);
+                                Err(e)
+                            }
+                        }
+                    }
+                    None => {
+                        // need more data
+                        trace!(
+                            "did not read http response payload, but buffered {}",
+                            num_read
+                        );
+                        Ok((None, num_read))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parse a payload of known length.
+    /// Only gets called if payload_len() returns Some(...)
+    fn read_payload(
+        &mut self,
+        preamble: &StacksHttpPreamble,
+        buf: &[u8],
+    ) -> Result<(StacksHttpMessage, usize), net_error> {
+        match preamble {
+            StacksHttpPreamble::Request(ref http_request_preamble) => {
+                // all requests have a known length
+                let len = http_request_preamble.get_content_length() as usize;
+                assert!(len <= buf.len(), "{} > {}", len, buf.len());
+
+                trace!("read http request payload of {} bytes", len);
+
+                let mut cursor = io::Cursor::new(buf);
+                match HttpRequestType::parse(self, http_request_preamble, &mut cursor) {
+                    Ok(data_request) => Ok((
+                        StacksHttpMessage::Request(data_request),
+                        cursor.position() as usize,
+                    )),
+                    Err(e) => {
+                        info!("Failed to parse HTTP request: {:?}", &e);
+                        if let net_error::ClientError(client_err) = e {
+                            let req = HttpRequestType::ClientError(
+                                HttpRequestMetadata::from_preamble(http_request_preamble),
+                                client_err,
+                            );
+                            // consume any remaining HTTP request content by returning bytes read = len
+                            Ok((StacksHttpMessage::Request(req), len))
+                        } else {
+                            Err(e)
+                        }
+                    }
+                }
+            }
+            StacksHttpPreamble::Response(ref http_response_preamble) => {
+                assert!(!http_response_preamble.is_chunked());
+                assert!(self.request_path.is_some());
+                assert!(self.request_version.is_some());
+
+                if let Some(mut verifier) = self.attachment_verifier(http_response_preamble) {
+                    let len = cmp::min(
+                        http_response_preamble.content_length.unwrap_or(0) as usize,
+                        buf.len(),
+                    );
+                    self.request_path = None;
+                    let request_version = self.request_version.take().unwrap();
+                    verifier.write(&buf[0..len])?;
+                    let response = HttpResponseType::GetAttachment(
+                        HttpResponseMetadata::from_preamble(
+                            request_version,
+                            http_response_preamble,
+                        ),
+                        GetAttachmentResponse {
+                            attachment: verifier.finish()?,
+                        },
+                    );
+                    return Ok((StacksHttpMessage::Response(response), len));
+                }
+
+                let request_path = self.request_path.take().unwrap();
+                let request_version = self.request_version.take().unwrap();
+
+                // message of known length
+                test_debug!(
+                    "read http response payload of {} bytes for {}",
+                    buf.len(),
+                    &request_path
+                );
+
+                let mut cursor = io::Cursor::new(buf);
+                match HttpResponseType::parse(
+                    self,
+                    request_version,
+                    http_response_preamble,
+                    request_path,
+                    &mut cursor,
+                    None,
+                ) {
+                    Ok(data_response) => Ok((
+                        StacksHttpMessage::Response(data_response),
+                        cursor.position() as usize,
+                    )),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+
+    fn verify_payload_bytes(
+        &mut self,
+        _key: &StacksPublicKey,
+        _preamble: &StacksHttpPreamble,
+        _bytes: &[u8],
+    ) -> Result<(), net_error> {
+        // not defined for HTTP messages, but maybe we could add a signature header at some point
+        // in the future if needed.
+        Ok(())
+    }
+
+    fn write_message<W: Write>(
+        &mut self,
+        fd: &mut W,
+        message: &StacksHttpMessage,
+    ) -> Result<(), net_error> {
+        match *message {
+            StacksHttpMessage::Request(ref req) => {
+                if self.request_path.is_some() {
+                    test_debug!("Have pending request already");
+                    return Err(net_error::InProgress);
+                }
+                req.send(self, fd)?;
+
+                self.reset();
+                self.begin_request(req.metadata().version, req.request_path());
+                Ok(())
+            }
+            StacksHttpMessage::Response(ref resp) => resp.send(self, fd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::convert::TryInto;
+    use std::error::Error;
+
+    use rand;
+    use rand::RngCore;
+
+    use burnchains::Txid;
+    use chainstate::stacks::db::blocks::test::make_sample_microblock_stream;
+    use chainstate::stacks::test::make_codec_test_block;
+    use chainstate::stacks::StacksBlock;
+    use chainstate::stacks::StacksMicroblock;
+    use chainstate::stacks::StacksPrivateKey;
+    use chainstate::stacks::StacksTransaction;
+    use chainstate::stacks::TokenTransferMemo;
+    use chainstate::stacks::TransactionAuth;
+    use chainstate::stacks::TransactionPayload;
+    use chainstate::stacks::TransactionPostConditionMode;
+    use chainstate::stacks::TransactionVersion;
+    use codec::Error as codec_error;
+    use net::codec::test::check_codec_and_corruption;
+    use net::connection::{ConnectionHttp, ConnectionOptions};
+    use net::db::LocalPeer;
+    use net::jobs::{QueryJobKind, QueryJobStatus};
+    use net::test::*;
+    use net::ClarityValueResponse;
+    use net::NeighborKey;
+    use net::RPCEventObserverStatus;
+    use net::RPCMemPoolDropData;
+    use net::RPCMemPoolImportData;
+    use net::RPCMicroblockProof;
+    use net::RPCNeighbor;
+    use net::RPCNeighborsInfo;
+    use net::RPCNodeIdentity;
+    use net::RPCPeerInfoData;
+    use net::RPCPendingTransaction;
+    use net::RPCStateHashData;
+    use net::RPCTransactionFork;
+    use net::{AttachmentPinAction, RPCAttachmentPins};
+    use net::{BlockFetchStatus, RPCBlockFetchData};
+    use net::{BlockProcessingAction, RPCBlockProcessingStatus};
+    use net::{CoinbaseScheduleEntry, MinerRewardEntry, MinerRewardsResponse};
+    use net::{ContractCallArgumentError, ValidateContractCallResponse};
+    use net::{EventReplayRequestBody, RPCEventReplay, DEFAULT_EVENT_REPLAY_RATE};
+    use net::{PeerFilterAction, PeerFilterList, PeerFilterRule};
+    use net::{RPCAtlasContract, RPCAtlasContracts};
+    use net::{
+        RPCAtlasStatus, RPCBurnchainStatus, RPCMemPoolStatus, RPCMiningStatus, RPCNodeStatus,
+        RPCPeerCounts, RPCStacksStatus, RPCSubsystemError,
+    };
+    use net::{RPCBlockSimulationData, RPCSimulatedTransaction};
+    use net::{RPCContractCostEntry, RPCContractCostsData};
+    use net::{RPCDecodedBlock, RPCDecodedMicroblock};
+    use net::{RPCFeeRatePercentiles, RPCFeeRateThreshold, RPCMemPoolFeeData};
+    use net::{RPCFrontierQuality, RPCHandshakeConcurrency, RPCNeighborWalkInfo};
+    use net::{RPCMicroblockForksInfo, RPCMicroblockStream};
+    use net::{RPCNodeFeature, RPCNodeFeatures};
+    use net::{RPCPeerFilterList, RPCPeerFilters};
+    use net::{RPCPeerVersionCount, RPCPeerVersionsInfo};
+    use net::{RPCQueryJob, RPCQueryJobResult};
+    use util::hash::to_hex;
+    use util::hash::Hash160;
+    use util::hash::MerkleTree;
+    use util::hash::Sha256Sum;
+    use util::hash::Sha512Trunc256Sum;
+    use util::strings::UrlString;
+    use vm::costs::ExecutionCost;
+    use vm::types::QualifiedContractIdentifier;
+
+    use crate::types::chainstate::StacksAddress;
+    use crate::types::chainstate::StacksBlockHeader;
+    use crate::types::chainstate::{SortitionId, StacksWorkScore};
+    use crate::types::proof::TrieHash;
+
+    use super::*;
+
+    /// Simulate reading variable-length segments
+    struct SegmentReader {
+        segments: Vec<Vec<u8>>,
+        i: usize, // which segment
+        j: usize, // which offset in segment
+    }
+
+    impl SegmentReader {
+        pub fn new(segments: Vec<Vec<u8>>) -> SegmentReader {
+            SegmentReader {
+                segments: segments,
+                i: 0,
+                j: 0,
+            }
+        }
+    }
+
+    impl Read for SegmentReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.i >= self.segments.len() {
+                return Ok(0);
+            }
+            let mut written = 0;
+            while written < buf.len() {
+                let to_copy = if self.segments[self.i][self.j..].len() < buf[written..].len() {
+                    self.segments[self.i][self.j..].len()
+                } else {
+                    buf[written..].len()
+                };
+
+                buf[written..(written + to_copy)]
+                    .copy_from_slice(&self.segments[self.i][self.j..(self.j + to_copy)]);
+
+                self.j += to_copy;
+                written += to_copy;
+
+                if self.j >= self.segments[self.i].len() {
+                    self.i += 1;
+                    self.j = 0;
+                }
+            }
+            Ok(written)
+        }
+    }
+
+    fn vec_u8(mut v: Vec<&str>) -> Vec<Vec<u8>> {
+        let mut ret = vec![];
+        for s_vec in v.drain(..) {
+            let v_u8 = s_vec.as_bytes().to_vec();
+            ret.push(v_u8);
+        }
+        ret
+    }
+
+    #[test]
+    fn test_segment_reader() {
+        let mut tests = vec![
+            (vec_u8(vec!["a", "b"]), "ab"),
+            (vec_u8(vec!["aa", "bbb", "cccc"]), "aabbbcccc"),
+            (vec_u8(vec!["aaaa", "bbb", "cc", "d", ""]), "aaaabbbccd"),
+            (vec_u8(vec!["", "a", "", "b", ""]), "ab"),
+            (vec_u8(vec![""]), ""),
+        ];
+        for (input_vec, expected) in tests.drain(..) {
+            let num_segments = input_vec.len();
+            let mut segment_io = SegmentReader::new(input_vec);
+            let mut output = vec![0u8; expected.len()];
+            let mut offset = 0;
+            for i in 0..num_segments {
+                let nw = segment_io.read(&mut output[offset..]).unwrap();
+                offset += nw;
+            }
+            assert_eq!(output, expected.as_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_http_chunked_encode() {
+        let tests = vec![
+            // (chunk size, byte string, expected encoding)
+            (10, "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd", "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n0\r\n\r\n"),
+            (10, "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddde", "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n1\r\ne\r\n0\r\n\r\n"),
+            (10, "aaaaaaaaaabbbbbbbbbbccccccccccddddddddddeeeee", "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n5\r\neeeee\r\n0\r\n\r\n"),
+            (1, "abcd", "1\r\na\r\n1\r\nb\r\n1\r\nc\r\n1\r\nd\r\n0\r\n\r\n"),
+            (3, "abcd", "3\r\nabc\r\n1\r\nd\r\n0\r\n\r\n"),
+            (10, "", "0\r\n\r\n")
+        ];
+        for (chunk_size, input_bytes, encoding) in tests.iter() {
+            let mut bytes = vec![];
+            {
+                let mut write_state = HttpChunkedTransferWriterState::new(*chunk_size as usize);
+                let mut encoder =
+                    HttpChunkedTransferWriter::from_writer_state(&mut bytes, &mut write_state);
+                encoder.write_all(input_bytes.as_bytes()).unwrap();
+                encoder.flush().unwrap();
+            }
+
+            assert_eq!(bytes, encoding.as_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_http_chunked_encode_multi() {
+        let tests = vec![
+            // chunk size, sequence of writes, expected encoding
+            (10, vec!["aaaaaaaaaa", "bbbbb", "bbbbb", "ccc", "ccc", "ccc", "c", "dd", "ddddd", "ddd"], "a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n0\r\n\r\n"),
+            (10, vec!["a", "a", "a", "a", "a", "a", "a", "a", "a", "a"], "a\r\naaaaaaaaaa\r\n0\r\n\r\n"),
+            (10, vec!["a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", "", "a", ""], "a\r\naaaaaaaaaa\r\n0\r\n\r\n"),
+        ];
+
+        for (chunk_size, input_vec, encoding) in tests.iter() {
+            let mut bytes = vec![];
+            {
+                let mut write_state = HttpChunkedTransferWriterState::new(*chunk_size as usize);
+                let mut encoder =
+                    HttpChunkedTransferWriter::from_writer_state(&mut bytes, &mut write_state);
+                for input in input_vec.iter() {
+                    encoder.write_all(input.as_bytes()).unwrap();
+                }
+                encoder.flush().unwrap();
+            }
+
+            assert_eq!(bytes, encoding.as_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_http_chunked_decode() {
+        let tests = vec![
+            ("a\r\naaaaaaaaaa\r\na\r\nbbbbbbbbbb\r\na\r\ncccccccccc\r\na\r\ndddddddddd\r\n0\r\n\r\n", "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd"),
+            ("A\r\naaaaaaaaaa\r\nA\r\nbbbbbbbbbb\r\nA\r\ncccccccccc\r\nA\r\ndddddddddd\r\n0\r\n\r\n", "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd"),
+            ("1\r\na\r\n2\r\nbb\r\n3\r\nccc\r\n4\r\ndddd\r\n0\r\n\r\n", "abbcccdddd"),
+            ("1\r\na\r\n0\r\n\r\n", "a"),
+            ("1\r\na\r\n0\r\n\r\n1\r\nb\r\n0\r\n\r\n", "a"),     // stop reading after the first 0-length chunk encountered
+            ("1; a; b\r\na\r\n0; c\r\n\r\n", "a"),                          // ignore short extensions
+            ("1  ; a ; b \r\na\r\n0     ; extension003\r\n\r\n", "a"),      // ignore short extensions
+            ("1 \t; a\t;\tb ;\r\na\r\n0\t\t;c\r\n\r\n", "a"),               // ignore short extensions
+        ];
+        for (encoded, expected) in tests.iter() {
+            let mut cursor = io::Cursor::new(encoded.as_bytes());
+            let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 50);
+            let mut output = vec![0u8; expected.len()];
+            decoder.read_exact(&mut output).unwrap();
+
+            assert_eq!(output, expected.as_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_http_chunked_decode_multi() {
+        let tests = vec![
+            (vec_u8(vec!["1\r\na", "\r\n", "0\r\n\r\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r", "\n0\r\n\r\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r\n", "0\r\n\r", "\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r\n0\r\n", "\r\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r\n0\r", "\n\r\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r\n0\r", "\n", "\r\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r\n0\r", "\n\r", "\n"]), "a"),
+            (vec_u8(vec!["1\r\na\r\n0\r", "\n", "\r", "\n"]), "a"),
+            (
+                vec_u8(vec![
+                    "1", "\r", "\n", "a", "\r", "\n", "0", "\r", "\n", "\r", "\n",
+                ]),
+                "a",
+            ),
+            (
+                vec_u8(vec![
+                    "a\r",
+                    "\n",
+                    "aaaa",
+                    "aaaaa",
+                    "a",
+                    "\r\n",
+                    "a\r\n",
+                    "bbbbbbbbbb\r",
+                    "\na\r\nccc",
+                    "ccccccc",
+                    "\r",
+                    "\na\r",
+                    "\ndddddd",
+                    "dddd",
+                    "\r\n0\r",
+                    "\n",
+                    "\r",
+                    "\n",
+                ]),
+                "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd",
+            ),
+            (
+                vec_u8(vec![
+                    "a\r\naaaaaaaaaa",
+                    "\r",
+                    "\n",
+                    "a\r\nbbbbbbbbbb\r",
+                    "\n",
+                    "a\r\ncccccccccc\r",
+                    "\na\r\nddddd",
+                    "ddddd\r",
+                    "\n0\r",
+                    "\n\r",
+                    "\n",
+                ]),
+                "aaaaaaaaaabbbbbbbbbbccccccccccdddddddddd",
+            ),
+            (
+                vec_u8(vec![
+                    "1",
+                    "\r",
+                    "\n",
+                    "",
+                    "a",
+                    "\r",
+                    "\n",
+                    "2",
+                    "\r\n",
+                    "bb",
+                    "\r\n",
+                    "3\r\n",
+                    "ccc\r",
+                    "\n4\r\n",
+                    "dddd\r\n",
+                    "0\r\n\r\n",
+                ]),
+                "abbcccdddd",
+            ),
+        ];
+        for (encoded_vec, expected) in tests.iter() {
+            test_debug!("expect {:?}", &expected);
+
+            let mut output = vec![];
+            let mut cursor = SegmentReader::new((*encoded_vec).clone());
+            let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 50);
+
+            for encoded in encoded_vec.iter() {
+                let mut tmp = vec![0u8; encoded.len()];
+                let nr = decoder.read(&mut tmp).unwrap();
+
+                output.extend_from_slice(&tmp[0..nr]);
+            }
+
+            assert_eq!(output, expected.as_bytes().to_vec());
+        }
+    }
+
+    #[test]
+    fn test_http_chunked_decode_err() {
+        let tests = vec![
+            (
+                "1; reallyreallyreallyreallylongextension;\r\na\r\n0\r\n\r\n",
+                1,
+                "too long",
+            ),
+            ("ffffffff\r\n", 1, "too big"),
+            ("nope\r\n", 1, "could not parse"),
+            ("1\na\r\n0\r\n\r\n", 1, "could not parse"),
+            ("a\r\naaaaaaaaaa", 11, "failed to fill whole buffer"),
+            ("1\r\nab\r\n0\r\n\r\n", 2, "Invalid chunk trailer"),
+            (
+                "15\r\naaaaaaaaaabbbbbbbbbbb\r\n0\r\n\r\n",
+                21,
+                "HTTP body exceeds maximum expected length",
+            ),
+            (
+                "7\r\naaaaaaa\r\n8\r\nbbbbbbbb\r\n6\r\ncccccc\r\n0\r\n\r\n",
+                21,
+                "HTTP body exceeds maximum expected length",
+            ),
+        ];
+        for (encoded, expected_len, expected) in tests.iter() {
+            test_debug!("expect '{}'", expected);
+            let mut cursor = io::Cursor::new(encoded.as_bytes());
+            let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 20);
+            let mut output = vec![0u8; *expected_len as usize];
+
+            let err = decoder.read_exact(&mut output).unwrap_err();
+            let errstr = format!("{:?}", &err);
+
+            assert!(
+                errstr.find(expected).is_some(),
+                "Expected '{}' in '{:?}'",
+                expected,
+                errstr
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_chunked_encode_decode_roundtrip() {
+        let mut rng = rand::thread_rng();
+        for i in 0..100 {
+            let mut data = vec![0u8; 256];
+            rng.fill_bytes(&mut data);
+
+            let mut encoded_data = vec![];
+            {
+                let mut write_state = HttpChunkedTransferWriterState::new(i + 1);
+                let mut encoder = HttpChunkedTransferWriter::from_writer_state(
+                    &mut encoded_data,
+                    &mut write_state,
+                );
+                encoder.write_all(&data).unwrap();
+                encoder.flush().unwrap();
+            }
+
+            let mut decoded_data = vec![0u8; 256];
+            {
+                let mut cursor = io::Cursor::new(&encoded_data);
+                let mut decoder = HttpChunkedTransferReader::from_reader(&mut cursor, 256);
+                decoder.read_exact(&mut decoded_data).unwrap();
+            }
+
+            assert_eq!(data, decoded_data);
+        }
+    }
+
+    #[test]
+    fn test_parse_reserved_header() {
+        let tests = vec![
+            (
+                "Content-Length",
+                "123",
+                Some(HttpReservedHeader::ContentLength(123)),
+            ),
+            (
+                "Content-Type",
+                "text/plain",
+                Some(HttpReservedHeader::ContentType(HttpContentType::Text)),
+            ),
+            (
+                "Content-Type",
+                "application/octet-stream",
+                Some(HttpReservedHeader::ContentType(HttpContentType::Bytes)),
+            ),
+            (
+                "Content-Type",
+                "application/json",
+                Some(HttpReservedHeader::ContentType(HttpContentType::JSON)),
+            ),
+            (
+                "X-Request-Id",
+                "123",
+                Some(HttpReservedHeader::XRequestID(123)),
+            ),
+            (
+                "Host",
+                "foo:123",
+                Some(HttpReservedHeader::Host(PeerHost::DNS(
+                    "foo".to_string(),
+                    123,
+                ))),
+            ),
+            (
+                "Host",
+                "1.2.3.4:123",
+                Some(HttpReservedHeader::Host(PeerHost::IP(
+                    PeerAddress([
+                        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff,
+                        0x01, 0x02, 0x03, 0x04,
+                    ]),
+                    123,
+                ))),
+            ),
+            // errors
+            ("Content-Length", "-1", None),
+            ("Content-Length", "asdf", None),
+            ("Content-Length", "4294967296", None),
+            ("Content-Type", "blargh", None),
+            ("X-Request-Id", "-1", None),
+            ("X-Request-Id", "asdf", None),
+            ("X-Request-Id", "4294967296", None),
+            ("Unrecognized", "header", None),
+        ];
+
+        for (key, value, expected_result) in tests {
+            let result = HttpReservedHeader::try_from_str(key, value);
+            assert_eq!(result, expected_result);
+        }
+    }
+
+    #[test]
+    fn test_parse_http_request_preamble_ok() {
+        let tests = vec![
+            ("GET /foo HTTP/1.1\r\nHost: localhost:6270\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, true, vec![], vec![])),
+            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nFoo: Bar\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, true, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nFoo: Bar\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, true, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("GET /foo HTTP/1.1\r\nConnection: close\r\nHost: localhost:6270\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, false, vec![], vec![])),
+            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nConnection: close\r\nFoo: Bar\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, false, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("POST asdf HTTP/1.1\r\nHost: core.blockstack.org\r\nFoo: Bar\r\nConnection: close\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, false, vec!["foo".to_string()], vec!["Bar".to_string()])) 
+        ];
+
+        for (data, request) in tests.iter() {
+            let req = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(req.is_ok(), format!("{:?}", &req));
+            assert_eq!(req.unwrap(), *request);
+
+            let sreq = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(sreq.is_ok(), format!("{:?}", &sreq));
+            assert_eq!(
+                sreq.unwrap(),
+                StacksHttpPreamble::Request((*request).clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_http_request_options() {
+        let data = "OPTIONS /foo HTTP/1.1\r\nHost: localhost:6270\r\n\r\n";
+        let req = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
+        let preamble = HttpRequestPreamble::from_headers(
+            HttpVersion::Http11,
+            "OPTIONS".to_string(),
+            "/foo".to_string(),
+            "localhost".to_string(),
+            6270,
+            true,
+            vec![],
+            vec![],
+        );
+        assert_eq!(req.unwrap(), preamble);
+    }
+
+    #[test]
+    fn test_parse_http_request_preamble_case_ok() {
+        let tests = vec![
+            ("GET /foo HTTP/1.1\r\nhOsT: localhost:6270\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, true, vec![], vec![])),
+            ("GET /foo HTTP/1.1\r\ncOnNeCtIoN: cLoSe\r\nhOsT: localhost:6270\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "GET".to_string(), "/foo".to_string(), "localhost".to_string(), 6270, false, vec![], vec![])),
+            ("POST asdf HTTP/1.1\r\nhOsT: core.blockstack.org\r\nCOnNeCtIoN: kEeP-aLiVE\r\nFoo: Bar\r\n\r\n",
+             HttpRequestPreamble::from_headers(HttpVersion::Http11, "POST".to_string(), "asdf".to_string(), "core.blockstack.org".to_string(), 80, true, vec!["foo".to_string()], vec!["Bar".to_string()])),
+        ];
+
+        for (data, request) in tests.iter() {
+            let req = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(req.is_ok(), format!("{:?}", &req));
+            assert_eq!(req.unwrap(), *request);
+
+            let sreq = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(sreq.is_ok(), format!("{:?}", &sreq));
+            assert_eq!(
+                sreq.unwrap(),
+                StacksHttpPreamble::Request((*request).clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_http_request_preamble_err() {
+        let tests = vec![
+            ("GET /foo HTTP/1.1\r\n", "failed to fill whole buffer"),
+            ("GET /foo HTTP/1.1\r\n\r\n", "Missing Host header"),
+            (
+                "GET /foo HTTP/1.1\r\nFoo: Bar\r\n\r\n",
+                "Missing Host header",
+            ),
+            ("GET /foo HTTP/\r\n\r\n", "Failed to parse HTTP request"),
+            ("GET /foo HTTP/1.1\r\nHost:", "failed to fill whole buffer"),
+            (
+                "GET /foo HTTP/1.1\r\nHost: foo:80\r\nHost: bar:80\r\n\r\n",
+                "duplicate header",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nHost: localhost:6270\r\nfoo: \u{2764}\r\n\r\n",
+                "header value is not ASCII-US",
+            ),
+            (
+                "Get /foo HTTP/1.1\r\nHost: localhost:666666\r\n\r\n",
+                "Missing Host header",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nHost: localhost:8080\r\nConnection: foo\r\n\r\n",
+                "invalid Connection: header",
+            ),
+        ];
+
+        for (data, errstr) in tests.iter() {
+            let res = HttpRequestPreamble::consensus_deserialize(&mut data.as_bytes());
+            test_debug!("Expect '{}'", errstr);
+            let expected_errstr = format!("{:?}", &res);
+            assert!(res.is_err(), expected_errstr);
+            assert!(
+                res.unwrap_err().to_string().find(errstr).is_some(),
+                expected_errstr
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_stacks_http_preamble_request_err() {
+        let tests = vec![
+            (
+                "GET /foo HTTP/1.1\r\n",
+                "Not enough bytes to form a HTTP request or response",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nFoo: Bar\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+            (
+                "GET /foo HTTP/\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nHost:",
+                "Not enough bytes to form a HTTP request or response",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nHost: foo:80\r\nHost: bar:80\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nHost: localhost:6270\r\nfoo: \u{2764}\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+            (
+                "Get /foo HTTP/1.1\r\nHost: localhost:666666\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+            (
+                "GET /foo HTTP/1.1\r\nHost: localhost:8080\r\nConnection: foo\r\n\r\n",
+                "Failed to decode HTTP request or HTTP response",
+            ),
+        ];
+
+        for (data, errstr) in tests.iter() {
+            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
+            let expected_serrstr = format!("{:?}", &sres);
+            test_debug!("Expect '{}'", errstr);
+            assert!(sres.is_err(), expected_serrstr);
+            assert!(
+                sres.unwrap_err().to_string().find(errstr).is_some(),
+                expected_serrstr
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_request_metadata_timeout() {
+        let mut req = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/contracts/call-read/foo/bar/baz".to_string(),
+            "localhost".to_string(),
+            6270,
+            true,
+        );
+        assert_eq!(
+            HttpRequestMetadata::from_preamble(&req).request_timeout,
+            None
+        );
+
+        req.add_header("X-Request-Timeout".to_string(), "5".to_string());
+        assert_eq!(
+            HttpRequestMetadata::from_preamble(&req).request_timeout,
+            Some(5)
+        );
+
+        let mut bytes = vec![];
+        req.consensus_serialize(&mut bytes).unwrap();
+        let req_decoded = HttpRequestPreamble::consensus_deserialize(&mut &bytes[..]).unwrap();
+        assert_eq!(
+            HttpRequestMetadata::from_preamble(&req_decoded).request_timeout,
+            Some(5)
+        );
+
+        req.add_header("X-Request-Timeout".to_string(), "soon".to_string());
+        assert_eq!(
+            HttpRequestMetadata::from_preamble(&req).request_timeout,
+            None
+        );
+    }
+
+    #[test]
+    fn test_http_post_transaction_idempotency_key() {
+        let mut md = HttpRequestMetadata::from_host(PeerHost::from_host_port(
+            "localhost".to_string(),
+            20443,
+        ));
+        md.idempotency_key = Some("0c5a6b3e-retry-1".to_string());
+
+        let req = HttpRequestType::PostTransaction(md, make_test_transaction(), None, None);
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(req.clone()))
+            .unwrap();
+
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+        match message {
+            StacksHttpMessage::Request(HttpRequestType::PostTransaction(md, ..)) => {
+                assert_eq!(md.idempotency_key, Some("0c5a6b3e-retry-1".to_string()));
+            }
+            _ => panic!("Expected a PostTransaction"),
+        }
+
+        let mut tx_body = vec![];
+        make_test_transaction()
+            .consensus_serialize(&mut tx_body)
+            .unwrap();
+
+        for bad_key in ["", "has space", &"a".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1)].iter() {
+            let mut preamble = HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "POST".to_string(),
+                "/v2/transactions".to_string(),
+                "localhost".to_string(),
+                20443,
+                true,
+            );
+            preamble.set_content_type(HttpContentType::Bytes);
+            preamble.set_content_length(tx_body.len() as u32);
+            preamble.add_header("Idempotency-Key".to_string(), bad_key.to_string());
+
+            let mut bytes = vec![];
+            preamble.consensus_serialize(&mut bytes).unwrap();
+            bytes.extend_from_slice(&tx_body);
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            match http.read_payload(&preamble, &bytes[offset..]) {
+                Err(net_error::DeserializeError(msg)) => {
+                    assert!(msg.find("Idempotency-Key").is_some());
+                }
+                x => panic!("Expected a DeserializeError for {:?}, got {:?}", bad_key, x),
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_request_preamble_headers() {
+        let mut req = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "GET".to_string(),
+            "/foo".to_string(),
+            "localhost".to_string(),
+            6270,
+            true,
+        );
+        let req_11 = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "GET".to_string(),
+            "/foo".to_string(),
+            "localhost".to_string(),
+            6270,
+            false,
+        );
+        let req_10 = HttpRequestPreamble::new(
+            HttpVersion::Http10,
+            "GET".to_string(),
+            "/foo".to_string(),
+            "localhost".to_string(),
+            6270,
+            false,
+        );
+
+        req.add_header("foo".to_string(), "bar".to_string());
+
+        assert_eq!(req.content_type, None);
+        req.set_content_type(HttpContentType::JSON);
+        assert_eq!(req.content_type, Some(HttpContentType::JSON));
+
+        req.add_header(
+            "content-type".to_string(),
+            "application/octet-stream".to_string(),
+        );
+        assert_eq!(req.content_type, Some(HttpContentType::Bytes));
+
+        let mut bytes = vec![];
+        req.consensus_serialize(&mut bytes).unwrap();
+        let txt = String::from_utf8(bytes).unwrap();
+
+        test_debug!("headers:\n{}", txt);
+
+        assert!(txt.find("HTTP/1.1").is_some(), "HTTP version is missing");
+        assert!(
+            txt.find("User-Agent: stacks/2.0\r\n").is_some(),
+            "User-Agnet header is missing"
+        );
+        assert!(
+            txt.find("Host: localhost:6270\r\n").is_some(),
+            "Host header is missing"
+        );
+        assert!(txt.find("foo: bar\r\n").is_some(), "foo header is missing");
+        assert!(
+            txt.find("Content-Type: application/octet-stream\r\n")
+                .is_some(),
+            "content-type is missing"
+        );
+        assert!(txt.find("Connection: ").is_none()); // not sent if keep_alive is true (for HTTP/1.1)
+
+        let mut bytes_10 = vec![];
+        req_10.consensus_serialize(&mut bytes_10).unwrap();
+        let txt_10 = String::from_utf8(bytes_10).unwrap();
+
+        assert!(txt_10.find("HTTP/1.0").is_some(), "HTTP version is missing");
+
+        let mut bytes_11 = vec![];
+        req_11.consensus_serialize(&mut bytes_11).unwrap();
+        let txt_11 = String::from_utf8(bytes_11).unwrap();
+
+        assert!(txt_11.find("HTTP/1.1").is_some(), "HTTP version is wrong");
+        assert!(
+            txt_11.find("Connection: close").is_some(),
+            "Explicit Connection: close is missing"
+        );
+    }
+
+    #[test]
+    fn test_parse_http_response_preamble_ok() {
+        let tests = vec![
+            ("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 123\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "OK".to_string(), true, Some(123), HttpContentType::Bytes, 0, vec![], vec![])),
+            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: 456\r\nFoo: Bar\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), true, Some(456), HttpContentType::JSON, 0, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: 456\r\nX-Request-Id: 123\r\nFoo: Bar\r\n\r\n",
+             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), true, Some(456), HttpContentType::JSON, 123, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("HTTP/1.1 200 Ok\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "Ok".to_string(), true, None, HttpContentType::Bytes, 0, vec![], vec![])),
+            ("HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 123\r\nConnection: close\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "OK".to_string(), false, Some(123), HttpContentType::Bytes, 0, vec![], vec![])),
+            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nContent-Length: 456\r\nConnection: close\r\nFoo: Bar\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), false, Some(456), HttpContentType::JSON, 0, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("HTTP/1.1 400 Bad Request\r\nContent-Type: application/json\r\nConnection: close\r\nContent-Length: 456\r\nX-Request-Id: 123\r\nFoo: Bar\r\n\r\n",
+             HttpResponsePreamble::from_headers(400, "Bad Request".to_string(), false, Some(456), HttpContentType::JSON, 123, vec!["foo".to_string()], vec!["Bar".to_string()])),
+            ("HTTP/1.1 200 Ok\r\nConnection: close\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "Ok".to_string(), false, None, HttpContentType::Bytes, 0, vec![], vec![])),
+        ];
+
+        for (data, response) in tests.iter() {
+            test_debug!("Try parsing:\n{}\n", data);
+            let res = HttpResponsePreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(res.is_ok(), format!("{:?}", &res));
+            assert_eq!(res.unwrap(), *response);
+
+            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(sres.is_ok(), format!("{:?}", &sres));
+            assert_eq!(
+                sres.unwrap(),
+                StacksHttpPreamble::Response((*response).clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_http_response_case_ok() {
+        let tests = vec![
+            ("HTTP/1.1 200 OK\r\ncOnTeNt-TyPe: aPpLiCaTiOn/oCtEt-StReAm\r\ncOnTeNt-LeNgTh: 123\r\nx-ReQuEsT-iD: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "OK".to_string(), true, Some(123), HttpContentType::Bytes, 0, vec![], vec![])),
+            ("HTTP/1.1 200 Ok\r\ncOnTeNt-tYpE: aPpLiCaTiOn/OcTeT-sTrEaM\r\ntRaNsFeR-eNcOdInG: cHuNkEd\r\nX-rEqUeSt-Id: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "Ok".to_string(), true, None, HttpContentType::Bytes, 0, vec![], vec![])),
+            ("HTTP/1.1 200 Ok\r\ncOnNeCtIoN: cLoSe\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "Ok".to_string(), false, None, HttpContentType::Bytes, 0, vec![], vec![])),
+            ("HTTP/1.1 200 Ok\r\ncOnNeCtIoN: kEeP-AlIvE\r\nContent-Type: application/octet-stream\r\nTransfer-encoding: chunked\r\nX-Request-ID: 0\r\n\r\n",
+             HttpResponsePreamble::from_headers(200, "Ok".to_string(), true, None, HttpContentType::Bytes, 0, vec![], vec![])),
+        ];
+
+        for (data, response) in tests.iter() {
+            test_debug!("Try parsing:\n{}\n", data);
+            let res = HttpResponsePreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(res.is_ok(), format!("{:?}", &res));
+            assert_eq!(res.unwrap(), *response);
+
+            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
+            assert!(sres.is_ok(), format!("{:?}", &sres));
+            assert_eq!(
+                sres.unwrap(),
+                StacksHttpPreamble::Response((*response).clone())
+            );
+        }
+    }
+
+    #[test]
+    fn test_http_response_preamble_headers() {
+        let mut res = HttpResponsePreamble::new(
+            200,
+            "OK".to_string(),
+            Some(123),
+            HttpContentType::JSON,
+            true,
+            123,
+        );
+        assert_eq!(res.request_id, 123);
+
+        res.set_request_id(456);
+        assert_eq!(res.request_id, 456);
+
+        res.add_header("foo".to_string(), "bar".to_string());
+        res.add_CORS_headers();
+
+        let mut bytes = vec![];
+        res.consensus_serialize(&mut bytes).unwrap();
+        let txt = String::from_utf8(bytes).unwrap();
+        assert!(
+            txt.find("Server: stacks/2.0\r\n").is_some(),
+            "Server header is missing"
+        );
+        assert!(
+            txt.find("Content-Length: 123\r\n").is_some(),
+            "Content-Length is missing"
+        );
+        assert!(
+            txt.find("Content-Type: application/json\r\n").is_some(),
+            "Content-Type is missing"
+        );
+        assert!(txt.find("Date: ").is_some(), "Date header is missing");
+        assert!(txt.find("foo: bar\r\n").is_some(), "foo header is missing");
+        assert!(
+            txt.find("X-Request-Id: 456\r\n").is_some(),
+            "X-Request-Id is missing"
+        );
+        assert!(
+            txt.find("Access-Control-Allow-Origin: *\r\n").is_some(),
+            "CORS header is missing"
+        );
+        assert!(
+            txt.find("Access-Control-Allow-Headers: origin, content-type\r\n")
+                .is_some(),
+            "CORS header is missing"
+        );
+        assert!(
+            txt.find("Access-Control-Allow-Methods: POST, GET, OPTIONS\r\n")
+                .is_some(),
+            "CORS header is missing"
+        );
+        assert!(txt.find("Connection: ").is_none()); // not sent if keep_alive is true
+    }
+
+    #[test]
+    fn test_parse_http_response_preamble_err() {
+        let tests = vec![
+            ("HTTP/1.1 200",
+            "failed to fill whole buffer"),
+            ("HTTP/1.1 200 OK\r\nfoo: \u{2764}\r\n\r\n",
+            "header value is not ASCII-US"),
+            ("HTTP/1.1 200 OK\r\nfoo: bar\r\nfoo: bar\r\n\r\n",
+             "duplicate header"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n",
+             "Unsupported HTTP content type"),
+            ("HTTP/1.1 200 OK\r\nContent-Length: foo\r\n\r\n",
+             "Invalid Content-Length"),
+            ("HTTP/1.1 200 OK\r\nContent-Length: 123\r\n\r\n",
+             "missing Content-Type, Content-Length"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n",
+             "missing Content-Type, Content-Length"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nTransfer-Encoding: chunked\r\n\r\n",
+             "incompatible transfer-encoding and content-length"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nConnection: foo\r\n\r\n",
+             "invalid Connection: header"),
+        ];
+
+        for (data, errstr) in tests.iter() {
+            let res = HttpResponsePreamble::consensus_deserialize(&mut data.as_bytes());
+            test_debug!("Expect '{}', got: {:?}", errstr, &res);
+            assert!(res.is_err(), format!("{:?}", &res));
+            assert!(res.unwrap_err().to_string().find(errstr).is_some());
+        }
+    }
+
+    #[test]
+    fn test_parse_stacks_http_preamble_response_err() {
+        let tests = vec![
+            ("HTTP/1.1 200",
+            "Not enough bytes to form a HTTP request or response"),
+            ("HTTP/1.1 200 OK\r\nfoo: \u{2764}\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nfoo: bar\r\nfoo: bar\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: image/png\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nContent-Length: foo\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nContent-Length: 123\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nTransfer-Encoding: chunked\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+            ("HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 123\r\nConnection: foo\r\n\r\n",
+             "Failed to decode HTTP request or HTTP response"),
+        ];
+
+        for (data, errstr) in tests.iter() {
+            let sres = StacksHttpPreamble::consensus_deserialize(&mut data.as_bytes());
+            let expected_serrstr = format!("{:?}", &sres);
+            test_debug!("Expect '{}', got: {:?}", errstr, &sres);
+            assert!(sres.is_err(), expected_serrstr);
+            assert!(
+                sres.unwrap_err().to_string().find(errstr).is_some(),
+                expected_serrstr
+            );
+        }
+    }
+
+    fn make_test_transaction() -> StacksTransaction {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let addr = auth.origin().address_testnet();
+        let recv_addr = StacksAddress {
+            version: 1,
+            bytes: Hash160([0xff; 20]),
+        };
+
+        let mut tx_stx_transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::TokenTransfer(
+                recv_addr.clone().into(),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        );
+        tx_stx_transfer.chain_id = 0x80000000;
+        tx_stx_transfer.post_condition_mode = TransactionPostConditionMode::Allow;
+        tx_stx_transfer.set_tx_fee(0);
+        tx_stx_transfer
+    }
+
+    #[test]
+    fn test_http_parse_host_header_value() {
+        let hosts = vec![
+            "1.2.3.4",
+            "1.2.3.4:5678",
+            "[1:203:405:607:809:a0b:c0d:e0f]",
+            "[1:203:405:607:809:a0b:c0d:e0f]:12345",
+            "www.foo.com",
+            "www.foo.com:12345",
+            // invalid IP addresses will be parsed to DNS names
+            "1.2.3.4.5",
+            "[1:203:405:607:809:a0b:c0d:e0f:1011]",
+            // these won't parse at all, since the port is invalid
+            "1.2.3.4:1234567",
+            "1.2.3.4.5:1234567",
+            "[1:203:405:607:809:a0b:c0d:e0f]:1234567",
+            "[1:203:405:607:809:a0b:c0d:e0f:1011]:1234567",
+            "www.foo.com:1234567",
+            ":",
+            ":123",
+        ];
+
+        let peerhosts = vec![
+            Some(PeerHost::IP(
+                PeerAddress([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 1, 2, 3, 4]),
+                80,
+            )),
+            Some(PeerHost::IP(
+                PeerAddress([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 1, 2, 3, 4]),
+                5678,
+            )),
+            Some(PeerHost::IP(
+                PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                80,
+            )),
+            Some(PeerHost::IP(
+                PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                12345,
+            )),
+            Some(PeerHost::DNS("www.foo.com".to_string(), 80)),
+            Some(PeerHost::DNS("www.foo.com".to_string(), 12345)),
+            Some(PeerHost::DNS("1.2.3.4.5".to_string(), 80)),
+            Some(PeerHost::DNS(
+                "[1:203:405:607:809:a0b:c0d:e0f:1011]".to_string(),
+                80,
+            )),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ];
+
+        for (host, expected_host) in hosts.iter().zip(peerhosts.iter()) {
+            let peerhost = match host.parse::<PeerHost>() {
+                Ok(ph) => Some(ph),
+                Err(_) => None,
+            };
+
+            match (peerhost, expected_host) {
+                (Some(ref ph), Some(ref expected_ph)) => assert_eq!(*ph, *expected_ph),
+                (None, None) => {}
+                (Some(ph), None) => {
+                    eprintln!(
+                        "Parsed {} successfully to {:?}, but expected error",
+                        host, ph
+                    );
+                    assert!(false);
+                }
+                (None, Some(expected_ph)) => {
+                    eprintln!("Failed to parse {} successfully", host);
+                    assert!(false);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_request_type_codec() {
+        let http_request_metadata_ip = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::IP(
+                PeerAddress([0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15]),
+                12345,
+            ),
+            keep_alive: true,
+            api_version: HttpApiVersion::V2,
+            request_timeout: None,
+            idempotency_key: None,
+        };
+        let http_request_metadata_dns = HttpRequestMetadata {
+            version: HttpVersion::Http11,
+            peer: PeerHost::DNS("www.foo.com".to_string(), 80),
+            keep_alive: true,
+            api_version: HttpApiVersion::V2,
+            request_timeout: None,
+            idempotency_key: None,
+        };
+
+        let tests = vec![
+            HttpRequestType::GetNeighbors(http_request_metadata_ip.clone()),
+            HttpRequestType::GetBlock(http_request_metadata_dns.clone(), StacksBlockId([2u8; 32])),
+            HttpRequestType::GetMicroblocksIndexed(
+                http_request_metadata_ip.clone(),
+                StacksBlockId([3u8; 32]),
+            ),
+            HttpRequestType::GetStateHash(http_request_metadata_dns.clone(), 123, None),
+            HttpRequestType::GetMemPoolDrops(http_request_metadata_dns.clone(), Txid([0x55; 32])),
+            HttpRequestType::GetMemPoolFees(http_request_metadata_ip.clone()),
+            HttpRequestType::GetMinerRewards(
+                http_request_metadata_dns.clone(),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                Some(StacksBlockId([0x66; 32])),
+            ),
+            HttpRequestType::GetMemPoolSnapshot(
+                http_request_metadata_ip.clone(),
+                RPCPageRequest::default(),
+            ),
+            HttpRequestType::GetEventObserverStatus(http_request_metadata_ip.clone()),
+            HttpRequestType::GetEventReplays(http_request_metadata_ip.clone()),
+            HttpRequestType::GetPeerFilters(http_request_metadata_ip.clone()),
+            HttpRequestType::GetAtlasContracts(http_request_metadata_ip.clone()),
+            HttpRequestType::GetAttachmentPins(http_request_metadata_ip.clone()),
+            HttpRequestType::GetBlockProcessing(http_request_metadata_ip.clone()),
+            HttpRequestType::GetQueryJob(
+                http_request_metadata_dns.clone(),
+                42,
+                RPCPageRequest {
+                    cursor: Some("100".to_string()),
+                    limit: Some(50),
+                },
+            ),
+            HttpRequestType::GetFeatures(http_request_metadata_dns.clone()),
+            HttpRequestType::GetNeighborWalkInfo(http_request_metadata_dns.clone()),
+            HttpRequestType::GetNodeStatus(http_request_metadata_ip.clone()),
+            HttpRequestType::GetMicroblockForks(http_request_metadata_dns.clone(), None),
+            HttpRequestType::GetMicroblockForks(
+                http_request_metadata_ip.clone(),
+                Some(StacksBlockId([0x22; 32])),
+            ),
+            HttpRequestType::GetBlockDecoded(
+                http_request_metadata_dns.clone(),
+                StacksBlockId([0x23; 32]),
+            ),
+            HttpRequestType::GetMicroblocksIndexedDecoded(
+                http_request_metadata_ip.clone(),
+                StacksBlockId([0x24; 32]),
+            ),
+            HttpRequestType::GetMicroblocksConfirmedDecoded(
+                http_request_metadata_dns.clone(),
+                StacksBlockId([0x25; 32]),
+            ),
+            HttpRequestType::GetMemPoolSnapshot(
+                http_request_metadata_ip.clone(),
+                RPCPageRequest {
+                    cursor: Some("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R:5".to_string()),
+                    limit: Some(50),
+                },
+            ),
+            HttpRequestType::GetAddressMempool(
+                http_request_metadata_dns.clone(),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                RPCPageRequest {
+                    cursor: Some(format!("7:{}", Txid([0x55; 32]))),
+                    limit: Some(10),
+                },
+            ),
+            HttpRequestType::GetBlockFetch(
+                http_request_metadata_ip.clone(),
+                StacksBlockId([0x26; 32]),
+                false,
+            ),
+            HttpRequestType::PostBlockFetch(
+                http_request_metadata_dns.clone(),
+                StacksBlockId([0x27; 32]),
+                true,
+            ),
+            HttpRequestType::PostTransaction(
+                http_request_metadata_dns.clone(),
+                make_test_transaction(),
+                None,
+                None,
+            ),
+            HttpRequestType::PostMemPoolSnapshot(
+                http_request_metadata_dns.clone(),
+                vec![make_test_transaction()],
+            ),
+            HttpRequestType::PostPeerFilter(
+                http_request_metadata_dns.clone(),
+                PeerFilterAction::Add,
+                PeerFilterList::Deny,
+                PeerFilterRule::from_cidr_str("10.0.0.0/8").unwrap(),
+            ),
+            HttpRequestType::PostAtlasContract(
+                http_request_metadata_dns.clone(),
+                QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.attestations")
+                    .unwrap(),
+                Some(512),
+                None,
+            ),
+            HttpRequestType::PostAttachmentPin(
+                http_request_metadata_dns.clone(),
+                AttachmentPinAction::Unpin,
+                Hash160([0x28; 20]),
+            ),
+            HttpRequestType::PostBlockProcessing(
+                http_request_metadata_dns.clone(),
+                BlockProcessingAction::Pause,
+            ),
+            HttpRequestType::PostEventReplay(
+                http_request_metadata_dns.clone(),
+                EventReplayRequestBody {
+                    endpoint: "localhost:3700".to_string(),
+                    from_height: 1,
+                    to_height: Some(1000),
+                    blocks_per_second: None,
+                },
+            ),
+            HttpRequestType::PostQueryJob(
+                http_request_metadata_dns.clone(),
+                QueryJobKind::ContractHistory(
+                    QualifiedContractIdentifier::parse(
+                        "ST000000000000000000002AMW42H.attestations",
+                    )
+                    .unwrap(),
+                ),
+            ),
+            HttpRequestType::PostTransaction(
+                http_request_metadata_dns.clone(),
+                make_test_transaction(),
+                None,
+                Some(12345),
+            ),
+            HttpRequestType::EncodeClarityValue(http_request_metadata_dns.clone(), Value::UInt(7)),
+            HttpRequestType::DecodeClarityValue(
+                http_request_metadata_dns.clone(),
+                "0x0000000000000000000000000000000001".to_string(),
+            ),
+            HttpRequestType::ValidateContractCall(
+                http_request_metadata_dns.clone(),
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+                "hello-world".try_into().unwrap(),
+                "add-unit".try_into().unwrap(),
+                vec!["0x0100000000000000000000000000000001".to_string()],
+                None,
+            ),
+            HttpRequestType::GetContractCosts(
+                http_request_metadata_ip.clone(),
+                Some(100),
+                None,
+                Some(10),
+            ),
+            HttpRequestType::SimulateBlock(http_request_metadata_dns.clone()),
+            HttpRequestType::GetPeerVersions(http_request_metadata_ip.clone()),
+            HttpRequestType::GetNodeIdentity(http_request_metadata_dns.clone(), None),
+            HttpRequestType::GetNodeIdentity(
+                http_request_metadata_ip.clone(),
+                Some(vec![0x00, 0xff]),
+            ),
+            HttpRequestType::GetMicroblockProof(
+                http_request_metadata_dns.clone(),
+                Txid([0x56; 32]),
+            ),
+            HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
+        ];
+
+        let mut tx_body = vec![];
+        make_test_transaction()
+            .consensus_serialize(&mut tx_body)
+            .unwrap();
+
+        let mut post_transaction_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/transactions".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_transaction_preamble.set_content_type(HttpContentType::Bytes);
+        post_transaction_preamble.set_content_length(tx_body.len() as u32);
+
+        let snapshot_body = format!("[\"{}\"]", to_hex(&tx_body)).into_bytes();
+
+        let mut post_snapshot_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/mempool/import".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_snapshot_preamble.set_content_type(HttpContentType::JSON);
+        post_snapshot_preamble.set_content_length(snapshot_body.len() as u32);
+
+        let peer_filter_body = r#"{"action":"add","list":"deny","cidr":"10.0.0.0/8"}"#
+            .as_bytes()
+            .to_vec();
+
+        let mut post_peer_filter_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/peers/filters".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_peer_filter_preamble.set_content_type(HttpContentType::JSON);
+        post_peer_filter_preamble.set_content_length(peer_filter_body.len() as u32);
+
+        let atlas_contract_body = r#"{"contract_id":"ST000000000000000000002AMW42H.attestations","attachments_max_size":512}"#
+            .as_bytes()
+            .to_vec();
+
+        let mut post_atlas_contract_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/atlas/contracts".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_atlas_contract_preamble.set_content_type(HttpContentType::JSON);
+        post_atlas_contract_preamble.set_content_length(atlas_contract_body.len() as u32);
+
+        let attachment_pin_body = format!(
+            r#"{{"action":"unpin","hash":"{}"}}"#,
+            Hash160([0x28; 20]).to_hex()
+        )
+        .into_bytes();
+
+        let mut post_attachment_pin_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/atlas/pins".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_attachment_pin_preamble.set_content_type(HttpContentType::JSON);
+        post_attachment_pin_preamble.set_content_length(attachment_pin_body.len() as u32);
+
+        let block_processing_body = r#"{"action":"pause"}"#.as_bytes().to_vec();
+
+        let mut post_block_processing_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/blocks/processing".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_block_processing_preamble.set_content_type(HttpContentType::JSON);
+        post_block_processing_preamble.set_content_length(block_processing_body.len() as u32);
+
+        let event_replay_body = r#"{"endpoint":"localhost:3700","from_height":1,"to_height":1000,"blocks_per_second":null}"#
+            .as_bytes()
+            .to_vec();
+
+        let mut post_event_replay_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/events/replay".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_event_replay_preamble.set_content_type(HttpContentType::JSON);
+        post_event_replay_preamble.set_content_length(event_replay_body.len() as u32);
+
+        let query_job_body =
+            r#"{"kind":"contract-history","contract_id":"ST000000000000000000002AMW42H.attestations"}"#
+                .as_bytes()
+                .to_vec();
+
+        let mut post_query_job_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/jobs".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_query_job_preamble.set_content_type(HttpContentType::JSON);
+        post_query_job_preamble.set_content_length(query_job_body.len() as u32);
+
+        let expiring_tx_body = format!(
+            r#"{{"tx":"{}","attachment":null,"expire_height":12345}}"#,
+            to_hex(&tx_body)
+        )
+        .into_bytes();
+
+        let mut post_expiring_transaction_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/transactions".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_expiring_transaction_preamble.set_content_type(HttpContentType::JSON);
+        post_expiring_transaction_preamble.set_content_length(expiring_tx_body.len() as u32);
+
+        let encode_value_body = r#"{"value":{"UInt":7}}"#.as_bytes().to_vec();
+
+        let mut post_encode_value_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/clarity/value/encode".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_encode_value_preamble.set_content_type(HttpContentType::JSON);
+        post_encode_value_preamble.set_content_length(encode_value_body.len() as u32);
+
+        let decode_value_body =
+            r#"{"hex":"0x0000000000000000000000000000000001"}"#.as_bytes().to_vec();
+
+        let mut post_decode_value_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/clarity/value/decode".to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_decode_value_preamble.set_content_type(HttpContentType::JSON);
+        post_decode_value_preamble.set_content_length(decode_value_body.len() as u32);
+
+        let validate_call_body = r#"{"arguments":["0x0100000000000000000000000000000001"]}"#
+            .as_bytes()
+            .to_vec();
+
+        let mut post_validate_call_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/contracts/validate-call/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/add-unit"
+                .to_string(),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_validate_call_preamble.set_content_type(HttpContentType::JSON);
+        post_validate_call_preamble.set_content_length(validate_call_body.len() as u32);
+
+        let mut post_block_fetch_preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            format!(
+                "/v2/blocks/fetch/{}?microblocks=1",
+                StacksBlockId([0x27; 32]).to_hex()
+            ),
+            http_request_metadata_dns.peer.hostname(),
+            http_request_metadata_dns.peer.port(),
+            http_request_metadata_dns.keep_alive,
+        );
+        post_block_fetch_preamble.set_content_length(0);
+
+        // all of these should parse
+        let expected_http_preambles = vec![
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/neighbors".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/blocks/{}", StacksBlockId([2u8; 32]).to_hex()),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/microblocks/{}", StacksBlockId([3u8; 32]).to_hex()),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/state_hash/123".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/mempool/dropped/{}", Txid([0x55; 32])),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/fees/mempool".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/miner_rewards/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R?tip={}",
+                    StacksBlockId([0x66; 32])
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/mempool/export".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/events/status".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/events/replay".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/peers/filters".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/atlas/contracts".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/atlas/pins".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/blocks/processing".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/jobs/42?cursor=100&limit=50".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/features".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/neighbors/walk".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/status".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/microblocks/forks".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/microblocks/forks/{}",
+                    StacksBlockId([0x22; 32]).to_hex()
+                ),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/blocks/{}?decode=true",
+                    StacksBlockId([0x23; 32]).to_hex()
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/microblocks/{}?decode=true",
+                    StacksBlockId([0x24; 32]).to_hex()
+                ),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/microblocks/confirmed/{}?decode=true",
+                    StacksBlockId([0x25; 32]).to_hex()
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/admin/mempool/export?cursor=ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R%3A5&limit=50"
+                    .to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!(
+                    "/v2/addresses/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/mempool?cursor=7%3A{}&limit=10",
+                    Txid([0x55; 32])
+                ),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/blocks/fetch/{}", StacksBlockId([0x26; 32]).to_hex()),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
+            ),
+            post_block_fetch_preamble,
+            post_transaction_preamble,
+            post_snapshot_preamble,
+            post_peer_filter_preamble,
+            post_atlas_contract_preamble,
+            post_attachment_pin_preamble,
+            post_block_processing_preamble,
+            post_event_replay_preamble,
+            post_query_job_preamble,
+            post_expiring_transaction_preamble,
+            post_encode_value_preamble,
+            post_decode_value_preamble,
+            post_validate_call_preamble,
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/contracts/costs?from_height=100&limit=10".to_string(),
+                http_request_metadata_ip.peer.hostname(),
+                http_request_metadata_ip.peer.port(),
+                http_request_metadata_ip.keep_alive,
             ),
-            HttpRequestType::PostTransaction(
-                http_request_metadata_dns.clone(),
-                make_test_transaction(),
-                None,
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                "/v2/mempool/simulate_block".to_string(),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
             ),
-            HttpRequestType::OptionsPreflight(http_request_metadata_ip.clone(), "/".to_string()),
-        ];
-
-        let mut tx_body = vec![];
-        make_test_transaction()
-            .consensus_serialize(&mut tx_body)
-            .unwrap();
-
-        let mut post_transaction_preamble = HttpRequestPreamble::new(
-            HttpVersion::Http11,
-            "POST".to_string(),
-            "/v2/transactions".to_string(),
-            http_request_metadata_dns.peer.hostname(),
-            http_request_metadata_dns.peer.port(),
-            http_request_metadata_dns.keep_alive,
-        );
-        post_transaction_preamble.set_content_type(HttpContentType::Bytes);
-        post_transaction_preamble.set_content_length(tx_body.len() as u32);
-
-        // all of these should parse
-        let expected_http_preambles = vec![
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "GET".to_string(),
-                "/v2/neighbors".to_string(),
+                "/v2/neighbors/versions".to_string(),
                 http_request_metadata_ip.peer.hostname(),
                 http_request_metadata_ip.peer.port(),
                 http_request_metadata_ip.keep_alive,
@@ -5387,7 +9505,7 @@ mod test {
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "GET".to_string(),
-                format!("/v2/blocks/{}", StacksBlockId([2u8; 32]).to_hex()),
+                "/v2/node_identity".to_string(),
                 http_request_metadata_dns.peer.hostname(),
                 http_request_metadata_dns.peer.port(),
                 http_request_metadata_dns.keep_alive,
@@ -5395,12 +9513,19 @@ mod test {
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "GET".to_string(),
-                format!("/v2/microblocks/{}", StacksBlockId([3u8; 32]).to_hex()),
+                "/v2/node_identity?challenge=00ff".to_string(),
                 http_request_metadata_ip.peer.hostname(),
                 http_request_metadata_ip.peer.port(),
                 http_request_metadata_ip.keep_alive,
             ),
-            post_transaction_preamble,
+            HttpRequestPreamble::new(
+                HttpVersion::Http11,
+                "GET".to_string(),
+                format!("/v2/microblocks/proof/{}", Txid([0x56; 32])),
+                http_request_metadata_dns.peer.hostname(),
+                http_request_metadata_dns.peer.port(),
+                http_request_metadata_dns.keep_alive,
+            ),
             HttpRequestPreamble::new(
                 HttpVersion::Http11,
                 "OPTIONS".to_string(),
@@ -5411,7 +9536,53 @@ mod test {
             ),
         ];
 
-        let expected_http_bodies = vec![vec![], vec![], vec![], tx_body];
+        let expected_http_bodies = vec![
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            tx_body,
+            snapshot_body,
+            peer_filter_body,
+            atlas_contract_body,
+            attachment_pin_body,
+            block_processing_body,
+            event_replay_body,
+            query_job_body,
+            expiring_tx_body,
+            encode_value_body,
+            decode_value_body,
+            validate_call_body,
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+            vec![],
+        ];
 
         for (test, (expected_http_preamble, expected_http_body)) in tests.iter().zip(
             expected_http_preambles
@@ -5484,6 +9655,232 @@ mod test {
             assert!(e.is_err());
             assert!(e.unwrap_err().to_string().find("Content-Type").is_some());
         }
+
+        let bad_page_limits = vec![
+            "GET /v2/admin/mempool/export?limit=0 HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\n\r\n",
+            "GET /v2/admin/mempool/export?limit=abc HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\n\r\n",
+        ];
+        for bad_page_limit in bad_page_limits {
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(bad_page_limit.as_bytes()).unwrap();
+            let e = http.read_payload(&preamble, &bad_page_limit.as_bytes()[offset..]);
+            assert!(e.is_err());
+            assert!(e
+                .unwrap_err()
+                .to_string()
+                .find("Invalid page limit")
+                .is_some());
+        }
+    }
+
+    #[test]
+    fn test_peer_filter_update_request_body() {
+        let parse = |body: &str| {
+            serde_json::from_str::<PeerFilterUpdateRequestBody>(body)
+                .unwrap()
+                .to_update()
+        };
+
+        assert_eq!(
+            parse(r#"{"action":"add","list":"deny","cidr":"10.1.2.3"}"#).unwrap(),
+            (
+                PeerFilterAction::Add,
+                PeerFilterList::Deny,
+                PeerFilterRule::Cidr(PeerAddress::from_ipv4(10, 1, 2, 3), 128)
+            )
+        );
+        assert_eq!(
+            parse(r#"{"action":"remove","list":"allow","cidr":"2001:db8::/32"}"#).unwrap(),
+            (
+                PeerFilterAction::Remove,
+                PeerFilterList::Allow,
+                PeerFilterRule::Cidr(PeerAddress::from_ip(&"2001:db8::".parse().unwrap()), 32)
+            )
+        );
+        assert_eq!(
+            parse(
+                r#"{"action":"add","list":"deny","pubkey_hash":"1111111111111111111111111111111111111111"}"#
+            )
+            .unwrap()
+            .2,
+            PeerFilterRule::PubkeyHash(Hash160([0x11; 20]))
+        );
+
+        let bad_bodies = vec![
+            r#"{"action":"ban","list":"deny","cidr":"10.0.0.0/8"}"#,
+            r#"{"action":"add","list":"block","cidr":"10.0.0.0/8"}"#,
+            r#"{"action":"add","list":"deny"}"#,
+            r#"{"action":"add","list":"deny","cidr":"10.0.0.0/8","pubkey_hash":"1111111111111111111111111111111111111111"}"#,
+            r#"{"action":"add","list":"deny","cidr":"10.0.0.0/33"}"#,
+            r#"{"action":"add","list":"deny","cidr":"10.0.0.0/0"}"#,
+            r#"{"action":"add","list":"deny","cidr":"10.0.0/8"}"#,
+            r#"{"action":"add","list":"deny","pubkey_hash":"1111"}"#,
+        ];
+        for bad_body in bad_bodies {
+            assert!(parse(bad_body).is_err(), "{}", bad_body);
+        }
+
+        // rules are displayed the way they are written
+        for cidr in &[
+            "10.0.0.0/8",
+            "10.1.2.3/32",
+            "2001:db8::/32",
+            "::ffff:0.0.0.0/96",
+        ] {
+            assert_eq!(
+                PeerFilterRule::from_cidr_str(cidr).unwrap().to_string(),
+                cidr.to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_atlas_contract_registration_request_body() {
+        let parse = |body: &str| {
+            serde_json::from_str::<AtlasContractRegistrationRequestBody>(body)
+                .unwrap()
+                .to_registration()
+        };
+
+        let attestations_contract_id =
+            QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.attestations")
+                .unwrap();
+        assert_eq!(
+            parse(r#"{"contract_id":"ST000000000000000000002AMW42H.attestations"}"#).unwrap(),
+            (attestations_contract_id.clone(), None, None)
+        );
+        assert_eq!(
+            parse(
+                r#"{"contract_id":"ST000000000000000000002AMW42H.attestations","attachments_max_size":512,"unresolved_attachment_instances_expire_after":3600}"#
+            )
+            .unwrap(),
+            (attestations_contract_id, Some(512), Some(3600))
+        );
+
+        let bad_bodies = vec![
+            r#"{"contract_id":"ST000000000000000000002AMW42H"}"#,
+            r#"{"contract_id":"attestations"}"#,
+            r#"{"contract_id":"ST000000000000000000002AMW42H.attestations","attachments_max_size":0}"#,
+        ];
+        for bad_body in bad_bodies {
+            assert!(parse(bad_body).is_err(), "{}", bad_body);
+        }
+    }
+
+    #[test]
+    fn test_attachment_pin_request_body() {
+        let parse = |body: &str| {
+            serde_json::from_str::<AttachmentPinRequestBody>(body)
+                .unwrap()
+                .to_update()
+        };
+
+        assert_eq!(
+            parse(r#"{"action":"pin","hash":"1111111111111111111111111111111111111111"}"#).unwrap(),
+            (AttachmentPinAction::Pin, Hash160([0x11; 20]))
+        );
+        assert_eq!(
+            parse(r#"{"action":"unpin","hash":"1111111111111111111111111111111111111111"}"#)
+                .unwrap(),
+            (AttachmentPinAction::Unpin, Hash160([0x11; 20]))
+        );
+
+        let bad_bodies = vec![
+            r#"{"action":"keep","hash":"1111111111111111111111111111111111111111"}"#,
+            r#"{"action":"pin","hash":"11111111"}"#,
+            r#"{"action":"pin","hash":"not hex"}"#,
+        ];
+        for bad_body in bad_bodies {
+            assert!(parse(bad_body).is_err(), "{}", bad_body);
+        }
+    }
+
+    #[test]
+    fn test_block_processing_request_body() {
+        let parse = |body: &str| {
+            serde_json::from_str::<BlockProcessingRequestBody>(body)
+                .unwrap()
+                .to_action()
+        };
+
+        assert_eq!(
+            parse(r#"{"action":"pause"}"#).unwrap(),
+            BlockProcessingAction::Pause
+        );
+        assert_eq!(
+            parse(r#"{"action":"resume"}"#).unwrap(),
+            BlockProcessingAction::Resume
+        );
+        assert!(parse(r#"{"action":"stop"}"#).is_err());
+    }
+
+    #[test]
+    fn test_event_replay_request_body() {
+        let parse = |body: &str| {
+            serde_json::from_str::<EventReplayRequestBody>(body)
+                .unwrap()
+                .validate()
+        };
+
+        assert_eq!(
+            parse(r#"{"endpoint":"localhost:3700","from_height":1}"#).unwrap(),
+            DEFAULT_EVENT_REPLAY_RATE
+        );
+        assert_eq!(
+            parse(
+                r#"{"endpoint":"localhost:3700","from_height":5,"to_height":5,"blocks_per_second":100}"#
+            )
+            .unwrap(),
+            100
+        );
+
+        let bad_bodies = vec![
+            r#"{"endpoint":"localhost:3700","from_height":0}"#,
+            r#"{"endpoint":"localhost:3700","from_height":5,"to_height":4}"#,
+            r#"{"endpoint":"localhost:3700","from_height":1,"blocks_per_second":0}"#,
+            r#"{"endpoint":"localhost:3700","from_height":1,"blocks_per_second":1001}"#,
+        ];
+        for bad_body in bad_bodies {
+            assert!(parse(bad_body).is_err(), "{}", bad_body);
+        }
+    }
+
+    #[test]
+    fn test_query_job_request_body() {
+        let parse = |body: &str| {
+            serde_json::from_str::<QueryJobRequestBody>(body)
+                .unwrap()
+                .to_query()
+        };
+
+        assert_eq!(
+            parse(r#"{"kind":"address-history","address":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R"}"#)
+                .unwrap(),
+            QueryJobKind::AddressHistory(
+                StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap()
+            )
+        );
+        assert_eq!(
+            parse(
+                r#"{"kind":"contract-history","contract_id":"ST000000000000000000002AMW42H.attestations"}"#
+            )
+            .unwrap(),
+            QueryJobKind::ContractHistory(
+                QualifiedContractIdentifier::parse("ST000000000000000000002AMW42H.attestations")
+                    .unwrap()
+            )
+        );
+
+        let bad_bodies = vec![
+            r#"{"kind":"map-scan","address":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R"}"#,
+            r#"{"kind":"address-history"}"#,
+            r#"{"kind":"address-history","address":"not-an-address"}"#,
+            r#"{"kind":"contract-history","address":"ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R"}"#,
+            r#"{"kind":"contract-history","contract_id":"ST000000000000000000002AMW42H"}"#,
+        ];
+        for bad_body in bad_bodies {
+            assert!(parse(bad_body).is_err(), "{}", bad_body);
+        }
     }
 
     #[test]
@@ -5523,6 +9920,415 @@ mod test {
             outbound: vec![],
         };
 
+        let test_mempool_drops = vec![RPCMemPoolDropData {
+            txid: Txid([0x55; 32]).to_hex(),
+            reason: "ReplaceByFee".to_string(),
+            drop_time: 1617735436,
+            tx_fee: 180,
+            block_height: 4512,
+            origin_address: "ST2MVNFYF6H9DCMAV3HVNHTJVVE3CFWT1JYMH1EZB".to_string(),
+            origin_nonce: 3,
+            sponsor_address: "ST2MVNFYF6H9DCMAV3HVNHTJVVE3CFWT1JYMH1EZB".to_string(),
+            sponsor_nonce: 3,
+            replaced_by: Some(Txid([0x66; 32]).to_hex()),
+        }];
+
+        let test_mempool_fees = RPCMemPoolFeeData {
+            stacks_tip_height: 4512,
+            num_txs: 3,
+            total_bytes: 540,
+            fee_rates: Some(RPCFeeRatePercentiles {
+                p10: 1.0,
+                p25: 1.0,
+                p50: 1.5,
+                p75: 2.25,
+                p90: 2.25,
+            }),
+            depth_in_blocks: 1,
+            recent_inclusion_thresholds: vec![
+                RPCFeeRateThreshold {
+                    stacks_block_height: 4512,
+                    index_block_hash: StacksBlockId([0x77; 32]),
+                    num_txs: 2,
+                    min_fee_rate: Some(0.5),
+                },
+                RPCFeeRateThreshold {
+                    stacks_block_height: 4511,
+                    index_block_hash: StacksBlockId([0x78; 32]),
+                    num_txs: 0,
+                    min_fee_rate: None,
+                },
+            ],
+        };
+
+        let test_miner_rewards = MinerRewardsResponse {
+            stacks_tip_height: 4512,
+            maturity: 100,
+            matured: "0x000000000000000000000000000003e8".to_string(),
+            pending: "0x000000000000000000000000000001f4".to_string(),
+            rewards: vec![MinerRewardEntry {
+                stacks_block_height: 4400,
+                index_block_hash: StacksBlockId([0x77; 32]),
+                miner: true,
+                coinbase: "0x000000000000000000000000000003e8".to_string(),
+                tx_fees_anchored: "0x00000000000000000000000000000000".to_string(),
+                tx_fees_streamed_confirmed: "0x00000000000000000000000000000000".to_string(),
+                maturity_height: 4501,
+                matured: true,
+            }],
+            reward_schedule: vec![CoinbaseScheduleEntry {
+                start_burn_height: 666050,
+                end_burn_height: None,
+                coinbase: "0x000000000000000000000000000003e8".to_string(),
+            }],
+        };
+
+        let test_event_observer_status = vec![RPCEventObserverStatus {
+            endpoint: "localhost:3700".to_string(),
+            pending_events: 2,
+            oldest_pending_at: Some(1600000000),
+            next_attempt_at: Some(1600000016),
+            last_error: Some("Connection refused".to_string()),
+            delivered: 120,
+            failed_attempts: 5,
+            last_delivered_at: Some(1599999990),
+        }];
+
+        let test_event_replays = vec![RPCEventReplay {
+            endpoint: "localhost:3700".to_string(),
+            from_height: 1,
+            to_height: 1000,
+            next_height: 501,
+            blocks_per_second: 10,
+            started_at: 1600000000,
+            finished_at: None,
+            error: None,
+        }];
+
+        let test_peer_filters = RPCPeerFilters {
+            allow: RPCPeerFilterList {
+                cidrs: vec!["192.168.0.0/16".to_string()],
+                pubkey_hashes: vec![],
+            },
+            deny: RPCPeerFilterList {
+                cidrs: vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()],
+                pubkey_hashes: vec!["1111111111111111111111111111111111111111".to_string()],
+            },
+        };
+
+        let test_atlas_contracts = RPCAtlasContracts {
+            contracts: vec![RPCAtlasContract {
+                contract_id: "ST000000000000000000002AMW42H.attestations".to_string(),
+                attachments_max_size: 512,
+                unresolved_attachment_instances_expire_after: 3600,
+                runtime: true,
+            }],
+        };
+
+        let test_query_job = RPCQueryJob {
+            id: 42,
+            kind: "address-history".to_string(),
+            target: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+            status: QueryJobStatus::Running,
+            error: None,
+            tip: StacksBlockId([0x29; 32]),
+            tip_height: 1000,
+            blocks_scanned: 250,
+            num_results: 2,
+            truncated: false,
+            created_at: 1234567890,
+            finished_at: None,
+            results: RPCPage {
+                items: vec![RPCQueryJobResult {
+                    txid: Txid([0x2a; 32]).to_hex(),
+                    index_block_hash: StacksBlockId([0x2b; 32]),
+                    block_height: 998,
+                    microblock_hash: Some(BlockHeaderHash([0x2c; 32])),
+                }],
+                next_cursor: Some("1".to_string()),
+            },
+        };
+
+        let test_attachment_pins = RPCAttachmentPins {
+            hashes: vec!["1111111111111111111111111111111111111111".to_string()],
+            stored_bytes: 4096,
+        };
+
+        let test_block_processing = RPCBlockProcessingStatus {
+            pause_requested: true,
+            paused: false,
+            burn_block_height: 700,
+            stacks_tip_height: 100,
+            stacks_tip: BlockHeaderHash([0x33; 32]),
+            stacks_tip_consensus_hash: ConsensusHash([0x44; 20]),
+        };
+
+        let test_node_features = RPCNodeFeatures {
+            features: vec![
+                RPCNodeFeature {
+                    name: "atlas".to_string(),
+                    source: "config".to_string(),
+                    available: true,
+                    enabled: true,
+                    service_bit: Some(0x0010),
+                },
+                RPCNodeFeature {
+                    name: "prometheus".to_string(),
+                    source: "build".to_string(),
+                    available: false,
+                    enabled: false,
+                    service_bit: None,
+                },
+            ],
+        };
+
+        let test_block_fetch = RPCBlockFetchData {
+            index_block_hash: StacksBlockId([0x26; 32]),
+            microblocks: false,
+            status: BlockFetchStatus::InFlight,
+            sortition_height: Some(123),
+            data_urls: vec!["http://127.0.0.1:20443".to_string()],
+            requested_at: 1600000000,
+            updated_at: 1600000001,
+        };
+
+        let test_neighbor_walk_info = RPCNeighborWalkInfo {
+            walks: 42,
+            resets: 3,
+            last_walk_duration_ms: Some(2500),
+            last_walk_time: Some(1600000000),
+            frontier_size: 120,
+            churn_added: 80,
+            churn_replaced: 4,
+            unreachable_ratio: 0.125,
+            num_inbound: 5,
+            num_outbound: 8,
+            frontier_quality: RPCFrontierQuality {
+                score: 86,
+                fresh_ratio: 0.9,
+                recent_contact_ratio: 0.75,
+                network_diversity: 0.5,
+                reachable_ratio: 0.875,
+            },
+            assessment: "healthy".to_string(),
+            handshake_concurrency: RPCHandshakeConcurrency {
+                limit: 16,
+                min_limit: 4,
+                max_limit: 32,
+                last_pass_ms: 1500,
+                backoffs: 1,
+            },
+        };
+
+        let test_node_status = RPCNodeStatus {
+            burnchain: RPCBurnchainStatus {
+                burn_block_height: 700000,
+                stable_burn_block_height: 699993,
+                burnchain_tip_height: Some(700002),
+                lag: Some(2),
+            },
+            stacks: RPCStacksStatus {
+                tip_height: 12345,
+                tip: StacksBlockId([0x27; 32]),
+                tip_burn_block_height: 699999,
+                lag: 1,
+            },
+            peers: RPCPeerCounts {
+                inbound: 5,
+                outbound: 8,
+            },
+            mempool: RPCMemPoolStatus { num_txs: 200 },
+            atlas: RPCAtlasStatus {
+                unresolved_attachment_instances: 3,
+                uninstantiated_attachments: 1,
+            },
+            mining: Some(RPCMiningStatus {
+                miner: true,
+                initial_block_download: false,
+            }),
+            last_errors: vec![RPCSubsystemError {
+                subsystem: "p2p".to_string(),
+                message: "DBError(Locked)".to_string(),
+                at: Some(1600000000),
+            }],
+        };
+
+        let test_node_identity = RPCNodeIdentity::from_local_peer(
+            &LocalPeer::new(
+                0x80000000,
+                0x80000000,
+                PeerAddress::from_ipv4(127, 0, 0, 1),
+                20444,
+                None,
+                12345,
+                UrlString::try_from("http://127.0.0.1:20443").unwrap(),
+            ),
+            Some(&[0x00, 0xff]),
+        )
+        .unwrap();
+
+        let test_microblock_proof = RPCMicroblockProof {
+            txid: Txid([0x56; 32]).to_hex(),
+            index_block_hash: StacksBlockId([0x57; 32]),
+            consensus_hash: ConsensusHash([0x58; 20]),
+            block_height: 123,
+            canonical: true,
+            block_header: "00".to_string(),
+            parent_consensus_hash: ConsensusHash([0x59; 20]),
+            parent_block_header: "01".to_string(),
+            microblock_headers: vec!["02".to_string(), "03".to_string()],
+            microblock_sequence: 1,
+            microblock_txids: vec![Txid([0x56; 32]).to_hex()],
+        };
+
+        let test_clarity_value =
+            ClarityValueResponse::from_value(Value::some(Value::Int(-1)).unwrap()).unwrap();
+
+        let test_validate_contract_call = ValidateContractCallResponse {
+            valid: false,
+            contract_id: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world".to_string(),
+            function_name: "add-unit".to_string(),
+            arguments: vec![],
+            errors: vec![
+                ContractCallArgumentError {
+                    index: Some(0),
+                    name: Some("amount".to_string()),
+                    expected_type: Some("uint".to_string()),
+                    message: "Expected uint, got 1".to_string(),
+                },
+                ContractCallArgumentError {
+                    index: None,
+                    name: None,
+                    expected_type: None,
+                    message: "Expected 1 arguments, got 2".to_string(),
+                },
+            ],
+        };
+
+        let test_contract_costs = RPCContractCostsData {
+            enabled: true,
+            from_height: 100,
+            to_height: 199,
+            contracts: vec![RPCContractCostEntry {
+                contract_id: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world".to_string(),
+                num_txs: 12,
+                cost: ExecutionCost {
+                    write_length: 100,
+                    write_count: 2,
+                    read_length: 300,
+                    read_count: 4,
+                    runtime: 5000,
+                },
+                budget_pct: 1,
+            }],
+        };
+
+        let test_block_simulation = RPCBlockSimulationData {
+            stacks_tip_height: 4512,
+            stacks_tip: StacksBlockId([0x77; 32]),
+            num_txs: 1,
+            total_bytes: 360,
+            total_fees: 180,
+            cost: ExecutionCost::zero(),
+            budget_pct: 0,
+            transactions: vec![RPCSimulatedTransaction {
+                txid: Txid([0x55; 32]).to_hex(),
+                origin_address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+                origin_nonce: 7,
+                tx_fee: 180,
+            }],
+        };
+
+        let test_peer_versions = RPCPeerVersionsInfo {
+            peer_version: 0x18000000,
+            connected: vec![RPCPeerVersionCount {
+                peer_version: 0x18000001,
+                count: 3,
+            }],
+            observed: vec![
+                RPCPeerVersionCount {
+                    peer_version: 0x18000000,
+                    count: 2,
+                },
+                RPCPeerVersionCount {
+                    peer_version: 0x18000001,
+                    count: 6,
+                },
+            ],
+            newer_pct: 75,
+            newest_peer_version: Some(0x18000001),
+        };
+
+        let test_microblock_forks = RPCMicroblockForksInfo {
+            index_block_hash: StacksBlockId([0x22; 32]),
+            forked: true,
+            streams: vec![
+                RPCMicroblockStream {
+                    tip: BlockHeaderHash([0x33; 32]),
+                    min_seq: 0,
+                    max_seq: 4,
+                    num_microblocks: 5,
+                    fork_seq: Some(3),
+                    signer: Some(Hash160([0x44; 20]).to_hex()),
+                    first_seen: Some(1600000000),
+                    tip_first_seen: Some(1600000040),
+                    num_confirmed: 0,
+                    orphaned: false,
+                },
+                RPCMicroblockStream {
+                    tip: BlockHeaderHash([0x55; 32]),
+                    min_seq: 0,
+                    max_seq: 3,
+                    num_microblocks: 4,
+                    fork_seq: Some(3),
+                    signer: None,
+                    first_seen: Some(1600000000),
+                    tip_first_seen: None,
+                    num_confirmed: 4,
+                    orphaned: false,
+                },
+            ],
+        };
+
+        let test_snapshot_txs = RPCPage {
+            items: vec![make_test_transaction()],
+            next_cursor: Some("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R:5".to_string()),
+        };
+        let test_snapshot_json = serde_json::to_string(&RPCPage {
+            items: encode_mempool_snapshot(&test_snapshot_txs.items),
+            next_cursor: test_snapshot_txs.next_cursor.clone(),
+        })
+        .unwrap();
+
+        let test_mempool_import = RPCMemPoolImportData {
+            accepted: 1,
+            already_present: 2,
+            rejected: vec![json!({
+                "error": "transaction rejected",
+                "reason": "FeeTooLow",
+                "reason_data": { "actual": 1, "expected": 180 },
+                "txid": Txid([0x77; 32]).to_hex()
+            })],
+        };
+
+        let test_state_hash_info = RPCStateHashData {
+            stacks_block_height: 5,
+            index_block_hash: StacksBlockId([0x11; 32]),
+            consensus_hash: ConsensusHash([0x22; 20]),
+            state_index_root: TrieHash([0x33; 32]),
+            total_burn: 1000,
+            total_work: 5,
+            sortition_id: SortitionId([0x44; 32]),
+            state_hash: RPCStateHashData::make_state_hash(
+                &TrieHash([0x33; 32]),
+                &StacksWorkScore {
+                    burn: 1000,
+                    work: 5,
+                },
+                &SortitionId([0x44; 32]),
+            ),
+        };
+
         let privk = StacksPrivateKey::from_hex(
             "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
         )
@@ -5541,58 +10347,467 @@ mod test {
             .consensus_serialize(&mut test_microblock_info_bytes)
             .unwrap();
 
+        let test_decoded_block =
+            RPCDecodedBlock::from_block(&StacksBlockId([0x23; 32]), &test_block_info);
+        let test_decoded_microblocks: Vec<RPCDecodedMicroblock> = test_microblock_info
+            .iter()
+            .map(RPCDecodedMicroblock::from_microblock)
+            .collect();
+
+        let test_address_mempool = RPCPage {
+            items: vec![RPCPendingTransaction {
+                txid: Txid([0x55; 32]).to_hex(),
+                origin_address: "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+                origin_nonce: 7,
+                sponsor_address: "ST2MVNFYF6H9DCMAV3HVNHTJVVE3CFWT1JYMH1EZB".to_string(),
+                sponsor_nonce: 3,
+                tx_fee: 180,
+                tx_len: 180,
+                accept_time: 1617735436,
+                block_height: 4512,
+                conflicts: vec![Txid([0x56; 32]).to_hex()],
+            }],
+            next_cursor: Some(format!("7:{}", Txid([0x55; 32]))),
+        };
+
+        let test_unconfirmed_tx = UnconfirmedTransactionResponse {
+            tx: to_hex(&make_test_transaction().serialize_to_vec()),
+            status: UnconfirmedTransactionStatus::Confirmed,
+            forks: vec![
+                RPCTransactionFork {
+                    index_block_hash: StacksBlockId([0x31; 32]),
+                    consensus_hash: ConsensusHash([0x32; 20]),
+                    block_hash: BlockHeaderHash([0x33; 32]),
+                    block_height: 10,
+                    canonical: false,
+                },
+                RPCTransactionFork {
+                    index_block_hash: StacksBlockId([0x34; 32]),
+                    consensus_hash: ConsensusHash([0x35; 20]),
+                    block_hash: BlockHeaderHash([0x36; 32]),
+                    block_height: 11,
+                    canonical: true,
+                },
+            ],
+        };
+
         let tests = vec![
             // length is known
             (
-                HttpResponseType::Neighbors(
+                HttpResponseType::Neighbors(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_neighbors_info).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_neighbors_info.clone(),
+                ),
+                "/v2/neighbors".to_string(),
+            ),
+            (
+                HttpResponseType::Block(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(test_block_info_bytes.len() as u32),
+                        true,
+                    ),
+                    test_block_info.clone(),
+                ),
+                format!("/v2/blocks/{}", test_block_info.block_hash().to_hex()),
+            ),
+            (
+                HttpResponseType::Microblocks(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(test_microblock_info_bytes.len() as u32),
+                        true,
+                    ),
+                    test_microblock_info.clone(),
+                ),
+                format!(
+                    "/v2/microblocks/{}",
+                    test_microblock_info[0].block_hash().to_hex()
+                ),
+            ),
+            (
+                HttpResponseType::TransactionID(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some((Txid([0x1; 32]).to_hex().len() + 2) as u32),
+                        true,
+                    ),
+                    Txid([0x1; 32]),
+                ),
+                "/v2/transactions".to_string(),
+            ),
+            (
+                HttpResponseType::StateHash(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_state_hash_info).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_state_hash_info.clone(),
+                ),
+                "/v2/state_hash/5".to_string(),
+            ),
+            (
+                HttpResponseType::MemPoolDrops(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_mempool_drops).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_mempool_drops.clone(),
+                ),
+                format!("/v2/mempool/dropped/{}", Txid([0x55; 32])),
+            ),
+            (
+                HttpResponseType::MemPoolFees(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_mempool_fees).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_mempool_fees.clone(),
+                ),
+                "/v2/fees/mempool".to_string(),
+            ),
+            (
+                HttpResponseType::GetMinerRewards(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_miner_rewards).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_miner_rewards.clone(),
+                ),
+                "/v2/miner_rewards/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R".to_string(),
+            ),
+            (
+                HttpResponseType::MemPoolSnapshot(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(test_snapshot_json.len() as u32),
+                        true,
+                    ),
+                    test_snapshot_txs.clone(),
+                ),
+                "/v2/admin/mempool/export".to_string(),
+            ),
+            (
+                HttpResponseType::MemPoolImport(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_mempool_import).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_mempool_import.clone(),
+                ),
+                "/v2/admin/mempool/import".to_string(),
+            ),
+            (
+                HttpResponseType::EventObserverStatus(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(
+                            serde_json::to_string(&test_event_observer_status)
+                                .unwrap()
+                                .len() as u32,
+                        ),
+                        true,
+                    ),
+                    test_event_observer_status.clone(),
+                ),
+                "/v2/admin/events/status".to_string(),
+            ),
+            (
+                HttpResponseType::EventReplays(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_event_replays).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_event_replays.clone(),
+                ),
+                "/v2/admin/events/replay".to_string(),
+            ),
+            (
+                HttpResponseType::PeerFilters(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_peer_filters).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_peer_filters.clone(),
+                ),
+                "/v2/admin/peers/filters".to_string(),
+            ),
+            (
+                HttpResponseType::AtlasContracts(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_atlas_contracts).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_atlas_contracts.clone(),
+                ),
+                "/v2/admin/atlas/contracts".to_string(),
+            ),
+            (
+                HttpResponseType::AttachmentPins(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_attachment_pins).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_attachment_pins.clone(),
+                ),
+                "/v2/admin/atlas/pins".to_string(),
+            ),
+            (
+                HttpResponseType::BlockProcessing(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_block_processing).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_block_processing.clone(),
+                ),
+                "/v2/admin/blocks/processing".to_string(),
+            ),
+            (
+                HttpResponseType::QueryJob(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_query_job).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_query_job.clone(),
+                ),
+                "/v2/jobs/42".to_string(),
+            ),
+            (
+                HttpResponseType::NodeFeatures(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_node_features).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_node_features.clone(),
+                ),
+                "/v2/features".to_string(),
+            ),
+            (
+                HttpResponseType::BlockFetch(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_block_fetch).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_block_fetch.clone(),
+                ),
+                format!("/v2/blocks/fetch/{}", StacksBlockId([0x26; 32])),
+            ),
+            (
+                HttpResponseType::NeighborWalkInfo(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(
+                            serde_json::to_string(&test_neighbor_walk_info)
+                                .unwrap()
+                                .len() as u32,
+                        ),
+                        true,
+                    ),
+                    test_neighbor_walk_info.clone(),
+                ),
+                "/v2/neighbors/walk".to_string(),
+            ),
+            (
+                HttpResponseType::NodeStatus(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_node_status).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_node_status.clone(),
+                ),
+                "/v2/status".to_string(),
+            ),
+            (
+                HttpResponseType::NodeIdentity(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_node_identity).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_node_identity.clone(),
+                ),
+                "/v2/node_identity?challenge=00ff".to_string(),
+            ),
+            (
+                HttpResponseType::MicroblockProof(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_microblock_proof).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_microblock_proof.clone(),
+                ),
+                format!("/v2/microblocks/proof/{}", Txid([0x56; 32])),
+            ),
+            (
+                HttpResponseType::ClarityValue(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_clarity_value).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_clarity_value.clone(),
+                ),
+                "/v2/clarity/value/encode".to_string(),
+            ),
+            (
+                HttpResponseType::ValidateContractCall(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(
+                            serde_json::to_string(&test_validate_contract_call)
+                                .unwrap()
+                                .len() as u32,
+                        ),
+                        true,
+                    ),
+                    test_validate_contract_call.clone(),
+                ),
+                "/v2/contracts/validate-call/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/hello-world/add-unit"
+                    .to_string(),
+            ),
+            (
+                HttpResponseType::BlockSimulation(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_block_simulation).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_block_simulation.clone(),
+                ),
+                "/v2/mempool/simulate_block".to_string(),
+            ),
+            (
+                HttpResponseType::ContractCosts(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_contract_costs).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_contract_costs.clone(),
+                ),
+                "/v2/contracts/costs".to_string(),
+            ),
+            (
+                HttpResponseType::PeerVersions(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_peer_versions).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_peer_versions.clone(),
+                ),
+                "/v2/neighbors/versions".to_string(),
+            ),
+            (
+                HttpResponseType::MicroblockForks(
+                    HttpResponseMetadata::new(
+                        HttpVersion::Http11,
+                        123,
+                        Some(serde_json::to_string(&test_microblock_forks).unwrap().len() as u32),
+                        true,
+                    ),
+                    test_microblock_forks.clone(),
+                ),
+                "/v2/microblocks/forks".to_string(),
+            ),
+            (
+                HttpResponseType::DecodedBlock(
                     HttpResponseMetadata::new(
                         HttpVersion::Http11,
                         123,
-                        Some(serde_json::to_string(&test_neighbors_info).unwrap().len() as u32),
+                        Some(serde_json::to_string(&test_decoded_block).unwrap().len() as u32),
                         true,
                     ),
-                    test_neighbors_info.clone(),
+                    test_decoded_block.clone(),
                 ),
-                "/v2/neighbors".to_string(),
+                format!("/v2/blocks/{}", StacksBlockId([0x23; 32]).to_hex()),
             ),
             (
-                HttpResponseType::Block(
+                HttpResponseType::DecodedMicroblocks(
                     HttpResponseMetadata::new(
                         HttpVersion::Http11,
                         123,
-                        Some(test_block_info_bytes.len() as u32),
+                        Some(
+                            serde_json::to_string(&test_decoded_microblocks)
+                                .unwrap()
+                                .len() as u32,
+                        ),
                         true,
                     ),
-                    test_block_info.clone(),
+                    test_decoded_microblocks.clone(),
                 ),
-                format!("/v2/blocks/{}", test_block_info.block_hash().to_hex()),
+                format!("/v2/microblocks/{}", StacksBlockId([0x24; 32]).to_hex()),
             ),
             (
-                HttpResponseType::Microblocks(
+                HttpResponseType::AddressMempool(
                     HttpResponseMetadata::new(
                         HttpVersion::Http11,
                         123,
-                        Some(test_microblock_info_bytes.len() as u32),
+                        Some(serde_json::to_string(&test_address_mempool).unwrap().len() as u32),
                         true,
                     ),
-                    test_microblock_info.clone(),
-                ),
-                format!(
-                    "/v2/microblocks/{}",
-                    test_microblock_info[0].block_hash().to_hex()
+                    test_address_mempool.clone(),
                 ),
+                "/v2/addresses/ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R/mempool".to_string(),
             ),
             (
-                HttpResponseType::TransactionID(
+                HttpResponseType::UnconfirmedTransaction(
                     HttpResponseMetadata::new(
                         HttpVersion::Http11,
                         123,
-                        Some((Txid([0x1; 32]).to_hex().len() + 2) as u32),
+                        Some(serde_json::to_string(&test_unconfirmed_tx).unwrap().len() as u32),
                         true,
                     ),
-                    Txid([0x1; 32]),
+                    test_unconfirmed_tx.clone(),
                 ),
-                "/v2/transactions".to_string(),
+                format!("/v2/transactions/unconfirmed/{}", Txid([0x55; 32])),
             ),
             // length is unknown
             (
@@ -5778,6 +10993,254 @@ mod test {
                 true,
                 123,
             ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_state_hash_info).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_mempool_drops).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_mempool_fees).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_miner_rewards).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(test_snapshot_json.len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_mempool_import).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(
+                    serde_json::to_string(&test_event_observer_status)
+                        .unwrap()
+                        .len() as u32,
+                ),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_event_replays).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_peer_filters).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_atlas_contracts).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_attachment_pins).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_block_processing).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_query_job).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_node_features).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_block_fetch).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(
+                    serde_json::to_string(&test_neighbor_walk_info)
+                        .unwrap()
+                        .len() as u32,
+                ),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_node_status).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_node_identity).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_microblock_proof).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_clarity_value).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(
+                    serde_json::to_string(&test_validate_contract_call)
+                        .unwrap()
+                        .len() as u32,
+                ),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_block_simulation).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_contract_costs).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_peer_versions).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_microblock_forks).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_decoded_block).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(
+                    serde_json::to_string(&test_decoded_microblocks)
+                        .unwrap()
+                        .len() as u32,
+                ),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_address_mempool).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
+            HttpResponsePreamble::new(
+                200,
+                "OK".to_string(),
+                Some(serde_json::to_string(&test_unconfirmed_tx).unwrap().len() as u32),
+                HttpContentType::JSON,
+                true,
+                123,
+            ),
             // length is unknown
             HttpResponsePreamble::new(
                 200,
@@ -5819,62 +11282,22 @@ mod test {
             HttpResponsePreamble::new_error(404, 123, None),
             HttpResponsePreamble::new_error(500, 123, None),
             HttpResponsePreamble::new_error(503, 123, None),
-            // generic error
-            HttpResponsePreamble::new_error(502, 123, None),
-            // errors with messages
-            HttpResponsePreamble::new_error(400, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(401, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(402, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(403, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(404, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(500, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(503, 123, Some("foo".to_string())),
-            HttpResponsePreamble::new_error(502, 123, Some("foo".to_string())),
-        ];
-
-        let expected_http_bodies = vec![
-            // with content-length
-            serde_json::to_string(&test_neighbors_info)
-                .unwrap()
-                .as_bytes()
-                .to_vec(),
-            test_block_info_bytes.clone(),
-            test_microblock_info_bytes.clone(),
-            Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
-            // with transfer-encoding: chunked
-            serde_json::to_string(&test_neighbors_info)
-                .unwrap()
-                .as_bytes()
-                .to_vec(),
-            test_block_info_bytes,
-            test_microblock_info_bytes,
-            Txid([0x1; 32]).to_hex().as_bytes().to_vec(),
-            // errors
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            vec![],
+            // generic error
+            HttpResponsePreamble::new_error(502, 123, None),
             // errors with messages
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
-            "foo".as_bytes().to_vec(),
+            HttpResponsePreamble::new_error(400, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(401, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(402, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(403, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(404, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(500, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(503, 123, Some("foo".to_string())),
+            HttpResponsePreamble::new_error(502, 123, Some("foo".to_string())),
         ];
 
-        for ((test, request_path), (expected_http_preamble, _expected_http_body)) in
-            tests.iter().zip(
-                expected_http_preambles
-                    .iter()
-                    .zip(expected_http_bodies.iter()),
-            )
+        assert_eq!(tests.len(), expected_http_preambles.len());
+        for ((test, request_path), expected_http_preamble) in
+            tests.iter().zip(expected_http_preambles.iter())
         {
             let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
             let mut bytes = vec![];
@@ -5934,6 +11357,249 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_unconfirmed_transaction_response_without_forks() {
+        // nodes that predate fork tracking do not report `forks`
+        let tx_hex = to_hex(&make_test_transaction().serialize_to_vec());
+        let json = format!(r#"{{"tx":"{}","status":"Mempool"}}"#, &tx_hex);
+        let response: UnconfirmedTransactionResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(response.tx, tx_hex);
+        assert_eq!(response.status, UnconfirmedTransactionStatus::Mempool);
+        assert!(response.forks.is_empty());
+    }
+
+    #[test]
+    fn test_http_attachment_response_verification() {
+        let attachment = Attachment::new(vec![0xab; 32]);
+        let request_path = format!("/v2/attachments/{}", attachment.hash());
+        let other_path = format!("/v2/attachments/{}", Hash160([0x11; 20]));
+
+        // chunk-encoded and of known length (64 hex digits in quotes)
+        for content_length in [None, Some(66)].iter() {
+            let response = HttpResponseType::GetAttachment(
+                HttpResponseMetadata::new(HttpVersion::Http11, 123, *content_length, true),
+                GetAttachmentResponse {
+                    attachment: attachment.clone(),
+                },
+            );
+
+            // only the attachment we asked for, within the size limit, gets through
+            for (path, max_size, accepted) in [
+                (&request_path, 32, true),
+                (&other_path, 32, false),
+                (&request_path, 31, false),
+            ]
+            .iter()
+            {
+                let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+                http.maximum_attachment_size = *max_size;
+                http.begin_request(HttpVersion::Http11, path.to_string());
+                let mut bytes = vec![];
+                http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+                    .unwrap();
+
+                let result = http.read_preamble(&bytes).and_then(|(preamble, offset)| {
+                    if content_length.is_none() {
+                        http.stream_payload(&preamble, &mut &bytes[offset..])
+                            .map(|(message_opt, _)| message_opt.unwrap().0)
+                    } else {
+                        http.read_payload(&preamble, &bytes[offset..])
+                            .map(|(message, _)| message)
+                    }
+                });
+                if *accepted {
+                    assert_eq!(
+                        result.unwrap(),
+                        StacksHttpMessage::Response(response.clone())
+                    );
+                } else {
+                    assert!(result.is_err());
+                }
+                assert_eq!(http.num_pending(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_http_api_version_paths() {
+        assert_eq!(
+            HttpApiVersion::from_path("/v2/info"),
+            Some(HttpApiVersion::V2)
+        );
+        assert_eq!(
+            HttpApiVersion::from_path("/v3/neighbors?foo=bar"),
+            Some(HttpApiVersion::V3)
+        );
+        assert_eq!(HttpApiVersion::from_path("/v3"), Some(HttpApiVersion::V3));
+        assert_eq!(HttpApiVersion::from_path("/v30/info"), None);
+        assert_eq!(HttpApiVersion::from_path("/"), None);
+
+        assert_eq!(
+            HttpApiVersion::V3.rewrite_path("/v2/blocks/1234"),
+            "/v3/blocks/1234"
+        );
+        assert_eq!(HttpApiVersion::V2.rewrite_path("/v3/info"), "/v2/info");
+        assert_eq!(
+            HttpApiVersion::V3.rewrite_path("/favicon.ico"),
+            "/favicon.ico"
+        );
+    }
+
+    #[test]
+    fn test_http_request_api_version() {
+        let mut md = HttpRequestMetadata::from_host(PeerHost::from_host_port(
+            "localhost".to_string(),
+            20443,
+        ));
+        md.api_version = HttpApiVersion::V3;
+
+        // v3 requests are served by the v2 handlers, and remember their version
+        for req in [
+            HttpRequestType::GetInfo(md.clone()),
+            HttpRequestType::GetNeighbors(md.clone()),
+            HttpRequestType::GetBlock(md.clone(), StacksBlockId([0x22; 32])),
+        ]
+        .iter()
+        {
+            assert!(req.request_path().starts_with("/v3/"));
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(req.clone()))
+                .unwrap();
+
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            let (message, _) = http.read_payload(&preamble, &bytes[offset..]).unwrap();
+            match message {
+                StacksHttpMessage::Request(parsed) => {
+                    assert_eq!(parsed.metadata().api_version, HttpApiVersion::V3);
+                    assert_eq!(&parsed, req);
+                }
+                x => panic!("Expected a request, got {:?}", &x),
+            }
+        }
+
+        // unknown versions are not routed
+        let preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "GET".to_string(),
+            "/v4/info".to_string(),
+            "localhost".to_string(),
+            20443,
+            true,
+        );
+        let mut bytes = vec![];
+        preamble.consensus_serialize(&mut bytes).unwrap();
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        match http.read_payload(&preamble, &bytes[offset..]).unwrap() {
+            (
+                StacksHttpMessage::Request(HttpRequestType::ClientError(
+                    _,
+                    ClientError::NotFound(path),
+                )),
+                _,
+            ) => assert_eq!(path, "/v4/info"),
+            x => panic!("Expected NotFound, got {:?}", &x),
+        }
+    }
+
+    #[test]
+    fn test_http_api_deprecation_headers() {
+        let md = HttpRequestMetadata::from_host(PeerHost::from_host_port(
+            "localhost".to_string(),
+            20443,
+        ));
+        let mut md_v3 = md.clone();
+        md_v3.api_version = HttpApiVersion::V3;
+
+        // no route is deprecated yet
+        assert_eq!(HttpRequestType::GetInfo(md.clone()).api_deprecation(), None);
+        assert_eq!(
+            HttpRequestType::GetInfo(md_v3.clone()).api_deprecation(),
+            None
+        );
+        assert_eq!(
+            HttpRequestType::GetNeighbors(md.clone()).api_deprecation(),
+            None
+        );
+
+        let peer_info = RPCPeerInfoData {
+            peer_version: 0x18000000,
+            pox_consensus: ConsensusHash([0x11; 20]),
+            burn_block_height: 700,
+            stable_pox_consensus: ConsensusHash([0x22; 20]),
+            stable_burn_block_height: 693,
+            server_version: "test".to_string(),
+            network_id: 1,
+            parent_network_id: 2,
+            stacks_tip_height: 100,
+            stacks_tip: BlockHeaderHash([0x33; 32]),
+            stacks_tip_consensus_hash: ConsensusHash([0x44; 20]).to_hex(),
+            genesis_chainstate_hash: Sha256Sum([0x55; 32]),
+            unanchored_tip: StacksBlockId([0x66; 32]),
+            unanchored_seq: 1,
+            exit_at_block_height: None,
+        };
+
+        let mut response_md = HttpResponseMetadata::from(&HttpRequestType::GetInfo(md.clone()));
+        response_md.deprecation = Some(HttpApiDeprecation {
+            successor: "/v3/info".to_string(),
+            sunset: Some(1861920000),
+        });
+
+        let tests = vec![
+            (
+                "/v2/info",
+                HttpResponseType::PeerInfo(response_md, peer_info.clone()),
+            ),
+            (
+                "/v3/info",
+                HttpResponseType::PeerInfo(
+                    HttpResponseMetadata::from(&HttpRequestType::GetInfo(md_v3.clone())),
+                    peer_info.clone(),
+                ),
+            ),
+        ];
+
+        for (path, response) in tests.iter() {
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            http.begin_request(HttpVersion::Http11, path.to_string());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+                .unwrap();
+
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            match preamble {
+                StacksHttpPreamble::Response(ref resp) => {
+                    if response.metadata().deprecation.is_some() {
+                        assert_eq!(resp.headers.get("deprecation").unwrap(), "true");
+                        assert_eq!(
+                            resp.headers.get("link").unwrap(),
+                            "</v3/info>; rel=\"successor-version\""
+                        );
+                        assert_eq!(
+                            resp.headers.get("sunset").unwrap(),
+                            "Mon, 01 Jan 2029 00:00:00 GMT"
+                        );
+                    } else {
+                        assert!(resp.headers.get("deprecation").is_none());
+                        assert!(resp.headers.get("sunset").is_none());
+                    }
+                }
+                _ => panic!("parsed a request"),
+            }
+
+            let (message_opt, _) = http
+                .stream_payload(&preamble, &mut &bytes[offset..])
+                .unwrap();
+            assert_eq!(
+                message_opt.unwrap().0,
+                StacksHttpMessage::Response(response.clone())
+            );
+        }
+    }
+
     #[test]
     fn test_http_response_type_codec_err() {
         let request_paths = vec![
@@ -5944,7 +11610,7 @@ mod test {
             "/v2/neighbors",
         ];
         let bad_request_payloads = vec![
-            "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nContent-length: 2\r\n\r\nab",
+            "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: text/plain\r\nContent-length: 2\r\n\r\nab",
             "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nContent-length: 4\r\n\r\n\"ab\"",
             "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nContent-length: 1\r\n\r\n{",
             "HTTP/1.1 200 OK\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/json\r\nContent-length: 1\r\n\r\na",
@@ -6292,6 +11958,238 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_attachments_batch_codec() {
+        let attachments = vec![
+            Attachment::new(vec![0xab; 32]),
+            Attachment::new(vec![0xcd; 16]),
+        ];
+        let content_hashes: Vec<Hash160> = attachments.iter().map(|a| a.hash()).collect();
+
+        let request = HttpRequestType::GetAttachmentsBatch(
+            HttpRequestMetadata::from_host(PeerHost::DNS("www.foo.com".to_string(), 80)),
+            content_hashes.clone(),
+        );
+        let request_path = request.request_path();
+        assert_eq!(
+            request_path,
+            format!(
+                "/v2/attachments?hashes={},{}",
+                content_hashes[0], content_hashes[1]
+            )
+        );
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        match http.read_payload(&preamble, &bytes[offset..]).unwrap().0 {
+            StacksHttpMessage::Request(HttpRequestType::GetAttachmentsBatch(_, parsed)) => {
+                assert_eq!(parsed, content_hashes)
+            }
+            message => panic!("Unexpected message {:?}", &message),
+        }
+
+        // the response round-trips
+        let response = HttpResponseType::GetAttachmentsBatch(
+            HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true),
+            GetAttachmentsBatchResponse {
+                attachments: attachments
+                    .into_iter()
+                    .map(|attachment| GetAttachmentResponse { attachment })
+                    .collect(),
+            },
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.begin_request(HttpVersion::Http11, request_path);
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message_opt, _) = http
+            .stream_payload(&preamble, &mut &bytes[offset..])
+            .unwrap();
+        assert_eq!(
+            message_opt.unwrap().0,
+            StacksHttpMessage::Response(response)
+        );
+
+        // hashes are required and must all be well-formed
+        for bad_path in [
+            "/v2/attachments".to_string(),
+            "/v2/attachments?hashes=".to_string(),
+            format!("/v2/attachments?hashes={},bad", content_hashes[0]),
+        ]
+        .iter()
+        {
+            let request = format!(
+                "GET {} HTTP/1.1\r\nUser-Agent: stacks/2.0\r\nHost: bad:123\r\nContent-Length: 0\r\n\r\n",
+                bad_path
+            );
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let (preamble, offset) = http.read_preamble(request.as_bytes()).unwrap();
+            assert!(http
+                .read_payload(&preamble, &request.as_bytes()[offset..])
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_http_byte_range_header() {
+        assert_eq!(
+            HttpByteRange::from_header("bytes=0-99"),
+            Some(HttpByteRange::Span(0, 99))
+        );
+        assert_eq!(
+            HttpByteRange::from_header("bytes=100-"),
+            Some(HttpByteRange::From(100))
+        );
+        assert_eq!(
+            HttpByteRange::from_header(" bytes=-50 "),
+            Some(HttpByteRange::Suffix(50))
+        );
+        for bad in [
+            "bytes=-",
+            "bytes=9-5",
+            "bytes=0-9,20-29",
+            "items=0-9",
+            "bytes=a-b",
+            "0-9",
+        ]
+        .iter()
+        {
+            assert_eq!(HttpByteRange::from_header(bad), None, "{}", bad);
+        }
+
+        for range in [
+            HttpByteRange::Span(5, 10),
+            HttpByteRange::From(5),
+            HttpByteRange::Suffix(5),
+        ]
+        .iter()
+        {
+            assert_eq!(HttpByteRange::from_header(&range.to_header()), Some(*range));
+        }
+
+        // ends are clamped to the content, and ranges wholly outside it are unsatisfiable
+        assert_eq!(HttpByteRange::Span(0, 99).resolve(10), Some((0, 9)));
+        assert_eq!(HttpByteRange::Span(3, 5).resolve(10), Some((3, 5)));
+        assert_eq!(HttpByteRange::Span(10, 20).resolve(10), None);
+        assert_eq!(HttpByteRange::From(4).resolve(10), Some((4, 9)));
+        assert_eq!(HttpByteRange::From(10).resolve(10), None);
+        assert_eq!(HttpByteRange::Suffix(3).resolve(10), Some((7, 9)));
+        assert_eq!(HttpByteRange::Suffix(30).resolve(10), Some((0, 9)));
+        assert_eq!(HttpByteRange::Suffix(0).resolve(10), None);
+        assert_eq!(HttpByteRange::From(0).resolve(0), None);
+    }
+
+    #[test]
+    fn test_http_attachment_content_codec() {
+        let attachment = Attachment::new((0..100u8).collect());
+        let content_hash = attachment.hash();
+
+        for range in [None, Some(HttpByteRange::Span(10, 19))].iter() {
+            let request = HttpRequestType::GetAttachmentContent(
+                HttpRequestMetadata::from_host(PeerHost::DNS("www.foo.com".to_string(), 80)),
+                content_hash.clone(),
+                range.clone(),
+            );
+            assert_eq!(
+                request.request_path(),
+                format!("/v2/attachments/{}/content", &content_hash)
+            );
+
+            let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+            let mut bytes = vec![];
+            http.write_message(&mut bytes, &StacksHttpMessage::Request(request))
+                .unwrap();
+            let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+            match http.read_payload(&preamble, &bytes[offset..]).unwrap().0 {
+                StacksHttpMessage::Request(HttpRequestType::GetAttachmentContent(
+                    _,
+                    parsed_hash,
+                    parsed_range,
+                )) => {
+                    assert_eq!(parsed_hash, content_hash);
+                    assert_eq!(parsed_range, *range);
+                }
+                message => panic!("Unexpected message {:?}", &message),
+            }
+        }
+
+        let request_path = format!("/v2/attachments/{}/content", &content_hash);
+
+        // a range is sent as a 206 with a Content-Range header
+        let partial = AttachmentContentRange {
+            start: 10,
+            total_len: 100,
+            content: attachment.content[10..20].to_vec(),
+        };
+        let response = HttpResponseType::GetAttachmentContent(
+            HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true),
+            partial.clone(),
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.begin_request(HttpVersion::Http11, request_path.clone());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Response(response))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        match preamble {
+            StacksHttpPreamble::Response(ref resp) => {
+                assert_eq!(resp.status_code, 206);
+                assert_eq!(
+                    resp.headers.get("content-range").unwrap(),
+                    "bytes 10-19/100"
+                );
+            }
+            _ => panic!("parsed a request"),
+        }
+        match http.read_payload(&preamble, &bytes[offset..]).unwrap().0 {
+            StacksHttpMessage::Response(HttpResponseType::GetAttachmentContent(_, parsed)) => {
+                assert_eq!(parsed, partial)
+            }
+            message => panic!("Unexpected message {:?}", &message),
+        }
+
+        // the whole attachment is sent as a chunk-encoded 200
+        let whole = AttachmentContentRange {
+            start: 0,
+            total_len: 100,
+            content: attachment.content.clone(),
+        };
+        let response = HttpResponseType::GetAttachmentContent(
+            HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true),
+            whole.clone(),
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.begin_request(HttpVersion::Http11, request_path.clone());
+        let mut bytes = vec![];
+        http.write_message(&mut bytes, &StacksHttpMessage::Response(response.clone()))
+            .unwrap();
+        let (preamble, offset) = http.read_preamble(&bytes).unwrap();
+        let (message_opt, _) = http
+            .stream_payload(&preamble, &mut &bytes[offset..])
+            .unwrap();
+        assert_eq!(
+            message_opt.unwrap().0,
+            StacksHttpMessage::Response(response)
+        );
+
+        // a Content-Range that doesn't match the content is rejected
+        let bad_response = format!(
+            "HTTP/1.1 206 Partial Content\r\nServer: stacks/v2.0\r\nX-Request-Id: 123\r\nContent-Type: application/octet-stream\r\nContent-Length: 10\r\nContent-Range: bytes 10-29/100\r\n\r\n{}",
+            "0123456789"
+        );
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.begin_request(HttpVersion::Http11, request_path);
+        let (preamble, offset) = http.read_preamble(bad_response.as_bytes()).unwrap();
+        assert!(http
+            .read_payload(&preamble, &bad_response.as_bytes()[offset..])
+            .is_err());
+    }
+
     #[test]
     fn test_http_parse_proof_tip_query() {
         let query_txt = "tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392";
@@ -6338,6 +12236,64 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_http_parse_at_block_query() {
+        let query_txt =
+            "tip=7070f213d719143d6045e08fd80f85014a161f8bbd3a42d1251576740826a392&at_height=12";
+        assert_eq!(
+            HttpRequestType::get_at_block_query(Some(query_txt)).unwrap(),
+            AtBlock::Height(12)
+        );
+
+        let query_txt = "at_block=03e26bd68a8722f8b3861e2058edcafde094ad059e152754986c3573306698f1";
+        assert_eq!(
+            HttpRequestType::get_at_block_query(Some(query_txt)).unwrap(),
+            AtBlock::BlockId(
+                StacksBlockId::from_hex(
+                    "03e26bd68a8722f8b3861e2058edcafde094ad059e152754986c3573306698f1"
+                )
+                .unwrap()
+            )
+        );
+
+        // first parseable value is taken
+        let query_txt = "at_height=bad&at_block=bad&at_height=3&at_height=4";
+        assert_eq!(
+            HttpRequestType::get_at_block_query(Some(query_txt)).unwrap(),
+            AtBlock::Height(3)
+        );
+
+        assert_eq!(
+            HttpRequestType::get_at_block_query(Some("at_height=-1")),
+            None
+        );
+        assert_eq!(HttpRequestType::get_at_block_query(None), None);
+
+        // round-trips through the request path
+        let request = HttpRequestType::CallReadOnlyFunction(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443),
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap(),
+            "hello-world".try_into().unwrap(),
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                .unwrap()
+                .to_account_principal(),
+            "get-bar".try_into().unwrap(),
+            vec![],
+            Some(StacksBlockId([0x11; 32])),
+            Some(AtBlock::Height(7)),
+        );
+        let path = request.request_path();
+        let query = path.split("?").nth(1);
+        assert_eq!(
+            HttpRequestType::get_chain_tip_query(query),
+            Some(StacksBlockId([0x11; 32]))
+        );
+        assert_eq!(
+            HttpRequestType::get_at_block_query(query),
+            Some(AtBlock::Height(7))
+        );
+    }
+
     #[test]
     fn test_http_live_headers() {
         // headers pulled from prod
@@ -6366,5 +12322,115 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_http_response_from_net_error() {
+        let md = HttpResponseMetadata::empty_error();
+
+        // missing data
+        match HttpResponseType::from_net_error(md.clone(), &net_error::NotFoundError) {
+            HttpResponseType::NotFound(_, msg) => assert!(msg.contains("retryable: true")),
+            x => panic!("Unexpected response {:?}", &x),
+        }
+
+        // client's fault
+        let e = net_error::DeserializeError("bad request body".to_string());
+        assert_eq!(e.fault(), ErrorFault::Peer);
+        assert!(e.is_protocol_violation());
+        match HttpResponseType::from_net_error(md.clone(), &e) {
+            HttpResponseType::BadRequest(_, msg) => {
+                assert!(msg.contains("bad request body"));
+                assert!(msg.contains("fault: peer"));
+            }
+            x => panic!("Unexpected response {:?}", &x),
+        }
+
+        // transient
+        let e = net_error::TooManyPeers;
+        assert_eq!(e.fault(), ErrorFault::Local);
+        assert!(e.is_retryable());
+        match HttpResponseType::from_net_error(md.clone(), &e) {
+            HttpResponseType::ServiceUnavailable(_, msg) => {
+                assert!(msg.contains("retryable: true"))
+            }
+            x => panic!("Unexpected response {:?}", &x),
+        }
+
+        // our fault, not retryable
+        let e = net_error::ChainstateError("corrupt".to_string());
+        assert!(!e.is_retryable());
+        assert!(!e.is_protocol_violation());
+        match HttpResponseType::from_net_error(md.clone(), &e) {
+            HttpResponseType::ServerError(_, msg) => assert!(msg.contains("fault: local")),
+            x => panic!("Unexpected response {:?}", &x),
+        }
+
+        // context is reported
+        let nk = NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress([0x0a; 16]),
+            port: 12345,
+        };
+        let ctx = net_error::InvalidMessage.with_context(Some(nk.clone()), Some("Handshake"));
+        assert!(ctx.to_string().contains(&format!("peer: {}", &nk)));
+        assert!(ctx.to_string().contains("message: Handshake"));
+
+        assert!(codec_error::UnderflowError("short".to_string()).is_malformed_input());
+        assert!(!codec_error::SerializeError("oops".to_string()).is_malformed_input());
+    }
+
+    #[test]
+    fn test_http_request_body_too_large() {
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.maximum_transaction_body_size = 100;
+        let mut conn = ConnectionHttp::new(http, &ConnectionOptions::default(), None);
+
+        let mut bytes = "POST /v2/transactions HTTP/1.1\r\nHost: localhost:20443\r\nContent-Type: application/octet-stream\r\nContent-Length: 10000\r\n\r\n".as_bytes().to_vec();
+        bytes.extend_from_slice(&[0xff; 10000]);
+        bytes
+            .extend_from_slice("GET /v2/info HTTP/1.1\r\nHost: localhost:20443\r\n\r\n".as_bytes());
+
+        // the oversized body is dropped as it arrives, and the connection stays usable
+        for chunk in bytes.chunks(1000) {
+            conn.recv_data(&mut &chunk[..]).unwrap();
+        }
+        assert_eq!(conn.inbox_len(), 2);
+
+        match conn.next_inbox_message() {
+            Some(StacksHttpMessage::Request(HttpRequestType::ClientError(
+                _,
+                ClientError::PayloadTooLarge(limit),
+            ))) => assert_eq!(limit, 100),
+            x => panic!("Unexpected message {:?}", &x),
+        }
+        match conn.next_inbox_message() {
+            Some(StacksHttpMessage::Request(HttpRequestType::GetInfo(..))) => {}
+            x => panic!("Unexpected message {:?}", &x),
+        }
+
+        // other routes fall back to the default limit
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        http.maximum_transaction_body_size = 100;
+        let preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/transactions".to_string(),
+            "localhost".to_string(),
+            20443,
+            true,
+        );
+        assert_eq!(http.maximum_body_size(&preamble), 100);
+
+        let preamble = HttpRequestPreamble::new(
+            HttpVersion::Http11,
+            "POST".to_string(),
+            "/v2/admin/mempool/import".to_string(),
+            "localhost".to_string(),
+            20443,
+            true,
+        );
+        assert_eq!(http.maximum_body_size(&preamble), MAX_PAYLOAD_LEN);
+    }
+
     // TODO: test mismatch between request path and reply
 }
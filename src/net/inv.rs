@@ -38,6 +38,7 @@ use chainstate::burn::db::sortdb::{
 };
 use chainstate::burn::BlockSnapshot;
 use chainstate::stacks::db::StacksChainState;
+use monitoring::{increment_inv_disagreement_alerts, update_inv_agreement};
 use net::asn::ASEntry4;
 use net::chat::ConversationP2P;
 use net::codec::*;
@@ -50,6 +51,7 @@ use net::neighbors::MAX_NEIGHBOR_BLOCK_DELAY;
 use net::p2p::PeerNetwork;
 use net::Error as net_error;
 use net::GetBlocksInv;
+use net::GetBlocksInvPage;
 use net::Neighbor;
 use net::NeighborKey;
 use net::PeerAddress;
@@ -502,6 +504,147 @@ impl PeerBlocksInv {
     }
 }
 
+/// How our block inventory compares to our neighbors' inventories
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InvAgreement {
+    /// Number of neighbors whose inventories could be compared to ours
+    pub num_peers: u64,
+    /// Number of neighbors that claim at least one block we don't have
+    pub num_ahead: u64,
+    /// Number of neighbors that lack at least one block we have
+    pub num_behind: u64,
+}
+
+impl InvAgreement {
+    /// Compare our block inventories -- each paired with the burn block height of its first
+    /// sortition -- against our neighbors' inventories.  A neighbor is only compared over the
+    /// sortitions its inventory covers.
+    pub fn compare(
+        local_invs: &[(u64, BlocksInvData)],
+        peer_invs: &[&PeerBlocksInv],
+    ) -> InvAgreement {
+        let mut agreement = InvAgreement::default();
+        for peer_inv in peer_invs.iter() {
+            let mut compared = false;
+            let mut ahead = false;
+            let mut behind = false;
+            for &(start_block_height, ref local_inv) in local_invs.iter() {
+                for i in 0..local_inv.bitlen {
+                    let height = start_block_height + (i as u64);
+                    if height >= peer_inv.get_block_height() {
+                        break;
+                    }
+                    compared = true;
+
+                    let local_has_block = local_inv.has_ith_block(i);
+                    let peer_has_block = peer_inv.has_ith_block(height);
+                    ahead = ahead || (peer_has_block && !local_has_block);
+                    behind = behind || (local_has_block && !peer_has_block);
+                }
+            }
+            if compared {
+                agreement.num_peers += 1;
+                if ahead {
+                    agreement.num_ahead += 1;
+                }
+                if behind {
+                    agreement.num_behind += 1;
+                }
+            }
+        }
+        agreement
+    }
+
+    /// Percentage of compared neighbors that claim blocks we don't have
+    pub fn pct_ahead(&self) -> u64 {
+        if self.num_peers == 0 {
+            0
+        } else {
+            self.num_ahead * 100 / self.num_peers
+        }
+    }
+
+    /// Percentage of compared neighbors that lack blocks we have
+    pub fn pct_behind(&self) -> u64 {
+        if self.num_peers == 0 {
+            0
+        } else {
+            self.num_behind * 100 / self.num_peers
+        }
+    }
+}
+
+/// State of the inventory agreement monitor, which periodically compares our block inventory to
+/// our neighbors' and alerts when too many of them disagree with us for too long.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InvAgreementMonitor {
+    /// When we last compared inventories
+    pub last_checked_at: u64,
+    /// Result of the last comparison
+    pub last_agreement: InvAgreement,
+    /// Since when too many neighbors have claimed blocks we don't have
+    pub ahead_since: Option<u64>,
+    /// Since when too many neighbors have lacked blocks we have
+    pub behind_since: Option<u64>,
+    /// Whether or not we've alerted on the current run of ahead_since
+    pub ahead_alerted: bool,
+    /// Whether or not we've alerted on the current run of behind_since
+    pub behind_alerted: bool,
+}
+
+impl InvAgreementMonitor {
+    /// Track one side of the disagreement.  Returns true if it has just lasted long enough to
+    /// alert on.
+    fn track(
+        since: &mut Option<u64>,
+        alerted: &mut bool,
+        disagreeing: bool,
+        now: u64,
+        alert_duration: u64,
+    ) -> bool {
+        if !disagreeing {
+            *since = None;
+            *alerted = false;
+            return false;
+        }
+        let start = *since.get_or_insert(now);
+        if !*alerted && now >= start + alert_duration {
+            *alerted = true;
+            return true;
+        }
+        false
+    }
+
+    /// Record a comparison made at `now`.  A side disagrees when more than `alert_pct` percent
+    /// of the compared neighbors disagree with us that way.  Returns whether or not to alert that
+    /// neighbors are ahead of us and behind us, respectively -- each is only alerted once per
+    /// unbroken run of disagreement lasting at least `alert_duration` seconds.
+    pub fn update(
+        &mut self,
+        agreement: InvAgreement,
+        now: u64,
+        alert_pct: u64,
+        alert_duration: u64,
+    ) -> (bool, bool) {
+        let ahead_alert = InvAgreementMonitor::track(
+            &mut self.ahead_since,
+            &mut self.ahead_alerted,
+            agreement.num_peers > 0 && agreement.pct_ahead() > alert_pct,
+            now,
+            alert_duration,
+        );
+        let behind_alert = InvAgreementMonitor::track(
+            &mut self.behind_since,
+            &mut self.behind_alerted,
+            agreement.num_peers > 0 && agreement.pct_behind() > alert_pct,
+            now,
+            alert_duration,
+        );
+        self.last_agreement = agreement;
+        (ahead_alert, behind_alert)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Copy)]
 pub enum InvWorkState {
     GetPoxInvBegin,
@@ -520,6 +663,18 @@ pub enum NodeStatus {
     Dead,
 }
 
+/// A multi-reward-cycle block inventory range that is being walked one BlocksInvPage at a time,
+/// with a peer that advertises ServiceFlags::PAGED_BLOCKS_INV.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlocksInvPageScan {
+    /// The next page to ask for.  Its cursor advances as pages arrive.
+    pub request: GetBlocksInvPage,
+    /// First reward cycle in the range
+    pub start_reward_cycle: u64,
+    /// Last reward cycle in the range
+    pub end_reward_cycle: u64,
+}
+
 #[derive(Debug)]
 pub struct NeighborBlockStats {
     /// Who are we talking to?
@@ -546,6 +701,10 @@ pub struct NeighborBlockStats {
     pub pox_inv: Option<PoxInvData>,
     /// Received BlocksInv
     pub blocks_inv: Option<BlocksInvData>,
+    /// Ongoing paged block inventory scan, if any
+    pub page_scan: Option<BlocksInvPageScan>,
+    /// Received BlocksInvPage
+    pub blocks_inv_page: Option<BlocksInvPageData>,
     /// Last time we did a full scan
     pub last_rescan_timestamp: u64,
     /// Finished synchronizing?
@@ -556,6 +715,9 @@ pub struct NeighborBlockStats {
     pub scans: u64,
     /// Do we need a full rescan?
     pub need_full_rescan: bool,
+    /// Reward cycle at which to pick up an interrupted block inventory scan (i.e. one that was
+    /// underway when the node restarted)
+    pub block_scan_resume: u64,
 }
 
 impl NeighborBlockStats {
@@ -573,14 +735,55 @@ impl NeighborBlockStats {
             request: None,
             pox_inv: None,
             blocks_inv: None,
+            page_scan: None,
+            blocks_inv_page: None,
             last_rescan_timestamp: 0,
             done: false,
             learned_data: false,
             scans: 0,
             need_full_rescan: false,
+            block_scan_resume: 0,
+        }
+    }
+
+    /// Instantiate from a saved block inventory, so we can pick up the scan where it left off
+    pub fn from_saved(saved: SavedInvSyncState) -> NeighborBlockStats {
+        let mut stats = NeighborBlockStats::new(saved.nk, saved.first_block_height);
+        stats.inv = PeerBlocksInv::new(
+            saved.block_inv,
+            saved.microblocks_inv,
+            saved.pox_inv,
+            saved.num_sortitions,
+            saved.num_reward_cycles,
+            saved.first_block_height,
+        );
+        stats.block_scan_resume = saved.block_reward_cycle;
+        stats
+    }
+
+    /// Snapshot this neighbor's block inventory and scan progress, so it can be saved
+    pub fn to_saved(&self) -> SavedInvSyncState {
+        SavedInvSyncState {
+            nk: self.nk.clone(),
+            block_inv: self.inv.block_inv.clone(),
+            microblocks_inv: self.inv.microblocks_inv.clone(),
+            pox_inv: self.inv.pox_inv.clone(),
+            num_sortitions: self.inv.num_sortitions,
+            num_reward_cycles: self.inv.num_reward_cycles,
+            first_block_height: self.inv.first_block_height,
+            block_reward_cycle: cmp::max(self.block_reward_cycle, self.block_scan_resume),
         }
     }
 
+    /// Where to begin this neighbor's block inventory scan, given where it would otherwise begin.
+    /// Picks up an interrupted scan if it had gotten further along.
+    fn get_block_scan_start(&self, scan_start: u64) -> u64 {
+        cmp::max(
+            scan_start,
+            cmp::min(self.block_scan_resume, self.inv.get_pox_height()),
+        )
+    }
+
     pub fn is_peer_online(&self) -> bool {
         self.status == NodeStatus::Online
     }
@@ -590,6 +793,8 @@ impl NeighborBlockStats {
         self.request = None;
         self.pox_inv = None;
         self.blocks_inv = None;
+        self.page_scan = None;
+        self.blocks_inv_page = None;
         self.need_full_rescan = false;
         self.state = InvWorkState::GetPoxInvBegin;
 
@@ -604,6 +809,8 @@ impl NeighborBlockStats {
         self.request = None;
         self.pox_inv = None;
         self.blocks_inv = None;
+        self.page_scan = None;
+        self.blocks_inv_page = None;
         self.need_full_rescan = false;
         self.state = InvWorkState::GetBlocksInvBegin;
 
@@ -613,6 +820,28 @@ impl NeighborBlockStats {
         );
     }
 
+    /// Carry on with a paged block inventory scan, asking for the page at the given cursor next.
+    /// block_reward_cycle is the reward cycle that page starts in.
+    pub fn next_blocks_inv_page(
+        &mut self,
+        mut page_scan: BlocksInvPageScan,
+        cursor: u32,
+        block_reward_cycle: u64,
+    ) {
+        page_scan.request.cursor = cursor;
+        self.block_reward_cycle = block_reward_cycle;
+        self.page_scan = Some(page_scan);
+        self.request = None;
+        self.blocks_inv = None;
+        self.blocks_inv_page = None;
+        self.state = InvWorkState::GetBlocksInvBegin;
+
+        debug!(
+            "Continue {:?} paged block scan at cursor {} (reward cycle {})",
+            &self.nk, cursor, self.block_reward_cycle
+        );
+    }
+
     /// Determine what to do with a NACK response.
     fn diagnose_nack(
         _nk: &NeighborKey,
@@ -873,6 +1102,28 @@ impl NeighborBlockStats {
         self.state = InvWorkState::GetBlocksInvFinish;
     }
 
+    /// Proceed to get the next page of a paged block inventory scan
+    pub fn getblocksinv_page_begin(
+        &mut self,
+        request: ReplyHandleP2P,
+        target_block_reward_cycle: u64,
+    ) {
+        assert!(!self.done);
+        assert_eq!(self.state, InvWorkState::GetBlocksInvBegin);
+
+        let page_request = &self
+            .page_scan
+            .as_ref()
+            .expect("BUG: no paged block inventory scan in progress")
+            .request;
+
+        self.request = Some(request);
+        self.target_block_reward_cycle = target_block_reward_cycle;
+        self.num_blocks_expected = (page_request.num_blocks - page_request.cursor) as u64;
+
+        self.state = InvWorkState::GetBlocksInvFinish;
+    }
+
     /// Try to finish getting all BlocksInvData requests.
     /// Return true if this method is done -- i.e. all requests have been handled.
     /// Return false if we're not done.
@@ -892,7 +1143,7 @@ impl NeighborBlockStats {
         let next_request = match request.try_send_recv() {
             Ok(message) => {
                 match message.payload {
-                    StacksMessageType::BlocksInv(blocks_inv_data) => {
+                    StacksMessageType::BlocksInv(blocks_inv_data) if self.page_scan.is_none() => {
                         // got a BlocksInv!
                         // but, did we get all the bits we asked for?
                         if blocks_inv_data.bitlen as u64 != self.num_blocks_expected {
@@ -906,6 +1157,33 @@ impl NeighborBlockStats {
                             self.blocks_inv = Some(blocks_inv_data);
                         }
                     }
+                    StacksMessageType::BlocksInvPage(blocks_inv_page)
+                        if self.page_scan.is_some() =>
+                    {
+                        // got a BlocksInvPage!
+                        // but, is it the page we asked for, and does it stay inside our range?
+                        let page_request = &self
+                            .page_scan
+                            .as_ref()
+                            .expect("BUG: no paged block inventory scan in progress")
+                            .request;
+                        let page_end =
+                            (blocks_inv_page.cursor as u64) + (blocks_inv_page.inv.bitlen as u64);
+                        if blocks_inv_page.cursor != page_request.cursor
+                            || page_end > page_request.num_blocks as u64
+                            || (blocks_inv_page.next_cursor == 0)
+                                != (page_end == page_request.num_blocks as u64)
+                        {
+                            info!(
+                                "Got invalid BlocksInvPage response: expected up to {} bits at cursor {}, got {} bits at cursor {} (next cursor {})",
+                                self.num_blocks_expected, page_request.cursor, blocks_inv_page.inv.bitlen, blocks_inv_page.cursor, blocks_inv_page.next_cursor
+                            );
+                            self.status = NodeStatus::Broken;
+                        } else {
+                            debug!("Got BlocksInvPage response from {:?} at reward cycle {} at ({},{}): {:?}", &self.nk, self.target_block_reward_cycle, message.preamble.burn_block_height, message.preamble.burn_stable_block_height, &blocks_inv_page);
+                            self.blocks_inv_page = Some(blocks_inv_page);
+                        }
+                    }
                     StacksMessageType::Nack(nack_data) => {
                         debug!("Remote neighbor {:?} nack'ed our GetBlocksInv at reward cycle {}: NACK code {}", &self.nk, self.target_block_reward_cycle, nack_data.error_code);
                         let always_allowed = PeerDB::is_peer_always_allowed(
@@ -994,6 +1272,10 @@ pub struct InvState {
 
     /// What's the last reward cycle we _started_ the inv scan at?
     pub block_sortition_start: u64,
+
+    /// Neighbor inventories saved before the last restart, which have yet to be picked up by an
+    /// inv sync with that neighbor.
+    saved_block_stats: HashMap<NeighborKey, SavedInvSyncState>,
 }
 
 impl InvState {
@@ -1018,7 +1300,46 @@ impl InvState {
             num_full_inv_syncs: 0,
 
             block_sortition_start: 0,
+
+            saved_block_stats: HashMap::new(),
+        }
+    }
+
+    /// Load neighbor inventories saved before a restart.  They get used once we begin an inv
+    /// sync with that neighbor again.
+    pub fn load_saved_block_stats(&mut self, saved: Vec<SavedInvSyncState>) -> () {
+        for state in saved.into_iter() {
+            if state.first_block_height != self.first_block_height {
+                debug!(
+                    "Ignoring saved inventory for {:?}: first block height {} != {}",
+                    &state.nk, state.first_block_height, self.first_block_height
+                );
+                continue;
+            }
+            self.saved_block_stats.insert(state.nk.clone(), state);
+        }
+        debug!(
+            "Loaded {} saved neighbor inventories",
+            self.saved_block_stats.len()
+        );
+    }
+
+    /// Snapshot all neighbor inventories worth saving -- those of online neighbors, as well as
+    /// those we loaded but haven't used yet.
+    pub fn get_saved_block_stats(&self) -> Vec<SavedInvSyncState> {
+        let mut saved: Vec<_> = self
+            .block_stats
+            .values()
+            .filter(|stats| stats.is_peer_online())
+            .map(|stats| stats.to_saved())
+            .collect();
+
+        for (nk, state) in self.saved_block_stats.iter() {
+            if !self.block_stats.contains_key(nk) {
+                saved.push(state.clone());
+            }
         }
+        saved
     }
 
     pub fn reset_sync_peers(&mut self, peers: HashSet<NeighborKey>, max_neighbors: usize) -> () {
@@ -1035,10 +1356,17 @@ impl InvState {
             if let Some(stats) = self.block_stats.get_mut(peer) {
                 stats.reset_pox_scan(0);
             } else if self.block_stats.len() < max_neighbors {
-                self.block_stats.insert(
-                    peer.clone(),
-                    NeighborBlockStats::new(peer.clone(), self.first_block_height),
-                );
+                let stats = match self.saved_block_stats.remove(peer) {
+                    Some(saved) => {
+                        debug!(
+                            "Resume inv sync with {:?} at reward cycle {}",
+                            peer, saved.block_reward_cycle
+                        );
+                        NeighborBlockStats::from_saved(saved)
+                    }
+                    None => NeighborBlockStats::new(peer.clone(), self.first_block_height),
+                };
+                self.block_stats.insert(peer.clone(), stats);
                 added += 1;
             }
         }
@@ -1733,6 +2061,81 @@ impl PeerNetwork {
         }
     }
 
+    /// Does this peer answer GetBlocksInvPage?
+    fn is_paged_inv_peer(&self, nk: &NeighborKey) -> bool {
+        self.get_convo(nk)
+            .map(|convo| convo.supports_service(ServiceFlags::PAGED_BLOCKS_INV))
+            .unwrap_or(false)
+    }
+
+    /// Plan a paged block inventory scan for a peer, starting at its current block reward cycle.
+    /// The range runs for as many consecutive reward cycles as we could ask the peer about one
+    /// GetBlocksInv at a time, and ends early at the first reward cycle that isn't complete.
+    /// Returns Ok(None) if we cannot ask the peer about any blocks at this reward cycle.
+    fn make_next_getblocksinv_page(
+        &self,
+        sortdb: &SortitionDB,
+        nk: &NeighborKey,
+        stats: &NeighborBlockStats,
+    ) -> Result<Option<BlocksInvPageScan>, net_error> {
+        let start_reward_cycle = stats.block_reward_cycle;
+        if start_reward_cycle > stats.inv.num_reward_cycles {
+            return Ok(None);
+        }
+
+        let first_request = match self.make_getblocksinv(sortdb, nk, stats, start_reward_cycle)? {
+            Some(request) => request,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let reward_cycle_length = self.burnchain.pox_constants.reward_cycle_length as u64;
+        let mut num_blocks = first_request.num_blocks as u64;
+        let mut end_reward_cycle = start_reward_cycle;
+        let mut last_num_blocks = num_blocks;
+
+        while last_num_blocks == reward_cycle_length
+            && end_reward_cycle + 1 <= stats.inv.num_reward_cycles
+            && num_blocks + reward_cycle_length <= u32::MAX as u64
+        {
+            last_num_blocks = match self.get_convo(nk) {
+                Some(convo) => self.get_getblocksinv_num_blocks(
+                    sortdb,
+                    end_reward_cycle + 1,
+                    nk,
+                    stats,
+                    convo,
+                )?,
+                None => 0,
+            };
+            if last_num_blocks == 0 {
+                break;
+            }
+            num_blocks += last_num_blocks;
+            end_reward_cycle += 1;
+        }
+
+        debug!(
+            "{:?}: Send GetBlocksInvPage to {:?} for {} blocks in reward cycles {}-{} ({})",
+            &self.local_peer,
+            nk,
+            num_blocks,
+            start_reward_cycle,
+            end_reward_cycle,
+            &first_request.consensus_hash
+        );
+        Ok(Some(BlocksInvPageScan {
+            request: GetBlocksInvPage {
+                consensus_hash: first_request.consensus_hash,
+                num_blocks: num_blocks as u32,
+                cursor: 0,
+            },
+            start_reward_cycle,
+            end_reward_cycle,
+        }))
+    }
+
     /// Determine at which reward cycle to begin scanning inventories
     fn get_block_scan_start(&self, highest_remote_reward_cycle: u64, full_rescan: bool) -> u64 {
         if full_rescan {
@@ -1757,7 +2160,9 @@ impl PeerNetwork {
             Some(x) => x,
             None => {
                 // proceed to block scan
-                let scan_start = self.get_block_scan_start(stats.inv.get_pox_height(), full_rescan);
+                let scan_start = stats.get_block_scan_start(
+                    self.get_block_scan_start(stats.inv.get_pox_height(), full_rescan),
+                );
                 debug!("{:?}: cannot make any more GetPoxInv requests for {:?}; proceeding to block inventory scan at reward cycle {}", &self.local_peer, nk, scan_start);
                 stats.reset_block_scan(scan_start);
                 return Ok(());
@@ -1809,6 +2214,8 @@ impl PeerNetwork {
                 stats
                     .inv
                     .truncate_block_inventories(&self.burnchain, stats.target_pox_reward_cycle);
+                stats.block_scan_resume =
+                    cmp::min(stats.block_scan_resume, stats.target_pox_reward_cycle);
 
                 // proceed with block scan.
                 // If we're in IBD, then this is an always-allowed peer and we should
@@ -1901,12 +2308,15 @@ impl PeerNetwork {
                 stats
                     .inv
                     .truncate_block_inventories(&self.burnchain, minimum_certainty);
+                stats.block_scan_resume = cmp::min(stats.block_scan_resume, minimum_certainty);
             } else {
                 debug!("{:?}: Sync'ed PoX inventory with {:?}, and it is equally certain up to reward cycle {}", &self.local_peer, nk, self.pox_id.num_inventory_reward_cycles());
             }
 
             // proceed to block scan.
-            let scan_start = self.get_block_scan_start(stats.inv.get_pox_height(), full_rescan);
+            let scan_start = stats.get_block_scan_start(
+                self.get_block_scan_start(stats.inv.get_pox_height(), full_rescan),
+            );
             debug!(
                 "{:?}: proceeding to block inventory scan for {:?} at reward cycle {}",
                 &self.local_peer, nk, scan_start
@@ -1929,6 +2339,10 @@ impl PeerNetwork {
         stats: &mut NeighborBlockStats,
         request_timeout: u64,
     ) -> Result<(), net_error> {
+        if stats.page_scan.is_some() || self.is_paged_inv_peer(nk) {
+            return self.inv_getblocksinv_page_begin(sortdb, nk, stats, request_timeout);
+        }
+
         let (target_block_reward_cycle, getblocksinv) =
             match self.make_next_getblocksinv(sortdb, nk, stats)? {
                 Some(x) => x,
@@ -1952,6 +2366,103 @@ impl PeerNetwork {
         Ok(())
     }
 
+    /// Start requesting the next page of a paged block inventory scan, planning the scan first if
+    /// one isn't underway
+    fn inv_getblocksinv_page_begin(
+        &mut self,
+        sortdb: &SortitionDB,
+        nk: &NeighborKey,
+        stats: &mut NeighborBlockStats,
+        request_timeout: u64,
+    ) -> Result<(), net_error> {
+        if stats.page_scan.is_none() {
+            match self.make_next_getblocksinv_page(sortdb, nk, stats)? {
+                Some(page_scan) => {
+                    stats.page_scan = Some(page_scan);
+                }
+                None => {
+                    stats.done = true;
+                    return Ok(());
+                }
+            }
+        }
+
+        let (getblocksinvpage, target_block_reward_cycle) = {
+            let page_scan = stats
+                .page_scan
+                .as_ref()
+                .expect("BUG: no paged block inventory scan in progress");
+            (page_scan.request.clone(), stats.block_reward_cycle)
+        };
+
+        let payload = StacksMessageType::GetBlocksInvPage(getblocksinvpage);
+        let message = self.sign_for_peer(nk, payload)?;
+        let request = self
+            .send_message(nk, message, request_timeout)
+            .map_err(|e| {
+                debug!("Failed to send GetBlocksInvPage to {:?}: {:?}", &nk, &e);
+                e
+            })?;
+
+        stats.getblocksinv_page_begin(request, target_block_reward_cycle);
+        Ok(())
+    }
+
+    /// Merge a page of a paged block inventory scan into a peer's block inventory.
+    /// If there are more pages to go, queue up the next one and return false.  Otherwise, return
+    /// true once the scan has covered its whole range.
+    fn inv_merge_blocks_inv_page(
+        &mut self,
+        nk: &NeighborKey,
+        stats: &mut NeighborBlockStats,
+        blocks_inv_page: BlocksInvPageData,
+    ) -> bool {
+        let page_scan = stats
+            .page_scan
+            .take()
+            .expect("BUG: got a BlocksInvPage without a paged block inventory scan");
+        let page_block_height = self
+            .burnchain
+            .reward_cycle_to_block_height(page_scan.start_reward_cycle)
+            + (blocks_inv_page.cursor as u64);
+
+        debug!(
+            "{:?}: got blocksinv page at cursor {} of {} (block height {}) from {:?}: {:?}",
+            &self.local_peer,
+            blocks_inv_page.cursor,
+            page_scan.request.num_blocks,
+            page_block_height,
+            nk,
+            &blocks_inv_page
+        );
+        let (new_blocks, new_microblocks) = stats.inv.merge_blocks_inv(
+            page_block_height,
+            blocks_inv_page.inv.bitlen as u64,
+            blocks_inv_page.inv.block_bitvec,
+            blocks_inv_page.inv.microblocks_bitvec,
+            true,
+        );
+
+        debug!("{:?}: {:?} has {} new blocks and {} new microblocks (total {} blocks, {} microblocks, {} sortitions): {:?}",
+               &self.local_peer, &nk, new_blocks, new_microblocks, stats.inv.num_blocks(), stats.inv.num_microblock_streams(), stats.inv.num_sortitions, &stats.inv);
+
+        if new_blocks > 0 || new_microblocks > 0 {
+            stats.learned_data = true;
+        }
+
+        if blocks_inv_page.next_cursor != 0 {
+            let next_reward_cycle = page_scan.start_reward_cycle
+                + (blocks_inv_page.next_cursor as u64)
+                    / (self.burnchain.pox_constants.reward_cycle_length as u64);
+            stats.next_blocks_inv_page(page_scan, blocks_inv_page.next_cursor, next_reward_cycle);
+            return false;
+        }
+
+        stats.target_block_reward_cycle = page_scan.end_reward_cycle;
+        stats.block_reward_cycle = page_scan.end_reward_cycle;
+        true
+    }
+
     /// Finish receiving the next batch of block inventories.
     /// Indicate whether or not we're done
     fn inv_getblocksinv_try_finish(
@@ -1979,31 +2490,43 @@ impl PeerNetwork {
 
         // if we get a blocksinv, then it means the remote peer still agrees with us on PoX state
         // (otherwise we would have been NACK'ed, and the peer would not be considered online)
-        let blocks_inv = stats
-            .blocks_inv
-            .take()
-            .expect("BUG: finished getblocksinv without an error but got no blocksinv");
-        let target_block_height = self
-            .burnchain
-            .reward_cycle_to_block_height(stats.target_block_reward_cycle);
+        if let Some(blocks_inv_page) = stats.blocks_inv_page.take() {
+            assert_eq!(stats.state, InvWorkState::Done);
+            if !self.inv_merge_blocks_inv_page(nk, stats, blocks_inv_page) {
+                // ask for the next page
+                return Ok(true);
+            }
+        } else {
+            let blocks_inv = stats
+                .blocks_inv
+                .take()
+                .expect("BUG: finished getblocksinv without an error but got no blocksinv");
+            let target_block_height = self
+                .burnchain
+                .reward_cycle_to_block_height(stats.target_block_reward_cycle);
 
-        debug!(
-            "{:?}: got blocksinv at reward cycle {} (block height {}) from {:?}: {:?}",
-            &self.local_peer, stats.target_block_reward_cycle, target_block_height, nk, &blocks_inv
-        );
-        let (new_blocks, new_microblocks) = stats.inv.merge_blocks_inv(
-            target_block_height,
-            blocks_inv.bitlen as u64,
-            blocks_inv.block_bitvec,
-            blocks_inv.microblocks_bitvec,
-            true,
-        );
+            debug!(
+                "{:?}: got blocksinv at reward cycle {} (block height {}) from {:?}: {:?}",
+                &self.local_peer,
+                stats.target_block_reward_cycle,
+                target_block_height,
+                nk,
+                &blocks_inv
+            );
+            let (new_blocks, new_microblocks) = stats.inv.merge_blocks_inv(
+                target_block_height,
+                blocks_inv.bitlen as u64,
+                blocks_inv.block_bitvec,
+                blocks_inv.microblocks_bitvec,
+                true,
+            );
 
-        debug!("{:?}: {:?} has {} new blocks and {} new microblocks (total {} blocks, {} microblocks, {} sortitions): {:?}",
-               &self.local_peer, &nk, new_blocks, new_microblocks, stats.inv.num_blocks(), stats.inv.num_microblock_streams(), stats.inv.num_sortitions, &stats.inv);
+            debug!("{:?}: {:?} has {} new blocks and {} new microblocks (total {} blocks, {} microblocks, {} sortitions): {:?}",
+                   &self.local_peer, &nk, new_blocks, new_microblocks, stats.inv.num_blocks(), stats.inv.num_microblock_streams(), stats.inv.num_sortitions, &stats.inv);
 
-        if new_blocks > 0 || new_microblocks > 0 {
-            stats.learned_data = true;
+            if new_blocks > 0 || new_microblocks > 0 {
+                stats.learned_data = true;
+            }
         }
 
         assert_eq!(stats.state, InvWorkState::Done);
@@ -2017,6 +2540,7 @@ impl PeerNetwork {
         } else {
             // we're done scanning!  proceed to rescan
             stats.last_rescan_timestamp = get_epoch_time_secs();
+            stats.block_scan_resume = 0;
             stats.done = true;
         }
 
@@ -2463,11 +2987,47 @@ impl PeerNetwork {
             "{:?}: Initializing peer block inventory state",
             &self.local_peer,
         );
-        self.inv_state = Some(InvState::new(
+        let mut inv_state = InvState::new(
             sortdb.first_block_height,
             self.connection_opts.timeout,
             self.connection_opts.inv_sync_interval,
-        ));
+        );
+
+        // pick up where we left off before we last shut down
+        match PeerDB::get_inv_sync_state(self.peerdb.conn(), self.local_peer.network_id) {
+            Ok(saved) => {
+                inv_state.load_saved_block_stats(saved);
+            }
+            Err(e) => {
+                warn!(
+                    "{:?}: Failed to load saved inventory sync state: {:?}",
+                    &self.local_peer, &e
+                );
+            }
+        }
+        self.inv_state = Some(inv_state);
+    }
+
+    /// Save our neighbors' block inventories and how far we got scanning them, so a restarted
+    /// node can resume its inventory sync.
+    pub fn save_inv_sync_state(&mut self) -> Result<(), net_error> {
+        let saved = match self.inv_state {
+            Some(ref inv_state) => inv_state.get_saved_block_stats(),
+            None => {
+                return Ok(());
+            }
+        };
+
+        let mut tx = self.peerdb.tx_begin()?;
+        PeerDB::set_inv_sync_state(&mut tx, &saved)?;
+        tx.commit()?;
+
+        debug!(
+            "{:?}: Saved inventory sync state for {} neighbors",
+            &self.local_peer,
+            saved.len()
+        );
+        Ok(())
     }
 
     /// Run a function over a given neighbor's inventory
@@ -2553,6 +3113,103 @@ impl PeerNetwork {
             }
         }
     }
+
+    /// Periodically compare our block inventory for the current and previous reward cycles to
+    /// our neighbors', and alert if too many of them have disagreed with us for too long.
+    pub fn check_inv_agreement(&mut self, sortdb: &SortitionDB, chainstate: &StacksChainState) {
+        let interval = self.connection_opts.inv_agreement_check_interval;
+        let now = get_epoch_time_secs();
+        if interval == 0 || self.inv_agreement.last_checked_at + interval > now {
+            return;
+        }
+        self.inv_agreement.last_checked_at = now;
+
+        if self.inv_state.is_none() {
+            return;
+        }
+
+        let tip_reward_cycle = match self
+            .burnchain
+            .block_height_to_reward_cycle(self.chain_view.burn_block_height)
+        {
+            Some(reward_cycle) => reward_cycle,
+            None => {
+                return;
+            }
+        };
+
+        // include the previous reward cycle, so there's something to compare at the start of a
+        // new one
+        let mut local_invs = vec![];
+        for reward_cycle in tip_reward_cycle.saturating_sub(1)..(tip_reward_cycle + 1) {
+            match self.get_local_blocks_inv(sortdb, chainstate, reward_cycle) {
+                Ok(inv) => {
+                    let start_block_height =
+                        self.burnchain.reward_cycle_to_block_height(reward_cycle);
+                    local_invs.push((start_block_height, inv));
+                }
+                Err(e) => {
+                    debug!(
+                        "{:?}: InvAgreement: Failed to load local blocks inventory for reward cycle {}: {:?}",
+                        &self.local_peer, reward_cycle, &e
+                    );
+                }
+            }
+        }
+
+        let agreement = match self.inv_state {
+            Some(ref inv_state) => {
+                let peer_invs: Vec<&PeerBlocksInv> = inv_state
+                    .block_stats
+                    .values()
+                    .map(|stats| &stats.inv)
+                    .collect();
+                InvAgreement::compare(&local_invs, &peer_invs)
+            }
+            None => {
+                return;
+            }
+        };
+
+        debug!(
+            "{:?}: InvAgreement: {} neighbors compared, {} claim blocks we lack, {} lack blocks we have",
+            &self.local_peer, agreement.num_peers, agreement.num_ahead, agreement.num_behind
+        );
+        update_inv_agreement(
+            agreement.num_peers as i64,
+            agreement.pct_ahead() as i64,
+            agreement.pct_behind() as i64,
+        );
+
+        let alert_pct = self.connection_opts.inv_agreement_alert_pct;
+        let alert_duration = self.connection_opts.inv_agreement_alert_duration;
+        let (ahead_alert, behind_alert) =
+            self.inv_agreement
+                .update(agreement.clone(), now, alert_pct, alert_duration);
+
+        if ahead_alert {
+            warn!(
+                "{:?}: {} of {} neighbors ({}%) have claimed Stacks blocks we don't have for over {} seconds -- this node may be failing to download or process blocks",
+                &self.local_peer,
+                agreement.num_ahead,
+                agreement.num_peers,
+                agreement.pct_ahead(),
+                alert_duration
+            );
+            increment_inv_disagreement_alerts("ahead");
+        }
+        if behind_alert {
+            warn!(
+                "{:?}: {} of {} neighbors ({}%) have lacked Stacks blocks we have for over {} seconds -- this node may be on a different fork, or failing to relay blocks",
+                &self.local_peer,
+                agreement.num_behind,
+                agreement.num_peers,
+                agreement.pct_behind(),
+                alert_duration
+            );
+            increment_inv_disagreement_alerts("behind");
+        }
+    }
 }
 
 #[cfg(test)]
@@ -2779,6 +3436,105 @@ mod test {
         assert_eq!(peer_inv.microblocks_inv, vec![0x00, 0x00, 0x00, 0x00, 0x00]);
     }
 
+    #[test]
+    fn inv_agreement_compare_and_alert() {
+        // we have the blocks for the first 4 of 16 sortitions
+        let local_invs = vec![(
+            12345,
+            BlocksInvData {
+                bitlen: 16,
+                block_bitvec: vec![0x0f, 0x00],
+                microblocks_bitvec: vec![0x0f, 0x00],
+            },
+        )];
+
+        let agrees =
+            PeerBlocksInv::new(vec![0x0f, 0x00], vec![0x0f, 0x00], vec![0x01], 16, 1, 12345);
+        let ahead =
+            PeerBlocksInv::new(vec![0x1f, 0x00], vec![0x1f, 0x00], vec![0x01], 16, 1, 12345);
+        let behind =
+            PeerBlocksInv::new(vec![0x07, 0x00], vec![0x07, 0x00], vec![0x01], 16, 1, 12345);
+        let ahead_and_behind =
+            PeerBlocksInv::new(vec![0x17, 0x00], vec![0x17, 0x00], vec![0x01], 16, 1, 12345);
+        // only compared over the 3 sortitions it knows about, so it agrees
+        let short = PeerBlocksInv::new(vec![0x07], vec![0x07], vec![0x01], 3, 1, 12345);
+        // knows about no sortitions, so it isn't compared
+        let empty = PeerBlocksInv::empty(12345);
+
+        let agreement = InvAgreement::compare(
+            &local_invs,
+            &[&agrees, &ahead, &behind, &ahead_and_behind, &short, &empty],
+        );
+        assert_eq!(
+            agreement,
+            InvAgreement {
+                num_peers: 5,
+                num_ahead: 2,
+                num_behind: 2,
+            }
+        );
+        assert_eq!(agreement.pct_ahead(), 40);
+        assert_eq!(agreement.pct_behind(), 40);
+
+        let all_agree = InvAgreement::compare(&local_invs, &[&agrees, &short]);
+        assert_eq!(all_agree.pct_ahead(), 0);
+        assert_eq!(all_agree.pct_behind(), 0);
+        assert_eq!(InvAgreement::compare(&local_invs, &[]).pct_ahead(), 0);
+
+        // 40% disagreement doesn't alert at 40%...
+        let mut monitor = InvAgreementMonitor::default();
+        assert_eq!(
+            monitor.update(agreement.clone(), 1000, 40, 600),
+            (false, false)
+        );
+        assert_eq!(
+            monitor.update(agreement.clone(), 2000, 40, 600),
+            (false, false)
+        );
+        assert_eq!(monitor.ahead_since, None);
+
+        // ...but it does at 30%, once it's lasted 600 seconds
+        let mut monitor = InvAgreementMonitor::default();
+        assert_eq!(
+            monitor.update(agreement.clone(), 1000, 30, 600),
+            (false, false)
+        );
+        assert_eq!(monitor.ahead_since, Some(1000));
+        assert_eq!(monitor.behind_since, Some(1000));
+        assert_eq!(
+            monitor.update(agreement.clone(), 1599, 30, 600),
+            (false, false)
+        );
+        assert_eq!(
+            monitor.update(agreement.clone(), 1600, 30, 600),
+            (true, true)
+        );
+
+        // only alerted once per run of disagreement
+        assert_eq!(
+            monitor.update(agreement.clone(), 1700, 30, 600),
+            (false, false)
+        );
+
+        // agreeing again resets the run
+        assert_eq!(
+            monitor.update(all_agree.clone(), 1800, 30, 600),
+            (false, false)
+        );
+        assert_eq!(monitor.ahead_since, None);
+        assert_eq!(monitor.behind_since, None);
+        assert_eq!(monitor.last_agreement, all_agree);
+
+        assert_eq!(
+            monitor.update(agreement.clone(), 1900, 30, 600),
+            (false, false)
+        );
+        assert_eq!(
+            monitor.update(agreement.clone(), 2500, 30, 600),
+            (true, true)
+        );
+    }
+
     #[test]
     fn peerblocksinv_merge_clear_bits() {
         let peer_inv = PeerBlocksInv::new(
@@ -2964,6 +3720,58 @@ mod test {
         assert_eq!(peer_inv.microblocks_inv, vec![0x00, 0x00, 0x00, 0x00, 0x00]);
     }
 
+    #[test]
+    fn test_inv_state_resume_saved_block_stats() {
+        let nk_1 = NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress([0x01; 16]),
+            port: 20444,
+        };
+        let nk_2 = NeighborKey {
+            peer_version: 0x12345678,
+            network_id: 0x9abcdef0,
+            addrbytes: PeerAddress([0x02; 16]),
+            port: 20444,
+        };
+        let saved_1 = SavedInvSyncState {
+            nk: nk_1.clone(),
+            block_inv: vec![0x55, 0x77],
+            microblocks_inv: vec![0x11, 0x22],
+            pox_inv: vec![0x0f],
+            num_sortitions: 16,
+            num_reward_cycles: 4,
+            first_block_height: 12345,
+            block_reward_cycle: 3,
+        };
+        let mut saved_2 = saved_1.clone();
+        saved_2.nk = nk_2.clone();
+        saved_2.first_block_height = 12346;
+
+        let mut inv_state = InvState::new(12345, 10, 10);
+        inv_state.load_saved_block_stats(vec![saved_1.clone(), saved_2]);
+
+        // saved state from a different chain is dropped; the rest is kept until it's used
+        assert_eq!(inv_state.get_saved_block_stats(), vec![saved_1.clone()]);
+
+        let mut peers = HashSet::new();
+        peers.insert(nk_1.clone());
+        inv_state.reset_sync_peers(peers, 10);
+
+        let stats = inv_state.block_stats.get(&nk_1).unwrap();
+        assert_eq!(stats.inv.block_inv, saved_1.block_inv);
+        assert_eq!(stats.inv.microblocks_inv, saved_1.microblocks_inv);
+        assert_eq!(stats.inv.pox_inv, saved_1.pox_inv);
+        assert_eq!(stats.inv.num_sortitions, 16);
+        assert_eq!(stats.block_scan_resume, 3);
+
+        // scan picks up where it left off, but no further than the neighbor's PoX inventory
+        assert_eq!(stats.get_block_scan_start(0), 3);
+        assert_eq!(stats.get_block_scan_start(4), 4);
+
+        assert_eq!(inv_state.get_saved_block_stats(), vec![saved_1]);
+    }
+
     #[test]
     fn test_inv_set_block_microblock_bits() {
         let mut peer_inv = PeerBlocksInv::new(vec![0x01], vec![0x01], vec![0x01], 1, 1, 12345);
@@ -3677,6 +4485,109 @@ mod test {
                 assert!(false);
             }
         };
+
+        // walk a paged getblocksinv that starts at the same reward cycle and runs past its end
+        let range_consensus_hash = peer_1
+            .with_network_state(|sortdb, _chainstate, network, _relayer, _mempool| {
+                let height = network.burnchain.reward_cycle_to_block_height(
+                    network
+                        .burnchain
+                        .block_height_to_reward_cycle(first_stacks_block_height)
+                        .unwrap(),
+                );
+                let ic = sortdb.index_conn();
+                let sn = SortitionDB::get_ancestor_snapshot(&ic, height, &tip.sortition_id)
+                    .unwrap()
+                    .unwrap();
+                Ok(sn.consensus_hash)
+            })
+            .unwrap();
+
+        let range_len = reward_cycle_length + 4;
+        assert!((range_len as u64) > BLOCKS_INV_PAGE_MAX_BITLEN);
+
+        let mut cursor = 0;
+        let mut pages = vec![];
+        loop {
+            let getblocksinvpage_request = GetBlocksInvPage {
+                consensus_hash: range_consensus_hash.clone(),
+                num_blocks: range_len,
+                cursor: cursor,
+            };
+
+            test_debug!("\n\nSend {:?}\n\n", &getblocksinvpage_request);
+
+            let reply = peer_1
+                .with_network_state(|sortdb, chainstate, network, _relayer, _mempool| {
+                    ConversationP2P::make_getblocksinv_page_response(
+                        &network.local_peer,
+                        &network.burnchain,
+                        sortdb,
+                        chainstate,
+                        &mut network.header_cache,
+                        &getblocksinvpage_request,
+                    )
+                })
+                .unwrap();
+
+            test_debug!("\n\nReply {:?}\n\n", &reply);
+
+            match reply {
+                StacksMessageType::BlocksInvPage(page) => {
+                    assert_eq!(page.cursor, cursor);
+                    cursor = page.next_cursor;
+                    pages.push(page);
+                }
+                x => {
+                    error!("Did not get BlocksInvPage, but got {:?}", &x);
+                    assert!(false);
+                }
+            }
+            if cursor == 0 {
+                break;
+            }
+        }
+
+        assert_eq!(pages.len(), 2);
+        assert_eq!(pages[0].inv.bitlen as u64, BLOCKS_INV_PAGE_MAX_BITLEN);
+        assert_eq!(pages[0].next_cursor as u64, BLOCKS_INV_PAGE_MAX_BITLEN);
+        assert_eq!(
+            pages[1].inv.bitlen as u64,
+            (range_len as u64) - BLOCKS_INV_PAGE_MAX_BITLEN
+        );
+
+        // same bits as the aligned getblocksinv above, followed by the next reward cycle's
+        assert_eq!(pages[0].inv.block_bitvec, vec![0xff]);
+        assert_eq!(pages[0].inv.microblocks_bitvec[0] & 0x1f, 0x1e);
+        assert_eq!(pages[1].inv.block_bitvec, vec![0x01]);
+
+        // a page past the end of our chain gets NACK'ed
+        let reply = peer_1
+            .with_network_state(|sortdb, chainstate, network, _relayer, _mempool| {
+                ConversationP2P::make_getblocksinv_page_response(
+                    &network.local_peer,
+                    &network.burnchain,
+                    sortdb,
+                    chainstate,
+                    &mut network.header_cache,
+                    &GetBlocksInvPage {
+                        consensus_hash: range_consensus_hash.clone(),
+                        num_blocks: 1000,
+                        cursor: 992,
+                    },
+                )
+            })
+            .unwrap();
+
+        match reply {
+            StacksMessageType::Nack(nack_data) => {
+                assert_eq!(nack_data.error_code, NackErrorCodes::NoSuchBurnchainBlock);
+            }
+            x => {
+                error!("Did not get Nack, but got {:?}", &x);
+                assert!(false);
+            }
+        };
     }
 
     #[test]
@@ -3955,6 +4866,135 @@ mod test {
         })
     }
 
+    #[test]
+    fn test_sync_inv_2_peers_paged() {
+        with_timeout(600, || {
+            let mut peer_1_config =
+                TestPeerConfig::new("test_sync_inv_2_peers_paged", 31962, 41962);
+            let mut peer_2_config =
+                TestPeerConfig::new("test_sync_inv_2_peers_paged", 31963, 41963);
+
+            // both peers answer GetBlocksInvPage, so they walk each other's inventories in pages
+            peer_1_config.services = ServiceFlags::PAGED_BLOCKS_INV.set(peer_1_config.services);
+            peer_2_config.services = ServiceFlags::PAGED_BLOCKS_INV.set(peer_2_config.services);
+
+            peer_1_config.add_neighbor(&peer_2_config.to_neighbor());
+            peer_2_config.add_neighbor(&peer_1_config.to_neighbor());
+
+            let mut peer_1 = TestPeer::new(peer_1_config);
+            let mut peer_2 = TestPeer::new(peer_2_config);
+
+            let num_blocks = (GETPOXINV_MAX_BITLEN * 2) as u64;
+            let first_stacks_block_height = {
+                let sn = SortitionDB::get_canonical_burn_chain_tip(
+                    &peer_1.sortdb.as_ref().unwrap().conn(),
+                )
+                .unwrap();
+                sn.block_height + 1
+            };
+
+            for _ in 0..num_blocks {
+                let (burn_ops, stacks_block, microblocks) = peer_2.make_default_tenure();
+
+                peer_1.next_burnchain_block(burn_ops.clone());
+                peer_2.next_burnchain_block(burn_ops.clone());
+
+                peer_1.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+                peer_2.process_stacks_epoch_at_tip(&stacks_block, &microblocks);
+            }
+
+            let num_burn_blocks = {
+                let sn = SortitionDB::get_canonical_burn_chain_tip(
+                    peer_1.sortdb.as_ref().unwrap().conn(),
+                )
+                .unwrap();
+                sn.block_height + 1
+            };
+
+            let mut inv_1_count = 0;
+            let mut inv_2_count = 0;
+
+            while inv_1_count < num_blocks || inv_2_count < num_blocks {
+                let _ = peer_1.step();
+                let _ = peer_2.step();
+
+                inv_1_count = match peer_1.network.inv_state {
+                    Some(ref inv) => inv.get_inv_num_blocks(&peer_2.to_neighbor().addr),
+                    None => 0,
+                };
+
+                inv_2_count = match peer_2.network.inv_state {
+                    Some(ref inv) => inv.get_inv_num_blocks(&peer_1.to_neighbor().addr),
+                    None => 0,
+                };
+
+                // nothing should break
+                for peer in [&peer_1, &peer_2].iter() {
+                    match peer.network.inv_state {
+                        Some(ref inv) => {
+                            assert_eq!(inv.get_broken_peers().len(), 0);
+                            assert_eq!(inv.get_dead_peers().len(), 0);
+                            assert_eq!(inv.get_diverged_peers().len(), 0);
+                        }
+                        None => {}
+                    }
+                }
+
+                info!("Peer 1: {}, Peer 2: {}", inv_1_count, inv_2_count);
+            }
+
+            // peers got their inventories from pages
+            for (peer, other) in [(&peer_1, &peer_2), (&peer_2, &peer_1)].iter() {
+                let other_addr = other.to_neighbor().addr;
+                assert!(
+                    peer.network
+                        .get_convo(&other_addr)
+                        .unwrap()
+                        .stats
+                        .get_message_recv_count(StacksMessageID::BlocksInvPage)
+                        > 0
+                );
+                assert_eq!(
+                    peer.network
+                        .get_convo(&other_addr)
+                        .unwrap()
+                        .stats
+                        .get_message_recv_count(StacksMessageID::BlocksInv),
+                    0
+                );
+
+                let other_inv = peer
+                    .network
+                    .inv_state
+                    .as_ref()
+                    .unwrap()
+                    .block_stats
+                    .get(&other_addr)
+                    .unwrap()
+                    .inv
+                    .clone();
+
+                assert_eq!(other_inv.num_sortitions, num_burn_blocks);
+                for i in 0..num_blocks {
+                    assert!(
+                        other_inv.has_ith_block(i + first_stacks_block_height),
+                        "Missing block {} (+ {})",
+                        i,
+                        first_stacks_block_height
+                    );
+                }
+                for i in 1..(num_blocks - 1) {
+                    assert!(
+                        other_inv.has_ith_microblock_stream(i + first_stacks_block_height),
+                        "Missing microblock {} (+ {})",
+                        i,
+                        first_stacks_block_height
+                    );
+                }
+            }
+        })
+    }
+
     #[test]
     #[ignore]
     fn test_sync_inv_2_peers_plain_full_sync() {
@@ -0,0 +1,489 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use chainstate::stacks::db::StacksChainState;
+use chainstate::stacks::Error as chain_error;
+use chainstate::stacks::{StacksTransaction, TransactionPayload};
+use net::connection::ConnectionOptions;
+use net::{RPCPage, RPCPageRequest, RPCQueryJob, RPCQueryJobResult};
+use util::get_epoch_time_secs;
+use vm::types::{PrincipalData, QualifiedContractIdentifier, StandardPrincipalData};
+
+use crate::types::chainstate::{BlockHeaderHash, StacksAddress, StacksBlockId};
+
+/// What a query job looks for.  Each kind is a scan over every block of the canonical fork,
+/// which is too slow to do within a single RPC request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryJobKind {
+    /// transactions sent or sponsored by an address, and STX transfers to it
+    AddressHistory(StacksAddress),
+    /// transactions that deployed or called a contract
+    ContractHistory(QualifiedContractIdentifier),
+}
+
+impl QueryJobKind {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            QueryJobKind::AddressHistory(_) => "address-history",
+            QueryJobKind::ContractHistory(_) => "contract-history",
+        }
+    }
+
+    /// The address or contract the job is about
+    pub fn target(&self) -> String {
+        match *self {
+            QueryJobKind::AddressHistory(ref address) => address.to_string(),
+            QueryJobKind::ContractHistory(ref contract_id) => contract_id.to_string(),
+        }
+    }
+
+    /// Is this transaction one of the job's results?
+    pub fn matches(&self, tx: &StacksTransaction) -> bool {
+        match *self {
+            QueryJobKind::AddressHistory(ref address) => {
+                if tx.origin_address() == *address || tx.sponsor_address().as_ref() == Some(address)
+                {
+                    return true;
+                }
+                match tx.payload {
+                    TransactionPayload::TokenTransfer(
+                        PrincipalData::Standard(ref recipient),
+                        ..,
+                    ) => *recipient == StandardPrincipalData::from(address.clone()),
+                    _ => false,
+                }
+            }
+            QueryJobKind::ContractHistory(ref contract_id) => match tx.payload {
+                TransactionPayload::ContractCall(ref call) => {
+                    call.to_clarity_contract_id() == *contract_id
+                }
+                TransactionPayload::SmartContract(ref smart_contract) => {
+                    QualifiedContractIdentifier::new(
+                        StandardPrincipalData::from(tx.origin_address()),
+                        smart_contract.name.clone(),
+                    ) == *contract_id
+                }
+                _ => false,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum QueryJobStatus {
+    /// still scanning blocks
+    Running,
+    /// scanned every block down to genesis, or stopped at `max_query_job_results`
+    Done,
+    /// a block could not be loaded
+    Failed,
+}
+
+/// A scan over the canonical fork as of the job's creation, which the p2p thread advances a few
+/// blocks at a time.  Blocks are visited from the tip down, so results are newest block first,
+/// and in the order they were mined within a block.
+#[derive(Debug, Clone)]
+pub struct QueryJob {
+    pub id: u64,
+    pub kind: QueryJobKind,
+    /// the chain tip the job scans back from
+    pub tip: StacksBlockId,
+    pub tip_height: u64,
+    /// the next block to scan, or None once the job is finished
+    cursor: Option<StacksBlockId>,
+    pub blocks_scanned: u64,
+    pub results: Vec<RPCQueryJobResult>,
+    /// stopped early because it found `max_query_job_results` results
+    pub truncated: bool,
+    pub status: QueryJobStatus,
+    pub error: Option<String>,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+}
+
+impl QueryJob {
+    pub fn new(id: u64, kind: QueryJobKind, tip: StacksBlockId, tip_height: u64) -> QueryJob {
+        QueryJob {
+            id,
+            kind,
+            tip: tip.clone(),
+            tip_height,
+            cursor: Some(tip),
+            blocks_scanned: 0,
+            results: vec![],
+            truncated: false,
+            status: QueryJobStatus::Running,
+            error: None,
+            created_at: get_epoch_time_secs(),
+            finished_at: None,
+        }
+    }
+
+    fn finish(&mut self, status: QueryJobStatus, error: Option<String>) {
+        self.cursor = None;
+        self.status = status;
+        self.error = error;
+        self.finished_at = Some(get_epoch_time_secs());
+    }
+
+    fn push_results(
+        &mut self,
+        txs: &[StacksTransaction],
+        index_block_hash: &StacksBlockId,
+        block_height: u64,
+        microblock_hash: Option<&BlockHeaderHash>,
+    ) {
+        let kind = &self.kind;
+        for tx in txs.iter().filter(|tx| kind.matches(tx)) {
+            self.results.push(RPCQueryJobResult {
+                txid: tx.txid().to_hex(),
+                index_block_hash: index_block_hash.clone(),
+                block_height,
+                microblock_hash: microblock_hash.cloned(),
+            });
+        }
+    }
+
+    /// Scan the block at the cursor, along with the microblock stream it confirmed, and move
+    /// the cursor to its parent.
+    fn scan_block(&mut self, chainstate: &StacksChainState) -> Result<(), chain_error> {
+        let index_block_hash = match self.cursor.take() {
+            Some(index_block_hash) => index_block_hash,
+            None => return Ok(()),
+        };
+        let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &index_block_hash,
+        )?
+        .ok_or(chain_error::NoSuchBlockError)?;
+        if header_info.block_height == 0 {
+            // the boot block has no transactions
+            return Ok(());
+        }
+
+        // the confirmed microblocks were mined before the block that confirmed them
+        if let Some(microblocks) = chainstate.load_confirmed_microblock_stream(&index_block_hash)? {
+            for microblock in microblocks.iter() {
+                self.push_results(
+                    &microblock.txs,
+                    &index_block_hash,
+                    header_info.block_height,
+                    Some(&microblock.block_hash()),
+                );
+            }
+        }
+        let block =
+            StacksChainState::load_block_indexed(&chainstate.blocks_path, &index_block_hash)?
+                .ok_or(chain_error::NoSuchBlockError)?;
+        self.push_results(
+            &block.txs,
+            &index_block_hash,
+            header_info.block_height,
+            None,
+        );
+
+        self.blocks_scanned += 1;
+        self.cursor = StacksChainState::get_parent_block_id(chainstate.db(), &index_block_hash)?;
+        Ok(())
+    }
+
+    /// Scan up to `max_blocks` more blocks.
+    pub fn run(&mut self, chainstate: &StacksChainState, max_blocks: u64, max_results: u64) {
+        for _ in 0..max_blocks {
+            if self.status != QueryJobStatus::Running {
+                return;
+            }
+            if let Err(e) = self.scan_block(chainstate) {
+                warn!("Query job {} failed: {:?}", self.id, &e);
+                self.finish(QueryJobStatus::Failed, Some(format!("{:?}", &e)));
+                return;
+            }
+            if self.results.len() as u64 >= max_results {
+                self.results.truncate(max_results as usize);
+                self.truncated = true;
+                self.finish(QueryJobStatus::Done, None);
+            } else if self.cursor.is_none() {
+                self.finish(QueryJobStatus::Done, None);
+            }
+        }
+    }
+
+    /// The job's progress, along with the page of its results that comes after the page's
+    /// cursor (the offset of the next result).
+    pub fn to_rpc(&self, page: &RPCPageRequest, max_page_size: u32) -> Result<RPCQueryJob, String> {
+        let start = match page.cursor {
+            Some(ref cursor) => match cursor.parse::<usize>() {
+                Ok(start) if start <= self.results.len() => start,
+                _ => return Err(format!("Invalid cursor '{}'", cursor)),
+            },
+            None => 0,
+        };
+        let page_size = page.page_size(max_page_size);
+        let items = self
+            .results
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(page_size as usize + 1)
+            .map(|(offset, result)| (offset, result.clone()))
+            .collect();
+        let results = RPCPage::from_items(items, page_size, |(offset, _)| (offset + 1).to_string())
+            .map(|(_, result)| result);
+
+        Ok(RPCQueryJob {
+            id: self.id,
+            kind: self.kind.name().to_string(),
+            target: self.kind.target(),
+            status: self.status,
+            error: self.error.clone(),
+            tip: self.tip.clone(),
+            tip_height: self.tip_height,
+            blocks_scanned: self.blocks_scanned,
+            num_results: self.results.len() as u64,
+            truncated: self.truncated,
+            created_at: self.created_at,
+            finished_at: self.finished_at,
+            results,
+        })
+    }
+}
+
+/// The query jobs of a node, started over the RPC interface and run by the p2p thread.  Finished
+/// jobs are kept for `query_job_ttl` seconds so their results can be paged through.
+#[derive(Debug, Clone, Default)]
+pub struct QueryJobs {
+    jobs: HashMap<u64, QueryJob>,
+    next_id: u64,
+}
+
+impl QueryJobs {
+    pub fn new() -> QueryJobs {
+        QueryJobs {
+            jobs: HashMap::new(),
+            next_id: 1,
+        }
+    }
+
+    pub fn get(&self, id: u64) -> Option<&QueryJob> {
+        self.jobs.get(&id)
+    }
+
+    pub fn num_running(&self) -> u64 {
+        self.jobs
+            .values()
+            .filter(|job| job.status == QueryJobStatus::Running)
+            .count() as u64
+    }
+
+    /// Start a job that scans back from `tip`, unless `max_query_jobs` are already running.
+    /// Returns the new job's ID.
+    pub fn start(
+        &mut self,
+        kind: QueryJobKind,
+        tip: StacksBlockId,
+        tip_height: u64,
+        options: &ConnectionOptions,
+    ) -> Option<u64> {
+        if self.num_running() >= options.max_query_jobs {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        debug!(
+            "Start query job {}: {} of {} as of {}",
+            id,
+            kind.name(),
+            kind.target(),
+            &tip
+        );
+        self.jobs
+            .insert(id, QueryJob::new(id, kind, tip, tip_height));
+        Some(id)
+    }
+
+    /// Advance every running job, and forget finished jobs that have expired.
+    pub fn run(&mut self, chainstate: &StacksChainState, options: &ConnectionOptions) {
+        let now = get_epoch_time_secs();
+        self.jobs.retain(|_, job| match job.finished_at {
+            Some(finished_at) => finished_at + options.query_job_ttl >= now,
+            None => true,
+        });
+        for job in self.jobs.values_mut() {
+            job.run(
+                chainstate,
+                options.query_job_blocks_per_pass,
+                options.max_query_job_results,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chainstate::stacks::*;
+    use util::hash::Hash160;
+
+    #[test]
+    fn test_query_job_kind_matches() {
+        let privk = StacksPrivateKey::from_hex(
+            "6d430bb91222408e7706c9001cfaeb91b08c2be6d5ac95779ab52c6b431950e001",
+        )
+        .unwrap();
+        let auth = TransactionAuth::from_p2pkh(&privk).unwrap();
+        let origin = auth.origin().address_testnet();
+        let other = StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        };
+
+        let transfer = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::TokenTransfer(
+                PrincipalData::from(other.clone()),
+                123,
+                TokenTransferMemo([0u8; 34]),
+            ),
+        );
+        assert!(QueryJobKind::AddressHistory(origin.clone()).matches(&transfer));
+        assert!(QueryJobKind::AddressHistory(other.clone()).matches(&transfer));
+
+        let deploy = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::new_smart_contract("hello-world", "(define-data-var x int 0)")
+                .unwrap(),
+        );
+        let call = StacksTransaction::new(
+            TransactionVersion::Testnet,
+            auth.clone(),
+            TransactionPayload::new_contract_call(origin.clone(), "hello-world", "get-x", vec![])
+                .unwrap(),
+        );
+        let contract_id = match call.payload {
+            TransactionPayload::ContractCall(ref contract_call) => {
+                contract_call.to_clarity_contract_id()
+            }
+            _ => unreachable!(),
+        };
+        assert!(!QueryJobKind::AddressHistory(other.clone()).matches(&deploy));
+        assert!(QueryJobKind::ContractHistory(contract_id.clone()).matches(&deploy));
+        assert!(QueryJobKind::ContractHistory(contract_id.clone()).matches(&call));
+        assert!(!QueryJobKind::ContractHistory(contract_id).matches(&transfer));
+    }
+
+    #[test]
+    fn test_query_job_pages() {
+        let mut job = QueryJob::new(
+            1,
+            QueryJobKind::AddressHistory(StacksAddress {
+                version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+                bytes: Hash160([0x11; 20]),
+            }),
+            StacksBlockId([0x01; 32]),
+            5,
+        );
+        for i in 0..5 {
+            job.results.push(RPCQueryJobResult {
+                txid: format!("{:064x}", i),
+                index_block_hash: StacksBlockId([0x01; 32]),
+                block_height: 5,
+                microblock_hash: None,
+            });
+        }
+
+        let first = job
+            .to_rpc(
+                &RPCPageRequest {
+                    cursor: None,
+                    limit: Some(2),
+                },
+                1000,
+            )
+            .unwrap();
+        assert_eq!(first.status, QueryJobStatus::Running);
+        assert_eq!(first.num_results, 5);
+        assert_eq!(first.results.items, job.results[0..2].to_vec());
+        assert_eq!(first.results.next_cursor, Some("2".to_string()));
+
+        let last = job
+            .to_rpc(
+                &RPCPageRequest {
+                    cursor: first.results.next_cursor.clone(),
+                    limit: Some(3),
+                },
+                1000,
+            )
+            .unwrap();
+        assert_eq!(last.results.items, job.results[2..5].to_vec());
+        assert_eq!(last.results.next_cursor, None);
+
+        // the page size is capped
+        let capped = job.to_rpc(&RPCPageRequest::default(), 4).unwrap();
+        assert_eq!(capped.results.items.len(), 4);
+
+        for bad_cursor in ["6", "-1", "abc"].iter() {
+            assert!(job
+                .to_rpc(
+                    &RPCPageRequest {
+                        cursor: Some(bad_cursor.to_string()),
+                        limit: None,
+                    },
+                    1000,
+                )
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_query_jobs_limit() {
+        let mut options = ConnectionOptions::default();
+        options.max_query_jobs = 2;
+        let kind = QueryJobKind::AddressHistory(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0x11; 20]),
+        });
+
+        let mut jobs = QueryJobs::new();
+        assert_eq!(
+            jobs.start(kind.clone(), StacksBlockId([0x01; 32]), 1, &options),
+            Some(1)
+        );
+        assert_eq!(
+            jobs.start(kind.clone(), StacksBlockId([0x01; 32]), 1, &options),
+            Some(2)
+        );
+        assert_eq!(
+            jobs.start(kind.clone(), StacksBlockId([0x01; 32]), 1, &options),
+            None
+        );
+
+        // finished jobs don't count towards the limit
+        jobs.jobs
+            .get_mut(&1)
+            .unwrap()
+            .finish(QueryJobStatus::Done, None);
+        assert_eq!(jobs.num_running(), 1);
+        assert_eq!(
+            jobs.start(kind, StacksBlockId([0x01; 32]), 1, &options),
+            Some(3)
+        );
+        assert!(jobs.get(1).is_some());
+    }
+}
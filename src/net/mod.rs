@@ -43,13 +43,14 @@ use serde::{Deserialize, Serialize};
 use serde_json;
 use url;
 
-use burnchains::Txid;
+use burnchains::{Address, Txid};
 use chainstate::burn::ConsensusHash;
 use chainstate::stacks::db::blocks::MemPoolRejection;
 use chainstate::stacks::index::Error as marf_error;
 use chainstate::stacks::Error as chainstate_error;
 use chainstate::stacks::{
     Error as chain_error, StacksBlock, StacksMicroblock, StacksPublicKey, StacksTransaction,
+    MAX_BLOCK_LEN,
 };
 use clarity_vm::clarity::Error as clarity_error;
 use codec::Error as codec_error;
@@ -57,6 +58,7 @@ use codec::StacksMessageCodec;
 use core::mempool::*;
 use core::POX_REWARD_CYCLE_LENGTH;
 use net::atlas::{Attachment, AttachmentInstance};
+use net::jobs::{QueryJobKind, QueryJobStatus};
 use util::db::DBConn;
 use util::db::Error as db_error;
 use util::get_epoch_time_secs;
@@ -69,7 +71,8 @@ use util::secp256k1::MessageSignature;
 use util::secp256k1::Secp256k1PublicKey;
 use util::secp256k1::MESSAGE_SIGNATURE_ENCODED_SIZE;
 use util::strings::UrlString;
-use vm::types::TraitIdentifier;
+use vm::costs::ExecutionCost;
+use vm::types::{QualifiedContractIdentifier, TraitIdentifier};
 use vm::{
     analysis::contract_interface_builder::ContractInterface, types::PrincipalData, ClarityName,
     ContractName, Value,
@@ -78,23 +81,32 @@ use vm::{
 use crate::codec::BURNCHAIN_HEADER_HASH_ENCODED_SIZE;
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::PoxId;
+use crate::types::chainstate::SortitionId;
 use crate::types::chainstate::{BurnchainHeaderHash, StacksAddress, StacksBlockId};
+use crate::types::chainstate::{StacksBlockHeader, StacksMicroblockHeader};
+use crate::types::proof::TrieHash;
 use crate::types::StacksPublicKeyBuffer;
 use crate::util::hash::Sha256Sum;
+use crate::util::hash::Sha512Trunc256Sum;
 
 use self::dns::*;
 pub use self::http::StacksHttp;
 
 pub mod asn;
+#[cfg(feature = "async_net")]
+pub mod asyncio;
 pub mod atlas;
 pub mod chat;
 pub mod codec;
+pub mod conformance;
 pub mod connection;
 pub mod db;
 pub mod dns;
 pub mod download;
+pub mod features;
 pub mod http;
 pub mod inv;
+pub mod jobs;
 pub mod neighbors;
 pub mod p2p;
 pub mod poll;
@@ -102,6 +114,7 @@ pub mod prune;
 pub mod relay;
 pub mod rpc;
 pub mod server;
+pub mod tls;
 
 #[derive(Debug)]
 pub enum Error {
@@ -207,6 +220,8 @@ pub enum Error {
     ConnectionCycle,
     /// Requested data not found
     NotFoundError,
+    /// TLS could not be configured, or a TLS session with a peer failed
+    TlsError(String),
 }
 
 impl From<codec_error> for Error {
@@ -223,6 +238,184 @@ impl From<codec_error> for Error {
     }
 }
 
+/// Who has to act for a network error to go away
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFault {
+    /// The remote peer (or HTTP client) sent malformed data or violated the protocol
+    Peer,
+    /// This node failed -- storage, configuration, a local resource limit, or a bug
+    Local,
+    /// Nobody in particular -- the network was slow, unavailable, or hung up
+    Network,
+}
+
+impl ErrorFault {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorFault::Peer => "peer",
+            ErrorFault::Local => "local",
+            ErrorFault::Network => "network",
+        }
+    }
+}
+
+impl Error {
+    /// Classify who is responsible for this error.
+    pub fn fault(&self) -> ErrorFault {
+        match *self {
+            Error::SerializeError(_) => ErrorFault::Local,
+            Error::ReadError(_) => ErrorFault::Network,
+            Error::DeserializeError(_) => ErrorFault::Peer,
+            Error::WriteError(_) => ErrorFault::Network,
+            Error::UnderflowError(_) => ErrorFault::Peer,
+            Error::OverflowError(_) => ErrorFault::Peer,
+            Error::WrongProtocolFamily => ErrorFault::Local,
+            Error::ArrayTooLong => ErrorFault::Peer,
+            Error::RecvTimeout => ErrorFault::Network,
+            Error::SigningError(_) => ErrorFault::Local,
+            Error::VerifyingError(_) => ErrorFault::Peer,
+            Error::TemporarilyDrained => ErrorFault::Network,
+            Error::PermanentlyDrained => ErrorFault::Network,
+            Error::FilesystemError => ErrorFault::Local,
+            Error::DBError(_) => ErrorFault::Local,
+            Error::SocketMutexPoisoned => ErrorFault::Local,
+            Error::SocketNotConnectedToPeer => ErrorFault::Network,
+            Error::ConnectionBroken => ErrorFault::Network,
+            Error::ConnectionError => ErrorFault::Network,
+            Error::OutboxOverflow => ErrorFault::Local,
+            Error::InboxOverflow => ErrorFault::Peer,
+            Error::SendError(_) => ErrorFault::Network,
+            Error::RecvError(_) => ErrorFault::Network,
+            Error::InvalidMessage => ErrorFault::Peer,
+            Error::InvalidHandle => ErrorFault::Local,
+            Error::FullHandle => ErrorFault::Local,
+            Error::InvalidHandshake => ErrorFault::Peer,
+            Error::StaleNeighbor => ErrorFault::Peer,
+            Error::NoSuchNeighbor => ErrorFault::Local,
+            Error::BindError => ErrorFault::Local,
+            Error::PollError => ErrorFault::Local,
+            Error::AcceptError => ErrorFault::Local,
+            Error::RegisterError => ErrorFault::Local,
+            Error::SocketError => ErrorFault::Network,
+            Error::NotConnected => ErrorFault::Local,
+            Error::PeerNotConnected => ErrorFault::Network,
+            Error::TooManyPeers => ErrorFault::Local,
+            Error::AlreadyConnected(..) => ErrorFault::Local,
+            Error::InProgress => ErrorFault::Local,
+            Error::Denied => ErrorFault::Peer,
+            Error::NoDataUrl => ErrorFault::Peer,
+            Error::PeerThrottled => ErrorFault::Peer,
+            Error::LookupError(_) => ErrorFault::Network,
+            Error::MARFError(_) => ErrorFault::Local,
+            Error::ClarityError(_) => ErrorFault::Local,
+            Error::ChainstateError(_) => ErrorFault::Local,
+            Error::ClientError(_) => ErrorFault::Peer,
+            Error::CoordinatorClosed => ErrorFault::Local,
+            Error::StaleView => ErrorFault::Local,
+            Error::ConnectionCycle => ErrorFault::Local,
+            Error::TlsError(_) => ErrorFault::Peer,
+            Error::NotFoundError => ErrorFault::Local,
+        }
+    }
+
+    /// Can the operation that failed succeed if it is simply tried again later (possibly over
+    /// a new connection)?  Protocol violations and local failures are not retryable.
+    pub fn is_retryable(&self) -> bool {
+        match *self {
+            Error::ReadError(_)
+            | Error::WriteError(_)
+            | Error::RecvTimeout
+            | Error::TemporarilyDrained
+            | Error::PermanentlyDrained
+            | Error::SocketNotConnectedToPeer
+            | Error::ConnectionBroken
+            | Error::ConnectionError
+            | Error::OutboxOverflow
+            | Error::InboxOverflow
+            | Error::SendError(_)
+            | Error::RecvError(_)
+            | Error::FullHandle
+            | Error::PollError
+            | Error::AcceptError
+            | Error::SocketError
+            | Error::PeerNotConnected
+            | Error::TooManyPeers
+            | Error::InProgress
+            | Error::PeerThrottled
+            | Error::LookupError(_)
+            | Error::StaleView
+            | Error::NotFoundError => true,
+            Error::DBError(db_error::SqliteError(rusqlite::Error::SqliteFailure(ref e, _))) => {
+                e.code == rusqlite::ErrorCode::DatabaseBusy
+                    || e.code == rusqlite::ErrorCode::DatabaseLocked
+            }
+            _ => false,
+        }
+    }
+
+    /// Did the remote peer violate the protocol?  Peers that do so should be disconnected
+    /// rather than retried.
+    pub fn is_protocol_violation(&self) -> bool {
+        self.fault() == ErrorFault::Peer && !self.is_retryable()
+    }
+
+    /// Attach the peer and message type this error concerns, for reporting.
+    pub fn with_context(
+        self,
+        peer: Option<NeighborKey>,
+        message_type: Option<&'static str>,
+    ) -> ContextError {
+        ContextError {
+            error: self,
+            peer,
+            message_type,
+        }
+    }
+}
+
+/// A network error along with the peer and message type it concerns
+#[derive(Debug)]
+pub struct ContextError {
+    pub error: Error,
+    pub peer: Option<NeighborKey>,
+    pub message_type: Option<&'static str>,
+}
+
+impl ContextError {
+    pub fn fault(&self) -> ErrorFault {
+        self.error.fault()
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        self.error.is_retryable()
+    }
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (fault: {}, retryable: {}",
+            &self.error,
+            self.fault().as_str(),
+            self.is_retryable()
+        )?;
+        if let Some(ref peer) = self.peer {
+            write!(f, ", peer: {}", peer)?;
+        }
+        if let Some(message_type) = self.message_type {
+            write!(f, ", message: {}", message_type)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl error::Error for ContextError {
+    fn cause(&self) -> Option<&dyn error::Error> {
+        Some(&self.error)
+    }
+}
+
 /// Enum for passing data for ClientErrors
 #[derive(Debug, Clone, PartialEq)]
 pub enum ClientError {
@@ -230,6 +423,8 @@ pub enum ClientError {
     Message(String),
     /// 404
     NotFound(String),
+    /// 413: the request body is longer than the given number of bytes allowed on its route
+    PayloadTooLarge(u32),
 }
 
 impl error::Error for ClientError {
@@ -243,6 +438,9 @@ impl fmt::Display for ClientError {
         match self {
             ClientError::Message(s) => write!(f, "{}", s),
             ClientError::NotFound(s) => write!(f, "HTTP path not matched: {}", s),
+            ClientError::PayloadTooLarge(limit) => {
+                write!(f, "HTTP request body is larger than {} bytes", limit)
+            }
         }
     }
 }
@@ -302,6 +500,7 @@ impl fmt::Display for Error {
             Error::CoordinatorClosed => write!(f, "Coordinator hung up"),
             Error::StaleView => write!(f, "State view is stale"),
             Error::ConnectionCycle => write!(f, "Tried to connect to myself"),
+            Error::TlsError(ref s) => fmt::Display::fmt(s, f),
             Error::NotFoundError => write!(f, "Requested data not found"),
         }
     }
@@ -360,6 +559,7 @@ impl error::Error for Error {
             Error::CoordinatorClosed => None,
             Error::StaleView => None,
             Error::ConnectionCycle => None,
+            Error::TlsError(ref _s) => None,
             Error::NotFoundError => None,
         }
     }
@@ -560,6 +760,15 @@ impl PeerAddress {
         ])
     }
 
+    /// The network this address belongs to, for telling apart peers that are likely run by
+    /// different operators: its /16 prefix if it's an IPv4 address, and its /32 prefix otherwise.
+    pub fn network_prefix(&self) -> [u8; 4] {
+        match self.ipv4_octets() {
+            Some(octets) => [octets[0], octets[1], 0, 0],
+            None => [self.0[0], self.0[1], self.0[2], self.0[3]],
+        }
+    }
+
     /// Is this the any-network address?  i.e. 0.0.0.0 (v4) or :: (v6)?
     pub fn is_anynet(&self) -> bool {
         self.0 == [0x00; 16] || self == &PeerAddress::from_ipv4(0, 0, 0, 0)
@@ -662,7 +871,7 @@ pub struct Preamble {
     pub burn_block_hash: BurnchainHeaderHash, // hash of the last-seen burn block
     pub burn_stable_block_height: u64, // latest stable block height (e.g. chain tip minus 7)
     pub burn_stable_block_hash: BurnchainHeaderHash, // latest stable burnchain header hash.
-    pub additional_data: u32, // RESERVED; pointer to additional data (should be all 0's if not used)
+    pub additional_data: u32, // PREAMBLE_FLAG_* bits; the rest are RESERVED (should be all 0's if not used)
     pub signature: MessageSignature, // signature from the peer that sent this
     pub payload_len: u32,     // length of the following payload, including relayers vector
 }
@@ -684,6 +893,24 @@ pub struct BlocksInvData {
     pub microblocks_bitvec: Vec<u8>, // bitmap of which confirmed micrblocks the peer has, in sortition order.  microblocks_bitvec[i] & (1 << j) != 0 means that this peer has the microblocks produced by sortition 8*i + j
 }
 
+/// Request for one page of a block inventory over a range of sortitions that can span several
+/// reward cycles.  The range starts at the beginning of the reward cycle identified by
+/// consensus_hash; the requester walks it by sending back each reply's next_cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GetBlocksInvPage {
+    pub consensus_hash: ConsensusHash, // consensus hash at the start of the range's first reward cycle
+    pub num_blocks: u32,               // number of sortitions in the whole range
+    pub cursor: u32,                   // offset into the range at which this page begins
+}
+
+/// One page of a block inventory.  Sent in reply to a GetBlocksInvPage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlocksInvPageData {
+    pub cursor: u32,      // offset into the requested range of the first bit in this page
+    pub next_cursor: u32, // offset at which the next page begins, or 0 if this is the last page
+    pub inv: BlocksInvData,
+}
+
 /// Request for a PoX bitvector range.
 /// Requests bits for [start_reward_cycle, start_reward_cycle + num_anchor_blocks)
 #[derive(Debug, Clone, PartialEq)]
@@ -705,6 +932,19 @@ pub struct BlocksData {
     pub blocks: Vec<(ConsensusHash, StacksBlock)>,
 }
 
+/// A pushed block, minus the transactions its recipient most likely already has in its mempool.
+/// `txids` lists all of the block's transactions in order, and `txs` carries in full only the
+/// ones the recipient can't have (i.e. the coinbase).  A recipient that can't find the rest in its
+/// mempool drops the message and downloads the whole block instead.  Only pushed to peers that
+/// advertise ServiceFlags::COMPACT_BLOCKS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactBlockData {
+    pub consensus_hash: ConsensusHash,
+    pub header: StacksBlockHeader,
+    pub txids: Vec<Txid>,
+    pub txs: Vec<StacksTransaction>,
+}
+
 /// Microblocks pushed
 #[derive(Debug, Clone, PartialEq)]
 pub struct MicroblocksData {
@@ -786,10 +1026,136 @@ pub struct HandshakeData {
     pub data_url: UrlString,
 }
 
-#[repr(u8)]
+/// Registry of the capability bits a node can advertise in `HandshakeData::services`.
+/// Each bit is assigned once and never reused.  A node only relies on a peer for a capability if
+/// the peer advertised its bit, so new behaviors are gated on these bits rather than on peer
+/// versions.
+///
+/// | Bit    | Flag            | Meaning                                                          |
+/// |--------|-----------------|------------------------------------------------------------------|
+/// | 0x0001 | `RELAY`         | forwards pushed blocks, microblocks, and transactions            |
+/// | 0x0002 | `RPC`           | serves the HTTP RPC interface at its data URL                    |
+/// | 0x0004 | `MESSAGE_LIMIT` | honors a `MessageLimit` (always set by current nodes)            |
+/// | 0x0008 | `ARCHIVAL`      | keeps and serves every historic block and microblock stream      |
+/// | 0x0010 | `ATLAS`         | serves Atlas attachments and inventories; accepts `AttachmentsAvailable` |
+/// | 0x0020 | `MEMPOOL_SYNC`  | reserved: answers mempool synchronization queries                |
+/// | 0x0040 | `TIP_SUBSCRIBE` | reserved: pushes chain tip updates to subscribed peers           |
+/// | 0x0080 | `URL_HEALTH`    | accepts `DataUrlHealth` reports (always set by current nodes)    |
+/// | 0x0100 | `COMPACT_BLOCKS`| accepts pushed blocks as `CompactBlock`s                         |
+/// | 0x0200 | `ATTACHMENTS_BATCH` | serves several attachments per request at `/v2/attachments`  |
+/// | 0x0400 | `COMPRESSION`   | accepts LZ4-compressed `BlocksData` and `MicroblocksData`        |
+/// | 0x0800 | `PAGED_BLOCKS_INV` | answers `GetBlocksInvPage`                                    |
+/// | 0x1000 | `MICROBLOCK_PUSH` | pushes the microblocks it mines to peers that sent `MicroblocksSubscribe` |
+/// | 0x2000 | `TLS`           | switches the connection to TLS after the handshake if the peer sets it too |
+/// | 0x4000 | `ATTACHMENT_RANGES` | serves byte ranges of attachments at `/v2/attachments/:hash/content` |
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u16)]
 pub enum ServiceFlags {
-    RELAY = 0x01,
-    RPC = 0x02,
+    RELAY = 0x0001,
+    RPC = 0x0002,
+    MESSAGE_LIMIT = 0x0004,
+    ARCHIVAL = 0x0008,
+    ATLAS = 0x0010,
+    MEMPOOL_SYNC = 0x0020,
+    TIP_SUBSCRIBE = 0x0040,
+    URL_HEALTH = 0x0080,
+    COMPACT_BLOCKS = 0x0100,
+    ATTACHMENTS_BATCH = 0x0200,
+    COMPRESSION = 0x0400,
+    PAGED_BLOCKS_INV = 0x0800,
+    MICROBLOCK_PUSH = 0x1000,
+    TLS = 0x2000,
+    ATTACHMENT_RANGES = 0x4000,
+}
+
+/// Every registered service flag, in bit order
+pub const SERVICE_FLAGS_REGISTRY: &[ServiceFlags] = &[
+    ServiceFlags::RELAY,
+    ServiceFlags::RPC,
+    ServiceFlags::MESSAGE_LIMIT,
+    ServiceFlags::ARCHIVAL,
+    ServiceFlags::ATLAS,
+    ServiceFlags::MEMPOOL_SYNC,
+    ServiceFlags::TIP_SUBSCRIBE,
+    ServiceFlags::URL_HEALTH,
+    ServiceFlags::COMPACT_BLOCKS,
+    ServiceFlags::ATTACHMENTS_BATCH,
+    ServiceFlags::COMPRESSION,
+    ServiceFlags::PAGED_BLOCKS_INV,
+    ServiceFlags::MICROBLOCK_PUSH,
+    ServiceFlags::TLS,
+    ServiceFlags::ATTACHMENT_RANGES,
+];
+
+impl ServiceFlags {
+    pub fn bit(&self) -> u16 {
+        *self as u16
+    }
+
+    /// Name of this flag, as used in logs and in the node config file
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ServiceFlags::RELAY => "relay",
+            ServiceFlags::RPC => "rpc",
+            ServiceFlags::MESSAGE_LIMIT => "message-limit",
+            ServiceFlags::ARCHIVAL => "archival",
+            ServiceFlags::ATLAS => "atlas",
+            ServiceFlags::MEMPOOL_SYNC => "mempool-sync",
+            ServiceFlags::TIP_SUBSCRIBE => "tip-subscribe",
+            ServiceFlags::URL_HEALTH => "url-health",
+            ServiceFlags::COMPACT_BLOCKS => "compact-blocks",
+            ServiceFlags::ATTACHMENTS_BATCH => "attachments-batch",
+            ServiceFlags::COMPRESSION => "compression",
+            ServiceFlags::PAGED_BLOCKS_INV => "paged-blocks-inv",
+            ServiceFlags::MICROBLOCK_PUSH => "microblock-push",
+            ServiceFlags::TLS => "tls",
+            ServiceFlags::ATTACHMENT_RANGES => "attachment-ranges",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ServiceFlags> {
+        SERVICE_FLAGS_REGISTRY
+            .iter()
+            .find(|flag| flag.name() == name)
+            .cloned()
+    }
+
+    /// Is this flag set in the given services bitfield?
+    pub fn is_set(&self, services: u16) -> bool {
+        (services & self.bit()) != 0
+    }
+
+    /// Set this flag in the given services bitfield
+    pub fn set(&self, services: u16) -> u16 {
+        services | self.bit()
+    }
+
+    /// Clear this flag in the given services bitfield
+    pub fn clear(&self, services: u16) -> u16 {
+        services & !self.bit()
+    }
+
+    /// Build a services bitfield from a list of flags
+    pub fn to_services(flags: &[ServiceFlags]) -> u16 {
+        flags.iter().fold(0, |services, flag| flag.set(services))
+    }
+
+    /// Names of the registered flags set in the given services bitfield.  Unregistered bits are
+    /// listed as hex, so that bits from newer nodes still show up in logs.
+    pub fn names(services: u16) -> Vec<String> {
+        let mut names = vec![];
+        let mut unknown = services;
+        for flag in SERVICE_FLAGS_REGISTRY.iter() {
+            if flag.is_set(services) {
+                names.push(flag.name().to_string());
+                unknown = flag.clear(unknown);
+            }
+        }
+        if unknown != 0 {
+            names.push(format!("0x{:04x}", unknown));
+        }
+        names
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -827,6 +1193,41 @@ pub struct NatPunchData {
     pub nonce: u32,
 }
 
+/// Sent after a completed handshake to tell the remote peer the largest message payload this
+/// peer is willing to accept from it.  Only sent to peers that advertise
+/// ServiceFlags::MESSAGE_LIMIT.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MessageLimitData {
+    pub max_payload_len: u32,
+}
+
+/// Sent periodically to tell the remote peer which data URLs this peer has recently failed to
+/// reach, so it can try other hosts first.  Only lists URLs this peer tried itself, never ones it
+/// was told about.  Only sent to peers that advertise ServiceFlags::URL_HEALTH.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataUrlHealthData {
+    pub unreachable: Vec<UrlString>,
+}
+
+/// Announces attachments that have just become available, so that peers still looking for them
+/// retry their downloads right away instead of waiting out their backoff.  Each entry is the
+/// index block hash of the block that announced the attachment, and its content hash.  A node
+/// sends one for the attachments it stores, and forwards the ones it receives for attachments it
+/// is missing too, once each.  Only sent to peers that advertise ServiceFlags::ATLAS.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentsAvailableData {
+    pub available: Vec<(StacksBlockId, Hash160)>,
+}
+
+/// Asks the remote peer to push us the microblocks it mines as soon as it mines them
+/// (`subscribe` is true), or to stop doing so (`subscribe` is false).  Pushed microblocks arrive
+/// as ordinary `MicroblocksData` messages.  A subscription lasts until it is withdrawn or the
+/// connection closes.  Only sent to peers that advertise ServiceFlags::MICROBLOCK_PUSH.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MicroblocksSubscribeData {
+    pub subscribe: bool,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RelayData {
     pub peer: NeighborAddress,
@@ -855,6 +1256,13 @@ pub enum StacksMessageType {
     Pong(PongData),
     NatPunchRequest(u32),
     NatPunchReply(NatPunchData),
+    MessageLimit(MessageLimitData),
+    DataUrlHealth(DataUrlHealthData),
+    CompactBlock(CompactBlockData),
+    AttachmentsAvailable(AttachmentsAvailableData),
+    GetBlocksInvPage(GetBlocksInvPage),
+    BlocksInvPage(BlocksInvPageData),
+    MicroblocksSubscribe(MicroblocksSubscribeData),
 }
 
 /// Peer address variants
@@ -956,6 +1364,25 @@ impl PeerHost {
     }
 }
 
+/// One of the node's optional capabilities, as returned on GET /v2/features
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNodeFeature {
+    pub name: String,
+    /// "build" or "config"
+    pub source: String,
+    /// can this build of the node do this at all?
+    pub available: bool,
+    pub enabled: bool,
+    /// the service bit that advertises this feature to peers, if any
+    pub service_bit: Option<u16>,
+}
+
+/// The data we return on GET /v2/features
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNodeFeatures {
+    pub features: Vec<RPCNodeFeature>,
+}
+
 /// The data we return on GET /v2/info
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RPCPeerInfoData {
@@ -1020,6 +1447,22 @@ pub struct RPCPoxInfoData {
     pub next_reward_cycle_in: u64,
 }
 
+/// The data we return on GET /v2/state_hash/{height}.
+/// `state_hash` commits to the MARF root, the header chain work, and the sortition of the
+/// canonical Stacks block at `stacks_block_height`, so two nodes that agree on consensus will
+/// report the same value for the same height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCStateHashData {
+    pub stacks_block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub state_index_root: TrieHash,
+    pub total_burn: u64,
+    pub total_work: u64,
+    pub sortition_id: SortitionId,
+    pub state_hash: Sha512Trunc256Sum,
+}
+
 #[derive(Debug, Clone, PartialEq, Copy, Hash)]
 #[repr(u8)]
 pub enum HttpVersion {
@@ -1027,11 +1470,83 @@ pub enum HttpVersion {
     Http11 = 0x11,
 }
 
+/// Versions of the RPC interface, identified by the route prefix.  Every route served under
+/// `/v2` is also served under `/v3` by the same handler; a handler only needs to look at the
+/// version if its response schema changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HttpApiVersion {
+    V2,
+    V3,
+}
+
+impl HttpApiVersion {
+    pub const ALL: &'static [HttpApiVersion] = &[HttpApiVersion::V2, HttpApiVersion::V3];
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            HttpApiVersion::V2 => "/v2",
+            HttpApiVersion::V3 => "/v3",
+        }
+    }
+
+    /// Which version a request path addresses, if any
+    pub fn from_path(path: &str) -> Option<HttpApiVersion> {
+        HttpApiVersion::ALL
+            .iter()
+            .find(|version| {
+                let prefix = version.prefix();
+                path.starts_with(prefix)
+                    && (path.len() == prefix.len()
+                        || path[prefix.len()..].starts_with('/')
+                        || path[prefix.len()..].starts_with('?'))
+            })
+            .cloned()
+    }
+
+    /// Rewrite a path under any known version prefix so it is under this version's prefix.
+    /// Paths without a version prefix are returned as-is.
+    pub fn rewrite_path(&self, path: &str) -> String {
+        match HttpApiVersion::from_path(path) {
+            Some(version) => format!("{}{}", self.prefix(), &path[version.prefix().len()..]),
+            None => path.to_string(),
+        }
+    }
+}
+
+/// A single byte range from an HTTP `Range` header (RFC 7233).  Range ends are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpByteRange {
+    /// `bytes=start-end`
+    Span(u64, u64),
+    /// `bytes=start-`: from `start` to the end
+    From(u64),
+    /// `bytes=-len`: the last `len` bytes
+    Suffix(u64),
+}
+
+/// Deprecation notice for a versioned route, sent back to the client as the `Deprecation`,
+/// `Link` (with `rel="successor-version"`) and `Sunset` headers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpApiDeprecation {
+    /// Path of the route that replaces the deprecated one
+    pub successor: String,
+    /// Unix time after which the deprecated route may stop being served
+    pub sunset: Option<u64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Hash)]
 pub struct HttpRequestMetadata {
     pub version: HttpVersion,
     pub peer: PeerHost,
     pub keep_alive: bool,
+    /// RPC interface version, from the request path's prefix
+    pub api_version: HttpApiVersion,
+    /// Seconds the client is willing to wait, from `X-Request-Timeout`.  It can only shorten
+    /// the node's own `rpc_request_timeout`.
+    pub request_timeout: Option<u64>,
+    /// Client-chosen key from `Idempotency-Key`, which lets a transaction submission be
+    /// retried without being mistaken for a duplicate.
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -1058,6 +1573,101 @@ pub struct GetIsTraitImplementedResponse {
     pub is_implemented: bool,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct VerifyContractSrcRequestBody {
+    pub source: String,
+}
+
+/// Attestation returned by POST /v2/contracts/verify.  Hashes are taken over the canonicalized
+/// source text (see `ConversationHttp::canonicalize_contract_src`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VerifyContractSrcResponse {
+    pub contract_id: String,
+    pub tip: StacksBlockId,
+    pub publish_height: u32,
+    pub source_hash: Sha512Trunc256Sum,
+    pub deployed_source_hash: Sha512Trunc256Sum,
+    pub source_match: bool,
+    pub ast_match: bool,
+    pub analysis_ok: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cause: Option<String>,
+}
+
+/// A contract's share of the execution cost spent in a range of blocks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCContractCostEntry {
+    pub contract_id: String,
+    pub num_txs: u64,
+    pub cost: ExecutionCost,
+    /// percentage of the blocks' combined budget consumed, in the dimension most of it went to
+    pub budget_pct: u64,
+}
+
+/// Returned by GET /v2/contracts/costs.  The range is widened to whole buckets of
+/// `CONTRACT_COST_STATS_BUCKET_SIZE` blocks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCContractCostsData {
+    pub enabled: bool,
+    pub from_height: u64,
+    pub to_height: u64,
+    pub contracts: Vec<RPCContractCostEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ValidateContractCallRequestBody {
+    pub arguments: Vec<String>,
+}
+
+/// A problem found with a contract call by POST /v2/contracts/validate-call.  `index`, `name` and
+/// `expected_type` identify the offending argument, and are omitted for errors about the call as
+/// a whole (e.g. an unknown function, or the wrong number of arguments).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractCallArgumentError {
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_type: Option<String>,
+    pub message: String,
+}
+
+/// Returned by POST /v2/contracts/validate-call.  If the call is valid, `arguments` holds the
+/// arguments re-encoded in the canonical form a contract-call transaction carries them in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidateContractCallResponse {
+    pub valid: bool,
+    pub contract_id: String,
+    pub function_name: String,
+    pub arguments: Vec<String>,
+    pub errors: Vec<ContractCallArgumentError>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct EncodeClarityValueRequestBody {
+    pub value: Value,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DecodeClarityValueRequestBody {
+    pub hex: String,
+}
+
+/// A Clarity value as returned by POST /v2/clarity/value/encode and /v2/clarity/value/decode.
+/// `hex` is the value's consensus serialization -- the bytes `to-consensus-buff?` produces --
+/// and `value` is the same value in the JSON form accepted by the encode endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClarityValueResponse {
+    pub hex: String,
+    pub repr: String,
+    pub value: Value,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CallReadOnlyResponse {
     pub okay: bool,
@@ -1083,6 +1693,64 @@ pub struct AccountEntryResponse {
     pub nonce_proof: Option<String>,
 }
 
+/// A genesis STX vesting entry for an account.  `amount` is hex-encoded uSTX, and unlocks once
+/// the Stacks chain reaches `block_height`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VestingUnlockEntry {
+    pub block_height: u64,
+    pub amount: String,
+    pub unlocked: bool,
+}
+
+/// An account's locked STX and unlock schedule as of a chain tip.  `locked` and `unlock_height`
+/// describe the account's PoX lock, which unlocks at a burnchain block height; `vesting` lists
+/// its genesis vesting entries, which unlock at Stacks block heights.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountUnlocksResponse {
+    pub locked: String,
+    pub unlock_height: u64,
+    pub vesting_locked: String,
+    pub stacks_tip_height: u64,
+    pub burn_block_height: u64,
+    pub vesting: Vec<VestingUnlockEntry>,
+}
+
+/// A block reward earned by an address, either as the block's miner or as a user burn
+/// supporter.  Amounts are hex-encoded uSTX.  The reward is credited once the Stacks chain
+/// reaches `maturity_height`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinerRewardEntry {
+    pub stacks_block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub miner: bool,
+    pub coinbase: String,
+    pub tx_fees_anchored: String,
+    pub tx_fees_streamed_confirmed: String,
+    pub maturity_height: u64,
+    pub matured: bool,
+}
+
+/// A coinbase reward era: blocks mined at burnchain heights `start_burn_height` through
+/// `end_burn_height` (inclusive) earn `coinbase` uSTX, hex-encoded.  The last era has no end.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoinbaseScheduleEntry {
+    pub start_burn_height: u64,
+    pub end_burn_height: Option<u64>,
+    pub coinbase: String,
+}
+
+/// An address's block rewards in a fork, as of a chain tip.  `matured` and `pending` are the
+/// hex-encoded totals of the matured and not-yet-matured rewards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MinerRewardsResponse {
+    pub stacks_tip_height: u64,
+    pub maturity: u64,
+    pub matured: String,
+    pub pending: String,
+    pub rewards: Vec<MinerRewardEntry>,
+    pub reward_schedule: Vec<CoinbaseScheduleEntry>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum UnconfirmedTransactionStatus {
     Microblock {
@@ -1090,18 +1758,665 @@ pub enum UnconfirmedTransactionStatus {
         seq: u16,
     },
     Mempool,
+    /// Only found in processed anchored blocks (or the microblocks they confirm)
+    Confirmed,
+}
+
+/// A processed Stacks block that includes a transaction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCTransactionFork {
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub block_hash: BlockHeaderHash,
+    pub block_height: u64,
+    /// whether or not the block is on the canonical Stacks fork
+    pub canonical: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnconfirmedTransactionResponse {
     pub tx: String,
     pub status: UnconfirmedTransactionStatus,
+    /// every processed block that includes the transaction, across all forks
+    #[serde(default)]
+    pub forks: Vec<RPCTransactionFork>,
+}
+
+/// Evidence that a transaction was confirmed from a microblock stream, as returned on
+/// GET /v2/microblocks/proof/{txid}.  A client can check it without trusting the node:
+/// * the microblock headers form a chain from the parent block's hash to the confirming block's
+/// `parent_microblock` and `parent_microblock_sequence`,
+/// * each microblock header is signed by the key whose hash is the parent block's
+/// `microblock_pubkey_hash`,
+/// * and the transaction's microblock commits to `microblock_txids` in its `tx_merkle_root`.
+///
+/// Headers are hex-encoded in their consensus serialization.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMicroblockProof {
+    pub txid: String,
+    /// the anchored block that confirmed the microblock stream
+    pub index_block_hash: StacksBlockId,
+    pub consensus_hash: ConsensusHash,
+    pub block_height: u64,
+    /// whether or not that block is on the canonical Stacks fork
+    pub canonical: bool,
+    pub block_header: String,
+    /// the anchored block that produced the microblock stream
+    pub parent_consensus_hash: ConsensusHash,
+    pub parent_block_header: String,
+    /// every microblock the block confirmed, in sequence order
+    pub microblock_headers: Vec<String>,
+    /// sequence number of the microblock that contains the transaction
+    pub microblock_sequence: u16,
+    /// the IDs of the transactions in that microblock, in order
+    pub microblock_txids: Vec<String>,
+}
+
+/// A mempool drop log entry, as returned on GET /v2/mempool/dropped/{txid}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMemPoolDropData {
+    pub txid: String,
+    pub reason: String,
+    pub drop_time: u64,
+    pub tx_fee: u64,
+    pub block_height: u64,
+    pub origin_address: String,
+    pub origin_nonce: u64,
+    pub sponsor_address: String,
+    pub sponsor_nonce: u64,
+    pub replaced_by: Option<String>,
+}
+
+/// A pending transaction that an account originates or sponsors, as returned on
+/// GET /v2/addresses/{principal}/mempool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPendingTransaction {
+    pub txid: String,
+    pub origin_address: String,
+    pub origin_nonce: u64,
+    pub sponsor_address: String,
+    pub sponsor_nonce: u64,
+    pub tx_fee: u64,
+    pub tx_len: u64,
+    /// when the mempool admitted the transaction, in seconds since the epoch
+    pub accept_time: u64,
+    pub block_height: u64,
+    /// transactions with the same origin or sponsor nonce that lost to this one -- replaced by it
+    /// (or by a transaction it replaced), or rejected while it was pending.  See
+    /// GET /v2/mempool/dropped/{txid} for each one's details.
+    #[serde(default)]
+    pub conflicts: Vec<String>,
+}
+
+/// The outcome of POST /v2/admin/mempool/import.  Each rejection is reported in the same form as
+/// a rejected POST /v2/transactions.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMemPoolImportData {
+    pub accepted: u64,
+    pub already_present: u64,
+    pub rejected: Vec<serde_json::Value>,
+}
+
+/// Percentiles of mempool transaction fee rates, in microSTX per byte
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCFeeRatePercentiles {
+    pub p10: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p90: f64,
+}
+
+/// The lowest fee rate (in microSTX per byte) paid by a transaction included in a recent block.
+/// `min_fee_rate` is None if the block had no transactions besides its coinbase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCFeeRateThreshold {
+    pub stacks_block_height: u64,
+    pub index_block_hash: StacksBlockId,
+    pub num_txs: u64,
+    pub min_fee_rate: Option<f64>,
+}
+
+/// Fee market statistics, as returned on GET /v2/fees/mempool.  `depth_in_blocks` is the number
+/// of blocks needed to mine everything in the mempool, going by transaction size and estimated
+/// execution cost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMemPoolFeeData {
+    pub stacks_tip_height: u64,
+    pub num_txs: u64,
+    pub total_bytes: u64,
+    pub fee_rates: Option<RPCFeeRatePercentiles>,
+    pub depth_in_blocks: u64,
+    pub recent_inclusion_thresholds: Vec<RPCFeeRateThreshold>,
+}
+
+/// A transaction the miner would mine next, in block order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCSimulatedTransaction {
+    pub txid: String,
+    pub origin_address: String,
+    pub origin_nonce: u64,
+    pub tx_fee: u64,
+}
+
+/// The block the miner would assemble from the mempool on top of the current Stacks chain tip,
+/// as returned on GET /v2/mempool/simulate_block.  `budget_pct` is the share of the block
+/// budget it would use, in whichever dimension it uses the most of.  The coinbase is left out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockSimulationData {
+    pub stacks_tip_height: u64,
+    pub stacks_tip: StacksBlockId,
+    pub num_txs: u64,
+    pub total_bytes: u64,
+    pub total_fees: u64,
+    pub cost: ExecutionCost,
+    pub budget_pct: u64,
+    pub transactions: Vec<RPCSimulatedTransaction>,
+}
+
+/// Delivery status of an event observer.  `pending_events` have not yet been delivered; the
+/// oldest of them was produced at `oldest_pending_at`, and will next be attempted at
+/// `next_attempt_at`.  `delivered` and `failed_attempts` count since the node started.
+/// Timestamps are in seconds since the epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCEventObserverStatus {
+    pub endpoint: String,
+    pub pending_events: u64,
+    pub oldest_pending_at: Option<u64>,
+    pub next_attempt_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub delivered: u64,
+    pub failed_attempts: u64,
+    pub last_delivered_at: Option<u64>,
+}
+
+/// Source of the event observers' delivery status, served over the admin RPC.
+pub trait EventObserverStatusSource {
+    fn get_event_observer_status(&self) -> Vec<RPCEventObserverStatus>;
+}
+
+/// Body of POST /v2/admin/events/replay: replay the canonical Stacks chain's blocks from
+/// `from_height` through `to_height` (the chain tip if unset) to the configured event observer at
+/// `endpoint`, at no more than `blocks_per_second` blocks a second (`DEFAULT_EVENT_REPLAY_RATE`
+/// if unset).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventReplayRequestBody {
+    pub endpoint: String,
+    pub from_height: u64,
+    #[serde(default)]
+    pub to_height: Option<u64>,
+    #[serde(default)]
+    pub blocks_per_second: Option<u64>,
+}
+
+pub const DEFAULT_EVENT_REPLAY_RATE: u64 = 10;
+pub const MAX_EVENT_REPLAY_RATE: u64 = 1000;
+
+impl EventReplayRequestBody {
+    /// Validate the request, returning the replay rate to use.
+    pub fn validate(&self) -> Result<u64, String> {
+        if self.from_height == 0 {
+            return Err("Replay must start at height 1 or later".to_string());
+        }
+        if let Some(to_height) = self.to_height {
+            if to_height < self.from_height {
+                return Err(format!(
+                    "Invalid height range {}-{}",
+                    self.from_height, to_height
+                ));
+            }
+        }
+        match self.blocks_per_second.unwrap_or(DEFAULT_EVENT_REPLAY_RATE) {
+            0 => Err("blocks_per_second must be positive".to_string()),
+            rate if rate > MAX_EVENT_REPLAY_RATE => Err(format!(
+                "blocks_per_second must be at most {}",
+                MAX_EVENT_REPLAY_RATE
+            )),
+            rate => Ok(rate),
+        }
+    }
+}
+
+/// Progress of a replay of past block events to an event observer.  `next_height` is the next
+/// block to be replayed; the replay is over once `finished_at` is set, and failed if `error`
+/// is set.  Timestamps are in seconds since the epoch.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCEventReplay {
+    pub endpoint: String,
+    pub from_height: u64,
+    pub to_height: u64,
+    pub next_height: u64,
+    pub blocks_per_second: u64,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Replays past block events to event observers, re-executing the blocks from chainstate, on
+/// behalf of the admin RPC.
+pub trait EventReplaySource {
+    /// Start a replay, returning an error message if it can't be started.
+    fn start_event_replay(&self, request: &EventReplayRequestBody) -> Result<(), String>;
+    /// All replays started since the node started, in the order they were started.
+    fn get_event_replays(&self) -> Vec<RPCEventReplay>;
+}
+
+/// Pauses and resumes the chains coordinator's processing of blocks, on behalf of the admin RPC.
+/// Blocks keep being downloaded and stored while processing is paused.
+pub trait BlockProcessingControl {
+    fn pause_block_processing(&self);
+    fn resume_block_processing(&self);
+    /// Whether a pause was asked for, and whether the coordinator has actually stopped
+    fn get_block_processing_state(&self) -> (bool, bool);
+}
+
+/// A PeerDB allow or deny rule: either a CIDR prefix (as a prefix address and a mask length in
+/// bits of its IPv6 form), or the Hash160 of a peer's public key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PeerFilterRule {
+    Cidr(PeerAddress, u32),
+    PubkeyHash(Hash160),
+}
+
+impl PeerFilterRule {
+    /// Parse a CIDR prefix like "10.0.0.0/8" or "2001:db8::/32".  A bare address matches only
+    /// itself.
+    pub fn from_cidr_str(s: &str) -> Option<PeerFilterRule> {
+        let mut parts = s.splitn(2, '/');
+        let ip = parts.next()?.parse::<IpAddr>().ok()?;
+        let max_mask = match ip {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let mask = match parts.next() {
+            Some(mask_str) => mask_str.parse::<u32>().ok()?,
+            None => max_mask,
+        };
+        if mask == 0 || mask > max_mask {
+            return None;
+        }
+        // IPv4 addresses are stored as IPv4-mapped IPv6 addresses
+        let mask = mask + (128 - max_mask);
+        Some(PeerFilterRule::Cidr(PeerAddress::from_ip(&ip), mask))
+    }
+
+    pub fn from_pubkey_hash_str(s: &str) -> Option<PeerFilterRule> {
+        Hash160::from_hex(s).ok().map(PeerFilterRule::PubkeyHash)
+    }
+}
+
+impl fmt::Display for PeerFilterRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PeerFilterRule::Cidr(prefix, mask) => {
+                if prefix.is_ipv4() && *mask > 96 {
+                    write!(f, "{}/{}", prefix.to_socketaddr(0).ip(), mask - 96)
+                } else {
+                    write!(f, "{}/{}", Ipv6Addr::from(prefix.0), mask)
+                }
+            }
+            PeerFilterRule::PubkeyHash(pubkey_hash) => write!(f, "{}", pubkey_hash),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeerFilterList {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PeerFilterAction {
+    Add,
+    Remove,
+}
+
+/// Body of POST /v2/admin/peers/filters.  Exactly one of `cidr` and `pubkey_hash` is given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerFilterUpdateRequestBody {
+    pub action: String,
+    pub list: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cidr: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pubkey_hash: Option<String>,
+}
+
+impl PeerFilterUpdateRequestBody {
+    pub fn new(
+        action: PeerFilterAction,
+        list: PeerFilterList,
+        rule: &PeerFilterRule,
+    ) -> PeerFilterUpdateRequestBody {
+        let (cidr, pubkey_hash) = match rule {
+            PeerFilterRule::Cidr(..) => (Some(rule.to_string()), None),
+            PeerFilterRule::PubkeyHash(..) => (None, Some(rule.to_string())),
+        };
+        PeerFilterUpdateRequestBody {
+            action: match action {
+                PeerFilterAction::Add => "add".to_string(),
+                PeerFilterAction::Remove => "remove".to_string(),
+            },
+            list: match list {
+                PeerFilterList::Allow => "allow".to_string(),
+                PeerFilterList::Deny => "deny".to_string(),
+            },
+            cidr,
+            pubkey_hash,
+        }
+    }
+
+    /// Validate the update, returning what to do to which list.
+    pub fn to_update(&self) -> Result<(PeerFilterAction, PeerFilterList, PeerFilterRule), String> {
+        let action = match self.action.as_str() {
+            "add" => PeerFilterAction::Add,
+            "remove" => PeerFilterAction::Remove,
+            _ => return Err(format!("Invalid action '{}'", &self.action)),
+        };
+        let list = match self.list.as_str() {
+            "allow" => PeerFilterList::Allow,
+            "deny" => PeerFilterList::Deny,
+            _ => return Err(format!("Invalid list '{}'", &self.list)),
+        };
+        let rule = match (&self.cidr, &self.pubkey_hash) {
+            (Some(cidr), None) => PeerFilterRule::from_cidr_str(cidr)
+                .ok_or_else(|| format!("Invalid CIDR prefix '{}'", cidr))?,
+            (None, Some(pubkey_hash)) => PeerFilterRule::from_pubkey_hash_str(pubkey_hash)
+                .ok_or_else(|| format!("Invalid public key hash '{}'", pubkey_hash))?,
+            _ => return Err("Expected exactly one of 'cidr' and 'pubkey_hash'".to_string()),
+        };
+        Ok((action, list, rule))
+    }
+}
+
+/// The rules of one of the PeerDB's allow or deny lists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerFilterList {
+    pub cidrs: Vec<String>,
+    pub pubkey_hashes: Vec<String>,
+}
+
+/// The PeerDB's allow and deny lists, as returned on GET and POST /v2/admin/peers/filters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerFilters {
+    pub allow: RPCPeerFilterList,
+    pub deny: RPCPeerFilterList,
+}
+
+/// Body of POST /v2/admin/atlas/contracts.  Limits that are left out default to the node's
+/// `[atlas]` defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AtlasContractRegistrationRequestBody {
+    pub contract_id: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attachments_max_size: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unresolved_attachment_instances_expire_after: Option<u32>,
+}
+
+impl AtlasContractRegistrationRequestBody {
+    /// Validate the registration, returning the contract to track and any limits it overrides.
+    pub fn to_registration(
+        &self,
+    ) -> Result<(QualifiedContractIdentifier, Option<u32>, Option<u32>), String> {
+        let contract_id = QualifiedContractIdentifier::parse(&self.contract_id)
+            .map_err(|_| format!("Invalid contract identifier '{}'", &self.contract_id))?;
+        if self.attachments_max_size == Some(0) {
+            return Err("attachments_max_size must be positive".to_string());
+        }
+        Ok((
+            contract_id,
+            self.attachments_max_size,
+            self.unresolved_attachment_instances_expire_after,
+        ))
+    }
+}
+
+/// Body of POST /v2/jobs.  `address` is given for an `address-history` job, and `contract_id` for
+/// a `contract-history` job.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryJobRequestBody {
+    pub kind: String,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contract_id: Option<String>,
+}
+
+impl QueryJobRequestBody {
+    pub fn new(kind: &QueryJobKind) -> QueryJobRequestBody {
+        let (address, contract_id) = match *kind {
+            QueryJobKind::AddressHistory(ref address) => (Some(address.to_string()), None),
+            QueryJobKind::ContractHistory(ref contract_id) => (None, Some(contract_id.to_string())),
+        };
+        QueryJobRequestBody {
+            kind: kind.name().to_string(),
+            address,
+            contract_id,
+        }
+    }
+
+    /// Validate the request, returning the job to start.
+    pub fn to_query(&self) -> Result<QueryJobKind, String> {
+        match self.kind.as_str() {
+            "address-history" => {
+                let address = self
+                    .address
+                    .as_ref()
+                    .ok_or_else(|| "address-history jobs need an address".to_string())?;
+                let address = StacksAddress::from_string(address)
+                    .ok_or_else(|| format!("Invalid address '{}'", address))?;
+                Ok(QueryJobKind::AddressHistory(address))
+            }
+            "contract-history" => {
+                let contract_id = self
+                    .contract_id
+                    .as_ref()
+                    .ok_or_else(|| "contract-history jobs need a contract_id".to_string())?;
+                let contract_id = QualifiedContractIdentifier::parse(contract_id)
+                    .map_err(|_| format!("Invalid contract identifier '{}'", contract_id))?;
+                Ok(QueryJobKind::ContractHistory(contract_id))
+            }
+            _ => Err(format!("Invalid job kind '{}'", &self.kind)),
+        }
+    }
+}
+
+/// A transaction found by a query job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCQueryJobResult {
+    /// hex-encoded txid
+    pub txid: String,
+    /// the anchored block that mined or confirmed the transaction
+    pub index_block_hash: StacksBlockId,
+    pub block_height: u64,
+    /// the microblock the transaction was mined in, if it was not in the anchored block
+    pub microblock_hash: Option<BlockHeaderHash>,
+}
+
+/// A query job's progress and a page of its results, as returned on POST /v2/jobs and GET
+/// /v2/jobs/{id}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCQueryJob {
+    pub id: u64,
+    pub kind: String,
+    /// the address or contract the job is about
+    pub target: String,
+    pub status: QueryJobStatus,
+    pub error: Option<String>,
+    /// the chain tip the job scans back from
+    pub tip: StacksBlockId,
+    pub tip_height: u64,
+    pub blocks_scanned: u64,
+    pub num_results: u64,
+    /// stopped early, at the node's `max_query_job_results`
+    pub truncated: bool,
+    pub created_at: u64,
+    pub finished_at: Option<u64>,
+    pub results: RPCPage<RPCQueryJobResult>,
+}
+
+/// A contract whose attachments the node tracks, and the limits that apply to them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAtlasContract {
+    pub contract_id: String,
+    pub attachments_max_size: u32,
+    pub unresolved_attachment_instances_expire_after: u32,
+    /// registered through POST /v2/admin/atlas/contracts, rather than configured at startup
+    pub runtime: bool,
+}
+
+/// The node's Atlas watch list, as returned on GET and POST /v2/admin/atlas/contracts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAtlasContracts {
+    pub contracts: Vec<RPCAtlasContract>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AttachmentPinAction {
+    Pin,
+    Unpin,
+}
+
+/// Body of POST /v2/admin/atlas/pins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttachmentPinRequestBody {
+    pub action: String,
+    pub hash: String,
+}
+
+impl AttachmentPinRequestBody {
+    pub fn new(action: AttachmentPinAction, content_hash: &Hash160) -> AttachmentPinRequestBody {
+        AttachmentPinRequestBody {
+            action: match action {
+                AttachmentPinAction::Pin => "pin".to_string(),
+                AttachmentPinAction::Unpin => "unpin".to_string(),
+            },
+            hash: content_hash.to_hex(),
+        }
+    }
+
+    /// Validate the update, returning what to do to which attachment.
+    pub fn to_update(&self) -> Result<(AttachmentPinAction, Hash160), String> {
+        let action = match self.action.as_str() {
+            "pin" => AttachmentPinAction::Pin,
+            "unpin" => AttachmentPinAction::Unpin,
+            _ => return Err(format!("Invalid action '{}'", &self.action)),
+        };
+        let content_hash = Hash160::from_hex(&self.hash)
+            .map_err(|_| format!("Invalid attachment hash '{}'", &self.hash))?;
+        Ok((action, content_hash))
+    }
+}
+
+/// The attachments that the Atlas retention policy keeps no matter what, as returned on GET and
+/// POST /v2/admin/atlas/pins
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAttachmentPins {
+    pub hashes: Vec<String>,
+    /// bytes of attachment content stored
+    pub stored_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockProcessingAction {
+    Pause,
+    Resume,
+}
+
+/// Body of POST /v2/admin/blocks/processing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockProcessingRequestBody {
+    pub action: String,
+}
+
+impl BlockProcessingRequestBody {
+    pub fn new(action: BlockProcessingAction) -> BlockProcessingRequestBody {
+        BlockProcessingRequestBody {
+            action: match action {
+                BlockProcessingAction::Pause => "pause".to_string(),
+                BlockProcessingAction::Resume => "resume".to_string(),
+            },
+        }
+    }
+
+    pub fn to_action(&self) -> Result<BlockProcessingAction, String> {
+        match self.action.as_str() {
+            "pause" => Ok(BlockProcessingAction::Pause),
+            "resume" => Ok(BlockProcessingAction::Resume),
+            _ => Err(format!("Invalid action '{}'", &self.action)),
+        }
+    }
+}
+
+/// Whether the node is processing blocks, as returned on GET and POST
+/// /v2/admin/blocks/processing.  Once `paused` is set, neither the chainstate nor the sortition DB
+/// change -- the tip below stays put -- until processing is resumed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockProcessingStatus {
+    pub pause_requested: bool,
+    /// the coordinator has finished what it was processing when the pause was asked for
+    pub paused: bool,
+    pub burn_block_height: u64,
+    pub stacks_tip_height: u64,
+    pub stacks_tip: BlockHeaderHash,
+    pub stacks_tip_consensus_hash: ConsensusHash,
+}
+
+/// Progress of an operator-requested block fetch
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BlockFetchStatus {
+    /// waiting for the block downloader's next pass
+    Queued,
+    /// requested from the peers in `data_urls`
+    InFlight,
+    /// downloaded and handed off for processing
+    Fetched,
+    /// already stored locally, so nothing was fetched
+    Stored,
+    /// no canonical sortition selected this block, or no peer advertises it
+    Unavailable,
+    /// every peer we asked failed to serve it (or served an invalid microblock stream)
+    Failed,
+}
+
+/// An operator-requested block fetch, as returned on GET and POST /v2/blocks/fetch/{index_block_hash}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBlockFetchData {
+    pub index_block_hash: StacksBlockId,
+    /// fetch the microblock stream confirmed by this block, instead of the block itself
+    pub microblocks: bool,
+    pub status: BlockFetchStatus,
+    pub sortition_height: Option<u64>,
+    pub data_urls: Vec<String>,
+    pub requested_at: u64,
+    pub updated_at: u64,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct PostTransactionRequestBody {
     pub tx: String,
     pub attachment: Option<String>,
+    /// highest block height the transaction may be mined at (mempool policy only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expire_height: Option<u64>,
+}
+
+/// Some or all of an attachment's content, as served by `GET /v2/attachments/:hash/content`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AttachmentContentRange {
+    /// Offset of `content` in the attachment
+    pub start: u64,
+    /// Length of the whole attachment
+    pub total_len: u64,
+    pub content: Vec<u8>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -1126,6 +2441,14 @@ impl<'de> Deserialize<'de> for GetAttachmentResponse {
     }
 }
 
+/// Attachments served by `GET /v2/attachments?hashes=...`.  Requested attachments that the node
+/// does not have (or that did not fit in the response) are left out; each entry is identified by
+/// the hash of its content.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GetAttachmentsBatchResponse {
+    pub attachments: Vec<GetAttachmentResponse>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GetAttachmentsInvResponse {
     pub block_id: StacksBlockId,
@@ -1150,6 +2473,9 @@ impl HttpRequestMetadata {
             version: HttpVersion::Http11,
             peer: PeerHost::from_host_port(host, port),
             keep_alive: true,
+            api_version: HttpApiVersion::V2,
+            request_timeout: None,
+            idempotency_key: None,
         }
     }
 
@@ -1158,6 +2484,9 @@ impl HttpRequestMetadata {
             version: HttpVersion::Http11,
             peer: peer_host,
             keep_alive: true,
+            api_version: HttpApiVersion::V2,
+            request_timeout: None,
+            idempotency_key: None,
         }
     }
 
@@ -1166,10 +2495,26 @@ impl HttpRequestMetadata {
             version: preamble.version,
             peer: preamble.host.clone(),
             keep_alive: preamble.keep_alive,
+            api_version: HttpApiVersion::from_path(&preamble.path).unwrap_or(HttpApiVersion::V2),
+            request_timeout: preamble
+                .headers
+                .get("x-request-timeout")
+                .and_then(|timeout| timeout.parse::<u64>().ok()),
+            idempotency_key: preamble.headers.get("idempotency-key").cloned(),
         }
     }
 }
 
+/// Historic block at which to evaluate a read-only function call, as with `(at-block ...)`.
+/// The block must be an ancestor of (or equal to) the chain tip the call is made against.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AtBlock {
+    /// The ancestor block at this Stacks block height
+    Height(u64),
+    /// The ancestor block with this index block hash
+    BlockId(StacksBlockId),
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CallReadOnlyRequestBody {
     pub sender: String,
@@ -1209,18 +2554,322 @@ pub struct RPCNeighborsInfo {
     pub outbound: Vec<RPCNeighbor>,
 }
 
+/// How fresh, reachable and diverse the frontier is.  Each ratio is between 0 and 1, and `score`
+/// (0 to 100) is their weighted sum.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCFrontierQuality {
+    pub score: u64,
+    /// fraction of frontier peers whose keys have not expired and that are not denied
+    pub fresh_ratio: f64,
+    /// fraction of frontier peers contacted within the last day
+    pub recent_contact_ratio: f64,
+    /// distinct networks over the number of frontier peers, where a network is an ASN, or a /16
+    /// (IPv4) or /32 (IPv6) prefix for peers with no known ASN
+    pub network_diversity: f64,
+    /// moving average of the fraction of peers the neighbor walk could reach
+    pub reachable_ratio: f64,
+}
+
+/// Number of peers advertising a peer version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerVersionCount {
+    pub peer_version: u32,
+    pub count: u64,
+}
+
+/// Struct given back from a call to `/v2/neighbors/versions`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerVersionsInfo {
+    /// our own peer version
+    pub peer_version: u32,
+    /// peer versions of connected peers
+    pub connected: Vec<RPCPeerVersionCount>,
+    /// peer versions of connected peers and unexpired frontier peers
+    pub observed: Vec<RPCPeerVersionCount>,
+    /// percentage of observed peers advertising a newer peer version than ours
+    pub newer_pct: u64,
+    pub newest_peer_version: Option<u32>,
+}
+
+/// Struct given back from a call to `/v2/neighbors/walk`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNeighborWalkInfo {
+    /// number of completed neighbor walk passes
+    pub walks: u64,
+    /// number of times the walk restarted from a new random neighbor
+    pub resets: u64,
+    pub last_walk_duration_ms: Option<u64>,
+    pub last_walk_time: Option<u64>,
+    pub frontier_size: u64,
+    /// frontier peers added and replaced by the walk since the node started
+    pub churn_added: u64,
+    pub churn_replaced: u64,
+    pub unreachable_ratio: f64,
+    pub num_inbound: u64,
+    pub num_outbound: u64,
+    pub frontier_quality: RPCFrontierQuality,
+    /// one of "healthy", "degraded_frontier" or "local_connectivity"
+    pub assessment: String,
+    pub handshake_concurrency: RPCHandshakeConcurrency,
+}
+
+/// How many outbound handshakes the neighbor walk has in flight at once, and how far it has
+/// backed off because the event loop was saturated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCHandshakeConcurrency {
+    /// current cap on handshakes in flight
+    pub limit: u64,
+    pub min_limit: u64,
+    pub max_limit: u64,
+    /// how long the last pass of the event loop took
+    pub last_pass_ms: u64,
+    /// number of times the cap was cut since the node started
+    pub backoffs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCBurnchainStatus {
+    /// height of the highest burnchain block we have processed
+    pub burn_block_height: u64,
+    pub stable_burn_block_height: u64,
+    /// height of the burnchain's tip, if the node has learned it yet
+    pub burnchain_tip_height: Option<u64>,
+    /// number of burnchain blocks we have yet to process
+    pub lag: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCStacksStatus {
+    pub tip_height: u64,
+    pub tip: StacksBlockId,
+    /// height of the burnchain block that selected the tip
+    pub tip_burn_block_height: u64,
+    /// number of burnchain blocks processed since the tip was selected
+    pub lag: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPeerCounts {
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMemPoolStatus {
+    pub num_txs: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCAtlasStatus {
+    /// attachment instances whose attachment has not been downloaded yet
+    pub unresolved_attachment_instances: u64,
+    /// attachments received ahead of the transaction that instantiates them
+    pub uninstantiated_attachments: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMiningStatus {
+    /// is this node configured to mine?
+    pub miner: bool,
+    /// is the node still in its initial block download?  Miners don't mine until it's over.
+    pub initial_block_download: bool,
+}
+
+/// The most recent error reported by one of the node's subsystems.  `at` is in seconds since the
+/// epoch, if known.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCSubsystemError {
+    pub subsystem: String,
+    pub message: String,
+    pub at: Option<u64>,
+}
+
+/// Struct given back from a call to `/v2/status`.  `mining` is only set if the node reports
+/// it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNodeStatus {
+    pub burnchain: RPCBurnchainStatus,
+    pub stacks: RPCStacksStatus,
+    pub peers: RPCPeerCounts,
+    pub mempool: RPCMemPoolStatus,
+    pub atlas: RPCAtlasStatus,
+    pub mining: Option<RPCMiningStatus>,
+    pub last_errors: Vec<RPCSubsystemError>,
+}
+
+/// Maximum length of the `Idempotency-Key` header a client can send with a transaction
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// Maximum length of the challenge a client can ask a node to sign in `/v2/node_identity`
+pub const MAX_NODE_IDENTITY_CHALLENGE_LEN: usize = 64;
+
+/// Prefix of the message a node signs in `/v2/node_identity`, so the signature can't be passed off
+/// as any other message signed with the node's p2p key
+pub const NODE_IDENTITY_SIGNATURE_DOMAIN: &[u8] = b"stacks-node-identity";
+
+/// Struct given back from a call to `/v2/node_identity`: the node's p2p identity, signed with its
+/// p2p private key so that a client can tell that the HTTP endpoint it asked and the p2p node with
+/// `public_key` are the same node.  `challenge` is the hex string the client passed, if any, and is
+/// covered by the signature so that the document can't be replayed by another endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCNodeIdentity {
+    pub public_key: StacksPublicKeyBuffer,
+    pub public_key_hash: Hash160,
+    pub services: u16,
+    pub network_id: u32,
+    pub parent_network_id: u32,
+    /// burn block height after which the key will be replaced
+    pub expire_block_height: u64,
+    pub data_url: UrlString,
+    pub challenge: Option<String>,
+    pub signature: MessageSignature,
+}
+
+/// Source of the node's own state for `/v2/status`, for the parts the peer network can't see.
+/// It is read on the p2p thread while serving requests, so it must not block.
+pub trait NodeStatusSource {
+    /// Height of the burnchain's tip, as last seen by the node's burnchain client
+    fn get_burnchain_tip_height(&self) -> Option<u64>;
+    fn get_mining_status(&self) -> RPCMiningStatus;
+    /// The most recent error from each subsystem that has reported one
+    fn get_last_errors(&self) -> Vec<RPCSubsystemError>;
+}
+
+/// One of the microblock streams seen for an anchored block.  Streams that fork off of one
+/// another share the microblocks before `fork_seq`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMicroblockStream {
+    pub tip: BlockHeaderHash,
+    pub min_seq: u16,
+    pub max_seq: u16,
+    pub num_microblocks: u64,
+    pub fork_seq: Option<u16>,
+    /// hex-encoded hash of the public key that signed the stream
+    pub signer: Option<String>,
+    pub first_seen: Option<u64>,
+    pub tip_first_seen: Option<u64>,
+    /// how many of the stream's microblocks were confirmed by a child anchored block
+    pub num_confirmed: u64,
+    pub orphaned: bool,
+}
+
+/// Struct given back from a call to `/v2/microblocks/forks`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCMicroblockForksInfo {
+    pub index_block_hash: StacksBlockId,
+    /// true if more than one stream was seen
+    pub forked: bool,
+    pub streams: Vec<RPCMicroblockStream>,
+}
+
+/// Cursor and page size given to a paginated list endpoint, as `?cursor=...&limit=...`.  The
+/// cursor is opaque to clients: it is the `next_cursor` of the previous page, and is left out to
+/// get the first page.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RPCPageRequest {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+}
+
+/// One page of the results of a paginated list endpoint.  `next_cursor` is `None` on the last
+/// page.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCPage<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// A transaction in a decoded block or microblock, along with the fields a client would otherwise
+/// have to derive from its consensus bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCDecodedTransaction {
+    /// hex-encoded txid
+    pub txid: String,
+    pub tx_len: u64,
+    /// c32-encoded address of the origin account
+    pub origin: String,
+    /// c32-encoded address of the sponsor account, if the transaction is sponsored
+    pub sponsor: Option<String>,
+    pub tx: StacksTransaction,
+}
+
+/// Struct given back from a call to `/v2/blocks/{id}?decode=true`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCDecodedBlock {
+    pub index_block_hash: StacksBlockId,
+    pub block_hash: BlockHeaderHash,
+    pub header: StacksBlockHeader,
+    pub txs: Vec<RPCDecodedTransaction>,
+}
+
+/// One microblock of the list given back from a call to `/v2/microblocks/{id}?decode=true` or
+/// `/v2/microblocks/confirmed/{id}?decode=true`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RPCDecodedMicroblock {
+    pub block_hash: BlockHeaderHash,
+    pub header: StacksMicroblockHeader,
+    pub txs: Vec<RPCDecodedTransaction>,
+}
+
 /// All HTTP request paths we support, and the arguments they carry in their paths
 #[derive(Debug, Clone, PartialEq)]
 pub enum HttpRequestType {
     GetInfo(HttpRequestMetadata),
+    GetFeatures(HttpRequestMetadata),
     GetPoxInfo(HttpRequestMetadata, Option<StacksBlockId>),
+    GetStateHash(HttpRequestMetadata, u64, Option<StacksBlockId>),
     GetNeighbors(HttpRequestMetadata),
+    GetNeighborWalkInfo(HttpRequestMetadata),
+    GetPeerVersions(HttpRequestMetadata),
+    GetNodeStatus(HttpRequestMetadata),
+    GetNodeIdentity(HttpRequestMetadata, Option<Vec<u8>>),
     GetBlock(HttpRequestMetadata, StacksBlockId),
+    GetBlockDecoded(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksIndexed(HttpRequestMetadata, StacksBlockId),
+    GetMicroblocksIndexedDecoded(HttpRequestMetadata, StacksBlockId),
     GetMicroblocksConfirmed(HttpRequestMetadata, StacksBlockId),
+    GetMicroblocksConfirmedDecoded(HttpRequestMetadata, StacksBlockId),
+    GetMicroblockForks(HttpRequestMetadata, Option<StacksBlockId>),
     GetMicroblocksUnconfirmed(HttpRequestMetadata, StacksBlockId, u16),
     GetTransactionUnconfirmed(HttpRequestMetadata, Txid),
-    PostTransaction(HttpRequestMetadata, StacksTransaction, Option<Attachment>),
+    GetMemPoolDrops(HttpRequestMetadata, Txid),
+    GetMicroblockProof(HttpRequestMetadata, Txid),
+    GetAddressMempool(HttpRequestMetadata, StacksAddress, RPCPageRequest),
+    GetMemPoolFees(HttpRequestMetadata),
+    SimulateBlock(HttpRequestMetadata),
+    GetEventObserverStatus(HttpRequestMetadata),
+    GetEventReplays(HttpRequestMetadata),
+    PostEventReplay(HttpRequestMetadata, EventReplayRequestBody),
+    GetPeerFilters(HttpRequestMetadata),
+    GetAtlasContracts(HttpRequestMetadata),
+    GetAttachmentPins(HttpRequestMetadata),
+    PostAttachmentPin(HttpRequestMetadata, AttachmentPinAction, Hash160),
+    PostAtlasContract(
+        HttpRequestMetadata,
+        QualifiedContractIdentifier,
+        Option<u32>,
+        Option<u32>,
+    ),
+    GetBlockProcessing(HttpRequestMetadata),
+    PostBlockProcessing(HttpRequestMetadata, BlockProcessingAction),
+    GetBlockFetch(HttpRequestMetadata, StacksBlockId, bool),
+    PostBlockFetch(HttpRequestMetadata, StacksBlockId, bool),
+    GetQueryJob(HttpRequestMetadata, u64, RPCPageRequest),
+    PostQueryJob(HttpRequestMetadata, QueryJobKind),
+    PostPeerFilter(
+        HttpRequestMetadata,
+        PeerFilterAction,
+        PeerFilterList,
+        PeerFilterRule,
+    ),
+    GetMemPoolSnapshot(HttpRequestMetadata, RPCPageRequest),
+    PostMemPoolSnapshot(HttpRequestMetadata, Vec<StacksTransaction>),
+    PostTransaction(
+        HttpRequestMetadata,
+        StacksTransaction,
+        Option<Attachment>,
+        Option<u64>,
+    ),
     PostBlock(HttpRequestMetadata, ConsensusHash, StacksBlock),
     PostMicroblock(HttpRequestMetadata, StacksMicroblock, Option<StacksBlockId>),
     GetAccount(
@@ -1229,6 +2878,8 @@ pub enum HttpRequestType {
         Option<StacksBlockId>,
         bool,
     ),
+    GetAccountUnlocks(HttpRequestMetadata, PrincipalData, Option<StacksBlockId>),
+    GetMinerRewards(HttpRequestMetadata, StacksAddress, Option<StacksBlockId>),
     GetMapEntry(
         HttpRequestMetadata,
         StacksAddress,
@@ -1246,6 +2897,7 @@ pub enum HttpRequestType {
         ClarityName,
         Vec<Value>,
         Option<StacksBlockId>,
+        Option<AtBlock>,
     ),
     GetTransferCost(HttpRequestMetadata),
     GetContractSrc(
@@ -1263,6 +2915,8 @@ pub enum HttpRequestType {
     ),
     OptionsPreflight(HttpRequestMetadata, String),
     GetAttachment(HttpRequestMetadata, Hash160),
+    GetAttachmentContent(HttpRequestMetadata, Hash160, Option<HttpByteRange>),
+    GetAttachmentsBatch(HttpRequestMetadata, Vec<Hash160>),
     GetAttachmentsInv(HttpRequestMetadata, StacksBlockId, HashSet<u32>),
     GetIsTraitImplemented(
         HttpRequestMetadata,
@@ -1271,6 +2925,24 @@ pub enum HttpRequestType {
         TraitIdentifier,
         Option<StacksBlockId>,
     ),
+    VerifyContractSrc(
+        HttpRequestMetadata,
+        StacksAddress,
+        ContractName,
+        String,
+        Option<StacksBlockId>,
+    ),
+    ValidateContractCall(
+        HttpRequestMetadata,
+        StacksAddress,
+        ContractName,
+        ClarityName,
+        Vec<String>,
+        Option<StacksBlockId>,
+    ),
+    GetContractCosts(HttpRequestMetadata, Option<u64>, Option<u64>, Option<u32>),
+    EncodeClarityValue(HttpRequestMetadata, Value),
+    DecodeClarityValue(HttpRequestMetadata, String),
     /// catch-all for any errors we should surface from parsing
     ClientError(HttpRequestMetadata, ClientError),
 }
@@ -1282,6 +2954,8 @@ pub struct HttpResponseMetadata {
     pub client_keep_alive: bool,
     pub request_id: u32,
     pub content_length: Option<u32>,
+    /// Set if the request was made against a deprecated route
+    pub deprecation: Option<HttpApiDeprecation>,
 }
 
 impl HttpResponseMetadata {
@@ -1305,6 +2979,7 @@ impl HttpResponseMetadata {
             client_keep_alive: client_keep_alive,
             request_id: request_id,
             content_length: content_length,
+            deprecation: None,
         }
     }
 
@@ -1317,6 +2992,7 @@ impl HttpResponseMetadata {
             client_keep_alive: preamble.keep_alive,
             request_id: preamble.request_id,
             content_length: preamble.content_length.clone(),
+            deprecation: HttpApiDeprecation::from_headers(&preamble.headers),
         }
     }
 
@@ -1326,6 +3002,7 @@ impl HttpResponseMetadata {
             client_keep_alive: false,
             request_id: HttpResponseMetadata::make_request_id(),
             content_length: Some(0),
+            deprecation: None,
         }
     }
 }
@@ -1333,12 +3010,14 @@ impl HttpResponseMetadata {
 impl From<&HttpRequestType> for HttpResponseMetadata {
     fn from(req: &HttpRequestType) -> HttpResponseMetadata {
         let metadata = req.metadata();
-        HttpResponseMetadata::new(
+        let mut response_md = HttpResponseMetadata::new(
             metadata.version,
             HttpResponseMetadata::make_request_id(),
             None,
             metadata.keep_alive,
-        )
+        );
+        response_md.deprecation = req.api_deprecation();
+        response_md
     }
 }
 
@@ -1347,10 +3026,19 @@ impl From<&HttpRequestType> for HttpResponseMetadata {
 pub enum HttpResponseType {
     PeerInfo(HttpResponseMetadata, RPCPeerInfoData),
     PoxInfo(HttpResponseMetadata, RPCPoxInfoData),
+    NodeFeatures(HttpResponseMetadata, RPCNodeFeatures),
+    StateHash(HttpResponseMetadata, RPCStateHashData),
     Neighbors(HttpResponseMetadata, RPCNeighborsInfo),
+    NeighborWalkInfo(HttpResponseMetadata, RPCNeighborWalkInfo),
+    PeerVersions(HttpResponseMetadata, RPCPeerVersionsInfo),
+    NodeStatus(HttpResponseMetadata, RPCNodeStatus),
+    NodeIdentity(HttpResponseMetadata, RPCNodeIdentity),
     Block(HttpResponseMetadata, StacksBlock),
+    DecodedBlock(HttpResponseMetadata, RPCDecodedBlock),
     BlockStream(HttpResponseMetadata),
     Microblocks(HttpResponseMetadata, Vec<StacksMicroblock>),
+    DecodedMicroblocks(HttpResponseMetadata, Vec<RPCDecodedMicroblock>),
+    MicroblockForks(HttpResponseMetadata, RPCMicroblockForksInfo),
     MicroblockStream(HttpResponseMetadata),
     TransactionID(HttpResponseMetadata, Txid),
     StacksBlockAccepted(HttpResponseMetadata, StacksBlockId, bool),
@@ -1359,11 +3047,34 @@ pub enum HttpResponseType {
     GetMapEntry(HttpResponseMetadata, MapEntryResponse),
     CallReadOnlyFunction(HttpResponseMetadata, CallReadOnlyResponse),
     GetAccount(HttpResponseMetadata, AccountEntryResponse),
+    GetAccountUnlocks(HttpResponseMetadata, AccountUnlocksResponse),
+    GetMinerRewards(HttpResponseMetadata, MinerRewardsResponse),
     GetContractABI(HttpResponseMetadata, ContractInterface),
     GetContractSrc(HttpResponseMetadata, ContractSrcResponse),
     GetIsTraitImplemented(HttpResponseMetadata, GetIsTraitImplementedResponse),
+    VerifyContractSrc(HttpResponseMetadata, VerifyContractSrcResponse),
+    ValidateContractCall(HttpResponseMetadata, ValidateContractCallResponse),
+    ContractCosts(HttpResponseMetadata, RPCContractCostsData),
+    ClarityValue(HttpResponseMetadata, ClarityValueResponse),
     UnconfirmedTransaction(HttpResponseMetadata, UnconfirmedTransactionResponse),
+    MemPoolDrops(HttpResponseMetadata, Vec<RPCMemPoolDropData>),
+    MicroblockProof(HttpResponseMetadata, RPCMicroblockProof),
+    AddressMempool(HttpResponseMetadata, RPCPage<RPCPendingTransaction>),
+    MemPoolFees(HttpResponseMetadata, RPCMemPoolFeeData),
+    BlockSimulation(HttpResponseMetadata, RPCBlockSimulationData),
+    EventObserverStatus(HttpResponseMetadata, Vec<RPCEventObserverStatus>),
+    EventReplays(HttpResponseMetadata, Vec<RPCEventReplay>),
+    PeerFilters(HttpResponseMetadata, RPCPeerFilters),
+    AtlasContracts(HttpResponseMetadata, RPCAtlasContracts),
+    AttachmentPins(HttpResponseMetadata, RPCAttachmentPins),
+    BlockProcessing(HttpResponseMetadata, RPCBlockProcessingStatus),
+    BlockFetch(HttpResponseMetadata, RPCBlockFetchData),
+    QueryJob(HttpResponseMetadata, RPCQueryJob),
+    MemPoolSnapshot(HttpResponseMetadata, RPCPage<StacksTransaction>),
+    MemPoolImport(HttpResponseMetadata, RPCMemPoolImportData),
     GetAttachment(HttpResponseMetadata, GetAttachmentResponse),
+    GetAttachmentContent(HttpResponseMetadata, AttachmentContentRange),
+    GetAttachmentsBatch(HttpResponseMetadata, GetAttachmentsBatchResponse),
     GetAttachmentsInv(HttpResponseMetadata, GetAttachmentsInvResponse),
     OptionsPreflight(HttpResponseMetadata),
     // peer-given error responses
@@ -1406,6 +3117,13 @@ pub enum StacksMessageID {
     Pong = 16,
     NatPunchRequest = 17,
     NatPunchReply = 18,
+    MessageLimit = 19,
+    DataUrlHealth = 20,
+    CompactBlock = 21,
+    AttachmentsAvailable = 22,
+    GetBlocksInvPage = 23,
+    BlocksInvPage = 24,
+    MicroblocksSubscribe = 25,
     Reserved = 255,
 }
 
@@ -1504,18 +3222,55 @@ pub const MAX_BROADCAST_INBOUND_RECEIVERS: usize = 16;
 // maximum number of blocks that can be announced as available
 pub const BLOCKS_AVAILABLE_MAX_LEN: u32 = 32;
 
+// maximum number of attachments that can be announced as available in one message
+pub const ATTACHMENTS_AVAILABLE_MAX_LEN: u32 = 64;
+
 // maximum number of PoX reward cycles we can ask about
 #[cfg(not(test))]
 pub const GETPOXINV_MAX_BITLEN: u64 = 4096;
 #[cfg(test)]
 pub const GETPOXINV_MAX_BITLEN: u64 = 8;
 
+// most block inventory bits a BlocksInvPage can carry
+#[cfg(not(test))]
+pub const BLOCKS_INV_PAGE_MAX_BITLEN: u64 = 4096;
+#[cfg(test)]
+pub const BLOCKS_INV_PAGE_MAX_BITLEN: u64 = 8;
+
 // maximum number of blocks that can be pushed at once (even if the entire message is undersized).
 // This bound is needed since it bounds the amount of I/O a peer can be asked to do to validate the
 // message.
 pub const BLOCKS_PUSHED_MAX: u32 = 32;
 
+// largest payload we'll build when pushing blocks.  Bigger pushes are split into multiple
+// BlocksData messages, since a push near MAX_MESSAGE_LEN easily trips a peer's bound readers.
+// This is still big enough to carry a maximally-sized block.
+#[cfg(not(test))]
+pub const MAX_BLOCKS_DATA_PUSH_LEN: u32 = 4 * 1024 * 1024;
+#[cfg(test)]
+pub const MAX_BLOCKS_DATA_PUSH_LEN: u32 = 64 * 1024;
+
+// smallest payload limit a peer can ask us to honor via a MessageLimit.  Anything smaller is
+// clamped up to this, so a peer can always be pushed at least a maximally-sized microblock.
+pub const MIN_PEER_PAYLOAD_LEN: u32 = 128 * 1024;
+
+// maximum number of data URLs in a DataUrlHealth message
+pub const MAX_DATA_URL_HEALTH_REPORTS: u32 = 16;
+
+// maximum number of transactions a CompactBlock can list (a loose upper bound, since every
+// transaction takes up more than its 32-byte txid in a block)
+pub const MAX_COMPACT_BLOCK_TXS: u32 = MAX_BLOCK_LEN / 32;
+
+// set in a preamble's additional_data when the relayers and payload that follow it are
+// LZ4-compressed.  Only ever sent to peers that advertise ServiceFlags::COMPRESSION.  The low
+// bits are left alone, so a preamble with them set still reads as uncompressed.
+pub const PREAMBLE_FLAG_COMPRESSED: u32 = 0x80000000;
+
+// message bodies smaller than this aren't worth compressing, and are always sent as-is
+pub const MIN_COMPRESSED_BODY_LEN: usize = 1024;
+
 impl_byte_array_message_codec!(ConsensusHash, 20);
+impl_byte_array_message_codec!(Txid, 32);
 impl_byte_array_message_codec!(Hash160, 20);
 impl_byte_array_message_codec!(BurnchainHeaderHash, 32);
 impl_byte_array_message_codec!(BlockHeaderHash, 32);
@@ -1669,7 +3424,10 @@ pub struct NetworkResult {
     pub confirmed_microblocks: Vec<(ConsensusHash, Vec<StacksMicroblock>, u64)>, // confiremd microblocks we downloaded, and time taken
     pub pushed_transactions: HashMap<NeighborKey, Vec<(Vec<RelayData>, StacksTransaction)>>, // all transactions pushed to us and their message relay hints
     pub pushed_blocks: HashMap<NeighborKey, Vec<BlocksData>>, // all blocks pushed to us
+    pub pushed_compact_blocks: HashMap<NeighborKey, Vec<CompactBlockData>>, // all compact blocks pushed to us
     pub pushed_microblocks: HashMap<NeighborKey, Vec<(Vec<RelayData>, MicroblocksData)>>, // all microblocks pushed to us, and the relay hints from the message
+    pub pushed_attachments_available:
+        HashMap<NeighborKey, Vec<(Vec<RelayData>, AttachmentsAvailableData)>>, // all attachment announcements pushed to us, and their relay hints
     pub uploaded_transactions: Vec<StacksTransaction>, // transactions sent to us by the http server
     pub uploaded_blocks: Vec<BlocksData>,              // blocks sent to us via the http server
     pub uploaded_microblocks: Vec<MicroblocksData>,    // microblocks sent to us by the http server
@@ -1692,7 +3450,9 @@ impl NetworkResult {
             confirmed_microblocks: vec![],
             pushed_transactions: HashMap::new(),
             pushed_blocks: HashMap::new(),
+            pushed_compact_blocks: HashMap::new(),
             pushed_microblocks: HashMap::new(),
+            pushed_attachments_available: HashMap::new(),
             uploaded_transactions: vec![],
             uploaded_blocks: vec![],
             uploaded_microblocks: vec![],
@@ -1704,7 +3464,9 @@ impl NetworkResult {
     }
 
     pub fn has_blocks(&self) -> bool {
-        self.blocks.len() > 0 || self.pushed_blocks.len() > 0
+        self.blocks.len() > 0
+            || self.pushed_blocks.len() > 0
+            || self.pushed_compact_blocks.len() > 0
     }
 
     pub fn has_microblocks(&self) -> bool {
@@ -1751,6 +3513,16 @@ impl NetworkResult {
                                 .insert(neighbor_key.clone(), vec![block_data]);
                         }
                     }
+                    StacksMessageType::CompactBlock(compact_block) => {
+                        if let Some(compact_msgs) =
+                            self.pushed_compact_blocks.get_mut(&neighbor_key)
+                        {
+                            compact_msgs.push(compact_block);
+                        } else {
+                            self.pushed_compact_blocks
+                                .insert(neighbor_key.clone(), vec![compact_block]);
+                        }
+                    }
                     StacksMessageType::Microblocks(mblock_data) => {
                         if let Some(mblocks_msgs) = self.pushed_microblocks.get_mut(&neighbor_key) {
                             mblocks_msgs.push((message.relayers, mblock_data));
@@ -1769,6 +3541,15 @@ impl NetworkResult {
                                 .insert(neighbor_key.clone(), vec![(message.relayers, tx_data)]);
                         }
                     }
+                    StacksMessageType::AttachmentsAvailable(available) => {
+                        if let Some(msgs) = self.pushed_attachments_available.get_mut(&neighbor_key)
+                        {
+                            msgs.push((message.relayers, available));
+                        } else {
+                            self.pushed_attachments_available
+                                .insert(neighbor_key.clone(), vec![(message.relayers, available)]);
+                        }
+                    }
                     _ => {
                         // forward along
                         if let Some(messages) = self.unhandled_messages.get_mut(&neighbor_key) {
@@ -2090,6 +3871,7 @@ pub mod test {
         pub org: u32,
         pub allowed: i64,
         pub denied: i64,
+        pub services: u16,
         pub data_url: UrlString,
         pub test_name: String,
         pub initial_balances: Vec<(PrincipalData, u64)>,
@@ -2100,7 +3882,11 @@ pub mod test {
 
     impl TestPeerConfig {
         pub fn default() -> TestPeerConfig {
-            let conn_opts = ConnectionOptions::default();
+            let mut conn_opts = ConnectionOptions::default();
+
+            // test peers usually have only one or two neighbors to ask for their public IP
+            conn_opts.public_ip_quorum = 1;
+
             let start_block = 0;
             let mut burnchain = Burnchain::default_unittest(
                 start_block,
@@ -2136,6 +3922,7 @@ pub mod test {
                 org: 0,
                 allowed: 0,
                 denied: 0,
+                services: ServiceFlags::RELAY as u16,
                 data_url: "".into(),
                 test_name: "".into(),
                 initial_balances: vec![],
@@ -2441,7 +4228,7 @@ pub mod test {
                     config.server_port,
                 )
                 .unwrap();
-                PeerDB::set_local_services(&mut tx, ServiceFlags::RELAY as u16).unwrap();
+                PeerDB::set_local_services(&mut tx, config.services).unwrap();
                 PeerDB::set_local_private_key(
                     &mut tx,
                     &config.private_key,
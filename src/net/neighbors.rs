@@ -43,6 +43,7 @@ use std::cmp;
 use std::mem;
 use std::net::SocketAddr;
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
@@ -51,8 +52,11 @@ use burnchains::Burnchain;
 use burnchains::BurnchainView;
 use burnchains::PublicKey;
 
+use monitoring;
+
 use rand::prelude::*;
 use rand::thread_rng;
+use util::get_epoch_time_ms;
 use util::get_epoch_time_secs;
 use util::hash::*;
 use util::log;
@@ -91,6 +95,18 @@ pub const NEIGHBOR_WALK_INTERVAL: u64 = 0;
 #[cfg(not(test))]
 pub const NEIGHBOR_WALK_INTERVAL: u64 = 120; // seconds
 
+pub const NEIGHBOR_WALK_UNREACHABLE_EMA_WEIGHT: f64 = 0.2; // weight of the latest walk pass in the moving average of the unreachable ratio
+pub const FRONTIER_RECENT_CONTACT_SECS: u64 = 86400; // a frontier peer we heard from within this many seconds counts as recently contacted
+pub const FRONTIER_QUALITY_DEGRADED_SCORE: u64 = 50; // frontier quality scores below this are reported as degraded
+pub const PEER_VERSION_ALERT_MIN_PEERS: u64 = 5; // don't warn about newer peer versions until we've observed at least this many peers
+pub const MAX_CONCURRENT_HANDSHAKES: u64 = 32; // most outbound handshakes a walk step will have in flight at once
+pub const MIN_CONCURRENT_HANDSHAKES: u64 = 4; // fewest outbound handshakes a walk step will have in flight, however busy we are
+
+#[cfg(test)]
+pub const SATURATED_NETWORK_PASS_MS: u64 = 60_000; // tests run many peers in one thread, so don't back off
+#[cfg(not(test))]
+pub const SATURATED_NETWORK_PASS_MS: u64 = 1_000; // a network pass that takes longer than this means the event loop is saturated
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct NeighborPingback {
     pub ts: u64,                 // when we discovered this neighbor to ping back
@@ -241,6 +257,333 @@ impl NeighborWalkResult {
     }
 }
 
+/// Bookkeeping for a single pass of the neighbor walk state machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeighborWalkPassStats {
+    pub start_time_ms: u128,
+    pub end_time_ms: u128,
+    pub contacted: u64,   // neighbors-of-neighbors we sent a handshake to
+    pub unreachable: u64, // ...and of those, how many we could not get a reply from
+    pub added: u64,       // new peers added to the frontier
+}
+
+impl NeighborWalkPassStats {
+    pub fn new() -> NeighborWalkPassStats {
+        NeighborWalkPassStats {
+            start_time_ms: get_epoch_time_ms(),
+            end_time_ms: 0,
+            contacted: 0,
+            unreachable: 0,
+            added: 0,
+        }
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.end_time_ms.saturating_sub(self.start_time_ms) as u64
+    }
+}
+
+/// Adaptive cap on how many outbound handshakes a neighbor walk step has in flight at once.  A
+/// node with a large peer DB would otherwise open connections faster than its event loop can
+/// service them, and starve everything else the event loop does (like burnchain sync).  The cap
+/// is halved whenever a pass of the event loop is too slow, down to `min_limit`, and grows back by
+/// one on every pass that isn't, up to `max_limit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HandshakeConcurrency {
+    pub limit: u64,
+    pub min_limit: u64,
+    pub max_limit: u64,
+    pub saturated_pass_ms: u64,
+    pub last_pass_ms: u64,
+    pub backoffs: u64, // number of times the cap was cut since the node started
+}
+
+impl HandshakeConcurrency {
+    pub fn new(connection_opts: &ConnectionOptions) -> HandshakeConcurrency {
+        let max_limit = cmp::max(connection_opts.max_concurrent_handshakes, 1);
+        let min_limit = cmp::min(
+            cmp::max(connection_opts.min_concurrent_handshakes, 1),
+            max_limit,
+        );
+        HandshakeConcurrency {
+            limit: max_limit,
+            min_limit,
+            max_limit,
+            saturated_pass_ms: connection_opts.saturated_network_pass_ms,
+            last_pass_ms: 0,
+            backoffs: 0,
+        }
+    }
+
+    /// Adjust the cap, given how long the last pass of the event loop took
+    pub fn record_pass(&mut self, pass_ms: u64) {
+        self.last_pass_ms = pass_ms;
+        if pass_ms > self.saturated_pass_ms {
+            let limit = cmp::max(self.limit / 2, self.min_limit);
+            if limit < self.limit {
+                debug!(
+                    "Network pass took {}ms; back off to {} concurrent handshakes",
+                    pass_ms, limit
+                );
+                self.limit = limit;
+                self.backoffs += 1;
+            }
+        } else if self.limit < self.max_limit {
+            self.limit += 1;
+        }
+    }
+
+    pub fn to_rpc(&self) -> RPCHandshakeConcurrency {
+        RPCHandshakeConcurrency {
+            limit: self.limit,
+            min_limit: self.min_limit,
+            max_limit: self.max_limit,
+            last_pass_ms: self.last_pass_ms,
+            backoffs: self.backoffs,
+        }
+    }
+}
+
+impl Default for HandshakeConcurrency {
+    fn default() -> HandshakeConcurrency {
+        HandshakeConcurrency::new(&ConnectionOptions::default())
+    }
+}
+
+/// Neighbor walk statistics, accumulated since the node started
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NeighborWalkStats {
+    pub walks: u64,
+    pub resets: u64,
+    pub last_walk_duration_ms: Option<u64>,
+    pub last_walk_time: Option<u64>,
+    pub frontier_size: u64,
+    pub churn_added: u64,
+    pub churn_replaced: u64,
+    pub unreachable_ratio: Option<f64>, // moving average; None until a pass contacts a peer
+    pub handshakes: HandshakeConcurrency,
+}
+
+impl NeighborWalkStats {
+    /// Account for a completed walk pass, which replaced `replaced` frontier peers and left the
+    /// frontier with `frontier_size` peers.
+    pub fn record_pass(
+        &mut self,
+        pass: &NeighborWalkPassStats,
+        replaced: u64,
+        frontier_size: u64,
+        now: u64,
+    ) {
+        self.walks += 1;
+        self.last_walk_duration_ms = Some(pass.duration_ms());
+        self.last_walk_time = Some(now);
+        self.frontier_size = frontier_size;
+        self.churn_added += pass.added;
+        self.churn_replaced += replaced;
+
+        if pass.contacted > 0 {
+            let ratio = (pass.unreachable as f64) / (pass.contacted as f64);
+            self.unreachable_ratio = Some(match self.unreachable_ratio {
+                Some(avg) => {
+                    NEIGHBOR_WALK_UNREACHABLE_EMA_WEIGHT * ratio
+                        + (1.0 - NEIGHBOR_WALK_UNREACHABLE_EMA_WEIGHT) * avg
+                }
+                None => ratio,
+            });
+        }
+    }
+
+    /// Score the frontier in `conn` by how many of its peers are fresh, how many we heard from
+    /// recently, how diverse their networks are, and how many of the peers the walk tries to
+    /// contact are reachable.  Until the walk has contacted any peers, reachability is not held
+    /// against the frontier.
+    pub fn frontier_quality(
+        &self,
+        conn: &DBConn,
+        burn_block_height: u64,
+        now: u64,
+    ) -> Result<RPCFrontierQuality, db_error> {
+        let peers = PeerDB::get_all_peers(conn)?;
+        let reachable_ratio = 1.0 - self.unreachable_ratio.unwrap_or(0.0);
+        if peers.len() == 0 {
+            return Ok(RPCFrontierQuality {
+                score: 0,
+                fresh_ratio: 0.0,
+                recent_contact_ratio: 0.0,
+                network_diversity: 0.0,
+                reachable_ratio,
+            });
+        }
+
+        let mut num_fresh = 0;
+        let mut num_recent = 0;
+        let mut networks = HashSet::new();
+        for peer in peers.iter() {
+            if peer.expire_block > burn_block_height && !peer.is_denied() {
+                num_fresh += 1;
+            }
+            if peer.last_contact_time + FRONTIER_RECENT_CONTACT_SECS >= now {
+                num_recent += 1;
+            }
+            // peers with no known ASN are grouped by /16 (IPv4) or /32 (IPv6) prefix instead
+            let network = if peer.asn != 0 {
+                (peer.asn, [0u8; 4])
+            } else {
+                (0, peer.addr.addrbytes.network_prefix())
+            };
+            networks.insert(network);
+        }
+
+        let count = peers.len() as f64;
+        let fresh_ratio = (num_fresh as f64) / count;
+        let recent_contact_ratio = (num_recent as f64) / count;
+        let network_diversity = (networks.len() as f64) / count;
+        let score = 100.0
+            * (0.3 * fresh_ratio
+                + 0.3 * recent_contact_ratio
+                + 0.15 * network_diversity
+                + 0.25 * reachable_ratio);
+
+        Ok(RPCFrontierQuality {
+            score: cmp::min(score.round() as u64, 100),
+            fresh_ratio,
+            recent_contact_ratio,
+            network_diversity,
+            reachable_ratio,
+        })
+    }
+
+    /// Tell whether poor connectivity is down to the frontier or to our own networking.  If most
+    /// frontier peers are fresh but the walk cannot reach them (or we have no outbound peers at
+    /// all), the problem is most likely local.
+    pub fn assess(&self, quality: &RPCFrontierQuality, num_outbound: u64) -> &'static str {
+        let frontier_looks_alive = self.frontier_size > 0 && quality.fresh_ratio >= 0.5;
+        if frontier_looks_alive && (quality.reachable_ratio < 0.25 || num_outbound == 0) {
+            "local_connectivity"
+        } else if quality.score < FRONTIER_QUALITY_DEGRADED_SCORE {
+            "degraded_frontier"
+        } else {
+            "healthy"
+        }
+    }
+}
+
+/// How many of the peers we know of advertise each peer version
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PeerVersionCensus {
+    /// peer version => number of connected peers advertising it
+    pub connected: BTreeMap<u32, u64>,
+    /// peer version => number of distinct peers advertising it, among connected peers and
+    /// unexpired frontier peers
+    pub observed: BTreeMap<u32, u64>,
+}
+
+impl PeerVersionCensus {
+    /// Count the peer versions of our handshaked conversations and of the unexpired frontier.  A
+    /// peer that is both connected and in the frontier is counted once, with the version it gave
+    /// us in its last handshake.
+    pub fn take(
+        peers: &PeerMap,
+        frontier: &[Neighbor],
+        burn_block_height: u64,
+    ) -> PeerVersionCensus {
+        let mut census = PeerVersionCensus::default();
+        let mut observed = HashMap::new();
+        for peer in frontier.iter() {
+            if peer.addr.peer_version == 0
+                || peer.expire_block <= burn_block_height
+                || peer.is_denied()
+            {
+                continue;
+            }
+            observed.insert(
+                (peer.addr.addrbytes.clone(), peer.addr.port),
+                peer.addr.peer_version,
+            );
+        }
+        for (_, convo) in peers.iter() {
+            if !convo.is_authenticated() || convo.peer_version == 0 {
+                continue;
+            }
+            *census.connected.entry(convo.peer_version).or_insert(0) += 1;
+            let nk = convo.to_handshake_neighbor_key();
+            observed.insert((nk.addrbytes, nk.port), nk.peer_version);
+        }
+        for (_, peer_version) in observed.into_iter() {
+            *census.observed.entry(peer_version).or_insert(0) += 1;
+        }
+        census
+    }
+
+    pub fn num_observed(&self) -> u64 {
+        self.observed.values().sum()
+    }
+
+    /// Number of observed peers advertising a newer peer version than `local_version`
+    pub fn num_observed_newer(&self, local_version: u32) -> u64 {
+        self.observed
+            .iter()
+            .filter(|(peer_version, _)| **peer_version > local_version)
+            .map(|(_, count)| *count)
+            .sum()
+    }
+
+    /// Percentage of observed peers advertising a newer peer version than `local_version`
+    pub fn pct_observed_newer(&self, local_version: u32) -> u64 {
+        let num_observed = self.num_observed();
+        if num_observed == 0 {
+            return 0;
+        }
+        (100 * self.num_observed_newer(local_version)) / num_observed
+    }
+
+    /// The newest peer version any observed peer advertises
+    pub fn newest_observed(&self) -> Option<u32> {
+        self.observed.keys().next_back().cloned()
+    }
+}
+
+/// State of the peer version monitor, which periodically takes a census of the peer versions
+/// our peers advertise and warns when too many of them run a newer version than ours.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PeerVersionMonitor {
+    /// When we last took a census
+    pub last_checked_at: u64,
+    /// Result of the last census
+    pub last_census: PeerVersionCensus,
+    /// The newest peer version we've warned about, if too many peers still advertise a newer
+    /// version than ours
+    pub alerted_version: Option<u32>,
+}
+
+impl PeerVersionMonitor {
+    /// Record a census.  We're outdated when at least `PEER_VERSION_ALERT_MIN_PEERS` peers were
+    /// observed, and more than `alert_pct` percent of them advertise a newer version than
+    /// `local_version`.  Returns the newest advertised version if we're outdated and haven't
+    /// warned about that version yet.
+    pub fn update(
+        &mut self,
+        census: PeerVersionCensus,
+        local_version: u32,
+        alert_pct: u64,
+    ) -> Option<u32> {
+        let outdated = census.num_observed() >= PEER_VERSION_ALERT_MIN_PEERS
+            && census.pct_observed_newer(local_version) > alert_pct;
+        let newest = census.newest_observed();
+        self.last_census = census;
+
+        if !outdated {
+            self.alerted_version = None;
+            return None;
+        }
+        if newest == self.alerted_version {
+            return None;
+        }
+        self.alerted_version = newest;
+        newest
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum NeighborWalkState {
     HandshakeBegin,
@@ -326,6 +669,10 @@ pub struct NeighborWalk {
     walk_reset_interval: u64, // how long a walk can last, in wall-clock time
     walk_state_time: u64,     // when the walk entered this state
     walk_state_timeout: u64,  // how long the walk can remain in this state
+
+    // stats for the current and last completed pass of the state machine
+    pass_stats: NeighborWalkPassStats,
+    last_pass_stats: Option<NeighborWalkPassStats>,
 }
 
 impl NeighborWalk {
@@ -387,6 +734,9 @@ impl NeighborWalk {
             walk_reset_interval: connection_opts.walk_reset_interval,
             walk_state_time: get_epoch_time_secs(),
             walk_state_timeout: connection_opts.walk_state_timeout,
+
+            pass_stats: NeighborWalkPassStats::new(),
+            last_pass_stats: None,
         }
     }
 
@@ -429,6 +779,10 @@ impl NeighborWalk {
 
         self.walk_end_time = get_epoch_time_secs();
 
+        let mut pass_stats = mem::replace(&mut self.pass_stats, NeighborWalkPassStats::new());
+        pass_stats.end_time_ms = get_epoch_time_ms();
+        self.last_pass_stats = Some(pass_stats);
+
         // leave self.frontier and self.result alone until the next walk.
         // (makes it so that at the end of the walk, we can query the result and frontier, which
         // get built up over successive passes of the state-machine)
@@ -988,6 +1342,8 @@ impl NeighborWalk {
         );
         for (naddr, mut rh) in unresolved_handshake_neighbors.drain() {
             if let Err(_e) = network.saturate_p2p_socket(rh.get_event_id(), &mut rh) {
+                self.pass_stats.contacted += 1;
+                self.pass_stats.unreachable += 1;
                 self.result.add_dead(NeighborKey::from_neighbor_address(
                     network.peer_version,
                     self.local_peer.network_id,
@@ -1000,6 +1356,8 @@ impl NeighborWalk {
             let rh_naddr = naddr.clone(); // used below
             let new_rh = match res {
                 Ok(message) => {
+                    self.pass_stats.contacted += 1;
+
                     // if the neighbor is still bootstrapping, we're done
                     if message.preamble.burn_stable_block_height + MAX_NEIGHBOR_BLOCK_DELAY
                         < stable_block_height
@@ -1031,6 +1389,7 @@ impl NeighborWalk {
                                 )?;
                                 if new {
                                     // neighbor was new
+                                    self.pass_stats.added += 1;
                                     self.new_frontier
                                         .insert(neighbor.addr.clone(), neighbor.clone());
                                 } else {
@@ -1092,6 +1451,8 @@ impl NeighborWalk {
                                 "{:?}: Failed to handshake with {:?}: {:?}",
                                 &self.local_peer, naddr, &e
                             );
+                            self.pass_stats.contacted += 1;
+                            self.pass_stats.unreachable += 1;
                             self.result.add_dead(NeighborKey::from_neighbor_address(
                                 network.peer_version,
                                 self.local_peer.network_id,
@@ -1677,7 +2038,7 @@ impl NeighborWalk {
                 let replaced_opt = PeerDB::get_peer_at(&mut tx, self.local_peer.network_id, *slot)?;
                 match replaced_opt {
                     Some(replaced) => {
-                        if PeerDB::is_address_denied(&mut tx, &replacement.addr.addrbytes)? {
+                        if PeerDB::is_neighbor_denied(&mut tx, &replacement)? {
                             debug!(
                                 "{:?}: Will not replace {:?} with {:?} -- is denied",
                                 &self.local_peer, &replaced.addr, &replacement.addr
@@ -2262,23 +2623,23 @@ impl PeerNetwork {
                     }
                 }
 
-                // prune this down to size
+                // prune this down to size -- we handshake with all of them at once
+                let max_neighbors = cmp::min(
+                    network.connection_opts.max_neighbors_of_neighbor,
+                    network.walk_stats.handshakes.limit,
+                );
                 let pending_neighbor_list =
                     if let Some(mut pending_neighbor_addrs) = walk.pending_neighbor_addrs.take() {
-                        if pending_neighbor_addrs.len() as u64
-                            > network.connection_opts.max_neighbors_of_neighbor
-                        {
+                        if pending_neighbor_addrs.len() as u64 > max_neighbors {
                             debug!(
                                 "{:?}: will handshake with {} neighbors out of {} reported by {:?}",
                                 &walk.local_peer,
-                                &network.connection_opts.max_neighbors_of_neighbor,
+                                max_neighbors,
                                 pending_neighbor_addrs.len(),
                                 &walk.cur_neighbor.addr
                             );
                             pending_neighbor_addrs.shuffle(&mut thread_rng());
-                            pending_neighbor_addrs
-                                [0..(network.connection_opts.max_neighbors_of_neighbor as usize)]
-                                .to_vec()
+                            pending_neighbor_addrs[0..(max_neighbors as usize)].to_vec()
                         } else {
                             pending_neighbor_addrs
                         }
@@ -2564,6 +2925,19 @@ impl PeerNetwork {
                     continue;
                 }
 
+                // too many handshakes in flight already?  Leave this one to a later walk.
+                if walk.pending_pingback_handshakes.len() as u64
+                    >= network.walk_stats.handshakes.limit
+                {
+                    test_debug!(
+                        "{:?}: skip pingback to {:?}: {} handshakes in flight",
+                        &network.local_peer,
+                        &naddr,
+                        walk.pending_pingback_handshakes.len()
+                    );
+                    continue;
+                }
+
                 let nk = NeighborKey::from_neighbor_address(
                     pingback.peer_version,
                     pingback.network_id,
@@ -2748,6 +3122,74 @@ impl PeerNetwork {
         })
     }
 
+    /// Update the walk statistics and metrics with the pass of the walk that just completed
+    fn record_walk_pass(&mut self, walk_result: &NeighborWalkResult) {
+        let pass_stats = match self
+            .walk
+            .as_mut()
+            .and_then(|walk| walk.last_pass_stats.take())
+        {
+            Some(pass_stats) => pass_stats,
+            None => {
+                return;
+            }
+        };
+        let frontier_size = match PeerDB::get_frontier_size(self.peerdb.conn()) {
+            Ok(count) => count,
+            Err(e) => {
+                debug!(
+                    "{:?}: failed to query frontier size: {:?}",
+                    &self.local_peer, &e
+                );
+                self.walk_stats.frontier_size
+            }
+        };
+
+        let now = get_epoch_time_secs();
+        self.walk_stats.record_pass(
+            &pass_stats,
+            walk_result.replaced_neighbors.len() as u64,
+            frontier_size,
+            now,
+        );
+
+        let score = match self.walk_stats.frontier_quality(
+            self.peerdb.conn(),
+            self.chain_view.burn_block_height,
+            now,
+        ) {
+            Ok(quality) => quality.score,
+            Err(e) => {
+                debug!(
+                    "{:?}: failed to compute frontier quality: {:?}",
+                    &self.local_peer, &e
+                );
+                0
+            }
+        };
+
+        debug!(
+            "{:?}: walk pass took {}ms: contacted {}, unreachable {}, added {}, replaced {}; frontier size {}, quality {}",
+            &self.local_peer,
+            pass_stats.duration_ms(),
+            pass_stats.contacted,
+            pass_stats.unreachable,
+            pass_stats.added,
+            walk_result.replaced_neighbors.len(),
+            frontier_size,
+            score
+        );
+
+        monitoring::update_neighbor_walk(
+            pass_stats.duration_ms(),
+            frontier_size as i64,
+            pass_stats.added,
+            walk_result.replaced_neighbors.len() as u64,
+            self.walk_stats.unreachable_ratio.unwrap_or(0.0),
+            score as i64,
+        );
+    }
+
     /// Get the walk state
     fn get_walk_state(&self) -> NeighborWalkState {
         match self.walk {
@@ -2961,6 +3403,7 @@ impl PeerNetwork {
                         );
 
                         walk_result.do_prune = true;
+                        self.record_walk_pass(walk_result);
                     }
                     None => {}
                 }
@@ -3020,6 +3463,7 @@ impl PeerNetwork {
                     debug!("{:?}: random walk restart", &self.local_peer);
                     self.walk = None;
                     self.walk_resets += 1;
+                    self.walk_stats.resets += 1;
                     done = true; // move onto the next p2p work item
                 }
 
@@ -3067,6 +3511,7 @@ impl PeerNetwork {
                 );
                 self.walk = None;
                 self.walk_resets += 1;
+                self.walk_stats.resets += 1;
                 (true, None)
             }
         }
@@ -3081,11 +3526,320 @@ mod test {
     use net::db::*;
     use net::test::*;
     use util::hash::*;
+    use util::secp256k1::Secp256k1PrivateKey;
     use util::sleep_ms;
     use util::test::*;
 
     const TEST_IN_OUT_DEGREES: u64 = 0x1;
 
+    fn make_frontier_neighbor(
+        addrbytes: PeerAddress,
+        expire_block: u64,
+        last_contact_time: u64,
+        asn: u32,
+    ) -> Neighbor {
+        Neighbor {
+            addr: NeighborKey {
+                peer_version: 0x12345678,
+                network_id: 0x9abcdef0,
+                addrbytes,
+                port: 20444,
+            },
+            public_key: Secp256k1PublicKey::from_private(&Secp256k1PrivateKey::new()),
+            expire_block,
+            last_contact_time,
+            allowed: 0,
+            denied: 0,
+            asn,
+            org: asn,
+            in_degree: 1,
+            out_degree: 1,
+        }
+    }
+
+    #[test]
+    fn test_walk_stats_record_pass() {
+        let mut stats = NeighborWalkStats::default();
+        let mut pass = NeighborWalkPassStats::new();
+        pass.end_time_ms = pass.start_time_ms + 1500;
+        pass.contacted = 4;
+        pass.unreachable = 1;
+        pass.added = 3;
+
+        stats.record_pass(&pass, 2, 10, 1000);
+        assert_eq!(stats.walks, 1);
+        assert_eq!(stats.last_walk_duration_ms, Some(1500));
+        assert_eq!(stats.last_walk_time, Some(1000));
+        assert_eq!(stats.frontier_size, 10);
+        assert_eq!(stats.churn_added, 3);
+        assert_eq!(stats.churn_replaced, 2);
+        assert_eq!(stats.unreachable_ratio, Some(0.25));
+
+        // a pass that contacts nobody leaves the unreachable ratio alone
+        let empty_pass = NeighborWalkPassStats::new();
+        stats.record_pass(&empty_pass, 0, 10, 1001);
+        assert_eq!(stats.walks, 2);
+        assert_eq!(stats.unreachable_ratio, Some(0.25));
+
+        // later passes are folded into a moving average
+        pass.unreachable = 4;
+        stats.record_pass(&pass, 0, 11, 1002);
+        let expected = NEIGHBOR_WALK_UNREACHABLE_EMA_WEIGHT * 1.0
+            + (1.0 - NEIGHBOR_WALK_UNREACHABLE_EMA_WEIGHT) * 0.25;
+        assert!((stats.unreachable_ratio.unwrap() - expected).abs() < 1e-9);
+        assert_eq!(stats.churn_added, 6);
+    }
+
+    #[test]
+    fn test_handshake_concurrency_backoff() {
+        let mut opts = ConnectionOptions::default();
+        opts.max_concurrent_handshakes = 16;
+        opts.min_concurrent_handshakes = 3;
+        opts.saturated_network_pass_ms = 1000;
+
+        let mut handshakes = HandshakeConcurrency::new(&opts);
+        assert_eq!(handshakes.limit, 16);
+
+        // slow passes halve the cap, down to the minimum
+        handshakes.record_pass(1001);
+        assert_eq!(handshakes.limit, 8);
+        assert_eq!(handshakes.last_pass_ms, 1001);
+        handshakes.record_pass(5000);
+        assert_eq!(handshakes.limit, 4);
+        handshakes.record_pass(5000);
+        assert_eq!(handshakes.limit, 3);
+        handshakes.record_pass(5000);
+        assert_eq!(handshakes.limit, 3);
+        assert_eq!(handshakes.backoffs, 3);
+
+        // fast passes grow it back one at a time, up to the maximum
+        handshakes.record_pass(1000);
+        assert_eq!(handshakes.limit, 4);
+        for _ in 0..100 {
+            handshakes.record_pass(10);
+        }
+        assert_eq!(handshakes.limit, 16);
+        assert_eq!(handshakes.backoffs, 3);
+
+        let rpc = handshakes.to_rpc();
+        assert_eq!(rpc.limit, 16);
+        assert_eq!(rpc.min_limit, 3);
+        assert_eq!(rpc.max_limit, 16);
+        assert_eq!(rpc.last_pass_ms, 10);
+
+        // nonsensical bounds are clamped
+        opts.max_concurrent_handshakes = 0;
+        opts.min_concurrent_handshakes = 10;
+        let handshakes = HandshakeConcurrency::new(&opts);
+        assert_eq!(handshakes.limit, 1);
+        assert_eq!(handshakes.min_limit, 1);
+        assert_eq!(handshakes.max_limit, 1);
+    }
+
+    #[test]
+    fn test_frontier_quality() {
+        let now = get_epoch_time_secs();
+        let burn_block_height = 1000;
+
+        let db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &vec![],
+        )
+        .unwrap();
+
+        let stats = NeighborWalkStats::default();
+        let quality = stats
+            .frontier_quality(db.conn(), burn_block_height, now)
+            .unwrap();
+        assert_eq!(quality.score, 0);
+        assert_eq!(stats.assess(&quality, 0), "degraded_frontier");
+
+        // fresh, recently-contacted peers on four distinct networks
+        let healthy_frontier = vec![
+            make_frontier_neighbor(PeerAddress::from_ipv4(1, 2, 3, 4), 2000, now, 1),
+            make_frontier_neighbor(PeerAddress::from_ipv4(1, 2, 3, 5), 2000, now, 2),
+            make_frontier_neighbor(PeerAddress::from_ipv4(5, 6, 7, 8), 2000, now, 0),
+            make_frontier_neighbor(PeerAddress::from_ipv4(9, 10, 11, 12), 2000, now, 0),
+        ];
+        let mut db = PeerDB::connect_memory(
+            0x9abcdef0,
+            12345,
+            0,
+            "http://foo.com".into(),
+            &vec![],
+            &healthy_frontier,
+        )
+        .unwrap();
+
+        let mut stats = NeighborWalkStats::default();
+        stats.frontier_size = 4;
+        let quality = stats
+            .frontier_quality(db.conn(), burn_block_height, now)
+            .unwrap();
+        assert_eq!(quality.fresh_ratio, 1.0);
+        assert_eq!(quality.recent_contact_ratio, 1.0);
+        assert_eq!(quality.network_diversity, 1.0);
+        assert_eq!(quality.reachable_ratio, 1.0);
+        assert_eq!(quality.score, 100);
+        assert_eq!(stats.assess(&quality, 2), "healthy");
+
+        // a live frontier that we cannot reach points at local networking
+        stats.unreachable_ratio = Some(0.8);
+        let quality = stats
+            .frontier_quality(db.conn(), burn_block_height, now)
+            .unwrap();
+        assert_eq!(quality.score, 80);
+        assert_eq!(stats.assess(&quality, 2), "local_connectivity");
+
+        stats.unreachable_ratio = Some(0.0);
+        let quality = stats
+            .frontier_quality(db.conn(), burn_block_height, now)
+            .unwrap();
+        assert_eq!(stats.assess(&quality, 0), "local_connectivity");
+
+        // a frontier of expired, long-unseen peers on one network is degraded
+        {
+            let mut tx = db.tx_begin().unwrap();
+            for neighbor in healthy_frontier.iter() {
+                PeerDB::drop_peer(
+                    &mut tx,
+                    neighbor.addr.network_id,
+                    &neighbor.addr.addrbytes,
+                    neighbor.addr.port,
+                )
+                .unwrap();
+            }
+            for i in 0..4 {
+                let stale = make_frontier_neighbor(
+                    PeerAddress::from_ipv4(1, 2, 3, i),
+                    500,
+                    now - 2 * FRONTIER_RECENT_CONTACT_SECS,
+                    0,
+                );
+                PeerDB::insert_or_replace_peer(&mut tx, &stale, i as u32).unwrap();
+            }
+            tx.commit().unwrap();
+        }
+
+        let quality = stats
+            .frontier_quality(db.conn(), burn_block_height, now)
+            .unwrap();
+        assert_eq!(quality.fresh_ratio, 0.0);
+        assert_eq!(quality.recent_contact_ratio, 0.0);
+        assert_eq!(quality.network_diversity, 0.25);
+        assert_eq!(quality.score, 29);
+        assert_eq!(stats.assess(&quality, 2), "degraded_frontier");
+    }
+
+    #[test]
+    fn test_peer_version_census() {
+        let now = get_epoch_time_secs();
+        let burn_block_height = 1000;
+        let local_version = 0x18000000;
+
+        let mut frontier = vec![];
+        for i in 0..8 {
+            let mut neighbor =
+                make_frontier_neighbor(PeerAddress::from_ipv4(1, 2, 3, i as u8), 2000, now, 0);
+            neighbor.addr.peer_version = if i < 3 { 0x18000000 } else { 0x18000001 };
+            frontier.push(neighbor);
+        }
+        // expired peers and peers with no known version are not counted
+        frontier.push(make_frontier_neighbor(
+            PeerAddress::from_ipv4(5, 6, 7, 8),
+            500,
+            now,
+            0,
+        ));
+        let mut unknown = make_frontier_neighbor(PeerAddress::from_ipv4(5, 6, 7, 9), 2000, now, 0);
+        unknown.addr.peer_version = 0;
+        frontier.push(unknown);
+
+        let census = PeerVersionCensus::take(&PeerMap::new(), &frontier, burn_block_height);
+        assert!(census.connected.is_empty());
+        assert_eq!(census.observed.get(&0x18000000), Some(&3));
+        assert_eq!(census.observed.get(&0x18000001), Some(&5));
+        assert_eq!(census.num_observed(), 8);
+        assert_eq!(census.num_observed_newer(local_version), 5);
+        assert_eq!(census.pct_observed_newer(local_version), 62);
+        assert_eq!(census.newest_observed(), Some(0x18000001));
+
+        // warn once per newer version, and again once it's cleared
+        let mut monitor = PeerVersionMonitor::default();
+        assert_eq!(
+            monitor.update(census.clone(), local_version, 50),
+            Some(0x18000001)
+        );
+        assert_eq!(monitor.update(census.clone(), local_version, 50), None);
+        assert_eq!(monitor.update(census.clone(), local_version, 75), None);
+        assert_eq!(monitor.alerted_version, None);
+        assert_eq!(
+            monitor.update(census.clone(), local_version, 50),
+            Some(0x18000001)
+        );
+
+        // too few peers to go by
+        let census = PeerVersionCensus::take(&PeerMap::new(), &frontier[3..7], burn_block_height);
+        assert_eq!(census.pct_observed_newer(local_version), 100);
+        let mut monitor = PeerVersionMonitor::default();
+        assert_eq!(monitor.update(census, local_version, 50), None);
+    }
+
+    #[test]
+    #[cfg(feature = "async_net")]
+    fn test_step_walk_1_neighbor_async_io() {
+        with_timeout(600, || {
+            let mut peer_1_config = TestPeerConfig::from_port(31894);
+            let mut peer_2_config = TestPeerConfig::from_port(31896);
+
+            // both peers do their socket I/O on the async front-end
+            peer_1_config.connection_opts.async_io_threads = 2;
+            peer_2_config.connection_opts.async_io_threads = 2;
+
+            peer_1_config.add_neighbor(&peer_2_config.to_neighbor());
+
+            let mut peer_1 = TestPeer::new(peer_1_config);
+            let mut peer_2 = TestPeer::new(peer_2_config);
+
+            let mut walk_1_count = 0;
+            let mut walk_2_count = 0;
+
+            while (walk_1_count < 20 || walk_2_count < 20)
+                || peer_1
+                    .network
+                    .get_neighbor_stats(&peer_2.to_neighbor().addr)
+                    .is_none()
+            {
+                let _ = peer_1.step();
+                let _ = peer_2.step();
+
+                walk_1_count = peer_1.network.walk_total_step_count;
+                walk_2_count = peer_2.network.walk_total_step_count;
+
+                match peer_1.network.walk {
+                    Some(ref w) => {
+                        assert_eq!(w.result.broken_connections.len(), 0);
+                        assert_eq!(w.result.replaced_neighbors.len(), 0);
+                    }
+                    None => {}
+                };
+            }
+
+            // peer 1 talked to peer 2
+            let stats_1 = peer_1
+                .network
+                .get_neighbor_stats(&peer_2.to_neighbor().addr)
+                .unwrap();
+            assert!(stats_1.last_handshake_time > 0);
+            assert!(stats_1.bytes_rx > 0);
+            assert!(stats_1.bytes_tx > 0);
+        })
+    }
+
     #[test]
     #[ignore]
     fn test_step_walk_1_neighbor_plain() {
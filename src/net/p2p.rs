@@ -19,6 +19,7 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
+use std::convert::TryFrom;
 use std::mem;
 use std::net::SocketAddr;
 use std::sync::mpsc::sync_channel;
@@ -41,29 +42,39 @@ use burnchains::PublicKey;
 use chainstate::burn::db::sortdb::{BlockHeaderCache, SortitionDB};
 use chainstate::stacks::db::StacksChainState;
 use chainstate::stacks::{MAX_BLOCK_LEN, MAX_TRANSACTION_LEN};
-use monitoring::{update_inbound_neighbors, update_outbound_neighbors};
+use monitoring::{
+    increment_attachments_mirror_failures_counter, increment_attachments_mirrored_counter,
+    increment_compact_block_sent_txs, increment_peer_version_alerts, record_attachments_pruned,
+    update_inbound_neighbors, update_outbound_neighbors, update_peer_versions,
+};
 use net::asn::ASEntry4;
 use net::atlas::AtlasDB;
-use net::atlas::{AttachmentInstance, AttachmentsDownloader};
+use net::atlas::{AttachmentInstance, AttachmentMirror, AttachmentsDownloader};
 use net::chat::ConversationP2P;
 use net::chat::NeighborStats;
 use net::connection::ConnectionOptions;
+use net::connection::NetworkMode;
 use net::connection::NetworkReplyHandle;
 use net::connection::ReplyHandleHttp;
 use net::connection::ReplyHandleP2P;
 use net::db::LocalPeer;
 use net::db::PeerDB;
 use net::download::BlockDownloader;
+use net::download::DataUrlStats;
+use net::features::{NodeFeature, NodeFeatures};
 use net::inv::*;
+use net::jobs::QueryJobs;
 use net::neighbors::*;
+use net::poll::NetSocket;
 use net::poll::NetworkPollState;
 use net::poll::NetworkState;
 use net::prune::*;
 use net::relay::RelayerStats;
 use net::relay::*;
 use net::relay::*;
-use net::rpc::RPCHandlerArgs;
+use net::rpc::{RPCHandlerArgs, RPCIdempotencyCache, RPCResponseCache};
 use net::server::*;
+use net::tls::TlsConfig;
 use net::Error as net_error;
 use net::Neighbor;
 use net::NeighborKey;
@@ -71,11 +82,14 @@ use net::PeerAddress;
 use net::*;
 use util::db::DBConn;
 use util::db::Error as db_error;
+use util::disk::DiskSpaceLevel;
+use util::get_epoch_time_ms;
 use util::get_epoch_time_secs;
 use util::hash::to_hex;
 use util::log;
 use util::secp256k1::Secp256k1PublicKey;
 
+use crate::codec::StacksMessageCodec;
 use crate::types::chainstate::{PoxId, SortitionId, StacksBlockHeader};
 
 /// inter-thread request to send a p2p message from another thread in this program.
@@ -201,7 +215,7 @@ pub struct PeerNetwork {
 
     // ongoing p2p conversations (either they reached out to us, or we to them)
     pub peers: PeerMap,
-    pub sockets: HashMap<usize, mio_net::TcpStream>,
+    pub sockets: HashMap<usize, NetSocket>,
     pub events: HashMap<NeighborKey, usize>,
     pub connecting: HashMap<usize, (mio_net::TcpStream, bool, u64)>, // (socket, outbound?, connection sent timestamp)
     pub bans: HashSet<usize>,
@@ -211,6 +225,13 @@ pub struct PeerNetwork {
     pub relay_handles: HashMap<usize, VecDeque<ReplyHandleP2P>>,
     pub relayer_stats: RelayerStats,
 
+    // peers whose handshake keys have expired, mapped to when we asked them to re-handshake
+    pub expired_key_rehandshakes: HashMap<usize, u64>,
+
+    // peers that asked us to push them the microblocks we mine, and peers we asked the same of
+    pub microblock_subscribers: HashSet<usize>,
+    pub microblock_subscriptions: HashSet<usize>,
+
     // handles for other threads to send/receive data to peers
     handles: VecDeque<NetworkHandleServer>,
 
@@ -225,6 +246,9 @@ pub struct PeerNetwork {
     // connection options
     pub connection_opts: ConnectionOptions,
 
+    // TLS certificates to encrypt p2p connections with, if we have any (loaded on bind)
+    tls_config: Option<TlsConfig>,
+
     // work state -- we can be walking, fetching block inventories, fetching blocks, pruning, etc.
     pub work_state: PeerNetworkWorkState,
 
@@ -238,9 +262,21 @@ pub struct PeerNetwork {
     pub walk_total_step_count: u64,
     pub walk_pingbacks: HashMap<NeighborAddress, NeighborPingback>, // inbound peers for us to try to ping back and add to our frontier, mapped to (peer_version, network_id, timeout, pubkey)
     pub walk_result: NeighborWalkResult, // last successful neighbor walk result
+    pub walk_stats: NeighborWalkStats,
 
     // peer block inventory state
     pub inv_state: Option<InvState>,
+    pub inv_agreement: InvAgreementMonitor,
+
+    // census of the peer versions our peers advertise
+    pub peer_versions: PeerVersionMonitor,
+
+    // are we refusing block pushes because we have too many staging blocks to process, or
+    // because we're running out of disk space?
+    pub push_backpressure: bool,
+    pub staging_backlog_exceeded: bool,
+    pub staging_backlog_checked_at: u64,
+    pub disk_space_level: DiskSpaceLevel,
 
     // cached view of PoX database
     // (maintained by the inv state machine)
@@ -254,8 +290,30 @@ pub struct PeerNetwork {
     // peer block download state
     pub block_downloader: Option<BlockDownloader>,
 
+    // operator-requested block fetches, keyed by (index block hash, microblocks?)
+    // (started over the admin RPC; carried out by the downloader state machine)
+    pub block_fetches: HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+
+    // long-running scans started over the RPC interface, run a few blocks at a time
+    pub query_jobs: QueryJobs,
+
+    // cached responses to expensive read-only RPC requests, dropped when the chain tip changes
+    pub rpc_cache: RPCResponseCache,
+
+    // responses to transaction submissions sent with an Idempotency-Key, replayed on retry
+    pub idempotency_cache: RPCIdempotencyCache,
+
     // peer attachment downloader
     pub attachments_downloader: Option<AttachmentsDownloader>,
+    // when we last applied the Atlas retention policy
+    pub attachments_last_sweep: u64,
+    // when we next look for attachments to upload to the Atlas mirror
+    pub attachments_mirror_next_pass: u64,
+
+    // how well each data URL host has served the block and attachment downloaders
+    pub data_url_stats: DataUrlStats,
+    // when we last told our peers which data URLs we couldn't reach
+    pub data_url_health_last_sent: u64,
 
     // how often we pruned a given inbound/outbound peer
     pub prune_outbound_counts: HashMap<NeighborKey, u64>,
@@ -274,6 +332,7 @@ pub struct PeerNetwork {
     public_ip_learned_at: u64,
     public_ip_reply_handle: Option<ReplyHandleP2P>,
     public_ip_retries: u64,
+    public_ip_votes: HashMap<PeerAddress, (PeerAddress, u64)>, // maps a peer's IP address to (the public IP address it says we have, when it said so)
 
     // how many loops of the state-machine have occured?
     // Used to coordinate with the chain synchronization logic to ensure that the node has at least
@@ -286,6 +345,9 @@ pub struct PeerNetwork {
     // how many downloader passes have we done?
     pub num_downloader_passes: u64,
 
+    // when did we last save inv sync and block download progress?
+    download_state_saved_at: u64,
+
     // to whom did we send a block or microblock stream as part of our anti-entropy protocol, and
     // when did we send it?
     antientropy_blocks: HashMap<NeighborKey, HashMap<StacksBlockId, u64>>,
@@ -293,6 +355,10 @@ pub struct PeerNetwork {
     antientropy_start_reward_cycle: u64,
     pub antientropy_last_push_ts: u64,
 
+    // when (in milliseconds) we relayed each recent transaction, for guessing which ones our
+    // neighbors don't have yet when we push them a compact block
+    pub recent_relayed_txs: HashMap<Txid, u128>,
+
     // pending messages (BlocksAvailable, MicroblocksAvailable, BlocksData, Microblocks) that we
     // can't process yet, but might be able to process on the next chain view update
     pub pending_messages: HashMap<usize, Vec<StacksMessage>>,
@@ -309,8 +375,9 @@ impl PeerNetwork {
         peer_version: u32,
         burnchain: Burnchain,
         chain_view: BurnchainView,
-        connection_opts: ConnectionOptions,
+        mut connection_opts: ConnectionOptions,
     ) -> PeerNetwork {
+        connection_opts.maximum_attachment_size = atlasdb.atlas_config.max_attachment_size();
         let http = HttpPeer::new(
             local_peer.network_id,
             burnchain.clone(),
@@ -321,13 +388,30 @@ impl PeerNetwork {
         let pub_ip = connection_opts.public_ip_address.clone();
         let pub_ip_learned = pub_ip.is_none();
         local_peer.public_ip_address = pub_ip.clone();
+        PeerNetwork::apply_network_mode(&connection_opts, &mut local_peer);
 
+        if connection_opts.network_mode != NetworkMode::Full {
+            info!(
+                "{:?}: running in {} mode",
+                &local_peer,
+                connection_opts.network_mode.as_str()
+            );
+        }
         if connection_opts.disable_inbound_handshakes {
             debug!("{:?}: disable inbound handshakes", &local_peer);
         }
         if connection_opts.disable_inbound_walks {
             debug!("{:?}: disable inbound neighbor walks", &local_peer);
         }
+        let rpc_cache = RPCResponseCache::new(connection_opts.rpc_cache_max_entries);
+        let idempotency_cache = RPCIdempotencyCache::new(
+            connection_opts.rpc_idempotency_key_ttl,
+            connection_opts.rpc_idempotency_max_keys,
+        );
+        let walk_stats = NeighborWalkStats {
+            handshakes: HandshakeConcurrency::new(&connection_opts),
+            ..NeighborWalkStats::default()
+        };
 
         let mut network = PeerNetwork {
             local_peer: local_peer,
@@ -345,6 +429,9 @@ impl PeerNetwork {
             bans: HashSet::new(),
 
             relay_handles: HashMap::new(),
+            expired_key_rehandshakes: HashMap::new(),
+            microblock_subscribers: HashSet::new(),
+            microblock_subscriptions: HashSet::new(),
             relayer_stats: RelayerStats::new(),
 
             handles: VecDeque::new(),
@@ -354,6 +441,7 @@ impl PeerNetwork {
 
             burnchain: burnchain,
             connection_opts: connection_opts,
+            tls_config: None,
 
             work_state: PeerNetworkWorkState::GetPublicIP,
 
@@ -366,14 +454,29 @@ impl PeerNetwork {
             walk_total_step_count: 0,
             walk_pingbacks: HashMap::new(),
             walk_result: NeighborWalkResult::new(),
+            walk_stats: walk_stats,
 
             inv_state: None,
+            inv_agreement: InvAgreementMonitor::default(),
+            peer_versions: PeerVersionMonitor::default(),
+            push_backpressure: false,
+            staging_backlog_exceeded: false,
+            staging_backlog_checked_at: 0,
+            disk_space_level: DiskSpaceLevel::Normal,
             pox_id: PoxId::initial(),
             tip_sort_id: SortitionId([0x00; 32]),
             header_cache: BlockHeaderCache::new(),
 
             block_downloader: None,
+            block_fetches: HashMap::new(),
+            query_jobs: QueryJobs::new(),
+            rpc_cache: rpc_cache,
+            idempotency_cache: idempotency_cache,
             attachments_downloader: None,
+            attachments_last_sweep: 0,
+            attachments_mirror_next_pass: 0,
+            data_url_stats: DataUrlStats::new(),
+            data_url_health_last_sent: 0,
 
             prune_outbound_counts: HashMap::new(),
             prune_inbound_counts: HashMap::new(),
@@ -392,14 +495,19 @@ impl PeerNetwork {
             public_ip_confirmed: false,
             public_ip_reply_handle: None,
             public_ip_retries: 0,
+            public_ip_votes: HashMap::new(),
 
             num_state_machine_passes: 0,
             num_inv_sync_passes: 0,
             num_downloader_passes: 0,
 
+            download_state_saved_at: 0,
+
             antientropy_blocks: HashMap::new(),
             antientropy_microblocks: HashMap::new(),
             antientropy_last_push_ts: 0,
+
+            recent_relayed_txs: HashMap::new(),
             antientropy_start_reward_cycle: 0,
 
             pending_messages: HashMap::new(),
@@ -413,7 +521,15 @@ impl PeerNetwork {
 
     /// start serving.
     pub fn bind(&mut self, my_addr: &SocketAddr, http_addr: &SocketAddr) -> Result<(), net_error> {
+        self.tls_config = TlsConfig::from_options(&self.connection_opts)?;
         let mut net = NetworkState::new(self.connection_opts.max_sockets)?;
+        if self.connection_opts.async_io_threads > 0 {
+            net.start_async_io(
+                self.connection_opts.async_io_threads,
+                self.connection_opts.async_io_max_inbound_bytes,
+                self.connection_opts.async_io_max_outbound_bytes,
+            )?;
+        }
 
         let p2p_handle = net.bind(my_addr)?;
         let http_handle = net.bind(http_addr)?;
@@ -490,7 +606,7 @@ impl PeerNetwork {
     /// Return (number of bytes sent, whether or not there's more to send)
     fn do_saturate_p2p_socket(
         convo: &mut ConversationP2P,
-        client_sock: &mut mio::net::TcpStream,
+        client_sock: &mut NetSocket,
         handle: &mut ReplyHandleP2P,
     ) -> Result<(usize, bool), net_error> {
         let mut total_sent = 0;
@@ -617,13 +733,172 @@ impl PeerNetwork {
         Ok(())
     }
 
-    /// Broadcast a message to a list of neighbors
+    /// Greedily pack `items` into consecutive batches of at most `max_items` items, such that each
+    /// batch's serialized length plus `base_len` is at most `max_len`.  Stops at the first item
+    /// that cannot fit into a batch on its own, since any later items may depend on it.
+    fn partition_by_len<T: StacksMessageCodec + Clone>(
+        items: &[T],
+        base_len: usize,
+        max_len: usize,
+        max_items: usize,
+    ) -> Vec<Vec<T>> {
+        let mut batches = vec![];
+        let mut batch = vec![];
+        let mut batch_len = base_len;
+        for item in items.iter() {
+            let item_len = item.serialize_to_vec().len();
+            if base_len + item_len > max_len {
+                break;
+            }
+            if batch_len + item_len > max_len || batch.len() >= max_items {
+                batches.push(mem::replace(&mut batch, vec![]));
+                batch_len = base_len;
+            }
+            batch.push(item.clone());
+            batch_len += item_len;
+        }
+        if batch.len() > 0 {
+            batches.push(batch);
+        }
+        batches
+    }
+
+    /// Split a pushed Blocks or Microblocks payload into one or more payloads of the same type,
+    /// each of which serializes to at most `max_payload_len` bytes and is independently valid.
+    /// Blocks payloads are additionally kept under MAX_BLOCKS_DATA_PUSH_LEN.
+    /// Anything that cannot fit into a payload on its own is dropped, along with everything
+    /// after it.  All other payload types are returned as-is.
+    pub fn split_push_payload(
+        payload: &StacksMessageType,
+        max_payload_len: u32,
+    ) -> Vec<StacksMessageType> {
+        let max_len = match payload {
+            StacksMessageType::Blocks(_) => cmp::min(max_payload_len, MAX_BLOCKS_DATA_PUSH_LEN),
+            _ => max_payload_len,
+        } as usize;
+        if payload.serialize_to_vec().len() <= max_len {
+            match payload {
+                StacksMessageType::Blocks(ref data)
+                    if data.blocks.len() > BLOCKS_PUSHED_MAX as usize => {}
+                _ => {
+                    return vec![payload.clone()];
+                }
+            }
+        }
+
+        // every payload starts with its message ID and the length prefix of its list
+        match payload {
+            StacksMessageType::Blocks(ref data) => PeerNetwork::partition_by_len(
+                &data.blocks,
+                1 + 4,
+                max_len,
+                BLOCKS_PUSHED_MAX as usize,
+            )
+            .into_iter()
+            .map(|blocks| StacksMessageType::Blocks(BlocksData { blocks }))
+            .collect(),
+            StacksMessageType::Microblocks(ref data) => PeerNetwork::partition_by_len(
+                &data.microblocks,
+                1 + 32 + 4,
+                max_len,
+                usize::max_value(),
+            )
+            .into_iter()
+            .map(|microblocks| {
+                StacksMessageType::Microblocks(MicroblocksData {
+                    index_anchor_block: data.index_anchor_block.clone(),
+                    microblocks,
+                })
+            })
+            .collect(),
+            _ => vec![payload.clone()],
+        }
+    }
+
+    /// Turn a pushed Blocks payload into one CompactBlock payload per block, for a peer that
+    /// accepts them.  The `prefill` transactions are sent in full along with the coinbase.  Any
+    /// compact block that still exceeds `max_payload_len` is dropped.
+    /// Returns None for all other payload types.
+    pub fn compact_push_payload(
+        payload: &StacksMessageType,
+        max_payload_len: u32,
+        prefill: &HashSet<Txid>,
+    ) -> Option<Vec<StacksMessageType>> {
+        match payload {
+            StacksMessageType::Blocks(ref data) => Some(
+                data.blocks
+                    .iter()
+                    .map(|(consensus_hash, block)| {
+                        StacksMessageType::CompactBlock(CompactBlockData::from_block_prefilled(
+                            consensus_hash,
+                            block,
+                            prefill,
+                        ))
+                    })
+                    .filter(|payload| payload.serialize_to_vec().len() <= max_payload_len as usize)
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Pick the transactions of a block to send in full when we push it as a compact block: the
+    /// ones we relayed since `since_ms`, which our neighbors are the least likely to have in
+    /// their mempools yet.  At most `max_prefill` are picked, highest fee first.
+    pub fn compact_block_prefill(
+        block: &StacksBlock,
+        recent_relayed_txs: &HashMap<Txid, u128>,
+        since_ms: u128,
+        max_prefill: usize,
+    ) -> HashSet<Txid> {
+        let mut candidates: Vec<_> = block
+            .txs
+            .iter()
+            .filter(|tx| match recent_relayed_txs.get(&tx.txid()) {
+                Some(relayed_at) => *relayed_at >= since_ms,
+                None => false,
+            })
+            .collect();
+        candidates.sort_by(|tx1, tx2| tx2.get_tx_fee().cmp(&tx1.get_tx_fee()));
+        candidates
+            .into_iter()
+            .take(max_prefill)
+            .map(|tx| tx.txid())
+            .collect()
+    }
+
+    /// Remember when we relayed a transaction, and forget the ones we relayed too long ago to be
+    /// worth sending in full in a compact block.
+    fn record_relayed_tx(&mut self, txid: Txid) {
+        let window_ms = self.connection_opts.compact_block_prefill_window_ms as u128;
+        if window_ms == 0 || self.connection_opts.compact_block_max_prefill_txs == 0 {
+            return;
+        }
+        let now_ms = get_epoch_time_ms();
+        self.recent_relayed_txs
+            .retain(|_, relayed_at| *relayed_at + window_ms >= now_ms);
+        self.recent_relayed_txs.entry(txid).or_insert(now_ms);
+    }
+
+    /// Broadcast a message to a list of neighbors.
+    /// Pushed blocks and microblocks are split up as needed to honor each neighbor's
+    /// advertised payload limit.  Pushed blocks go out as compact blocks to neighbors that
+    /// accept them, if we do too.
     pub fn broadcast_message(
         &mut self,
         mut neighbor_keys: Vec<NeighborKey>,
         relay_hints: Vec<RelayData>,
         message_payload: StacksMessageType,
     ) -> () {
+        if !self.connection_opts.network_mode.relays_pushes() {
+            debug!(
+                "{:?}: Will not broadcast '{}' in {} mode",
+                &self.local_peer,
+                message_payload.get_message_description(),
+                self.connection_opts.network_mode.as_str()
+            );
+            return;
+        }
         debug!(
             "{:?}: Will broadcast '{}' to up to {} neighbors; relayed by {:?}",
             &self.local_peer,
@@ -631,6 +906,28 @@ impl PeerNetwork {
             neighbor_keys.len(),
             &relay_hints
         );
+
+        let send_compact_blocks = self.features().is_enabled(NodeFeature::CompactBlocks);
+        let mut prefill = HashSet::new();
+        match message_payload {
+            StacksMessageType::Transaction(ref tx) => {
+                self.record_relayed_tx(tx.txid());
+            }
+            StacksMessageType::Blocks(ref data) if send_compact_blocks => {
+                let since_ms = get_epoch_time_ms()
+                    .saturating_sub(self.connection_opts.compact_block_prefill_window_ms as u128);
+                for (_, block) in data.blocks.iter() {
+                    prefill.extend(PeerNetwork::compact_block_prefill(
+                        block,
+                        &self.recent_relayed_txs,
+                        since_ms,
+                        self.connection_opts.compact_block_max_prefill_txs as usize,
+                    ));
+                }
+            }
+            _ => {}
+        }
+
         for nk in neighbor_keys.drain(..) {
             if let Some(event_id) = self.events.get(&nk) {
                 let event_id = *event_id;
@@ -656,28 +953,74 @@ impl PeerNetwork {
                         continue;
                     }
 
-                    match convo.sign_and_forward(
-                        &self.local_peer,
-                        &self.chain_view,
-                        relay_hints.clone(),
-                        message_payload.clone(),
-                    ) {
-                        Ok(rh) => {
-                            debug!(
-                                "{:?}: Broadcasted '{}' to {:?}",
-                                &self.local_peer,
-                                message_payload.get_message_description(),
-                                &nk
-                            );
-                            self.add_relay_handle(event_id, rh);
-                        }
-                        Err(e) => {
-                            warn!(
-                                "{:?}: Failed to broadcast message to {:?}: {:?}",
-                                &self.local_peer, nk, &e
-                            );
+                    let compact_payloads = if send_compact_blocks
+                        && convo.supports_service(ServiceFlags::COMPACT_BLOCKS)
+                    {
+                        PeerNetwork::compact_push_payload(
+                            &message_payload,
+                            convo.peer_max_payload_len,
+                            &prefill,
+                        )
+                    } else {
+                        None
+                    };
+                    let payloads = compact_payloads.unwrap_or_else(|| {
+                        PeerNetwork::split_push_payload(
+                            &message_payload,
+                            convo.peer_max_payload_len,
+                        )
+                    });
+                    if payloads.len() == 0 {
+                        debug!(
+                            "{:?}: Do not broadcast '{}' to {:?}: it exceeds the peer's limit of {} bytes",
+                            &self.local_peer,
+                            message_payload.get_message_description(),
+                            &nk,
+                            convo.peer_max_payload_len
+                        );
+                        continue;
+                    }
+
+                    let mut relay_handles = vec![];
+                    for payload in payloads.into_iter() {
+                        let payload_desc = payload.get_message_description();
+                        let compact_txs = match payload {
+                            StacksMessageType::CompactBlock(ref data) => {
+                                Some((data.txs.len(), data.txids.len() - data.txs.len()))
+                            }
+                            _ => None,
+                        };
+                        match convo.sign_and_forward(
+                            &self.local_peer,
+                            &self.chain_view,
+                            relay_hints.clone(),
+                            payload,
+                        ) {
+                            Ok(rh) => {
+                                debug!(
+                                    "{:?}: Broadcasted '{}' to {:?}",
+                                    &self.local_peer, payload_desc, &nk
+                                );
+                                if let Some((num_full, num_txids)) = compact_txs {
+                                    increment_compact_block_sent_txs(
+                                        num_full as u64,
+                                        num_txids as u64,
+                                    );
+                                }
+                                relay_handles.push(rh);
+                            }
+                            Err(e) => {
+                                warn!(
+                                    "{:?}: Failed to broadcast message to {:?}: {:?}",
+                                    &self.local_peer, nk, &e
+                                );
+                                break;
+                            }
                         }
                     }
+                    for rh in relay_handles.into_iter() {
+                        self.add_relay_handle(event_id, rh);
+                    }
                 } else {
                     debug!(
                         "{:?}: No open conversation for {:?}; will not broadcast {:?} to it",
@@ -714,10 +1057,7 @@ impl PeerNetwork {
     }
 
     /// Count how many connections to a given IP address we have
-    pub fn count_ip_connections(
-        ipaddr: &SocketAddr,
-        sockets: &HashMap<usize, mio_net::TcpStream>,
-    ) -> u64 {
+    pub fn count_ip_connections(ipaddr: &SocketAddr, sockets: &HashMap<usize, NetSocket>) -> u64 {
         let mut ret = 0;
         for (_, socket) in sockets.iter() {
             match socket.peer_addr() {
@@ -749,6 +1089,16 @@ impl PeerNetwork {
     ) -> Result<usize, net_error> {
         debug!("{:?}: connect to {:?}", &self.local_peer, neighbor);
 
+        if !self.connection_opts.network_mode.initiates_outbound() {
+            debug!(
+                "{:?}: will not connect to {:?} in {} mode",
+                &self.local_peer,
+                neighbor,
+                self.connection_opts.network_mode.as_str()
+            );
+            return Err(net_error::Denied);
+        }
+
         if check_denied {
             // don't talk to our bind address
             if self.is_bound(neighbor) {
@@ -850,6 +1200,10 @@ impl PeerNetwork {
             if !convo.is_authenticated() {
                 continue;
             }
+            if !convo.supports_service(ServiceFlags::RELAY) {
+                // peer doesn't forward pushed data
+                continue;
+            }
             let nk = convo.to_neighbor_key();
             if convo.is_outbound() {
                 outbound_neighbors.push(nk);
@@ -941,13 +1295,17 @@ impl PeerNetwork {
                 Ok(())
             }
             NetworkRequest::AdvertizeBlocks(blocks) => {
-                if !(cfg!(test) && self.connection_opts.disable_block_advertisement) {
+                if !(cfg!(test) && self.connection_opts.disable_block_advertisement)
+                    && self.connection_opts.network_mode.relays_pushes()
+                {
                     self.advertize_blocks(blocks)?;
                 }
                 Ok(())
             }
             NetworkRequest::AdvertizeMicroblocks(mblocks) => {
-                if !(cfg!(test) && self.connection_opts.disable_block_advertisement) {
+                if !(cfg!(test) && self.connection_opts.disable_block_advertisement)
+                    && self.connection_opts.network_mode.relays_pushes()
+                {
                     self.advertize_microblocks(mblocks)?;
                 }
                 Ok(())
@@ -970,13 +1328,22 @@ impl PeerNetwork {
                         Ok(all_neighbors.into_iter().collect())
                     }
                     StacksMessageType::Microblocks(ref data) => {
-                        // send to each neighbor that needs at least one
+                        // microblocks we mined ourselves go straight to our subscribers
+                        let pushed = if relay_hints.len() == 0 {
+                            self.push_microblocks_to_subscribers(data)
+                        } else {
+                            HashSet::new()
+                        };
+
+                        // send to each other neighbor that needs at least one
                         let mut all_neighbors = HashSet::new();
                         for mblock in data.microblocks.iter() {
                             let mut neighbors =
                                 self.sample_broadcast_peers(&relay_hints, mblock)?;
                             for nk in neighbors.drain(..) {
-                                all_neighbors.insert(nk);
+                                if !pushed.contains(&nk) {
+                                    all_neighbors.insert(nk);
+                                }
                             }
                         }
                         Ok(all_neighbors.into_iter().collect())
@@ -984,6 +1351,18 @@ impl PeerNetwork {
                     StacksMessageType::Transaction(ref data) => {
                         self.sample_broadcast_peers(&relay_hints, data)
                     }
+                    StacksMessageType::AttachmentsAvailable(ref data) => {
+                        // only peers that sync attachments have any use for it
+                        let neighbors = self.sample_broadcast_peers(&relay_hints, data)?;
+                        Ok(neighbors
+                            .into_iter()
+                            .filter(|nk| {
+                                self.get_convo(nk)
+                                    .map(|convo| convo.supports_service(ServiceFlags::ATLAS))
+                                    .unwrap_or(false)
+                            })
+                            .collect())
+                    }
                     _ => {
                         // not suitable for broadcast
                         return Err(net_error::InvalidMessage);
@@ -1215,6 +1594,19 @@ impl PeerNetwork {
             return Err(net_error::Denied);
         }
 
+        // not in this direction?
+        let mode = self.connection_opts.network_mode;
+        if (outbound && !mode.initiates_outbound()) || (!outbound && !mode.accepts_inbound()) {
+            debug!(
+                "{:?}: will not register {} peer {:?} in {} mode",
+                &self.local_peer,
+                if outbound { "outbound" } else { "inbound" },
+                neighbor_key,
+                mode.as_str()
+            );
+            return Err(net_error::Denied);
+        }
+
         // denied?
         if PeerDB::is_peer_denied(
             &self.peerdb.conn(),
@@ -1267,6 +1659,15 @@ impl PeerNetwork {
             return Err(net_error::ConnectionCycle);
         }
 
+        // denied by public key?
+        if PeerDB::is_pubkey_hash_denied(&self.peerdb.conn(), pubkh)? {
+            info!(
+                "{:?}: Peer {:?} with public key hash {} is denied; dropping",
+                &self.local_peer, nk, pubkh
+            );
+            return Err(net_error::Denied);
+        }
+
         self.can_register_peer(nk, outbound).and_then(|_| {
             let other_events = self.get_pubkey_events(pubkh);
             if other_events.len() > 0 {
@@ -1297,6 +1698,10 @@ impl PeerNetwork {
         socket: mio_net::TcpStream,
         outbound: bool,
     ) -> Result<(), net_error> {
+        let socket = match self.network {
+            Some(ref mut network) => network.start_socket(event_id, socket)?,
+            None => NetSocket::Mio(socket),
+        };
         let client_addr = match socket.peer_addr() {
             Ok(addr) => addr,
             Err(e) => {
@@ -1357,6 +1762,7 @@ impl PeerNetwork {
             event_id,
         );
         new_convo.set_public_key(pubkey_opt);
+        new_convo.push_backpressure = self.push_backpressure;
 
         debug!(
             "{:?}: Registered {} as event {} ({:?},outbound={})",
@@ -1401,10 +1807,10 @@ impl PeerNetwork {
     }
 
     /// Deregister a socket from our p2p network instance.
-    fn deregister_socket(&mut self, event_id: usize, socket: mio_net::TcpStream) -> () {
+    fn deregister_socket(&mut self, event_id: usize, socket: NetSocket) -> () {
         match self.network {
             Some(ref mut network) => {
-                let _ = network.deregister(event_id, &socket);
+                let _ = network.deregister_socket(event_id, socket);
             }
             None => {}
         }
@@ -1443,7 +1849,7 @@ impl PeerNetwork {
             Some(ref mut network) => {
                 // deregister socket if connected and registered already
                 if let Some(socket) = self.sockets.remove(&event_id) {
-                    let _ = network.deregister(event_id, &socket);
+                    let _ = network.deregister_socket(event_id, socket);
                 }
                 // deregister socket if still connecting
                 if let Some((socket, ..)) = self.connecting.remove(&event_id) {
@@ -1453,6 +1859,9 @@ impl PeerNetwork {
         }
 
         self.relay_handles.remove(&event_id);
+        self.expired_key_rehandshakes.remove(&event_id);
+        self.microblock_subscribers.remove(&event_id);
+        self.microblock_subscriptions.remove(&event_id);
         self.peers.remove(&event_id);
         self.pending_messages.remove(&event_id);
     }
@@ -1576,8 +1985,9 @@ impl PeerNetwork {
         chainstate: &mut StacksChainState,
         header_cache: &mut BlockHeaderCache,
         chain_view: &BurnchainView,
+        tls_config: Option<&TlsConfig>,
         event_id: usize,
-        client_sock: &mut mio_net::TcpStream,
+        client_sock: &mut NetSocket,
         convo: &mut ConversationP2P,
     ) -> Result<(Vec<StacksMessage>, bool), net_error> {
         // get incoming bytes and update the state of this conversation.
@@ -1620,8 +2030,10 @@ impl PeerNetwork {
         let unhandled = match chat_res {
             Err(e) => {
                 debug!(
-                    "Failed to converse on event {} (socket {:?}): {:?}",
-                    event_id, &client_sock, &e
+                    "Failed to converse on event {} (socket {:?}): {}",
+                    event_id,
+                    &client_sock,
+                    e.with_context(Some(convo.to_neighbor_key()), None)
                 );
                 convo_dead = true;
                 vec![]
@@ -1630,6 +2042,10 @@ impl PeerNetwork {
         };
 
         if !convo_dead {
+            // switch to TLS once the first handshake completes, if we both want it.  Any reply
+            // we just queued (i.e. a HandshakeAccept) still goes out in the clear.
+            convo.negotiate_tls(local_peer, tls_config);
+
             // (continue) sending out data in this conversation, if the conversation is still
             // ongoing
             let send_res = convo.send(client_sock);
@@ -1709,6 +2125,7 @@ impl PeerNetwork {
                         chainstate,
                         &mut self.header_cache,
                         &self.chain_view,
+                        self.tls_config.as_ref(),
                         *event_id,
                         client_sock,
                         convo,
@@ -1817,9 +2234,579 @@ impl PeerNetwork {
                 };
             }
         }
-        for (event_id, handle) in relay_handles.drain() {
-            self.add_relay_handle(event_id, handle);
+        for (event_id, handle) in relay_handles.drain() {
+            self.add_relay_handle(event_id, handle);
+        }
+    }
+
+    /// Find authenticated peers whose advertised key has expired as of the current burnchain
+    /// height.  Send each one a handshake, so it will reply with its current key, and evict any
+    /// peer that still has an expired key after neighbor_request_timeout seconds.
+    /// Returns the list of peers that must be evicted.
+    fn find_expired_key_peers(&mut self) -> Vec<usize> {
+        let now = get_epoch_time_secs();
+        let burn_block_height = self.chain_view.burn_block_height;
+        let mut to_evict = vec![];
+        let mut relay_handles = HashMap::new();
+
+        // forget about peers that re-handshaked with a fresh key
+        let peers = &self.peers;
+        self.expired_key_rehandshakes.retain(|event_id, _| {
+            peers
+                .get(event_id)
+                .map(|convo| convo.is_key_expired(burn_block_height))
+                .unwrap_or(false)
+        });
+
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.is_key_expired(burn_block_height) {
+                continue;
+            }
+
+            if let Some(requested_at) = self.expired_key_rehandshakes.get(event_id) {
+                if *requested_at + self.connection_opts.neighbor_request_timeout < now {
+                    debug!(
+                        "{:?}: Evict {:?}: key expired at {} (burn height is {}), and no new key since {}",
+                        &self.local_peer,
+                        &convo,
+                        convo.peer_expire_block_height,
+                        burn_block_height,
+                        requested_at
+                    );
+                    to_evict.push(*event_id);
+                }
+                continue;
+            }
+
+            debug!(
+                "{:?}: Key for {:?} expired at {} (burn height is {}); re-handshake",
+                &self.local_peer, &convo, convo.peer_expire_block_height, burn_block_height
+            );
+
+            self.expired_key_rehandshakes.insert(*event_id, now);
+
+            let handshake_data = HandshakeData::from_local_peer(&self.local_peer);
+            let payload = StacksMessageType::Handshake(handshake_data);
+            match convo.sign_message(&self.chain_view, &self.local_peer.private_key, payload) {
+                Ok(handshake) => {
+                    // NOTE: use "relay" here because we don't intend to wait for a reply
+                    // (the conversational logic will store the peer's new key)
+                    match convo.relay_signed_message(handshake) {
+                        Ok(handle) => {
+                            relay_handles.insert(*event_id, handle);
+                        }
+                        Err(_e) => {
+                            debug!("Outbox to {:?} is full; cannot re-handshake", &convo);
+                        }
+                    }
+                }
+                Err(e) => {
+                    debug!(
+                        "Unable to create handshake message for {:?}: {:?}",
+                        &convo, &e
+                    );
+                }
+            }
+        }
+
+        for (event_id, handle) in relay_handles.drain() {
+            self.add_relay_handle(event_id, handle);
+        }
+        to_evict
+    }
+
+    /// Re-handshake with peers whose handshake data is at least rehandshake_interval burn blocks
+    /// old, so their data URLs, services, and key expiries stay current.  The conversational logic
+    /// stores whatever the peer sends back.  Returns the number of handshakes sent.
+    fn queue_rehandshakes(&mut self) -> usize {
+        let interval = self.connection_opts.rehandshake_interval;
+        let burn_block_height = self.chain_view.burn_block_height;
+        let mut to_handshake = vec![];
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.needs_rehandshake(burn_block_height, interval)
+                || self.expired_key_rehandshakes.contains_key(event_id)
+            {
+                continue;
+            }
+
+            debug!(
+                "{:?}: Handshake data for {:?} is from burn height {} (now {}); re-handshake",
+                &self.local_peer, &convo, convo.handshake_burn_block_height, burn_block_height
+            );
+
+            // don't ask again for another interval, whether or not the peer answers
+            convo.rehandshake_burn_block_height = burn_block_height;
+            to_handshake.push(*event_id);
+        }
+        self.send_handshakes(to_handshake)
+    }
+
+    /// Send our current handshake data to the given authenticated peers, without waiting for their
+    /// replies.  Returns the number of handshakes queued.
+    fn send_handshakes(&mut self, event_ids: Vec<usize>) -> usize {
+        let mut relay_handles = HashMap::new();
+        for event_id in event_ids.iter() {
+            let convo = match self.peers.get_mut(event_id) {
+                Some(convo) if convo.is_authenticated() => convo,
+                _ => {
+                    continue;
+                }
+            };
+
+            let handshake_data = HandshakeData::from_local_peer(&self.local_peer);
+            let payload = StacksMessageType::Handshake(handshake_data);
+            match convo.sign_message(&self.chain_view, &self.local_peer.private_key, payload) {
+                Ok(handshake) => {
+                    // NOTE: use "relay" here because we don't intend to wait for a reply
+                    match convo.relay_signed_message(handshake) {
+                        Ok(handle) => {
+                            relay_handles.insert(*event_id, handle);
+                        }
+                        Err(_e) => {
+                            debug!("Outbox to {:?} is full; cannot re-handshake", &convo);
+                        }
+                    }
+                }
+                Err(e) => {
+                    debug!(
+                        "Unable to create handshake message for {:?}: {:?}",
+                        &convo, &e
+                    );
+                }
+            }
+        }
+
+        let ret = relay_handles.len();
+        for (event_id, handle) in relay_handles.drain() {
+            self.add_relay_handle(event_id, handle);
+        }
+        ret
+    }
+
+    /// Re-handshake with peers whose keys have expired, and evict the ones that do not come back
+    /// with a fresh key.  Returns the number of peers evicted.
+    fn evict_expired_key_peers(&mut self) -> usize {
+        let to_evict = self.find_expired_key_peers();
+        let ret = to_evict.len();
+        for event_id in to_evict.into_iter() {
+            self.deregister_peer(event_id);
+        }
+        ret
+    }
+
+    /// Periodically count the peer versions our connected and crawled peers advertise, and warn
+    /// if too many of them run a newer version than ours.
+    fn check_peer_versions(&mut self) {
+        let interval = self.connection_opts.peer_version_check_interval;
+        let now = get_epoch_time_secs();
+        if interval == 0 || self.peer_versions.last_checked_at + interval > now {
+            return;
+        }
+        self.peer_versions.last_checked_at = now;
+
+        let frontier = match PeerDB::get_all_peers(self.peerdb.conn()) {
+            Ok(frontier) => frontier,
+            Err(e) => {
+                debug!(
+                    "{:?}: PeerVersions: Failed to load frontier: {:?}",
+                    &self.local_peer, &e
+                );
+                return;
+            }
+        };
+        let census =
+            PeerVersionCensus::take(&self.peers, &frontier, self.chain_view.burn_block_height);
+        let num_observed = census.num_observed();
+        let num_newer = census.num_observed_newer(self.peer_version);
+        let pct_newer = census.pct_observed_newer(self.peer_version);
+
+        debug!(
+            "{:?}: PeerVersions: {} peers observed, {} advertise a newer version than ours ({:08x})",
+            &self.local_peer, num_observed, num_newer, self.peer_version
+        );
+        update_peer_versions(&census.connected, &census.observed, pct_newer as i64);
+
+        let alert_pct = self.connection_opts.peer_version_alert_pct;
+        if let Some(newest) = self
+            .peer_versions
+            .update(census, self.peer_version, alert_pct)
+        {
+            warn!(
+                "{:?}: {} of {} observed peers ({}%) advertise a newer peer version than ours ({:08x} > {:08x}) -- this node may be outdated, and should be upgraded",
+                &self.local_peer,
+                num_newer,
+                num_observed,
+                pct_newer,
+                newest,
+                self.peer_version
+            );
+            increment_peer_version_alerts();
+        }
+    }
+
+    /// Periodically tell our peers which data URLs we recently failed to reach ourselves, so they
+    /// can try other hosts first.  Only sent to peers that can take the report.
+    fn send_data_url_health(&mut self) {
+        let interval = self.connection_opts.data_url_health_interval;
+        let now = get_epoch_time_secs();
+        if interval == 0 || self.data_url_health_last_sent + interval > now {
+            return;
+        }
+        self.data_url_health_last_sent = now;
+
+        let unreachable = self
+            .data_url_stats
+            .get_unreachable_urls(now, MAX_DATA_URL_HEALTH_REPORTS as usize);
+        if unreachable.len() == 0 {
+            return;
+        }
+
+        debug!(
+            "{:?}: DataUrlHealth: report {} unreachable data URLs: {:?}",
+            &self.local_peer,
+            unreachable.len(),
+            &unreachable
+        );
+        let mut relay_handles = HashMap::new();
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.is_authenticated() || !convo.supports_service(ServiceFlags::URL_HEALTH) {
+                continue;
+            }
+            let payload = StacksMessageType::DataUrlHealth(DataUrlHealthData {
+                unreachable: unreachable.clone(),
+            });
+            match convo.sign_message(&self.chain_view, &self.local_peer.private_key, payload) {
+                Ok(msg) => match convo.relay_signed_message(msg) {
+                    Ok(handle) => {
+                        relay_handles.insert(*event_id, handle);
+                    }
+                    Err(_e) => {
+                        debug!("Outbox to {:?} is full; cannot send DataUrlHealth", &convo);
+                    }
+                },
+                Err(e) => {
+                    debug!(
+                        "Unable to create DataUrlHealth message for {:?}: {:?}",
+                        &convo, &e
+                    );
+                }
+            }
+        }
+        for (event_id, handle) in relay_handles.drain() {
+            self.add_relay_handle(event_id, handle);
+        }
+    }
+
+    /// Handle a peer's report of the data URLs it couldn't reach.  The report counts for the
+    /// network the peer connected to us from, not for the peer, so that many peers run from one
+    /// network can't outvote the rest.
+    fn handle_unsolicited_DataUrlHealth(&mut self, event_id: usize, data: &DataUrlHealthData) {
+        let reporter_prefix = match self.peers.get(&event_id) {
+            Some(convo) => convo.peer_addrbytes.network_prefix(),
+            None => {
+                return;
+            }
+        };
+        debug!(
+            "{:?}: DataUrlHealth: event {} reports {} unreachable data URLs",
+            &self.local_peer,
+            event_id,
+            data.unreachable.len()
+        );
+        self.data_url_stats.record_unreachable_reports(
+            reporter_prefix,
+            &data.unreachable,
+            get_epoch_time_secs(),
+        );
+    }
+
+    /// Ask each outbound peer that pushes the microblocks it mines to push them to us, if we
+    /// haven't already.  Returns the number of subscriptions sent.
+    fn subscribe_microblock_pushes(&mut self) -> usize {
+        if !self.features().is_enabled(NodeFeature::MicroblockPush) {
+            return 0;
+        }
+
+        let mut relay_handles = HashMap::new();
+        for (event_id, convo) in self.peers.iter_mut() {
+            if !convo.stats.outbound
+                || !convo.is_authenticated()
+                || !convo.supports_service(ServiceFlags::MICROBLOCK_PUSH)
+                || self.microblock_subscriptions.contains(event_id)
+            {
+                continue;
+            }
+            let payload = StacksMessageType::MicroblocksSubscribe(MicroblocksSubscribeData {
+                subscribe: true,
+            });
+            match convo.sign_message(&self.chain_view, &self.local_peer.private_key, payload) {
+                Ok(msg) => match convo.relay_signed_message(msg) {
+                    Ok(handle) => {
+                        debug!(
+                            "{:?}: Subscribe to microblock pushes from {:?}",
+                            &self.local_peer, &convo
+                        );
+                        relay_handles.insert(*event_id, handle);
+                    }
+                    Err(_e) => {
+                        debug!(
+                            "Outbox to {:?} is full; cannot send MicroblocksSubscribe",
+                            &convo
+                        );
+                    }
+                },
+                Err(e) => {
+                    debug!(
+                        "Unable to create MicroblocksSubscribe message for {:?}: {:?}",
+                        &convo, &e
+                    );
+                }
+            }
+        }
+
+        let num_sent = relay_handles.len();
+        for (event_id, handle) in relay_handles.drain() {
+            self.microblock_subscriptions.insert(event_id);
+            self.add_relay_handle(event_id, handle);
+        }
+        num_sent
+    }
+
+    /// Handle a peer's request to start or stop receiving the microblocks we mine.  Requests are
+    /// ignored if we don't push microblocks, and new subscribers are turned away once we have
+    /// max_microblock_subscribers of them.
+    fn handle_unsolicited_MicroblocksSubscribe(
+        &mut self,
+        event_id: usize,
+        data: &MicroblocksSubscribeData,
+    ) {
+        if !data.subscribe {
+            if self.microblock_subscribers.remove(&event_id) {
+                debug!(
+                    "{:?}: MicroblocksSubscribe: event {} unsubscribed",
+                    &self.local_peer, event_id
+                );
+            }
+            return;
+        }
+        if !self.features().is_enabled(NodeFeature::MicroblockPush) {
+            debug!(
+                "{:?}: MicroblocksSubscribe: ignore subscription from event {}: we do not push microblocks",
+                &self.local_peer, event_id
+            );
+            return;
+        }
+        if !self.microblock_subscribers.contains(&event_id)
+            && (self.microblock_subscribers.len() as u64)
+                >= self.connection_opts.max_microblock_subscribers
+        {
+            debug!(
+                "{:?}: MicroblocksSubscribe: turn away event {}: already have {} subscribers",
+                &self.local_peer,
+                event_id,
+                self.microblock_subscribers.len()
+            );
+            return;
+        }
+        debug!(
+            "{:?}: MicroblocksSubscribe: event {} subscribed",
+            &self.local_peer, event_id
+        );
+        self.microblock_subscribers.insert(event_id);
+    }
+
+    /// Push microblocks we just mined to each subscriber, ahead of the usual relay.  A subscriber
+    /// that still has microblock_push_max_pending messages queued is skipped, so a slow peer can't
+    /// make us buffer our whole stream for it; it will learn of the microblocks the usual way.
+    /// Returns the subscribers we pushed to.
+    fn push_microblocks_to_subscribers(&mut self, data: &MicroblocksData) -> HashSet<NeighborKey> {
+        let mut pushed = HashSet::new();
+        if self.microblock_subscribers.len() == 0 {
+            return pushed;
+        }
+
+        let payload = StacksMessageType::Microblocks(data.clone());
+        let mut relay_handles = vec![];
+        for event_id in self.microblock_subscribers.iter() {
+            let convo = match self.peers.get_mut(event_id) {
+                Some(convo) if convo.is_authenticated() => convo,
+                _ => {
+                    continue;
+                }
+            };
+            let pending = self
+                .relay_handles
+                .get(event_id)
+                .map(|handles| handles.len() as u64)
+                .unwrap_or(0);
+            if pending >= self.connection_opts.microblock_push_max_pending {
+                debug!(
+                    "{:?}: Skip pushing '{}' to {:?}: {} messages still queued for it",
+                    &self.local_peer,
+                    payload.get_message_description(),
+                    &convo,
+                    pending
+                );
+                continue;
+            }
+
+            let payloads = PeerNetwork::split_push_payload(&payload, convo.peer_max_payload_len);
+            let mut sent = false;
+            for payload in payloads.into_iter() {
+                match convo.sign_and_forward(&self.local_peer, &self.chain_view, vec![], payload) {
+                    Ok(rh) => {
+                        relay_handles.push((*event_id, rh));
+                        sent = true;
+                    }
+                    Err(e) => {
+                        debug!(
+                            "{:?}: Failed to push microblocks to {:?}: {:?}",
+                            &self.local_peer, &convo, &e
+                        );
+                        break;
+                    }
+                }
+            }
+            if sent {
+                pushed.insert(convo.to_neighbor_key());
+            }
+        }
+
+        debug!(
+            "{:?}: Pushed '{}' to {} of {} microblock subscribers",
+            &self.local_peer,
+            payload.get_message_description(),
+            pushed.len(),
+            self.microblock_subscribers.len()
+        );
+        for (event_id, rh) in relay_handles.into_iter() {
+            self.add_relay_handle(event_id, rh);
+        }
+        pushed
+    }
+
+    /// Handle a peer's announcement of attachments it now has: retry the downloads that wait on
+    /// them right away.
+    fn handle_unsolicited_AttachmentsAvailable(
+        &mut self,
+        event_id: usize,
+        data: &AttachmentsAvailableData,
+    ) {
+        if let Some(ref mut downloader) = self.attachments_downloader {
+            let woken = downloader.wake_batches(&data.available);
+            debug!(
+                "{:?}: AttachmentsAvailable: event {} announced {} attachments, waking up {} Atlas batches",
+                &self.local_peer,
+                event_id,
+                data.available.len(),
+                woken
+            );
+        }
+    }
+
+    /// Should we refuse block pushes, given how many attachable staging blocks we have left to
+    /// process?  Once on, backpressure stays on until the backlog has drained to half the limit, so
+    /// we don't flap between the two states.  A limit of 0 disables it.
+    pub fn staging_backlog_backpressure(active: bool, backlog: u64, max_backlog: u64) -> bool {
+        if max_backlog == 0 {
+            false
+        } else if active {
+            backlog > max_backlog / 2
+        } else {
+            backlog > max_backlog
+        }
+    }
+
+    /// Periodically count the attachable staging blocks we have yet to process, and note whether
+    /// there are too many of them.
+    fn check_staging_backlog(&mut self, chainstate: &StacksChainState) {
+        let interval = self.connection_opts.staging_backlog_check_interval;
+        let now = get_epoch_time_secs();
+        if self.staging_backlog_checked_at + interval > now {
+            return;
+        }
+        self.staging_backlog_checked_at = now;
+
+        let max_backlog = self.connection_opts.max_staging_blocks_backlog;
+        let backlog = if max_backlog > 0 {
+            match StacksChainState::count_attachable_staging_blocks(
+                chainstate.db(),
+                max_backlog + 1,
+                0,
+            ) {
+                Ok(cnt) => cnt,
+                Err(e) => {
+                    warn!("Failed to count attachable staging blocks: {:?}", &e);
+                    return;
+                }
+            }
+        } else {
+            0
+        };
+
+        let exceeded = PeerNetwork::staging_backlog_backpressure(
+            self.staging_backlog_exceeded,
+            backlog,
+            max_backlog,
+        );
+        if exceeded != self.staging_backlog_exceeded {
+            if exceeded {
+                warn!(
+                    "{:?}: {} attachable staging blocks left to process (limit {})",
+                    &self.local_peer, backlog, max_backlog
+                );
+            } else {
+                info!(
+                    "{:?}: {} attachable staging blocks left to process",
+                    &self.local_peer, backlog
+                );
+            }
+        }
+        self.staging_backlog_exceeded = exceeded;
+    }
+
+    /// Tell the network how low the node is on disk space.  Block pushes are refused from
+    /// `DiskSpaceLevel::StopPushes` on, and Atlas downloads are paused from
+    /// `DiskSpaceLevel::PauseDownloads` on.
+    pub fn set_disk_space_level(&mut self, level: DiskSpaceLevel) {
+        self.disk_space_level = level;
+    }
+
+    /// While we have too many staging blocks to process or too little disk space to store more,
+    /// stop advertising the relay service and NACK pushed blocks and microblocks, so our
+    /// neighbors push them to peers that can keep up.  Neighbors learn about the service change
+    /// through a fresh handshake.
+    fn update_push_backpressure(&mut self) {
+        let backpressure =
+            self.staging_backlog_exceeded || self.disk_space_level >= DiskSpaceLevel::StopPushes;
+        if backpressure == self.push_backpressure {
+            return;
+        }
+
+        if backpressure {
+            warn!(
+                "{:?}: refusing block pushes (staging backlog exceeded: {}, disk space: {})",
+                &self.local_peer, self.staging_backlog_exceeded, self.disk_space_level
+            );
+        } else {
+            info!("{:?}: accepting block pushes again", &self.local_peer);
+        }
+
+        self.push_backpressure = backpressure;
+        match self.load_local_peer() {
+            Ok(local_peer) => {
+                self.local_peer = local_peer;
+            }
+            Err(e) => {
+                warn!("Failed to reload local peer: {:?}", &e);
+            }
+        }
+        for (_, convo) in self.peers.iter_mut() {
+            convo.push_backpressure = backpressure;
         }
+
+        let event_ids = self.peers.keys().map(|event_id| *event_id).collect();
+        self.send_handshakes(event_ids);
     }
 
     /// Remove unresponsive peers
@@ -2044,6 +3031,10 @@ impl PeerNetwork {
             test_debug!("neighbor walk is disabled");
             return Ok(true);
         }
+        if !self.connection_opts.network_mode.initiates_outbound() {
+            // walking means connecting out to new neighbors
+            return Ok(true);
+        }
 
         debug!("{:?}: walk peer graph", &self.local_peer);
 
@@ -2069,32 +3060,38 @@ impl PeerNetwork {
 
         debug!("{:?}: begin obtaining public IP address", &self.local_peer);
 
-        // pick a random outbound conversation to one of the initial neighbors
-        let mut idx = thread_rng().gen::<usize>() % self.peers.len();
-        for _ in 0..self.peers.len() + 1 {
-            let event_id = match self.peers.keys().skip(idx).next() {
-                Some(eid) => *eid,
-                None => {
-                    idx = 0;
-                    continue;
-                }
-            };
-            idx = (idx + 1) % self.peers.len();
-
-            if let Some(convo) = self.peers.get_mut(&event_id) {
-                if !convo.is_authenticated() || !convo.is_outbound() {
-                    continue;
-                }
+        // ask an outbound peer that hasn't told us our IP address yet, preferring our initial
+        // neighbors
+        let mut initial_candidates = vec![];
+        let mut candidates = vec![];
+        for (event_id, convo) in self.peers.iter() {
+            if !convo.is_authenticated() || !convo.is_outbound() {
+                continue;
+            }
+            if self.public_ip_votes.contains_key(&convo.peer_addrbytes) {
+                continue;
+            }
+            if PeerDB::is_initial_peer(
+                self.peerdb.conn(),
+                convo.peer_network_id,
+                &convo.peer_addrbytes,
+                convo.peer_port,
+            )? {
+                initial_candidates.push(*event_id);
+            } else {
+                candidates.push(*event_id);
+            }
+        }
 
-                if !PeerDB::is_initial_peer(
-                    self.peerdb.conn(),
-                    convo.peer_network_id,
-                    &convo.peer_addrbytes,
-                    convo.peer_port,
-                )? {
-                    continue;
-                }
+        let candidates = if initial_candidates.len() > 0 {
+            initial_candidates
+        } else {
+            candidates
+        };
 
+        if candidates.len() > 0 {
+            let event_id = candidates[thread_rng().gen::<usize>() % candidates.len()];
+            if let Some(convo) = self.peers.get_mut(&event_id) {
                 debug!("Ask {:?} for my IP address", &convo);
 
                 let nonce = thread_rng().gen::<u32>();
@@ -2122,7 +3119,6 @@ impl PeerNetwork {
                 })?;
 
                 self.public_ip_reply_handle = Some(rh);
-                break;
             }
         }
 
@@ -2178,22 +3174,62 @@ impl PeerNetwork {
                 return Err(e);
             }
 
+            let event_id = rh.get_event_id();
             match rh.try_send_recv() {
                 Ok(message) => match message.payload {
                     StacksMessageType::NatPunchReply(data) => {
-                        // peer offers us our public IP address.
+                        // peer offers us our public IP address.  Only believe it once enough
+                        // distinct peers agree.
+                        let now = get_epoch_time_secs();
+                        let voter_opt = self
+                            .peers
+                            .get(&event_id)
+                            .map(|convo| convo.peer_addrbytes.clone());
+                        if let Some(voter) = voter_opt {
+                            debug!(
+                                "{:?}: {:?} says my IP address is {:?}",
+                                &self.local_peer, &voter, &data.addrbytes
+                            );
+                            self.public_ip_votes
+                                .insert(voter, (data.addrbytes.clone(), now));
+                        }
+
+                        // forget old votes, since our IP address may have changed since then
+                        let public_ip_timeout = self.connection_opts.public_ip_timeout;
+                        self.public_ip_votes
+                            .retain(|_, (_, voted_at)| *voted_at + public_ip_timeout >= now);
+
+                        // this request went through, so don't count it against us
+                        self.public_ip_retries = 0;
+
+                        let addrbytes = match PeerNetwork::public_ip_quorum_address(
+                            &self.public_ip_votes,
+                            self.connection_opts.public_ip_quorum,
+                        ) {
+                            Some(addrbytes) => addrbytes,
+                            None => {
+                                debug!(
+                                    "{:?}: no quorum of {} peers on my IP address yet ({} votes)",
+                                    &self.local_peer,
+                                    self.connection_opts.public_ip_quorum,
+                                    self.public_ip_votes.len()
+                                );
+                                return Ok(true);
+                            }
+                        };
+
                         debug!(
                             "{:?}: learned that my IP address is {:?}",
-                            &self.local_peer, &data.addrbytes
+                            &self.local_peer, &addrbytes
                         );
+                        self.public_ip_votes.clear();
                         self.public_ip_confirmed = true;
                         self.public_ip_learned_at = get_epoch_time_secs();
                         self.public_ip_retries = 0;
 
                         // if our IP address changed, then disconnect witih everyone
                         let old_ip = self.local_peer.public_ip_address.clone();
-                        self.local_peer.public_ip_address =
-                            Some((data.addrbytes, self.bind_nk.port));
+                        self.local_peer.public_ip_address = Some((addrbytes, self.bind_nk.port));
 
                         if old_ip != self.local_peer.public_ip_address {
                             info!(
@@ -2234,6 +3270,22 @@ impl PeerNetwork {
         return Ok(true);
     }
 
+    /// Find the public IP address that at least `quorum` distinct peers say we have, if any.
+    fn public_ip_quorum_address(
+        votes: &HashMap<PeerAddress, (PeerAddress, u64)>,
+        quorum: u64,
+    ) -> Option<PeerAddress> {
+        let mut tally: HashMap<&PeerAddress, u64> = HashMap::new();
+        for (_, (addrbytes, _)) in votes.iter() {
+            let count = tally.entry(addrbytes).or_insert(0);
+            *count += 1;
+            if *count >= cmp::max(quorum, 1) {
+                return Some(addrbytes.clone());
+            }
+        }
+        None
+    }
+
     /// Do we need to (re)fetch our public IP?
     fn need_public_ip(&mut self) -> bool {
         if !self.public_ip_learned {
@@ -2454,6 +3506,30 @@ impl PeerNetwork {
         Ok(done && at_chain_tip)
     }
 
+    /// Periodically save inventory sync and block download progress, so a restarted node can
+    /// resume its initial block download instead of starting over.
+    fn save_download_state(&mut self) -> () {
+        let interval = self.connection_opts.download_state_save_interval;
+        let now = get_epoch_time_secs();
+        if interval == 0 || self.download_state_saved_at + interval > now {
+            return;
+        }
+        self.download_state_saved_at = now;
+
+        if let Err(e) = self.save_inv_sync_state() {
+            warn!(
+                "{:?}: Failed to save inventory sync state: {:?}",
+                &self.local_peer, &e
+            );
+        }
+        if let Err(e) = self.save_block_download_state() {
+            warn!(
+                "{:?}: Failed to save block download state: {:?}",
+                &self.local_peer, &e
+            );
+        }
+    }
+
     /// Find the next block to push
     fn find_next_push_block(
         &mut self,
@@ -2625,6 +3701,9 @@ impl PeerNetwork {
         sortdb: &SortitionDB,
         chainstate: &StacksChainState,
     ) -> Result<(), net_error> {
+        if !self.connection_opts.network_mode.relays_pushes() {
+            return Ok(());
+        }
         if self.antientropy_last_push_ts + self.connection_opts.antientropy_retry
             >= get_epoch_time_secs()
         {
@@ -3166,6 +4245,68 @@ impl PeerNetwork {
         Ok(do_prune)
     }
 
+    /// Delete the attachments that the Atlas retention policy no longer keeps, if a sweep is due.
+    fn sweep_attachments(&mut self, sortdb: &SortitionDB) -> Result<(), net_error> {
+        let policy = &self.atlasdb.atlas_config.retention;
+        let now = get_epoch_time_secs();
+        if !policy.is_enabled() || self.attachments_last_sweep + policy.sweep_interval > now {
+            return Ok(());
+        }
+        self.attachments_last_sweep = now;
+
+        let stacks_tip_height =
+            SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?.canonical_stacks_tip_height;
+        let pruned = self.atlasdb.prune_attachments(stacks_tip_height)?;
+        let stored_bytes = self.atlasdb.get_attachments_total_size()?;
+        record_attachments_pruned(pruned.attachments, pruned.bytes, stored_bytes);
+        if pruned.attachments > 0 {
+            info!(
+                "Atlas: pruned {} attachments, reclaiming {} bytes ({} bytes stored)",
+                pruned.attachments, pruned.bytes, stored_bytes
+            );
+        }
+        Ok(())
+    }
+
+    /// Upload the attachments that are not in the Atlas mirror yet, if a mirror is configured.
+    /// Uploads block this thread, so at most `max_uploads_per_pass` are done per pass, and the
+    /// pass stops at the first failure and waits `upload_interval` seconds before retrying.
+    fn mirror_attachments(&mut self) -> Result<(), net_error> {
+        let mirror = match self.atlasdb.atlas_config.mirror {
+            Some(ref config) => AttachmentMirror::new(config.clone()),
+            None => return Ok(()),
+        };
+        let now = get_epoch_time_secs();
+        if self.attachments_mirror_next_pass > now {
+            return Ok(());
+        }
+
+        let attachments = self
+            .atlasdb
+            .get_attachments_to_mirror(mirror.config.max_uploads_per_pass)?;
+        for attachment in attachments.iter() {
+            let content_hash = attachment.hash();
+            if let Err(e) = mirror.put_attachment(attachment) {
+                warn!(
+                    "Atlas: failed to upload attachment {} to mirror: {:?}",
+                    &content_hash, &e
+                );
+                increment_attachments_mirror_failures_counter();
+                self.attachments_mirror_next_pass = now + mirror.config.upload_interval;
+                return Ok(());
+            }
+            self.atlasdb.mark_attachment_mirrored(&content_hash)?;
+            increment_attachments_mirrored_counter();
+            debug!("Atlas: uploaded attachment {} to mirror", &content_hash);
+        }
+
+        if (attachments.len() as u64) < mirror.config.max_uploads_per_pass {
+            // caught up
+            self.attachments_mirror_next_pass = now + mirror.config.upload_interval;
+        }
+        Ok(())
+    }
+
     fn do_attachment_downloads(
         &mut self,
         chainstate: &mut StacksChainState,
@@ -3422,7 +4563,7 @@ impl PeerNetwork {
             // check limits:
             // at most 1 BlocksAvailable
             // at most 1 MicroblocksAvailable
-            // at most 1 BlocksData
+            // at most 1 BlocksData or CompactBlock
             // at most $self.connection_opts.max_buffered_microblocks MicroblocksDatas
             let mut blocks_available = 0;
             let mut microblocks_available = 0;
@@ -3436,7 +4577,7 @@ impl PeerNetwork {
                     StacksMessageType::MicroblocksAvailable(_) => {
                         microblocks_available += 1;
                     }
-                    StacksMessageType::Blocks(_) => {
+                    StacksMessageType::Blocks(_) | StacksMessageType::CompactBlock(_) => {
                         blocks_data += 1;
                     }
                     StacksMessageType::Microblocks(_) => {
@@ -3465,11 +4606,15 @@ impl PeerNetwork {
                     return;
                 }
             }
-            if let StacksMessageType::Blocks(_) = &msg.payload {
+            if let StacksMessageType::Blocks(_) | StacksMessageType::CompactBlock(_) = &msg.payload
+            {
                 if blocks_data >= self.connection_opts.max_buffered_blocks {
                     debug!(
-                        "{:?}: Drop BlocksData from event {} -- already have {} buffered",
-                        &self.local_peer, event_id, blocks_data
+                        "{:?}: Drop {} from event {} -- already have {} buffered",
+                        &self.local_peer,
+                        msg.payload.get_message_name(),
+                        event_id,
+                        blocks_data
                     );
                     return;
                 }
@@ -3641,8 +4786,63 @@ impl PeerNetwork {
         &mut self,
         sortdb: &SortitionDB,
         event_id: usize,
+        relayers: &Vec<RelayData>,
         new_blocks: &BlocksData,
         buffer: bool,
+    ) -> bool {
+        let pushed: Vec<_> = new_blocks
+            .blocks
+            .iter()
+            .map(|(consensus_hash, block)| (consensus_hash.clone(), block.block_hash()))
+            .collect();
+        self.handle_unsolicited_pushed_blocks(
+            sortdb,
+            event_id,
+            relayers,
+            &pushed,
+            "BlocksData",
+            buffer,
+        )
+    }
+
+    /// Handle an unsolicited CompactBlock the same way as a BlocksData.  If the relayer can't
+    /// rebuild the block from our mempool, the inv update lets the downloader fetch it from this
+    /// peer instead.
+    /// Mask errors.
+    fn handle_unsolicited_CompactBlock(
+        &mut self,
+        sortdb: &SortitionDB,
+        event_id: usize,
+        relayers: &Vec<RelayData>,
+        compact_block: &CompactBlockData,
+        buffer: bool,
+    ) -> bool {
+        let pushed = [(
+            compact_block.consensus_hash.clone(),
+            compact_block.header.block_hash(),
+        )];
+        self.handle_unsolicited_pushed_blocks(
+            sortdb,
+            event_id,
+            relayers,
+            &pushed,
+            "CompactBlock",
+            buffer,
+        )
+    }
+
+    /// Check pushed blocks, given by their consensus hashes and block hashes, against the
+    /// sortitions that elected them, and update our inv for the peer that pushed them.
+    /// `msg_name` is the kind of message they came in, for logging.
+    /// Returns whether or not to buffer the message (if buffer is true).
+    fn handle_unsolicited_pushed_blocks(
+        &mut self,
+        sortdb: &SortitionDB,
+        event_id: usize,
+        relayers: &Vec<RelayData>,
+        pushed: &[(ConsensusHash, BlockHeaderHash)],
+        msg_name: &str,
+        buffer: bool,
     ) -> bool {
         let (remote_neighbor_key, remote_is_authenticated) = match self.peers.get(&event_id) {
             Some(convo) => (convo.to_neighbor_key(), convo.is_authenticated()),
@@ -3659,8 +4859,9 @@ impl PeerNetwork {
         if !remote_is_authenticated {
             // drop -- a correct peer will have authenticated before sending this message
             test_debug!(
-                "{:?}: Drop unauthenticated BlocksData from {:?}",
+                "{:?}: Drop unauthenticated {} from {:?}",
                 &self.local_peer,
+                msg_name,
                 &remote_neighbor_key
             );
             return false;
@@ -3669,17 +4870,18 @@ impl PeerNetwork {
         let outbound_neighbor_key_opt = self.find_outbound_neighbor(event_id);
 
         debug!(
-            "{:?}: Process BlocksData from {:?} with {} entries",
+            "{:?}: Process {} from {:?} with {} entries",
             &self.local_peer,
+            msg_name,
             outbound_neighbor_key_opt
                 .as_ref()
                 .unwrap_or(&remote_neighbor_key),
-            new_blocks.blocks.len()
+            pushed.len()
         );
 
         let mut to_buffer = false;
 
-        for (consensus_hash, block) in new_blocks.blocks.iter() {
+        for (consensus_hash, block_hash) in pushed.iter() {
             let sn = match SortitionDB::get_block_snapshot_consensus(
                 &sortdb.conn(),
                 &consensus_hash,
@@ -3688,25 +4890,27 @@ impl PeerNetwork {
                 Ok(None) => {
                     if buffer {
                         debug!(
-                                "{:?}: Will buffer unsolicited BlocksData({}/{}) ({}) -- consensus hash not (yet) recognized",
+                                "{:?}: Will buffer unsolicited {}({}/{}) ({}) -- consensus hash not (yet) recognized",
                                 &self.local_peer,
+                                msg_name,
                                 &consensus_hash,
-                                &block.block_hash(),
+                                &block_hash,
                                 StacksBlockHeader::make_index_block_hash(
                                     &consensus_hash,
-                                    &block.block_hash()
+                                    &block_hash
                                 )
                             );
                         to_buffer = true;
                     } else {
                         debug!(
-                                "{:?}: Will drop unsolicited BlocksData({}/{}) ({}) -- consensus hash not (yet) recognized",
+                                "{:?}: Will drop unsolicited {}({}/{}) ({}) -- consensus hash not (yet) recognized",
                                 &self.local_peer,
+                                msg_name,
                                 &consensus_hash,
-                                &block.block_hash(),
+                                &block_hash,
                                 StacksBlockHeader::make_index_block_hash(
                                     &consensus_hash,
-                                    &block.block_hash()
+                                    &block_hash
                                 )
                             );
                     }
@@ -3729,13 +4933,10 @@ impl PeerNetwork {
                 continue;
             }
 
-            if sn.winning_stacks_block_hash != block.block_hash() {
+            if sn.winning_stacks_block_hash != *block_hash {
                 info!(
                     "{:?}: Ignoring block {} -- winning block was {} (sortition: {})",
-                    &self.local_peer,
-                    block.block_hash(),
-                    sn.winning_stacks_block_hash,
-                    sn.sortition
+                    &self.local_peer, block_hash, sn.winning_stacks_block_hash, sn.sortition
                 );
                 continue;
             }
@@ -3751,11 +4952,67 @@ impl PeerNetwork {
                     false,
                 );
             }
+
+            self.update_miner_relay_hops(sortdb, &sn.consensus_hash, relayers);
         }
 
         to_buffer
     }
 
+    /// Learn how close our outbound neighbors are to the miner of a pushed block, using the
+    /// pushed message's relayer chain.  The ith relayer is i hops away from the peer that first
+    /// pushed the block, so the downloader will prefer the relayers closest to the miner when
+    /// fetching that miner's subsequent blocks.
+    fn update_miner_relay_hops(
+        &mut self,
+        sortdb: &SortitionDB,
+        consensus_hash: &ConsensusHash,
+        relayers: &Vec<RelayData>,
+    ) -> () {
+        if relayers.len() == 0 || self.block_downloader.is_none() {
+            return;
+        }
+
+        let miner_pubkey_hash = match PeerNetwork::get_miner_pubkey_hash(sortdb, consensus_hash) {
+            Ok(Some(pkh)) => pkh,
+            Ok(None) => {
+                return;
+            }
+            Err(e) => {
+                debug!(
+                    "{:?}: Failed to identify miner of block in {}: {:?}",
+                    &self.local_peer, consensus_hash, &e
+                );
+                return;
+            }
+        };
+
+        let mut outbound_neighbors = HashMap::new();
+        for (_, convo) in self.peers.iter() {
+            if !convo.is_outbound() || !convo.is_authenticated() {
+                continue;
+            }
+            if let Some(pkh) = convo.get_public_key_hash() {
+                outbound_neighbors.insert(pkh, convo.to_neighbor_key());
+            }
+        }
+
+        if let Some(ref mut downloader) = self.block_downloader {
+            for (hops, relayer) in relayers.iter().enumerate() {
+                if let Some(nk) = outbound_neighbors.get(&relayer.peer.public_key_hash) {
+                    test_debug!(
+                        "{:?}: Neighbor {:?} is {} relay hops from miner {}",
+                        &self.local_peer,
+                        nk,
+                        hops,
+                        &miner_pubkey_hash
+                    );
+                    downloader.record_miner_relay_hops(&miner_pubkey_hash, nk, hops);
+                }
+            }
+        }
+    }
+
     /// Handle unsolicited MicroblocksData.
     /// Returns whether or not to buffer (if buffer is true)
     /// Returns whether or not to pass to the relayer (if buffer is false).
@@ -3839,6 +5096,7 @@ impl PeerNetwork {
         sortdb: &SortitionDB,
         chainstate: &StacksChainState,
         event_id: usize,
+        relayers: &Vec<RelayData>,
         payload: &StacksMessageType,
         buffer: bool,
     ) -> (bool, bool) {
@@ -3865,12 +5123,25 @@ impl PeerNetwork {
             }
             StacksMessageType::Blocks(ref new_blocks) => {
                 // update inv state for this peer
-                let to_buffer =
-                    self.handle_unsolicited_BlocksData(sortdb, event_id, new_blocks, buffer);
+                let to_buffer = self
+                    .handle_unsolicited_BlocksData(sortdb, event_id, relayers, new_blocks, buffer);
 
                 // forward to relayer for processing
                 (to_buffer, true)
             }
+            StacksMessageType::CompactBlock(ref compact_block) => {
+                // update inv state for this peer
+                let to_buffer = self.handle_unsolicited_CompactBlock(
+                    sortdb,
+                    event_id,
+                    relayers,
+                    compact_block,
+                    buffer,
+                );
+
+                // forward to relayer to rebuild from the mempool
+                (to_buffer, true)
+            }
             StacksMessageType::Microblocks(ref new_mblocks) => {
                 let to_buffer = self.handle_unsolicited_MicroblocksData(
                     chainstate,
@@ -3882,6 +5153,22 @@ impl PeerNetwork {
                 // only forward to the relayer if we don't need to buffer it.
                 (to_buffer, true)
             }
+            StacksMessageType::DataUrlHealth(ref data) => {
+                // only feeds our data URL host rankings
+                self.handle_unsolicited_DataUrlHealth(event_id, data);
+                (false, false)
+            }
+            StacksMessageType::AttachmentsAvailable(ref data) => {
+                self.handle_unsolicited_AttachmentsAvailable(event_id, data);
+
+                // forward to relayer, which passes it on to our other neighbors
+                (false, true)
+            }
+            StacksMessageType::MicroblocksSubscribe(ref data) => {
+                // only concerns this peer and us
+                self.handle_unsolicited_MicroblocksSubscribe(event_id, data);
+                (false, false)
+            }
             _ => (false, true),
         }
     }
@@ -3931,6 +5218,7 @@ impl PeerNetwork {
                     sortdb,
                     chainstate,
                     event_id,
+                    &message.relayers,
                     &message.payload,
                     buffer,
                 );
@@ -4093,10 +5381,29 @@ impl PeerNetwork {
         }
     }
 
+    /// The optional features this node has switched on
+    pub fn features(&self) -> NodeFeatures {
+        NodeFeatures::new(self.local_peer.services, &self.connection_opts)
+    }
+
+    /// Stop advertising the services and data URL that our network mode and TLS settings don't
+    /// allow
+    fn apply_network_mode(connection_opts: &ConnectionOptions, local_peer: &mut LocalPeer) {
+        local_peer.services = connection_opts.filter_services(local_peer.services);
+        if !connection_opts.network_mode.serves_data() {
+            local_peer.data_url = UrlString::try_from("".to_string()).unwrap();
+        }
+    }
+
     /// Get the local peer from the peer DB, but also preserve the public IP address
     pub fn load_local_peer(&self) -> Result<LocalPeer, net_error> {
         let mut lp = PeerDB::get_local_peer(&self.peerdb.conn())?;
         lp.public_ip_address = self.local_peer.public_ip_address.clone();
+        PeerNetwork::apply_network_mode(&self.connection_opts, &mut lp);
+        if self.push_backpressure {
+            // don't invite block pushes we can't keep up with
+            lp.services &= !(ServiceFlags::RELAY as u16);
+        }
         Ok(lp)
     }
 
@@ -4225,11 +5532,36 @@ impl PeerNetwork {
             update_inbound_neighbors(inbound_neighbors as i64);
         }
 
+        // remember how far we got, in case we restart
+        self.save_download_state();
+
         // In parallel, do a neighbor walk, but only if we're not doing the initial block download
         self.do_network_neighbor_walk()?;
 
-        // download attachments
-        self.do_attachment_downloads(chainstate, dns_client_opt, network_result)?;
+        // download attachments, unless we're running out of disk space or another node keeps
+        // our databases in sync
+        if self.disk_space_level < DiskSpaceLevel::PauseDownloads
+            && self.connection_opts.network_mode.accepts_writes()
+        {
+            self.do_attachment_downloads(chainstate, dns_client_opt, network_result)?;
+
+            // reclaim space from old attachments once we're done with everything else
+            if do_prune {
+                if let Err(e) = self.sweep_attachments(sortdb) {
+                    warn!("Atlas: failed to prune attachments: {:?}", &e);
+                }
+            }
+        }
+
+        // copy new attachments to the Atlas mirror, even if we're low on disk space
+        if self.connection_opts.network_mode.accepts_writes() {
+            if let Err(e) = self.mirror_attachments() {
+                warn!("Atlas: failed to mirror attachments: {:?}", &e);
+            }
+        }
+
+        // advance the query jobs started over the RPC interface
+        self.query_jobs.run(chainstate, &self.connection_opts);
 
         // remove timed-out requests from other threads
         for (_, convo) in self.peers.iter_mut() {
@@ -4239,6 +5571,29 @@ impl PeerNetwork {
         // clear out peers that we haven't heard from in our heartbeat interval
         self.disconnect_unresponsive();
 
+        // re-handshake with peers whose keys have expired, and drop those that don't re-key
+        self.evict_expired_key_peers();
+
+        // refresh stale handshake data from peers we've been talking to for a while
+        self.queue_rehandshakes();
+
+        // see if our block inventory has fallen out of step with our neighbors'
+        self.check_inv_agreement(sortdb, chainstate);
+
+        // see if the rest of the network has moved on to a newer version
+        self.check_peer_versions();
+
+        // tell our peers which data URLs we couldn't reach
+        self.send_data_url_health();
+
+        // ask peers that push the microblocks they mine to push them to us
+        self.subscribe_microblock_pushes();
+
+        // stop accepting pushed blocks if we're falling behind on processing them, or are
+        // running out of disk space
+        self.check_staging_backlog(chainstate);
+        self.update_push_backpressure();
+
         // queue up pings to neighbors we haven't spoken to in a while
         self.queue_ping_heartbeats();
 
@@ -4387,6 +5742,10 @@ impl PeerNetwork {
             }
         }?;
 
+        // time the work we do on this pass, but not the wait for events, so the neighbor walk
+        // can back off when we fall behind
+        let pass_start_ms = get_epoch_time_ms();
+
         let p2p_poll_state = poll_states
             .remove(&self.p2p_network_handle)
             .expect("BUG: no poll state for p2p network handle");
@@ -4418,13 +5777,28 @@ impl PeerNetwork {
             }
         }
 
+        // cached RPC responses are only good until either chain tip changes
+        let burn_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        self.rpc_cache.refresh(
+            &burn_tip.sortition_id,
+            &StacksBlockHeader::make_index_block_hash(
+                &burn_tip.canonical_stacks_tip_consensus_hash,
+                &burn_tip.canonical_stacks_tip_hash,
+            ),
+        );
+
         PeerNetwork::with_network_state(self, |ref mut network, ref mut network_state| {
             let http_stacks_msgs = network.http.run(
                 network_state,
                 network.chain_view.clone(),
                 &network.peers,
                 sortdb,
-                &network.peerdb,
+                &mut network.peerdb,
+                &network.walk_stats,
+                &mut network.block_fetches,
+                &mut network.query_jobs,
+                &mut network.rpc_cache,
+                &mut network.idempotency_cache,
                 &mut network.atlasdb,
                 chainstate,
                 mempool,
@@ -4445,6 +5819,9 @@ impl PeerNetwork {
             p2p_poll_state,
         )?;
 
+        let pass_ms = get_epoch_time_ms().saturating_sub(pass_start_ms) as u64;
+        self.walk_stats.handshakes.record_pass(pass_ms);
+
         debug!("<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<< End Network Dispatch <<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<<");
         Ok(network_result)
     }
@@ -4460,6 +5837,8 @@ mod test {
 
     use burnchains::burnchain::*;
     use burnchains::*;
+    use chainstate::stacks::db::blocks::test::make_sample_microblock_stream;
+    use chainstate::stacks::db::test::instantiate_chainstate;
     use chainstate::stacks::test::*;
     use chainstate::stacks::*;
     use net::atlas::*;
@@ -4470,6 +5849,7 @@ mod test {
     use util::sleep_ms;
     use util::test::*;
 
+    use crate::codec::MAX_PAYLOAD_LEN;
     use crate::types::chainstate::BurnchainHeaderHash;
 
     use super::*;
@@ -4820,4 +6200,284 @@ mod test {
             test_debug!("fake endpoint thread joined");
         })
     }
+
+    #[test]
+    fn test_public_ip_quorum_address() {
+        let addr_a = PeerAddress::from_ipv4(1, 2, 3, 4);
+        let addr_b = PeerAddress::from_ipv4(5, 6, 7, 8);
+
+        let mut votes = HashMap::new();
+        assert_eq!(PeerNetwork::public_ip_quorum_address(&votes, 2), None);
+
+        votes.insert(PeerAddress::from_ipv4(10, 0, 0, 1), (addr_a.clone(), 1));
+        assert_eq!(PeerNetwork::public_ip_quorum_address(&votes, 2), None);
+        assert_eq!(
+            PeerNetwork::public_ip_quorum_address(&votes, 1),
+            Some(addr_a.clone())
+        );
+
+        // a lone dissenting peer can't change the outcome
+        votes.insert(PeerAddress::from_ipv4(10, 0, 0, 2), (addr_b.clone(), 2));
+        assert_eq!(PeerNetwork::public_ip_quorum_address(&votes, 2), None);
+
+        votes.insert(PeerAddress::from_ipv4(10, 0, 0, 3), (addr_a.clone(), 3));
+        assert_eq!(
+            PeerNetwork::public_ip_quorum_address(&votes, 2),
+            Some(addr_a.clone())
+        );
+        assert_eq!(PeerNetwork::public_ip_quorum_address(&votes, 3), None);
+    }
+
+    #[test]
+    fn test_split_push_payload() {
+        let blocks: Vec<(ConsensusHash, StacksBlock)> = (0..4)
+            .map(|i| (ConsensusHash([i as u8; 20]), make_codec_test_block(25)))
+            .collect();
+        let payload = StacksMessageType::Blocks(BlocksData {
+            blocks: blocks.clone(),
+        });
+        let payload_len = payload.serialize_to_vec().len() as u32;
+        let entry_len = blocks[0].serialize_to_vec().len() as u32;
+
+        // fits as-is
+        assert_eq!(
+            PeerNetwork::split_push_payload(&payload, payload_len),
+            vec![payload.clone()]
+        );
+
+        // room for two blocks per message
+        let max_payload_len = 1 + 4 + 2 * entry_len;
+        let split = PeerNetwork::split_push_payload(&payload, max_payload_len);
+        assert_eq!(split.len(), 2);
+        for (i, p) in split.iter().enumerate() {
+            assert!(p.serialize_to_vec().len() as u32 <= max_payload_len);
+            match p {
+                StacksMessageType::Blocks(ref data) => {
+                    assert_eq!(data.blocks, blocks[(2 * i)..(2 * i + 2)].to_vec());
+                }
+                _ => panic!("not a Blocks payload"),
+            }
+        }
+
+        // room for no blocks at all
+        assert_eq!(
+            PeerNetwork::split_push_payload(&payload, entry_len).len(),
+            0
+        );
+
+        // microblocks are split into contiguous runs under the same anchor block
+        let privk = StacksPrivateKey::new();
+        let microblocks = make_sample_microblock_stream(&privk, &blocks[0].1.block_hash());
+        let index_anchor_block = StacksBlockId([0x11; 32]);
+        let payload = StacksMessageType::Microblocks(MicroblocksData {
+            index_anchor_block: index_anchor_block.clone(),
+            microblocks: microblocks.clone(),
+        });
+        let max_payload_len = 1
+            + 32
+            + 4
+            + microblocks[0].serialize_to_vec().len() as u32
+            + microblocks[1].serialize_to_vec().len() as u32;
+        let split = PeerNetwork::split_push_payload(&payload, max_payload_len);
+        assert!(split.len() > 1);
+
+        let mut all_microblocks = vec![];
+        for p in split.iter() {
+            assert!(p.serialize_to_vec().len() as u32 <= max_payload_len);
+            match p {
+                StacksMessageType::Microblocks(ref data) => {
+                    assert_eq!(data.index_anchor_block, index_anchor_block);
+                    all_microblocks.append(&mut data.microblocks.clone());
+                }
+                _ => panic!("not a Microblocks payload"),
+            }
+        }
+        assert_eq!(all_microblocks, microblocks);
+    }
+
+    #[test]
+    fn test_split_push_payload_blocks_bound() {
+        let block = make_codec_test_block(25);
+        let entry_len = (ConsensusHash([0u8; 20]), block.clone())
+            .serialize_to_vec()
+            .len() as u32;
+
+        // too big for one push
+        let num_blocks = MAX_BLOCKS_DATA_PUSH_LEN / entry_len + 2;
+        let blocks: Vec<(ConsensusHash, StacksBlock)> = (0..num_blocks)
+            .map(|i| (ConsensusHash([i as u8; 20]), block.clone()))
+            .collect();
+        let payload = StacksMessageType::Blocks(BlocksData {
+            blocks: blocks.clone(),
+        });
+        assert!(payload.serialize_to_vec().len() as u32 > MAX_BLOCKS_DATA_PUSH_LEN);
+
+        // split even though the peer would take all of it
+        let split = PeerNetwork::split_push_payload(&payload, MAX_PAYLOAD_LEN);
+        assert!(split.len() > 1);
+
+        let mut all_blocks = vec![];
+        for p in split.iter() {
+            // each message is independently valid
+            let bytes = p.serialize_to_vec();
+            assert!(bytes.len() as u32 <= MAX_BLOCKS_DATA_PUSH_LEN);
+            let decoded = StacksMessageType::consensus_deserialize(&mut &bytes[..]).unwrap();
+            assert_eq!(decoded, *p);
+            match decoded {
+                StacksMessageType::Blocks(mut data) => {
+                    assert!(data.blocks.len() <= BLOCKS_PUSHED_MAX as usize);
+                    all_blocks.append(&mut data.blocks);
+                }
+                _ => panic!("not a Blocks payload"),
+            }
+        }
+        assert_eq!(all_blocks, blocks);
+    }
+
+    #[test]
+    fn test_compact_block_prefill() {
+        let mut block = make_codec_test_block(5);
+        for (tx, fee) in block.txs[1..].iter_mut().zip([10, 40, 20, 30].iter()) {
+            tx.set_tx_fee(*fee);
+        }
+        let txids: Vec<Txid> = block.txs.iter().map(|tx| tx.txid()).collect();
+
+        // the last transaction was relayed too long ago to be picked
+        let mut recent_relayed_txs = HashMap::new();
+        for (txid, relayed_at) in txids[1..].iter().zip([1000, 2000, 3000, 100].iter()) {
+            recent_relayed_txs.insert(txid.clone(), *relayed_at as u128);
+        }
+
+        // highest fee first
+        let prefill = PeerNetwork::compact_block_prefill(&block, &recent_relayed_txs, 1000, 2);
+        let expected: HashSet<Txid> = vec![txids[2].clone(), txids[3].clone()]
+            .into_iter()
+            .collect();
+        assert_eq!(prefill, expected);
+
+        let prefill = PeerNetwork::compact_block_prefill(&block, &recent_relayed_txs, 1000, 10);
+        let expected: HashSet<Txid> = txids[1..4].iter().cloned().collect();
+        assert_eq!(prefill, expected);
+
+        assert!(
+            PeerNetwork::compact_block_prefill(&block, &recent_relayed_txs, 1000, 0).is_empty()
+        );
+        assert!(PeerNetwork::compact_block_prefill(&block, &HashMap::new(), 0, 10).is_empty());
+
+        // the picked transactions are sent in full, along with the coinbase
+        let payload = StacksMessageType::Blocks(BlocksData {
+            blocks: vec![(ConsensusHash([0x11; 20]), block.clone())],
+        });
+        let prefill = PeerNetwork::compact_block_prefill(&block, &recent_relayed_txs, 1000, 2);
+        let compact_payloads =
+            PeerNetwork::compact_push_payload(&payload, MAX_PAYLOAD_LEN, &prefill).unwrap();
+        assert_eq!(compact_payloads.len(), 1);
+        match compact_payloads[0] {
+            StacksMessageType::CompactBlock(ref data) => {
+                assert_eq!(data.txids, txids);
+                assert_eq!(
+                    data.txs,
+                    vec![
+                        block.txs[0].clone(),
+                        block.txs[2].clone(),
+                        block.txs[3].clone()
+                    ]
+                );
+            }
+            _ => panic!("not a CompactBlock payload"),
+        }
+    }
+
+    #[test]
+    fn test_staging_backlog_backpressure() {
+        // turns on above the limit...
+        assert!(!PeerNetwork::staging_backlog_backpressure(false, 100, 100));
+        assert!(PeerNetwork::staging_backlog_backpressure(false, 101, 100));
+
+        // ...and only turns off again once the backlog is down to half of it
+        assert!(PeerNetwork::staging_backlog_backpressure(true, 100, 100));
+        assert!(PeerNetwork::staging_backlog_backpressure(true, 51, 100));
+        assert!(!PeerNetwork::staging_backlog_backpressure(true, 50, 100));
+
+        // disabled
+        assert!(!PeerNetwork::staging_backlog_backpressure(false, 0, 0));
+        assert!(!PeerNetwork::staging_backlog_backpressure(true, 0, 0));
+    }
+
+    #[test]
+    fn test_staging_backlog_disabled_by_default() {
+        // no backlog is large enough to throttle block pushes with the default limit...
+        let max_backlog = ConnectionOptions::default().max_staging_blocks_backlog;
+        assert_eq!(max_backlog, 0);
+        assert!(!PeerNetwork::staging_backlog_backpressure(
+            false,
+            u64::max_value(),
+            max_backlog
+        ));
+
+        // ...so a default network keeps accepting them
+        let mut p2p = make_test_p2p_network(&vec![]);
+        let chainstate = instantiate_chainstate(
+            false,
+            0x80000000,
+            "test_staging_backlog_disabled_by_default",
+        );
+        p2p.check_staging_backlog(&chainstate);
+        p2p.update_push_backpressure();
+        assert!(!p2p.staging_backlog_exceeded);
+        assert!(!p2p.push_backpressure);
+    }
+
+    #[test]
+    fn test_microblocks_subscribe() {
+        let mut p2p = make_test_p2p_network(&vec![]);
+        p2p.local_peer.services |= ServiceFlags::MICROBLOCK_PUSH as u16;
+        p2p.connection_opts.max_microblock_subscribers = 2;
+
+        let subscribe = MicroblocksSubscribeData { subscribe: true };
+        let unsubscribe = MicroblocksSubscribeData { subscribe: false };
+
+        // up to max_microblock_subscribers peers can subscribe
+        p2p.handle_unsolicited_MicroblocksSubscribe(1, &subscribe);
+        p2p.handle_unsolicited_MicroblocksSubscribe(2, &subscribe);
+        p2p.handle_unsolicited_MicroblocksSubscribe(3, &subscribe);
+        assert_eq!(
+            p2p.microblock_subscribers,
+            vec![1, 2].into_iter().collect::<HashSet<usize>>()
+        );
+
+        // re-subscribing is a no-op
+        p2p.handle_unsolicited_MicroblocksSubscribe(1, &subscribe);
+        assert_eq!(p2p.microblock_subscribers.len(), 2);
+
+        // unsubscribing makes room
+        p2p.handle_unsolicited_MicroblocksSubscribe(2, &unsubscribe);
+        p2p.handle_unsolicited_MicroblocksSubscribe(3, &subscribe);
+        assert_eq!(
+            p2p.microblock_subscribers,
+            vec![1, 3].into_iter().collect::<HashSet<usize>>()
+        );
+
+        // disconnecting unsubscribes
+        p2p.microblock_subscriptions.insert(3);
+        p2p.deregister_peer(3);
+        assert_eq!(
+            p2p.microblock_subscribers,
+            vec![1].into_iter().collect::<HashSet<usize>>()
+        );
+        assert!(p2p.microblock_subscriptions.is_empty());
+
+        // subscribers without an open conversation get nothing pushed
+        let pushed = p2p.push_microblocks_to_subscribers(&MicroblocksData {
+            index_anchor_block: StacksBlockId([0x11; 32]),
+            microblocks: vec![],
+        });
+        assert!(pushed.is_empty());
+
+        // no new subscriptions if we don't push microblocks
+        p2p.local_peer.services &= !(ServiceFlags::MICROBLOCK_PUSH as u16);
+        p2p.handle_unsolicited_MicroblocksSubscribe(4, &subscribe);
+        assert!(!p2p.microblock_subscribers.contains(&4));
+        assert_eq!(p2p.subscribe_microblock_pushes(), 0);
+    }
 }
@@ -45,6 +45,9 @@ use mio::Token;
 
 use std::net::Shutdown;
 
+#[cfg(feature = "async_net")]
+use net::asyncio::{AsyncFrontEnd, AsyncSocket};
+
 use rand;
 use rand::RngCore;
 
@@ -64,6 +67,54 @@ impl NetworkPollState {
     }
 }
 
+/// A connected socket, registered with a `NetworkState`
+#[derive(Debug)]
+pub enum NetSocket {
+    /// I/O is done by whoever polls the `NetworkState`
+    Mio(mio_net::TcpStream),
+    /// I/O is done by the `NetworkState`'s async front-end
+    #[cfg(feature = "async_net")]
+    Async(AsyncSocket),
+}
+
+impl NetSocket {
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            NetSocket::Mio(ref sock) => sock.peer_addr(),
+            #[cfg(feature = "async_net")]
+            NetSocket::Async(ref sock) => sock.peer_addr(),
+        }
+    }
+}
+
+impl Read for NetSocket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            NetSocket::Mio(ref mut sock) => sock.read(buf),
+            #[cfg(feature = "async_net")]
+            NetSocket::Async(ref mut sock) => sock.read(buf),
+        }
+    }
+}
+
+impl Write for NetSocket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            NetSocket::Mio(ref mut sock) => sock.write(buf),
+            #[cfg(feature = "async_net")]
+            NetSocket::Async(ref mut sock) => sock.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            NetSocket::Mio(ref mut sock) => sock.flush(),
+            #[cfg(feature = "async_net")]
+            NetSocket::Async(ref mut sock) => sock.flush(),
+        }
+    }
+}
+
 // state for a single network server
 #[derive(Debug)]
 pub struct NetworkServerState {
@@ -81,6 +132,8 @@ pub struct NetworkState {
     servers: Vec<NetworkServerState>,
     count: usize,
     event_map: HashMap<usize, usize>, // map socket events to their registered server socket (including server sockets)
+    #[cfg(feature = "async_net")]
+    async_io: Option<AsyncFrontEnd>,
 }
 
 impl NetworkState {
@@ -99,9 +152,40 @@ impl NetworkState {
             servers: vec![],
             count: 1,
             event_map: HashMap::new(),
+            #[cfg(feature = "async_net")]
+            async_io: None,
         })
     }
 
+    /// Do the I/O for all sockets started from now on in `num_threads` background threads.
+    /// Each socket buffers at most `max_inbound_bytes` of received data and `max_outbound_bytes`
+    /// of data to send.
+    #[cfg(feature = "async_net")]
+    pub fn start_async_io(
+        &mut self,
+        num_threads: usize,
+        max_inbound_bytes: usize,
+        max_outbound_bytes: usize,
+    ) -> Result<(), net_error> {
+        self.async_io = Some(AsyncFrontEnd::new(
+            num_threads,
+            max_inbound_bytes,
+            max_outbound_bytes,
+        )?);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "async_net"))]
+    pub fn start_async_io(
+        &mut self,
+        _num_threads: usize,
+        _max_inbound_bytes: usize,
+        _max_outbound_bytes: usize,
+    ) -> Result<(), net_error> {
+        warn!("Not built with the `async_net` feature; socket I/O will not be asynchronous");
+        Ok(())
+    }
+
     pub fn num_events(&self) -> usize {
         self.event_map.len()
     }
@@ -275,6 +359,115 @@ impl NetworkState {
         Ok(())
     }
 
+    /// Start using a connected socket that was registered with `register()`.  If async I/O is on,
+    /// the socket's I/O moves to the async front-end, and the `AsyncSocket` takes its place
+    /// under the same event ID.
+    /// If this fails, the socket is deregistered.
+    #[cfg(not(feature = "async_net"))]
+    pub fn start_socket(
+        &mut self,
+        _event_id: usize,
+        sock: mio_net::TcpStream,
+    ) -> Result<NetSocket, net_error> {
+        Ok(NetSocket::Mio(sock))
+    }
+
+    /// Start using a connected socket that was registered with `register()`.  If async I/O is on,
+    /// the socket's I/O moves to the async front-end, and the `AsyncSocket` takes its place
+    /// under the same event ID.
+    /// If this fails, the socket is deregistered.
+    #[cfg(feature = "async_net")]
+    pub fn start_socket(
+        &mut self,
+        event_id: usize,
+        sock: mio_net::TcpStream,
+    ) -> Result<NetSocket, net_error> {
+        if self.async_io.is_none() {
+            return Ok(NetSocket::Mio(sock));
+        }
+
+        if let Err(e) = self.poll.deregister(&sock) {
+            warn!("Failed to deregister socket {}: {:?}", event_id, &e);
+            let _ = self.deregister(event_id, &sock);
+            return Err(net_error::RegisterError);
+        }
+
+        let async_sock = match NetworkState::into_std_stream(sock) {
+            Ok(std_sock) => self
+                .async_io
+                .as_ref()
+                .expect("BUG: no async front-end")
+                .start(event_id, std_sock),
+            Err(e) => Err(e),
+        };
+        let async_sock = match async_sock {
+            Ok(async_sock) => async_sock,
+            Err(e) => {
+                self.event_map.remove(&event_id);
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = self.poll.register(
+            &async_sock,
+            mio::Token(event_id),
+            Ready::all(),
+            PollOpt::edge(),
+        ) {
+            warn!("Failed to register async socket {}: {:?}", event_id, &e);
+            self.event_map.remove(&event_id);
+            return Err(net_error::RegisterError);
+        }
+
+        debug!("Socket {} started async I/O: {:?}", event_id, &async_sock);
+        Ok(NetSocket::Async(async_sock))
+    }
+
+    #[cfg(all(feature = "async_net", unix))]
+    fn into_std_stream(sock: mio_net::TcpStream) -> Result<net::TcpStream, net_error> {
+        use std::os::unix::io::{FromRawFd, IntoRawFd};
+        // the fd stays in non-blocking mode
+        Ok(unsafe { net::TcpStream::from_raw_fd(sock.into_raw_fd()) })
+    }
+
+    #[cfg(all(feature = "async_net", not(unix)))]
+    fn into_std_stream(sock: mio_net::TcpStream) -> Result<net::TcpStream, net_error> {
+        warn!("Async socket I/O is not supported on this platform");
+        let _ = sock.shutdown(Shutdown::Both);
+        Err(net_error::SocketError)
+    }
+
+    /// Deregister and close a socket returned by `start_socket()`
+    pub fn deregister_socket(&mut self, event_id: usize, sock: NetSocket) -> Result<(), net_error> {
+        match sock {
+            NetSocket::Mio(sock) => self.deregister(event_id, &sock),
+            #[cfg(feature = "async_net")]
+            NetSocket::Async(sock) => {
+                assert!(
+                    self.event_map.contains_key(&event_id),
+                    "BUG: no such socket {}",
+                    event_id
+                );
+                self.event_map.remove(&event_id);
+
+                if let Err(e) = self.poll.deregister(&sock) {
+                    warn!("Failed to deregister socket {}: {:?}", event_id, &e);
+                };
+
+                debug!(
+                    "Socket deregistered: {}, {:?} (Events total: {}, max: {})",
+                    event_id,
+                    &sock,
+                    self.event_map.len(),
+                    self.event_capacity
+                );
+
+                // dropping the socket closes it
+                Ok(())
+            }
+        }
+    }
+
     fn make_next_event_id(&self, cur_count: usize, in_use: &HashSet<usize>) -> Option<usize> {
         let mut ret = cur_count;
 
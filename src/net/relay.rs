@@ -36,8 +36,11 @@ use chainstate::burn::ConsensusHash;
 use chainstate::coordinator::comm::CoordinatorChannels;
 use chainstate::stacks::db::{StacksChainState, StacksEpochReceipt, StacksHeaderInfo};
 use chainstate::stacks::events::StacksTransactionReceipt;
+use chainstate::stacks::TransactionPayload;
+use clarity_vm::clarity::ClarityConnection;
 use core::mempool::MemPoolDB;
 use core::mempool::*;
+use monitoring;
 use net::chat::*;
 use net::connection::*;
 use net::db::*;
@@ -48,7 +51,7 @@ use net::rpc::*;
 use net::Error as net_error;
 use net::*;
 use util::get_epoch_time_secs;
-use util::hash::Sha512Trunc256Sum;
+use util::hash::{MerkleHashFunc, Sha512Trunc256Sum};
 use vm::costs::ExecutionCost;
 
 use crate::chainstate::coordinator::BlockEventDispatcher;
@@ -56,6 +59,7 @@ use crate::types::chainstate::{PoxId, SortitionId};
 use chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
 use codec::MAX_PAYLOAD_LEN;
 use types::chainstate::BurnchainHeaderHash;
+use types::chainstate::StacksAddress;
 
 pub type BlocksAvailableMap = HashMap<BurnchainHeaderHash, (u64, ConsensusHash)>;
 
@@ -63,10 +67,187 @@ pub const MAX_RELAYER_STATS: usize = 4096;
 pub const MAX_RECENT_MESSAGES: usize = 256;
 pub const MAX_RECENT_MESSAGE_AGE: usize = 600; // seconds; equal to the expected epoch length
 pub const RELAY_DUPLICATE_INFERENCE_WARMUP: usize = 128;
+/// How many announced attachments the relayer remembers, so it announces each one once
+pub const MAX_RECENT_ATTACHMENTS_ANNOUNCED: usize = 4096;
+/// How far back the relayer remembers the transactions it relayed from each origin, in seconds
+pub const TX_ORIGIN_RATE_WINDOW: u64 = 60;
+/// How many transaction origins the relayer keeps rate-limiting state for
+pub const MAX_TX_ORIGINS_TRACKED: usize = 4096;
 
 pub struct Relayer {
     /// Connection to the p2p thread
     p2p: NetworkHandle,
+    /// Which transactions we'll forward to our neighbors
+    relay_policy: TxRelayPolicy,
+    /// Chain tip at which we last removed mempool transactions with already-used nonces
+    stale_nonce_gc_tip: Option<StacksBlockId>,
+    /// Attachments we recently announced to our neighbors, and when
+    recent_attachments_announced: HashMap<(StacksBlockId, Hash160), u64>,
+    /// How many transactions (and bytes) we recently forwarded from each origin
+    origin_rate_limiter: TxOriginRateLimiter,
+}
+
+/// Node-level policy on which transactions to forward to neighbors.  This is separate from
+/// mempool admission: a transaction that fails this policy can still be stored and mined by this
+/// node, but it won't be relayed.  A limit of 0 means "no limit".
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TxRelayPolicy {
+    /// minimum fee, in microSTX per byte of the serialized transaction
+    pub min_fee_rate: u64,
+    /// minimum amount of microSTX an STX transfer must send
+    pub min_stx_transfer: u64,
+    /// maximum length of the serialized transaction, in bytes
+    pub max_tx_len: u64,
+}
+
+impl TxRelayPolicy {
+    pub fn from_connection_opts(opts: &ConnectionOptions) -> TxRelayPolicy {
+        TxRelayPolicy {
+            min_fee_rate: opts.relay_min_fee_rate,
+            min_stx_transfer: opts.relay_min_stx_transfer,
+            max_tx_len: opts.relay_max_tx_len,
+        }
+    }
+
+    /// Check a transaction against this policy.
+    /// Returns Err(reason) if it must not be relayed.
+    pub fn check(&self, tx: &StacksTransaction) -> Result<(), String> {
+        let tx_len = tx.tx_len();
+        if self.max_tx_len > 0 && tx_len > self.max_tx_len {
+            return Err(format!(
+                "transaction is {} bytes, more than the relay limit of {}",
+                tx_len, self.max_tx_len
+            ));
+        }
+        if self.min_fee_rate > 0 && tx.get_tx_fee() < self.min_fee_rate.saturating_mul(tx_len) {
+            return Err(format!(
+                "fee of {} is below the minimum relay fee rate of {} per byte ({} bytes)",
+                tx.get_tx_fee(),
+                self.min_fee_rate,
+                tx_len
+            ));
+        }
+        if let TransactionPayload::TokenTransfer(_, amount, _) = tx.payload {
+            if amount < self.min_stx_transfer {
+                return Err(format!(
+                    "STX transfer of {} is below the minimum relay amount of {}",
+                    amount, self.min_stx_transfer
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Per-origin limits on the transactions we forward to our neighbors, so that a single spammy key
+/// can't use up the network's transaction propagation bandwidth.  Each origin address gets at
+/// most `max_txs` transactions, totalling at most `max_bytes` bytes, relayed in any
+/// TX_ORIGIN_RATE_WINDOW seconds.  Like TxRelayPolicy, this only affects relaying, and a limit of
+/// 0 means "no limit".
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TxOriginRateLimiter {
+    pub max_txs: u64,
+    pub max_bytes: u64,
+    /// When we relayed each recent transaction from each origin, and how big it was
+    recent: HashMap<StacksAddress, VecDeque<(u64, u64)>>,
+}
+
+impl TxOriginRateLimiter {
+    pub fn from_connection_opts(opts: &ConnectionOptions) -> TxOriginRateLimiter {
+        TxOriginRateLimiter {
+            max_txs: opts.relay_max_txs_per_origin,
+            max_bytes: opts.relay_max_tx_bytes_per_origin,
+            recent: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_txs > 0 || self.max_bytes > 0
+    }
+
+    /// How many origins we're tracking
+    pub fn num_origins(&self) -> usize {
+        self.recent.len()
+    }
+
+    /// Forget about the transactions we relayed before the window that ends at `now`
+    pub fn expire(&mut self, now: u64) {
+        self.recent.retain(|_, relayed| {
+            while let Some((relayed_at, _)) = relayed.front() {
+                if relayed_at + TX_ORIGIN_RATE_WINDOW > now {
+                    break;
+                }
+                relayed.pop_front();
+            }
+            relayed.len() > 0
+        });
+    }
+
+    /// Check whether a transaction's origin has room to have it relayed at `now`, and if so,
+    /// count it against the origin's limits.
+    /// Returns Err((limit, reason)) if it must not be relayed, where limit is which limit it hit
+    /// ("txs" or "bytes").
+    pub fn check(
+        &mut self,
+        tx: &StacksTransaction,
+        now: u64,
+    ) -> Result<(), (&'static str, String)> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+
+        let origin = tx.origin_address();
+        let tx_len = tx.tx_len();
+
+        if !self.recent.contains_key(&origin) && self.recent.len() >= MAX_TX_ORIGINS_TRACKED {
+            self.expire(now);
+            if self.recent.len() >= MAX_TX_ORIGINS_TRACKED {
+                // make room by forgetting the origin we've heard from least recently
+                let stalest = self
+                    .recent
+                    .iter()
+                    .min_by_key(|(_, relayed)| relayed.back().map(|(t, _)| *t).unwrap_or(0))
+                    .map(|(addr, _)| addr.clone());
+                if let Some(stalest) = stalest {
+                    self.recent.remove(&stalest);
+                }
+            }
+        }
+
+        let relayed = self.recent.entry(origin.clone()).or_insert(VecDeque::new());
+        while let Some((relayed_at, _)) = relayed.front() {
+            if relayed_at + TX_ORIGIN_RATE_WINDOW > now {
+                break;
+            }
+            relayed.pop_front();
+        }
+
+        if self.max_txs > 0 && (relayed.len() as u64) >= self.max_txs {
+            return Err((
+                "txs",
+                format!(
+                    "origin {} already had {} transactions relayed in the last {} seconds",
+                    &origin,
+                    relayed.len(),
+                    TX_ORIGIN_RATE_WINDOW
+                ),
+            ));
+        }
+
+        let relayed_bytes: u64 = relayed.iter().map(|(_, len)| *len).sum();
+        if self.max_bytes > 0 && relayed_bytes.saturating_add(tx_len) > self.max_bytes {
+            return Err((
+                "bytes",
+                format!(
+                    "origin {} already had {} bytes of transactions relayed in the last {} seconds, and this one is {} bytes",
+                    &origin, relayed_bytes, TX_ORIGIN_RATE_WINDOW, tx_len
+                ),
+            ));
+        }
+
+        relayed.push_back((now, tx_len));
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
@@ -112,6 +293,19 @@ impl RelayPayload for BlocksAvailableData {
     }
 }
 
+impl RelayPayload for AttachmentsAvailableData {
+    fn get_digest(&self) -> Sha512Trunc256Sum {
+        let mut bytes = vec![];
+        self.consensus_serialize(&mut bytes)
+            .expect("BUG: failed to serialize");
+        let h = Sha512Trunc256Sum::from_data(&bytes);
+        h
+    }
+    fn get_id(&self) -> String {
+        format!("AttachmentsAvailable({})", self.available.len())
+    }
+}
+
 impl RelayPayload for StacksBlock {
     fn get_digest(&self) -> Sha512Trunc256Sum {
         let h = self.block_hash();
@@ -438,12 +632,33 @@ impl RelayerStats {
 
 impl Relayer {
     pub fn new(handle: NetworkHandle) -> Relayer {
-        Relayer { p2p: handle }
+        Relayer::new_with_policy(
+            handle,
+            TxRelayPolicy::default(),
+            TxOriginRateLimiter::default(),
+        )
+    }
+
+    pub fn new_with_policy(
+        handle: NetworkHandle,
+        relay_policy: TxRelayPolicy,
+        origin_rate_limiter: TxOriginRateLimiter,
+    ) -> Relayer {
+        Relayer {
+            p2p: handle,
+            relay_policy: relay_policy,
+            stale_nonce_gc_tip: None,
+            recent_attachments_announced: HashMap::new(),
+            origin_rate_limiter: origin_rate_limiter,
+        }
     }
 
     pub fn from_p2p(network: &mut PeerNetwork) -> Relayer {
         let handle = network.new_handle(1024);
-        Relayer::new(handle)
+        let relay_policy = TxRelayPolicy::from_connection_opts(&network.connection_opts);
+        let origin_rate_limiter =
+            TxOriginRateLimiter::from_connection_opts(&network.connection_opts);
+        Relayer::new_with_policy(handle, relay_policy, origin_rate_limiter)
     }
 
     /// Given blocks pushed to us, verify that they correspond to expected block data.
@@ -753,6 +968,131 @@ impl Relayer {
         Ok((new_blocks, bad_neighbors))
     }
 
+    /// Rebuild a pushed compact block from the transactions in our mempool.
+    /// Returns None if we're missing any of its transactions, or if the rebuilt block doesn't pass
+    /// the same checks a block pushed to us in full would.
+    pub fn reconstruct_compact_block(
+        mempool: &MemPoolDB,
+        compact_block: &CompactBlockData,
+    ) -> Result<Option<StacksBlock>, net_error> {
+        let sent_txs: HashMap<Txid, &StacksTransaction> =
+            compact_block.txs.iter().map(|tx| (tx.txid(), tx)).collect();
+
+        // keep looking after the first missing transaction, so the metrics say how many we had
+        let mut txs = Vec::with_capacity(compact_block.txids.len());
+        let mut num_from_mempool = 0;
+        let mut num_missing = 0;
+        for txid in compact_block.txids.iter() {
+            if let Some(tx) = sent_txs.get(txid) {
+                txs.push((*tx).clone());
+                continue;
+            }
+            match MemPoolDB::get_tx(mempool.conn(), txid)? {
+                Some(tx_info) => {
+                    txs.push(tx_info.tx);
+                    num_from_mempool += 1;
+                }
+                None => {
+                    num_missing += 1;
+                }
+            }
+        }
+
+        let num_sent = compact_block.txs.len() as u64;
+        if num_missing > 0 {
+            debug!(
+                "Cannot rebuild compact block {}/{}: {} of its {} transactions are not in our mempool",
+                &compact_block.consensus_hash,
+                compact_block.header.block_hash(),
+                num_missing,
+                compact_block.txids.len()
+            );
+            monitoring::record_compact_block_reconstruction(
+                "incomplete",
+                num_sent,
+                num_from_mempool,
+                num_missing,
+            );
+            return Ok(None);
+        }
+
+        let block = StacksBlock {
+            header: compact_block.header.clone(),
+            txs,
+        };
+        let block_bytes = block.serialize_to_vec();
+        match StacksBlock::consensus_deserialize(&mut &block_bytes[..]) {
+            Ok(block) => {
+                monitoring::record_compact_block_reconstruction(
+                    "rebuilt",
+                    num_sent,
+                    num_from_mempool,
+                    0,
+                );
+                Ok(Some(block))
+            }
+            Err(e) => {
+                warn!(
+                    "Rebuilt compact block {}/{} is invalid: {:?}",
+                    &compact_block.consensus_hash,
+                    compact_block.header.block_hash(),
+                    &e
+                );
+                monitoring::record_compact_block_reconstruction(
+                    "invalid",
+                    num_sent,
+                    num_from_mempool,
+                    0,
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Rebuild the compact blocks pushed to us, and hand the ones we could rebuild over to be
+    /// processed with the other pushed blocks.  The rest are dropped; the downloader will fetch
+    /// them in full from the peers that pushed them.
+    /// Does not fail; just logs warnings.
+    fn reconstruct_compact_blocks(network_result: &mut NetworkResult, mempool: &MemPoolDB) -> () {
+        for (neighbor_key, compact_blocks) in network_result.pushed_compact_blocks.drain() {
+            for compact_block in compact_blocks.into_iter() {
+                match Relayer::reconstruct_compact_block(mempool, &compact_block) {
+                    Ok(Some(block)) => {
+                        debug!(
+                            "Rebuilt compact block {}/{} from {} ({} of {} transactions sent)",
+                            &compact_block.consensus_hash,
+                            block.block_hash(),
+                            &neighbor_key,
+                            compact_block.txs.len(),
+                            compact_block.txids.len()
+                        );
+                        let blocks_data = BlocksData {
+                            blocks: vec![(compact_block.consensus_hash, block)],
+                        };
+                        if let Some(blocks_msgs) =
+                            network_result.pushed_blocks.get_mut(&neighbor_key)
+                        {
+                            blocks_msgs.push(blocks_data);
+                        } else {
+                            network_result
+                                .pushed_blocks
+                                .insert(neighbor_key.clone(), vec![blocks_data]);
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        warn!(
+                            "Failed to rebuild compact block {}/{}: {:?}",
+                            &compact_block.consensus_hash,
+                            compact_block.header.block_hash(),
+                            &e
+                        );
+                    }
+                }
+            }
+        }
+    }
+
     /// Preprocess all downloaded, confirmed microblock streams.
     /// Does not fail on invalid blocks; just logs a warning.
     /// Returns the consensus hashes for the sortitions that elected the stacks anchored blocks that produced these streams.
@@ -1009,6 +1349,49 @@ impl Relayer {
         Ok(ret)
     }
 
+    /// Remove mempool transactions whose origin or sponsor nonces have been used at the given chain
+    /// tip, so they don't linger until they are old enough to be garbage-collected.  Only done
+    /// once per chain tip, since the nonces only change when a new block is processed.
+    fn garbage_collect_stale_nonces(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        tip: &StacksBlockId,
+        last_gc_tip: &mut Option<StacksBlockId>,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<(), net_error> {
+        if last_gc_tip.as_ref() == Some(tip) {
+            return Ok(());
+        }
+
+        let addresses = MemPoolDB::get_tx_addresses(mempool.conn())?;
+        let account_nonces =
+            match chainstate.with_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    addresses
+                        .into_iter()
+                        .map(|address| {
+                            let nonce = clarity_db.get_account_nonce(&address.clone().into());
+                            (address, nonce)
+                        })
+                        .collect::<HashMap<_, _>>()
+                })
+            }) {
+                Some(account_nonces) => account_nonces,
+                None => {
+                    debug!("No chain state for {}; not checking mempool nonces", tip);
+                    return Ok(());
+                }
+            };
+
+        let mut mempool_tx = mempool.tx_begin()?;
+        MemPoolDB::garbage_collect_stale_nonces(&mut mempool_tx, &account_nonces, event_observer)?;
+        mempool_tx.commit()?;
+
+        *last_gc_tip = Some(tip.clone());
+        Ok(())
+    }
+
     /// Store all new transactions we received, and return the list of transactions that we need to
     /// forward (as well as their relay hints).  Also, garbage-collect the mempool.
     fn process_transactions(
@@ -1016,10 +1399,17 @@ impl Relayer {
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
+        stale_nonce_gc_tip: &mut Option<StacksBlockId>,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
     ) -> Result<Vec<(Vec<RelayData>, StacksTransaction)>, net_error> {
-        let chain_height = match chainstate.get_stacks_chain_tip(sortdb)? {
-            Some(tip) => tip.height,
+        let (chain_height, chain_tip) = match chainstate.get_stacks_chain_tip(sortdb)? {
+            Some(tip) => (
+                tip.height,
+                StacksBlockHeader::make_index_block_hash(
+                    &tip.consensus_hash,
+                    &tip.anchored_block_hash,
+                ),
+            ),
             None => {
                 debug!(
                     "No Stacks chain tip; dropping {} transaction(s)",
@@ -1054,6 +1444,17 @@ impl Relayer {
             ret.push((vec![], tx.clone()));
         }
 
+        // don't forward a transaction that a higher-fee conflicting transaction has since replaced
+        ret.retain(|(_, tx)| {
+            let txid = tx.txid();
+            if mempool.has_tx(&txid) {
+                true
+            } else {
+                debug!("Will not forward tx {}: no longer in the mempool", &txid);
+                false
+            }
+        });
+
         // garbage-collect
         if chain_height > MEMPOOL_MAX_TRANSACTION_AGE {
             let min_height = chain_height.saturating_sub(MEMPOOL_MAX_TRANSACTION_AGE);
@@ -1067,9 +1468,82 @@ impl Relayer {
             mempool_tx.commit()?;
         }
 
+        // drop transactions whose clients asked for them to expire before the next block
+        let mut mempool_tx = mempool.tx_begin()?;
+        MemPoolDB::garbage_collect_expired(&mut mempool_tx, chain_height, event_observer)?;
+        mempool_tx.commit()?;
+
+        // drop transactions whose nonces were used by the blocks processed since the last pass
+        Relayer::garbage_collect_stale_nonces(
+            sortdb,
+            chainstate,
+            mempool,
+            &chain_tip,
+            stale_nonce_gc_tip,
+            event_observer,
+        )?;
+
         Ok(ret)
     }
 
+    /// Find the attachment announcements to send to our neighbors: the attachments we just
+    /// stored, and (if relay_pushed is true) the attachments that were announced to us, with the
+    /// announcements' relay hints.  Each attachment is announced at most once every
+    /// MAX_RECENT_MESSAGE_AGE seconds, however many neighbors announce it to us; once we remember
+    /// MAX_RECENT_ATTACHMENTS_ANNOUNCED of them, we only announce our own.
+    fn process_attachments_available(
+        network_result: &NetworkResult,
+        recent_attachments_announced: &mut HashMap<(StacksBlockId, Hash160), u64>,
+        relay_pushed: bool,
+    ) -> Vec<(Vec<RelayData>, AttachmentsAvailableData)> {
+        let now = get_epoch_time_secs();
+        recent_attachments_announced.retain(|_, ts| *ts + (MAX_RECENT_MESSAGE_AGE as u64) >= now);
+
+        let stored: Vec<(StacksBlockId, Hash160)> = network_result
+            .attachments
+            .iter()
+            .filter(|(instance, _)| instance.content_hash != Hash160::empty())
+            .map(|(instance, _)| {
+                (
+                    instance.index_block_hash.clone(),
+                    instance.content_hash.clone(),
+                )
+            })
+            .collect();
+        let mut announcements = vec![(vec![], stored, true)];
+        if relay_pushed {
+            for (_, msgs) in network_result.pushed_attachments_available.iter() {
+                for (relayers, data) in msgs.iter() {
+                    announcements.push((relayers.clone(), data.available.clone(), false));
+                }
+            }
+        }
+
+        let mut ret = vec![];
+        for (relayers, available, ours) in announcements.into_iter() {
+            let mut novel = vec![];
+            for entry in available.into_iter() {
+                if recent_attachments_announced.contains_key(&entry) {
+                    continue;
+                }
+                if !ours && recent_attachments_announced.len() >= MAX_RECENT_ATTACHMENTS_ANNOUNCED {
+                    continue;
+                }
+                recent_attachments_announced.insert(entry.clone(), now);
+                novel.push(entry);
+            }
+            for chunk in novel.chunks(ATTACHMENTS_AVAILABLE_MAX_LEN as usize) {
+                ret.push((
+                    relayers.clone(),
+                    AttachmentsAvailableData {
+                        available: chunk.to_vec(),
+                    },
+                ));
+            }
+        }
+        ret
+    }
+
     pub fn advertize_blocks(&mut self, available: BlocksAvailableMap) -> Result<(), net_error> {
         self.p2p.advertize_blocks(available)
     }
@@ -1188,6 +1662,8 @@ impl Relayer {
         coord_comms: Option<&CoordinatorChannels>,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
     ) -> Result<ProcessedNetReceipts, net_error> {
+        Relayer::reconstruct_compact_blocks(network_result, mempool);
+
         match Relayer::process_new_blocks(network_result, sortdb, chainstate, coord_comms) {
             Ok((new_blocks, new_confirmed_microblocks, new_microblocks, bad_block_neighbors)) => {
                 // attempt to relay messages (note that this is all best-effort).
@@ -1263,6 +1739,7 @@ impl Relayer {
             sortdb,
             chainstate,
             mempool,
+            &mut self.stale_nonce_gc_tip,
             event_observer,
         )?;
 
@@ -1274,16 +1751,67 @@ impl Relayer {
             );
         }
 
+        let now = get_epoch_time_secs();
+        if self.origin_rate_limiter.is_enabled() {
+            self.origin_rate_limiter.expire(now);
+        }
+
         let mut mempool_txs_added = vec![];
         for (relayers, tx) in new_txs.into_iter() {
-            debug!("{:?}: Broadcast tx {}", &_local_peer, &tx.txid());
             mempool_txs_added.push(tx.clone());
+            if let Err(reason) = self.relay_policy.check(&tx) {
+                debug!(
+                    "{:?}: Will not relay tx {}: {}",
+                    &_local_peer,
+                    &tx.txid(),
+                    &reason
+                );
+                continue;
+            }
+            if let Err((limit, reason)) = self.origin_rate_limiter.check(&tx, now) {
+                debug!(
+                    "{:?}: Will not relay tx {}: {}",
+                    &_local_peer,
+                    &tx.txid(),
+                    &reason
+                );
+                monitoring::increment_tx_relay_throttled_counter(limit);
+                continue;
+            }
+
+            debug!("{:?}: Broadcast tx {}", &_local_peer, &tx.txid());
             let msg = StacksMessageType::Transaction(tx);
             if let Err(e) = self.p2p.broadcast_message(relayers, msg) {
                 warn!("Failed to broadcast transaction: {:?}", &e);
             }
         }
 
+        // announce the attachments we stored, and pass on the announcements of others if we sync
+        // attachments ourselves
+        let relay_pushed = ServiceFlags::ATLAS.is_set(_local_peer.services);
+        let announcements = Relayer::process_attachments_available(
+            network_result,
+            &mut self.recent_attachments_announced,
+            relay_pushed,
+        );
+        for (relayers, data) in announcements.into_iter() {
+            debug!(
+                "{:?}: Announce {} attachments",
+                &_local_peer,
+                data.available.len()
+            );
+            let msg = StacksMessageType::AttachmentsAvailable(data);
+            if let Err(e) = self.p2p.broadcast_message(relayers, msg) {
+                warn!("Failed to announce attachments: {:?}", &e);
+            }
+        }
+
+        if self.origin_rate_limiter.is_enabled() {
+            monitoring::update_tx_relay_origins_tracked(
+                self.origin_rate_limiter.num_origins() as u64
+            );
+        }
+
         let mut processed_unconfirmed_state = Default::default();
 
         // finally, refresh the unconfirmed chainstate, if need be
@@ -1580,6 +2108,12 @@ impl PeerNetwork {
                 self.relayer_stats.add_relayed_message((*nk).clone(), tx);
             }
         }
+
+        for (nk, msgs) in network_result.pushed_attachments_available.iter() {
+            for (_, data) in msgs.iter() {
+                self.relayer_stats.add_relayed_message((*nk).clone(), data);
+            }
+        }
     }
 }
 
@@ -1588,12 +2122,16 @@ mod test {
     use std::cell::RefCell;
     use std::collections::HashMap;
 
+    use burnchains::Txid;
     use chainstate::stacks::db::blocks::MINIMUM_TX_FEE;
     use chainstate::stacks::db::blocks::MINIMUM_TX_FEE_RATE_PER_BYTE;
+    use chainstate::stacks::db::test::{chainstate_path, instantiate_chainstate};
+    use chainstate::stacks::miner::test::{make_user_coinbase, make_user_stacks_transfer};
     use chainstate::stacks::test::*;
     use chainstate::stacks::*;
     use chainstate::stacks::*;
     use net::asn::*;
+    use net::atlas::{Attachment, AttachmentInstance};
     use net::chat::*;
     use net::codec::*;
     use net::download::test::run_get_blocks_and_microblocks;
@@ -1606,11 +2144,241 @@ mod test {
     use util::test::*;
     use vm::costs::LimitedCostTracker;
     use vm::database::ClarityDatabase;
+    use vm::types::QualifiedContractIdentifier;
 
     use super::*;
     use clarity_vm::clarity::ClarityConnection;
     use types::chainstate::BlockHeaderHash;
 
+    #[test]
+    fn test_tx_relay_policy() {
+        let privk = StacksPrivateKey::new();
+        let recipient = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0xff; 20]),
+        });
+
+        let tx = make_user_stacks_transfer(&privk, 0, 1000, &recipient, 100);
+        let tx_len = tx.tx_len();
+
+        // no limits by default
+        let cheap_tx = make_user_stacks_transfer(&privk, 0, 1, &recipient, 1);
+        assert!(TxRelayPolicy::default().check(&cheap_tx).is_ok());
+
+        let policy = TxRelayPolicy {
+            min_fee_rate: 1000 / tx_len,
+            min_stx_transfer: 100,
+            max_tx_len: tx_len,
+        };
+        assert!(policy.check(&tx).is_ok());
+
+        // fee rate too low
+        let low_fee_tx = make_user_stacks_transfer(&privk, 0, 1, &recipient, 100);
+        assert!(policy.check(&low_fee_tx).is_err());
+
+        // dust transfer
+        let dust_tx = make_user_stacks_transfer(&privk, 0, 1000, &recipient, 99);
+        assert!(policy.check(&dust_tx).is_err());
+
+        // too big
+        let small_policy = TxRelayPolicy {
+            max_tx_len: tx_len - 1,
+            ..TxRelayPolicy::default()
+        };
+        assert!(small_policy.check(&tx).is_err());
+
+        // the dust limit only applies to STX transfers
+        let dust_policy = TxRelayPolicy {
+            min_stx_transfer: u64::max_value(),
+            ..TxRelayPolicy::default()
+        };
+        let coinbase_tx = make_user_coinbase(&privk, 0, 0);
+        assert!(dust_policy.check(&coinbase_tx).is_ok());
+        assert!(dust_policy.check(&tx).is_err());
+    }
+
+    #[test]
+    fn test_tx_origin_rate_limiter() {
+        let privk_1 = StacksPrivateKey::new();
+        let privk_2 = StacksPrivateKey::new();
+        let recipient = PrincipalData::from(StacksAddress {
+            version: C32_ADDRESS_VERSION_TESTNET_SINGLESIG,
+            bytes: Hash160([0xff; 20]),
+        });
+
+        let txs_1: Vec<_> = (0..4)
+            .map(|nonce| make_user_stacks_transfer(&privk_1, nonce, 1000, &recipient, 100))
+            .collect();
+        let tx_2 = make_user_stacks_transfer(&privk_2, 0, 1000, &recipient, 100);
+        let tx_len = txs_1[0].tx_len();
+
+        // no limits by default
+        let mut limiter = TxOriginRateLimiter::default();
+        for tx in txs_1.iter() {
+            assert!(limiter.check(tx, 1000).is_ok());
+        }
+        assert_eq!(limiter.num_origins(), 0);
+
+        // at most 2 transactions per origin per window
+        let mut limiter = TxOriginRateLimiter {
+            max_txs: 2,
+            ..TxOriginRateLimiter::default()
+        };
+        assert!(limiter.check(&txs_1[0], 1000).is_ok());
+        assert!(limiter.check(&txs_1[1], 1010).is_ok());
+        assert_eq!(limiter.check(&txs_1[2], 1020).unwrap_err().0, "txs");
+
+        // other origins aren't affected
+        assert!(limiter.check(&tx_2, 1020).is_ok());
+        assert_eq!(limiter.num_origins(), 2);
+
+        // the oldest transaction leaves the window, making room for one more
+        assert!(limiter
+            .check(&txs_1[2], 1000 + TX_ORIGIN_RATE_WINDOW)
+            .is_ok());
+        assert_eq!(
+            limiter
+                .check(&txs_1[3], 1000 + TX_ORIGIN_RATE_WINDOW)
+                .unwrap_err()
+                .0,
+            "txs"
+        );
+
+        // origins with nothing left in the window are forgotten
+        limiter.expire(1010 + TX_ORIGIN_RATE_WINDOW);
+        assert_eq!(limiter.num_origins(), 2);
+        limiter.expire(1020 + TX_ORIGIN_RATE_WINDOW);
+        assert_eq!(limiter.num_origins(), 1);
+        limiter.expire(1000 + 2 * TX_ORIGIN_RATE_WINDOW);
+        assert_eq!(limiter.num_origins(), 0);
+
+        // at most 3 transactions' worth of bytes per origin per window
+        let mut limiter = TxOriginRateLimiter {
+            max_bytes: 3 * tx_len,
+            ..TxOriginRateLimiter::default()
+        };
+        for tx in txs_1[0..3].iter() {
+            assert!(limiter.check(tx, 1000).is_ok());
+        }
+        assert_eq!(limiter.check(&txs_1[3], 1000).unwrap_err().0, "bytes");
+        assert!(limiter.check(&tx_2, 1000).is_ok());
+    }
+
+    #[test]
+    fn test_process_attachments_available() {
+        let attachment = Attachment::new(vec![1, 2, 3]);
+        let instance = AttachmentInstance {
+            content_hash: attachment.hash(),
+            attachment_index: 0,
+            block_height: 1,
+            index_block_hash: StacksBlockId([0x11; 32]),
+            metadata: "".to_string(),
+            contract_id: QualifiedContractIdentifier::transient(),
+            tx_id: Txid([0x22; 32]),
+        };
+        let ours = (
+            instance.index_block_hash.clone(),
+            instance.content_hash.clone(),
+        );
+        let theirs = (StacksBlockId([0x33; 32]), Hash160([0x44; 20]));
+        let relayers = vec![RelayData {
+            peer: NeighborAddress {
+                addrbytes: PeerAddress([0u8; 16]),
+                port: 20444,
+                public_key_hash: Hash160([0x55; 20]),
+            },
+            seq: 1,
+        }];
+        let nk = NeighborKey {
+            peer_version: 0,
+            network_id: 0,
+            addrbytes: PeerAddress([0u8; 16]),
+            port: 20444,
+        };
+
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        network_result
+            .attachments
+            .push((instance.clone(), attachment));
+        let mut empty_instance = instance.clone();
+        empty_instance.content_hash = Hash160::empty();
+        network_result
+            .attachments
+            .push((empty_instance, Attachment::empty()));
+        network_result.pushed_attachments_available.insert(
+            nk.clone(),
+            vec![(
+                relayers.clone(),
+                AttachmentsAvailableData {
+                    available: vec![ours.clone(), theirs.clone()],
+                },
+            )],
+        );
+
+        // announced pushes aren't passed on unless asked to
+        let mut recent = HashMap::new();
+        let announcements =
+            Relayer::process_attachments_available(&network_result, &mut recent, false);
+        assert_eq!(
+            announcements,
+            vec![(
+                vec![],
+                AttachmentsAvailableData {
+                    available: vec![ours.clone()],
+                }
+            )]
+        );
+
+        // what we announced ourselves is not announced again on behalf of the pushing peer
+        let mut recent = HashMap::new();
+        let announcements =
+            Relayer::process_attachments_available(&network_result, &mut recent, true);
+        assert_eq!(
+            announcements,
+            vec![
+                (
+                    vec![],
+                    AttachmentsAvailableData {
+                        available: vec![ours.clone()],
+                    }
+                ),
+                (
+                    relayers.clone(),
+                    AttachmentsAvailableData {
+                        available: vec![theirs.clone()],
+                    }
+                ),
+            ]
+        );
+
+        // each attachment is announced once
+        assert!(
+            Relayer::process_attachments_available(&network_result, &mut recent, true).is_empty()
+        );
+
+        // announcements are split up to fit in messages
+        let mut network_result = NetworkResult::new(0, 0, 0);
+        network_result.pushed_attachments_available.insert(
+            nk.clone(),
+            vec![(
+                relayers.clone(),
+                AttachmentsAvailableData {
+                    available: (0..(ATTACHMENTS_AVAILABLE_MAX_LEN + 1))
+                        .map(|i| (StacksBlockId([i as u8; 32]), Hash160([0x66; 20])))
+                        .collect(),
+                },
+            )],
+        );
+        let announcements =
+            Relayer::process_attachments_available(&network_result, &mut HashMap::new(), true);
+        assert_eq!(announcements.len(), 2);
+        assert_eq!(
+            announcements[0].1.available.len(),
+            ATTACHMENTS_AVAILABLE_MAX_LEN as usize
+        );
+        assert_eq!(announcements[1].1.available.len(), 1);
+    }
+
     #[test]
     fn test_relayer_stats_add_relyed_messages() {
         let mut relay_stats = RelayerStats::new();
@@ -1966,6 +2734,53 @@ mod test {
         assert_eq!(*ranking.get(&nk_3).unwrap(), 4 - 2 + 1);
     }
 
+    #[test]
+    fn test_reconstruct_compact_block() {
+        let _chainstate = instantiate_chainstate(false, 0x80000000, "reconstruct_compact_block");
+        let chainstate_path = chainstate_path("reconstruct_compact_block");
+        let mempool = MemPoolDB::open(false, 0x80000000, &chainstate_path).unwrap();
+
+        let consensus_hash = ConsensusHash([0x11; 20]);
+        let block = make_codec_test_block(5);
+        let compact_block = CompactBlockData::from_block(&consensus_hash, &block);
+
+        // only the coinbase is sent in full
+        assert_eq!(compact_block.txids.len(), block.txs.len());
+        assert_eq!(compact_block.txs.len(), 1);
+        assert_eq!(compact_block.txs[0], block.txs[0]);
+
+        // the rest aren't in our (empty) mempool
+        assert_eq!(
+            Relayer::reconstruct_compact_block(&mempool, &compact_block).unwrap(),
+            None
+        );
+
+        // everything was sent in full
+        let mut full_compact_block = compact_block.clone();
+        full_compact_block.txs = block.txs.clone();
+        assert_eq!(
+            Relayer::reconstruct_compact_block(&mempool, &full_compact_block).unwrap(),
+            Some(block.clone())
+        );
+
+        // the transactions that aren't in our mempool were sent in full
+        let prefill = block.txs[1..].iter().map(|tx| tx.txid()).collect();
+        let prefilled_compact_block =
+            CompactBlockData::from_block_prefilled(&consensus_hash, &block, &prefill);
+        assert_eq!(prefilled_compact_block.txs, block.txs);
+        assert_eq!(
+            Relayer::reconstruct_compact_block(&mempool, &prefilled_compact_block).unwrap(),
+            Some(block.clone())
+        );
+
+        // the transactions don't match the header
+        full_compact_block.txids.swap(1, 2);
+        assert_eq!(
+            Relayer::reconstruct_compact_block(&mempool, &full_compact_block).unwrap(),
+            None
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_get_blocks_and_microblocks_3_peers_push_available() {
@@ -17,6 +17,8 @@
  along with Blockstack. If not, see <http://www.gnu.org/licenses/>.
 */
 
+use std::cmp;
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -24,6 +26,7 @@ use std::io;
 use std::io::prelude::*;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::net::SocketAddr;
+use std::time::{Duration, Instant};
 use std::{convert::TryFrom, fmt};
 
 use rand::prelude::*;
@@ -35,27 +38,42 @@ use burnchains::Burnchain;
 use burnchains::BurnchainView;
 use burnchains::*;
 use chainstate::burn::db::sortdb::SortitionDB;
+use chainstate::burn::BlockSnapshot;
 use chainstate::burn::ConsensusHash;
 use chainstate::stacks::db::blocks::CheckError;
+use chainstate::stacks::db::blocks::MicroblockStreamFork;
+use chainstate::stacks::db::contract_costs::{
+    contract_cost_stats_bucket, CONTRACT_COST_STATS_BUCKET_SIZE,
+};
 use chainstate::stacks::db::{
-    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, BlockStreamData, StacksChainState,
+    blocks::MINIMUM_TX_FEE_RATE_PER_BYTE, BlockStreamData, StacksChainState, StacksHeaderInfo,
+    MINER_REWARD_MATURITY,
 };
 use chainstate::stacks::Error as chain_error;
 use chainstate::stacks::*;
 use clarity_vm::clarity::ClarityConnection;
 use core::mempool::*;
 use monitoring;
-use net::atlas::{AtlasDB, Attachment, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST};
+use net::atlas::{
+    AtlasConfig, AtlasDB, Attachment, MAX_ATTACHMENTS_BATCH_RESPONSE_LEN,
+    MAX_ATTACHMENTS_PER_BATCH_REQUEST, MAX_ATTACHMENT_INV_PAGES_PER_REQUEST,
+};
 use net::connection::ConnectionHttp;
 use net::connection::ConnectionOptions;
 use net::connection::ReplyHandleHttp;
-use net::db::PeerDB;
+use net::db::{LocalPeer, PeerDB};
+use net::features::NodeFeatures;
 use net::http::*;
+use net::jobs::{QueryJobKind, QueryJobs};
+use net::neighbors::{NeighborWalkStats, PeerVersionCensus};
 use net::p2p::PeerMap;
 use net::p2p::PeerNetwork;
 use net::relay::Relayer;
+use net::AtBlock;
+use net::AttachmentContentRange;
 use net::ClientError;
 use net::Error as net_error;
+use net::HttpByteRange;
 use net::HttpRequestMetadata;
 use net::HttpRequestType;
 use net::HttpResponseMetadata;
@@ -75,21 +93,51 @@ use net::UrlString;
 use net::HTTP_REQUEST_ID_RESERVED;
 use net::MAX_NEIGHBORS_DATA_LEN;
 use net::{
-    AccountEntryResponse, AttachmentPage, CallReadOnlyResponse, ContractSrcResponse,
-    GetAttachmentResponse, GetAttachmentsInvResponse, MapEntryResponse,
+    AccountEntryResponse, AccountUnlocksResponse, AttachmentPage, CallReadOnlyResponse,
+    ClarityValueResponse, CoinbaseScheduleEntry, ContractCallArgumentError, ContractSrcResponse,
+    GetAttachmentResponse, GetAttachmentsBatchResponse, GetAttachmentsInvResponse,
+    MapEntryResponse, MinerRewardEntry, MinerRewardsResponse, ValidateContractCallResponse,
+    VerifyContractSrcResponse, VestingUnlockEntry,
+};
+use net::{AttachmentPinAction, RPCAttachmentPins};
+use net::{BlockFetchStatus, RPCBlockFetchData};
+use net::{BlockProcessingAction, BlockProcessingControl, RPCBlockProcessingStatus};
+use net::{BlocksData, EventObserverStatusSource, GetIsTraitImplementedResponse};
+use net::{EventReplayRequestBody, EventReplaySource};
+use net::{
+    NodeStatusSource, RPCAtlasStatus, RPCBurnchainStatus, RPCMemPoolStatus, RPCNodeStatus,
+    RPCPeerCounts, RPCStacksStatus, RPCSubsystemError,
 };
-use net::{BlocksData, GetIsTraitImplementedResponse};
-use net::{RPCNeighbor, RPCNeighborsInfo};
-use net::{RPCPeerInfoData, RPCPoxInfoData};
+use net::{PeerFilterAction, PeerFilterList, PeerFilterRule, RPCPeerFilterList, RPCPeerFilters};
+use net::{RPCAtlasContract, RPCAtlasContracts};
+use net::{RPCBlockSimulationData, RPCSimulatedTransaction};
+use net::{RPCContractCostEntry, RPCContractCostsData};
+use net::{RPCDecodedBlock, RPCDecodedMicroblock, RPCDecodedTransaction};
+use net::{
+    RPCFeeRatePercentiles, RPCFeeRateThreshold, RPCMemPoolDropData, RPCMemPoolFeeData,
+    RPCMemPoolImportData, RPCPeerInfoData, RPCPoxInfoData, RPCStateHashData,
+};
+use net::{RPCMicroblockForksInfo, RPCMicroblockProof, RPCMicroblockStream, RPCTransactionFork};
+use net::{RPCNeighbor, RPCNeighborWalkInfo, RPCNeighborsInfo};
+use net::{RPCNodeIdentity, NODE_IDENTITY_SIGNATURE_DOMAIN};
+use net::{RPCPage, RPCPageRequest, RPCPendingTransaction};
+use net::{RPCPeerVersionCount, RPCPeerVersionsInfo};
+use util::db::tx_begin_read;
 use util::db::DBConn;
 use util::db::Error as db_error;
 use util::get_epoch_time_secs;
 use util::hash::Hash160;
-use util::hash::{hex_bytes, to_hex};
+use util::hash::{hex_bytes, to_hex, MerkleTree};
+use util::secp256k1::{MessageSignature, Secp256k1PublicKey};
+use vm::analysis::contract_interface_builder::ContractInterface;
+use vm::analysis::ContractAnalysis;
 use vm::database::clarity_store::make_contract_hash_key;
-use vm::types::TraitIdentifier;
+use vm::representations::SymbolicExpressionType;
+use vm::types::{FunctionType, TraitIdentifier, TypeSignature};
 use vm::{
+    analysis,
     analysis::errors::CheckErrors,
+    ast,
     costs::{ExecutionCost, LimitedCostTracker},
     database::{
         clarity_store::ContractCommitment, ClarityDatabase, ClaritySerializable, STXBalance,
@@ -104,22 +152,342 @@ use vm::{
 use crate::clarity_vm::database::marf::MarfedKV;
 use crate::types::chainstate::BlockHeaderHash;
 use crate::types::chainstate::{
-    BurnchainHeaderHash, StacksAddress, StacksBlockHeader, StacksBlockId,
+    BurnchainHeaderHash, SortitionId, StacksAddress, StacksBlockHeader, StacksBlockId,
+    StacksMicroblockHeader, StacksWorkScore,
 };
+use crate::types::proof::TrieHash;
+use crate::types::StacksPublicKeyBuffer;
 use crate::{
     chainstate::burn::operations::leader_block_commit::OUTPUTS_PER_COMMIT, types, util,
-    util::hash::Sha256Sum, version_string,
+    util::hash::Sha256Sum, util::hash::Sha512Trunc256Sum, version_string,
 };
 
 use super::{RPCPoxCurrentCycleInfo, RPCPoxNextCycleInfo};
 
 pub const STREAM_CHUNK_SIZE: u64 = 4096;
+// number of recent blocks whose inclusion fee rates are reported on GET /v2/fees/mempool
+pub const MEMPOOL_FEE_RECENT_BLOCKS: u64 = 10;
 
 #[derive(Default)]
 pub struct RPCHandlerArgs<'a> {
     pub exit_at_block_height: Option<&'a u64>,
     pub genesis_chainstate_hash: Sha256Sum,
     pub event_observer: Option<&'a dyn MemPoolEventDispatcher>,
+    pub event_observer_status: Option<&'a dyn EventObserverStatusSource>,
+    pub event_replay: Option<&'a dyn EventReplaySource>,
+    pub node_status: Option<&'a dyn NodeStatusSource>,
+    pub block_processing: Option<&'a dyn BlockProcessingControl>,
+}
+
+/// A successful response to one of the expensive read-only endpoints, kept in the
+/// `RPCResponseCache`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RPCCachedResponse {
+    PoxInfo(RPCPoxInfoData),
+    ContractABI(ContractInterface),
+    MapEntry(MapEntryResponse),
+}
+
+impl RPCCachedResponse {
+    /// The cacheable part of a response, if it can be cached at all.  Errors are never cached.
+    pub fn from_response(response: &HttpResponseType) -> Option<RPCCachedResponse> {
+        match response {
+            HttpResponseType::PoxInfo(_, data) => Some(RPCCachedResponse::PoxInfo(data.clone())),
+            HttpResponseType::GetContractABI(_, data) => {
+                Some(RPCCachedResponse::ContractABI(data.clone()))
+            }
+            HttpResponseType::GetMapEntry(_, data) => {
+                Some(RPCCachedResponse::MapEntry(data.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_response(&self, metadata: HttpResponseMetadata) -> HttpResponseType {
+        match self {
+            RPCCachedResponse::PoxInfo(data) => HttpResponseType::PoxInfo(metadata, data.clone()),
+            RPCCachedResponse::ContractABI(data) => {
+                HttpResponseType::GetContractABI(metadata, data.clone())
+            }
+            RPCCachedResponse::MapEntry(data) => {
+                HttpResponseType::GetMapEntry(metadata, data.clone())
+            }
+        }
+    }
+}
+
+/// Responses to expensive read-only RPC requests, keyed on the request's route (path, query and
+/// any parameters sent in the body) and the Stacks block it was evaluated at.  Every entry is
+/// dropped whenever the canonical burnchain or Stacks chain tip changes, since a response can
+/// depend on either (e.g. the PoX reward cycle).  Responses at the unconfirmed microblock tip are
+/// not cached, since it changes without the tip changing.
+#[derive(Debug)]
+pub struct RPCResponseCache {
+    sortition_tip: SortitionId,
+    stacks_tip: StacksBlockId,
+    max_entries: u64,
+    entries: HashMap<(String, StacksBlockId), RPCCachedResponse>,
+}
+
+impl RPCResponseCache {
+    /// A cache of at most `max_entries` responses.  0 disables caching.
+    pub fn new(max_entries: u64) -> RPCResponseCache {
+        RPCResponseCache {
+            sortition_tip: SortitionId([0u8; 32]),
+            stacks_tip: StacksBlockId([0u8; 32]),
+            max_entries,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Drop every cached response if either chain tip has changed since the last refresh.
+    pub fn refresh(&mut self, sortition_tip: &SortitionId, stacks_tip: &StacksBlockId) {
+        if self.sortition_tip != *sortition_tip || self.stacks_tip != *stacks_tip {
+            if self.entries.len() > 0 {
+                debug!(
+                    "Chain tip changed to {}/{}; dropping {} cached RPC responses",
+                    sortition_tip,
+                    stacks_tip,
+                    self.entries.len()
+                );
+            }
+            self.entries.clear();
+            self.sortition_tip = sortition_tip.clone();
+            self.stacks_tip = stacks_tip.clone();
+        }
+    }
+
+    /// Can responses evaluated at `tip` be cached?
+    pub fn is_cacheable_tip(chainstate: &StacksChainState, tip: &StacksBlockId) -> bool {
+        match chainstate.unconfirmed_state {
+            Some(ref unconfirmed) => unconfirmed.unconfirmed_chain_tip != *tip,
+            None => true,
+        }
+    }
+
+    /// Get the cached response to `route` at `tip`, if there is one
+    pub fn get(
+        &self,
+        route: &str,
+        tip: &StacksBlockId,
+        metadata: HttpResponseMetadata,
+    ) -> Option<HttpResponseType> {
+        if self.max_entries == 0 {
+            return None;
+        }
+        let cached = self.entries.get(&(route.to_string(), tip.clone()));
+        monitoring::increment_rpc_cache_lookups(cached.is_some());
+        cached.map(|cached| cached.to_response(metadata))
+    }
+
+    /// Cache the response to `route` at `tip`, if it is a cacheable success and there is room
+    pub fn insert(&mut self, route: String, tip: &StacksBlockId, response: &HttpResponseType) {
+        if self.entries.len() as u64 >= self.max_entries {
+            return;
+        }
+        if let Some(cached) = RPCCachedResponse::from_response(response) {
+            self.entries.insert((route, tip.clone()), cached);
+        }
+    }
+}
+
+/// The response a transaction submission got, kept in the `RPCIdempotencyCache`
+#[derive(Debug, Clone, PartialEq)]
+pub enum RPCSubmissionResponse {
+    /// The transaction was admitted to (or was already in) the mempool
+    Accepted(Txid),
+    /// The transaction was rejected with this error
+    Rejected(serde_json::Value),
+}
+
+impl RPCSubmissionResponse {
+    /// The replayable part of a response to a transaction submission, if it is one
+    pub fn from_response(response: &HttpResponseType) -> Option<RPCSubmissionResponse> {
+        match response {
+            HttpResponseType::TransactionID(_, txid) => {
+                Some(RPCSubmissionResponse::Accepted(txid.clone()))
+            }
+            HttpResponseType::BadRequestJSON(_, error) => {
+                Some(RPCSubmissionResponse::Rejected(error.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn to_response(&self, metadata: HttpResponseMetadata) -> HttpResponseType {
+        match self {
+            RPCSubmissionResponse::Accepted(txid) => {
+                HttpResponseType::TransactionID(metadata, txid.clone())
+            }
+            RPCSubmissionResponse::Rejected(error) => {
+                HttpResponseType::BadRequestJSON(metadata, error.clone())
+            }
+        }
+    }
+}
+
+/// Responses to transaction submissions that carried an `Idempotency-Key`, keyed on that key and
+/// the submitted transaction.  A client that retries a submission with the same key gets the
+/// response to its first attempt back, instead of whatever the mempool says about the transaction
+/// now (e.g. a nonce conflict with itself, or a bad nonce once it has been mined).  Since keys are
+/// chosen by clients, a key only ever replays a response to the same transaction, so clients that
+/// pick the same key can't see or block each other's submissions.  Entries expire `ttl` seconds
+/// after they are made, and the oldest are forgotten first once there are `max_entries` of them.
+#[derive(Debug)]
+pub struct RPCIdempotencyCache {
+    ttl: u64,
+    max_entries: u64,
+    entries: HashMap<(String, Txid), RPCSubmissionResponse>,
+    /// (insertion time, (key, txid)), oldest first
+    expiry_queue: VecDeque<(u64, (String, Txid))>,
+}
+
+impl RPCIdempotencyCache {
+    /// A cache that remembers each key for `ttl` seconds, and at most `max_entries` keys.
+    /// A `ttl` or `max_entries` of 0 disables it.
+    pub fn new(ttl: u64, max_entries: u64) -> RPCIdempotencyCache {
+        RPCIdempotencyCache {
+            ttl,
+            max_entries,
+            entries: HashMap::new(),
+            expiry_queue: VecDeque::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Forget every key that was stored more than `ttl` seconds before `now`
+    fn expire(&mut self, now: u64) {
+        while let Some((inserted_at, _)) = self.expiry_queue.front() {
+            if inserted_at.saturating_add(self.ttl) > now {
+                break;
+            }
+            if let Some((_, entry)) = self.expiry_queue.pop_front() {
+                self.entries.remove(&entry);
+            }
+        }
+    }
+
+    /// Get the response originally sent to a submission of `txid` with `key`, if it is still
+    /// remembered
+    pub fn get(&mut self, key: &str, txid: &Txid, now: u64) -> Option<&RPCSubmissionResponse> {
+        self.expire(now);
+        self.entries.get(&(key.to_string(), txid.clone()))
+    }
+
+    /// Remember the response to a submission of `txid` with `key`, if it can be replayed.  A
+    /// submission that is already remembered keeps its original response.
+    pub fn insert(&mut self, key: String, txid: &Txid, response: &HttpResponseType, now: u64) {
+        let entry = (key, txid.clone());
+        if self.ttl == 0 || self.max_entries == 0 || self.entries.contains_key(&entry) {
+            return;
+        }
+        let submission = match RPCSubmissionResponse::from_response(response) {
+            Some(submission) => submission,
+            None => {
+                return;
+            }
+        };
+        self.expire(now);
+        while self.entries.len() as u64 >= self.max_entries {
+            match self.expiry_queue.pop_front() {
+                Some((_, oldest)) => {
+                    self.entries.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+        self.entries.insert(entry.clone(), submission);
+        self.expiry_queue.push_back((now, entry));
+    }
+}
+
+/// The chain tips a response that combines several databases is assembled against, so that it
+/// can't mix state from before and after the node processes a block.  The tips are read inside
+/// one read transaction on each of the sortition DB and the headers DB.  Anything else the
+/// response needs should be read relative to them -- block headers, sortitions and Clarity/MARF
+/// state at a given block never change once written, so only which block is the tip can move
+/// while the response is being put together.
+#[derive(Debug, Clone)]
+pub struct RPCReadSnapshot {
+    /// The canonical burnchain tip, which names the canonical Stacks tip
+    pub burnchain_tip: BlockSnapshot,
+    /// The header of the canonical Stacks tip, if it has been processed
+    pub stacks_tip: Option<StacksHeaderInfo>,
+    /// The unconfirmed chain tip and its last microblock sequence number, if the unconfirmed
+    /// state has transactions and builds on the canonical Stacks tip
+    pub unconfirmed_tip: Option<(StacksBlockId, u16)>,
+}
+
+impl RPCReadSnapshot {
+    /// Read the chain tips
+    pub fn new(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+    ) -> Result<RPCReadSnapshot, net_error> {
+        RPCReadSnapshot::read(sortdb, chainstate, |_| Ok(())).map(|(snapshot, _)| snapshot)
+    }
+
+    /// Read the chain tips, and then run `to_do` with them while the read transactions are still
+    /// open, so that whatever it reads from the sortition DB or headers DB is consistent with the
+    /// tips.  Keep `to_do` short, since block processing can't commit to a DB not in WAL mode
+    /// until it returns.
+    pub fn read<F, R>(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        to_do: F,
+    ) -> Result<(RPCReadSnapshot, R), net_error>
+    where
+        F: FnOnce(&RPCReadSnapshot) -> Result<R, net_error>,
+    {
+        let _sortdb_tx = tx_begin_read(sortdb.conn())?;
+        let _headers_tx = tx_begin_read(chainstate.db())?;
+
+        let burnchain_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        let stacks_tip = StacksChainState::get_anchored_block_header_info(
+            chainstate.db(),
+            &burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &burnchain_tip.canonical_stacks_tip_hash,
+        )?;
+        let stacks_tip_index_hash = StacksBlockHeader::make_index_block_hash(
+            &burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &burnchain_tip.canonical_stacks_tip_hash,
+        );
+        let unconfirmed_tip = match chainstate.unconfirmed_state {
+            Some(ref unconfirmed)
+                if unconfirmed.num_mined_txs() > 0
+                    && unconfirmed.confirmed_chain_tip == stacks_tip_index_hash =>
+            {
+                Some((
+                    unconfirmed.unconfirmed_chain_tip.clone(),
+                    unconfirmed.last_mblock_seq,
+                ))
+            }
+            _ => None,
+        };
+
+        let snapshot = RPCReadSnapshot {
+            burnchain_tip,
+            stacks_tip,
+            unconfirmed_tip,
+        };
+        let result = to_do(&snapshot)?;
+        Ok((snapshot, result))
+    }
+
+    /// The index block hash of the canonical Stacks tip
+    pub fn stacks_tip_index_hash(&self) -> StacksBlockId {
+        StacksBlockHeader::make_index_block_hash(
+            &self.burnchain_tip.canonical_stacks_tip_consensus_hash,
+            &self.burnchain_tip.canonical_stacks_tip_hash,
+        )
+    }
 }
 
 pub struct ConversationHttp {
@@ -182,7 +550,8 @@ impl RPCPeerInfoData {
         exit_at_block_height: &Option<&u64>,
         genesis_chainstate_hash: &Sha256Sum,
     ) -> Result<RPCPeerInfoData, net_error> {
-        let burnchain_tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+        let snapshot = RPCReadSnapshot::new(sortdb, chainstate)?;
+        let burnchain_tip = snapshot.burnchain_tip;
         let local_peer = PeerDB::get_local_peer(peerdb.conn())?;
         let stable_burnchain_tip = {
             let ic = sortdb.index_conn();
@@ -205,19 +574,9 @@ impl RPCPeerInfoData {
         let stacks_tip_consensus_hash = burnchain_tip.canonical_stacks_tip_consensus_hash;
         let stacks_tip = burnchain_tip.canonical_stacks_tip_hash;
         let stacks_tip_height = burnchain_tip.canonical_stacks_tip_height;
-        let (unconfirmed_tip, unconfirmed_seq) = match chainstate.unconfirmed_state {
-            Some(ref unconfirmed) => {
-                if unconfirmed.num_mined_txs() > 0 {
-                    (
-                        unconfirmed.unconfirmed_chain_tip.clone(),
-                        unconfirmed.last_mblock_seq,
-                    )
-                } else {
-                    (StacksBlockId([0x00; 32]), 0)
-                }
-            }
-            None => (StacksBlockId([0x00; 32]), 0),
-        };
+        let (unconfirmed_tip, unconfirmed_seq) = snapshot
+            .unconfirmed_tip
+            .unwrap_or((StacksBlockId([0x00; 32]), 0));
 
         Ok(RPCPeerInfoData {
             peer_version: burnchain.peer_version,
@@ -239,6 +598,215 @@ impl RPCPeerInfoData {
     }
 }
 
+impl RPCNodeIdentity {
+    /// Describe the local peer, and sign the description and the client's challenge with its
+    /// p2p private key
+    pub fn from_local_peer(
+        local_peer: &LocalPeer,
+        challenge: Option<&[u8]>,
+    ) -> Result<RPCNodeIdentity, net_error> {
+        let public_key = Secp256k1PublicKey::from_private(&local_peer.private_key);
+        let mut identity = RPCNodeIdentity {
+            public_key: StacksPublicKeyBuffer::from_public_key(&public_key),
+            public_key_hash: Hash160::from_node_public_key(&public_key),
+            services: local_peer.services,
+            network_id: local_peer.network_id,
+            parent_network_id: local_peer.parent_network_id,
+            expire_block_height: local_peer.private_key_expire,
+            data_url: local_peer.data_url.clone(),
+            challenge: challenge.map(|challenge| to_hex(challenge)),
+            signature: MessageSignature::empty(),
+        };
+        let sighash = identity.signature_hash()?;
+        identity.signature = local_peer
+            .private_key
+            .sign(sighash.as_bytes())
+            .map_err(|e| net_error::SigningError(e.to_string()))?;
+        Ok(identity)
+    }
+
+    /// The hash that `signature` signs: every other field, in order, with the challenge decoded
+    /// from hex.  Fails if the challenge isn't hex.
+    pub fn signature_hash(&self) -> Result<Sha512Trunc256Sum, net_error> {
+        let challenge = match self.challenge {
+            Some(ref challenge_hex) => hex_bytes(challenge_hex).map_err(|_e| {
+                net_error::VerifyingError("Invalid challenge: not a hex string".to_string())
+            })?,
+            None => vec![],
+        };
+
+        let mut bytes = NODE_IDENTITY_SIGNATURE_DOMAIN.to_vec();
+        bytes.extend_from_slice(self.public_key.as_bytes());
+        bytes.extend_from_slice(self.public_key_hash.as_bytes());
+        bytes.extend_from_slice(&self.services.to_be_bytes());
+        bytes.extend_from_slice(&self.network_id.to_be_bytes());
+        bytes.extend_from_slice(&self.parent_network_id.to_be_bytes());
+        bytes.extend_from_slice(&self.expire_block_height.to_be_bytes());
+        self.data_url.consensus_serialize(&mut bytes)?;
+        bytes.extend_from_slice(&(challenge.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&challenge);
+        Ok(Sha512Trunc256Sum::from_data(&bytes))
+    }
+
+    /// Is this identity signed by the holder of `public_key`, and is `public_key_hash` its hash?
+    pub fn verify(&self) -> Result<bool, net_error> {
+        let public_key = self.public_key.to_public_key()?;
+        if Hash160::from_node_public_key(&public_key) != self.public_key_hash {
+            return Ok(false);
+        }
+        let sighash = self.signature_hash()?;
+        Ok(public_key
+            .verify(sighash.as_bytes(), &self.signature)
+            .unwrap_or(false))
+    }
+}
+
+impl RPCMicroblockProof {
+    /// Build the proof that `txid` was confirmed from the microblock stream that `fork`'s block
+    /// confirms.
+    /// Returns Ok(None) if it was not (e.g. it was mined in that block itself).
+    pub fn from_chainstate(
+        chainstate: &StacksChainState,
+        txid: &Txid,
+        fork: &RPCTransactionFork,
+    ) -> Result<Option<RPCMicroblockProof>, net_error> {
+        let microblocks =
+            match chainstate.load_confirmed_microblock_stream(&fork.index_block_hash)? {
+                Some(microblocks) => microblocks,
+                None => return Ok(None),
+            };
+        let microblock = match microblocks
+            .iter()
+            .find(|mblock| mblock.txs.iter().any(|tx| tx.txid() == *txid))
+        {
+            Some(microblock) => microblock,
+            None => return Ok(None),
+        };
+        let header = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &fork.index_block_hash,
+        )? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let (parent_consensus_hash, parent_block_hash) =
+            match StacksChainState::get_parent_block_header_hashes(
+                chainstate.db(),
+                &fork.index_block_hash,
+            )? {
+                Some(hashes) => hashes,
+                None => return Ok(None),
+            };
+        let parent_header = match StacksChainState::get_anchored_block_header_info(
+            chainstate.db(),
+            &parent_consensus_hash,
+            &parent_block_hash,
+        )? {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+
+        Ok(Some(RPCMicroblockProof {
+            txid: txid.to_hex(),
+            index_block_hash: fork.index_block_hash.clone(),
+            consensus_hash: fork.consensus_hash.clone(),
+            block_height: fork.block_height,
+            canonical: fork.canonical,
+            block_header: to_hex(&header.anchored_header.serialize_to_vec()),
+            parent_consensus_hash,
+            parent_block_header: to_hex(&parent_header.anchored_header.serialize_to_vec()),
+            microblock_headers: microblocks
+                .iter()
+                .map(|mblock| to_hex(&mblock.header.serialize_to_vec()))
+                .collect(),
+            microblock_sequence: microblock.header.sequence,
+            microblock_txids: microblock.txs.iter().map(|tx| tx.txid().to_hex()).collect(),
+        }))
+    }
+
+    fn decode<T: StacksMessageCodec>(hex: &str, what: &str) -> Result<T, net_error> {
+        let bytes = hex_bytes(hex)
+            .map_err(|_| net_error::VerifyingError(format!("Failed to decode {} hex", what)))?;
+        T::consensus_deserialize(&mut &bytes[..])
+            .map_err(|_| net_error::VerifyingError(format!("Failed to decode {}", what)))
+    }
+
+    /// Check the proof the way a client that does not trust this node would.  This does not
+    /// check that the confirming block itself was mined (i.e. that it won the sortition at
+    /// `consensus_hash`); that is up to the caller.
+    pub fn verify(&self) -> Result<(), net_error> {
+        let header: StacksBlockHeader =
+            RPCMicroblockProof::decode(&self.block_header, "block header")?;
+        if header.index_block_hash(&self.consensus_hash) != self.index_block_hash {
+            return Err(net_error::VerifyingError(
+                "Block header does not match index block hash".to_string(),
+            ));
+        }
+
+        let parent_header: StacksBlockHeader =
+            RPCMicroblockProof::decode(&self.parent_block_header, "parent block header")?;
+        if header.parent_block != parent_header.block_hash() {
+            return Err(net_error::VerifyingError(
+                "Parent block header is not the block's parent".to_string(),
+            ));
+        }
+
+        let mut microblock_headers: Vec<StacksMicroblockHeader> = vec![];
+        for hex in self.microblock_headers.iter() {
+            let mblock_header: StacksMicroblockHeader =
+                RPCMicroblockProof::decode(hex, "microblock header")?;
+            let prev_block = match microblock_headers.last() {
+                Some(prev) => prev.block_hash(),
+                None => parent_header.block_hash(),
+            };
+            if mblock_header.sequence as usize != microblock_headers.len()
+                || mblock_header.prev_block != prev_block
+            {
+                return Err(net_error::VerifyingError(format!(
+                    "Microblock {} does not extend the stream",
+                    mblock_header.block_hash()
+                )));
+            }
+            mblock_header.verify(&parent_header.microblock_pubkey_hash)?;
+            microblock_headers.push(mblock_header);
+        }
+
+        match microblock_headers.last() {
+            Some(tail)
+                if tail.block_hash() == header.parent_microblock
+                    && tail.sequence == header.parent_microblock_sequence => {}
+            _ => {
+                return Err(net_error::VerifyingError(
+                    "Microblock stream is not the one the block confirms".to_string(),
+                ));
+            }
+        }
+
+        let mblock_header = microblock_headers
+            .get(self.microblock_sequence as usize)
+            .ok_or(net_error::VerifyingError(
+                "No microblock with the transaction's sequence number".to_string(),
+            ))?;
+        let mut txid_vecs = vec![];
+        for txid in self.microblock_txids.iter() {
+            let txid = Txid::from_hex(txid)
+                .map_err(|_| net_error::VerifyingError("Failed to decode txid".to_string()))?;
+            txid_vecs.push(txid.as_bytes().to_vec());
+        }
+        if MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs).root() != mblock_header.tx_merkle_root {
+            return Err(net_error::VerifyingError(
+                "Microblock transactions do not match its Merkle root".to_string(),
+            ));
+        }
+        if !self.microblock_txids.contains(&self.txid) {
+            return Err(net_error::VerifyingError(
+                "Transaction is not in the microblock".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
 impl RPCPoxInfoData {
     pub fn from_db(
         sortdb: &SortitionDB,
@@ -415,64 +983,670 @@ impl RPCPoxInfoData {
     }
 }
 
-impl RPCNeighborsInfo {
-    /// Load neighbor address information from the peer network
-    pub fn from_p2p(
-        network_id: u32,
-        peers: &PeerMap,
-        chain_view: &BurnchainView,
-        peerdb: &PeerDB,
-    ) -> Result<RPCNeighborsInfo, net_error> {
-        let neighbor_sample = PeerDB::get_random_neighbors(
-            peerdb.conn(),
-            network_id,
-            MAX_NEIGHBORS_DATA_LEN,
-            chain_view.burn_block_height,
-            false,
-        )
-        .map_err(net_error::DBError)?;
+impl RPCStateHashData {
+    /// Digest a block's MARF root, header chain work, and sortition ID into a single hash that
+    /// nodes can compare to check that they agree on consensus at that block.
+    pub fn make_state_hash(
+        state_index_root: &TrieHash,
+        total_work: &StacksWorkScore,
+        sortition_id: &SortitionId,
+    ) -> Sha512Trunc256Sum {
+        let mut bytes = Vec::with_capacity(32 + 8 + 8 + 32);
+        bytes.extend_from_slice(state_index_root.as_bytes());
+        bytes.extend_from_slice(&total_work.burn.to_be_bytes());
+        bytes.extend_from_slice(&total_work.work.to_be_bytes());
+        bytes.extend_from_slice(sortition_id.as_bytes());
+        Sha512Trunc256Sum::from_data(&bytes)
+    }
 
-        let sample: Vec<RPCNeighbor> = neighbor_sample
-            .into_iter()
-            .map(|n| {
-                RPCNeighbor::from_neighbor_key_and_pubkh(
-                    n.addr.clone(),
-                    Hash160::from_node_public_key(&n.public_key),
-                    true,
-                )
-            })
-            .collect();
+    /// Load the state hash for the given Stacks block.
+    /// Returns NotFoundError if the block or its sortition is unknown.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        block_id: &StacksBlockId,
+    ) -> Result<RPCStateHashData, net_error> {
+        let header_info = StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            block_id,
+        )?
+        .ok_or(net_error::NotFoundError)?;
+
+        let snapshot =
+            SortitionDB::get_block_snapshot_consensus(sortdb.conn(), &header_info.consensus_hash)?
+                .ok_or(net_error::NotFoundError)?;
+
+        let header = &header_info.anchored_header;
+        let state_hash = RPCStateHashData::make_state_hash(
+            &header.state_index_root,
+            &header.total_work,
+            &snapshot.sortition_id,
+        );
 
-        let mut inbound = vec![];
-        let mut outbound = vec![];
-        for (_, convo) in peers.iter() {
-            let nk = convo.to_neighbor_key();
-            let naddr = convo.to_neighbor_address();
-            if convo.is_outbound() {
-                outbound.push(RPCNeighbor::from_neighbor_key_and_pubkh(
-                    nk,
-                    naddr.public_key_hash,
-                    convo.is_authenticated(),
-                ));
+        Ok(RPCStateHashData {
+            stacks_block_height: header_info.block_height,
+            index_block_hash: header_info.index_block_hash(),
+            consensus_hash: header_info.consensus_hash.clone(),
+            state_index_root: header.state_index_root.clone(),
+            total_burn: header.total_work.burn,
+            total_work: header.total_work.work,
+            sortition_id: snapshot.sortition_id,
+            state_hash,
+        })
+    }
+}
+
+impl RPCMemPoolFeeData {
+    /// Fee rate of a transaction, in microSTX per byte
+    fn fee_rate(tx_fee: u64, tx_len: u64) -> f64 {
+        (tx_fee as f64) / (cmp::max(tx_len, 1) as f64)
+    }
+
+    /// Nearest-rank percentile of an ascending list of fee rates
+    fn percentile(sorted_fee_rates: &[f64], percentile: u64) -> f64 {
+        let rank = (percentile * (sorted_fee_rates.len() as u64) + 99) / 100;
+        sorted_fee_rates[cmp::max(rank, 1) as usize - 1]
+    }
+
+    /// How many blocks would it take to mine `total_bytes` of transactions that are
+    /// estimated to consume `total_cost`?
+    fn depth_in_blocks(
+        total_bytes: u64,
+        total_cost: &[u128; 5],
+        block_limit: &ExecutionCost,
+    ) -> u64 {
+        let blocks_needed = |total: u128, limit: u64| -> u128 {
+            if limit == 0 {
+                0
             } else {
-                inbound.push(RPCNeighbor::from_neighbor_key_and_pubkh(
-                    nk,
-                    naddr.public_key_hash,
-                    convo.is_authenticated(),
-                ));
+                (total + (limit as u128) - 1) / (limit as u128)
+            }
+        };
+        let limits = [
+            block_limit.runtime,
+            block_limit.write_length,
+            block_limit.write_count,
+            block_limit.read_length,
+            block_limit.read_count,
+        ];
+        let mut depth = blocks_needed(total_bytes as u128, MAX_EPOCH_SIZE as u64);
+        for (total, limit) in total_cost.iter().zip(limits.iter()) {
+            depth = cmp::max(depth, blocks_needed(*total, *limit));
+        }
+        u64::try_from(depth).unwrap_or(u64::MAX)
+    }
+
+    /// Compute fee market statistics from the mempool and the last few canonical blocks.
+    pub fn from_db(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        mempool: &MemPoolDB,
+    ) -> Result<RPCMemPoolFeeData, net_error> {
+        let tip_header = RPCReadSnapshot::new(sortdb, chainstate)?.stacks_tip;
+        let stacks_tip_height = tip_header.as_ref().map(|hdr| hdr.block_height).unwrap_or(0);
+
+        // what's waiting to be mined
+        let txs = MemPoolDB::get_candidate_txs(mempool.conn(), stacks_tip_height)?;
+        let mut fee_rates = Vec::with_capacity(txs.len());
+        let mut total_bytes: u64 = 0;
+        let mut total_cost = [0u128; 5];
+        for txinfo in txs.iter() {
+            fee_rates.push(RPCMemPoolFeeData::fee_rate(
+                txinfo.metadata.tx_fee,
+                txinfo.metadata.len,
+            ));
+            total_bytes = total_bytes.saturating_add(txinfo.metadata.len);
+            if let Some(cost) = mempool.estimate_tx_cost(&txinfo.tx)? {
+                total_cost[0] += cost.runtime as u128;
+                total_cost[1] += cost.write_length as u128;
+                total_cost[2] += cost.write_count as u128;
+                total_cost[3] += cost.read_length as u128;
+                total_cost[4] += cost.read_count as u128;
             }
         }
+        fee_rates.sort_by(|a, b| a.partial_cmp(b).unwrap_or(cmp::Ordering::Equal));
+
+        let fee_rate_percentiles = if fee_rates.len() > 0 {
+            Some(RPCFeeRatePercentiles {
+                p10: RPCMemPoolFeeData::percentile(&fee_rates, 10),
+                p25: RPCMemPoolFeeData::percentile(&fee_rates, 25),
+                p50: RPCMemPoolFeeData::percentile(&fee_rates, 50),
+                p75: RPCMemPoolFeeData::percentile(&fee_rates, 75),
+                p90: RPCMemPoolFeeData::percentile(&fee_rates, 90),
+            })
+        } else {
+            None
+        };
 
-        Ok(RPCNeighborsInfo {
-            sample: sample,
-            inbound: inbound,
-            outbound: outbound,
+        // what it took to get into recent blocks
+        let mut recent_inclusion_thresholds = vec![];
+        if let Some(tip_header) = tip_header {
+            let lowest_height =
+                stacks_tip_height.saturating_sub(MEMPOOL_FEE_RECENT_BLOCKS.saturating_sub(1));
+            for header in
+                StacksChainState::get_ancestors_headers(chainstate.db(), tip_header, lowest_height)?
+                    .into_iter()
+            {
+                let block = match StacksChainState::load_block(
+                    &chainstate.blocks_path,
+                    &header.consensus_hash,
+                    &header.anchored_header.block_hash(),
+                ) {
+                    Ok(Some(block)) => block,
+                    Ok(None) | Err(_) => {
+                        // e.g. the genesis block has no block file
+                        continue;
+                    }
+                };
+
+                let mut num_txs = 0;
+                let mut min_fee_rate: Option<f64> = None;
+                for tx in block.txs.iter() {
+                    if let TransactionPayload::Coinbase(_) = tx.payload {
+                        continue;
+                    }
+                    num_txs += 1;
+                    let fee_rate = RPCMemPoolFeeData::fee_rate(
+                        tx.get_tx_fee(),
+                        tx.serialize_to_vec().len() as u64,
+                    );
+                    min_fee_rate = Some(match min_fee_rate {
+                        Some(rate) if rate < fee_rate => rate,
+                        _ => fee_rate,
+                    });
+                }
+
+                recent_inclusion_thresholds.push(RPCFeeRateThreshold {
+                    stacks_block_height: header.block_height,
+                    index_block_hash: header.index_block_hash(),
+                    num_txs,
+                    min_fee_rate,
+                });
+            }
+        }
+
+        Ok(RPCMemPoolFeeData {
+            stacks_tip_height,
+            num_txs: txs.len() as u64,
+            total_bytes,
+            fee_rates: fee_rate_percentiles,
+            depth_in_blocks: RPCMemPoolFeeData::depth_in_blocks(
+                total_bytes,
+                &total_cost,
+                &chainstate.block_limit,
+            ),
+            recent_inclusion_thresholds,
         })
     }
 }
 
-impl ConversationHttp {
-    pub fn new(
+impl ClarityValueResponse {
+    /// Consensus-serialize a Clarity value.  Values given as JSON are not checked for internal
+    /// consistency (e.g. a list mixing item types), so the encoding is only accepted if it
+    /// decodes back to the same value.  The decoded value is the one returned, so its type
+    /// information is what the VM would infer.
+    pub fn from_value(value: Value) -> Result<ClarityValueResponse, String> {
+        let mut bytes = vec![];
+        value
+            .serialize_write(&mut bytes)
+            .map_err(|e| format!("Failed to serialize value: {}", e))?;
+        let decoded = match Value::try_deserialize_bytes_untyped(&bytes) {
+            Ok(decoded) if decoded == value => decoded,
+            Ok(_) => return Err("Value is not well-formed".to_string()),
+            Err(e) => return Err(format!("Value is not well-formed: {}", e)),
+        };
+        Ok(ClarityValueResponse {
+            hex: to_hex(&bytes),
+            repr: decoded.to_string(),
+            value: decoded,
+        })
+    }
+
+    /// Decode a consensus-serialized Clarity value, with or without a leading `0x`.  Like
+    /// `from-consensus-buff?`, this fails if there are bytes left over after the value.
+    pub fn from_hex(hex: &str) -> Result<ClarityValueResponse, String> {
+        let hex = if hex.starts_with("0x") {
+            &hex[2..]
+        } else {
+            hex
+        };
+        let bytes = hex_bytes(hex).map_err(|_| "Bad hex string".to_string())?;
+        let mut cursor = bytes.as_slice();
+        let value = Value::deserialize_read(&mut cursor, None)
+            .map_err(|e| format!("Failed to deserialize value: {}", e))?;
+        if !cursor.is_empty() {
+            return Err(format!(
+                "Failed to deserialize value: {} trailing bytes",
+                cursor.len()
+            ));
+        }
+        ClarityValueResponse::from_value(value)
+    }
+}
+
+impl RPCMemPoolDropData {
+    pub fn from_record(record: &MemPoolDropRecord) -> RPCMemPoolDropData {
+        RPCMemPoolDropData {
+            txid: record.txid.to_hex(),
+            reason: record.reason.clone(),
+            drop_time: record.drop_time,
+            tx_fee: record.tx_fee,
+            block_height: record.block_height,
+            origin_address: record.origin_address.to_string(),
+            origin_nonce: record.origin_nonce,
+            sponsor_address: record.sponsor_address.to_string(),
+            sponsor_nonce: record.sponsor_nonce,
+            replaced_by: record.replaced_by.as_ref().map(|txid| txid.to_hex()),
+        }
+    }
+}
+
+impl RPCPendingTransaction {
+    pub fn from_metadata(
+        metadata: &MemPoolTxMetadata,
+        conflicts: &[MemPoolDropRecord],
+    ) -> RPCPendingTransaction {
+        let mut conflict_txids: Vec<String> = vec![];
+        for record in conflicts.iter() {
+            let txid = record.txid.to_hex();
+            if !conflict_txids.contains(&txid) {
+                conflict_txids.push(txid);
+            }
+        }
+        RPCPendingTransaction {
+            txid: metadata.txid.to_hex(),
+            origin_address: metadata.origin_address.to_string(),
+            origin_nonce: metadata.origin_nonce,
+            sponsor_address: metadata.sponsor_address.to_string(),
+            sponsor_nonce: metadata.sponsor_nonce,
+            tx_fee: metadata.tx_fee,
+            tx_len: metadata.len,
+            accept_time: metadata.accept_time,
+            block_height: metadata.block_height,
+            conflicts: conflict_txids,
+        }
+    }
+}
+
+impl RPCMicroblockStream {
+    pub fn from_fork(fork: &MicroblockStreamFork) -> RPCMicroblockStream {
+        RPCMicroblockStream {
+            tip: fork.tip_hash.clone(),
+            min_seq: fork.min_seq,
+            max_seq: fork.max_seq,
+            num_microblocks: fork.num_microblocks,
+            fork_seq: fork.fork_seq,
+            signer: fork.signer.as_ref().map(|signer| signer.to_hex()),
+            first_seen: fork.first_seen,
+            tip_first_seen: fork.tip_first_seen,
+            num_confirmed: fork.num_confirmed,
+            orphaned: fork.orphaned,
+        }
+    }
+}
+
+impl RPCPageRequest {
+    /// How many items to put in the requested page: the requested limit, capped at
+    /// `max_page_size`.  Defaults to `max_page_size`.
+    pub fn page_size(&self, max_page_size: u32) -> u32 {
+        cmp::max(
+            1,
+            cmp::min(self.limit.unwrap_or(max_page_size), max_page_size),
+        )
+    }
+}
+
+impl<T> RPCPage<T> {
+    /// Make a page out of the items that come after the request's cursor.  Callers load up to
+    /// `page_size + 1` items, so that the extra item tells us whether or not there is another
+    /// page.  The extra item is dropped, and the next cursor is made from the page's last item.
+    pub fn from_items<F>(mut items: Vec<T>, page_size: u32, make_cursor: F) -> RPCPage<T>
+    where
+        F: FnOnce(&T) -> String,
+    {
+        let next_cursor = if items.len() > page_size as usize {
+            items.truncate(page_size as usize);
+            items.last().map(make_cursor)
+        } else {
+            None
+        };
+        RPCPage { items, next_cursor }
+    }
+
+    pub fn map<U, F>(self, f: F) -> RPCPage<U>
+    where
+        F: FnMut(T) -> U,
+    {
+        RPCPage {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+impl RPCDecodedTransaction {
+    pub fn from_tx(tx: &StacksTransaction) -> RPCDecodedTransaction {
+        RPCDecodedTransaction {
+            txid: tx.txid().to_hex(),
+            tx_len: tx.serialize_to_vec().len() as u64,
+            origin: tx.origin_address().to_string(),
+            sponsor: tx.sponsor_address().map(|addr| addr.to_string()),
+            tx: tx.clone(),
+        }
+    }
+}
+
+impl RPCDecodedBlock {
+    pub fn from_block(index_block_hash: &StacksBlockId, block: &StacksBlock) -> RPCDecodedBlock {
+        RPCDecodedBlock {
+            index_block_hash: index_block_hash.clone(),
+            block_hash: block.block_hash(),
+            header: block.header.clone(),
+            txs: block
+                .txs
+                .iter()
+                .map(RPCDecodedTransaction::from_tx)
+                .collect(),
+        }
+    }
+}
+
+impl RPCDecodedMicroblock {
+    pub fn from_microblock(microblock: &StacksMicroblock) -> RPCDecodedMicroblock {
+        RPCDecodedMicroblock {
+            block_hash: microblock.block_hash(),
+            header: microblock.header.clone(),
+            txs: microblock
+                .txs
+                .iter()
+                .map(RPCDecodedTransaction::from_tx)
+                .collect(),
+        }
+    }
+}
+
+impl RPCNeighborsInfo {
+    /// Load neighbor address information from the peer network
+    pub fn from_p2p(
+        network_id: u32,
+        peers: &PeerMap,
+        chain_view: &BurnchainView,
+        peerdb: &PeerDB,
+    ) -> Result<RPCNeighborsInfo, net_error> {
+        let neighbor_sample = PeerDB::get_random_neighbors(
+            peerdb.conn(),
+            network_id,
+            MAX_NEIGHBORS_DATA_LEN,
+            chain_view.burn_block_height,
+            false,
+        )
+        .map_err(net_error::DBError)?;
+
+        let sample: Vec<RPCNeighbor> = neighbor_sample
+            .into_iter()
+            .map(|n| {
+                RPCNeighbor::from_neighbor_key_and_pubkh(
+                    n.addr.clone(),
+                    Hash160::from_node_public_key(&n.public_key),
+                    true,
+                )
+            })
+            .collect();
+
+        let mut inbound = vec![];
+        let mut outbound = vec![];
+        for (_, convo) in peers.iter() {
+            let nk = convo.to_neighbor_key();
+            let naddr = convo.to_neighbor_address();
+            if convo.is_outbound() {
+                outbound.push(RPCNeighbor::from_neighbor_key_and_pubkh(
+                    nk,
+                    naddr.public_key_hash,
+                    convo.is_authenticated(),
+                ));
+            } else {
+                inbound.push(RPCNeighbor::from_neighbor_key_and_pubkh(
+                    nk,
+                    naddr.public_key_hash,
+                    convo.is_authenticated(),
+                ));
+            }
+        }
+
+        Ok(RPCNeighborsInfo {
+            sample: sample,
+            inbound: inbound,
+            outbound: outbound,
+        })
+    }
+}
+
+impl RPCNeighborWalkInfo {
+    /// Report on the neighbor walk and the quality of the frontier it maintains
+    pub fn from_p2p(
+        peers: &PeerMap,
+        chain_view: &BurnchainView,
+        peerdb: &PeerDB,
+        walk_stats: &NeighborWalkStats,
+    ) -> Result<RPCNeighborWalkInfo, net_error> {
+        let mut num_inbound = 0;
+        let mut num_outbound = 0;
+        for (_, convo) in peers.iter() {
+            if convo.is_outbound() {
+                num_outbound += 1;
+            } else {
+                num_inbound += 1;
+            }
+        }
+
+        let frontier_size = PeerDB::get_frontier_size(peerdb.conn())?;
+        let frontier_quality = walk_stats.frontier_quality(
+            peerdb.conn(),
+            chain_view.burn_block_height,
+            get_epoch_time_secs(),
+        )?;
+        let assessment = walk_stats
+            .assess(&frontier_quality, num_outbound)
+            .to_string();
+
+        Ok(RPCNeighborWalkInfo {
+            walks: walk_stats.walks,
+            resets: walk_stats.resets,
+            last_walk_duration_ms: walk_stats.last_walk_duration_ms,
+            last_walk_time: walk_stats.last_walk_time,
+            frontier_size,
+            churn_added: walk_stats.churn_added,
+            churn_replaced: walk_stats.churn_replaced,
+            unreachable_ratio: walk_stats.unreachable_ratio.unwrap_or(0.0),
+            num_inbound,
+            num_outbound,
+            frontier_quality,
+            assessment,
+            handshake_concurrency: walk_stats.handshakes.to_rpc(),
+        })
+    }
+}
+
+impl RPCPeerVersionsInfo {
+    /// Count the peer versions our connected and crawled peers advertise
+    pub fn from_p2p(
+        local_peer_version: u32,
+        peers: &PeerMap,
+        chain_view: &BurnchainView,
+        peerdb: &PeerDB,
+    ) -> Result<RPCPeerVersionsInfo, net_error> {
+        let frontier = PeerDB::get_all_peers(peerdb.conn())?;
+        let census = PeerVersionCensus::take(peers, &frontier, chain_view.burn_block_height);
+        let to_counts = |counts: &BTreeMap<u32, u64>| -> Vec<RPCPeerVersionCount> {
+            counts
+                .iter()
+                .map(|(peer_version, count)| RPCPeerVersionCount {
+                    peer_version: *peer_version,
+                    count: *count,
+                })
+                .collect()
+        };
+
+        Ok(RPCPeerVersionsInfo {
+            peer_version: local_peer_version,
+            connected: to_counts(&census.connected),
+            observed: to_counts(&census.observed),
+            newer_pct: census.pct_observed_newer(local_peer_version),
+            newest_peer_version: census.newest_observed(),
+        })
+    }
+}
+
+impl RPCNodeStatus {
+    /// Assemble the node's status from the state each subsystem already keeps
+    pub fn from_p2p(
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        atlasdb: &AtlasDB,
+        mempool: &MemPoolDB,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<RPCNodeStatus, net_error> {
+        let burnchain_tip_height = handler_args
+            .node_status
+            .and_then(|source| source.get_burnchain_tip_height());
+        let burnchain = RPCBurnchainStatus {
+            burn_block_height: chain_view.burn_block_height,
+            stable_burn_block_height: chain_view.burn_stable_block_height,
+            burnchain_tip_height,
+            lag: burnchain_tip_height
+                .map(|height| height.saturating_sub(chain_view.burn_block_height)),
+        };
+
+        let snapshot = RPCReadSnapshot::new(sortdb, chainstate)?;
+        let tip_burn_block_height = snapshot
+            .stacks_tip
+            .as_ref()
+            .map(|header| header.burn_header_height as u64)
+            .unwrap_or(sortdb.first_block_height);
+        let stacks = RPCStacksStatus {
+            tip_height: snapshot.burnchain_tip.canonical_stacks_tip_height,
+            tip: snapshot.stacks_tip_index_hash(),
+            tip_burn_block_height,
+            lag: snapshot
+                .burnchain_tip
+                .block_height
+                .saturating_sub(tip_burn_block_height),
+        };
+
+        let mut peer_counts = RPCPeerCounts {
+            inbound: 0,
+            outbound: 0,
+        };
+        for (_, convo) in peers.iter() {
+            if convo.is_outbound() {
+                peer_counts.outbound += 1;
+            } else {
+                peer_counts.inbound += 1;
+            }
+        }
+
+        let atlas = RPCAtlasStatus {
+            unresolved_attachment_instances: atlasdb.count_unresolved_attachment_instances()?
+                as u64,
+            uninstantiated_attachments: atlasdb.count_uninstantiated_attachments()? as u64,
+        };
+
+        let mut last_errors = handler_args
+            .node_status
+            .map(|source| source.get_last_errors())
+            .unwrap_or(vec![]);
+        if let Some(observers) = handler_args.event_observer_status {
+            for observer in observers.get_event_observer_status() {
+                if let Some(message) = observer.last_error {
+                    last_errors.push(RPCSubsystemError {
+                        subsystem: format!("event_observer {}", &observer.endpoint),
+                        message,
+                        at: None,
+                    });
+                }
+            }
+        }
+
+        Ok(RPCNodeStatus {
+            burnchain,
+            stacks,
+            peers: peer_counts,
+            mempool: RPCMemPoolStatus {
+                num_txs: MemPoolDB::get_num_txs(mempool.conn())?,
+            },
+            atlas,
+            mining: handler_args
+                .node_status
+                .map(|source| source.get_mining_status()),
+            last_errors,
+        })
+    }
+}
+
+impl RPCPeerFilters {
+    /// Load the PeerDB's allow and deny lists
+    pub fn from_db(conn: &DBConn) -> Result<RPCPeerFilters, db_error> {
+        let to_list =
+            |cidrs: Vec<(PeerAddress, u32)>, pubkey_hashes: Vec<Hash160>| RPCPeerFilterList {
+                cidrs: cidrs
+                    .into_iter()
+                    .map(|(prefix, mask)| PeerFilterRule::Cidr(prefix, mask).to_string())
+                    .collect(),
+                pubkey_hashes: pubkey_hashes
+                    .into_iter()
+                    .map(|pubkey_hash| pubkey_hash.to_hex())
+                    .collect(),
+            };
+        Ok(RPCPeerFilters {
+            allow: to_list(
+                PeerDB::get_allowed_cidrs(conn)?,
+                PeerDB::get_allowed_pubkey_hashes(conn)?,
+            ),
+            deny: to_list(
+                PeerDB::get_denied_cidrs(conn)?,
+                PeerDB::get_denied_pubkey_hashes(conn)?,
+            ),
+        })
+    }
+}
+
+impl RPCAttachmentPins {
+    /// List the pinned attachments, and how much attachment content is stored
+    pub fn from_db(atlasdb: &AtlasDB) -> Result<RPCAttachmentPins, db_error> {
+        let hashes = atlasdb
+            .get_pinned_attachments()?
+            .into_iter()
+            .map(|content_hash| content_hash.to_hex())
+            .collect();
+        Ok(RPCAttachmentPins {
+            hashes,
+            stored_bytes: atlasdb.get_attachments_total_size()?,
+        })
+    }
+}
+
+impl RPCAtlasContracts {
+    /// List the contracts whose attachments are tracked
+    pub fn from_config(atlas_config: &AtlasConfig) -> RPCAtlasContracts {
+        let contracts = atlas_config
+            .tracked_contracts()
+            .into_iter()
+            .map(|(contract_id, domain)| RPCAtlasContract {
+                runtime: atlas_config.watch_list.contains(&contract_id),
+                contract_id: contract_id.to_string(),
+                attachments_max_size: domain.attachments_max_size,
+                unresolved_attachment_instances_expire_after: domain
+                    .unresolved_attachment_instances_expire_after,
+            })
+            .collect();
+        RPCAtlasContracts { contracts }
+    }
+}
+
+impl ConversationHttp {
+    pub fn new(
         network_id: u32,
         burnchain: &Burnchain,
         peer_addr: SocketAddr,
@@ -483,6 +1657,9 @@ impl ConversationHttp {
     ) -> ConversationHttp {
         let mut stacks_http = StacksHttp::new(peer_addr.clone());
         stacks_http.maximum_call_argument_size = conn_opts.maximum_call_argument_size;
+        stacks_http.maximum_transaction_body_size = conn_opts.maximum_transaction_body_size;
+        stacks_http.maximum_request_body_size = conn_opts.maximum_request_body_size;
+        stacks_http.maximum_attachment_size = conn_opts.maximum_attachment_size;
         ConversationHttp {
             network_id: network_id,
             connection: ConnectionHttp::new(stacks_http, conn_opts, None),
@@ -601,6 +1778,23 @@ impl ConversationHttp {
         Ok(())
     }
 
+    /// Handle a GET for the node's optional features, and whether or not each is switched on.
+    /// The response will be synchronously written to the fd.
+    fn handle_getfeatures<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        peerdb: &PeerDB,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let local_peer = PeerDB::get_local_peer(peerdb.conn())?;
+        let features = NodeFeatures::new(local_peer.services, options);
+        HttpResponseType::NodeFeatures(response_metadata, features.to_rpc())
+            .send(http, fd)
+            .map(|_| ())
+    }
+
     /// Handle a GET peer info.
     /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
     fn handle_getinfo<W: Write>(
@@ -628,11 +1822,8 @@ impl ConversationHttp {
                 response.send(http, fd)
             }
             Err(e) => {
-                warn!("Failed to get peer info {:?}: {:?}", req, &e);
-                let response = HttpResponseType::ServerError(
-                    response_metadata,
-                    "Failed to query peer info".to_string(),
-                );
+                warn!("Failed to get peer info {:?}: {}", req, &e);
+                let response = HttpResponseType::from_net_error(response_metadata, &e);
                 // timer.observe_duration();
                 response.send(http, fd)
             }
@@ -649,28 +1840,65 @@ impl ConversationHttp {
         chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
         burnchain: &Burnchain,
+        rpc_cache: &mut RPCResponseCache,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
+        let route = req.request_path();
+        let cacheable = RPCResponseCache::is_cacheable_tip(chainstate, tip);
+        if cacheable {
+            if let Some(response) = rpc_cache.get(&route, tip, response_metadata.clone()) {
+                return response.send(http, fd);
+            }
+        }
 
-        match RPCPoxInfoData::from_db(sortdb, chainstate, tip, burnchain) {
-            Ok(pi) => {
-                let response = HttpResponseType::PoxInfo(response_metadata, pi);
+        let response = match RPCPoxInfoData::from_db(sortdb, chainstate, tip, burnchain) {
+            Ok(pi) => HttpResponseType::PoxInfo(response_metadata, pi),
+            Err(net_error::NotFoundError) => {
+                debug!("Chain tip not found during get PoX info: {:?}", req);
+                HttpResponseType::NotFound(
+                    response_metadata,
+                    "Failed to find chain tip".to_string(),
+                )
+            }
+            Err(e) => {
+                warn!("Failed to get PoX info {:?}: {}", req, &e);
+                HttpResponseType::from_net_error(response_metadata, &e)
+            }
+        };
+        if cacheable {
+            rpc_cache.insert(route, tip, &response);
+        }
+        response.send(http, fd)
+    }
+
+    /// Handle a GET state hash for the Stacks block at a given height.
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getstatehash<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        block_id: &StacksBlockId,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        match RPCStateHashData::from_db(sortdb, chainstate, block_id) {
+            Ok(data) => {
+                let response = HttpResponseType::StateHash(response_metadata, data);
                 response.send(http, fd)
             }
             Err(net_error::NotFoundError) => {
-                debug!("Chain tip not found during get PoX info: {:?}", req);
+                debug!("Block not found during get state hash: {:?}", req);
                 let response = HttpResponseType::NotFound(
                     response_metadata,
-                    "Failed to find chain tip".to_string(),
+                    format!("Failed to find block {}", block_id),
                 );
                 response.send(http, fd)
             }
             Err(e) => {
-                warn!("Failed to get PoX info {:?}: {:?}", req, &e);
-                let response = HttpResponseType::ServerError(
-                    response_metadata,
-                    "Failed to query peer info".to_string(),
-                );
+                warn!("Failed to get state hash {:?}: {}", req, &e);
+                let response = HttpResponseType::from_net_error(response_metadata, &e);
                 response.send(http, fd)
             }
         }
@@ -683,6 +1911,7 @@ impl ConversationHttp {
         atlasdb: &AtlasDB,
         index_block_hash: &StacksBlockId,
         pages_indexes: &HashSet<u32>,
+        deadline: Option<Instant>,
         _options: &ConnectionOptions,
     ) -> Result<(), net_error> {
         // We are receiving a list of page indexes with a chain tip hash.
@@ -719,6 +1948,13 @@ impl ConversationHttp {
         let mut pages = vec![];
 
         for page_index in pages_indexes.iter() {
+            if ConversationHttp::deadline_passed(deadline) {
+                let response = HttpResponseType::ServiceUnavailable(
+                    response_metadata,
+                    "Request deadline exceeded".to_string(),
+                );
+                return response.send(http, fd);
+            }
             match atlasdb.get_attachments_available_at_page_index(*page_index, &index_block_hash) {
                 Ok(inventory) => {
                     pages.push(AttachmentPage {
@@ -727,9 +1963,9 @@ impl ConversationHttp {
                     });
                 }
                 Err(e) => {
-                    let msg = format!("Unable to read Atlas DB - {}", e);
-                    warn!("{}", msg);
-                    let response = HttpResponseType::NotFound(response_metadata, msg);
+                    let e = net_error::DBError(e);
+                    warn!("Unable to read Atlas DB - {}", &e);
+                    let response = HttpResponseType::from_net_error(response_metadata, &e);
                     return response.send(http, fd);
                 }
             }
@@ -751,11 +1987,13 @@ impl ConversationHttp {
         content_hash: Hash160,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        match atlasdb.find_attachment(&content_hash) {
+        match atlasdb.find_attachment_or_mirrored(&content_hash) {
             Ok(Some(attachment)) => {
                 let content = GetAttachmentResponse { attachment };
                 let response = HttpResponseType::GetAttachment(response_metadata, content);
-                response.send(http, fd)
+                response.send(http, fd)?;
+                monitoring::increment_attachments_served_counter();
+                Ok(())
             }
             _ => {
                 let msg = format!("Unable to find attachment");
@@ -766,27 +2004,259 @@ impl ConversationHttp {
         }
     }
 
-    /// Handle a GET neighbors
-    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
-    fn handle_getneighbors<W: Write>(
+    /// Handle a GET for an attachment's raw content, or the byte range of it that the client
+    /// asked for.  Without a range, the whole content is sent chunk-encoded.
+    fn handle_getattachment_content<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
-        network_id: u32,
-        chain_view: &BurnchainView,
-        peers: &PeerMap,
-        peerdb: &PeerDB,
+        atlasdb: &mut AtlasDB,
+        content_hash: Hash160,
+        range: Option<HttpByteRange>,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let neighbor_data = RPCNeighborsInfo::from_p2p(network_id, peers, chain_view, peerdb)?;
-        let response = HttpResponseType::Neighbors(response_metadata, neighbor_data);
-        response.send(http, fd)
-    }
-
-    /// Handle a not-found
-    fn handle_notfound<W: Write>(
-        http: &mut StacksHttp,
-        fd: &mut W,
+        let attachment = match atlasdb.find_attachment_or_mirrored(&content_hash) {
+            Ok(Some(attachment)) => attachment,
+            _ => {
+                let msg = format!("Unable to find attachment");
+                warn!("{}", msg);
+                let response = HttpResponseType::NotFound(response_metadata, msg);
+                return response.send(http, fd);
+            }
+        };
+
+        let total_len = attachment.content.len() as u64;
+        let (start, end) = match range {
+            // an empty attachment has no byte ranges, so just send all of it
+            Some(range) if total_len > 0 => match range.resolve(total_len) {
+                Some(bounds) => bounds,
+                None => {
+                    let msg = format!(
+                        "Range {} is not satisfiable for {} bytes",
+                        range.to_header(),
+                        total_len
+                    );
+                    let response = HttpResponseType::Error(response_metadata, 416, msg);
+                    return response.send(http, fd);
+                }
+            },
+            _ => (0, total_len.saturating_sub(1)),
+        };
+
+        let content = if total_len > 0 {
+            attachment.content[(start as usize)..=(end as usize)].to_vec()
+        } else {
+            vec![]
+        };
+        let response = HttpResponseType::GetAttachmentContent(
+            response_metadata,
+            AttachmentContentRange {
+                start,
+                total_len,
+                content,
+            },
+        );
+        response.send(http, fd)?;
+        if end + 1 >= total_len {
+            monitoring::increment_attachments_served_counter();
+        }
+        Ok(())
+    }
+
+    /// Handle a GET for several attachments at once.
+    /// Attachments we don't have are left out of the response, as are any that would push the
+    /// response past MAX_ATTACHMENTS_BATCH_RESPONSE_LEN bytes of content -- the client will ask
+    /// again for whatever it did not get.
+    fn handle_getattachments_batch<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &mut AtlasDB,
+        content_hashes: &Vec<Hash160>,
+        deadline: Option<Instant>,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if content_hashes.len() > MAX_ATTACHMENTS_PER_BATCH_REQUEST {
+            let msg = format!(
+                "Number of attachments is limited by {} per request",
+                MAX_ATTACHMENTS_PER_BATCH_REQUEST
+            );
+            warn!("{}", msg);
+            let response = HttpResponseType::BadRequest(response_metadata, msg);
+            return response.send(http, fd);
+        }
+
+        let mut attachments = vec![];
+        let mut total_len: u64 = 0;
+        for content_hash in content_hashes.iter() {
+            if ConversationHttp::deadline_passed(deadline) {
+                let response = HttpResponseType::ServiceUnavailable(
+                    response_metadata,
+                    "Request deadline exceeded".to_string(),
+                );
+                return response.send(http, fd);
+            }
+            match atlasdb.find_attachment_or_mirrored(content_hash) {
+                Ok(Some(attachment)) => {
+                    let len = attachment.content.len() as u64;
+                    if total_len + len > MAX_ATTACHMENTS_BATCH_RESPONSE_LEN {
+                        debug!(
+                            "Leaving attachment {} out of batch response: response is full",
+                            content_hash
+                        );
+                        continue;
+                    }
+                    total_len += len;
+                    attachments.push(GetAttachmentResponse { attachment });
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    let e = net_error::DBError(e);
+                    warn!("Unable to read Atlas DB - {}", &e);
+                    let response = HttpResponseType::from_net_error(response_metadata, &e);
+                    return response.send(http, fd);
+                }
+            }
+        }
+
+        let num_attachments = attachments.len();
+        let content = GetAttachmentsBatchResponse { attachments };
+        let response = HttpResponseType::GetAttachmentsBatch(response_metadata, content);
+        response.send(http, fd)?;
+        for _ in 0..num_attachments {
+            monitoring::increment_attachments_served_counter();
+        }
+        Ok(())
+    }
+
+    /// Handle a GET neighbors
+    /// The response will be synchronously written to the given fd (so use a fd that can buffer!)
+    fn handle_getneighbors<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        network_id: u32,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        peerdb: &PeerDB,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let neighbor_data = RPCNeighborsInfo::from_p2p(network_id, peers, chain_view, peerdb)?;
+        let response = HttpResponseType::Neighbors(response_metadata, neighbor_data);
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the neighbor walk statistics and frontier quality.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_neighbor_walk_info<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        peerdb: &PeerDB,
+        walk_stats: &NeighborWalkStats,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let walk_info = RPCNeighborWalkInfo::from_p2p(peers, chain_view, peerdb, walk_stats)?;
+        let response = HttpResponseType::NeighborWalkInfo(response_metadata, walk_info);
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the peer versions our peers advertise.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_peer_versions<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        local_peer_version: u32,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        peerdb: &PeerDB,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let peer_versions =
+            RPCPeerVersionsInfo::from_p2p(local_peer_version, peers, chain_view, peerdb)?;
+        let response = HttpResponseType::PeerVersions(response_metadata, peer_versions);
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the node's status, for dashboards.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_node_status<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        chain_view: &BurnchainView,
+        peers: &PeerMap,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        atlasdb: &AtlasDB,
+        mempool: &MemPoolDB,
+        handler_args: &RPCHandlerArgs,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match RPCNodeStatus::from_p2p(
+            chain_view,
+            peers,
+            sortdb,
+            chainstate,
+            atlasdb,
+            mempool,
+            handler_args,
+        ) {
+            Ok(status) => HttpResponseType::NodeStatus(response_metadata, status),
+            Err(e) => {
+                warn!("Failed to get node status {:?}: {:?}", req, &e);
+                HttpResponseType::from_net_error(response_metadata, &e)
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a GET for the node's signed identity, signing the client's challenge with it if it
+    /// gave one.  The response will be synchronously written to the fd.
+    fn handle_get_node_identity<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        challenge: Option<&[u8]>,
+        peerdb: &PeerDB,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match PeerDB::get_local_peer(peerdb.conn())
+            .map_err(net_error::DBError)
+            .and_then(|local_peer| RPCNodeIdentity::from_local_peer(&local_peer, challenge))
+        {
+            Ok(identity) => HttpResponseType::NodeIdentity(response_metadata, identity),
+            Err(e) => {
+                warn!("Failed to get node identity {:?}: {:?}", req, &e);
+                HttpResponseType::from_net_error(response_metadata, &e)
+            }
+        };
+        response.send(http, fd)
+    }
+
+    /// Handle a POST to convert a Clarity value to or from its consensus serialization.
+    /// Needs no chain state.
+    fn handle_clarity_value_conversion<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        converted: Result<ClarityValueResponse, String>,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match converted {
+            Ok(data) => HttpResponseType::ClarityValue(response_metadata, data),
+            Err(msg) => HttpResponseType::BadRequest(response_metadata, msg),
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a not-found
+    fn handle_notfound<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
         response_metadata: HttpResponseMetadata,
         msg: String,
     ) -> Result<Option<BlockStreamData>, net_error> {
@@ -850,6 +2320,180 @@ impl ConversationHttp {
         }
     }
 
+    /// Handle a GET block with `decode=true`.  Unlike handle_getblock(), the block is loaded and
+    /// sent back as a single JSON document instead of being streamed.
+    /// The response will be synchronously written to the fd.
+    fn handle_getblock_decoded<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<BlockStreamData>, net_error> {
+        monitoring::increment_stx_blocks_served_counter();
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        match StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash) {
+            Ok(true) => {}
+            Ok(false) => {
+                return ConversationHttp::handle_notfound(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!("No such block {}", index_block_hash.to_hex()),
+                );
+            }
+            Err(e) => {
+                return ConversationHttp::handle_server_error(
+                    http,
+                    fd,
+                    response_metadata,
+                    format!("Failed to query block {:?}: {:?}", req, &e),
+                );
+            }
+        }
+
+        match StacksChainState::load_block_indexed(&chainstate.blocks_path, index_block_hash) {
+            Ok(Some(block)) => {
+                let decoded_block = RPCDecodedBlock::from_block(index_block_hash, &block);
+                let response = HttpResponseType::DecodedBlock(response_metadata, decoded_block);
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+            Ok(None) => ConversationHttp::handle_notfound(
+                http,
+                fd,
+                response_metadata,
+                format!("Block {} is invalid", index_block_hash.to_hex()),
+            ),
+            Err(e) => ConversationHttp::handle_server_error(
+                http,
+                fd,
+                response_metadata,
+                format!("Failed to load block {:?}: {:?}", req, &e),
+            ),
+        }
+    }
+
+    /// Send back the processed microblock stream ending in the given index microblock hash as
+    /// decoded JSON, in sequence order.
+    /// The response will be synchronously written to the fd.
+    fn send_microblocks_decoded<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        response_metadata: HttpResponseMetadata,
+        tail_index_microblock_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<BlockStreamData>, net_error> {
+        let mblock_info_res = StacksChainState::load_staging_microblock_info_indexed(
+            chainstate.db(),
+            tail_index_microblock_hash,
+        )
+        .and_then(|mblock_info_opt| match mblock_info_opt {
+            Some(mblock_info) => StacksChainState::load_processed_microblock_stream_fork(
+                chainstate.db(),
+                &mblock_info.consensus_hash,
+                &mblock_info.anchored_block_hash,
+                &mblock_info.microblock_hash,
+            ),
+            None => Ok(None),
+        });
+
+        match mblock_info_res {
+            Ok(Some(microblocks)) => {
+                let decoded_microblocks = microblocks
+                    .iter()
+                    .map(RPCDecodedMicroblock::from_microblock)
+                    .collect();
+                let response =
+                    HttpResponseType::DecodedMicroblocks(response_metadata, decoded_microblocks);
+                response.send(http, fd).and_then(|_| Ok(None))
+            }
+            Ok(None) => ConversationHttp::handle_notfound(
+                http,
+                fd,
+                response_metadata,
+                format!(
+                    "No such confirmed microblock stream ending with {}",
+                    tail_index_microblock_hash.to_hex()
+                ),
+            ),
+            Err(e) => ConversationHttp::handle_server_error(
+                http,
+                fd,
+                response_metadata,
+                format!(
+                    "Failed to load confirmed microblock stream {:?}: {:?}",
+                    req, &e
+                ),
+            ),
+        }
+    }
+
+    /// Handle a GET confirmed microblock stream with `decode=true`, by last _index microblock
+    /// hash_ in the stream.
+    /// The response will be synchronously written to the fd.
+    fn handle_getmicroblocks_indexed_decoded<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tail_index_microblock_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<BlockStreamData>, net_error> {
+        monitoring::increment_stx_micro_blocks_served_counter();
+        let response_metadata = HttpResponseMetadata::from(req);
+        ConversationHttp::send_microblocks_decoded(
+            http,
+            fd,
+            req,
+            response_metadata,
+            tail_index_microblock_hash,
+            chainstate,
+        )
+    }
+
+    /// Handle a GET confirmed microblock stream with `decode=true`, by _anchor block hash_.
+    /// The response will be synchronously written to the fd.
+    fn handle_getmicroblocks_confirmed_decoded<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        index_anchor_block_hash: &StacksBlockId,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<BlockStreamData>, net_error> {
+        monitoring::increment_stx_confirmed_micro_blocks_served_counter();
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        match chainstate.get_confirmed_microblock_index_hash(index_anchor_block_hash) {
+            Ok(Some(tail_index_microblock_hash)) => ConversationHttp::send_microblocks_decoded(
+                http,
+                fd,
+                req,
+                response_metadata,
+                &tail_index_microblock_hash,
+                chainstate,
+            ),
+            Ok(None) => ConversationHttp::handle_notfound(
+                http,
+                fd,
+                response_metadata,
+                format!(
+                    "No such confirmed microblock stream for anchor block {}",
+                    &index_anchor_block_hash
+                ),
+            ),
+            Err(e) => ConversationHttp::handle_server_error(
+                http,
+                fd,
+                response_metadata,
+                format!(
+                    "Failed to serve confirmed microblock stream {:?}: {:?}",
+                    req, &e
+                ),
+            ),
+        }
+    }
+
     /// Handle a GET confirmed microblock stream, by _anchor block hash_.  Start streaming the reply.
     /// The response's preamble (but not the block data) will be synchronously written to the fd
     /// (so use a fd that can buffer!)
@@ -1119,29 +2763,223 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
 
-    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
-    /// supplies a MARF proof for the value.
-    fn handle_get_map_entry<W: Write>(
+    /// Handle a GET on an account's locked STX and unlock schedule, given the current chain tip.
+    /// The schedule combines the account's genesis vesting entries with its PoX lock.
+    fn handle_get_account_unlocks<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
         sortdb: &SortitionDB,
         chainstate: &mut StacksChainState,
         tip: &StacksBlockId,
-        contract_addr: &StacksAddress,
-        contract_name: &ContractName,
-        map_name: &ClarityName,
-        key: &Value,
-        with_proof: bool,
+        account: &PrincipalData,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
-        let contract_identifier =
-            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
-        let response =
-            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                clarity_tx.with_clarity_db_readonly(|clarity_db| {
-                    let key = ClarityDatabase::make_key_for_data_map_entry(
+        let genesis_lockups = match StacksChainState::get_genesis_lockups(chainstate.db(), account)
+        {
+            Ok(lockups) => lockups,
+            Err(e) => {
+                warn!("Failed to load genesis lockups for {}: {:?}", account, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load genesis lockups".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let key = ClarityDatabase::make_key_for_account_balance(&account);
+                    let stacks_tip_height = clarity_db.get_current_block_height() as u64;
+                    let burn_block_height = clarity_db.get_current_burnchain_block_height() as u64;
+                    let balance = clarity_db
+                        .get::<STXBalance>(&key)
+                        .unwrap_or_else(|| STXBalance::zero());
+
+                    let (locked, unlock_height) =
+                        balance.get_locked_balance_at_burn_block(burn_block_height);
+
+                    let mut vesting_locked = 0u128;
+                    let vesting = genesis_lockups
+                        .iter()
+                        .map(|(block_height, amount)| {
+                            // lockups at height h are credited when the block at height h is
+                            // processed
+                            let unlocked = *block_height <= stacks_tip_height;
+                            if !unlocked {
+                                vesting_locked = vesting_locked.saturating_add(*amount);
+                            }
+                            VestingUnlockEntry {
+                                block_height: *block_height,
+                                amount: format!("0x{}", to_hex(&amount.to_be_bytes())),
+                                unlocked,
+                            }
+                        })
+                        .collect();
+
+                    AccountUnlocksResponse {
+                        locked: format!("0x{}", to_hex(&locked.to_be_bytes())),
+                        unlock_height,
+                        vesting_locked: format!("0x{}", to_hex(&vesting_locked.to_be_bytes())),
+                        stacks_tip_height,
+                        burn_block_height,
+                        vesting,
+                    }
+                })
+            }) {
+                Ok(Some(data)) => HttpResponseType::GetAccountUnlocks(response_metadata, data),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                }
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET on the block rewards an address has earned in the fork ending at the given
+    /// chain tip, along with the coinbase reward schedule.
+    fn handle_get_miner_rewards<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        address: &StacksAddress,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+
+        let stacks_tip_height =
+            match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+                chainstate.db(),
+                tip,
+            ) {
+                Ok(Some(header)) => header.block_height,
+                Ok(None) | Err(_) => {
+                    let response =
+                        HttpResponseType::NotFound(response_metadata, "Chain tip not found".into());
+                    return response.send(http, fd).map(|_| ());
+                }
+            };
+
+        let payments = match chainstate.index_conn().and_then(|conn| {
+            StacksChainState::get_scheduled_block_rewards_for_address(&conn, tip, address)
+        }) {
+            Ok(payments) => payments,
+            Err(e) => {
+                warn!("Failed to load miner rewards for {}: {:?}", address, &e);
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load miner rewards".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let to_hex_amount = |amount: u128| format!("0x{}", to_hex(&amount.to_be_bytes()));
+
+        let mut matured = 0u128;
+        let mut pending = 0u128;
+        let rewards = payments
+            .iter()
+            .map(|payment| {
+                // only the miner gets a share of the microblock stream it confirmed
+                let tx_fees_streamed_confirmed = if payment.miner {
+                    payment.streamed_tx_fees_confirmed()
+                } else {
+                    0
+                };
+                let total = payment
+                    .coinbase
+                    .saturating_add(payment.tx_fees_anchored)
+                    .saturating_add(tx_fees_streamed_confirmed);
+
+                // the reward for the block at height h is paid by the block at height
+                // h + MINER_REWARD_MATURITY + 1
+                let maturity_height = payment.stacks_block_height + MINER_REWARD_MATURITY + 1;
+                let is_matured = maturity_height <= stacks_tip_height;
+                if is_matured {
+                    matured = matured.saturating_add(total);
+                } else {
+                    pending = pending.saturating_add(total);
+                }
+
+                MinerRewardEntry {
+                    stacks_block_height: payment.stacks_block_height,
+                    index_block_hash: StacksBlockHeader::make_index_block_hash(
+                        &payment.consensus_hash,
+                        &payment.block_hash,
+                    ),
+                    miner: payment.miner,
+                    coinbase: to_hex_amount(payment.coinbase),
+                    tx_fees_anchored: to_hex_amount(payment.tx_fees_anchored),
+                    tx_fees_streamed_confirmed: to_hex_amount(tx_fees_streamed_confirmed),
+                    maturity_height,
+                    matured: is_matured,
+                }
+            })
+            .collect();
+
+        let reward_schedule =
+            StacksChainState::get_coinbase_reward_schedule(sortdb.first_block_height)
+                .into_iter()
+                .map(
+                    |(start_burn_height, end_burn_height, coinbase)| CoinbaseScheduleEntry {
+                        start_burn_height,
+                        end_burn_height,
+                        coinbase: to_hex_amount(coinbase),
+                    },
+                )
+                .collect();
+
+        let data = MinerRewardsResponse {
+            stacks_tip_height,
+            maturity: MINER_REWARD_MATURITY,
+            matured: to_hex_amount(matured),
+            pending: to_hex_amount(pending),
+            rewards,
+            reward_schedule,
+        };
+        let response = HttpResponseType::GetMinerRewards(response_metadata, data);
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET on a smart contract's data map, given the current chain tip.  Optionally
+    /// supplies a MARF proof for the value.
+    fn handle_get_map_entry<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        map_name: &ClarityName,
+        key: &Value,
+        with_proof: bool,
+        rpc_cache: &mut RPCResponseCache,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        // the key is sent in the request body, so it is not part of the path
+        let route = format!("{}#{}", req.request_path(), key.serialize());
+        let cacheable = RPCResponseCache::is_cacheable_tip(chainstate, tip);
+        if cacheable {
+            if let Some(response) = rpc_cache.get(&route, tip, response_metadata.clone()) {
+                return response.send(http, fd).map(|_| ());
+            }
+        }
+
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|clarity_db| {
+                    let key = ClarityDatabase::make_key_for_data_map_entry(
                         &contract_identifier,
                         map_name,
                         key,
@@ -1174,6 +3012,9 @@ impl ConversationHttp {
                 }
             };
 
+        if cacheable {
+            rpc_cache.insert(route, tip, &response);
+        }
         response.send(http, fd).map(|_| ())
     }
 
@@ -1191,6 +3032,7 @@ impl ConversationHttp {
         function: &ClarityName,
         sender: &PrincipalData,
         args: &[Value],
+        deadline: Option<Instant>,
         options: &ConnectionOptions,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
@@ -1208,13 +3050,16 @@ impl ConversationHttp {
 
         let data_opt_res =
             chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
-                let cost_track = clarity_tx
+                let mut cost_track = clarity_tx
                     .with_clarity_db_readonly(|clarity_db| {
                         LimitedCostTracker::new_mid_block(mainnet, cost_limit, clarity_db)
                     })
                     .map_err(|_| {
                         ClarityRuntimeError::from(InterpreterError::CostContractLoadFailure)
                     })?;
+                if let Some(deadline) = deadline {
+                    cost_track.set_deadline(deadline);
+                }
 
                 clarity_tx.with_readonly_clarity_env(mainnet, sender.clone(), cost_track, |env| {
                     // we want to execute any function as long as no actual writes are made as
@@ -1237,6 +3082,12 @@ impl ConversationHttp {
                 },
             ),
             Ok(Some(Err(e))) => match e {
+                Unchecked(CheckErrors::ExecutionTimeExpired) => {
+                    HttpResponseType::ServiceUnavailable(
+                        response_metadata,
+                        "Request deadline exceeded".to_string(),
+                    )
+                }
                 Unchecked(CheckErrors::CostBalanceExceeded(actual_cost, _))
                     if actual_cost.write_count > 0 =>
                 {
@@ -1367,6 +3218,338 @@ impl ConversationHttp {
         response.send(http, fd).map(|_| ())
     }
 
+    /// Check a contract call's arguments against the called function's signature in the
+    /// contract's stored analysis, the same way the VM does when the call is made (see
+    /// `DefinedFunction::execute_apply`).  Contracts passed for trait arguments must exist and
+    /// implement the trait; `load_analysis` is used to look them up.
+    pub fn validate_contract_call<F>(
+        analysis: &ContractAnalysis,
+        function_name: &ClarityName,
+        args: &[String],
+        mut load_analysis: F,
+    ) -> ValidateContractCallResponse
+    where
+        F: FnMut(&QualifiedContractIdentifier) -> Option<ContractAnalysis>,
+    {
+        let call_error = |message: String| ContractCallArgumentError {
+            index: None,
+            name: None,
+            expected_type: None,
+            message,
+        };
+        let mut errors = vec![];
+        let mut arguments = vec![];
+
+        let function_type = analysis
+            .public_function_types
+            .get(function_name)
+            .or_else(|| analysis.read_only_function_types.get(function_name));
+        let function_args = match function_type {
+            Some(FunctionType::Fixed(function)) => &function.args[..],
+            Some(_) => {
+                errors.push(call_error(format!(
+                    "Function '{}' does not take a fixed argument list",
+                    function_name
+                )));
+                &[]
+            }
+            None if analysis.private_function_types.contains_key(function_name) => {
+                errors.push(call_error(format!(
+                    "Function '{}' is private",
+                    function_name
+                )));
+                &[]
+            }
+            None => {
+                errors.push(call_error(format!(
+                    "No such public or read-only function '{}'",
+                    function_name
+                )));
+                &[]
+            }
+        };
+
+        if function_type.is_some() && function_args.len() != args.len() {
+            errors.push(call_error(format!(
+                "Expected {} arguments, got {}",
+                function_args.len(),
+                args.len()
+            )));
+        }
+
+        for (index, (function_arg, arg)) in function_args.iter().zip(args.iter()).enumerate() {
+            let arg_error = |message: String| ContractCallArgumentError {
+                index: Some(index as u32),
+                name: Some(function_arg.name.to_string()),
+                expected_type: Some(function_arg.signature.to_string()),
+                message,
+            };
+
+            let data = match ClarityValueResponse::from_hex(arg) {
+                Ok(data) => data,
+                Err(msg) => {
+                    errors.push(arg_error(msg));
+                    continue;
+                }
+            };
+
+            let problem = match (&function_arg.signature, &data.value) {
+                (
+                    TypeSignature::TraitReferenceType(trait_id),
+                    Value::Principal(PrincipalData::Contract(callee)),
+                ) => ConversationHttp::check_trait_argument(callee, trait_id, &mut load_analysis),
+                (TypeSignature::TraitReferenceType(trait_id), _) => Some(format!(
+                    "Expected a contract implementing trait '{}', got {}",
+                    trait_id, data.repr
+                )),
+                (signature, value) if !signature.admits(value) => {
+                    Some(format!("Expected {}, got {}", signature, data.repr))
+                }
+                _ => None,
+            };
+            match problem {
+                Some(msg) => errors.push(arg_error(msg)),
+                None => arguments.push(format!("0x{}", data.hex)),
+            }
+        }
+
+        let valid = errors.is_empty();
+        ValidateContractCallResponse {
+            valid,
+            contract_id: analysis.contract_identifier.to_string(),
+            function_name: function_name.to_string(),
+            arguments: if valid { arguments } else { vec![] },
+            errors,
+        }
+    }
+
+    /// Why a contract can't be passed for a trait argument, if it can't
+    fn check_trait_argument<F>(
+        callee: &QualifiedContractIdentifier,
+        trait_id: &TraitIdentifier,
+        load_analysis: &mut F,
+    ) -> Option<String>
+    where
+        F: FnMut(&QualifiedContractIdentifier) -> Option<ContractAnalysis>,
+    {
+        let callee_analysis = match load_analysis(callee) {
+            Some(analysis) => analysis,
+            None => return Some(format!("No such contract '{}'", callee)),
+        };
+        if callee_analysis.implemented_traits.contains(trait_id) {
+            return None;
+        }
+        let trait_definition = load_analysis(&trait_id.contract_identifier)
+            .and_then(|analysis| analysis.get_defined_trait(&trait_id.name).cloned());
+        match trait_definition {
+            Some(trait_definition) => callee_analysis
+                .check_trait_compliance(trait_id, &trait_definition)
+                .err()
+                .map(|_| {
+                    format!(
+                        "Contract '{}' does not implement trait '{}'",
+                        callee, trait_id
+                    )
+                }),
+            None => Some(format!("No such trait '{}'", trait_id)),
+        }
+    }
+
+    /// Handle a POST to validate a contract call's arguments, given the chain tip
+    fn handle_validate_contract_call<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        function_name: &ClarityName,
+        args: &[String],
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                clarity_tx.with_clarity_db_readonly(|db| {
+                    let analysis = db.load_contract_analysis(&contract_identifier)?;
+                    Some(ConversationHttp::validate_contract_call(
+                        &analysis,
+                        function_name,
+                        args,
+                        |contract_id| db.load_contract_analysis(contract_id),
+                    ))
+                })
+            }) {
+                Ok(Some(Some(data))) => {
+                    HttpResponseType::ValidateContractCall(response_metadata, data)
+                }
+                Ok(Some(None)) => HttpResponseType::NotFound(
+                    response_metadata,
+                    "No contract analysis found".into(),
+                ),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                }
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Canonicalize contract source text before hashing or comparing it: line endings are
+    /// normalized to `\n`, and trailing whitespace is stripped from each line and from the end of
+    /// the text.  None of these transformations change the contract's AST.
+    pub fn canonicalize_contract_src(source: &str) -> String {
+        source
+            .lines()
+            .map(|line| line.trim_end())
+            .collect::<Vec<&str>>()
+            .join("\n")
+            .trim_end()
+            .to_string()
+    }
+
+    /// Are two contract ASTs the same, modulo expression IDs and source spans?
+    fn contract_asts_equivalent(a: &[SymbolicExpression], b: &[SymbolicExpression]) -> bool {
+        a.len() == b.len()
+            && a.iter()
+                .zip(b.iter())
+                .all(|(x, y)| match (&x.expr, &y.expr) {
+                    (
+                        SymbolicExpressionType::List(ref xs),
+                        SymbolicExpressionType::List(ref ys),
+                    ) => ConversationHttp::contract_asts_equivalent(xs, ys),
+                    (x_expr, y_expr) => x_expr == y_expr,
+                })
+    }
+
+    /// Handle a POST to verify that the given source code is what was deployed for a smart
+    /// contract as of the given chain tip.  The source is compared to the deployed source both
+    /// byte-wise (after canonicalization) and AST-wise, and is run through static analysis
+    /// against the chain state at the tip.  Parsing and analysis are bounded by the read and
+    /// runtime parts of the read-only call cost limit.
+    fn handle_verify_contract_src<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_addr: &StacksAddress,
+        contract_name: &ContractName,
+        source: &str,
+        deadline: Option<Instant>,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let contract_identifier =
+            QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
+        let mainnet = chainstate.mainnet;
+        // analysis charges for storing the contract's type information, but nothing is stored
+        // here, so only reads and runtime are bounded
+        let mut cost_limit = options.read_only_call_limit.clone();
+        cost_limit.write_length = u64::max_value();
+        cost_limit.write_count = u64::max_value();
+
+        let response =
+            match chainstate.maybe_read_only_clarity_tx(&sortdb.index_conn(), tip, |clarity_tx| {
+                let (deployed_source, publish_height, mut cost_track) = clarity_tx
+                    .with_clarity_db_readonly(|db| {
+                        let deployed_source = db.get_contract_src(&contract_identifier)?;
+                        let contract_commitment = db.get::<ContractCommitment>(
+                            &make_contract_hash_key(&contract_identifier),
+                        )?;
+                        let mut cost_track =
+                            LimitedCostTracker::new_mid_block(mainnet, cost_limit, db).ok()?;
+                        if let Some(deadline) = deadline {
+                            cost_track.set_deadline(deadline);
+                        }
+                        Some((
+                            deployed_source,
+                            contract_commitment.block_height,
+                            cost_track,
+                        ))
+                    })?;
+
+                let canonical_source = ConversationHttp::canonicalize_contract_src(source);
+                let canonical_deployed_source =
+                    ConversationHttp::canonicalize_contract_src(&deployed_source);
+
+                let (ast_match, analysis_ok, cause) = match ast::build_ast(
+                    &contract_identifier,
+                    &canonical_source,
+                    &mut cost_track,
+                ) {
+                    Ok(mut contract_ast) => {
+                        let ast_match = ast::build_ast(
+                            &contract_identifier,
+                            &deployed_source,
+                            &mut LimitedCostTracker::new_free(),
+                        )
+                        .map(|deployed_ast| {
+                            ConversationHttp::contract_asts_equivalent(
+                                &contract_ast.expressions,
+                                &deployed_ast.expressions,
+                            )
+                        })
+                        .unwrap_or(false);
+
+                        let analysis_res = clarity_tx.with_analysis_db_readonly(|db| {
+                            analysis::run_analysis(
+                                &contract_identifier,
+                                &mut contract_ast.expressions,
+                                db,
+                                false,
+                                cost_track,
+                                mainnet,
+                            )
+                        });
+                        match analysis_res {
+                            Ok(_) => (ast_match, true, None),
+                            Err((e, _)) => (ast_match, false, Some(e.to_string())),
+                        }
+                    }
+                    Err(e) => (false, false, Some(e.to_string())),
+                };
+
+                Some(VerifyContractSrcResponse {
+                    contract_id: contract_identifier.to_string(),
+                    tip: tip.clone(),
+                    publish_height,
+                    source_hash: Sha512Trunc256Sum::from_data(canonical_source.as_bytes()),
+                    deployed_source_hash: Sha512Trunc256Sum::from_data(
+                        canonical_deployed_source.as_bytes(),
+                    ),
+                    source_match: canonical_source == canonical_deployed_source,
+                    ast_match,
+                    analysis_ok,
+                    cause,
+                })
+            }) {
+                Ok(Some(Some(_))) if ConversationHttp::deadline_passed(deadline) => {
+                    HttpResponseType::ServiceUnavailable(
+                        response_metadata,
+                        "Request deadline exceeded".to_string(),
+                    )
+                }
+                Ok(Some(Some(data))) => {
+                    HttpResponseType::VerifyContractSrc(response_metadata, data)
+                }
+                Ok(Some(None)) => HttpResponseType::NotFound(
+                    response_metadata,
+                    "No contract source data found".into(),
+                ),
+                Ok(None) | Err(_) => {
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into())
+                }
+            };
+
+        response.send(http, fd).map(|_| ())
+    }
+
     /// Handle a GET to fetch a contract's analysis data, given the chain tip.  Note that this isn't
     /// something that's anchored to the blockchain, and can be different across different versions
     /// of Stacks -- callers must trust the Stacks node to return correct analysis data.
@@ -1381,8 +3564,17 @@ impl ConversationHttp {
         tip: &StacksBlockId,
         contract_addr: &StacksAddress,
         contract_name: &ContractName,
+        rpc_cache: &mut RPCResponseCache,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
+        let route = req.request_path();
+        let cacheable = RPCResponseCache::is_cacheable_tip(chainstate, tip);
+        if cacheable {
+            if let Some(response) = rpc_cache.get(&route, tip, response_metadata.clone()) {
+                return response.send(http, fd).map(|_| ());
+            }
+        }
+
         let contract_identifier =
             QualifiedContractIdentifier::new(contract_addr.clone().into(), contract_name.clone());
 
@@ -1403,6 +3595,9 @@ impl ConversationHttp {
                 }
             };
 
+        if cacheable {
+            rpc_cache.insert(route, tip, &response);
+        }
         response.send(http, fd).map(|_| ())
     }
 
@@ -1494,17 +3689,59 @@ impl ConversationHttp {
         }
     }
 
-    /// Handle a GET unconfirmed transaction.
+    /// Find every processed block that includes a transaction, across all forks, and whether each
+    /// one is on the canonical Stacks fork.
+    fn get_transaction_forks(
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        txid: &Txid,
+    ) -> Result<Vec<RPCTransactionFork>, net_error> {
+        // load the blocks in the same read transaction as the canonical tip, so a block processed
+        // in the meantime can't show up as non-canonical
+        let (snapshot, headers) = RPCReadSnapshot::read(sortdb, chainstate, |_| {
+            Ok(StacksChainState::get_transaction_blocks(
+                chainstate.db(),
+                txid,
+            )?)
+        })?;
+        if headers.len() == 0 {
+            return Ok(vec![]);
+        }
+
+        let canonical_tip = snapshot.stacks_tip_index_hash();
+        let index_conn = chainstate.index_conn()?;
+
+        let mut forks = vec![];
+        for header in headers.into_iter() {
+            let index_block_hash = header.index_block_hash();
+            let canonical = index_conn
+                .get_ancestor_block_height(&index_block_hash, &canonical_tip)?
+                .is_some();
+            forks.push(RPCTransactionFork {
+                index_block_hash,
+                consensus_hash: header.consensus_hash,
+                block_hash: header.anchored_header.block_hash(),
+                block_height: header.block_height,
+                canonical,
+            });
+        }
+        Ok(forks)
+    }
+
+    /// Handle a GET unconfirmed transaction.  Also reports every processed block that includes the
+    /// transaction, so clients can tell which forks it landed in.
     /// The response will be synchronously written to the fd.
     fn handle_gettransaction_unconfirmed<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
         req: &HttpRequestType,
+        sortdb: &SortitionDB,
         chainstate: &StacksChainState,
         mempool: &MemPoolDB,
         txid: &Txid,
     ) -> Result<(), net_error> {
         let response_metadata = HttpResponseMetadata::from(req);
+        let forks = ConversationHttp::get_transaction_forks(sortdb, chainstate, txid)?;
 
         // present in the unconfirmed state?
         if let Some(ref unconfirmed) = chainstate.unconfirmed_state.as_ref() {
@@ -1519,6 +3756,7 @@ impl ConversationHttp {
                             seq: seq,
                         },
                         tx: to_hex(&transaction.serialize_to_vec()),
+                        forks,
                     },
                 );
                 return response.send(http, fd).map(|_| ());
@@ -1532,26 +3770,1050 @@ impl ConversationHttp {
                 UnconfirmedTransactionResponse {
                     status: UnconfirmedTransactionStatus::Mempool,
                     tx: to_hex(&txinfo.tx.serialize_to_vec()),
+                    forks,
                 },
             );
             return response.send(http, fd).map(|_| ());
         }
 
-        // not found
-        let response = HttpResponseType::NotFound(
-            response_metadata,
+        // only present in processed blocks?
+        if let Some(fork) = forks.first() {
+            if let Some(transaction) =
+                chainstate.load_processed_transaction(&fork.index_block_hash, txid)?
+            {
+                let response = HttpResponseType::UnconfirmedTransaction(
+                    response_metadata,
+                    UnconfirmedTransactionResponse {
+                        status: UnconfirmedTransactionStatus::Confirmed,
+                        tx: to_hex(&transaction.serialize_to_vec()),
+                        forks,
+                    },
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        }
+
+        // not found
+        let response = HttpResponseType::NotFound(
+            response_metadata,
             format!("No such unconfirmed transaction {}", txid),
         );
         return response.send(http, fd).map(|_| ());
     }
 
+    /// Handle a GET for the mempool drop log entries of a transaction.
+    /// The response will be synchronously written to the fd.
+    fn handle_getmempool_drops<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        mempool: &MemPoolDB,
+        txid: &Txid,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let records = MemPoolDB::get_drop_records(mempool.conn(), txid)?;
+        let response = if records.len() > 0 {
+            HttpResponseType::MemPoolDrops(
+                response_metadata,
+                records
+                    .iter()
+                    .map(|record| RPCMemPoolDropData::from_record(record))
+                    .collect(),
+            )
+        } else {
+            HttpResponseType::NotFound(
+                response_metadata,
+                format!("No record of dropping transaction {}", txid),
+            )
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the proof that a transaction was confirmed from a microblock stream.
+    /// Prefers a confirming block on the canonical Stacks fork, if there is one.
+    /// The response will be synchronously written to the fd.
+    fn handle_getmicroblock_proof<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        txid: &Txid,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let mut forks = ConversationHttp::get_transaction_forks(sortdb, chainstate, txid)?;
+        forks.sort_by_key(|fork| !fork.canonical);
+
+        for fork in forks.iter() {
+            if let Some(proof) = RPCMicroblockProof::from_chainstate(chainstate, txid, fork)? {
+                let response = HttpResponseType::MicroblockProof(response_metadata, proof);
+                return response.send(http, fd).map(|_| ());
+            }
+        }
+
+        let response = HttpResponseType::NotFound(
+            response_metadata,
+            format!(
+                "Transaction {} was not confirmed from a microblock stream",
+                txid
+            ),
+        );
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Decode the cursor of a page of an address's pending transactions: the address's nonce in
+    /// the previous page's last transaction, and that transaction's txid.
+    fn parse_address_mempool_cursor(cursor: &str) -> Option<(u64, Txid)> {
+        let mut parts = cursor.splitn(2, ':');
+        let nonce = parts.next()?.parse::<u64>().ok()?;
+        let txid = Txid::from_hex(parts.next()?).ok()?;
+        Some((nonce, txid))
+    }
+
+    /// Handle a GET for a page of the pending transactions that an address originates or
+    /// sponsors, in the order the address's nonces would be consumed.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_address_mempool<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        address: &StacksAddress,
+        page: &RPCPageRequest,
+        mempool: &MemPoolDB,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let after = match page.cursor {
+            Some(ref cursor) => match ConversationHttp::parse_address_mempool_cursor(cursor) {
+                Some(after) => Some(after),
+                None => {
+                    let response = HttpResponseType::BadRequest(
+                        response_metadata,
+                        format!("Invalid cursor '{}'", cursor),
+                    );
+                    return response.send(http, fd).map(|_| ());
+                }
+            },
+            None => None,
+        };
+
+        let page_size = page.page_size(options.max_rpc_page_size);
+        let txs = MemPoolDB::get_address_txs_page(
+            mempool.conn(),
+            address,
+            after.as_ref(),
+            page_size.saturating_add(1),
+        )?;
+        let address_page = RPCPage::from_items(txs, page_size, |tx| {
+            let nonce = if &tx.origin_address == address {
+                tx.origin_nonce
+            } else {
+                tx.sponsor_nonce
+            };
+            format!("{}:{}", nonce, &tx.txid)
+        });
+        let mut conflicts = VecDeque::with_capacity(address_page.items.len());
+        for tx in address_page.items.iter() {
+            conflicts.push_back(MemPoolDB::get_conflict_records(mempool.conn(), tx)?);
+        }
+        let address_page = address_page.map(|tx| {
+            RPCPendingTransaction::from_metadata(&tx, &conflicts.pop_front().unwrap_or(vec![]))
+        });
+
+        HttpResponseType::AddressMempool(response_metadata, address_page)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Handle a GET for the microblock streams seen for an anchored block -- by default, the
+    /// canonical Stacks chain tip.
+    /// The response will be synchronously written to the fd.
+    fn handle_getmicroblocks_forks<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        index_anchor_block_hash_opt: &Option<StacksBlockId>,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let index_anchor_block_hash = match index_anchor_block_hash_opt {
+            Some(index_block_hash) => index_block_hash.clone(),
+            None => {
+                let (consensus_hash, block_hash) =
+                    SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+                StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash)
+            }
+        };
+
+        let response = match StacksChainState::get_microblock_stream_forks(
+            chainstate.db(),
+            &index_anchor_block_hash,
+        ) {
+            Ok(forks) => HttpResponseType::MicroblockForks(
+                response_metadata,
+                RPCMicroblockForksInfo {
+                    index_block_hash: index_anchor_block_hash,
+                    forked: forks.len() > 1,
+                    streams: forks.iter().map(RPCMicroblockStream::from_fork).collect(),
+                },
+            ),
+            Err(e) => {
+                warn!("Failed to load microblock streams {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load microblock streams".to_string(),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for fee market statistics.
+    /// The response will be synchronously written to the fd.
+    fn handle_getmempool_fees<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        mempool: &MemPoolDB,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match RPCMemPoolFeeData::from_db(sortdb, chainstate, mempool) {
+            Ok(data) => HttpResponseType::MemPoolFees(response_metadata, data),
+            Err(e) => {
+                warn!(
+                    "Failed to compute mempool fee statistics {:?}: {:?}",
+                    req, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to compute mempool fee statistics".to_string(),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the block the miner would assemble from the mempool right now.  Block
+    /// assembly is run for real, but the result is thrown away.
+    /// The response will be synchronously written to the fd.
+    fn handle_simulate_block<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        mempool: &mut MemPoolDB,
+        deadline: Option<Instant>,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let snapshot = RPCReadSnapshot::new(sortdb, chainstate)?;
+        let tip_header = match snapshot.stacks_tip {
+            Some(tip_header) => tip_header,
+            None => {
+                let response = HttpResponseType::NotFound(
+                    response_metadata,
+                    "No Stacks chain tip to build on".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+        let total_burn = snapshot.burnchain_tip.total_burn;
+
+        let simulation = StacksBlockBuilder::simulate_anchored_block(
+            chainstate,
+            &sortdb.index_conn(),
+            mempool,
+            &tip_header,
+            total_burn,
+            chainstate.block_limit.clone(),
+            &mut || ConversationHttp::deadline_passed(deadline),
+        );
+        let response = match simulation {
+            Ok(simulation) => HttpResponseType::BlockSimulation(
+                response_metadata,
+                RPCBlockSimulationData {
+                    stacks_tip_height: tip_header.block_height,
+                    stacks_tip: tip_header.index_block_hash(),
+                    num_txs: simulation.txs.len() as u64,
+                    total_bytes: simulation.size,
+                    total_fees: simulation.total_fees,
+                    budget_pct: chainstate
+                        .block_limit
+                        .proportion_largest_dimension(&simulation.cost),
+                    cost: simulation.cost,
+                    transactions: simulation
+                        .txs
+                        .iter()
+                        .map(|tx| RPCSimulatedTransaction {
+                            txid: tx.txid().to_hex(),
+                            origin_address: tx.origin_address().to_string(),
+                            origin_nonce: tx.get_origin_nonce(),
+                            tx_fee: tx.get_tx_fee(),
+                        })
+                        .collect(),
+                },
+            ),
+            Err(chain_error::BlockAssemblyPreempted) => HttpResponseType::ServiceUnavailable(
+                response_metadata,
+                "Request deadline exceeded".to_string(),
+            ),
+            Err(e) => {
+                warn!("Failed to simulate block assembly {:?}: {:?}", req, &e);
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to simulate block assembly".to_string(),
+                )
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the execution cost spent per contract over a range of blocks.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_contract_costs<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        from_height: Option<u64>,
+        to_height: Option<u64>,
+        limit: Option<u32>,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !chainstate.contract_cost_stats {
+            let response = HttpResponseType::ContractCosts(
+                response_metadata,
+                RPCContractCostsData {
+                    enabled: false,
+                    from_height: from_height.unwrap_or(0),
+                    to_height: to_height.unwrap_or(0),
+                    contracts: vec![],
+                },
+            );
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let tip_height =
+            SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?.canonical_stacks_tip_height;
+        let to_height = to_height.unwrap_or(tip_height);
+        let from_height = from_height.unwrap_or(0);
+        if from_height > to_height {
+            let response = HttpResponseType::BadRequest(
+                response_metadata,
+                "from_height must not be greater than to_height".to_string(),
+            );
+            return response.send(http, fd).map(|_| ());
+        }
+
+        // statistics are kept per bucket of blocks, so report the range they actually cover
+        let from_height = contract_cost_stats_bucket(from_height);
+        let to_height = cmp::min(
+            contract_cost_stats_bucket(to_height) + CONTRACT_COST_STATS_BUCKET_SIZE - 1,
+            cmp::max(tip_height, from_height),
+        );
+
+        let stats = match StacksChainState::get_contract_cost_stats(
+            chainstate.db(),
+            from_height,
+            to_height,
+        ) {
+            Ok(stats) => stats,
+            Err(e) => {
+                warn!(
+                    "Failed to load contract cost statistics {:?}: {:?}",
+                    req, &e
+                );
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    "Failed to load contract cost statistics".to_string(),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let mut budget = chainstate.block_limit.clone();
+        if budget.multiply(to_height - from_height + 1).is_err() {
+            budget = ExecutionCost::max_value();
+        }
+
+        let mut contracts: Vec<RPCContractCostEntry> = stats
+            .into_iter()
+            .map(|stats| RPCContractCostEntry {
+                budget_pct: budget.proportion_largest_dimension(&stats.cost),
+                contract_id: stats.contract_id,
+                num_txs: stats.num_txs,
+                cost: stats.cost,
+            })
+            .collect();
+        contracts.sort_by(|a, b| {
+            b.budget_pct
+                .cmp(&a.budget_pct)
+                .then_with(|| b.cost.runtime.cmp(&a.cost.runtime))
+                .then_with(|| a.contract_id.cmp(&b.contract_id))
+        });
+        let limit = cmp::min(
+            limit.unwrap_or(options.max_rpc_page_size),
+            options.max_rpc_page_size,
+        );
+        contracts.truncate(limit as usize);
+
+        let response = HttpResponseType::ContractCosts(
+            response_metadata,
+            RPCContractCostsData {
+                enabled: true,
+                from_height,
+                to_height,
+                contracts,
+            },
+        );
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the delivery status of the node's event observers.  Only served if the
+    /// admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_event_observer_status<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        status_source: Option<&dyn EventObserverStatusSource>,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            let status = status_source
+                .map(|source| source.get_event_observer_status())
+                .unwrap_or(vec![]);
+            HttpResponseType::EventObserverStatus(response_metadata, status)
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the replays of past block events to event observers.  Only served if the
+    /// admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_event_replays<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        replay_source: Option<&dyn EventReplaySource>,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            let replays = replay_source
+                .map(|source| source.get_event_replays())
+                .unwrap_or(vec![]);
+            HttpResponseType::EventReplays(response_metadata, replays)
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST that starts replaying past block events to one of the node's event
+    /// observers.  The replay runs in the background; all replays are returned.  Only served if
+    /// the admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_event_replay<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        replay_source: Option<&dyn EventReplaySource>,
+        request: &EventReplayRequestBody,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            match replay_source {
+                Some(source) => match source.start_event_replay(request) {
+                    Ok(()) => HttpResponseType::EventReplays(
+                        response_metadata,
+                        source.get_event_replays(),
+                    ),
+                    Err(msg) => HttpResponseType::BadRequest(response_metadata, msg),
+                },
+                None => HttpResponseType::BadRequest(
+                    response_metadata,
+                    "Event replay is not supported by this node".to_string(),
+                ),
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the PeerDB's allow and deny lists.  Only served if the admin RPC is
+    /// enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_peer_filters<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        peerdb: &PeerDB,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            let filters = RPCPeerFilters::from_db(peerdb.conn())?;
+            HttpResponseType::PeerFilters(response_metadata, filters)
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST that adds a rule to, or removes a rule from, one of the PeerDB's allow or
+    /// deny lists.  The change is applied to the known peers right away, and the updated lists
+    /// are returned.  Only served if the admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_peer_filter<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        peerdb: &mut PeerDB,
+        action: PeerFilterAction,
+        list: PeerFilterList,
+        rule: &PeerFilterRule,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let mut tx = peerdb.tx_begin()?;
+        match rule {
+            PeerFilterRule::Cidr(prefix, mask) => match (action, list) {
+                (PeerFilterAction::Add, PeerFilterList::Allow) => {
+                    PeerDB::add_allow_cidr(&mut tx, prefix, *mask)?
+                }
+                (PeerFilterAction::Add, PeerFilterList::Deny) => {
+                    PeerDB::add_deny_cidr(&mut tx, prefix, *mask)?
+                }
+                (PeerFilterAction::Remove, PeerFilterList::Allow) => {
+                    PeerDB::remove_allow_cidr(&mut tx, prefix, *mask)?
+                }
+                (PeerFilterAction::Remove, PeerFilterList::Deny) => {
+                    PeerDB::remove_deny_cidr(&mut tx, prefix, *mask)?
+                }
+            },
+            PeerFilterRule::PubkeyHash(pubkey_hash) => match (action, list) {
+                (PeerFilterAction::Add, PeerFilterList::Allow) => {
+                    PeerDB::add_allow_pubkey_hash(&mut tx, pubkey_hash)?
+                }
+                (PeerFilterAction::Add, PeerFilterList::Deny) => {
+                    PeerDB::add_deny_pubkey_hash(&mut tx, pubkey_hash)?
+                }
+                (PeerFilterAction::Remove, PeerFilterList::Allow) => {
+                    PeerDB::remove_allow_pubkey_hash(&mut tx, pubkey_hash)?
+                }
+                (PeerFilterAction::Remove, PeerFilterList::Deny) => {
+                    PeerDB::remove_deny_pubkey_hash(&mut tx, pubkey_hash)?
+                }
+            },
+        }
+        tx.commit().map_err(db_error::SqliteError)?;
+
+        debug!(
+            "Applied peer filter update: {:?} {:?} {}",
+            action, list, rule
+        );
+        let filters = RPCPeerFilters::from_db(peerdb.conn())?;
+        HttpResponseType::PeerFilters(response_metadata, filters)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Handle a GET for the contracts whose attachments are tracked.  Only served if the admin
+    /// RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_atlas_contracts<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &AtlasDB,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            let contracts = RPCAtlasContracts::from_config(&atlasdb.atlas_config);
+            HttpResponseType::AtlasContracts(response_metadata, contracts)
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST that starts tracking a contract's attachments.  Limits that are not given
+    /// default to the node's `[atlas]` defaults.  The watch list is shared with the chains
+    /// coordinator, so attachment instances in blocks processed from now on are picked up.  The
+    /// updated watch list is returned.  Only served if the admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_atlas_contract<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &AtlasDB,
+        contract_id: &QualifiedContractIdentifier,
+        attachments_max_size: Option<u32>,
+        unresolved_attachment_instances_expire_after: Option<u32>,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let atlas_config = &atlasdb.atlas_config;
+        let mut domain = atlas_config.get_domain(contract_id);
+        if let Some(attachments_max_size) = attachments_max_size {
+            domain.attachments_max_size = attachments_max_size;
+        }
+        if let Some(expire_after) = unresolved_attachment_instances_expire_after {
+            domain.unresolved_attachment_instances_expire_after = expire_after;
+        }
+        let response = match atlas_config.add_contract(contract_id.clone(), domain) {
+            Ok(added) => {
+                if added {
+                    info!("Atlas: now tracking attachments of {}", contract_id);
+                }
+                let contracts = RPCAtlasContracts::from_config(atlas_config);
+                HttpResponseType::AtlasContracts(response_metadata, contracts)
+            }
+            Err(msg) => HttpResponseType::BadRequest(response_metadata, msg),
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a GET for the attachments that are exempt from pruning.  Only served if the admin
+    /// RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_attachment_pins<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &AtlasDB,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let pins = RPCAttachmentPins::from_db(atlasdb)?;
+        HttpResponseType::AttachmentPins(response_metadata, pins)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Handle a POST that pins or unpins an attachment.  A pinned attachment is never pruned, and
+    /// may be pinned before it is downloaded.  The updated pins are returned.  Only served if the
+    /// admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_attachment_pin<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        atlasdb: &mut AtlasDB,
+        action: AttachmentPinAction,
+        content_hash: &Hash160,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        match action {
+            AttachmentPinAction::Pin => atlasdb.pin_attachment(content_hash)?,
+            AttachmentPinAction::Unpin => atlasdb.unpin_attachment(content_hash)?,
+        }
+        let pins = RPCAttachmentPins::from_db(atlasdb)?;
+        HttpResponseType::AttachmentPins(response_metadata, pins)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Handle a GET for whether the node is processing blocks, and the chain tip it's at.  Only
+    /// served if the admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_block_processing<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        control: Option<&dyn BlockProcessingControl>,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            let (pause_requested, paused) = control
+                .map(|control| control.get_block_processing_state())
+                .unwrap_or((false, false));
+            let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())?;
+            HttpResponseType::BlockProcessing(
+                response_metadata,
+                RPCBlockProcessingStatus {
+                    pause_requested,
+                    paused,
+                    burn_block_height: tip.block_height,
+                    stacks_tip_height: tip.canonical_stacks_tip_height,
+                    stacks_tip: tip.canonical_stacks_tip_hash,
+                    stacks_tip_consensus_hash: tip.canonical_stacks_tip_consensus_hash,
+                },
+            )
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST that pauses or resumes block processing.  A pause takes effect once the
+    /// block or sortition being processed is done; the response's `paused` says whether that has
+    /// happened yet.  Only served if the admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_block_processing<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        control: Option<&dyn BlockProcessingControl>,
+        action: BlockProcessingAction,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        if options.enable_admin_rpc {
+            match control {
+                Some(control) => match action {
+                    BlockProcessingAction::Pause => {
+                        info!("Pausing block processing at an operator's request");
+                        control.pause_block_processing();
+                    }
+                    BlockProcessingAction::Resume => {
+                        info!("Resuming block processing at an operator's request");
+                        control.resume_block_processing();
+                    }
+                },
+                None => {
+                    let response = HttpResponseType::BadRequest(
+                        HttpResponseMetadata::from(req),
+                        "Pausing block processing is not supported by this node".to_string(),
+                    );
+                    return response.send(http, fd).map(|_| ());
+                }
+            }
+        }
+        ConversationHttp::handle_get_block_processing(http, fd, req, sortdb, control, options)
+    }
+
+    /// Handle a GET for the state of an operator-requested block fetch.  Only served if the admin
+    /// RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_block_fetch<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        block_fetches: &HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        index_block_hash: &StacksBlockId,
+        microblocks: bool,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = if !options.enable_admin_rpc {
+            HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string())
+        } else {
+            match block_fetches.get(&(index_block_hash.clone(), microblocks)) {
+                Some(fetch) => HttpResponseType::BlockFetch(response_metadata, fetch.clone()),
+                None => HttpResponseType::NotFound(
+                    response_metadata,
+                    format!("No fetch requested for {}", index_block_hash),
+                ),
+            }
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST that asks the block downloader to fetch an anchored block, or the
+    /// microblock stream it confirms, from any peer that advertises it.  The fetch is carried out
+    /// on the downloader's next pass, so this returns the fetch's state as of now; poll with GET
+    /// for its outcome.  A fetch that is already queued or in-flight is not restarted.  Only
+    /// served if the admin RPC is enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_block_fetch<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        block_fetches: &mut HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        chainstate: &StacksChainState,
+        index_block_hash: &StacksBlockId,
+        microblocks: bool,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let key = (index_block_hash.clone(), microblocks);
+        let pending = block_fetches
+            .get(&key)
+            .map(|fetch| {
+                fetch.status == BlockFetchStatus::Queued
+                    || fetch.status == BlockFetchStatus::InFlight
+            })
+            .unwrap_or(false);
+
+        if !pending {
+            let now = get_epoch_time_secs();
+            let status = if !microblocks
+                && StacksChainState::has_block_indexed(&chainstate.blocks_path, index_block_hash)?
+            {
+                BlockFetchStatus::Stored
+            } else {
+                BlockFetchStatus::Queued
+            };
+            debug!(
+                "Operator requested fetch of {} (microblocks: {}): {:?}",
+                index_block_hash, microblocks, status
+            );
+            block_fetches.insert(
+                key.clone(),
+                RPCBlockFetchData {
+                    index_block_hash: index_block_hash.clone(),
+                    microblocks,
+                    status,
+                    sortition_height: None,
+                    data_urls: vec![],
+                    requested_at: now,
+                    updated_at: now,
+                },
+            );
+        }
+
+        let fetch = block_fetches
+            .get(&key)
+            .cloned()
+            .expect("BUG: block fetch not recorded");
+        HttpResponseType::BlockFetch(response_metadata, fetch)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Handle a GET for a query job's progress and a page of its results.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_query_job<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        query_jobs: &QueryJobs,
+        job_id: u64,
+        page: &RPCPageRequest,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match query_jobs.get(job_id) {
+            Some(job) => match job.to_rpc(page, options.max_rpc_page_size) {
+                Ok(job) => HttpResponseType::QueryJob(response_metadata, job),
+                Err(msg) => HttpResponseType::BadRequest(response_metadata, msg),
+            },
+            None => HttpResponseType::NotFound(
+                response_metadata,
+                format!("No such query job {}", job_id),
+            ),
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Handle a POST that starts a query job, which scans the canonical fork back from the
+    /// current chain tip.  The p2p thread runs the job; its results are polled with GET
+    /// /v2/jobs/{id}.  Returns a 503 if the node already runs `max_query_jobs` jobs.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_query_job<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        query_jobs: &mut QueryJobs,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        kind: &QueryJobKind,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+        let tip = StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+        let tip_height = match StacksChainState::get_stacks_block_header_info_by_index_block_hash(
+            chainstate.db(),
+            &tip,
+        )? {
+            Some(header_info) => header_info.block_height,
+            None => {
+                let response =
+                    HttpResponseType::NotFound(response_metadata, "Chain tip not found".into());
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let response = match query_jobs.start(kind.clone(), tip, tip_height, options) {
+            Some(job_id) => {
+                let job = query_jobs
+                    .get(job_id)
+                    .expect("BUG: query job not recorded")
+                    .to_rpc(&RPCPageRequest::default(), options.max_rpc_page_size)
+                    .map_err(net_error::DeserializeError)?;
+                HttpResponseType::QueryJob(response_metadata, job)
+            }
+            None => HttpResponseType::ServiceUnavailable(
+                response_metadata,
+                format!(
+                    "Too many query jobs running (at most {}); try again later",
+                    options.max_query_jobs
+                ),
+            ),
+        };
+        response.send(http, fd).map(|_| ())
+    }
+
+    /// Decode the cursor of a page of a mempool snapshot: the origin address and nonce of the
+    /// previous page's last transaction.
+    fn parse_mempool_snapshot_cursor(cursor: &str) -> Option<(StacksAddress, u64)> {
+        let mut parts = cursor.splitn(2, ':');
+        let origin_address = StacksAddress::from_string(parts.next()?)?;
+        let origin_nonce = parts.next()?.parse::<u64>().ok()?;
+        Some((origin_address, origin_nonce))
+    }
+
+    /// Handle a GET for a page of a snapshot of the mempool.  Only served if the admin RPC is
+    /// enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_get_mempool_snapshot<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        page: &RPCPageRequest,
+        mempool: &MemPoolDB,
+        options: &ConnectionOptions,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let after = match page.cursor {
+            Some(ref cursor) => match ConversationHttp::parse_mempool_snapshot_cursor(cursor) {
+                Some(after) => Some(after),
+                None => {
+                    let response = HttpResponseType::BadRequest(
+                        response_metadata,
+                        format!("Invalid cursor '{}'", cursor),
+                    );
+                    return response.send(http, fd).map(|_| ());
+                }
+            },
+            None => None,
+        };
+
+        let page_size = page.page_size(options.max_rpc_page_size);
+        let txinfos = MemPoolDB::get_snapshot_txs_page(
+            mempool.conn(),
+            after.as_ref(),
+            page_size.saturating_add(1),
+        )?;
+        let snapshot_page = RPCPage::from_items(txinfos, page_size, |txinfo| {
+            format!(
+                "{}:{}",
+                &txinfo.metadata.origin_address, txinfo.metadata.origin_nonce
+            )
+        })
+        .map(|txinfo| txinfo.tx);
+
+        HttpResponseType::MemPoolSnapshot(response_metadata, snapshot_page)
+            .send(http, fd)
+            .map(|_| ())
+    }
+
+    /// Handle a POST of a mempool snapshot.  Each transaction is admitted to the mempool at the
+    /// canonical chain tip, as if it were posted on its own.  Only served if the admin RPC is
+    /// enabled.
+    /// The response will be synchronously written to the fd.
+    fn handle_post_mempool_snapshot<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        mempool: &mut MemPoolDB,
+        txs: Vec<StacksTransaction>,
+        options: &ConnectionOptions,
+        event_observer: Option<&dyn MemPoolEventDispatcher>,
+    ) -> Result<(), net_error> {
+        let response_metadata = HttpResponseMetadata::from(req);
+        if !options.enable_admin_rpc {
+            let response =
+                HttpResponseType::Forbidden(response_metadata, "Admin RPC is disabled".to_string());
+            return response.send(http, fd).map(|_| ());
+        }
+
+        let tip = match chainstate.get_stacks_chain_tip(sortdb)? {
+            Some(tip) => tip,
+            None => {
+                warn!("Failed to load Stacks chain tip");
+                let response = HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to load Stacks chain tip"),
+                );
+                return response.send(http, fd).map(|_| ());
+            }
+        };
+
+        let report = mempool.import_txs(
+            chainstate,
+            &tip.consensus_hash,
+            &tip.anchored_block_hash,
+            txs,
+            event_observer,
+        )?;
+        let import_data = RPCMemPoolImportData {
+            accepted: report.accepted,
+            already_present: report.already_present,
+            rejected: report
+                .rejected
+                .into_iter()
+                .map(|(txid, rejection)| rejection.into_json(&txid))
+                .collect(),
+        };
+        let response = HttpResponseType::MemPoolImport(response_metadata, import_data);
+        response.send(http, fd).map(|_| ())
+    }
+
     /// Load up the canonical Stacks chain tip.  Note that this is subject to both burn chain block
     /// Stacks block availability -- different nodes with different partial replicas of the Stacks chain state
     /// will return different values here.
     /// tip_opt is given by the HTTP request as the optional query parameter for the chain tip
     /// hash.  It will be None if there was no paramter given.
     /// The order of chain tips this method prefers is as follows:
-    /// * tip_opt, if it's Some(..),
+    /// * tip_opt, if it's Some(..) and it is a tip this node serves (see
+    /// `handle_check_stacks_chain_tip`),
     /// * the unconfirmed canonical stacks chain tip, if initialized
     /// * the confirmed canonical stacks chain tip
     fn handle_load_stacks_chain_tip<W: Write>(
@@ -1561,9 +4823,18 @@ impl ConversationHttp {
         tip_opt: Option<&StacksBlockId>,
         sortdb: &SortitionDB,
         chainstate: &StacksChainState,
+        max_fork_depth: u64,
     ) -> Result<Option<StacksBlockId>, net_error> {
         match tip_opt {
-            Some(tip) => Ok(Some(*tip).clone()),
+            Some(tip) => ConversationHttp::handle_check_stacks_chain_tip(
+                http,
+                fd,
+                req,
+                tip,
+                sortdb,
+                chainstate,
+                max_fork_depth,
+            ),
             None => match chainstate.get_stacks_chain_tip(sortdb)? {
                 Some(tip) => Ok(Some(StacksBlockHeader::make_index_block_hash(
                     &tip.consensus_hash,
@@ -1582,6 +4853,117 @@ impl ConversationHttp {
         }
     }
 
+    /// Check a chain tip given by a client.  It must be the unconfirmed chain tip, or a processed
+    /// block whose fork diverged from the canonical Stacks fork at most `max_fork_depth` blocks
+    /// below the canonical tip (0 for no limit).  Blocks on the canonical fork are always served.
+    /// If the tip is not served, a 404 is sent to the client and None is returned.
+    fn handle_check_stacks_chain_tip<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: &StacksBlockId,
+        sortdb: &SortitionDB,
+        chainstate: &StacksChainState,
+        max_fork_depth: u64,
+    ) -> Result<Option<StacksBlockId>, net_error> {
+        if let Some(ref unconfirmed_state) = chainstate.unconfirmed_state {
+            if unconfirmed_state.unconfirmed_chain_tip == *tip {
+                return Ok(Some(tip.clone()));
+            }
+        }
+
+        let (consensus_hash, block_hash) =
+            SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())?;
+        let canonical_tip = StacksBlockHeader::make_index_block_hash(&consensus_hash, &block_hash);
+
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match chainstate.get_fork_depth(tip, &canonical_tip)? {
+            Some(depth) if max_fork_depth == 0 || depth <= max_fork_depth => {
+                return Ok(Some(tip.clone()));
+            }
+            Some(depth) => {
+                debug!(
+                    "Refusing to serve chain tip {} on a fork {} blocks deep",
+                    tip, depth
+                );
+                HttpResponseType::NotFound(
+                    response_metadata,
+                    format!(
+                        "Chain tip {} is on a fork that diverged {} blocks below the canonical tip; this node serves forks at most {} blocks deep",
+                        tip, depth, max_fork_depth
+                    ),
+                )
+            }
+            None => {
+                HttpResponseType::NotFound(response_metadata, format!("No such chain tip {}", tip))
+            }
+        };
+        response.send(http, fd).and_then(|_| Ok(None))
+    }
+
+    /// Resolve a historic block for an `(at-block ...)`-style request against the given chain tip.
+    /// The block must be an ancestor of the tip (or the tip itself).  If the tip is the unconfirmed
+    /// chain tip, its ancestry is that of the confirmed tip it builds on.
+    /// If the block cannot be resolved, a 404 is sent to the client and None is returned, since
+    /// the usual cause is a stale or forked-off tip on the client's side.
+    fn handle_load_at_block<W: Write>(
+        http: &mut StacksHttp,
+        fd: &mut W,
+        req: &HttpRequestType,
+        tip: &StacksBlockId,
+        at_block: &AtBlock,
+        chainstate: &StacksChainState,
+    ) -> Result<Option<StacksBlockId>, net_error> {
+        let ancestry_tip = match chainstate.unconfirmed_state {
+            Some(ref unconfirmed_state) if unconfirmed_state.unconfirmed_chain_tip == *tip => {
+                unconfirmed_state.confirmed_chain_tip.clone()
+            }
+            _ => tip.clone(),
+        };
+
+        let index_conn = chainstate.index_conn()?;
+        let (resolved_res, not_found_msg) = match at_block {
+            AtBlock::Height(height) => (
+                index_conn.get_ancestor_block_hash(*height, &ancestry_tip),
+                format!(
+                    "No block at height {} in the ancestry of chain tip {}",
+                    height, tip
+                ),
+            ),
+            AtBlock::BlockId(block_id) => (
+                index_conn
+                    .get_ancestor_block_height(block_id, &ancestry_tip)
+                    .map(|height_opt| height_opt.map(|_| block_id.clone())),
+                format!(
+                    "Block {} is not in the ancestry of chain tip {}",
+                    block_id, tip
+                ),
+            ),
+        };
+
+        let response_metadata = HttpResponseMetadata::from(req);
+        let response = match resolved_res {
+            Ok(Some(block_id)) => {
+                return Ok(Some(block_id));
+            }
+            Ok(None) => {
+                debug!("{}", &not_found_msg);
+                HttpResponseType::NotFound(response_metadata, not_found_msg)
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to resolve {:?} from chain tip {}: {:?}",
+                    at_block, tip, &e
+                );
+                HttpResponseType::ServerError(
+                    response_metadata,
+                    format!("Failed to resolve historic block from chain tip {}", tip),
+                )
+            }
+        };
+        response.send(http, fd).and_then(|_| Ok(None))
+    }
+
     fn handle_load_stacks_chain_tip_hashes<W: Write>(
         http: &mut StacksHttp,
         fd: &mut W,
@@ -1626,23 +5008,44 @@ impl ConversationHttp {
         block_hash: BlockHeaderHash,
         mempool: &mut MemPoolDB,
         tx: StacksTransaction,
+        expire_height: Option<u64>,
         atlasdb: &mut AtlasDB,
         attachment: Option<Attachment>,
         event_observer: Option<&dyn MemPoolEventDispatcher>,
+        idempotency_cache: &mut RPCIdempotencyCache,
     ) -> Result<bool, net_error> {
         let txid = tx.txid();
         let response_metadata = HttpResponseMetadata::from(req);
+        let idempotency_key = req.metadata().idempotency_key.clone();
+        let now = get_epoch_time_secs();
+
+        // a retry of a submission we have already answered gets the same answer, and is not
+        // resubmitted or relayed again
+        if let Some(ref key) = idempotency_key {
+            if let Some(original_response) = idempotency_cache.get(key, &txid, now) {
+                debug!(
+                    "Replaying response to transaction {} for Idempotency-Key {}",
+                    &txid, key
+                );
+                return original_response
+                    .to_response(response_metadata)
+                    .send(http, fd)
+                    .and_then(|_| Ok(false));
+            }
+        }
+
         let (response, accepted) = if mempool.has_tx(&txid) {
             (
                 HttpResponseType::TransactionID(response_metadata, txid),
                 false,
             )
         } else {
-            match mempool.submit(
+            match mempool.submit_with_expiration(
                 chainstate,
                 &consensus_hash,
                 &block_hash,
                 &tx,
+                expire_height,
                 event_observer,
             ) {
                 Ok(_) => (
@@ -1668,6 +5071,10 @@ impl ConversationHttp {
             }
         }
 
+        if let Some(key) = idempotency_key {
+            idempotency_cache.insert(key, &txid, &response, now);
+        }
+
         response.send(http, fd).and_then(|_| Ok(accepted))
     }
 
@@ -1840,6 +5247,25 @@ impl ConversationHttp {
         response.send(http, fd).and_then(|_| Ok(accepted))
     }
 
+    /// When a request must be answered by: `rpc_request_timeout` seconds from now, or sooner if
+    /// the client asked for less with `X-Request-Timeout`.  None if neither sets a limit.
+    fn request_deadline(&self, md: &HttpRequestMetadata) -> Option<Instant> {
+        let timeout_secs = match (
+            self.connection.options.rpc_request_timeout,
+            md.request_timeout,
+        ) {
+            (0, None) => return None,
+            (0, Some(client_timeout)) => client_timeout,
+            (timeout, None) => timeout,
+            (timeout, Some(client_timeout)) => cmp::min(timeout, client_timeout),
+        };
+        Some(Instant::now() + Duration::from_secs(timeout_secs))
+    }
+
+    fn deadline_passed(deadline: Option<Instant>) -> bool {
+        deadline.map(|d| Instant::now() > d).unwrap_or(false)
+    }
+
     /// Handle an external HTTP request.
     /// Some requests, such as those for blocks, will create new reply streams.  This method adds
     /// those new streams into the `reply_streams` set.
@@ -1851,7 +5277,12 @@ impl ConversationHttp {
         chain_view: &BurnchainView,
         peers: &PeerMap,
         sortdb: &SortitionDB,
-        peerdb: &PeerDB,
+        peerdb: &mut PeerDB,
+        walk_stats: &NeighborWalkStats,
+        block_fetches: &mut HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        query_jobs: &mut QueryJobs,
+        rpc_cache: &mut RPCResponseCache,
+        idempotency_cache: &mut RPCIdempotencyCache,
         atlasdb: &mut AtlasDB,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
@@ -1859,9 +5290,38 @@ impl ConversationHttp {
     ) -> Result<Option<StacksMessageType>, net_error> {
         let mut reply = self.connection.make_relay_handle(self.conn_id)?;
         let keep_alive = req.metadata().keep_alive;
+        let deadline = self.request_deadline(req.metadata());
         let mut ret = None;
 
         let stream_opt = match req {
+            ref data_req
+                if data_req.is_chain_data_request()
+                    && !self.connection.options.network_mode.serves_data() =>
+            {
+                let response = HttpResponseType::Forbidden(
+                    HttpResponseMetadata::from(data_req),
+                    format!(
+                        "Chain data is not served in {} mode",
+                        self.connection.options.network_mode.as_str()
+                    ),
+                );
+                response.send(&mut self.connection.protocol, &mut reply)?;
+                None
+            }
+            ref write_req
+                if write_req.is_chain_write_request()
+                    && !self.connection.options.network_mode.accepts_writes() =>
+            {
+                let response = HttpResponseType::Forbidden(
+                    HttpResponseMetadata::from(write_req),
+                    format!(
+                        "Writes are not accepted in {} mode",
+                        self.connection.options.network_mode.as_str()
+                    ),
+                );
+                response.send(&mut self.connection.protocol, &mut reply)?;
+                None
+            }
             HttpRequestType::GetInfo(ref _md) => {
                 ConversationHttp::handle_getinfo(
                     &mut self.connection.protocol,
@@ -1875,6 +5335,16 @@ impl ConversationHttp {
                 )?;
                 None
             }
+            HttpRequestType::GetFeatures(ref _md) => {
+                ConversationHttp::handle_getfeatures(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    peerdb,
+                    &self.connection.options,
+                )?;
+                None
+            }
             HttpRequestType::GetPoxInfo(ref _md, ref tip_opt) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
@@ -1883,6 +5353,7 @@ impl ConversationHttp {
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
                     ConversationHttp::handle_getpoxinfo(
                         &mut self.connection.protocol,
@@ -1892,18 +5363,98 @@ impl ConversationHttp {
                         chainstate,
                         &tip,
                         &self.burnchain,
+                        rpc_cache,
                     )?;
                 }
                 None
             }
+            HttpRequestType::GetStateHash(ref _md, ref height, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
+                )? {
+                    if let Some(block_id) = ConversationHttp::handle_load_at_block(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        &tip,
+                        &AtBlock::Height(*height),
+                        chainstate,
+                    )? {
+                        ConversationHttp::handle_getstatehash(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            sortdb,
+                            chainstate,
+                            &block_id,
+                        )?;
+                    }
+                }
+                None
+            }
             HttpRequestType::GetNeighbors(ref _md) => {
                 ConversationHttp::handle_getneighbors(
                     &mut self.connection.protocol,
                     &mut reply,
                     &req,
-                    self.network_id,
-                    chain_view,
-                    peers,
+                    self.network_id,
+                    chain_view,
+                    peers,
+                    peerdb,
+                )?;
+                None
+            }
+            HttpRequestType::GetNeighborWalkInfo(ref _md) => {
+                ConversationHttp::handle_get_neighbor_walk_info(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    chain_view,
+                    peers,
+                    peerdb,
+                    walk_stats,
+                )?;
+                None
+            }
+            HttpRequestType::GetPeerVersions(ref _md) => {
+                ConversationHttp::handle_get_peer_versions(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    self.burnchain.peer_version,
+                    chain_view,
+                    peers,
+                    peerdb,
+                )?;
+                None
+            }
+            HttpRequestType::GetNodeStatus(ref _md) => {
+                ConversationHttp::handle_get_node_status(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    chain_view,
+                    peers,
+                    sortdb,
+                    chainstate,
+                    atlasdb,
+                    mempool,
+                    handler_opts,
+                )?;
+                None
+            }
+            HttpRequestType::GetNodeIdentity(ref _md, ref challenge) => {
+                ConversationHttp::handle_get_node_identity(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    challenge.as_ref().map(|c| c.as_slice()),
                     peerdb,
                 )?;
                 None
@@ -1917,6 +5468,34 @@ impl ConversationHttp {
                     chainstate,
                 )?
             }
+            HttpRequestType::GetBlockDecoded(ref _md, ref index_block_hash) => {
+                ConversationHttp::handle_getblock_decoded(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_block_hash,
+                    chainstate,
+                )?
+            }
+            HttpRequestType::GetMicroblocksIndexedDecoded(ref _md, ref index_head_hash) => {
+                ConversationHttp::handle_getmicroblocks_indexed_decoded(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    index_head_hash,
+                    chainstate,
+                )?
+            }
+            HttpRequestType::GetMicroblocksConfirmedDecoded(
+                ref _md,
+                ref anchor_index_block_hash,
+            ) => ConversationHttp::handle_getmicroblocks_confirmed_decoded(
+                &mut self.connection.protocol,
+                &mut reply,
+                &req,
+                anchor_index_block_hash,
+                chainstate,
+            )?,
             HttpRequestType::GetMicroblocksIndexed(ref _md, ref index_head_hash) => {
                 ConversationHttp::handle_getmicroblocks_indexed(
                     &mut self.connection.protocol,
@@ -1935,6 +5514,17 @@ impl ConversationHttp {
                     chainstate,
                 )?
             }
+            HttpRequestType::GetMicroblockForks(ref _md, ref index_anchor_block_hash_opt) => {
+                ConversationHttp::handle_getmicroblocks_forks(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    index_anchor_block_hash_opt,
+                )?;
+                None
+            }
             HttpRequestType::GetMicroblocksUnconfirmed(
                 ref _md,
                 ref index_anchor_block_hash,
@@ -1952,13 +5542,331 @@ impl ConversationHttp {
                     &mut self.connection.protocol,
                     &mut reply,
                     &req,
+                    sortdb,
                     chainstate,
                     mempool,
                     txid,
                 )?;
                 None
             }
-            HttpRequestType::GetAccount(ref _md, ref principal, ref tip_opt, ref with_proof) => {
+            HttpRequestType::GetMemPoolDrops(ref _md, ref txid) => {
+                ConversationHttp::handle_getmempool_drops(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    mempool,
+                    txid,
+                )?;
+                None
+            }
+            HttpRequestType::GetMicroblockProof(ref _md, ref txid) => {
+                ConversationHttp::handle_getmicroblock_proof(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    txid,
+                )?;
+                None
+            }
+            HttpRequestType::GetAddressMempool(ref _md, ref address, ref page) => {
+                ConversationHttp::handle_get_address_mempool(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    address,
+                    page,
+                    mempool,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetMemPoolFees(ref _md) => {
+                ConversationHttp::handle_getmempool_fees(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    mempool,
+                )?;
+                None
+            }
+            HttpRequestType::SimulateBlock(ref _md) => {
+                ConversationHttp::handle_simulate_block(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    mempool,
+                    deadline,
+                )?;
+                None
+            }
+            HttpRequestType::GetContractCosts(ref _md, from_height, to_height, limit) => {
+                ConversationHttp::handle_get_contract_costs(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    from_height,
+                    to_height,
+                    limit,
+                    sortdb,
+                    chainstate,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetMemPoolSnapshot(ref _md, ref page) => {
+                ConversationHttp::handle_get_mempool_snapshot(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    page,
+                    mempool,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetPeerFilters(ref _md) => {
+                ConversationHttp::handle_get_peer_filters(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    peerdb,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostPeerFilter(ref _md, action, list, ref rule) => {
+                ConversationHttp::handle_post_peer_filter(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    peerdb,
+                    action,
+                    list,
+                    rule,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetAtlasContracts(ref _md) => {
+                ConversationHttp::handle_get_atlas_contracts(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostAtlasContract(
+                ref _md,
+                ref contract_id,
+                attachments_max_size,
+                unresolved_attachment_instances_expire_after,
+            ) => {
+                ConversationHttp::handle_post_atlas_contract(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    contract_id,
+                    attachments_max_size,
+                    unresolved_attachment_instances_expire_after,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetAttachmentPins(ref _md) => {
+                ConversationHttp::handle_get_attachment_pins(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostAttachmentPin(ref _md, action, ref content_hash) => {
+                ConversationHttp::handle_post_attachment_pin(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    action,
+                    content_hash,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetBlockProcessing(ref _md) => {
+                ConversationHttp::handle_get_block_processing(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    handler_opts.block_processing,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostBlockProcessing(ref _md, action) => {
+                ConversationHttp::handle_post_block_processing(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    handler_opts.block_processing,
+                    action,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetBlockFetch(ref _md, ref index_block_hash, microblocks) => {
+                ConversationHttp::handle_get_block_fetch(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    block_fetches,
+                    index_block_hash,
+                    microblocks,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostBlockFetch(ref _md, ref index_block_hash, microblocks) => {
+                ConversationHttp::handle_post_block_fetch(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    block_fetches,
+                    chainstate,
+                    index_block_hash,
+                    microblocks,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetQueryJob(ref _md, job_id, ref page) => {
+                ConversationHttp::handle_get_query_job(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    query_jobs,
+                    job_id,
+                    page,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostQueryJob(ref _md, ref kind) => {
+                ConversationHttp::handle_post_query_job(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    query_jobs,
+                    sortdb,
+                    chainstate,
+                    kind,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetEventObserverStatus(ref _md) => {
+                ConversationHttp::handle_get_event_observer_status(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    handler_opts.event_observer_status,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::GetEventReplays(ref _md) => {
+                ConversationHttp::handle_get_event_replays(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    handler_opts.event_replay,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostEventReplay(ref _md, ref request) => {
+                ConversationHttp::handle_post_event_replay(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    handler_opts.event_replay,
+                    request,
+                    &self.connection.options,
+                )?;
+                None
+            }
+            HttpRequestType::PostMemPoolSnapshot(ref _md, ref txs) => {
+                ConversationHttp::handle_post_mempool_snapshot(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    sortdb,
+                    chainstate,
+                    mempool,
+                    txs.clone(),
+                    &self.connection.options,
+                    handler_opts.event_observer.as_deref(),
+                )?;
+                None
+            }
+            HttpRequestType::GetAccount(ref _md, ref principal, ref tip_opt, ref with_proof) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
+                )? {
+                    ConversationHttp::handle_get_account_entry(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        principal,
+                        *with_proof,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetAccountUnlocks(ref _md, ref principal, ref tip_opt) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
+                )? {
+                    ConversationHttp::handle_get_account_unlocks(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        principal,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::GetMinerRewards(ref _md, ref address, ref tip_opt) => {
                 if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
                     &mut reply,
@@ -1966,16 +5874,16 @@ impl ConversationHttp {
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
-                    ConversationHttp::handle_get_account_entry(
+                    ConversationHttp::handle_get_miner_rewards(
                         &mut self.connection.protocol,
                         &mut reply,
                         &req,
                         sortdb,
                         chainstate,
                         &tip,
-                        principal,
-                        *with_proof,
+                        address,
                     )?;
                 }
                 None
@@ -1996,6 +5904,7 @@ impl ConversationHttp {
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
                     ConversationHttp::handle_get_map_entry(
                         &mut self.connection.protocol,
@@ -2009,6 +5918,7 @@ impl ConversationHttp {
                         map_name,
                         key,
                         *with_proof,
+                        rpc_cache,
                     )?;
                 }
                 None
@@ -2034,6 +5944,7 @@ impl ConversationHttp {
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
                     ConversationHttp::handle_get_contract_abi(
                         &mut self.connection.protocol,
@@ -2044,6 +5955,7 @@ impl ConversationHttp {
                         &tip,
                         contract_addr,
                         contract_name,
+                        rpc_cache,
                     )?;
                 }
                 None
@@ -2056,15 +5968,31 @@ impl ConversationHttp {
                 ref func_name,
                 ref args,
                 ref tip_opt,
+                ref at_block_opt,
             ) => {
-                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                let tip_opt = match ConversationHttp::handle_load_stacks_chain_tip(
                     &mut self.connection.protocol,
                     &mut reply,
                     &req,
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
+                    Some(tip) => match at_block_opt {
+                        Some(at_block) => ConversationHttp::handle_load_at_block(
+                            &mut self.connection.protocol,
+                            &mut reply,
+                            &req,
+                            &tip,
+                            at_block,
+                            chainstate,
+                        )?,
+                        None => Some(tip),
+                    },
+                    None => None,
+                };
+                if let Some(tip) = tip_opt {
                     ConversationHttp::handle_readonly_function_call(
                         &mut self.connection.protocol,
                         &mut reply,
@@ -2077,6 +6005,7 @@ impl ConversationHttp {
                         func_name,
                         as_sender,
                         args,
+                        deadline,
                         &self.connection.options,
                     )?;
                 }
@@ -2096,6 +6025,7 @@ impl ConversationHttp {
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
                     ConversationHttp::handle_get_contract_src(
                         &mut self.connection.protocol,
@@ -2111,7 +6041,12 @@ impl ConversationHttp {
                 }
                 None
             }
-            HttpRequestType::PostTransaction(ref _md, ref tx, ref attachment) => {
+            HttpRequestType::PostTransaction(
+                ref _md,
+                ref tx,
+                ref attachment,
+                ref expire_height,
+            ) => {
                 match chainstate.get_stacks_chain_tip(sortdb)? {
                     Some(tip) => {
                         let accepted = ConversationHttp::handle_post_transaction(
@@ -2123,9 +6058,11 @@ impl ConversationHttp {
                             tip.anchored_block_hash,
                             mempool,
                             tx.clone(),
+                            *expire_height,
                             atlasdb,
                             attachment.clone(),
                             handler_opts.event_observer.as_deref(),
+                            idempotency_cache,
                         )?;
                         if accepted {
                             // forward to peer network
@@ -2154,6 +6091,28 @@ impl ConversationHttp {
                 )?;
                 None
             }
+            HttpRequestType::GetAttachmentContent(ref _md, ref content_hash, ref range) => {
+                ConversationHttp::handle_getattachment_content(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    content_hash.clone(),
+                    range.clone(),
+                )?;
+                None
+            }
+            HttpRequestType::GetAttachmentsBatch(ref _md, ref content_hashes) => {
+                ConversationHttp::handle_getattachments_batch(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    atlasdb,
+                    content_hashes,
+                    deadline,
+                )?;
+                None
+            }
             HttpRequestType::GetAttachmentsInv(
                 ref _md,
                 ref index_block_hash,
@@ -2166,6 +6125,7 @@ impl ConversationHttp {
                     atlasdb,
                     &index_block_hash,
                     pages_indexes,
+                    deadline,
                     &self.connection.options,
                 )?;
                 None
@@ -2242,6 +6202,7 @@ impl ConversationHttp {
                     tip_opt.as_ref(),
                     sortdb,
                     chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
                 )? {
                     ConversationHttp::handle_get_is_trait_implemented(
                         &mut self.connection.protocol,
@@ -2257,6 +6218,88 @@ impl ConversationHttp {
                 }
                 None
             }
+            HttpRequestType::VerifyContractSrc(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref source,
+                ref tip_opt,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
+                )? {
+                    ConversationHttp::handle_verify_contract_src(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        source,
+                        deadline,
+                        &self.connection.options,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::ValidateContractCall(
+                ref _md,
+                ref contract_addr,
+                ref contract_name,
+                ref func_name,
+                ref args,
+                ref tip_opt,
+            ) => {
+                if let Some(tip) = ConversationHttp::handle_load_stacks_chain_tip(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    tip_opt.as_ref(),
+                    sortdb,
+                    chainstate,
+                    self.connection.options.max_rpc_tip_fork_depth,
+                )? {
+                    ConversationHttp::handle_validate_contract_call(
+                        &mut self.connection.protocol,
+                        &mut reply,
+                        &req,
+                        sortdb,
+                        chainstate,
+                        &tip,
+                        contract_addr,
+                        contract_name,
+                        func_name,
+                        args,
+                    )?;
+                }
+                None
+            }
+            HttpRequestType::EncodeClarityValue(ref _md, ref value) => {
+                ConversationHttp::handle_clarity_value_conversion(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    ClarityValueResponse::from_value(value.clone()),
+                )?;
+                None
+            }
+            HttpRequestType::DecodeClarityValue(ref _md, ref hex) => {
+                ConversationHttp::handle_clarity_value_conversion(
+                    &mut self.connection.protocol,
+                    &mut reply,
+                    &req,
+                    ClarityValueResponse::from_hex(hex),
+                )?;
+                None
+            }
             HttpRequestType::ClientError(ref _md, ref err) => {
                 let response_metadata = HttpResponseMetadata::from(&req);
                 let response = match err {
@@ -2267,6 +6310,9 @@ impl ConversationHttp {
                     ClientError::NotFound(path) => {
                         HttpResponseType::NotFound(response_metadata, path.clone())
                     }
+                    ClientError::PayloadTooLarge(_) => {
+                        HttpResponseType::Error(response_metadata, 413, err.to_string())
+                    }
                 };
 
                 response
@@ -2520,7 +6566,12 @@ impl ConversationHttp {
         chain_view: &BurnchainView,
         peers: &PeerMap,
         sortdb: &SortitionDB,
-        peerdb: &PeerDB,
+        peerdb: &mut PeerDB,
+        walk_stats: &NeighborWalkStats,
+        block_fetches: &mut HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        query_jobs: &mut QueryJobs,
+        rpc_cache: &mut RPCResponseCache,
+        idempotency_cache: &mut RPCIdempotencyCache,
         atlasdb: &mut AtlasDB,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
@@ -2556,6 +6607,11 @@ impl ConversationHttp {
                             peers,
                             sortdb,
                             peerdb,
+                            walk_stats,
+                            block_fetches,
+                            query_jobs,
+                            rpc_cache,
+                            idempotency_cache,
                             atlasdb,
                             chainstate,
                             mempool,
@@ -2672,6 +6728,14 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new getblock request to this endpoint for the decoded block
+    pub fn new_getblock_decoded(&self, index_block_hash: StacksBlockId) -> HttpRequestType {
+        HttpRequestType::GetBlockDecoded(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_block_hash,
+        )
+    }
+
     /// Make a new get-microblocks request to this endpoint
     pub fn new_getmicroblocks_indexed(
         &self,
@@ -2694,6 +6758,40 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new get-microblocks request to this endpoint for the decoded microblocks
+    pub fn new_getmicroblocks_indexed_decoded(
+        &self,
+        index_microblock_hash: StacksBlockId,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksIndexedDecoded(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_microblock_hash,
+        )
+    }
+
+    /// Make a new get-microblocks-confirmed request to this endpoint for the decoded microblocks
+    pub fn new_getmicroblocks_confirmed_decoded(
+        &self,
+        index_anchor_block_hash: StacksBlockId,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblocksConfirmedDecoded(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_anchor_block_hash,
+        )
+    }
+
+    /// Make a new request for the microblock streams seen for an anchored block, or for the
+    /// canonical Stacks chain tip if None
+    pub fn new_getmicroblocks_forks(
+        &self,
+        index_anchor_block_hash_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMicroblockForks(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            index_anchor_block_hash_opt,
+        )
+    }
+
     /// Make a new get-microblocks request for unconfirmed microblocks
     pub fn new_getmicroblocks_unconfirmed(
         &self,
@@ -2715,12 +6813,26 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for a page of an address's pending transactions
+    pub fn new_get_address_mempool(
+        &self,
+        address: StacksAddress,
+        page: RPCPageRequest,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAddressMempool(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            address,
+            page,
+        )
+    }
+
     /// Make a new post-transaction request
     pub fn new_post_transaction(&self, tx: StacksTransaction) -> HttpRequestType {
         HttpRequestType::PostTransaction(
             HttpRequestMetadata::from_host(self.peer_host.clone()),
             tx,
             None,
+            None,
         )
     }
 
@@ -2761,6 +6873,32 @@ impl ConversationHttp {
         )
     }
 
+    /// Make a new request for an account's locked STX and unlock schedule
+    pub fn new_getaccountunlocks(
+        &self,
+        principal: PrincipalData,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetAccountUnlocks(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            principal,
+            tip_opt,
+        )
+    }
+
+    /// Make a new request for the block rewards an address has earned
+    pub fn new_getminerrewards(
+        &self,
+        address: StacksAddress,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::GetMinerRewards(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            address,
+            tip_opt,
+        )
+    }
+
     /// Make a new request for a data map
     pub fn new_getmapentry(
         &self,
@@ -2823,6 +6961,7 @@ impl ConversationHttp {
         function_name: ClarityName,
         function_args: Vec<Value>,
         tip_opt: Option<StacksBlockId>,
+        at_block_opt: Option<AtBlock>,
     ) -> HttpRequestType {
         HttpRequestType::CallReadOnlyFunction(
             HttpRequestMetadata::from_host(self.peer_host.clone()),
@@ -2832,6 +6971,24 @@ impl ConversationHttp {
             function_name,
             function_args,
             tip_opt,
+            at_block_opt,
+        )
+    }
+
+    /// Make a new request to verify a contract's source code
+    pub fn new_verifycontractsrc(
+        &self,
+        contract_addr: StacksAddress,
+        contract_name: ContractName,
+        source: String,
+        tip_opt: Option<StacksBlockId>,
+    ) -> HttpRequestType {
+        HttpRequestType::VerifyContractSrc(
+            HttpRequestMetadata::from_host(self.peer_host.clone()),
+            contract_addr,
+            contract_name,
+            source,
+            tip_opt,
         )
     }
 
@@ -2853,6 +7010,7 @@ impl ConversationHttp {
 mod test {
     use std::cell::RefCell;
     use std::convert::TryInto;
+    use std::fs;
     use std::iter::FromIterator;
 
     use address::*;
@@ -2861,12 +7019,15 @@ mod test {
     use burnchains::*;
     use chainstate::burn::ConsensusHash;
     use chainstate::stacks::db::blocks::test::*;
+    use chainstate::stacks::db::test::chainstate_path;
     use chainstate::stacks::db::BlockStreamData;
     use chainstate::stacks::db::StacksChainState;
+    use chainstate::stacks::db::{ChainStateBootData, ClarityTx};
     use chainstate::stacks::miner::*;
     use chainstate::stacks::test::*;
     use chainstate::stacks::Error as chain_error;
     use chainstate::stacks::*;
+    use core::{FIRST_BURNCHAIN_CONSENSUS_HASH, FIRST_STACKS_BLOCK_HASH};
     use net::codec::*;
     use net::http::*;
     use net::test::*;
@@ -3257,7 +7418,12 @@ mod test {
                 &view_1,
                 &PeerMap::new(),
                 &mut peer_1_sortdb,
-                &peer_1.network.peerdb,
+                &mut peer_1.network.peerdb,
+                &peer_1.network.walk_stats,
+                &mut peer_1.network.block_fetches,
+                &mut peer_1.network.query_jobs,
+                &mut peer_1.network.rpc_cache,
+                &mut peer_1.network.idempotency_cache,
                 &mut peer_1.network.atlasdb,
                 &mut peer_1_stacks_node.chainstate,
                 &mut peer_1_mempool,
@@ -3284,7 +7450,12 @@ mod test {
                 &view_2,
                 &PeerMap::new(),
                 &mut peer_2_sortdb,
-                &peer_2.network.peerdb,
+                &mut peer_2.network.peerdb,
+                &peer_2.network.walk_stats,
+                &mut peer_2.network.block_fetches,
+                &mut peer_2.network.query_jobs,
+                &mut peer_2.network.rpc_cache,
+                &mut peer_2.network.idempotency_cache,
                 &mut peer_2.network.atlasdb,
                 &mut peer_2_stacks_node.chainstate,
                 &mut peer_2_mempool,
@@ -3325,7 +7496,12 @@ mod test {
                 &view_1,
                 &PeerMap::new(),
                 &mut peer_1_sortdb,
-                &peer_1.network.peerdb,
+                &mut peer_1.network.peerdb,
+                &peer_1.network.walk_stats,
+                &mut peer_1.network.block_fetches,
+                &mut peer_1.network.query_jobs,
+                &mut peer_1.network.rpc_cache,
+                &mut peer_1.network.idempotency_cache,
                 &mut peer_1.network.atlasdb,
                 &mut peer_1_stacks_node.chainstate,
                 &mut peer_1_mempool,
@@ -3347,6 +7523,168 @@ mod test {
         assert!(check_result(&req, &resp, &mut peer_1, &mut peer_2));
     }
 
+    #[test]
+    fn test_mempool_fee_data_helpers() {
+        let fee_rates = vec![0.5, 1.0, 1.0, 2.0, 4.0, 8.0, 8.0, 16.0, 32.0, 64.0];
+        assert_eq!(RPCMemPoolFeeData::percentile(&fee_rates, 10), 0.5);
+        assert_eq!(RPCMemPoolFeeData::percentile(&fee_rates, 50), 4.0);
+        assert_eq!(RPCMemPoolFeeData::percentile(&fee_rates, 90), 32.0);
+        assert_eq!(RPCMemPoolFeeData::percentile(&[3.0], 10), 3.0);
+
+        assert_eq!(RPCMemPoolFeeData::fee_rate(300, 150), 2.0);
+        assert_eq!(RPCMemPoolFeeData::fee_rate(300, 0), 300.0);
+
+        let block_limit = ExecutionCost {
+            runtime: 100,
+            write_length: 100,
+            write_count: 100,
+            read_length: 100,
+            read_count: 100,
+        };
+        assert_eq!(
+            RPCMemPoolFeeData::depth_in_blocks(0, &[0; 5], &block_limit),
+            0
+        );
+        // size-bound
+        assert_eq!(
+            RPCMemPoolFeeData::depth_in_blocks((MAX_EPOCH_SIZE as u64) + 1, &[0; 5], &block_limit),
+            2
+        );
+        // cost-bound, by the most constrained dimension
+        assert_eq!(
+            RPCMemPoolFeeData::depth_in_blocks(100, &[50, 0, 301, 0, 0], &block_limit),
+            4
+        );
+    }
+
+    #[test]
+    fn test_node_identity_signature() {
+        let local_peer = LocalPeer::new(
+            0x80000000,
+            0x80000000,
+            PeerAddress::from_ipv4(127, 0, 0, 1),
+            20444,
+            None,
+            12345,
+            UrlString::try_from("http://127.0.0.1:20443").unwrap(),
+        );
+        let public_key = Secp256k1PublicKey::from_private(&local_peer.private_key);
+
+        let identity = RPCNodeIdentity::from_local_peer(&local_peer, None).unwrap();
+        assert_eq!(
+            identity.public_key,
+            StacksPublicKeyBuffer::from_public_key(&public_key)
+        );
+        assert_eq!(identity.expire_block_height, 12345);
+        assert!(identity.challenge.is_none());
+        assert!(identity.verify().unwrap());
+
+        let identity = RPCNodeIdentity::from_local_peer(&local_peer, Some(&[0x01, 0x02])).unwrap();
+        assert_eq!(identity.challenge, Some("0102".to_string()));
+        assert!(identity.verify().unwrap());
+
+        // survives the round-trip through JSON
+        let json = serde_json::to_string(&identity).unwrap();
+        let decoded: RPCNodeIdentity = serde_json::from_str(&json).unwrap();
+        assert!(decoded.verify().unwrap());
+
+        // a replayed signature doesn't cover a different challenge, or a different data URL
+        let mut replayed = identity.clone();
+        replayed.challenge = Some("0103".to_string());
+        assert!(!replayed.verify().unwrap());
+
+        let mut replayed = identity.clone();
+        replayed.data_url = UrlString::try_from("http://10.0.0.1:20443").unwrap();
+        assert!(!replayed.verify().unwrap());
+
+        // nor can it be claimed for another key
+        let other_key = Secp256k1PublicKey::from_private(&StacksPrivateKey::new());
+        let mut replayed = identity.clone();
+        replayed.public_key = StacksPublicKeyBuffer::from_public_key(&other_key);
+        replayed.public_key_hash = Hash160::from_node_public_key(&other_key);
+        assert!(!replayed.verify().unwrap());
+
+        let mut replayed = identity;
+        replayed.challenge = Some("not hex".to_string());
+        assert!(replayed.verify().is_err());
+    }
+
+    #[test]
+    fn test_microblock_proof_verify() {
+        let microblock_privk = StacksPrivateKey::new();
+        let mut parent_header = StacksBlockHeader::genesis_block_header();
+        parent_header.microblock_pubkey_hash =
+            Hash160::from_node_public_key(&StacksPublicKey::from_private(&microblock_privk));
+
+        // a stream of two microblocks; the transaction is in the second one
+        let txids = vec![Txid([0x01; 32]), Txid([0x02; 32])];
+        let txid_vecs = txids.iter().map(|txid| txid.as_bytes().to_vec()).collect();
+        let mut mblock_0 =
+            StacksMicroblockHeader::first_empty_unsigned(&parent_header.block_hash());
+        mblock_0.sign(&microblock_privk).unwrap();
+        let mut mblock_1 = StacksMicroblockHeader::from_parent_unsigned(
+            &mblock_0,
+            &MerkleTree::<Sha512Trunc256Sum>::new(&txid_vecs).root(),
+        )
+        .unwrap();
+        mblock_1.sign(&microblock_privk).unwrap();
+
+        let mut header = StacksBlockHeader::genesis_block_header();
+        header.parent_block = parent_header.block_hash();
+        header.parent_microblock = mblock_1.block_hash();
+        header.parent_microblock_sequence = 1;
+        let consensus_hash = ConsensusHash([0x11; 20]);
+
+        let proof = RPCMicroblockProof {
+            txid: txids[1].to_hex(),
+            index_block_hash: header.index_block_hash(&consensus_hash),
+            consensus_hash,
+            block_height: 2,
+            canonical: true,
+            block_header: to_hex(&header.serialize_to_vec()),
+            parent_consensus_hash: ConsensusHash([0x10; 20]),
+            parent_block_header: to_hex(&parent_header.serialize_to_vec()),
+            microblock_headers: vec![
+                to_hex(&mblock_0.serialize_to_vec()),
+                to_hex(&mblock_1.serialize_to_vec()),
+            ],
+            microblock_sequence: 1,
+            microblock_txids: txids.iter().map(|txid| txid.to_hex()).collect(),
+        };
+        proof.verify().unwrap();
+
+        // the transaction must be in the microblock...
+        let mut bad_proof = proof.clone();
+        bad_proof.txid = Txid([0x03; 32]).to_hex();
+        assert!(bad_proof.verify().is_err());
+
+        // ...which must commit to the given transactions
+        let mut bad_proof = proof.clone();
+        bad_proof.microblock_txids.pop();
+        assert!(bad_proof.verify().is_err());
+
+        let mut bad_proof = proof.clone();
+        bad_proof.microblock_sequence = 0;
+        assert!(bad_proof.verify().is_err());
+
+        // the stream must be the one the block confirms
+        let mut bad_proof = proof.clone();
+        bad_proof.microblock_headers.pop();
+        assert!(bad_proof.verify().is_err());
+
+        // and be signed by the parent block's miner
+        let mut forged = mblock_1.clone();
+        forged.sign(&StacksPrivateKey::new()).unwrap();
+        let mut bad_proof = proof.clone();
+        bad_proof.microblock_headers[1] = to_hex(&forged.serialize_to_vec());
+        assert!(bad_proof.verify().is_err());
+
+        // the block must be the one named by the proof
+        let mut bad_proof = proof;
+        bad_proof.consensus_hash = ConsensusHash([0x12; 20]);
+        assert!(bad_proof.verify().is_err());
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_getinfo() {
@@ -4178,8 +8516,90 @@ mod test {
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetContractSrc(response_md, data) => {
-                        assert_eq!(data.source, TEST_CONTRACT);
+                    HttpResponseType::GetContractSrc(response_md, data) => {
+                        assert_eq!(data.source, TEST_CONTRACT);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account() {
+        test_rpc(
+            "test_rpc_get_account",
+            40110,
+            40111,
+            50110,
+            50111,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccount(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                    false,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccount(response_md, data) => {
+                        assert_eq!(data.nonce, 2);
+                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
+                        assert_eq!(balance, 1000000000);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_get_account_unlocks() {
+        test_rpc(
+            "test_rpc_get_account_unlocks",
+            40230,
+            40231,
+            50230,
+            50231,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_getaccountunlocks(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::GetAccountUnlocks(response_md, data) => {
+                        let locked = u128::from_str_radix(&data.locked[2..], 16).unwrap();
+                        assert_eq!(locked, 0);
+                        assert_eq!(data.unlock_height, 0);
+                        let vesting_locked =
+                            u128::from_str_radix(&data.vesting_locked[2..], 16).unwrap();
+                        assert_eq!(vesting_locked, 0);
+                        assert_eq!(data.vesting.len(), 0);
+                        assert!(data.stacks_tip_height > 0);
                         true
                     }
                     _ => {
@@ -4193,32 +8613,33 @@ mod test {
 
     #[test]
     #[ignore]
-    fn test_rpc_get_account() {
+    fn test_rpc_get_miner_rewards() {
         test_rpc(
-            "test_rpc_get_account",
-            40110,
-            40111,
-            50110,
-            50111,
+            "test_rpc_get_miner_rewards",
+            40232,
+            40233,
+            50232,
+            50233,
             |ref mut peer_client,
              ref mut convo_client,
              ref mut peer_server,
              ref mut convo_server| {
-                convo_client.new_getaccount(
+                convo_client.new_getminerrewards(
                     StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
-                        .unwrap()
-                        .to_account_principal(),
+                        .unwrap(),
                     None,
-                    false,
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
                 let req_md = http_request.metadata().clone();
                 match http_response {
-                    HttpResponseType::GetAccount(response_md, data) => {
-                        assert_eq!(data.nonce, 2);
-                        let balance = u128::from_str_radix(&data.balance[2..], 16).unwrap();
-                        assert_eq!(balance, 1000000000);
+                    HttpResponseType::GetMinerRewards(response_md, data) => {
+                        assert_eq!(data.rewards.len(), 0);
+                        let matured = u128::from_str_radix(&data.matured[2..], 16).unwrap();
+                        assert_eq!(matured, 0);
+                        assert_eq!(data.maturity, MINER_REWARD_MATURITY);
+                        assert_eq!(data.reward_schedule.len(), 4);
+                        assert!(data.stacks_tip_height > 0);
                         true
                     }
                     _ => {
@@ -4488,6 +8909,7 @@ mod test {
                     "ro-test".try_into().unwrap(),
                     vec![],
                     None,
+                    None,
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
@@ -4539,6 +8961,7 @@ mod test {
                     "ro-test".try_into().unwrap(),
                     vec![],
                     Some(unconfirmed_tip),
+                    None,
                 )
             },
             |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
@@ -4563,6 +8986,632 @@ mod test {
         );
     }
 
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only_at_block() {
+        test_rpc(
+            "test_rpc_call_read_only_at_block",
+            40210,
+            40211,
+            50210,
+            50211,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    "get-bar".try_into().unwrap(),
+                    vec![],
+                    None,
+                    Some(AtBlock::Height(1)),
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::CallReadOnlyFunction(response_md, data) => {
+                        assert!(data.okay);
+                        assert_eq!(
+                            Value::try_deserialize_hex_untyped(&data.result.clone().unwrap())
+                                .unwrap(),
+                            Value::okay(Value::Int(0)).unwrap()
+                        );
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_call_read_only_at_block_not_found() {
+        test_rpc(
+            "test_rpc_call_read_only_at_block_not_found",
+            40220,
+            40221,
+            50220,
+            50221,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_callreadonlyfunction(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap()
+                        .to_account_principal(),
+                    "get-bar".try_into().unwrap(),
+                    vec![],
+                    None,
+                    Some(AtBlock::Height(100)),
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::NotFound(response_md, msg) => {
+                        assert!(msg.find("No block at height 100").is_some());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_verify_contract_src() {
+        test_rpc(
+            "test_rpc_verify_contract_src",
+            40190,
+            40191,
+            50190,
+            50191,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                // same contract, but with different line endings and trailing whitespace
+                let source = format!("{}  \r\n\r\n", TEST_CONTRACT.replace("\n", "\r\n"));
+                convo_client.new_verifycontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    source,
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::VerifyContractSrc(response_md, data) => {
+                        assert!(data.source_match);
+                        assert!(data.ast_match);
+                        assert!(data.analysis_ok);
+                        assert_eq!(data.source_hash, data.deployed_source_hash);
+                        assert!(data.cause.is_none());
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    #[ignore]
+    fn test_rpc_verify_contract_src_mismatch() {
+        test_rpc(
+            "test_rpc_verify_contract_src_mismatch",
+            40200,
+            40201,
+            50200,
+            50201,
+            |ref mut peer_client,
+             ref mut convo_client,
+             ref mut peer_server,
+             ref mut convo_server| {
+                convo_client.new_verifycontractsrc(
+                    StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R")
+                        .unwrap(),
+                    "hello-world".try_into().unwrap(),
+                    "(define-read-only (ro-test) (ok 1))".to_string(),
+                    None,
+                )
+            },
+            |ref http_request, ref http_response, ref mut peer_client, ref mut peer_server| {
+                let req_md = http_request.metadata().clone();
+                match http_response {
+                    HttpResponseType::VerifyContractSrc(response_md, data) => {
+                        assert!(!data.source_match);
+                        assert!(!data.ast_match);
+                        assert!(data.analysis_ok);
+                        assert!(data.source_hash != data.deployed_source_hash);
+                        true
+                    }
+                    _ => {
+                        error!("Invalid response; {:?}", &http_response);
+                        false
+                    }
+                }
+            },
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_contract_src() {
+        assert_eq!(
+            ConversationHttp::canonicalize_contract_src("(define-data-var a int 1)  \r\n(a)\t\n\n"),
+            "(define-data-var a int 1)\n(a)"
+        );
+        assert_eq!(
+            ConversationHttp::canonicalize_contract_src(TEST_CONTRACT),
+            ConversationHttp::canonicalize_contract_src(&TEST_CONTRACT.replace("\n", "\r\n"))
+        );
+    }
+
+    /// Instantiate a chainstate whose genesis block deploys TEST_CONTRACT as
+    /// `ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world`, and get its chain tip.
+    fn instantiate_chainstate_with_test_contract(
+        test_name: &str,
+    ) -> (StacksChainState, StacksBlockId) {
+        let path = chainstate_path(test_name);
+        if fs::metadata(&path).is_ok() {
+            fs::remove_dir_all(&path).unwrap();
+        }
+
+        let contract_id = QualifiedContractIdentifier::parse(
+            "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world",
+        )
+        .unwrap();
+        let deploy_test_contract = move |clarity_tx: &mut ClarityTx| {
+            clarity_tx.connection().as_transaction(|tx| {
+                let (ast, analysis) = tx
+                    .analyze_smart_contract(&contract_id, TEST_CONTRACT)
+                    .unwrap();
+                tx.initialize_smart_contract(&contract_id, &ast, TEST_CONTRACT, |_, _| false)
+                    .unwrap();
+                tx.save_analysis(&contract_id, &analysis).unwrap();
+            });
+        };
+
+        let mut boot_data = ChainStateBootData {
+            initial_balances: vec![],
+            post_flight_callback: Some(Box::new(deploy_test_contract)),
+            first_burnchain_block_hash: BurnchainHeaderHash::zero(),
+            first_burnchain_block_height: 0,
+            first_burnchain_block_timestamp: 0,
+            pox_constants: PoxConstants::testnet_default(),
+            get_bulk_initial_lockups: None,
+            get_bulk_initial_balances: None,
+            get_bulk_initial_names: None,
+            get_bulk_initial_namespaces: None,
+        };
+        let (chainstate, _) = StacksChainState::open_and_exec(
+            false,
+            0x80000000,
+            &path,
+            Some(&mut boot_data),
+            ExecutionCost::max_value(),
+        )
+        .unwrap();
+
+        let tip = StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        );
+        (chainstate, tip)
+    }
+
+    /// Run a contract source verification request through its handler, and parse the reply.
+    fn run_verify_contract_src(
+        sortdb: &SortitionDB,
+        chainstate: &mut StacksChainState,
+        tip: &StacksBlockId,
+        contract_name: &str,
+        source: &str,
+    ) -> HttpResponseType {
+        let contract_addr =
+            StacksAddress::from_string("ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R").unwrap();
+        let contract_name: ContractName = contract_name.try_into().unwrap();
+        let request = HttpRequestType::VerifyContractSrc(
+            HttpRequestMetadata::new("127.0.0.1".to_string(), 20443),
+            contract_addr.clone(),
+            contract_name.clone(),
+            source.to_string(),
+            None,
+        );
+
+        let mut http = StacksHttp::new("127.0.0.1:20443".parse().unwrap());
+        let mut reply = vec![];
+        ConversationHttp::handle_verify_contract_src(
+            &mut http,
+            &mut reply,
+            &request,
+            sortdb,
+            chainstate,
+            tip,
+            &contract_addr,
+            &contract_name,
+            source,
+            None,
+            &ConnectionOptions::default(),
+        )
+        .unwrap();
+
+        match StacksHttp::parse_response(&request.request_path(), &reply).unwrap() {
+            StacksHttpMessage::Response(response) => response,
+            StacksHttpMessage::Request(request) => {
+                panic!("Expected a response, got {:?}", &request)
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_verify_contract_src() {
+        let (mut chainstate, tip) =
+            instantiate_chainstate_with_test_contract("test_handle_verify_contract_src");
+        let sortdb = SortitionDB::connect_test(0, &BurnchainHeaderHash::zero()).unwrap();
+
+        // the deployed source, with different line endings and trailing whitespace
+        let source = format!("{}  \r\n\r\n", TEST_CONTRACT.replace("\n", "\r\n"));
+        match run_verify_contract_src(&sortdb, &mut chainstate, &tip, "hello-world", &source) {
+            HttpResponseType::VerifyContractSrc(_, data) => {
+                assert_eq!(
+                    data.contract_id,
+                    "ST2DS4MSWSGJ3W9FBC6BVT0Y92S345HY8N3T6AV7R.hello-world"
+                );
+                assert_eq!(data.tip, tip);
+                assert!(data.source_match);
+                assert!(data.ast_match);
+                assert!(data.analysis_ok);
+                assert_eq!(data.source_hash, data.deployed_source_hash);
+                assert!(data.cause.is_none());
+            }
+            resp => panic!("Invalid response: {:?}", &resp),
+        }
+
+        // the same contract, but with comments and extra whitespace between tokens: the source
+        // doesn't match, but the AST does
+        let source = format!(
+            ";; hello world\n{}",
+            TEST_CONTRACT.replace(
+                "(define-data-var bar int 0)",
+                "(define-data-var  bar   int 0) ;; counter"
+            )
+        );
+        match run_verify_contract_src(&sortdb, &mut chainstate, &tip, "hello-world", &source) {
+            HttpResponseType::VerifyContractSrc(_, data) => {
+                assert!(!data.source_match);
+                assert!(data.ast_match);
+                assert!(data.analysis_ok);
+                assert!(data.source_hash != data.deployed_source_hash);
+                assert!(data.cause.is_none());
+            }
+            resp => panic!("Invalid response: {:?}", &resp),
+        }
+
+        // no such contract
+        match run_verify_contract_src(&sortdb, &mut chainstate, &tip, "goodbye-world", &source) {
+            HttpResponseType::NotFound(_, msg) => {
+                assert_eq!(msg, "No contract source data found");
+            }
+            resp => panic!("Invalid response: {:?}", &resp),
+        }
+    }
+
+    #[test]
+    fn test_clarity_value_conversion() {
+        let int_hex = "0000000000000000000000000000000001";
+        let data = ClarityValueResponse::from_value(Value::Int(1)).unwrap();
+        assert_eq!(data.hex, int_hex);
+        assert_eq!(data.repr, "1");
+        assert_eq!(
+            ClarityValueResponse::from_hex(&format!("0x{}", int_hex)).unwrap(),
+            data
+        );
+
+        let value = Value::from(
+            TupleData::from_data(vec![
+                (
+                    "a".into(),
+                    Value::list_from(vec![Value::UInt(1), Value::UInt(2)]).unwrap(),
+                ),
+                (
+                    "b".into(),
+                    Value::string_ascii_from_bytes(b"hi".to_vec()).unwrap(),
+                ),
+            ])
+            .unwrap(),
+        );
+        let data = ClarityValueResponse::from_value(value.clone()).unwrap();
+        assert_eq!(data.hex, ClaritySerializable::serialize(&value));
+        assert_eq!(
+            ClarityValueResponse::from_hex(&data.hex).unwrap().value,
+            value
+        );
+
+        // a list whose items have different types
+        let bad_list = Value::Sequence(SequenceData::List(ListData {
+            data: vec![Value::Int(1), Value::UInt(1)],
+            type_signature: ListTypeData::new_list(TypeSignature::IntType, 2).unwrap(),
+        }));
+        assert!(ClarityValueResponse::from_value(bad_list).is_err());
+
+        assert!(ClarityValueResponse::from_hex("0x").is_err());
+        assert!(ClarityValueResponse::from_hex("zz").is_err());
+        assert!(ClarityValueResponse::from_hex(&format!("{}00", int_hex)).is_err());
+    }
+
+    #[test]
+    fn test_rpc_response_cache() {
+        let md = HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true);
+        let entry = MapEntryResponse {
+            data: "0x09".to_string(),
+            marf_proof: None,
+        };
+        let response = HttpResponseType::GetMapEntry(md.clone(), entry.clone());
+        let sort_tip = SortitionId([0x01; 32]);
+        let tip = StacksBlockId([0x02; 32]);
+        let other_tip = StacksBlockId([0x03; 32]);
+
+        let mut cache = RPCResponseCache::new(2);
+        cache.refresh(&sort_tip, &tip);
+        assert!(cache.get("/v2/map_entry/a", &tip, md.clone()).is_none());
+
+        cache.insert("/v2/map_entry/a".to_string(), &tip, &response);
+        assert_eq!(
+            cache.get("/v2/map_entry/a", &tip, md.clone()),
+            Some(response.clone())
+        );
+        assert!(cache
+            .get("/v2/map_entry/a", &other_tip, md.clone())
+            .is_none());
+        assert!(cache.get("/v2/map_entry/b", &tip, md.clone()).is_none());
+
+        // errors are not cached
+        cache.insert(
+            "/v2/map_entry/b".to_string(),
+            &tip,
+            &HttpResponseType::NotFound(md.clone(), "Chain tip not found".into()),
+        );
+        assert_eq!(cache.len(), 1);
+
+        // nothing is cached once the cache is full
+        cache.insert("/v2/map_entry/b".to_string(), &other_tip, &response);
+        cache.insert("/v2/map_entry/c".to_string(), &tip, &response);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("/v2/map_entry/c", &tip, md.clone()).is_none());
+
+        // refreshing at the same tips keeps the cache; a new tip of either chain clears it
+        cache.refresh(&sort_tip, &tip);
+        assert_eq!(cache.len(), 2);
+        cache.refresh(&sort_tip, &other_tip);
+        assert_eq!(cache.len(), 0);
+        cache.insert("/v2/map_entry/a".to_string(), &tip, &response);
+        cache.refresh(&SortitionId([0x04; 32]), &other_tip);
+        assert_eq!(cache.len(), 0);
+
+        // a cache of size 0 caches nothing
+        let mut cache = RPCResponseCache::new(0);
+        cache.insert("/v2/map_entry/a".to_string(), &tip, &response);
+        assert!(cache.get("/v2/map_entry/a", &tip, md.clone()).is_none());
+    }
+
+    #[test]
+    fn test_rpc_idempotency_cache() {
+        let md = HttpResponseMetadata::new(HttpVersion::Http11, 123, None, true);
+        let txid_1 = Txid([0x01; 32]);
+        let txid_2 = Txid([0x02; 32]);
+        let accepted = HttpResponseType::TransactionID(md.clone(), txid_1.clone());
+        let rejected = HttpResponseType::BadRequestJSON(
+            md.clone(),
+            json!({"txid": txid_2.to_hex(), "reason": "BadNonce"}),
+        );
+
+        let mut cache = RPCIdempotencyCache::new(100, 2);
+        assert!(cache.get("a", &txid_1, 1000).is_none());
+
+        cache.insert("a".to_string(), &txid_1, &accepted, 1000);
+        cache.insert("b".to_string(), &txid_2, &rejected, 1010);
+        assert_eq!(
+            cache.get("a", &txid_1, 1050).unwrap(),
+            &RPCSubmissionResponse::Accepted(txid_1.clone())
+        );
+        assert_eq!(
+            cache
+                .get("b", &txid_2, 1050)
+                .unwrap()
+                .to_response(md.clone()),
+            rejected
+        );
+
+        // a key only replays the response to the transaction it was submitted with
+        assert!(cache.get("a", &txid_2, 1050).is_none());
+        assert!(cache.get("b", &txid_1, 1050).is_none());
+
+        // a remembered submission keeps its first response
+        cache.insert("a".to_string(), &txid_1, &rejected, 1050);
+        assert_eq!(
+            cache.get("a", &txid_1, 1050).unwrap(),
+            &RPCSubmissionResponse::Accepted(txid_1.clone())
+        );
+
+        // other errors are not remembered
+        cache.insert(
+            "c".to_string(),
+            &txid_1,
+            &HttpResponseType::ServerError(md.clone(), "Failed to load Stacks chain tip".into()),
+            1050,
+        );
+        assert!(cache.get("c", &txid_1, 1050).is_none());
+
+        // the oldest submission is forgotten once the cache is full, even if another client
+        // reuses its key for a different transaction
+        cache.insert("b".to_string(), &txid_1, &accepted, 1060);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a", &txid_1, 1060).is_none());
+        assert!(cache.get("b", &txid_2, 1060).is_some());
+        assert!(cache.get("b", &txid_1, 1060).is_some());
+
+        // submissions expire after the TTL
+        assert!(cache.get("b", &txid_2, 1110).is_none());
+        assert!(cache.get("b", &txid_1, 1110).is_some());
+        assert!(cache.get("b", &txid_1, 1160).is_none());
+        assert_eq!(cache.len(), 0);
+
+        // a TTL of 0 remembers nothing
+        let mut cache = RPCIdempotencyCache::new(0, 2);
+        cache.insert("a".to_string(), &txid_1, &accepted, 1000);
+        assert!(cache.get("a", &txid_1, 1000).is_none());
+    }
+
+    #[test]
+    fn test_validate_contract_call() {
+        let (_, analysis) = analysis::mem_type_check(
+            "(define-trait trait-1 ((f () (response uint uint))))
+             (define-public (call (target <trait-1>) (amount uint)) (ok amount))
+             (define-read-only (greet (name (string-ascii 5))) name)
+             (define-private (hidden) true)",
+        )
+        .unwrap();
+        let (_, implementer) = analysis::mem_type_check("(define-public (f) (ok u1))").unwrap();
+        let (_, non_implementer) = analysis::mem_type_check("(define-public (g) (ok u1))").unwrap();
+
+        let implementer_id =
+            QualifiedContractIdentifier::parse("S1G2081040G2081040G2081040G208105NK8PE5.impl")
+                .unwrap();
+        let non_implementer_id =
+            QualifiedContractIdentifier::parse("S1G2081040G2081040G2081040G208105NK8PE5.other")
+                .unwrap();
+        let load_analysis = |contract_id: &QualifiedContractIdentifier| {
+            if *contract_id == analysis.contract_identifier {
+                Some(analysis.clone())
+            } else if *contract_id == implementer_id {
+                Some(implementer.clone())
+            } else if *contract_id == non_implementer_id {
+                Some(non_implementer.clone())
+            } else {
+                None
+            }
+        };
+        let validate = |function_name: &str, args: Vec<Value>| {
+            let args: Vec<String> = args
+                .iter()
+                .map(|arg| format!("0x{}", ClaritySerializable::serialize(arg)))
+                .collect();
+            ConversationHttp::validate_contract_call(
+                &analysis,
+                &ClarityName::try_from(function_name.to_string()).unwrap(),
+                &args,
+                load_analysis,
+            )
+        };
+        let hello = Value::string_ascii_from_bytes(b"hello".to_vec()).unwrap();
+
+        let res = validate("greet", vec![hello.clone()]);
+        assert!(res.valid);
+        assert_eq!(
+            res.arguments,
+            vec![format!("0x{}", ClaritySerializable::serialize(&hello))]
+        );
+        assert!(res.errors.is_empty());
+
+        // arguments are normalized
+        let res = ConversationHttp::validate_contract_call(
+            &analysis,
+            &ClarityName::try_from("greet".to_string()).unwrap(),
+            &[ClaritySerializable::serialize(&hello).to_uppercase()],
+            load_analysis,
+        );
+        assert!(res.valid);
+        assert_eq!(
+            res.arguments,
+            vec![format!("0x{}", ClaritySerializable::serialize(&hello))]
+        );
+
+        let res = validate(
+            "call",
+            vec![
+                Value::from(PrincipalData::Contract(implementer_id.clone())),
+                Value::UInt(1),
+            ],
+        );
+        assert!(res.valid);
+
+        let res = validate("greet", vec![Value::Int(1)]);
+        assert!(!res.valid);
+        assert!(res.arguments.is_empty());
+        assert_eq!(
+            res.errors,
+            vec![ContractCallArgumentError {
+                index: Some(0),
+                name: Some("name".to_string()),
+                expected_type: Some("(string-ascii 5)".to_string()),
+                message: "Expected (string-ascii 5), got 1".to_string(),
+            }]
+        );
+
+        let res = validate("greet", vec![hello.clone(), hello.clone()]);
+        assert_eq!(res.errors.len(), 1);
+        assert_eq!(res.errors[0].index, None);
+        assert_eq!(res.errors[0].message, "Expected 1 arguments, got 2");
+
+        let res = validate("hidden", vec![]);
+        assert_eq!(res.errors[0].message, "Function 'hidden' is private");
+        let res = validate("missing", vec![]);
+        assert_eq!(
+            res.errors[0].message,
+            "No such public or read-only function 'missing'"
+        );
+
+        let res = validate(
+            "call",
+            vec![
+                Value::from(PrincipalData::Contract(non_implementer_id.clone())),
+                Value::Int(1),
+            ],
+        );
+        assert_eq!(res.errors.len(), 2);
+        assert_eq!(res.errors[0].index, Some(0));
+        assert!(res.errors[0].message.contains("does not implement trait"));
+        assert_eq!(res.errors[1].index, Some(1));
+
+        let res = validate("call", vec![Value::UInt(1), Value::UInt(1)]);
+        assert!(res.errors[0]
+            .message
+            .starts_with("Expected a contract implementing trait"));
+
+        let res = ConversationHttp::validate_contract_call(
+            &analysis,
+            &ClarityName::try_from("greet".to_string()).unwrap(),
+            &["0xzz".to_string()],
+            load_analysis,
+        );
+        assert_eq!(res.errors[0].index, Some(0));
+        assert_eq!(res.errors[0].message, "Bad hex string");
+    }
+
     #[test]
     #[ignore]
     fn test_rpc_getattachmentsinv_limit_reached() {
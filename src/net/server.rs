@@ -21,6 +21,7 @@ use std::io::Error as io_error;
 use std::io::ErrorKind;
 use std::io::{Read, Write};
 
+use std::cmp;
 use std::collections::HashMap;
 use std::collections::VecDeque;
 
@@ -35,6 +36,8 @@ use net::atlas::AtlasDB;
 use net::connection::*;
 use net::db::*;
 use net::http::*;
+use net::jobs::QueryJobs;
+use net::neighbors::NeighborWalkStats;
 use net::p2p::PeerMap;
 use net::poll::*;
 use net::rpc::*;
@@ -60,13 +63,14 @@ pub struct HttpPeer {
 
     // ongoing http conversations (either they reached out to us, or we to them)
     pub peers: HashMap<usize, ConversationHttp>,
-    pub sockets: HashMap<usize, mio_net::TcpStream>,
+    pub sockets: HashMap<usize, NetSocket>,
 
     // outbound connections that are pending connection
     pub connecting: HashMap<
         usize,
         (
             mio_net::TcpStream,
+            SocketAddr,
             Option<UrlString>,
             Option<HttpRequestType>,
             u64,
@@ -109,18 +113,35 @@ impl HttpPeer {
         self.http_server_handle = h;
     }
 
-    /// Is there a HTTP conversation open to this data_url that is not in progress?
-    pub fn find_free_conversation(&self, data_url: &UrlString) -> Option<usize> {
+    /// Is there an outbound HTTP conversation open to this address that is not in progress?
+    /// Data URLs served from the same address share their kept-alive connections.
+    pub fn find_free_conversation(&self, addr: &SocketAddr) -> Option<usize> {
         for (event_id, convo) in self.peers.iter() {
-            if let Some(ref url) = convo.get_url() {
-                if *url == data_url && !convo.is_request_inflight() {
-                    return Some(*event_id);
-                }
+            if convo.get_url().is_some()
+                && convo.get_peer_addr() == addr
+                && !convo.is_request_inflight()
+            {
+                return Some(*event_id);
             }
         }
         None
     }
 
+    /// How many outbound HTTP connections are open (or opening) to this address?
+    fn count_outbound_conversations(&self, addr: &SocketAddr) -> u64 {
+        let num_connected = self
+            .peers
+            .values()
+            .filter(|convo| convo.get_url().is_some() && convo.get_peer_addr() == addr)
+            .count();
+        let num_connecting = self
+            .connecting
+            .values()
+            .filter(|(_, peer_addr, ..)| peer_addr == addr)
+            .count();
+        (num_connected + num_connecting) as u64
+    }
+
     /// Get a mut ref to a conversation
     pub fn get_conversation(&mut self, event_id: usize) -> Option<&mut ConversationHttp> {
         self.peers.get_mut(&event_id)
@@ -130,10 +151,7 @@ impl HttpPeer {
     pub fn get_conversation_and_socket(
         &mut self,
         event_id: usize,
-    ) -> (
-        Option<&mut ConversationHttp>,
-        Option<&mut mio::net::TcpStream>,
-    ) {
+    ) -> (Option<&mut ConversationHttp>, Option<&mut NetSocket>) {
         (
             self.peers.get_mut(&event_id),
             self.sockets.get_mut(&event_id),
@@ -151,7 +169,7 @@ impl HttpPeer {
         addr: SocketAddr,
         request: Option<HttpRequestType>,
     ) -> Result<usize, net_error> {
-        if let Some(event_id) = self.find_free_conversation(&data_url) {
+        if let Some(event_id) = self.find_free_conversation(&addr) {
             let http_nk = NeighborKey {
                 peer_version: self.burnchain.peer_version,
                 network_id: self.network_id,
@@ -161,6 +179,15 @@ impl HttpPeer {
             return Err(net_error::AlreadyConnected(event_id, http_nk));
         }
 
+        let num_outbound = self.count_outbound_conversations(&addr);
+        if num_outbound >= self.connection_opts.max_http_connections_per_host {
+            debug!(
+                "HTTP: all {} connections to {:?} are busy (max {})",
+                num_outbound, &addr, self.connection_opts.max_http_connections_per_host
+            );
+            return Err(net_error::TooManyPeers);
+        }
+
         let sock = NetworkState::connect(&addr)?;
         let hint_event_id = network_state.next_event_id()?;
         let next_event_id =
@@ -168,7 +195,7 @@ impl HttpPeer {
 
         self.connecting.insert(
             next_event_id,
-            (sock, Some(data_url), request, get_epoch_time_secs()),
+            (sock, addr, Some(data_url), request, get_epoch_time_secs()),
         );
         Ok(next_event_id)
     }
@@ -231,15 +258,16 @@ impl HttpPeer {
         network_state: &mut NetworkState,
         chainstate: &mut StacksChainState,
         event_id: usize,
-        mut socket: mio_net::TcpStream,
+        socket: mio_net::TcpStream,
         outbound_url: Option<UrlString>,
         initial_request: Option<HttpRequestType>,
     ) -> Result<(), net_error> {
+        let mut socket = network_state.start_socket(event_id, socket)?;
         let client_addr = match socket.peer_addr() {
             Ok(addr) => addr,
             Err(e) => {
                 warn!("Failed to get peer address of {:?}: {:?}", &socket, &e);
-                let _ = network_state.deregister(event_id, &socket);
+                let _ = network_state.deregister_socket(event_id, socket);
                 return Err(net_error::SocketError);
             }
         };
@@ -247,7 +275,7 @@ impl HttpPeer {
         match self.can_register_http(&client_addr, outbound_url.as_ref()) {
             Ok(_) => {}
             Err(e) => {
-                let _ = network_state.deregister(event_id, &socket);
+                let _ = network_state.deregister_socket(event_id, socket);
                 return Err(e);
             }
         }
@@ -279,7 +307,7 @@ impl HttpPeer {
             match new_convo.send_request(request) {
                 Ok(_) => {}
                 Err(e) => {
-                    let _ = network_state.deregister(event_id, &socket);
+                    let _ = network_state.deregister_socket(event_id, socket);
                     return Err(e);
                 }
             }
@@ -288,7 +316,7 @@ impl HttpPeer {
             match HttpPeer::saturate_http_socket(&mut socket, &mut new_convo, chainstate) {
                 Ok(_) => {}
                 Err(e) => {
-                    let _ = network_state.deregister(event_id, &socket);
+                    let _ = network_state.deregister_socket(event_id, socket);
                     return Err(e);
                 }
             }
@@ -306,7 +334,7 @@ impl HttpPeer {
         match self.sockets.remove(&event_id) {
             None => {}
             Some(sock) => {
-                let _ = network_state.deregister(event_id, &sock);
+                let _ = network_state.deregister_socket(event_id, sock);
             }
         }
         match self.connecting.remove(&event_id) {
@@ -321,7 +349,7 @@ impl HttpPeer {
     fn disconnect_unresponsive(&mut self, network_state: &mut NetworkState) -> () {
         let now = get_epoch_time_secs();
         let mut to_remove = vec![];
-        for (event_id, (socket, _, _, ts)) in self.connecting.iter() {
+        for (event_id, (socket, _, _, _, ts)) in self.connecting.iter() {
             if ts + self.connection_opts.connect_timeout < now {
                 debug!("Disconnect connecting HTTP peer {:?}", &socket);
                 to_remove.push(*event_id);
@@ -341,6 +369,18 @@ impl HttpPeer {
                 last_response_time = convo.get_connection_time();
             }
 
+            if convo.get_url().is_some()
+                && !convo.is_request_inflight()
+                && cmp::max(last_request_time, last_response_time)
+                    + self.connection_opts.http_client_idle_timeout
+                    < now
+            {
+                // kept-alive outbound connection that we haven't reused in a while
+                debug!("Removing idle outbound HTTP conversation {:?}", convo);
+                to_remove.push(*event_id);
+                continue;
+            }
+
             if last_request_time + self.connection_opts.timeout < now
                 && last_response_time + self.connection_opts.idle_timeout < now
             {
@@ -358,7 +398,7 @@ impl HttpPeer {
     /// Saturate a conversation's socket -- either sends the whole request, or fills the socket
     /// buffer.
     pub fn saturate_http_socket(
-        client_sock: &mut mio::net::TcpStream,
+        client_sock: &mut NetSocket,
         convo: &mut ConversationHttp,
         chainstate: &mut StacksChainState,
     ) -> Result<(), net_error> {
@@ -437,12 +477,17 @@ impl HttpPeer {
         chain_view: &BurnchainView,
         peers: &PeerMap,
         sortdb: &SortitionDB,
-        peerdb: &PeerDB,
+        peerdb: &mut PeerDB,
+        walk_stats: &NeighborWalkStats,
+        block_fetches: &mut HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        query_jobs: &mut QueryJobs,
+        rpc_cache: &mut RPCResponseCache,
+        idempotency_cache: &mut RPCIdempotencyCache,
         atlasdb: &mut AtlasDB,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
         event_id: usize,
-        client_sock: &mut mio_net::TcpStream,
+        client_sock: &mut NetSocket,
         convo: &mut ConversationHttp,
         handler_args: &RPCHandlerArgs,
     ) -> Result<(bool, Vec<StacksMessageType>), net_error> {
@@ -513,6 +558,11 @@ impl HttpPeer {
             peers,
             sortdb,
             peerdb,
+            walk_stats,
+            block_fetches,
+            query_jobs,
+            rpc_cache,
+            idempotency_cache,
             atlasdb,
             chainstate,
             mempool,
@@ -521,8 +571,10 @@ impl HttpPeer {
             Ok(msgs) => msgs,
             Err(e) => {
                 debug!(
-                    "Failed to converse HTTP on event {} (socket {:?}): {:?}",
-                    event_id, &client_sock, &e
+                    "Failed to converse HTTP on event {} (socket {:?}): {}",
+                    event_id,
+                    &client_sock,
+                    e.with_context(None, None)
                 );
                 convo_dead = true;
                 vec![]
@@ -561,7 +613,7 @@ impl HttpPeer {
     ) -> () {
         for event_id in poll_state.ready.iter() {
             if self.connecting.contains_key(event_id) {
-                let (socket, data_url, initial_request_opt, _) =
+                let (socket, _, data_url, initial_request_opt, _) =
                     self.connecting.remove(event_id).unwrap();
                 debug!("HTTP event {} connected ({:?})", event_id, &data_url);
 
@@ -591,7 +643,12 @@ impl HttpPeer {
         poll_state: &mut NetworkPollState,
         peers: &PeerMap,
         sortdb: &SortitionDB,
-        peerdb: &PeerDB,
+        peerdb: &mut PeerDB,
+        walk_stats: &NeighborWalkStats,
+        block_fetches: &mut HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        query_jobs: &mut QueryJobs,
+        rpc_cache: &mut RPCResponseCache,
+        idempotency_cache: &mut RPCIdempotencyCache,
         atlasdb: &mut AtlasDB,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
@@ -623,6 +680,11 @@ impl HttpPeer {
                         peers,
                         sortdb,
                         peerdb,
+                        walk_stats,
+                        block_fetches,
+                        query_jobs,
+                        rpc_cache,
+                        idempotency_cache,
                         atlasdb,
                         chainstate,
                         mempool,
@@ -690,7 +752,12 @@ impl HttpPeer {
         new_chain_view: BurnchainView,
         p2p_peers: &PeerMap,
         sortdb: &SortitionDB,
-        peerdb: &PeerDB,
+        peerdb: &mut PeerDB,
+        walk_stats: &NeighborWalkStats,
+        block_fetches: &mut HashMap<(StacksBlockId, bool), RPCBlockFetchData>,
+        query_jobs: &mut QueryJobs,
+        rpc_cache: &mut RPCResponseCache,
+        idempotency_cache: &mut RPCIdempotencyCache,
         atlasdb: &mut AtlasDB,
         chainstate: &mut StacksChainState,
         mempool: &mut MemPoolDB,
@@ -712,6 +779,11 @@ impl HttpPeer {
             p2p_peers,
             sortdb,
             peerdb,
+            walk_stats,
+            block_fetches,
+            query_jobs,
+            rpc_cache,
+            idempotency_cache,
             atlasdb,
             chainstate,
             mempool,
@@ -932,6 +1004,38 @@ mod test {
         );
     }
 
+    #[test]
+    #[cfg(feature = "async_net")]
+    fn test_http_getinfo_async_io() {
+        let mut conn_opts = ConnectionOptions::default();
+        conn_opts.async_io_threads = 2;
+
+        test_http_server(
+            "test_http_getinfo_async_io",
+            51102,
+            51103,
+            conn_opts,
+            1,
+            0,
+            |client_id, _| {
+                let mut request = HttpRequestType::GetInfo(HttpRequestMetadata::from_host(
+                    PeerHost::from_host_port("127.0.0.1".to_string(), 51103),
+                ));
+                request.metadata_mut().keep_alive = false;
+
+                let request_bytes = StacksHttp::serialize_request(&request).unwrap();
+                request_bytes
+            },
+            |client_id, http_response_bytes_res| {
+                // should be a PeerInfo
+                let http_response_bytes = http_response_bytes_res.unwrap();
+                let response =
+                    StacksHttp::parse_response("/v2/info", &http_response_bytes).unwrap();
+                true
+            },
+        );
+    }
+
     #[test]
     #[ignore]
     fn test_http_10_threads_getinfo() {
@@ -1238,6 +1342,7 @@ mod test {
                     )),
                     signed_contract_tx,
                     None,
+                    None,
                 );
                 request.metadata_mut().keep_alive = false;
 
@@ -512,6 +512,21 @@ pub fn tx_begin_immediate<'a>(conn: &'a mut Connection) -> Result<DBTx<'a>, Erro
     Ok(tx)
 }
 
+/// Begin a deferred-mode read transaction on a connection we only have a shared reference to.
+/// Every query on `conn` while the transaction is open reads from the same snapshot of the
+/// database, even if another connection commits in the meantime.  Returns None if `conn` is
+/// already in a transaction, since its queries already share that transaction's snapshot.
+/// Don't hold this for long: unless the DB is in WAL mode, writers can't commit until it's
+/// dropped.
+pub fn tx_begin_read<'a>(conn: &'a Connection) -> Result<Option<DBTx<'a>>, Error> {
+    if !conn.is_autocommit() {
+        return Ok(None);
+    }
+    conn.busy_handler(Some(tx_busy_handler))?;
+    let tx = conn.unchecked_transaction()?;
+    Ok(Some(tx))
+}
+
 /// Get the ancestor block hash of a block of a given height, given a descendent block hash.
 pub fn get_ancestor_block_hash<T: MarfTrieId>(
     index: &MARF<T>,
@@ -746,3 +761,49 @@ impl<'a, C: Clone, T: MarfTrieId> Drop for IndexDBTx<'a, C, T> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rusqlite::OpenFlags;
+
+    #[test]
+    fn test_tx_begin_read() {
+        let path = "/tmp/test_tx_begin_read.sqlite";
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path).unwrap();
+        }
+
+        let writer = Connection::open(path).unwrap();
+        writer
+            .execute_batch(
+                "PRAGMA journal_mode = WAL; CREATE TABLE tip(height INTEGER NOT NULL); INSERT INTO tip (height) VALUES (1);",
+            )
+            .unwrap();
+        let reader = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).unwrap();
+        let get_height = |conn: &Connection| -> i64 {
+            conn.query_row("SELECT height FROM tip", NO_PARAMS, |row| row.get(0))
+                .unwrap()
+        };
+
+        {
+            let tx = tx_begin_read(&reader).unwrap();
+            assert!(tx.is_some());
+            assert_eq!(get_height(&reader), 1);
+
+            // nested read transactions reuse the open one
+            assert!(tx_begin_read(&reader).unwrap().is_none());
+
+            // the reader doesn't see the tip advance while its read transaction is open...
+            writer
+                .execute("UPDATE tip SET height = 2", NO_PARAMS)
+                .unwrap();
+            assert_eq!(get_height(&writer), 2);
+            assert_eq!(get_height(&reader), 1);
+        }
+
+        // ...but does once it closes
+        assert!(reader.is_autocommit());
+        assert_eq!(get_height(&reader), 2);
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Free disk space monitoring.
+//!
+//! SQLite does not always recover gracefully from running out of disk space mid-write, so a node
+//! should stop taking on new data well before its disks fill up.  A `DiskWatcher` periodically
+//! checks the free space on the filesystems holding each of the node's databases, and maps the
+//! lowest of them to a `DiskSpaceLevel` via a `DiskSpacePolicy`.  Acting on the level is up to
+//! the caller.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use monitoring::update_disk_free_space;
+use util::get_epoch_time_secs;
+
+const MIB: u64 = 1024 * 1024;
+
+/// How degraded the node should be, from least to most severe.  Each level implies the ones
+/// below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiskSpaceLevel {
+    /// Plenty of free space
+    Normal,
+    /// Free space is getting low; warn the operator
+    Alert,
+    /// Stop accepting blocks and microblocks pushed to us
+    StopPushes,
+    /// Also pause Atlas attachment downloads
+    PauseDownloads,
+    /// Shut the node down cleanly before a write fails
+    Halt,
+}
+
+impl fmt::Display for DiskSpaceLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            DiskSpaceLevel::Normal => "normal",
+            DiskSpaceLevel::Alert => "alert",
+            DiskSpaceLevel::StopPushes => "stop-pushes",
+            DiskSpaceLevel::PauseDownloads => "pause-downloads",
+            DiskSpaceLevel::Halt => "halt",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The free space (in MiB) below which each level applies.  A threshold of 0 disables its level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskSpacePolicy {
+    pub check_interval: u64,
+    pub alert_free_mb: u64,
+    pub stop_pushes_free_mb: u64,
+    pub pause_downloads_free_mb: u64,
+    pub halt_free_mb: u64,
+}
+
+impl Default for DiskSpacePolicy {
+    fn default() -> DiskSpacePolicy {
+        DiskSpacePolicy {
+            check_interval: 60,            // how often to check free space, in seconds
+            alert_free_mb: 10240,          // warn below 10 GiB
+            stop_pushes_free_mb: 4096,     // refuse pushed blocks below 4 GiB
+            pause_downloads_free_mb: 2048, // pause Atlas downloads below 2 GiB
+            halt_free_mb: 1024,            // shut down below 1 GiB
+        }
+    }
+}
+
+impl DiskSpacePolicy {
+    /// The most severe level whose threshold `free_bytes` is below
+    pub fn level_for(&self, free_bytes: u64) -> DiskSpaceLevel {
+        let levels = [
+            (self.halt_free_mb, DiskSpaceLevel::Halt),
+            (self.pause_downloads_free_mb, DiskSpaceLevel::PauseDownloads),
+            (self.stop_pushes_free_mb, DiskSpaceLevel::StopPushes),
+            (self.alert_free_mb, DiskSpaceLevel::Alert),
+        ];
+        for (threshold_mb, level) in levels.iter() {
+            if free_bytes < threshold_mb.saturating_mul(MIB) {
+                return *level;
+            }
+        }
+        DiskSpaceLevel::Normal
+    }
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`.  If `path` does not
+/// exist yet (e.g. a database that has not been created), its nearest existing ancestor is used.
+#[cfg(unix)]
+pub fn get_free_space(path: &Path) -> io::Result<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    use libc;
+
+    let existing = path
+        .ancestors()
+        .find(|p| p.exists())
+        .unwrap_or_else(|| Path::new("."));
+    let c_path = CString::new(existing.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut stats: libc::statvfs = unsafe { ::std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok((stats.f_bavail as u64).saturating_mul(stats.f_frsize as u64))
+}
+
+#[cfg(not(unix))]
+pub fn get_free_space(_path: &Path) -> io::Result<u64> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "free space checks are not supported on this platform",
+    ))
+}
+
+/// Tracks the free space on the filesystems holding a set of paths.
+pub struct DiskWatcher {
+    paths: Vec<PathBuf>,
+    policy: DiskSpacePolicy,
+    last_checked_at: u64,
+    level: DiskSpaceLevel,
+}
+
+impl DiskWatcher {
+    pub fn new(paths: Vec<PathBuf>, policy: DiskSpacePolicy) -> DiskWatcher {
+        DiskWatcher {
+            paths,
+            policy,
+            last_checked_at: 0,
+            level: DiskSpaceLevel::Normal,
+        }
+    }
+
+    pub fn level(&self) -> DiskSpaceLevel {
+        self.level
+    }
+
+    /// Re-check free space if the check interval has passed, and return the current level.
+    /// Paths whose free space cannot be determined are skipped.
+    pub fn check(&mut self) -> DiskSpaceLevel {
+        let now = get_epoch_time_secs();
+        if self.last_checked_at + self.policy.check_interval > now {
+            return self.level;
+        }
+        self.last_checked_at = now;
+
+        let mut lowest: Option<(u64, &PathBuf)> = None;
+        for path in self.paths.iter() {
+            match get_free_space(path) {
+                Ok(free) => {
+                    if lowest
+                        .map(|(lowest_free, _)| free < lowest_free)
+                        .unwrap_or(true)
+                    {
+                        lowest = Some((free, path));
+                    }
+                }
+                Err(e) => {
+                    debug!("Failed to get free space for {}: {:?}", path.display(), &e);
+                }
+            }
+        }
+        let (free, path) = match lowest {
+            Some(lowest) => lowest,
+            None => {
+                return self.level;
+            }
+        };
+        update_disk_free_space(free as i64);
+
+        let level = self.policy.level_for(free);
+        if level > DiskSpaceLevel::Normal && level != self.level {
+            warn!(
+                "Low disk space: {} MiB free for {}; degrading to '{}'",
+                free / MIB,
+                path.display(),
+                level
+            );
+        } else if level == DiskSpaceLevel::Normal && self.level != DiskSpaceLevel::Normal {
+            info!(
+                "Disk space recovered: {} MiB free for {}",
+                free / MIB,
+                path.display()
+            );
+        }
+        self.level = level;
+        level
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disk_space_policy_levels() {
+        let policy = DiskSpacePolicy {
+            check_interval: 0,
+            alert_free_mb: 400,
+            stop_pushes_free_mb: 300,
+            pause_downloads_free_mb: 200,
+            halt_free_mb: 100,
+        };
+        assert_eq!(policy.level_for(500 * MIB), DiskSpaceLevel::Normal);
+        assert_eq!(policy.level_for(400 * MIB), DiskSpaceLevel::Normal);
+        assert_eq!(policy.level_for(400 * MIB - 1), DiskSpaceLevel::Alert);
+        assert_eq!(policy.level_for(250 * MIB), DiskSpaceLevel::StopPushes);
+        assert_eq!(policy.level_for(150 * MIB), DiskSpaceLevel::PauseDownloads);
+        assert_eq!(policy.level_for(0), DiskSpaceLevel::Halt);
+
+        // disabled levels are skipped
+        let policy = DiskSpacePolicy {
+            check_interval: 0,
+            alert_free_mb: 400,
+            stop_pushes_free_mb: 0,
+            pause_downloads_free_mb: 0,
+            halt_free_mb: 0,
+        };
+        assert_eq!(policy.level_for(0), DiskSpaceLevel::Alert);
+        assert_eq!(policy.level_for(500 * MIB), DiskSpaceLevel::Normal);
+    }
+
+    #[test]
+    fn test_disk_watcher() {
+        assert!(get_free_space(Path::new("/tmp/no/such/dir/db.sqlite")).unwrap() > 0);
+
+        let mut policy = DiskSpacePolicy::default();
+        policy.check_interval = 0;
+        policy.alert_free_mb = u64::MAX;
+        let mut watcher = DiskWatcher::new(vec![PathBuf::from("/tmp")], policy);
+        assert_eq!(watcher.level(), DiskSpaceLevel::Normal);
+        assert!(watcher.check() >= DiskSpaceLevel::Alert);
+    }
+}
@@ -21,6 +21,7 @@ pub mod macros;
 #[macro_use]
 pub mod db;
 pub mod boot;
+pub mod disk;
 pub mod hash;
 pub mod pair;
 pub mod pipe;
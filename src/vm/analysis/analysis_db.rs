@@ -64,6 +64,11 @@ impl<'a> AnalysisDatabase<'a> {
         self.store.rollback();
     }
 
+    /// Height of the Stacks block whose state this reads
+    pub fn get_current_block_height(&mut self) -> u32 {
+        self.store.get_current_block_height()
+    }
+
     pub fn storage_key() -> &'static str {
         "analysis"
     }
@@ -16,6 +16,8 @@
 
 use vm::analysis::types::{AnalysisPass, ContractAnalysis};
 use vm::functions::define::{DefineFunctions, DefineFunctionsParsed};
+#[cfg(feature = "clarity_extensions")]
+use vm::functions::extensions;
 use vm::functions::tuples;
 use vm::functions::NativeFunctions;
 use vm::representations::SymbolicExpressionType::{
@@ -49,6 +51,8 @@ pub enum Error {
     DefineTypeForbidden(DefineFunctions),
     VariableForbidden(NativeVariables),
     FunctionNotPermitted(NativeFunctions),
+    #[cfg(feature = "clarity_extensions")]
+    BuiltinExtensionNotPermitted(String),
     TraitReferencesForbidden,
     UnexpectedContractStructure,
 }
@@ -158,6 +162,17 @@ impl ArithmeticOnlyChecker {
             .map(|function| self.check_native_function(function, args))
     }
 
+    #[cfg(feature = "clarity_extensions")]
+    fn try_builtin_extension_check(&self, function: &str) -> Option<Result<(), Error>> {
+        extensions::lookup_builtin_extension(function)
+            .map(|_| Err(Error::BuiltinExtensionNotPermitted(function.to_string())))
+    }
+
+    #[cfg(not(feature = "clarity_extensions"))]
+    fn try_builtin_extension_check(&self, _function: &str) -> Option<Result<(), Error>> {
+        None
+    }
+
     fn check_native_function(
         &self,
         function: NativeFunctions,
@@ -262,6 +277,8 @@ impl ArithmeticOnlyChecker {
 
         if let Some(result) = self.try_native_function_check(function_name, args) {
             result
+        } else if let Some(result) = self.try_builtin_extension_check(function_name) {
+            result
         } else {
             // non-native function invocations are always okay, just check the args!
             self.check_all(args)
@@ -34,6 +34,7 @@ fn arithmetic_check(contract: &str) -> Result<(), Error> {
         contract_identifier,
         expressions,
         LimitedCostTracker::new_free(),
+        false,
     );
 
     ArithmeticOnlyChecker::run(&analysis)
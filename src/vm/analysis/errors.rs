@@ -29,6 +29,7 @@ pub enum CheckErrors {
     CostOverflow,
     CostBalanceExceeded(ExecutionCost, ExecutionCost),
     MemoryBalanceExceeded(u64, u64),
+    ExecutionTimeExpired,
     CostComputationFailed(String),
 
     ValueTooLarge,
@@ -245,6 +246,7 @@ impl From<CostErrors> for CheckErrors {
             CostErrors::CostContractLoadFailure => {
                 CheckErrors::CostComputationFailed("Failed to load cost contract".into())
             }
+            CostErrors::ExecutionTimeExpired => CheckErrors::ExecutionTimeExpired,
         }
     }
 }
@@ -314,6 +316,7 @@ impl DiagnosableError for CheckErrors {
             CheckErrors::CostOverflow => "contract execution cost overflowed cost counter".into(),
             CheckErrors::CostBalanceExceeded(a, b) => format!("contract execution cost exceeded budget: {:?} > {:?}", a, b),
             CheckErrors::MemoryBalanceExceeded(a, b) => format!("contract execution cost exceeded memory budget: {:?} > {:?}", a, b),
+            CheckErrors::ExecutionTimeExpired => "contract execution time expired".into(),
             CheckErrors::InvalidTypeDescription => "supplied type description is invalid".into(),
             CheckErrors::EmptyTuplesNotAllowed => "tuple types may not be empty".into(),
             CheckErrors::BadSyntaxExpectedListOfPairs => "bad syntax: function expects a list of pairs to bind names, e.g., ((name-0 a) (name-1 b) ...)".into(),
@@ -77,6 +77,7 @@ pub fn type_check(
         analysis_db,
         insert_contract,
         LimitedCostTracker::new_free(),
+        false,
     )
     .map_err(|(e, _cost_tracker)| e)
 }
@@ -87,11 +88,13 @@ pub fn run_analysis(
     analysis_db: &mut AnalysisDatabase,
     save_contract: bool,
     cost_tracker: LimitedCostTracker,
+    mainnet: bool,
 ) -> Result<ContractAnalysis, (CheckError, LimitedCostTracker)> {
     let mut contract_analysis = ContractAnalysis::new(
         contract_identifier.clone(),
         expressions.to_vec(),
         cost_tracker,
+        mainnet,
     );
     let result = analysis_db.execute(|db| {
         ReadOnlyChecker::run_pass(&mut contract_analysis, db)?;
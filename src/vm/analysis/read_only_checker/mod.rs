@@ -16,6 +16,8 @@
 
 use vm::analysis::types::{AnalysisPass, ContractAnalysis};
 use vm::functions::define::DefineFunctionsParsed;
+#[cfg(feature = "clarity_extensions")]
+use vm::functions::extensions;
 use vm::functions::tuples;
 use vm::functions::NativeFunctions;
 use vm::representations::SymbolicExpressionType::{
@@ -163,6 +165,28 @@ impl<'a, 'b> ReadOnlyChecker<'a, 'b> {
             .map(|function| self.check_native_function(&function, args))
     }
 
+    #[cfg(feature = "clarity_extensions")]
+    fn try_builtin_extension_check(
+        &mut self,
+        function: &str,
+        args: &[SymbolicExpression],
+    ) -> Option<CheckResult<bool>> {
+        let extension = extensions::lookup_builtin_extension(function)?;
+        Some(
+            self.check_all_read_only(args)
+                .map(|args_read_only| args_read_only && extension.read_only),
+        )
+    }
+
+    #[cfg(not(feature = "clarity_extensions"))]
+    fn try_builtin_extension_check(
+        &mut self,
+        _function: &str,
+        _args: &[SymbolicExpression],
+    ) -> Option<CheckResult<bool>> {
+        None
+    }
+
     fn check_native_function(
         &mut self,
         function: &NativeFunctions,
@@ -303,6 +327,8 @@ impl<'a, 'b> ReadOnlyChecker<'a, 'b> {
                 check_err.set_expressions(expression);
             }
             result
+        } else if let Some(result) = self.try_builtin_extension_check(function_name, args) {
+            result
         } else {
             let is_function_read_only = self
                 .defined_functions
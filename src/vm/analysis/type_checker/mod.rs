@@ -25,6 +25,8 @@ use vm::costs::{
     CostErrors, CostOverflowingMath, CostTracker, ExecutionCost, LimitedCostTracker,
 };
 use vm::functions::define::DefineFunctionsParsed;
+#[cfg(feature = "clarity_extensions")]
+use vm::functions::extensions;
 use vm::functions::NativeFunctions;
 use vm::representations::SymbolicExpressionType::{
     Atom, AtomValue, Field, List, LiteralValue, TraitReference,
@@ -75,6 +77,7 @@ pub struct TypeChecker<'a, 'b> {
     function_return_tracker: Option<Option<TypeSignature>>,
     db: &'a mut AnalysisDatabase<'b>,
     pub cost_track: LimitedCostTracker,
+    mainnet: bool,
 }
 
 impl CostTracker for TypeChecker<'_, '_> {
@@ -115,7 +118,7 @@ impl AnalysisPass for TypeChecker<'_, '_> {
         analysis_db: &mut AnalysisDatabase,
     ) -> CheckResult<()> {
         let cost_track = contract_analysis.take_contract_cost_tracker();
-        let mut command = TypeChecker::new(analysis_db, cost_track);
+        let mut command = TypeChecker::new(analysis_db, cost_track, contract_analysis.mainnet);
         // run the analysis, and replace the cost tracker whether or not the
         //   analysis succeeded.
         match command.run(contract_analysis) {
@@ -315,10 +318,12 @@ impl<'a, 'b> TypeChecker<'a, 'b> {
     fn new(
         db: &'a mut AnalysisDatabase<'b>,
         cost_track: LimitedCostTracker,
+        mainnet: bool,
     ) -> TypeChecker<'a, 'b> {
         Self {
             db,
             cost_track,
+            mainnet,
             contract_context: ContractContext::new(),
             function_return_tracker: None,
             type_map: TypeMap::new(),
@@ -610,10 +615,35 @@ impl<'a, 'b> TypeChecker<'a, 'b> {
             let typed_function = TypedNativeFunction::type_native_function(native_function);
             Some(typed_function.type_check_appliction(self, args, context))
         } else {
-            None
+            self.try_builtin_extension_check(function, args, context)
         }
     }
 
+    #[cfg(feature = "clarity_extensions")]
+    fn try_builtin_extension_check(
+        &mut self,
+        function: &str,
+        args: &[SymbolicExpression],
+        context: &TypingContext,
+    ) -> Option<TypeResult> {
+        let extension =
+            extensions::lookup_active_builtin_extension(function, self.mainnet, self.db)?;
+        Some(
+            self.type_check_all(args, context)
+                .and_then(|arg_types| (extension.type_check)(&arg_types)),
+        )
+    }
+
+    #[cfg(not(feature = "clarity_extensions"))]
+    fn try_builtin_extension_check(
+        &mut self,
+        _function: &str,
+        _args: &[SymbolicExpression],
+        _context: &TypingContext,
+    ) -> Option<TypeResult> {
+        None
+    }
+
     fn type_check_function_application(
         &mut self,
         expression: &[SymbolicExpression],
@@ -57,6 +57,9 @@ pub struct ContractAnalysis {
     pub type_map: Option<TypeMap>,
     #[serde(skip)]
     pub cost_track: Option<LimitedCostTracker>,
+    /// Whether the contract is analyzed for mainnet, where no builtin extension can be called
+    #[serde(skip)]
+    pub mainnet: bool,
 }
 
 impl ContractAnalysis {
@@ -64,6 +67,7 @@ impl ContractAnalysis {
         contract_identifier: QualifiedContractIdentifier,
         expressions: Vec<SymbolicExpression>,
         cost_track: LimitedCostTracker,
+        mainnet: bool,
     ) -> ContractAnalysis {
         ContractAnalysis {
             contract_identifier,
@@ -82,6 +86,7 @@ impl ContractAnalysis {
             non_fungible_tokens: BTreeMap::new(),
             cost_track: Some(cost_track),
             is_cost_contract_eligible: false,
+            mainnet,
         }
     }
 
@@ -29,6 +29,7 @@ pub enum ParseErrors {
     CostOverflow,
     CostBalanceExceeded(ExecutionCost, ExecutionCost),
     MemoryBalanceExceeded(u64, u64),
+    ExecutionTimeExpired,
     TooManyExpressions,
     ExpressionStackDepthTooDeep,
     FailedCapturingInput,
@@ -141,6 +142,7 @@ impl From<CostErrors> for ParseError {
             CostErrors::CostContractLoadFailure => ParseError::new(
                 ParseErrors::CostComputationFailed("Failed to load cost contract".into()),
             ),
+            CostErrors::ExecutionTimeExpired => ParseError::new(ParseErrors::ExecutionTimeExpired),
         }
     }
 }
@@ -157,6 +159,7 @@ impl DiagnosableError for ParseErrors {
                 "Used up memory budget during the parse: {} balance, {} used",
                 bal, used
             ),
+            ParseErrors::ExecutionTimeExpired => format!("Execution time expired during the parse"),
             ParseErrors::TooManyExpressions => format!("Too many expressions"),
             ParseErrors::FailedCapturingInput => format!("Failed to capture value from input"),
             ParseErrors::SeparatorExpected(found) => {
@@ -693,6 +693,32 @@ pub fn parse(input: &str) -> ParseResult<Vec<PreSymbolicExpression>> {
     parse_lexed(lexed)
 }
 
+/// Measure the shape of lexed source without building any expressions.
+/// Returns (maximum list/tuple nesting depth, number of expressions). Unbalanced
+///  delimiters are not detected here -- `parse_lexed()` will catch them.
+pub fn measure_lexed(input: &[(LexItem, u32, u32)]) -> (u64, u64) {
+    let mut depth: u64 = 0;
+    let mut max_depth: u64 = 0;
+    let mut num_expressions: u64 = 0;
+    for (item, _, _) in input.iter() {
+        match item {
+            LexItem::LeftParen | LexItem::LeftCurly => {
+                depth += 1;
+                max_depth = cmp::max(max_depth, depth);
+                num_expressions += 1;
+            }
+            LexItem::RightParen | LexItem::RightCurly => {
+                depth = depth.saturating_sub(1);
+            }
+            LexItem::CommaSeparator | LexItem::ColonSeparator | LexItem::Whitespace => {}
+            _ => {
+                num_expressions += 1;
+            }
+        }
+    }
+    (max_depth, num_expressions)
+}
+
 #[cfg(test)]
 mod test {
     use vm::ast;
@@ -1229,4 +1255,18 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn test_measure_lexed() {
+        let lexed =
+            ast::parser::lex("(define-constant a { x: (+ 1 2), y: u3 }) (print a)").unwrap();
+        assert_eq!(ast::parser::measure_lexed(&lexed), (3, 14));
+
+        let lexed = ast::parser::lex("").unwrap();
+        assert_eq!(ast::parser::measure_lexed(&lexed), (0, 0));
+
+        let deep = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        let lexed = ast::parser::lex(&deep).unwrap();
+        assert_eq!(ast::parser::measure_lexed(&lexed), (100, 101));
+    }
 }
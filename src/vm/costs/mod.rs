@@ -16,6 +16,7 @@
 
 use std::collections::{BTreeMap, HashMap};
 use std::convert::{TryFrom, TryInto};
+use std::time::Instant;
 use std::{cmp, fmt};
 
 use regex::internal::Exec;
@@ -232,6 +233,8 @@ pub struct LimitedCostTracker {
     memory_limit: u64,
     free: bool,
     mainnet: bool,
+    /// Wall-clock time after which charging any further cost fails
+    deadline: Option<Instant>,
 }
 
 #[cfg(test)]
@@ -276,6 +279,7 @@ pub enum CostErrors {
     CostBalanceExceeded(ExecutionCost, ExecutionCost),
     MemoryBalanceExceeded(u64, u64),
     CostContractLoadFailure,
+    ExecutionTimeExpired,
 }
 
 fn load_state_summary(mainnet: bool, clarity_db: &mut ClarityDatabase) -> Result<CostStateSummary> {
@@ -587,6 +591,7 @@ impl LimitedCostTracker {
             memory: 0,
             free: false,
             mainnet,
+            deadline: None,
         };
         assert!(clarity_db.is_stack_empty());
         cost_tracker.load_costs(clarity_db, true)?;
@@ -608,6 +613,7 @@ impl LimitedCostTracker {
             memory: 0,
             free: false,
             mainnet,
+            deadline: None,
         };
         cost_tracker.load_costs(clarity_db, false)?;
         Ok(cost_tracker)
@@ -630,9 +636,17 @@ impl LimitedCostTracker {
             memory_limit: CLARITY_MEMORY_LIMIT,
             free: true,
             mainnet: false,
+            deadline: None,
         }
     }
 
+    /// Abort execution with `CostErrors::ExecutionTimeExpired` once `deadline` has passed.
+    /// Only meant for off-chain evaluation (e.g. RPC read-only calls): block processing must not
+    /// depend on wall-clock time.
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.deadline = Some(deadline);
+    }
+
     /// `apply_updates` - tells this function to look for any changes in the cost voting contract
     ///   which would need to be applied. if `false`, just load the last computed cost state in this
     ///   fork.
@@ -711,6 +725,19 @@ impl LimitedCostTracker {
     pub fn get_limit(&self) -> ExecutionCost {
         self.limit.clone()
     }
+    pub fn get_memory(&self) -> u64 {
+        self.memory
+    }
+
+    /// Would this tracker and `other` charge the same costs for the same work?  True if they use
+    /// the same cost functions, contract-call circuits, and memory limit.
+    pub fn has_same_costs_as(&self, other: &LimitedCostTracker) -> bool {
+        self.mainnet == other.mainnet
+            && self.free == other.free
+            && self.memory_limit == other.memory_limit
+            && self.cost_function_references == other.cost_function_references
+            && self.contract_call_circuits == other.contract_call_circuits
+    }
 }
 
 fn parse_cost(
@@ -798,6 +825,11 @@ fn add_cost(
     s: &mut LimitedCostTracker,
     cost: ExecutionCost,
 ) -> std::result::Result<(), CostErrors> {
+    if let Some(deadline) = s.deadline {
+        if Instant::now() > deadline {
+            return Err(CostErrors::ExecutionTimeExpired);
+        }
+    }
     s.total.add(&cost)?;
     if s.total.exceeds(&s.limit) {
         Err(CostErrors::CostBalanceExceeded(
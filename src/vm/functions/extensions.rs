@@ -0,0 +1,405 @@
+// Copyright (C) 2013-2020 Blockstack PBC, a public benefit corporation
+// Copyright (C) 2020-2021 Stacks Open Internet Foundation
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with this program.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Extra native Clarity functions for private chains.
+//!
+//! A chain built with the `clarity_extensions` feature can register builtins of its own here
+//! before it opens its chainstate.  Each one brings the rules the VM needs to treat it like a
+//! native function: how to type-check a call, whether it is read-only, what a call costs, and how
+//! to evaluate it.  A builtin only exists from its activation height (a Stacks block height) on,
+//! and never on mainnet.  Its name is reserved as soon as it is registered, so no contract can
+//! define a function that a later activation would shadow.
+//!
+//! Registering a builtin changes consensus: every node on the chain must register the same
+//! builtins, with the same activation heights.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::sync::RwLock;
+
+use vm::analysis::errors::CheckResult;
+use vm::analysis::AnalysisDatabase;
+use vm::callables::CallableType;
+use vm::costs::{CostTracker, ExecutionCost, MemoryConsumer};
+use vm::errors::InterpreterResult as Result;
+use vm::functions::define::DefineFunctions;
+use vm::representations::{ClarityName, SymbolicExpression};
+use vm::types::{TypeSignature, Value};
+use vm::{eval, is_reserved, Environment, LocalContext};
+
+type ExtensionHandler =
+    dyn Fn(&[SymbolicExpression], &mut Environment, &LocalContext) -> Result<Value> + Send + Sync;
+
+/// A native function that a private chain adds to Clarity
+#[derive(Clone)]
+pub struct BuiltinExtension {
+    /// Name that contracts call it by
+    pub name: &'static str,
+    /// Stacks block height from which contracts can call it
+    pub activation_height: u32,
+    /// Whether it can be called from a read-only function
+    pub read_only: bool,
+    /// Type of a call, given the types of its arguments
+    pub type_check: fn(&[TypeSignature]) -> CheckResult<TypeSignature>,
+    /// Cost of a call, given the total size of its arguments
+    pub cost: fn(u64) -> ExecutionCost,
+    /// Evaluate a call on its (already evaluated) arguments
+    pub implementation: fn(Vec<Value>) -> Result<Value>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExtensionError {
+    /// The name is not a valid Clarity name
+    InvalidName(String),
+    /// The name is a keyword, a native function, or another builtin extension
+    NameTaken(String),
+}
+
+impl fmt::Display for ExtensionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExtensionError::InvalidName(name) => write!(f, "Invalid builtin name '{}'", name),
+            ExtensionError::NameTaken(name) => write!(f, "Builtin name '{}' is taken", name),
+        }
+    }
+}
+
+impl std::error::Error for ExtensionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+struct RegisteredExtension {
+    extension: BuiltinExtension,
+    handler: &'static ExtensionHandler,
+}
+
+lazy_static! {
+    static ref BUILTIN_EXTENSIONS: RwLock<HashMap<&'static str, RegisteredExtension>> =
+        RwLock::new(HashMap::new());
+}
+
+/// Add a builtin to Clarity.  Must happen before any contract is analyzed or run.
+pub fn register_builtin_extension(
+    extension: BuiltinExtension,
+) -> std::result::Result<(), ExtensionError> {
+    let name = extension.name;
+    if ClarityName::try_from(name.to_string()).is_err() {
+        return Err(ExtensionError::InvalidName(name.to_string()));
+    }
+    if is_reserved(name) || DefineFunctions::lookup_by_name(name).is_some() {
+        return Err(ExtensionError::NameTaken(name.to_string()));
+    }
+
+    let implementation = extension.clone();
+    let handler: Box<ExtensionHandler> = Box::new(move |args, env, context| {
+        special_builtin_extension(&implementation, args, env, context)
+    });
+    let mut registry = BUILTIN_EXTENSIONS
+        .write()
+        .expect("FATAL: builtin extension registry lock poisoned");
+    if registry.contains_key(name) {
+        return Err(ExtensionError::NameTaken(name.to_string()));
+    }
+    registry.insert(
+        name,
+        RegisteredExtension {
+            extension,
+            handler: Box::leak(handler),
+        },
+    );
+    Ok(())
+}
+
+/// Are there any builtin extensions?
+pub fn has_builtin_extensions() -> bool {
+    !BUILTIN_EXTENSIONS
+        .read()
+        .expect("FATAL: builtin extension registry lock poisoned")
+        .is_empty()
+}
+
+/// Names of all the builtin extensions
+pub fn builtin_extension_names() -> Vec<&'static str> {
+    let mut names: Vec<_> = BUILTIN_EXTENSIONS
+        .read()
+        .expect("FATAL: builtin extension registry lock poisoned")
+        .keys()
+        .cloned()
+        .collect();
+    names.sort();
+    names
+}
+
+/// The builtin extension called `name`, whether or not it has activated
+pub fn lookup_builtin_extension(name: &str) -> Option<BuiltinExtension> {
+    BUILTIN_EXTENSIONS
+        .read()
+        .expect("FATAL: builtin extension registry lock poisoned")
+        .get(name)
+        .map(|registered| registered.extension.clone())
+}
+
+/// The builtin extension called `name`, if contracts analyzed against `analysis_db` can call it
+pub fn lookup_active_builtin_extension(
+    name: &str,
+    mainnet: bool,
+    analysis_db: &mut AnalysisDatabase,
+) -> Option<BuiltinExtension> {
+    if mainnet {
+        return None;
+    }
+    let extension = lookup_builtin_extension(name)?;
+    if !is_active(&extension, || analysis_db.get_current_block_height()) {
+        return None;
+    }
+    Some(extension)
+}
+
+/// The callable for the builtin extension called `name`, if `env` can call it
+pub fn lookup_extension_function(name: &str, env: &mut Environment) -> Option<CallableType> {
+    if env.global_context.mainnet {
+        return None;
+    }
+    let registry = BUILTIN_EXTENSIONS
+        .read()
+        .expect("FATAL: builtin extension registry lock poisoned");
+    let registered = registry.get(name)?;
+    if !is_active(&registered.extension, || {
+        env.global_context.database.get_current_block_height()
+    }) {
+        return None;
+    }
+    Some(CallableType::SpecialFunction(
+        registered.extension.name,
+        registered.handler,
+    ))
+}
+
+/// Only look up the block height for builtins that were not there from the start, since not
+/// every store has one.
+fn is_active<F: FnOnce() -> u32>(extension: &BuiltinExtension, block_height: F) -> bool {
+    extension.activation_height == 0 || extension.activation_height <= block_height()
+}
+
+fn special_builtin_extension(
+    extension: &BuiltinExtension,
+    args: &[SymbolicExpression],
+    env: &mut Environment,
+    context: &LocalContext,
+) -> Result<Value> {
+    let mut used_memory = 0;
+    let result = (|| {
+        let mut evaluated_args = Vec::with_capacity(args.len());
+        for arg in args.iter() {
+            let value = eval(arg, env, context)?;
+            let memory = value.get_memory_use();
+            env.add_memory(memory)?;
+            used_memory += memory;
+            evaluated_args.push(value);
+        }
+        let input_size = evaluated_args.iter().map(|x| x.size() as u64).sum();
+        env.add_cost((extension.cost)(input_size))?;
+        (extension.implementation)(evaluated_args)
+    })();
+    env.drop_memory(used_memory);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use vm::analysis::errors::CheckErrors;
+    use vm::analysis::{run_analysis, type_check};
+    use vm::ast::parse;
+    use vm::costs::LimitedCostTracker;
+    use vm::execute;
+    use vm::types::QualifiedContractIdentifier;
+
+    use crate::clarity_vm::database::MemoryBackingStore;
+
+    use super::*;
+
+    fn type_check_add3(args: &[TypeSignature]) -> CheckResult<TypeSignature> {
+        if args.len() != 1 {
+            return Err(CheckErrors::IncorrectArgumentCount(1, args.len()).into());
+        }
+        if args[0] != TypeSignature::UIntType {
+            return Err(CheckErrors::TypeError(TypeSignature::UIntType, args[0].clone()).into());
+        }
+        Ok(TypeSignature::UIntType)
+    }
+
+    fn add3(args: Vec<Value>) -> Result<Value> {
+        match args[0] {
+            Value::UInt(x) => Ok(Value::UInt(x + 3)),
+            ref x => Err(CheckErrors::TypeValueError(TypeSignature::UIntType, x.clone()).into()),
+        }
+    }
+
+    fn add3_cost(input_size: u64) -> ExecutionCost {
+        ExecutionCost::runtime(10 + input_size)
+    }
+
+    fn add3_extension(name: &'static str, activation_height: u32) -> BuiltinExtension {
+        BuiltinExtension {
+            name,
+            activation_height,
+            read_only: false,
+            type_check: type_check_add3,
+            cost: add3_cost,
+            implementation: add3,
+        }
+    }
+
+    #[test]
+    fn test_register_builtin_extension() {
+        register_builtin_extension(add3_extension("test-register-add3", 0)).unwrap();
+        assert!(has_builtin_extensions());
+        assert!(builtin_extension_names().contains(&"test-register-add3"));
+
+        assert_eq!(
+            register_builtin_extension(add3_extension("test-register-add3", 10)).unwrap_err(),
+            ExtensionError::NameTaken("test-register-add3".to_string())
+        );
+        assert_eq!(
+            register_builtin_extension(add3_extension("+", 0)).unwrap_err(),
+            ExtensionError::NameTaken("+".to_string())
+        );
+        assert_eq!(
+            register_builtin_extension(add3_extension("define-public", 0)).unwrap_err(),
+            ExtensionError::NameTaken("define-public".to_string())
+        );
+        assert_eq!(
+            register_builtin_extension(add3_extension("not a name", 0)).unwrap_err(),
+            ExtensionError::InvalidName("not a name".to_string())
+        );
+    }
+
+    #[test]
+    fn test_builtin_extension_activation() {
+        register_builtin_extension(add3_extension("test-activation-add3", 5)).unwrap();
+        let extension = lookup_builtin_extension("test-activation-add3").unwrap();
+        assert!(!is_active(&extension, || 4));
+        assert!(is_active(&extension, || 5));
+        assert!(is_active(
+            &add3_extension("test-activation-add3", 0),
+            || { panic!("looked up the block height") }
+        ));
+        assert!(lookup_builtin_extension("test-activation-missing").is_none());
+
+        // the in-memory store is at block height 0
+        assert_eq!(
+            execute("(test-activation-add3 u1)").unwrap_err(),
+            CheckErrors::UndefinedFunction("test-activation-add3".to_string()).into()
+        );
+        let contract_id = QualifiedContractIdentifier::transient();
+        let mut expressions = parse(&contract_id, "(test-activation-add3 u1)").unwrap();
+        let mut marf = MemoryBackingStore::new();
+        let mut analysis_db = marf.as_analysis_db();
+        let err = type_check(&contract_id, &mut expressions, &mut analysis_db, false).unwrap_err();
+        assert_eq!(
+            err.err,
+            CheckErrors::UnknownFunction("test-activation-add3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_builtin_extension_mainnet() {
+        register_builtin_extension(add3_extension("test-mainnet-add3", 0)).unwrap();
+        let contract_id = QualifiedContractIdentifier::transient();
+        let mut marf = MemoryBackingStore::new();
+        let mut analysis_db = marf.as_analysis_db();
+
+        let mut expressions = parse(
+            &contract_id,
+            "(define-public (get-sum) (ok (test-mainnet-add3 u1)))",
+        )
+        .unwrap();
+        run_analysis(
+            &contract_id,
+            &mut expressions,
+            &mut analysis_db,
+            false,
+            LimitedCostTracker::new_free(),
+            false,
+        )
+        .unwrap();
+        let (err, _) = run_analysis(
+            &contract_id,
+            &mut expressions,
+            &mut analysis_db,
+            false,
+            LimitedCostTracker::new_free(),
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.err,
+            CheckErrors::UnknownFunction("test-mainnet-add3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_call_builtin_extension() {
+        register_builtin_extension(add3_extension("test-call-add3", 0)).unwrap();
+        assert_eq!(
+            execute("(test-call-add3 (+ u1 u2))").unwrap(),
+            Some(Value::UInt(6))
+        );
+
+        let contract_id = QualifiedContractIdentifier::transient();
+        let mut marf = MemoryBackingStore::new();
+        let mut analysis_db = marf.as_analysis_db();
+
+        let mut expressions = parse(
+            &contract_id,
+            "(define-read-only (get-sum) (test-call-add3 u1))",
+        )
+        .unwrap();
+        let err = type_check(&contract_id, &mut expressions, &mut analysis_db, false).unwrap_err();
+        assert_eq!(err.err, CheckErrors::WriteAttemptedInReadOnly);
+
+        let mut expressions = parse(
+            &contract_id,
+            "(define-public (get-sum) (ok (test-call-add3 1)))",
+        )
+        .unwrap();
+        let err = type_check(&contract_id, &mut expressions, &mut analysis_db, false).unwrap_err();
+        assert_eq!(
+            err.err,
+            CheckErrors::TypeError(TypeSignature::UIntType, TypeSignature::IntType)
+        );
+
+        assert_eq!(
+            execute("(define-public (test-call-add3 (x uint)) (ok x))").unwrap_err(),
+            CheckErrors::NameAlreadyUsed("test-call-add3".to_string()).into()
+        );
+
+        let mut expressions = parse(
+            &contract_id,
+            "(define-public (get-sum) (ok (test-call-add3 u1)))",
+        )
+        .unwrap();
+        type_check(&contract_id, &mut expressions, &mut analysis_db, false).unwrap();
+
+        assert_eq!(
+            execute("(test-call-add3 1)").unwrap_err(),
+            CheckErrors::TypeValueError(TypeSignature::UIntType, Value::Int(1)).into()
+        );
+    }
+}
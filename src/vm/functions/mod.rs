@@ -44,6 +44,8 @@ mod boolean;
 mod crypto;
 mod database;
 pub mod define;
+#[cfg(feature = "clarity_extensions")]
+pub mod extensions;
 mod options;
 mod sequences;
 mod special;
@@ -65,6 +65,9 @@ pub use vm::contexts::MAX_CONTEXT_DEPTH;
 use vm::costs::cost_functions::ClarityCostFunction;
 pub use vm::functions::stx_transfer_consolidated;
 
+#[cfg(feature = "clarity_extensions")]
+pub use vm::functions::extensions;
+
 const MAX_CALL_STACK_DEPTH: usize = 64;
 
 fn lookup_variable(name: &str, context: &LocalContext, env: &mut Environment) -> Result<Value> {
@@ -105,14 +108,21 @@ pub fn lookup_function(name: &str, env: &mut Environment) -> Result<CallableType
     runtime_cost(ClarityCostFunction::LookupFunction, env, 0)?;
 
     if let Some(result) = functions::lookup_reserved_functions(name) {
-        Ok(result)
-    } else {
-        let user_function = env
-            .contract_context
-            .lookup_function(name)
-            .ok_or(CheckErrors::UndefinedFunction(name.to_string()))?;
-        Ok(CallableType::UserFunction(user_function))
+        return Ok(result);
+    }
+
+    #[cfg(feature = "clarity_extensions")]
+    {
+        if let Some(result) = functions::extensions::lookup_extension_function(name, env) {
+            return Ok(result);
+        }
     }
+
+    let user_function = env
+        .contract_context
+        .lookup_function(name)
+        .ok_or(CheckErrors::UndefinedFunction(name.to_string()))?;
+    Ok(CallableType::UserFunction(user_function))
 }
 
 fn add_stack_trace(result: &mut Result<Value>, env: &Environment) {
@@ -228,11 +238,23 @@ pub fn is_reserved(name: &str) -> bool {
         true
     } else if variables::is_reserved_name(name) {
         true
+    } else if is_builtin_extension(name) {
+        true
     } else {
         false
     }
 }
 
+#[cfg(feature = "clarity_extensions")]
+fn is_builtin_extension(name: &str) -> bool {
+    functions::extensions::lookup_builtin_extension(name).is_some()
+}
+
+#[cfg(not(feature = "clarity_extensions"))]
+fn is_builtin_extension(_name: &str) -> bool {
+    false
+}
+
 /* This function evaluates a list of expressions, sharing a global context.
  * It returns the final evaluated result.
  */
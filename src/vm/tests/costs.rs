@@ -239,6 +239,48 @@ fn test_tracked_costs(prog: &str) -> ExecutionCost {
     tracker.get_total()
 }
 
+#[test]
+fn test_cost_tracker_deadline() {
+    use std::thread;
+    use std::time::{Duration, Instant};
+    use vm::costs::{CostErrors, CostTracker};
+
+    let marf_kv = MarfedKV::temporary();
+    let mut clarity_instance = ClarityInstance::new(false, marf_kv, ExecutionCost::max_value());
+    clarity_instance
+        .begin_test_genesis_block(
+            &StacksBlockId::sentinel(),
+            &StacksBlockHeader::make_index_block_hash(
+                &FIRST_BURNCHAIN_CONSENSUS_HASH,
+                &FIRST_STACKS_BLOCK_HASH,
+            ),
+            &NULL_HEADER_DB,
+            &NULL_BURN_STATE_DB,
+        )
+        .commit_block();
+
+    let mut marf_kv = clarity_instance.destroy();
+    let mut store = marf_kv.begin(
+        &StacksBlockHeader::make_index_block_hash(
+            &FIRST_BURNCHAIN_CONSENSUS_HASH,
+            &FIRST_STACKS_BLOCK_HASH,
+        ),
+        &StacksBlockId([1 as u8; 32]),
+    );
+    let mut clarity_db = store.as_clarity_db(&NULL_HEADER_DB, &NULL_BURN_STATE_DB);
+    let mut tracker = LimitedCostTracker::new_max_limit(&mut clarity_db).unwrap();
+
+    tracker.set_deadline(Instant::now() + Duration::from_secs(3600));
+    tracker.add_cost(ExecutionCost::runtime(1)).unwrap();
+
+    tracker.set_deadline(Instant::now());
+    thread::sleep(Duration::from_millis(10));
+    assert_eq!(
+        tracker.add_cost(ExecutionCost::runtime(1)),
+        Err(CostErrors::ExecutionTimeExpired)
+    );
+}
+
 #[test]
 fn test_all() {
     let baseline = test_tracked_costs("1");
@@ -52,7 +52,11 @@ use stacks::util::hash::{hex_bytes, Hash160};
 use stacks::util::secp256k1::Secp256k1PublicKey;
 use stacks::util::sleep_ms;
 
-use stacks::monitoring::{increment_btc_blocks_received_counter, increment_btc_ops_sent_counter};
+use stacks::monitoring::{
+    increment_block_commit_fee_capped_counter, increment_btc_blocks_received_counter,
+    increment_btc_ops_sent_counter, update_block_commit_fee_rate,
+    update_burnchain_fee_rate_estimate,
+};
 
 #[cfg(test)]
 use stacks::chainstate::burn::Opcodes;
@@ -93,6 +97,8 @@ impl OngoingBlockCommit {
 struct LeaderBlockCommitFees {
     sunset_fee: u64,
     fee_rate: u64,
+    /// Fee rate the first attempt of this commit was sent with; RBF is bounded relative to it
+    base_fee_rate: u64,
     sortition_fee: u64,
     outputs_len: u64,
     default_tx_size: u64,
@@ -107,7 +113,8 @@ impl LeaderBlockCommitFees {
         payload: &LeaderBlockCommitOp,
         config: &Config,
     ) -> LeaderBlockCommitFees {
-        let mut fees = LeaderBlockCommitFees::estimated_fees_from_payload(payload, config);
+        let mut fees =
+            LeaderBlockCommitFees::estimated_fees_from_payload(payload, config, self.base_fee_rate);
         fees.spent_in_attempts = cmp::max(1, self.spent_in_attempts);
         fees.final_size = self.final_size;
         fees.fee_rate = self.fee_rate + config.burnchain.rbf_fee_increment;
@@ -118,6 +125,7 @@ impl LeaderBlockCommitFees {
     pub fn estimated_fees_from_payload(
         payload: &LeaderBlockCommitOp,
         config: &Config,
+        fee_rate: u64,
     ) -> LeaderBlockCommitFees {
         let sunset_fee = if payload.sunset_burn > 0 {
             cmp::max(payload.sunset_burn, DUST_UTXO_LIMIT)
@@ -129,12 +137,12 @@ impl LeaderBlockCommitFees {
         let value_per_transfer = payload.burn_fee / number_of_transfers;
         let sortition_fee = value_per_transfer * number_of_transfers;
         let spent_in_attempts = 0;
-        let fee_rate = config.burnchain.satoshis_per_byte;
         let default_tx_size = config.burnchain.block_commit_tx_estimated_size;
 
         LeaderBlockCommitFees {
             sunset_fee,
             fee_rate,
+            base_fee_rate: fee_rate,
             sortition_fee,
             outputs_len: number_of_transfers,
             default_tx_size,
@@ -382,6 +390,73 @@ impl BitcoinRegtestController {
         rest
     }
 
+    /// Delete orphaned burnchain blocks that are too deep to ever be reorged back in, if the node
+    /// is configured to do so.
+    fn prune_burnchain_db(&mut self) {
+        if !self.config.burnchain.prune_burnchain_db {
+            return;
+        }
+        let burnchain = self.get_burnchain();
+        let prune_depth = cmp::max(
+            self.config.burnchain.burnchain_db_prune_depth,
+            burnchain.stable_confirmations as u64,
+        );
+        let reprocessing_floor = match self.db.as_ref() {
+            Some(sortdb) => match sortdb.get_pox_reprocessing_floor(&burnchain) {
+                Ok(floor) => floor,
+                Err(e) => {
+                    warn!("Failed to load PoX reprocessing floor: {:?}", &e);
+                    return;
+                }
+            },
+            None => {
+                return;
+            }
+        };
+        let burnchain_db = match self.burnchain_db.as_mut() {
+            Some(burnchain_db) => burnchain_db,
+            None => {
+                return;
+            }
+        };
+        match burnchain_db.prune_orphaned_blocks(prune_depth) {
+            Ok(0) => {}
+            Ok(num_pruned) => {
+                info!("Pruned {} orphaned burnchain blocks", num_pruned);
+            }
+            Err(e) => {
+                warn!("Failed to prune burnchain DB: {:?}", &e);
+                return;
+            }
+        }
+
+        // only prune ops the chains coordinator will never read again, and only where orphans
+        // have already been pruned
+        let tip_height = match burnchain_db.get_canonical_chain_tip() {
+            Ok(tip) => tip.block_height,
+            Err(e) => {
+                warn!("Failed to load burnchain DB tip: {:?}", &e);
+                return;
+            }
+        };
+        if tip_height < prune_depth || reprocessing_floor == 0 {
+            return;
+        }
+        let stable_height = cmp::min(tip_height - prune_depth, reprocessing_floor - 1);
+        match burnchain_db.prune_stable_blocks(stable_height) {
+            Ok(0) => {}
+            Ok(num_pruned) => {
+                info!(
+                    "Pruned {} burnchain ops at or below stable height {}",
+                    num_pruned, stable_height
+                );
+            }
+            Err(e) => {
+                warn!("Failed to prune burnchain DB: {:?}", &e);
+            }
+        }
+    }
+
     fn receive_blocks(
         &mut self,
         block_for_sortitions: bool,
@@ -414,6 +489,7 @@ impl BitcoinRegtestController {
 
                     // initialize the dbs...
                     self.sortdb_mut();
+                    self.prune_burnchain_db();
 
                     // wait for the chains coordinator to catch up with us
                     if block_for_sortitions {
@@ -918,6 +994,54 @@ impl BitcoinRegtestController {
         Some(tx)
     }
 
+    /// Fee rate (sat/vB) to use for a fresh block-commit.  If fee estimation is enabled, this
+    /// asks bitcoind for an estimate, falling back to the static `satoshis_per_byte` setting if
+    /// none is available.  The result is clamped to `max_satoshis_per_byte`, if set.
+    fn get_block_commit_fee_rate(&self) -> u64 {
+        let burnchain_config = &self.config.burnchain;
+        let mut fee_rate = burnchain_config.satoshis_per_byte;
+
+        if burnchain_config.fee_estimation {
+            match BitcoinRPCRequest::estimate_smart_fee(
+                &self.config,
+                burnchain_config.fee_estimation_conf_target,
+            ) {
+                Ok(Some(estimate)) => {
+                    debug!(
+                        "Bitcoind estimated a fee rate of {} sat/vB (conf_target: {})",
+                        estimate, burnchain_config.fee_estimation_conf_target
+                    );
+                    update_burnchain_fee_rate_estimate(estimate as i64);
+                    fee_rate = estimate;
+                }
+                Ok(None) => {
+                    warn!(
+                        "Bitcoind could not estimate a fee rate, using {} sat/vB",
+                        fee_rate
+                    );
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to estimate fee rate: {:?}; using {} sat/vB",
+                        e, fee_rate
+                    );
+                }
+            }
+        }
+
+        if let Some(max_satoshis_per_byte) = burnchain_config.max_satoshis_per_byte {
+            if fee_rate > max_satoshis_per_byte {
+                debug!(
+                    "Capping block commit fee rate at {} sat/vB (was {})",
+                    max_satoshis_per_byte, fee_rate
+                );
+                fee_rate = max_satoshis_per_byte;
+            }
+        }
+
+        fee_rate
+    }
+
     fn send_block_commit_operation(
         &mut self,
         payload: LeaderBlockCommitOp,
@@ -929,9 +1053,24 @@ impl BitcoinRegtestController {
     ) -> Option<Transaction> {
         let mut estimated_fees = match previous_fees {
             Some(fees) => fees.fees_from_previous_tx(&payload, &self.config),
-            None => LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config),
+            None => {
+                let fee_rate = self.get_block_commit_fee_rate();
+                LeaderBlockCommitFees::estimated_fees_from_payload(&payload, &self.config, fee_rate)
+            }
         };
 
+        if let Some(max_commit_tx_fee) = self.config.burnchain.max_commit_tx_fee {
+            let miner_fee = estimated_fees.estimated_miner_fee() + estimated_fees.rbf_fee();
+            if miner_fee > max_commit_tx_fee {
+                warn!(
+                    "Block commit would pay {} sats in fees (fee_rate: {}), above the configured cap of {}; not submitting",
+                    miner_fee, estimated_fees.fee_rate, max_commit_tx_fee
+                );
+                increment_block_commit_fee_capped_counter();
+                return None;
+            }
+        }
+
         let public_key = signer.get_public_key();
         let (mut tx, mut utxos) = self.prepare_tx(
             &public_key,
@@ -968,6 +1107,7 @@ impl BitcoinRegtestController {
         }
 
         let fee_rate = estimated_fees.fee_rate;
+        update_block_commit_fee_rate(fee_rate as i64);
         self.finalize_tx(
             &mut tx,
             estimated_fees.total_spent_in_outputs(),
@@ -1045,7 +1185,7 @@ impl BitcoinRegtestController {
 
         // Stop as soon as the fee_rate is ${self.config.burnchain.max_rbf} percent higher, stop RBF
         if ongoing_op.fees.fee_rate
-            > (self.config.burnchain.satoshis_per_byte * self.config.burnchain.max_rbf / 100)
+            > (ongoing_op.fees.base_fee_rate * self.config.burnchain.max_rbf / 100)
         {
             warn!(
                 "RBF'd block commits reached {}% satoshi per byte fee rate, not resubmitting",
@@ -1055,6 +1195,21 @@ impl BitcoinRegtestController {
             return None;
         }
 
+        // Never RBF past the configured fee rate cap
+        if let Some(max_satoshis_per_byte) = self.config.burnchain.max_satoshis_per_byte {
+            if ongoing_op.fees.fee_rate + self.config.burnchain.rbf_fee_increment
+                > max_satoshis_per_byte
+            {
+                warn!(
+                    "RBF'd block commits reached the fee rate cap of {} sat/vB, not resubmitting",
+                    max_satoshis_per_byte
+                );
+                increment_block_commit_fee_capped_counter();
+                self.ongoing_block_commit = Some(ongoing_op);
+                return None;
+            }
+        }
+
         // Did a re-org occurred since we fetched our UTXOs, or are the UTXOs so stale that they should be abandoned?
         let mut traversal_depth = 0;
         let mut burn_chain_tip = burnchain_db.get_canonical_chain_tip().ok()?;
@@ -1889,6 +2044,45 @@ impl BitcoinRPCRequest {
         Ok(())
     }
 
+    /// Calls `estimatesmartfee` and returns the estimated fee rate in sat/vB, or None if
+    /// bitcoind does not have enough data to produce an estimate.
+    pub fn estimate_smart_fee(config: &Config, conf_target: u64) -> RPCResult<Option<u64>> {
+        let payload = BitcoinRPCRequest {
+            method: "estimatesmartfee".to_string(),
+            params: vec![conf_target.into()],
+            id: "stacks".to_string(),
+            jsonrpc: "2.0".to_string(),
+        };
+
+        let json_resp = BitcoinRPCRequest::send(&config, payload)?;
+
+        if let Some(e) = json_resp.get("error") {
+            if !e.is_null() {
+                return Err(RPCError::Bitcoind(json_resp.to_string()));
+            }
+        }
+
+        // feerate is expressed in BTC/kvB
+        let btc_per_kvb = match json_resp
+            .get("result")
+            .and_then(|res| res.get("feerate"))
+            .and_then(|feerate| feerate.as_f64())
+        {
+            Some(feerate) => feerate,
+            None => return Ok(None),
+        };
+
+        if !btc_per_kvb.is_finite() || btc_per_kvb <= 0.0 {
+            return Err(RPCError::Parsing(format!(
+                "Invalid feerate from estimatesmartfee: {}",
+                btc_per_kvb
+            )));
+        }
+
+        let sats_per_vb = (btc_per_kvb * 100_000_000.0 / 1000.0).ceil() as u64;
+        Ok(Some(cmp::max(1, sats_per_vb)))
+    }
+
     pub fn import_public_key(config: &Config, public_key: &Secp256k1PublicKey) -> RPCResult<()> {
         let rescan = true;
         let label = "";
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fs;
 use std::net::{SocketAddr, ToSocketAddrs};
@@ -6,15 +7,19 @@ use std::path::PathBuf;
 use rand::RngCore;
 
 use stacks::burnchains::bitcoin::BitcoinNetworkType;
-use stacks::burnchains::{MagicBytes, BLOCKSTACK_MAGIC_MAINNET};
+use stacks::burnchains::{Address, MagicBytes, BLOCKSTACK_MAGIC_MAINNET};
+use stacks::burnchains::db::BURNCHAIN_DB_DEFAULT_PRUNE_DEPTH;
 use stacks::core::{
     BLOCK_LIMIT_MAINNET, CHAIN_ID_MAINNET, CHAIN_ID_TESTNET, HELIUM_BLOCK_LIMIT,
     PEER_VERSION_MAINNET, PEER_VERSION_TESTNET,
 };
-use stacks::net::connection::ConnectionOptions;
-use stacks::net::{Neighbor, NeighborKey, PeerAddress};
+use stacks::net::atlas::{AtlasConfig, AttachmentDomainConfig, AttachmentMirrorConfig};
+use stacks::net::connection::{ConnectionOptions, NetworkMode};
+use stacks::net::{Neighbor, NeighborKey, PeerAddress, ServiceFlags};
+use stacks::types::chainstate::StacksAddress;
+use stacks::util::disk::DiskSpacePolicy;
 use stacks::util::get_epoch_time_ms;
-use stacks::util::hash::hex_bytes;
+use stacks::util::hash::{hex_bytes, Sha256Sum};
 use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::secp256k1::Secp256k1PublicKey;
 use stacks::vm::costs::ExecutionCost;
@@ -23,6 +28,7 @@ use stacks::vm::types::{AssetIdentifier, PrincipalData, QualifiedContractIdentif
 const DEFAULT_SATS_PER_VB: u64 = 50;
 const DEFAULT_MAX_RBF_RATE: u64 = 150; // 1.5x
 const DEFAULT_RBF_FEE_RATE_INCREMENT: u64 = 5;
+const DEFAULT_FEE_ESTIMATION_CONF_TARGET: u64 = 2;
 const LEADER_KEY_TX_ESTIM_SIZE: u64 = 290;
 const BLOCK_COMMIT_TX_ESTIM_SIZE: u64 = 350;
 const INV_REWARD_CYCLES_TESTNET: u64 = 6;
@@ -34,6 +40,10 @@ pub struct ConfigFile {
     pub ustx_balance: Option<Vec<InitialBalanceFile>>,
     pub events_observer: Option<Vec<EventObserverConfigFile>>,
     pub connection_options: Option<ConnectionOptionsFile>,
+    pub atlas: Option<AtlasConfigFile>,
+    /// Independent networks to run side by side in this process, keyed by name
+    /// (e.g. `[network.mainnet.node]`, `[network.testnet.node]`).
+    pub network: Option<BTreeMap<String, ConfigFile>>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -88,6 +98,131 @@ mod tests {
             "STRYYQQ9M8KAF4NS7WNZQYY59X93XEKR31JP64CP"
         );
     }
+
+    #[test]
+    fn should_load_network_sections() {
+        let config = ConfigFile::from_str(
+            r#"
+            [network.mainnet.node]
+            working_dir = "/tmp/stacks-multi"
+            rpc_bind = "0.0.0.0:20443"
+            p2p_bind = "0.0.0.0:20444"
+
+            [network.mainnet.burnchain]
+            mode = "mainnet"
+
+            [network.testnet.node]
+            working_dir = "/tmp/stacks-multi"
+            rpc_bind = "0.0.0.0:21443"
+            p2p_bind = "0.0.0.0:21444"
+
+            [network.testnet.burnchain]
+            mode = "xenon"
+            "#,
+        );
+        let networks = config.network.expect("Failed to parse networks from toml");
+        let confs = Config::from_network_config_files(networks).unwrap();
+        assert_eq!(confs.len(), 2);
+
+        assert_eq!(confs[0].0, "mainnet");
+        assert_eq!(confs[0].1.node.working_dir, "/tmp/stacks-multi/mainnet");
+        assert_eq!(confs[0].1.burnchain.mode, "mainnet");
+
+        assert_eq!(confs[1].0, "testnet");
+        assert_eq!(confs[1].1.node.working_dir, "/tmp/stacks-multi/testnet");
+        assert_eq!(confs[1].1.burnchain.mode, "xenon");
+        assert_ne!(
+            confs[0].1.get_chainstate_path_str(),
+            confs[1].1.get_chainstate_path_str()
+        );
+    }
+
+    #[test]
+    fn should_reject_conflicting_network_binds() {
+        let config = ConfigFile::from_str(
+            r#"
+            [network.a.node]
+            rpc_bind = "0.0.0.0:20443"
+            p2p_bind = "0.0.0.0:20444"
+
+            [network.b.node]
+            rpc_bind = "0.0.0.0:21443"
+            p2p_bind = "0.0.0.0:20444"
+            "#,
+        );
+        match Config::from_network_config_files(config.network.unwrap()) {
+            Err(e) => assert!(e.contains("0.0.0.0:20444")),
+            Ok(_) => panic!("Expected conflicting binds to be rejected"),
+        }
+
+        // a wildcard address overlaps a specific address on the same port
+        let config = ConfigFile::from_str(
+            r#"
+            [network.a.node]
+            rpc_bind = "0.0.0.0:20443"
+            p2p_bind = "0.0.0.0:20444"
+
+            [network.b.node]
+            rpc_bind = "127.0.0.1:20443"
+            p2p_bind = "127.0.0.1:21444"
+            "#,
+        );
+        match Config::from_network_config_files(config.network.unwrap()) {
+            Err(e) => assert!(e.contains("127.0.0.1:20443")),
+            Ok(_) => panic!("Expected overlapping binds to be rejected"),
+        }
+
+        // distinct addresses on the same port don't overlap
+        let config = ConfigFile::from_str(
+            r#"
+            [network.a.node]
+            rpc_bind = "127.0.0.1:20443"
+            p2p_bind = "127.0.0.1:20444"
+
+            [network.b.node]
+            rpc_bind = "127.0.0.2:20443"
+            p2p_bind = "127.0.0.2:20444"
+            "#,
+        );
+        let confs = Config::from_network_config_files(config.network.unwrap()).unwrap();
+        assert!(confs.iter().all(|(_, conf)| conf.node.shares_process));
+
+        let mut networks = BTreeMap::new();
+        networks.insert("bad/name".to_string(), ConfigFile::default());
+        assert!(Config::from_network_config_files(networks).is_err());
+    }
+
+    #[test]
+    fn should_reject_shared_metrics_in_networks() {
+        let config = ConfigFile::from_str(
+            r#"
+            [network.a.node]
+            rpc_bind = "0.0.0.0:20443"
+            p2p_bind = "0.0.0.0:20444"
+            prometheus_bind = "0.0.0.0:9153"
+
+            [network.b.node]
+            rpc_bind = "0.0.0.0:21443"
+            p2p_bind = "0.0.0.0:21444"
+            "#,
+        );
+        match Config::from_network_config_files(config.network.unwrap()) {
+            Err(e) => assert!(e.contains("prometheus_bind")),
+            Ok(_) => panic!("Expected prometheus_bind to be rejected"),
+        }
+
+        // ...but a lone network owns the process
+        let config = ConfigFile::from_str(
+            r#"
+            [network.a.node]
+            rpc_bind = "0.0.0.0:20443"
+            p2p_bind = "0.0.0.0:20444"
+            prometheus_bind = "0.0.0.0:9153"
+            "#,
+        );
+        let confs = Config::from_network_config_files(config.network.unwrap()).unwrap();
+        assert!(!confs[0].1.node.shares_process);
+    }
 }
 
 impl ConfigFile {
@@ -406,6 +541,7 @@ pub struct Config {
     pub events_observers: Vec<EventObserverConfig>,
     pub connection_options: ConnectionOptions,
     pub block_limit: ExecutionCost,
+    pub atlas: AtlasConfig,
 }
 
 lazy_static! {
@@ -488,6 +624,86 @@ impl Config {
                         .pox_sync_sample_secs
                         .unwrap_or(default_node_config.pox_sync_sample_secs),
                     use_test_genesis_chainstate: node.use_test_genesis_chainstate,
+                    tip_selection_policy: match node.tip_selection_policy {
+                        Some(policy) => TipSelectionPolicy::from_string(&policy)
+                            .expect("Invalid tip_selection_policy -- expected first_seen, highest_fees, or most_confirmations"),
+                        None => default_node_config.tip_selection_policy,
+                    },
+                    services: match node.services {
+                        Some(names) => {
+                            let flags: Vec<ServiceFlags> = names
+                                .iter()
+                                .map(|name| {
+                                    ServiceFlags::from_name(name).unwrap_or_else(|| {
+                                        panic!("Invalid service '{}' in node.services", name)
+                                    })
+                                })
+                                .collect();
+                            ServiceFlags::to_services(&flags)
+                        }
+                        None => default_node_config.services,
+                    },
+                    preempt_on_new_parent: node
+                        .preempt_on_new_parent
+                        .unwrap_or(default_node_config.preempt_on_new_parent),
+                    preempt_on_microblocks: node
+                        .preempt_on_microblocks
+                        .unwrap_or(default_node_config.preempt_on_microblocks),
+                    preempt_microblock_min_fee: node
+                        .preempt_microblock_min_fee
+                        .unwrap_or(default_node_config.preempt_microblock_min_fee),
+                    preempt_check_interval_ms: node
+                        .preempt_check_interval_ms
+                        .unwrap_or(default_node_config.preempt_check_interval_ms),
+                    max_preemptions: node
+                        .max_preemptions
+                        .unwrap_or(default_node_config.max_preemptions),
+                    priority_principals: match node.priority_principals {
+                        Some(addrs) => addrs
+                            .iter()
+                            .map(|addr| {
+                                StacksAddress::from_string(addr).unwrap_or_else(|| {
+                                    panic!("Invalid address '{}' in node.priority_principals", addr)
+                                })
+                            })
+                            .collect(),
+                        None => default_node_config.priority_principals,
+                    },
+                    priority_budget_pct: match node.priority_budget_pct {
+                        Some(pct) if pct > 100 => {
+                            panic!("node.priority_budget_pct must be between 0 and 100")
+                        }
+                        Some(pct) => pct,
+                        None => default_node_config.priority_budget_pct,
+                    },
+                    resume_block_assembly: node
+                        .resume_block_assembly
+                        .unwrap_or(default_node_config.resume_block_assembly),
+                    replica_of: node.replica_of,
+                    disk_space_policy: DiskSpacePolicy {
+                        check_interval: node
+                            .disk_check_interval
+                            .unwrap_or(default_node_config.disk_space_policy.check_interval),
+                        alert_free_mb: node
+                            .disk_alert_free_mb
+                            .unwrap_or(default_node_config.disk_space_policy.alert_free_mb),
+                        stop_pushes_free_mb: node
+                            .disk_stop_pushes_free_mb
+                            .unwrap_or(default_node_config.disk_space_policy.stop_pushes_free_mb),
+                        pause_downloads_free_mb: node.disk_pause_downloads_free_mb.unwrap_or(
+                            default_node_config.disk_space_policy.pause_downloads_free_mb,
+                        ),
+                        halt_free_mb: node
+                            .disk_halt_free_mb
+                            .unwrap_or(default_node_config.disk_space_policy.halt_free_mb),
+                    },
+                    parallel_analysis_workers: node
+                        .parallel_analysis_workers
+                        .unwrap_or(default_node_config.parallel_analysis_workers),
+                    contract_cost_stats: node
+                        .contract_cost_stats
+                        .unwrap_or(default_node_config.contract_cost_stats),
+                    shares_process: default_node_config.shares_process,
                 };
                 (node_config, node.bootstrap_node, node.deny_nodes)
             }
@@ -521,6 +737,15 @@ impl Config {
                     if node.use_test_genesis_chainstate == Some(true) {
                         panic!("Attempted to run mainnet node with `use_test_genesis_chainstate`");
                     }
+                    #[cfg(feature = "clarity_extensions")]
+                    {
+                        if stacks::vm::extensions::has_builtin_extensions() {
+                            panic!(
+                                "Attempted to run mainnet node with Clarity builtin extensions {:?}",
+                                stacks::vm::extensions::builtin_extension_names()
+                            );
+                        }
+                    }
                     if let Some(ref balances) = config_file.ustx_balance {
                         if balances.len() > 0 {
                             panic!(
@@ -603,6 +828,20 @@ impl Config {
                     rbf_fee_increment: burnchain
                         .rbf_fee_increment
                         .unwrap_or(default_burnchain_config.rbf_fee_increment),
+                    fee_estimation: burnchain
+                        .fee_estimation
+                        .unwrap_or(default_burnchain_config.fee_estimation),
+                    fee_estimation_conf_target: burnchain
+                        .fee_estimation_conf_target
+                        .unwrap_or(default_burnchain_config.fee_estimation_conf_target),
+                    max_satoshis_per_byte: burnchain.max_satoshis_per_byte,
+                    max_commit_tx_fee: burnchain.max_commit_tx_fee,
+                    prune_burnchain_db: burnchain
+                        .prune_burnchain_db
+                        .unwrap_or(default_burnchain_config.prune_burnchain_db),
+                    burnchain_db_prune_depth: burnchain
+                        .burnchain_db_prune_depth
+                        .unwrap_or(default_burnchain_config.burnchain_db_prune_depth),
                 }
             }
             None => default_burnchain_config,
@@ -668,9 +907,34 @@ impl Config {
 
                     let endpoint = format!("{}", observer.endpoint);
 
+                    let payload_encoding = match observer.payload_encoding {
+                        Some(encoding) => EventPayloadEncoding::from_string(&encoding)
+                            .expect(&format!("Unknown payload_encoding '{}'", encoding)),
+                        None => EventPayloadEncoding::JSON,
+                    };
+
+                    let mut redaction = EventRedactionPolicy::default();
+                    for rule in observer.redact.unwrap_or(vec![]) {
+                        match rule.as_str() {
+                            "raw_tx" => redaction.omit_raw_tx = true,
+                            "memo" => redaction.omit_memos = true,
+                            "principals" => redaction.hash_principals = true,
+                            _ => panic!("Unknown redaction rule '{}'", rule),
+                        }
+                    }
+                    redaction.principal_hash_salt = observer.redaction_salt.unwrap_or_default();
+
+                    let limits = EventLimitPolicy {
+                        max_print_events_per_tx: observer.max_print_events_per_tx.unwrap_or(0),
+                        max_print_bytes_per_tx: observer.max_print_bytes_per_tx.unwrap_or(0),
+                    };
+
                     observers.push(EventObserverConfig {
                         endpoint,
                         events_keys,
+                        payload_encoding,
+                        redaction,
+                        limits,
                     });
                 }
                 observers
@@ -683,6 +947,9 @@ impl Config {
             Ok(val) => events_observers.push(EventObserverConfig {
                 endpoint: val,
                 events_keys: vec![EventKeyType::AnyEvent],
+                payload_encoding: EventPayloadEncoding::JSON,
+                redaction: EventRedactionPolicy::default(),
+                limits: EventLimitPolicy::default(),
             }),
             _ => (),
         };
@@ -796,6 +1063,13 @@ impl Config {
                             .max_inflight_attachments
                             .clone()
                     }),
+                    max_attachments_per_request: opts.max_attachments_per_request.unwrap_or_else(
+                        || {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS
+                                .max_attachments_per_request
+                                .clone()
+                        },
+                    ),
                     maximum_call_argument_size: opts.maximum_call_argument_size.unwrap_or_else(
                         || {
                             HELIUM_DEFAULT_CONNECTION_OPTIONS
@@ -803,6 +1077,14 @@ impl Config {
                                 .clone()
                         },
                     ),
+                    maximum_transaction_body_size: opts
+                        .maximum_transaction_body_size
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.maximum_transaction_body_size
+                        }),
+                    maximum_request_body_size: opts.maximum_request_body_size.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.maximum_request_body_size,
+                    ),
                     download_interval: opts.download_interval.unwrap_or_else(|| {
                         HELIUM_DEFAULT_CONNECTION_OPTIONS.download_interval.clone()
                     }),
@@ -830,10 +1112,173 @@ impl Config {
                     max_http_clients: opts.max_http_clients.unwrap_or_else(|| {
                         HELIUM_DEFAULT_CONNECTION_OPTIONS.max_http_clients.clone()
                     }),
+                    max_http_connections_per_host: opts.max_http_connections_per_host.unwrap_or_else(
+                        || {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS
+                                .max_http_connections_per_host
+                                .clone()
+                        },
+                    ),
+                    http_client_idle_timeout: opts.http_client_idle_timeout.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS
+                            .http_client_idle_timeout
+                            .clone()
+                    }),
                     connect_timeout: opts.connect_timeout.unwrap_or(10),
                     handshake_timeout: opts.connect_timeout.unwrap_or(5),
                     max_sockets: opts.max_sockets.unwrap_or(800) as usize,
                     antientropy_public: opts.antientropy_public.unwrap_or(true),
+                    max_payload_len: opts.max_payload_len.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.max_payload_len.clone()
+                    }),
+                    download_state_save_interval: opts.download_state_save_interval.unwrap_or_else(
+                        || {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS
+                                .download_state_save_interval
+                                .clone()
+                        },
+                    ),
+                    enable_admin_rpc: opts.enable_admin_rpc.unwrap_or(false),
+                    public_ip_quorum: opts
+                        .public_ip_quorum
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.public_ip_quorum),
+                    relay_min_fee_rate: opts
+                        .relay_min_fee_rate
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.relay_min_fee_rate),
+                    relay_min_stx_transfer: opts.relay_min_stx_transfer.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.relay_min_stx_transfer
+                    }),
+                    relay_max_tx_len: opts
+                        .relay_max_tx_len
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.relay_max_tx_len),
+                    relay_max_txs_per_origin: opts.relay_max_txs_per_origin.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.relay_max_txs_per_origin
+                    }),
+                    relay_max_tx_bytes_per_origin: opts
+                        .relay_max_tx_bytes_per_origin
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.relay_max_tx_bytes_per_origin
+                        }),
+                    inv_agreement_check_interval: opts.inv_agreement_check_interval.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.inv_agreement_check_interval,
+                    ),
+                    inv_agreement_alert_pct: opts.inv_agreement_alert_pct.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.inv_agreement_alert_pct
+                    }),
+                    inv_agreement_alert_duration: opts.inv_agreement_alert_duration.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.inv_agreement_alert_duration,
+                    ),
+                    peer_version_check_interval: opts.peer_version_check_interval.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.peer_version_check_interval,
+                    ),
+                    peer_version_alert_pct: opts.peer_version_alert_pct.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.peer_version_alert_pct
+                    }),
+                    data_url_health_interval: opts.data_url_health_interval.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.data_url_health_interval
+                    }),
+                    rehandshake_interval: opts
+                        .rehandshake_interval
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rehandshake_interval),
+                    staging_backlog_check_interval: opts
+                        .staging_backlog_check_interval
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.staging_backlog_check_interval
+                        }),
+                    max_staging_blocks_backlog: opts
+                        .max_staging_blocks_backlog
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.max_staging_blocks_backlog
+                        }),
+                    max_rpc_page_size: opts
+                        .max_rpc_page_size
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_rpc_page_size),
+                    rpc_cache_max_entries: opts.rpc_cache_max_entries.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_cache_max_entries
+                    }),
+                    rpc_idempotency_key_ttl: opts.rpc_idempotency_key_ttl.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_idempotency_key_ttl
+                    }),
+                    rpc_idempotency_max_keys: opts.rpc_idempotency_max_keys.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_idempotency_max_keys
+                    }),
+                    max_query_jobs: opts
+                        .max_query_jobs
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_query_jobs),
+                    query_job_blocks_per_pass: opts.query_job_blocks_per_pass.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.query_job_blocks_per_pass
+                    }),
+                    max_query_job_results: opts.max_query_job_results.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.max_query_job_results
+                    }),
+                    query_job_ttl: opts
+                        .query_job_ttl
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.query_job_ttl),
+                    rpc_request_timeout: opts
+                        .rpc_request_timeout
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.rpc_request_timeout),
+                    max_rpc_tip_fork_depth: opts.max_rpc_tip_fork_depth.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.max_rpc_tip_fork_depth
+                    }),
+                    max_download_peers: opts
+                        .max_download_peers
+                        .unwrap_or_else(|| HELIUM_DEFAULT_CONNECTION_OPTIONS.max_download_peers),
+                    max_download_bandwidth: opts.max_download_bandwidth.unwrap_or_else(|| {
+                        HELIUM_DEFAULT_CONNECTION_OPTIONS.max_download_bandwidth
+                    }),
+                    compact_block_prefill_window_ms: opts
+                        .compact_block_prefill_window_ms
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.compact_block_prefill_window_ms
+                        }),
+                    compact_block_max_prefill_txs: opts.compact_block_max_prefill_txs.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.compact_block_max_prefill_txs,
+                    ),
+                    max_microblock_subscribers: opts.max_microblock_subscribers.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.max_microblock_subscribers,
+                    ),
+                    microblock_push_max_pending: opts.microblock_push_max_pending.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.microblock_push_max_pending,
+                    ),
+                    max_concurrent_handshakes: opts.max_concurrent_handshakes.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.max_concurrent_handshakes,
+                    ),
+                    min_concurrent_handshakes: opts.min_concurrent_handshakes.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.min_concurrent_handshakes,
+                    ),
+                    saturated_network_pass_ms: opts.saturated_network_pass_ms.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.saturated_network_pass_ms,
+                    ),
+                    network_mode: match opts.network_mode {
+                        Some(mode) => NetworkMode::from_string(&mode).unwrap_or_else(|| {
+                            panic!(
+                                "Invalid network_mode '{}' -- expected full, listener, leecher, or replica",
+                                mode
+                            )
+                        }),
+                        None => HELIUM_DEFAULT_CONNECTION_OPTIONS.network_mode,
+                    },
+                    p2p_tls_cert_file: opts.p2p_tls_cert_file,
+                    p2p_tls_key_file: opts.p2p_tls_key_file,
+                    p2p_tls_ca_file: opts.p2p_tls_ca_file,
+                    p2p_tls_require_client_cert: opts.p2p_tls_require_client_cert.unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.p2p_tls_require_client_cert,
+                    ),
+                    async_io_threads: opts.async_io_threads.map(|x| x as usize).unwrap_or_else(
+                        || HELIUM_DEFAULT_CONNECTION_OPTIONS.async_io_threads,
+                    ),
+                    async_io_max_inbound_bytes: opts
+                        .async_io_max_inbound_bytes
+                        .map(|x| x as usize)
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.async_io_max_inbound_bytes
+                        }),
+                    async_io_max_outbound_bytes: opts
+                        .async_io_max_outbound_bytes
+                        .map(|x| x as usize)
+                        .unwrap_or_else(|| {
+                            HELIUM_DEFAULT_CONNECTION_OPTIONS.async_io_max_outbound_bytes
+                        }),
                     ..ConnectionOptions::default()
                 }
             }
@@ -842,6 +1287,75 @@ impl Config {
 
         let block_limit = BLOCK_LIMIT_MAINNET.clone();
 
+        let mut atlas = AtlasConfig::default(burnchain.mode == "mainnet");
+        let atlas_file = config_file.atlas.unwrap_or_default();
+        if let Some(contracts) = atlas_file.contracts {
+            for contract_id in contracts {
+                let contract_id = QualifiedContractIdentifier::parse(&contract_id)
+                    .expect("Invalid atlas contract_id");
+                atlas.contracts.insert(contract_id);
+            }
+        }
+        if let Some(domains) = atlas_file.domain {
+            for domain in domains {
+                let contract_id = QualifiedContractIdentifier::parse(&domain.contract_id)
+                    .expect("Invalid atlas domain contract_id");
+                let attachments_max_size = domain
+                    .attachments_max_size
+                    .unwrap_or(atlas.attachments_max_size);
+                if attachments_max_size == 0 {
+                    panic!(
+                        "Invalid atlas domain for {} -- attachments_max_size must be positive",
+                        contract_id
+                    );
+                }
+                atlas.add_domain(
+                    contract_id,
+                    AttachmentDomainConfig {
+                        attachments_max_size,
+                        unresolved_attachment_instances_expire_after: domain
+                            .unresolved_attachment_instances_expire_after
+                            .unwrap_or(atlas.unresolved_attachment_instances_expire_after),
+                    },
+                );
+            }
+        }
+        atlas.retention.max_total_size = atlas_file.max_attachments_total_size;
+        atlas.retention.max_age_blocks = atlas_file.attachments_max_age_blocks;
+        if let Some(sweep_interval) = atlas_file.attachments_sweep_interval {
+            atlas.retention.sweep_interval = sweep_interval;
+        }
+        if let Some(mirror_file) = atlas_file.mirror {
+            let mut mirror = AttachmentMirrorConfig::new(
+                &mirror_file.endpoint,
+                &mirror_file.bucket,
+                &mirror_file.access_key_id,
+                &mirror_file.secret_access_key,
+            );
+            if let Some(prefix) = mirror_file.prefix {
+                mirror.prefix = prefix;
+            }
+            if let Some(region) = mirror_file.region {
+                mirror.region = region;
+            }
+            if let Some(read_fallback) = mirror_file.read_fallback {
+                mirror.read_fallback = read_fallback;
+            }
+            if let Some(timeout) = mirror_file.timeout {
+                mirror.timeout = timeout;
+            }
+            if let Some(max_uploads_per_pass) = mirror_file.max_uploads_per_pass {
+                mirror.max_uploads_per_pass = max_uploads_per_pass;
+            }
+            if let Some(upload_interval) = mirror_file.upload_interval {
+                mirror.upload_interval = upload_interval;
+            }
+            if let Err(e) = mirror.validate() {
+                panic!("Invalid [atlas.mirror] config -- {}", e);
+            }
+            atlas.mirror = Some(mirror);
+        }
+
         Config {
             node,
             burnchain,
@@ -849,9 +1363,84 @@ impl Config {
             events_observers,
             connection_options,
             block_limit,
+            atlas,
         }
     }
 
+    /// Build one config per `[network.<name>]` section of a multi-network config file.
+    /// Each network keeps its databases under `<working_dir>/<name>`, so networks never share
+    /// state even when they share a working directory. Fails on bad names, on binds that
+    /// overlap, and on settings that would share process-wide state between networks.
+    pub fn from_network_config_files(
+        networks: BTreeMap<String, ConfigFile>,
+    ) -> Result<Vec<(String, Config)>, String> {
+        if networks.is_empty() {
+            return Err("No networks configured".to_string());
+        }
+        let shares_process = networks.len() > 1;
+
+        let mut binds: Vec<(SocketAddr, String)> = vec![];
+        let mut confs = vec![];
+        for (name, network_file) in networks.into_iter() {
+            if name.is_empty()
+                || !name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                return Err(format!(
+                    "Invalid network name '{}': use only letters, digits, '-' and '_'",
+                    name
+                ));
+            }
+            if network_file.network.is_some() {
+                return Err(format!("Network '{}' cannot define nested networks", name));
+            }
+
+            let mut conf = Config::from_config_file(network_file);
+            conf.node.working_dir = PathBuf::from(&conf.node.working_dir)
+                .join(&name)
+                .to_str()
+                .expect("Unable to produce path")
+                .to_string();
+            conf.node.shares_process = shares_process;
+
+            // the metrics registry is process-wide, so it can't be served per network
+            if shares_process && conf.node.prometheus_bind.is_some() {
+                return Err(format!(
+                    "Network '{}': prometheus_bind cannot be set when running more than one network",
+                    name
+                ));
+            }
+
+            let mut network_binds = vec![conf.node.rpc_bind.clone(), conf.node.p2p_bind.clone()];
+            if let Some(prometheus_bind) = conf.node.prometheus_bind.as_ref() {
+                network_binds.push(prometheus_bind.clone());
+            }
+            for bind in network_binds.into_iter() {
+                let addr: SocketAddr = bind
+                    .parse()
+                    .map_err(|_| format!("Network '{}': invalid bind address '{}'", name, bind))?;
+                // a wildcard address overlaps every address on the same port
+                let overlap = binds.iter().find(|(other_addr, _)| {
+                    other_addr.port() == addr.port()
+                        && (other_addr.ip() == addr.ip()
+                            || other_addr.ip().is_unspecified()
+                            || addr.ip().is_unspecified())
+                });
+                if let Some((other_addr, other)) = overlap {
+                    return Err(format!(
+                        "Networks '{}' and '{}' bind overlapping addresses {} and {}",
+                        other, name, other_addr, addr
+                    ));
+                }
+                binds.push((addr, name.clone()));
+            }
+
+            confs.push((name, conf));
+        }
+        Ok(confs)
+    }
+
     fn get_burnchain_path(&self) -> PathBuf {
         let mut path = PathBuf::from(&self.node.working_dir);
         path.push(&self.burnchain.mode);
@@ -911,6 +1500,12 @@ impl Config {
         path.to_str().expect("Unable to produce path").to_string()
     }
 
+    pub fn get_event_queue_file_path(&self) -> String {
+        let mut path = self.get_chainstate_path();
+        path.set_file_name("event_observers.sqlite");
+        path.to_str().expect("Unable to produce path").to_string()
+    }
+
     pub fn add_initial_balance(&mut self, address: String, amount: u64) {
         let new_balance = InitialBalance {
             address: PrincipalData::parse_standard_principal(&address)
@@ -962,6 +1557,7 @@ impl std::default::Default for Config {
             events_observers: vec![],
             connection_options,
             block_limit,
+            atlas: AtlasConfig::default(false),
         }
     }
 }
@@ -990,6 +1586,21 @@ pub struct BurnchainConfig {
     pub leader_key_tx_estimated_size: u64,
     pub block_commit_tx_estimated_size: u64,
     pub rbf_fee_increment: u64,
+    /// Use bitcoind's `estimatesmartfee` to pick the block-commit fee rate,
+    /// falling back to `satoshis_per_byte` if no estimate is available.
+    pub fee_estimation: bool,
+    pub fee_estimation_conf_target: u64,
+    /// Upper bound on the block-commit fee rate (sat/vB), estimated or not.
+    pub max_satoshis_per_byte: Option<u64>,
+    /// Upper bound on the total fee (in sats) paid by a single block-commit,
+    /// including RBF attempts.
+    pub max_commit_tx_fee: Option<u64>,
+    /// Delete orphaned burnchain blocks from the burnchain DB once they are
+    /// `burnchain_db_prune_depth` blocks below the canonical burnchain tip, along with
+    /// the ops of canonical blocks that deep whose sortitions can no longer be
+    /// reprocessed (PreStx ops are kept).
+    pub prune_burnchain_db: bool,
+    pub burnchain_db_prune_depth: u64,
 }
 
 impl BurnchainConfig {
@@ -1017,6 +1628,12 @@ impl BurnchainConfig {
             leader_key_tx_estimated_size: LEADER_KEY_TX_ESTIM_SIZE,
             block_commit_tx_estimated_size: BLOCK_COMMIT_TX_ESTIM_SIZE,
             rbf_fee_increment: DEFAULT_RBF_FEE_RATE_INCREMENT,
+            fee_estimation: false,
+            fee_estimation_conf_target: DEFAULT_FEE_ESTIMATION_CONF_TARGET,
+            max_satoshis_per_byte: None,
+            max_commit_tx_fee: None,
+            prune_burnchain_db: false,
+            burnchain_db_prune_depth: BURNCHAIN_DB_DEFAULT_PRUNE_DEPTH,
         }
     }
 
@@ -1070,6 +1687,12 @@ pub struct BurnchainConfigFile {
     pub block_commit_tx_estimated_size: Option<u64>,
     pub rbf_fee_increment: Option<u64>,
     pub max_rbf: Option<u64>,
+    pub fee_estimation: Option<bool>,
+    pub fee_estimation_conf_target: Option<u64>,
+    pub max_satoshis_per_byte: Option<u64>,
+    pub max_commit_tx_fee: Option<u64>,
+    pub prune_burnchain_db: Option<bool>,
+    pub burnchain_db_prune_depth: Option<u64>,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -1093,6 +1716,77 @@ pub struct NodeConfig {
     pub prometheus_bind: Option<String>,
     pub pox_sync_sample_secs: u64,
     pub use_test_genesis_chainstate: Option<bool>,
+    pub tip_selection_policy: TipSelectionPolicy,
+    /// Service bits advertised to peers in the handshake (see `ServiceFlags`).
+    pub services: u16,
+    /// Abandon and restart block assembly if a newer Stacks chain tip arrives mid-tenure.
+    pub preempt_on_new_parent: bool,
+    /// Abandon and restart block assembly if the parent's microblock stream grows.
+    pub preempt_on_microblocks: bool,
+    /// Minimum total fee (in microSTX) the new microblocks must carry to preempt assembly.
+    pub preempt_microblock_min_fee: u64,
+    /// How often (in milliseconds) block assembly checks whether it should be preempted.
+    pub preempt_check_interval_ms: u64,
+    /// Maximum number of times block assembly may be restarted within a single tenure.
+    pub max_preemptions: u64,
+    /// Addresses whose transactions are mined ahead of the fee-ordered mempool walk.
+    pub priority_principals: Vec<StacksAddress>,
+    /// Percent of the block budget reserved for the priority principals each tenure.
+    pub priority_budget_pct: u64,
+    /// Checkpoint block assembly progress, and resume from it after a restart mid-tenure.
+    pub resume_block_assembly: bool,
+    /// Working directory of the primary node whose databases this node serves, when running
+    /// with `connection.network_mode = "replica"`.
+    pub replica_of: Option<String>,
+    /// Free disk space below which the node refuses pushes, pauses downloads, or halts.
+    pub disk_space_policy: DiskSpacePolicy,
+    /// Number of threads that analyze the contracts deployed in a block before it is processed.
+    /// Fewer than 2 disables it.
+    pub parallel_analysis_workers: u64,
+    /// Whether to keep per-contract execution cost statistics, served at `/v2/contracts/costs`.
+    pub contract_cost_stats: bool,
+    /// Whether other networks run in this process.  If so, this network leaves the
+    /// process-wide monitoring state alone: the Prometheus registry, its persisted counters,
+    /// and the global burnchain signer.
+    pub shares_process: bool,
+}
+
+/// How a miner chooses which Stacks chain tip to build on when more than one
+/// processed block exists at the canonical tip's height.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TipSelectionPolicy {
+    /// Build on the canonical tip, i.e. the first block processed at that height (the default).
+    FirstSeen,
+    /// Build on the candidate tip whose block paid the most transaction fees.
+    HighestFees,
+    /// Build on the candidate tip that the most block-commits in the
+    /// current burnchain block already build on.
+    MostConfirmations,
+}
+
+impl Default for TipSelectionPolicy {
+    fn default() -> TipSelectionPolicy {
+        TipSelectionPolicy::FirstSeen
+    }
+}
+
+impl TipSelectionPolicy {
+    pub fn from_string(raw_policy: &str) -> Option<TipSelectionPolicy> {
+        match raw_policy {
+            "first_seen" => Some(TipSelectionPolicy::FirstSeen),
+            "highest_fees" => Some(TipSelectionPolicy::HighestFees),
+            "most_confirmations" => Some(TipSelectionPolicy::MostConfirmations),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TipSelectionPolicy::FirstSeen => "first_seen",
+            TipSelectionPolicy::HighestFees => "highest_fees",
+            TipSelectionPolicy::MostConfirmations => "most_confirmations",
+        }
+    }
 }
 
 impl NodeConfig {
@@ -1134,6 +1828,32 @@ impl NodeConfig {
             prometheus_bind: None,
             pox_sync_sample_secs: 30,
             use_test_genesis_chainstate: None,
+            tip_selection_policy: TipSelectionPolicy::FirstSeen,
+            services: ServiceFlags::to_services(&[
+                ServiceFlags::RELAY,
+                ServiceFlags::RPC,
+                ServiceFlags::ARCHIVAL,
+                ServiceFlags::ATLAS,
+                ServiceFlags::COMPACT_BLOCKS,
+                ServiceFlags::ATTACHMENTS_BATCH,
+                ServiceFlags::COMPRESSION,
+                ServiceFlags::PAGED_BLOCKS_INV,
+                ServiceFlags::MICROBLOCK_PUSH,
+                ServiceFlags::ATTACHMENT_RANGES,
+            ]),
+            preempt_on_new_parent: true,
+            preempt_on_microblocks: false,
+            preempt_microblock_min_fee: 0,
+            preempt_check_interval_ms: 1_000,
+            max_preemptions: 3,
+            priority_principals: vec![],
+            priority_budget_pct: 10,
+            resume_block_assembly: true,
+            replica_of: None,
+            disk_space_policy: DiskSpacePolicy::default(),
+            parallel_analysis_workers: 0,
+            contract_cost_stats: false,
+            shares_process: false,
         }
     }
 
@@ -1211,6 +1931,22 @@ impl NodeConfig {
             }
         }
     }
+
+    /// The node's p2p private key, derived from `local_peer_seed`.  Also used to sign the
+    /// payloads sent to event observers.
+    pub fn get_node_private_key(&self) -> Secp256k1PrivateKey {
+        let mut re_hashed_seed = self.local_peer_seed.clone();
+        loop {
+            match Secp256k1PrivateKey::from_slice(&re_hashed_seed[..]) {
+                Ok(sk) => break sk,
+                Err(_) => {
+                    re_hashed_seed = Sha256Sum::from_data(&re_hashed_seed[..])
+                        .as_bytes()
+                        .to_vec()
+                }
+            }
+        }
+    }
 }
 
 #[derive(Clone, Default, Deserialize)]
@@ -1226,6 +1962,8 @@ pub struct ConnectionOptionsFile {
     pub num_neighbors: Option<u64>,
     pub num_clients: Option<u64>,
     pub max_http_clients: Option<u64>,
+    pub max_http_connections_per_host: Option<u64>,
+    pub http_client_idle_timeout: Option<u64>,
     pub soft_num_neighbors: Option<u64>,
     pub soft_num_clients: Option<u64>,
     pub max_neighbors_per_host: Option<u64>,
@@ -1238,12 +1976,15 @@ pub struct ConnectionOptionsFile {
     pub dns_timeout: Option<u128>,
     pub max_inflight_blocks: Option<u64>,
     pub max_inflight_attachments: Option<u64>,
+    pub max_attachments_per_request: Option<u64>,
     pub read_only_call_limit_write_length: Option<u64>,
     pub read_only_call_limit_read_length: Option<u64>,
     pub read_only_call_limit_write_count: Option<u64>,
     pub read_only_call_limit_read_count: Option<u64>,
     pub read_only_call_limit_runtime: Option<u64>,
     pub maximum_call_argument_size: Option<u32>,
+    pub maximum_transaction_body_size: Option<u32>,
+    pub maximum_request_body_size: Option<u32>,
     pub download_interval: Option<u64>,
     pub inv_sync_interval: Option<u64>,
     pub full_inv_sync_interval: Option<u64>,
@@ -1254,6 +1995,51 @@ pub struct ConnectionOptionsFile {
     pub disable_block_download: Option<bool>,
     pub force_disconnect_interval: Option<u64>,
     pub antientropy_public: Option<bool>,
+    pub max_payload_len: Option<u32>,
+    pub download_state_save_interval: Option<u64>,
+    pub enable_admin_rpc: Option<bool>,
+    pub public_ip_quorum: Option<u64>,
+    pub relay_min_fee_rate: Option<u64>,
+    pub relay_min_stx_transfer: Option<u64>,
+    pub relay_max_tx_len: Option<u64>,
+    pub relay_max_txs_per_origin: Option<u64>,
+    pub relay_max_tx_bytes_per_origin: Option<u64>,
+    pub inv_agreement_check_interval: Option<u64>,
+    pub inv_agreement_alert_pct: Option<u64>,
+    pub inv_agreement_alert_duration: Option<u64>,
+    pub peer_version_check_interval: Option<u64>,
+    pub peer_version_alert_pct: Option<u64>,
+    pub data_url_health_interval: Option<u64>,
+    pub rehandshake_interval: Option<u64>,
+    pub staging_backlog_check_interval: Option<u64>,
+    pub max_staging_blocks_backlog: Option<u64>,
+    pub max_rpc_page_size: Option<u32>,
+    pub rpc_cache_max_entries: Option<u64>,
+    pub rpc_idempotency_key_ttl: Option<u64>,
+    pub rpc_idempotency_max_keys: Option<u64>,
+    pub max_query_jobs: Option<u64>,
+    pub query_job_blocks_per_pass: Option<u64>,
+    pub max_query_job_results: Option<u64>,
+    pub query_job_ttl: Option<u64>,
+    pub rpc_request_timeout: Option<u64>,
+    pub max_rpc_tip_fork_depth: Option<u64>,
+    pub max_download_peers: Option<u64>,
+    pub max_download_bandwidth: Option<u64>,
+    pub compact_block_prefill_window_ms: Option<u64>,
+    pub compact_block_max_prefill_txs: Option<u64>,
+    pub max_microblock_subscribers: Option<u64>,
+    pub microblock_push_max_pending: Option<u64>,
+    pub max_concurrent_handshakes: Option<u64>,
+    pub min_concurrent_handshakes: Option<u64>,
+    pub saturated_network_pass_ms: Option<u64>,
+    pub network_mode: Option<String>,
+    pub p2p_tls_cert_file: Option<String>,
+    pub p2p_tls_key_file: Option<String>,
+    pub p2p_tls_ca_file: Option<String>,
+    pub p2p_tls_require_client_cert: Option<bool>,
+    pub async_io_threads: Option<u64>,
+    pub async_io_max_inbound_bytes: Option<u64>,
+    pub async_io_max_outbound_bytes: Option<u64>,
 }
 
 #[derive(Clone, Deserialize, Default)]
@@ -1277,18 +2063,164 @@ pub struct NodeConfigFile {
     pub prometheus_bind: Option<String>,
     pub pox_sync_sample_secs: Option<u64>,
     pub use_test_genesis_chainstate: Option<bool>,
+    pub tip_selection_policy: Option<String>,
+    pub services: Option<Vec<String>>,
+    pub preempt_on_new_parent: Option<bool>,
+    pub preempt_on_microblocks: Option<bool>,
+    pub preempt_microblock_min_fee: Option<u64>,
+    pub preempt_check_interval_ms: Option<u64>,
+    pub max_preemptions: Option<u64>,
+    pub priority_principals: Option<Vec<String>>,
+    pub priority_budget_pct: Option<u64>,
+    pub resume_block_assembly: Option<bool>,
+    pub replica_of: Option<String>,
+    pub disk_check_interval: Option<u64>,
+    pub disk_alert_free_mb: Option<u64>,
+    pub disk_stop_pushes_free_mb: Option<u64>,
+    pub disk_pause_downloads_free_mb: Option<u64>,
+    pub disk_halt_free_mb: Option<u64>,
+    pub parallel_analysis_workers: Option<u64>,
+    pub contract_cost_stats: Option<bool>,
+}
+
+/// Contracts to track attachments for, in addition to BNS, e.g.
+/// ```toml
+/// [atlas]
+/// contracts = ["SP000000000000000000002Q6VF78.attestations"]
+/// ```
+/// Listed contracts use the default limits; use `[[atlas.domain]]` to give a contract its own.
+/// Downloaded attachments are kept forever unless `max_attachments_total_size` (bytes) or
+/// `attachments_max_age_blocks` is set, in which case they are pruned every
+/// `attachments_sweep_interval` seconds.
+#[derive(Clone, Deserialize, Default)]
+pub struct AtlasConfigFile {
+    pub contracts: Option<Vec<String>>,
+    pub domain: Option<Vec<AttachmentDomainConfigFile>>,
+    pub max_attachments_total_size: Option<u64>,
+    pub attachments_max_age_blocks: Option<u64>,
+    pub attachments_sweep_interval: Option<u64>,
+    pub mirror: Option<AttachmentMirrorConfigFile>,
+}
+
+/// S3-compatible object storage to copy attachment content to, e.g.
+/// ```toml
+/// [atlas.mirror]
+/// endpoint = "http://127.0.0.1:9000"
+/// bucket = "atlas"
+/// access_key_id = "..."
+/// secret_access_key = "..."
+/// ```
+/// With a mirror, the retention policy only prunes attachments that were uploaded, and unless
+/// `read_fallback = false` pruned attachments are still served, from the mirror.
+#[derive(Clone, Deserialize, Default)]
+pub struct AttachmentMirrorConfigFile {
+    pub endpoint: String,
+    pub bucket: String,
+    pub prefix: Option<String>,
+    pub region: Option<String>,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub read_fallback: Option<bool>,
+    pub timeout: Option<u64>,
+    pub max_uploads_per_pass: Option<u64>,
+    pub upload_interval: Option<u64>,
+}
+
+/// Attachment limits for one contract, e.g.
+/// ```toml
+/// [[atlas.domain]]
+/// contract_id = "SP000000000000000000002Q6VF78.attestations"
+/// attachments_max_size = 512
+/// unresolved_attachment_instances_expire_after = 3600
+/// ```
+#[derive(Clone, Deserialize, Default)]
+pub struct AttachmentDomainConfigFile {
+    pub contract_id: String,
+    pub attachments_max_size: Option<u32>,
+    pub unresolved_attachment_instances_expire_after: Option<u32>,
 }
 
 #[derive(Clone, Deserialize, Default)]
 pub struct EventObserverConfigFile {
     pub endpoint: String,
     pub events_keys: Vec<String>,
+    pub payload_encoding: Option<String>,
+    pub redact: Option<Vec<String>>,
+    pub redaction_salt: Option<String>,
+    pub max_print_events_per_tx: Option<u64>,
+    pub max_print_bytes_per_tx: Option<u64>,
 }
 
 #[derive(Clone, Default)]
 pub struct EventObserverConfig {
     pub endpoint: String,
     pub events_keys: Vec<EventKeyType>,
+    pub payload_encoding: EventPayloadEncoding,
+    pub redaction: EventRedactionPolicy,
+    pub limits: EventLimitPolicy,
+}
+
+/// What to strip from, or rewrite in, the payloads sent to an observer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventRedactionPolicy {
+    /// Leave out raw transaction bytes
+    pub omit_raw_tx: bool,
+    /// Zero the memos of STX transfers in raw transactions
+    pub omit_memos: bool,
+    /// Replace standard principals with the SHA-256 of the salt and the principal
+    pub hash_principals: bool,
+    pub principal_hash_salt: String,
+}
+
+impl EventRedactionPolicy {
+    pub fn is_empty(&self) -> bool {
+        !self.omit_raw_tx && !self.omit_memos && !self.hash_principals
+    }
+}
+
+/// Per-transaction limits on the print events sent to an observer.  Print events past either
+/// limit are sent without their values, marked as truncated.  0 means no limit.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EventLimitPolicy {
+    pub max_print_events_per_tx: u64,
+    pub max_print_bytes_per_tx: u64,
+}
+
+impl EventLimitPolicy {
+    pub fn is_empty(&self) -> bool {
+        self.max_print_events_per_tx == 0 && self.max_print_bytes_per_tx == 0
+    }
+}
+
+/// How event payloads are encoded in the bodies POSTed to an observer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventPayloadEncoding {
+    JSON,
+    /// The message for each path from `proto/event_observer.proto`
+    Protobuf,
+}
+
+impl Default for EventPayloadEncoding {
+    fn default() -> EventPayloadEncoding {
+        EventPayloadEncoding::JSON
+    }
+}
+
+impl EventPayloadEncoding {
+    fn from_string(raw: &str) -> Option<EventPayloadEncoding> {
+        match raw {
+            "json" => Some(EventPayloadEncoding::JSON),
+            "protobuf" => Some(EventPayloadEncoding::Protobuf),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            EventPayloadEncoding::JSON => "application/json",
+            EventPayloadEncoding::Protobuf => "application/x-protobuf",
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -1300,6 +2232,8 @@ pub enum EventKeyType {
     Microblocks,
     AnyEvent,
     BurnchainBlocks,
+    RewardCycles,
+    Sortitions,
 }
 
 impl EventKeyType {
@@ -1324,6 +2258,14 @@ impl EventKeyType {
             return Some(EventKeyType::Microblocks);
         }
 
+        if raw_key == "reward_cycles" {
+            return Some(EventKeyType::RewardCycles);
+        }
+
+        if raw_key == "sortitions" {
+            return Some(EventKeyType::Sortitions);
+        }
+
         let comps: Vec<_> = raw_key.split("::").collect();
         if comps.len() == 1 {
             let split: Vec<_> = comps[0].split(".").collect();
@@ -1,4 +1,5 @@
 use std::collections::hash_map::Entry;
+use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
 use std::{
@@ -12,10 +13,16 @@ use std::{
 use async_h1::client;
 use async_std::net::TcpStream;
 use http_types::{Method, Request, Url};
+use prost::Message;
 use serde_json::json;
 
+use stacks::burnchains::PrivateKey;
 use stacks::burnchains::Txid;
-use stacks::chainstate::coordinator::BlockEventDispatcher;
+use stacks::chainstate::burn::operations::LeaderBlockCommitOp;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::chainstate::coordinator::{
+    BlockEventDispatcher, PoxAnchorBlockStatus, RewardCycleInfo,
+};
 use stacks::chainstate::stacks::db::StacksHeaderInfo;
 use stacks::chainstate::stacks::events::{
     FTEventType, NFTEventType, STXEventType, StacksTransactionEvent, StacksTransactionReceipt,
@@ -28,19 +35,46 @@ use stacks::chainstate::stacks::{
 use stacks::codec::StacksMessageCodec;
 use stacks::core::mempool::{MemPoolDropReason, MemPoolEventDispatcher};
 use stacks::net::atlas::{Attachment, AttachmentInstance};
+use stacks::net::{EventObserverStatusSource, RPCEventObserverStatus};
+use stacks::net::{EventReplayRequestBody, EventReplaySource, RPCEventReplay};
 use stacks::types::chainstate::{BurnchainHeaderHash, StacksAddress, StacksBlockId};
-use stacks::util::hash::bytes_to_hex;
+use stacks::util::get_epoch_time_secs;
+use stacks::util::hash::{bytes_to_hex, Sha256Sum};
+use stacks::util::secp256k1::{Secp256k1PrivateKey, Secp256k1PublicKey};
 use stacks::vm::analysis::contract_interface_builder::build_contract_interface;
+use stacks::vm::costs::ExecutionCost;
 use stacks::vm::types::{AssetIdentifier, QualifiedContractIdentifier, Value};
 
-use super::config::{EventKeyType, EventObserverConfig};
+use super::config::{
+    EventKeyType, EventLimitPolicy, EventObserverConfig, EventPayloadEncoding,
+    EventRedactionPolicy,
+};
+use super::event_encoding;
+use super::event_limits;
+use super::event_queue::{EventRetryQueue, PendingEvent};
+use super::event_redaction;
+use super::event_replay::EventReplayer;
 use super::node::ChainTip;
 use stacks::chainstate::stacks::db::unconfirmed::ProcessedUnconfirmedState;
 
-#[derive(Debug, Clone)]
+/// Signs event payloads, and persists the ones that could not be delivered so they can be
+/// retried.  Shared by all of a dispatcher's observers.
+struct EventDelivery {
+    signing_key: Secp256k1PrivateKey,
+    public_key_hex: String,
+    queue: Mutex<EventRetryQueue>,
+    // held for the whole of a delivery, so each observer receives its events in order
+    send_lock: Mutex<()>,
+}
+
+#[derive(Clone)]
 struct EventObserver {
     endpoint: String,
     should_keep_running: Arc<AtomicBool>,
+    delivery: Option<Arc<EventDelivery>>,
+    payload_encoding: EventPayloadEncoding,
+    redaction: EventRedactionPolicy,
+    limits: EventLimitPolicy,
 }
 
 struct ReceiptPayloadInfo<'a> {
@@ -60,11 +94,28 @@ pub const PATH_MICROBLOCK_SUBMIT: &str = "new_microblocks";
 pub const PATH_MEMPOOL_TX_SUBMIT: &str = "new_mempool_tx";
 pub const PATH_MEMPOOL_TX_DROP: &str = "drop_mempool_tx";
 pub const PATH_BURN_BLOCK_SUBMIT: &str = "new_burn_block";
+pub const PATH_REWARD_CYCLE_SUBMIT: &str = "new_reward_cycle";
+pub const PATH_SORTITION_SUBMIT: &str = "new_sortition";
 pub const PATH_BLOCK_PROCESSED: &str = "new_block";
 pub const PATH_ATTACHMENT_PROCESSED: &str = "attachments/new";
 
+/// Headers carrying the node's public key and its signature over the SHA-256 of the POST body
+pub const HEADER_NODE_PUBLIC_KEY: &str = "X-Stacks-Node-Public-Key";
+pub const HEADER_PAYLOAD_SIGNATURE: &str = "X-Stacks-Signature";
+
+/// How often pending events are checked for redelivery
+const EVENT_RETRY_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
 impl EventObserver {
     fn send_payload(&self, payload: &serde_json::Value, path: &str) {
+        let redacted_payload;
+        let payload = if self.redaction.is_empty() {
+            payload
+        } else {
+            redacted_payload = event_redaction::redact_payload(&self.redaction, path, payload);
+            &redacted_payload
+        };
+
         let body = match serde_json::to_vec(&payload) {
             Ok(body) => body,
             Err(err) => {
@@ -72,19 +123,83 @@ impl EventObserver {
                 return;
             }
         };
+        self.send_body(body, path);
+    }
 
-        let url = {
-            let joined_components = match path.starts_with("/") {
-                true => format!("{}{}", &self.endpoint, path),
-                false => format!("{}/{}", &self.endpoint, path),
-            };
-            let url = format!("http://{}", joined_components);
-            Url::parse(&url).expect(&format!(
-                "Event dispatcher: unable to parse {} as a URL",
-                url
-            ))
+    /// Send a protobuf payload, already built with this observer's redaction policy.
+    fn send_message<M: Message>(&self, message: &M, path: &str) {
+        self.send_body(event_encoding::encode_message(message), path);
+    }
+
+    fn send_body(&self, body: Vec<u8>, path: &str) {
+        match self.delivery {
+            Some(ref delivery) => self.send_or_queue(delivery, body, path),
+            None => self.send_until_delivered(body, path),
+        }
+    }
+
+    fn is_json(&self) -> bool {
+        self.payload_encoding == EventPayloadEncoding::JSON
+    }
+
+    fn make_url(&self, path: &str) -> Url {
+        let joined_components = match path.starts_with("/") {
+            true => format!("{}{}", &self.endpoint, path),
+            false => format!("{}/{}", &self.endpoint, path),
         };
+        let url = format!("http://{}", joined_components);
+        Url::parse(&url).expect(&format!(
+            "Event dispatcher: unable to parse {} as a URL",
+            url
+        ))
+    }
 
+    /// Make a single attempt to POST `body` to this observer.
+    fn post(&self, path: &str, body: Vec<u8>, content_type: &str) -> Result<(), String> {
+        let url = self.make_url(path);
+        let mut req = Request::new(Method::Post, url.clone());
+        req.append_header("Content-Type", content_type)
+            .expect("Unable to set header");
+        if let Some(ref delivery) = self.delivery {
+            let digest = Sha256Sum::from_data(&body);
+            let signature = delivery
+                .signing_key
+                .sign(digest.as_bytes())
+                .expect("FATAL: failed to sign event payload");
+            req.append_header(HEADER_NODE_PUBLIC_KEY, delivery.public_key_hex.as_str())
+                .expect("Unable to set header");
+            req.append_header(HEADER_PAYLOAD_SIGNATURE, signature.to_hex().as_str())
+                .expect("Unable to set header");
+        }
+        req.set_body(body);
+
+        async_std::task::block_on(async {
+            let stream = match TcpStream::connect(self.endpoint.clone()).await {
+                Ok(stream) => stream,
+                Err(err) => {
+                    return Err(format!("connection failed - {:?}", err));
+                }
+            };
+
+            match client::connect(stream, req).await {
+                Ok(response) => {
+                    if response.status().is_success() {
+                        Ok(())
+                    } else {
+                        Err(format!(
+                            "POST {} failed with status {}",
+                            &url,
+                            response.status()
+                        ))
+                    }
+                }
+                Err(err) => Err(format!("rpc invokation failed - {:?}", err)),
+            }
+        })
+    }
+
+    /// Deliver `body`, retrying until it is accepted or the node shuts down.
+    fn send_until_delivered(&self, body: Vec<u8>, path: &str) {
         let backoff = Duration::from_millis((1.0 * 1_000.0) as u64);
 
         loop {
@@ -93,47 +208,194 @@ impl EventObserver {
                 return;
             }
 
-            let body = body.clone();
-            let mut req = Request::new(Method::Post, url.clone());
-            req.append_header("Content-Type", "application/json")
-                .expect("Unable to set header");
-            req.set_body(body);
-
-            let response = async_std::task::block_on(async {
-                let stream = match TcpStream::connect(self.endpoint.clone()).await {
-                    Ok(stream) => stream,
-                    Err(err) => {
-                        println!("Event dispatcher: connection failed  - {:?}", err);
-                        return None;
-                    }
-                };
-
-                match client::connect(stream, req).await {
-                    Ok(response) => Some(response),
-                    Err(err) => {
-                        println!("Event dispatcher: rpc invokation failed  - {:?}", err);
-                        return None;
-                    }
+            match self.post(path, body.clone(), self.payload_encoding.content_type()) {
+                Ok(()) => break,
+                Err(err) => {
+                    error!(
+                        "Event dispatcher: delivery to {} failed - {}",
+                        self.endpoint, err
+                    );
                 }
+            }
+            sleep(backoff);
+        }
+    }
+
+    /// Try to deliver `body` once.  If that fails -- or if earlier events are still waiting to
+    /// be redelivered -- queue it to be retried in the background.
+    fn send_or_queue(&self, delivery: &EventDelivery, body: Vec<u8>, path: &str) {
+        let _send_guard = delivery
+            .send_lock
+            .lock()
+            .expect("FATAL: event delivery lock poisoned");
+
+        let num_pending = delivery
+            .queue
+            .lock()
+            .expect("FATAL: event retry queue lock poisoned")
+            .num_pending(&self.endpoint)
+            .unwrap_or_else(|e| {
+                error!(
+                    "Event dispatcher: failed to read retry queue for {}: {:?}",
+                    self.endpoint, e
+                );
+                0
             });
 
-            if let Some(response) = response {
-                if response.status().is_success() {
-                    break;
-                } else {
+        let now = get_epoch_time_secs();
+        let (attempts, last_error) = if num_pending > 0 {
+            // wait behind the events that have yet to be delivered
+            (0, None)
+        } else {
+            match self.post(path, body.clone(), self.payload_encoding.content_type()) {
+                Ok(()) => {
+                    delivery
+                        .queue
+                        .lock()
+                        .expect("FATAL: event retry queue lock poisoned")
+                        .note_delivered(&self.endpoint, now);
+                    return;
+                }
+                Err(err) => {
+                    warn!(
+                        "Event dispatcher: delivery to {} failed, will retry - {}",
+                        self.endpoint, err
+                    );
+                    (1, Some(err))
+                }
+            }
+        };
+
+        let mut queue = delivery
+            .queue
+            .lock()
+            .expect("FATAL: event retry queue lock poisoned");
+        if attempts > 0 {
+            queue.note_failed_attempt(&self.endpoint);
+        }
+        if let Err(e) = queue.push(
+            &self.endpoint,
+            path,
+            &body,
+            self.payload_encoding.content_type(),
+            now,
+            attempts,
+            last_error.as_ref().map(|err| err.as_str()),
+        ) {
+            error!(
+                "Event dispatcher: failed to queue undelivered event for {}: {:?}",
+                self.endpoint, e
+            );
+        }
+    }
+
+    /// Redeliver this observer's pending events, oldest first, until one fails or is not yet
+    /// due for another attempt.
+    fn retry_pending(&self, delivery: &EventDelivery) {
+        let _send_guard = delivery
+            .send_lock
+            .lock()
+            .expect("FATAL: event delivery lock poisoned");
+
+        while self.should_keep_running.load(Ordering::SeqCst) {
+            let next_event: Option<PendingEvent> = match delivery
+                .queue
+                .lock()
+                .expect("FATAL: event retry queue lock poisoned")
+                .peek(&self.endpoint)
+            {
+                Ok(event_opt) => event_opt,
+                Err(e) => {
                     error!(
-                        "Event dispatcher: POST {}/{} failed with error {:?}",
-                        self.endpoint, &url, response
+                        "Event dispatcher: failed to read retry queue for {}: {:?}",
+                        self.endpoint, e
                     );
+                    return;
+                }
+            };
+
+            let event = match next_event {
+                Some(event) => event,
+                None => return,
+            };
+
+            let now = get_epoch_time_secs();
+            if event.next_attempt_at > now {
+                return;
+            }
+
+            // sent as it was encoded, even if the observer's encoding has changed since
+            let result = self.post(&event.path, event.body.clone(), &event.content_type);
+            let mut queue = delivery
+                .queue
+                .lock()
+                .expect("FATAL: event retry queue lock poisoned");
+            match result {
+                Ok(()) => {
+                    debug!(
+                        "Event dispatcher: redelivered event {} to {} after {} attempts",
+                        event.id, self.endpoint, event.attempts
+                    );
+                    queue.note_delivered(&self.endpoint, now);
+                    if let Err(e) = queue.remove(event.id) {
+                        error!(
+                            "Event dispatcher: failed to dequeue event {} for {}: {:?}",
+                            event.id, self.endpoint, e
+                        );
+                        return;
+                    }
+                }
+                Err(err) => {
+                    warn!(
+                        "Event dispatcher: redelivery to {} failed ({} attempts) - {}",
+                        self.endpoint,
+                        event.attempts + 1,
+                        err
+                    );
+                    queue.note_failed_attempt(&self.endpoint);
+                    if let Err(e) = queue.record_retry_failure(&event, now, &err) {
+                        error!(
+                            "Event dispatcher: failed to update event {} for {}: {:?}",
+                            event.id, self.endpoint, e
+                        );
+                    }
+                    return;
                 }
             }
-            sleep(backoff);
         }
     }
 
-    fn make_new_mempool_txs_payload(transactions: Vec<StacksTransaction>) -> serde_json::Value {
+    fn get_status(&self) -> RPCEventObserverStatus {
+        let status = match self.delivery {
+            Some(ref delivery) => delivery
+                .queue
+                .lock()
+                .expect("FATAL: event retry queue lock poisoned")
+                .get_status(&self.endpoint)
+                .map_err(|e| {
+                    error!(
+                        "Event dispatcher: failed to read retry queue for {}: {:?}",
+                        self.endpoint, e
+                    );
+                })
+                .ok(),
+            None => None,
+        };
+        status.unwrap_or_else(|| RPCEventObserverStatus {
+            endpoint: self.endpoint.clone(),
+            pending_events: 0,
+            oldest_pending_at: None,
+            next_attempt_at: None,
+            last_error: None,
+            delivered: 0,
+            failed_attempts: 0,
+            last_delivered_at: None,
+        })
+    }
+
+    fn make_new_mempool_txs_payload(transactions: &[StacksTransaction]) -> serde_json::Value {
         let raw_txs = transactions
-            .into_iter()
+            .iter()
             .map(|tx| {
                 serde_json::Value::String(format!("0x{}", &bytes_to_hex(&tx.serialize_to_vec())))
             })
@@ -145,12 +407,12 @@ impl EventObserver {
     fn make_new_burn_block_payload(
         burn_block: &BurnchainHeaderHash,
         burn_block_height: u64,
-        rewards: Vec<(StacksAddress, u64)>,
+        rewards: &[(StacksAddress, u64)],
         burns: u64,
-        slot_holders: Vec<StacksAddress>,
+        slot_holders: &[StacksAddress],
     ) -> serde_json::Value {
         let reward_recipients = rewards
-            .into_iter()
+            .iter()
             .map(|(stx_addr, amt)| {
                 json!({
                     "recipient": stx_addr.to_b58(),
@@ -160,7 +422,7 @@ impl EventObserver {
             .collect();
 
         let reward_slot_holders = slot_holders
-            .into_iter()
+            .iter()
             .map(|stx_addr| json!(stx_addr.to_b58()))
             .collect();
 
@@ -173,6 +435,90 @@ impl EventObserver {
         })
     }
 
+    fn make_new_reward_cycle_payload(
+        burn_block: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        reward_cycle: u64,
+        reward_cycle_info: &RewardCycleInfo,
+    ) -> serde_json::Value {
+        let anchor_block_status = match reward_cycle_info.anchor_status {
+            PoxAnchorBlockStatus::SelectedAndKnown(..) => "selected_known",
+            PoxAnchorBlockStatus::SelectedAndUnknown(..) => "selected_unknown",
+            PoxAnchorBlockStatus::NotSelected => "not_selected",
+        };
+
+        let anchor_block_hash = match reward_cycle_info.selected_anchor_block() {
+            Some(block_hash) => json!(format!("0x{}", block_hash)),
+            None => json!(null),
+        };
+
+        let reward_set = reward_cycle_info
+            .known_selected_anchor_block()
+            .map(|reward_set| {
+                reward_set
+                    .iter()
+                    .map(|stx_addr| json!(stx_addr.to_b58()))
+                    .collect()
+            })
+            .unwrap_or(vec![]);
+
+        json!({
+            "burn_block_hash": format!("0x{}", burn_block),
+            "burn_block_height": burn_block_height,
+            "reward_cycle": reward_cycle,
+            "anchor_block_status": anchor_block_status,
+            "anchor_block_hash": anchor_block_hash,
+            "reward_set": serde_json::Value::Array(reward_set),
+            "pox_active": reward_cycle_info.is_pox_active(),
+        })
+    }
+
+    fn make_new_sortition_payload(
+        snapshot: &BlockSnapshot,
+        block_commits: &[LeaderBlockCommitOp],
+    ) -> serde_json::Value {
+        let block_commits: Vec<_> = block_commits
+            .iter()
+            .map(|commit| {
+                let commit_outs: Vec<_> = commit
+                    .commit_outs
+                    .iter()
+                    .map(|addr| json!(addr.to_b58()))
+                    .collect();
+                json!({
+                    "txid": format!("0x{}", commit.txid),
+                    "vtxindex": commit.vtxindex,
+                    "block_header_hash": format!("0x{}", commit.block_header_hash),
+                    "burn_fee": commit.burn_fee,
+                    "sunset_burn": commit.sunset_burn,
+                    "commit_outs": commit_outs,
+                    "won": snapshot.sortition && commit.txid == snapshot.winning_block_txid,
+                })
+            })
+            .collect();
+
+        let (winning_block_txid, winning_stacks_block_hash) = if snapshot.sortition {
+            (
+                json!(format!("0x{}", snapshot.winning_block_txid)),
+                json!(format!("0x{}", snapshot.winning_stacks_block_hash)),
+            )
+        } else {
+            (json!(null), json!(null))
+        };
+
+        json!({
+            "burn_block_hash": format!("0x{}", snapshot.burn_header_hash),
+            "burn_block_height": snapshot.block_height,
+            "consensus_hash": format!("0x{}", snapshot.consensus_hash),
+            "sortition_id": format!("0x{}", snapshot.sortition_id),
+            "sortition": snapshot.sortition,
+            "winning_block_txid": winning_block_txid,
+            "winning_stacks_block_hash": winning_stacks_block_hash,
+            "total_burn": snapshot.total_burn,
+            "block_commits": block_commits,
+        })
+    }
+
     /// Returns tuple of (txid, success, raw_result, raw_tx, contract_interface_json)
     fn generate_payload_info_for_receipt(receipt: &StacksTransactionReceipt) -> ReceiptPayloadInfo {
         let tx = &receipt.transaction;
@@ -255,6 +601,33 @@ impl EventObserver {
         })
     }
 
+    fn make_matured_rewards_payload(
+        mature_rewards: &[MinerReward],
+        mature_rewards_info: Option<&MinerRewardInfo>,
+    ) -> serde_json::Value {
+        let mature_rewards_vec = if let Some(rewards_info) = mature_rewards_info {
+            mature_rewards
+                .iter()
+                .map(|reward| {
+                    json!({
+                        "recipient": reward.address.to_string(),
+                        "coinbase_amount": reward.coinbase.to_string(),
+                        "tx_fees_anchored": reward.tx_fees_anchored.to_string(),
+                        "tx_fees_streamed_confirmed": reward.tx_fees_streamed_confirmed.to_string(),
+                        "tx_fees_streamed_produced": reward.tx_fees_streamed_produced.to_string(),
+                        "from_stacks_block_hash": format!("0x{}", &rewards_info.from_stacks_block_hash),
+                        "from_index_consensus_hash": format!("0x{}", StacksBlockId::new(&rewards_info.from_block_consensus_hash,
+                                                                                        &rewards_info.from_stacks_block_hash)),
+                    })
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        serde_json::Value::Array(mature_rewards_vec)
+    }
+
     fn make_new_attachment_payload(
         attachment: &(AttachmentInstance, Attachment),
     ) -> serde_json::Value {
@@ -278,6 +651,49 @@ impl EventObserver {
         self.send_payload(payload, PATH_MEMPOOL_TX_SUBMIT);
     }
 
+    /// Serialize events to JSON in event_index order, applying this observer's limits
+    fn serialize_events(
+        &self,
+        mut filtered_events: Vec<(usize, &(bool, Txid, &StacksTransactionEvent))>,
+    ) -> Vec<serde_json::Value> {
+        filtered_events.sort_by_key(|(event_index, _)| *event_index);
+        let mut serialized_events: Vec<serde_json::Value> = filtered_events
+            .iter()
+            .map(|(event_index, (committed, txid, event))| {
+                event.json_serialize(*event_index, txid, *committed)
+            })
+            .collect();
+        if !self.limits.is_empty() {
+            event_limits::limit_print_events(&self.limits, &mut serialized_events);
+        }
+        serialized_events
+    }
+
+    /// Build protobuf events in event_index order, applying this observer's redaction policy
+    /// and limits
+    fn encode_events(
+        &self,
+        mut filtered_events: Vec<(usize, &(bool, Txid, &StacksTransactionEvent))>,
+    ) -> Vec<event_encoding::TransactionEvent> {
+        filtered_events.sort_by_key(|(event_index, _)| *event_index);
+        let mut encoded_events: Vec<event_encoding::TransactionEvent> = filtered_events
+            .iter()
+            .map(|(event_index, (committed, txid, event))| {
+                event_encoding::make_transaction_event(
+                    &self.redaction,
+                    *event_index,
+                    txid,
+                    *committed,
+                    event,
+                )
+            })
+            .collect();
+        if !self.limits.is_empty() {
+            event_limits::limit_print_event_messages(&self.limits, &mut encoded_events);
+        }
+        encoded_events
+    }
+
     /// Serializes new microblocks data into a JSON payload and sends it off to the correct path
     fn send_new_microblocks(
         &self,
@@ -286,12 +702,7 @@ impl EventObserver {
         serialized_txs: &Vec<serde_json::Value>,
     ) {
         // Serialize events to JSON
-        let serialized_events: Vec<serde_json::Value> = filtered_events
-            .iter()
-            .map(|(event_index, (committed, txid, event))| {
-                event.json_serialize(*event_index, txid, *committed)
-            })
-            .collect();
+        let serialized_events = self.serialize_events(filtered_events);
 
         let payload = json!({
             "parent_index_block_hash": format!("0x{}", parent_index_block_hash),
@@ -310,6 +721,14 @@ impl EventObserver {
         self.send_payload(payload, PATH_BURN_BLOCK_SUBMIT);
     }
 
+    fn send_new_reward_cycle(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_REWARD_CYCLE_SUBMIT);
+    }
+
+    fn send_new_sortition(&self, payload: &serde_json::Value) {
+        self.send_payload(payload, PATH_SORTITION_SUBMIT);
+    }
+
     fn send(
         &self,
         filtered_events: Vec<(usize, &(bool, Txid, &StacksTransactionEvent))>,
@@ -317,15 +736,26 @@ impl EventObserver {
         parent_index_hash: &StacksBlockId,
         boot_receipts: &Vec<StacksTransactionReceipt>,
         winner_txid: &Txid,
-        mature_rewards: &serde_json::Value,
+        mature_rewards: &[MinerReward],
+        mature_rewards_info: Option<&MinerRewardInfo>,
     ) {
+        if !self.is_json() {
+            let block_event = event_encoding::make_block_event(
+                &self.redaction,
+                chain_tip,
+                parent_index_hash,
+                boot_receipts,
+                winner_txid,
+                mature_rewards,
+                mature_rewards_info,
+                self.encode_events(filtered_events),
+            );
+            self.send_message(&block_event, PATH_BLOCK_PROCESSED);
+            return;
+        }
+
         // Serialize events to JSON
-        let serialized_events: Vec<serde_json::Value> = filtered_events
-            .iter()
-            .map(|(event_index, (committed, txid, event))| {
-                event.json_serialize(*event_index, txid, *committed)
-            })
-            .collect();
+        let serialized_events = self.serialize_events(filtered_events);
 
         let mut tx_index: u32 = 0;
         let mut serialized_txs = vec![];
@@ -348,7 +778,7 @@ impl EventObserver {
             "parent_block_hash": format!("0x{}", chain_tip.block.header.parent_block),
             "parent_index_block_hash": format!("0x{}", parent_index_hash),
             "parent_microblock": format!("0x{}", chain_tip.block.header.parent_microblock),
-            "matured_miner_rewards": mature_rewards.clone(),
+            "matured_miner_rewards": EventObserver::make_matured_rewards_payload(mature_rewards, mature_rewards_info),
             "events": serialized_events,
             "transactions": serialized_txs,
         });
@@ -364,11 +794,74 @@ pub struct EventDispatcher {
     contract_events_observers_lookup: HashMap<(QualifiedContractIdentifier, String), HashSet<u16>>,
     assets_observers_lookup: HashMap<AssetIdentifier, HashSet<u16>>,
     burn_block_observers_lookup: HashSet<u16>,
+    reward_cycle_observers_lookup: HashSet<u16>,
+    sortition_observers_lookup: HashSet<u16>,
     mempool_observers_lookup: HashSet<u16>,
     microblock_observers_lookup: HashSet<u16>,
     stx_observers_lookup: HashSet<u16>,
     any_event_observers_lookup: HashSet<u16>,
     boot_receipts: Arc<Mutex<Option<Vec<StacksTransactionReceipt>>>>,
+    delivery: Option<Arc<EventDelivery>>,
+    replayer: Option<Arc<EventReplayer>>,
+}
+
+impl EventObserverStatusSource for EventDispatcher {
+    fn get_event_observer_status(&self) -> Vec<RPCEventObserverStatus> {
+        self.registered_observers
+            .iter()
+            .map(|observer| observer.get_status())
+            .collect()
+    }
+}
+
+impl EventReplaySource for EventDispatcher {
+    fn start_event_replay(&self, request: &EventReplayRequestBody) -> Result<(), String> {
+        let replayer = self
+            .replayer
+            .clone()
+            .ok_or("Event replay is not enabled on this node".to_string())?;
+        let observer_id = self
+            .registered_observers
+            .iter()
+            .position(|observer| observer.endpoint == request.endpoint)
+            .ok_or(format!(
+                "No event observer is configured at {}",
+                &request.endpoint
+            ))?;
+        let rate = request.validate()?;
+        let replay_index = replayer.begin(request, rate)?;
+
+        let dispatcher = self.clone();
+        thread::Builder::new()
+            .name("event-replay".to_string())
+            .spawn(move || {
+                replayer.run(replay_index, |block, receipt, parent, winner_txid| {
+                    let chain_tip = ChainTip {
+                        metadata: receipt.header,
+                        block,
+                        receipts: receipt.tx_receipts,
+                    };
+                    dispatcher.dispatch_chain_tip(
+                        &chain_tip,
+                        parent,
+                        winner_txid,
+                        receipt.matured_rewards,
+                        receipt.matured_rewards_info,
+                        &vec![],
+                        Some(observer_id),
+                    );
+                })
+            })
+            .map_err(|e| format!("Failed to start event replay thread: {:?}", &e))?;
+        Ok(())
+    }
+
+    fn get_event_replays(&self) -> Vec<RPCEventReplay> {
+        self.replayer
+            .as_ref()
+            .map(|replayer| replayer.get_replays())
+            .unwrap_or(vec![])
+    }
 }
 
 impl MemPoolEventDispatcher for EventDispatcher {
@@ -421,6 +914,25 @@ impl BlockEventDispatcher for EventDispatcher {
         )
     }
 
+    fn announce_reward_cycle(
+        &self,
+        burn_block: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        reward_cycle: u64,
+        reward_cycle_info: &RewardCycleInfo,
+    ) {
+        self.process_reward_cycle(
+            burn_block,
+            burn_block_height,
+            reward_cycle,
+            reward_cycle_info,
+        )
+    }
+
+    fn announce_sortition(&self, snapshot: &BlockSnapshot, block_commits: &[LeaderBlockCommitOp]) {
+        self.process_sortition(snapshot, block_commits)
+    }
+
     fn dispatch_boot_receipts(&mut self, receipts: Vec<StacksTransactionReceipt>) {
         self.process_boot_receipts(receipts)
     }
@@ -435,12 +947,87 @@ impl EventDispatcher {
             stx_observers_lookup: HashSet::new(),
             any_event_observers_lookup: HashSet::new(),
             burn_block_observers_lookup: HashSet::new(),
+            reward_cycle_observers_lookup: HashSet::new(),
+            sortition_observers_lookup: HashSet::new(),
             mempool_observers_lookup: HashSet::new(),
             microblock_observers_lookup: HashSet::new(),
             boot_receipts: Arc::new(Mutex::new(None)),
+            delivery: None,
+            replayer: None,
         }
     }
 
+    /// Allow past blocks to be replayed to an observer on request, by re-executing them against
+    /// the chainstate at `chainstate_path`.
+    pub fn enable_event_replay(
+        &mut self,
+        mainnet: bool,
+        chain_id: u32,
+        chainstate_path: String,
+        burn_db_path: String,
+        block_limit: ExecutionCost,
+        should_keep_running: Arc<AtomicBool>,
+    ) {
+        self.replayer = Some(Arc::new(EventReplayer::new(
+            mainnet,
+            chain_id,
+            chainstate_path,
+            burn_db_path,
+            block_limit,
+            should_keep_running,
+        )));
+    }
+
+    /// Sign every payload with `signing_key`, and instead of blocking until an observer accepts
+    /// an event, queue undelivered events in the DB at `queue_path` and redeliver them in the
+    /// background with exponential backoff.  Events still queued from a previous run are
+    /// redelivered too.
+    pub fn enable_reliable_delivery(
+        &mut self,
+        signing_key: Secp256k1PrivateKey,
+        queue_path: &str,
+        should_keep_running: Arc<AtomicBool>,
+    ) -> Result<(), String> {
+        let queue = EventRetryQueue::open(queue_path)
+            .map_err(|e| format!("Failed to open event queue {}: {:?}", queue_path, &e))?;
+        match queue.total_pending() {
+            Ok(0) => {}
+            Ok(num_pending) => info!(
+                "Event dispatcher: {} undelivered events will be retried",
+                num_pending
+            ),
+            Err(e) => warn!(
+                "Event dispatcher: failed to count undelivered events: {:?}",
+                &e
+            ),
+        }
+
+        let delivery = Arc::new(EventDelivery {
+            public_key_hex: Secp256k1PublicKey::from_private(&signing_key).to_hex(),
+            signing_key,
+            queue: Mutex::new(queue),
+            send_lock: Mutex::new(()),
+        });
+        for observer in self.registered_observers.iter_mut() {
+            observer.delivery = Some(delivery.clone());
+        }
+        self.delivery = Some(delivery.clone());
+
+        let observers = self.registered_observers.clone();
+        thread::Builder::new()
+            .name("event-retry".to_string())
+            .spawn(move || {
+                while should_keep_running.load(Ordering::SeqCst) {
+                    for observer in observers.iter() {
+                        observer.retry_pending(&delivery);
+                    }
+                    sleep(EVENT_RETRY_POLL_INTERVAL);
+                }
+            })
+            .map_err(|e| format!("Failed to start event retry thread: {:?}", &e))?;
+        Ok(())
+    }
+
     pub fn process_burn_block(
         &self,
         burn_block: &BurnchainHeaderHash,
@@ -463,16 +1050,111 @@ impl EventDispatcher {
             return;
         }
 
-        let payload = EventObserver::make_new_burn_block_payload(
-            burn_block,
-            burn_block_height,
-            rewards,
-            burns,
-            recipient_info,
-        );
+        let mut payload = None;
+        for (_, observer) in interested_observers.iter() {
+            if observer.is_json() {
+                observer.send_new_burn_block(payload.get_or_insert_with(|| {
+                    EventObserver::make_new_burn_block_payload(
+                        burn_block,
+                        burn_block_height,
+                        &rewards,
+                        burns,
+                        &recipient_info,
+                    )
+                }));
+            } else {
+                let burn_block_event = event_encoding::make_burn_block_event(
+                    &observer.redaction,
+                    burn_block,
+                    burn_block_height,
+                    &rewards,
+                    burns,
+                    &recipient_info,
+                );
+                observer.send_message(&burn_block_event, PATH_BURN_BLOCK_SUBMIT);
+            }
+        }
+    }
+
+    pub fn process_reward_cycle(
+        &self,
+        burn_block: &BurnchainHeaderHash,
+        burn_block_height: u64,
+        reward_cycle: u64,
+        reward_cycle_info: &RewardCycleInfo,
+    ) {
+        // lazily assemble payload only if we have observers.  Observers subscribed to "*" don't
+        // get this event, since they may not serve its path -- it's opt-in only.
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.reward_cycle_observers_lookup
+                    .contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
 
+        let mut payload = None;
         for (_, observer) in interested_observers.iter() {
-            observer.send_new_burn_block(&payload);
+            if observer.is_json() {
+                observer.send_new_reward_cycle(payload.get_or_insert_with(|| {
+                    EventObserver::make_new_reward_cycle_payload(
+                        burn_block,
+                        burn_block_height,
+                        reward_cycle,
+                        reward_cycle_info,
+                    )
+                }));
+            } else {
+                let reward_cycle_event = event_encoding::make_reward_cycle_event(
+                    &observer.redaction,
+                    burn_block,
+                    burn_block_height,
+                    reward_cycle,
+                    reward_cycle_info,
+                );
+                observer.send_message(&reward_cycle_event, PATH_REWARD_CYCLE_SUBMIT);
+            }
+        }
+    }
+
+    pub fn process_sortition(
+        &self,
+        snapshot: &BlockSnapshot,
+        block_commits: &[LeaderBlockCommitOp],
+    ) {
+        // lazily assemble payload only if we have observers.  Observers subscribed to "*" don't
+        // get this event, since they may not serve its path -- it's opt-in only.
+        let interested_observers: Vec<_> = self
+            .registered_observers
+            .iter()
+            .enumerate()
+            .filter(|(obs_id, _observer)| {
+                self.sortition_observers_lookup.contains(&(*obs_id as u16))
+            })
+            .collect();
+        if interested_observers.len() < 1 {
+            return;
+        }
+
+        let mut payload = None;
+        for (_, observer) in interested_observers.iter() {
+            if observer.is_json() {
+                observer.send_new_sortition(payload.get_or_insert_with(|| {
+                    EventObserver::make_new_sortition_payload(snapshot, block_commits)
+                }));
+            } else {
+                let sortition_event = event_encoding::make_sortition_event(
+                    &observer.redaction,
+                    snapshot,
+                    block_commits,
+                );
+                observer.send_message(&sortition_event, PATH_SORTITION_SUBMIT);
+            }
         }
     }
 
@@ -596,6 +1278,29 @@ impl EventDispatcher {
         } else {
             vec![]
         };
+        self.dispatch_chain_tip(
+            chain_tip,
+            parent_index_hash,
+            winner_txid,
+            mature_rewards,
+            mature_rewards_info,
+            &boot_receipts,
+            None,
+        )
+    }
+
+    /// Send a processed block, with its events, to every observer, or only to the observer at
+    /// `only_observer`.
+    fn dispatch_chain_tip(
+        &self,
+        chain_tip: &ChainTip,
+        parent_index_hash: &StacksBlockId,
+        winner_txid: Txid,
+        mature_rewards: Vec<MinerReward>,
+        mature_rewards_info: Option<MinerRewardInfo>,
+        boot_receipts: &Vec<StacksTransactionReceipt>,
+        only_observer: Option<usize>,
+    ) {
         let receipts = chain_tip
             .receipts
             .iter()
@@ -606,29 +1311,10 @@ impl EventDispatcher {
         let (dispatch_matrix, events) = self.create_dispatch_matrix_and_event_vector(&receipts);
 
         if dispatch_matrix.len() > 0 {
-            let mature_rewards_vec = if let Some(rewards_info) = mature_rewards_info {
-                mature_rewards
-                    .iter()
-                    .map(|reward| {
-                        json!({
-                            "recipient": reward.address.to_string(),
-                            "coinbase_amount": reward.coinbase.to_string(),
-                            "tx_fees_anchored": reward.tx_fees_anchored.to_string(),
-                            "tx_fees_streamed_confirmed": reward.tx_fees_streamed_confirmed.to_string(),
-                            "tx_fees_streamed_produced": reward.tx_fees_streamed_produced.to_string(),
-                            "from_stacks_block_hash": format!("0x{}", &rewards_info.from_stacks_block_hash),
-                            "from_index_consensus_hash": format!("0x{}", StacksBlockId::new(&rewards_info.from_block_consensus_hash,
-                                                                                            &rewards_info.from_stacks_block_hash)),
-                        })
-                    })
-                    .collect()
-            } else {
-                vec![]
-            };
-
-            let mature_rewards = serde_json::Value::Array(mature_rewards_vec);
-
             for (observer_id, filtered_events_ids) in dispatch_matrix.iter().enumerate() {
+                if only_observer.map(|id| id != observer_id).unwrap_or(false) {
+                    continue;
+                }
                 let filtered_events: Vec<_> = filtered_events_ids
                     .iter()
                     .map(|event_id| (*event_id, &events[*event_id]))
@@ -638,9 +1324,10 @@ impl EventDispatcher {
                     filtered_events,
                     chain_tip,
                     parent_index_hash,
-                    &boot_receipts,
+                    boot_receipts,
                     &winner_txid,
                     &mature_rewards,
+                    mature_rewards_info.as_ref(),
                 );
             }
         }
@@ -675,20 +1362,25 @@ impl EventDispatcher {
         let (dispatch_matrix, events) =
             self.create_dispatch_matrix_and_event_vector(&flattened_receipts);
 
-        // Serialize receipts
+        // Serialize receipts, if any observer takes JSON
         let mut tx_index;
         let mut serialized_txs = Vec::new();
 
-        for (curr_sequence_number, receipts) in processed_unconfirmed_state.receipts.iter() {
-            tx_index = 0;
-            for receipt in receipts.iter() {
-                let payload = EventObserver::make_new_microblock_txs_payload(
-                    receipt,
-                    tx_index,
-                    *curr_sequence_number,
-                );
-                serialized_txs.push(payload);
-                tx_index += 1;
+        if interested_observers
+            .iter()
+            .any(|(_, observer)| observer.is_json())
+        {
+            for (curr_sequence_number, receipts) in processed_unconfirmed_state.receipts.iter() {
+                tx_index = 0;
+                for receipt in receipts.iter() {
+                    let payload = EventObserver::make_new_microblock_txs_payload(
+                        receipt,
+                        tx_index,
+                        *curr_sequence_number,
+                    );
+                    serialized_txs.push(payload);
+                    tx_index += 1;
+                }
             }
         }
 
@@ -699,11 +1391,21 @@ impl EventDispatcher {
                 .map(|event_id| (*event_id, &events[*event_id]))
                 .collect();
 
-            observer.send_new_microblocks(
-                parent_index_block_hash,
-                filtered_events,
-                &serialized_txs,
-            );
+            if observer.is_json() {
+                observer.send_new_microblocks(
+                    parent_index_block_hash,
+                    filtered_events,
+                    &serialized_txs,
+                );
+            } else {
+                let microblock_event = event_encoding::make_microblock_event(
+                    &observer.redaction,
+                    &parent_index_block_hash,
+                    &processed_unconfirmed_state.receipts,
+                    observer.encode_events(filtered_events),
+                );
+                observer.send_message(&microblock_event, PATH_MICROBLOCK_SUBMIT);
+            }
         }
     }
 
@@ -722,10 +1424,18 @@ impl EventDispatcher {
             return;
         }
 
-        let payload = EventObserver::make_new_mempool_txs_payload(txs);
-
+        let mut payload = None;
         for (_, observer) in interested_observers.iter() {
-            observer.send_new_mempool_txs(&payload);
+            if observer.is_json() {
+                observer.send_new_mempool_txs(
+                    payload
+                        .get_or_insert_with(|| EventObserver::make_new_mempool_txs_payload(&txs)),
+                );
+            } else {
+                let mempool_tx_event =
+                    event_encoding::make_mempool_tx_event(&observer.redaction, &txs);
+                observer.send_message(&mempool_tx_event, PATH_MEMPOOL_TX_SUBMIT);
+            }
         }
     }
 
@@ -744,18 +1454,26 @@ impl EventDispatcher {
             return;
         }
 
-        let dropped_txids: Vec<_> = txs
-            .into_iter()
-            .map(|tx| serde_json::Value::String(format!("0x{}", &tx)))
-            .collect();
-
-        let payload = json!({
-            "dropped_txids": serde_json::Value::Array(dropped_txids),
-            "reason": reason.to_string(),
-        });
-
+        let drop_time = get_epoch_time_secs();
+        let mut payload = None;
         for (_, observer) in interested_observers.iter() {
-            observer.send_dropped_mempool_txs(&payload);
+            if observer.is_json() {
+                observer.send_dropped_mempool_txs(payload.get_or_insert_with(|| {
+                    let dropped_txids: Vec<_> = txs
+                        .iter()
+                        .map(|tx| serde_json::Value::String(format!("0x{}", &tx)))
+                        .collect();
+
+                    json!({
+                        "dropped_txids": serde_json::Value::Array(dropped_txids),
+                        "reason": reason.to_string(),
+                        "drop_time": drop_time,
+                    })
+                }));
+            } else {
+                let drop_event = event_encoding::make_mempool_drop_event(&txs, &reason, drop_time);
+                observer.send_message(&drop_event, PATH_MEMPOOL_TX_DROP);
+            }
         }
     }
 
@@ -765,14 +1483,20 @@ impl EventDispatcher {
             return;
         }
 
-        let mut serialized_attachments = vec![];
-        for attachment in attachments.iter() {
-            let payload = EventObserver::make_new_attachment_payload(attachment);
-            serialized_attachments.push(payload);
-        }
-
+        let mut payload = None;
         for (_, observer) in interested_observers.iter() {
-            observer.send_new_attachments(&json!(serialized_attachments));
+            if observer.is_json() {
+                observer.send_new_attachments(payload.get_or_insert_with(|| {
+                    let serialized_attachments: Vec<_> = attachments
+                        .iter()
+                        .map(EventObserver::make_new_attachment_payload)
+                        .collect();
+                    json!(serialized_attachments)
+                }));
+            } else {
+                let attachments_event = event_encoding::make_attachments_event(attachments);
+                observer.send_message(&attachments_event, PATH_ATTACHMENT_PROCESSED);
+            }
         }
     }
 
@@ -802,6 +1526,10 @@ impl EventDispatcher {
         let event_observer = EventObserver {
             endpoint: conf.endpoint.clone(),
             should_keep_running,
+            delivery: self.delivery.clone(),
+            payload_encoding: conf.payload_encoding,
+            redaction: conf.redaction.clone(),
+            limits: conf.limits.clone(),
         };
 
         let observer_index = self.registered_observers.len() as u16;
@@ -826,6 +1554,12 @@ impl EventDispatcher {
                 EventKeyType::BurnchainBlocks => {
                     self.burn_block_observers_lookup.insert(observer_index);
                 }
+                EventKeyType::RewardCycles => {
+                    self.reward_cycle_observers_lookup.insert(observer_index);
+                }
+                EventKeyType::Sortitions => {
+                    self.sortition_observers_lookup.insert(observer_index);
+                }
                 EventKeyType::MemPoolTransactions => {
                     self.mempool_observers_lookup.insert(observer_index);
                 }
@@ -0,0 +1,1057 @@
+//! Protobuf encoding of event observer payloads.  Each path's payload has its own message, built
+//! straight from the node's types.  These definitions mirror `proto/event_observer.proto`, which
+//! is what observers should generate their decoders from.
+
+use prost::{Enumeration, Message, Oneof};
+
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::operations::LeaderBlockCommitOp;
+use stacks::chainstate::burn::BlockSnapshot;
+use stacks::chainstate::coordinator::{PoxAnchorBlockStatus, RewardCycleInfo};
+use stacks::chainstate::stacks::db::accounts::MinerReward as StacksMinerReward;
+use stacks::chainstate::stacks::db::MinerRewardInfo;
+use stacks::chainstate::stacks::events::{
+    FTEventType, NFTEventType, STXEventType, StacksTransactionEvent, StacksTransactionReceipt,
+    TransactionOrigin,
+};
+use stacks::chainstate::stacks::StacksTransaction;
+use stacks::codec::StacksMessageCodec;
+use stacks::core::mempool::MemPoolDropReason;
+use stacks::net::atlas::{Attachment as AtlasAttachment, AttachmentInstance};
+use stacks::types::chainstate::{BurnchainHeaderHash, StacksAddress, StacksBlockId};
+use stacks::util::hash::hex_bytes;
+use stacks::vm::analysis::contract_interface_builder::build_contract_interface;
+use stacks::vm::costs::ExecutionCost as StacksExecutionCost;
+use stacks::vm::types::{PrincipalData, Value};
+
+use super::config::EventRedactionPolicy;
+use super::event_redaction::{redact_principal, redact_tx};
+use super::node::ChainTip;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockEvent {
+    #[prost(bytes, tag = "1")]
+    pub block_hash: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub block_height: u64,
+    #[prost(bytes, tag = "3")]
+    pub burn_block_hash: Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub burn_block_height: u64,
+    #[prost(bytes, tag = "5")]
+    pub miner_txid: Vec<u8>,
+    #[prost(uint64, tag = "6")]
+    pub burn_block_time: u64,
+    #[prost(bytes, tag = "7")]
+    pub index_block_hash: Vec<u8>,
+    #[prost(bytes, tag = "8")]
+    pub parent_block_hash: Vec<u8>,
+    #[prost(bytes, tag = "9")]
+    pub parent_index_block_hash: Vec<u8>,
+    #[prost(bytes, tag = "10")]
+    pub parent_microblock: Vec<u8>,
+    #[prost(message, repeated, tag = "11")]
+    pub matured_miner_rewards: Vec<MinerReward>,
+    #[prost(message, repeated, tag = "12")]
+    pub events: Vec<TransactionEvent>,
+    #[prost(message, repeated, tag = "13")]
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MinerReward {
+    #[prost(string, tag = "1")]
+    pub recipient: String,
+    #[prost(string, tag = "2")]
+    pub coinbase_amount: String,
+    #[prost(string, tag = "3")]
+    pub tx_fees_anchored: String,
+    #[prost(string, tag = "4")]
+    pub tx_fees_streamed_confirmed: String,
+    #[prost(string, tag = "5")]
+    pub tx_fees_streamed_produced: String,
+    #[prost(bytes, tag = "6")]
+    pub from_stacks_block_hash: Vec<u8>,
+    #[prost(bytes, tag = "7")]
+    pub from_index_consensus_hash: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MicroblockEvent {
+    #[prost(bytes, tag = "1")]
+    pub parent_index_block_hash: Vec<u8>,
+    #[prost(message, repeated, tag = "2")]
+    pub events: Vec<TransactionEvent>,
+    #[prost(message, repeated, tag = "3")]
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum TransactionStatus {
+    Success = 0,
+    AbortByResponse = 1,
+    AbortByPostCondition = 2,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ExecutionCost {
+    #[prost(uint64, tag = "1")]
+    pub write_length: u64,
+    #[prost(uint64, tag = "2")]
+    pub write_count: u64,
+    #[prost(uint64, tag = "3")]
+    pub read_length: u64,
+    #[prost(uint64, tag = "4")]
+    pub read_count: u64,
+    #[prost(uint64, tag = "5")]
+    pub runtime: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Transaction {
+    #[prost(bytes, tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub tx_index: u32,
+    #[prost(enumeration = "TransactionStatus", tag = "3")]
+    pub status: i32,
+    #[prost(bytes, tag = "4")]
+    pub raw_result: Vec<u8>,
+    #[prost(bytes, tag = "5")]
+    pub raw_tx: Vec<u8>,
+    #[prost(string, tag = "6")]
+    pub contract_abi: String,
+    #[prost(message, optional, tag = "7")]
+    pub execution_cost: Option<ExecutionCost>,
+    #[prost(uint32, tag = "8")]
+    pub microblock_sequence: u32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct TransactionEvent {
+    #[prost(bytes, tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub event_index: u64,
+    #[prost(bool, tag = "3")]
+    pub committed: bool,
+    #[prost(
+        oneof = "transaction_event::Event",
+        tags = "4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14"
+    )]
+    pub event: Option<transaction_event::Event>,
+}
+
+pub mod transaction_event {
+    use super::*;
+
+    #[derive(Clone, PartialEq, Oneof)]
+    pub enum Event {
+        #[prost(message, tag = "4")]
+        ContractEvent(ContractEvent),
+        #[prost(message, tag = "5")]
+        StxTransferEvent(StxTransferEvent),
+        #[prost(message, tag = "6")]
+        StxMintEvent(StxMintEvent),
+        #[prost(message, tag = "7")]
+        StxBurnEvent(StxBurnEvent),
+        #[prost(message, tag = "8")]
+        StxLockEvent(StxLockEvent),
+        #[prost(message, tag = "9")]
+        NftTransferEvent(NftTransferEvent),
+        #[prost(message, tag = "10")]
+        NftMintEvent(NftMintEvent),
+        #[prost(message, tag = "11")]
+        NftBurnEvent(NftBurnEvent),
+        #[prost(message, tag = "12")]
+        FtTransferEvent(FtTransferEvent),
+        #[prost(message, tag = "13")]
+        FtMintEvent(FtMintEvent),
+        #[prost(message, tag = "14")]
+        FtBurnEvent(FtBurnEvent),
+    }
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct ContractEvent {
+    #[prost(string, tag = "1")]
+    pub contract_identifier: String,
+    #[prost(string, tag = "2")]
+    pub topic: String,
+    #[prost(bytes, tag = "3")]
+    pub raw_value: Vec<u8>,
+    #[prost(bool, tag = "4")]
+    pub truncated: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StxTransferEvent {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(string, tag = "2")]
+    pub recipient: String,
+    #[prost(string, tag = "3")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StxMintEvent {
+    #[prost(string, tag = "1")]
+    pub recipient: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StxBurnEvent {
+    #[prost(string, tag = "1")]
+    pub sender: String,
+    #[prost(string, tag = "2")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StxLockEvent {
+    #[prost(string, tag = "1")]
+    pub locked_amount: String,
+    #[prost(uint64, tag = "2")]
+    pub unlock_height: u64,
+    #[prost(string, tag = "3")]
+    pub locked_address: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NftTransferEvent {
+    #[prost(string, tag = "1")]
+    pub asset_identifier: String,
+    #[prost(string, tag = "2")]
+    pub sender: String,
+    #[prost(string, tag = "3")]
+    pub recipient: String,
+    #[prost(bytes, tag = "4")]
+    pub raw_value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NftMintEvent {
+    #[prost(string, tag = "1")]
+    pub asset_identifier: String,
+    #[prost(string, tag = "2")]
+    pub recipient: String,
+    #[prost(bytes, tag = "3")]
+    pub raw_value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct NftBurnEvent {
+    #[prost(string, tag = "1")]
+    pub asset_identifier: String,
+    #[prost(string, tag = "2")]
+    pub sender: String,
+    #[prost(bytes, tag = "3")]
+    pub raw_value: Vec<u8>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FtTransferEvent {
+    #[prost(string, tag = "1")]
+    pub asset_identifier: String,
+    #[prost(string, tag = "2")]
+    pub sender: String,
+    #[prost(string, tag = "3")]
+    pub recipient: String,
+    #[prost(string, tag = "4")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FtMintEvent {
+    #[prost(string, tag = "1")]
+    pub asset_identifier: String,
+    #[prost(string, tag = "2")]
+    pub recipient: String,
+    #[prost(string, tag = "3")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct FtBurnEvent {
+    #[prost(string, tag = "1")]
+    pub asset_identifier: String,
+    #[prost(string, tag = "2")]
+    pub sender: String,
+    #[prost(string, tag = "3")]
+    pub amount: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MempoolTxEvent {
+    #[prost(message, repeated, tag = "1")]
+    pub transactions: Vec<MempoolTransaction>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MempoolTransaction {
+    #[prost(bytes, tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(bytes, tag = "2")]
+    pub raw_tx: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum MempoolDropReason {
+    ReplaceAcrossFork = 0,
+    ReplaceByFee = 1,
+    StaleGarbageCollect = 2,
+    TooExpensive = 3,
+    Expired = 4,
+    StaleNonce = 5,
+    ConflictingNonce = 6,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct MempoolDropEvent {
+    #[prost(bytes, repeated, tag = "1")]
+    pub dropped_txids: Vec<Vec<u8>>,
+    #[prost(enumeration = "MempoolDropReason", tag = "2")]
+    pub reason: i32,
+    #[prost(uint64, tag = "3")]
+    pub drop_time: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BurnBlockEvent {
+    #[prost(bytes, tag = "1")]
+    pub burn_block_hash: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub burn_block_height: u64,
+    #[prost(message, repeated, tag = "3")]
+    pub reward_recipients: Vec<RewardRecipient>,
+    #[prost(string, repeated, tag = "4")]
+    pub reward_slot_holders: Vec<String>,
+    #[prost(uint64, tag = "5")]
+    pub burn_amount: u64,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RewardRecipient {
+    #[prost(string, tag = "1")]
+    pub recipient: String,
+    #[prost(uint64, tag = "2")]
+    pub amt: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Enumeration)]
+#[repr(i32)]
+pub enum AnchorBlockStatus {
+    NotSelected = 0,
+    SelectedUnknown = 1,
+    SelectedKnown = 2,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct RewardCycleEvent {
+    #[prost(bytes, tag = "1")]
+    pub burn_block_hash: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub burn_block_height: u64,
+    #[prost(uint64, tag = "3")]
+    pub reward_cycle: u64,
+    #[prost(enumeration = "AnchorBlockStatus", tag = "4")]
+    pub anchor_block_status: i32,
+    #[prost(bytes, tag = "5")]
+    pub anchor_block_hash: Vec<u8>,
+    #[prost(string, repeated, tag = "6")]
+    pub reward_set: Vec<String>,
+    #[prost(bool, tag = "7")]
+    pub pox_active: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct SortitionEvent {
+    #[prost(bytes, tag = "1")]
+    pub burn_block_hash: Vec<u8>,
+    #[prost(uint64, tag = "2")]
+    pub burn_block_height: u64,
+    #[prost(bytes, tag = "3")]
+    pub consensus_hash: Vec<u8>,
+    #[prost(bytes, tag = "4")]
+    pub sortition_id: Vec<u8>,
+    #[prost(bool, tag = "5")]
+    pub sortition: bool,
+    #[prost(bytes, tag = "6")]
+    pub winning_block_txid: Vec<u8>,
+    #[prost(bytes, tag = "7")]
+    pub winning_stacks_block_hash: Vec<u8>,
+    #[prost(uint64, tag = "8")]
+    pub total_burn: u64,
+    #[prost(message, repeated, tag = "9")]
+    pub block_commits: Vec<BlockCommit>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct BlockCommit {
+    #[prost(bytes, tag = "1")]
+    pub txid: Vec<u8>,
+    #[prost(uint32, tag = "2")]
+    pub vtxindex: u32,
+    #[prost(bytes, tag = "3")]
+    pub block_header_hash: Vec<u8>,
+    #[prost(uint64, tag = "4")]
+    pub burn_fee: u64,
+    #[prost(uint64, tag = "5")]
+    pub sunset_burn: u64,
+    #[prost(string, repeated, tag = "6")]
+    pub commit_outs: Vec<String>,
+    #[prost(bool, tag = "7")]
+    pub won: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct AttachmentsEvent {
+    #[prost(message, repeated, tag = "1")]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Attachment {
+    #[prost(uint32, tag = "1")]
+    pub attachment_index: u32,
+    #[prost(bytes, tag = "2")]
+    pub index_block_hash: Vec<u8>,
+    #[prost(uint64, tag = "3")]
+    pub block_height: u64,
+    #[prost(bytes, tag = "4")]
+    pub content_hash: Vec<u8>,
+    #[prost(string, tag = "5")]
+    pub contract_id: String,
+    #[prost(bytes, tag = "6")]
+    pub metadata: Vec<u8>,
+    #[prost(bytes, tag = "7")]
+    pub tx_id: Vec<u8>,
+    #[prost(bytes, tag = "8")]
+    pub content: Vec<u8>,
+}
+
+/// Encode a message as the body of a POST to an observer.
+pub fn encode_message<M: Message>(message: &M) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(message.encoded_len());
+    message
+        .encode(&mut buf)
+        .expect("FATAL: insufficient capacity to encode event payload");
+    buf
+}
+
+fn principal_string(policy: &EventRedactionPolicy, principal: &PrincipalData) -> String {
+    redact_principal(policy, &principal.to_string())
+}
+
+fn address_string(policy: &EventRedactionPolicy, address: &StacksAddress) -> String {
+    redact_principal(policy, &address.to_string())
+}
+
+fn transaction_status(receipt: &StacksTransactionReceipt) -> TransactionStatus {
+    match (receipt.post_condition_aborted, &receipt.result) {
+        (false, Value::Response(response_data)) => {
+            if response_data.committed {
+                TransactionStatus::Success
+            } else {
+                TransactionStatus::AbortByResponse
+            }
+        }
+        (true, Value::Response(_)) => TransactionStatus::AbortByPostCondition,
+        _ => unreachable!(), // Transaction results should always be a Value::Response type
+    }
+}
+
+impl From<&StacksExecutionCost> for ExecutionCost {
+    fn from(cost: &StacksExecutionCost) -> ExecutionCost {
+        ExecutionCost {
+            write_length: cost.write_length,
+            write_count: cost.write_count,
+            read_length: cost.read_length,
+            read_count: cost.read_count,
+            runtime: cost.runtime,
+        }
+    }
+}
+
+impl From<&MemPoolDropReason> for MempoolDropReason {
+    fn from(reason: &MemPoolDropReason) -> MempoolDropReason {
+        match reason {
+            MemPoolDropReason::REPLACE_ACROSS_FORK => MempoolDropReason::ReplaceAcrossFork,
+            MemPoolDropReason::REPLACE_BY_FEE => MempoolDropReason::ReplaceByFee,
+            MemPoolDropReason::STALE_COLLECT => MempoolDropReason::StaleGarbageCollect,
+            MemPoolDropReason::TOO_EXPENSIVE => MempoolDropReason::TooExpensive,
+            MemPoolDropReason::EXPIRED => MempoolDropReason::Expired,
+            MemPoolDropReason::STALE_NONCE => MempoolDropReason::StaleNonce,
+            MemPoolDropReason::CONFLICTING_NONCE => MempoolDropReason::ConflictingNonce,
+        }
+    }
+}
+
+/// A processed transaction, as sent in block and microblock events.  `microblock_sequence` is 0
+/// for transactions in anchored blocks.
+pub fn make_transaction(
+    policy: &EventRedactionPolicy,
+    receipt: &StacksTransactionReceipt,
+    tx_index: u32,
+    microblock_sequence: u16,
+) -> Transaction {
+    let raw_tx = match receipt.transaction {
+        TransactionOrigin::Burn(_) => vec![],
+        TransactionOrigin::Stacks(ref tx) => redact_tx(policy, tx).unwrap_or(vec![]),
+    };
+    let contract_abi = match receipt.contract_analysis {
+        Some(ref analysis) => serde_json::to_string(&build_contract_interface(analysis))
+            .expect("FATAL: failed to serialize contract interface"),
+        None => String::new(),
+    };
+    Transaction {
+        txid: receipt.transaction.txid().as_bytes().to_vec(),
+        tx_index,
+        status: transaction_status(receipt) as i32,
+        raw_result: receipt.result.serialize_to_vec(),
+        raw_tx,
+        contract_abi,
+        execution_cost: Some(ExecutionCost::from(&receipt.execution_cost)),
+        microblock_sequence: microblock_sequence as u32,
+    }
+}
+
+/// A transaction event, with the index it has among all of its block's events.
+pub fn make_transaction_event(
+    policy: &EventRedactionPolicy,
+    event_index: usize,
+    txid: &Txid,
+    committed: bool,
+    event: &StacksTransactionEvent,
+) -> TransactionEvent {
+    use self::transaction_event::Event;
+
+    let event = match event {
+        StacksTransactionEvent::SmartContractEvent(event_data) => {
+            Event::ContractEvent(ContractEvent {
+                contract_identifier: event_data.key.0.to_string(),
+                topic: event_data.key.1.clone(),
+                raw_value: event_data.value.serialize_to_vec(),
+                truncated: false,
+            })
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(event_data)) => {
+            Event::StxTransferEvent(StxTransferEvent {
+                sender: principal_string(policy, &event_data.sender),
+                recipient: principal_string(policy, &event_data.recipient),
+                amount: event_data.amount.to_string(),
+            })
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXMintEvent(event_data)) => {
+            Event::StxMintEvent(StxMintEvent {
+                recipient: principal_string(policy, &event_data.recipient),
+                amount: event_data.amount.to_string(),
+            })
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXBurnEvent(event_data)) => {
+            Event::StxBurnEvent(StxBurnEvent {
+                sender: principal_string(policy, &event_data.sender),
+                amount: event_data.amount.to_string(),
+            })
+        }
+        StacksTransactionEvent::STXEvent(STXEventType::STXLockEvent(event_data)) => {
+            Event::StxLockEvent(StxLockEvent {
+                locked_amount: event_data.locked_amount.to_string(),
+                unlock_height: event_data.unlock_height,
+                locked_address: principal_string(policy, &event_data.locked_address),
+            })
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTTransferEvent(event_data)) => {
+            Event::NftTransferEvent(NftTransferEvent {
+                asset_identifier: event_data.asset_identifier.to_string(),
+                sender: principal_string(policy, &event_data.sender),
+                recipient: principal_string(policy, &event_data.recipient),
+                raw_value: event_data.value.serialize_to_vec(),
+            })
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTMintEvent(event_data)) => {
+            Event::NftMintEvent(NftMintEvent {
+                asset_identifier: event_data.asset_identifier.to_string(),
+                recipient: principal_string(policy, &event_data.recipient),
+                raw_value: event_data.value.serialize_to_vec(),
+            })
+        }
+        StacksTransactionEvent::NFTEvent(NFTEventType::NFTBurnEvent(event_data)) => {
+            Event::NftBurnEvent(NftBurnEvent {
+                asset_identifier: event_data.asset_identifier.to_string(),
+                sender: principal_string(policy, &event_data.sender),
+                raw_value: event_data.value.serialize_to_vec(),
+            })
+        }
+        StacksTransactionEvent::FTEvent(FTEventType::FTTransferEvent(event_data)) => {
+            Event::FtTransferEvent(FtTransferEvent {
+                asset_identifier: event_data.asset_identifier.to_string(),
+                sender: principal_string(policy, &event_data.sender),
+                recipient: principal_string(policy, &event_data.recipient),
+                amount: event_data.amount.to_string(),
+            })
+        }
+        StacksTransactionEvent::FTEvent(FTEventType::FTMintEvent(event_data)) => {
+            Event::FtMintEvent(FtMintEvent {
+                asset_identifier: event_data.asset_identifier.to_string(),
+                recipient: principal_string(policy, &event_data.recipient),
+                amount: event_data.amount.to_string(),
+            })
+        }
+        StacksTransactionEvent::FTEvent(FTEventType::FTBurnEvent(event_data)) => {
+            Event::FtBurnEvent(FtBurnEvent {
+                asset_identifier: event_data.asset_identifier.to_string(),
+                sender: principal_string(policy, &event_data.sender),
+                amount: event_data.amount.to_string(),
+            })
+        }
+    };
+    TransactionEvent {
+        txid: txid.as_bytes().to_vec(),
+        event_index: event_index as u64,
+        committed,
+        event: Some(event),
+    }
+}
+
+fn make_miner_reward(
+    policy: &EventRedactionPolicy,
+    reward: &StacksMinerReward,
+    rewards_info: &MinerRewardInfo,
+) -> MinerReward {
+    MinerReward {
+        recipient: address_string(policy, &reward.address),
+        coinbase_amount: reward.coinbase.to_string(),
+        tx_fees_anchored: reward.tx_fees_anchored.to_string(),
+        tx_fees_streamed_confirmed: reward.tx_fees_streamed_confirmed.to_string(),
+        tx_fees_streamed_produced: reward.tx_fees_streamed_produced.to_string(),
+        from_stacks_block_hash: rewards_info.from_stacks_block_hash.as_bytes().to_vec(),
+        from_index_consensus_hash: StacksBlockId::new(
+            &rewards_info.from_block_consensus_hash,
+            &rewards_info.from_stacks_block_hash,
+        )
+        .as_bytes()
+        .to_vec(),
+    }
+}
+
+/// A processed anchored block.  `events` are the events the observer subscribed to, in
+/// event_index order.
+pub fn make_block_event(
+    policy: &EventRedactionPolicy,
+    chain_tip: &ChainTip,
+    parent_index_hash: &StacksBlockId,
+    boot_receipts: &Vec<StacksTransactionReceipt>,
+    winner_txid: &Txid,
+    mature_rewards: &[StacksMinerReward],
+    mature_rewards_info: Option<&MinerRewardInfo>,
+    events: Vec<TransactionEvent>,
+) -> BlockEvent {
+    let matured_miner_rewards = match mature_rewards_info {
+        Some(rewards_info) => mature_rewards
+            .iter()
+            .map(|reward| make_miner_reward(policy, reward, rewards_info))
+            .collect(),
+        None => vec![],
+    };
+    let transactions = chain_tip
+        .receipts
+        .iter()
+        .chain(boot_receipts.iter())
+        .enumerate()
+        .map(|(tx_index, receipt)| make_transaction(policy, receipt, tx_index as u32, 0))
+        .collect();
+
+    BlockEvent {
+        block_hash: chain_tip.block.block_hash().as_bytes().to_vec(),
+        block_height: chain_tip.metadata.block_height,
+        burn_block_hash: chain_tip.metadata.burn_header_hash.as_bytes().to_vec(),
+        burn_block_height: chain_tip.metadata.burn_header_height as u64,
+        miner_txid: winner_txid.as_bytes().to_vec(),
+        burn_block_time: chain_tip.metadata.burn_header_timestamp,
+        index_block_hash: chain_tip.metadata.index_block_hash().as_bytes().to_vec(),
+        parent_block_hash: chain_tip.block.header.parent_block.as_bytes().to_vec(),
+        parent_index_block_hash: parent_index_hash.as_bytes().to_vec(),
+        parent_microblock: chain_tip.block.header.parent_microblock.as_bytes().to_vec(),
+        matured_miner_rewards,
+        events,
+        transactions,
+    }
+}
+
+/// Newly-processed microblocks, given as their sequence numbers and transaction receipts.
+/// `events` are the events the observer subscribed to, in event_index order.
+pub fn make_microblock_event(
+    policy: &EventRedactionPolicy,
+    parent_index_block_hash: &StacksBlockId,
+    receipts: &Vec<(u16, Vec<StacksTransactionReceipt>)>,
+    events: Vec<TransactionEvent>,
+) -> MicroblockEvent {
+    let mut transactions = vec![];
+    for (sequence, microblock_receipts) in receipts.iter() {
+        for (tx_index, receipt) in microblock_receipts.iter().enumerate() {
+            transactions.push(make_transaction(
+                policy,
+                receipt,
+                tx_index as u32,
+                *sequence,
+            ));
+        }
+    }
+    MicroblockEvent {
+        parent_index_block_hash: parent_index_block_hash.as_bytes().to_vec(),
+        events,
+        transactions,
+    }
+}
+
+pub fn make_mempool_tx_event(
+    policy: &EventRedactionPolicy,
+    txs: &[StacksTransaction],
+) -> MempoolTxEvent {
+    MempoolTxEvent {
+        transactions: txs
+            .iter()
+            .map(|tx| MempoolTransaction {
+                txid: tx.txid().as_bytes().to_vec(),
+                raw_tx: redact_tx(policy, tx).unwrap_or(vec![]),
+            })
+            .collect(),
+    }
+}
+
+pub fn make_mempool_drop_event(
+    txids: &[Txid],
+    reason: &MemPoolDropReason,
+    drop_time: u64,
+) -> MempoolDropEvent {
+    MempoolDropEvent {
+        dropped_txids: txids.iter().map(|txid| txid.as_bytes().to_vec()).collect(),
+        reason: MempoolDropReason::from(reason) as i32,
+        drop_time,
+    }
+}
+
+pub fn make_burn_block_event(
+    policy: &EventRedactionPolicy,
+    burn_block: &BurnchainHeaderHash,
+    burn_block_height: u64,
+    rewards: &[(StacksAddress, u64)],
+    burns: u64,
+    slot_holders: &[StacksAddress],
+) -> BurnBlockEvent {
+    BurnBlockEvent {
+        burn_block_hash: burn_block.as_bytes().to_vec(),
+        burn_block_height,
+        reward_recipients: rewards
+            .iter()
+            .map(|(stx_addr, amt)| RewardRecipient {
+                recipient: redact_principal(policy, &stx_addr.to_b58()),
+                amt: *amt,
+            })
+            .collect(),
+        reward_slot_holders: slot_holders
+            .iter()
+            .map(|stx_addr| redact_principal(policy, &stx_addr.to_b58()))
+            .collect(),
+        burn_amount: burns,
+    }
+}
+
+pub fn make_reward_cycle_event(
+    policy: &EventRedactionPolicy,
+    burn_block: &BurnchainHeaderHash,
+    burn_block_height: u64,
+    reward_cycle: u64,
+    reward_cycle_info: &RewardCycleInfo,
+) -> RewardCycleEvent {
+    let anchor_block_status = match reward_cycle_info.anchor_status {
+        PoxAnchorBlockStatus::SelectedAndKnown(..) => AnchorBlockStatus::SelectedKnown,
+        PoxAnchorBlockStatus::SelectedAndUnknown(..) => AnchorBlockStatus::SelectedUnknown,
+        PoxAnchorBlockStatus::NotSelected => AnchorBlockStatus::NotSelected,
+    };
+    let reward_set = reward_cycle_info
+        .known_selected_anchor_block()
+        .map(|reward_set| {
+            reward_set
+                .iter()
+                .map(|stx_addr| redact_principal(policy, &stx_addr.to_b58()))
+                .collect()
+        })
+        .unwrap_or(vec![]);
+
+    RewardCycleEvent {
+        burn_block_hash: burn_block.as_bytes().to_vec(),
+        burn_block_height,
+        reward_cycle,
+        anchor_block_status: anchor_block_status as i32,
+        anchor_block_hash: reward_cycle_info
+            .selected_anchor_block()
+            .map(|block_hash| block_hash.as_bytes().to_vec())
+            .unwrap_or(vec![]),
+        reward_set,
+        pox_active: reward_cycle_info.is_pox_active(),
+    }
+}
+
+pub fn make_sortition_event(
+    policy: &EventRedactionPolicy,
+    snapshot: &BlockSnapshot,
+    block_commits: &[LeaderBlockCommitOp],
+) -> SortitionEvent {
+    let block_commits = block_commits
+        .iter()
+        .map(|commit| BlockCommit {
+            txid: commit.txid.as_bytes().to_vec(),
+            vtxindex: commit.vtxindex,
+            block_header_hash: commit.block_header_hash.as_bytes().to_vec(),
+            burn_fee: commit.burn_fee,
+            sunset_burn: commit.sunset_burn,
+            commit_outs: commit
+                .commit_outs
+                .iter()
+                .map(|addr| redact_principal(policy, &addr.to_b58()))
+                .collect(),
+            won: snapshot.sortition && commit.txid == snapshot.winning_block_txid,
+        })
+        .collect();
+
+    let (winning_block_txid, winning_stacks_block_hash) = if snapshot.sortition {
+        (
+            snapshot.winning_block_txid.as_bytes().to_vec(),
+            snapshot.winning_stacks_block_hash.as_bytes().to_vec(),
+        )
+    } else {
+        (vec![], vec![])
+    };
+
+    SortitionEvent {
+        burn_block_hash: snapshot.burn_header_hash.as_bytes().to_vec(),
+        burn_block_height: snapshot.block_height,
+        consensus_hash: snapshot.consensus_hash.as_bytes().to_vec(),
+        sortition_id: snapshot.sortition_id.as_bytes().to_vec(),
+        sortition: snapshot.sortition,
+        winning_block_txid,
+        winning_stacks_block_hash,
+        total_burn: snapshot.total_burn,
+        block_commits,
+    }
+}
+
+pub fn make_attachments_event(
+    attachments: &Vec<(AttachmentInstance, AtlasAttachment)>,
+) -> AttachmentsEvent {
+    AttachmentsEvent {
+        attachments: attachments
+            .iter()
+            .map(|(instance, attachment)| Attachment {
+                attachment_index: instance.attachment_index,
+                index_block_hash: instance.index_block_hash.as_bytes().to_vec(),
+                block_height: instance.block_height,
+                content_hash: instance.content_hash.as_bytes().to_vec(),
+                contract_id: instance.contract_id.to_string(),
+                // the node keeps the serialized metadata hex-encoded
+                metadata: hex_bytes(&instance.metadata)
+                    .expect("FATAL: attachment metadata is not hex"),
+                tx_id: instance.tx_id.as_bytes().to_vec(),
+                content: attachment.content.clone(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{make_stacks_transfer, to_addr};
+    use stacks::chainstate::stacks::events::{STXTransferEventData, SmartContractEventData};
+    use stacks::chainstate::stacks::{StacksPrivateKey, TokenTransferMemo, TransactionPayload};
+    use stacks::vm::types::QualifiedContractIdentifier;
+
+    fn make_transfer() -> StacksTransaction {
+        let sk = StacksPrivateKey::new();
+        let recipient = PrincipalData::from(to_addr(&StacksPrivateKey::new()));
+        let raw_tx = make_stacks_transfer(&sk, 0, 180, &recipient, 1000);
+        let mut tx = StacksTransaction::consensus_deserialize(&mut &raw_tx[..]).unwrap();
+        if let TransactionPayload::TokenTransfer(_, _, ref mut memo) = tx.payload {
+            *memo = TokenTransferMemo([7u8; 34]);
+        }
+        tx
+    }
+
+    fn make_receipt(tx: &StacksTransaction, result: Value) -> StacksTransactionReceipt {
+        StacksTransactionReceipt {
+            transaction: TransactionOrigin::Stacks(tx.clone()),
+            events: vec![],
+            post_condition_aborted: false,
+            result,
+            stx_burned: 0,
+            contract_analysis: None,
+            execution_cost: StacksExecutionCost {
+                write_length: 1,
+                write_count: 2,
+                read_length: 3,
+                read_count: 4,
+                runtime: 5,
+            },
+        }
+    }
+
+    #[test]
+    fn test_microblock_event() {
+        let tx = make_transfer();
+        let aborted = make_transfer();
+        let receipts = vec![
+            (0, vec![make_receipt(&tx, Value::okay_true())]),
+            (1, vec![make_receipt(&aborted, Value::err_none())]),
+        ];
+        let parent = StacksBlockId([0x11; 32]);
+
+        let event =
+            make_microblock_event(&EventRedactionPolicy::default(), &parent, &receipts, vec![]);
+        let decoded = MicroblockEvent::decode(&encode_message(&event)[..]).unwrap();
+        assert_eq!(decoded, event);
+
+        assert_eq!(decoded.parent_index_block_hash, vec![0x11; 32]);
+        assert_eq!(decoded.transactions.len(), 2);
+
+        let first = &decoded.transactions[0];
+        assert_eq!(first.txid, tx.txid().as_bytes().to_vec());
+        assert_eq!(first.tx_index, 0);
+        assert_eq!(first.microblock_sequence, 0);
+        assert_eq!(first.status, TransactionStatus::Success as i32);
+        assert_eq!(first.raw_tx, tx.serialize_to_vec());
+        assert_eq!(first.raw_result, Value::okay_true().serialize_to_vec());
+        assert_eq!(first.contract_abi, "");
+        assert_eq!(first.execution_cost.as_ref().unwrap().runtime, 5);
+
+        let second = &decoded.transactions[1];
+        assert_eq!(second.txid, aborted.txid().as_bytes().to_vec());
+        assert_eq!(second.tx_index, 0);
+        assert_eq!(second.microblock_sequence, 1);
+        assert_eq!(second.status, TransactionStatus::AbortByResponse as i32);
+    }
+
+    #[test]
+    fn test_redacted_transactions() {
+        let tx = make_transfer();
+        let receipt = make_receipt(&tx, Value::okay_true());
+
+        let policy = EventRedactionPolicy {
+            omit_memos: true,
+            ..EventRedactionPolicy::default()
+        };
+        let raw_tx = make_transaction(&policy, &receipt, 0, 0).raw_tx;
+        let cleared = StacksTransaction::consensus_deserialize(&mut &raw_tx[..]).unwrap();
+        match cleared.payload {
+            TransactionPayload::TokenTransfer(_, amount, memo) => {
+                assert_eq!(amount, 1000);
+                assert_eq!(memo, TokenTransferMemo([0u8; 34]));
+            }
+            _ => panic!("expected a token transfer"),
+        }
+        // the txid is still that of the transaction that was mined
+        assert_eq!(
+            make_transaction(&policy, &receipt, 0, 0).txid,
+            tx.txid().as_bytes().to_vec()
+        );
+
+        let policy = EventRedactionPolicy {
+            omit_raw_tx: true,
+            ..EventRedactionPolicy::default()
+        };
+        assert!(make_transaction(&policy, &receipt, 0, 0).raw_tx.is_empty());
+        let mempool_event = make_mempool_tx_event(&policy, &[tx.clone()]);
+        assert_eq!(
+            mempool_event.transactions,
+            vec![MempoolTransaction {
+                txid: tx.txid().as_bytes().to_vec(),
+                raw_tx: vec![],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_transaction_events() {
+        let sender = PrincipalData::from(to_addr(&StacksPrivateKey::new()));
+        let contract =
+            QualifiedContractIdentifier::parse("SP000000000000000000002Q6VF78.pox").unwrap();
+        let txid = Txid([0x22; 32]);
+
+        let transfer = StacksTransactionEvent::STXEvent(STXEventType::STXTransferEvent(
+            STXTransferEventData {
+                sender: sender.clone(),
+                recipient: PrincipalData::Contract(contract.clone()),
+                amount: u128::max_value(),
+            },
+        ));
+        let print = StacksTransactionEvent::SmartContractEvent(SmartContractEventData {
+            key: (contract.clone(), "print".to_string()),
+            value: Value::UInt(1),
+        });
+
+        let policy = EventRedactionPolicy::default();
+        let event = make_transaction_event(&policy, 3, &txid, true, &transfer);
+        let decoded = TransactionEvent::decode(&encode_message(&event)[..]).unwrap();
+        assert_eq!(decoded.txid, vec![0x22; 32]);
+        assert_eq!(decoded.event_index, 3);
+        assert!(decoded.committed);
+        assert_eq!(
+            decoded.event,
+            Some(transaction_event::Event::StxTransferEvent(
+                StxTransferEvent {
+                    sender: sender.to_string(),
+                    recipient: contract.to_string(),
+                    amount: u128::max_value().to_string(),
+                }
+            ))
+        );
+
+        let event = make_transaction_event(&policy, 4, &txid, false, &print);
+        assert_eq!(
+            event.event,
+            Some(transaction_event::Event::ContractEvent(ContractEvent {
+                contract_identifier: contract.to_string(),
+                topic: "print".to_string(),
+                raw_value: Value::UInt(1).serialize_to_vec(),
+                truncated: false,
+            }))
+        );
+
+        // standard principals are hashed, contract principals are not
+        let policy = EventRedactionPolicy {
+            hash_principals: true,
+            principal_hash_salt: "salt".to_string(),
+            ..EventRedactionPolicy::default()
+        };
+        match make_transaction_event(&policy, 3, &txid, true, &transfer).event {
+            Some(transaction_event::Event::StxTransferEvent(event)) => {
+                assert_eq!(event.sender, redact_principal(&policy, &sender.to_string()));
+                assert_ne!(event.sender, sender.to_string());
+                assert_eq!(event.recipient, contract.to_string());
+            }
+            _ => panic!("expected an STX transfer event"),
+        }
+    }
+
+    #[test]
+    fn test_mempool_drop_event() {
+        let event = make_mempool_drop_event(
+            &[Txid([0x01; 32]), Txid([0x02; 32])],
+            &MemPoolDropReason::REPLACE_BY_FEE,
+            1234,
+        );
+        let decoded = MempoolDropEvent::decode(&encode_message(&event)[..]).unwrap();
+        assert_eq!(decoded.dropped_txids, vec![vec![0x01; 32], vec![0x02; 32]]);
+        assert_eq!(
+            MempoolDropReason::from_i32(decoded.reason),
+            Some(MempoolDropReason::ReplaceByFee)
+        );
+        assert_eq!(decoded.drop_time, 1234);
+    }
+}
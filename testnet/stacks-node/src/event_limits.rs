@@ -0,0 +1,247 @@
+//! Per-observer limits on the print events delivered for each transaction.  These only shape
+//! what an observer is sent -- the events themselves are unaffected, and so is consensus.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::config::EventLimitPolicy;
+use super::event_encoding::{transaction_event, TransactionEvent};
+
+/// Print events and bytes of print values sent so far for each transaction.
+struct PrintBudget<'a, K: Hash + Eq> {
+    policy: &'a EventLimitPolicy,
+    // txid --> (print events so far, print bytes so far)
+    usage: HashMap<K, (u64, u64)>,
+}
+
+impl<'a, K: Hash + Eq> PrintBudget<'a, K> {
+    fn new(policy: &'a EventLimitPolicy) -> PrintBudget<'a, K> {
+        PrintBudget {
+            policy,
+            usage: HashMap::new(),
+        }
+    }
+
+    /// Count a print event of `len` bytes from transaction `txid`.  Returns false if it is past
+    /// either limit and must be truncated.
+    fn admit(&mut self, txid: K, len: u64) -> bool {
+        let (count, bytes) = self.usage.entry(txid).or_insert((0, 0));
+
+        *count += 1;
+        *bytes += len;
+
+        let over_count =
+            self.policy.max_print_events_per_tx > 0 && *count > self.policy.max_print_events_per_tx;
+        let over_bytes =
+            self.policy.max_print_bytes_per_tx > 0 && *bytes > self.policy.max_print_bytes_per_tx;
+        if !over_count && !over_bytes {
+            return true;
+        }
+
+        // a truncated event doesn't count against the byte budget
+        *bytes -= len;
+        false
+    }
+}
+
+/// Size, in bytes, of the serialized Clarity value in a contract event's "raw_value" field
+fn raw_value_len(contract_event: &serde_json::Value) -> u64 {
+    contract_event["raw_value"]
+        .as_str()
+        .map(|raw_value| (raw_value.trim_start_matches("0x").len() / 2) as u64)
+        .unwrap_or(0)
+}
+
+fn is_print_event(event: &serde_json::Value) -> bool {
+    event["type"] == "contract_event" && event["contract_event"]["topic"] == "print"
+}
+
+/// Truncate the print events in `events` -- serialized events in event_index order -- that
+/// go past the policy's per-transaction count or byte budget.  A truncated event keeps its
+/// txid, event_index, contract and topic, but loses its value, and is marked with
+/// `"truncated": true`.
+pub fn limit_print_events(policy: &EventLimitPolicy, events: &mut Vec<serde_json::Value>) {
+    let mut budget = PrintBudget::new(policy);
+    for event in events.iter_mut() {
+        if !is_print_event(event) {
+            continue;
+        }
+        let txid = event["txid"].as_str().unwrap_or("").to_string();
+        let len = raw_value_len(&event["contract_event"]);
+        if budget.admit(txid, len) {
+            continue;
+        }
+
+        if let serde_json::Value::Object(contract_event) = &mut event["contract_event"] {
+            contract_event.remove("value");
+            contract_event.remove("raw_value");
+            contract_event.insert("truncated".to_string(), json!(true));
+        }
+    }
+}
+
+/// `limit_print_events()` for protobuf events.  A truncated contract event loses its raw value
+/// and has `truncated` set.
+pub fn limit_print_event_messages(policy: &EventLimitPolicy, events: &mut Vec<TransactionEvent>) {
+    let mut budget = PrintBudget::new(policy);
+    for event in events.iter_mut() {
+        let contract_event = match event.event {
+            Some(transaction_event::Event::ContractEvent(ref mut contract_event))
+                if contract_event.topic == "print" =>
+            {
+                contract_event
+            }
+            _ => continue,
+        };
+        let len = contract_event.raw_value.len() as u64;
+        if budget.admit(event.txid.clone(), len) {
+            continue;
+        }
+
+        contract_event.raw_value.clear();
+        contract_event.truncated = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_encoding::{ContractEvent, StxTransferEvent};
+
+    fn make_print_event(txid: &str, event_index: usize, raw_value: &str) -> serde_json::Value {
+        json!({
+            "txid": txid,
+            "event_index": event_index,
+            "committed": true,
+            "type": "contract_event",
+            "contract_event": {
+                "contract_identifier": "ST000000000000000000002AMW42H.spammer",
+                "topic": "print",
+                "value": { "UInt": 1 },
+                "raw_value": raw_value,
+            }
+        })
+    }
+
+    #[test]
+    fn test_limit_print_events() {
+        let transfer = json!({
+            "txid": "0x01",
+            "event_index": 2,
+            "committed": true,
+            "type": "stx_transfer_event",
+            "stx_transfer_event": { "amount": "1" }
+        });
+        let events = vec![
+            make_print_event("0x01", 0, "0x0100"),
+            make_print_event("0x01", 1, "0x01000000"),
+            transfer.clone(),
+            make_print_event("0x01", 3, "0x01"),
+            make_print_event("0x02", 4, "0x01000000"),
+        ];
+
+        // no limits
+        let mut limited = events.clone();
+        limit_print_events(&EventLimitPolicy::default(), &mut limited);
+        assert_eq!(limited, events);
+
+        // at most 2 print events per tx
+        let mut limited = events.clone();
+        let policy = EventLimitPolicy {
+            max_print_events_per_tx: 2,
+            ..EventLimitPolicy::default()
+        };
+        limit_print_events(&policy, &mut limited);
+        assert_eq!(limited[0], events[0]);
+        assert_eq!(limited[1], events[1]);
+        assert_eq!(limited[2], transfer);
+        assert_eq!(limited[3]["contract_event"]["truncated"], json!(true));
+        assert!(limited[3]["contract_event"].get("value").is_none());
+        assert!(limited[3]["contract_event"].get("raw_value").is_none());
+        assert_eq!(limited[3]["event_index"], json!(3));
+        assert_eq!(limited[4], events[4]);
+
+        // at most 3 bytes of print values per tx: the 4-byte value doesn't fit, but the
+        // 1-byte value after it still does
+        let mut limited = events.clone();
+        let policy = EventLimitPolicy {
+            max_print_bytes_per_tx: 3,
+            ..EventLimitPolicy::default()
+        };
+        limit_print_events(&policy, &mut limited);
+        assert_eq!(limited[0], events[0]);
+        assert_eq!(limited[1]["contract_event"]["truncated"], json!(true));
+        assert_eq!(limited[3], events[3]);
+        assert_eq!(limited[4]["contract_event"]["truncated"], json!(true));
+    }
+
+    fn make_print_event_message(txid: u8, event_index: u64, raw_value: &[u8]) -> TransactionEvent {
+        TransactionEvent {
+            txid: vec![txid; 32],
+            event_index,
+            committed: true,
+            event: Some(transaction_event::Event::ContractEvent(ContractEvent {
+                contract_identifier: "ST000000000000000000002AMW42H.spammer".to_string(),
+                topic: "print".to_string(),
+                raw_value: raw_value.to_vec(),
+                truncated: false,
+            })),
+        }
+    }
+
+    #[test]
+    fn test_limit_print_event_messages() {
+        let transfer = TransactionEvent {
+            txid: vec![1; 32],
+            event_index: 2,
+            committed: true,
+            event: Some(transaction_event::Event::StxTransferEvent(
+                StxTransferEvent::default(),
+            )),
+        };
+        let events = vec![
+            make_print_event_message(1, 0, &[1, 0]),
+            make_print_event_message(1, 1, &[1, 0, 0, 0]),
+            transfer.clone(),
+            make_print_event_message(1, 3, &[1]),
+            make_print_event_message(2, 4, &[1, 0, 0, 0]),
+        ];
+        let truncated = |event: &TransactionEvent| match event.event {
+            Some(transaction_event::Event::ContractEvent(ref contract_event)) => {
+                contract_event.truncated && contract_event.raw_value.is_empty()
+            }
+            _ => false,
+        };
+
+        // no limits
+        let mut limited = events.clone();
+        limit_print_event_messages(&EventLimitPolicy::default(), &mut limited);
+        assert_eq!(limited, events);
+
+        // at most 2 print events per tx
+        let mut limited = events.clone();
+        let policy = EventLimitPolicy {
+            max_print_events_per_tx: 2,
+            ..EventLimitPolicy::default()
+        };
+        limit_print_event_messages(&policy, &mut limited);
+        assert_eq!(limited[0], events[0]);
+        assert_eq!(limited[1], events[1]);
+        assert_eq!(limited[2], transfer);
+        assert!(truncated(&limited[3]));
+        assert_eq!(limited[3].event_index, 3);
+        assert_eq!(limited[4], events[4]);
+
+        // at most 3 bytes of print values per tx
+        let mut limited = events.clone();
+        let policy = EventLimitPolicy {
+            max_print_bytes_per_tx: 3,
+            ..EventLimitPolicy::default()
+        };
+        limit_print_event_messages(&policy, &mut limited);
+        assert_eq!(limited[0], events[0]);
+        assert!(truncated(&limited[1]));
+        assert_eq!(limited[3], events[3]);
+        assert!(truncated(&limited[4]));
+    }
+}
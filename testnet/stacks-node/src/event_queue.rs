@@ -0,0 +1,393 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use rusqlite::types::ToSql;
+use rusqlite::{OpenFlags, Row, NO_PARAMS};
+
+use stacks::net::RPCEventObserverStatus;
+use stacks::util::db::Error as db_error;
+use stacks::util::db::{
+    query_count, query_row, tx_begin_immediate, tx_busy_handler, u64_to_sql, DBConn, FromColumn,
+    FromRow,
+};
+
+/// Delay before the first redelivery attempt of an event, in seconds.  It doubles on each
+/// subsequent failure.
+pub const EVENT_RETRY_BASE_DELAY_SECS: u64 = 1;
+/// Longest delay between redelivery attempts of an event, in seconds.
+pub const EVENT_RETRY_MAX_DELAY_SECS: u64 = 300;
+
+const EVENT_QUEUE_SCHEMA: &'static [&'static str] = &[
+    r#"
+    -- events that could not be delivered to their observer, in the order they were produced
+    CREATE TABLE IF NOT EXISTS pending_events(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        endpoint TEXT NOT NULL,
+        path TEXT NOT NULL,
+        body BLOB NOT NULL,
+        content_type TEXT NOT NULL DEFAULT 'application/json',
+        created_at INTEGER NOT NULL,
+        attempts INTEGER NOT NULL,
+        next_attempt_at INTEGER NOT NULL,
+        last_error TEXT
+    );"#,
+    "CREATE INDEX IF NOT EXISTS pending_events_by_endpoint ON pending_events(endpoint, id);",
+];
+
+/// Added to `pending_events` on open if it is missing, since it was added after the initial
+/// schema.  Every event queued before then is JSON.
+const EVENT_QUEUE_CONTENT_TYPE_COLUMN: &'static str =
+    "ALTER TABLE pending_events ADD COLUMN content_type TEXT NOT NULL DEFAULT 'application/json';";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingEvent {
+    pub id: i64,
+    pub endpoint: String,
+    pub path: String,
+    pub body: Vec<u8>,
+    /// Content type of `body`, which is sent as it was encoded when the event was queued
+    pub content_type: String,
+    pub created_at: u64,
+    pub attempts: u64,
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+}
+
+impl FromRow<PendingEvent> for PendingEvent {
+    fn from_row<'a>(row: &'a Row) -> Result<PendingEvent, db_error> {
+        Ok(PendingEvent {
+            id: row.get_unwrap("id"),
+            endpoint: row.get_unwrap("endpoint"),
+            path: row.get_unwrap("path"),
+            body: row.get_unwrap("body"),
+            content_type: row.get_unwrap("content_type"),
+            created_at: u64::from_column(row, "created_at")?,
+            attempts: u64::from_column(row, "attempts")?,
+            next_attempt_at: u64::from_column(row, "next_attempt_at")?,
+            last_error: row.get_unwrap("last_error"),
+        })
+    }
+}
+
+/// In-memory delivery counters for an observer since the node started.
+#[derive(Debug, Clone, Default)]
+struct DeliveryStats {
+    delivered: u64,
+    failed_attempts: u64,
+    last_delivered_at: Option<u64>,
+}
+
+/// How long to wait before the next delivery attempt of an event that has failed `attempts`
+/// times.
+pub fn retry_delay_secs(attempts: u64) -> u64 {
+    let exponent = cmp::min(attempts.saturating_sub(1), 32);
+    cmp::min(
+        EVENT_RETRY_BASE_DELAY_SECS.saturating_mul(1u64 << exponent),
+        EVENT_RETRY_MAX_DELAY_SECS,
+    )
+}
+
+/// Durable queue of events that could not be delivered to their observers.  Events are kept
+/// per observer endpoint in the order they were produced, so an observer never receives an
+/// event ahead of one it missed.
+pub struct EventRetryQueue {
+    conn: DBConn,
+    stats: HashMap<String, DeliveryStats>,
+}
+
+impl EventRetryQueue {
+    pub fn open(path: &str) -> Result<EventRetryQueue, db_error> {
+        let open_flags = OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE;
+        let conn = DBConn::open_with_flags(path, open_flags).map_err(db_error::SqliteError)?;
+        conn.busy_handler(Some(tx_busy_handler))
+            .map_err(db_error::SqliteError)?;
+        EventRetryQueue::instantiate(conn)
+    }
+
+    #[cfg(test)]
+    pub fn open_memory() -> Result<EventRetryQueue, db_error> {
+        let conn = DBConn::open_in_memory().map_err(db_error::SqliteError)?;
+        EventRetryQueue::instantiate(conn)
+    }
+
+    fn instantiate(mut conn: DBConn) -> Result<EventRetryQueue, db_error> {
+        let tx = tx_begin_immediate(&mut conn)?;
+        for cmd in EVENT_QUEUE_SCHEMA {
+            tx.execute_batch(cmd).map_err(db_error::SqliteError)?;
+        }
+        let has_content_type = query_count(
+            &tx,
+            "SELECT COUNT(*) FROM pragma_table_info('pending_events') WHERE name = 'content_type'",
+            NO_PARAMS,
+        )? > 0;
+        if !has_content_type {
+            tx.execute_batch(EVENT_QUEUE_CONTENT_TYPE_COLUMN)
+                .map_err(db_error::SqliteError)?;
+        }
+        tx.commit().map_err(db_error::SqliteError)?;
+
+        Ok(EventRetryQueue {
+            conn,
+            stats: HashMap::new(),
+        })
+    }
+
+    /// Queue an event for `endpoint` behind any it already has pending.  `attempts` is the
+    /// number of delivery attempts already made.
+    pub fn push(
+        &mut self,
+        endpoint: &str,
+        path: &str,
+        body: &[u8],
+        content_type: &str,
+        now: u64,
+        attempts: u64,
+        last_error: Option<&str>,
+    ) -> Result<(), db_error> {
+        let next_attempt_at = if attempts > 0 {
+            now + retry_delay_secs(attempts)
+        } else {
+            now
+        };
+        let args: &[&dyn ToSql] = &[
+            &endpoint,
+            &path,
+            &body,
+            &content_type,
+            &u64_to_sql(now)?,
+            &u64_to_sql(attempts)?,
+            &u64_to_sql(next_attempt_at)?,
+            &last_error,
+        ];
+        self.conn
+            .execute(
+                "INSERT INTO pending_events (endpoint, path, body, content_type, created_at, attempts, next_attempt_at, last_error) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                args,
+            )
+            .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Number of events pending for `endpoint`.
+    pub fn num_pending(&self, endpoint: &str) -> Result<u64, db_error> {
+        let args: &[&dyn ToSql] = &[&endpoint];
+        let count = query_count(
+            &self.conn,
+            "SELECT COUNT(*) FROM pending_events WHERE endpoint = ?1",
+            args,
+        )?;
+        Ok(count as u64)
+    }
+
+    /// The oldest event pending for `endpoint`, which must be delivered first.
+    pub fn peek(&self, endpoint: &str) -> Result<Option<PendingEvent>, db_error> {
+        let args: &[&dyn ToSql] = &[&endpoint];
+        query_row(
+            &self.conn,
+            "SELECT * FROM pending_events WHERE endpoint = ?1 ORDER BY id ASC LIMIT 1",
+            args,
+        )
+    }
+
+    /// Forget a pending event once it has been delivered.
+    pub fn remove(&mut self, id: i64) -> Result<(), db_error> {
+        let args: &[&dyn ToSql] = &[&id];
+        self.conn
+            .execute("DELETE FROM pending_events WHERE id = ?1", args)
+            .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    /// Record a failed redelivery of a pending event, and back off before the next one.
+    pub fn record_retry_failure(
+        &mut self,
+        event: &PendingEvent,
+        now: u64,
+        error: &str,
+    ) -> Result<(), db_error> {
+        let attempts = event.attempts + 1;
+        let args: &[&dyn ToSql] = &[
+            &u64_to_sql(attempts)?,
+            &u64_to_sql(now + retry_delay_secs(attempts))?,
+            &error,
+            &event.id,
+        ];
+        self.conn
+            .execute(
+                "UPDATE pending_events SET attempts = ?1, next_attempt_at = ?2, last_error = ?3 WHERE id = ?4",
+                args,
+            )
+            .map_err(db_error::SqliteError)?;
+        Ok(())
+    }
+
+    pub fn note_delivered(&mut self, endpoint: &str, now: u64) {
+        let stats = self.stats.entry(endpoint.to_string()).or_default();
+        stats.delivered += 1;
+        stats.last_delivered_at = Some(now);
+    }
+
+    pub fn note_failed_attempt(&mut self, endpoint: &str) {
+        self.stats
+            .entry(endpoint.to_string())
+            .or_default()
+            .failed_attempts += 1;
+    }
+
+    /// Delivery status of the observer at `endpoint`.
+    pub fn get_status(&self, endpoint: &str) -> Result<RPCEventObserverStatus, db_error> {
+        let pending_events = self.num_pending(endpoint)?;
+        let oldest = self.peek(endpoint)?;
+        let stats = self.stats.get(endpoint).cloned().unwrap_or_default();
+        Ok(RPCEventObserverStatus {
+            endpoint: endpoint.to_string(),
+            pending_events,
+            oldest_pending_at: oldest.as_ref().map(|ev| ev.created_at),
+            next_attempt_at: oldest.as_ref().map(|ev| ev.next_attempt_at),
+            last_error: oldest.and_then(|ev| ev.last_error),
+            delivered: stats.delivered,
+            failed_attempts: stats.failed_attempts,
+            last_delivered_at: stats.last_delivered_at,
+        })
+    }
+
+    /// Total number of pending events, across all observers.
+    pub fn total_pending(&self) -> Result<u64, db_error> {
+        let count = query_count(&self.conn, "SELECT COUNT(*) FROM pending_events", NO_PARAMS)?;
+        Ok(count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay_secs(1), 1);
+        assert_eq!(retry_delay_secs(2), 2);
+        assert_eq!(retry_delay_secs(5), 16);
+        assert_eq!(retry_delay_secs(9), 256);
+        assert_eq!(retry_delay_secs(10), EVENT_RETRY_MAX_DELAY_SECS);
+        assert_eq!(
+            retry_delay_secs(u64::max_value()),
+            EVENT_RETRY_MAX_DELAY_SECS
+        );
+    }
+
+    #[test]
+    fn test_pending_events_in_order() {
+        let mut queue = EventRetryQueue::open_memory().unwrap();
+        let a = "localhost:3700";
+        let b = "localhost:3701";
+
+        queue
+            .push(
+                a,
+                "new_block",
+                b"1",
+                "application/json",
+                100,
+                1,
+                Some("refused"),
+            )
+            .unwrap();
+        queue
+            .push(b, "new_block", b"x", "application/json", 100, 1, None)
+            .unwrap();
+        queue
+            .push(
+                a,
+                "new_burn_block",
+                b"2",
+                "application/x-protobuf",
+                101,
+                0,
+                None,
+            )
+            .unwrap();
+
+        assert_eq!(queue.num_pending(a).unwrap(), 2);
+        assert_eq!(queue.total_pending().unwrap(), 3);
+
+        let first = queue.peek(a).unwrap().unwrap();
+        assert_eq!(first.body, b"1".to_vec());
+        assert_eq!(first.content_type, "application/json");
+        assert_eq!(first.path, "new_block");
+        assert_eq!(first.next_attempt_at, 101);
+
+        queue
+            .record_retry_failure(&first, 200, "timed out")
+            .unwrap();
+        queue.note_failed_attempt(a);
+        let first = queue.peek(a).unwrap().unwrap();
+        assert_eq!(first.attempts, 2);
+        assert_eq!(first.next_attempt_at, 202);
+
+        let status = queue.get_status(a).unwrap();
+        assert_eq!(status.pending_events, 2);
+        assert_eq!(status.oldest_pending_at, Some(100));
+        assert_eq!(status.next_attempt_at, Some(202));
+        assert_eq!(status.last_error, Some("timed out".to_string()));
+        assert_eq!(status.failed_attempts, 1);
+
+        queue.remove(first.id).unwrap();
+        queue.note_delivered(a, 202);
+        let second = queue.peek(a).unwrap().unwrap();
+        assert_eq!(second.body, b"2".to_vec());
+        assert_eq!(second.content_type, "application/x-protobuf");
+        assert_eq!(second.next_attempt_at, 101);
+
+        queue.remove(second.id).unwrap();
+        let status = queue.get_status(a).unwrap();
+        assert_eq!(status.pending_events, 0);
+        assert_eq!(status.next_attempt_at, None);
+        assert_eq!(status.delivered, 1);
+        assert_eq!(status.last_delivered_at, Some(202));
+
+        // other observers are unaffected
+        assert_eq!(queue.num_pending(b).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_content_type_added_on_open() {
+        // a queue from before events were queued with their content type
+        let conn = DBConn::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE pending_events(
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                endpoint TEXT NOT NULL,
+                path TEXT NOT NULL,
+                body BLOB NOT NULL,
+                created_at INTEGER NOT NULL,
+                attempts INTEGER NOT NULL,
+                next_attempt_at INTEGER NOT NULL,
+                last_error TEXT
+            );
+            INSERT INTO pending_events (endpoint, path, body, created_at, attempts, next_attempt_at)
+                VALUES ('localhost:3700', 'new_block', X'7b7d', 100, 1, 101);",
+        )
+        .unwrap();
+
+        let mut queue = EventRetryQueue::instantiate(conn).unwrap();
+        let old = queue.peek("localhost:3700").unwrap().unwrap();
+        assert_eq!(old.body, b"{}".to_vec());
+        assert_eq!(old.content_type, "application/json");
+
+        queue
+            .push(
+                "localhost:3700",
+                "new_block",
+                b"\x0a\x00",
+                "application/x-protobuf",
+                102,
+                0,
+                None,
+            )
+            .unwrap();
+        queue.remove(old.id).unwrap();
+        let new = queue.peek("localhost:3700").unwrap().unwrap();
+        assert_eq!(new.content_type, "application/x-protobuf");
+    }
+}
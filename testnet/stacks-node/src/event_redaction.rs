@@ -0,0 +1,242 @@
+//! Per-observer redaction of event payloads.  JSON payloads are redacted before they are
+//! serialized; protobuf payloads are redacted as they are built, with `redact_tx()` and
+//! `redact_principal()`.
+
+use stacks::chainstate::stacks::{StacksTransaction, TokenTransferMemo, TransactionPayload};
+use stacks::codec::StacksMessageCodec;
+use stacks::util::hash::{hex_bytes, to_hex, Sha256Sum};
+use stacks::vm::types::PrincipalData;
+
+use super::config::EventRedactionPolicy;
+use super::event_dispatcher::PATH_MEMPOOL_TX_SUBMIT;
+
+fn decode_raw_tx(raw_tx: &str) -> Option<StacksTransaction> {
+    if !raw_tx.starts_with("0x") {
+        return None;
+    }
+    let bytes = hex_bytes(&raw_tx[2..]).ok()?;
+    StacksTransaction::consensus_deserialize(&mut &bytes[..]).ok()
+}
+
+/// Zero the memo of an STX transfer.  Returns false, leaving `tx` unchanged, for anything else.
+fn clear_tx_memo(tx: &mut StacksTransaction) -> bool {
+    match tx.payload {
+        TransactionPayload::TokenTransfer(_, _, ref mut memo) => {
+            *memo = TokenTransferMemo([0u8; 34]);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Zero the memo of a hex-encoded STX transfer.  Anything else is returned unchanged.
+fn clear_memo(raw_tx: &str) -> String {
+    let mut tx = match decode_raw_tx(raw_tx) {
+        Some(tx) => tx,
+        None => return raw_tx.to_string(),
+    };
+    if !clear_tx_memo(&mut tx) {
+        return raw_tx.to_string();
+    }
+    format!("0x{}", to_hex(&tx.serialize_to_vec()))
+}
+
+fn hash_principal(salt: &str, principal: &str) -> String {
+    let mut preimage = salt.as_bytes().to_vec();
+    preimage.extend_from_slice(principal.as_bytes());
+    format!("0x{}", Sha256Sum::from_data(&preimage))
+}
+
+/// The serialized transaction to send to an observer with `policy`, or `None` if it omits
+/// transactions.
+pub fn redact_tx(policy: &EventRedactionPolicy, tx: &StacksTransaction) -> Option<Vec<u8>> {
+    if policy.omit_raw_tx {
+        return None;
+    }
+    if policy.omit_memos {
+        let mut cleared = tx.clone();
+        if clear_tx_memo(&mut cleared) {
+            return Some(cleared.serialize_to_vec());
+        }
+    }
+    Some(tx.serialize_to_vec())
+}
+
+/// The principal or address to send to an observer with `policy`.  Standard principals are
+/// hashed if it hashes principals; contract principals never are.
+pub fn redact_principal(policy: &EventRedactionPolicy, principal: &str) -> String {
+    if policy.hash_principals && PrincipalData::parse_standard_principal(principal).is_ok() {
+        hash_principal(&policy.principal_hash_salt, principal)
+    } else {
+        principal.to_string()
+    }
+}
+
+fn redact_value(policy: &EventRedactionPolicy, value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            if policy.omit_raw_tx {
+                map.remove("raw_tx");
+            } else if policy.omit_memos {
+                if let Some(serde_json::Value::String(raw_tx)) = map.get_mut("raw_tx") {
+                    *raw_tx = clear_memo(raw_tx);
+                }
+            }
+            for (_, field) in map.iter_mut() {
+                redact_value(policy, field);
+            }
+        }
+        serde_json::Value::Array(values) => {
+            for value in values.iter_mut() {
+                redact_value(policy, value);
+            }
+        }
+        serde_json::Value::String(s) => {
+            *s = redact_principal(policy, s);
+        }
+        _ => {}
+    }
+}
+
+/// Apply `policy` to a payload destined for `path`.
+pub fn redact_payload(
+    policy: &EventRedactionPolicy,
+    path: &str,
+    payload: &serde_json::Value,
+) -> serde_json::Value {
+    let mut redacted = payload.clone();
+    if path == PATH_MEMPOOL_TX_SUBMIT {
+        // this payload is nothing but a list of raw transactions, so send their txids instead
+        // if the transactions are to be omitted
+        if let serde_json::Value::Array(raw_txs) = &mut redacted {
+            for raw_tx in raw_txs.iter_mut() {
+                let redacted_tx = match raw_tx.as_str() {
+                    Some(s) if policy.omit_raw_tx => match decode_raw_tx(s) {
+                        Some(tx) => json!(format!("0x{}", tx.txid())),
+                        None => serde_json::Value::Null,
+                    },
+                    Some(s) if policy.omit_memos => json!(clear_memo(s)),
+                    _ => continue,
+                };
+                *raw_tx = redacted_tx;
+            }
+        }
+        return redacted;
+    }
+
+    redact_value(policy, &mut redacted);
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event_dispatcher::PATH_BLOCK_PROCESSED;
+    use crate::tests::{make_stacks_transfer, to_addr};
+    use stacks::chainstate::stacks::StacksPrivateKey;
+
+    fn make_transfer() -> (String, StacksTransaction) {
+        let sk = StacksPrivateKey::new();
+        let recipient = PrincipalData::from(to_addr(&StacksPrivateKey::new()));
+        let mut tx = decode_raw_tx(&format!(
+            "0x{}",
+            to_hex(&make_stacks_transfer(&sk, 0, 180, &recipient, 1000))
+        ))
+        .unwrap();
+        if let TransactionPayload::TokenTransfer(_, _, ref mut memo) = tx.payload {
+            *memo = TokenTransferMemo([7u8; 34]);
+        }
+        (format!("0x{}", to_hex(&tx.serialize_to_vec())), tx)
+    }
+
+    #[test]
+    fn test_redact_block_payload() {
+        let (raw_tx, tx) = make_transfer();
+        let sender = to_addr(&StacksPrivateKey::new()).to_string();
+        let payload = json!({
+            "block_height": 12,
+            "transactions": [{ "txid": format!("0x{}", tx.txid()), "raw_tx": raw_tx.clone() }],
+            "events": [{
+                "type": "stx_transfer_event",
+                "stx_transfer_event": {
+                    "sender": sender.clone(),
+                    "recipient": "SP000000000000000000002Q6VF78.pox",
+                    "amount": "1000"
+                }
+            }]
+        });
+
+        let policy = EventRedactionPolicy::default();
+        assert!(policy.is_empty());
+        assert_eq!(
+            redact_payload(&policy, PATH_BLOCK_PROCESSED, &payload),
+            payload
+        );
+
+        let policy = EventRedactionPolicy {
+            omit_raw_tx: true,
+            ..EventRedactionPolicy::default()
+        };
+        let redacted = redact_payload(&policy, PATH_BLOCK_PROCESSED, &payload);
+        assert!(redacted["transactions"][0].get("raw_tx").is_none());
+        assert_eq!(
+            redacted["transactions"][0]["txid"],
+            payload["transactions"][0]["txid"]
+        );
+
+        let policy = EventRedactionPolicy {
+            omit_memos: true,
+            ..EventRedactionPolicy::default()
+        };
+        let redacted = redact_payload(&policy, PATH_BLOCK_PROCESSED, &payload);
+        let cleared =
+            decode_raw_tx(redacted["transactions"][0]["raw_tx"].as_str().unwrap()).unwrap();
+        match cleared.payload {
+            TransactionPayload::TokenTransfer(_, amount, memo) => {
+                assert_eq!(amount, 1000);
+                assert_eq!(memo, TokenTransferMemo([0u8; 34]));
+            }
+            _ => panic!("expected a token transfer"),
+        }
+
+        let policy = EventRedactionPolicy {
+            hash_principals: true,
+            principal_hash_salt: "salt".to_string(),
+            ..EventRedactionPolicy::default()
+        };
+        let redacted = redact_payload(&policy, PATH_BLOCK_PROCESSED, &payload);
+        let event = &redacted["events"][0]["stx_transfer_event"];
+        assert_eq!(event["sender"], json!(hash_principal("salt", &sender)));
+        assert_ne!(event["sender"], json!(hash_principal("", &sender)));
+        // contract principals are left alone
+        assert_eq!(
+            event["recipient"],
+            json!("SP000000000000000000002Q6VF78.pox")
+        );
+        assert_eq!(event["amount"], json!("1000"));
+    }
+
+    #[test]
+    fn test_redact_mempool_payload() {
+        let (raw_tx, tx) = make_transfer();
+        let payload = json!([raw_tx]);
+
+        let policy = EventRedactionPolicy {
+            omit_raw_tx: true,
+            omit_memos: true,
+            ..EventRedactionPolicy::default()
+        };
+        assert_eq!(
+            redact_payload(&policy, PATH_MEMPOOL_TX_SUBMIT, &payload),
+            json!([format!("0x{}", tx.txid())])
+        );
+
+        let policy = EventRedactionPolicy {
+            omit_memos: true,
+            ..EventRedactionPolicy::default()
+        };
+        let redacted = redact_payload(&policy, PATH_MEMPOOL_TX_SUBMIT, &payload);
+        let cleared = decode_raw_tx(redacted[0].as_str().unwrap()).unwrap();
+        assert_ne!(cleared.txid(), tx.txid());
+    }
+}
@@ -0,0 +1,279 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::sleep;
+use std::time::Duration;
+
+use stacks::burnchains::Txid;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::{StacksChainState, StacksEpochReceipt};
+use stacks::chainstate::stacks::StacksBlock;
+use stacks::net::{EventReplayRequestBody, RPCEventReplay};
+use stacks::types::chainstate::StacksBlockId;
+use stacks::util::get_epoch_time_secs;
+use stacks::vm::costs::ExecutionCost;
+
+/// Regenerates the events of past Stacks blocks for an event observer that was added after the
+/// node synced, by re-executing the blocks against the node's own chainstate.  Nothing is
+/// written to the chainstate, and consensus is not re-run: the canonical chain is only read.
+/// Replays run in the background, one block at a time, at no more than the requested rate.
+pub struct EventReplayer {
+    mainnet: bool,
+    chain_id: u32,
+    chainstate_path: String,
+    burn_db_path: String,
+    block_limit: ExecutionCost,
+    should_keep_running: Arc<AtomicBool>,
+    // every replay started since the node started, in the order they were started
+    replays: Mutex<Vec<RPCEventReplay>>,
+}
+
+impl EventReplayer {
+    pub fn new(
+        mainnet: bool,
+        chain_id: u32,
+        chainstate_path: String,
+        burn_db_path: String,
+        block_limit: ExecutionCost,
+        should_keep_running: Arc<AtomicBool>,
+    ) -> EventReplayer {
+        EventReplayer {
+            mainnet,
+            chain_id,
+            chainstate_path,
+            burn_db_path,
+            block_limit,
+            should_keep_running,
+            replays: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn get_replays(&self) -> Vec<RPCEventReplay> {
+        self.replays
+            .lock()
+            .expect("FATAL: event replay lock poisoned")
+            .clone()
+    }
+
+    /// Height of the canonical Stacks chain tip
+    fn get_stacks_tip_height(&self) -> Result<u64, String> {
+        let sortdb = SortitionDB::open(&self.burn_db_path, false)
+            .map_err(|e| format!("Failed to open sortition DB: {:?}", &e))?;
+        let tip = SortitionDB::get_canonical_burn_chain_tip(sortdb.conn())
+            .map_err(|e| format!("Failed to load burnchain tip: {:?}", &e))?;
+        Ok(tip.canonical_stacks_tip_height)
+    }
+
+    /// Record a new replay for `request`, which has already been validated and replays at
+    /// `rate` blocks a second, returning its index.  Fails if the requested heights are past the
+    /// chain tip, or if a replay to the same observer is still running.
+    pub fn begin(&self, request: &EventReplayRequestBody, rate: u64) -> Result<usize, String> {
+        let tip_height = self.get_stacks_tip_height()?;
+        let to_height = request.to_height.unwrap_or(tip_height);
+        if to_height > tip_height {
+            return Err(format!(
+                "Cannot replay up to height {}: the chain tip is at height {}",
+                to_height, tip_height
+            ));
+        }
+        if request.from_height > to_height {
+            return Err(format!(
+                "Cannot replay from height {}: the chain tip is at height {}",
+                request.from_height, tip_height
+            ));
+        }
+        self.register(request, rate, to_height)
+    }
+
+    fn register(
+        &self,
+        request: &EventReplayRequestBody,
+        rate: u64,
+        to_height: u64,
+    ) -> Result<usize, String> {
+        let mut replays = self
+            .replays
+            .lock()
+            .expect("FATAL: event replay lock poisoned");
+        if replays
+            .iter()
+            .any(|replay| replay.endpoint == request.endpoint && replay.finished_at.is_none())
+        {
+            return Err(format!(
+                "A replay to {} is already running",
+                &request.endpoint
+            ));
+        }
+        replays.push(RPCEventReplay {
+            endpoint: request.endpoint.clone(),
+            from_height: request.from_height,
+            to_height,
+            next_height: request.from_height,
+            blocks_per_second: rate,
+            started_at: get_epoch_time_secs(),
+            finished_at: None,
+            error: None,
+        });
+        Ok(replays.len() - 1)
+    }
+
+    fn update<F>(&self, index: usize, update: F)
+    where
+        F: FnOnce(&mut RPCEventReplay),
+    {
+        let mut replays = self
+            .replays
+            .lock()
+            .expect("FATAL: event replay lock poisoned");
+        update(&mut replays[index]);
+    }
+
+    /// Run the replay at `index` to completion, handing each canonical block, its regenerated
+    /// receipt, its parent and the txid of the block-commit that won its sortition to
+    /// `announce`.
+    pub fn run<F>(&self, index: usize, mut announce: F)
+    where
+        F: FnMut(StacksBlock, StacksEpochReceipt, &StacksBlockId, Txid),
+    {
+        let result = self.replay_blocks(index, &mut announce);
+        self.update(index, |replay| {
+            replay.finished_at = Some(get_epoch_time_secs());
+            replay.error = result.err();
+        });
+
+        let replay = self.get_replays().remove(index);
+        match replay.error {
+            Some(ref error) => warn!(
+                "Event replay to {} stopped at height {}: {}",
+                &replay.endpoint, replay.next_height, error
+            ),
+            None => info!(
+                "Event replay to {} finished at height {}",
+                &replay.endpoint, replay.to_height
+            ),
+        }
+    }
+
+    fn replay_blocks<F>(&self, index: usize, announce: &mut F) -> Result<(), String>
+    where
+        F: FnMut(StacksBlock, StacksEpochReceipt, &StacksBlockId, Txid),
+    {
+        let replay = self.get_replays().remove(index);
+        info!(
+            "Event replay to {}: replaying blocks {}-{}",
+            &replay.endpoint, replay.from_height, replay.to_height
+        );
+
+        let sortdb = SortitionDB::open(&self.burn_db_path, false)
+            .map_err(|e| format!("Failed to open sortition DB: {:?}", &e))?;
+        let (mut chainstate, _) = StacksChainState::open_with_block_limit(
+            self.mainnet,
+            self.chain_id,
+            &self.chainstate_path,
+            self.block_limit.clone(),
+        )
+        .map_err(|e| format!("Failed to open chainstate: {:?}", &e))?;
+
+        let delay = Duration::from_millis(1000 / replay.blocks_per_second);
+        for height in replay.from_height..=replay.to_height {
+            if !self.should_keep_running.load(Ordering::SeqCst) {
+                return Err("Node is shutting down".to_string());
+            }
+
+            // the canonical chain can change during a long replay, so find each block anew
+            let (tip_consensus_hash, tip_block_hash) =
+                SortitionDB::get_canonical_stacks_chain_tip_hash(sortdb.conn())
+                    .map_err(|e| format!("Failed to load canonical Stacks tip: {:?}", &e))?;
+            let tip = StacksBlockId::new(&tip_consensus_hash, &tip_block_hash);
+            let index_block_hash = chainstate
+                .index_conn()
+                .map_err(|e| format!("Failed to open chainstate index: {:?}", &e))?
+                .get_ancestor_block_hash(height, &tip)
+                .map_err(|e| format!("Failed to find block at height {}: {:?}", height, &e))?
+                .ok_or(format!("No canonical block at height {}", height))?;
+
+            let receipt = chainstate
+                .replay_processed_block(&sortdb.index_conn(), &index_block_hash)
+                .map_err(|e| format!("Failed to replay block {}: {:?}", &index_block_hash, &e))?
+                .ok_or(format!("Block {} is not processed", &index_block_hash))?;
+            let block = StacksChainState::load_block(
+                &chainstate.blocks_path,
+                &receipt.header.consensus_hash,
+                &receipt.header.anchored_header.block_hash(),
+            )
+            .map_err(|e| format!("Failed to load block {}: {:?}", &index_block_hash, &e))?
+            .ok_or(format!("Block {} is not stored", &index_block_hash))?;
+            let parent = chainstate
+                .get_parent(&index_block_hash)
+                .map_err(|e| format!("Failed to load parent of {}: {:?}", &index_block_hash, &e))?;
+            let winner_txid = SortitionDB::get_block_snapshot_consensus(
+                sortdb.conn(),
+                &receipt.header.consensus_hash,
+            )
+            .map_err(|e| format!("Failed to load snapshot: {:?}", &e))?
+            .ok_or(format!("No snapshot for block {}", &index_block_hash))?
+            .winning_block_txid;
+
+            announce(block, receipt, &parent, winner_txid);
+            self.update(index, |replay| replay.next_height = height + 1);
+            sleep(delay);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_replays() {
+        let replayer = EventReplayer::new(
+            false,
+            0x80000000,
+            "/nonexistent/chainstate".to_string(),
+            "/nonexistent/burnchain".to_string(),
+            ExecutionCost::max_value(),
+            Arc::new(AtomicBool::new(true)),
+        );
+        let request = |endpoint: &str| EventReplayRequestBody {
+            endpoint: endpoint.to_string(),
+            from_height: 1,
+            to_height: None,
+            blocks_per_second: None,
+        };
+
+        assert_eq!(
+            replayer.register(&request("localhost:3700"), 10, 100),
+            Ok(0)
+        );
+        assert_eq!(
+            replayer.register(&request("localhost:3701"), 10, 100),
+            Ok(1)
+        );
+
+        // only one replay at a time to each observer
+        assert!(replayer
+            .register(&request("localhost:3700"), 10, 100)
+            .is_err());
+
+        replayer.update(0, |replay| {
+            replay.next_height = 101;
+            replay.finished_at = Some(1600000100);
+        });
+        assert_eq!(
+            replayer.register(&request("localhost:3700"), 20, 200),
+            Ok(2)
+        );
+
+        let replays = replayer.get_replays();
+        assert_eq!(replays.len(), 3);
+        assert_eq!(replays[0].next_height, 101);
+        assert_eq!(replays[2].to_height, 200);
+        assert_eq!(replays[2].blocks_per_second, 20);
+        assert_eq!(replays[2].finished_at, None);
+
+        // can't start a replay without a chain to replay
+        assert!(replayer.begin(&request("localhost:3702"), 10).is_err());
+        assert_eq!(replayer.get_replays().len(), 3);
+    }
+}
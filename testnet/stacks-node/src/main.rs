@@ -16,6 +16,7 @@ extern crate stacks;
 extern crate slog;
 
 pub use stacks::util;
+use stacks::net::connection::NetworkMode;
 use stacks::util::hash::hex_bytes;
 
 pub mod monitoring;
@@ -23,6 +24,11 @@ pub mod monitoring;
 pub mod burnchains;
 pub mod config;
 pub mod event_dispatcher;
+pub mod event_encoding;
+pub mod event_limits;
+pub mod event_queue;
+pub mod event_redaction;
+pub mod event_replay;
 pub mod genesis_data;
 pub mod keychain;
 pub mod neon_node;
@@ -40,7 +46,7 @@ pub use self::event_dispatcher::EventDispatcher;
 pub use self::keychain::Keychain;
 pub use self::neon_node::{InitializedNeonNode, NeonGenesisNode};
 pub use self::node::{ChainTip, Node};
-pub use self::run_loop::{helium, neon};
+pub use self::run_loop::{helium, neon, replica};
 pub use self::tenure::Tenure;
 
 use pico_args::Arguments;
@@ -49,6 +55,7 @@ use std::env;
 use std::convert::TryInto;
 use std::panic;
 use std::process;
+use std::thread;
 
 use backtrace::Backtrace;
 
@@ -162,6 +169,13 @@ fn main() {
         }
     };
 
+    if let Some(networks) = config_file.network.clone() {
+        let confs = Config::from_network_config_files(networks)
+            .unwrap_or_else(|e| panic!("Invalid multi-network config: {}", e));
+        run_networks(confs, mine_start.unwrap_or(0));
+        return;
+    }
+
     let conf = Config::from_config_file(config_file);
     debug!("node configuration {:?}", &conf.node);
     debug!("burnchain configuration {:?}", &conf.burnchain);
@@ -170,17 +184,16 @@ fn main() {
 
     let num_round: u64 = 0; // Infinite number of rounds
 
-    if conf.burnchain.mode == "helium" || conf.burnchain.mode == "mocknet" {
+    if conf.connection_options.network_mode == NetworkMode::Replica {
+        let mut run_loop = replica::RunLoop::new(conf);
+        run_loop.start();
+    } else if conf.burnchain.mode == "helium" || conf.burnchain.mode == "mocknet" {
         let mut run_loop = helium::RunLoop::new(conf);
         if let Err(e) = run_loop.start(num_round) {
             warn!("Helium runloop exited: {}", e);
             return;
         }
-    } else if conf.burnchain.mode == "neon"
-        || conf.burnchain.mode == "xenon"
-        || conf.burnchain.mode == "krypton"
-        || conf.burnchain.mode == "mainnet"
-    {
+    } else if is_neon_mode(&conf.burnchain.mode) {
         let mut run_loop = neon::RunLoop::new(conf);
         run_loop.start(None, mine_start.unwrap_or(0));
     } else {
@@ -188,6 +201,67 @@ fn main() {
     }
 }
 
+fn is_neon_mode(mode: &str) -> bool {
+    mode == "neon" || mode == "xenon" || mode == "krypton" || mode == "mainnet"
+}
+
+/// Run one neon run loop per configured network, each on its own thread, until a
+/// termination request stops all of them.
+fn run_networks(confs: Vec<(String, Config)>, mine_start: u64) {
+    for (name, conf) in confs.iter() {
+        if !is_neon_mode(&conf.burnchain.mode) {
+            panic!(
+                "Network '{}': burnchain mode '{}' cannot run alongside other networks",
+                name, conf.burnchain.mode
+            );
+        }
+    }
+
+    // the monitoring metrics, persisted counters, global burnchain signer and builtin Clarity
+    // extensions are process-wide, so networks sharing the process would see each other's state
+    if confs.len() > 1 {
+        if cfg!(feature = "monitoring_prom") {
+            panic!("Multiple networks cannot run in a process built with the `monitoring_prom` feature");
+        }
+        if cfg!(feature = "clarity_extensions") {
+            panic!("Multiple networks cannot run in a process with builtin Clarity extensions");
+        }
+    }
+
+    let should_keep_running =
+        run_loop::termination_flag("Graceful termination request received, will stop all networks");
+
+    let mut handles = vec![];
+    for (name, conf) in confs.into_iter() {
+        info!(
+            "Starting network '{}' ({}) in {}",
+            &name, &conf.burnchain.mode, &conf.node.working_dir
+        );
+        let should_keep_running = should_keep_running.clone();
+        let handle = thread::Builder::new()
+            .name(format!("network-{}", &name))
+            .spawn(move || {
+                if conf.connection_options.network_mode == NetworkMode::Replica {
+                    let mut run_loop = replica::RunLoop::new(conf);
+                    run_loop.set_termination_flag(should_keep_running);
+                    run_loop.start();
+                } else {
+                    let mut run_loop = neon::RunLoop::new(conf);
+                    run_loop.set_termination_flag(should_keep_running);
+                    run_loop.start(None, mine_start);
+                }
+            })
+            .expect("Failed to spawn network thread");
+        handles.push((name, handle));
+    }
+
+    for (name, handle) in handles.into_iter() {
+        if handle.join().is_err() {
+            error!("Network '{}' exited abnormally", name);
+        }
+    }
+}
+
 fn version() -> String {
     stacks::version_string(
         "stacks-node",
@@ -230,6 +304,10 @@ start\t\tStart a node with a config of your own. Can be used for joining a netwo
 \t\t  --config: path of the config (such as https://github.com/blockstack/stacks-blockchain/blob/master/testnet/stacks-node/conf/testnet-follower-conf.toml).
 \t\tExample:
 \t\t  stacks-node start --config=/path/to/config.toml
+\t\tA config with `[network.<name>.*]` sections runs each named network side by side in this process,
+\t\twith databases isolated under `<working_dir>/<name>`. Binds must not overlap, and
+\t\t`prometheus_bind` is only allowed if there is a single network. Multiple networks cannot run in a
+\t\tnode built with the `monitoring_prom` or `clarity_extensions` features.
 
 version\t\tDisplay information about the current version and our release cycle.
 
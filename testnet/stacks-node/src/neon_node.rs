@@ -1,9 +1,10 @@
+use std::cell::RefCell;
 use std::cmp;
 use std::collections::HashMap;
 use std::collections::{HashSet, VecDeque};
 use std::convert::{TryFrom, TryInto};
-use std::default::Default;
 use std::net::SocketAddr;
+use std::path::PathBuf;
 use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -22,13 +23,16 @@ use stacks::chainstate::burn::BlockSnapshot;
 use stacks::chainstate::burn::ConsensusHash;
 use stacks::chainstate::coordinator::comm::CoordinatorChannels;
 use stacks::chainstate::coordinator::{get_next_recipients, OnChainRewardSetProvider};
+use stacks::chainstate::stacks::db::blocks::StagingBlock;
 use stacks::chainstate::stacks::db::unconfirmed::UnconfirmedTxMap;
 use stacks::chainstate::stacks::db::{
-    ChainStateBootData, ClarityTx, StacksChainState, MINER_REWARD_MATURITY,
+    ChainStateBootData, ClarityTx, StacksChainState, StacksHeaderInfo, MINER_REWARD_MATURITY,
 };
 use stacks::chainstate::stacks::Error as ChainstateError;
 use stacks::chainstate::stacks::StacksPublicKey;
-use stacks::chainstate::stacks::{miner::StacksMicroblockBuilder, StacksBlockBuilder};
+use stacks::chainstate::stacks::{
+    miner::PriorityLanes, miner::StacksMicroblockBuilder, StacksBlockBuilder,
+};
 use stacks::chainstate::stacks::{
     CoinbasePayload, StacksBlock, StacksMicroblock, StacksTransaction, StacksTransactionSigner,
     TransactionAnchorMode, TransactionPayload, TransactionVersion,
@@ -44,11 +48,13 @@ use stacks::net::{
     p2p::PeerNetwork,
     relay::Relayer,
     rpc::RPCHandlerArgs,
-    Error as NetError, NetworkResult, PeerAddress,
+    Error as NetError, NetworkResult, NodeStatusSource, PeerAddress, RPCMiningStatus,
+    RPCSubsystemError,
 };
 use stacks::types::chainstate::{
     BlockHeaderHash, BurnchainHeaderHash, SortitionId, StacksAddress, StacksBlockHeader, VRFSeed,
 };
+use stacks::util::disk::{DiskSpaceLevel, DiskWatcher};
 use stacks::util::get_epoch_time_ms;
 use stacks::util::get_epoch_time_secs;
 use stacks::util::hash::{to_hex, Hash160, Sha256Sum};
@@ -59,6 +65,7 @@ use stacks::util::vrf::VRFPublicKey;
 use stacks::vm::costs::ExecutionCost;
 
 use crate::burnchains::bitcoin_regtest_controller::BitcoinRegtestController;
+use crate::config::TipSelectionPolicy;
 use crate::run_loop::RegisteredKey;
 use crate::syncctl::PoxSyncWatchdogComms;
 use crate::ChainTip;
@@ -303,6 +310,207 @@ fn inner_generate_block_commit_op(
     })
 }
 
+/// Score a candidate chain tip under the given tip selection policy.  Higher is better.
+fn score_mining_tip(
+    policy: TipSelectionPolicy,
+    chain_state: &StacksChainState,
+    burn_db: &SortitionDB,
+    burn_block: &BlockSnapshot,
+    candidate: &StagingBlock,
+) -> Result<u128, ChainstateError> {
+    match policy {
+        TipSelectionPolicy::FirstSeen => Ok(0),
+        TipSelectionPolicy::HighestFees => {
+            let score = StacksChainState::get_miner_info(
+                chain_state.db(),
+                &candidate.consensus_hash,
+                &candidate.anchored_block_hash,
+            )?
+            .map(|info| info.tx_fees_anchored + info.tx_fees_streamed)
+            .unwrap_or(0);
+            Ok(score)
+        }
+        TipSelectionPolicy::MostConfirmations => {
+            let candidate_commit = match SortitionDB::get_block_commit_for_stacks_block(
+                burn_db.conn(),
+                &candidate.consensus_hash,
+                &candidate.anchored_block_hash,
+            )? {
+                Some(commit) => commit,
+                None => return Ok(0),
+            };
+            let confirmations =
+                SortitionDB::get_block_commits_by_block(burn_db.conn(), &burn_block.sortition_id)?
+                    .into_iter()
+                    .filter(|commit| {
+                        commit.parent_block_ptr as u64 == candidate_commit.block_height
+                            && commit.parent_vtxindex as u32 == candidate_commit.vtxindex
+                    })
+                    .count();
+            Ok(confirmations as u128)
+        }
+    }
+}
+
+/// Choose the Stacks chain tip to mine on, per the node's configured tip selection policy.
+/// Candidates are the processed blocks at the canonical tip's height on the canonical burn
+/// fork.  The canonical tip wins ties, so `first_seen` always keeps it.
+fn select_mining_tip(
+    config: &Config,
+    chain_state: &StacksChainState,
+    burn_db: &SortitionDB,
+    burn_block: &BlockSnapshot,
+) -> Result<Option<StagingBlock>, ChainstateError> {
+    let policy = config.node.tip_selection_policy;
+    let canonical_tip = match chain_state.get_stacks_chain_tip(burn_db)? {
+        Some(tip) => tip,
+        None => return Ok(None),
+    };
+    if policy == TipSelectionPolicy::FirstSeen {
+        monitoring::increment_miner_tip_selection(policy.as_str(), "canonical");
+        return Ok(Some(canonical_tip));
+    }
+
+    let mut best_score =
+        score_mining_tip(policy, chain_state, burn_db, burn_block, &canonical_tip)?;
+    let mut best_tip = None;
+    for candidate in chain_state
+        .get_stacks_chain_tips_at_height(burn_db, canonical_tip.height)?
+        .into_iter()
+    {
+        if candidate.consensus_hash == canonical_tip.consensus_hash
+            && candidate.anchored_block_hash == canonical_tip.anchored_block_hash
+        {
+            continue;
+        }
+        let score = score_mining_tip(policy, chain_state, burn_db, burn_block, &candidate)?;
+        if score > best_score {
+            best_score = score;
+            best_tip = Some(candidate);
+        }
+    }
+
+    match best_tip {
+        Some(tip) => {
+            info!(
+                "Tip selection policy {} chose {}/{} over canonical tip {}/{} at height {}",
+                policy.as_str(),
+                &tip.consensus_hash,
+                &tip.anchored_block_hash,
+                &canonical_tip.consensus_hash,
+                &canonical_tip.anchored_block_hash,
+                canonical_tip.height
+            );
+            monitoring::increment_miner_tip_selection(policy.as_str(), "alternate");
+            Ok(Some(tip))
+        }
+        None => {
+            monitoring::increment_miner_tip_selection(policy.as_str(), "canonical");
+            Ok(Some(canonical_tip))
+        }
+    }
+}
+
+/// Tracks whether an in-progress block assembly has gone stale, per the node's configured
+/// preemption rules.  Checks are rate-limited to once per `preempt_check_interval_ms`.
+struct AssemblyPreemption {
+    parent_consensus_hash: ConsensusHash,
+    parent_block_hash: BlockHeaderHash,
+    parent_height: u64,
+    /// sequence number of the first parent microblock we have not seen yet
+    next_microblock_seq: u16,
+    last_check_ms: u128,
+}
+
+impl AssemblyPreemption {
+    fn new(
+        parent_consensus_hash: &ConsensusHash,
+        parent_header: &StacksHeaderInfo,
+        next_microblock_seq: u16,
+    ) -> AssemblyPreemption {
+        AssemblyPreemption {
+            parent_consensus_hash: parent_consensus_hash.clone(),
+            parent_block_hash: parent_header.anchored_header.block_hash(),
+            parent_height: parent_header.block_height,
+            next_microblock_seq,
+            last_check_ms: get_epoch_time_ms(),
+        }
+    }
+
+    /// Should the block we're assembling be abandoned in favor of a better parent?
+    fn check(
+        &mut self,
+        config: &Config,
+        chain_state: &StacksChainState,
+        burn_db: &SortitionDB,
+    ) -> bool {
+        let now = get_epoch_time_ms();
+        if now < self.last_check_ms + (config.node.preempt_check_interval_ms as u128) {
+            return false;
+        }
+        self.last_check_ms = now;
+
+        if config.node.preempt_on_new_parent {
+            match chain_state.get_stacks_chain_tip(burn_db) {
+                Ok(Some(tip)) if tip.height > self.parent_height => {
+                    info!(
+                        "Preempt block assembly off of {}/{}: new Stacks chain tip {}/{} at height {} > {}",
+                        &self.parent_consensus_hash,
+                        &self.parent_block_hash,
+                        &tip.consensus_hash,
+                        &tip.anchored_block_hash,
+                        tip.height,
+                        self.parent_height
+                    );
+                    monitoring::increment_miner_block_assembly_preempted("new_parent");
+                    return true;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("Failed to query Stacks chain tip for preemption: {:?}", &e);
+                }
+            }
+        }
+
+        if config.node.preempt_on_microblocks {
+            match StacksChainState::load_descendant_staging_microblock_stream(
+                chain_state.db(),
+                &StacksBlockHeader::make_index_block_hash(
+                    &self.parent_consensus_hash,
+                    &self.parent_block_hash,
+                ),
+                self.next_microblock_seq,
+                u16::MAX,
+            ) {
+                Ok(Some(microblocks)) => {
+                    let new_fees: u64 = microblocks
+                        .iter()
+                        .flat_map(|mblock| mblock.txs.iter())
+                        .map(|tx| tx.get_tx_fee())
+                        .sum();
+                    if new_fees >= config.node.preempt_microblock_min_fee {
+                        info!(
+                            "Preempt block assembly off of {}/{}: {} new microblock(s) with {} in fees",
+                            &self.parent_consensus_hash,
+                            &self.parent_block_hash,
+                            microblocks.len(),
+                            new_fees
+                        );
+                        monitoring::increment_miner_block_assembly_preempted("microblocks");
+                        return true;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    warn!("Failed to load parent microblocks for preemption: {:?}", &e);
+                }
+            }
+        }
+
+        false
+    }
+}
+
 /// Mine and broadcast a single microblock, unconditionally.
 fn mine_one_microblock(
     microblock_state: &mut MicroblockMinerState,
@@ -579,6 +787,44 @@ fn recv_unconfirmed_txs(
     }
 }
 
+/// The node's state as served on `/v2/status`.  It lives on the p2p thread, which records its
+/// own errors in it; everything else is read from atomics shared with the other threads.
+struct P2PNodeStatus {
+    miner: bool,
+    sync_comms: PoxSyncWatchdogComms,
+    last_error: RefCell<Option<RPCSubsystemError>>,
+}
+
+impl P2PNodeStatus {
+    fn record_error(&self, message: String) {
+        self.last_error.replace(Some(RPCSubsystemError {
+            subsystem: "p2p".to_string(),
+            message,
+            at: Some(get_epoch_time_secs()),
+        }));
+    }
+}
+
+impl NodeStatusSource for P2PNodeStatus {
+    fn get_burnchain_tip_height(&self) -> Option<u64> {
+        match self.sync_comms.get_burnchain_tip_height() {
+            0 => None,
+            height => Some(height),
+        }
+    }
+
+    fn get_mining_status(&self) -> RPCMiningStatus {
+        RPCMiningStatus {
+            miner: self.miner,
+            initial_block_download: self.sync_comms.get_ibd(),
+        }
+    }
+
+    fn get_last_errors(&self) -> Vec<RPCSubsystemError> {
+        self.last_error.borrow().iter().cloned().collect()
+    }
+}
+
 fn spawn_peer(
     is_mainnet: bool,
     mut this: PeerNetwork,
@@ -591,6 +837,7 @@ fn spawn_peer(
     attachments_rx: Receiver<HashSet<AttachmentInstance>>,
     unconfirmed_txs: Arc<Mutex<UnconfirmedTxMap>>,
     event_observer: EventDispatcher,
+    coord_comms: CoordinatorChannels,
     should_keep_running: Arc<AtomicBool>,
 ) -> Result<JoinHandle<()>, NetError> {
     let burn_db_path = config.get_burn_db_file_path();
@@ -609,6 +856,7 @@ fn spawn_peer(
         block_limit,
     )
     .map_err(|e| NetError::ChainstateError(e.to_string()))?;
+    chainstate.contract_cost_stats = config.node.contract_cost_stats;
 
     let mut mem_pool = MemPoolDB::open(
         is_mainnet,
@@ -620,15 +868,34 @@ fn spawn_peer(
     // buffer up blocks to store without stalling the p2p thread
     let mut results_with_data = VecDeque::new();
 
+    // degrade gracefully as the disks holding our databases fill up
+    let mut disk_watcher = DiskWatcher::new(
+        vec![
+            PathBuf::from(config.get_burn_db_file_path()),
+            PathBuf::from(config.get_chainstate_path_str()),
+            PathBuf::from(config.get_peer_db_file_path()),
+            PathBuf::from(config.get_atlas_db_file_path()),
+        ],
+        config.node.disk_space_policy.clone(),
+    );
+
     let server_thread = thread::Builder::new()
         .name("p2p".to_string())
         .spawn(move || {
+            let node_status = P2PNodeStatus {
+                miner: config.node.miner,
+                sync_comms: sync_comms.clone(),
+                last_error: RefCell::new(None),
+            };
             let handler_args = RPCHandlerArgs {
                 exit_at_block_height: exit_at_block_height.as_ref(),
                 genesis_chainstate_hash: Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
                     .unwrap(),
                 event_observer: Some(&event_observer),
-                ..RPCHandlerArgs::default()
+                event_observer_status: Some(&event_observer),
+                event_replay: Some(&event_observer),
+                node_status: Some(&node_status),
+                block_processing: Some(&coord_comms),
             };
 
             let mut num_p2p_state_machine_passes = 0;
@@ -637,6 +904,14 @@ fn spawn_peer(
             let mut mblock_deadline = 0;
 
             while should_keep_running.load(Ordering::SeqCst) {
+                let disk_space_level = disk_watcher.check();
+                if disk_space_level >= DiskSpaceLevel::Halt {
+                    error!("P2P: Running out of disk space; shutting down");
+                    should_keep_running.store(false, Ordering::SeqCst);
+                    break;
+                }
+                this.set_disk_space_level(disk_space_level);
+
                 // initial block download?
                 let ibd = sync_comms.get_ibd();
                 let download_backpressure = results_with_data.len() > 0;
@@ -652,13 +927,13 @@ fn spawn_peer(
                     cmp::min(poll_timeout, config.node.microblock_frequency)
                 };
 
-                let mut expected_attachments = match attachments_rx.try_recv() {
-                    Ok(expected_attachments) => expected_attachments,
-                    _ => {
-                        debug!("Atlas: attachment channel is empty");
-                        HashSet::new()
-                    }
-                };
+                let mut expected_attachments = HashSet::new();
+                while let Ok(attachments_batch) = attachments_rx.try_recv() {
+                    expected_attachments.extend(attachments_batch);
+                }
+                if expected_attachments.is_empty() {
+                    debug!("Atlas: attachment channel is empty");
+                }
 
                 let _ = Relayer::setup_unconfirmed_state_readonly(&mut chainstate, &sortdb);
                 recv_unconfirmed_txs(&mut chainstate, unconfirmed_txs.clone());
@@ -711,6 +986,7 @@ fn spawn_peer(
                     }
                     Err(e) => {
                         error!("P2P: Failed to process network dispatch: {:?}", &e);
+                        node_status.record_error(format!("{:?}", &e));
                         if config.is_node_event_driven() {
                             panic!();
                         }
@@ -967,6 +1243,11 @@ fn spawn_miner_relayer(
                     if last_tenure_issue_time > issue_timestamp_ms {
                         continue;
                     }
+                    if coord_comms.is_block_processing_pause_requested() {
+                        // don't mine on top of a chain tip that isn't advancing
+                        info!("Relayer: Block processing is paused; not running tenure");
+                        continue;
+                    }
 
                     let burn_header_hash = last_burn_block.burn_header_hash.clone();
                     debug!(
@@ -990,20 +1271,34 @@ fn spawn_miner_relayer(
                         .remove(&burn_header_hash)
                         .unwrap_or_default();
 
-                    let last_mined_block_opt = InitializedNeonNode::relayer_run_tenure(
-                        &config,
-                        registered_key,
-                        &mut chainstate,
-                        &mut sortdb,
-                        &burnchain,
-                        last_burn_block,
-                        &mut keychain,
-                        &mut mem_pool,
-                        burn_fee_cap,
-                        &mut bitcoin_controller,
-                        &last_mined_blocks_vec.iter().map(|(blk, _)| blk).collect(),
-                        &event_dispatcher,
-                    );
+                    let mut num_preemptions = 0;
+                    let last_mined_block_opt = loop {
+                        let mut preempted = false;
+                        let last_mined_block_opt = InitializedNeonNode::relayer_run_tenure(
+                            &config,
+                            registered_key.clone(),
+                            &mut chainstate,
+                            &mut sortdb,
+                            &burnchain,
+                            last_burn_block.clone(),
+                            &mut keychain,
+                            &mut mem_pool,
+                            burn_fee_cap,
+                            &mut bitcoin_controller,
+                            &last_mined_blocks_vec.iter().map(|(blk, _)| blk).collect(),
+                            &event_dispatcher,
+                            num_preemptions < config.node.max_preemptions,
+                            &mut preempted,
+                        );
+                        if !preempted {
+                            break last_mined_block_opt;
+                        }
+                        num_preemptions += 1;
+                        info!(
+                            "Relayer: restarting block assembly after preemption {} of {}",
+                            num_preemptions, config.node.max_preemptions
+                        );
+                    };
                     if let Some((last_mined_block, microblock_privkey)) = last_mined_block_opt {
                         if last_mined_blocks_vec.len() == 0 {
                             // (for testing) only bump once per epoch
@@ -1136,20 +1431,7 @@ impl InitializedNeonNode {
             "Failed to parse socket: {}",
             &config.node.p2p_address
         ));
-        let node_privkey = {
-            let mut re_hashed_seed = config.node.local_peer_seed.clone();
-            let my_private_key = loop {
-                match Secp256k1PrivateKey::from_slice(&re_hashed_seed[..]) {
-                    Ok(sk) => break sk,
-                    Err(_) => {
-                        re_hashed_seed = Sha256Sum::from_data(&re_hashed_seed[..])
-                            .as_bytes()
-                            .to_vec()
-                    }
-                }
-            };
-            my_private_key
-        };
+        let node_privkey = config.node.get_node_private_key();
 
         let mut peerdb = PeerDB::connect(
             &config.get_peer_db_file_path(),
@@ -1174,6 +1456,13 @@ impl InitializedNeonNode {
         })
         .unwrap();
 
+        {
+            // advertise the configured services
+            let mut tx = peerdb.tx_begin().unwrap();
+            PeerDB::set_local_services(&mut tx, config.node.services).unwrap();
+            tx.commit().unwrap();
+        }
+
         {
             // bootstrap nodes *always* allowed
             let mut tx = peerdb.tx_begin().unwrap();
@@ -1238,12 +1527,15 @@ impl InitializedNeonNode {
         // setup the relayer channel
         let (relay_send, relay_recv) = sync_channel(RELAYER_MAX_BUFFER);
 
+        // the global burnchain signer can only belong to one network
         let burnchain_signer = keychain.get_burnchain_signer();
-        match monitoring::set_burnchain_signer(burnchain_signer.clone()) {
-            Err(e) => {
-                warn!("Failed to set global burnchain signer: {:?}", &e);
+        if !config.node.shares_process {
+            match monitoring::set_burnchain_signer(burnchain_signer.clone()) {
+                Err(e) => {
+                    warn!("Failed to set global burnchain signer: {:?}", &e);
+                }
+                _ => {}
             }
-            _ => {}
         }
 
         let relayer = Relayer::from_p2p(&mut p2p_net);
@@ -1276,7 +1568,7 @@ impl InitializedNeonNode {
             blocks_processed.clone(),
             microblocks_processed.clone(),
             burnchain,
-            coord_comms,
+            coord_comms.clone(),
             shared_unconfirmed_txs.clone(),
         )
         .expect("Failed to initialize mine/relay thread");
@@ -1293,6 +1585,7 @@ impl InitializedNeonNode {
             attachments_rx,
             shared_unconfirmed_txs,
             event_dispatcher,
+            coord_comms,
             should_keep_running,
         )
         .expect("Failed to initialize p2p thread");
@@ -1304,7 +1597,7 @@ impl InitializedNeonNode {
 
         let is_miner = miner;
 
-        let atlas_config = AtlasConfig::default(config.is_mainnet());
+        let atlas_config = config.atlas.clone();
         InitializedNeonNode {
             config,
             relay_channel: relay_send,
@@ -1401,6 +1694,8 @@ impl InitializedNeonNode {
     // return stack's parent's burn header hash,
     //        the anchored block,
     //        the burn header hash of the burnchain tip
+    // If `allow_preemption` is set and a better parent shows up while the block is being
+    // assembled, `preempted` is set and None is returned so the caller can try again.
     fn relayer_run_tenure(
         config: &Config,
         registered_key: RegisteredKey,
@@ -1414,6 +1709,8 @@ impl InitializedNeonNode {
         bitcoin_controller: &mut BitcoinRegtestController,
         last_mined_blocks: &Vec<&AssembledAnchorBlock>,
         event_observer: &EventDispatcher,
+        allow_preemption: bool,
+        preempted: &mut bool,
     ) -> Option<(AssembledAnchorBlock, Secp256k1PrivateKey)> {
         let (
             mut stacks_parent_header,
@@ -1422,7 +1719,9 @@ impl InitializedNeonNode {
             parent_block_total_burn,
             parent_winning_vtxindex,
             coinbase_nonce,
-        ) = if let Some(stacks_tip) = chain_state.get_stacks_chain_tip(burn_db).unwrap() {
+        ) = if let Some(stacks_tip) =
+            select_mining_tip(config, chain_state, burn_db, &burn_block).unwrap()
+        {
             let stacks_tip_header = match StacksChainState::get_anchored_block_header_info(
                 chain_state.db(),
                 &stacks_tip.consensus_hash,
@@ -1749,7 +2048,24 @@ impl InitializedNeonNode {
             }
         }
 
-        let (anchored_block, _, _) = match StacksBlockBuilder::build_anchored_block(
+        // note how much of the parent's microblock stream we've seen, so we can tell if it grows
+        // while we're assembling
+        let mut preemption = AssemblyPreemption::new(
+            &parent_consensus_hash,
+            &stacks_parent_header,
+            microblock_info_opt
+                .as_ref()
+                .and_then(|(microblocks, _)| microblocks.last())
+                .map(|mblock| mblock.header.sequence.saturating_add(1))
+                .unwrap_or(0),
+        );
+
+        let priority_lanes = PriorityLanes::new(
+            config.node.priority_principals.clone(),
+            config.node.priority_budget_pct,
+        );
+
+        let (anchored_block, _, _) = match StacksBlockBuilder::build_anchored_block_preemptible(
             chain_state,
             &burn_db.index_conn(),
             mem_pool,
@@ -1760,8 +2076,15 @@ impl InitializedNeonNode {
             &coinbase_tx,
             config.block_limit.clone(),
             Some(event_observer),
+            &priority_lanes,
+            config.node.resume_block_assembly,
+            &mut || allow_preemption && preemption.check(config, chain_state, burn_db),
         ) {
             Ok(block) => block,
+            Err(ChainstateError::BlockAssemblyPreempted) => {
+                *preempted = true;
+                return None;
+            }
             Err(ChainstateError::InvalidStacksMicroblock(msg, mblock_header_hash)) => {
                 // part of the parent microblock stream is invalid, so try again
                 info!("Parent microblock stream is invalid; trying again without the offender {} (msg: {})", &mblock_header_hash, &msg);
@@ -1789,7 +2112,7 @@ impl InitializedNeonNode {
                 };
 
                 // try again
-                match StacksBlockBuilder::build_anchored_block(
+                match StacksBlockBuilder::build_anchored_block_preemptible(
                     chain_state,
                     &burn_db.index_conn(),
                     mem_pool,
@@ -1800,8 +2123,15 @@ impl InitializedNeonNode {
                     &coinbase_tx,
                     config.block_limit.clone(),
                     Some(event_observer),
+                    &priority_lanes,
+                    config.node.resume_block_assembly,
+                    &mut || allow_preemption && preemption.check(config, chain_state, burn_db),
                 ) {
                     Ok(block) => block,
+                    Err(ChainstateError::BlockAssemblyPreempted) => {
+                        *preempted = true;
+                        return None;
+                    }
                     Err(e) => {
                         error!("Failure mining anchor block even after removing offending microblock {}: {}", &mblock_header_hash, &e);
                         return None;
@@ -14,18 +14,16 @@ use stacks::chainstate::burn::ConsensusHash;
 use stacks::chainstate::stacks::db::{
     ChainStateBootData, ClarityTx, StacksChainState, StacksHeaderInfo,
 };
-use stacks::chainstate::stacks::events::{
-    StacksTransactionEvent, StacksTransactionReceipt, TransactionOrigin,
-};
+use stacks::chainstate::stacks::events::StacksTransactionReceipt;
 use stacks::chainstate::stacks::{
     CoinbasePayload, StacksBlock, StacksMicroblock, StacksTransaction, StacksTransactionSigner,
     TransactionAnchorMode, TransactionPayload, TransactionVersion,
 };
-use stacks::chainstate::{burn::db::sortdb::SortitionDB, stacks::db::StacksEpochReceipt};
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
 use stacks::core::mempool::MemPoolDB;
-use stacks::net::atlas::AttachmentInstance;
+use stacks::net::atlas::{AttachmentInstance, ATTACHMENT_BATCHES_CHANNEL_CAPACITY};
 use stacks::net::{
-    atlas::{AtlasConfig, AtlasDB},
+    atlas::AtlasDB,
     db::PeerDB,
     p2p::PeerNetwork,
     rpc::RPCHandlerArgs,
@@ -37,7 +35,6 @@ use stacks::types::chainstate::{
 use stacks::types::proof::TrieHash;
 use stacks::util::get_epoch_time_secs;
 use stacks::util::hash::Sha256Sum;
-use stacks::util::secp256k1::Secp256k1PrivateKey;
 use stacks::util::strings::UrlString;
 use stacks::util::vrf::VRFPublicKey;
 use stacks::{
@@ -204,13 +201,13 @@ fn spawn_peer(
                 }
             };
 
-            let mut expected_attachments = match attachments_rx.try_recv() {
-                Ok(expected_attachments) => expected_attachments,
-                _ => {
-                    debug!("Atlas: attachment channel is empty");
-                    HashSet::new()
-                }
-            };
+            let mut expected_attachments = HashSet::new();
+            while let Ok(attachments_batch) = attachments_rx.try_recv() {
+                expected_attachments.extend(attachments_batch);
+            }
+            if expected_attachments.is_empty() {
+                debug!("Atlas: attachment channel is empty");
+            }
 
             let net_result = this
                 .run(
@@ -368,7 +365,7 @@ impl Node {
             Err(_e) => panic!(),
         };
 
-        let (attachments_tx, attachments_rx) = sync_channel(1);
+        let (attachments_tx, attachments_rx) = sync_channel(ATTACHMENT_BATCHES_CHANNEL_CAPACITY);
         let mut node = Node {
             active_registered_key: None,
             bootstraping_chain: false,
@@ -439,20 +436,7 @@ impl Node {
             "Failed to parse socket: {}",
             &self.config.node.p2p_address
         ));
-        let node_privkey = {
-            let mut re_hashed_seed = self.config.node.local_peer_seed.clone();
-            let my_private_key = loop {
-                match Secp256k1PrivateKey::from_slice(&re_hashed_seed[..]) {
-                    Ok(sk) => break sk,
-                    Err(_) => {
-                        re_hashed_seed = Sha256Sum::from_data(&re_hashed_seed[..])
-                            .as_bytes()
-                            .to_vec()
-                    }
-                }
-            };
-            my_private_key
-        };
+        let node_privkey = self.config.node.get_node_private_key();
 
         let mut peerdb = PeerDB::connect(
             &self.config.get_peer_db_file_path(),
@@ -469,6 +453,13 @@ impl Node {
         )
         .unwrap();
 
+        {
+            // advertise the configured services
+            let mut tx = peerdb.tx_begin().unwrap();
+            PeerDB::set_local_services(&mut tx, self.config.node.services).unwrap();
+            tx.commit().unwrap();
+        }
+
         println!("DENY NEIGHBORS {:?}", &self.config.node.deny_nodes);
         {
             let mut tx = peerdb.tx_begin().unwrap();
@@ -484,7 +475,7 @@ impl Node {
             }
             tx.commit().unwrap();
         }
-        let atlas_config = AtlasConfig::default(false);
+        let atlas_config = self.config.atlas.clone();
         let atlasdb =
             AtlasDB::connect(atlas_config, &self.config.get_atlas_db_file_path(), true).unwrap();
 
@@ -769,7 +760,7 @@ impl Node {
 
             parent_consensus_hash
         };
-        let atlas_config = AtlasConfig::default(false);
+        let atlas_config = self.config.atlas.clone();
         let mut processed_blocks = vec![];
         loop {
             let mut process_blocks_at_tip = {
@@ -786,7 +777,10 @@ impl Node {
                             match block {
                                 (Some(epoch_receipt), _) => {
                                     let attachments_instances =
-                                        self.get_attachment_instances(epoch_receipt, &atlas_config);
+                                        AttachmentInstance::try_new_from_block_receipt(
+                                            &atlas_config,
+                                            epoch_receipt,
+                                        );
                                     if !attachments_instances.is_empty() {
                                         match self.attachments_tx.send(attachments_instances) {
                                             Ok(_) => {}
@@ -853,40 +847,6 @@ impl Node {
         chain_tip
     }
 
-    pub fn get_attachment_instances(
-        &self,
-        epoch_receipt: &StacksEpochReceipt,
-        atlas_config: &AtlasConfig,
-    ) -> HashSet<AttachmentInstance> {
-        let mut attachments_instances = HashSet::new();
-        for receipt in epoch_receipt.tx_receipts.iter() {
-            if let TransactionOrigin::Stacks(ref transaction) = receipt.transaction {
-                if let TransactionPayload::ContractCall(ref contract_call) = transaction.payload {
-                    let contract_id = contract_call.to_clarity_contract_id();
-                    if atlas_config.contracts.contains(&contract_id) {
-                        for event in receipt.events.iter() {
-                            if let StacksTransactionEvent::SmartContractEvent(ref event_data) =
-                                event
-                            {
-                                let res = AttachmentInstance::try_new_from_value(
-                                    &event_data.value,
-                                    &contract_id,
-                                    epoch_receipt.header.index_block_hash(),
-                                    epoch_receipt.header.block_height,
-                                    receipt.transaction.txid(),
-                                );
-                                if let Some(attachment_instance) = res {
-                                    attachments_instances.insert(attachment_instance);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        attachments_instances
-    }
-
     /// Returns the Stacks address of the node
     pub fn get_address(&self) -> StacksAddress {
         self.keychain.get_address(self.config.is_mainnet())
@@ -4,7 +4,7 @@ use crate::{
     BitcoinRegtestController, BurnchainController, ChainTip, Config, MocknetController, Node,
 };
 use stacks::chainstate::stacks::db::ClarityTx;
-use stacks::net::atlas::AttachmentInstance;
+use stacks::net::atlas::{AttachmentInstance, ATTACHMENT_BATCHES_CHANNEL_CAPACITY};
 use stacks::types::chainstate::BurnchainHeaderHash;
 use std::collections::HashSet;
 use std::sync::mpsc::{sync_channel, Receiver};
@@ -28,7 +28,7 @@ impl RunLoop {
         config: Config,
         boot_exec: Box<dyn FnOnce(&mut ClarityTx) -> ()>,
     ) -> Self {
-        let (attachments_tx, attachments_rx) = sync_channel(1);
+        let (attachments_tx, attachments_rx) = sync_channel(ATTACHMENT_BATCHES_CHANNEL_CAPACITY);
 
         // Build node based on config
         let node = Node::new(config.clone(), boot_exec, attachments_tx);
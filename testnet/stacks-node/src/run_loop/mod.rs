@@ -1,5 +1,11 @@
 pub mod helium;
 pub mod neon;
+pub mod replica;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
+
+use ctrlc as termination;
 
 use crate::{BurnchainController, BurnchainTip, ChainTip, Tenure};
 
@@ -30,6 +36,40 @@ macro_rules! info_green {
     })
 }
 
+lazy_static! {
+    /// Flags to clear, and what to log, when the process receives a termination request.
+    static ref TERMINATION_FLAGS: Mutex<Vec<(&'static str, Arc<AtomicBool>)>> = Mutex::new(vec![]);
+}
+
+static INSTALL_TERMINATION_HANDLER: Once = Once::new();
+
+/// Get a flag that is cleared (after logging `message`) when the process receives a termination
+/// request.  The process-wide handler is installed the first time this is called, so every run
+/// loop in the process is stopped by the same request.
+pub fn termination_flag(message: &'static str) -> Arc<AtomicBool> {
+    let should_keep_running = Arc::new(AtomicBool::new(true));
+    TERMINATION_FLAGS
+        .lock()
+        .expect("FATAL: termination flags lock poisoned")
+        .push((message, should_keep_running.clone()));
+
+    INSTALL_TERMINATION_HANDLER.call_once(|| {
+        let install = termination::set_handler(|| {
+            let flags = TERMINATION_FLAGS
+                .lock()
+                .expect("FATAL: termination flags lock poisoned");
+            for (message, keep_running_writer) in flags.iter() {
+                info!("{}", message);
+                keep_running_writer.store(false, Ordering::SeqCst);
+            }
+        });
+        if let Err(e) = install {
+            error!("Error setting termination handler - {}", e);
+        }
+    });
+    should_keep_running
+}
+
 pub struct RunLoopCallbacks {
     on_burn_chain_initialized: Option<fn(&mut Box<dyn BurnchainController>)>,
     on_new_burn_chain_state: Option<fn(u64, &BurnchainTip, &ChainTip)>,
@@ -1,11 +1,11 @@
 use std::cmp;
+use std::fs;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::sync_channel;
 use std::sync::Arc;
 use std::thread;
 
-use ctrlc as termination;
-
 use stacks::burnchains::bitcoin::address::BitcoinAddress;
 use stacks::burnchains::bitcoin::address::BitcoinAddressType;
 use stacks::burnchains::{Address, Burnchain};
@@ -15,7 +15,8 @@ use stacks::chainstate::coordinator::{
     BlockEventDispatcher, ChainsCoordinator, CoordinatorCommunication,
 };
 use stacks::chainstate::stacks::db::{ChainStateBootData, ClarityTx, StacksChainState};
-use stacks::net::atlas::{AtlasConfig, Attachment};
+use stacks::monitoring::{persist_counters, restore_persisted_counters};
+use stacks::net::atlas::{AtlasConfig, Attachment, ATTACHMENT_BATCHES_CHANNEL_CAPACITY};
 use stacks::vm::types::{PrincipalData, Value};
 use stx_genesis::GenesisData;
 
@@ -28,7 +29,7 @@ use crate::{
     NeonGenesisNode,
 };
 
-use super::RunLoopCallbacks;
+use super::{termination_flag, RunLoopCallbacks};
 
 /// Coordinating a node running in neon mode.
 #[cfg(test)]
@@ -38,6 +39,7 @@ pub struct RunLoop {
     blocks_processed: std::sync::Arc<std::sync::atomic::AtomicU64>,
     microblocks_processed: std::sync::Arc<std::sync::atomic::AtomicU64>,
     coordinator_channels: Option<(CoordinatorReceivers, CoordinatorChannels)>,
+    termination_flag: Option<Arc<AtomicBool>>,
 }
 
 #[cfg(not(test))]
@@ -45,6 +47,7 @@ pub struct RunLoop {
     config: Config,
     pub callbacks: RunLoopCallbacks,
     coordinator_channels: Option<(CoordinatorReceivers, CoordinatorChannels)>,
+    termination_flag: Option<Arc<AtomicBool>>,
 }
 
 impl RunLoop {
@@ -56,6 +59,7 @@ impl RunLoop {
             config,
             coordinator_channels: Some(channels),
             callbacks: RunLoopCallbacks::new(),
+            termination_flag: None,
         }
     }
 
@@ -66,11 +70,18 @@ impl RunLoop {
             config,
             coordinator_channels: Some(channels),
             callbacks: RunLoopCallbacks::new(),
+            termination_flag: None,
             blocks_processed: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
             microblocks_processed: std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0)),
         }
     }
 
+    /// Stop when the given flag is cleared, instead of installing our own termination handler.
+    /// Used when several run loops share the process, since only one handler can be installed.
+    pub fn set_termination_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.termination_flag = Some(flag);
+    }
+
     pub fn get_coordinator_channel(&self) -> Option<CoordinatorChannels> {
         self.coordinator_channels.as_ref().map(|x| x.1.clone())
     }
@@ -112,16 +123,10 @@ impl RunLoop {
             .take()
             .expect("Run loop already started, can only start once after initialization.");
 
-        let should_keep_running = Arc::new(AtomicBool::new(true));
-        let keep_running_writer = should_keep_running.clone();
-
-        let install = termination::set_handler(move || {
-            info!("Graceful termination request received, will complete the ongoing runloop cycles and terminate");
-            keep_running_writer.store(false, Ordering::SeqCst);
-        });
-        if let Err(e) = install {
-            error!("Error setting termination handler - {}", e);
-        }
+        let should_keep_running = match self.termination_flag.take() {
+            Some(flag) => flag,
+            None => termination_flag("Graceful termination request received, will complete the ongoing runloop cycles and terminate"),
+        };
 
         // Initialize and start the burnchain.
         let mut burnchain = BitcoinRegtestController::with_burnchain(
@@ -192,10 +197,31 @@ impl RunLoop {
         for observer in self.config.events_observers.iter() {
             event_dispatcher.register_observer(observer, should_keep_running.clone());
         }
+        if !self.config.events_observers.is_empty() {
+            let queue_path = self.config.get_event_queue_file_path();
+            if let Some(queue_dir) = Path::new(&queue_path).parent() {
+                fs::create_dir_all(queue_dir).expect("Unable to create the event queue directory");
+            }
+            event_dispatcher
+                .enable_reliable_delivery(
+                    self.config.node.get_node_private_key(),
+                    &queue_path,
+                    should_keep_running.clone(),
+                )
+                .expect("Unable to set up event observer delivery");
+            event_dispatcher.enable_event_replay(
+                self.config.is_mainnet(),
+                self.config.burnchain.chain_id,
+                self.config.get_chainstate_path_str(),
+                self.config.get_burn_db_file_path(),
+                self.config.block_limit.clone(),
+                should_keep_running.clone(),
+            );
+        }
 
         let use_test_genesis_data = use_test_genesis_chainstate(&self.config);
 
-        let mut atlas_config = AtlasConfig::default(false);
+        let mut atlas_config = self.config.atlas.clone();
         let genesis_attachments = GenesisData::new(use_test_genesis_data)
             .read_name_zonefiles()
             .into_iter()
@@ -208,7 +234,7 @@ impl RunLoop {
         let chainstate_path = self.config.get_chainstate_path_str();
         let coordinator_burnchain_config = burnchain_config.clone();
 
-        let (attachments_tx, attachments_rx) = sync_channel(1);
+        let (attachments_tx, attachments_rx) = sync_channel(ATTACHMENT_BATCHES_CHANNEL_CAPACITY);
 
         let mut boot_data = ChainStateBootData {
             initial_balances,
@@ -229,7 +255,7 @@ impl RunLoop {
             get_bulk_initial_names: Some(Box::new(move || get_names(use_test_genesis_data))),
         };
 
-        let (chain_state_db, receipts) = StacksChainState::open_and_exec(
+        let (mut chain_state_db, receipts) = StacksChainState::open_and_exec(
             mainnet,
             chainid,
             &chainstate_path,
@@ -237,9 +263,12 @@ impl RunLoop {
             block_limit,
         )
         .unwrap();
+        chain_state_db.parallel_analysis_workers =
+            self.config.node.parallel_analysis_workers as usize;
+        chain_state_db.contract_cost_stats = self.config.node.contract_cost_stats;
         coordinator_dispatcher.dispatch_boot_receipts(receipts);
 
-        let atlas_config = AtlasConfig::default(mainnet);
+        let atlas_config = self.config.atlas.clone();
         let moved_atlas_config = atlas_config.clone();
 
         let coordinator_thread_handle = thread::Builder::new()
@@ -316,6 +345,16 @@ impl RunLoop {
         trace!("Begin run loop");
         self.bump_blocks_processed();
 
+        // pick up the monitoring counters' totals from the last run before serving them.  The
+        // counters are process-wide, so if other networks run in this process, they are neither
+        // restored nor (since `persist_counters()` only saves restored counters) persisted.
+        let metrics_root_path = self.config.get_chainstate_path_str();
+        if !self.config.node.shares_process {
+            if let Err(e) = restore_persisted_counters(&metrics_root_path) {
+                warn!("Failed to restore persisted monitoring counters: {:?}", &e);
+            }
+        }
+
         let prometheus_bind = self.config.node.prometheus_bind.clone();
         if let Some(prometheus_bind) = prometheus_bind {
             thread::Builder::new()
@@ -349,10 +388,19 @@ impl RunLoop {
                 node.relayer_thread_handle.join().unwrap();
                 node.p2p_thread_handle.join().unwrap();
 
+                if let Err(e) = persist_counters(&metrics_root_path) {
+                    warn!("Failed to persist monitoring counters: {:?}", &e);
+                }
+
                 info!("Exiting stacks-node");
                 break;
             }
 
+            // save the counters' totals as we go, in case the node doesn't get to exit cleanly
+            if let Err(e) = persist_counters(&metrics_root_path) {
+                warn!("Failed to persist monitoring counters: {:?}", &e);
+            }
+
             // wait for the p2p state-machine to do at least one pass
             debug!("Wait until we reach steady-state before processing more burnchain blocks...");
 
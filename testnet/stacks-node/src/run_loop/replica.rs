@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time;
+
+use stacks::burnchains::Burnchain;
+use stacks::chainstate::burn::db::sortdb::SortitionDB;
+use stacks::chainstate::stacks::db::StacksChainState;
+use stacks::core::mempool::MemPoolDB;
+use stacks::net::{
+    atlas::AtlasDB, db::PeerDB, p2p::PeerNetwork, relay::Relayer, rpc::RPCHandlerArgs, PeerAddress,
+};
+use stacks::util::hash::Sha256Sum;
+use stacks::util::strings::UrlString;
+
+use super::termination_flag;
+use crate::Config;
+
+/// Coordinating a read replica: a node that serves the read-only RPC endpoints from the
+/// databases of a primary node it shares a filesystem with.  The replica neither syncs the
+/// burnchain nor talks to p2p peers; the primary keeps the databases up to date, so several
+/// replicas can serve RPC traffic behind a single syncing node.
+pub struct RunLoop {
+    config: Config,
+    primary: Config,
+    termination_flag: Option<Arc<AtomicBool>>,
+}
+
+impl RunLoop {
+    /// Sets up a replica of the primary node whose working directory is `node.replica_of`.
+    pub fn new(config: Config) -> Self {
+        let mut primary = config.clone();
+        primary.node.working_dir =
+            config.node.replica_of.clone().expect(
+                "A replica needs `node.replica_of`, the working directory of its primary node",
+            );
+        Self {
+            config,
+            primary,
+            termination_flag: None,
+        }
+    }
+
+    /// Stop when the given flag is cleared, instead of installing our own termination handler.
+    pub fn set_termination_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.termination_flag = Some(flag);
+    }
+
+    /// Serve RPC requests until terminated.
+    pub fn start(&mut self) {
+        let should_keep_running = match self.termination_flag.take() {
+            Some(flag) => flag,
+            None => termination_flag("Graceful termination request received, will stop serving"),
+        };
+
+        let burn_db_path = self.primary.get_burn_db_file_path();
+        let chainstate_path = self.primary.get_chainstate_path_str();
+        let chainstate_index_path = Path::new(&chainstate_path).join("vm").join("index.sqlite");
+        let mainnet = self.config.is_mainnet();
+        let chain_id = self.config.burnchain.chain_id;
+
+        // the primary creates (and migrates) the databases -- wait for it rather than doing it
+        // ourselves
+        info!(
+            "Replica: serving from the databases of the node in {}",
+            &self.primary.node.working_dir
+        );
+        while !Path::new(&burn_db_path).exists() || !chainstate_index_path.exists() {
+            if !should_keep_running.load(Ordering::SeqCst) {
+                return;
+            }
+            info!("Replica: waiting for the primary node to create its databases");
+            thread::sleep(time::Duration::from_secs(5));
+        }
+
+        let (network_name, _) = self.config.burnchain.get_bitcoin_network();
+        let burnchain = Burnchain::new(
+            &self.primary.get_burn_db_path(),
+            &self.config.burnchain.chain,
+            &network_name,
+        )
+        .expect("Failed to instantiate burnchain");
+
+        let view = {
+            let sortdb = SortitionDB::open(&burn_db_path, false)
+                .expect("Error while opening the primary's sortition db");
+            let sortition_tip = SortitionDB::get_canonical_burn_chain_tip(&sortdb.conn())
+                .expect("Failed to get sortition tip");
+            SortitionDB::get_burnchain_view(&sortdb.conn(), &burnchain, &sortition_tip).unwrap()
+        };
+
+        let p2p_sock: SocketAddr = self.config.node.p2p_bind.parse().expect(&format!(
+            "Failed to parse socket: {}",
+            &self.config.node.p2p_bind
+        ));
+        let rpc_sock: SocketAddr = self.config.node.rpc_bind.parse().expect(&format!(
+            "Failed to parse socket: {}",
+            &self.config.node.rpc_bind
+        ));
+        let p2p_addr: SocketAddr = self.config.node.p2p_address.parse().expect(&format!(
+            "Failed to parse socket: {}",
+            &self.config.node.p2p_address
+        ));
+
+        // the peer DB is our own, since it holds our node key
+        let peer_db_path = self.config.get_peer_db_file_path();
+        if let Some(parent) = Path::new(&peer_db_path).parent() {
+            fs::create_dir_all(parent).expect("Failed to create the replica's working directory");
+        }
+        let mut peerdb = PeerDB::connect(
+            &peer_db_path,
+            true,
+            chain_id,
+            burnchain.network_id,
+            Some(self.config.node.get_node_private_key()),
+            self.config.connection_options.private_key_lifetime,
+            PeerAddress::from_socketaddr(&p2p_addr),
+            p2p_sock.port(),
+            UrlString::try_from(format!("{}", &self.config.node.data_url)).unwrap(),
+            &vec![],
+            None,
+        )
+        .unwrap_or_else(|e| panic!("Failed to open {}: {:?}", &peer_db_path, &e));
+        {
+            let mut tx = peerdb.tx_begin().unwrap();
+            PeerDB::set_local_services(&mut tx, self.config.node.services).unwrap();
+            tx.commit().unwrap();
+        }
+
+        // attachments are downloaded by the primary
+        let atlasdb = AtlasDB::connect(
+            self.config.atlas.clone(),
+            &self.primary.get_atlas_db_file_path(),
+            false,
+        )
+        .expect("Error while opening the primary's atlas db");
+
+        let local_peer = PeerDB::get_local_peer(peerdb.conn()).expect("Unable to get local peer");
+        let mut p2p_net = PeerNetwork::new(
+            peerdb,
+            atlasdb,
+            local_peer,
+            self.config.burnchain.peer_version,
+            burnchain,
+            view,
+            self.config.connection_options.clone(),
+        );
+        p2p_net.bind(&p2p_sock, &rpc_sock).unwrap();
+        info!(
+            "Replica: start HTTP server on {}",
+            &self.config.node.rpc_bind
+        );
+
+        let handler_args = RPCHandlerArgs {
+            genesis_chainstate_hash: Sha256Sum::from_hex(stx_genesis::GENESIS_CHAINSTATE_HASH)
+                .unwrap(),
+            ..RPCHandlerArgs::default()
+        };
+
+        // these see the primary's writes as it commits them, like any other SQLite connection
+        let sortdb = SortitionDB::open(&burn_db_path, false)
+            .expect("Error while opening the primary's sortition db");
+        let (mut chainstate, _) = StacksChainState::open_with_block_limit(
+            mainnet,
+            chain_id,
+            &chainstate_path,
+            self.config.block_limit.clone(),
+        )
+        .expect("Error while opening the primary's chainstate");
+        // the primary keeps the statistics; this only decides whether to serve them
+        chainstate.contract_cost_stats = self.config.node.contract_cost_stats;
+        let mut mem_pool = MemPoolDB::open(mainnet, chain_id, &chainstate_path)
+            .expect("Error while opening the primary's mempool");
+
+        while should_keep_running.load(Ordering::SeqCst) {
+            // serve the primary's unconfirmed microblock state, too
+            let _ = Relayer::setup_unconfirmed_state_readonly(&mut chainstate, &sortdb);
+
+            if let Err(e) = p2p_net.run(
+                &sortdb,
+                &mut chainstate,
+                &mut mem_pool,
+                None,
+                false,
+                false,
+                1000,
+                &handler_args,
+                &mut HashSet::new(),
+            ) {
+                warn!("Replica: failed to serve requests: {:?}", &e);
+            }
+        }
+        info!("Replica: exit");
+    }
+}
@@ -62,6 +62,11 @@ impl PoxSyncWatchdogComms {
         self.last_ibd.load(Ordering::SeqCst)
     }
 
+    /// Height of the burnchain's tip, or 0 if it isn't known yet
+    pub fn get_burnchain_tip_height(&self) -> u64 {
+        self.burnchain_tip_height.load(Ordering::SeqCst)
+    }
+
     /// Wait for at least one inv-sync state-machine passes
     pub fn wait_for_inv_sync_pass(&self, timeout: u64) -> Result<bool, burnchain_error> {
         let current = self.get_inv_sync_passes();
@@ -123,6 +128,10 @@ impl PoxSyncWatchdogComms {
     pub fn set_ibd(&mut self, value: bool) {
         self.last_ibd.store(value, Ordering::SeqCst);
     }
+
+    pub fn set_burnchain_tip_height(&mut self, value: u64) {
+        self.burnchain_tip_height.store(value, Ordering::SeqCst);
+    }
 }
 
 /// Monitor the state of the Stacks blockchain as the peer network and relay threads download and
@@ -445,6 +454,9 @@ impl PoxSyncWatchdog {
             }
         };
 
+        self.relayer_comms
+            .set_burnchain_tip_height(burnchain_height);
+
         if self.watch_start_ts == 0 {
             self.watch_start_ts = get_epoch_time_secs();
         }
@@ -119,6 +119,8 @@ mod test_observer {
         pub static ref NEW_BLOCKS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
         pub static ref NEW_MICROBLOCKS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
         pub static ref BURN_BLOCKS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
+        pub static ref REWARD_CYCLES: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
+        pub static ref SORTITIONS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
         pub static ref MEMTXS: Mutex<Vec<String>> = Mutex::new(Vec::new());
         pub static ref MEMTXS_DROPPED: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
         pub static ref ATTACHMENTS: Mutex<Vec<serde_json::Value>> = Mutex::new(Vec::new());
@@ -132,6 +134,22 @@ mod test_observer {
         Ok(warp::http::StatusCode::OK)
     }
 
+    async fn handle_reward_cycle(
+        reward_cycle: serde_json::Value,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let mut reward_cycles = REWARD_CYCLES.lock().unwrap();
+        reward_cycles.push(reward_cycle);
+        Ok(warp::http::StatusCode::OK)
+    }
+
+    async fn handle_sortition(
+        sortition: serde_json::Value,
+    ) -> Result<impl warp::Reply, Infallible> {
+        let mut sortitions = SORTITIONS.lock().unwrap();
+        sortitions.push(sortition);
+        Ok(warp::http::StatusCode::OK)
+    }
+
     async fn handle_block(block: serde_json::Value) -> Result<impl warp::Reply, Infallible> {
         let mut blocks = NEW_BLOCKS.lock().unwrap();
         blocks.push(block);
@@ -209,6 +227,14 @@ mod test_observer {
         BURN_BLOCKS.lock().unwrap().clone()
     }
 
+    pub fn get_reward_cycles() -> Vec<serde_json::Value> {
+        REWARD_CYCLES.lock().unwrap().clone()
+    }
+
+    pub fn get_sortitions() -> Vec<serde_json::Value> {
+        SORTITIONS.lock().unwrap().clone()
+    }
+
     pub fn get_attachments() -> Vec<serde_json::Value> {
         ATTACHMENTS.lock().unwrap().clone()
     }
@@ -231,6 +257,14 @@ mod test_observer {
             .and(warp::post())
             .and(warp::body::json())
             .and_then(handle_burn_block);
+        let new_reward_cycles = warp::path!("new_reward_cycle")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(handle_reward_cycle);
+        let new_sortitions = warp::path!("new_sortition")
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then(handle_sortition);
         let new_attachments = warp::path!("attachments" / "new")
             .and(warp::post())
             .and(warp::body::json())
@@ -246,6 +280,8 @@ mod test_observer {
                 .or(mempool_txs)
                 .or(mempool_drop_txs)
                 .or(new_burn_blocks)
+                .or(new_reward_cycles)
+                .or(new_sortitions)
                 .or(new_attachments)
                 .or(new_microblocks),
         )
@@ -264,6 +300,8 @@ mod test_observer {
     pub fn clear() {
         ATTACHMENTS.lock().unwrap().clear();
         BURN_BLOCKS.lock().unwrap().clear();
+        REWARD_CYCLES.lock().unwrap().clear();
+        SORTITIONS.lock().unwrap().clear();
         NEW_BLOCKS.lock().unwrap().clear();
         MEMTXS.lock().unwrap().clear();
         MEMTXS_DROPPED.lock().unwrap().clear();
@@ -691,6 +729,7 @@ fn liquid_ustx_integration() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -819,6 +858,7 @@ fn lockup_integration() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1370,7 +1410,9 @@ fn microblock_integration_test() {
 
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
-        events_keys: vec![EventKeyType::AnyEvent],
+        // sortition events are opt-in, even with "*"
+        events_keys: vec![EventKeyType::AnyEvent, EventKeyType::Sortitions],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -1632,6 +1674,33 @@ fn microblock_integration_test() {
         eprintln!("{}", burn_block);
     }
 
+    // every sortition with a winner names exactly one of its block commits as the winner
+    let sortitions_observed = test_observer::get_sortitions();
+    let sortitions_with_winners: Vec<_> = sortitions_observed
+        .into_iter()
+        .filter(|sortition| sortition.get("sortition").unwrap().as_bool().unwrap())
+        .collect();
+    assert!(
+        sortitions_with_winners.len() >= 3,
+        "Sortitions with winners {} should be >= 3",
+        sortitions_with_winners.len()
+    );
+    for sortition in sortitions_with_winners {
+        let winners: Vec<_> = sortition
+            .get("block_commits")
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|commit| commit.get("won").unwrap().as_bool().unwrap())
+            .collect();
+        assert_eq!(winners.len(), 1);
+        assert_eq!(
+            winners[0].get("txid").unwrap(),
+            sortition.get("winning_block_txid").unwrap()
+        );
+    }
+
     let mut prior = None;
     for block in blocks_observed.iter() {
         let parent_index_hash = block
@@ -1992,6 +2061,7 @@ fn size_overflow_unconfirmed_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2182,6 +2252,7 @@ fn size_overflow_unconfirmed_stream_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2377,6 +2448,7 @@ fn size_overflow_unconfirmed_invalid_stream_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2636,6 +2708,7 @@ fn runtime_overflow_unconfirmed_microblocks_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2802,6 +2875,7 @@ fn block_replay_integration_test() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let mut btcd_controller = BitcoinCoreController::new(conf.clone());
@@ -2926,6 +3000,7 @@ fn cost_voting_integration() {
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
         events_keys: vec![EventKeyType::AnyEvent],
+        ..EventObserverConfig::default()
     });
 
     let spender_bal = 10_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -3348,7 +3423,9 @@ fn pox_integration_test() {
 
     conf.events_observers.push(EventObserverConfig {
         endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
-        events_keys: vec![EventKeyType::AnyEvent],
+        // reward cycle events are opt-in, even with "*"
+        events_keys: vec![EventKeyType::AnyEvent, EventKeyType::RewardCycles],
+        ..EventObserverConfig::default()
     });
 
     let first_bal = 6_000_000_000 * (core::MICROSTACKS_PER_STACKS as u64);
@@ -3765,6 +3842,21 @@ fn pox_integration_test() {
         Some(7u64)
     );
 
+    // the reward cycles that paid out were announced as such
+    let reward_cycles = test_observer::get_reward_cycles();
+    let pox_active_cycles: Vec<_> = reward_cycles
+        .iter()
+        .filter(|cycle| cycle.get("pox_active").unwrap().as_bool().unwrap())
+        .collect();
+    assert!(pox_active_cycles.len() >= 1);
+    for cycle in pox_active_cycles {
+        assert_eq!(
+            cycle.get("anchor_block_status").unwrap().as_str().unwrap(),
+            "selected_known"
+        );
+        assert!(cycle.get("reward_set").unwrap().as_array().unwrap().len() > 0);
+    }
+
     // get the canonical chain tip
     let path = format!("{}/v2/info", &http_origin);
     let tip_info = client
@@ -3895,6 +3987,7 @@ fn atlas_integration_test() {
         .push(EventObserverConfig {
             endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![EventKeyType::AnyEvent],
+            ..EventObserverConfig::default()
         });
 
     // Our 2 nodes will share the bitcoind node
@@ -4422,6 +4515,7 @@ fn antientropy_integration_test() {
         .push(EventObserverConfig {
             endpoint: format!("localhost:{}", test_observer::EVENT_OBSERVER_PORT),
             events_keys: vec![EventKeyType::AnyEvent],
+            ..EventObserverConfig::default()
         });
 
     // Our 2 nodes will share the bitcoind node